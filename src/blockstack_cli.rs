@@ -22,13 +22,16 @@
 extern crate blockstack_lib;
 
 use blockstack_lib::address::AddressHashMode;
+use blockstack_lib::burnchains::bitcoin::address::BitcoinAddress;
 use blockstack_lib::burnchains::Address;
+use blockstack_lib::chainstate::stacks::boot::STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR;
 use blockstack_lib::chainstate::stacks::{
-    StacksAddress, StacksBlock, StacksMicroblock, StacksPrivateKey, StacksPublicKey,
-    StacksTransaction, StacksTransactionSigner, TokenTransferMemo, TransactionAnchorMode,
-    TransactionAuth, TransactionContractCall, TransactionPayload, TransactionSmartContract,
-    TransactionSpendingCondition, TransactionVersion, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
-    C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+    StacksAddress, StacksBlock, StacksMicroblock, StacksMicroblockHeader, StacksPrivateKey,
+    StacksPublicKey, StacksTransaction, StacksTransactionSigner, TokenTransferMemo,
+    TransactionAnchorMode, TransactionAuth, TransactionContractCall, TransactionPayload,
+    TransactionSmartContract, TransactionSpendingCondition, TransactionVersion,
+    C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+    MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH,
 };
 use blockstack_lib::net::{Error as NetError, StacksMessageCodec};
 use blockstack_lib::util::{
@@ -64,6 +67,10 @@ This CLI has these methods:
   contract-call      used to generate and sign a contract-call transaction
   generate-sk        used to generate a secret key for transaction signing
   token-transfer     used to generate and sign a transfer transaction
+  delegate-stack-stx used to generate and sign a PoX delegate's delegate-stack-stx transaction
+  stack-aggregation-commit
+                     used to generate and sign a PoX delegate's stack-aggregation-commit transaction
+  poison-microblock  used to generate and sign a transaction reporting a miner's conflicting microblocks
   addresses          used to get both Bitcoin and Stacks addresses from a private key
   decode-tx          used to decode a hex-encoded transaction into a human-readable representation
   decode-block       used to decode a hex-encoded Stacks block into a human-readable representation
@@ -125,12 +132,62 @@ this command outputs the hex string encoding of the transaction to stdout, and e
 code 0
 
 A token-transfer can be mined in a Stacks block, a Stacks microblock, or either.  The default
+is that the miner chooses, but you can decide which with the following options:
+
+  --microblock-only  indicates to mine this transaction only in a microblock
+  --block-only       indicates to mine this transaction only in a block
+
+A token-transfer can also carry a long memo: a Clarity value, such as a tuple holding a
+destination tag, whose serialization is at most 1024 bytes.  Long memos are only valid once
+they have activated on the network.  The value is supplied in the same way as a contract-call
+argument, with `-e` to evaluate it or `-x` to pass its hex serialization:
+
+  --long-memo -e \"(tuple (tag u42))\"
+";
+
+const DELEGATE_STACK_STX_USAGE: &str = "blockstack-cli (options) delegate-stack-stx [delegate-secret-key-hex] [fee-rate] [nonce] [stacker-address] [amount-ustx] [pox-address] [start-burn-height] [lock-period]
+
+The delegate-stack-stx command generates and signs a contract-call transaction to the PoX
+contract's delegate-stack-stx function, which a PoX delegate (e.g. a pool operator) sends to
+lock [amount-ustx] of the STX that [stacker-address] has delegated to it. The PoX address is
+a Bitcoin address. If successful, this command outputs the hex string encoding of the
+transaction to stdout, and exits with code 0.
+
+A delegate-stack-stx can be mined in a Stacks block, a Stacks microblock, or either.  The default
 is that the miner chooses, but you can decide which with the following options:
 
   --microblock-only  indicates to mine this transaction only in a microblock
   --block-only       indicates to mine this transaction only in a block
 ";
 
+const STACK_AGGREGATION_COMMIT_USAGE: &str = "blockstack-cli (options) stack-aggregation-commit [delegate-secret-key-hex] [fee-rate] [nonce] [pox-address] [reward-cycle]
+
+The stack-aggregation-commit command generates and signs a contract-call transaction to the PoX
+contract's stack-aggregation-commit function, which a PoX delegate sends to commit the STX it
+has locked with delegate-stack-stx to [pox-address] in [reward-cycle]. The PoX address is a
+Bitcoin address. If successful, this command outputs the hex string encoding of the
+transaction to stdout, and exits with code 0.
+
+A stack-aggregation-commit can be mined in a Stacks block, a Stacks microblock, or either.  The
+default is that the miner chooses, but you can decide which with the following options:
+
+  --microblock-only  indicates to mine this transaction only in a microblock
+  --block-only       indicates to mine this transaction only in a block
+";
+
+const POISON_MICROBLOCK_USAGE: &str = "blockstack-cli (options) poison-microblock [origin-secret-key-hex] [fee-rate] [nonce] [microblock-header-1-hex] [microblock-header-2-hex]
+
+The poison-microblock command generates and signs a PoisonMicroblock transaction, which reports
+that a miner signed two different microblocks with the same parent and sequence number. The
+headers must be hex-encoded signed microblock headers (or whole microblocks), and are checked to
+actually conflict. If the report is mined before the miner's reward matures, the miner's reward
+is forfeited, and the origin of this transaction is paid a commission out of it. If successful,
+this command outputs the hex string encoding of the transaction to stdout, and exits with code 0.
+
+A poison-microblock can only be mined in a Stacks block, so that the reported miner cannot
+orphan it in its own microblock stream.
+";
+
 const GENERATE_USAGE: &str = "blockstack-cli (options) generate-sk
 
 This method generates a secret key, outputting the hex encoding of the
@@ -270,6 +327,14 @@ fn make_contract_call(
     })
 }
 
+fn parse_pox_addr(pox_address: &str) -> Result<Value, CliError> {
+    let btc_addr =
+        BitcoinAddress::from_b58(pox_address).map_err(|_e| "Failed to parse PoX address")?;
+    Ok(Value::Tuple(
+        StacksAddress::from_bitcoin_address(&btc_addr).as_clarity_tuple(),
+    ))
+}
+
 fn make_standard_single_sig_tx(
     version: TransactionVersion,
     chain_id: u32,
@@ -485,6 +550,31 @@ fn handle_contract_call(
     Ok(to_hex(&signed_tx_bytes))
 }
 
+/// Remove a `--long-memo [-e|-x] [value]` option from the arguments, and parse its value
+fn parse_long_memo(args: &mut Vec<String>, usage: &str) -> Result<Option<Value>, CliError> {
+    let idx = match args.iter().position(|arg| arg == "--long-memo") {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+    if args.len() < idx + 3 {
+        return Err(CliError::Message(format!("USAGE:\n {}", usage)));
+    }
+    let mut option: Vec<String> = args.drain(idx..idx + 3).collect();
+    let input = option.pop().unwrap();
+    let long_memo = match option[1].as_str() {
+        "-x" => Value::try_deserialize_hex_untyped(&input)?,
+        "-e" => vm::execute(&input)?.ok_or("Supplied long memo did not evaluate to a Value")?,
+        _ => return Err(CliError::Message(format!("USAGE:\n {}", usage))),
+    };
+    if long_memo.serialize_to_vec().len() > MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH {
+        return Err(CliError::Message(format!(
+            "Long memo is longer than {} bytes",
+            MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH
+        )));
+    }
+    Ok(Some(long_memo))
+}
+
 fn handle_token_transfer(
     args_slice: &[String],
     version: TransactionVersion,
@@ -497,6 +587,7 @@ fn handle_token_transfer(
             TOKEN_TRANSFER_USAGE
         )));
     }
+    let long_memo = parse_long_memo(&mut args, TOKEN_TRANSFER_USAGE)?;
     if args.len() < 5 {
         return Err(CliError::Message(format!(
             "Incorrect argument count supplied \n\nUSAGE:\n {}",
@@ -523,7 +614,7 @@ fn handle_token_transfer(
         TokenTransferMemo(memo)
     };
 
-    let payload = TransactionPayload::TokenTransfer(recipient_address, *amount, memo);
+    let payload = TransactionPayload::TokenTransfer(recipient_address, *amount, memo, long_memo);
     let mut unsigned_tx = make_standard_single_sig_tx(
         version,
         chain_id,
@@ -547,6 +638,222 @@ fn handle_token_transfer(
     Ok(to_hex(&signed_tx_bytes))
 }
 
+fn make_pox_contract_call_tx(
+    version: TransactionVersion,
+    chain_id: u32,
+    sk_origin: &StacksPrivateKey,
+    fee_rate: u64,
+    nonce: u64,
+    anchor_mode: TransactionAnchorMode,
+    function_name: &str,
+    function_args: Vec<Value>,
+) -> Result<String, CliError> {
+    let payload = make_contract_call(
+        STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR.to_string(),
+        "pox".to_string(),
+        function_name.to_string(),
+        function_args,
+    )?;
+    let mut unsigned_tx = make_standard_single_sig_tx(
+        version,
+        chain_id,
+        payload.into(),
+        &StacksPublicKey::from_private(sk_origin),
+        nonce,
+        fee_rate,
+    );
+    unsigned_tx.anchor_mode = anchor_mode;
+
+    let mut unsigned_tx_bytes = vec![];
+    unsigned_tx
+        .consensus_serialize(&mut unsigned_tx_bytes)
+        .expect("FATAL: invalid transaction");
+    let signed_tx = sign_transaction_single_sig_standard(&to_hex(&unsigned_tx_bytes), sk_origin)?;
+
+    let mut signed_tx_bytes = vec![];
+    signed_tx
+        .consensus_serialize(&mut signed_tx_bytes)
+        .expect("FATAL: invalid signed transaction");
+    Ok(to_hex(&signed_tx_bytes))
+}
+
+fn handle_delegate_stack_stx(
+    args_slice: &[String],
+    version: TransactionVersion,
+    chain_id: u32,
+) -> Result<String, CliError> {
+    let mut args = args_slice.to_vec();
+    if args.len() >= 1 && args[0] == "-h" {
+        return Err(CliError::Message(format!(
+            "USAGE:\n {}",
+            DELEGATE_STACK_STX_USAGE
+        )));
+    }
+    let anchor_mode = parse_anchor_mode(&mut args, DELEGATE_STACK_STX_USAGE)?;
+    if args.len() != 8 {
+        return Err(CliError::Message(format!(
+            "Incorrect argument count supplied \n\nUSAGE:\n {}",
+            DELEGATE_STACK_STX_USAGE
+        )));
+    }
+
+    let sk_origin = StacksPrivateKey::from_hex(&args[0])?;
+    let fee_rate = args[1].parse()?;
+    let nonce = args[2].parse()?;
+    let stacker = PrincipalData::parse(&args[3]).map_err(|_e| "Failed to parse stacker")?;
+    let amount_ustx: u128 = args[4].parse()?;
+    let pox_addr = parse_pox_addr(&args[5])?;
+    let start_burn_height: u128 = args[6].parse()?;
+    let lock_period: u128 = args[7].parse()?;
+
+    make_pox_contract_call_tx(
+        version,
+        chain_id,
+        &sk_origin,
+        fee_rate,
+        nonce,
+        anchor_mode,
+        "delegate-stack-stx",
+        vec![
+            Value::Principal(stacker),
+            Value::UInt(amount_ustx),
+            pox_addr,
+            Value::UInt(start_burn_height),
+            Value::UInt(lock_period),
+        ],
+    )
+}
+
+fn handle_stack_aggregation_commit(
+    args_slice: &[String],
+    version: TransactionVersion,
+    chain_id: u32,
+) -> Result<String, CliError> {
+    let mut args = args_slice.to_vec();
+    if args.len() >= 1 && args[0] == "-h" {
+        return Err(CliError::Message(format!(
+            "USAGE:\n {}",
+            STACK_AGGREGATION_COMMIT_USAGE
+        )));
+    }
+    let anchor_mode = parse_anchor_mode(&mut args, STACK_AGGREGATION_COMMIT_USAGE)?;
+    if args.len() != 5 {
+        return Err(CliError::Message(format!(
+            "Incorrect argument count supplied \n\nUSAGE:\n {}",
+            STACK_AGGREGATION_COMMIT_USAGE
+        )));
+    }
+
+    let sk_origin = StacksPrivateKey::from_hex(&args[0])?;
+    let fee_rate = args[1].parse()?;
+    let nonce = args[2].parse()?;
+    let pox_addr = parse_pox_addr(&args[3])?;
+    let reward_cycle: u128 = args[4].parse()?;
+
+    make_pox_contract_call_tx(
+        version,
+        chain_id,
+        &sk_origin,
+        fee_rate,
+        nonce,
+        anchor_mode,
+        "stack-aggregation-commit",
+        vec![pox_addr, Value::UInt(reward_cycle)],
+    )
+}
+
+/// Decode a hex-encoded microblock header.  Trailing bytes are ignored, so a whole microblock
+/// (whose header comes first) is accepted too.
+fn parse_microblock_header(header_hex: &str) -> Result<StacksMicroblockHeader, CliError> {
+    let header_bytes = hex_bytes(header_hex)?;
+    StacksMicroblockHeader::consensus_deserialize(&mut io::Cursor::new(&header_bytes))
+        .map_err(|e| CliError::Message(format!("Failed to decode microblock header: {:?}", e)))
+}
+
+/// Check that two microblock headers are proof of a miner forking its microblock stream: they
+/// must be different microblocks at the same position in the stream, signed by the same key.
+fn check_conflicting_microblock_headers(
+    header_1: &StacksMicroblockHeader,
+    header_2: &StacksMicroblockHeader,
+) -> Result<(), CliError> {
+    if header_1.block_hash() == header_2.block_hash() {
+        return Err("Microblock headers are identical".into());
+    }
+    if header_1.sequence != header_2.sequence
+        || header_1.prev_block != header_2.prev_block
+        || header_1.version != header_2.version
+    {
+        return Err(
+            "Microblock headers do not conflict: they must have the same version, sequence and parent"
+                .into(),
+        );
+    }
+
+    let pubkey_hash_1 = header_1
+        .check_recover_pubkey()
+        .map_err(|e| CliError::Message(format!("Failed to recover microblock signer: {:?}", e)))?;
+    let pubkey_hash_2 = header_2
+        .check_recover_pubkey()
+        .map_err(|e| CliError::Message(format!("Failed to recover microblock signer: {:?}", e)))?;
+    if pubkey_hash_1 != pubkey_hash_2 {
+        return Err(CliError::Message(format!(
+            "Microblock headers do not conflict: signed by different keys ({} != {})",
+            pubkey_hash_1, pubkey_hash_2
+        )));
+    }
+    Ok(())
+}
+
+fn handle_poison_microblock(
+    args_slice: &[String],
+    version: TransactionVersion,
+    chain_id: u32,
+) -> Result<String, CliError> {
+    let args = args_slice.to_vec();
+    if args.len() >= 1 && args[0] == "-h" {
+        return Err(CliError::Message(format!(
+            "USAGE:\n {}",
+            POISON_MICROBLOCK_USAGE
+        )));
+    }
+    if args.len() != 5 {
+        return Err(CliError::Message(format!(
+            "Incorrect argument count supplied \n\nUSAGE:\n {}",
+            POISON_MICROBLOCK_USAGE
+        )));
+    }
+
+    let sk_origin = StacksPrivateKey::from_hex(&args[0])?;
+    let fee_rate = args[1].parse()?;
+    let nonce = args[2].parse()?;
+    let header_1 = parse_microblock_header(&args[3])?;
+    let header_2 = parse_microblock_header(&args[4])?;
+    check_conflicting_microblock_headers(&header_1, &header_2)?;
+
+    let payload = TransactionPayload::PoisonMicroblock(header_1, header_2);
+    let mut unsigned_tx = make_standard_single_sig_tx(
+        version,
+        chain_id,
+        payload,
+        &StacksPublicKey::from_private(&sk_origin),
+        nonce,
+        fee_rate,
+    );
+    unsigned_tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
+
+    let mut unsigned_tx_bytes = vec![];
+    unsigned_tx
+        .consensus_serialize(&mut unsigned_tx_bytes)
+        .expect("FATAL: invalid transaction");
+    let signed_tx = sign_transaction_single_sig_standard(&to_hex(&unsigned_tx_bytes), &sk_origin)?;
+
+    let mut signed_tx_bytes = vec![];
+    signed_tx
+        .consensus_serialize(&mut signed_tx_bytes)
+        .expect("FATAL: invalid signed transaction");
+    Ok(to_hex(&signed_tx_bytes))
+}
+
 fn generate_secret_key(args: &[String], version: TransactionVersion) -> Result<String, CliError> {
     if args.len() >= 1 && args[0] == "-h" {
         return Err(CliError::Message(format!("USAGE:\n {}", GENERATE_USAGE)));
@@ -766,6 +1073,11 @@ fn main_handler(mut argv: Vec<String>) -> Result<String, CliError> {
             "contract-call" => handle_contract_call(args, tx_version, chain_id),
             "publish" => handle_contract_publish(args, tx_version, chain_id),
             "token-transfer" => handle_token_transfer(args, tx_version, chain_id),
+            "delegate-stack-stx" => handle_delegate_stack_stx(args, tx_version, chain_id),
+            "stack-aggregation-commit" => {
+                handle_stack_aggregation_commit(args, tx_version, chain_id)
+            }
+            "poison-microblock" => handle_poison_microblock(args, tx_version, chain_id),
             "generate-sk" => generate_secret_key(args, tx_version),
             "addresses" => get_addresses(args, tx_version),
             "decode-tx" => decode_transaction(args, tx_version),
@@ -911,6 +1223,82 @@ mod test {
         assert!(main_handler(to_string_vec(&tt_args)).is_err());
     }
 
+    #[test]
+    fn token_transfer_long_memo() {
+        let tt_args = [
+            "token-transfer",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "0",
+            "ST1A14RBKJ289E3DP89QAZE2RRHDPWP5RHMYFRCHV",
+            "10",
+            "Memo",
+            "--long-memo",
+            "-e",
+            "(tuple (tag u42))",
+        ];
+
+        let tx_hex = main_handler(to_string_vec(&tt_args)).unwrap();
+        let tx = StacksTransaction::consensus_deserialize(&mut &hex_bytes(&tx_hex).unwrap()[..])
+            .unwrap();
+        match tx.payload {
+            TransactionPayload::TokenTransfer(_, amount, _, Some(long_memo)) => {
+                assert_eq!(amount, 10);
+                assert_eq!(
+                    long_memo,
+                    vm::execute("(tuple (tag u42))").unwrap().unwrap()
+                );
+            }
+            _ => panic!("Expected a token transfer with a long memo"),
+        }
+
+        let long_memo_hex = to_hex(&Value::UInt(42).serialize_to_vec());
+        let tt_args = [
+            "token-transfer",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "--long-memo",
+            "-x",
+            &long_memo_hex,
+            "0",
+            "ST1A14RBKJ289E3DP89QAZE2RRHDPWP5RHMYFRCHV",
+            "10",
+        ];
+
+        assert!(main_handler(to_string_vec(&tt_args)).is_ok());
+
+        let too_long = format!("0x{}", "00".repeat(MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH));
+        let tt_args = [
+            "token-transfer",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "0",
+            "ST1A14RBKJ289E3DP89QAZE2RRHDPWP5RHMYFRCHV",
+            "10",
+            "--long-memo",
+            "-e",
+            &too_long,
+        ];
+
+        assert!(
+            format!("{}", main_handler(to_string_vec(&tt_args)).unwrap_err())
+                .contains("Long memo is longer than")
+        );
+
+        let tt_args = [
+            "token-transfer",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "0",
+            "ST1A14RBKJ289E3DP89QAZE2RRHDPWP5RHMYFRCHV",
+            "10",
+            "--long-memo",
+            "-e",
+        ];
+
+        assert!(main_handler(to_string_vec(&tt_args)).is_err());
+    }
+
     #[test]
     fn simple_cc() {
         let cc_args = [
@@ -1060,6 +1448,180 @@ mod test {
         );
     }
 
+    #[test]
+    fn simple_pox_delegate_calls() {
+        let pox_addr =
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let pox_addr_b58 = pox_addr.to_b58();
+        let pox_addr_tuple = format!(
+            "{{ version: 0x00, hashbytes: 0x{} }}",
+            to_hex(pox_addr.bytes.as_bytes())
+        );
+
+        let commit_args = [
+            "stack-aggregation-commit",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "0",
+            pox_addr_b58.as_str(),
+            "12",
+        ];
+        let cc_args = [
+            "contract-call",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "0",
+            STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR,
+            "pox",
+            "stack-aggregation-commit",
+            "-e",
+            pox_addr_tuple.as_str(),
+            "-e",
+            "u12",
+        ];
+        assert_eq!(
+            main_handler(to_string_vec(&commit_args)).unwrap(),
+            main_handler(to_string_vec(&cc_args)).unwrap()
+        );
+
+        let delegate_args = [
+            "delegate-stack-stx",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "1",
+            "SPJT598WY1RJN792HRKRHRQYFB7RJ5ZCG6J6GEZ4",
+            "1000000",
+            pox_addr_b58.as_str(),
+            "666050",
+            "6",
+            "--block-only",
+        ];
+        let cc_args = [
+            "contract-call",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "1",
+            STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR,
+            "pox",
+            "delegate-stack-stx",
+            "-e",
+            "'SPJT598WY1RJN792HRKRHRQYFB7RJ5ZCG6J6GEZ4",
+            "-e",
+            "u1000000",
+            "-e",
+            pox_addr_tuple.as_str(),
+            "-e",
+            "u666050",
+            "-e",
+            "u6",
+            "--block-only",
+        ];
+        assert_eq!(
+            main_handler(to_string_vec(&delegate_args)).unwrap(),
+            main_handler(to_string_vec(&cc_args)).unwrap()
+        );
+
+        let bad_args = [
+            "stack-aggregation-commit",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "0",
+            "not-a-pox-address",
+            "12",
+        ];
+        assert!(
+            format!("{}", main_handler(to_string_vec(&bad_args)).unwrap_err())
+                .contains("Failed to parse PoX address")
+        );
+
+        let bad_args = ["delegate-stack-stx", "-h"];
+        assert!(
+            format!("{}", main_handler(to_string_vec(&bad_args)).unwrap_err()).contains("USAGE")
+        );
+    }
+
+    #[test]
+    fn simple_poison_microblock() {
+        use blockstack_lib::chainstate::burn::BlockHeaderHash;
+        use blockstack_lib::util::hash::Sha512Trunc256Sum;
+
+        let miner_sk = StacksPrivateKey::from_hex(
+            "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+        )
+        .unwrap();
+        let make_header = |sk: &StacksPrivateKey, sequence: u16, root: u8| {
+            let mut header = StacksMicroblockHeader::first_unsigned(
+                &BlockHeaderHash([0x11; 32]),
+                &Sha512Trunc256Sum([root; 32]),
+            );
+            header.sequence = sequence;
+            header.sign(sk).unwrap();
+            header
+        };
+        let header_hex = |header: &StacksMicroblockHeader| {
+            let mut bytes = vec![];
+            header.consensus_serialize(&mut bytes).unwrap();
+            to_hex(&bytes)
+        };
+
+        let header_1 = make_header(&miner_sk, 0, 0x01);
+        let header_2 = make_header(&miner_sk, 0, 0x02);
+
+        let poison_args = [
+            "--testnet",
+            "poison-microblock",
+            "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+            "1",
+            "0",
+            &header_hex(&header_1),
+            &header_hex(&header_2),
+        ];
+        let result = main_handler(to_string_vec(&poison_args)).unwrap();
+        let tx = StacksTransaction::consensus_deserialize(&mut io::Cursor::new(
+            &hex_bytes(&result).unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(tx.version, TransactionVersion::Testnet);
+        assert_eq!(tx.anchor_mode, TransactionAnchorMode::OnChainOnly);
+        assert_eq!(
+            tx.payload,
+            TransactionPayload::PoisonMicroblock(header_1.clone(), header_2.clone())
+        );
+        assert!(tx.verify().is_ok());
+
+        // not conflicting
+        let other_sk = StacksPrivateKey::from_hex(
+            "b8d99fd45da58038d630d9855d3ca2466e8e0f89d3894c4724f0efc9ff4b51f001",
+        )
+        .unwrap();
+        for bad_header in [
+            header_1.clone(),
+            make_header(&miner_sk, 1, 0x02),
+            make_header(&other_sk, 0, 0x02),
+        ]
+        .iter()
+        {
+            let poison_args = [
+                "--testnet",
+                "poison-microblock",
+                "043ff5004e3d695060fa48ac94c96049b8c14ef441c50a184a6a3875d2a000f3",
+                "1",
+                "0",
+                &header_hex(&header_1),
+                &header_hex(bad_header),
+            ];
+            assert!(
+                format!("{}", main_handler(to_string_vec(&poison_args)).unwrap_err())
+                    .contains("Microblock headers")
+            );
+        }
+
+        let bad_args = ["poison-microblock", "-h"];
+        assert!(
+            format!("{}", main_handler(to_string_vec(&bad_args)).unwrap_err()).contains("USAGE")
+        );
+    }
+
     #[test]
     fn simple_addresses() {
         let addr_args = [
@@ -29,7 +29,7 @@ use deps::bitcoin::util::hash::bitcoin_merkle_root;
 
 use burnchains::bitcoin::address::BitcoinAddress;
 use burnchains::bitcoin::bits;
-use burnchains::bitcoin::indexer::BitcoinIndexer;
+use burnchains::bitcoin::indexer::{BitcoinIndexer, BitcoinSyncMode};
 use burnchains::bitcoin::keys::BitcoinPublicKey;
 use burnchains::bitcoin::messages::BitcoinMessageHandler;
 use burnchains::bitcoin::BitcoinInputType;
@@ -125,6 +125,12 @@ impl BitcoinBlockDownloader {
         // due to &mut self.
         let mut indexer = self.indexer.take().unwrap();
 
+        if indexer.config.sync_mode == BitcoinSyncMode::Rpc {
+            let res = BitcoinBlockDownloader::download_rpc(&indexer, header);
+            self.indexer = Some(indexer);
+            return res;
+        }
+
         indexer.peer_communicate(self, false)?;
 
         self.indexer = Some(indexer);
@@ -138,6 +144,34 @@ impl BitcoinBlockDownloader {
     }
 }
 
+impl BitcoinBlockDownloader {
+    /// Fetch a block from the indexer's bitcoind over JSON-RPC
+    fn download_rpc(
+        indexer: &BitcoinIndexer,
+        header: &BitcoinHeaderIPC,
+    ) -> Result<BitcoinBlockIPC, btc_error> {
+        let block_hash = header.block_header.header.bitcoin_hash();
+        let block = indexer.rpc_client().get_block(&block_hash)?;
+        if !BitcoinBlockParser::check_block(&block, &header.block_header) {
+            warn!(
+                "Requested block {}, but bitcoind sent a block that does not match it",
+                &to_hex(block_hash.as_bytes())
+            );
+            return Err(btc_error::InvalidReply);
+        }
+
+        debug!(
+            "Got block {} over RPC: {}",
+            header.block_height,
+            &to_hex(BurnchainHeaderHash::from_bitcoin_hash(&block_hash).as_bytes())
+        );
+        Ok(BitcoinBlockIPC {
+            header_data: header.clone(),
+            block_message: btc_message::NetworkMessage::Block(block),
+        })
+    }
+}
+
 impl BurnchainBlockDownloader for BitcoinBlockDownloader {
     type H = BitcoinHeaderIPC;
     type B = BitcoinBlockIPC;
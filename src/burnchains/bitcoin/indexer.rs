@@ -15,6 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use rand::{thread_rng, Rng};
+use std::cmp;
 use std::fs;
 use std::net;
 use std::net::Shutdown;
@@ -22,6 +23,7 @@ use std::ops::Deref;
 use std::ops::DerefMut;
 use std::path;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::time;
 use std::time::Duration;
 
@@ -29,6 +31,7 @@ use tini::Ini;
 
 use burnchains::bitcoin::blocks::BitcoinHeaderIPC;
 use burnchains::bitcoin::messages::BitcoinMessageHandler;
+use burnchains::bitcoin::rpc::{BitcoindRpcClient, RPC_BATCH_SIZE};
 use burnchains::bitcoin::spv::*;
 use burnchains::bitcoin::Error as btc_error;
 use burnchains::indexer::BurnchainIndexer;
@@ -44,6 +47,7 @@ use burnchains::MagicBytes;
 use burnchains::BLOCKSTACK_MAGIC_MAINNET;
 
 use deps::bitcoin::blockdata::block::LoneBlockHeader;
+use deps::bitcoin::network::encodable::VarInt;
 use deps::bitcoin::network::message::NetworkMessage;
 use deps::bitcoin::network::serialize::BitcoinHash;
 
@@ -66,6 +70,9 @@ const REORG_BATCH_SIZE: u64 = 16;
 #[cfg(test)]
 const REORG_BATCH_SIZE: u64 = 2;
 
+/// How many peer connections to download the headers between checkpoints over, by default
+pub const DEFAULT_SPV_DOWNLOAD_THREADS: usize = 4;
+
 pub fn network_id_to_bytes(network_id: BitcoinNetworkType) -> u32 {
     match network_id {
         BitcoinNetworkType::Mainnet => BITCOIN_MAINNET,
@@ -74,6 +81,45 @@ pub fn network_id_to_bytes(network_id: BitcoinNetworkType) -> u32 {
     }
 }
 
+/// Where the indexer gets Bitcoin headers and blocks from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitcoinSyncMode {
+    /// a Bitcoin peer, over the P2P protocol
+    Spv,
+    /// a trusted bitcoind, over its JSON-RPC interface
+    Rpc,
+}
+
+impl FromStr for BitcoinSyncMode {
+    type Err = btc_error;
+
+    fn from_str(s: &str) -> Result<BitcoinSyncMode, btc_error> {
+        match s {
+            "spv" => Ok(BitcoinSyncMode::Spv),
+            "rpc" => Ok(BitcoinSyncMode::Rpc),
+            _ => Err(btc_error::ConfigError(format!(
+                "Invalid sync mode '{}' -- expected spv or rpc",
+                s
+            ))),
+        }
+    }
+}
+
+impl Default for BitcoinSyncMode {
+    fn default() -> BitcoinSyncMode {
+        BitcoinSyncMode::Spv
+    }
+}
+
+impl BitcoinSyncMode {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            BitcoinSyncMode::Spv => "spv",
+            BitcoinSyncMode::Rpc => "rpc",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct BitcoinIndexerConfig {
     // config fields
@@ -87,6 +133,12 @@ pub struct BitcoinIndexerConfig {
     pub spv_headers_path: String,
     pub first_block: u64,
     pub magic_bytes: MagicBytes,
+    pub sync_mode: BitcoinSyncMode,
+    /// header checkpoints to use instead of the compiled-in ones, if given
+    pub spv_checkpoints: Option<Vec<SpvCheckpoint>>,
+    /// how many peer connections to download the headers between checkpoints over (0 to only
+    /// download headers sequentially)
+    pub spv_download_threads: usize,
 }
 
 #[derive(Debug)]
@@ -120,6 +172,9 @@ impl BitcoinIndexerConfig {
             spv_headers_path: "./spv-headers.dat".to_string(),
             first_block,
             magic_bytes: BLOCKSTACK_MAGIC_MAINNET.clone(),
+            sync_mode: BitcoinSyncMode::Spv,
+            spv_checkpoints: None,
+            spv_download_threads: DEFAULT_SPV_DOWNLOAD_THREADS,
         }
     }
 
@@ -135,6 +190,9 @@ impl BitcoinIndexerConfig {
             spv_headers_path: spv_headers_path,
             first_block: 0,
             magic_bytes: BLOCKSTACK_MAGIC_MAINNET.clone(),
+            sync_mode: BitcoinSyncMode::Spv,
+            spv_checkpoints: None,
+            spv_download_threads: DEFAULT_SPV_DOWNLOAD_THREADS,
         }
     }
 
@@ -142,7 +200,7 @@ impl BitcoinIndexerConfig {
         let username = self.username.clone().unwrap_or("".to_string());
         let password = self.password.clone().unwrap_or("".to_string());
 
-        let conf = Ini::new()
+        let mut conf = Ini::new()
             .section("bitcoin")
             .item("server", self.peer_host.as_str())
             .item("p2p_port", format!("{}", self.peer_port).as_str())
@@ -153,6 +211,11 @@ impl BitcoinIndexerConfig {
             .item("timeout", format!("{}", self.timeout).as_str())
             .item("spv_path", self.spv_headers_path.as_str())
             .item("first_block", format!("{}", self.first_block).as_str())
+            .item("sync_mode", self.sync_mode.as_str())
+            .item(
+                "spv_download_threads",
+                format!("{}", self.spv_download_threads).as_str(),
+            )
             .section("blockstack")
             .item(
                 "network_id",
@@ -164,6 +227,13 @@ impl BitcoinIndexerConfig {
                 .as_str(),
             );
 
+        if let Some(ref checkpoints) = self.spv_checkpoints {
+            conf = conf.section("bitcoin").item(
+                "spv_checkpoints",
+                SpvCheckpoint::list_to_string(checkpoints).as_str(),
+            );
+        }
+
         conf.to_file(&path).map_err(|e| btc_error::Io(e))
     }
 
@@ -257,6 +327,27 @@ impl BitcoinIndexerConfig {
 
                 let rpc_ssl = rpc_ssl_str == "1" || rpc_ssl_str == "true";
 
+                let sync_mode = match ini_file.get::<String>("bitcoin", "sync_mode") {
+                    Some(sync_mode_str) => sync_mode_str.trim().parse()?,
+                    None => default_config.sync_mode,
+                };
+
+                let spv_checkpoints = match ini_file.get::<String>("bitcoin", "spv_checkpoints") {
+                    Some(checkpoints_str) => Some(SpvCheckpoint::parse_list(&checkpoints_str)?),
+                    None => None,
+                };
+
+                let spv_download_threads = ini_file
+                    .get("bitcoin", "spv_download_threads")
+                    .unwrap_or(format!("{}", default_config.spv_download_threads))
+                    .trim()
+                    .parse()
+                    .map_err(|_e| {
+                        btc_error::ConfigError(
+                            "Invalid bitcoin:spv_download_threads value".to_string(),
+                        )
+                    })?;
+
                 // [blockstack]
                 let blockstack_magic_str =
                     ini_file.get("blockstack", "network_id").unwrap_or(format!(
@@ -287,6 +378,9 @@ impl BitcoinIndexerConfig {
                     spv_headers_path: spv_headers_path.to_string(),
                     first_block: first_block,
                     magic_bytes: blockstack_magic,
+                    sync_mode: sync_mode,
+                    spv_checkpoints: spv_checkpoints,
+                    spv_download_threads: spv_download_threads,
                 };
 
                 Ok(cfg)
@@ -523,9 +617,77 @@ impl BitcoinIndexer {
             true,
             false,
         )?;
-        spv_client
-            .run(self)
-            .and_then(|_r| Ok(spv_client.end_block_height.unwrap()))
+        self.apply_spv_checkpoints(&mut spv_client);
+        match self.config.sync_mode {
+            BitcoinSyncMode::Spv => {
+                // fetch the headers between checkpoints concurrently, then the rest in order
+                spv_client.download_checkpointed_headers(
+                    self,
+                    last_block,
+                    self.config.spv_download_threads,
+                )?;
+                spv_client
+                    .run(self)
+                    .and_then(|_r| Ok(spv_client.end_block_height.unwrap()))
+            }
+            BitcoinSyncMode::Rpc => self.load_headers_rpc(&mut spv_client, start_block, last_block),
+        }
+    }
+
+    /// Use the configured header checkpoints, if any, instead of the compiled-in ones
+    fn apply_spv_checkpoints(&self, spv_client: &mut SpvClient) -> () {
+        if let Some(ref checkpoints) = self.config.spv_checkpoints {
+            spv_client.set_checkpoints(checkpoints.clone());
+        }
+    }
+
+    /// A client for the JSON-RPC interface of our configured bitcoind
+    pub fn rpc_client(&self) -> BitcoindRpcClient {
+        BitcoindRpcClient::new(
+            &self.config.peer_host,
+            self.config.rpc_port,
+            self.config.username.clone(),
+            self.config.password.clone(),
+            self.config.timeout as u64,
+        )
+    }
+
+    /// Fetch headers from bitcoind's JSON-RPC interface and store them with the given SPV client,
+    /// starting with the child of start_block.  If end_block is None, then fetch headers up to
+    /// bitcoind's chain tip.
+    /// Returns the height of the last block fetched
+    pub fn load_headers_rpc(
+        &mut self,
+        spv_client: &mut SpvClient,
+        start_block: u64,
+        end_block: Option<u64>,
+    ) -> Result<u64, btc_error> {
+        let client = self.rpc_client();
+        let chain_tip = client.get_block_count()?;
+        self.runtime.block_height = chain_tip;
+
+        let end_block = match end_block {
+            Some(end_block) => cmp::min(end_block, chain_tip),
+            None => chain_tip,
+        };
+
+        let mut height = start_block;
+        while height < end_block {
+            let batch_end = cmp::min(end_block, height + RPC_BATCH_SIZE as u64);
+            debug!("Get headers {}-{} over RPC", height + 1, batch_end);
+
+            let headers = client
+                .get_block_headers_range(height + 1, batch_end + 1)?
+                .into_iter()
+                .map(|header| LoneBlockHeader {
+                    header,
+                    tx_count: VarInt(0),
+                })
+                .collect();
+            spv_client.handle_headers(height, headers)?;
+            height = batch_end;
+        }
+        Ok(end_block)
     }
 
     /// Create a SPV client for starting reorg processing
@@ -551,6 +713,7 @@ impl BitcoinIndexer {
             true,
             true,
         )?;
+        self.apply_spv_checkpoints(&mut reorg_spv_client);
         if start_block > 0 {
             let start_header = canonical_spv_client
                 .read_block_header(start_block)?
@@ -827,7 +990,21 @@ impl BurnchainIndexer for BitcoinIndexer {
     /// Use the peer host and peer port given in the config file,
     /// and loaded in on setup.  Don't call this before init().
     fn connect(&mut self) -> Result<(), burnchain_error> {
-        self.reconnect_peer().map_err(burnchain_error::Bitcoin)
+        match self.config.sync_mode {
+            BitcoinSyncMode::Spv => self.reconnect_peer().map_err(burnchain_error::Bitcoin),
+            BitcoinSyncMode::Rpc => {
+                if self.config.rpc_ssl {
+                    return Err(burnchain_error::Bitcoin(btc_error::ConfigError(
+                        "Syncing over bitcoind RPC does not support SSL".to_string(),
+                    )));
+                }
+                self.runtime.block_height = self
+                    .rpc_client()
+                    .get_block_count()
+                    .map_err(burnchain_error::Bitcoin)?;
+                Ok(())
+            }
+        }
     }
 
     /// Get the location on disk where we keep headers
@@ -943,15 +1120,26 @@ impl BurnchainIndexer for BitcoinIndexer {
     fn find_chain_reorg(&mut self) -> Result<u64, burnchain_error> {
         let headers_path = self.config.spv_headers_path.clone();
         let reorg_path = format!("{}.reorg", &self.config.spv_headers_path);
-        self.find_bitcoin_reorg(
-            &headers_path,
-            &reorg_path,
-            |ref mut indexer, ref mut spv_client, start_block, end_block_opt| {
-                spv_client.set_scan_range(start_block, end_block_opt);
-                spv_client.run(indexer)
-            },
-        )
-        .map_err(|e| match e {
+        let res = match self.config.sync_mode {
+            BitcoinSyncMode::Spv => self.find_bitcoin_reorg(
+                &headers_path,
+                &reorg_path,
+                |ref mut indexer, ref mut spv_client, start_block, end_block_opt| {
+                    spv_client.set_scan_range(start_block, end_block_opt);
+                    spv_client.run(indexer)
+                },
+            ),
+            BitcoinSyncMode::Rpc => self.find_bitcoin_reorg(
+                &headers_path,
+                &reorg_path,
+                |ref mut indexer, ref mut spv_client, start_block, end_block_opt| {
+                    indexer
+                        .load_headers_rpc(spv_client, start_block, end_block_opt)
+                        .map(|_| ())
+                },
+            ),
+        };
+        res.map_err(|e| match e {
             btc_error::TimedOut => burnchain_error::TrySyncAgain,
             x => burnchain_error::Bitcoin(x),
         })
@@ -1406,6 +1594,9 @@ mod test {
             spv_headers_path: "/tmp/test_indexer_sync_headers.db".to_string(),
             first_block: 0,
             magic_bytes: MagicBytes([105, 100]),
+            sync_mode: BitcoinSyncMode::Spv,
+            spv_checkpoints: None,
+            spv_download_threads: DEFAULT_SPV_DOWNLOAD_THREADS,
         };
 
         if fs::metadata(&indexer_conf.spv_headers_path).is_ok() {
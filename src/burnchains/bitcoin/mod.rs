@@ -24,6 +24,7 @@ pub mod indexer;
 pub mod keys;
 pub mod messages;
 pub mod network;
+pub mod rpc;
 pub mod spv;
 
 use std::error;
@@ -90,6 +91,10 @@ pub enum Error {
     BlockchainHeight,
     /// Request timed out
     TimedOut,
+    /// bitcoind refused a JSON-RPC call
+    RpcError(String),
+    /// Header at the given height does not match our checkpoint
+    CheckpointMismatch(u64),
 }
 
 impl fmt::Display for Error {
@@ -114,6 +119,10 @@ impl fmt::Display for Error {
             Error::ConfigError(ref e_str) => fmt::Display::fmt(e_str, f),
             Error::BlockchainHeight => write!(f, "Value is beyond the end of the blockchain"),
             Error::TimedOut => write!(f, "Request timed out"),
+            Error::RpcError(ref msg) => write!(f, "bitcoind RPC error: {}", msg),
+            Error::CheckpointMismatch(height) => {
+                write!(f, "Header at height {} does not match checkpoint", height)
+            }
         }
     }
 }
@@ -140,6 +149,8 @@ impl error::Error for Error {
             Error::ConfigError(ref _e_str) => None,
             Error::BlockchainHeight => None,
             Error::TimedOut => None,
+            Error::RpcError(ref _msg) => None,
+            Error::CheckpointMismatch(_) => None,
         }
     }
 }
@@ -16,9 +16,14 @@
 
 use std::cmp;
 use std::collections::VecDeque;
+use std::fmt;
 use std::fs;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
 
 use deps::bitcoin::blockdata::block::{BlockHeader, LoneBlockHeader};
 use deps::bitcoin::blockdata::constants::genesis_block;
@@ -65,6 +70,71 @@ const GENESIS_BLOCK_HASH_REGTEST: &'static str =
 pub const BLOCK_DIFFICULTY_CHUNK_SIZE: u64 = 2016;
 const BLOCK_DIFFICULTY_INTERVAL: u32 = 14 * 24 * 60 * 60; // two weeks, in seconds
 
+/// Most headers a bitcoin peer sends in reply to a getheaders
+const MAX_HEADERS_PER_REPLY: usize = 2000;
+
+/// Known-good mainnet headers, as (height, block hash) (from Bitcoin Core's checkpoints)
+const CHECKPOINTS_MAINNET: &[(u64, &'static str)] = &[
+    (
+        11111,
+        "0000000069e244f73d78e8fd29ba2fd2ed618bd6fa2ee92559f542fdb26e7c1d",
+    ),
+    (
+        33333,
+        "000000002dd5588a74784eaa7ab0507a18ad16a236e7b1ce69f00d7ddfb5d0a6",
+    ),
+    (
+        74000,
+        "0000000000573993a3c9e41ce34471c079dcf5f52a0e824a81e7f953b8661a20",
+    ),
+    (
+        105000,
+        "00000000000291ce28027faea320c8d2b054b2e0fe44a773f3eefb151d6bdc97",
+    ),
+    (
+        134444,
+        "00000000000005b12ffd4cd315cd34ffd4a594f430ac814c91184a0d42d2b0fe",
+    ),
+    (
+        168000,
+        "000000000000099e61ea72015e79632f216fe6cb33d7899acb35b75c8303b763",
+    ),
+    (
+        193000,
+        "000000000000059f452a5f7340de6682a977387c17010ff6e6c3bd83ca8b1317",
+    ),
+    (
+        210000,
+        "000000000000048b95347e83192f69cf0366076336c639f9b7228e9ba171342e",
+    ),
+    (
+        216116,
+        "00000000000001b4f4b433e81ee46494af945cf96014816a4e2370f11b23df4e",
+    ),
+    (
+        225430,
+        "00000000000001c108384350f74090433e7fcf79a606b8e797f065b130575932",
+    ),
+    (
+        250000,
+        "000000000000003887df1f29024b06fc2200b55f8af8f35453d7be294df2d214",
+    ),
+    (
+        279000,
+        "0000000000000001ae8c72a0b0c301f67e3afca10e819efa9041e458e9bd7e40",
+    ),
+    (
+        295000,
+        "00000000000000004d9b4ef50f0f9d686fd69db2e03af35a100370c64632a983",
+    ),
+];
+
+/// Known-good testnet headers, as (height, block hash)
+const CHECKPOINTS_TESTNET: &[(u64, &'static str)] = &[(
+    546,
+    "000000002a936ca763904c3c35fce2f3556c559c0214345d31b1bcebf76acb70",
+)];
+
 const SPV_SQL: &[&'static str] = &[r#"
     CREATE TABLE headers(
         version INTEGER NOT NULL,
@@ -86,6 +156,196 @@ pub struct SpvClient {
     readwrite: bool,
     reverse_order: bool,
     headers_db: DBConn,
+    checkpoints: Vec<SpvCheckpoint>,
+}
+
+/// A block header hash that the header at a given height must have.  Headers that disagree with
+/// a checkpoint are rejected, and the headers between two checkpoints can be downloaded
+/// independently of (and concurrently with) the headers before them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpvCheckpoint {
+    pub height: u64,
+    pub hash: Sha256dHash,
+}
+
+impl SpvCheckpoint {
+    /// The compiled-in checkpoints for a network, in ascending order of height
+    pub fn defaults(network_id: BitcoinNetworkType) -> Vec<SpvCheckpoint> {
+        let checkpoints = match network_id {
+            BitcoinNetworkType::Mainnet => CHECKPOINTS_MAINNET,
+            BitcoinNetworkType::Testnet => CHECKPOINTS_TESTNET,
+            BitcoinNetworkType::Regtest => &[],
+        };
+        checkpoints
+            .iter()
+            .map(|(height, hash)| SpvCheckpoint {
+                height: *height,
+                hash: Sha256dHash::from_hex(hash).expect("FATAL: invalid checkpoint hash"),
+            })
+            .collect()
+    }
+
+    /// Parse a comma-separated list of `height:hash` checkpoints.  An empty list disables
+    /// checkpoints altogether.
+    pub fn parse_list(list: &str) -> Result<Vec<SpvCheckpoint>, btc_error> {
+        let mut checkpoints = list
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| s.len() > 0)
+            .map(|s| s.parse())
+            .collect::<Result<Vec<SpvCheckpoint>, btc_error>>()?;
+        checkpoints.sort_by_key(|cp| cp.height);
+        Ok(checkpoints)
+    }
+
+    /// Encode checkpoints in the format `parse_list()` reads
+    pub fn list_to_string(checkpoints: &[SpvCheckpoint]) -> String {
+        checkpoints
+            .iter()
+            .map(|cp| cp.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+impl FromStr for SpvCheckpoint {
+    type Err = btc_error;
+
+    fn from_str(s: &str) -> Result<SpvCheckpoint, btc_error> {
+        let mut parts = s.trim().splitn(2, ':');
+        let height = parts
+            .next()
+            .and_then(|height| height.trim().parse().ok())
+            .ok_or_else(|| {
+                btc_error::ConfigError(format!("Invalid checkpoint height in '{}'", s))
+            })?;
+        let hash = parts
+            .next()
+            .and_then(|hash| Sha256dHash::from_hex(hash.trim()).ok())
+            .ok_or_else(|| btc_error::ConfigError(format!("Invalid checkpoint hash in '{}'", s)))?;
+        Ok(SpvCheckpoint { height, hash })
+    }
+}
+
+impl fmt::Display for SpvCheckpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.height, self.hash.be_hex_string())
+    }
+}
+
+/// Downloads the headers in (start_height, end_height] from a bitcoin peer, given the hash of the
+/// header at start_height and the hash the header at end_height must have.  Used to fetch the
+/// headers between two checkpoints without needing the headers before them.
+struct HeaderRangeDownloader {
+    start_height: u64,
+    start_hash: Sha256dHash,
+    end_height: u64,
+    end_hash: Sha256dHash,
+    headers: Vec<LoneBlockHeader>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl HeaderRangeDownloader {
+    /// Hash of the last header we have
+    fn tip_hash(&self) -> Sha256dHash {
+        match self.headers.last() {
+            Some(header) => header.header.bitcoin_hash(),
+            None => self.start_hash,
+        }
+    }
+
+    /// Download the range on its own thread, over its own connection to the peer.
+    fn spawn(
+        mut self,
+        mut indexer: BitcoinIndexer,
+    ) -> thread::JoinHandle<Result<Vec<LoneBlockHeader>, btc_error>> {
+        thread::spawn(move || {
+            debug!(
+                "Download headers {}-{} from checkpoint {}",
+                self.start_height + 1,
+                self.end_height,
+                &self.start_hash
+            );
+            indexer.peer_communicate(&mut self, true)?;
+            if self.headers.len() as u64 != self.end_height - self.start_height {
+                // cancelled
+                return Err(btc_error::MissingHeader);
+            }
+            Ok(self.headers)
+        })
+    }
+}
+
+impl BitcoinMessageHandler for HeaderRangeDownloader {
+    /// (re)start the download from the last header we got
+    fn begin_session(&mut self, indexer: &mut BitcoinIndexer) -> Result<bool, btc_error> {
+        if self.cancel.load(Ordering::SeqCst)
+            || self.headers.len() as u64 >= self.end_height - self.start_height
+        {
+            return Ok(false);
+        }
+        indexer
+            .send_getheaders(self.tip_hash())
+            .and_then(|_r| Ok(true))
+    }
+
+    /// Take headers, check that they continue the range, and ask for more until we reach the
+    /// checkpoint at the end of the range
+    fn handle_message(
+        &mut self,
+        indexer: &mut BitcoinIndexer,
+        msg: PeerMessage,
+    ) -> Result<bool, btc_error> {
+        match msg {
+            btc_message::NetworkMessage::Headers(mut block_headers) => {
+                if self.cancel.load(Ordering::SeqCst) {
+                    return Ok(false);
+                }
+                if block_headers.len() == 0 {
+                    warn!(
+                        "Peer has no headers after block {}",
+                        self.start_height + self.headers.len() as u64
+                    );
+                    return Err(btc_error::MissingHeader);
+                }
+
+                let next_height = self.start_height + self.headers.len() as u64;
+                SpvClient::validate_header_integrity(next_height + 1, &block_headers)?;
+                if block_headers[0].header.prev_blockhash != self.tip_hash() {
+                    warn!(
+                        "Received discontiguous headers at height {}: expected parent {}, got {}",
+                        next_height + 1,
+                        self.tip_hash(),
+                        block_headers[0].header.prev_blockhash
+                    );
+                    return Err(btc_error::NoncontiguousHeader);
+                }
+
+                block_headers.truncate((self.end_height - next_height) as usize);
+                self.headers.append(&mut block_headers);
+                indexer.runtime.last_getheaders_send_time = 0;
+
+                if (self.headers.len() as u64) < self.end_height - self.start_height {
+                    return indexer
+                        .send_getheaders(self.tip_hash())
+                        .and_then(|_r| Ok(true));
+                }
+
+                // stitched to the checkpoint at the end of the range?
+                if self.tip_hash() != self.end_hash {
+                    warn!(
+                        "Header {} is {}, but checkpoint requires {}",
+                        self.end_height,
+                        self.tip_hash(),
+                        self.end_hash
+                    );
+                    return Err(btc_error::CheckpointMismatch(self.end_height));
+                }
+                Ok(false)
+            }
+            x => Err(btc_error::UnhandledMessage(x)),
+        }
+    }
 }
 
 impl FromSql for Sha256dHash {
@@ -148,6 +408,7 @@ impl SpvClient {
             readwrite: readwrite,
             reverse_order: reverse_order,
             headers_db: conn,
+            checkpoints: SpvCheckpoint::defaults(network_id),
         };
 
         if readwrite {
@@ -229,12 +490,169 @@ impl SpvClient {
         self.cur_block_height = start_block;
     }
 
+    /// Replace the checkpoints that stored headers must agree with
+    pub fn set_checkpoints(&mut self, mut checkpoints: Vec<SpvCheckpoint>) -> () {
+        checkpoints.sort_by_key(|cp| cp.height);
+        self.checkpoints = checkpoints;
+    }
+
+    pub fn get_checkpoints(&self) -> &[SpvCheckpoint] {
+        &self.checkpoints
+    }
+
     /// go get all the headers.
     /// keep trying forever.
     pub fn run(&mut self, indexer: &mut BitcoinIndexer) -> Result<(), btc_error> {
         indexer.peer_communicate(self, true)
     }
 
+    /// Split the headers between cur_block_height and the last checkpoint at or below end_block
+    /// into ranges that each end on a checkpoint.  Each range is given as (start height, hash of
+    /// the header at the start height, end height, checkpoint hash at the end height).
+    fn checkpoint_ranges(
+        &self,
+        end_block: Option<u64>,
+    ) -> Result<Vec<(u64, Sha256dHash, u64, Sha256dHash)>, btc_error> {
+        let mut start_height = self.cur_block_height;
+        let mut start_hash = match self.read_block_header(start_height)? {
+            Some(header) => header.header.bitcoin_hash(),
+            None => return Ok(vec![]),
+        };
+
+        let mut ranges = vec![];
+        for checkpoint in self.checkpoints.iter() {
+            if checkpoint.height <= start_height {
+                continue;
+            }
+            if let Some(end_block) = end_block {
+                if checkpoint.height > end_block {
+                    break;
+                }
+            }
+            ranges.push((start_height, start_hash, checkpoint.height, checkpoint.hash));
+            start_height = checkpoint.height;
+            start_hash = checkpoint.hash;
+        }
+        Ok(ranges)
+    }
+
+    /// Download the headers up to the last checkpoint at or below end_block (if given), fetching
+    /// the ranges between checkpoints over up to num_threads concurrent peer connections.  Ranges
+    /// are stored in order as they arrive, so each one is validated against the headers before it
+    /// just as if it had been downloaded sequentially.
+    /// Returns the height of the last header stored, from which `run()` picks up.
+    pub fn download_checkpointed_headers(
+        &mut self,
+        indexer: &BitcoinIndexer,
+        end_block: Option<u64>,
+        num_threads: usize,
+    ) -> Result<u64, btc_error> {
+        assert!(self.readwrite, "SPV header DB is open read-only");
+
+        let ranges = self.checkpoint_ranges(end_block)?;
+        if ranges.len() == 0 || num_threads == 0 {
+            return Ok(self.cur_block_height);
+        }
+
+        debug!(
+            "Download headers {}-{} in {} ranges over {} connections",
+            self.cur_block_height + 1,
+            ranges[ranges.len() - 1].2,
+            ranges.len(),
+            num_threads
+        );
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut pending = ranges.into_iter();
+        let mut downloads = VecDeque::new();
+        let res = loop {
+            while downloads.len() < num_threads {
+                match pending.next() {
+                    Some((start_height, start_hash, end_height, end_hash)) => {
+                        let downloader = HeaderRangeDownloader {
+                            start_height,
+                            start_hash,
+                            end_height,
+                            end_hash,
+                            headers: vec![],
+                            cancel: cancel.clone(),
+                        };
+                        downloads.push_back((start_height, downloader.spawn(indexer.dup())));
+                    }
+                    None => break,
+                }
+            }
+
+            let (start_height, download) = match downloads.pop_front() {
+                Some(download) => download,
+                None => break Ok(self.cur_block_height),
+            };
+
+            let headers = match download
+                .join()
+                .expect("FATAL: header download thread panicked")
+            {
+                Ok(headers) => headers,
+                Err(e) => break Err(e),
+            };
+
+            if let Err(e) = self.store_header_range(start_height, headers) {
+                break Err(e);
+            }
+        };
+
+        if res.is_err() {
+            // stop the downloads still in flight
+            cancel.store(true, Ordering::SeqCst);
+        }
+        res
+    }
+
+    /// Store a downloaded range of headers after start_height, in batches the size of a
+    /// getheaders reply so that each one gets the same validation as headers from `run()`.
+    fn store_header_range(
+        &mut self,
+        start_height: u64,
+        mut headers: Vec<LoneBlockHeader>,
+    ) -> Result<(), btc_error> {
+        let mut height = start_height;
+        while headers.len() > 0 {
+            let rest = headers.split_off(cmp::min(headers.len(), MAX_HEADERS_PER_REPLY));
+            let batch = std::mem::replace(&mut headers, rest);
+            let num_headers = batch.len() as u64;
+
+            self.handle_headers(height, batch)?;
+            height += num_headers;
+            self.cur_block_height = height;
+        }
+        Ok(())
+    }
+
+    /// Verify that the headers starting at first_height agree with our checkpoints
+    fn check_checkpoints(
+        &self,
+        first_height: u64,
+        headers: &Vec<LoneBlockHeader>,
+    ) -> Result<(), btc_error> {
+        let end_height = first_height + headers.len() as u64;
+        for checkpoint in self.checkpoints.iter() {
+            if checkpoint.height < first_height || checkpoint.height >= end_height {
+                continue;
+            }
+            let header_hash = headers[(checkpoint.height - first_height) as usize]
+                .header
+                .bitcoin_hash();
+            if header_hash != checkpoint.hash {
+                warn!(
+                    "Header {} is {}, but checkpoint requires {}",
+                    checkpoint.height, header_hash, checkpoint.hash
+                );
+                return Err(btc_error::CheckpointMismatch(checkpoint.height));
+            }
+        }
+        Ok(())
+    }
+
     /// Validate a headers message we requested
     /// * must have at least one header
     /// * headers must be contiguous
@@ -479,7 +897,7 @@ impl SpvClient {
     /// -- store them
     /// Can error if there has been a reorg, or if the headers don't correspond to headers we asked
     /// for.
-    fn handle_headers(
+    pub fn handle_headers(
         &mut self,
         insert_height: u64,
         block_headers: Vec<LoneBlockHeader>,
@@ -619,6 +1037,8 @@ impl SpvClient {
             return Err(btc_error::NoncontiguousHeader);
         }
 
+        self.check_checkpoints(start_height + 1, &block_headers)?;
+
         // store them
         self.write_block_headers(start_height + 1, block_headers)
     }
@@ -689,6 +1109,8 @@ impl SpvClient {
             }
         }
 
+        self.check_checkpoints(start_height + 1, &block_headers)?;
+
         // store them
         self.write_block_headers(start_height + 1, block_headers)
     }
@@ -938,6 +1360,13 @@ mod test {
     use util::log;
 
     use std::env;
+    use std::net::{TcpListener, TcpStream};
+
+    use burnchains::bitcoin::indexer::{
+        network_id_to_bytes, BitcoinIndexerConfig, BitcoinIndexerRuntime,
+    };
+    use deps::bitcoin::network::encodable::{ConsensusDecodable, ConsensusEncodable};
+    use deps::bitcoin::network::serialize::{RawDecoder, RawEncoder};
 
     fn get_genesis_regtest_header() -> LoneBlockHeader {
         let genesis_regtest_header = LoneBlockHeader {
@@ -1287,4 +1716,209 @@ mod test {
             spv_client.validate_header_work(i, i + 1).unwrap();
         }
     }
+
+    /// Mine a regtest header chain of the given length, starting with the regtest genesis block
+    fn make_regtest_headers(len: usize) -> Vec<LoneBlockHeader> {
+        let mut headers = vec![get_genesis_regtest_header()];
+        while headers.len() < len {
+            let mut header = BlockHeader {
+                bits: 545259519,
+                merkle_root: Sha256dHash::from_data(&(headers.len() as u64).to_be_bytes()),
+                nonce: 0,
+                prev_blockhash: headers[headers.len() - 1].header.bitcoin_hash(),
+                time: 1587626881 + headers.len() as u32,
+                version: 0x20000000,
+            };
+            while header.bitcoin_hash().into_le() >= header.target() {
+                header.nonce += 1;
+            }
+            headers.push(LoneBlockHeader {
+                header,
+                tx_count: VarInt(0),
+            });
+        }
+        headers
+    }
+
+    /// Serve the given regtest header chain to any number of SPV clients.
+    /// Returns the port it listens on.
+    fn mock_bitcoin_peer(headers: Vec<LoneBlockHeader>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for sock in listener.incoming() {
+                let sock = sock.unwrap();
+                let headers = headers.clone();
+                thread::spawn(move || mock_bitcoin_peer_session(sock, headers));
+            }
+        });
+        port
+    }
+
+    fn mock_bitcoin_peer_session(mut sock: TcpStream, headers: Vec<LoneBlockHeader>) {
+        let magic = network_id_to_bytes(BitcoinNetworkType::Regtest);
+        loop {
+            let msg: btc_message::RawNetworkMessage =
+                match ConsensusDecodable::consensus_decode(&mut RawDecoder::new(&mut sock)) {
+                    Ok(msg) => msg,
+                    Err(_) => return,
+                };
+            let replies = match msg.payload {
+                btc_message::NetworkMessage::Version(mut version) => {
+                    version.start_height = (headers.len() - 1) as i32;
+                    vec![
+                        btc_message::NetworkMessage::Version(version),
+                        btc_message::NetworkMessage::Verack,
+                    ]
+                }
+                btc_message::NetworkMessage::GetHeaders(getheaders) => {
+                    let start = headers
+                        .iter()
+                        .position(|h| h.header.bitcoin_hash() == getheaders.locator_hashes[0])
+                        .unwrap()
+                        + 1;
+                    let end = cmp::min(headers.len(), start + MAX_HEADERS_PER_REPLY);
+                    vec![btc_message::NetworkMessage::Headers(
+                        headers[start..end].to_vec(),
+                    )]
+                }
+                _ => vec![],
+            };
+            for payload in replies.into_iter() {
+                let reply = btc_message::RawNetworkMessage { magic, payload };
+                if reply
+                    .consensus_encode(&mut RawEncoder::new(&mut sock))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_spv_checkpoint_parse() {
+        let checkpoints = SpvCheckpoint::parse_list(
+            " 33333:000000002dd5588a74784eaa7ab0507a18ad16a236e7b1ce69f00d7ddfb5d0a6, \
+             11111:0000000069e244f73d78e8fd29ba2fd2ed618bd6fa2ee92559f542fdb26e7c1d",
+        )
+        .unwrap();
+        assert_eq!(
+            checkpoints,
+            SpvCheckpoint::defaults(BitcoinNetworkType::Mainnet)[0..2]
+        );
+        assert_eq!(
+            SpvCheckpoint::parse_list(&SpvCheckpoint::list_to_string(&checkpoints)).unwrap(),
+            checkpoints
+        );
+
+        assert_eq!(SpvCheckpoint::parse_list("").unwrap(), vec![]);
+        assert!(SpvCheckpoint::defaults(BitcoinNetworkType::Regtest).is_empty());
+
+        for bad in ["11111", "abc:00", "11111:xyz", ":00"].iter() {
+            match SpvCheckpoint::parse_list(bad) {
+                Err(btc_error::ConfigError(_)) => {}
+                x => panic!("parsed '{}' as {:?}", bad, x),
+            }
+        }
+    }
+
+    #[test]
+    fn test_spv_checkpoint_mismatch() {
+        let path = "/tmp/test-spv-checkpoint-mismatch.dat";
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+        let headers = make_regtest_headers(10);
+        let mut spv_client =
+            SpvClient::new(path, 0, None, BitcoinNetworkType::Regtest, true, false).unwrap();
+        spv_client.set_checkpoints(vec![SpvCheckpoint {
+            height: 5,
+            hash: headers[4].header.bitcoin_hash(),
+        }]);
+
+        match spv_client.insert_block_headers_after(0, headers[1..].to_vec()) {
+            Err(btc_error::CheckpointMismatch(5)) => {}
+            x => panic!("inserted headers against a checkpoint: {:?}", x),
+        }
+        assert_eq!(spv_client.get_highest_header_height().unwrap(), 0);
+
+        // headers that stop short of the checkpoint are fine
+        spv_client
+            .insert_block_headers_after(0, headers[1..5].to_vec())
+            .unwrap();
+        spv_client.set_checkpoints(vec![SpvCheckpoint {
+            height: 5,
+            hash: headers[5].header.bitcoin_hash(),
+        }]);
+        spv_client
+            .insert_block_headers_after(4, headers[5..].to_vec())
+            .unwrap();
+        assert_eq!(spv_client.read_block_headers(0, 10).unwrap(), headers);
+    }
+
+    #[test]
+    fn test_spv_download_checkpointed_headers() {
+        let headers = make_regtest_headers(5000);
+        let port = mock_bitcoin_peer(headers.clone());
+
+        let checkpoints: Vec<_> = [1500, 2100, 2500, 4000]
+            .iter()
+            .map(|height| SpvCheckpoint {
+                height: *height,
+                hash: headers[*height as usize].header.bitcoin_hash(),
+            })
+            .collect();
+
+        let path = "/tmp/test-spv-download-checkpointed-headers.dat";
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+        let mut indexer_conf = BitcoinIndexerConfig::default_regtest(path.to_string());
+        indexer_conf.peer_port = port;
+        let mut indexer = BitcoinIndexer::new(
+            indexer_conf,
+            BitcoinIndexerRuntime::new(BitcoinNetworkType::Regtest),
+        );
+
+        let mut spv_client =
+            SpvClient::new(path, 0, None, BitcoinNetworkType::Regtest, true, false).unwrap();
+        spv_client.set_checkpoints(checkpoints.clone());
+
+        // only download up to the last checkpoint below the end block
+        let height = spv_client
+            .download_checkpointed_headers(&indexer, Some(3000), 2)
+            .unwrap();
+        assert_eq!(height, 2500);
+        assert_eq!(
+            spv_client.read_block_headers(0, 5000).unwrap(),
+            headers[0..2501].to_vec()
+        );
+
+        let height = spv_client
+            .download_checkpointed_headers(&indexer, None, 3)
+            .unwrap();
+        assert_eq!(height, 4000);
+        assert_eq!(
+            spv_client.read_block_headers(0, 5000).unwrap(),
+            headers[0..4001].to_vec()
+        );
+
+        // the rest get fetched in order
+        spv_client.run(&mut indexer).unwrap();
+        assert_eq!(spv_client.read_block_headers(0, 5000).unwrap(), headers);
+
+        // a range that doesn't end on its checkpoint is rejected
+        fs::remove_file(path).unwrap();
+        let mut spv_client =
+            SpvClient::new(path, 0, None, BitcoinNetworkType::Regtest, true, false).unwrap();
+        let mut bad_checkpoints = checkpoints.clone();
+        bad_checkpoints[1].hash = headers[2099].header.bitcoin_hash();
+        spv_client.set_checkpoints(bad_checkpoints);
+        match spv_client.download_checkpointed_headers(&indexer, None, 2) {
+            Err(btc_error::CheckpointMismatch(2100)) => {}
+            x => panic!("downloaded headers against a checkpoint: {:?}", x),
+        }
+        assert_eq!(spv_client.get_highest_header_height().unwrap(), 1500);
+    }
 }
@@ -44,6 +44,7 @@ use chainstate::burn::{
     BlockHeaderHash, BlockSnapshot, ConsensusHash, OpsHash, SortitionHash, VRFSeed,
 };
 
+use chainstate::coordinator::fork_choice::is_preferred_stacks_tip;
 use chainstate::coordinator::{Error as CoordinatorError, PoxAnchorBlockStatus, RewardCycleInfo};
 use core::CHAINSTATE_VERSION;
 
@@ -86,7 +87,10 @@ use net::neighbors::MAX_NEIGHBOR_BLOCK_DELAY;
 
 use std::collections::HashMap;
 
-use core::{FIRST_BURNCHAIN_BLOCK_HASH, FIRST_STACKS_BLOCK_HASH, INITIAL_MINING_BONUS_WINDOW};
+use core::{
+    BURNCHAIN_MEDIAN_TIME_PAST_WINDOW, FIRST_BURNCHAIN_BLOCK_HASH, FIRST_STACKS_BLOCK_HASH,
+    INITIAL_MINING_BONUS_WINDOW,
+};
 
 use vm::representations::{ClarityName, ContractName};
 use vm::types::Value;
@@ -386,6 +390,33 @@ struct AcceptedStacksBlockHeader {
     pub height: u64,                       // stacks block height
 }
 
+/// How a snapshot's memoized canonical Stacks chain tip differs from the one recomputed from the
+/// accepted Stacks blocks in its burnchain fork.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CanonicalStacksTipDivergenceKind {
+    /// The memoized tip is in this fork, but a higher (or earlier-arriving) block was accepted
+    /// after the snapshot stopped being the burnchain tip.  Only a problem on the canonical tip.
+    Stale,
+    /// The memoized tip is not an accepted block in this fork, or is higher than any block the
+    /// fork-choice rule would select.
+    Conflict,
+}
+
+/// A snapshot whose memoized canonical Stacks chain tip does not match the recomputed one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanonicalStacksTipDivergence {
+    pub kind: CanonicalStacksTipDivergenceKind,
+    pub burn_block_height: u64,
+    pub sortition_id: SortitionId,
+    pub is_burnchain_tip: bool,
+    pub stored_consensus_hash: ConsensusHash,
+    pub stored_block_hash: BlockHeaderHash,
+    pub stored_height: u64,
+    pub computed_consensus_hash: ConsensusHash,
+    pub computed_block_hash: BlockHeaderHash,
+    pub computed_height: u64,
+}
+
 pub struct InitialMiningBonus {
     pub total_reward: u128,
     pub per_block: u128,
@@ -1476,6 +1507,26 @@ impl<'a> SortitionHandleConn<'a> {
         SortitionDB::get_ancestor_snapshot(self, block_height, &self.context.chain_tip)
     }
 
+    /// Get the median-time-past of the burnchain block at the given height in this fork.  This is
+    /// the median of the timestamps of that block and up to BURNCHAIN_MEDIAN_TIME_PAST_WINDOW - 1
+    /// of its ancestors (fewer if the window extends below the first burnchain block).
+    /// Returns None if there is no burnchain block at this height in this fork.
+    pub fn get_burn_median_time_past(&self, block_height: u64) -> Result<Option<u64>, db_error> {
+        let mut timestamps = vec![];
+        let lowest_height = block_height.saturating_sub(BURNCHAIN_MEDIAN_TIME_PAST_WINDOW - 1);
+        for height in (lowest_height..=block_height).rev() {
+            match self.get_block_snapshot_by_height(height)? {
+                Some(sn) => timestamps.push(sn.burn_header_timestamp),
+                None => break,
+            }
+        }
+        if timestamps.is_empty() {
+            return Ok(None);
+        }
+        timestamps.sort_unstable();
+        Ok(Some(timestamps[timestamps.len() / 2]))
+    }
+
     /// Get all user burns that burned for the winning block in the chain_tip sortition
     /// Returns list of user burns in order by vtxindex.
     pub fn get_winning_user_burns_by_block(&self) -> Result<Vec<UserBurnSupportOp>, db_error> {
@@ -2645,6 +2696,19 @@ impl SortitionDB {
         )
     }
 
+    /// Get the heights of the burnchain blocks, in any burnchain fork, that contain an operation
+    /// with the given txid.
+    pub fn get_burnchain_op_heights(conn: &Connection, txid: &Txid) -> Result<Vec<u64>, db_error> {
+        let qry = "SELECT block_height FROM leader_keys WHERE txid = ?1
+                   UNION SELECT block_height FROM block_commits WHERE txid = ?1
+                   UNION SELECT block_height FROM user_burn_support WHERE txid = ?1
+                   UNION SELECT block_height FROM stack_stx WHERE txid = ?1
+                   UNION SELECT block_height FROM transfer_stx WHERE txid = ?1
+                   ORDER BY block_height ASC";
+        let args: &[&dyn ToSql] = &[txid];
+        query_rows(conn, qry, args)
+    }
+
     pub fn index_handle_at_tip<'a>(&'a self) -> SortitionHandleConn<'a> {
         let sortition_id = SortitionDB::get_canonical_sortition_tip(self.conn()).unwrap();
         self.index_handle(&sortition_id)
@@ -2668,6 +2732,111 @@ impl SortitionDB {
         Ok((consensus_hash, stacks_block_hash))
     }
 
+    /// Recompute the canonical Stacks chain tip for each snapshot on the canonical burnchain fork
+    /// with a block height in [start_height, end_height], and compare it to the memoized one.
+    /// The fork-choice rule is the same one applied when blocks are accepted: the highest accepted
+    /// Stacks block in the burnchain fork wins, and ties go to the block that arrived first.
+    /// Returns the list of snapshots whose memoized tip diverges from the recomputed tip.
+    pub fn audit_canonical_stacks_tips(
+        &self,
+        start_height: u64,
+        end_height: u64,
+    ) -> Result<Vec<CanonicalStacksTipDivergence>, db_error> {
+        let burn_tip = SortitionDB::get_canonical_burn_chain_tip(self.conn())?;
+        let end_height = cmp::min(end_height, burn_tip.block_height);
+        let first_snapshot = SortitionDB::get_first_block_snapshot(self.conn())?;
+        if start_height > end_height {
+            return Ok(vec![]);
+        }
+
+        let ic = self.index_handle(&burn_tip.sortition_id);
+
+        // (consensus hash, block hash) --> (stacks height, arrival index) of each accepted block
+        // seen so far in this fork
+        let mut accepted = HashMap::new();
+        accepted.insert(
+            (
+                first_snapshot.canonical_stacks_tip_consensus_hash,
+                first_snapshot.canonical_stacks_tip_hash,
+            ),
+            (first_snapshot.canonical_stacks_tip_height, 0),
+        );
+
+        let mut best = (
+            first_snapshot.canonical_stacks_tip_consensus_hash,
+            first_snapshot.canonical_stacks_tip_hash,
+            first_snapshot.canonical_stacks_tip_height,
+            0,
+        );
+
+        let mut divergences = vec![];
+        for height in first_snapshot.block_height..(end_height + 1) {
+            let sn = match ic.get_block_snapshot_by_height(height)? {
+                Some(sn) => sn,
+                None => {
+                    break;
+                }
+            };
+
+            if sn.stacks_block_accepted {
+                accepted.insert(
+                    (sn.consensus_hash, sn.winning_stacks_block_hash),
+                    (sn.stacks_block_height, sn.arrival_index),
+                );
+
+                if is_preferred_stacks_tip(sn.stacks_block_height, sn.arrival_index, best.2, best.3)
+                {
+                    best = (
+                        sn.consensus_hash,
+                        sn.winning_stacks_block_hash,
+                        sn.stacks_block_height,
+                        sn.arrival_index,
+                    );
+                }
+            }
+
+            if height < start_height {
+                continue;
+            }
+
+            if sn.canonical_stacks_tip_consensus_hash == best.0
+                && sn.canonical_stacks_tip_hash == best.1
+            {
+                continue;
+            }
+
+            let stored_in_fork = accepted.get(&(
+                sn.canonical_stacks_tip_consensus_hash,
+                sn.canonical_stacks_tip_hash,
+            ));
+
+            let kind = match stored_in_fork {
+                Some((stored_height, _))
+                    if *stored_height == sn.canonical_stacks_tip_height
+                        && sn.canonical_stacks_tip_height <= best.2 =>
+                {
+                    CanonicalStacksTipDivergenceKind::Stale
+                }
+                _ => CanonicalStacksTipDivergenceKind::Conflict,
+            };
+
+            divergences.push(CanonicalStacksTipDivergence {
+                kind,
+                burn_block_height: sn.block_height,
+                sortition_id: sn.sortition_id,
+                is_burnchain_tip: sn.sortition_id == burn_tip.sortition_id,
+                stored_consensus_hash: sn.canonical_stacks_tip_consensus_hash,
+                stored_block_hash: sn.canonical_stacks_tip_hash,
+                stored_height: sn.canonical_stacks_tip_height,
+                computed_consensus_hash: best.0,
+                computed_block_hash: best.1,
+                computed_height: best.2,
+            });
+        }
+
+        Ok(divergences)
+    }
+
     /// Get an accepted stacks block header in a fork whose chain tip has not yet committed
     /// to it.
     fn get_accepted_stacks_block_pointer(
@@ -5983,6 +6152,140 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_get_burn_median_time_past() {
+        let first_burn_hash = BurnchainHeaderHash::from_hex(
+            "10000000000000000000000000000000000000000000000000000000000000ff",
+        )
+        .unwrap();
+        let mut db = SortitionDB::connect_test(0, &first_burn_hash).unwrap();
+
+        let last_snapshot = SortitionDB::get_first_block_snapshot(db.conn()).unwrap();
+        make_fork_run(&mut db, &last_snapshot, 15, 0);
+
+        // give each burnchain block a timestamp that does not increase monotonically
+        let timestamps: Vec<u64> = vec![
+            1000, 1600, 1300, 1900, 1200, 2500, 2200, 2800, 2100, 3400, 3100, 3700, 3000, 4300,
+            4000, 4600,
+        ];
+        for (height, timestamp) in timestamps.iter().enumerate() {
+            db.conn()
+                .execute(
+                    "UPDATE snapshots SET burn_header_timestamp = ?1 WHERE block_height = ?2",
+                    &[
+                        &u64_to_sql(*timestamp).unwrap() as &dyn ToSql,
+                        &u64_to_sql(height as u64).unwrap() as &dyn ToSql,
+                    ],
+                )
+                .unwrap();
+        }
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(db.conn()).unwrap();
+        assert_eq!(tip.block_height, 15);
+
+        let ic = db.index_handle(&tip.sortition_id);
+        for height in 0..=tip.block_height {
+            let lowest_height =
+                height.saturating_sub(BURNCHAIN_MEDIAN_TIME_PAST_WINDOW - 1) as usize;
+            let mut window = timestamps[lowest_height..=(height as usize)].to_vec();
+            window.sort();
+            assert_eq!(
+                ic.get_burn_median_time_past(height).unwrap(),
+                Some(window[window.len() / 2])
+            );
+        }
+
+        // full window at the tip: median of 3100, 3700, 3000, 4300, 4000, 4600, 2500, 2200,
+        // 2800, 2100, 3400
+        assert_eq!(ic.get_burn_median_time_past(15).unwrap(), Some(3100));
+
+        // no such block
+        assert_eq!(ic.get_burn_median_time_past(16).unwrap(), None);
+    }
+
+    #[test]
+    fn test_audit_canonical_stacks_tips() {
+        let first_burn_hash = BurnchainHeaderHash::from_hex(
+            "10000000000000000000000000000000000000000000000000000000000000ff",
+        )
+        .unwrap();
+        let mut db = SortitionDB::connect_test(0, &first_burn_hash).unwrap();
+
+        let last_snapshot = SortitionDB::get_first_block_snapshot(db.conn()).unwrap();
+        make_fork_run(&mut db, &last_snapshot, 5, 0);
+
+        // nothing accepted yet, so nothing can diverge
+        assert_eq!(db.audit_canonical_stacks_tips(0, 5).unwrap(), vec![]);
+
+        // accept all blocks while the last snapshot is the burnchain tip, so only it gets the
+        // new canonical stacks tip memoized.
+        for i in 0..5 {
+            let consensus_hash = ConsensusHash([(i + 1) as u8; 20]);
+            let parent_stacks_block_hash = if i == 0 {
+                FIRST_STACKS_BLOCK_HASH.clone()
+            } else {
+                BlockHeaderHash([(i - 1) as u8; 32])
+            };
+
+            let stacks_block_hash = BlockHeaderHash([i as u8; 32]);
+
+            let mut tx = db.tx_begin_at_tip();
+            tx.set_stacks_block_accepted(
+                &consensus_hash,
+                &parent_stacks_block_hash,
+                &stacks_block_hash,
+                i as u64,
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+
+        // the earlier snapshots are stale, but the burnchain tip is not
+        let divergences = db.audit_canonical_stacks_tips(0, 5).unwrap();
+        assert_eq!(divergences.len(), 3);
+        for (i, divergence) in divergences.iter().enumerate() {
+            assert_eq!(divergence.kind, CanonicalStacksTipDivergenceKind::Stale);
+            assert_eq!(divergence.burn_block_height, (i + 2) as u64);
+            assert_eq!(divergence.computed_height, (i + 1) as u64);
+            assert_eq!(divergence.stored_height, 0);
+            assert!(!divergence.is_burnchain_tip);
+        }
+
+        assert_eq!(db.audit_canonical_stacks_tips(5, 5).unwrap(), vec![]);
+
+        // corrupt the memoized tip on the burnchain tip
+        let tip = SortitionDB::get_canonical_burn_chain_tip(db.conn()).unwrap();
+        db.conn()
+            .execute(
+                "UPDATE snapshots SET canonical_stacks_tip_hash = ?1 WHERE sortition_id = ?2",
+                &[
+                    &BlockHeaderHash([0x11; 32]) as &dyn ToSql,
+                    &tip.sortition_id as &dyn ToSql,
+                ],
+            )
+            .unwrap();
+
+        let divergences = db.audit_canonical_stacks_tips(5, 10).unwrap();
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(
+            divergences[0].kind,
+            CanonicalStacksTipDivergenceKind::Conflict
+        );
+        assert!(divergences[0].is_burnchain_tip);
+        assert_eq!(
+            divergences[0].stored_block_hash,
+            BlockHeaderHash([0x11; 32])
+        );
+        assert_eq!(
+            divergences[0].computed_block_hash,
+            BlockHeaderHash([0x04; 32])
+        );
+        assert_eq!(
+            divergences[0].computed_consensus_hash,
+            ConsensusHash([0x05; 20])
+        );
+    }
+
     #[test]
     fn test_set_stacks_block_accepted() {
         let first_burn_hash = BurnchainHeaderHash::from_hex(
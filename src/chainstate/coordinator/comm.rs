@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::{HashSet, VecDeque};
 use std::sync::{
     atomic::{AtomicU64, Ordering},
     Arc, Condvar, LockResult, Mutex, MutexGuard, RwLock, TryLockResult,
@@ -21,6 +22,14 @@ use std::sync::{
 use std::time::{Duration, Instant};
 use std::{process, thread};
 
+use monitoring::{
+    increment_coordinator_events_dropped_counter, update_coordinator_event_queue_depth_gauge,
+};
+use net::atlas::AttachmentInstance;
+
+/// Default number of attachment instance batches the event bus holds for the p2p thread
+pub const DEFAULT_ATTACHMENTS_QUEUE_CAPACITY: usize = 1024;
+
 /// Trait for use by the ChainsCoordinator
 ///
 pub trait CoordinatorNotices {
@@ -51,11 +60,8 @@ impl CoordinatorNotices for ArcCounterCoordinatorNotices {
 ///   ChainsCoordinator
 #[derive(Clone)]
 pub struct CoordinatorChannels {
-    /// Mutex guarded signaling struct for communicating
-    ///  to the coordinator.
-    signal_bools: Arc<Mutex<SignalBools>>,
-    /// Condvar for notifying on updates to signal_bools
-    signal_wakeup: Arc<Condvar>,
+    /// The event bus shared with the coordinator
+    event_bus: CoordinatorEventBus,
     /// how many stacks blocks have been processed by this Coordinator thread since startup?
     stacks_blocks_processed: Arc<AtomicU64>,
     /// how many sortitions have been processed by this Coordinator thread since startup?
@@ -65,25 +71,11 @@ pub struct CoordinatorChannels {
     kludgy_temporary_clarity_db_lock: Arc<Mutex<()>>,
 }
 
-/// Notification struct for communicating to
-///  the coordinator. Each bool indicates a notice
-///  that there are new events of a type to check
-struct SignalBools {
-    new_stacks_block: bool,
-    new_burn_block: bool,
-    stop: bool,
-}
-
 /// Structure used by the Coordinator's run-loop
 ///   to receive signals
 pub struct CoordinatorReceivers {
-    /// Mutex guarded signaling struct for communicating
-    ///  to the coordinator.
-    signal_bools: Arc<Mutex<SignalBools>>,
-    /// Condvar for notifying on updates to signal_bools.
-    ///   the Condvar should only be used with the Mutex guarding
-    ///   signal_bools
-    signal_wakeup: Arc<Condvar>,
+    /// The event bus shared with the coordinator's clients
+    event_bus: CoordinatorEventBus,
     pub stacks_blocks_processed: Arc<AtomicU64>,
     pub sortitions_processed: Arc<AtomicU64>,
 
@@ -104,32 +96,237 @@ pub enum CoordinatorEvents {
     TIMEOUT,
 }
 
-impl SignalBools {
+/// The kinds of events on the coordinator's event bus, in decreasing order of priority.  The
+/// coordinator handles a stop before a new burn block, and a new burn block before a new Stacks
+/// block.  Attachment instances flow the other way, from the coordinator to the p2p thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CoordinatorEventKind {
+    Stop,
+    NewBurnBlock,
+    NewStacksBlock,
+    NewAttachments,
+}
+
+impl CoordinatorEventKind {
+    pub const ALL: [CoordinatorEventKind; 4] = [
+        CoordinatorEventKind::Stop,
+        CoordinatorEventKind::NewBurnBlock,
+        CoordinatorEventKind::NewStacksBlock,
+        CoordinatorEventKind::NewAttachments,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CoordinatorEventKind::Stop => "stop",
+            CoordinatorEventKind::NewBurnBlock => "new_burn_block",
+            CoordinatorEventKind::NewStacksBlock => "new_stacks_block",
+            CoordinatorEventKind::NewAttachments => "new_attachments",
+        }
+    }
+}
+
+/// What a bounded event queue does with an event posted while it is full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropPolicy {
+    /// discard the event being posted
+    DropNewest,
+    /// discard the oldest queued event to make room for the one being posted
+    DropOldest,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordinatorEventBusConfig {
+    /// most batches of attachment instances queued for the p2p thread at once
+    pub attachments_capacity: usize,
+    pub attachments_drop_policy: DropPolicy,
+}
+
+impl std::default::Default for CoordinatorEventBusConfig {
+    fn default() -> CoordinatorEventBusConfig {
+        CoordinatorEventBusConfig {
+            attachments_capacity: DEFAULT_ATTACHMENTS_QUEUE_CAPACITY,
+            attachments_drop_policy: DropPolicy::DropOldest,
+        }
+    }
+}
+
+/// Pending events.  New burn and Stacks block events only tell the coordinator that there is
+/// something to check, so each is a queue of at most one event into which repeated postings
+/// coalesce.  Attachment instances carry data, so they queue up to the configured capacity.
+struct EventQueues {
+    stop: bool,
+    new_burn_block: bool,
+    new_stacks_block: bool,
+    attachments: VecDeque<HashSet<AttachmentInstance>>,
+    config: CoordinatorEventBusConfig,
+    /// events dropped or coalesced, by kind
+    dropped: [u64; 4],
+}
+
+/// A bounded, prioritized event bus between the chains coordinator and the threads that feed
+/// it blocks and consume its attachment instances.  Posting to the bus never blocks, so a
+/// backlog of one kind of event can't hold up the handling of another.
+#[derive(Clone)]
+pub struct CoordinatorEventBus {
+    /// Mutex guarded pending events
+    queues: Arc<Mutex<EventQueues>>,
+    /// Condvar for notifying the coordinator of updates to queues.
+    ///   the Condvar should only be used with the Mutex guarding
+    ///   queues
+    wakeup: Arc<Condvar>,
+}
+
+impl EventQueues {
+    fn depth(&self, kind: CoordinatorEventKind) -> usize {
+        match kind {
+            CoordinatorEventKind::Stop => self.stop as usize,
+            CoordinatorEventKind::NewBurnBlock => self.new_burn_block as usize,
+            CoordinatorEventKind::NewStacksBlock => self.new_stacks_block as usize,
+            CoordinatorEventKind::NewAttachments => self.attachments.len(),
+        }
+    }
+
+    fn record_drop(&mut self, kind: CoordinatorEventKind) {
+        self.dropped[kind as usize] += 1;
+        increment_coordinator_events_dropped_counter(kind.name());
+    }
+
+    fn update_depth_gauge(&self, kind: CoordinatorEventKind) {
+        update_coordinator_event_queue_depth_gauge(kind.name(), self.depth(kind) as u64);
+    }
+
     fn activated_signal(&self) -> bool {
         self.stop || self.new_stacks_block || self.new_burn_block
     }
+
     fn receive_signal(&mut self) -> CoordinatorEvents {
         if self.stop {
             return CoordinatorEvents::STOP;
         } else if self.new_burn_block {
             self.new_burn_block = false;
+            self.update_depth_gauge(CoordinatorEventKind::NewBurnBlock);
             return CoordinatorEvents::NEW_BURN_BLOCK;
         } else if self.new_stacks_block {
             self.new_stacks_block = false;
+            self.update_depth_gauge(CoordinatorEventKind::NewStacksBlock);
             return CoordinatorEvents::NEW_STACKS_BLOCK;
         } else {
             return CoordinatorEvents::TIMEOUT;
         }
     }
+
+    /// Set a coalescing notice, returning whether or not the coordinator is still running
+    fn post_notice(&mut self, kind: CoordinatorEventKind) -> bool {
+        if self.depth(kind) > 0 {
+            self.record_drop(kind);
+        }
+        match kind {
+            CoordinatorEventKind::Stop => self.stop = true,
+            CoordinatorEventKind::NewBurnBlock => self.new_burn_block = true,
+            CoordinatorEventKind::NewStacksBlock => self.new_stacks_block = true,
+            CoordinatorEventKind::NewAttachments => {
+                panic!("BUG: attachment instances are not a notice")
+            }
+        }
+        self.update_depth_gauge(kind);
+        !self.stop
+    }
 }
 
-impl CoordinatorReceivers {
+impl CoordinatorEventBus {
+    pub fn new(config: CoordinatorEventBusConfig) -> CoordinatorEventBus {
+        CoordinatorEventBus {
+            queues: Arc::new(Mutex::new(EventQueues {
+                stop: false,
+                new_burn_block: false,
+                new_stacks_block: false,
+                attachments: VecDeque::new(),
+                config,
+                dropped: [0; 4],
+            })),
+            wakeup: Arc::new(Condvar::new()),
+        }
+    }
+
+    fn post_notice(&self, kind: CoordinatorEventKind) -> bool {
+        let mut queues = self.queues.lock().unwrap();
+        let running = queues.post_notice(kind);
+        self.wakeup.notify_all();
+        running
+    }
+
+    /// Block until the coordinator has an event to handle, and take the most important one
     pub fn wait_on(&self) -> CoordinatorEvents {
-        let mut signal_bools = self.signal_bools.lock().unwrap();
-        if !signal_bools.activated_signal() {
-            signal_bools = self.signal_wakeup.wait(signal_bools).unwrap();
+        let mut queues = self.queues.lock().unwrap();
+        if !queues.activated_signal() {
+            queues = self.wakeup.wait(queues).unwrap();
+        }
+        queues.receive_signal()
+    }
+
+    /// Queue a batch of attachment instances for the p2p thread, applying the drop policy if the
+    /// queue is full.  Returns false if the batch, or an older one, was dropped.
+    pub fn post_new_attachments(&self, attachments: HashSet<AttachmentInstance>) -> bool {
+        let mut queues = self.queues.lock().unwrap();
+        let mut dropped_none = true;
+        if queues.attachments.len() >= queues.config.attachments_capacity {
+            dropped_none = false;
+            queues.record_drop(CoordinatorEventKind::NewAttachments);
+            match queues.config.attachments_drop_policy {
+                DropPolicy::DropNewest => {
+                    warn!(
+                        "Atlas: attachments queue is full; dropping {} new attachment instances",
+                        attachments.len()
+                    );
+                    return false;
+                }
+                DropPolicy::DropOldest => {
+                    let oldest = queues.attachments.pop_front().unwrap_or_default();
+                    warn!(
+                        "Atlas: attachments queue is full; dropping {} old attachment instances",
+                        oldest.len()
+                    );
+                }
+            }
+        }
+        if queues.config.attachments_capacity > 0 {
+            queues.attachments.push_back(attachments);
         }
-        signal_bools.receive_signal()
+        queues.update_depth_gauge(CoordinatorEventKind::NewAttachments);
+        dropped_none
+    }
+
+    /// Take all the attachment instances queued so far, without blocking
+    pub fn take_new_attachments(&self) -> HashSet<AttachmentInstance> {
+        let mut queues = self.queues.lock().unwrap();
+        let mut attachments = HashSet::new();
+        for batch in queues.attachments.drain(..) {
+            attachments.extend(batch);
+        }
+        queues.update_depth_gauge(CoordinatorEventKind::NewAttachments);
+        attachments
+    }
+
+    /// Number of events of the given kind waiting on the bus
+    pub fn get_queue_depth(&self, kind: CoordinatorEventKind) -> usize {
+        self.queues.lock().unwrap().depth(kind)
+    }
+
+    /// Number of events of the given kind the bus dropped, or coalesced into a pending event,
+    /// since startup
+    pub fn get_events_dropped(&self, kind: CoordinatorEventKind) -> u64 {
+        self.queues.lock().unwrap().dropped[kind as usize]
+    }
+}
+
+impl CoordinatorReceivers {
+    pub fn wait_on(&self) -> CoordinatorEvents {
+        self.event_bus.wait_on()
+    }
+
+    /// The event bus, for the coordinator to post attachment instances to
+    pub fn event_bus(&self) -> CoordinatorEventBus {
+        self.event_bus.clone()
     }
 
     /// TODO: remove before mainnet
@@ -145,26 +342,33 @@ impl CoordinatorReceivers {
 
 impl CoordinatorChannels {
     pub fn announce_new_stacks_block(&self) -> bool {
-        let mut bools = self.signal_bools.lock().unwrap();
-        bools.new_stacks_block = true;
-        self.signal_wakeup.notify_all();
-        !bools.stop
+        self.event_bus
+            .post_notice(CoordinatorEventKind::NewStacksBlock)
     }
 
     pub fn announce_new_burn_block(&self) -> bool {
-        let mut bools = self.signal_bools.lock().unwrap();
-        bools.new_burn_block = true;
-        self.signal_wakeup.notify_all();
-        !bools.stop
+        self.event_bus
+            .post_notice(CoordinatorEventKind::NewBurnBlock)
     }
 
     pub fn stop_chains_coordinator(&self) -> bool {
-        let mut bools = self.signal_bools.lock().unwrap();
-        bools.stop = true;
-        self.signal_wakeup.notify_all();
+        self.event_bus.post_notice(CoordinatorEventKind::Stop);
         false
     }
 
+    /// Take all the attachment instances the coordinator has found since the last call
+    pub fn take_new_attachments(&self) -> HashSet<AttachmentInstance> {
+        self.event_bus.take_new_attachments()
+    }
+
+    pub fn get_event_queue_depth(&self, kind: CoordinatorEventKind) -> usize {
+        self.event_bus.get_queue_depth(kind)
+    }
+
+    pub fn get_events_dropped(&self, kind: CoordinatorEventKind) -> u64 {
+        self.event_bus.get_events_dropped(kind)
+    }
+
     pub fn get_stacks_blocks_processed(&self) -> u64 {
         self.stacks_blocks_processed.load(Ordering::SeqCst)
     }
@@ -210,21 +414,20 @@ impl CoordinatorChannels {
 
 impl CoordinatorCommunication {
     pub fn instantiate() -> (CoordinatorReceivers, CoordinatorChannels) {
-        let signal_bools = Arc::new(Mutex::new(SignalBools {
-            new_stacks_block: false,
-            new_burn_block: false,
-            stop: false,
-        }));
+        CoordinatorCommunication::instantiate_with_config(CoordinatorEventBusConfig::default())
+    }
 
-        let signal_wakeup = Arc::new(Condvar::new());
+    pub fn instantiate_with_config(
+        config: CoordinatorEventBusConfig,
+    ) -> (CoordinatorReceivers, CoordinatorChannels) {
+        let event_bus = CoordinatorEventBus::new(config);
 
         let stacks_blocks_processed = Arc::new(AtomicU64::new(0));
         let sortitions_processed = Arc::new(AtomicU64::new(0));
         let kludgy_clarity_db_lock = Arc::new(Mutex::new(()));
 
         let senders = CoordinatorChannels {
-            signal_bools: signal_bools.clone(),
-            signal_wakeup: signal_wakeup.clone(),
+            event_bus: event_bus.clone(),
             stacks_blocks_processed: stacks_blocks_processed.clone(),
 
             sortitions_processed: sortitions_processed.clone(),
@@ -232,8 +435,7 @@ impl CoordinatorCommunication {
         };
 
         let rcvrs = CoordinatorReceivers {
-            signal_bools: signal_bools,
-            signal_wakeup: signal_wakeup,
+            event_bus,
             stacks_blocks_processed,
             sortitions_processed,
             kludgy_temporary_clarity_db_lock: kludgy_clarity_db_lock.clone(),
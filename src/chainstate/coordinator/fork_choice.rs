@@ -0,0 +1,612 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simulation of the node's fork-choice rule over hypothetical sortitions and Stacks blocks.
+//!
+//! The node picks its canonical chain tip in two steps:
+//!   * the canonical burnchain fork is the PoX-valid sortition with the greatest burnchain block
+//!     height, with ties going to the lowest burnchain header hash;
+//!   * within that fork, the canonical Stacks tip is the highest Stacks block that was accepted
+//!     (i.e. was selected by a sortition in the fork and builds on a block accepted in the fork),
+//!     with ties going to the block that arrived first.
+//!
+//! `ForkChoiceSimulation` applies the same rule to a set of sortitions and blocks that only exist
+//! in memory, so the outcome of a reorg can be worked out without a sortition DB, a chainstate,
+//! or a network of nodes:
+//!
+//! ```ignore
+//! let mut sim = ForkChoiceSimulation::new(&first_burn_header_hash, first_block_height);
+//! sim.add_sortition(&burn_1, &first_burn_header_hash, &ch_1, true)?;
+//! sim.add_sortition(&burn_2, &first_burn_header_hash, &ch_2, true)?;
+//! sim.add_block(&ch_1, &block_1, &FIRST_BURNCHAIN_CONSENSUS_HASH, &FIRST_STACKS_BLOCK_HASH)?;
+//! let tip = sim.canonical_stacks_tip();
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+
+use burnchains::BurnchainHeaderHash;
+use chainstate::burn::{BlockHeaderHash, ConsensusHash};
+use chainstate::stacks::StacksBlockId;
+use core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// No sortition has this burnchain header hash
+    UnknownSortition(BurnchainHeaderHash),
+    /// No sortition has this consensus hash
+    UnknownConsensusHash(ConsensusHash),
+    /// A sortition with this burnchain header hash or consensus hash was already added
+    DuplicateSortition(BurnchainHeaderHash, ConsensusHash),
+    /// This sortition already selected a Stacks block
+    DuplicateBlock(ConsensusHash, BlockHeaderHash),
+    /// The parent of a Stacks block was never added
+    UnknownParentBlock(ConsensusHash, BlockHeaderHash),
+    /// A Stacks block's parent was selected by the same or a later sortition
+    ParentNotEarlier(ConsensusHash, BlockHeaderHash),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownSortition(ref bhh) => write!(f, "No such sortition: {}", bhh),
+            Error::UnknownConsensusHash(ref ch) => {
+                write!(f, "No sortition with consensus hash {}", ch)
+            }
+            Error::DuplicateSortition(ref bhh, ref ch) => {
+                write!(
+                    f,
+                    "Sortition {} (consensus hash {}) already exists",
+                    bhh, ch
+                )
+            }
+            Error::DuplicateBlock(ref ch, ref bhh) => write!(
+                f,
+                "Sortition {} already selected a block, so it cannot select {}",
+                ch, bhh
+            ),
+            Error::UnknownParentBlock(ref ch, ref bhh) => {
+                write!(f, "No such parent block: {}/{}", ch, bhh)
+            }
+            Error::ParentNotEarlier(ref ch, ref bhh) => write!(
+                f,
+                "Block {}/{} does not build on a block from an earlier sortition",
+                ch, bhh
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        None
+    }
+}
+
+/// Whether a Stacks block at `height` that arrived at `arrival_index` takes the place of the
+/// current best tip.  Higher blocks win; the first block to arrive at a given height keeps it.
+pub fn is_preferred_stacks_tip(
+    height: u64,
+    arrival_index: u64,
+    best_height: u64,
+    best_arrival_index: u64,
+) -> bool {
+    height > best_height || (height == best_height && arrival_index < best_arrival_index)
+}
+
+/// A hypothetical burnchain block and its sortition
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedSortition {
+    pub burn_header_hash: BurnchainHeaderHash,
+    pub parent_burn_header_hash: BurnchainHeaderHash,
+    pub consensus_hash: ConsensusHash,
+    pub block_height: u64,
+    /// whether this sortition (and all of its ancestors) is on a valid PoX fork
+    pub pox_valid: bool,
+}
+
+/// A hypothetical Stacks block, in the order in which the node would process it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedStacksBlock {
+    pub consensus_hash: ConsensusHash,
+    pub block_hash: BlockHeaderHash,
+    pub parent_consensus_hash: ConsensusHash,
+    pub parent_block_hash: BlockHeaderHash,
+    pub stacks_block_height: u64,
+    pub arrival_index: u64,
+}
+
+/// The chain tip fork-choice selects
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForkChoiceTip {
+    /// the tip of the canonical burnchain fork
+    pub burn_header_hash: BurnchainHeaderHash,
+    pub burn_block_height: u64,
+    /// the canonical Stacks block in that fork
+    pub consensus_hash: ConsensusHash,
+    pub block_hash: BlockHeaderHash,
+    pub stacks_block_height: u64,
+}
+
+impl ForkChoiceTip {
+    pub fn index_block_hash(&self) -> StacksBlockId {
+        StacksBlockId::new(&self.consensus_hash, &self.block_hash)
+    }
+}
+
+/// An in-memory set of sortitions and Stacks blocks that the fork-choice rule can be evaluated
+/// against.  Sortitions and blocks can be added in any order that keeps parents ahead of their
+/// children; blocks are considered to arrive in the order in which they are added.
+#[derive(Debug, Clone)]
+pub struct ForkChoiceSimulation {
+    first_burn_header_hash: BurnchainHeaderHash,
+    sortitions: HashMap<BurnchainHeaderHash, SimulatedSortition>,
+    consensus_hashes: HashMap<ConsensusHash, BurnchainHeaderHash>,
+    blocks: Vec<SimulatedStacksBlock>,
+    /// index into `blocks` of the block each sortition selected
+    selected: HashMap<ConsensusHash, usize>,
+}
+
+impl ForkChoiceSimulation {
+    /// Start a simulation from the first burnchain block, whose sortition selects the boot block
+    pub fn new(
+        first_burn_header_hash: &BurnchainHeaderHash,
+        first_block_height: u64,
+    ) -> ForkChoiceSimulation {
+        let first_sortition = SimulatedSortition {
+            burn_header_hash: first_burn_header_hash.clone(),
+            parent_burn_header_hash: first_burn_header_hash.clone(),
+            consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            block_height: first_block_height,
+            pox_valid: true,
+        };
+        let boot_block = SimulatedStacksBlock {
+            consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            block_hash: FIRST_STACKS_BLOCK_HASH.clone(),
+            parent_consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            parent_block_hash: FIRST_STACKS_BLOCK_HASH.clone(),
+            stacks_block_height: 0,
+            arrival_index: 0,
+        };
+
+        let mut sortitions = HashMap::new();
+        sortitions.insert(first_burn_header_hash.clone(), first_sortition);
+        let mut consensus_hashes = HashMap::new();
+        consensus_hashes.insert(
+            FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            first_burn_header_hash.clone(),
+        );
+        let mut selected = HashMap::new();
+        selected.insert(FIRST_BURNCHAIN_CONSENSUS_HASH.clone(), 0);
+
+        ForkChoiceSimulation {
+            first_burn_header_hash: first_burn_header_hash.clone(),
+            sortitions,
+            consensus_hashes,
+            blocks: vec![boot_block],
+            selected,
+        }
+    }
+
+    /// Add a burnchain block on top of `parent_burn_header_hash`.  It is PoX-valid only if
+    /// `pox_valid` is set and its parent is PoX-valid.
+    pub fn add_sortition(
+        &mut self,
+        burn_header_hash: &BurnchainHeaderHash,
+        parent_burn_header_hash: &BurnchainHeaderHash,
+        consensus_hash: &ConsensusHash,
+        pox_valid: bool,
+    ) -> Result<&SimulatedSortition, Error> {
+        if self.sortitions.contains_key(burn_header_hash)
+            || self.consensus_hashes.contains_key(consensus_hash)
+        {
+            return Err(Error::DuplicateSortition(
+                burn_header_hash.clone(),
+                consensus_hash.clone(),
+            ));
+        }
+        let parent = self
+            .sortitions
+            .get(parent_burn_header_hash)
+            .ok_or_else(|| Error::UnknownSortition(parent_burn_header_hash.clone()))?;
+
+        let sortition = SimulatedSortition {
+            burn_header_hash: burn_header_hash.clone(),
+            parent_burn_header_hash: parent_burn_header_hash.clone(),
+            consensus_hash: consensus_hash.clone(),
+            block_height: parent.block_height + 1,
+            pox_valid: pox_valid && parent.pox_valid,
+        };
+        self.consensus_hashes
+            .insert(consensus_hash.clone(), burn_header_hash.clone());
+        Ok(self
+            .sortitions
+            .entry(burn_header_hash.clone())
+            .or_insert(sortition))
+    }
+
+    /// Have the sortition with `consensus_hash` select a Stacks block that builds on
+    /// `parent_consensus_hash`/`parent_block_hash`, and process it.
+    pub fn add_block(
+        &mut self,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        parent_consensus_hash: &ConsensusHash,
+        parent_block_hash: &BlockHeaderHash,
+    ) -> Result<&SimulatedStacksBlock, Error> {
+        let sortition = self.get_sortition_by_consensus_hash(consensus_hash)?;
+        if self.selected.contains_key(consensus_hash) {
+            return Err(Error::DuplicateBlock(
+                consensus_hash.clone(),
+                block_hash.clone(),
+            ));
+        }
+
+        let parent = match self.selected.get(parent_consensus_hash) {
+            Some(idx) if self.blocks[*idx].block_hash == *parent_block_hash => &self.blocks[*idx],
+            _ => {
+                return Err(Error::UnknownParentBlock(
+                    parent_consensus_hash.clone(),
+                    parent_block_hash.clone(),
+                ));
+            }
+        };
+        let parent_sortition = self.get_sortition_by_consensus_hash(parent_consensus_hash)?;
+        if parent_sortition.block_height >= sortition.block_height {
+            return Err(Error::ParentNotEarlier(
+                consensus_hash.clone(),
+                block_hash.clone(),
+            ));
+        }
+
+        let block = SimulatedStacksBlock {
+            consensus_hash: consensus_hash.clone(),
+            block_hash: block_hash.clone(),
+            parent_consensus_hash: parent_consensus_hash.clone(),
+            parent_block_hash: parent_block_hash.clone(),
+            stacks_block_height: parent.stacks_block_height + 1,
+            arrival_index: self.blocks.len() as u64,
+        };
+        self.selected
+            .insert(consensus_hash.clone(), self.blocks.len());
+        self.blocks.push(block);
+        Ok(self.blocks.last().expect("BUG: no blocks"))
+    }
+
+    pub fn get_sortition(
+        &self,
+        burn_header_hash: &BurnchainHeaderHash,
+    ) -> Option<&SimulatedSortition> {
+        self.sortitions.get(burn_header_hash)
+    }
+
+    fn get_sortition_by_consensus_hash(
+        &self,
+        consensus_hash: &ConsensusHash,
+    ) -> Result<&SimulatedSortition, Error> {
+        self.consensus_hashes
+            .get(consensus_hash)
+            .and_then(|bhh| self.sortitions.get(bhh))
+            .ok_or_else(|| Error::UnknownConsensusHash(consensus_hash.clone()))
+    }
+
+    /// The tip of the canonical burnchain fork: the highest PoX-valid sortition, with ties going
+    /// to the lowest burnchain header hash.
+    pub fn canonical_burn_tip(&self) -> &SimulatedSortition {
+        self.sortitions
+            .values()
+            .filter(|sn| sn.pox_valid)
+            .min_by(|a, b| {
+                b.block_height
+                    .cmp(&a.block_height)
+                    .then_with(|| a.burn_header_hash.0.cmp(&b.burn_header_hash.0))
+            })
+            .expect("BUG: the first sortition is always PoX-valid")
+    }
+
+    /// The canonical chain tip fork-choice selects over everything added so far
+    pub fn canonical_stacks_tip(&self) -> ForkChoiceTip {
+        let burn_tip = self.canonical_burn_tip().burn_header_hash.clone();
+        self.canonical_stacks_tip_at(&burn_tip)
+            .expect("BUG: the canonical burnchain tip is a known sortition")
+    }
+
+    /// The canonical Stacks tip in the burnchain fork ending at `burn_header_hash`, whether or
+    /// not that fork is the canonical one.
+    pub fn canonical_stacks_tip_at(
+        &self,
+        burn_header_hash: &BurnchainHeaderHash,
+    ) -> Result<ForkChoiceTip, Error> {
+        let burn_tip = self
+            .sortitions
+            .get(burn_header_hash)
+            .ok_or_else(|| Error::UnknownSortition(burn_header_hash.clone()))?;
+
+        // consensus hashes of the sortitions in this burnchain fork
+        let mut fork = HashSet::new();
+        let mut cursor = burn_tip;
+        loop {
+            fork.insert(cursor.consensus_hash.clone());
+            if cursor.burn_header_hash == self.first_burn_header_hash {
+                break;
+            }
+            cursor = &self.sortitions[&cursor.parent_burn_header_hash];
+        }
+
+        // blocks arrive after their parents, so a single pass in arrival order finds every block
+        // this fork accepted
+        let mut accepted = HashSet::new();
+        let mut best = &self.blocks[0];
+        for block in self.blocks.iter() {
+            if !fork.contains(&block.consensus_hash) {
+                continue;
+            }
+            if block.stacks_block_height > 0
+                && !accepted.contains(&(&block.parent_consensus_hash, &block.parent_block_hash))
+            {
+                continue;
+            }
+            accepted.insert((&block.consensus_hash, &block.block_hash));
+            if is_preferred_stacks_tip(
+                block.stacks_block_height,
+                block.arrival_index,
+                best.stacks_block_height,
+                best.arrival_index,
+            ) {
+                best = block;
+            }
+        }
+
+        Ok(ForkChoiceTip {
+            burn_header_hash: burn_tip.burn_header_hash.clone(),
+            burn_block_height: burn_tip.block_height,
+            consensus_hash: best.consensus_hash.clone(),
+            block_hash: best.block_hash.clone(),
+            stacks_block_height: best.stacks_block_height,
+        })
+    }
+}
+
+/// Work out which chain tip fork-choice selects, given hypothetical sortitions (in an order that
+/// keeps parents ahead of children) and Stacks blocks (in the order they arrive) on top of the
+/// first burnchain block.  Heights and arrival indexes are worked out from the order of the
+/// inputs, so the ones they carry are ignored.
+pub fn simulate_fork_choice(
+    first_burn_header_hash: &BurnchainHeaderHash,
+    first_block_height: u64,
+    sortitions: &[SimulatedSortition],
+    blocks: &[SimulatedStacksBlock],
+) -> Result<ForkChoiceTip, Error> {
+    let mut sim = ForkChoiceSimulation::new(first_burn_header_hash, first_block_height);
+    for sn in sortitions.iter() {
+        sim.add_sortition(
+            &sn.burn_header_hash,
+            &sn.parent_burn_header_hash,
+            &sn.consensus_hash,
+            sn.pox_valid,
+        )?;
+    }
+    for block in blocks.iter() {
+        sim.add_block(
+            &block.consensus_hash,
+            &block.block_hash,
+            &block.parent_consensus_hash,
+            &block.parent_block_hash,
+        )?;
+    }
+    Ok(sim.canonical_stacks_tip())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn burn(i: u8) -> BurnchainHeaderHash {
+        BurnchainHeaderHash([i; 32])
+    }
+
+    fn ch(i: u8) -> ConsensusHash {
+        ConsensusHash([i; 20])
+    }
+
+    fn block(i: u8) -> BlockHeaderHash {
+        BlockHeaderHash([i; 32])
+    }
+
+    /// a linear burnchain fork of sortitions `ids`, built on `parent`
+    fn add_fork(sim: &mut ForkChoiceSimulation, parent: u8, ids: &[u8]) {
+        let mut parent = burn(parent);
+        for id in ids.iter() {
+            sim.add_sortition(&burn(*id), &parent, &ch(*id), true)
+                .unwrap();
+            parent = burn(*id);
+        }
+    }
+
+    #[test]
+    fn fork_choice_follows_longest_stacks_chain() {
+        let mut sim = ForkChoiceSimulation::new(&burn(0), 100);
+        let tip = sim.canonical_stacks_tip();
+        assert_eq!(tip.block_hash, FIRST_STACKS_BLOCK_HASH);
+        assert_eq!(tip.stacks_block_height, 0);
+
+        add_fork(&mut sim, 0, &[1, 2, 3, 4]);
+        sim.add_block(
+            &ch(1),
+            &block(1),
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        )
+        .unwrap();
+        sim.add_block(&ch(2), &block(2), &ch(1), &block(1)).unwrap();
+
+        // a sibling of block 2 at the same height doesn't displace it
+        sim.add_block(&ch(3), &block(3), &ch(1), &block(1)).unwrap();
+        let tip = sim.canonical_stacks_tip();
+        assert_eq!(tip.block_hash, block(2));
+        assert_eq!(tip.stacks_block_height, 2);
+        assert_eq!(tip.burn_header_hash, burn(4));
+        assert_eq!(tip.burn_block_height, 104);
+
+        // but once it's extended, it does
+        sim.add_block(&ch(4), &block(4), &ch(3), &block(3)).unwrap();
+        let tip = sim.canonical_stacks_tip();
+        assert_eq!(tip.block_hash, block(4));
+        assert_eq!(tip.stacks_block_height, 3);
+        assert_eq!(
+            tip.index_block_hash(),
+            StacksBlockId::new(&ch(4), &block(4))
+        );
+
+        // the tip as of an earlier burnchain block only considers that block's ancestors
+        let tip = sim.canonical_stacks_tip_at(&burn(3)).unwrap();
+        assert_eq!(tip.block_hash, block(2));
+    }
+
+    #[test]
+    fn fork_choice_burnchain_reorg() {
+        let mut sim = ForkChoiceSimulation::new(&burn(0), 0);
+        add_fork(&mut sim, 0, &[1, 2, 3]);
+        sim.add_block(
+            &ch(1),
+            &block(1),
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        )
+        .unwrap();
+        sim.add_block(&ch(2), &block(2), &ch(1), &block(1)).unwrap();
+        sim.add_block(&ch(3), &block(3), &ch(2), &block(2)).unwrap();
+        assert_eq!(sim.canonical_stacks_tip().block_hash, block(3));
+
+        // a longer burnchain fork off of burn block 1 wins, even though it has a shorter Stacks
+        // chain
+        add_fork(&mut sim, 1, &[12, 13, 14]);
+        sim.add_block(&ch(12), &block(12), &ch(1), &block(1))
+            .unwrap();
+        let tip = sim.canonical_stacks_tip();
+        assert_eq!(tip.burn_header_hash, burn(14));
+        assert_eq!(tip.block_hash, block(12));
+        assert_eq!(tip.stacks_block_height, 2);
+
+        // the losing fork still has its own tip
+        let tip = sim.canonical_stacks_tip_at(&burn(3)).unwrap();
+        assert_eq!(tip.block_hash, block(3));
+
+        // blocks from the other fork can't be built upon in this one
+        sim.add_block(&ch(14), &block(14), &ch(3), &block(3))
+            .unwrap();
+        assert_eq!(sim.canonical_stacks_tip().block_hash, block(12));
+
+        // the original fork catches up, and wins the tie in burnchain height because it has the
+        // lower burn header hash
+        add_fork(&mut sim, 3, &[5]);
+        assert_eq!(sim.canonical_burn_tip().burn_header_hash, burn(5));
+        assert_eq!(sim.canonical_stacks_tip().block_hash, block(3));
+        add_fork(&mut sim, 14, &[15]);
+        add_fork(&mut sim, 5, &[16]);
+        assert_eq!(sim.canonical_burn_tip().burn_header_hash, burn(15));
+    }
+
+    #[test]
+    fn fork_choice_ignores_pox_invalid_forks() {
+        let mut sim = ForkChoiceSimulation::new(&burn(0), 0);
+        add_fork(&mut sim, 0, &[1]);
+        sim.add_sortition(&burn(2), &burn(1), &ch(2), false)
+            .unwrap();
+        add_fork(&mut sim, 2, &[3]);
+        sim.add_block(
+            &ch(1),
+            &block(1),
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        )
+        .unwrap();
+        sim.add_block(&ch(3), &block(3), &ch(1), &block(1)).unwrap();
+
+        // sortition 3 descends from an invalid sortition
+        assert!(!sim.get_sortition(&burn(3)).unwrap().pox_valid);
+        let tip = sim.canonical_stacks_tip();
+        assert_eq!(tip.burn_header_hash, burn(1));
+        assert_eq!(tip.block_hash, block(1));
+    }
+
+    #[test]
+    fn fork_choice_rejects_bad_inputs() {
+        let mut sim = ForkChoiceSimulation::new(&burn(0), 0);
+        add_fork(&mut sim, 0, &[1, 2]);
+
+        assert_eq!(
+            sim.add_sortition(&burn(3), &burn(9), &ch(3), true),
+            Err(Error::UnknownSortition(burn(9)))
+        );
+        assert_eq!(
+            sim.add_sortition(&burn(3), &burn(2), &ch(2), true),
+            Err(Error::DuplicateSortition(burn(3), ch(2)))
+        );
+        assert_eq!(
+            sim.add_block(&ch(9), &block(9), &ch(1), &block(1)),
+            Err(Error::UnknownConsensusHash(ch(9)))
+        );
+        assert_eq!(
+            sim.add_block(&ch(2), &block(2), &ch(1), &block(1)),
+            Err(Error::UnknownParentBlock(ch(1), block(1)))
+        );
+
+        sim.add_block(
+            &ch(2),
+            &block(2),
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        )
+        .unwrap();
+        assert_eq!(
+            sim.add_block(
+                &ch(2),
+                &block(3),
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH
+            ),
+            Err(Error::DuplicateBlock(ch(2), block(3)))
+        );
+        assert_eq!(
+            sim.add_block(&ch(1), &block(1), &ch(2), &block(2)),
+            Err(Error::ParentNotEarlier(ch(1), block(1)))
+        );
+
+        let tip = simulate_fork_choice(
+            &burn(0),
+            0,
+            &[SimulatedSortition {
+                burn_header_hash: burn(1),
+                parent_burn_header_hash: burn(0),
+                consensus_hash: ch(1),
+                block_height: 1,
+                pox_valid: true,
+            }],
+            &[SimulatedStacksBlock {
+                consensus_hash: ch(1),
+                block_hash: block(1),
+                parent_consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                parent_block_hash: FIRST_STACKS_BLOCK_HASH.clone(),
+                stacks_block_height: 1,
+                arrival_index: 1,
+            }],
+        )
+        .unwrap();
+        assert_eq!(tip.block_hash, block(1));
+    }
+}
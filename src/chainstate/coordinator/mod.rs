@@ -16,7 +16,6 @@
 
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
-use std::sync::mpsc::SyncSender;
 use std::time::Duration;
 
 use burnchains::{
@@ -34,14 +33,17 @@ use chainstate::stacks::{
         boot_code_id, STACKS_BOOT_CODE_CONTRACT_ADDRESS, STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR,
     },
     db::{
-        accounts::MinerReward, ChainStateBootData, ClarityTx, MinerRewardInfo, StacksChainState,
-        StacksHeaderInfo,
+        accounts::MinerReward, header_chain::HeaderChainDB, ChainStateBootData, ClarityTx,
+        MinerRewardInfo, StacksChainState, StacksHeaderInfo,
     },
     events::{StacksTransactionEvent, StacksTransactionReceipt, TransactionOrigin},
     Error as ChainstateError, StacksAddress, StacksBlock, StacksBlockHeader, StacksBlockId,
     TransactionPayload,
 };
-use monitoring::increment_stx_blocks_processed_counter;
+use monitoring::{
+    increment_stx_blocks_processed_counter, update_burnchain_height_gauge,
+    update_stacks_tip_height_gauge,
+};
 use net::atlas::{AtlasConfig, AttachmentInstance};
 use util::db::Error as DBError;
 use vm::{
@@ -51,6 +53,7 @@ use vm::{
 };
 
 pub mod comm;
+pub mod fork_choice;
 use chainstate::stacks::index::MarfTrieId;
 
 #[cfg(test)]
@@ -59,7 +62,8 @@ pub mod tests;
 pub use self::comm::CoordinatorCommunication;
 
 use chainstate::coordinator::comm::{
-    ArcCounterCoordinatorNotices, CoordinatorEvents, CoordinatorNotices, CoordinatorReceivers,
+    ArcCounterCoordinatorNotices, CoordinatorEventBus, CoordinatorEventBusConfig,
+    CoordinatorEvents, CoordinatorNotices, CoordinatorReceivers,
 };
 
 /// The 3 different states for the current
@@ -134,6 +138,27 @@ pub trait BlockEventDispatcher {
     );
 
     fn dispatch_boot_receipts(&mut self, receipts: Vec<StacksTransactionReceipt>);
+
+    /// called whenever the canonical Stacks chain tip moves
+    ///  to a block that does not descend from the previous
+    ///  canonical tip.  if the new tip was just processed,
+    ///  this is called before its block is announced.
+    fn announce_reorg(&self, reorg: &StacksChainReorg);
+}
+
+/// A move of the canonical Stacks chain tip to a block that does not descend from the previous
+/// canonical tip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StacksChainReorg {
+    pub old_tip: StacksBlockId,
+    pub old_tip_height: u64,
+    pub new_tip: StacksBlockId,
+    pub new_tip_height: u64,
+    pub common_ancestor: StacksBlockId,
+    pub common_ancestor_height: u64,
+    /// Blocks no longer in the canonical fork, from the old tip back to (but not including) the
+    /// common ancestor
+    pub orphaned_blocks: Vec<StacksBlockId>,
 }
 
 pub struct ChainsCoordinator<
@@ -149,11 +174,17 @@ pub struct ChainsCoordinator<
     chain_state_db: StacksChainState,
     sortition_db: SortitionDB,
     burnchain: Burnchain,
-    attachments_tx: SyncSender<HashSet<AttachmentInstance>>,
+    /// bus the coordinator posts the attachment instances it finds to
+    event_bus: CoordinatorEventBus,
     dispatcher: Option<&'a T>,
+    /// canonical Stacks chain tip as of the last reorg check, if the dispatcher is set
+    announced_chain_tip: Option<StacksBlockId>,
     reward_set_provider: R,
     notifier: N,
     atlas_config: AtlasConfig,
+    /// Set if this coordinator runs in header-only mode, in which case it only accepts Stacks
+    /// block headers into this header chain and never processes Stacks blocks.
+    header_chain: Option<HeaderChainDB>,
 }
 
 #[derive(Debug)]
@@ -246,7 +277,39 @@ impl<'a, T: BlockEventDispatcher>
     pub fn run(
         chain_state_db: StacksChainState,
         burnchain: Burnchain,
-        attachments_tx: SyncSender<HashSet<AttachmentInstance>>,
+        dispatcher: &mut T,
+        comms: CoordinatorReceivers,
+    ) where
+        T: BlockEventDispatcher,
+    {
+        ChainsCoordinator::run_with_header_chain(chain_state_db, None, burnchain, dispatcher, comms)
+    }
+
+    /// Run the coordinator in header-only mode: process sortitions as usual, but only accept
+    /// Stacks block headers into `header_chain`.  Stacks blocks are never processed, so
+    /// `chain_state_db` stays at its boot state.
+    pub fn run_headers_only(
+        chain_state_db: StacksChainState,
+        header_chain: HeaderChainDB,
+        burnchain: Burnchain,
+        dispatcher: &mut T,
+        comms: CoordinatorReceivers,
+    ) where
+        T: BlockEventDispatcher,
+    {
+        ChainsCoordinator::run_with_header_chain(
+            chain_state_db,
+            Some(header_chain),
+            burnchain,
+            dispatcher,
+            comms,
+        )
+    }
+
+    fn run_with_header_chain(
+        chain_state_db: StacksChainState,
+        header_chain: Option<HeaderChainDB>,
+        burnchain: Burnchain,
         dispatcher: &mut T,
         comms: CoordinatorReceivers,
     ) where
@@ -261,6 +324,12 @@ impl<'a, T: BlockEventDispatcher>
 
         let canonical_sortition_tip =
             SortitionDB::get_canonical_sortition_tip(sortition_db.conn()).unwrap();
+        let canonical_snapshot =
+            SortitionDB::get_canonical_burn_chain_tip(sortition_db.conn()).unwrap();
+        let announced_chain_tip = StacksBlockId::new(
+            &canonical_snapshot.canonical_stacks_tip_consensus_hash,
+            &canonical_snapshot.canonical_stacks_tip_hash,
+        );
 
         let arc_notices = ArcCounterCoordinatorNotices {
             stacks_blocks_processed,
@@ -275,11 +344,13 @@ impl<'a, T: BlockEventDispatcher>
             chain_state_db,
             sortition_db,
             burnchain,
-            attachments_tx,
+            event_bus: comms.event_bus(),
             dispatcher: Some(dispatcher),
+            announced_chain_tip: Some(announced_chain_tip),
             notifier: arc_notices,
             reward_set_provider: OnChainRewardSetProvider(),
             atlas_config: AtlasConfig::default(),
+            header_chain,
         };
 
         loop {
@@ -324,7 +395,6 @@ impl<'a, T: BlockEventDispatcher, U: RewardSetProvider> ChainsCoordinator<'a, T,
         burnchain: &Burnchain,
         path: &str,
         reward_set_provider: U,
-        attachments_tx: SyncSender<HashSet<AttachmentInstance>>,
     ) -> ChainsCoordinator<'a, T, (), U> {
         let burnchain = burnchain.clone();
 
@@ -353,12 +423,32 @@ impl<'a, T: BlockEventDispatcher, U: RewardSetProvider> ChainsCoordinator<'a, T,
             sortition_db,
             burnchain,
             dispatcher: None,
+            announced_chain_tip: None,
             reward_set_provider,
             notifier: (),
-            attachments_tx,
+            event_bus: CoordinatorEventBus::new(CoordinatorEventBusConfig::default()),
             atlas_config: AtlasConfig::default(),
+            header_chain: None,
         }
     }
+
+    /// Make a header-only coordinator, whose header chain is stored in
+    /// `{path}/chainstate/headers.sqlite`
+    #[cfg(test)]
+    pub fn test_new_headers_only(
+        burnchain: &Burnchain,
+        path: &str,
+        reward_set_provider: U,
+    ) -> ChainsCoordinator<'a, T, (), U> {
+        let mut coord = ChainsCoordinator::test_new(burnchain, path, reward_set_provider);
+        let header_chain = HeaderChainDB::open(
+            &format!("{}/chainstate/headers.sqlite", path),
+            &mut coord.chain_state_db,
+        )
+        .unwrap();
+        coord.header_chain = Some(header_chain);
+        coord
+    }
 }
 
 pub fn get_next_recipients<U: RewardSetProvider>(
@@ -386,6 +476,62 @@ pub fn get_next_recipients<U: RewardSetProvider>(
         .map_err(|e| Error::from(e))
 }
 
+/// Header-only counterpart to get_reward_cycle_info().  A light node cannot compute a reward set,
+/// so the PoX anchor block is only considered known once its header has been accepted _and_ the
+/// reward set it chose has been obtained from a peer.  If the anchor block's reward set is
+/// missing, it is recorded as wanted in the header chain.
+pub fn get_header_chain_reward_cycle_info(
+    burn_height: u64,
+    parent_bhh: &BurnchainHeaderHash,
+    sortition_tip: &SortitionId,
+    burnchain: &Burnchain,
+    header_chain: &mut HeaderChainDB,
+    sort_db: &SortitionDB,
+) -> Result<Option<RewardCycleInfo>, Error> {
+    if burnchain.is_reward_cycle_start(burn_height) {
+        if burn_height >= burnchain.pox_constants.sunset_end {
+            return Ok(Some(RewardCycleInfo {
+                anchor_status: PoxAnchorBlockStatus::NotSelected,
+            }));
+        }
+
+        info!("Beginning reward cycle. block_height={}", burn_height);
+        let reward_cycle_info = {
+            let ic = sort_db.index_handle(sortition_tip);
+            ic.get_chosen_pox_anchor(&parent_bhh, &burnchain.pox_constants)
+        }?;
+        if let Some((consensus_hash, stacks_block_hash)) = reward_cycle_info {
+            info!("Anchor block selected: {}", stacks_block_hash);
+            let anchor_status = if header_chain.has_header(&consensus_hash, &stacks_block_hash)? {
+                let block_id =
+                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &stacks_block_hash);
+                match header_chain.get_reward_set(&block_id)? {
+                    Some(reward_set) => {
+                        PoxAnchorBlockStatus::SelectedAndKnown(stacks_block_hash, reward_set)
+                    }
+                    None => {
+                        info!(
+                            "Have anchor block header {}, but not its reward set",
+                            &block_id
+                        );
+                        header_chain.want_reward_set(&block_id)?;
+                        PoxAnchorBlockStatus::SelectedAndUnknown(stacks_block_hash)
+                    }
+                }
+            } else {
+                PoxAnchorBlockStatus::SelectedAndUnknown(stacks_block_hash)
+            };
+            Ok(Some(RewardCycleInfo { anchor_status }))
+        } else {
+            Ok(Some(RewardCycleInfo {
+                anchor_status: PoxAnchorBlockStatus::NotSelected,
+            }))
+        }
+    } else {
+        Ok(None)
+    }
+}
+
 /// returns None if this burnchain block is _not_ the start of a reward cycle
 ///         otherwise, returns the required reward cycle info for this burnchain block
 ///                     in our current sortition view:
@@ -453,6 +599,10 @@ fn dispatcher_announce_burn_ops<T: BlockEventDispatcher>(
     let mut burn_amt = 0;
     for op in ops.iter() {
         if let BlockstackOperationType::LeaderBlockCommit(commit) = op {
+            if commit.commit_outs.len() == 0 {
+                burn_amt += commit.burn_fee;
+                continue;
+            }
             let amt_per_address = commit.burn_fee / (commit.commit_outs.len() as u64);
             for addr in commit.commit_outs.iter() {
                 if addr.is_burn() {
@@ -476,6 +626,94 @@ fn dispatcher_announce_burn_ops<T: BlockEventDispatcher>(
     );
 }
 
+/// Find how the canonical Stacks fork changed when its tip moved from `old_tip` to `new_tip`.
+/// Returns None if `new_tip` descends from `old_tip`, or if either block was never processed.
+pub fn find_stacks_chain_reorg(
+    chainstate: &StacksChainState,
+    old_tip: &StacksBlockId,
+    new_tip: &StacksBlockId,
+) -> Result<Option<StacksChainReorg>, Error> {
+    let get_height = |block_id: &StacksBlockId| -> Result<Option<u64>, Error> {
+        Ok(
+            StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                block_id,
+            )?
+            .map(|header| header.block_height),
+        )
+    };
+    let (old_tip_height, new_tip_height) = match (get_height(old_tip)?, get_height(new_tip)?) {
+        (Some(old_tip_height), Some(new_tip_height)) => (old_tip_height, new_tip_height),
+        _ => {
+            return Ok(None);
+        }
+    };
+
+    // walk back from the higher of the two blocks until they meet
+    let mut orphaned_blocks = vec![];
+    let (mut old_cursor, mut old_height) = (old_tip.clone(), old_tip_height);
+    let (mut new_cursor, mut new_height) = (new_tip.clone(), new_tip_height);
+    while old_cursor != new_cursor {
+        if old_height == 0 && new_height == 0 {
+            // no common ancestor
+            return Ok(None);
+        }
+        if old_height >= new_height {
+            let parent = chainstate.get_parent(&old_cursor)?;
+            orphaned_blocks.push(old_cursor);
+            old_cursor = parent;
+            old_height -= 1;
+        } else {
+            new_cursor = chainstate.get_parent(&new_cursor)?;
+            new_height -= 1;
+        }
+    }
+
+    if orphaned_blocks.len() == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(StacksChainReorg {
+        old_tip: old_tip.clone(),
+        old_tip_height,
+        new_tip: new_tip.clone(),
+        new_tip_height,
+        common_ancestor: old_cursor,
+        common_ancestor_height: old_height,
+        orphaned_blocks,
+    }))
+}
+
+/// Announce a reorg if the canonical Stacks chain tip moved off of the fork `announced_tip` is
+/// on, and remember the new tip.
+fn dispatcher_announce_reorg<T: BlockEventDispatcher>(
+    dispatcher: &T,
+    chainstate: &StacksChainState,
+    announced_tip: &mut Option<StacksBlockId>,
+    new_tip: &StacksBlockId,
+) -> Result<(), Error> {
+    let old_tip = match announced_tip.replace(new_tip.clone()) {
+        Some(old_tip) => old_tip,
+        None => {
+            return Ok(());
+        }
+    };
+    if old_tip == *new_tip {
+        return Ok(());
+    }
+    if let Some(reorg) = find_stacks_chain_reorg(chainstate, &old_tip, new_tip)? {
+        info!(
+            "Canonical Stacks chain reorg";
+            "old_tip" => %reorg.old_tip,
+            "new_tip" => %reorg.new_tip,
+            "common_ancestor" => %reorg.common_ancestor,
+            "orphaned_blocks" => reorg.orphaned_blocks.len()
+        );
+        dispatcher.announce_reorg(&reorg);
+    }
+    Ok(())
+}
+
 impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
     ChainsCoordinator<'a, T, N, U>
 {
@@ -555,9 +793,14 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
                 })?
                 .0;
 
+            let canonical_stacks_tip = StacksBlockId::new(
+                &next_snapshot.canonical_stacks_tip_consensus_hash,
+                &next_snapshot.canonical_stacks_tip_hash,
+            );
             let sortition_id = next_snapshot.sortition_id;
 
             self.notifier.notify_sortition_processed();
+            update_burnchain_height_gauge(next_snapshot.block_height);
 
             debug!(
                 "Sortition processed";
@@ -572,6 +815,16 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
             self.canonical_sortition_tip = Some(sortition_id.clone());
             last_processed_ancestor = sortition_id;
 
+            // a burnchain fork can move the canonical Stacks tip to another Stacks fork
+            if let Some(dispatcher) = self.dispatcher {
+                dispatcher_announce_reorg(
+                    dispatcher,
+                    &self.chain_state_db,
+                    &mut self.announced_chain_tip,
+                    &canonical_stacks_tip,
+                )?;
+            }
+
             if let Some(pox_anchor) = self.process_ready_blocks()? {
                 return self.process_new_pox_anchor(pox_anchor);
             }
@@ -593,6 +846,16 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
             .canonical_sortition_tip
             .as_ref()
             .expect("FATAL: Processing anchor block, but no known sortition tip");
+        if let Some(ref mut header_chain) = self.header_chain {
+            return get_header_chain_reward_cycle_info(
+                burn_header.block_height,
+                &burn_header.parent_block_hash,
+                sortition_tip,
+                &self.burnchain,
+                header_chain,
+                &self.sortition_db,
+            );
+        }
         get_reward_cycle_info(
             burn_header.block_height,
             &burn_header.parent_block_hash,
@@ -613,6 +876,10 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
     ///   otherwise returns None
     ///
     fn process_ready_blocks(&mut self) -> Result<Option<BlockHeaderHash>, Error> {
+        if self.header_chain.is_some() {
+            return self.process_ready_headers();
+        }
+
         let canonical_sortition_tip = self.canonical_sortition_tip.as_ref().expect(
             "FAIL: processing a new Stacks block, but don't have a canonical sortition tip",
         );
@@ -641,10 +908,25 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
                     ));
                     let new_canonical_stacks_block =
                         new_canonical_block_snapshot.get_canonical_stacks_block_id();
+                    if let Some(dispatcher) = self.dispatcher {
+                        let canonical_stacks_tip = StacksBlockId::new(
+                            &new_canonical_block_snapshot.canonical_stacks_tip_consensus_hash,
+                            &new_canonical_block_snapshot.canonical_stacks_tip_hash,
+                        );
+                        dispatcher_announce_reorg(
+                            dispatcher,
+                            &self.chain_state_db,
+                            &mut self.announced_chain_tip,
+                            &canonical_stacks_tip,
+                        )?;
+                    }
                     self.canonical_chain_tip = Some(new_canonical_stacks_block);
                     debug!("Bump blocks processed");
                     self.notifier.notify_stacks_block_processed();
                     increment_stx_blocks_processed_counter();
+                    update_stacks_tip_height_gauge(
+                        new_canonical_block_snapshot.canonical_stacks_tip_height,
+                    );
                     let block_hash = block_receipt.header.anchored_header.block_hash();
 
                     let mut attachments_instances = HashSet::new();
@@ -677,13 +959,7 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
                         }
                     }
                     if !attachments_instances.is_empty() {
-                        match self.attachments_tx.send(attachments_instances) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                error!("Error dispatching attachments {}", e);
-                                panic!();
-                            }
-                        };
+                        self.event_bus.post_new_attachments(attachments_instances);
                     }
 
                     if let Some(dispatcher) = self.dispatcher {
@@ -744,6 +1020,101 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
         Ok(None)
     }
 
+    ///
+    /// Header-only counterpart to process_ready_blocks(): accept ready staging headers into the
+    /// header chain until there are either:
+    ///   * there are no more to accept
+    ///   * a PoX anchor block whose reward set we have is accepted, or the reward set of an
+    ///     already-accepted PoX anchor block arrives, which invalidates the current PoX fork
+    ///
+    /// Returns Some(BlockHeaderHash) if such an anchor block is discovered,
+    ///   otherwise returns None
+    ///
+    fn process_ready_headers(&mut self) -> Result<Option<BlockHeaderHash>, Error> {
+        let canonical_sortition_tip = self.canonical_sortition_tip.as_ref().expect(
+            "FAIL: processing a new Stacks header, but don't have a canonical sortition tip",
+        );
+        let header_chain = self
+            .header_chain
+            .as_mut()
+            .expect("FAIL: processing headers, but not in header-only mode");
+
+        loop {
+            let sortdb_handle = self.sortition_db.tx_handle_begin(canonical_sortition_tip)?;
+            let accepted_headers = header_chain.process_headers(sortdb_handle, 1)?;
+            if accepted_headers.len() == 0 {
+                break;
+            }
+
+            for accepted_header in accepted_headers.into_iter() {
+                let block_hash = accepted_header.header.block_hash();
+                let in_sortition_set = self
+                    .sortition_db
+                    .is_stacks_block_in_sortition_set(canonical_sortition_tip, &block_hash)?;
+                if !in_sortition_set {
+                    continue;
+                }
+
+                let new_canonical_block_snapshot = SortitionDB::get_block_snapshot(
+                    self.sortition_db.conn(),
+                    canonical_sortition_tip,
+                )?
+                .expect(&format!(
+                    "FAIL: could not find data for the canonical sortition {}",
+                    canonical_sortition_tip
+                ));
+                self.canonical_chain_tip =
+                    Some(new_canonical_block_snapshot.get_canonical_stacks_block_id());
+                debug!("Bump headers processed");
+                self.notifier.notify_stacks_block_processed();
+                increment_stx_blocks_processed_counter();
+                update_stacks_tip_height_gauge(
+                    new_canonical_block_snapshot.canonical_stacks_tip_height,
+                );
+
+                // as with blocks, an anchor block header accepted after its reward cycle began
+                // means we need to unwind -- but only once we know the reward set it chose.
+                if let Some(pox_anchor) = self
+                    .sortition_db
+                    .is_stacks_block_pox_anchor(&block_hash, canonical_sortition_tip)?
+                {
+                    let anchor_block_id = accepted_header.index_block_hash();
+                    if header_chain.get_reward_set(&anchor_block_id)?.is_some() {
+                        info!("Discovered an old anchor block: {}", &pox_anchor);
+                        return Ok(Some(pox_anchor));
+                    }
+                    info!(
+                        "Discovered an old anchor block {}, but do not have its reward set",
+                        &pox_anchor
+                    );
+                    header_chain.want_reward_set(&anchor_block_id)?;
+                }
+            }
+        }
+
+        // did we obtain the reward set of an anchor block we already accepted?
+        for anchor_block_id in header_chain.take_new_reward_sets()?.into_iter() {
+            let anchor_header = match header_chain.get_header(&anchor_block_id)? {
+                Some(header) => header,
+                None => {
+                    continue;
+                }
+            };
+            if let Some(pox_anchor) = self.sortition_db.is_stacks_block_pox_anchor(
+                &anchor_header.header.block_hash(),
+                canonical_sortition_tip,
+            )? {
+                info!(
+                    "Obtained the reward set of an old anchor block: {}",
+                    &pox_anchor
+                );
+                return Ok(Some(pox_anchor));
+            }
+        }
+
+        Ok(None)
+    }
+
     fn process_new_pox_anchor(&mut self, block_id: BlockHeaderHash) -> Result<(), Error> {
         // get the last sortition in the prepare phase that chose this anchor block
         //   that sortition is now the current canonical sortition,
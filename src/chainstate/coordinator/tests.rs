@@ -16,16 +16,26 @@
 
 use chainstate::burn::operations::leader_block_commit::*;
 use chainstate::burn::operations::*;
+use chainstate::coordinator::comm::{
+    CoordinatorEventBusConfig, CoordinatorEventKind, CoordinatorEvents, DropPolicy,
+};
 use chainstate::coordinator::{Error as CoordError, *};
 use chainstate::stacks::*;
+use net::atlas::AttachmentInstance;
 use std::collections::VecDeque;
 use util::hash::Hash160;
 
 use burnchains::{db::*, *};
 use chainstate::burn::db::sortdb::{PoxId, SortitionDB, SortitionId};
 use chainstate::burn::*;
+use chainstate::stacks::boot::boot_code_id;
 use chainstate::stacks::db::{
-    accounts::MinerReward, ClarityTx, StacksChainState, StacksHeaderInfo,
+    accounts::MinerReward,
+    audit::{ConsensusWrite, CONSENSUS_AUDIT_LOG_FILE},
+    diff::ChainstateDivergence,
+    header_chain::HeaderChainDB,
+    indexer::{IndexedBlock, Indexer},
+    ClarityTx, StacksChainState, StacksHeaderInfo, MINER_REWARD_MATURITY,
 };
 use chainstate::stacks::index::TrieHash;
 use core;
@@ -35,13 +45,13 @@ use monitoring::increment_stx_blocks_processed_counter;
 use std::collections::HashSet;
 use std::sync::{
     atomic::{AtomicBool, AtomicU64, Ordering},
-    mpsc::sync_channel,
-    Arc, RwLock,
+    Arc, Mutex, RwLock,
 };
 use util::vrf::*;
 use vm::{
     clarity::ClarityConnection,
     costs::{ExecutionCost, LimitedCostTracker},
+    database::{ClarityDatabase, ClaritySerializable},
     types::PrincipalData,
     types::QualifiedContractIdentifier,
     Value,
@@ -288,15 +298,16 @@ impl BlockEventDispatcher for NullEventDispatcher {
     }
 
     fn dispatch_boot_receipts(&mut self, _receipts: Vec<StacksTransactionReceipt>) {}
+
+    fn announce_reorg(&self, _reorg: &StacksChainReorg) {}
 }
 
 pub fn make_coordinator<'a>(
     path: &str,
     burnchain: Option<Burnchain>,
 ) -> ChainsCoordinator<'a, NullEventDispatcher, (), OnChainRewardSetProvider> {
-    let (tx, _) = sync_channel(100000);
     let burnchain = burnchain.unwrap_or_else(|| get_burnchain(path, None));
-    ChainsCoordinator::test_new(&burnchain, path, OnChainRewardSetProvider(), tx)
+    ChainsCoordinator::test_new(&burnchain, path, OnChainRewardSetProvider())
 }
 
 struct StubbedRewardSetProvider(Vec<StacksAddress>);
@@ -319,12 +330,10 @@ fn make_reward_set_coordinator<'a>(
     addrs: Vec<StacksAddress>,
     pox_consts: Option<PoxConstants>,
 ) -> ChainsCoordinator<'a, NullEventDispatcher, (), StubbedRewardSetProvider> {
-    let (tx, _) = sync_channel(100000);
     ChainsCoordinator::test_new(
         &get_burnchain(path, pox_consts),
         path,
         StubbedRewardSetProvider(addrs),
-        tx,
     )
 }
 
@@ -398,7 +407,7 @@ fn make_genesis_block_with_recipients(
     let mut tx = StacksTransaction::new(
         TransactionVersion::Testnet,
         tx_auth,
-        TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+        TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
     );
     tx.chain_id = 0x80000000;
     tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
@@ -535,7 +544,7 @@ fn make_stacks_block_with_recipients_and_sunset_burn(
     let mut tx = StacksTransaction::new(
         TransactionVersion::Testnet,
         tx_auth,
-        TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+        TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
     );
     tx.chain_id = 0x80000000;
     tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
@@ -824,6 +833,827 @@ fn test_simple_setup() {
     }
 }
 
+struct RecordingIndexer(Arc<Mutex<Vec<IndexedBlock>>>);
+
+impl Indexer for RecordingIndexer {
+    fn name(&self) -> String {
+        "recording".to_string()
+    }
+
+    fn index_block(&mut self, block: &IndexedBlock) -> Result<(), String> {
+        self.0.lock().unwrap().push(block.clone());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_indexer_sees_processed_blocks() {
+    let path = "/tmp/stacks-blockchain-indexer";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..5).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..5).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(&[path], &vrf_keys, &committers, None, None);
+
+    let mut coord = make_coordinator(path, None);
+    let indexed = Arc::new(Mutex::new(vec![]));
+    coord
+        .chain_state_db
+        .register_indexer(Box::new(RecordingIndexer(indexed.clone())), 1)
+        .unwrap();
+
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    let mut stacks_blocks = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            Vec::<BurnchainDB>::new().iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        let block_hash = block.header.block_hash();
+        stacks_blocks.push(StacksBlockId::new(&tip.consensus_hash, &block_hash));
+
+        preprocess_block(&mut chainstate, &sort_db, &tip, block);
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block_hash;
+    }
+
+    // dropping the coordinator drains the indexer's queue
+    drop(coord);
+
+    let indexed = indexed.lock().unwrap();
+    assert_eq!(indexed.len(), stacks_blocks.len());
+    for (i, indexed_block) in indexed.iter().enumerate() {
+        assert_eq!(indexed_block.index_block_hash(), stacks_blocks[i]);
+        assert_eq!(
+            indexed_block.block.block_hash(),
+            indexed_block.receipt.header.anchored_header.block_hash()
+        );
+        if i > 0 {
+            assert_eq!(indexed_block.parent_block_id, stacks_blocks[i - 1]);
+        }
+        // every block at least writes its coinbase and microblock public key hash
+        assert!(indexed_block.receipt.tx_receipts.len() > 0);
+        assert!(indexed_block.receipt.marf_writes.len() > 0);
+    }
+}
+
+#[test]
+fn test_consensus_audit_log_records_processed_blocks() {
+    let path = "/tmp/stacks-blockchain-consensus-audit-log";
+    let audit_path = "/tmp/stacks-blockchain-consensus-audit-log-files";
+    let _r = std::fs::remove_dir_all(path);
+    let _r = std::fs::remove_dir_all(audit_path);
+
+    let vrf_keys: Vec<_> = (0..3).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..3).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(&[path], &vrf_keys, &committers, None, None);
+
+    let mut coord = make_coordinator(path, None);
+    coord
+        .chain_state_db
+        .enable_consensus_audit_log(audit_path, 1024 * 1024, 1)
+        .unwrap();
+
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    let mut stacks_blocks = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            Vec::<BurnchainDB>::new().iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        let block_hash = block.header.block_hash();
+        stacks_blocks.push(StacksBlockId::new(&tip.consensus_hash, &block_hash));
+
+        preprocess_block(&mut chainstate, &sort_db, &tip, block);
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block_hash;
+    }
+    drop(coord);
+
+    let log =
+        std::fs::read_to_string(format!("{}/{}", audit_path, CONSENSUS_AUDIT_LOG_FILE)).unwrap();
+    let writes: Vec<ConsensusWrite> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    // blocks are logged in processing order, one after the other
+    let mut logged_blocks: Vec<StacksBlockId> = vec![];
+    for write in writes.iter() {
+        if logged_blocks.last() != Some(&write.index_block_hash) {
+            logged_blocks.push(write.index_block_hash.clone());
+        }
+    }
+    assert_eq!(logged_blocks, stacks_blocks);
+
+    for block_id in stacks_blocks.iter() {
+        let block_writes: Vec<_> = writes
+            .iter()
+            .filter(|w| &w.index_block_hash == block_id)
+            .collect();
+        let count = |table: &str| block_writes.iter().filter(|w| w.table == table).count();
+
+        // each block writes at least its coinbase and microblock public key hash to the MARF,
+        // and adds a header and its miner's payment
+        assert!(count("marf") > 0);
+        assert_eq!(count("block_headers"), 1);
+        assert_eq!(count("payments"), 1);
+    }
+}
+
+#[test]
+fn test_matured_miner_rewards_are_recorded() {
+    let path = "/tmp/stacks-blockchain-matured-miner-reward-events";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..5).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..5).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(&[path], &vrf_keys, &committers, None, None);
+
+    let mut coord = make_coordinator(path, None);
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    let mut stacks_blocks = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            Vec::<BurnchainDB>::new().iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        let block_hash = block.header.block_hash();
+        stacks_blocks.push(StacksBlockId::new(&tip.consensus_hash, &block_hash));
+
+        preprocess_block(&mut chainstate, &sort_db, &tip, block);
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block_hash;
+    }
+
+    let chainstate = get_chainstate(path);
+    for (ix, block_id) in stacks_blocks.iter().enumerate() {
+        let receipts =
+            StacksChainState::get_transaction_receipts(chainstate.db(), block_id).unwrap();
+        let reward_events: Vec<_> = receipts[0]
+            .events
+            .iter()
+            .filter(|event| event["type"] == "stx_miner_reward_event")
+            .collect();
+
+        if (ix as u64) <= MINER_REWARD_MATURITY {
+            assert_eq!(reward_events.len(), 0);
+            continue;
+        }
+
+        // the reward for mining the block MINER_REWARD_MATURITY blocks before the parent is
+        // credited
+        let rewarded_ix = ix - MINER_REWARD_MATURITY as usize - 1;
+        assert_eq!(reward_events.len(), 1);
+        let event = &reward_events[0]["stx_miner_reward_event"];
+        assert_eq!(
+            event["recipient"],
+            p2pkh_from(&committers[rewarded_ix]).to_string()
+        );
+        assert_eq!(
+            event["from_index_block_hash"],
+            format!("0x{}", &stacks_blocks[rewarded_ix])
+        );
+        assert!(event["coinbase_amount"].as_str().unwrap() != "0");
+    }
+}
+
+struct RecordingReorgDispatcher(Mutex<Vec<StacksChainReorg>>);
+
+impl BlockEventDispatcher for RecordingReorgDispatcher {
+    fn announce_block(
+        &self,
+        _block: StacksBlock,
+        _metadata: StacksHeaderInfo,
+        _receipts: Vec<StacksTransactionReceipt>,
+        _parent: &StacksBlockId,
+        _winner_txid: Txid,
+        _rewards: Vec<MinerReward>,
+        _rewards_info: Option<MinerRewardInfo>,
+    ) {
+    }
+
+    fn announce_burn_block(
+        &self,
+        _burn_block: &BurnchainHeaderHash,
+        _burn_block_height: u64,
+        _rewards: Vec<(StacksAddress, u64)>,
+        _burns: u64,
+    ) {
+    }
+
+    fn dispatch_boot_receipts(&mut self, _receipts: Vec<StacksTransactionReceipt>) {}
+
+    fn announce_reorg(&self, reorg: &StacksChainReorg) {
+        self.0.lock().unwrap().push(reorg.clone());
+    }
+}
+
+#[test]
+fn test_reorgs_are_announced() {
+    let path = "/tmp/stacks-blockchain-reorgs";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..6).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..6).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(&[path], &vrf_keys, &committers, None, None);
+
+    let dispatcher = RecordingReorgDispatcher(Mutex::new(vec![]));
+    let mut coord: ChainsCoordinator<_, (), _> =
+        ChainsCoordinator::test_new(&get_burnchain(path, None), path, OnChainRewardSetProvider());
+    coord.dispatcher = Some(&dispatcher);
+
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    // fork A: b0 <- a1 <- a2, then fork B: b0 <- b1 <- b2 <- b3
+    let parents = [None, Some(0), Some(1), Some(0), Some(3), Some(4)];
+    let mut stacks_blocks: Vec<(BlockHeaderHash, StacksBlockId)> = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let (op, block) = match parents[ix] {
+            None => make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &BlockHeaderHash([0; 32]),
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            ),
+            Some(parent_ix) => make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &stacks_blocks[parent_ix].0,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            ),
+        };
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            Vec::<BurnchainDB>::new().iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        let block_hash = block.header.block_hash();
+        stacks_blocks.push((
+            block_hash.clone(),
+            StacksBlockId::new(&tip.consensus_hash, &block_hash),
+        ));
+
+        preprocess_block(&mut chainstate, &sort_db, &tip, block);
+        coord.handle_new_stacks_block().unwrap();
+    }
+
+    // only b3 overtakes fork A
+    let reorgs = dispatcher.0.lock().unwrap();
+    assert_eq!(
+        *reorgs,
+        vec![StacksChainReorg {
+            old_tip: stacks_blocks[2].1.clone(),
+            old_tip_height: 3,
+            new_tip: stacks_blocks[5].1.clone(),
+            new_tip_height: 4,
+            common_ancestor: stacks_blocks[0].1.clone(),
+            common_ancestor_height: 1,
+            orphaned_blocks: vec![stacks_blocks[2].1.clone(), stacks_blocks[1].1.clone()],
+        }]
+    );
+
+    // moving along a fork is not a reorg
+    assert!(find_stacks_chain_reorg(
+        &coord.chain_state_db,
+        &stacks_blocks[3].1,
+        &stacks_blocks[5].1
+    )
+    .unwrap()
+    .is_none());
+
+    // but moving back is
+    let reorg = find_stacks_chain_reorg(
+        &coord.chain_state_db,
+        &stacks_blocks[5].1,
+        &stacks_blocks[3].1,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(reorg.common_ancestor, stacks_blocks[3].1);
+    assert_eq!(
+        reorg.orphaned_blocks,
+        vec![stacks_blocks[5].1.clone(), stacks_blocks[4].1.clone()]
+    );
+}
+
+#[test]
+fn test_max_fork_depth() {
+    let path = "/tmp/stacks-blockchain-max-fork-depth";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..5).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..5).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(&[path], &vrf_keys, &committers, None, None);
+
+    let mut coord = make_coordinator(path, None);
+
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+
+    // b0 <- a1 <- a2 <- a3, then a fork b0 <- b1 two blocks behind the tip
+    let parents = [None, Some(0), Some(1), Some(2), Some(0)];
+    let mut stacks_blocks: Vec<BlockHeaderHash> = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        chainstate.max_fork_depth = Some(1);
+        let (op, block) = match parents[ix] {
+            None => make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &BlockHeaderHash([0; 32]),
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            ),
+            Some(parent_ix) => make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &stacks_blocks[parent_ix],
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            ),
+        };
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            Vec::<BurnchainDB>::new().iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        let block_hash = block.header.block_hash();
+        stacks_blocks.push(block_hash.clone());
+
+        let ic = sort_db.index_conn();
+        let parent_consensus_hash = SortitionDB::get_block_snapshot_for_winning_stacks_block(
+            &ic,
+            &tip.sortition_id,
+            &block.header.parent_block,
+        )
+        .unwrap()
+        .unwrap()
+        .consensus_hash;
+        let accepted = chainstate
+            .preprocess_anchored_block(&ic, &tip.consensus_hash, &block, &parent_consensus_hash, 5)
+            .unwrap();
+
+        if ix < 4 {
+            // blocks on the tip are always stored
+            assert!(accepted);
+            coord.handle_new_stacks_block().unwrap();
+        } else {
+            // the fork block is more than one block behind the tip
+            assert!(!accepted);
+            assert!(!StacksChainState::has_staging_block(
+                chainstate.db(),
+                &tip.consensus_hash,
+                &block_hash
+            )
+            .unwrap());
+
+            // without the policy, it is stored as usual
+            chainstate.max_fork_depth = None;
+            assert!(chainstate
+                .preprocess_anchored_block(
+                    &ic,
+                    &tip.consensus_hash,
+                    &block,
+                    &parent_consensus_hash,
+                    5
+                )
+                .unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_diff_chainstates() {
+    let path = "/tmp/stacks-blockchain-diff-a";
+    let path_b = "/tmp/stacks-blockchain-diff-b";
+    let _r = std::fs::remove_dir_all(path);
+    let _r = std::fs::remove_dir_all(path_b);
+
+    let vrf_keys: Vec<_> = (0..5).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..5).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(&[path, path_b], &vrf_keys, &committers, None, None);
+
+    let mut coord = make_coordinator(path, None);
+    let mut coord_b = make_coordinator(path_b, None);
+
+    coord.handle_new_burnchain_block().unwrap();
+    coord_b.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+    let sort_db_b = get_sortition_db(path_b, None);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    let mut stacks_blocks = vec![];
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        let burnchain_b = get_burnchain_db(path_b, None);
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            [burnchain_b].iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+        coord_b.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        let block_hash = block.header.block_hash();
+        stacks_blocks.push(StacksBlockId::new(&tip.consensus_hash, &block_hash));
+
+        // b never gets the last block
+        if ix + 1 < vrf_keys.len() {
+            let tip_b = SortitionDB::get_canonical_burn_chain_tip(sort_db_b.conn()).unwrap();
+            assert_eq!(tip.consensus_hash, tip_b.consensus_hash);
+            preprocess_block(
+                &mut get_chainstate(path_b),
+                &sort_db_b,
+                &tip_b,
+                block.clone(),
+            );
+            coord_b.handle_new_stacks_block().unwrap();
+        }
+
+        preprocess_block(&mut chainstate, &sort_db, &tip, block);
+        coord.handle_new_stacks_block().unwrap();
+
+        parent = block_hash;
+    }
+
+    // b is one block behind a, but otherwise agrees with it
+    let diff = StacksChainState::diff_chainstates(
+        &mut get_chainstate(path),
+        &sort_db,
+        &mut get_chainstate(path_b),
+        &sort_db_b,
+        100,
+        100,
+    )
+    .unwrap();
+    let common_tip = diff.common_tip.unwrap().index_block_hash();
+    assert_eq!(common_tip, stacks_blocks[3]);
+    // 4 blocks, plus the boot header
+    assert_eq!(diff.blocks_compared, 5);
+    assert!(diff.accounts_compared > 0);
+    assert_eq!(
+        diff.divergences,
+        vec![ChainstateDivergence::CanonicalTip {
+            a_tip: stacks_blocks[4].clone(),
+            a_height: 5,
+            b_tip: stacks_blocks[3].clone(),
+            b_height: 4,
+        }]
+    );
+
+    // the comparison is symmetric
+    let diff = StacksChainState::diff_chainstates(
+        &mut get_chainstate(path_b),
+        &sort_db_b,
+        &mut get_chainstate(path),
+        &sort_db,
+        100,
+        100,
+    )
+    .unwrap();
+    assert_eq!(diff.common_tip.unwrap().index_block_hash(), common_tip);
+    assert_eq!(diff.divergences.len(), 1);
+
+    // a corrupted header row is reported
+    get_chainstate(path_b)
+        .db()
+        .execute(
+            "UPDATE block_headers SET burn_header_timestamp = 1 WHERE index_block_hash = ?1",
+            &[&stacks_blocks[1]],
+        )
+        .unwrap();
+    let diff = StacksChainState::diff_chainstates(
+        &mut get_chainstate(path),
+        &sort_db,
+        &mut get_chainstate(path_b),
+        &sort_db_b,
+        100,
+        100,
+    )
+    .unwrap();
+    assert_eq!(diff.divergences.len(), 2);
+    match diff.divergences[1] {
+        ChainstateDivergence::Header {
+            ref index_block_hash,
+            ref a,
+            ref b,
+        } => {
+            assert_eq!(index_block_hash, &stacks_blocks[1]);
+            assert_eq!(b.burn_header_timestamp, 1);
+            assert!(a.burn_header_timestamp != 1);
+        }
+        ref x => panic!("Unexpected divergence {:?}", x),
+    }
+
+    // limiting the walk limits the blocks compared
+    let diff = StacksChainState::diff_chainstates(
+        &mut get_chainstate(path),
+        &sort_db,
+        &mut get_chainstate(path_b),
+        &sort_db_b,
+        2,
+        0,
+    )
+    .unwrap();
+    assert_eq!(diff.blocks_compared, 2);
+    assert_eq!(diff.accounts_compared, 0);
+    assert_eq!(diff.divergences.len(), 1);
+}
+
+#[test]
+fn test_header_chain_light_sync() {
+    let path = "/tmp/stacks-blockchain-header-chain-full";
+    // a second set of states that will only see block headers
+    let path_light = "/tmp/stacks-blockchain-header-chain-light";
+    let _r = std::fs::remove_dir_all(path);
+    let _r = std::fs::remove_dir_all(path_light);
+
+    let vrf_keys: Vec<_> = (0..50).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..50).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(&[path, path_light], &vrf_keys, &committers, None, None);
+
+    let mut coord = make_coordinator(path, None);
+    let mut coord_light: ChainsCoordinator<NullEventDispatcher, (), OnChainRewardSetProvider> =
+        ChainsCoordinator::test_new_headers_only(
+            &get_burnchain(path_light, None),
+            path_light,
+            OnChainRewardSetProvider(),
+        );
+
+    coord.handle_new_burnchain_block().unwrap();
+    coord_light.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+    let sort_db_light = get_sortition_db(path_light, None);
+    let mut header_chain = HeaderChainDB::open(
+        &format!("{}/chainstate/headers.sqlite", path_light),
+        &mut get_chainstate(path_light),
+    )
+    .unwrap();
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        let burnchain_light = get_burnchain_db(path_light, None);
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            [burnchain_light].iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+        coord_light.handle_new_burnchain_block().unwrap();
+
+        // the light node asks for the reward sets of the anchor blocks it has accepted.
+        // nobody stacks in this test, so the full node's reward sets are all empty.
+        for anchor_block_id in header_chain.get_wanted_reward_sets().unwrap() {
+            header_chain
+                .store_reward_set(&anchor_block_id, &vec![])
+                .unwrap();
+        }
+        coord_light.handle_new_stacks_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        coord.handle_new_stacks_block().unwrap();
+
+        // the light node only gets the header
+        let tip_light = SortitionDB::get_canonical_burn_chain_tip(sort_db_light.conn()).unwrap();
+        assert_eq!(tip_light.winning_stacks_block_hash, block.block_hash());
+        assert!(header_chain
+            .preprocess_header(
+                &sort_db_light.index_conn(),
+                &tip_light.consensus_hash,
+                &block.header,
+                5
+            )
+            .unwrap());
+        coord_light.handle_new_stacks_block().unwrap();
+
+        parent = block.block_hash();
+    }
+
+    // both nodes agree on the chain tip and PoX history
+    let stacks_tip = SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap();
+    let stacks_tip_light =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db_light.conn()).unwrap();
+    assert_eq!(stacks_tip, stacks_tip_light);
+
+    let pox_id = sort_db.index_handle_at_tip().get_pox_id().unwrap();
+    let pox_id_light = sort_db_light.index_handle_at_tip().get_pox_id().unwrap();
+    assert_eq!(&pox_id.to_string(), "11111111111");
+    assert_eq!(pox_id.to_string(), pox_id_light.to_string());
+
+    let tip_id = StacksBlockId::new(&stacks_tip.0, &stacks_tip.1);
+    let header_chain_headers = header_chain.get_header_chain(&tip_id, 100).unwrap();
+    assert_eq!(header_chain_headers.len(), 50);
+    assert_eq!(header_chain_headers[0].index_block_hash(), tip_id);
+    assert_eq!(header_chain.get_block_height(&tip_id).unwrap(), Some(50));
+
+    // the light node can check a MARF proof from the full node against its header chain
+    let key = ClarityDatabase::make_key_for_data_var(
+        &boot_code_id("pox"),
+        "first-burnchain-block-height",
+    );
+    let (_, proof) = get_chainstate(path)
+        .with_clarity_marf(|marf| marf.get_with_proof(&tip_id, &key))
+        .unwrap()
+        .unwrap();
+    let first_block_height = get_burnchain(path, None).first_block_height;
+    let value = Value::UInt(first_block_height as u128).serialize();
+    assert!(header_chain
+        .verify_marf_proof(&tip_id, &key, &value, &proof)
+        .unwrap());
+    assert!(!header_chain
+        .verify_marf_proof(&tip_id, &key, &Value::UInt(12345).serialize(), &proof)
+        .unwrap());
+}
+
 #[test]
 fn test_sortition_with_reward_set() {
     let path = "/tmp/stacks-blockchain-simple-reward-set";
@@ -1467,7 +2297,9 @@ fn test_pox_btc_ops() {
                 )
                 .unwrap();
 
-            if ix > 2 && reward_cycle_count < 6 {
+            // the stacked uSTX unlock at burn height 25, and are released into the stacker's
+            // unlocked balance by the first block processed at that height
+            if ix > 2 && ix < 25 {
                 assert_eq!(
                     stacker_balance.amount_unlocked,
                     (balance as u128) - stacked_amt,
@@ -1480,6 +2312,8 @@ fn test_pox_btc_ops() {
                     balance as u128,
                     "No lock should be active"
                 );
+                assert_eq!(stacker_balance.amount_unlocked, balance as u128);
+                assert_eq!(stacker_balance.amount_locked, 0);
             }
         }
 
@@ -3142,3 +3976,101 @@ fn preprocess_block(
         )
         .unwrap();
 }
+
+fn make_attachment_instance(attachment_index: u32) -> AttachmentInstance {
+    AttachmentInstance {
+        content_hash: Hash160([attachment_index as u8; 20]),
+        attachment_index,
+        block_height: 1,
+        consensus_hash: ConsensusHash([0x01; 20]),
+        block_header_hash: BlockHeaderHash([0x02; 32]),
+        metadata: "".to_string(),
+        contract_id: QualifiedContractIdentifier::transient(),
+    }
+}
+
+#[test]
+fn test_coordinator_event_bus() {
+    let (rcvrs, channels) =
+        CoordinatorCommunication::instantiate_with_config(CoordinatorEventBusConfig {
+            attachments_capacity: 2,
+            attachments_drop_policy: DropPolicy::DropOldest,
+        });
+    let event_bus = rcvrs.event_bus();
+
+    // repeated notices coalesce, and burn blocks are handled before Stacks blocks
+    assert!(channels.announce_new_stacks_block());
+    assert!(channels.announce_new_stacks_block());
+    assert!(channels.announce_new_burn_block());
+    assert_eq!(
+        channels.get_event_queue_depth(CoordinatorEventKind::NewStacksBlock),
+        1
+    );
+    assert_eq!(
+        channels.get_events_dropped(CoordinatorEventKind::NewStacksBlock),
+        1
+    );
+    match rcvrs.wait_on() {
+        CoordinatorEvents::NEW_BURN_BLOCK => {}
+        _ => panic!("expected a new burn block first"),
+    }
+    match rcvrs.wait_on() {
+        CoordinatorEvents::NEW_STACKS_BLOCK => {}
+        _ => panic!("expected a new stacks block second"),
+    }
+    assert_eq!(
+        channels.get_event_queue_depth(CoordinatorEventKind::NewStacksBlock),
+        0
+    );
+
+    // a flood of attachment instances never blocks the coordinator, and doesn't get in the way
+    // of its notices
+    for i in 0..10 {
+        let accepted =
+            event_bus.post_new_attachments(vec![make_attachment_instance(i)].into_iter().collect());
+        assert_eq!(accepted, i < 2);
+    }
+    assert_eq!(
+        channels.get_event_queue_depth(CoordinatorEventKind::NewAttachments),
+        2
+    );
+    assert_eq!(
+        channels.get_events_dropped(CoordinatorEventKind::NewAttachments),
+        8
+    );
+    assert!(channels.announce_new_burn_block());
+    match rcvrs.wait_on() {
+        CoordinatorEvents::NEW_BURN_BLOCK => {}
+        _ => panic!("expected a new burn block"),
+    }
+
+    // the newest batches were kept, and are taken all at once
+    let attachments = channels.take_new_attachments();
+    let mut indexes: Vec<_> = attachments.iter().map(|a| a.attachment_index).collect();
+    indexes.sort();
+    assert_eq!(indexes, vec![8, 9]);
+    assert!(channels.take_new_attachments().is_empty());
+
+    // stopping takes priority over everything
+    assert!(channels.announce_new_stacks_block());
+    assert!(!channels.stop_chains_coordinator());
+    assert!(!channels.announce_new_burn_block());
+    match rcvrs.wait_on() {
+        CoordinatorEvents::STOP => {}
+        _ => panic!("expected a stop"),
+    }
+
+    // with the other policy, the oldest batches are kept
+    let (rcvrs, _) = CoordinatorCommunication::instantiate_with_config(CoordinatorEventBusConfig {
+        attachments_capacity: 1,
+        attachments_drop_policy: DropPolicy::DropNewest,
+    });
+    let event_bus = rcvrs.event_bus();
+    assert!(event_bus.post_new_attachments(vec![make_attachment_instance(1)].into_iter().collect()));
+    assert!(
+        !event_bus.post_new_attachments(vec![make_attachment_instance(2)].into_iter().collect())
+    );
+    let attachments = event_bus.take_new_attachments();
+    assert_eq!(attachments.len(), 1);
+    assert!(attachments.contains(&make_attachment_instance(1)));
+}
@@ -55,6 +55,29 @@ use chainstate::stacks::{
     C32_ADDRESS_VERSION_TESTNET_MULTISIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
 };
 
+/// What kind of account an address or principal names.  The same on every network: the burn
+/// address of one network is a burn address on all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddressKind {
+    #[serde(rename = "standard")]
+    Standard,
+    #[serde(rename = "contract")]
+    Contract,
+    /// The all-zeros address hash, whose keys no one has.  STX credited to it are destroyed.
+    #[serde(rename = "burn")]
+    Burn,
+}
+
+impl AddressKind {
+    pub fn of_principal(principal: &PrincipalData) -> AddressKind {
+        match principal {
+            PrincipalData::Standard(data) => StacksAddress::from(data.clone()).kind(),
+            // contracts published by the burn address (e.g. the boot contracts) can spend
+            PrincipalData::Contract(_) => AddressKind::Contract,
+        }
+    }
+}
+
 impl StacksMessageCodec for StacksAddress {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), net_error> {
         write_next(fd, &self.version)?;
@@ -112,6 +135,14 @@ impl StacksAddress {
         }
     }
 
+    pub fn kind(&self) -> AddressKind {
+        if self.is_burn() {
+            AddressKind::Burn
+        } else {
+            AddressKind::Standard
+        }
+    }
+
     pub fn burn_address(mainnet: bool) -> StacksAddress {
         StacksAddress {
             version: if mainnet {
@@ -279,6 +310,48 @@ mod test {
     use util::hash::*;
     use util::secp256k1::Secp256k1PublicKey as PubKey;
 
+    #[test]
+    fn tx_stacks_address_kind() {
+        for version in [
+            C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+            C32_ADDRESS_VERSION_MAINNET_MULTISIG,
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            C32_ADDRESS_VERSION_TESTNET_MULTISIG,
+        ]
+        .iter()
+        {
+            let burn_addr = StacksAddress::new(*version, Hash160([0u8; 20]));
+            assert_eq!(burn_addr.kind(), AddressKind::Burn);
+            assert_eq!(
+                AddressKind::of_principal(&burn_addr.to_account_principal()),
+                AddressKind::Burn
+            );
+
+            let addr = StacksAddress::new(*version, Hash160([0x01; 20]));
+            assert_eq!(addr.kind(), AddressKind::Standard);
+            assert_eq!(
+                AddressKind::of_principal(&addr.to_account_principal()),
+                AddressKind::Standard
+            );
+        }
+
+        assert_eq!(StacksAddress::burn_address(true).kind(), AddressKind::Burn);
+        assert_eq!(StacksAddress::burn_address(false).kind(), AddressKind::Burn);
+
+        assert_eq!(
+            AddressKind::of_principal(
+                &PrincipalData::parse("ST000000000000000000002AMW42H.pox").unwrap()
+            ),
+            AddressKind::Contract
+        );
+        assert_eq!(
+            AddressKind::of_principal(
+                &PrincipalData::parse("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR.foo").unwrap()
+            ),
+            AddressKind::Contract
+        );
+    }
+
     #[test]
     fn tx_stacks_address_codec() {
         let addr = StacksAddress {
@@ -408,7 +408,7 @@ impl StacksMessageCodec for StacksBlock {
         let mut coinbase_count = 0;
         for tx in txs.iter() {
             match tx.payload {
-                TransactionPayload::Coinbase(_) => {
+                TransactionPayload::Coinbase(..) => {
                     coinbase_count += 1;
                     if coinbase_count > 1 {
                         return Err(net_error::DeserializeError(
@@ -487,7 +487,7 @@ impl StacksBlock {
             return None;
         }
         match self.txs[0].payload {
-            TransactionPayload::Coinbase(_) => Some(self.txs[0].clone()),
+            TransactionPayload::Coinbase(..) => Some(self.txs[0].clone()),
             _ => None,
         }
     }
@@ -573,7 +573,7 @@ impl StacksBlock {
         let mut coinbase_index = 0;
         for (i, tx) in txs.iter().enumerate() {
             match tx.payload {
-                TransactionPayload::Coinbase(_) => {
+                TransactionPayload::Coinbase(..) => {
                     if !check_present {
                         warn!("Found unexpected coinbase tx {}", tx.txid());
                         return false;
@@ -1067,7 +1067,7 @@ mod test {
         let origin_auth = TransactionAuth::Standard(TransactionSpendingCondition::new_singlesig_p2pkh(StacksPublicKey::from_private(&privk)).unwrap());
         let mut tx_coinbase = StacksTransaction::new(TransactionVersion::Mainnet,
                                                      origin_auth.clone(),
-                                                     TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])));
+                                                     TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None));
 
         tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
 
@@ -1080,7 +1080,7 @@ mod test {
 
         for tx in all_txs.drain(..) {
             match tx.payload {
-                TransactionPayload::Coinbase(_) => {
+                TransactionPayload::Coinbase(..) => {
                     continue;
                 },
                 _ => {}
@@ -1201,7 +1201,7 @@ mod test {
 
         for tx in all_txs.iter() {
             match tx.payload {
-                TransactionPayload::Coinbase(_) => {
+                TransactionPayload::Coinbase(..) => {
                     continue;
                 }
                 _ => {}
@@ -1511,13 +1511,13 @@ mod test {
         let tx_coinbase = StacksTransaction::new(
             TransactionVersion::Testnet,
             origin_auth.clone(),
-            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
         );
 
         let tx_coinbase_2 = StacksTransaction::new(
             TransactionVersion::Testnet,
             origin_auth.clone(),
-            TransactionPayload::Coinbase(CoinbasePayload([1u8; 32])),
+            TransactionPayload::Coinbase(CoinbasePayload([1u8; 32]), None),
         );
 
         let mut tx_invalid_coinbase = tx_coinbase.clone();
@@ -1534,6 +1534,7 @@ mod test {
                 stx_address.into(),
                 123,
                 TokenTransferMemo([1u8; 34]),
+                None,
             ),
         );
 
@@ -1645,7 +1646,7 @@ mod test {
         let tx_coinbase = StacksTransaction::new(
             TransactionVersion::Testnet,
             origin_auth.clone(),
-            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
         );
 
         let mut tx_coinbase_offchain = tx_coinbase.clone();
@@ -1662,6 +1663,7 @@ mod test {
                 stx_address.into(),
                 123,
                 TokenTransferMemo([1u8; 34]),
+                None,
             ),
         );
 
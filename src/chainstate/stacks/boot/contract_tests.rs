@@ -240,6 +240,9 @@ impl HeadersDB for TestSimHeadersDB {
             }
         }
     }
+    fn get_consensus_hash_for_block(&self, _id_bhh: &StacksBlockId) -> Option<ConsensusHash> {
+        None
+    }
     fn get_miner_address(&self, _id_bhh: &StacksBlockId) -> Option<StacksAddress> {
         Some(MINER_ADDR.clone())
     }
@@ -38,10 +38,15 @@ use burnchains::Burnchain;
 
 use vm::clarity::ClarityConnection;
 use vm::contexts::ContractContext;
+use vm::costs::LimitedCostTracker;
 use vm::database::{NULL_BURN_STATE_DB, NULL_HEADER_DB};
 use vm::representations::ContractName;
+use vm::representations::SymbolicExpression;
 
-use util::hash::Hash160;
+use util::db::{query_row_columns, DBConn};
+use util::hash::{to_hex, Hash160};
+
+use rusqlite::types::ToSql;
 
 use std::boxed::Box;
 use std::cmp;
@@ -53,8 +58,10 @@ pub const STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR: &'static str = "ST0000000000000
 const BOOT_CODE_POX_BODY: &'static str = std::include_str!("pox.clar");
 const BOOT_CODE_POX_TESTNET_CONSTS: &'static str = std::include_str!("pox-testnet.clar");
 const BOOT_CODE_POX_MAINNET_CONSTS: &'static str = std::include_str!("pox-mainnet.clar");
+const BOOT_CODE_POX_2_BODY: &'static str = std::include_str!("pox-2.clar");
 const BOOT_CODE_LOCKUP: &'static str = std::include_str!("lockup.clar");
 pub const BOOT_CODE_COSTS: &'static str = std::include_str!("costs.clar");
+pub const BOOT_CODE_COSTS_2: &'static str = std::include_str!("costs-2.clar");
 const BOOT_CODE_BNS: &'static str = std::include_str!("bns.clar");
 
 lazy_static! {
@@ -76,7 +83,22 @@ lazy_static! {
         ("bns", &BOOT_CODE_BNS),
         ("costs", BOOT_CODE_COSTS)
     ];
+    static ref BOOT_CODE_POX_2_MAINNET: String =
+        format!("{}\n{}", BOOT_CODE_POX_MAINNET_CONSTS, BOOT_CODE_POX_2_BODY);
+    static ref BOOT_CODE_POX_2_TESTNET: String =
+        format!("{}\n{}", BOOT_CODE_POX_TESTNET_CONSTS, BOOT_CODE_POX_2_BODY);
+    /// Boot code that is deployed when the chain upgrades to Stacks 2.1
+    pub static ref STACKS_2_1_BOOT_CODE_MAINNET: [(&'static str, &'static str); 2] = [
+        ("pox-2", &BOOT_CODE_POX_2_MAINNET),
+        ("costs-2", BOOT_CODE_COSTS_2)
+    ];
+    pub static ref STACKS_2_1_BOOT_CODE_TESTNET: [(&'static str, &'static str); 2] = [
+        ("pox-2", &BOOT_CODE_POX_2_TESTNET),
+        ("costs-2", BOOT_CODE_COSTS_2)
+    ];
     pub static ref STACKS_BOOT_COST_CONTRACT: QualifiedContractIdentifier = boot_code_id("costs");
+    pub static ref STACKS_BOOT_COST_2_CONTRACT: QualifiedContractIdentifier =
+        boot_code_id("costs-2");
 }
 
 pub fn boot_code_addr() -> StacksAddress {
@@ -110,6 +132,37 @@ impl StacksAddress {
     }
 }
 
+/// A stacker's active delegation of the right to stack its STX, as recorded in the PoX contract's
+/// `delegation-state` map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoxDelegation {
+    pub stacker: PrincipalData,
+    pub amount_ustx: u128,
+    pub delegated_to: PrincipalData,
+    pub until_burn_ht: Option<u64>,
+    pub pox_addr: Option<StacksAddress>, // will be None if the delegate may choose any PoX address
+}
+
+/// A stacker's current lock-up, as recorded in the PoX contract's `stacking-state` map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoxStackingState {
+    pub stacker: PrincipalData,
+    pub amount_ustx: u128,
+    pub pox_addr: StacksAddress,
+    pub first_reward_cycle: u64,
+    pub lock_period: u64,
+    pub delegated_to: Option<PrincipalData>, // will be None if the stacker locked its own STX
+}
+
+/// uSTX that a delegate has stacked to a PoX address in a reward cycle, but not yet committed
+/// with `stack-aggregation-commit`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoxPartialStack {
+    pub pox_addr: StacksAddress,
+    pub reward_cycle: u64,
+    pub stacked_ustx: u128,
+}
+
 /// Extract a PoX address from its tuple representation
 fn tuple_to_pox_addr(tuple_data: TupleData) -> (AddressHashMode, Hash160) {
     let version_value = tuple_data
@@ -162,6 +215,27 @@ impl StacksChainState {
             / burnchain.pox_constants.reward_cycle_length as u64) as u128
     }
 
+    /// Get the PoX contracts in use as of the given block, newest first.  Once the chain has
+    /// upgraded to Stacks 2.1, new lock-ups are made with `pox-2`, but lock-ups made with the
+    /// original `pox` contract still count towards their reward cycles.
+    pub fn get_pox_contract_names(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+    ) -> Result<Vec<&'static str>, Error> {
+        let upgraded = self
+            .maybe_read_only_clarity_tx(&sortdb.index_conn(), stacks_block_id, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|db| db.is_stacks_2_1_active())
+            })
+            .ok_or(Error::NoSuchBlockError)?;
+
+        if upgraded {
+            Ok(vec!["pox-2", "pox"])
+        } else {
+            Ok(vec!["pox"])
+        }
+    }
+
     /// Determine the minimum amount of STX per reward address required to stack in the _next_
     /// reward cycle
     #[cfg(test)]
@@ -170,16 +244,17 @@ impl StacksChainState {
         sortdb: &SortitionDB,
         stacks_block_id: &StacksBlockId,
     ) -> Result<u128, Error> {
+        let pox_contract = self.get_pox_contract_names(sortdb, stacks_block_id)?[0];
         self.eval_boot_code_read_only(
             sortdb,
             stacks_block_id,
-            "pox",
+            pox_contract,
             &format!("(get-stacking-minimum)"),
         )
         .map(|value| value.expect_u128())
     }
 
-    /// Determine how many uSTX are stacked in a given reward cycle
+    /// Determine how many uSTX are stacked in a given reward cycle, across all PoX contracts
     #[cfg(test)]
     pub fn get_total_ustx_stacked(
         &mut self,
@@ -187,29 +262,387 @@ impl StacksChainState {
         stacks_block_id: &StacksBlockId,
         reward_cycle: u128,
     ) -> Result<u128, Error> {
-        self.eval_boot_code_read_only(
-            sortdb,
-            stacks_block_id,
-            "pox",
-            &format!("(get-total-ustx-stacked u{})", reward_cycle),
-        )
-        .map(|value| value.expect_u128())
+        let mut total_stacked = 0;
+        for pox_contract in self.get_pox_contract_names(sortdb, stacks_block_id)? {
+            total_stacked += self
+                .eval_boot_code_read_only(
+                    sortdb,
+                    stacks_block_id,
+                    pox_contract,
+                    &format!("(get-total-ustx-stacked u{})", reward_cycle),
+                )?
+                .expect_u128();
+        }
+        Ok(total_stacked)
     }
 
-    /// Is PoX active in the given reward cycle?
+    /// Is PoX active in the given reward cycle?  Each PoX contract counts its own rejection
+    /// votes, and PoX is only active if none of them rejected it.
     pub fn is_pox_active(
         &mut self,
         sortdb: &SortitionDB,
         stacks_block_id: &StacksBlockId,
         reward_cycle: u128,
     ) -> Result<bool, Error> {
-        self.eval_boot_code_read_only(
-            sortdb,
-            stacks_block_id,
-            "pox",
-            &format!("(is-pox-active u{})", reward_cycle),
-        )
-        .map(|value| value.expect_bool())
+        for pox_contract in self.get_pox_contract_names(sortdb, stacks_block_id)? {
+            let active = self
+                .eval_boot_code_read_only(
+                    sortdb,
+                    stacks_block_id,
+                    pox_contract,
+                    &format!("(is-pox-active u{})", reward_cycle),
+                )?
+                .expect_bool();
+            if !active {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Get every principal that has ever successfully delegated to `delegate`, in any fork, as
+    /// recorded by `ChainstateTx::log_pox_delegations()`.  Use `get_pox_delegation()` to find out
+    /// which of them are still delegating to it at a given chain tip.
+    pub fn get_pox_delegators(
+        conn: &DBConn,
+        delegate: &PrincipalData,
+    ) -> Result<Vec<PrincipalData>, Error> {
+        let qry =
+            "SELECT stacker FROM pox_delegations WHERE delegate = ?1 ORDER BY stacker".to_string();
+        let args: &[&dyn ToSql] = &[&delegate.to_string()];
+        query_row_columns::<PrincipalData, _>(conn, &qry, args, "stacker").map_err(Error::DBError)
+    }
+
+    /// Get the stacker's delegation as of the given block, if it has one that has not expired.
+    /// If it delegated with more than one PoX contract, the newest contract's delegation wins.
+    pub fn get_pox_delegation(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+        stacker: &PrincipalData,
+    ) -> Result<Option<PoxDelegation>, Error> {
+        let mut value_opt = None;
+        for pox_contract in self.get_pox_contract_names(sortdb, stacks_block_id)? {
+            value_opt = self
+                .eval_boot_code_read_only(
+                    sortdb,
+                    stacks_block_id,
+                    pox_contract,
+                    &format!("(get-check-delegation '{})", stacker),
+                )?
+                .expect_optional();
+            if value_opt.is_some() {
+                break;
+            }
+        }
+
+        let data = match value_opt {
+            Some(data) => data.expect_tuple(),
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let amount_ustx = data
+            .get("amount-ustx")
+            .expect("FATAL: no 'amount-ustx' in delegation-state")
+            .to_owned()
+            .expect_u128();
+        let delegated_to = data
+            .get("delegated-to")
+            .expect("FATAL: no 'delegated-to' in delegation-state")
+            .to_owned()
+            .expect_principal();
+        let until_burn_ht = data
+            .get("until-burn-ht")
+            .expect("FATAL: no 'until-burn-ht' in delegation-state")
+            .to_owned()
+            .expect_optional()
+            .map(|ht| ht.expect_u128() as u64);
+        let pox_addr = data
+            .get("pox-addr")
+            .expect("FATAL: no 'pox-addr' in delegation-state")
+            .to_owned()
+            .expect_optional()
+            .map(|addr| {
+                let (hash_mode, hash) = tuple_to_pox_addr(addr.expect_tuple());
+                let version = match self.mainnet {
+                    true => hash_mode.to_version_mainnet(),
+                    false => hash_mode.to_version_testnet(),
+                };
+                StacksAddress::new(version, hash)
+            });
+
+        Ok(Some(PoxDelegation {
+            stacker: stacker.clone(),
+            amount_ustx,
+            delegated_to,
+            until_burn_ht,
+            pox_addr,
+        }))
+    }
+
+    /// How many uSTX has `sender` stacked to `pox_addr` in the given reward cycle that it has not
+    /// yet committed with `stack-aggregation-commit`, across all PoX contracts?
+    pub fn get_partial_stacked_ustx(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+        sender: &PrincipalData,
+        pox_addr: &StacksAddress,
+        reward_cycle: u64,
+    ) -> Result<u128, Error> {
+        let mut partial_stacked = 0;
+        for pox_contract in self.get_pox_contract_names(sortdb, stacks_block_id)? {
+            partial_stacked += self.get_partial_stacked_ustx_in_contract(
+                sortdb,
+                stacks_block_id,
+                pox_contract,
+                sender,
+                pox_addr,
+                reward_cycle,
+            )?;
+        }
+        Ok(partial_stacked)
+    }
+
+    /// How many uSTX has `sender` stacked to `pox_addr` in the given reward cycle that it has not
+    /// yet committed with one PoX contract's `stack-aggregation-commit`?
+    fn get_partial_stacked_ustx_in_contract(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+        pox_contract: &str,
+        sender: &PrincipalData,
+        pox_addr: &StacksAddress,
+        reward_cycle: u64,
+    ) -> Result<u128, Error> {
+        let partial_stacked = self
+            .eval_boot_code_read_only(
+                sortdb,
+                stacks_block_id,
+                pox_contract,
+                &format!(
+                    "(map-get? partial-stacked-by-cycle {{ pox-addr: {{ version: 0x{:02x}, hashbytes: 0x{} }}, reward-cycle: u{}, sender: '{} }})",
+                    AddressHashMode::from_version(pox_addr.version) as u8,
+                    to_hex(pox_addr.bytes.as_bytes()),
+                    reward_cycle,
+                    sender
+                ),
+            )?
+            .expect_optional();
+
+        Ok(partial_stacked
+            .map(|data| {
+                data.expect_tuple()
+                    .get("stacked-amount")
+                    .expect("FATAL: no 'stacked-amount' in partial-stacked-by-cycle")
+                    .to_owned()
+                    .expect_u128()
+            })
+            .unwrap_or(0))
+    }
+
+    /// Get the stacker's current lock-up as of the given block, if it has one that has not
+    /// expired.  Only `pox-2` records who locked a delegated stacker's STX, so lock-ups made with
+    /// the original `pox` contract never have a `delegated_to`.
+    pub fn get_pox_stacking_state(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+        stacker: &PrincipalData,
+    ) -> Result<Option<PoxStackingState>, Error> {
+        let mut found = None;
+        for pox_contract in self.get_pox_contract_names(sortdb, stacks_block_id)? {
+            let value_opt = self
+                .eval_boot_code_read_only(
+                    sortdb,
+                    stacks_block_id,
+                    pox_contract,
+                    &format!("(get-stacker-info '{})", stacker),
+                )?
+                .expect_optional();
+            if let Some(data) = value_opt {
+                found = Some((pox_contract, data.expect_tuple()));
+                break;
+            }
+        }
+
+        let (pox_contract, data) = match found {
+            Some(found) => found,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let amount_ustx = data
+            .get("amount-ustx")
+            .expect("FATAL: no 'amount-ustx' in stacking-state")
+            .to_owned()
+            .expect_u128();
+        let (hash_mode, hash) = tuple_to_pox_addr(
+            data.get("pox-addr")
+                .expect("FATAL: no 'pox-addr' in stacking-state")
+                .to_owned()
+                .expect_tuple(),
+        );
+        let version = match self.mainnet {
+            true => hash_mode.to_version_mainnet(),
+            false => hash_mode.to_version_testnet(),
+        };
+        let first_reward_cycle = data
+            .get("first-reward-cycle")
+            .expect("FATAL: no 'first-reward-cycle' in stacking-state")
+            .to_owned()
+            .expect_u128() as u64;
+        let lock_period = data
+            .get("lock-period")
+            .expect("FATAL: no 'lock-period' in stacking-state")
+            .to_owned()
+            .expect_u128() as u64;
+        let delegated_to = if pox_contract == "pox-2" {
+            self.eval_boot_code_read_only(
+                sortdb,
+                stacks_block_id,
+                pox_contract,
+                &format!("(map-get? stacking-delegate {{ stacker: '{} }})", stacker),
+            )?
+            .expect_optional()
+            .map(|data| {
+                data.expect_tuple()
+                    .get("delegated-to")
+                    .expect("FATAL: no 'delegated-to' in stacking-delegate")
+                    .to_owned()
+                    .expect_principal()
+            })
+        } else {
+            None
+        };
+
+        Ok(Some(PoxStackingState {
+            stacker: stacker.clone(),
+            amount_ustx,
+            pox_addr: StacksAddress::new(version, hash),
+            first_reward_cycle,
+            lock_period,
+            delegated_to,
+        }))
+    }
+
+    /// What is the PoX contract's current reward cycle at the given block?
+    pub fn get_current_pox_reward_cycle(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+    ) -> Result<u64, Error> {
+        self.eval_boot_code_read_only(sortdb, stacks_block_id, "pox", "(current-pox-reward-cycle)")
+            .map(|value| value.expect_u128() as u64)
+    }
+
+    /// Find the uSTX that `delegate` has stacked on behalf of `stackers` but not yet committed,
+    /// in reward cycles that can still be committed to (i.e. that have not started yet), in
+    /// order by reward cycle.  The PoX addresses and reward cycles to check come from the
+    /// stackers' lock-ups, so only stackers that `delegate` locked are considered.
+    pub fn get_pox_partial_stacks(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+        delegate: &PrincipalData,
+        stackers: &[PrincipalData],
+    ) -> Result<Vec<PoxPartialStack>, Error> {
+        let current_reward_cycle = self.get_current_pox_reward_cycle(sortdb, stacks_block_id)?;
+
+        let mut candidates: Vec<(u64, StacksAddress)> = vec![];
+        for stacker in stackers.iter() {
+            let stacking_state =
+                match self.get_pox_stacking_state(sortdb, stacks_block_id, stacker)? {
+                    Some(state) if state.delegated_to.as_ref() == Some(delegate) => state,
+                    _ => {
+                        continue;
+                    }
+                };
+            let first_cycle = cmp::max(stacking_state.first_reward_cycle, current_reward_cycle + 1);
+            let end_cycle = stacking_state.first_reward_cycle + stacking_state.lock_period;
+            for reward_cycle in first_cycle..end_cycle {
+                let candidate = (reward_cycle, stacking_state.pox_addr.clone());
+                if !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates.sort_by(|a, b| (a.0, a.1.to_string()).cmp(&(b.0, b.1.to_string())));
+
+        let mut partial_stacks = vec![];
+        for (reward_cycle, pox_addr) in candidates.into_iter() {
+            let stacked_ustx = self.get_partial_stacked_ustx(
+                sortdb,
+                stacks_block_id,
+                delegate,
+                &pox_addr,
+                reward_cycle,
+            )?;
+            if stacked_ustx > 0 {
+                partial_stacks.push(PoxPartialStack {
+                    pox_addr,
+                    reward_cycle,
+                    stacked_ustx,
+                });
+            }
+        }
+        Ok(partial_stacks)
+    }
+
+    /// Evaluate `sender` calling `stack-aggregation-commit` on `pox_addr` and `reward_cycle` at
+    /// the given block, and return the call's result.  Nothing is written to the chainstate.
+    /// The call goes to the newest PoX contract in which `sender` has uSTX to commit, or to the
+    /// newest PoX contract if there are none.
+    pub fn simulate_stack_aggregation_commit(
+        &mut self,
+        sortdb: &SortitionDB,
+        stacks_block_id: &StacksBlockId,
+        sender: &PrincipalData,
+        pox_addr: &StacksAddress,
+        reward_cycle: u64,
+    ) -> Result<Value, Error> {
+        let pox_contracts = self.get_pox_contract_names(sortdb, stacks_block_id)?;
+        let mut pox_contract = pox_contracts[0];
+        for candidate in pox_contracts.into_iter() {
+            let partial_stacked = self.get_partial_stacked_ustx_in_contract(
+                sortdb,
+                stacks_block_id,
+                candidate,
+                sender,
+                pox_addr,
+                reward_cycle,
+            )?;
+            if partial_stacked > 0 {
+                pox_contract = candidate;
+                break;
+            }
+        }
+
+        let contract_identifier = boot_code_id(pox_contract);
+        let args = vec![
+            SymbolicExpression::atom_value(Value::Tuple(pox_addr.as_clarity_tuple())),
+            SymbolicExpression::atom_value(Value::UInt(reward_cycle as u128)),
+        ];
+
+        self.maybe_read_only_clarity_tx(&sortdb.index_conn(), stacks_block_id, |clarity_tx| {
+            clarity_tx.with_readonly_clarity_env(
+                sender.clone(),
+                LimitedCostTracker::new_free(),
+                |env| {
+                    env.execute_contract(
+                        &contract_identifier,
+                        "stack-aggregation-commit",
+                        &args,
+                        false,
+                    )
+                },
+            )
+        })
+        .ok_or(Error::NoSuchBlockError)?
+        .map_err(|e| Error::ClarityError(e.into()))
     }
 
     /// Given a threshold and set of registered addresses, return a reward set where
@@ -297,19 +730,40 @@ impl StacksChainState {
             return Ok(vec![]);
         }
 
+        let mut ret = vec![];
+        for pox_contract in self.get_pox_contract_names(sortdb, block_id)? {
+            ret.append(&mut self.get_reward_addresses_in_contract(
+                sortdb,
+                block_id,
+                pox_contract,
+                reward_cycle,
+            )?);
+        }
+
+        Ok(ret)
+    }
+
+    /// Get the PoX addresses registered in one PoX contract for the given reward cycle
+    fn get_reward_addresses_in_contract(
+        &mut self,
+        sortdb: &SortitionDB,
+        block_id: &StacksBlockId,
+        pox_contract: &str,
+        reward_cycle: u128,
+    ) -> Result<Vec<(StacksAddress, u128)>, Error> {
         // how many in this cycle?
         let num_addrs = self
             .eval_boot_code_read_only(
                 sortdb,
                 block_id,
-                "pox",
+                pox_contract,
                 &format!("(get-reward-set-size u{})", reward_cycle),
             )?
             .expect_u128();
 
         debug!(
-            "At block {:?} (reward cycle {}): {} PoX reward addresses",
-            block_id, reward_cycle, num_addrs
+            "At block {:?} (reward cycle {}): {} PoX reward addresses in {}",
+            block_id, reward_cycle, num_addrs, pox_contract
         );
 
         let mut ret = vec![];
@@ -320,7 +774,7 @@ impl StacksChainState {
                 .eval_boot_code_read_only(
                     sortdb,
                     block_id,
-                    "pox",
+                    pox_contract,
                     &format!("(get-reward-set-pox-address u{} u{})", reward_cycle, i),
                 )?
                 .expect_optional()
@@ -382,6 +836,7 @@ pub mod test {
 
     use core::*;
     use vm::contracts::Contract;
+    use vm::database::STXLock;
     use vm::types::*;
 
     use std::collections::HashMap;
@@ -522,6 +977,20 @@ pub mod test {
         test_name: &str,
         port: u16,
     ) -> (TestPeer<'a>, Vec<StacksPrivateKey>) {
+        instantiate_pox_peer_with_locks(burnchain, test_name, port, |_| vec![])
+    }
+
+    /// Instantiate a PoX peer whose accounts start out with the given (address, uSTX, unlock burn
+    /// height) locks, computed from the accounts' addresses
+    fn instantiate_pox_peer_with_locks<'a, F>(
+        burnchain: &Burnchain,
+        test_name: &str,
+        port: u16,
+        make_locks: F,
+    ) -> (TestPeer<'a>, Vec<StacksPrivateKey>)
+    where
+        F: FnOnce(&[StacksAddress]) -> Vec<(PrincipalData, u64, u64)>,
+    {
         let mut peer_config = TestPeerConfig::new(test_name, port, port + 1);
         peer_config.burnchain = burnchain.clone();
         peer_config.setup_code = format!(
@@ -562,6 +1031,7 @@ pub mod test {
             .collect();
 
         peer_config.initial_balances = balances;
+        peer_config.initial_locks = make_locks(&addrs);
         let peer = TestPeer::new(peer_config);
 
         (peer, keys.to_vec())
@@ -636,10 +1106,18 @@ pub mod test {
     fn get_stacker_info(
         peer: &mut TestPeer,
         addr: &PrincipalData,
+    ) -> Option<(u128, (AddressHashMode, Hash160), u128, u128)> {
+        get_stacker_info_in(peer, "pox", addr)
+    }
+
+    fn get_stacker_info_in(
+        peer: &mut TestPeer,
+        pox_contract: &str,
+        addr: &PrincipalData,
     ) -> Option<(u128, (AddressHashMode, Hash160), u128, u128)> {
         let value_opt = eval_at_tip(
             peer,
-            "pox",
+            pox_contract,
             &format!("(get-stacker-info '{})", addr.to_string()),
         );
         let data = if let Some(d) = value_opt.expect_optional() {
@@ -744,6 +1222,28 @@ pub mod test {
         )
     }
 
+    fn make_pox_2_lockup(
+        key: &StacksPrivateKey,
+        nonce: u64,
+        amount: u128,
+        addr_version: AddressHashMode,
+        addr_bytes: Hash160,
+        lock_period: u128,
+        burn_ht: u64,
+    ) -> StacksTransaction {
+        make_pox_2_contract_call(
+            key,
+            nonce,
+            "stack-stx",
+            vec![
+                Value::UInt(amount),
+                make_pox_addr(addr_version, addr_bytes),
+                Value::UInt(burn_ht as u128),
+                Value::UInt(lock_period),
+            ],
+        )
+    }
+
     fn make_tx(
         key: &StacksPrivateKey,
         nonce: u64,
@@ -776,6 +1276,49 @@ pub mod test {
         make_tx(key, nonce, 0, payload)
     }
 
+    fn make_pox_2_contract_call(
+        key: &StacksPrivateKey,
+        nonce: u64,
+        function_name: &str,
+        args: Vec<Value>,
+    ) -> StacksTransaction {
+        let payload =
+            TransactionPayload::new_contract_call(boot_code_addr(), "pox-2", function_name, args)
+                .unwrap();
+
+        make_tx(key, nonce, 0, payload)
+    }
+
+    /// Make a call to pox-2 whose fee is paid by a sponsor, so that the origin account's balance
+    /// is not touched before the contract call
+    fn make_sponsored_pox_2_contract_call(
+        key: &StacksPrivateKey,
+        nonce: u64,
+        sponsor: &StacksPrivateKey,
+        sponsor_nonce: u64,
+        function_name: &str,
+        args: Vec<Value>,
+    ) -> StacksTransaction {
+        let payload =
+            TransactionPayload::new_contract_call(boot_code_addr(), "pox-2", function_name, args)
+                .unwrap();
+        let auth = TransactionAuth::from_p2pkh(key)
+            .unwrap()
+            .into_sponsored(TransactionAuth::from_p2pkh(sponsor).unwrap())
+            .unwrap();
+        let mut tx = StacksTransaction::new(TransactionVersion::Testnet, auth, payload);
+        tx.chain_id = 0x80000000;
+        tx.auth.set_origin_nonce(nonce);
+        tx.set_sponsor_nonce(sponsor_nonce).unwrap();
+        tx.set_post_condition_mode(TransactionPostConditionMode::Allow);
+        tx.set_fee_rate(0);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx);
+        tx_signer.sign_origin(key).unwrap();
+        tx_signer.sign_sponsor(sponsor).unwrap();
+        tx_signer.get_tx().unwrap()
+    }
+
     // make a stream of invalid pox-lockup transactions
     fn make_invalid_pox_lockups(key: &StacksPrivateKey, mut nonce: u64) -> Vec<StacksTransaction> {
         let mut ret = vec![];
@@ -860,7 +1403,8 @@ pub mod test {
         dest: PrincipalData,
         amount: u64,
     ) -> StacksTransaction {
-        let payload = TransactionPayload::TokenTransfer(dest, amount, TokenTransferMemo([0u8; 34]));
+        let payload =
+            TransactionPayload::TokenTransfer(dest, amount, TokenTransferMemo([0u8; 34]), None);
         make_tx(key, nonce, fee_rate, payload)
     }
 
@@ -1175,6 +1719,26 @@ pub mod test {
 
             let (burn_ht, _, _) = peer.next_burnchain_block(burn_ops.clone());
             peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            // the block's unlocks are recorded as mint events on its coinbase
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+            let block_id = StacksBlockId::new(&tip.consensus_hash, &stacks_block.block_hash());
+            let receipts =
+                StacksChainState::get_transaction_receipts(peer.chainstate().db(), &block_id)
+                    .unwrap();
+            let num_unlocks = receipts[0]
+                .events
+                .iter()
+                .filter(|event| event["type"] == "stx_mint_event")
+                .count();
+            let expected_unlocks = match tenure_id {
+                0 | 3 => 2,
+                1 | 2 | 4 | 5 | 6 => 1,
+                _ => 0,
+            };
+            assert_eq!(num_unlocks, expected_unlocks);
         }
     }
 
@@ -1357,56 +1921,1072 @@ pub mod test {
                 },
             );
 
-            peer.next_burnchain_block(burn_ops.clone());
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            expected_liquid_ustx -= 1;
+
+            let liquid_ustx = get_liquid_ustx(&mut peer);
+            assert_eq!(liquid_ustx, expected_liquid_ustx);
+
+            if tenure_id >= MINER_REWARD_MATURITY as usize {
+                let block_reward = 1_000 * MICROSTACKS_PER_STACKS as u128;
+                let expected_bonus = (missed_initial_blocks as u128) * block_reward
+                    / (INITIAL_MINING_BONUS_WINDOW as u128);
+                // add mature coinbases
+                expected_liquid_ustx += block_reward + expected_bonus;
+            }
+        }
+    }
+
+    fn get_par_burn_block_height(state: &mut StacksChainState, block_id: &StacksBlockId) -> u64 {
+        let parent_block_id = StacksChainState::get_parent_block_id(state.db(), block_id)
+            .unwrap()
+            .unwrap();
+
+        let parent_header_info =
+            StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                state.db(),
+                &parent_block_id,
+            )
+            .unwrap()
+            .unwrap();
+
+        parent_header_info.burn_header_height as u64
+    }
+
+    #[test]
+    fn test_pox_lockup_single_tx_sender() {
+        let mut burnchain = Burnchain::default_unittest(0, &BurnchainHeaderHash::zero());
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+
+        let (mut peer, mut keys) =
+            instantiate_pox_peer(&burnchain, "test-pox-lockup-single-tx-sender", 6002);
+
+        let num_blocks = 10;
+
+        let alice = keys.pop().unwrap();
+        let bob = keys.pop().unwrap();
+        let charlie = keys.pop().unwrap();
+
+        let mut alice_reward_cycle = 0;
+
+        for tenure_id in 0..num_blocks {
+            let microblock_privkey = StacksPrivateKey::new();
+            let microblock_pubkeyhash =
+                Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut block_txs = vec![coinbase_tx];
+
+                    if tenure_id == 1 {
+                        // Alice locks up exactly 25% of the liquid STX supply, so this should succeed.
+                        let alice_lockup = make_pox_lockup(
+                            &alice,
+                            0,
+                            1024 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&alice).bytes,
+                            12,
+                            tip.block_height,
+                        );
+                        block_txs.push(alice_lockup);
+                    }
+
+                    let block_builder = StacksBlockBuilder::make_block_builder(
+                        &parent_tip,
+                        vrf_proof,
+                        tip.total_burn,
+                        microblock_pubkeyhash,
+                    )
+                    .unwrap();
+                    let (anchored_block, _size, _cost) =
+                        StacksBlockBuilder::make_anchored_block_from_txs(
+                            block_builder,
+                            chainstate,
+                            &sortdb.index_conn(),
+                            block_txs,
+                        )
+                        .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops);
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let total_liquid_ustx = get_liquid_ustx(&mut peer);
+            let tip_index_block = StacksBlockHeader::make_index_block_hash(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+            );
+
+            if tenure_id <= 1 {
+                if tenure_id < 1 {
+                    // Alice has not locked up STX
+                    let alice_balance = get_balance(&mut peer, &key_to_stacks_addr(&alice).into());
+                    assert_eq!(alice_balance, 1024 * 1000000);
+
+                    let alice_account = get_account(&mut peer, &key_to_stacks_addr(&alice).into());
+                    assert_eq!(alice_account.stx_balance.amount_unlocked, 1024 * 1000000);
+                    assert_eq!(alice_account.stx_balance.amount_locked, 0);
+                    assert_eq!(alice_account.stx_balance.unlock_height, 0);
+                }
+                let min_ustx = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                    chainstate.get_stacking_minimum(sortdb, &tip_index_block)
+                })
+                .unwrap();
+                assert_eq!(min_ustx, total_liquid_ustx / 480);
+
+                // no reward addresses
+                let reward_addrs = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                    get_reward_addresses_with_par_tip(
+                        chainstate,
+                        &burnchain,
+                        sortdb,
+                        &tip_index_block,
+                    )
+                })
+                .unwrap();
+                assert_eq!(reward_addrs.len(), 0);
+
+                // record the first reward cycle when Alice's tokens get stacked
+                let tip_burn_block_height =
+                    get_par_burn_block_height(peer.chainstate(), &tip_index_block);
+                alice_reward_cycle = 1 + peer
+                    .chainstate()
+                    .get_reward_cycle(&burnchain, tip_burn_block_height);
+                let cur_reward_cycle = peer
+                    .chainstate()
+                    .get_reward_cycle(&burnchain, tip_burn_block_height);
+
+                eprintln!(
+                    "\nalice reward cycle: {}\ncur reward cycle: {}\n",
+                    alice_reward_cycle, cur_reward_cycle
+                );
+            } else {
+                // Alice's address is locked as of the next reward cycle
+                let tip_burn_block_height =
+                    get_par_burn_block_height(peer.chainstate(), &tip_index_block);
+                let cur_reward_cycle = peer
+                    .chainstate()
+                    .get_reward_cycle(&burnchain, tip_burn_block_height);
+
+                // Alice has locked up STX no matter what
+                let alice_balance = get_balance(&mut peer, &key_to_stacks_addr(&alice).into());
+                assert_eq!(alice_balance, 0);
+
+                let min_ustx = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                    chainstate.get_stacking_minimum(sortdb, &tip_index_block)
+                })
+                .unwrap();
+                let reward_addrs = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                    get_reward_addresses_with_par_tip(
+                        chainstate,
+                        &burnchain,
+                        sortdb,
+                        &tip_index_block,
+                    )
+                })
+                .unwrap();
+                let total_stacked = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                    chainstate.get_total_ustx_stacked(sortdb, &tip_index_block, cur_reward_cycle)
+                })
+                .unwrap();
+
+                eprintln!("\ntenure: {}\nreward cycle: {}\nmin-uSTX: {}\naddrs: {:?}\ntotal_liquid_ustx: {}\ntotal-stacked: {}\n", tenure_id, cur_reward_cycle, min_ustx, &reward_addrs, total_liquid_ustx, total_stacked);
+
+                if cur_reward_cycle >= alice_reward_cycle {
+                    // this will grow as more miner rewards are unlocked, so be wary
+                    if tenure_id >= (MINER_REWARD_MATURITY + 1) as usize {
+                        // miner rewards increased liquid supply, so less than 25% is locked.
+                        // minimum participation decreases.
+                        assert!(total_liquid_ustx > 4 * 1024 * 1000000);
+                        assert_eq!(min_ustx, total_liquid_ustx / 480);
+                    } else {
+                        // still at 25% or more locked
+                        assert!(total_liquid_ustx <= 4 * 1024 * 1000000);
+                    }
+
+                    let (amount_ustx, pox_addr, lock_period, first_reward_cycle) =
+                        get_stacker_info(&mut peer, &key_to_stacks_addr(&alice).into()).unwrap();
+                    eprintln!("\nAlice: {} uSTX stacked for {} cycle(s); addr is {:?}; first reward cycle is {}\n", amount_ustx, lock_period, &pox_addr, first_reward_cycle);
+
+                    // one reward address, and it's Alice's
+                    // either way, there's a single reward address
+                    assert_eq!(reward_addrs.len(), 1);
+                    assert_eq!(
+                        (reward_addrs[0].0).version,
+                        AddressHashMode::SerializeP2PKH.to_version_testnet()
+                    );
+                    assert_eq!((reward_addrs[0].0).bytes, key_to_stacks_addr(&alice).bytes);
+                    assert_eq!(reward_addrs[0].1, 1024 * 1000000);
+
+                    // Lock-up is consistent with stacker state
+                    let alice_account = get_account(&mut peer, &key_to_stacks_addr(&alice).into());
+                    assert_eq!(alice_account.stx_balance.amount_unlocked, 0);
+                    assert_eq!(alice_account.stx_balance.amount_locked, 1024 * 1000000);
+                    assert_eq!(
+                        alice_account.stx_balance.unlock_height as u128,
+                        (first_reward_cycle + lock_period)
+                            * (burnchain.pox_constants.reward_cycle_length as u128)
+                            + (burnchain.first_block_height as u128)
+                    );
+                } else {
+                    // no reward addresses
+                    assert_eq!(reward_addrs.len(), 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pox_lockup_with_genesis_lock() {
+        let mut burnchain = Burnchain::default_unittest(0, &BurnchainHeaderHash::zero());
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+
+        // Alice's STX are half vested at genesis
+        let vesting_height = 1000;
+        let (mut peer, mut keys) = instantiate_pox_peer_with_locks(
+            &burnchain,
+            "test-pox-lockup-with-genesis-lock",
+            6032,
+            |addrs| vec![(addrs[3].clone().into(), 512 * 1000000, vesting_height)],
+        );
+
+        let num_blocks = 15;
+
+        let alice = keys.pop().unwrap();
+        let alice_principal: PrincipalData = key_to_stacks_addr(&alice).into();
+
+        let mut alice_unlock_height = 0;
+        let mut alice_pox_unlocked = false;
+
+        for tenure_id in 0..num_blocks {
+            let microblock_privkey = StacksPrivateKey::new();
+            let microblock_pubkeyhash =
+                Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut block_txs = vec![coinbase_tx];
+
+                    if tenure_id == 1 {
+                        // Alice cannot stack her unvested STX...
+                        let alice_too_much = make_pox_lockup(
+                            &alice,
+                            0,
+                            768 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&alice).bytes,
+                            1,
+                            tip.block_height,
+                        );
+                        // ...but she can stack the rest of them
+                        let alice_lockup = make_pox_lockup(
+                            &alice,
+                            1,
+                            512 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&alice).bytes,
+                            1,
+                            tip.block_height,
+                        );
+                        block_txs.push(alice_too_much);
+                        block_txs.push(alice_lockup);
+                    }
+
+                    let block_builder = StacksBlockBuilder::make_block_builder(
+                        &parent_tip,
+                        vrf_proof,
+                        tip.total_burn,
+                        microblock_pubkeyhash,
+                    )
+                    .unwrap();
+                    let (anchored_block, _size, _cost) =
+                        StacksBlockBuilder::make_anchored_block_from_txs(
+                            block_builder,
+                            chainstate,
+                            &sortdb.index_conn(),
+                            block_txs,
+                        )
+                        .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops);
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let tip_index_block = StacksBlockHeader::make_index_block_hash(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+            );
+            let tip_burn_block_height =
+                get_par_burn_block_height(peer.chainstate(), &tip_index_block);
+
+            let alice_account = get_account(&mut peer, &alice_principal);
+            let alice_balance = &alice_account.stx_balance;
+
+            // the vesting lock is untouched by stacking
+            assert_eq!(
+                alice_balance.get_independent_locks_at_burn_block(tip_burn_block_height),
+                vec![STXLock {
+                    amount: 512 * 1000000,
+                    unlock_height: vesting_height,
+                }]
+            );
+            assert_eq!(alice_balance.get_total_balance(), 1024 * 1000000);
+
+            if tenure_id == 0 {
+                assert_eq!(alice_balance.amount_unlocked, 512 * 1000000);
+                assert_eq!(alice_balance.amount_locked, 0);
+                assert!(get_stacker_info(&mut peer, &alice_principal).is_none());
+            }
+            if tenure_id == 1 {
+                let (amount_ustx, _, lock_period, _) =
+                    get_stacker_info(&mut peer, &alice_principal).unwrap();
+                assert_eq!(amount_ustx, 512 * 1000000);
+                assert_eq!(lock_period, 1);
+
+                assert_eq!(alice_balance.amount_unlocked, 0);
+                assert_eq!(alice_balance.amount_locked, 512 * 1000000);
+                alice_unlock_height = alice_balance.unlock_height;
+            }
+            if tenure_id >= 1 {
+                if tip_burn_block_height < alice_unlock_height {
+                    // both locks hold
+                    assert_eq!(
+                        alice_balance.get_available_balance_at_burn_block(tip_burn_block_height),
+                        0
+                    );
+                    assert_eq!(
+                        alice_balance.get_total_locked_balance_at_burn_block(tip_burn_block_height),
+                        1024 * 1000000
+                    );
+                    assert!(!alice_balance.can_transfer_at_burn_block(1, tip_burn_block_height));
+                } else {
+                    // only the vesting lock holds, and the expired PoX lock was released into
+                    // Alice's unlocked balance as soon as the chain reached its unlock height
+                    alice_pox_unlocked = true;
+                    assert_eq!(alice_balance.amount_unlocked, 512 * 1000000);
+                    assert_eq!(alice_balance.amount_locked, 0);
+                    assert_eq!(alice_balance.unlock_height, 0);
+                    assert_eq!(
+                        alice_balance.get_available_balance_at_burn_block(tip_burn_block_height),
+                        512 * 1000000
+                    );
+                    assert_eq!(
+                        alice_balance.get_locked_balance_at_burn_block(tip_burn_block_height),
+                        (0, 0)
+                    );
+                    assert!(alice_balance
+                        .can_transfer_at_burn_block(512 * 1000000, tip_burn_block_height));
+                    assert!(!alice_balance
+                        .can_transfer_at_burn_block(512 * 1000000 + 1, tip_burn_block_height));
+                }
+            }
+        }
+
+        // Alice's PoX lock expired during the test
+        assert!(alice_pox_unlocked);
+    }
+
+    #[test]
+    fn test_pox_lockup_extend_increase() {
+        let mut burnchain = Burnchain::default_unittest(0, &BurnchainHeaderHash::zero());
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+
+        let (mut peer, mut keys) =
+            instantiate_pox_peer(&burnchain, "test-pox-lockup-extend-increase", 6030);
+
+        let num_blocks = 4;
+
+        let alice = keys.pop().unwrap();
+        let bob = keys.pop().unwrap();
+
+        let mut alice_reward_cycle = 0;
+
+        for tenure_id in 0..num_blocks {
+            let microblock_privkey = StacksPrivateKey::new();
+            let microblock_pubkeyhash =
+                Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut block_txs = vec![coinbase_tx];
+
+                    if tenure_id == 1 {
+                        // Alice locks up half of her STX for one cycle
+                        let alice_lockup = make_pox_2_lockup(
+                            &alice,
+                            0,
+                            512 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&alice).bytes,
+                            1,
+                            tip.block_height,
+                        );
+                        block_txs.push(alice_lockup);
+
+                        // Bob has nothing locked, so he can neither extend nor increase
+                        let bob_extend =
+                            make_pox_2_contract_call(&bob, 0, "stack-extend", vec![Value::UInt(2)]);
+                        let bob_increase = make_pox_2_contract_call(
+                            &bob,
+                            1,
+                            "stack-increase",
+                            vec![Value::UInt(256 * 1000000)],
+                        );
+                        block_txs.push(bob_extend);
+                        block_txs.push(bob_increase);
+                    }
+                    if tenure_id == 2 {
+                        // Alice locks up another quarter of her STX, and stays locked for two
+                        // more cycles, all without unlocking
+                        let alice_increase = make_pox_2_contract_call(
+                            &alice,
+                            1,
+                            "stack-increase",
+                            vec![Value::UInt(256 * 1000000)],
+                        );
+                        let alice_extend = make_pox_2_contract_call(
+                            &alice,
+                            2,
+                            "stack-extend",
+                            vec![Value::UInt(2)],
+                        );
+                        block_txs.push(alice_increase);
+                        block_txs.push(alice_extend);
+                    }
+
+                    let block_builder = StacksBlockBuilder::make_block_builder(
+                        &parent_tip,
+                        vrf_proof,
+                        tip.total_burn,
+                        microblock_pubkeyhash,
+                    )
+                    .unwrap();
+                    let (anchored_block, _size, _cost) =
+                        StacksBlockBuilder::make_anchored_block_from_txs(
+                            block_builder,
+                            chainstate,
+                            &sortdb.index_conn(),
+                            block_txs,
+                        )
+                        .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops);
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let tip_index_block = StacksBlockHeader::make_index_block_hash(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+            );
+            let tip_burn_block_height =
+                get_par_burn_block_height(peer.chainstate(), &tip_index_block);
+
+            if tenure_id == 1 {
+                alice_reward_cycle = 1 + peer
+                    .chainstate()
+                    .get_reward_cycle(&burnchain, tip_burn_block_height);
+
+                let alice_account = get_account(&mut peer, &key_to_stacks_addr(&alice).into());
+                assert_eq!(alice_account.stx_balance.amount_unlocked, 512 * 1000000);
+                assert_eq!(alice_account.stx_balance.amount_locked, 512 * 1000000);
+                assert_eq!(
+                    alice_account.stx_balance.unlock_height as u128,
+                    (alice_reward_cycle + 1)
+                        * (burnchain.pox_constants.reward_cycle_length as u128)
+                        + (burnchain.first_block_height as u128)
+                );
+
+                let bob_account = get_account(&mut peer, &key_to_stacks_addr(&bob).into());
+                assert_eq!(bob_account.stx_balance.amount_unlocked, 1024 * 1000000);
+                assert_eq!(bob_account.stx_balance.amount_locked, 0);
+                assert!(
+                    get_stacker_info_in(&mut peer, "pox-2", &key_to_stacks_addr(&bob).into())
+                        .is_none()
+                );
+            }
+            if tenure_id >= 2 {
+                let (amount_ustx, _, lock_period, first_reward_cycle) =
+                    get_stacker_info_in(&mut peer, "pox-2", &key_to_stacks_addr(&alice).into())
+                        .unwrap();
+                assert_eq!(amount_ustx, 768 * 1000000);
+                assert_eq!(lock_period, 3);
+                assert_eq!(first_reward_cycle, alice_reward_cycle);
+
+                let alice_account = get_account(&mut peer, &key_to_stacks_addr(&alice).into());
+                assert_eq!(alice_account.stx_balance.amount_unlocked, 256 * 1000000);
+                assert_eq!(alice_account.stx_balance.amount_locked, 768 * 1000000);
+                assert_eq!(
+                    alice_account.stx_balance.unlock_height as u128,
+                    (alice_reward_cycle + 3)
+                        * (burnchain.pox_constants.reward_cycle_length as u128)
+                        + (burnchain.first_block_height as u128)
+                );
+
+                // all of Alice's uSTX count towards each of her reward cycles, and no others
+                for (cycle, expected_ustx) in [
+                    (alice_reward_cycle, 768 * 1000000),
+                    (alice_reward_cycle + 1, 768 * 1000000),
+                    (alice_reward_cycle + 2, 768 * 1000000),
+                    (alice_reward_cycle + 3, 0),
+                ]
+                .iter()
+                {
+                    let total_stacked = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                        chainstate.get_total_ustx_stacked(sortdb, &tip_index_block, *cycle)
+                    })
+                    .unwrap();
+                    assert_eq!(total_stacked, *expected_ustx);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pox_lockup_increase_with_expired_lock() {
+        let mut burnchain = Burnchain::default_unittest(0, &BurnchainHeaderHash::zero());
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+
+        // Alice's other half of her STX vests while her PoX lock is still active
+        let vesting_height = 30;
+        let (mut peer, mut keys) = instantiate_pox_peer_with_locks(
+            &burnchain,
+            "test-pox-lockup-increase-with-expired-lock",
+            6034,
+            |addrs| vec![(addrs[3].clone().into(), 512 * 1000000, vesting_height)],
+        );
+
+        let num_blocks = 10;
+
+        let alice = keys.pop().unwrap();
+        let bob = keys.pop().unwrap();
+        let alice_principal: PrincipalData = key_to_stacks_addr(&alice).into();
+
+        let mut alice_increased = false;
+
+        for tenure_id in 0..num_blocks {
+            let microblock_privkey = StacksPrivateKey::new();
+            let microblock_pubkeyhash =
+                Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+            let increase_in_block = tip.block_height == vesting_height;
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut block_txs = vec![coinbase_tx];
+
+                    if tenure_id == 1 {
+                        // Alice stacks her vested STX for long enough to outlast the vesting lock
+                        let alice_lockup = make_pox_2_lockup(
+                            &alice,
+                            0,
+                            512 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&alice).bytes,
+                            6,
+                            tip.block_height,
+                        );
+                        block_txs.push(alice_lockup);
+                    }
+                    if increase_in_block {
+                        // In the first block in which the vesting lock has expired, and before
+                        // the block releases it, Alice adds the newly-vested STX to her PoX
+                        // lock.  Bob sponsors the transaction, so paying its fee does not
+                        // release the vesting lock first.
+                        let alice_increase = make_sponsored_pox_2_contract_call(
+                            &alice,
+                            1,
+                            &bob,
+                            0,
+                            "stack-increase",
+                            vec![Value::UInt(512 * 1000000)],
+                        );
+                        block_txs.push(alice_increase);
+                    }
+
+                    let block_builder = StacksBlockBuilder::make_block_builder(
+                        &parent_tip,
+                        vrf_proof,
+                        tip.total_burn,
+                        microblock_pubkeyhash,
+                    )
+                    .unwrap();
+                    let (anchored_block, _size, _cost) =
+                        StacksBlockBuilder::make_anchored_block_from_txs(
+                            block_builder,
+                            chainstate,
+                            &sortdb.index_conn(),
+                            block_txs,
+                        )
+                        .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops);
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let alice_account = get_account(&mut peer, &alice_principal);
+            let alice_balance = &alice_account.stx_balance;
+            assert_eq!(alice_balance.get_total_balance(), 1024 * 1000000);
+
+            if tenure_id >= 1 && !alice_increased && !increase_in_block {
+                assert_eq!(alice_balance.amount_locked, 512 * 1000000);
+                let (amount_ustx, _, _, _) =
+                    get_stacker_info_in(&mut peer, "pox-2", &alice_principal).unwrap();
+                assert_eq!(amount_ustx, 512 * 1000000);
+            }
+            if increase_in_block {
+                alice_increased = true;
+            }
+            if alice_increased {
+                // all of Alice's STX are now locked for PoX, and the vesting lock is gone
+                assert_eq!(alice_balance.amount_unlocked, 0);
+                assert_eq!(alice_balance.amount_locked, 1024 * 1000000);
+                assert!(alice_balance.locks.is_empty());
+
+                let (amount_ustx, _, _, _) =
+                    get_stacker_info_in(&mut peer, "pox-2", &alice_principal).unwrap();
+                assert_eq!(amount_ustx, 1024 * 1000000);
+            }
+        }
+
+        assert!(alice_increased);
+    }
+
+    #[test]
+    fn test_pox_lockup_with_expired_unswept_lock() {
+        let mut burnchain = Burnchain::default_unittest(0, &BurnchainHeaderHash::zero());
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+
+        // Alice and Charlie each have half of their STX vesting
+        let vesting_height = 30;
+        let (mut peer, mut keys) = instantiate_pox_peer_with_locks(
+            &burnchain,
+            "test-pox-lockup-with-expired-unswept-lock",
+            6038,
+            |addrs| {
+                vec![
+                    (addrs[3].clone().into(), 512 * 1000000, vesting_height),
+                    (addrs[1].clone().into(), 512 * 1000000, vesting_height),
+                ]
+            },
+        );
+
+        let num_blocks = 10;
+
+        let alice = keys.pop().unwrap();
+        let bob = keys.pop().unwrap();
+        let charlie = keys.pop().unwrap();
+        let alice_principal: PrincipalData = key_to_stacks_addr(&alice).into();
+        let charlie_principal: PrincipalData = key_to_stacks_addr(&charlie).into();
+
+        let mut charlie_unlock_height = 0;
+        let mut vested = false;
+
+        for tenure_id in 0..num_blocks {
+            let microblock_privkey = StacksPrivateKey::new();
+            let microblock_pubkeyhash =
+                Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+            let vest_in_block = tip.block_height == vesting_height;
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut block_txs = vec![coinbase_tx];
+
+                    if tenure_id == 1 {
+                        // Charlie stacks his vested STX for long enough to outlast the vesting
+                        // lock
+                        let charlie_lockup = make_pox_2_lockup(
+                            &charlie,
+                            0,
+                            512 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&charlie).bytes,
+                            6,
+                            tip.block_height,
+                        );
+                        block_txs.push(charlie_lockup);
+                    }
+                    if vest_in_block {
+                        // In the first block in which the vesting locks have expired, and before
+                        // the block releases them, Alice stacks all of her STX and Charlie
+                        // extends his PoX lock.  Bob sponsors both transactions, so paying their
+                        // fees does not release the vesting locks first.
+                        let alice_lockup = make_sponsored_pox_2_contract_call(
+                            &alice,
+                            0,
+                            &bob,
+                            0,
+                            "stack-stx",
+                            vec![
+                                Value::UInt(1024 * 1000000),
+                                make_pox_addr(
+                                    AddressHashMode::SerializeP2PKH,
+                                    key_to_stacks_addr(&alice).bytes,
+                                ),
+                                Value::UInt(tip.block_height as u128),
+                                Value::UInt(1),
+                            ],
+                        );
+                        let charlie_extend = make_sponsored_pox_2_contract_call(
+                            &charlie,
+                            1,
+                            &bob,
+                            1,
+                            "stack-extend",
+                            vec![Value::UInt(1)],
+                        );
+                        block_txs.push(alice_lockup);
+                        block_txs.push(charlie_extend);
+                    }
+
+                    let block_builder = StacksBlockBuilder::make_block_builder(
+                        &parent_tip,
+                        vrf_proof,
+                        tip.total_burn,
+                        microblock_pubkeyhash,
+                    )
+                    .unwrap();
+                    let (anchored_block, _size, _cost) =
+                        StacksBlockBuilder::make_anchored_block_from_txs(
+                            block_builder,
+                            chainstate,
+                            &sortdb.index_conn(),
+                            block_txs,
+                        )
+                        .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops);
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let alice_balance = get_account(&mut peer, &alice_principal).stx_balance;
+            let charlie_balance = get_account(&mut peer, &charlie_principal).stx_balance;
+            assert_eq!(alice_balance.get_total_balance(), 1024 * 1000000);
+            assert_eq!(charlie_balance.get_total_balance(), 1024 * 1000000);
+
+            if tenure_id >= 1 && !vested && !vest_in_block {
+                assert_eq!(alice_balance.amount_locked, 0);
+                assert_eq!(alice_balance.locks.len(), 1);
+                assert!(get_stacker_info_in(&mut peer, "pox-2", &alice_principal).is_none());
+
+                assert_eq!(charlie_balance.amount_locked, 512 * 1000000);
+                assert_eq!(charlie_balance.locks.len(), 1);
+                charlie_unlock_height = charlie_balance.unlock_height;
+            }
+            if vest_in_block {
+                vested = true;
+            }
+            if vested {
+                // the expired vesting locks were released into the accounts' balances by the
+                // PoX operations, and none were released twice by the block
+                assert!(alice_balance.locks.is_empty());
+                assert_eq!(alice_balance.amount_unlocked, 0);
+                assert_eq!(alice_balance.amount_locked, 1024 * 1000000);
+                let (amount_ustx, _, lock_period, _) =
+                    get_stacker_info_in(&mut peer, "pox-2", &alice_principal).unwrap();
+                assert_eq!(amount_ustx, 1024 * 1000000);
+                assert_eq!(lock_period, 1);
+
+                assert!(charlie_balance.locks.is_empty());
+                assert_eq!(charlie_balance.amount_unlocked, 512 * 1000000);
+                assert_eq!(charlie_balance.amount_locked, 512 * 1000000);
+                assert_eq!(
+                    charlie_balance.unlock_height,
+                    charlie_unlock_height + burnchain.pox_constants.reward_cycle_length as u64
+                );
+                let (_, _, lock_period, _) =
+                    get_stacker_info_in(&mut peer, "pox-2", &charlie_principal).unwrap();
+                assert_eq!(lock_period, 7);
+            }
+        }
+
+        assert!(vested);
+    }
+
+    #[test]
+    fn test_pox_lockup_across_pox_contracts() {
+        let mut burnchain = Burnchain::default_unittest(0, &BurnchainHeaderHash::zero());
+        burnchain.pox_constants.reward_cycle_length = 5;
+        burnchain.pox_constants.prepare_length = 2;
+
+        let (mut peer, mut keys) =
+            instantiate_pox_peer(&burnchain, "test-pox-lockup-across-pox-contracts", 6036);
+
+        let num_blocks = 3;
+
+        let alice = keys.pop().unwrap();
+        let bob = keys.pop().unwrap();
+        let alice_principal: PrincipalData = key_to_stacks_addr(&alice).into();
+        let bob_principal: PrincipalData = key_to_stacks_addr(&bob).into();
+
+        let mut reward_cycle = 0;
+
+        for tenure_id in 0..num_blocks {
+            let microblock_privkey = StacksPrivateKey::new();
+            let microblock_pubkeyhash =
+                Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = get_parent_tip(parent_opt, chainstate, sortdb);
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut block_txs = vec![coinbase_tx];
+
+                    if tenure_id == 1 {
+                        // Alice stacks with the original PoX contract, and Bob with pox-2
+                        let alice_lockup = make_pox_lockup(
+                            &alice,
+                            0,
+                            512 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&alice).bytes,
+                            1,
+                            tip.block_height,
+                        );
+                        let bob_lockup = make_pox_2_lockup(
+                            &bob,
+                            0,
+                            512 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&bob).bytes,
+                            1,
+                            tip.block_height,
+                        );
+                        block_txs.push(alice_lockup);
+                        block_txs.push(bob_lockup);
+
+                        // neither can stack again with the other contract while locked
+                        let alice_lockup_2 = make_pox_2_lockup(
+                            &alice,
+                            1,
+                            256 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&alice).bytes,
+                            1,
+                            tip.block_height,
+                        );
+                        let bob_lockup_2 = make_pox_lockup(
+                            &bob,
+                            1,
+                            256 * 1000000,
+                            AddressHashMode::SerializeP2PKH,
+                            key_to_stacks_addr(&bob).bytes,
+                            1,
+                            tip.block_height,
+                        );
+                        block_txs.push(alice_lockup_2);
+                        block_txs.push(bob_lockup_2);
+                    }
+
+                    let block_builder = StacksBlockBuilder::make_block_builder(
+                        &parent_tip,
+                        vrf_proof,
+                        tip.total_burn,
+                        microblock_pubkeyhash,
+                    )
+                    .unwrap();
+                    let (anchored_block, _size, _cost) =
+                        StacksBlockBuilder::make_anchored_block_from_txs(
+                            block_builder,
+                            chainstate,
+                            &sortdb.index_conn(),
+                            block_txs,
+                        )
+                        .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops);
             peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
 
-            expected_liquid_ustx -= 1;
+            let tip_index_block = StacksBlockHeader::make_index_block_hash(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+            );
 
-            let liquid_ustx = get_liquid_ustx(&mut peer);
-            assert_eq!(liquid_ustx, expected_liquid_ustx);
+            if tenure_id == 0 {
+                // pox-2 took on the burnchain parameters given to the original PoX contract
+                for pox_contract in ["pox", "pox-2"].iter() {
+                    let pox_info = eval_at_tip(&mut peer, pox_contract, "(get-pox-info)")
+                        .expect_result_ok()
+                        .expect_tuple();
+                    assert_eq!(
+                        pox_info
+                            .get("reward-cycle-length")
+                            .unwrap()
+                            .to_owned()
+                            .expect_u128(),
+                        5
+                    );
+                    assert_eq!(
+                        pox_info
+                            .get("prepare-cycle-length")
+                            .unwrap()
+                            .to_owned()
+                            .expect_u128(),
+                        2
+                    );
+                }
+                let pox_contracts = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                    chainstate.get_pox_contract_names(sortdb, &tip_index_block)
+                })
+                .unwrap();
+                assert_eq!(pox_contracts, vec!["pox-2", "pox"]);
+                continue;
+            }
 
-            if tenure_id >= MINER_REWARD_MATURITY as usize {
-                let block_reward = 1_000 * MICROSTACKS_PER_STACKS as u128;
-                let expected_bonus = (missed_initial_blocks as u128) * block_reward
-                    / (INITIAL_MINING_BONUS_WINDOW as u128);
-                // add mature coinbases
-                expected_liquid_ustx += block_reward + expected_bonus;
+            if tenure_id == 1 {
+                let tip_burn_block_height =
+                    get_par_burn_block_height(peer.chainstate(), &tip_index_block);
+                reward_cycle = 1 + peer
+                    .chainstate()
+                    .get_reward_cycle(&burnchain, tip_burn_block_height);
             }
-        }
-    }
 
-    fn get_par_burn_block_height(state: &mut StacksChainState, block_id: &StacksBlockId) -> u64 {
-        let parent_block_id = StacksChainState::get_parent_block_id(state.db(), block_id)
-            .unwrap()
-            .unwrap();
+            // each lock-up is only in the contract that made it
+            for (principal, pox_contract, other_pox_contract) in [
+                (&alice_principal, "pox", "pox-2"),
+                (&bob_principal, "pox-2", "pox"),
+            ]
+            .iter()
+            {
+                let account = get_account(&mut peer, principal);
+                assert_eq!(account.stx_balance.amount_locked, 512 * 1000000);
+                assert_eq!(account.stx_balance.amount_unlocked, 512 * 1000000);
+
+                let (amount_ustx, _, _, _) =
+                    get_stacker_info_in(&mut peer, pox_contract, principal).unwrap();
+                assert_eq!(amount_ustx, 512 * 1000000);
+                assert!(get_stacker_info_in(&mut peer, other_pox_contract, principal).is_none());
+            }
 
-        let parent_header_info =
-            StacksChainState::get_stacks_block_header_info_by_index_block_hash(
-                state.db(),
-                &parent_block_id,
-            )
-            .unwrap()
+            // both lock-ups count towards the reward cycle
+            let total_stacked = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_total_ustx_stacked(sortdb, &tip_index_block, reward_cycle)
+            })
             .unwrap();
+            assert_eq!(total_stacked, 1024 * 1000000);
 
-        parent_header_info.burn_header_height as u64
+            let mut reward_addrs = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_reward_addresses(
+                    &burnchain,
+                    sortdb,
+                    burnchain.reward_cycle_to_block_height(reward_cycle as u64),
+                    &tip_index_block,
+                )
+            })
+            .unwrap();
+            reward_addrs.sort_by_key(|(addr, _)| addr.to_string());
+            let mut expected_addrs = vec![
+                (key_to_stacks_addr(&alice), 512 * 1000000),
+                (key_to_stacks_addr(&bob), 512 * 1000000),
+            ];
+            expected_addrs.sort_by_key(|(addr, _)| addr.to_string());
+            assert_eq!(reward_addrs, expected_addrs);
+        }
     }
 
     #[test]
-    fn test_pox_lockup_single_tx_sender() {
+    fn test_pox_delegations() {
         let mut burnchain = Burnchain::default_unittest(0, &BurnchainHeaderHash::zero());
         burnchain.pox_constants.reward_cycle_length = 5;
         burnchain.pox_constants.prepare_length = 2;
 
-        let (mut peer, mut keys) =
-            instantiate_pox_peer(&burnchain, "test-pox-lockup-single-tx-sender", 6002);
+        let (mut peer, mut keys) = instantiate_pox_peer(&burnchain, "test-pox-delegations", 6028);
 
-        let num_blocks = 10;
+        let num_blocks = 4;
 
         let alice = keys.pop().unwrap();
         let bob = keys.pop().unwrap();
         let charlie = keys.pop().unwrap();
 
-        let mut alice_reward_cycle = 0;
+        let alice_principal: PrincipalData = key_to_stacks_addr(&alice).into();
+        let bob_principal: PrincipalData = key_to_stacks_addr(&bob).into();
+        let charlie_principal: PrincipalData = key_to_stacks_addr(&charlie).into();
+        let bob_pox_addr = key_to_stacks_addr(&bob);
+
+        let mut stacked_reward_cycle = 0;
 
         for tenure_id in 0..num_blocks {
             let microblock_privkey = StacksPrivateKey::new();
@@ -1429,17 +3009,52 @@ pub mod test {
                     let mut block_txs = vec![coinbase_tx];
 
                     if tenure_id == 1 {
-                        // Alice locks up exactly 25% of the liquid STX supply, so this should succeed.
-                        let alice_lockup = make_pox_lockup(
+                        // Alice and Charlie both delegate to Bob
+                        let alice_delegate = make_pox_2_contract_call(
                             &alice,
                             0,
-                            1024 * 1000000,
-                            AddressHashMode::SerializeP2PKH,
-                            key_to_stacks_addr(&alice).bytes,
-                            12,
-                            tip.block_height,
+                            "delegate-stx",
+                            vec![
+                                Value::UInt(1024 * 1000000),
+                                bob_principal.clone().into(),
+                                Value::none(),
+                                Value::none(),
+                            ],
                         );
-                        block_txs.push(alice_lockup);
+                        block_txs.push(alice_delegate);
+
+                        let charlie_delegate = make_pox_2_contract_call(
+                            &charlie,
+                            0,
+                            "delegate-stx",
+                            vec![
+                                Value::UInt(1024 * 1000000),
+                                bob_principal.clone().into(),
+                                Value::none(),
+                                Value::none(),
+                            ],
+                        );
+                        block_txs.push(charlie_delegate);
+                    } else if tenure_id == 2 {
+                        // Charlie changes their mind
+                        let charlie_revoke =
+                            make_pox_2_contract_call(&charlie, 1, "revoke-delegate-stx", vec![]);
+                        block_txs.push(charlie_revoke);
+
+                        // Bob stacks half of Alice's STX
+                        let bob_stack_alice = make_pox_2_contract_call(
+                            &bob,
+                            0,
+                            "delegate-stack-stx",
+                            vec![
+                                alice_principal.clone().into(),
+                                Value::UInt(512 * 1000000),
+                                Value::Tuple(bob_pox_addr.as_clarity_tuple()),
+                                Value::UInt(tip.block_height as u128),
+                                Value::UInt(1),
+                            ],
+                        );
+                        block_txs.push(bob_stack_alice);
                     }
 
                     let block_builder = StacksBlockBuilder::make_block_builder(
@@ -1464,128 +3079,165 @@ pub mod test {
             let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops);
             peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
 
-            let total_liquid_ustx = get_liquid_ustx(&mut peer);
             let tip_index_block = StacksBlockHeader::make_index_block_hash(
                 &consensus_hash,
                 &stacks_block.block_hash(),
             );
 
-            if tenure_id <= 1 {
-                if tenure_id < 1 {
-                    // Alice has not locked up STX
-                    let alice_balance = get_balance(&mut peer, &key_to_stacks_addr(&alice).into());
-                    assert_eq!(alice_balance, 1024 * 1000000);
+            let delegators =
+                StacksChainState::get_pox_delegators(peer.chainstate().db(), &bob_principal)
+                    .unwrap();
+            let alice_delegation = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_pox_delegation(sortdb, &tip_index_block, &alice_principal)
+            })
+            .unwrap();
+            let charlie_delegation = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_pox_delegation(sortdb, &tip_index_block, &charlie_principal)
+            })
+            .unwrap();
 
-                    let alice_account = get_account(&mut peer, &key_to_stacks_addr(&alice).into());
-                    assert_eq!(alice_account.stx_balance.amount_unlocked, 1024 * 1000000);
-                    assert_eq!(alice_account.stx_balance.amount_locked, 0);
-                    assert_eq!(alice_account.stx_balance.unlock_height, 0);
-                }
-                let min_ustx = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
-                    chainstate.get_stacking_minimum(sortdb, &tip_index_block)
-                })
-                .unwrap();
-                assert_eq!(min_ustx, total_liquid_ustx / 480);
+            if tenure_id < 1 {
+                assert!(delegators.is_empty());
+                assert!(alice_delegation.is_none());
+                assert!(charlie_delegation.is_none());
+                continue;
+            }
 
-                // no reward addresses
-                let reward_addrs = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
-                    get_reward_addresses_with_par_tip(
-                        chainstate,
-                        &burnchain,
-                        sortdb,
-                        &tip_index_block,
-                    )
+            // the index remembers Charlie's delegation even after it is revoked
+            let mut expected_delegators = vec![alice_principal.clone(), charlie_principal.clone()];
+            expected_delegators.sort_by_key(|p| p.to_string());
+            assert_eq!(delegators, expected_delegators);
+
+            assert_eq!(
+                alice_delegation,
+                Some(PoxDelegation {
+                    stacker: alice_principal.clone(),
+                    amount_ustx: 1024 * 1000000,
+                    delegated_to: bob_principal.clone(),
+                    until_burn_ht: None,
+                    pox_addr: None,
                 })
-                .unwrap();
-                assert_eq!(reward_addrs.len(), 0);
+            );
 
-                // record the first reward cycle when Alice's tokens get stacked
+            if tenure_id == 1 {
+                assert!(charlie_delegation.is_some());
                 let tip_burn_block_height =
                     get_par_burn_block_height(peer.chainstate(), &tip_index_block);
-                alice_reward_cycle = 1 + peer
+                stacked_reward_cycle = 1 + peer
                     .chainstate()
-                    .get_reward_cycle(&burnchain, tip_burn_block_height);
-                let cur_reward_cycle = peer
-                    .chainstate()
-                    .get_reward_cycle(&burnchain, tip_burn_block_height);
+                    .get_reward_cycle(&burnchain, tip_burn_block_height)
+                    as u64;
+                continue;
+            }
 
-                eprintln!(
-                    "\nalice reward cycle: {}\ncur reward cycle: {}\n",
-                    alice_reward_cycle, cur_reward_cycle
-                );
-            } else {
-                // Alice's address is locked as of the next reward cycle
-                let tip_burn_block_height =
-                    get_par_burn_block_height(peer.chainstate(), &tip_index_block);
-                let cur_reward_cycle = peer
-                    .chainstate()
-                    .get_reward_cycle(&burnchain, tip_burn_block_height);
+            assert!(charlie_delegation.is_none());
 
-                // Alice has locked up STX no matter what
-                let alice_balance = get_balance(&mut peer, &key_to_stacks_addr(&alice).into());
-                assert_eq!(alice_balance, 0);
+            // Bob has not yet committed Alice's STX
+            let partial_stacked = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_partial_stacked_ustx(
+                    sortdb,
+                    &tip_index_block,
+                    &bob_principal,
+                    &bob_pox_addr,
+                    stacked_reward_cycle,
+                )
+            })
+            .unwrap();
+            assert_eq!(partial_stacked, 512 * 1000000);
 
-                let min_ustx = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
-                    chainstate.get_stacking_minimum(sortdb, &tip_index_block)
+            // Bob locked Alice's STX
+            let alice_stacking_state = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_pox_stacking_state(sortdb, &tip_index_block, &alice_principal)
+            })
+            .unwrap();
+            assert_eq!(
+                alice_stacking_state,
+                Some(PoxStackingState {
+                    stacker: alice_principal.clone(),
+                    amount_ustx: 512 * 1000000,
+                    pox_addr: bob_pox_addr.clone(),
+                    first_reward_cycle: stacked_reward_cycle,
+                    lock_period: 1,
+                    delegated_to: Some(bob_principal.clone()),
                 })
-                .unwrap();
-                let reward_addrs = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
-                    get_reward_addresses_with_par_tip(
-                        chainstate,
-                        &burnchain,
+            );
+
+            // ...and that is all Bob has to commit
+            let bob_partial_stacks = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_pox_partial_stacks(
+                    sortdb,
+                    &tip_index_block,
+                    &bob_principal,
+                    &delegators,
+                )
+            })
+            .unwrap();
+            assert_eq!(
+                bob_partial_stacks,
+                vec![PoxPartialStack {
+                    pox_addr: bob_pox_addr.clone(),
+                    reward_cycle: stacked_reward_cycle,
+                    stacked_ustx: 512 * 1000000,
+                }]
+            );
+            let charlie_partial_stacks =
+                with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                    chainstate.get_pox_partial_stacks(
                         sortdb,
                         &tip_index_block,
+                        &charlie_principal,
+                        &delegators,
                     )
                 })
                 .unwrap();
-                let total_stacked = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
-                    chainstate.get_total_ustx_stacked(sortdb, &tip_index_block, cur_reward_cycle)
-                })
-                .unwrap();
-
-                eprintln!("\ntenure: {}\nreward cycle: {}\nmin-uSTX: {}\naddrs: {:?}\ntotal_liquid_ustx: {}\ntotal-stacked: {}\n", tenure_id, cur_reward_cycle, min_ustx, &reward_addrs, total_liquid_ustx, total_stacked);
-
-                if cur_reward_cycle >= alice_reward_cycle {
-                    // this will grow as more miner rewards are unlocked, so be wary
-                    if tenure_id >= (MINER_REWARD_MATURITY + 1) as usize {
-                        // miner rewards increased liquid supply, so less than 25% is locked.
-                        // minimum participation decreases.
-                        assert!(total_liquid_ustx > 4 * 1024 * 1000000);
-                        assert_eq!(min_ustx, total_liquid_ustx / 480);
-                    } else {
-                        // still at 25% or more locked
-                        assert!(total_liquid_ustx <= 4 * 1024 * 1000000);
-                    }
+            assert!(charlie_partial_stacks.is_empty());
 
-                    let (amount_ustx, pox_addr, lock_period, first_reward_cycle) =
-                        get_stacker_info(&mut peer, &key_to_stacks_addr(&alice).into()).unwrap();
-                    eprintln!("\nAlice: {} uSTX stacked for {} cycle(s); addr is {:?}; first reward cycle is {}\n", amount_ustx, lock_period, &pox_addr, first_reward_cycle);
+            // Bob's commit would succeed, but Charlie has nothing to commit
+            let bob_commit = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.simulate_stack_aggregation_commit(
+                    sortdb,
+                    &tip_index_block,
+                    &bob_principal,
+                    &bob_pox_addr,
+                    stacked_reward_cycle,
+                )
+            })
+            .unwrap();
+            assert_eq!(bob_commit, Value::okay_true());
 
-                    // one reward address, and it's Alice's
-                    // either way, there's a single reward address
-                    assert_eq!(reward_addrs.len(), 1);
-                    assert_eq!(
-                        (reward_addrs[0].0).version,
-                        AddressHashMode::SerializeP2PKH.to_version_testnet()
-                    );
-                    assert_eq!((reward_addrs[0].0).bytes, key_to_stacks_addr(&alice).bytes);
-                    assert_eq!(reward_addrs[0].1, 1024 * 1000000);
+            let charlie_commit = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.simulate_stack_aggregation_commit(
+                    sortdb,
+                    &tip_index_block,
+                    &charlie_principal,
+                    &bob_pox_addr,
+                    stacked_reward_cycle,
+                )
+            })
+            .unwrap();
+            assert!(!charlie_commit.clone().expect_result().is_ok());
 
-                    // Lock-up is consistent with stacker state
-                    let alice_account = get_account(&mut peer, &key_to_stacks_addr(&alice).into());
-                    assert_eq!(alice_account.stx_balance.amount_unlocked, 0);
-                    assert_eq!(alice_account.stx_balance.amount_locked, 1024 * 1000000);
-                    assert_eq!(
-                        alice_account.stx_balance.unlock_height as u128,
-                        (first_reward_cycle + lock_period)
-                            * (burnchain.pox_constants.reward_cycle_length as u128)
-                            + (burnchain.first_block_height as u128)
-                    );
-                } else {
-                    // no reward addresses
-                    assert_eq!(reward_addrs.len(), 0);
-                }
-            }
+            // simulating did not commit anything
+            let partial_stacked = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_partial_stacked_ustx(
+                    sortdb,
+                    &tip_index_block,
+                    &bob_principal,
+                    &bob_pox_addr,
+                    stacked_reward_cycle,
+                )
+            })
+            .unwrap();
+            assert_eq!(partial_stacked, 512 * 1000000);
+            let total_stacked = with_sortdb(&mut peer, |ref mut chainstate, ref sortdb| {
+                chainstate.get_total_ustx_stacked(
+                    sortdb,
+                    &tip_index_block,
+                    stacked_reward_cycle as u128,
+                )
+            })
+            .unwrap();
+            assert_eq!(total_stacked, 0);
         }
     }
 
@@ -2822,18 +4474,13 @@ pub mod test {
                     // min STX is reset
                     assert_eq!(min_ustx, total_liquid_ustx / 480);
 
-                    // Unlock is lazy
+                    // Unlock was released into Alice's balance as soon as it expired
                     let alice_account = get_account(&mut peer, &key_to_stacks_addr(&alice).into());
-                    assert_eq!(alice_account.stx_balance.amount_unlocked, 0);
-                    assert_eq!(alice_account.stx_balance.amount_locked, 1024 * 1000000);
-                    assert_eq!(
-                        alice_account.stx_balance.unlock_height as u128,
-                        (first_reward_cycle + 1)
-                            * (burnchain.pox_constants.reward_cycle_length as u128)
-                            + (burnchain.first_block_height as u128)
-                    );
+                    assert_eq!(alice_account.stx_balance.amount_unlocked, 1024 * 1000000);
+                    assert_eq!(alice_account.stx_balance.amount_locked, 0);
+                    assert_eq!(alice_account.stx_balance.unlock_height, 0);
 
-                    // Unlock is lazy
+                    // Charlie spent all of its unlocked tokens
                     let charlie_account = get_account(
                         &mut peer,
                         &make_contract_id(&key_to_stacks_addr(&bob), "do-lockup").into(),
@@ -2940,30 +4587,19 @@ pub mod test {
                     // min STX is reset
                     assert_eq!(min_ustx, total_liquid_ustx / 480);
 
-                    // Unlock is lazy
+                    // Unlocks were released into the balances as soon as they expired
                     let alice_account = get_account(&mut peer, &key_to_stacks_addr(&alice).into());
-                    assert_eq!(alice_account.stx_balance.amount_unlocked, 512 * 1000000);
-                    assert_eq!(alice_account.stx_balance.amount_locked, 512 * 1000000);
-                    assert_eq!(
-                        alice_account.stx_balance.unlock_height as u128,
-                        (second_reward_cycle + 1)
-                            * (burnchain.pox_constants.reward_cycle_length as u128)
-                            + (burnchain.first_block_height as u128)
-                    );
+                    assert_eq!(alice_account.stx_balance.amount_unlocked, 1024 * 1000000);
+                    assert_eq!(alice_account.stx_balance.amount_locked, 0);
+                    assert_eq!(alice_account.stx_balance.unlock_height, 0);
 
-                    // Unlock is lazy
                     let charlie_account = get_account(
                         &mut peer,
                         &make_contract_id(&key_to_stacks_addr(&bob), "do-lockup").into(),
                     );
-                    assert_eq!(charlie_account.stx_balance.amount_unlocked, 0);
-                    assert_eq!(charlie_account.stx_balance.amount_locked, 512 * 1000000);
-                    assert_eq!(
-                        charlie_account.stx_balance.unlock_height as u128,
-                        (second_reward_cycle + 1)
-                            * (burnchain.pox_constants.reward_cycle_length as u128)
-                            + (burnchain.first_block_height as u128)
-                    );
+                    assert_eq!(charlie_account.stx_balance.amount_unlocked, 512 * 1000000);
+                    assert_eq!(charlie_account.stx_balance.amount_locked, 0);
+                    assert_eq!(charlie_account.stx_balance.unlock_height, 0);
                 }
             }
         }
@@ -3399,19 +5035,30 @@ pub mod test {
                     assert_eq!(account.stx_balance.unlock_height, 0);
                 }
             } else if cur_reward_cycle >= reward_cycle {
-                // not unlocked, but unlock is lazy
-                for (addr, (expected_locked, expected_balance)) in stacker_addrs
-                    .iter()
-                    .zip(balances_stacked.iter().zip(balances_during_stacking.iter()))
-                {
-                    let account = get_account(&mut peer, addr);
-                    assert_eq!(account.stx_balance.amount_unlocked, *expected_balance);
-                    assert_eq!(account.stx_balance.amount_locked, *expected_locked);
-                    assert_eq!(
-                        account.stx_balance.unlock_height as u128,
-                        (reward_cycle + 1) * (burnchain.pox_constants.reward_cycle_length as u128)
-                            + (burnchain.first_block_height as u128)
-                    );
+                let unlock_height = (reward_cycle + 1)
+                    * (burnchain.pox_constants.reward_cycle_length as u128)
+                    + (burnchain.first_block_height as u128);
+                if (tip_burn_block_height as u128) < unlock_height {
+                    // not unlocked yet
+                    for (addr, (expected_locked, expected_balance)) in stacker_addrs
+                        .iter()
+                        .zip(balances_stacked.iter().zip(balances_during_stacking.iter()))
+                    {
+                        let account = get_account(&mut peer, addr);
+                        assert_eq!(account.stx_balance.amount_unlocked, *expected_balance);
+                        assert_eq!(account.stx_balance.amount_locked, *expected_locked);
+                        assert_eq!(account.stx_balance.unlock_height as u128, unlock_height);
+                    }
+                } else {
+                    // unlocked, and released into the unlocked balances without any spending
+                    for (addr, expected_balance) in
+                        stacker_addrs.iter().zip(balances_after_stacking.iter())
+                    {
+                        let account = get_account(&mut peer, addr);
+                        assert_eq!(account.stx_balance.amount_unlocked, *expected_balance);
+                        assert_eq!(account.stx_balance.amount_locked, 0);
+                        assert_eq!(account.stx_balance.unlock_height, 0);
+                    }
                 }
             }
         }
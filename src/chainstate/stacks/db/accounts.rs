@@ -15,12 +15,14 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 
 use rusqlite::types::ToSql;
 use rusqlite::Row;
 
-use burnchains::Address;
+use burnchains::{Address, Txid};
 
+use chainstate::stacks::address::AddressKind;
 use chainstate::stacks::db::blocks::*;
 use chainstate::stacks::db::*;
 use chainstate::stacks::Error;
@@ -33,9 +35,69 @@ use vm::types::*;
 use util::db::Error as db_error;
 use util::db::*;
 
+/// The block and transaction in which a principal first appeared in some fork
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrincipalFirstSeen {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub txid: Txid,
+}
+
+impl FromRow<PrincipalFirstSeen> for PrincipalFirstSeen {
+    fn from_row<'a>(row: &'a Row) -> Result<PrincipalFirstSeen, db_error> {
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let block_height = u64::from_column(row, "block_height")?;
+        let txid = Txid::from_column(row, "txid")?;
+        Ok(PrincipalFirstSeen {
+            index_block_hash,
+            block_height,
+            txid,
+        })
+    }
+}
+
+/// A transaction that involved a principal, as recorded by
+/// `ChainstateTx::log_principal_transactions()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrincipalTransaction {
+    pub txid: Txid,
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub tx_index: u32,
+}
+
+impl FromRow<PrincipalTransaction> for PrincipalTransaction {
+    fn from_row<'a>(row: &'a Row) -> Result<PrincipalTransaction, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let block_height = u64::from_column(row, "block_height")?;
+        let tx_index = u64::from_column(row, "tx_index")? as u32;
+        Ok(PrincipalTransaction {
+            txid,
+            index_block_hash,
+            block_height,
+            tx_index,
+        })
+    }
+}
+
+/// A miner reward scheduled in some block of a fork which has not yet matured in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingMinerReward {
+    pub schedule: MinerPaymentSchedule,
+    /// height of the Stacks block which will pay out this reward
+    pub maturity_height: u64,
+    /// earliest burnchain height at which that Stacks block can be mined, assuming the fork's
+    /// next blocks are mined in consecutive burnchain blocks
+    pub earliest_maturity_burn_height: u64,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MinerReward {
     pub address: StacksAddress,
+    /// who the reward is credited to -- `address`, unless the block's coinbase named someone
+    /// else
+    pub recipient: PrincipalData,
     pub coinbase: u128,
     pub tx_fees_anchored: u128,
     pub tx_fees_streamed_produced: u128,
@@ -43,6 +105,44 @@ pub struct MinerReward {
     pub vtxindex: u32, // will be 0 for the reward to the miner, and >0 for user burn supports
 }
 
+/// A fungible or non-fungible token that a principal has been credited at some point, in some
+/// fork.  The principal may no longer hold it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetHolding {
+    pub asset_identifier: AssetIdentifier,
+    pub value: Option<Value>, // will be None for fungible tokens
+}
+
+impl FromRow<AssetHolding> for AssetHolding {
+    fn from_row<'a>(row: &'a Row) -> Result<AssetHolding, db_error> {
+        let contract_identifier_text: String = row.get("contract_identifier");
+        let asset_name_text: String = row.get("asset_name");
+        let asset_value_text: String = row.get("asset_value");
+        let fungible: bool = row.get("fungible");
+
+        let contract_identifier = QualifiedContractIdentifier::parse(&contract_identifier_text)
+            .map_err(|_e| db_error::ParseError)?;
+        let asset_name =
+            ClarityName::try_from(asset_name_text).map_err(|_e| db_error::ParseError)?;
+        let value = if fungible {
+            None
+        } else {
+            Some(
+                Value::try_deserialize_hex_untyped(&asset_value_text)
+                    .map_err(|_e| db_error::ParseError)?,
+            )
+        };
+
+        Ok(AssetHolding {
+            asset_identifier: AssetIdentifier {
+                contract_identifier,
+                asset_name,
+            },
+            value,
+        })
+    }
+}
+
 impl FromRow<MinerPaymentSchedule> for MinerPaymentSchedule {
     fn from_row<'a>(row: &'a Row) -> Result<MinerPaymentSchedule, db_error> {
         let address = StacksAddress::from_column(row, "address")?;
@@ -61,7 +161,12 @@ impl FromRow<MinerPaymentSchedule> for MinerPaymentSchedule {
         let miner: bool = row.get("miner");
         let stacks_block_height = u64::from_column(row, "stacks_block_height")?;
         let vtxindex: u32 = row.get("vtxindex");
+        let recipient_text: Option<String> = row.get("recipient");
 
+        let recipient = match recipient_text {
+            Some(text) => Some(PrincipalData::parse(&text).map_err(|_e| db_error::ParseError)?),
+            None => None,
+        };
         let coinbase = coinbase_text
             .parse::<u128>()
             .map_err(|_e| db_error::ParseError)?;
@@ -80,6 +185,7 @@ impl FromRow<MinerPaymentSchedule> for MinerPaymentSchedule {
 
         let payment_data = MinerPaymentSchedule {
             address,
+            recipient,
             block_hash,
             consensus_hash,
             parent_block_hash,
@@ -106,6 +212,17 @@ impl MinerReward {
             + self.tx_fees_streamed_produced
             + self.tx_fees_streamed_confirmed
     }
+
+    /// Is this reward credited to a burn address, and so destroyed?  This happens to the shares
+    /// of users who supported a miner that was caught forking its microblock stream.
+    pub fn is_burned(&self) -> bool {
+        match self.recipient {
+            PrincipalData::Standard(ref data) => {
+                StacksAddress::from(data.clone()).kind() == AddressKind::Burn
+            }
+            PrincipalData::Contract(_) => false,
+        }
+    }
 }
 
 impl StacksChainState {
@@ -154,6 +271,127 @@ impl StacksChainState {
             .map_err(Error::ClarityError)
     }
 
+    /// Get every asset this principal has ever been credited, in any fork, as recorded by
+    /// `ChainstateTx::log_asset_holdings()`.  Use `get_account_ft()` and `get_account_nft()` to
+    /// find out which of them the principal still holds at a given chain tip.
+    pub fn get_account_asset_holdings(
+        conn: &DBConn,
+        principal: &PrincipalData,
+    ) -> Result<Vec<AssetHolding>, Error> {
+        let qry = "SELECT * FROM asset_holdings WHERE principal = ?1 ORDER BY contract_identifier, asset_name, asset_value".to_string();
+        let args: &[&dyn ToSql] = &[&principal.to_string()];
+        query_rows::<AssetHolding, _>(conn, &qry, args).map_err(Error::DBError)
+    }
+
+    /// Get the block in which this principal first sent a transaction or received STX in the
+    /// fork ending at `tip`, as recorded by `ChainstateTx::log_first_seen_principals()`.
+    /// Principals only credited in the genesis state have not been seen.
+    pub fn get_principal_first_seen(
+        &self,
+        principal: &PrincipalData,
+        tip: &StacksBlockId,
+    ) -> Result<Option<PrincipalFirstSeen>, Error> {
+        let qry = "SELECT * FROM first_seen_principals WHERE principal = ?1 ORDER BY block_height";
+        let args: &[&dyn ToSql] = &[&principal.to_string()];
+        let rows = query_rows::<PrincipalFirstSeen, _>(self.db(), qry, args)?;
+        for row in rows.into_iter() {
+            if get_ancestor_block_height(&self.state_index, &row.index_block_hash, tip)?.is_some() {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get a page of the transactions that involved this principal in the fork ending at `tip`,
+    /// as recorded by `ChainstateTx::log_principal_transactions()`, newest first.  Skips the
+    /// `offset` newest transactions and returns at most `limit`, along with the total number of
+    /// transactions that involved the principal in the fork.
+    pub fn get_principal_transactions(
+        &self,
+        principal: &PrincipalData,
+        tip: &StacksBlockId,
+        offset: u64,
+        limit: u64,
+    ) -> Result<(u64, Vec<PrincipalTransaction>), Error> {
+        let qry = "SELECT * FROM principal_transactions WHERE principal = ?1 ORDER BY block_height DESC, tx_index DESC";
+        let args: &[&dyn ToSql] = &[&principal.to_string()];
+        let rows = query_rows::<PrincipalTransaction, _>(self.db(), qry, args)?;
+
+        // rows from other forks are skipped; blocks tend to have several rows, so remember
+        // which blocks are in this fork
+        let mut in_fork = HashMap::new();
+        let mut total = 0;
+        let mut page = vec![];
+        for row in rows.into_iter() {
+            let is_ancestor = match in_fork.get(&row.index_block_hash) {
+                Some(is_ancestor) => *is_ancestor,
+                None => {
+                    let is_ancestor =
+                        get_ancestor_block_height(&self.state_index, &row.index_block_hash, tip)?
+                            .is_some();
+                    in_fork.insert(row.index_block_hash.clone(), is_ancestor);
+                    is_ancestor
+                }
+            };
+            if !is_ancestor {
+                continue;
+            }
+            if total >= offset && (page.len() as u64) < limit {
+                page.push(row);
+            }
+            total += 1;
+        }
+        Ok((total, page))
+    }
+
+    /// Get the miner rewards scheduled for this address -- as a block's miner or as one of its
+    /// user-burn supporters -- in the fork ending at `tip` which have not yet matured, oldest
+    /// first.  The reward scheduled in a block at height `h` is paid out by the block at height
+    /// `h + MINER_REWARD_MATURITY + 1`.  Returns Ok(None) if the tip does not exist.
+    pub fn get_pending_miner_rewards(
+        &self,
+        address: &StacksAddress,
+        tip: &StacksBlockId,
+    ) -> Result<Option<Vec<PendingMinerReward>>, Error> {
+        let tip_info = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            tip,
+        )? {
+            Some(info) => info,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let qry = "SELECT * FROM payments WHERE address = ?1 AND stacks_block_height >= ?2 AND stacks_block_height <= ?3 ORDER BY stacks_block_height ASC, vtxindex ASC";
+        let args: &[&dyn ToSql] = &[
+            &address.to_string(),
+            &(tip_info.block_height.saturating_sub(MINER_REWARD_MATURITY) as i64),
+            &(tip_info.block_height as i64),
+        ];
+        let rows = query_rows::<MinerPaymentSchedule, _>(self.db(), qry, args)?;
+
+        let mut pending = vec![];
+        for schedule in rows.into_iter() {
+            let block_id = StacksBlockHeader::make_index_block_hash(
+                &schedule.consensus_hash,
+                &schedule.block_hash,
+            );
+            if get_ancestor_block_height(&self.state_index, &block_id, tip)?.is_none() {
+                continue;
+            }
+            let maturity_height = schedule.stacks_block_height + MINER_REWARD_MATURITY + 1;
+            let earliest_maturity_burn_height =
+                tip_info.burn_header_height as u64 + (maturity_height - tip_info.block_height);
+            pending.push(PendingMinerReward {
+                schedule,
+                maturity_height,
+                earliest_maturity_burn_height,
+            });
+        }
+        Ok(Some(pending))
+    }
+
     /// Called each time a transaction is invoked from this principal, to e.g.
     /// debit the STX-denominated tx fee or transfer/burn STX.
     /// Will consolidate unlocked STX.
@@ -230,6 +468,67 @@ impl StacksChainState {
             .expect("FATAL: failed to credit account")
     }
 
+    /// Called during the genesis / boot sequence to credit many accounts at once.  Equivalent to
+    /// calling `account_genesis_credit` on each (principal, amount) pair in turn, but all of the
+    /// balances are written through a single Clarity DB context (and thus a single MARF batch).
+    /// Returns the number of credits applied.
+    pub fn account_genesis_credit_batch<I>(
+        clarity_tx: &mut ClarityTransactionConnection,
+        credits: I,
+    ) -> u64
+    where
+        I: IntoIterator<Item = (PrincipalData, u128)>,
+    {
+        clarity_tx
+            .with_clarity_db(|ref mut db| {
+                let mut count = 0;
+                for (principal, amount) in credits {
+                    let mut balance = STXBalance::initial(amount);
+                    let mut snapshot = db.get_stx_balance_snapshot_genesis(&principal);
+                    let existing_balance = snapshot.balance().amount_unlocked;
+                    if existing_balance > 0 {
+                        balance.amount_unlocked = balance
+                            .amount_unlocked
+                            .checked_add(existing_balance)
+                            .expect("Genesis credit balance overflow");
+                    }
+                    snapshot.set_balance(balance);
+                    snapshot.save();
+                    count += 1;
+                }
+                Ok(count)
+            })
+            .expect("FATAL: failed to credit accounts")
+    }
+
+    /// Called during the genesis / boot sequence, after the accounts are credited, to lock up some
+    /// of their STX until a burnchain block height (e.g. for a vesting schedule).  Each lock is
+    /// independent of any other lock on the account, so the account can still stack the rest of
+    /// its STX.  Returns the number of locks applied.
+    pub fn account_genesis_lock_batch<I>(
+        clarity_tx: &mut ClarityTransactionConnection,
+        locks: I,
+    ) -> Result<u64, Error>
+    where
+        I: IntoIterator<Item = (PrincipalData, u128, u64)>,
+    {
+        clarity_tx
+            .with_clarity_db(|ref mut db| {
+                let mut count = 0;
+                for (principal, amount, unlock_burn_height) in locks {
+                    let mut snapshot = db.get_stx_balance_snapshot_genesis(&principal);
+                    if amount == 0 || unlock_burn_height == 0 || !snapshot.can_transfer(amount) {
+                        return Ok(Err(Error::PoxInsufficientBalance));
+                    }
+                    snapshot.add_lock(amount, unlock_burn_height);
+                    snapshot.save();
+                    count += 1;
+                }
+                Ok(Ok(count))
+            })
+            .expect("FATAL: failed to lock accounts")
+    }
+
     /// Increment an account's nonce
     pub fn update_account_nonce(
         clarity_tx: &mut ClarityTransactionConnection,
@@ -276,6 +575,71 @@ impl StacksChainState {
         Ok(())
     }
 
+    /// Extend an account's PoX lock until a later burnchain block height, without unlocking it
+    /// first.  Returns the amount locked and the new unlock height.  Does NOT touch the account
+    /// nonce.
+    pub fn pox_lock_extend(
+        db: &mut ClarityDatabase,
+        principal: &PrincipalData,
+        unlock_burn_height: u64,
+    ) -> Result<(u128, u64), Error> {
+        let mut snapshot = db.get_stx_balance_snapshot(principal);
+        if !snapshot.has_locked_tokens() {
+            return Err(Error::PoxNoLock);
+        }
+        let (_, old_unlock_burn_height) = snapshot.get_locked_balance();
+        if unlock_burn_height <= old_unlock_burn_height {
+            return Err(Error::PoxInvalidLockChange);
+        }
+        snapshot.extend_lock_tokens(unlock_burn_height);
+
+        debug!(
+            "PoX lock of {} uSTX extended from burnchain block height {} to {} for {:?}",
+            snapshot.balance().amount_locked,
+            old_unlock_burn_height,
+            unlock_burn_height,
+            principal
+        );
+
+        let locked_amount = snapshot.balance().amount_locked;
+        snapshot.save();
+        Ok((locked_amount, unlock_burn_height))
+    }
+
+    /// Increase the amount of STX in an account's PoX lock to `new_total_locked`, without
+    /// unlocking it first.  The lock's unlock height does not change.  Returns the new amount
+    /// locked and the unlock height.  Does NOT touch the account nonce.
+    pub fn pox_lock_increase(
+        db: &mut ClarityDatabase,
+        principal: &PrincipalData,
+        new_total_locked: u128,
+    ) -> Result<(u128, u64), Error> {
+        let mut snapshot = db.get_stx_balance_snapshot(principal);
+        if !snapshot.has_locked_tokens() {
+            return Err(Error::PoxNoLock);
+        }
+        let (old_total_locked, unlock_burn_height) = snapshot.get_locked_balance();
+        if new_total_locked <= old_total_locked {
+            return Err(Error::PoxInvalidLockChange);
+        }
+        if !snapshot.can_transfer(new_total_locked - old_total_locked) {
+            return Err(Error::PoxInsufficientBalance);
+        }
+        snapshot.increase_lock_tokens(new_total_locked);
+
+        debug!(
+            "PoX lock increased from {} to {} uSTX (new balance {}) until burnchain block height {} for {:?}",
+            old_total_locked,
+            new_total_locked,
+            snapshot.balance().amount_unlocked,
+            unlock_burn_height,
+            principal
+        );
+
+        snapshot.save();
+        Ok((new_total_locked, unlock_burn_height))
+    }
+
     /// Schedule a miner payment in the future.
     /// Schedules payments out to both miners and users that support them.
     pub fn insert_miner_payment_schedule<'a>(
@@ -309,6 +673,10 @@ impl StacksChainState {
             &true,
             &0i64,
             &index_block_hash,
+            &block_reward
+                .recipient
+                .as_ref()
+                .map(|recipient| recipient.to_string()),
         ];
 
         tx.execute(
@@ -328,8 +696,9 @@ impl StacksChainState {
                         stacks_block_height,
                         miner,
                         vtxindex,
-                        index_block_hash) \
-                    VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16)",
+                        index_block_hash,
+                        recipient) \
+                    VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16,?17)",
             args,
         )
         .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
@@ -581,9 +950,17 @@ impl StacksChainState {
             tx_fees_streamed_confirmed
         );
 
+        // the coinbase may have named a different principal to pay, but not if the reward was
+        // stolen by a poison-reporter or destroyed
+        let paid_principal = match (poison_reporter_opt, &participant.recipient) {
+            (None, Some(ref alt_recipient)) => alt_recipient.clone(),
+            _ => PrincipalData::Standard(StandardPrincipalData::from(recipient.clone())),
+        };
+
         // ("bad miner! no coinbase!")
         let miner_reward = MinerReward {
             address: recipient,
+            recipient: paid_principal,
             coinbase: coinbase_reward,
             tx_fees_anchored: tx_fees_anchored,
             tx_fees_streamed_produced: tx_fees_streamed_produced,
@@ -670,130 +1047,109 @@ mod test {
     use burnchains::*;
     use chainstate::burn::*;
     use chainstate::stacks::db::test::*;
+    use chainstate::stacks::db::testing::*;
     use chainstate::stacks::index::*;
     use chainstate::stacks::Error;
     use chainstate::stacks::*;
+    use core::*;
     use util::hash::*;
     use vm::costs::ExecutionCost;
 
-    fn make_dummy_miner_payment_schedule(
-        addr: &StacksAddress,
-        coinbase: u128,
-        tx_fees_anchored: u128,
-        tx_fees_streamed: u128,
-        commit_burn: u64,
-        sortition_burn: u64,
-    ) -> MinerPaymentSchedule {
-        MinerPaymentSchedule {
-            address: addr.clone(),
-            block_hash: FIRST_STACKS_BLOCK_HASH.clone(),
-            consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
-            parent_block_hash: FIRST_STACKS_BLOCK_HASH.clone(),
-            parent_consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
-            coinbase,
-            tx_fees_anchored,
-            tx_fees_streamed,
-            stx_burns: 0,
-            burnchain_commit_burn: commit_burn,
-            burnchain_sortition_burn: sortition_burn,
-            fill: 0xffffffffffffffff,
-            miner: true,
-            stacks_block_height: 0,
-            vtxindex: 0,
-        }
-    }
+    #[test]
+    fn advance_fork_tips() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "advance_fork_tips");
+        let miner_1 =
+            StacksAddress::from_string(&"SP1A2K3ENNA6QQ7G8DVJXM24T6QMBDVS7D0TRTAR5".to_string())
+                .unwrap();
+        let genesis = StacksHeaderInfo::regtest_genesis(0);
 
-    fn make_dummy_user_payment_schedule(
-        addr: &StacksAddress,
-        coinbase: u128,
-        tx_fees_anchored: u128,
-        tx_fees_streamed: u128,
-        commit_burn: u64,
-        sortition_burn: u64,
-        vtxindex: u32,
-    ) -> MinerPaymentSchedule {
-        let mut sched = make_dummy_miner_payment_schedule(
-            addr,
-            coinbase,
-            tx_fees_anchored,
-            tx_fees_streamed,
-            commit_burn,
-            sortition_burn,
-        );
-        sched.miner = false;
-        sched.vtxindex = vtxindex;
-        sched
-    }
+        let mut reward_a = make_dummy_miner_payment_schedule(&miner_1, 500, 0, 0, 1000, 1000);
+        let mut reward_b = make_dummy_miner_payment_schedule(&miner_1, 500, 0, 0, 1000, 1000);
+        let tip_a = advance_tip(&mut chainstate, &genesis, &mut reward_a, &mut vec![]);
+        let tip_b = advance_fork_tip(&mut chainstate, &genesis, 1, &mut reward_b, &mut vec![]);
 
-    impl StagingUserBurnSupport {
-        pub fn from_miner_payment_schedule(user: &MinerPaymentSchedule) -> StagingUserBurnSupport {
-            StagingUserBurnSupport {
-                consensus_hash: user.consensus_hash.clone(),
-                anchored_block_hash: user.block_hash.clone(),
-                address: user.address.clone(),
-                burn_amount: user.burnchain_commit_burn,
-                vtxindex: user.vtxindex,
-            }
-        }
+        assert_eq!(tip_a.block_height, tip_b.block_height);
+        assert!(tip_a.consensus_hash != tip_b.consensus_hash);
+        assert!(tip_a.index_block_hash() != tip_b.index_block_hash());
+
+        // each fork can be extended on its own
+        let mut reward_a_2 = make_dummy_miner_payment_schedule(&miner_1, 500, 0, 0, 1000, 1000);
+        let mut reward_b_2 = make_dummy_miner_payment_schedule(&miner_1, 500, 0, 0, 1000, 1000);
+        let tip_a_2 = advance_tip(&mut chainstate, &tip_a, &mut reward_a_2, &mut vec![]);
+        let tip_b_2 = advance_fork_tip(&mut chainstate, &tip_b, 1, &mut reward_b_2, &mut vec![]);
+
+        let mut tx = chainstate.index_tx_begin().unwrap();
+        let ancestor_a = StacksChainState::get_tip_ancestor(&mut tx, &tip_a_2, 1)
+            .unwrap()
+            .unwrap();
+        let ancestor_b = StacksChainState::get_tip_ancestor(&mut tx, &tip_b_2, 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ancestor_a.index_block_hash(), tip_a.index_block_hash());
+        assert_eq!(ancestor_b.index_block_hash(), tip_b.index_block_hash());
     }
 
-    fn advance_tip(
-        chainstate: &mut StacksChainState,
-        parent_header_info: &StacksHeaderInfo,
-        block_reward: &mut MinerPaymentSchedule,
-        user_burns: &mut Vec<StagingUserBurnSupport>,
-    ) -> StacksHeaderInfo {
-        let mut new_tip = parent_header_info.clone();
-
-        new_tip.anchored_header.parent_block = parent_header_info.anchored_header.block_hash();
-        new_tip.anchored_header.microblock_pubkey_hash =
-            Hash160::from_data(&parent_header_info.anchored_header.microblock_pubkey_hash.0);
-        new_tip.anchored_header.total_work.work =
-            parent_header_info.anchored_header.total_work.work + 1;
-        new_tip.microblock_tail = None;
-        new_tip.block_height = parent_header_info.block_height + 1;
-        new_tip.consensus_hash = ConsensusHash(
-            Hash160::from_data(
-                &Sha512Trunc256Sum::from_data(&parent_header_info.consensus_hash.0).0,
-            )
-            .0,
-        );
-        new_tip.burn_header_hash = BurnchainHeaderHash(
-            Sha512Trunc256Sum::from_data(&parent_header_info.consensus_hash.0).0,
-        );
-        new_tip.burn_header_height = parent_header_info.burn_header_height + 1;
-        new_tip.total_liquid_ustx = parent_header_info.total_liquid_ustx + block_reward.coinbase;
+    #[test]
+    fn account_genesis_credit_batch() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "account_genesis_credit_batch");
+        let addrs: Vec<PrincipalData> = (0..4u8)
+            .map(|i| {
+                StacksAddress {
+                    version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                    bytes: Hash160([i + 1; 20]),
+                }
+                .into()
+            })
+            .collect();
 
-        block_reward.parent_consensus_hash = parent_header_info.consensus_hash.clone();
-        block_reward.parent_block_hash = parent_header_info.anchored_header.block_hash().clone();
-        block_reward.block_hash = new_tip.anchored_header.block_hash();
-        block_reward.consensus_hash = new_tip.consensus_hash.clone();
+        // the same credits, including repeats, applied one at a time and in a batch
+        let credits = vec![
+            (addrs[0].clone(), 100u128),
+            (addrs[1].clone(), 200u128),
+            (addrs[0].clone(), 300u128),
+        ];
+        let batched_credits = vec![
+            (addrs[2].clone(), 100u128),
+            (addrs[3].clone(), 200u128),
+            (addrs[2].clone(), 300u128),
+        ];
 
-        for ref mut user_burn in user_burns.iter_mut() {
-            user_burn.anchored_block_hash = new_tip.anchored_header.block_hash();
-            user_burn.consensus_hash = new_tip.consensus_hash.clone();
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+        conn.connection().as_transaction(|tx| {
+            for (addr, amount) in credits.iter() {
+                StacksChainState::account_genesis_credit(tx, addr, *amount);
+            }
+        });
+        let count = conn.connection().as_transaction(|tx| {
+            StacksChainState::account_genesis_credit_batch(tx, batched_credits)
+        });
+        assert_eq!(count, 3);
+
+        for (single, batched) in [(0, 2), (1, 3)].iter() {
+            let single_account = StacksChainState::get_account(&mut conn, &addrs[*single]);
+            let batched_account = StacksChainState::get_account(&mut conn, &addrs[*batched]);
+            assert_eq!(single_account.stx_balance, batched_account.stx_balance);
         }
-
-        let mut tx = chainstate.index_tx_begin().unwrap();
-        let tip = StacksChainState::advance_tip(
-            &mut tx,
-            &parent_header_info.anchored_header,
-            &parent_header_info.consensus_hash,
-            &new_tip.anchored_header,
-            &new_tip.consensus_hash,
-            &new_tip.burn_header_hash,
-            new_tip.burn_header_height,
-            new_tip.burn_header_timestamp,
-            new_tip.microblock_tail.clone(),
-            &block_reward,
-            &user_burns,
-            new_tip.total_liquid_ustx,
-            &ExecutionCost::zero(),
-            123,
-        )
-        .unwrap();
-        tx.commit().unwrap();
-        tip
+        assert_eq!(
+            StacksChainState::get_account(&mut conn, &addrs[2])
+                .stx_balance
+                .amount_unlocked,
+            400
+        );
+        assert_eq!(
+            StacksChainState::get_account(&mut conn, &addrs[3])
+                .stx_balance
+                .amount_unlocked,
+            200
+        );
+        conn.commit_block();
     }
 
     #[test]
@@ -908,6 +1264,10 @@ mod test {
             0,
             0,
         );
+        tip_reward.recipient = Some(PrincipalData::Contract(QualifiedContractIdentifier::new(
+            StandardPrincipalData(0, [0u8; 20]),
+            "reward-splitter".into(),
+        )));
         let tip = advance_tip(&mut chainstate, &parent_tip, &mut tip_reward, &mut vec![]);
 
         {
@@ -1052,6 +1412,62 @@ mod test {
         assert_eq!(miner_reward.tx_fees_streamed_confirmed, 0);
     }
 
+    #[test]
+    fn miner_reward_one_miner_one_user_alt_recipient() {
+        let miner_1 =
+            StacksAddress::from_string(&"SP1A2K3ENNA6QQ7G8DVJXM24T6QMBDVS7D0TRTAR5".to_string())
+                .unwrap();
+        let user_1 =
+            StacksAddress::from_string(&"SP2837ZMC89J40K4YTS64B00M7065C6X46JX6ARG0".to_string())
+                .unwrap();
+        let reporter = StacksAddress {
+            version: C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+            bytes: Hash160([0x33; 20]),
+        };
+        let recipient = PrincipalData::Contract(QualifiedContractIdentifier::new(
+            StandardPrincipalData::from(miner_1.clone()),
+            "reward-splitter".into(),
+        ));
+
+        let mut miner = make_dummy_miner_payment_schedule(&miner_1, 500, 0, 0, 250, 1000);
+        miner.recipient = Some(recipient.clone());
+        let user = make_dummy_user_payment_schedule(&user_1, 500, 0, 0, 750, 1000, 1);
+
+        let reward_miner_1 = StacksChainState::calculate_miner_reward(
+            false,
+            &miner,
+            &miner,
+            &vec![user.clone()],
+            None,
+        );
+        let reward_user_1 = StacksChainState::calculate_miner_reward(
+            false,
+            &user,
+            &miner,
+            &vec![user.clone()],
+            None,
+        );
+
+        // the miner's share goes to the coinbase's recipient, but the user's does not
+        assert_eq!(reward_miner_1.address, miner_1);
+        assert_eq!(reward_miner_1.recipient, recipient);
+        assert_eq!(reward_miner_1.coinbase, 125);
+        assert!(!reward_miner_1.is_burned());
+
+        assert_eq!(reward_user_1.recipient, PrincipalData::from(user_1.clone()));
+        assert_eq!(reward_user_1.coinbase, 375);
+
+        // a poison-reporter's commission is paid to the reporter, not the coinbase's recipient
+        let reward_miner_1 = StacksChainState::calculate_miner_reward(
+            false,
+            &miner,
+            &miner,
+            &vec![user.clone()],
+            Some(&reporter),
+        );
+        assert_eq!(reward_miner_1.recipient, PrincipalData::from(reporter));
+    }
+
     #[test]
     fn miner_reward_one_miner_one_user_no_tx_fees() {
         let miner_1 =
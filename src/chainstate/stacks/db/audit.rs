@@ -0,0 +1,337 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Opt-in audit log of the consensus-critical writes made while processing each block.
+//!
+//! Once enabled on a `StacksChainState`, every anchored block it processes gets one JSON line
+//! per write appended to `consensus-audit.log` in the log directory:
+//!
+//! * `marf`: each Clarity MARF key the block wrote, in write order, with the MARF value hash
+//!   (which is also the value's key in the Clarity side store's `data_table`).
+//! * `metadata_table`: each Clarity contract metadata entry the block wrote.
+//! * `block_headers` and `payments`: the rows the block added to the headers DB.
+//!
+//! Values are never logged, only their hashes -- an external pipeline can check them against
+//! its own replay of the chain, or against a node's databases after an incident.  Once the log
+//! file grows past the configured size it is rotated to `consensus-audit.log.1` (shifting older
+//! files up by one), and the oldest files beyond the configured count are deleted.  A block's
+//! writes never straddle two files.
+
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rusqlite::types::ValueRef;
+use rusqlite::Row;
+use rusqlite::types::ToSql;
+
+use chainstate::stacks::db::{StacksChainState, StacksEpochReceipt};
+use chainstate::stacks::index::MARFValue;
+use chainstate::stacks::StacksBlockId;
+use util::db::Error as db_error;
+use util::hash::Sha512Trunc256Sum;
+
+/// Name of the audit log file currently being written
+pub const CONSENSUS_AUDIT_LOG_FILE: &str = "consensus-audit.log";
+
+/// Default size past which the audit log is rotated
+pub const DEFAULT_AUDIT_LOG_MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+/// Default number of rotated audit log files to keep
+pub const DEFAULT_AUDIT_LOG_MAX_FILES: usize = 16;
+
+/// One write made while processing a block, as recorded in the audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsensusWrite {
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub table: String,
+    pub key: String,
+    pub value_hash: String,
+}
+
+/// Hash of a row's column values, in column order.  Each value is prefixed with its type and
+/// length, so that rows with different values can't hash the same.
+fn hash_row(row: &Row) -> String {
+    let mut preimage = vec![];
+    for i in 0..row.column_count() {
+        let (tag, bytes) = match row.get_raw(i) {
+            ValueRef::Null => (b'n', vec![]),
+            ValueRef::Integer(i) => (b'i', i.to_be_bytes().to_vec()),
+            ValueRef::Real(f) => (b'r', f.to_bits().to_be_bytes().to_vec()),
+            ValueRef::Text(s) => (b't', s.as_bytes().to_vec()),
+            ValueRef::Blob(b) => (b'b', b.to_vec()),
+        };
+        preimage.push(tag);
+        preimage.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+        preimage.extend_from_slice(&bytes);
+    }
+    Sha512Trunc256Sum::from_data(&preimage).to_hex()
+}
+
+pub struct ConsensusAuditLog {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    max_files: usize,
+    file: BufWriter<File>,
+    file_bytes: u64,
+}
+
+impl ConsensusAuditLog {
+    /// Open (or continue) the audit log in `dir`, creating the directory if need be.  The log is
+    /// rotated once it grows past `max_file_bytes`, keeping at most `max_files` rotated files.
+    pub fn open(dir: &str, max_file_bytes: u64, max_files: usize) -> io::Result<ConsensusAuditLog> {
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir)?;
+        let (file, file_bytes) = ConsensusAuditLog::open_file(&dir)?;
+        Ok(ConsensusAuditLog {
+            dir,
+            max_file_bytes,
+            max_files,
+            file,
+            file_bytes,
+        })
+    }
+
+    fn open_file(dir: &Path) -> io::Result<(BufWriter<File>, u64)> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(CONSENSUS_AUDIT_LOG_FILE))?;
+        let file_bytes = file.metadata()?.len();
+        Ok((BufWriter::new(file), file_bytes))
+    }
+
+    /// Path of the `n`th most recently rotated log file, or of the current one if `n` is 0
+    pub fn file_path(&self, n: usize) -> PathBuf {
+        if n == 0 {
+            self.dir.join(CONSENSUS_AUDIT_LOG_FILE)
+        } else {
+            self.dir.join(format!("{}.{}", CONSENSUS_AUDIT_LOG_FILE, n))
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let _ = fs::remove_file(self.file_path(self.max_files));
+        for n in (0..self.max_files).rev() {
+            let from = self.file_path(n);
+            if from.exists() {
+                fs::rename(&from, self.file_path(n + 1))?;
+            }
+        }
+
+        let (file, file_bytes) = ConsensusAuditLog::open_file(&self.dir)?;
+        self.file = file;
+        self.file_bytes = file_bytes;
+        Ok(())
+    }
+
+    /// Append one block's writes to the log
+    pub fn append(&mut self, writes: &[ConsensusWrite]) -> io::Result<()> {
+        if self.file_bytes > 0 && self.file_bytes >= self.max_file_bytes {
+            self.rotate()?;
+        }
+        for write in writes.iter() {
+            let mut line = serde_json::to_vec(write)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            line.push(b'\n');
+            self.file.write_all(&line)?;
+            self.file_bytes += line.len() as u64;
+        }
+        self.file.flush()
+    }
+}
+
+impl StacksChainState {
+    /// Start recording the writes made by each block this chainstate processes from now on.
+    /// See `ConsensusAuditLog::open`.
+    pub fn enable_consensus_audit_log(
+        &mut self,
+        dir: &str,
+        max_file_bytes: u64,
+        max_files: usize,
+    ) -> io::Result<()> {
+        self.consensus_audit_log = Some(ConsensusAuditLog::open(dir, max_file_bytes, max_files)?);
+        Ok(())
+    }
+
+    /// List the writes a processed block made, in the order the audit log records them.
+    pub fn get_consensus_writes(
+        &self,
+        receipt: &StacksEpochReceipt,
+    ) -> Result<Vec<ConsensusWrite>, db_error> {
+        let index_block_hash = receipt.header.index_block_hash();
+        let block_height = receipt.header.block_height;
+        let make_write = |table: &str, key: String, value_hash: String| ConsensusWrite {
+            index_block_hash: index_block_hash.clone(),
+            block_height,
+            table: table.to_string(),
+            key,
+            value_hash,
+        };
+
+        let mut writes = vec![];
+        for (key, value) in receipt.marf_writes.iter() {
+            let value_hash = MARFValue::from_value(value).to_hex();
+            writes.push(make_write("marf", key.clone(), value_hash));
+        }
+        for (key, value) in receipt.metadata_writes.iter() {
+            let value_hash = Sha512Trunc256Sum::from_data(value.as_bytes()).to_hex();
+            writes.push(make_write("metadata_table", key.clone(), value_hash));
+        }
+
+        let args: &[&dyn ToSql] = &[&index_block_hash];
+        let mut stmt = self
+            .db()
+            .prepare("SELECT * FROM block_headers WHERE index_block_hash = ?1")?;
+        let headers = stmt.query_map(args, |row| hash_row(row))?;
+        for row_hash in headers {
+            writes.push(make_write(
+                "block_headers",
+                index_block_hash.to_string(),
+                row_hash?,
+            ));
+        }
+
+        let mut stmt = self.db().prepare(
+            "SELECT * FROM payments WHERE index_block_hash = ?1 ORDER BY vtxindex ASC, address ASC",
+        )?;
+        let payments = stmt.query_map(args, |row| {
+            let address: String = row.get("address");
+            let vtxindex: i64 = row.get("vtxindex");
+            (address, vtxindex, hash_row(row))
+        })?;
+        for payment in payments {
+            let (address, vtxindex, row_hash) = payment?;
+            writes.push(make_write(
+                "payments",
+                format!("{}/{}/{}", &index_block_hash, vtxindex, &address),
+                row_hash,
+            ));
+        }
+
+        Ok(writes)
+    }
+
+    /// Append each processed block's writes to the audit log, if it's enabled.  Failures are
+    /// logged, and do not stop block processing.
+    pub(crate) fn audit_consensus_writes(&mut self, receipts: &[StacksEpochReceipt]) {
+        if self.consensus_audit_log.is_none() {
+            return;
+        }
+
+        for receipt in receipts.iter() {
+            let writes = match self.get_consensus_writes(receipt) {
+                Ok(writes) => writes,
+                Err(e) => {
+                    error!(
+                        "Failed to collect consensus writes of block {}: {:?}",
+                        &receipt.header.index_block_hash(),
+                        &e
+                    );
+                    continue;
+                }
+            };
+            if let Some(ref mut audit_log) = self.consensus_audit_log {
+                if let Err(e) = audit_log.append(&writes) {
+                    error!(
+                        "Failed to write consensus writes of block {} to the audit log: {:?}",
+                        &receipt.header.index_block_hash(),
+                        &e
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn make_write(i: u64) -> ConsensusWrite {
+        ConsensusWrite {
+            index_block_hash: StacksBlockId([i as u8; 32]),
+            block_height: i,
+            table: "marf".to_string(),
+            key: format!("key-{}", i),
+            value_hash: MARFValue::from_value(&format!("value-{}", i)).to_hex(),
+        }
+    }
+
+    fn read_writes(path: &Path) -> Vec<ConsensusWrite> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_audit_log_rotation() {
+        let dir = "/tmp/stacks-consensus-audit-log-rotation";
+        let _ = fs::remove_dir_all(dir);
+
+        let line_len = serde_json::to_vec(&make_write(0)).unwrap().len() as u64 + 1;
+        {
+            // rotate after every 2 blocks of 2 writes each, keeping 2 old files
+            let mut audit_log = ConsensusAuditLog::open(dir, 4 * line_len, 2).unwrap();
+            for i in 0..7 {
+                audit_log
+                    .append(&[make_write(2 * i), make_write(2 * i + 1)])
+                    .unwrap();
+            }
+        }
+
+        // blocks 0-1 were rotated out
+        let audit_log = ConsensusAuditLog::open(dir, 4 * line_len, 2).unwrap();
+        assert!(!audit_log.file_path(3).exists());
+        assert_eq!(
+            read_writes(&audit_log.file_path(2)),
+            (4..8).map(make_write).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            read_writes(&audit_log.file_path(1)),
+            (8..12).map(make_write).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            read_writes(&audit_log.file_path(0)),
+            (12..14).map(make_write).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_audit_log_reopen_appends() {
+        let dir = "/tmp/stacks-consensus-audit-log-reopen";
+        let _ = fs::remove_dir_all(dir);
+
+        for i in 0..3 {
+            let mut audit_log =
+                ConsensusAuditLog::open(dir, DEFAULT_AUDIT_LOG_MAX_FILE_BYTES, 1).unwrap();
+            audit_log.append(&[make_write(i)]).unwrap();
+        }
+
+        let audit_log = ConsensusAuditLog::open(dir, DEFAULT_AUDIT_LOG_MAX_FILE_BYTES, 1).unwrap();
+        assert_eq!(
+            read_writes(&audit_log.file_path(0)),
+            (0..3).map(make_write).collect::<Vec<_>>()
+        );
+        assert!(!audit_log.file_path(1).exists());
+    }
+}
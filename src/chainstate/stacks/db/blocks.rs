@@ -41,6 +41,7 @@ use chainstate::stacks::*;
 use chainstate::burn::BlockSnapshot;
 
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use util::db::Error as db_error;
 use util::db::{
@@ -62,7 +63,7 @@ use net::Error as net_error;
 use net::MAX_MESSAGE_LEN;
 
 use vm::types::{
-    AssetIdentifier, PrincipalData, QualifiedContractIdentifier, SequenceData,
+    AssetIdentifier, BuffData, PrincipalData, QualifiedContractIdentifier, SequenceData,
     StandardPrincipalData, TupleData, TypeSignature, Value,
 };
 
@@ -83,6 +84,8 @@ use vm::costs::LimitedCostTracker;
 use rand::thread_rng;
 use rand::RngCore;
 
+use rayon::prelude::*;
+
 use rusqlite::{Error as sqlite_error, OptionalExtension};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -143,6 +146,10 @@ pub enum MemPoolRejection {
     NoCoinbaseViaMempool,
     NoSuchChainTip(ConsensusHash, BlockHeaderHash),
     ConflictingNonceInMempool,
+    TransactionTooLarge(u64, u64),
+    BannedContract(QualifiedContractIdentifier),
+    TooManyPendingTransactions(PrincipalData, u64),
+    AdmissionPredicateRejected(String),
     TooMuchChaining {
         max_nonce: u64,
         actual_nonce: u64,
@@ -216,6 +223,27 @@ impl MemPoolRejection {
                 Some(json!({"message": e.to_string()})),
             ),
             ConflictingNonceInMempool => ("ConflictingNonceInMempool", None),
+            TransactionTooLarge(actual, max) => (
+                "TransactionTooLarge",
+                Some(json!({
+                    "expected": max,
+                    "actual": actual})),
+            ),
+            BannedContract(id) => (
+                "BannedContract",
+                Some(json!({ "contract_identifier": id.to_string() })),
+            ),
+            TooManyPendingTransactions(principal, max) => (
+                "TooManyPendingTransactions",
+                Some(json!({
+                    "message": "Origin has too many pending transactions in mempool",
+                    "expected": max,
+                    "principal": principal.to_string()})),
+            ),
+            AdmissionPredicateRejected(message) => (
+                "AdmissionPredicateRejected",
+                Some(json!({ "message": message })),
+            ),
             ContractAlreadyExists(id) => (
                 "ContractAlreadyExists",
                 Some(json!({ "contract_identifier": id.to_string() })),
@@ -254,6 +282,12 @@ impl From<db_error> for MemPoolRejection {
     }
 }
 
+/// Public function that a contract paid a matured miner reward may define, to act on the reward
+/// (e.g. to split it among a mining pool's members).  It is called as
+/// `(receive-reward amount miner)` with the contract itself as `tx-sender`, once the reward has
+/// been credited to the contract.
+pub const MINER_REWARD_HOOK_FUNCTION: &str = "receive-reward";
+
 // These constants are mempool acceptance heuristics, but
 //  not part of the protocol consensus (i.e., a block
 //  that includes a transaction that violates these won't
@@ -1383,6 +1417,69 @@ impl StacksChainState {
         }
     }
 
+    /// Find microblock streams that fork -- i.e. two unorphaned microblocks off of the same
+    /// parent anchored block with the same sequence number -- where the parent anchored block
+    /// is at or above `min_height`.  Only the lowest-sequence fork of each stream is returned,
+    /// as a poison-microblock payload paired with the parent's index block hash.
+    ///
+    /// DO NOT USE IN CONSENSUS CODE.
+    pub fn find_microblock_stream_forks(
+        blocks_conn: &DBConn,
+        min_height: u64,
+    ) -> Result<Vec<(StacksBlockId, TransactionPayload)>, Error> {
+        let sql = "SELECT m.* FROM staging_microblocks m JOIN staging_blocks s ON m.index_block_hash = s.index_block_hash \
+                   WHERE m.orphaned = 0 AND s.height >= ?1 AND EXISTS \
+                   (SELECT 1 FROM staging_microblocks o WHERE o.index_block_hash = m.index_block_hash AND o.sequence = m.sequence AND o.microblock_hash != m.microblock_hash AND o.orphaned = 0) \
+                   ORDER BY m.index_block_hash ASC, m.sequence ASC, m.microblock_hash ASC";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(min_height)?];
+        let staging_microblocks =
+            query_rows::<StagingMicroblock, _>(blocks_conn, sql, args).map_err(Error::DBError)?;
+
+        let mut ret = vec![];
+        for pair in staging_microblocks.windows(2) {
+            let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                &pair[0].consensus_hash,
+                &pair[0].anchored_block_hash,
+            );
+            if pair[0].consensus_hash != pair[1].consensus_hash
+                || pair[0].anchored_block_hash != pair[1].anchored_block_hash
+                || pair[0].sequence != pair[1].sequence
+            {
+                continue;
+            }
+            if let Some((ref last_parent, _)) = ret.last() {
+                if *last_parent == parent_index_block_hash {
+                    // already have this stream's lowest fork
+                    continue;
+                }
+            }
+
+            let mut headers = vec![];
+            for staging_mblock in pair.iter() {
+                let mblock_data = StacksChainState::load_staging_microblock_bytes(
+                    blocks_conn,
+                    &staging_mblock.microblock_hash,
+                )?
+                .ok_or(Error::DBError(db_error::NotFoundError))?;
+                let mblock = StacksMicroblock::consensus_deserialize(&mut &mblock_data[..])
+                    .map_err(Error::NetError)?;
+                headers.push(mblock.header);
+            }
+            let header_2 = headers.pop().expect("BUG: no second header");
+            let header_1 = headers.pop().expect("BUG: no first header");
+
+            debug!(
+                "Microblock fork found off of {} at sequence {}",
+                &parent_index_block_hash, pair[0].sequence
+            );
+            ret.push((
+                parent_index_block_hash,
+                TransactionPayload::PoisonMicroblock(header_1, header_2),
+            ));
+        }
+        Ok(ret)
+    }
+
     /// Load up the next block in a microblock stream, assuming there is only one child.
     /// If there are zero children, or more than one child, then returns None.
     ///
@@ -2957,9 +3054,44 @@ impl StacksChainState {
         block: &StacksBlock,
         mainnet: bool,
         chain_id: u32,
+    ) -> Result<Option<(u64, u64)>, Error> {
+        let burns = match StacksChainState::validate_anchored_header_burnchain(
+            db_handle,
+            consensus_hash,
+            &block.header,
+        )? {
+            Some(burns) => burns,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        // static checks on transactions all pass
+        let valid = block.validate_transactions_static(mainnet, chain_id);
+        if !valid {
+            warn!(
+                "Invalid block, transactions failed static checks: {}/{}",
+                consensus_hash,
+                block.block_hash()
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(burns))
+    }
+
+    /// Validate an anchored block header against the burn chain state: it must be the block that
+    /// won the sortition with the given consensus hash, build off of the parent the winning
+    /// block-commit named, and carry a VRF proof from the winning leader's key.
+    /// Returns Some(commit burn, total burn) if valid
+    /// Returns None if not valid
+    pub fn validate_anchored_header_burnchain(
+        db_handle: &SortitionHandleConn,
+        consensus_hash: &ConsensusHash,
+        header: &StacksBlockHeader,
     ) -> Result<Option<(u64, u64)>, Error> {
         // sortition-winning block commit for this block?
-        let block_hash = block.block_hash();
+        let block_hash = header.block_hash();
         let (block_commit, stacks_chain_tip) = match db_handle
             .get_block_snapshot_of_parent_stacks_block(consensus_hash, &block_hash)
         {
@@ -3004,7 +3136,7 @@ impl StacksChainState {
             .expect("FATAL: have block commit but no leader key");
 
         // attaches to burn chain
-        match block.header.validate_burnchain(
+        match header.validate_burnchain(
             &burn_chain_tip,
             &penultimate_sortition_snapshot,
             &leader_key,
@@ -3022,16 +3154,6 @@ impl StacksChainState {
             }
         };
 
-        // static checks on transactions all pass
-        let valid = block.validate_transactions_static(mainnet, chain_id);
-        if !valid {
-            warn!(
-                "Invalid block, transactions failed static checks: {}/{}",
-                consensus_hash, block_hash
-            );
-            return Ok(None);
-        }
-
         let sortition_burns =
             SortitionDB::get_block_burn_amount(db_handle, &penultimate_sortition_snapshot)
                 .expect("FATAL: have block commit but no total burns in its sortition");
@@ -3109,6 +3231,25 @@ impl StacksChainState {
             return Ok(false);
         }
 
+        // too far behind the canonical tip to be worth processing?
+        if let Some(max_fork_depth) = self.max_fork_depth {
+            let canonical_sn = SortitionDB::get_canonical_burn_chain_tip(sort_ic)?;
+            let block_height = block.header.total_work.work;
+            if block_height.saturating_add(max_fork_depth)
+                < canonical_sn.canonical_stacks_tip_height
+            {
+                warn!(
+                    "Refusing to store block {}/{} at height {}: more than {} blocks behind the canonical Stacks tip at height {}",
+                    consensus_hash,
+                    &block.block_hash(),
+                    block_height,
+                    max_fork_depth,
+                    canonical_sn.canonical_stacks_tip_height
+                );
+                return Ok(false);
+            }
+        }
+
         // find all user burns that supported this block
         let user_burns = sort_handle.get_winning_user_burns_by_block()?;
 
@@ -3377,9 +3518,14 @@ impl StacksChainState {
         } else {
             miner_auth.address_testnet()
         };
+        let recipient = match coinbase_tx.payload {
+            TransactionPayload::Coinbase(_, ref recipient_opt) => recipient_opt.clone(),
+            _ => None,
+        };
 
         let miner_reward = MinerPaymentSchedule {
             address: miner_addr,
+            recipient,
             block_hash: block.block_hash(),
             consensus_hash: block_consensus_hash.clone(),
             parent_block_hash: parent_block_hash.clone(),
@@ -3716,6 +3862,7 @@ impl StacksChainState {
     }
 
     /// Process a stream of microblocks
+    /// Each microblock's signatures are checked up front, like an anchored block's.
     /// Return the fees and burns.
     pub fn process_microblocks_transactions(
         clarity_tx: &mut ClarityTx,
@@ -3726,10 +3873,15 @@ impl StacksChainState {
         let mut receipts = vec![];
         for microblock in microblocks.iter() {
             debug!("Process microblock {}", &microblock.block_hash());
-            for tx in microblock.txs.iter() {
-                let (tx_fee, tx_receipt) =
-                    StacksChainState::process_transaction(clarity_tx, tx, false)
-                        .map_err(|e| (e, microblock.block_hash()))?;
+            let verified = StacksChainState::verify_block_transactions(&microblock.txs);
+            for (tx, tx_verified) in microblock.txs.iter().zip(verified.into_iter()) {
+                let (tx_fee, tx_receipt) = StacksChainState::process_transaction_verified(
+                    clarity_tx,
+                    tx,
+                    tx_verified,
+                    false,
+                )
+                .map_err(|e| (e, microblock.block_hash()))?;
 
                 fees = fees.checked_add(tx_fee as u128).expect("Fee overflow");
                 burns = burns
@@ -3783,9 +3935,9 @@ impl StacksChainState {
                                    "burn_block" => %burn_header_hash,
                                    "contract_call_ecode" => %resp.data);
                         }
-                        let mut execution_cost = clarity_tx.cost_so_far();
-                        execution_cost
-                            .sub(&cost_so_far)
+                        let execution_cost = clarity_tx
+                            .cost_so_far()
+                            .checked_sub(&cost_so_far)
                             .expect("BUG: cost declined between executions");
                         cost_so_far = clarity_tx.cost_so_far();
 
@@ -3834,12 +3986,18 @@ impl StacksChainState {
                             sender,
                             recipient,
                             transfered_ustx,
+                            memo,
                             txid,
                             burn_header_hash,
                             ..
                         } = transfer_stx_op;
                         let result = clarity_tx.connection().as_transaction(|tx| {
-                            tx.run_stx_transfer(&sender.into(), &recipient.into(), transfered_ustx)
+                            tx.run_stx_transfer(
+                                &sender.into(),
+                                &recipient.into(),
+                                transfered_ustx,
+                                &BuffData { data: memo },
+                            )
                         });
                         match result {
                             Ok((value, _, events)) => Some(StacksTransactionReceipt {
@@ -3866,7 +4024,16 @@ impl StacksChainState {
         all_receipts
     }
 
+    /// Check the signatures of an anchored block's or a microblock's transactions on the worker
+    /// pool, instead of one at a time while they are executed.  Returns each transaction's result,
+    /// in block order.
+    fn verify_block_transactions(txs: &[StacksTransaction]) -> Vec<Result<(), net_error>> {
+        txs.par_iter().map(|tx| tx.verify()).collect()
+    }
+
     /// Process a single anchored block.
+    /// All signatures are checked up front, but each transaction's check is only acted on when
+    /// that transaction is processed, so the first failing transaction's error is reported.
     /// Return the fees and burns.
     fn process_block_transactions(
         clarity_tx: &mut ClarityTx,
@@ -3875,9 +4042,10 @@ impl StacksChainState {
         let mut fees = 0u128;
         let mut burns = 0u128;
         let mut receipts = vec![];
-        for tx in block.txs.iter() {
+        let verified = StacksChainState::verify_block_transactions(&block.txs);
+        for (tx, tx_verified) in block.txs.iter().zip(verified.into_iter()) {
             let (tx_fee, tx_receipt) =
-                StacksChainState::process_transaction(clarity_tx, tx, false)?;
+                StacksChainState::process_transaction_verified(clarity_tx, tx, tx_verified, false)?;
             fees = fees.checked_add(tx_fee as u128).expect("Fee overflow");
             burns = burns
                 .checked_add(tx_receipt.stx_burned as u128)
@@ -3888,54 +4056,140 @@ impl StacksChainState {
     }
 
     /// Process a single matured miner reward.
-    /// Grant it STX tokens.
+    /// Grant it STX tokens.  If it is paid to a contract, then call the contract's reward hook.
+    /// Returns the events the hook emitted.
     fn process_matured_miner_reward<'a>(
         clarity_tx: &mut ClarityTx<'a>,
         miner_reward: &MinerReward,
-    ) -> Result<(), Error> {
+    ) -> Result<Vec<StacksTransactionEvent>, Error> {
         let miner_reward_total = miner_reward.total();
         clarity_tx
             .connection()
             .as_transaction(|x| {
                 x.with_clarity_db(|ref mut db| {
-                    let miner_principal = PrincipalData::Standard(StandardPrincipalData::from(
-                        miner_reward.address.clone(),
-                    ));
-                    let mut snapshot = db.get_stx_balance_snapshot(&miner_principal);
+                    let mut snapshot = db.get_stx_balance_snapshot(&miner_reward.recipient);
                     snapshot.credit(miner_reward_total);
 
-                    debug!(
-                        "Balance available for {} is {} STX",
-                        &miner_reward.address,
-                        snapshot.get_available_balance();
-                    );
+                    if miner_reward.is_burned() {
+                        debug!(
+                            "Burned miner reward of {} uSTX to {}",
+                            miner_reward_total, &miner_reward.recipient
+                        );
+                    } else {
+                        debug!(
+                            "Balance available for {} is {} STX",
+                            &miner_reward.recipient,
+                            snapshot.get_available_balance();
+                        );
+                    }
                     snapshot.save();
 
                     Ok(())
                 })
             })
             .map_err(Error::ClarityError)?;
-        Ok(())
+
+        let contract_id = match miner_reward.recipient {
+            PrincipalData::Contract(ref contract_id) => contract_id,
+            PrincipalData::Standard(_) => {
+                return Ok(vec![]);
+            }
+        };
+
+        // the hook is optional, and if it fails, the contract simply keeps the reward
+        let result = clarity_tx.connection().as_transaction(|tx| {
+            tx.run_contract_call(
+                &miner_reward.recipient,
+                contract_id,
+                MINER_REWARD_HOOK_FUNCTION,
+                &[
+                    Value::UInt(miner_reward_total),
+                    Value::Principal(PrincipalData::from(miner_reward.address.clone())),
+                ],
+                |_, _| false,
+            )
+        });
+        match result {
+            Ok((value, _, events)) => {
+                debug!("Called miner reward hook";
+                       "contract" => %contract_id,
+                       "amount" => %miner_reward_total,
+                       "result" => %value);
+                Ok(events)
+            }
+            Err(e) => {
+                debug!("Did not call miner reward hook";
+                       "contract" => %contract_id,
+                       "amount" => %miner_reward_total,
+                       "error" => %format!("{:?}", &e));
+                Ok(vec![])
+            }
+        }
     }
 
     /// Process matured miner rewards for this block.
-    /// Returns the number of liquid uSTX created -- i.e. the coinbase
+    /// Returns the number of liquid uSTX created -- i.e. the coinbase -- and the events emitted
+    /// by the reward hooks of any contracts that were paid.
     pub fn process_matured_miner_rewards<'a>(
         clarity_tx: &mut ClarityTx<'a>,
         miner_share: &MinerReward,
         users_share: &Vec<MinerReward>,
-    ) -> Result<u128, Error> {
+    ) -> Result<(u128, Vec<StacksTransactionEvent>), Error> {
         let mut coinbase_reward = miner_share.coinbase;
-        StacksChainState::process_matured_miner_reward(clarity_tx, miner_share)?;
+        let mut events = StacksChainState::process_matured_miner_reward(clarity_tx, miner_share)?;
         for reward in users_share.iter() {
             coinbase_reward += reward.coinbase;
-            StacksChainState::process_matured_miner_reward(clarity_tx, reward)?;
+            events.append(&mut StacksChainState::process_matured_miner_reward(
+                clarity_tx, reward,
+            )?);
         }
-        Ok(coinbase_reward)
+        Ok((coinbase_reward, events))
     }
 
-    /// Process all STX that unlock at this block height.
-    /// Return the total number of uSTX unlocked in this block
+    /// Make the events that record matured miner rewards being credited to their recipients
+    pub fn make_matured_miner_reward_events(
+        rewards: &[MinerReward],
+        reward_info: &MinerRewardInfo,
+    ) -> Vec<StacksTransactionEvent> {
+        rewards
+            .iter()
+            .map(|reward| {
+                StacksTransactionEvent::STXEvent(STXEventType::STXMinerRewardEvent(
+                    STXMinerRewardEventData {
+                        recipient: reward.recipient.clone(),
+                        coinbase: reward.coinbase,
+                        tx_fees_anchored: reward.tx_fees_anchored,
+                        tx_fees_streamed_produced: reward.tx_fees_streamed_produced,
+                        tx_fees_streamed_confirmed: reward.tx_fees_streamed_confirmed,
+                        burned: reward.is_burned(),
+                        from_block_consensus_hash: reward_info.from_block_consensus_hash.clone(),
+                        from_stacks_block_hash: reward_info.from_stacks_block_hash.clone(),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Find the receipt of a block's coinbase transaction
+    fn find_coinbase_receipt(
+        receipts: &mut [StacksTransactionReceipt],
+    ) -> Option<&mut StacksTransactionReceipt> {
+        receipts.iter_mut().find(|receipt| {
+            if let TransactionOrigin::Stacks(ref tx) = receipt.transaction {
+                if let TransactionPayload::Coinbase(..) = tx.payload {
+                    return true;
+                }
+            }
+            false
+        })
+    }
+
+    /// Process all STX that unlock at this block height:  mint the genesis lockups scheduled for
+    /// this block height, and, once the fork has upgraded to Stacks 2.1, release the scheduled
+    /// account locks that have expired by this block's burn height into their accounts' unlocked
+    /// balances, up to MAX_STX_UNLOCKS_PER_BLOCK of them.
+    /// Return the total number of uSTX minted in this block (released locks were already liquid),
+    /// and the events for both.
     pub fn process_stx_unlocks<'a>(
         clarity_tx: &mut ClarityTx<'a>,
     ) -> Result<(u128, Vec<StacksTransactionEvent>), Error> {
@@ -3952,9 +4206,9 @@ impl StacksChainState {
                 let entries = match result {
                     Value::Optional(_) => match result.expect_optional() {
                         Some(Value::Sequence(SequenceData::List(entries))) => entries.data,
-                        _ => return Ok((0, vec![])),
+                        _ => vec![],
                     },
-                    _ => return Ok((0, vec![])),
+                    _ => vec![],
                 };
 
                 let mut total_minted = 0;
@@ -3976,6 +4230,39 @@ impl StacksChainState {
                     let event = STXEventType::STXMintEvent(STXMintEventData { recipient, amount });
                     events.push(StacksTransactionEvent::STXEvent(event));
                 }
+
+                let mut unlock_events = tx_connection.with_clarity_db(|db| {
+                    let mut unlock_events = vec![];
+                    if !db.is_stacks_2_1_active() {
+                        return Ok(unlock_events);
+                    }
+
+                    let burn_block_height = db.get_current_burnchain_block_height() as u64;
+                    for principal in db
+                        .take_stx_unlocks(burn_block_height, MAX_STX_UNLOCKS_PER_BLOCK)
+                        .into_iter()
+                    {
+                        let mut snapshot = db.get_stx_balance_snapshot(&principal);
+                        let unlocked = snapshot.unlock_available_tokens_if_any();
+                        if unlocked == 0 {
+                            // the lock was moved, or already released
+                            continue;
+                        }
+                        snapshot.save();
+                        debug!(
+                            "Released {} expired uSTX of {} at burn height {}",
+                            unlocked, &principal, burn_block_height
+                        );
+                        let event = STXEventType::STXUnlockEvent(STXUnlockEventData {
+                            unlocked_amount: unlocked,
+                            unlocked_address: principal,
+                        });
+                        unlock_events.push(StacksTransactionEvent::STXEvent(event));
+                    }
+                    Ok(unlock_events)
+                })?;
+                events.append(&mut unlock_events);
+
                 Ok((total_minted, events))
             })
             .map_err(Error::ClarityError)
@@ -4029,6 +4316,9 @@ impl StacksChainState {
             total_liquid_ustx,
             matured_rewards,
             matured_rewards_info,
+            marf_writes,
+            metadata_writes,
+            marf_commit_time,
         ) = {
             let (parent_consensus_hash, parent_block_hash) = if block.is_first_mined() {
                 // has to be the sentinal hashes if this block has no parent
@@ -4188,9 +4478,9 @@ impl StacksChainState {
                 };
 
             // find microblock cost
-            let mut microblock_cost = clarity_tx.cost_so_far();
-            microblock_cost
-                .sub(&parent_block_cost)
+            let microblock_cost = clarity_tx
+                .cost_so_far()
+                .checked_sub(&parent_block_cost)
                 .expect("BUG: block_cost + microblock_cost < block_cost");
 
             // if we get here, then we need to reset the block-cost back to 0 since this begins the
@@ -4235,18 +4525,19 @@ impl StacksChainState {
             let block_cost = clarity_tx.cost_so_far();
 
             // grant matured miner rewards
-            let new_liquid_miner_ustx = if let Some((ref miner_reward, ref user_rewards, _)) =
-                matured_miner_rewards_opt.as_ref()
-            {
-                // grant in order by miner, then users
-                StacksChainState::process_matured_miner_rewards(
-                    &mut clarity_tx,
-                    miner_reward,
-                    user_rewards,
-                )?
-            } else {
-                0
-            };
+            let (new_liquid_miner_ustx, mut reward_hook_events) =
+                if let Some((ref miner_reward, ref user_rewards, _)) =
+                    matured_miner_rewards_opt.as_ref()
+                {
+                    // grant in order by miner, then users
+                    StacksChainState::process_matured_miner_rewards(
+                        &mut clarity_tx,
+                        miner_reward,
+                        user_rewards,
+                    )?
+                } else {
+                    (0, vec![])
+                };
 
             // obtain reward info for receipt
             let (matured_rewards, matured_rewards_info) =
@@ -4261,14 +4552,32 @@ impl StacksChainState {
                     (vec![], None)
                 };
 
+            // record the credited rewards with the block's coinbase
+            if let Some(ref reward_info) = matured_rewards_info {
+                if let Some(coinbase_receipt) =
+                    StacksChainState::find_coinbase_receipt(&mut receipts)
+                {
+                    coinbase_receipt.events.extend(
+                        StacksChainState::make_matured_miner_reward_events(
+                            &matured_rewards,
+                            reward_info,
+                        ),
+                    );
+                    coinbase_receipt.events.append(&mut reward_hook_events);
+                }
+            }
+
             // total burns
             let total_burnt = block_burns
                 .checked_add(microblock_burns)
                 .expect("Overflow: Too many STX burnt");
 
-            // unlock any uSTX
-            let (new_unlocked_ustx, _unlocked_events) =
+            // unlock any uSTX, and record the unlocks with the block's coinbase
+            let (new_unlocked_ustx, unlocked_events) =
                 StacksChainState::process_stx_unlocks(&mut clarity_tx)?;
+            if let Some(coinbase_receipt) = StacksChainState::find_coinbase_receipt(&mut receipts) {
+                coinbase_receipt.events.extend(unlocked_events);
+            }
 
             // calculate total liquid uSTX
             let total_liquid_ustx = parent_chain_tip
@@ -4321,7 +4630,11 @@ impl StacksChainState {
             debug!("Reached state root {}", root_hash);
 
             // good to go!
+            let marf_writes = clarity_tx.take_marf_writes();
+            let metadata_writes = clarity_tx.take_metadata_writes();
+            let commit_start = Instant::now();
             clarity_tx.commit_to_block(chain_tip_consensus_hash, &block.block_hash());
+            let marf_commit_time = commit_start.elapsed();
 
             // figure out if there any accumulated rewards by
             //   getting the snapshot that elected this block.
@@ -4367,6 +4680,9 @@ impl StacksChainState {
                 total_liquid_ustx,
                 matured_rewards,
                 matured_rewards_info,
+                marf_writes,
+                metadata_writes,
+                marf_commit_time,
             )
         };
 
@@ -4394,6 +4710,20 @@ impl StacksChainState {
         .expect("FATAL: failed to advance chain tip");
 
         chainstate_tx.log_transactions_processed(&new_tip.index_block_hash(), &tx_receipts);
+        chainstate_tx.log_asset_holdings(&tx_receipts);
+        chainstate_tx.log_pox_delegations(&tx_receipts);
+        chainstate_tx.log_first_seen_principals(
+            &new_tip.index_block_hash(),
+            new_tip.block_height,
+            &tx_receipts,
+        );
+        chainstate_tx.log_principal_transactions(
+            &new_tip.index_block_hash(),
+            new_tip.block_height,
+            &tx_receipts,
+        );
+        chainstate_tx.log_transaction_receipts(&new_tip.index_block_hash(), &tx_receipts);
+        chainstate_tx.log_event_bloom(&new_tip.index_block_hash(), &tx_receipts);
 
         let epoch_receipt = StacksEpochReceipt {
             header: new_tip,
@@ -4402,6 +4732,9 @@ impl StacksChainState {
             matured_rewards_info,
             parent_microblocks_cost: microblock_execution_cost,
             anchored_block_cost: block_execution_cost,
+            marf_writes,
+            metadata_writes,
+            marf_commit_time,
         };
 
         Ok(epoch_receipt)
@@ -4410,7 +4743,7 @@ impl StacksChainState {
     /// Verify that a Stacks anchored block attaches to its parent anchored block.
     /// * checks .header.total_work.work
     /// * checks .header.parent_block
-    fn check_block_attachment(
+    pub fn check_block_attachment(
         parent_block_header: &StacksBlockHeader,
         block_header: &StacksBlockHeader,
     ) -> bool {
@@ -4433,6 +4766,38 @@ impl StacksChainState {
         return true;
     }
 
+    /// Verify that a Stacks anchored block fits within the given epoch size limits.
+    /// * checks the encoded block length against .max_epoch_size
+    /// * checks each transaction's encoded length against .max_tx_size
+    ///
+    /// Returns a description of the violation if the block is too big.
+    fn check_block_size_limits(
+        block: &StacksBlock,
+        block_size: u64,
+        size_limits: &EpochSizeLimits,
+    ) -> Option<String> {
+        if block_size >= size_limits.max_epoch_size {
+            return Some(format!(
+                "block is {} bytes, but the epoch size limit is {}",
+                block_size, size_limits.max_epoch_size
+            ));
+        }
+
+        for tx in block.txs.iter() {
+            let tx_len = tx.tx_len();
+            if tx_len > size_limits.max_tx_size {
+                return Some(format!(
+                    "transaction {} is {} bytes, but the transaction size limit is {}",
+                    tx.txid(),
+                    tx_len,
+                    size_limits.max_tx_size
+                ));
+            }
+        }
+
+        None
+    }
+
     /// Get the parent header info for a block we're processing, if it's known.
     /// The header info will be pulled from the headers DB, so this method only succeeds if the
     /// parent block has been processed.
@@ -4550,6 +4915,7 @@ impl StacksChainState {
         sort_tx: &mut SortitionHandleTx,
     ) -> Result<(Option<StacksEpochReceipt>, Option<TransactionPayload>), Error> {
         let blocks_path = self.blocks_path.clone();
+        let size_limits = self.size_limits.clone();
         let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
 
         // this is a transaction against both the headers and staging blocks databases!
@@ -4661,6 +5027,32 @@ impl StacksChainState {
             return Err(Error::InvalidStacksBlock(msg));
         }
 
+        // validation check -- the block and its transactions must fit within the epoch size limits
+        if let Some(msg) =
+            StacksChainState::check_block_size_limits(&block, block_size, &size_limits)
+        {
+            let msg = format!(
+                "Invalid stacks block {}/{} -- {}",
+                &next_staging_block.consensus_hash,
+                block.block_hash(),
+                &msg
+            );
+            warn!("{}", &msg);
+
+            // clear out
+            StacksChainState::set_block_processed(
+                chainstate_tx.deref_mut(),
+                None,
+                &blocks_path,
+                &next_staging_block.consensus_hash,
+                &next_staging_block.anchored_block_hash,
+                false,
+            )?;
+            chainstate_tx.commit().map_err(Error::DBError)?;
+
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
         // validation check -- validate parent microblocks and find the ones that connect the
         // block's parent to this block.
         let next_microblocks = StacksChainState::extract_connecting_microblocks(
@@ -4698,7 +5090,7 @@ impl StacksChainState {
         // attach the block to the chain state and calculate the next chain tip.
         // Execute the confirmed microblocks' transactions against the chain state, and then
         // execute the anchored block's transactions against the chain state.
-        let epoch_receipt = match StacksChainState::append_block(
+        let mut epoch_receipt = match StacksChainState::append_block(
             &mut chainstate_tx,
             clarity_instance,
             sort_tx,
@@ -4820,11 +5212,284 @@ impl StacksChainState {
             true,
         )?;
 
+        let commit_start = Instant::now();
         chainstate_tx.commit().map_err(Error::DBError)?;
+        epoch_receipt.marf_commit_time += commit_start.elapsed();
 
         Ok((Some(epoch_receipt), None))
     }
 
+    /// Validate a proposed anchored block that has not yet been mined, without storing it or
+    /// committing any of its state.  The block must be a child of the processed anchored block
+    /// identified by `parent_consensus_hash` and the block header's `parent_block`, and it must
+    /// confirm the stream of that block's microblocks named by its `parent_microblock`.
+    ///
+    /// The block goes through the same checks as a staging block does in
+    /// `process_next_staging_block`: transaction static checks, attachment to its parent, epoch
+    /// size limits, the parent microblock stream, the microblock public key hash, transaction
+    /// execution and budget, and the state root.  Since its sortition is not known yet, it is
+    /// evaluated at the miner's sentinel block hashes with the burnchain operations at the
+    /// canonical burnchain tip, just as a miner would build it, and then rolled back.
+    ///
+    /// Returns the anchored block's execution cost if the block is valid.  Returns
+    /// Error::NoSuchBlockError if the parent block or parent microblock stream is not known,
+    /// Error::InvalidStacksMicroblock if the confirmed microblock stream is invalid, and
+    /// Error::InvalidStacksBlock if the block itself is invalid.
+    pub fn validate_block_proposal(
+        &mut self,
+        burn_dbconn: &SortitionDBConn,
+        parent_consensus_hash: &ConsensusHash,
+        block: &StacksBlock,
+    ) -> Result<ExecutionCost, Error> {
+        let block_hash = block.block_hash();
+        let mainnet = self.mainnet;
+        let chain_id = self.chain_id;
+
+        // validation check -- static checks on transactions all pass
+        if !block.validate_transactions_static(mainnet, chain_id) {
+            let msg = format!(
+                "Invalid stacks block {} -- transactions failed static checks",
+                &block_hash
+            );
+            warn!("{}", &msg);
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        let (parent_consensus_hash, parent_block_hash) = if block.is_first_mined() {
+            (FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH)
+        } else {
+            (*parent_consensus_hash, block.header.parent_block)
+        };
+
+        let parent_header_info = match StacksChainState::get_anchored_block_header_info(
+            self.db(),
+            &parent_consensus_hash,
+            &parent_block_hash,
+        )? {
+            Some(hinfo) => hinfo,
+            None => {
+                debug!(
+                    "No parent block {}/{} processed for proposed block {}",
+                    &parent_consensus_hash, &parent_block_hash, &block_hash
+                );
+                return Err(Error::NoSuchBlockError);
+            }
+        };
+
+        // validation check -- the block must attach to its parent
+        if !StacksChainState::check_block_attachment(
+            &parent_header_info.anchored_header,
+            &block.header,
+        ) {
+            let msg = format!(
+                "Invalid stacks block {} -- does not attach to parent {}/{}",
+                &block_hash, &parent_consensus_hash, &parent_block_hash
+            );
+            warn!("{}", &msg);
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        // validation check -- the block and its transactions must fit within the epoch size limits
+        let block_size = block.serialize_to_vec().len() as u64;
+        if let Some(msg) =
+            StacksChainState::check_block_size_limits(block, block_size, &self.size_limits)
+        {
+            let msg = format!("Invalid stacks block {} -- {}", &block_hash, &msg);
+            warn!("{}", &msg);
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        // validation check -- the parent microblock stream must be known, and must connect the
+        // parent to this block.
+        let microblocks = if block.header.parent_microblock == EMPTY_MICROBLOCK_PARENT_HASH
+            && block.header.parent_microblock_sequence == 0
+        {
+            vec![]
+        } else {
+            let microblocks = match StacksChainState::load_microblock_stream_fork(
+                self.db(),
+                &parent_consensus_hash,
+                &parent_block_hash,
+                &block.header.parent_microblock,
+            )? {
+                Some(microblocks) => microblocks,
+                None => {
+                    debug!(
+                        "No microblock stream {}/{}-{} known for proposed block {}",
+                        &parent_consensus_hash,
+                        &parent_block_hash,
+                        &block.header.parent_microblock,
+                        &block_hash
+                    );
+                    return Err(Error::NoSuchBlockError);
+                }
+            };
+
+            match StacksChainState::validate_parent_microblock_stream(
+                &parent_header_info.anchored_header,
+                &block.header,
+                &microblocks,
+                true,
+            ) {
+                Some((terminus, _)) if terminus == microblocks.len() => microblocks,
+                _ => {
+                    let msg = format!(
+                        "Invalid stacks block {} -- parent microblock stream {}/{}-{} (seq {}) does not connect",
+                        &block_hash,
+                        &parent_consensus_hash,
+                        &parent_block_hash,
+                        &block.header.parent_microblock,
+                        block.header.parent_microblock_sequence
+                    );
+                    warn!("{}", &msg);
+                    return Err(Error::InvalidStacksBlock(msg));
+                }
+            }
+        };
+
+        let parent_index_hash =
+            StacksBlockHeader::make_index_block_hash(&parent_consensus_hash, &parent_block_hash);
+        let parent_block_cost =
+            StacksChainState::get_stacks_block_anchored_cost(self.db(), &parent_index_hash)?
+                .ok_or(Error::NoSuchBlockError)?;
+
+        // find matured miner rewards, so we can grant them within the Clarity DB tx.
+        let latest_matured_miners = {
+            let mut tx = self.index_tx_begin()?;
+            StacksChainState::get_scheduled_block_rewards(&mut tx, &parent_header_info)?
+        };
+
+        // this block would be mined in the next sortition, so it sees the burnchain operations
+        // at the current burnchain tip.
+        let burn_tip = SortitionDB::get_canonical_chain_tip_bhh(burn_dbconn.conn())?;
+        let stacking_burn_ops = SortitionDB::get_stack_stx_ops(burn_dbconn.conn(), &burn_tip)?;
+        let transfer_burn_ops = SortitionDB::get_transfer_stx_ops(burn_dbconn.conn(), &burn_tip)?;
+
+        let mut clarity_tx = self.block_begin(
+            burn_dbconn,
+            &parent_consensus_hash,
+            &parent_block_hash,
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+        clarity_tx.reset_cost(parent_block_cost);
+
+        let matured_miner_rewards_opt = match StacksChainState::find_mature_miner_rewards(
+            &mut clarity_tx,
+            &parent_header_info,
+            latest_matured_miners,
+        ) {
+            Ok(miner_rewards_opt) => miner_rewards_opt,
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(e);
+            }
+        };
+
+        // validation check -- is this microblock public key hash new to this fork?
+        match StacksChainState::has_microblock_pubkey_hash(
+            &mut clarity_tx,
+            &block.header.microblock_pubkey_hash,
+        ) {
+            Ok(Some(height)) => {
+                let msg = format!(
+                    "Invalid stacks block {} -- already used microblock pubkey hash {} at height {}",
+                    &block_hash, &block.header.microblock_pubkey_hash, height
+                );
+                warn!("{}", &msg);
+
+                clarity_tx.rollback_block();
+                return Err(Error::InvalidStacksBlock(msg));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(e);
+            }
+        }
+
+        // process microblock stream
+        if let Err((e, offending_mblock_header_hash)) =
+            StacksChainState::process_microblocks_transactions(&mut clarity_tx, &microblocks)
+        {
+            let msg = format!(
+                "Invalid Stacks microblocks {},{} (offender {}): {:?}",
+                block.header.parent_microblock,
+                block.header.parent_microblock_sequence,
+                offending_mblock_header_hash,
+                &e
+            );
+            warn!("{}", &msg);
+
+            clarity_tx.rollback_block();
+            return Err(Error::InvalidStacksMicroblock(
+                msg,
+                offending_mblock_header_hash,
+            ));
+        }
+
+        // this begins the epoch defined by the proposed block
+        clarity_tx.reset_cost(ExecutionCost::zero());
+
+        StacksChainState::process_stacking_ops(&mut clarity_tx, stacking_burn_ops);
+        StacksChainState::process_transfer_ops(&mut clarity_tx, transfer_burn_ops);
+
+        // process anchored block
+        if let Err(e) = StacksChainState::process_block_transactions(&mut clarity_tx, block) {
+            let msg = format!("Invalid Stacks block {}: {:?}", &block_hash, &e);
+            warn!("{}", &msg);
+
+            clarity_tx.rollback_block();
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        let block_cost = clarity_tx.cost_so_far();
+
+        // grant matured miner rewards, unlock any uSTX, and record the microblock public key hash,
+        // so the state root can be computed
+        let finish_result = match matured_miner_rewards_opt {
+            Some((ref miner_reward, ref user_rewards, _)) => {
+                StacksChainState::process_matured_miner_rewards(
+                    &mut clarity_tx,
+                    miner_reward,
+                    user_rewards,
+                )
+                .map(|_| ())
+            }
+            None => Ok(()),
+        }
+        .and_then(|_| StacksChainState::process_stx_unlocks(&mut clarity_tx).map(|_| ()))
+        .and_then(|_| {
+            StacksChainState::insert_microblock_pubkey_hash(
+                &mut clarity_tx,
+                block.header.total_work.work as u32,
+                &block.header.microblock_pubkey_hash,
+            )
+        });
+
+        if let Err(e) = finish_result {
+            let msg = format!("Invalid Stacks block {}: {:?}", &block_hash, &e);
+            warn!("{}", &msg);
+
+            clarity_tx.rollback_block();
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        let root_hash = clarity_tx.get_root_hash();
+        clarity_tx.rollback_block();
+
+        if root_hash != block.header.state_index_root {
+            let msg = format!(
+                "Block {} state root mismatch: expected {}, got {}",
+                &block_hash, root_hash, block.header.state_index_root
+            );
+            warn!("{}", &msg);
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        Ok(block_cost)
+    }
+
     /// Process staging blocks at the canonical chain tip,
     ///  this only needs to be used in contexts that aren't
     ///  PoX aware (i.e., unit tests, and old stacks-node loops),
@@ -4897,6 +5562,15 @@ impl StacksChainState {
 
         sort_tx.commit()?;
 
+        if !self.indexers.is_empty() || self.consensus_audit_log.is_some() {
+            let processed: Vec<_> = ret
+                .iter()
+                .filter_map(|(receipt_opt, _)| receipt_opt.clone())
+                .collect();
+            self.audit_consensus_writes(&processed);
+            self.dispatch_to_indexers(&processed);
+        }
+
         let blocks_path = self.blocks_path.clone();
         let mut block_tx = self.db_tx_begin()?;
         for _ in 0..max_blocks {
@@ -4912,7 +5586,7 @@ impl StacksChainState {
         Ok(ret)
     }
 
-    fn is_valid_address_version(mainnet: bool, version: u8) -> bool {
+    pub fn is_valid_address_version(mainnet: bool, version: u8) -> bool {
         if mainnet {
             version == C32_ADDRESS_VERSION_MAINNET_SINGLESIG
                 || version == C32_ADDRESS_VERSION_MAINNET_MULTISIG
@@ -4949,14 +5623,17 @@ impl StacksChainState {
     }
 
     /// Check to see if a transaction can be (potentially) appended on top of a given chain tip.
-    /// Note that this only checks the transaction against the _anchored chain tip_, not the
-    /// unconfirmed microblock stream trailing off of it.
+    /// The transaction is first checked against the _anchored chain tip_.  If it fails the nonce
+    /// or balance checks there, and `check_unconfirmed` is true, then it is checked again against
+    /// the unconfirmed microblock stream trailing off of it (if this node tracks one).  This way,
+    /// transactions that depend on transactions confirmed in microblocks can be admitted.
     pub fn will_admit_mempool_tx(
         &mut self,
         current_consensus_hash: &ConsensusHash,
         current_block: &BlockHeaderHash,
         tx: &StacksTransaction,
         tx_size: u64,
+        check_unconfirmed: bool,
     ) -> Result<(), MemPoolRejection> {
         let conf = self.config();
         let staging_height =
@@ -5004,31 +5681,43 @@ impl StacksChainState {
             )),
         };
 
-        match res {
-            Ok(x) => Ok(x),
-            Err(MemPoolRejection::BadNonces(mismatch_error)) => {
-                // try again, but against the _unconfirmed_ chain tip, if we
-                // (a) have one, and (b) the expected nonce is less than the given one.
-                if self.unconfirmed_state.is_some()
-                    && mismatch_error.expected < mismatch_error.actual
-                {
-                    debug!("Transaction {} is unminable in the confirmed chain tip due to nonce {} != {}; trying the unconfirmed chain tip",
-                           &tx.txid(), mismatch_error.expected, mismatch_error.actual);
-                    self.with_read_only_unconfirmed_clarity_tx(&NULL_BURN_STATE_DB, |conn| {
-                        StacksChainState::can_include_tx(
-                            conn,
-                            &conf,
-                            has_microblock_pubk,
-                            tx,
-                            tx_size,
-                        )
-                    })
-                    .expect("BUG: do not have unconfirmed state, despite being Some(..)")
-                } else {
-                    Err(MemPoolRejection::BadNonces(mismatch_error))
-                }
+        let retry_unconfirmed = match res {
+            // try again, but against the _unconfirmed_ chain tip, if the expected nonce is less
+            // than the given one (i.e. the tx may depend on txs confirmed in microblocks).
+            Err(MemPoolRejection::BadNonces(ref mismatch_error)) => {
+                mismatch_error.expected < mismatch_error.actual
+            }
+            Err(MemPoolRejection::TooMuchChaining { .. }) => true,
+            // try again, since the payer may have received funds in a microblock.
+            Err(MemPoolRejection::NotEnoughFunds(..)) => true,
+            _ => false,
+        };
+
+        if !retry_unconfirmed || !check_unconfirmed {
+            return res;
+        }
+
+        let unconfirmed_matches_tip = match self.unconfirmed_state {
+            Some(ref unconfirmed) => unconfirmed.confirmed_chain_tip == current_tip,
+            None => false,
+        };
+        if !unconfirmed_matches_tip {
+            return res;
+        }
+
+        debug!(
+            "Transaction {} is unminable in the confirmed chain tip ({:?}); trying the unconfirmed chain tip",
+            &tx.txid(),
+            &res
+        );
+        match self.with_read_only_unconfirmed_clarity_tx(&NULL_BURN_STATE_DB, |conn| {
+            StacksChainState::can_include_tx(conn, &conf, has_microblock_pubk, tx, tx_size)
+        }) {
+            Some(unconfirmed_res) => unconfirmed_res,
+            None => {
+                // unconfirmed state is not readable at this time
+                res
             }
-            Err(e) => Err(e),
         }
     }
 
@@ -5109,6 +5798,15 @@ impl StacksChainState {
         let block_height = clarity_connection
             .with_clarity_db_readonly(|ref mut db| db.get_current_burnchain_block_height() as u64);
 
+        let stacks_2_1_active =
+            clarity_connection.with_clarity_db_readonly(|db| db.is_stacks_2_1_active());
+        StacksChainState::process_transaction_precheck_activation(
+            chainstate_config.mainnet,
+            stacks_2_1_active,
+            &tx,
+        )
+        .map_err(|e| MemPoolRejection::FailedToValidate(e))?;
+
         // 5: the paying account must have enough funds
         if !payer
             .stx_balance
@@ -5129,7 +5827,7 @@ impl StacksChainState {
 
         // 6: payload-specific checks
         match &tx.payload {
-            TransactionPayload::TokenTransfer(addr, amount, _memo) => {
+            TransactionPayload::TokenTransfer(addr, amount, _memo, _long_memo) => {
                 // version byte matches?
                 if !StacksChainState::is_valid_address_version(
                     chainstate_config.mainnet,
@@ -5215,7 +5913,7 @@ impl StacksChainState {
                     ));
                 }
             }
-            TransactionPayload::Coinbase(_) => return Err(MemPoolRejection::NoCoinbaseViaMempool),
+            TransactionPayload::Coinbase(..) => return Err(MemPoolRejection::NoCoinbaseViaMempool),
         };
 
         Ok(())
@@ -5258,7 +5956,7 @@ pub mod test {
         let mut tx_coinbase = StacksTransaction::new(
             TransactionVersion::Testnet,
             auth,
-            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
         );
         tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
         let mut tx_signer = StacksTransactionSigner::new(&tx_coinbase);
@@ -8839,6 +9537,86 @@ pub mod test {
         );
     }
 
+    #[test]
+    fn stacks_db_find_microblock_stream_forks() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "stacks_db_find_microblock_stream_forks");
+        let privk = StacksPrivateKey::from_hex(
+            "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+        )
+        .unwrap();
+
+        let block_1 = make_empty_coinbase_block(&privk);
+        let consensus_hash = ConsensusHash([2u8; 20]);
+        let parent_index_block_hash =
+            StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_1.block_hash());
+
+        let mut mblocks_1 = make_sample_microblock_stream(&privk, &block_1.block_hash());
+        mblocks_1.truncate(3);
+
+        store_staging_block(
+            &mut chainstate,
+            &consensus_hash,
+            &block_1,
+            &ConsensusHash([1u8; 20]),
+            1,
+            2,
+        );
+        for mblock in mblocks_1.iter() {
+            store_staging_microblock(
+                &mut chainstate,
+                &consensus_hash,
+                &block_1.block_hash(),
+                mblock,
+            );
+        }
+
+        // a single stream has no forks
+        assert_eq!(
+            StacksChainState::find_microblock_stream_forks(&chainstate.db(), 0).unwrap(),
+            vec![]
+        );
+
+        let mut mblocks_2 = make_sample_microblock_stream(&privk, &block_1.block_hash());
+        mblocks_2.truncate(3);
+        for mblock in mblocks_2.iter() {
+            store_staging_microblock(
+                &mut chainstate,
+                &consensus_hash,
+                &block_1.block_hash(),
+                mblock,
+            );
+        }
+
+        // only the lowest-sequence fork is reported
+        let forks = StacksChainState::find_microblock_stream_forks(&chainstate.db(), 0).unwrap();
+        assert_eq!(forks.len(), 1);
+        assert_eq!(forks[0].0, parent_index_block_hash);
+        match forks[0].1 {
+            TransactionPayload::PoisonMicroblock(ref h1, ref h2) => {
+                assert_eq!(h1.sequence, 0);
+                assert_eq!(h2.sequence, 0);
+                assert!(h1.block_hash() != h2.block_hash());
+                let mut hashes = vec![h1.block_hash(), h2.block_hash()];
+                hashes.sort();
+                let mut expected = vec![mblocks_1[0].block_hash(), mblocks_2[0].block_hash()];
+                expected.sort();
+                assert_eq!(hashes, expected);
+            }
+            _ => panic!("Not a poison-microblock payload"),
+        }
+
+        // forks off of blocks below the minimum height are ignored
+        assert_eq!(
+            StacksChainState::find_microblock_stream_forks(
+                &chainstate.db(),
+                block_1.header.total_work.work + 1
+            )
+            .unwrap(),
+            vec![]
+        );
+    }
+
     #[test]
     fn stacks_db_staging_microblocks_multiple_forks() {
         // multiple anchored blocks build off of a microblock stream that gets forked multiple
@@ -8999,6 +9777,251 @@ pub mod test {
         );
     }
 
+    #[test]
+    fn test_verify_block_transactions() {
+        let privk = StacksPrivateKey::from_hex(
+            "59e4d5e18351d6027a37920efe53c2f1cbadc50dca7d77169b7291dff936ed6d01",
+        )
+        .unwrap();
+        let recipient = StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x11; 20]),
+        };
+
+        let mut txs = vec![];
+        for nonce in 0..64 {
+            let mut tx = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                TransactionAuth::from_p2pkh(&privk).unwrap(),
+                TransactionPayload::TokenTransfer(
+                    recipient.to_account_principal(),
+                    123,
+                    TokenTransferMemo([0u8; 34]),
+                    None,
+                ),
+            );
+            tx.chain_id = 0x80000000;
+            tx.auth.set_origin_nonce(nonce);
+
+            let mut tx_signer = StacksTransactionSigner::new(&tx);
+            tx_signer.sign_origin(&privk).unwrap();
+            let mut tx_signed = tx_signer.get_tx().unwrap();
+
+            // every seventh transaction no longer matches its signature
+            if nonce % 7 == 3 {
+                tx_signed.set_fee_rate(1);
+            }
+            txs.push(tx_signed);
+        }
+
+        let verified = StacksChainState::verify_block_transactions(&txs);
+        assert_eq!(verified.len(), txs.len());
+        for (i, (tx, tx_verified)) in txs.iter().zip(verified.iter()).enumerate() {
+            assert_eq!(tx_verified.is_ok(), i % 7 != 3);
+            assert_eq!(tx_verified.is_ok(), tx.verify().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_process_microblocks_transactions_bad_signature() {
+        let mut chainstate = instantiate_chainstate(
+            false,
+            0x80000000,
+            "process-microblocks-transactions-bad-signature",
+        );
+        let privk = StacksPrivateKey::from_hex(
+            "59e4d5e18351d6027a37920efe53c2f1cbadc50dca7d77169b7291dff936ed6d01",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+
+        let make_stream = |tamper: bool| {
+            let mut txs = vec![];
+            for nonce in 0..4 {
+                let mut tx = StacksTransaction::new(
+                    TransactionVersion::Testnet,
+                    auth.clone(),
+                    TransactionPayload::new_smart_contract(
+                        &format!("hello-microblock-{}", nonce),
+                        &"(begin (+ 1 2))".to_string(),
+                    )
+                    .unwrap(),
+                );
+                tx.chain_id = 0x80000000;
+                tx.set_origin_nonce(nonce);
+                tx.set_fee_rate(0);
+
+                let mut tx_signer = StacksTransactionSigner::new(&tx);
+                tx_signer.sign_origin(&privk).unwrap();
+                let mut tx_signed = tx_signer.get_tx().unwrap();
+
+                // the last transaction no longer matches its signature
+                if tamper && nonce == 3 {
+                    tx_signed.set_fee_rate(1);
+                }
+                txs.push(tx_signed);
+            }
+
+            let mblock_1 =
+                StacksMicroblock::first_unsigned(&BlockHeaderHash([0x22; 32]), txs[0..2].to_vec());
+            let mblock_2 =
+                StacksMicroblock::from_parent_unsigned(&mblock_1.header, txs[2..4].to_vec())
+                    .unwrap();
+            vec![mblock_1, mblock_2]
+        };
+
+        // the stream is rejected at the microblock with the bad signature
+        let microblocks = make_stream(true);
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+        match StacksChainState::process_microblocks_transactions(&mut conn, &microblocks) {
+            Err((chainstate_error::NetError(_), mblock_hash)) => {
+                assert_eq!(mblock_hash, microblocks[1].block_hash());
+            }
+            res => panic!("expected a signature error: {:?}", &res),
+        }
+        conn.rollback_block();
+
+        // the same stream, correctly signed, is accepted
+        let microblocks = make_stream(false);
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+        let (_, _, receipts) =
+            StacksChainState::process_microblocks_transactions(&mut conn, &microblocks).unwrap();
+        assert_eq!(receipts.len(), 4);
+        conn.rollback_block();
+    }
+
+    #[test]
+    fn process_matured_miner_rewards_to_contracts() {
+        let mut chainstate = instantiate_chainstate(
+            false,
+            0x80000000,
+            "process-matured-miner-rewards-to-contracts",
+        );
+
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let addr = auth.origin().address_testnet();
+        let miner_addr = StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x11; 20]),
+        };
+        let user_addr = StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x22; 20]),
+        };
+
+        // pays half of each reward back to the miner that earned it
+        let pool_contract = "
+        (define-data-var received uint u0)
+        (define-public (receive-reward (amount uint) (miner principal))
+          (begin
+            (var-set received (+ (var-get received) amount))
+            (stx-transfer? (/ amount u2) tx-sender miner)))";
+        let plain_contract = "(define-data-var unused uint u0)";
+
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+
+        for (nonce, (name, code)) in [("pool", pool_contract), ("plain", plain_contract)]
+            .iter()
+            .enumerate()
+        {
+            let mut tx_contract = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                auth.clone(),
+                TransactionPayload::new_smart_contract(&name.to_string(), &code.to_string())
+                    .unwrap(),
+            );
+            tx_contract.chain_id = 0x80000000;
+            tx_contract.set_origin_nonce(nonce as u64);
+            tx_contract.set_fee_rate(0);
+
+            let mut signer = StacksTransactionSigner::new(&tx_contract);
+            signer.sign_origin(&privk).unwrap();
+            let signed_tx = signer.get_tx().unwrap();
+
+            StacksChainState::process_transaction(&mut conn, &signed_tx, false).unwrap();
+        }
+
+        let pool_principal = PrincipalData::Contract(QualifiedContractIdentifier::new(
+            StandardPrincipalData::from(addr.clone()),
+            "pool".into(),
+        ));
+        let plain_principal = PrincipalData::Contract(QualifiedContractIdentifier::new(
+            StandardPrincipalData::from(addr.clone()),
+            "plain".into(),
+        ));
+
+        let miner_reward = MinerReward {
+            address: miner_addr.clone(),
+            recipient: pool_principal.clone(),
+            coinbase: 1000,
+            tx_fees_anchored: 0,
+            tx_fees_streamed_produced: 0,
+            tx_fees_streamed_confirmed: 0,
+            vtxindex: 0,
+        };
+        let user_reward = MinerReward {
+            address: user_addr.clone(),
+            recipient: plain_principal.clone(),
+            coinbase: 500,
+            tx_fees_anchored: 0,
+            tx_fees_streamed_produced: 0,
+            tx_fees_streamed_confirmed: 0,
+            vtxindex: 1,
+        };
+
+        let (coinbase, events) = StacksChainState::process_matured_miner_rewards(
+            &mut conn,
+            &miner_reward,
+            &vec![user_reward],
+        )
+        .unwrap();
+        assert_eq!(coinbase, 1500);
+
+        // only the pool contract has a hook to call, and it paid the miner
+        match events.as_slice() {
+            [StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(data))] => {
+                assert_eq!(data.sender, pool_principal);
+                assert_eq!(data.recipient, PrincipalData::from(miner_addr.clone()));
+                assert_eq!(data.amount, 500);
+            }
+            _ => panic!("expected exactly one STX transfer event: {:?}", &events),
+        }
+
+        let pool_account = StacksChainState::get_account(&mut conn, &pool_principal);
+        assert_eq!(pool_account.stx_balance.amount_unlocked, 500);
+
+        let miner_account =
+            StacksChainState::get_account(&mut conn, &PrincipalData::from(miner_addr));
+        assert_eq!(miner_account.stx_balance.amount_unlocked, 500);
+
+        let plain_account = StacksChainState::get_account(&mut conn, &plain_principal);
+        assert_eq!(plain_account.stx_balance.amount_unlocked, 500);
+
+        conn.commit_block();
+    }
+
     // TODO: test multiple anchored blocks confirming the same microblock stream (in the same
     // place, and different places, with/without orphans)
     // TODO: process_next_staging_block
@@ -0,0 +1,297 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compare two nodes' chainstates.
+//!
+//! Given two (chainstate, sortition DB) pairs -- "a" and "b" -- find the highest Stacks block
+//! both have processed on their canonical forks, and then walk back from it comparing the
+//! stored headers and the headers and Clarity MARF roots of each block.  A sample of accounts is
+//! also compared at the common tip.  Used to check that a replica matches its source, and to
+//! find where two nodes parted ways after an incident.
+
+use std::collections::BTreeSet;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use rusqlite::NO_PARAMS;
+
+use chainstate::burn::db::sortdb::SortitionDB;
+use chainstate::stacks::db::{StacksAccount, StacksChainState, StacksHeaderInfo};
+use chainstate::stacks::index::marf::{MarfConnection, MARF};
+use chainstate::stacks::index::TrieHash;
+use chainstate::stacks::Error;
+use chainstate::stacks::{StacksBlockHeader, StacksBlockId};
+
+use util::db::DBConn;
+use util::db::Error as db_error;
+
+use vm::types::PrincipalData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainstateDivergence {
+    /// The sortition DBs have different canonical Stacks chain tips
+    CanonicalTip {
+        a_tip: StacksBlockId,
+        a_height: u64,
+        b_tip: StacksBlockId,
+        b_height: u64,
+    },
+    /// Both nodes processed the block, but stored different header info for it
+    Header {
+        index_block_hash: StacksBlockId,
+        a: StacksHeaderInfo,
+        b: StacksHeaderInfo,
+    },
+    /// The headers MARF roots at the block differ (None if the MARF does not have the block)
+    HeadersMarfRoot {
+        index_block_hash: StacksBlockId,
+        a: Option<TrieHash>,
+        b: Option<TrieHash>,
+    },
+    /// The Clarity MARF roots at the block differ (None if the MARF does not have the block)
+    ClarityMarfRoot {
+        index_block_hash: StacksBlockId,
+        a: Option<TrieHash>,
+        b: Option<TrieHash>,
+    },
+    /// An account's nonce or balance differs at the common tip
+    Account {
+        principal: PrincipalData,
+        a: StacksAccount,
+        b: StacksAccount,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainstateDiff {
+    /// Highest block on both canonical forks, if any was found within the search limit
+    pub common_tip: Option<StacksHeaderInfo>,
+    pub blocks_compared: u64,
+    pub accounts_compared: u64,
+    pub divergences: Vec<ChainstateDivergence>,
+}
+
+impl StacksChainState {
+    /// Compare chainstate `a` against chainstate `b`.  At most `max_blocks` blocks are compared
+    /// (walking back from the common tip), and at most `num_accounts` accounts are sampled from
+    /// the miners and asset holders either node knows about.
+    pub fn diff_chainstates(
+        a: &mut StacksChainState,
+        a_sortdb: &SortitionDB,
+        b: &mut StacksChainState,
+        b_sortdb: &SortitionDB,
+        max_blocks: u64,
+        num_accounts: usize,
+    ) -> Result<ChainstateDiff, Error> {
+        let mut divergences = vec![];
+
+        let a_sn = SortitionDB::get_canonical_burn_chain_tip(a_sortdb.conn())?;
+        let b_sn = SortitionDB::get_canonical_burn_chain_tip(b_sortdb.conn())?;
+        let a_tip = StacksBlockHeader::make_index_block_hash(
+            &a_sn.canonical_stacks_tip_consensus_hash,
+            &a_sn.canonical_stacks_tip_hash,
+        );
+        let b_tip = StacksBlockHeader::make_index_block_hash(
+            &b_sn.canonical_stacks_tip_consensus_hash,
+            &b_sn.canonical_stacks_tip_hash,
+        );
+        if a_tip != b_tip {
+            divergences.push(ChainstateDivergence::CanonicalTip {
+                a_tip: a_tip.clone(),
+                a_height: a_sn.canonical_stacks_tip_height,
+                b_tip: b_tip.clone(),
+                b_height: b_sn.canonical_stacks_tip_height,
+            });
+        }
+
+        // the common tip is the lower of the two tips, or its nearest ancestor the other node has
+        let common_tip = if a_sn.canonical_stacks_tip_height <= b_sn.canonical_stacks_tip_height {
+            StacksChainState::find_common_ancestor(a, b, &a_tip, max_blocks)?
+        } else {
+            StacksChainState::find_common_ancestor(b, a, &b_tip, max_blocks)?
+        };
+
+        let common_tip = match common_tip {
+            Some(common_tip) => common_tip,
+            None => {
+                return Ok(ChainstateDiff {
+                    common_tip: None,
+                    blocks_compared: 0,
+                    accounts_compared: 0,
+                    divergences,
+                });
+            }
+        };
+        let common_tip_id = common_tip.index_block_hash();
+
+        let mut a_clarity_marf =
+            MARF::<StacksBlockId>::from_path(&a.clarity_state_index_path).map_err(Error::from)?;
+        let mut b_clarity_marf =
+            MARF::<StacksBlockId>::from_path(&b.clarity_state_index_path).map_err(Error::from)?;
+
+        let mut blocks_compared = 0;
+        let mut cursor = common_tip_id.clone();
+        while blocks_compared < max_blocks {
+            let a_header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                a.db(),
+                &cursor,
+            )?;
+            let b_header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                b.db(),
+                &cursor,
+            )?;
+            let (a_header, b_header) = match (a_header, b_header) {
+                (Some(a_header), Some(b_header)) => (a_header, b_header),
+                _ => {
+                    // walked past the first block
+                    break;
+                }
+            };
+
+            if a_header != b_header {
+                divergences.push(ChainstateDivergence::Header {
+                    index_block_hash: cursor.clone(),
+                    a: a_header,
+                    b: b_header,
+                });
+            }
+
+            let a_root = a.state_index.get_root_hash_at(&cursor).ok();
+            let b_root = b.state_index.get_root_hash_at(&cursor).ok();
+            if a_root != b_root {
+                divergences.push(ChainstateDivergence::HeadersMarfRoot {
+                    index_block_hash: cursor.clone(),
+                    a: a_root,
+                    b: b_root,
+                });
+            }
+
+            let a_root = a_clarity_marf.get_root_hash_at(&cursor).ok();
+            let b_root = b_clarity_marf.get_root_hash_at(&cursor).ok();
+            if a_root != b_root {
+                divergences.push(ChainstateDivergence::ClarityMarfRoot {
+                    index_block_hash: cursor.clone(),
+                    a: a_root,
+                    b: b_root,
+                });
+            }
+
+            blocks_compared += 1;
+
+            let parent = a.get_parent(&cursor)?;
+            if parent == cursor {
+                break;
+            }
+            cursor = parent;
+        }
+
+        let mut principals: Vec<_> = StacksChainState::get_known_principals(a.db())?
+            .union(&StacksChainState::get_known_principals(b.db())?)
+            .cloned()
+            .collect();
+        principals.shuffle(&mut thread_rng());
+        principals.truncate(num_accounts);
+        principals.sort();
+
+        let mut accounts_compared = 0;
+        for principal_str in principals.iter() {
+            let principal = PrincipalData::parse(principal_str).map_err(|e| {
+                warn!("Unparseable principal {}: {:?}", principal_str, &e);
+                db_error::ParseError
+            })?;
+            let a_account =
+                a.with_read_only_clarity_tx(&a_sortdb.index_conn(), &common_tip_id, |conn| {
+                    StacksChainState::get_account(conn, &principal)
+                });
+            let b_account =
+                b.with_read_only_clarity_tx(&b_sortdb.index_conn(), &common_tip_id, |conn| {
+                    StacksChainState::get_account(conn, &principal)
+                });
+            if let (Some(a_account), Some(b_account)) = (a_account, b_account) {
+                if a_account != b_account {
+                    divergences.push(ChainstateDivergence::Account {
+                        principal,
+                        a: a_account,
+                        b: b_account,
+                    });
+                }
+                accounts_compared += 1;
+            }
+        }
+
+        Ok(ChainstateDiff {
+            common_tip: Some(common_tip),
+            blocks_compared,
+            accounts_compared,
+            divergences,
+        })
+    }
+
+    /// Walk back from `tip` in `ours` until we find a block `theirs` has also processed.
+    fn find_common_ancestor(
+        ours: &StacksChainState,
+        theirs: &StacksChainState,
+        tip: &StacksBlockId,
+        max_blocks: u64,
+    ) -> Result<Option<StacksHeaderInfo>, Error> {
+        let mut cursor = tip.clone();
+        for _ in 0..max_blocks {
+            if let Some(header) =
+                StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                    theirs.db(),
+                    &cursor,
+                )?
+            {
+                return Ok(Some(header));
+            }
+            if StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                ours.db(),
+                &cursor,
+            )?
+            .is_none()
+            {
+                return Ok(None);
+            }
+
+            let parent = ours.get_parent(&cursor)?;
+            if parent == cursor {
+                return Ok(None);
+            }
+            cursor = parent;
+        }
+        Ok(None)
+    }
+
+    /// Principals this chainstate has seen mine a block or hold an asset
+    fn get_known_principals(conn: &DBConn) -> Result<BTreeSet<String>, Error> {
+        let mut principals = BTreeSet::new();
+        for sql in [
+            "SELECT DISTINCT address FROM payments",
+            "SELECT DISTINCT principal FROM asset_holdings",
+        ]
+        .iter()
+        {
+            let mut stmt = conn.prepare(sql).map_err(db_error::SqliteError)?;
+            let rows = stmt
+                .query_map(NO_PARAMS, |row| row.get(0))
+                .map_err(db_error::SqliteError)?;
+            for row in rows {
+                principals.insert(row.map_err(db_error::SqliteError)?);
+            }
+        }
+        Ok(principals)
+    }
+}
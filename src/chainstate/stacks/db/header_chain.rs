@@ -0,0 +1,809 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use rusqlite::types::ToSql;
+use rusqlite::Transaction;
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+use rusqlite::{OptionalExtension, Row};
+
+use std::collections::HashMap;
+use std::fs;
+
+use chainstate::burn::db::sortdb::{
+    SortitionDB, SortitionDBConn, SortitionHandleConn, SortitionHandleTx,
+};
+use chainstate::burn::{BlockHeaderHash, ConsensusHash};
+use chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
+use chainstate::stacks::index::proofs::TrieMerkleProof;
+use chainstate::stacks::index::{MarfTrieId, TrieHash};
+use chainstate::stacks::Error;
+use chainstate::stacks::{StacksAddress, StacksBlockHeader, StacksBlockId};
+
+use util::db::tx_begin_immediate;
+use util::db::Error as db_error;
+use util::db::{query_count, query_row, query_row_columns, u64_to_sql, FromColumn, FromRow};
+use util::get_epoch_time_secs;
+
+use core::FIRST_BURNCHAIN_CONSENSUS_HASH;
+use core::FIRST_STACKS_BLOCK_HASH;
+
+pub const HEADER_CHAIN_DB_VERSION: &'static str = "1";
+
+const HEADER_CHAIN_DB_SETUP: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE headers(
+        version INTEGER NOT NULL,
+        total_burn TEXT NOT NULL,       -- converted to/from u64
+        total_work TEXT NOT NULL,       -- converted to/from u64
+        proof TEXT NOT NULL,
+        parent_block TEXT NOT NULL,
+        parent_microblock TEXT NOT NULL,
+        parent_microblock_sequence INTEGER NOT NULL,
+        tx_merkle_root TEXT NOT NULL,
+        state_index_root TEXT NOT NULL,
+        microblock_pubkey_hash TEXT NOT NULL,
+
+        block_hash TEXT NOT NULL,
+        consensus_hash TEXT NOT NULL,
+        index_block_hash TEXT UNIQUE NOT NULL,
+        parent_block_id TEXT NOT NULL,
+        block_height INTEGER NOT NULL,
+        burn_header_height INTEGER NOT NULL,
+        state_root TEXT NOT NULL,                -- root hash of this block's trie in the Clarity MARF.  Same as
+                                                 -- state_index_root, except for the boot block.
+
+        PRIMARY KEY(consensus_hash,block_hash)
+    );"#,
+    r#"
+    CREATE INDEX headers_by_state_root ON headers(state_root);
+    "#,
+    r#"
+    CREATE TABLE staging_headers(
+        version INTEGER NOT NULL,
+        total_burn TEXT NOT NULL,
+        total_work TEXT NOT NULL,
+        proof TEXT NOT NULL,
+        parent_block TEXT NOT NULL,
+        parent_microblock TEXT NOT NULL,
+        parent_microblock_sequence INTEGER NOT NULL,
+        tx_merkle_root TEXT NOT NULL,
+        state_index_root TEXT NOT NULL,
+        microblock_pubkey_hash TEXT NOT NULL,
+
+        block_hash TEXT NOT NULL,
+        consensus_hash TEXT NOT NULL,
+        index_block_hash TEXT UNIQUE NOT NULL,
+        parent_block_id TEXT NOT NULL,
+        arrival_time INTEGER NOT NULL,
+        download_time INTEGER NOT NULL,
+        processed INTEGER NOT NULL,
+        orphaned INTEGER NOT NULL,
+
+        PRIMARY KEY(consensus_hash,block_hash)
+    );"#,
+    r#"
+    -- reward sets chosen by PoX anchor blocks, as learned from peers.
+    -- reward_set is NULL until we have obtained it.
+    CREATE TABLE reward_sets(
+        anchor_block_id TEXT PRIMARY KEY,
+        reward_set TEXT,
+        processed INTEGER NOT NULL
+    );"#,
+    r#"
+    CREATE TABLE db_config(
+        version TEXT NOT NULL,
+        mainnet INTEGER NOT NULL,
+        chain_id INTEGER NOT NULL
+    );"#,
+];
+
+/// A Stacks block header, along with the data needed to place it in the Stacks chain without
+/// the sortition DB.  This is what the header chain is made of, and what GET /v2/headers returns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtendedStacksHeader {
+    pub consensus_hash: ConsensusHash,
+    pub header: StacksBlockHeader,
+    pub parent_block_id: StacksBlockId,
+}
+
+impl ExtendedStacksHeader {
+    pub fn index_block_hash(&self) -> StacksBlockId {
+        self.header.index_block_hash(&self.consensus_hash)
+    }
+}
+
+impl FromRow<ExtendedStacksHeader> for ExtendedStacksHeader {
+    fn from_row<'a>(row: &'a Row) -> Result<ExtendedStacksHeader, db_error> {
+        let header = StacksBlockHeader::from_row(row)?;
+        let consensus_hash = ConsensusHash::from_column(row, "consensus_hash")?;
+        let parent_block_id = StacksBlockId::from_column(row, "parent_block_id")?;
+        Ok(ExtendedStacksHeader {
+            consensus_hash,
+            header,
+            parent_block_id,
+        })
+    }
+}
+
+impl StacksChainState {
+    /// Get up to `max` processed headers ending at `tip`, in order from the tip backwards, so a
+    /// full node can serve them to light nodes.  The boot block is not included.
+    pub fn get_extended_header_chain(
+        conn: &Connection,
+        tip: &StacksBlockId,
+        max: u64,
+    ) -> Result<Vec<ExtendedStacksHeader>, Error> {
+        let mut ret = vec![];
+        let mut cursor = tip.clone();
+        while (ret.len() as u64) < max {
+            let header_info =
+                match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                    conn, &cursor,
+                )? {
+                    Some(header_info) => header_info,
+                    None => break,
+                };
+            if header_info.block_height == 0 {
+                // boot block
+                break;
+            }
+            let parent_block_id = StacksChainState::get_parent_block_id(conn, &cursor)?
+                .expect("BUG: processed block has no parent block ID");
+            cursor = parent_block_id.clone();
+            ret.push(ExtendedStacksHeader {
+                consensus_hash: header_info.consensus_hash,
+                header: header_info.anchored_header,
+                parent_block_id,
+            });
+        }
+        Ok(ret)
+    }
+}
+
+/// The header chain is the slimmed-down chainstate kept by a node running in header-only ("light")
+/// mode.  Such a node processes sortitions as usual, but only downloads and validates Stacks
+/// block headers -- it never downloads block bodies or replays Clarity.  It keeps just enough
+/// state to serve header chains to other nodes, and to verify MARF proofs against the state roots
+/// of the headers it has accepted.
+///
+/// Because it cannot evaluate the PoX contract, a light node learns the reward set chosen by each
+/// PoX anchor block from its peers, and records which ones it still needs here.
+#[derive(Debug)]
+pub struct HeaderChainDB {
+    pub conn: Connection,
+    pub readwrite: bool,
+    pub mainnet: bool,
+    pub chain_id: u32,
+}
+
+impl HeaderChainDB {
+    fn instantiate(&mut self) -> Result<(), Error> {
+        let mainnet = self.mainnet;
+        let chain_id = self.chain_id;
+        let tx = self.tx_begin()?;
+
+        for row_text in HEADER_CHAIN_DB_SETUP {
+            tx.execute(row_text, NO_PARAMS)
+                .map_err(db_error::SqliteError)?;
+        }
+
+        let args: &[&dyn ToSql] = &[&HEADER_CHAIN_DB_VERSION, &mainnet, &chain_id];
+        tx.execute(
+            "INSERT INTO db_config (version,mainnet,chain_id) VALUES (?1,?2,?3)",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Open the header chain database at the given path.  Open read-only or read/write.
+    /// If opened for read/write and it doesn't exist, instantiate it.
+    /// The caller must call set_genesis() on a newly-instantiated DB before using it.
+    pub fn connect(
+        path: &str,
+        mainnet: bool,
+        chain_id: u32,
+        readwrite: bool,
+    ) -> Result<HeaderChainDB, Error> {
+        let mut create_flag = false;
+        let open_flags = if fs::metadata(path).is_err() {
+            // need to create
+            if readwrite {
+                create_flag = true;
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+            } else {
+                return Err(Error::DBError(db_error::NoDBError));
+            }
+        } else {
+            // can just open
+            if readwrite {
+                OpenFlags::SQLITE_OPEN_READ_WRITE
+            } else {
+                OpenFlags::SQLITE_OPEN_READ_ONLY
+            }
+        };
+        let conn = Connection::open_with_flags(path, open_flags).map_err(db_error::SqliteError)?;
+
+        let mut db = HeaderChainDB {
+            conn,
+            readwrite,
+            mainnet,
+            chain_id,
+        };
+        if create_flag {
+            db.instantiate()?;
+        } else {
+            let (db_mainnet, db_chain_id) = db.get_db_config()?;
+            if db_mainnet != mainnet || db_chain_id != chain_id {
+                error!(
+                    "Header chain DB at {} is for mainnet={}, chain_id={:x}, but expected mainnet={}, chain_id={:x}",
+                    path, db_mainnet, db_chain_id, mainnet, chain_id
+                );
+                return Err(Error::InvalidChainstateDB);
+            }
+        }
+        Ok(db)
+    }
+
+    /// Open (or create) the header chain database at the given path for a light node whose
+    /// chainstate is `chainstate`, seeding it with the chainstate's boot block.
+    pub fn open(path: &str, chainstate: &mut StacksChainState) -> Result<HeaderChainDB, Error> {
+        let mut db = HeaderChainDB::connect(path, chainstate.mainnet, chainstate.chain_id, true)?;
+        let genesis = StacksChainState::get_genesis_header_info(chainstate.db())?;
+        let genesis_block_id = genesis.index_block_hash();
+        let genesis_state_root =
+            chainstate.with_clarity_marf(|marf| marf.get_root_hash_at(&genesis_block_id))?;
+        db.set_genesis(&genesis, &genesis_state_root)?;
+        Ok(db)
+    }
+
+    /// Open a header chain database in memory (used for testing)
+    #[cfg(test)]
+    pub fn connect_memory(mainnet: bool, chain_id: u32) -> Result<HeaderChainDB, Error> {
+        let conn = Connection::open_in_memory().map_err(db_error::SqliteError)?;
+
+        let mut db = HeaderChainDB {
+            conn,
+            readwrite: true,
+            mainnet,
+            chain_id,
+        };
+
+        db.instantiate()?;
+        Ok(db)
+    }
+
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn tx_begin<'a>(&'a mut self) -> Result<Transaction<'a>, Error> {
+        if !self.readwrite {
+            return Err(Error::DBError(db_error::ReadOnly));
+        }
+
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        Ok(tx)
+    }
+
+    fn get_db_config(&self) -> Result<(bool, u32), Error> {
+        self.conn
+            .query_row(
+                "SELECT mainnet,chain_id FROM db_config LIMIT 1",
+                NO_PARAMS,
+                |row| (row.get::<_, bool>(0), row.get::<_, u32>(1)),
+            )
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))
+    }
+
+    /// Seed the header chain with the boot block.  `genesis_state_root` is the root hash of the
+    /// boot block's trie in the Clarity MARF, which the light node computes itself when it
+    /// instantiates its (otherwise unused) chainstate.  Does nothing if already seeded.
+    pub fn set_genesis(
+        &mut self,
+        genesis: &StacksHeaderInfo,
+        genesis_state_root: &TrieHash,
+    ) -> Result<(), Error> {
+        let genesis_block_hash = genesis.anchored_header.block_hash();
+        if self.has_header(&genesis.consensus_hash, &genesis_block_hash)? {
+            return Ok(());
+        }
+
+        let tx = self.tx_begin()?;
+        HeaderChainDB::insert_header(
+            &tx,
+            &genesis.consensus_hash,
+            &genesis.anchored_header,
+            &StacksBlockId::sentinel(),
+            0,
+            genesis.burn_header_height as u64,
+            genesis_state_root,
+        )?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    fn insert_header(
+        tx: &Transaction,
+        consensus_hash: &ConsensusHash,
+        header: &StacksBlockHeader,
+        parent_block_id: &StacksBlockId,
+        block_height: u64,
+        burn_header_height: u64,
+        state_root: &TrieHash,
+    ) -> Result<(), Error> {
+        let block_hash = header.block_hash();
+        let index_block_hash =
+            StacksBlockHeader::make_index_block_hash(consensus_hash, &block_hash);
+        let total_work_str = format!("{}", header.total_work.work);
+        let total_burn_str = format!("{}", header.total_work.burn);
+
+        let args: &[&dyn ToSql] = &[
+            &header.version,
+            &total_burn_str,
+            &total_work_str,
+            &header.proof,
+            &header.parent_block,
+            &header.parent_microblock,
+            &header.parent_microblock_sequence,
+            &header.tx_merkle_root,
+            &header.state_index_root,
+            &header.microblock_pubkey_hash,
+            &block_hash,
+            consensus_hash,
+            &index_block_hash,
+            parent_block_id,
+            &u64_to_sql(block_height)?,
+            &u64_to_sql(burn_header_height)?,
+            state_root,
+        ];
+
+        tx.execute("INSERT INTO headers \
+                    (version, total_burn, total_work, proof, parent_block, parent_microblock, parent_microblock_sequence, \
+                    tx_merkle_root, state_index_root, microblock_pubkey_hash, block_hash, consensus_hash, index_block_hash, \
+                    parent_block_id, block_height, burn_header_height, state_root) \
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)", args)
+            .map_err(db_error::SqliteError)?;
+
+        Ok(())
+    }
+
+    fn insert_staging_header(
+        tx: &Transaction,
+        consensus_hash: &ConsensusHash,
+        header: &StacksBlockHeader,
+        parent_block_id: &StacksBlockId,
+        download_time: u64,
+    ) -> Result<(), Error> {
+        let block_hash = header.block_hash();
+        let index_block_hash =
+            StacksBlockHeader::make_index_block_hash(consensus_hash, &block_hash);
+        let total_work_str = format!("{}", header.total_work.work);
+        let total_burn_str = format!("{}", header.total_work.burn);
+
+        let args: &[&dyn ToSql] = &[
+            &header.version,
+            &total_burn_str,
+            &total_work_str,
+            &header.proof,
+            &header.parent_block,
+            &header.parent_microblock,
+            &header.parent_microblock_sequence,
+            &header.tx_merkle_root,
+            &header.state_index_root,
+            &header.microblock_pubkey_hash,
+            &block_hash,
+            consensus_hash,
+            &index_block_hash,
+            parent_block_id,
+            &u64_to_sql(get_epoch_time_secs())?,
+            &u64_to_sql(download_time)?,
+        ];
+
+        tx.execute("INSERT OR IGNORE INTO staging_headers \
+                    (version, total_burn, total_work, proof, parent_block, parent_microblock, parent_microblock_sequence, \
+                    tx_merkle_root, state_index_root, microblock_pubkey_hash, block_hash, consensus_hash, index_block_hash, \
+                    parent_block_id, arrival_time, download_time, processed, orphaned) \
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, 0, 0)", args)
+            .map_err(db_error::SqliteError)?;
+
+        Ok(())
+    }
+
+    /// Have we accepted this header into the header chain?
+    pub fn has_header(
+        &self,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+    ) -> Result<bool, Error> {
+        let sql = "SELECT COUNT(*) FROM headers WHERE consensus_hash = ?1 AND block_hash = ?2";
+        let args: &[&dyn ToSql] = &[consensus_hash, block_hash];
+        Ok(query_count(&self.conn, sql, args)? > 0)
+    }
+
+    /// Have we stored this header for processing (whether or not it was processed)?
+    pub fn has_staging_header(
+        &self,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+    ) -> Result<bool, Error> {
+        let sql =
+            "SELECT COUNT(*) FROM staging_headers WHERE consensus_hash = ?1 AND block_hash = ?2";
+        let args: &[&dyn ToSql] = &[consensus_hash, block_hash];
+        Ok(query_count(&self.conn, sql, args)? > 0)
+    }
+
+    /// Get an accepted header by index block hash
+    pub fn get_header(
+        &self,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<ExtendedStacksHeader>, Error> {
+        let sql = "SELECT * FROM headers WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        query_row(&self.conn, sql, args).map_err(Error::DBError)
+    }
+
+    /// Get the Stacks block height of an accepted header
+    pub fn get_block_height(&self, index_block_hash: &StacksBlockId) -> Result<Option<u64>, Error> {
+        let sql = "SELECT block_height FROM headers WHERE index_block_hash = ?1".to_string();
+        let args: &[&dyn ToSql] = &[index_block_hash];
+        let mut heights = query_row_columns::<u64, _>(&self.conn, &sql, args, "block_height")?;
+        Ok(heights.pop())
+    }
+
+    /// Get up to `max` headers ending at `tip`, in order from the tip backwards.
+    /// The boot block is not included.
+    pub fn get_header_chain(
+        &self,
+        tip: &StacksBlockId,
+        max: u64,
+    ) -> Result<Vec<ExtendedStacksHeader>, Error> {
+        let mut ret = vec![];
+        let mut cursor = tip.clone();
+        while (ret.len() as u64) < max {
+            let header = match self.get_header(&cursor)? {
+                Some(header) => header,
+                None => break,
+            };
+            if header.parent_block_id == StacksBlockId::sentinel() {
+                // boot block
+                break;
+            }
+            cursor = header.parent_block_id.clone();
+            ret.push(header);
+        }
+        Ok(ret)
+    }
+
+    /// Get the mapping from each accepted block's Clarity MARF root hash to its index block hash,
+    /// for verifying MARF proofs (see TrieMerkleProof::verify()).
+    pub fn get_root_to_block(&self) -> Result<HashMap<TrieHash, StacksBlockId>, Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT state_root,index_block_hash FROM headers")
+            .map_err(db_error::SqliteError)?;
+        let mut rows = stmt.query(NO_PARAMS).map_err(db_error::SqliteError)?;
+        let mut root_to_block = HashMap::new();
+        while let Some(row_res) = rows.next() {
+            let row = row_res.map_err(db_error::SqliteError)?;
+            let root_hash = TrieHash::from_column(&row, "state_root")?;
+            let block_id = StacksBlockId::from_column(&row, "index_block_hash")?;
+            root_to_block.insert(root_hash, block_id);
+        }
+        Ok(root_to_block)
+    }
+
+    /// Verify a MARF proof from another node that `key` maps to `value` in the Clarity state as
+    /// of the accepted block `tip`.  Returns false if the proof is invalid, and
+    /// NoSuchBlockError if we have not accepted `tip`.
+    pub fn verify_marf_proof(
+        &self,
+        tip: &StacksBlockId,
+        key: &str,
+        value: &str,
+        proof: &TrieMerkleProof<StacksBlockId>,
+    ) -> Result<bool, Error> {
+        let sql = "SELECT state_root FROM headers WHERE index_block_hash = ?1".to_string();
+        let args: &[&dyn ToSql] = &[tip];
+        let state_root = query_row_columns::<TrieHash, _>(&self.conn, &sql, args, "state_root")?
+            .pop()
+            .ok_or(Error::NoSuchBlockError)?;
+        let root_to_block = self.get_root_to_block()?;
+        Ok(proof.verify_entry(key, value, &state_root, &root_to_block))
+    }
+
+    /// Pre-process and store a downloaded header, queuing it up for processing once its parent
+    /// has been accepted.  The header must correspond to the winning block-commit of the sortition
+    /// identified by `consensus_hash`.
+    /// Returns Ok(true) if stored, Ok(false) if we already had it or if it has no (canonical)
+    /// sortition, and InvalidStacksBlock if it does not match the burnchain state.
+    pub fn preprocess_header(
+        &mut self,
+        sort_ic: &SortitionDBConn,
+        consensus_hash: &ConsensusHash,
+        header: &StacksBlockHeader,
+        download_time: u64,
+    ) -> Result<bool, Error> {
+        let block_hash = header.block_hash();
+        debug!("preprocess header {}/{}", consensus_hash, &block_hash);
+
+        if self.has_header(consensus_hash, &block_hash)?
+            || self.has_staging_header(consensus_hash, &block_hash)?
+        {
+            debug!("Header already stored: {}/{}", consensus_hash, &block_hash);
+            return Ok(false);
+        }
+
+        let sort_handle = SortitionHandleConn::open_reader_consensus(sort_ic, consensus_hash)?;
+
+        // find the snapshot of the parent of this header
+        let parent_consensus_hash = match sort_handle
+            .get_block_snapshot_of_parent_stacks_block(consensus_hash, &block_hash)
+        {
+            Ok(Some((_, sn))) => sn.consensus_hash,
+            Ok(None) => {
+                debug!(
+                    "Received header with unknown parent snapshot: {}/{}",
+                    consensus_hash, &block_hash
+                );
+                return Ok(false);
+            }
+            Err(db_error::InvalidPoxSortition) => {
+                warn!(
+                    "Received header {}/{} on a non-canonical PoX sortition",
+                    consensus_hash, &block_hash
+                );
+                return Ok(false);
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        };
+
+        // does this header match the burnchain state?
+        if StacksChainState::validate_anchored_header_burnchain(
+            &sort_handle,
+            consensus_hash,
+            header,
+        )?
+        .is_none()
+        {
+            let msg = format!(
+                "Invalid header {}: does not correspond to burn chain state",
+                &block_hash
+            );
+            warn!("{}", &msg);
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        let parent_block_id = if header.parent_block == FIRST_STACKS_BLOCK_HASH {
+            StacksBlockHeader::make_index_block_hash(
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+            )
+        } else {
+            StacksBlockHeader::make_index_block_hash(&parent_consensus_hash, &header.parent_block)
+        };
+
+        let tx = self.tx_begin()?;
+        HeaderChainDB::insert_staging_header(
+            &tx,
+            consensus_hash,
+            header,
+            &parent_block_id,
+            download_time,
+        )?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(true)
+    }
+
+    /// Find the next staging header whose parent has been accepted
+    fn find_next_staging_header(
+        tx: &Transaction,
+    ) -> Result<Option<(ExtendedStacksHeader, ExtendedStacksHeader)>, Error> {
+        // pick randomly -- don't allow the network sender to choose the processing order!
+        let sql = "SELECT staging_headers.* FROM staging_headers JOIN headers ON staging_headers.parent_block_id = headers.index_block_hash \
+                   WHERE staging_headers.processed = 0 AND staging_headers.orphaned = 0 ORDER BY RANDOM() LIMIT 1";
+        let next: Option<ExtendedStacksHeader> = query_row(tx, sql, NO_PARAMS)?;
+        match next {
+            Some(next) => {
+                let sql = "SELECT * FROM headers WHERE index_block_hash = ?1";
+                let args: &[&dyn ToSql] = &[&next.parent_block_id];
+                let parent: ExtendedStacksHeader =
+                    query_row(tx, sql, args)?.expect("BUG: joined on a header that does not exist");
+                Ok(Some((parent, next)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn set_staging_header_processed(
+        tx: &Transaction,
+        index_block_hash: &StacksBlockId,
+        accepted: bool,
+    ) -> Result<(), Error> {
+        let sql =
+            "UPDATE staging_headers SET processed = 1, orphaned = ?1 WHERE index_block_hash = ?2";
+        let args: &[&dyn ToSql] = &[&!accepted, index_block_hash];
+        tx.execute(sql, args).map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Process up to `max_headers` staging headers whose parents have been accepted, adding them
+    /// to the header chain and marking them as accepted in the sortition DB.  This is the
+    /// header-only counterpart to StacksChainState::process_blocks().
+    /// Returns the headers accepted, in the order they were accepted.
+    pub fn process_headers(
+        &mut self,
+        mut sort_tx: SortitionHandleTx,
+        max_headers: usize,
+    ) -> Result<Vec<ExtendedStacksHeader>, Error> {
+        debug!("Process up to {} headers", max_headers);
+
+        let mut ret = vec![];
+        let tx = self.tx_begin()?;
+        for _ in 0..max_headers {
+            let (parent, next) = match HeaderChainDB::find_next_staging_header(&tx)? {
+                Some(x) => x,
+                None => {
+                    debug!("No more staging headers -- processed {}", ret.len());
+                    break;
+                }
+            };
+            let block_hash = next.header.block_hash();
+            let index_block_hash = next.index_block_hash();
+
+            // validation check -- the header must attach to its accepted parent
+            if !StacksChainState::check_block_attachment(&parent.header, &next.header) {
+                warn!(
+                    "Invalid header {}/{} -- does not attach to parent {}/{}",
+                    &next.consensus_hash,
+                    &block_hash,
+                    &parent.consensus_hash,
+                    parent.header.block_hash()
+                );
+                HeaderChainDB::set_staging_header_processed(&tx, &index_block_hash, false)?;
+                continue;
+            }
+
+            let burn_header_height =
+                match SortitionDB::get_block_snapshot_consensus(&sort_tx, &next.consensus_hash)? {
+                    Some(sn) => sn.block_height,
+                    None => {
+                        // shouldn't happen
+                        panic!(
+                            "CORRUPTION: staging header {}/{} does not correspond to a burn block",
+                            &next.consensus_hash, &block_hash
+                        );
+                    }
+                };
+
+            let block_height = next.header.total_work.work;
+            HeaderChainDB::insert_header(
+                &tx,
+                &next.consensus_hash,
+                &next.header,
+                &next.parent_block_id,
+                block_height,
+                burn_header_height,
+                &next.header.state_index_root,
+            )?;
+            HeaderChainDB::set_staging_header_processed(&tx, &index_block_hash, true)?;
+
+            sort_tx.set_stacks_block_accepted(
+                &next.consensus_hash,
+                &next.header.parent_block,
+                &block_hash,
+                block_height,
+            )?;
+
+            debug!(
+                "Accepted header {}/{} ({}) at height {}",
+                &next.consensus_hash, &block_hash, &index_block_hash, block_height
+            );
+            ret.push(next);
+        }
+
+        sort_tx.commit()?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(ret)
+    }
+
+    /// Record that we need the reward set chosen by the given PoX anchor block
+    pub fn want_reward_set(&mut self, anchor_block_id: &StacksBlockId) -> Result<(), Error> {
+        let tx = self.tx_begin()?;
+        let args: &[&dyn ToSql] = &[anchor_block_id];
+        tx.execute(
+            "INSERT OR IGNORE INTO reward_sets (anchor_block_id, reward_set, processed) VALUES (?1, NULL, 0)",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Get the PoX anchor blocks whose reward sets we need but do not have
+    pub fn get_wanted_reward_sets(&self) -> Result<Vec<StacksBlockId>, Error> {
+        let sql = "SELECT anchor_block_id FROM reward_sets WHERE reward_set IS NULL".to_string();
+        query_row_columns(&self.conn, &sql, NO_PARAMS, "anchor_block_id").map_err(Error::DBError)
+    }
+
+    /// Store the reward set chosen by a PoX anchor block.  Does nothing if we already have it.
+    pub fn store_reward_set(
+        &mut self,
+        anchor_block_id: &StacksBlockId,
+        reward_set: &Vec<StacksAddress>,
+    ) -> Result<(), Error> {
+        let reward_set_json = serde_json::to_string(reward_set)
+            .expect("FATAL: failed to serialize reward set to JSON");
+        let tx = self.tx_begin()?;
+        let args: &[&dyn ToSql] = &[anchor_block_id, &reward_set_json];
+        tx.execute(
+            "INSERT OR IGNORE INTO reward_sets (anchor_block_id, reward_set, processed) VALUES (?1, ?2, 0)",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.execute(
+            "UPDATE reward_sets SET reward_set = ?2 WHERE anchor_block_id = ?1 AND reward_set IS NULL",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Get the reward set chosen by a PoX anchor block, if we have it
+    pub fn get_reward_set(
+        &self,
+        anchor_block_id: &StacksBlockId,
+    ) -> Result<Option<Vec<StacksAddress>>, Error> {
+        let sql = "SELECT reward_set FROM reward_sets WHERE anchor_block_id = ?1 AND reward_set IS NOT NULL";
+        let args: &[&dyn ToSql] = &[anchor_block_id];
+        let reward_set_json: Option<String> = self
+            .conn
+            .query_row(sql, args, |row| row.get(0))
+            .optional()
+            .map_err(db_error::SqliteError)?;
+        match reward_set_json {
+            Some(json) => {
+                let reward_set = serde_json::from_str(&json)
+                    .map_err(|_| Error::DBError(db_error::ParseError))?;
+                Ok(Some(reward_set))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the anchor blocks of the reward sets that have been stored since the last call, and
+    /// mark them as seen.
+    pub fn take_new_reward_sets(&mut self) -> Result<Vec<StacksBlockId>, Error> {
+        let tx = self.tx_begin()?;
+        let sql =
+            "SELECT anchor_block_id FROM reward_sets WHERE reward_set IS NOT NULL AND processed = 0"
+                .to_string();
+        let anchors: Vec<StacksBlockId> =
+            query_row_columns(&tx, &sql, NO_PARAMS, "anchor_block_id")?;
+        tx.execute(
+            "UPDATE reward_sets SET processed = 1 WHERE reward_set IS NOT NULL",
+            NO_PARAMS,
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(anchors)
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pluggable block indexers.
+//!
+//! An embedder can register any number of `Indexer` implementations with a `StacksChainState`.
+//! Each processed anchored block -- along with its receipts and the MARF key/value pairs it
+//! wrote -- is handed to every indexer, in processing order, on the indexer's own thread.
+//! Each indexer is fed through a bounded queue: if an indexer falls behind, block processing
+//! waits for it to catch up instead of dropping blocks.
+
+use std::io;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+
+use chainstate::stacks::db::{StacksChainState, StacksEpochReceipt};
+use chainstate::stacks::{StacksBlock, StacksBlockId};
+
+/// Default number of processed blocks that may be queued up for a single indexer
+pub const DEFAULT_INDEXER_QUEUE_SIZE: usize = 16;
+
+/// A processed anchored block, as handed to indexers
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedBlock {
+    pub block: StacksBlock,
+    pub parent_block_id: StacksBlockId,
+    /// Header info, transaction receipts, matured rewards and MARF writes for the block
+    pub receipt: StacksEpochReceipt,
+}
+
+impl IndexedBlock {
+    pub fn index_block_hash(&self) -> StacksBlockId {
+        self.receipt.header.index_block_hash()
+    }
+}
+
+pub trait Indexer: Send {
+    /// Name of this indexer, used for logging
+    fn name(&self) -> String;
+
+    /// Index a processed block.  Blocks are delivered in the order in which they were processed
+    /// (which, across forks, is not necessarily height order).  An error is logged, and does not
+    /// stop subsequent blocks from being delivered.
+    fn index_block(&mut self, block: &IndexedBlock) -> Result<(), String>;
+}
+
+struct IndexerWorker {
+    name: String,
+    sender: SyncSender<Arc<IndexedBlock>>,
+    handle: JoinHandle<()>,
+}
+
+/// The set of indexers registered with a chainstate.
+/// Dropping the set waits for every indexer to drain its queue.
+pub struct IndexerSet {
+    workers: Vec<IndexerWorker>,
+}
+
+impl IndexerSet {
+    pub fn new() -> IndexerSet {
+        IndexerSet { workers: vec![] }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Register an indexer, which will run on its own thread and be fed through a queue of at
+    /// most `queue_size` blocks.
+    pub fn register(&mut self, indexer: Box<dyn Indexer>, queue_size: usize) -> io::Result<()> {
+        let name = indexer.name();
+        let (sender, receiver) = sync_channel(queue_size);
+        let handle = thread::Builder::new()
+            .name(format!("indexer-{}", &name))
+            .spawn(move || IndexerSet::indexer_main(indexer, receiver))?;
+
+        debug!("Registered indexer '{}' (queue size {})", &name, queue_size);
+        self.workers.push(IndexerWorker {
+            name,
+            sender,
+            handle,
+        });
+        Ok(())
+    }
+
+    fn indexer_main(mut indexer: Box<dyn Indexer>, receiver: Receiver<Arc<IndexedBlock>>) {
+        let name = indexer.name();
+        while let Ok(block) = receiver.recv() {
+            if let Err(msg) = indexer.index_block(&block) {
+                error!(
+                    "Indexer '{}' failed to index block {}: {}",
+                    &name,
+                    &block.index_block_hash(),
+                    &msg
+                );
+            }
+        }
+        debug!("Indexer '{}' exiting", &name);
+    }
+
+    /// Hand a processed block to each indexer.  Blocks the caller while any indexer's queue is
+    /// full.  An indexer whose thread has died is logged and removed.
+    pub fn index_block(&mut self, block: IndexedBlock) {
+        let block = Arc::new(block);
+        let mut dead = vec![];
+        for (i, worker) in self.workers.iter().enumerate() {
+            if worker.sender.send(block.clone()).is_err() {
+                error!(
+                    "Indexer '{}' is no longer running; not sending block {}",
+                    &worker.name,
+                    &block.index_block_hash()
+                );
+                dead.push(i);
+            }
+        }
+        for i in dead.into_iter().rev() {
+            let worker = self.workers.remove(i);
+            if worker.handle.join().is_err() {
+                error!("Indexer '{}' panicked", &worker.name);
+            }
+        }
+    }
+}
+
+impl Drop for IndexerSet {
+    fn drop(&mut self) {
+        for worker in self.workers.drain(..) {
+            let IndexerWorker {
+                name,
+                sender,
+                handle,
+            } = worker;
+
+            // closing the queue lets the indexer finish what it has and exit
+            drop(sender);
+            if handle.join().is_err() {
+                error!("Indexer '{}' panicked", &name);
+            }
+        }
+    }
+}
+
+impl StacksChainState {
+    /// Register an indexer to be fed every block this chainstate processes from now on.
+    pub fn register_indexer(
+        &mut self,
+        indexer: Box<dyn Indexer>,
+        queue_size: usize,
+    ) -> io::Result<()> {
+        self.indexers.register(indexer, queue_size)
+    }
+
+    /// Hand each processed block to the registered indexers.
+    pub(crate) fn dispatch_to_indexers(&mut self, receipts: &[StacksEpochReceipt]) {
+        if self.indexers.is_empty() {
+            return;
+        }
+
+        for receipt in receipts.iter() {
+            let index_block_hash = receipt.header.index_block_hash();
+            let block = match StacksChainState::load_block(
+                &self.blocks_path,
+                &receipt.header.consensus_hash,
+                &receipt.header.anchored_header.block_hash(),
+            ) {
+                Ok(Some(block)) => block,
+                Ok(None) => {
+                    warn!(
+                        "Processed block {} is empty on disk; not indexing",
+                        &index_block_hash
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to load processed block {} for indexing: {:?}",
+                        &index_block_hash, &e
+                    );
+                    continue;
+                }
+            };
+
+            let parent_block_id = match self.get_parent(&index_block_hash) {
+                Ok(parent) => parent,
+                Err(e) => {
+                    warn!(
+                        "Failed to load parent of processed block {} for indexing: {:?}",
+                        &index_block_hash, &e
+                    );
+                    continue;
+                }
+            };
+
+            self.indexers.index_block(IndexedBlock {
+                block,
+                parent_block_id,
+                receipt: receipt.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::Duration;
+
+    use chainstate::stacks::db::StacksHeaderInfo;
+    use vm::costs::ExecutionCost;
+
+    struct TestIndexer {
+        gate: Receiver<()>,
+        seen: Sender<(StacksBlockId, Vec<(String, String)>)>,
+    }
+
+    impl Indexer for TestIndexer {
+        fn name(&self) -> String {
+            "test".to_string()
+        }
+
+        fn index_block(&mut self, block: &IndexedBlock) -> Result<(), String> {
+            self.gate.recv().map_err(|e| format!("{:?}", &e))?;
+            self.seen
+                .send((
+                    block.parent_block_id.clone(),
+                    block.receipt.marf_writes.clone(),
+                ))
+                .map_err(|e| format!("{:?}", &e))
+        }
+    }
+
+    fn make_indexed_block(i: u8) -> IndexedBlock {
+        IndexedBlock {
+            block: StacksBlock::genesis_block(),
+            parent_block_id: StacksBlockId([i; 32]),
+            receipt: StacksEpochReceipt {
+                header: StacksHeaderInfo::regtest_genesis(0),
+                tx_receipts: vec![],
+                matured_rewards: vec![],
+                matured_rewards_info: None,
+                parent_microblocks_cost: ExecutionCost::zero(),
+                anchored_block_cost: ExecutionCost::zero(),
+                marf_writes: vec![(format!("key-{}", i), format!("value-{}", i))],
+                metadata_writes: vec![],
+                marf_commit_time: Duration::default(),
+            },
+        }
+    }
+
+    #[test]
+    fn indexer_queue_applies_backpressure() {
+        let (gate_tx, gate_rx) = channel();
+        let (seen_tx, seen_rx) = channel();
+
+        let mut indexers = IndexerSet::new();
+        indexers
+            .register(
+                Box::new(TestIndexer {
+                    gate: gate_rx,
+                    seen: seen_tx,
+                }),
+                1,
+            )
+            .unwrap();
+
+        // the indexer takes block 0 and blocks on the gate; block 1 fills the queue, so
+        // dispatching block 2 must wait until the indexer makes progress.
+        let (done_tx, done_rx) = channel();
+        let dispatcher = thread::spawn(move || {
+            for i in 0..3 {
+                indexers.index_block(make_indexed_block(i));
+                done_tx.send(i).unwrap();
+            }
+            indexers
+        });
+
+        assert_eq!(done_rx.recv().unwrap(), 0);
+        assert_eq!(done_rx.recv().unwrap(), 1);
+        assert!(done_rx.recv_timeout(Duration::from_millis(500)).is_err());
+
+        gate_tx.send(()).unwrap();
+        assert_eq!(done_rx.recv().unwrap(), 2);
+
+        gate_tx.send(()).unwrap();
+        gate_tx.send(()).unwrap();
+        let indexers = dispatcher.join().unwrap();
+        drop(indexers);
+
+        // nothing was dropped, and blocks arrived in order
+        let seen: Vec<_> = seen_rx.try_iter().collect();
+        assert_eq!(seen.len(), 3);
+        for (i, (parent, writes)) in seen.into_iter().enumerate() {
+            assert_eq!(parent, StacksBlockId([i as u8; 32]));
+            assert_eq!(writes, vec![(format!("key-{}", i), format!("value-{}", i))]);
+        }
+    }
+
+    #[test]
+    fn dead_indexer_is_removed() {
+        struct PanicIndexer;
+        impl Indexer for PanicIndexer {
+            fn name(&self) -> String {
+                "panic".to_string()
+            }
+            fn index_block(&mut self, _block: &IndexedBlock) -> Result<(), String> {
+                panic!("boom");
+            }
+        }
+
+        let mut indexers = IndexerSet::new();
+        indexers.register(Box::new(PanicIndexer), 1).unwrap();
+        assert_eq!(indexers.len(), 1);
+
+        for i in 0..10 {
+            indexers.index_block(make_indexed_block(i));
+            if indexers.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(indexers.is_empty());
+    }
+}
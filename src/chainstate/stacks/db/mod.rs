@@ -15,20 +15,28 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 pub mod accounts;
+pub mod audit;
 pub mod blocks;
 pub mod contracts;
+pub mod diff;
+pub mod header_chain;
 pub mod headers;
+pub mod indexer;
+pub mod snapshot;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod transactions;
 pub mod unconfirmed;
 
 use rusqlite::types::ToSql;
 use rusqlite::Connection;
 use rusqlite::OpenFlags;
+use rusqlite::OptionalExtension;
 use rusqlite::Row;
 use rusqlite::Transaction;
 use rusqlite::NO_PARAMS;
 
-use std::collections::{hash_map::Entry, HashMap};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 use std::fmt;
 use std::fs;
 use std::io;
@@ -38,7 +46,7 @@ use std::ops::{Deref, DerefMut};
 
 use core::*;
 
-use burnchains::{Address, Burnchain, BurnchainParameters};
+use burnchains::{Address, Burnchain, BurnchainParameters, Txid};
 
 use chainstate::burn::db::sortdb::{SortitionDB, SortitionDBConn};
 use chainstate::burn::ConsensusHash;
@@ -58,11 +66,13 @@ use chainstate::stacks::index::storage::TrieFileStorage;
 use chainstate::burn::db::sortdb::BlockHeaderCache;
 
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use util::db::Error as db_error;
 use util::db::{
-    db_mkdirs, query_count, query_row, tx_begin_immediate, tx_busy_handler, DBConn, DBTx,
-    FromColumn, FromRow, IndexDBConn, IndexDBTx,
+    db_mkdirs, get_ancestor_block_height, query_count, query_row, query_row_columns, query_rows,
+    tx_begin_immediate, tx_busy_handler, u64_to_sql, DBConn, DBTx, FromColumn, FromRow,
+    IndexDBConn, IndexDBTx,
 };
 
 use util::hash::to_hex;
@@ -74,24 +84,29 @@ use chainstate::stacks::boot::*;
 use net::Error as net_error;
 
 use vm::analysis::analysis_db::AnalysisDatabase;
+use vm::analysis::contract_interface_builder::build_contract_interface;
 use vm::analysis::run_analysis;
 use vm::ast::build_ast;
 use vm::clarity::{
     ClarityBlockConnection, ClarityConnection, ClarityInstance, ClarityReadOnlyConnection,
-    Error as clarity_error,
+    ClarityTransactionConnection, Error as clarity_error,
 };
 use vm::contexts::OwnedEnvironment;
 use vm::costs::{ExecutionCost, LimitedCostTracker};
 use vm::database::marf::MarfedKV;
 use vm::database::{
-    BurnStateDB, ClarityDatabase, HeadersDB, STXBalance, SqliteConnection, NULL_BURN_STATE_DB,
+    BurnStateDB, ClarityDatabase, ClaritySerializable, HeadersDB, STXBalance, SqliteConnection,
+    NULL_BURN_STATE_DB,
 };
+use vm::errors::Error as InterpreterError;
 use vm::representations::ClarityName;
 use vm::representations::ContractName;
 use vm::types::TupleData;
 
 use core::CHAINSTATE_VERSION;
 
+use chainstate::stacks::db::audit::ConsensusAuditLog;
+use chainstate::stacks::db::indexer::IndexerSet;
 use chainstate::stacks::db::unconfirmed::UnconfirmedState;
 
 use crate::burnchains::bitcoin::address::BitcoinAddress;
@@ -106,7 +121,15 @@ pub struct StacksChainState {
     pub clarity_state_index_root: String, // path to dir containing clarity MARF and side-store
     pub root_path: String,
     pub block_limit: ExecutionCost,
+    pub size_limits: EpochSizeLimits,
     pub unconfirmed_state: Option<UnconfirmedState>,
+    pub indexers: IndexerSet,
+    /// If set, every processed block's consensus writes are recorded here
+    pub consensus_audit_log: Option<ConsensusAuditLog>,
+    /// If set, refuse to store blocks more than this many blocks behind the canonical Stacks
+    /// chain tip.  This is local policy, not consensus -- it bounds the work a node will do for
+    /// deep forks it is never going to switch to.
+    pub max_fork_depth: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -119,6 +142,9 @@ pub struct StacksAccount {
 #[derive(Debug, Clone, PartialEq)]
 pub struct MinerPaymentSchedule {
     pub address: StacksAddress,
+    /// who the reward is paid to, if not `address` (i.e. the principal named in the block's
+    /// coinbase)
+    pub recipient: Option<PrincipalData>,
     pub block_hash: BlockHeaderHash,
     pub consensus_hash: ConsensusHash,
     pub parent_block_hash: BlockHeaderHash,
@@ -163,6 +189,12 @@ pub struct StacksEpochReceipt {
     pub matured_rewards_info: Option<MinerRewardInfo>,
     pub parent_microblocks_cost: ExecutionCost,
     pub anchored_block_cost: ExecutionCost,
+    /// MARF key/value pairs written while processing the block
+    pub marf_writes: Vec<(String, String)>,
+    /// Contract metadata key/value pairs written while processing the block
+    pub metadata_writes: Vec<(String, String)>,
+    /// Time spent committing the block's Clarity and headers MARF state
+    pub marf_commit_time: Duration,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -306,6 +338,16 @@ impl<'a> ClarityTx<'a> {
         self.block.cost_so_far()
     }
 
+    /// Take the MARF key/value pairs written by this block so far.
+    pub fn take_marf_writes(&mut self) -> Vec<(String, String)> {
+        self.block.take_marf_writes()
+    }
+
+    /// Take the contract metadata key/value pairs written by this block so far.
+    pub fn take_metadata_writes(&mut self) -> Vec<(String, String)> {
+        self.block.take_metadata_writes()
+    }
+
     /// Set the ClarityTx's cost tracker.
     /// Returns the replaced cost tracker.
     fn set_cost_tracker(&mut self, new_tracker: LimitedCostTracker) -> LimitedCostTracker {
@@ -420,6 +462,329 @@ impl<'a> ChainstateTx<'a> {
         _events: &[StacksTransactionReceipt],
     ) {
     }
+
+    /// Record each principal that was credited a fungible or non-fungible token in this block's
+    /// transaction receipts.  This index only ever grows, so it lists every asset a principal
+    /// has *ever* received in *any* fork -- callers must check the principal's balance or
+    /// ownership at their chain tip before reporting a holding.
+    pub fn log_asset_holdings(&self, events: &[StacksTransactionReceipt]) {
+        let insert = "INSERT OR IGNORE INTO asset_holdings (principal, contract_identifier, asset_name, asset_value, fungible) VALUES (?, ?, ?, ?, ?)";
+        for tx_event in events.iter() {
+            for event in tx_event.events.iter() {
+                let (recipient, asset_identifier, asset_value, fungible) = match event {
+                    StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(data)) => (
+                        &data.recipient,
+                        &data.asset_identifier,
+                        "".to_string(),
+                        true,
+                    ),
+                    StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(data)) => (
+                        &data.recipient,
+                        &data.asset_identifier,
+                        "".to_string(),
+                        true,
+                    ),
+                    StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(data)) => (
+                        &data.recipient,
+                        &data.asset_identifier,
+                        data.value.serialize(),
+                        false,
+                    ),
+                    StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(data)) => (
+                        &data.recipient,
+                        &data.asset_identifier,
+                        data.value.serialize(),
+                        false,
+                    ),
+                    _ => {
+                        continue;
+                    }
+                };
+                let params: &[&dyn ToSql] = &[
+                    &recipient.to_string(),
+                    &asset_identifier.contract_identifier.to_string(),
+                    &asset_identifier.asset_name.to_string(),
+                    &asset_value,
+                    &fungible,
+                ];
+                if let Err(e) = self.tx.tx().execute(insert, params) {
+                    warn!("Failed to log asset holding: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Record the block in which each principal first appears in this block's fork -- as the
+    /// origin or sponsor of a transaction, or as the recipient of an STX transfer or mint.
+    /// A principal is only recorded again in a block that does not descend from the block(s)
+    /// it was already recorded in.
+    pub fn log_first_seen_principals(
+        &mut self,
+        block_id: &StacksBlockId,
+        block_height: u64,
+        receipts: &[StacksTransactionReceipt],
+    ) {
+        let insert = "INSERT OR IGNORE INTO first_seen_principals (principal, index_block_hash, block_height, txid) VALUES (?1, ?2, ?3, ?4)";
+        for receipt in receipts.iter() {
+            let txid = receipt.transaction.txid();
+            let mut principals = vec![];
+            if let TransactionOrigin::Stacks(ref tx) = receipt.transaction {
+                principals.push(tx.origin_address().to_account_principal());
+                if let Some(sponsor) = tx.sponsor_address() {
+                    principals.push(sponsor.to_account_principal());
+                }
+            }
+            for event in receipt.events.iter() {
+                match event {
+                    StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(data)) => {
+                        principals.push(data.recipient.clone())
+                    }
+                    StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(data)) => {
+                        principals.push(data.recipient.clone())
+                    }
+                    StacksTransactionEvent::STXEvent(STXEventType::STXMinerRewardEvent(data)) => {
+                        principals.push(data.recipient.clone())
+                    }
+                    _ => {}
+                }
+            }
+
+            for principal in principals.into_iter() {
+                let principal = principal.to_string();
+                match self.is_principal_seen_before(&principal, block_id, block_height) {
+                    Ok(true) => {
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        warn!("Failed to look up first-seen principal: {:?}", &e);
+                        continue;
+                    }
+                }
+                let params: &[&dyn ToSql] = &[&principal, block_id, &(block_height as i64), &txid];
+                if let Err(e) = self.tx.tx().execute(insert, params) {
+                    warn!("Failed to log first-seen principal: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Record each principal that this block's transactions involve -- as origin or sponsor, as
+    /// the contract called or deployed, or as a principal named in one of the transaction's
+    /// events -- so a principal's transaction history can be listed.  Transactions are numbered
+    /// as in `log_transaction_receipts()`.
+    pub fn log_principal_transactions(
+        &self,
+        block_id: &StacksBlockId,
+        block_height: u64,
+        receipts: &[StacksTransactionReceipt],
+    ) {
+        let insert = "INSERT OR IGNORE INTO principal_transactions (principal, txid, index_block_hash, block_height, tx_index) VALUES (?1, ?2, ?3, ?4, ?5)";
+        for (tx_index, receipt) in receipts.iter().enumerate() {
+            let txid = receipt.transaction.txid();
+            let mut principals = HashSet::new();
+            if let TransactionOrigin::Stacks(ref tx) = receipt.transaction {
+                principals.insert(tx.origin_address().to_account_principal());
+                if let Some(sponsor) = tx.sponsor_address() {
+                    principals.insert(sponsor.to_account_principal());
+                }
+                match tx.payload {
+                    TransactionPayload::ContractCall(ref cc) => {
+                        principals.insert(PrincipalData::Contract(cc.to_clarity_contract_id()));
+                    }
+                    TransactionPayload::SmartContract(ref sc) => {
+                        principals.insert(PrincipalData::Contract(
+                            QualifiedContractIdentifier::new(
+                                tx.origin_address().into(),
+                                sc.name.clone(),
+                            ),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+            for event in receipt.events.iter() {
+                match event {
+                    StacksTransactionEvent::SmartContractEvent(data) => {
+                        principals.insert(PrincipalData::Contract(data.key.0.clone()));
+                    }
+                    StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(data)) => {
+                        principals.insert(data.sender.clone());
+                        principals.insert(data.recipient.clone());
+                    }
+                    StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(data)) => {
+                        principals.insert(data.recipient.clone());
+                    }
+                    StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(data)) => {
+                        principals.insert(data.sender.clone());
+                    }
+                    StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+                        principals.insert(data.locked_address.clone());
+                    }
+                    StacksTransactionEvent::STXEvent(STXEventType::STXUnlockEvent(data)) => {
+                        principals.insert(data.unlocked_address.clone());
+                    }
+                    StacksTransactionEvent::STXEvent(STXEventType::STXMinerRewardEvent(data)) => {
+                        principals.insert(data.recipient.clone());
+                    }
+                    StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(data)) => {
+                        principals.insert(data.sender.clone());
+                        principals.insert(data.recipient.clone());
+                    }
+                    StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(data)) => {
+                        principals.insert(data.recipient.clone());
+                    }
+                    StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(data)) => {
+                        principals.insert(data.sender.clone());
+                        principals.insert(data.recipient.clone());
+                    }
+                    StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(data)) => {
+                        principals.insert(data.recipient.clone());
+                    }
+                }
+            }
+
+            for principal in principals.into_iter() {
+                let params: &[&dyn ToSql] = &[
+                    &principal.to_string(),
+                    &txid,
+                    block_id,
+                    &(block_height as i64),
+                    &(tx_index as i64),
+                ];
+                if let Err(e) = self.tx.tx().execute(insert, params) {
+                    warn!("Failed to log principal transaction: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Has this principal already been recorded in an ancestor of the given block?
+    fn is_principal_seen_before(
+        &mut self,
+        principal: &str,
+        block_id: &StacksBlockId,
+        block_height: u64,
+    ) -> Result<bool, Error> {
+        let sql = "SELECT index_block_hash FROM first_seen_principals WHERE principal = ?1 AND block_height < ?2";
+        let args: &[&dyn ToSql] = &[&principal, &u64_to_sql(block_height)?];
+        let blocks: Vec<StacksBlockId> =
+            query_row_columns(self.tx.tx(), &sql.to_string(), args, "index_block_hash")?;
+        for index_block_hash in blocks.iter() {
+            if self
+                .tx
+                .get_ancestor_block_height(index_block_hash, block_id)?
+                .is_some()
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Record each stacker that successfully called a PoX contract's `delegate-stx` in this
+    /// block's transaction receipts, along with its delegate.  Like the asset holdings index,
+    /// this only ever grows -- callers must check the stacker's `delegation-state` at their
+    /// chain tip before reporting a delegation.
+    pub fn log_pox_delegations(&self, events: &[StacksTransactionReceipt]) {
+        let insert = "INSERT OR IGNORE INTO pox_delegations (delegate, stacker) VALUES (?, ?)";
+        let pox_contracts = [boot_code_id("pox"), boot_code_id("pox-2")];
+        for tx_event in events.iter() {
+            let tx = match tx_event.transaction {
+                TransactionOrigin::Stacks(ref tx) => tx,
+                TransactionOrigin::Burn(_) => {
+                    continue;
+                }
+            };
+            let delegate = match tx.payload {
+                TransactionPayload::ContractCall(ref cc)
+                    if pox_contracts.contains(&cc.to_clarity_contract_id())
+                        && cc.function_name.as_str() == "delegate-stx" =>
+                {
+                    match (&tx_event.result, cc.function_args.get(1)) {
+                        (Value::Response(ref res), Some(Value::Principal(ref delegate)))
+                            if res.committed =>
+                        {
+                            delegate
+                        }
+                        _ => {
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    continue;
+                }
+            };
+            let stacker = tx.origin_address().to_account_principal();
+            let params: &[&dyn ToSql] = &[&delegate.to_string(), &stacker.to_string()];
+            if let Err(e) = self.tx.tx().execute(insert, params) {
+                warn!("Failed to log PoX delegation: {}", e);
+            }
+        }
+    }
+
+    /// Record this block's transaction receipts, in the same form the event dispatcher hands
+    /// them to event observers, so the block's events can be replayed -- and its receipts
+    /// queried -- later without re-processing the block.  Events are numbered across the whole block, as they are when
+    /// the block is first announced.
+    pub fn log_transaction_receipts(
+        &self,
+        block_id: &StacksBlockId,
+        receipts: &[StacksTransactionReceipt],
+    ) {
+        let insert = "INSERT OR REPLACE INTO transaction_receipts (txid, index_block_hash, tx_index, raw_tx, raw_result, post_condition_aborted, stx_burned, execution_cost, contract_abi, events, asset_map) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+        let mut event_index = 0;
+        for (tx_index, receipt) in receipts.iter().enumerate() {
+            let txid = receipt.transaction.txid();
+            let raw_tx = match receipt.transaction {
+                TransactionOrigin::Stacks(ref tx) => to_hex(&tx.serialize_to_vec()),
+                TransactionOrigin::Burn(_) => "00".to_string(),
+            };
+            let contract_abi = receipt
+                .contract_analysis
+                .as_ref()
+                .map(|analysis| json!(build_contract_interface(analysis)).to_string());
+            let events: Vec<serde_json::Value> = receipt
+                .events
+                .iter()
+                .map(|event| {
+                    let event_json =
+                        event.json_serialize(event_index, &txid, !receipt.post_condition_aborted);
+                    event_index += 1;
+                    event_json
+                })
+                .collect();
+
+            let params: &[&dyn ToSql] = &[
+                &txid,
+                block_id,
+                &(tx_index as i64),
+                &raw_tx,
+                &receipt.result.serialize(),
+                &receipt.post_condition_aborted,
+                &receipt.stx_burned.to_string(),
+                &json!(receipt.execution_cost).to_string(),
+                &contract_abi,
+                &serde_json::Value::Array(events).to_string(),
+                &receipt.asset_map_json().to_string(),
+            ];
+            if let Err(e) = self.tx.tx().execute(insert, params) {
+                warn!("Failed to log transaction receipt: {}", e);
+            }
+        }
+    }
+
+    /// Record the bloom filter over the contract events in this block's transaction receipts.
+    pub fn log_event_bloom(&self, block_id: &StacksBlockId, receipts: &[StacksTransactionReceipt]) {
+        let bloom = EventBloom::from_receipts(receipts);
+        let insert =
+            "INSERT OR REPLACE INTO block_event_blooms (index_block_hash, bloom) VALUES (?1, ?2)";
+        let params: &[&dyn ToSql] = &[block_id, &bloom];
+        if let Err(e) = self.tx.tx().execute(insert, params) {
+            warn!("Failed to log event bloom: {}", e);
+        }
+    }
 }
 
 impl<'a> Deref for ChainstateTx<'a> {
@@ -525,12 +890,14 @@ const STACKS_CHAIN_STATE_SQL: &'static [&'static str] = &[
         burnchain_sortition_burn INT NOT NULL,
         fill TEXT NOT NULL,                 -- encodes u64 
         miner INT NOT NULL,
+        recipient TEXT,                     -- principal paid instead of the miner, if any
         
         -- internal use
         stacks_block_height INTEGER NOT NULL,
         index_block_hash TEXT NOT NULL,     -- NOTE: can't enforce UNIQUE here, because there will be multiple entries per block
         vtxindex INT NOT NULL               -- user burn support vtxindex
     );
+    CREATE INDEX address_payments ON payments(address,stacks_block_height);
     "#,
     r#"
     -- users who supported miners
@@ -596,6 +963,72 @@ const STACKS_CHAIN_STATE_SQL: &'static [&'static str] = &[
     CREATE INDEX orphaned_stacks_blocks ON staging_blocks(orphaned,anchored_block_hash,consensus_hash);
     "#,
     r#"
+    -- principals that have been credited a fungible or non-fungible token, in any fork.
+    -- NOTE: not consensus-critical; the actual balance or owner must be read from the chain tip.
+    CREATE TABLE asset_holdings(principal TEXT NOT NULL,
+                                contract_identifier TEXT NOT NULL,
+                                asset_name TEXT NOT NULL,
+                                asset_value TEXT NOT NULL,          -- hex-serialized NFT value; empty for fungible tokens
+                                fungible INT NOT NULL,
+                                PRIMARY KEY(principal,contract_identifier,asset_name,asset_value)
+    );
+    "#,
+    r#"
+    -- stackers that have delegated to a principal with the PoX contract's `delegate-stx`, in any fork.
+    -- NOTE: not consensus-critical; the delegation must be read from the chain tip.
+    CREATE TABLE pox_delegations(delegate TEXT NOT NULL,
+                                 stacker TEXT NOT NULL,
+                                 PRIMARY KEY(delegate,stacker)
+    );
+    "#,
+    r#"
+    -- receipts of each transaction in each processed block, in any fork, as they were reported to event observers.
+    -- NOTE: not consensus-critical; used to replay a block's events and to serve receipts without re-processing it.
+    CREATE TABLE transaction_receipts(txid TEXT NOT NULL,
+                                      index_block_hash TEXT NOT NULL,
+                                      tx_index INT NOT NULL,
+                                      raw_tx TEXT NOT NULL,                 -- hex-serialized transaction; "00" for burnchain operations
+                                      raw_result TEXT NOT NULL,             -- hex-serialized Clarity value
+                                      post_condition_aborted INT NOT NULL,
+                                      stx_burned TEXT NOT NULL,             -- encodes u128
+                                      execution_cost TEXT NOT NULL,         -- JSON-encoded ExecutionCost
+                                      contract_abi TEXT,                    -- JSON-encoded contract interface; NULL unless a contract was deployed
+                                      events TEXT NOT NULL,                 -- JSON-encoded list of events
+                                      asset_map TEXT NOT NULL,              -- JSON-encoded assets sent or burned by each principal
+                                      PRIMARY KEY(index_block_hash,tx_index)
+    );
+    CREATE INDEX txid_transaction_receipts ON transaction_receipts(txid);
+    "#,
+    r#"
+    -- the block(s) in which each principal first sent a transaction or received STX, one per fork.
+    -- NOTE: not consensus-critical; callers must pick the row whose block is an ancestor of their chain tip.
+    CREATE TABLE first_seen_principals(principal TEXT NOT NULL,
+                                       index_block_hash TEXT NOT NULL,
+                                       block_height INT NOT NULL,
+                                       txid TEXT NOT NULL,
+                                       PRIMARY KEY(principal,index_block_hash)
+    );
+    CREATE INDEX principal_first_seen_principals ON first_seen_principals(principal,block_height);
+    "#,
+    r#"
+    -- the transactions in each processed block, in any fork, that each principal sent, sponsored, called or was named in an event of.
+    -- NOTE: not consensus-critical; callers must only report rows whose block is an ancestor of their chain tip.
+    CREATE TABLE principal_transactions(principal TEXT NOT NULL,
+                                        txid TEXT NOT NULL,
+                                        index_block_hash TEXT NOT NULL,
+                                        block_height INT NOT NULL,
+                                        tx_index INT NOT NULL,
+                                        PRIMARY KEY(principal,index_block_hash,tx_index)
+    );
+    CREATE INDEX principal_principal_transactions ON principal_transactions(principal,block_height);
+    "#,
+    r#"
+    -- bloom filter over the contract events emitted by each processed block
+    CREATE TABLE block_event_blooms(index_block_hash TEXT PRIMARY KEY NOT NULL,
+                                    bloom TEXT NOT NULL                   -- hex-encoded EventBloom
+    );
+    "#,
+    r#"
     -- users who burned in support of a block
     CREATE TABLE staging_user_burn_support(anchored_block_hash TEXT NOT NULL,
                                            consensus_hash TEXT NOT NULL,
@@ -642,16 +1075,88 @@ impl ChainstateAccountLockup {
     }
 }
 
+/// A transaction receipt as recorded by `ChainstateTx::log_transaction_receipts()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredTransactionReceipt {
+    pub txid: Txid,
+    pub index_block_hash: StacksBlockId,
+    pub tx_index: u32,
+    pub raw_tx: String,
+    pub result: Value,
+    pub post_condition_aborted: bool,
+    pub stx_burned: u128,
+    pub execution_cost: ExecutionCost,
+    pub contract_abi: Option<serde_json::Value>,
+    pub events: Vec<serde_json::Value>,
+    pub asset_map: serde_json::Value,
+}
+
+impl FromRow<StoredTransactionReceipt> for StoredTransactionReceipt {
+    fn from_row<'a>(row: &'a Row) -> Result<StoredTransactionReceipt, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let tx_index = u64::from_column(row, "tx_index")? as u32;
+        let raw_tx: String = row.get("raw_tx");
+        let raw_result: String = row.get("raw_result");
+        let post_condition_aborted: bool = row.get("post_condition_aborted");
+        let stx_burned_text: String = row.get("stx_burned");
+        let execution_cost_text: String = row.get("execution_cost");
+        let contract_abi_text: Option<String> = row.get("contract_abi");
+        let events_text: String = row.get("events");
+        let asset_map_text: String = row.get("asset_map");
+
+        let result =
+            Value::try_deserialize_hex_untyped(&raw_result).map_err(|_e| db_error::ParseError)?;
+        let stx_burned = stx_burned_text
+            .parse::<u128>()
+            .map_err(|_| db_error::ParseError)?;
+        let execution_cost =
+            serde_json::from_str(&execution_cost_text).map_err(|_| db_error::ParseError)?;
+        let contract_abi = match contract_abi_text {
+            Some(text) => Some(serde_json::from_str(&text).map_err(|_| db_error::ParseError)?),
+            None => None,
+        };
+        let events = serde_json::from_str(&events_text).map_err(|_| db_error::ParseError)?;
+        let asset_map = serde_json::from_str(&asset_map_text).map_err(|_| db_error::ParseError)?;
+
+        Ok(StoredTransactionReceipt {
+            txid,
+            index_block_hash,
+            tx_index,
+            raw_tx,
+            result,
+            post_condition_aborted,
+            stx_burned,
+            execution_cost,
+            contract_abi,
+            events,
+            asset_map,
+        })
+    }
+}
+
+/// What the bulk genesis balances are expected to add up to.  If given, the balances imported on
+/// first boot are checked against it, and the chainstate is not instantiated if they differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenesisBalancesAudit {
+    pub expected_count: u64,
+    pub expected_ustx: u128,
+}
+
 pub struct ChainStateBootData {
     pub first_burnchain_block_hash: BurnchainHeaderHash,
     pub first_burnchain_block_height: u32,
     pub first_burnchain_block_timestamp: u32,
     pub initial_balances: Vec<(PrincipalData, u64)>,
+    /// (principal, uSTX, unlock burnchain block height) locks on the initial balances, such as
+    /// vesting schedules.  They are independent of PoX, so a locked account can still stack.
+    pub initial_locks: Vec<(PrincipalData, u64, u64)>,
     pub post_flight_callback: Option<Box<dyn FnOnce(&mut ClarityTx) -> ()>>,
     pub get_bulk_initial_lockups:
         Option<Box<dyn FnOnce() -> Box<dyn Iterator<Item = ChainstateAccountLockup>>>>,
     pub get_bulk_initial_balances:
         Option<Box<dyn FnOnce() -> Box<dyn Iterator<Item = ChainstateAccountBalance>>>>,
+    pub genesis_balances_audit: Option<GenesisBalancesAudit>,
 }
 
 impl ChainStateBootData {
@@ -665,9 +1170,11 @@ impl ChainStateBootData {
             first_burnchain_block_height: burnchain.first_block_height as u32,
             first_burnchain_block_timestamp: burnchain.first_block_timestamp,
             initial_balances,
+            initial_locks: vec![],
             post_flight_callback,
             get_bulk_initial_lockups: None,
             get_bulk_initial_balances: None,
+            genesis_balances_audit: None,
         }
     }
 }
@@ -747,10 +1254,36 @@ impl StacksChainState {
                 return Err(Error::InvalidChainstateDB);
             }
 
+            StacksChainState::migrate_db(marf.sqlite_conn())?;
             Ok(marf)
         }
     }
 
+    /// Bring an existing chain state database's schema up to date.  Databases created before
+    /// coinbases could name a reward recipient have no `payments.recipient` column; existing
+    /// rows paid their miner, so they get NULL.
+    fn migrate_db(conn: &DBConn) -> Result<(), Error> {
+        let mut columns: Vec<String> = vec![];
+        {
+            let mut stmt = conn
+                .prepare("PRAGMA table_info(payments)")
+                .map_err(db_error::SqliteError)?;
+            let rows = stmt
+                .query_map(NO_PARAMS, |row| row.get(1))
+                .map_err(db_error::SqliteError)?;
+            for row in rows {
+                columns.push(row.map_err(db_error::SqliteError)?);
+            }
+        }
+
+        if !columns.iter().any(|column| column == "recipient") {
+            info!("Migrate chain state database: add payments.recipient");
+            conn.execute("ALTER TABLE payments ADD COLUMN recipient TEXT", NO_PARAMS)
+                .map_err(db_error::SqliteError)?;
+        }
+        Ok(())
+    }
+
     pub fn open_index(marf_path: &str) -> Result<MARF<StacksBlockId>, Error> {
         test_debug!("Open MARF index at {}", marf_path);
         let marf =
@@ -897,10 +1430,16 @@ impl StacksChainState {
                 "Initializing chain with {} config balances",
                 boot_data.initial_balances.len()
             );
+            let config_balances = &boot_data.initial_balances;
+            clarity_tx.connection().as_transaction(|clarity| {
+                StacksChainState::account_genesis_credit_batch(
+                    clarity,
+                    config_balances
+                        .iter()
+                        .map(|(address, amount)| (address.clone(), *amount as u128)),
+                )
+            });
             for (address, amount) in boot_data.initial_balances.iter() {
-                clarity_tx.connection().as_transaction(|clarity| {
-                    StacksChainState::account_genesis_credit(clarity, address, (*amount).into())
-                });
                 initial_liquid_ustx = initial_liquid_ustx
                     .checked_add(*amount as u128)
                     .expect("FATAL: liquid STX overflow");
@@ -913,35 +1452,51 @@ impl StacksChainState {
                 allocation_events.push(mint_event);
             }
 
+            let mut balances_count = 0;
+            let mut balances_ustx = 0u128;
             if let Some(get_balances) = boot_data.get_bulk_initial_balances.take() {
                 info!("Initializing chain with balances");
-                let mut balances_count = 0;
                 clarity_tx.connection().as_transaction(|clarity| {
-                    let initial_balances = get_balances();
-                    for balance in initial_balances {
+                    let initial_balances = get_balances().map(|balance| {
                         balances_count = balances_count + 1;
                         let stx_address =
                             StacksChainState::parse_genesis_address(&balance.address, mainnet);
-                        StacksChainState::account_genesis_credit(
-                            clarity,
-                            &stx_address,
-                            balance.amount.into(),
-                        );
                         initial_liquid_ustx = initial_liquid_ustx
                             .checked_add(balance.amount as u128)
                             .expect("FATAL: liquid STX overflow");
+                        balances_ustx = balances_ustx
+                            .checked_add(balance.amount as u128)
+                            .expect("FATAL: liquid STX overflow");
                         let mint_event = StacksTransactionEvent::STXEvent(
                             STXEventType::STXMintEvent(STXMintEventData {
-                                recipient: stx_address,
+                                recipient: stx_address.clone(),
                                 amount: balance.amount.into(),
                             }),
                         );
                         allocation_events.push(mint_event);
-                    }
+                        (stx_address, balance.amount as u128)
+                    });
+                    StacksChainState::account_genesis_credit_batch(clarity, initial_balances);
                     info!("Committing {} balances to genesis tx", balances_count);
                 });
             }
 
+            if let Some(audit) = boot_data.genesis_balances_audit.as_ref() {
+                if balances_count != audit.expected_count || balances_ustx != audit.expected_ustx {
+                    let msg = format!(
+                        "Genesis balance audit failed: imported {} balances totaling {} uSTX, but expected {} balances totaling {} uSTX",
+                        balances_count, balances_ustx, audit.expected_count, audit.expected_ustx
+                    );
+                    error!("{}", &msg);
+                    clarity_tx.rollback_block();
+                    return Err(Error::GenesisAuditFailed(msg));
+                }
+                info!(
+                    "Genesis balance audit passed: {} balances totaling {} uSTX",
+                    balances_count, balances_ustx
+                );
+            }
+
             let allocations_tx = StacksTransaction::new(
                 tx_version.clone(),
                 boot_code_auth.clone(),
@@ -949,6 +1504,7 @@ impl StacksChainState {
                     PrincipalData::Standard(boot_code_address.into()),
                     0,
                     TokenTransferMemo([0u8; 34]),
+                    None,
                 ),
             );
             let allocations_receipt = StacksTransactionReceipt::from_stx_transfer(
@@ -1006,6 +1562,49 @@ impl StacksChainState {
                 callback(&mut clarity_tx);
             }
 
+            // networks that activate Stacks 2.1 from their first burnchain block start out
+            // upgraded, so their initial locks are locked under the new rules
+            let upgraded = match StacksChainState::process_stacks_2_1_upgrade(
+                &mut clarity_tx,
+                boot_data.first_burnchain_block_height as u64,
+            ) {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    error!(
+                        "Failed to upgrade the genesis block to Stacks 2.1: {:?}",
+                        &e
+                    );
+                    clarity_tx.rollback_block();
+                    return Err(e);
+                }
+            };
+
+            if !boot_data.initial_locks.is_empty() {
+                // balances with independent locks have a longer encoding, which only Stacks 2.1
+                // nodes can read
+                if !upgraded {
+                    error!("Initial locks require a chain that starts out on Stacks 2.1");
+                    clarity_tx.rollback_block();
+                    return Err(Error::InvalidChainstateDB);
+                }
+
+                info!("Locking {} initial balances", boot_data.initial_locks.len());
+                let config_locks = &boot_data.initial_locks;
+                let result = clarity_tx.connection().as_transaction(|clarity| {
+                    StacksChainState::account_genesis_lock_batch(
+                        clarity,
+                        config_locks.iter().map(|(address, amount, unlock_height)| {
+                            (address.clone(), *amount as u128, *unlock_height)
+                        }),
+                    )
+                });
+                if let Err(e) = result {
+                    error!("Failed to lock initial balances: {:?}", &e);
+                    clarity_tx.rollback_block();
+                    return Err(e);
+                }
+            }
+
             clarity_tx.commit_to_block(&FIRST_BURNCHAIN_CONSENSUS_HASH, &FIRST_STACKS_BLOCK_HASH);
         }
 
@@ -1054,6 +1653,175 @@ impl StacksChainState {
         Ok(receipts)
     }
 
+    /// Carry out the Stacks 2.1 upgrade, if this fork has not upgraded yet and
+    /// `burn_block_height` has reached the network's activation height.  Every block and
+    /// microblock stream runs this before anything else, so a fork upgrades at the start of the
+    /// first block built off of a parent mined at or after the activation height.  Networks
+    /// without an activation height never upgrade.
+    /// Returns true if this call upgraded the fork.
+    pub fn process_stacks_2_1_upgrade(
+        clarity_tx: &mut ClarityTx,
+        burn_block_height: u64,
+    ) -> Result<bool, Error> {
+        let activation_height = if clarity_tx.config.mainnet {
+            STACKS_2_1_ACTIVATION_HEIGHT_MAINNET
+        } else {
+            STACKS_2_1_ACTIVATION_HEIGHT_TESTNET
+        };
+        match activation_height {
+            Some(activation_height) if burn_block_height >= activation_height => {
+                StacksChainState::upgrade_to_stacks_2_1(clarity_tx, burn_block_height)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Upgrade this fork to Stacks 2.1 in a block built at `burn_block_height`: record the
+    /// upgrade and instantiate the boot code it adds.  Returns false if the fork already
+    /// upgraded.
+    fn upgrade_to_stacks_2_1(
+        clarity_tx: &mut ClarityTx,
+        burn_block_height: u64,
+    ) -> Result<bool, Error> {
+        let upgraded = clarity_tx.connection().as_transaction(|clarity| {
+            clarity.with_clarity_db(|db| {
+                if db.is_stacks_2_1_active() {
+                    return Ok(false);
+                }
+                db.set_stacks_2_1_active(burn_block_height);
+                Ok(true)
+            })
+        })?;
+        if !upgraded {
+            return Ok(false);
+        }
+
+        let boot_code = if clarity_tx.config.mainnet {
+            *boot::STACKS_2_1_BOOT_CODE_MAINNET
+        } else {
+            *boot::STACKS_2_1_BOOT_CODE_TESTNET
+        };
+
+        // like at genesis, boot code is free to deploy
+        let (result, _) =
+            clarity_tx.with_temporary_cost_tracker(LimitedCostTracker::new_free(), |clarity_tx| {
+                clarity_tx.connection().as_transaction(|clarity| {
+                    for (boot_code_name, boot_code_contract) in boot_code.iter() {
+                        StacksChainState::install_upgrade_boot_code(
+                            clarity,
+                            boot_code_name,
+                            boot_code_contract,
+                        )?;
+                    }
+                    StacksChainState::configure_pox_2(clarity)
+                })
+            });
+        result?;
+
+        // this block's cost tracker was set up before costs-2 existed
+        clarity_tx
+            .connection()
+            .as_transaction(|clarity| clarity.reload_boot_costs())?;
+
+        info!(
+            "Upgraded to Stacks 2.1 at burnchain block height {}",
+            burn_block_height
+        );
+        Ok(true)
+    }
+
+    /// Instantiate a boot code contract that a network upgrade adds
+    fn install_upgrade_boot_code(
+        clarity: &mut ClarityTransactionConnection,
+        boot_code_name: &str,
+        boot_code_contract: &str,
+    ) -> Result<(), Error> {
+        debug!(
+            "Instantiate boot code contract '{}.{}' ({} bytes)...",
+            &STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR,
+            boot_code_name,
+            boot_code_contract.len()
+        );
+
+        let contract_id = boot_code_id(boot_code_name);
+        let (contract_ast, contract_analysis) =
+            clarity.analyze_smart_contract(&contract_id, boot_code_contract)?;
+        clarity.initialize_smart_contract(
+            &contract_id,
+            &contract_ast,
+            boot_code_contract,
+            |_, _| false,
+        )?;
+        clarity
+            .save_analysis(&contract_id, &contract_analysis)
+            .expect("FATAL: failed to store contract analysis");
+        Ok(())
+    }
+
+    /// Give `pox-2` the burnchain parameters of the original PoX contract, if it was configured
+    /// with any, so that both contracts agree on when reward cycles begin.
+    fn configure_pox_2(clarity: &mut ClarityTransactionConnection) -> Result<(), Error> {
+        let pox_contract = boot_code_id("pox");
+        let params = clarity.with_clarity_db_readonly(
+            |db| -> Result<Option<Vec<Value>>, InterpreterError> {
+                if !db
+                    .lookup_variable(&pox_contract, "configured")?
+                    .expect_bool()
+                {
+                    return Ok(None);
+                }
+                let mut params = vec![];
+                for variable_name in [
+                    "first-burnchain-block-height",
+                    "pox-prepare-cycle-length",
+                    "pox-reward-cycle-length",
+                    "pox-rejection-fraction",
+                ]
+                .iter()
+                {
+                    params.push(db.lookup_variable(&pox_contract, variable_name)?);
+                }
+                Ok(Some(params))
+            },
+        )?;
+
+        let params = match params {
+            Some(params) => params,
+            None => {
+                return Ok(());
+            }
+        };
+
+        let pox_2_contract = boot_code_id("pox-2");
+        let (result, _, _) = clarity.run_contract_call(
+            &PrincipalData::from(pox_2_contract.clone()),
+            &pox_2_contract,
+            "set-burnchain-parameters",
+            &params,
+            |_, _| false,
+        )?;
+        result.expect_result_ok().expect_bool();
+        Ok(())
+    }
+
+    /// Carry out the Stacks 2.1 upgrade at the start of a block or microblock stream, if it is
+    /// due
+    fn begin_stacks_2_1_upgrade(clarity_tx: &mut ClarityTx) {
+        let burn_block_height = match clarity_tx.with_clarity_db_readonly(|db| {
+            if db.is_stacks_2_1_active() {
+                None
+            } else {
+                Some(db.get_current_burnchain_block_height() as u64)
+            }
+        }) {
+            Some(burn_block_height) => burn_block_height,
+            // already upgraded
+            None => return,
+        };
+        StacksChainState::process_stacks_2_1_upgrade(clarity_tx, burn_block_height)
+            .expect("FATAL: failed to upgrade to Stacks 2.1");
+    }
+
     pub fn open(
         mainnet: bool,
         chain_id: u32,
@@ -1071,7 +1839,10 @@ impl StacksChainState {
     /// Re-open the chainstate -- i.e. to get a new handle to it using an existing chain state's
     /// parameters
     pub fn reopen(&self) -> Result<(StacksChainState, Vec<StacksTransactionReceipt>), Error> {
-        StacksChainState::open(self.mainnet, self.chain_id, &self.root_path)
+        let (mut chainstate, receipts) =
+            StacksChainState::open(self.mainnet, self.chain_id, &self.root_path)?;
+        chainstate.size_limits = self.size_limits.clone();
+        Ok((chainstate, receipts))
     }
 
     /// Re-open the chainstate -- i.e. to get a new handle to it using an existing chain state's
@@ -1080,7 +1851,15 @@ impl StacksChainState {
         &self,
         budget: ExecutionCost,
     ) -> Result<(StacksChainState, Vec<StacksTransactionReceipt>), Error> {
-        StacksChainState::open_and_exec(self.mainnet, self.chain_id, &self.root_path, None, budget)
+        let (mut chainstate, receipts) = StacksChainState::open_and_exec(
+            self.mainnet,
+            self.chain_id,
+            &self.root_path,
+            None,
+            budget,
+        )?;
+        chainstate.size_limits = self.size_limits.clone();
+        Ok((chainstate, receipts))
     }
 
     pub fn open_testnet<F>(
@@ -1183,7 +1962,11 @@ impl StacksChainState {
             clarity_state_index_root: clarity_state_index_root,
             root_path: path_str.to_string(),
             block_limit: block_limit,
+            size_limits: EpochSizeLimits::default(),
             unconfirmed_state: None,
+            indexers: IndexerSet::new(),
+            consensus_audit_log: None,
+            max_fork_depth: None,
         };
 
         let mut receipts = vec![];
@@ -1280,6 +2063,49 @@ impl StacksChainState {
         self.state_index.sqlite_conn()
     }
 
+    /// Get the transaction receipts recorded for a processed block, in block order.
+    /// Returns an empty list if the block was never processed.
+    pub fn get_transaction_receipts(
+        conn: &Connection,
+        block_id: &StacksBlockId,
+    ) -> Result<Vec<StoredTransactionReceipt>, Error> {
+        let sql =
+            "SELECT * FROM transaction_receipts WHERE index_block_hash = ?1 ORDER BY tx_index ASC";
+        let args: &[&dyn ToSql] = &[block_id];
+        query_rows(conn, sql, args).map_err(Error::DBError)
+    }
+
+    /// Get the recorded receipt of a transaction mined in the fork ending at `tip`, if it was.
+    /// Receipts of the same transaction mined in other forks are ignored.
+    pub fn get_transaction_receipt(
+        &self,
+        txid: &Txid,
+        tip: &StacksBlockId,
+    ) -> Result<Option<StoredTransactionReceipt>, Error> {
+        let sql = "SELECT * FROM transaction_receipts WHERE txid = ?1";
+        let args: &[&dyn ToSql] = &[txid];
+        let rows = query_rows::<StoredTransactionReceipt, _>(self.db(), sql, args)?;
+        for row in rows.into_iter() {
+            if get_ancestor_block_height(&self.state_index, &row.index_block_hash, tip)?.is_some() {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the contract event bloom filter recorded for a processed block.
+    /// Returns None if the block was never processed.
+    pub fn get_block_event_bloom(
+        conn: &Connection,
+        block_id: &StacksBlockId,
+    ) -> Result<Option<EventBloom>, Error> {
+        let sql = "SELECT bloom FROM block_event_blooms WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[block_id];
+        conn.query_row(sql, args, |row| row.get(0))
+            .optional()
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))
+    }
+
     /// Begin processing an epoch's transactions within the context of a chainstate transaction
     pub fn chainstate_block_begin<'a>(
         chainstate_tx: &'a ChainstateTx<'a>,
@@ -1486,6 +2312,75 @@ impl StacksChainState {
         }
     }
 
+    /// Run to_do against chain state that no block has confirmed yet: the state at the processed
+    /// block `parent_tip`, plus the transactions of its descendant microblock stream if
+    /// `with_microblocks` is set, plus `pending_txs` applied in order.  Pending transactions that
+    /// cannot be applied (e.g. because of a bad nonce) are skipped.  Nothing is committed -- the
+    /// Clarity state is rolled back once to_do returns.  Returns Ok(None) if the tip doesn't exist.
+    pub fn with_pending_clarity_tx<F, R>(
+        &mut self,
+        burn_dbconn: &dyn BurnStateDB,
+        parent_tip: &StacksBlockId,
+        with_microblocks: bool,
+        pending_txs: &[StacksTransaction],
+        to_do: F,
+    ) -> Result<Option<R>, Error>
+    where
+        F: FnOnce(&mut ClarityTx) -> R,
+    {
+        let parent_header =
+            match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                self.db(),
+                parent_tip,
+            )? {
+                Some(header) => header,
+                None => {
+                    return Ok(None);
+                }
+            };
+
+        let microblocks = if with_microblocks {
+            StacksChainState::load_descendant_staging_microblock_stream(
+                self.db(),
+                parent_tip,
+                0,
+                u16::MAX,
+            )?
+            .unwrap_or(vec![])
+        } else {
+            vec![]
+        };
+
+        let mut clarity_tx = self.block_begin(
+            burn_dbconn,
+            &parent_header.consensus_hash,
+            &parent_header.anchored_header.block_hash(),
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+
+        if let Err((e, microblock_hash)) =
+            StacksChainState::process_microblocks_transactions(&mut clarity_tx, &microblocks)
+        {
+            warn!(
+                "Failed to apply microblock stream of {} (offender {}): {:?}",
+                parent_tip, &microblock_hash, &e
+            );
+            clarity_tx.rollback_block();
+            return Err(e);
+        }
+
+        for tx in pending_txs.iter() {
+            if let Err(e) = StacksChainState::process_transaction(&mut clarity_tx, tx, true) {
+                debug!("Skip pending transaction {}: {:?}", &tx.txid(), &e);
+            }
+        }
+
+        let result = to_do(&mut clarity_tx);
+        clarity_tx.rollback_block();
+        Ok(Some(result))
+    }
+
     fn get_parent_index_block(
         parent_consensus_hash: &ConsensusHash,
         parent_block: &BlockHeaderHash,
@@ -1536,13 +2431,15 @@ impl StacksChainState {
 
             unconfirmed.set_dirty(true);
 
-            Some(StacksChainState::chainstate_begin_unconfirmed(
+            let mut clarity_tx = StacksChainState::chainstate_begin_unconfirmed(
                 conf,
                 self.state_index.sqlite_conn(),
                 &mut unconfirmed.clarity_inst,
                 burn_dbconn,
                 &unconfirmed.confirmed_chain_tip,
-            ))
+            );
+            StacksChainState::begin_stacks_2_1_upgrade(&mut clarity_tx);
+            Some(clarity_tx)
         } else {
             debug!("Unconfirmed state is not instantiated; cannot begin unconfirmed Clarity Tx");
             None
@@ -1594,10 +2491,12 @@ impl StacksChainState {
         );
 
         test_debug!("Got clarity TX!");
-        ClarityTx {
+        let mut clarity_tx = ClarityTx {
             block: inner_clarity_tx,
             config: conf,
-        }
+        };
+        StacksChainState::begin_stacks_2_1_upgrade(&mut clarity_tx);
+        clarity_tx
     }
 
     /// Get the appropriate MARF index hash to use to identify a chain tip, given a block header
@@ -1737,21 +2636,68 @@ pub mod test {
     use std::fs;
 
     use vm::database::NULL_BURN_STATE_DB;
+    use vm::types::BuffData;
 
-    pub fn instantiate_chainstate(
-        mainnet: bool,
-        chain_id: u32,
-        test_name: &str,
-    ) -> StacksChainState {
-        instantiate_chainstate_with_balances(mainnet, chain_id, test_name, vec![])
+    pub use chainstate::stacks::db::testing::{
+        chainstate_path, instantiate_chainstate, instantiate_chainstate_with_balances,
+        open_chainstate,
+    };
+
+    #[test]
+    fn test_instantiate_chainstate() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "instantiate-chainstate");
+
+        // verify that the boot code is there
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+
+        for (boot_contract_name, _) in STACKS_BOOT_CODE_TESTNET.iter() {
+            let boot_contract_id = QualifiedContractIdentifier::new(
+                StandardPrincipalData::from(STACKS_BOOT_CODE_CONTRACT_ADDRESS.clone()),
+                ContractName::try_from(boot_contract_name.to_string()).unwrap(),
+            );
+            let contract_res =
+                StacksChainState::get_contract(&mut conn, &boot_contract_id).unwrap();
+            assert!(contract_res.is_some());
+        }
     }
 
-    pub fn instantiate_chainstate_with_balances(
-        mainnet: bool,
-        chain_id: u32,
+    #[test]
+    fn test_migrate_db_payments_recipient() {
+        // a payments table from before coinbases could name a reward recipient
+        let conn = DBConn::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE payments(address TEXT NOT NULL, coinbase TEXT NOT NULL)",
+            NO_PARAMS,
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO payments (address,coinbase) VALUES ('SP000000000000000000002Q6VF78','1')",
+            NO_PARAMS,
+        )
+        .unwrap();
+
+        // migrating is idempotent
+        StacksChainState::migrate_db(&conn).unwrap();
+        StacksChainState::migrate_db(&conn).unwrap();
+
+        let recipient: Option<String> = conn
+            .query_row("SELECT recipient FROM payments", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(recipient, None);
+    }
+
+    fn boot_with_genesis_balances_audit(
         test_name: &str,
-        balances: Vec<(StacksAddress, u64)>,
-    ) -> StacksChainState {
+        audit: GenesisBalancesAudit,
+    ) -> Result<StacksChainState, Error> {
         let path = chainstate_path(test_name);
         match fs::metadata(&path) {
             Ok(_) => {
@@ -1760,46 +2706,116 @@ pub mod test {
             Err(_) => {}
         };
 
-        let initial_balances = balances
-            .into_iter()
-            .map(|(addr, balance)| (PrincipalData::from(addr), balance))
-            .collect();
+        let get_balances = || -> Box<dyn Iterator<Item = ChainstateAccountBalance>> {
+            Box::new(
+                vec![
+                    ChainstateAccountBalance {
+                        address: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+                        amount: 1000,
+                    },
+                    ChainstateAccountBalance {
+                        address: "STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW".to_string(),
+                        amount: 2000,
+                    },
+                ]
+                .into_iter(),
+            )
+        };
 
         let mut boot_data = ChainStateBootData {
-            initial_balances,
+            initial_balances: vec![],
+            initial_locks: vec![],
             post_flight_callback: None,
             first_burnchain_block_hash: BurnchainHeaderHash::zero(),
             first_burnchain_block_height: 0,
             first_burnchain_block_timestamp: 0,
             get_bulk_initial_lockups: None,
-            get_bulk_initial_balances: None,
+            get_bulk_initial_balances: Some(Box::new(get_balances)),
+            genesis_balances_audit: Some(audit),
         };
 
         StacksChainState::open_and_exec(
-            mainnet,
-            chain_id,
+            false,
+            0x80000000,
             &path,
             Some(&mut boot_data),
             ExecutionCost::max_value(),
         )
-        .unwrap()
-        .0
-    }
-
-    pub fn open_chainstate(mainnet: bool, chain_id: u32, test_name: &str) -> StacksChainState {
-        let path = chainstate_path(test_name);
-        StacksChainState::open(mainnet, chain_id, &path).unwrap().0
+        .map(|(chainstate, _)| chainstate)
     }
 
-    pub fn chainstate_path(test_name: &str) -> String {
-        format!("/tmp/blockstack-test-chainstate-{}", test_name)
+    #[test]
+    fn test_stacks_2_1_upgrade() {
+        let principal = PrincipalData::from(StacksAddress {
+            version: C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+            bytes: Hash160([0x01; 20]),
+        });
+
+        // mainnet has no activation height, so locks are not scheduled
+        let mut chainstate = instantiate_chainstate(true, 0x00000001, "stacks-2-1-upgrade");
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+        let scheduled = conn.connection().as_transaction(|clarity| {
+            clarity
+                .with_clarity_db(|db| {
+                    assert!(!db.is_stacks_2_1_active());
+                    db.schedule_stx_unlock(&principal, 100);
+                    Ok(db.take_stx_unlocks(100, 1))
+                })
+                .unwrap()
+        });
+        assert!(scheduled.is_empty());
+
+        assert!(!StacksChainState::process_stacks_2_1_upgrade(&mut conn, u32::MAX as u64).unwrap());
+        assert!(!conn.with_clarity_db_readonly(|db| db.is_stacks_2_1_active()));
+
+        // the upgrade happens only once
+        assert!(StacksChainState::upgrade_to_stacks_2_1(&mut conn, 100).unwrap());
+        assert!(!StacksChainState::upgrade_to_stacks_2_1(&mut conn, 101).unwrap());
+
+        let scheduled = conn.connection().as_transaction(|clarity| {
+            clarity
+                .with_clarity_db(|db| {
+                    assert!(db.is_stacks_2_1_active());
+                    db.schedule_stx_unlock(&principal, 100);
+                    Ok(db.take_stx_unlocks(100, 1))
+                })
+                .unwrap()
+        });
+        assert_eq!(scheduled, vec![principal.clone()]);
+        conn.rollback_block();
+
+        // test networks start out upgraded
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "stacks-2-1-upgrade-testnet");
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+        assert!(conn.with_clarity_db_readonly(|db| db.is_stacks_2_1_active()));
+        conn.rollback_block();
     }
 
     #[test]
-    fn test_instantiate_chainstate() {
-        let mut chainstate = instantiate_chainstate(false, 0x80000000, "instantiate-chainstate");
+    fn test_take_stx_unlocks_bounded() {
+        let principals: Vec<PrincipalData> = (1..4)
+            .map(|i| {
+                PrincipalData::from(StacksAddress {
+                    version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                    bytes: Hash160([i; 20]),
+                })
+            })
+            .collect();
 
-        // verify that the boot code is there
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "take-stx-unlocks-bounded");
         let mut conn = chainstate.block_begin(
             &NULL_BURN_STATE_DB,
             &FIRST_BURNCHAIN_CONSENSUS_HASH,
@@ -1807,15 +2823,220 @@ pub mod test {
             &MINER_BLOCK_CONSENSUS_HASH,
             &MINER_BLOCK_HEADER_HASH,
         );
+        conn.connection().as_transaction(|clarity| {
+            clarity
+                .with_clarity_db(|db| {
+                    db.schedule_stx_unlock(&principals[0], 100);
+                    db.schedule_stx_unlock(&principals[1], 100);
+                    db.schedule_stx_unlock(&principals[2], 101);
+                    db.schedule_stx_unlock(&principals[0], 102);
+
+                    // at most 2 are taken, and the rest stay scheduled
+                    assert_eq!(
+                        db.take_stx_unlocks(102, 2),
+                        vec![principals[1].clone(), principals[0].clone()]
+                    );
+                    assert_eq!(db.take_stx_unlocks(101, 10), vec![principals[2].clone()]);
+                    assert_eq!(db.take_stx_unlocks(102, 10), vec![principals[0].clone()]);
+                    assert!(db.take_stx_unlocks(102, 10).is_empty());
+
+                    // a height that was only partly taken can still be scheduled
+                    db.schedule_stx_unlock(&principals[0], 103);
+                    db.schedule_stx_unlock(&principals[1], 103);
+                    assert_eq!(db.take_stx_unlocks(103, 1), vec![principals[1].clone()]);
+                    db.schedule_stx_unlock(&principals[2], 103);
+                    assert_eq!(
+                        db.take_stx_unlocks(103, 10),
+                        vec![principals[2].clone(), principals[0].clone()]
+                    );
+                    Ok(())
+                })
+                .unwrap()
+        });
+        conn.rollback_block();
+    }
 
-        for (boot_contract_name, _) in STACKS_BOOT_CODE_TESTNET.iter() {
-            let boot_contract_id = QualifiedContractIdentifier::new(
-                StandardPrincipalData::from(STACKS_BOOT_CODE_CONTRACT_ADDRESS.clone()),
-                ContractName::try_from(boot_contract_name.to_string()).unwrap(),
-            );
-            let contract_res =
-                StacksChainState::get_contract(&mut conn, &boot_contract_id).unwrap();
-            assert!(contract_res.is_some());
+    #[test]
+    fn test_genesis_balances_audit() {
+        let chainstate = boot_with_genesis_balances_audit(
+            "genesis-balances-audit-pass",
+            GenesisBalancesAudit {
+                expected_count: 2,
+                expected_ustx: 3000,
+            },
+        )
+        .unwrap();
+        let genesis = StacksChainState::get_genesis_header_info(chainstate.db()).unwrap();
+        assert_eq!(genesis.total_liquid_ustx, 3000);
+
+        // a truncated import is caught
+        match boot_with_genesis_balances_audit(
+            "genesis-balances-audit-missing-balance",
+            GenesisBalancesAudit {
+                expected_count: 3,
+                expected_ustx: 3000,
+            },
+        ) {
+            Err(Error::GenesisAuditFailed(_)) => {}
+            Err(e) => panic!("Unexpected error: {:?}", &e),
+            Ok(_) => panic!("Genesis balance audit did not fail"),
+        }
+
+        // so is an unexpected total supply
+        match boot_with_genesis_balances_audit(
+            "genesis-balances-audit-wrong-supply",
+            GenesisBalancesAudit {
+                expected_count: 2,
+                expected_ustx: 4000,
+            },
+        ) {
+            Err(Error::GenesisAuditFailed(_)) => {}
+            Err(e) => panic!("Unexpected error: {:?}", &e),
+            Ok(_) => panic!("Genesis balance audit did not fail"),
+        }
+    }
+
+    #[test]
+    fn test_log_transaction_receipts() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "log-transaction-receipts");
+
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let addr = auth.origin().address_testnet();
+        let recv_addr = StacksAddress {
+            version: 1,
+            bytes: Hash160([0xff; 20]),
+        };
+
+        let tx_coinbase = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
+        );
+        let tx_stx_transfer = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::TokenTransfer(
+                recv_addr.clone().into(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+                None,
+            ),
+        );
+        let transfer_event = StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+            STXTransferEventData {
+                sender: addr.to_account_principal(),
+                recipient: recv_addr.to_account_principal(),
+                amount: 123,
+                memo: BuffData::empty(),
+                long_memo: None,
+            },
+        ));
+        let transfer_cost = ExecutionCost {
+            write_length: 1,
+            write_count: 2,
+            read_length: 3,
+            read_count: 4,
+            runtime: 5,
+        };
+
+        let receipts = vec![
+            StacksTransactionReceipt::from_coinbase(tx_coinbase.clone()),
+            StacksTransactionReceipt::from_stx_transfer(
+                tx_stx_transfer.clone(),
+                vec![transfer_event.clone(), transfer_event.clone()],
+                Value::okay_true(),
+                transfer_cost.clone(),
+            ),
+        ];
+
+        let block_id = StacksBlockId([0x01; 32]);
+        {
+            let (chainstate_tx, _) = chainstate.chainstate_tx_begin().unwrap();
+            chainstate_tx.log_transaction_receipts(&block_id, &receipts);
+            chainstate_tx.commit().unwrap();
+        }
+
+        let stored =
+            StacksChainState::get_transaction_receipts(chainstate.db(), &block_id).unwrap();
+        assert_eq!(stored.len(), 2);
+
+        assert_eq!(stored[0].txid, tx_coinbase.txid());
+        assert_eq!(stored[0].tx_index, 0);
+        assert_eq!(stored[0].raw_tx, to_hex(&tx_coinbase.serialize_to_vec()));
+        assert_eq!(stored[0].result, Value::okay_true());
+        assert!(stored[0].events.is_empty());
+
+        assert_eq!(stored[1].txid, tx_stx_transfer.txid());
+        assert_eq!(stored[1].tx_index, 1);
+        assert_eq!(stored[1].execution_cost, transfer_cost);
+        assert_eq!(stored[1].contract_abi, None);
+        assert!(!stored[1].post_condition_aborted);
+
+        // events are numbered across the block
+        assert_eq!(
+            stored[1].events,
+            vec![
+                transfer_event.json_serialize(0, &tx_stx_transfer.txid(), true),
+                transfer_event.json_serialize(1, &tx_stx_transfer.txid(), true),
+            ]
+        );
+
+        assert!(StacksChainState::get_transaction_receipts(
+            chainstate.db(),
+            &StacksBlockId([0x02; 32])
+        )
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn test_log_event_bloom() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "log-event-bloom");
+
+        let contract_id =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.foo")
+                .unwrap();
+        let other_contract_id =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.bar")
+                .unwrap();
+        let receipt = StacksTransactionReceipt {
+            transaction: TransactionOrigin::Burn(Txid([0x01; 32])),
+            events: vec![StacksTransactionEvent::SmartContractEvent(
+                SmartContractEventData {
+                    key: (contract_id.clone(), "print".to_string()),
+                    value: Value::Int(1),
+                },
+            )],
+            post_condition_aborted: false,
+            result: Value::okay_true(),
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::zero(),
+        };
+
+        let block_id = StacksBlockId([0x01; 32]);
+        {
+            let (chainstate_tx, _) = chainstate.chainstate_tx_begin().unwrap();
+            chainstate_tx.log_event_bloom(&block_id, &[receipt.clone()]);
+            chainstate_tx.commit().unwrap();
         }
+
+        let bloom = StacksChainState::get_block_event_bloom(chainstate.db(), &block_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bloom, EventBloom::from_receipts(&[receipt]));
+        assert!(bloom.may_contain_contract_topic(&contract_id, "print"));
+        assert!(!bloom.may_contain_contract(&other_contract_id));
+
+        assert!(StacksChainState::get_block_event_bloom(
+            chainstate.db(),
+            &StacksBlockId([0x02; 32])
+        )
+        .unwrap()
+        .is_none());
     }
 }
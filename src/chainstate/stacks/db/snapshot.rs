@@ -0,0 +1,380 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fungible token holder snapshots.
+//!
+//! Given a SIP-010 token contract and a Stacks block height, find every principal that has held
+//! the token and ask the contract for its balance as of the canonical block at that height.  The
+//! candidate holders come from the `asset_holdings` index, which records every principal ever
+//! credited with the token in any fork; the balances come from read-only `get-balance` calls, so
+//! they are whatever the contract itself reports.  The result can be written out as CSV and
+//! committed to with a Merkle root -- e.g. for an airdrop or a governance vote.
+
+use std::io::Write;
+
+use rusqlite::types::ToSql;
+
+use chainstate::burn::db::sortdb::SortitionDB;
+use chainstate::stacks::db::StacksChainState;
+use chainstate::stacks::Error;
+use chainstate::stacks::{StacksBlockHeader, StacksBlockId};
+
+use util::db::Error as db_error;
+use util::db::{get_ancestor_block_hash, query_row_columns, DBConn};
+use util::hash::{MerkleTree, Sha512Trunc256Sum};
+
+use vm::clarity::{ClarityConnection, Error as clarity_error};
+use vm::costs::LimitedCostTracker;
+use vm::types::{PrincipalData, QualifiedContractIdentifier, TupleData, Value};
+use vm::SymbolicExpression;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenHolderBalance {
+    pub holder: PrincipalData,
+    pub balance: u128,
+}
+
+impl TokenHolderBalance {
+    /// The Merkle tree leaf for this balance: the consensus serialization of the Clarity tuple
+    /// `{ balance: uint, holder: principal }`, so that a contract can check a proof against it.
+    pub fn merkle_leaf(&self) -> Vec<u8> {
+        let tuple = TupleData::from_data(vec![
+            ("balance".into(), Value::UInt(self.balance)),
+            ("holder".into(), Value::Principal(self.holder.clone())),
+        ])
+        .expect("BUG: failed to construct balance tuple");
+        let mut bytes = vec![];
+        Value::Tuple(tuple)
+            .serialize_write(&mut bytes)
+            .expect("BUG: failed to serialize balance tuple");
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenSnapshot {
+    pub contract_identifier: QualifiedContractIdentifier,
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    /// Non-zero balances, ordered by holder
+    pub balances: Vec<TokenHolderBalance>,
+    /// Sum of `balances`
+    pub total: u128,
+    /// What `get-total-supply` reported, if the contract implements it
+    pub total_supply: Option<u128>,
+}
+
+impl TokenSnapshot {
+    /// Root of the Merkle tree over the balances' leaves, in order
+    pub fn merkle_root(&self) -> Sha512Trunc256Sum {
+        let leaves = self.balances.iter().map(|b| b.merkle_leaf()).collect();
+        MerkleTree::<Sha512Trunc256Sum>::new(&leaves).root()
+    }
+
+    /// Write the balances as CSV, with a `holder,balance` header
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+        writeln!(w, "holder,balance").map_err(Error::WriteError)?;
+        for entry in self.balances.iter() {
+            writeln!(w, "{},{}", &entry.holder, entry.balance).map_err(Error::WriteError)?;
+        }
+        Ok(())
+    }
+}
+
+impl StacksChainState {
+    /// Every principal that has been credited with one of the contract's fungible tokens, in any
+    /// fork.  A principal in this list may no longer hold any.
+    pub fn get_token_holders(
+        conn: &DBConn,
+        contract_identifier: &QualifiedContractIdentifier,
+    ) -> Result<Vec<PrincipalData>, Error> {
+        let qry = "SELECT DISTINCT principal FROM asset_holdings WHERE contract_identifier = ?1 AND fungible = 1 ORDER BY principal".to_string();
+        let args: &[&dyn ToSql] = &[&contract_identifier.to_string()];
+        query_row_columns(conn, &qry, args, "principal").map_err(Error::DBError)
+    }
+
+    /// Call a SIP-010 read-only function that returns `(ok uint)`.  Returns None if the contract
+    /// does not define the function.
+    fn call_token_getter<C: ClarityConnection>(
+        conn: &mut C,
+        contract_identifier: &QualifiedContractIdentifier,
+        function: &str,
+        args: &[SymbolicExpression],
+    ) -> Result<Option<u128>, Error> {
+        let defined = conn.with_clarity_db_readonly(|clarity_db| {
+            clarity_db
+                .get_contract(contract_identifier)
+                .map(|contract| {
+                    contract
+                        .contract_context
+                        .lookup_function(function)
+                        .is_some()
+                })
+                .unwrap_or(false)
+        });
+        if !defined {
+            return Ok(None);
+        }
+
+        let sender = PrincipalData::Contract(contract_identifier.clone());
+        let result = conn
+            .with_readonly_clarity_env(sender, LimitedCostTracker::new_free(), |env| {
+                env.execute_contract(contract_identifier, function, args, true)
+            })
+            .map_err(|e| Error::ClarityError(clarity_error::Interpreter(e)))?;
+
+        match result {
+            Value::Response(ref data) if data.committed => match *data.data {
+                Value::UInt(amount) => Ok(Some(amount)),
+                _ => Err(Error::ClarityError(clarity_error::BadTransaction(format!(
+                    "{}::{} returned {}, expected (ok uint)",
+                    contract_identifier, function, &result
+                )))),
+            },
+            _ => Err(Error::ClarityError(clarity_error::BadTransaction(format!(
+                "{}::{} returned {}, expected (ok uint)",
+                contract_identifier, function, &result
+            )))),
+        }
+    }
+
+    /// Ask the token contract for each holder's balance, plus its total supply.  Holders with a
+    /// zero balance are left out.  Fails if the contract has no `get-balance` function.
+    pub fn get_token_balances<C: ClarityConnection>(
+        conn: &mut C,
+        contract_identifier: &QualifiedContractIdentifier,
+        holders: &[PrincipalData],
+    ) -> Result<(Vec<TokenHolderBalance>, Option<u128>), Error> {
+        let mut balances = vec![];
+        for holder in holders.iter() {
+            let args = [SymbolicExpression::atom_value(Value::Principal(
+                holder.clone(),
+            ))];
+            let balance = StacksChainState::call_token_getter(
+                conn,
+                contract_identifier,
+                "get-balance",
+                &args,
+            )?
+            .ok_or_else(|| {
+                Error::ClarityError(clarity_error::BadTransaction(format!(
+                    "{} does not define get-balance",
+                    contract_identifier
+                )))
+            })?;
+            if balance > 0 {
+                balances.push(TokenHolderBalance {
+                    holder: holder.clone(),
+                    balance,
+                });
+            }
+        }
+        balances.sort_by(|a, b| a.holder.to_string().cmp(&b.holder.to_string()));
+
+        let total_supply = StacksChainState::call_token_getter(
+            conn,
+            contract_identifier,
+            "get-total-supply",
+            &[],
+        )?;
+        Ok((balances, total_supply))
+    }
+
+    /// Snapshot the token's holder balances as of the block at `block_height` on the canonical
+    /// Stacks fork.
+    pub fn snapshot_token_balances(
+        &mut self,
+        sortdb: &SortitionDB,
+        contract_identifier: &QualifiedContractIdentifier,
+        block_height: u64,
+    ) -> Result<TokenSnapshot, Error> {
+        let sn = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        if block_height > sn.canonical_stacks_tip_height {
+            return Err(Error::NoSuchBlockError);
+        }
+        let tip = StacksBlockHeader::make_index_block_hash(
+            &sn.canonical_stacks_tip_consensus_hash,
+            &sn.canonical_stacks_tip_hash,
+        );
+        let index_block_hash = get_ancestor_block_hash(&self.state_index, block_height, &tip)
+            .map_err(Error::DBError)?
+            .ok_or(Error::NoSuchBlockError)?;
+
+        let holders = StacksChainState::get_token_holders(self.db(), contract_identifier)?;
+        let (balances, total_supply) = self
+            .maybe_read_only_clarity_tx(&sortdb.index_conn(), &index_block_hash, |conn| {
+                StacksChainState::get_token_balances(conn, contract_identifier, &holders)
+            })
+            .ok_or(Error::DBError(db_error::NotFoundError))??;
+
+        let total = balances.iter().map(|b| b.balance).sum();
+        Ok(TokenSnapshot {
+            contract_identifier: contract_identifier.clone(),
+            index_block_hash,
+            block_height,
+            balances,
+            total,
+            total_supply,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::burn::ConsensusHash;
+    use chainstate::stacks::db::test::*;
+    use chainstate::stacks::*;
+    use core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
+    use vm::database::NULL_BURN_STATE_DB;
+    use vm::representations::ContractName;
+    use vm::types::StandardPrincipalData;
+
+    #[test]
+    fn test_token_snapshot() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "token-snapshot");
+
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let addr = auth.origin().address_testnet();
+
+        let alice = PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let bob = PrincipalData::parse("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR").unwrap();
+
+        // the deployer is credited with some tokens, but gives them all away
+        let contract = format!(
+            "
+        (define-fungible-token tok)
+        (define-read-only (get-balance (who principal)) (ok (ft-get-balance tok who)))
+        (define-data-var supply uint u160)
+        (define-read-only (get-total-supply) (ok (var-get supply)))
+        (begin
+          (unwrap-panic (ft-mint? tok u100 '{}))
+          (unwrap-panic (ft-mint? tok u50 '{}))
+          (unwrap-panic (ft-mint? tok u10 tx-sender))
+          (unwrap-panic (ft-transfer? tok u10 tx-sender '{})))",
+            &alice, &bob, &bob
+        );
+
+        let mut tx_contract = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::new_smart_contract(&"token".to_string(), &contract).unwrap(),
+        );
+        tx_contract.chain_id = 0x80000000;
+        tx_contract.set_fee_rate(0);
+        tx_contract.post_condition_mode = TransactionPostConditionMode::Allow;
+
+        let mut signer = StacksTransactionSigner::new(&tx_contract);
+        signer.sign_origin(&privk).unwrap();
+        let signed_tx = signer.get_tx().unwrap();
+
+        let contract_id = QualifiedContractIdentifier::new(
+            StandardPrincipalData::from(addr.clone()),
+            ContractName::from("token"),
+        );
+
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+        let (_, receipt) =
+            StacksChainState::process_transaction(&mut conn, &signed_tx, false).unwrap();
+
+        let holders = vec![alice.clone(), bob.clone(), addr.to_account_principal()];
+        let (balances, total_supply) =
+            StacksChainState::get_token_balances(&mut conn, &contract_id, &holders).unwrap();
+
+        // a contract that is not a token can't be snapshotted
+        let not_a_token = QualifiedContractIdentifier::transient();
+        assert!(StacksChainState::get_token_balances(&mut conn, &not_a_token, &holders).is_err());
+
+        conn.commit_block();
+
+        {
+            let (chainstate_tx, _) = chainstate.chainstate_tx_begin().unwrap();
+            chainstate_tx.log_asset_holdings(&[receipt]);
+            chainstate_tx.commit().unwrap();
+        }
+
+        let mut known_holders =
+            StacksChainState::get_token_holders(chainstate.db(), &contract_id).unwrap();
+        known_holders.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+        let mut expected_holders = holders.clone();
+        expected_holders.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+        assert_eq!(known_holders, expected_holders);
+
+        // the deployer's zero balance is left out
+        assert_eq!(balances.len(), 2);
+        assert!(balances.contains(&TokenHolderBalance {
+            holder: alice.clone(),
+            balance: 100
+        }));
+        assert!(balances.contains(&TokenHolderBalance {
+            holder: bob.clone(),
+            balance: 60
+        }));
+        assert_eq!(total_supply, Some(160));
+
+        let snapshot = TokenSnapshot {
+            contract_identifier: contract_id.clone(),
+            index_block_hash: StacksBlockId([0x01; 32]),
+            block_height: 1,
+            total: balances.iter().map(|b| b.balance).sum(),
+            balances: balances.clone(),
+            total_supply,
+        };
+        assert_eq!(snapshot.total, 160);
+
+        let mut csv = vec![];
+        snapshot.write_csv(&mut csv).unwrap();
+        let mut expected_csv = "holder,balance\n".to_string();
+        for entry in balances.iter() {
+            expected_csv.push_str(&format!("{},{}\n", &entry.holder, entry.balance));
+        }
+        assert_eq!(String::from_utf8(csv).unwrap(), expected_csv);
+
+        let leaves = balances.iter().map(|b| b.merkle_leaf()).collect();
+        assert_eq!(
+            snapshot.merkle_root(),
+            MerkleTree::<Sha512Trunc256Sum>::new(&leaves).root()
+        );
+        assert_eq!(
+            Value::try_deserialize_bytes_untyped(&balances[0].merkle_leaf()).unwrap(),
+            Value::Tuple(
+                TupleData::from_data(vec![
+                    ("balance".into(), Value::UInt(balances[0].balance)),
+                    (
+                        "holder".into(),
+                        Value::Principal(balances[0].holder.clone())
+                    ),
+                ])
+                .unwrap()
+            )
+        );
+
+        // a different balance gives a different root
+        let mut changed = snapshot.clone();
+        changed.balances[0].balance += 1;
+        assert!(changed.merkle_root() != snapshot.merkle_root());
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deterministic chainstate builders for tests.
+//!
+//! These helpers instantiate a fresh chainstate and grow it block by block without a burnchain,
+//! sortition DB, or block processing pipeline: each call to `advance_tip` (or `advance_fork_tip`)
+//! writes a header and its miner payment schedule directly, deriving the new block's hashes from
+//! its parent so that the same sequence of calls always produces the same chain.
+//!
+//! They are compiled into the crate's own unit tests, and into the library when the `testing`
+//! feature is enabled so that integration tests and tooling outside the crate can use them:
+//!
+//! ```ignore
+//! let mut chainstate = instantiate_chainstate(false, 0x80000000, "my-test");
+//! let genesis = StacksHeaderInfo::regtest_genesis(0);
+//! let mut reward = make_dummy_miner_payment_schedule(&miner, 500, 0, 0, 1000, 1000);
+//! let tip_a = advance_tip(&mut chainstate, &genesis, &mut reward, &mut vec![]);
+//! // a sibling of tip_a
+//! let tip_b = advance_fork_tip(&mut chainstate, &genesis, 1, &mut reward, &mut vec![]);
+//! ```
+//!
+//! Note that outside of the crate's unit tests, `MINER_REWARD_MATURITY` keeps its mainnet value.
+
+use std::fs;
+
+use burnchains::BurnchainHeaderHash;
+use chainstate::burn::ConsensusHash;
+use chainstate::stacks::db::MinerPaymentSchedule;
+use chainstate::stacks::db::blocks::StagingUserBurnSupport;
+use chainstate::stacks::db::{ChainStateBootData, StacksChainState, StacksHeaderInfo};
+use chainstate::stacks::StacksAddress;
+use core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
+use util::hash::{Hash160, Sha512Trunc256Sum};
+use vm::costs::ExecutionCost;
+use vm::types::PrincipalData;
+
+/// Path of the chainstate a test named `test_name` uses.
+pub fn chainstate_path(test_name: &str) -> String {
+    format!("/tmp/blockstack-test-chainstate-{}", test_name)
+}
+
+/// Instantiate a fresh chainstate for `test_name`, removing any left over from a previous run.
+pub fn instantiate_chainstate(mainnet: bool, chain_id: u32, test_name: &str) -> StacksChainState {
+    instantiate_chainstate_with_balances(mainnet, chain_id, test_name, vec![])
+}
+
+/// Instantiate a fresh chainstate for `test_name` whose genesis block credits `balances`.
+pub fn instantiate_chainstate_with_balances(
+    mainnet: bool,
+    chain_id: u32,
+    test_name: &str,
+    balances: Vec<(StacksAddress, u64)>,
+) -> StacksChainState {
+    let path = chainstate_path(test_name);
+    match fs::metadata(&path) {
+        Ok(_) => {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        Err(_) => {}
+    };
+
+    let initial_balances = balances
+        .into_iter()
+        .map(|(addr, balance)| (PrincipalData::from(addr), balance))
+        .collect();
+
+    let mut boot_data = ChainStateBootData {
+        initial_balances,
+        initial_locks: vec![],
+        post_flight_callback: None,
+        first_burnchain_block_hash: BurnchainHeaderHash::zero(),
+        first_burnchain_block_height: 0,
+        first_burnchain_block_timestamp: 0,
+        get_bulk_initial_lockups: None,
+        get_bulk_initial_balances: None,
+        genesis_balances_audit: None,
+    };
+
+    StacksChainState::open_and_exec(
+        mainnet,
+        chain_id,
+        &path,
+        Some(&mut boot_data),
+        ExecutionCost::max_value(),
+    )
+    .unwrap()
+    .0
+}
+
+/// Re-open the chainstate a previous call to `instantiate_chainstate` created for `test_name`.
+pub fn open_chainstate(mainnet: bool, chain_id: u32, test_name: &str) -> StacksChainState {
+    let path = chainstate_path(test_name);
+    StacksChainState::open(mainnet, chain_id, &path).unwrap().0
+}
+
+/// A miner's payment schedule for a block on top of the boot block.  `advance_tip` fills in the
+/// block it actually pays out for.
+pub fn make_dummy_miner_payment_schedule(
+    addr: &StacksAddress,
+    coinbase: u128,
+    tx_fees_anchored: u128,
+    tx_fees_streamed: u128,
+    commit_burn: u64,
+    sortition_burn: u64,
+) -> MinerPaymentSchedule {
+    MinerPaymentSchedule {
+        address: addr.clone(),
+        recipient: None,
+        block_hash: FIRST_STACKS_BLOCK_HASH.clone(),
+        consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+        parent_block_hash: FIRST_STACKS_BLOCK_HASH.clone(),
+        parent_consensus_hash: FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+        coinbase,
+        tx_fees_anchored,
+        tx_fees_streamed,
+        stx_burns: 0,
+        burnchain_commit_burn: commit_burn,
+        burnchain_sortition_burn: sortition_burn,
+        fill: 0xffffffffffffffff,
+        miner: true,
+        stacks_block_height: 0,
+        vtxindex: 0,
+    }
+}
+
+/// A user-burn supporter's payment schedule, at position `vtxindex` in the burnchain block.
+pub fn make_dummy_user_payment_schedule(
+    addr: &StacksAddress,
+    coinbase: u128,
+    tx_fees_anchored: u128,
+    tx_fees_streamed: u128,
+    commit_burn: u64,
+    sortition_burn: u64,
+    vtxindex: u32,
+) -> MinerPaymentSchedule {
+    let mut sched = make_dummy_miner_payment_schedule(
+        addr,
+        coinbase,
+        tx_fees_anchored,
+        tx_fees_streamed,
+        commit_burn,
+        sortition_burn,
+    );
+    sched.miner = false;
+    sched.vtxindex = vtxindex;
+    sched
+}
+
+impl StagingUserBurnSupport {
+    pub fn from_miner_payment_schedule(user: &MinerPaymentSchedule) -> StagingUserBurnSupport {
+        StagingUserBurnSupport {
+            consensus_hash: user.consensus_hash.clone(),
+            anchored_block_hash: user.block_hash.clone(),
+            address: user.address.clone(),
+            burn_amount: user.burnchain_commit_burn,
+            vtxindex: user.vtxindex,
+        }
+    }
+}
+
+/// Append `fork_id` to `data`, unless it is the canonical fork (0), so that siblings get
+/// different hashes while fork 0 keeps the hashes it always had.
+fn fork_preimage(data: &[u8], fork_id: u64) -> Vec<u8> {
+    let mut preimage = data.to_vec();
+    if fork_id != 0 {
+        preimage.extend_from_slice(&fork_id.to_be_bytes());
+    }
+    preimage
+}
+
+/// Store a child of `parent_header_info`, paying out `block_reward` and `user_burns` for it.
+/// `block_reward` and `user_burns` are updated to point at the new block.
+pub fn advance_tip(
+    chainstate: &mut StacksChainState,
+    parent_header_info: &StacksHeaderInfo,
+    block_reward: &mut MinerPaymentSchedule,
+    user_burns: &mut Vec<StagingUserBurnSupport>,
+) -> StacksHeaderInfo {
+    advance_fork_tip(chainstate, parent_header_info, 0, block_reward, user_burns)
+}
+
+/// Like `advance_tip`, but children of the same parent built with different `fork_id`s get
+/// different block and consensus hashes, so they can coexist as competing forks.
+pub fn advance_fork_tip(
+    chainstate: &mut StacksChainState,
+    parent_header_info: &StacksHeaderInfo,
+    fork_id: u64,
+    block_reward: &mut MinerPaymentSchedule,
+    user_burns: &mut Vec<StagingUserBurnSupport>,
+) -> StacksHeaderInfo {
+    let mut new_tip = parent_header_info.clone();
+
+    new_tip.anchored_header.parent_block = parent_header_info.anchored_header.block_hash();
+    new_tip.anchored_header.microblock_pubkey_hash = Hash160::from_data(&fork_preimage(
+        &parent_header_info.anchored_header.microblock_pubkey_hash.0,
+        fork_id,
+    ));
+    new_tip.anchored_header.total_work.work =
+        parent_header_info.anchored_header.total_work.work + 1;
+    new_tip.microblock_tail = None;
+    new_tip.block_height = parent_header_info.block_height + 1;
+
+    let burn_digest = Sha512Trunc256Sum::from_data(&fork_preimage(
+        &parent_header_info.consensus_hash.0,
+        fork_id,
+    ));
+    new_tip.consensus_hash = ConsensusHash(Hash160::from_data(&burn_digest.0).0);
+    new_tip.burn_header_hash = BurnchainHeaderHash(burn_digest.0);
+    new_tip.burn_header_height = parent_header_info.burn_header_height + 1;
+    new_tip.total_liquid_ustx = parent_header_info.total_liquid_ustx + block_reward.coinbase;
+
+    block_reward.parent_consensus_hash = parent_header_info.consensus_hash.clone();
+    block_reward.parent_block_hash = parent_header_info.anchored_header.block_hash().clone();
+    block_reward.block_hash = new_tip.anchored_header.block_hash();
+    block_reward.consensus_hash = new_tip.consensus_hash.clone();
+
+    for ref mut user_burn in user_burns.iter_mut() {
+        user_burn.anchored_block_hash = new_tip.anchored_header.block_hash();
+        user_burn.consensus_hash = new_tip.consensus_hash.clone();
+    }
+
+    let mut tx = chainstate.index_tx_begin().unwrap();
+    let tip = StacksChainState::advance_tip(
+        &mut tx,
+        &parent_header_info.anchored_header,
+        &parent_header_info.consensus_hash,
+        &new_tip.anchored_header,
+        &new_tip.consensus_hash,
+        &new_tip.burn_header_hash,
+        new_tip.burn_header_height,
+        new_tip.burn_header_timestamp,
+        new_tip.microblock_tail.clone(),
+        &block_reward,
+        &user_burns,
+        new_tip.total_liquid_ustx,
+        &ExecutionCost::zero(),
+        123,
+    )
+    .unwrap();
+    tx.commit().unwrap();
+    tip
+}
@@ -38,6 +38,7 @@ use util::hash::to_hex;
 use chainstate::burn::db::sortdb::*;
 
 use net::Error as net_error;
+use net::StacksMessageCodec;
 
 use vm::types::{
     AssetIdentifier, BuffData, PrincipalData, QualifiedContractIdentifier, SequenceData,
@@ -199,6 +200,76 @@ impl StacksTransactionReceipt {
         }
     }
 
+    /// Reconstruct the transaction's asset map -- the assets each principal sent or burned -- from
+    /// the receipt's events.  Mints and locks are not part of an asset map.
+    pub fn asset_map(&self) -> AssetMap {
+        let mut asset_map = AssetMap::new();
+        for event in self.events.iter() {
+            match event {
+                StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(ref data)) => {
+                    asset_map
+                        .add_stx_transfer(&data.sender, data.amount)
+                        .expect("BUG: asset map STX transfer total overflowed");
+                }
+                StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(ref data)) => {
+                    asset_map
+                        .add_stx_burn(&data.sender, data.amount)
+                        .expect("BUG: asset map STX burn total overflowed");
+                }
+                StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(ref data)) => {
+                    asset_map
+                        .add_token_transfer(
+                            &data.sender,
+                            data.asset_identifier.clone(),
+                            data.amount,
+                        )
+                        .expect("BUG: asset map token transfer total overflowed");
+                }
+                StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(ref data)) => {
+                    asset_map.add_asset_transfer(
+                        &data.sender,
+                        data.asset_identifier.clone(),
+                        data.value.clone(),
+                    );
+                }
+                _ => {}
+            }
+        }
+        asset_map
+    }
+
+    /// Render the transaction's asset map as JSON, keyed by principal and then by asset
+    /// identifier.  STX sent and burned are keyed by `stx` and `stx_burned`; fungible token
+    /// amounts are decimal strings, and non-fungible tokens are lists of hex-encoded Clarity
+    /// values.
+    pub fn asset_map_json(&self) -> serde_json::Value {
+        let mut principals = serde_json::Map::new();
+        for (principal, assets) in self.asset_map().to_table().into_iter() {
+            let mut entries = serde_json::Map::new();
+            for (asset_id, entry) in assets.into_iter() {
+                let (key, value) = match entry {
+                    AssetMapEntry::STX(amount) => ("stx".to_string(), json!(amount.to_string())),
+                    AssetMapEntry::Burn(amount) => {
+                        ("stx_burned".to_string(), json!(amount.to_string()))
+                    }
+                    AssetMapEntry::Token(amount) => {
+                        (asset_id.to_string(), json!(amount.to_string()))
+                    }
+                    AssetMapEntry::Asset(values) => {
+                        let values: Vec<_> = values
+                            .iter()
+                            .map(|value| format!("0x{}", value.serialize()))
+                            .collect();
+                        (asset_id.to_string(), json!(values))
+                    }
+                };
+                entries.insert(key, value);
+            }
+            principals.insert(principal.to_string(), serde_json::Value::Object(entries));
+        }
+        serde_json::Value::Object(principals)
+    }
+
     pub fn from_poison_microblock(
         tx: StacksTransaction,
         result: Value,
@@ -361,6 +432,73 @@ impl StacksChainState {
         // valid auth?
         tx.verify().map_err(Error::NetError)?;
 
+        StacksChainState::process_transaction_precheck_network(config, tx)
+    }
+
+    /// Pre-check that a transaction only uses wire-format features that are active in this fork.
+    /// Today, that means STX transfers may only carry a long memo, and coinbases may only name an
+    /// alternate recipient, once the fork has upgraded to Stacks 2.1.  An alternate recipient must
+    /// also be an address on this network.
+    pub fn process_transaction_precheck_activation(
+        mainnet: bool,
+        stacks_2_1_active: bool,
+        tx: &StacksTransaction,
+    ) -> Result<(), Error> {
+        if let TransactionPayload::TokenTransfer(_, _, _, Some(ref long_memo)) = tx.payload {
+            if !stacks_2_1_active {
+                let msg = format!(
+                    "Invalid tx {}: long memos are not active until Stacks 2.1",
+                    tx.txid()
+                );
+                warn!("{}", &msg);
+
+                return Err(Error::InvalidStacksTransaction(msg, false));
+            }
+
+            if long_memo.serialize_to_vec().len() > MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH {
+                let msg = format!(
+                    "Invalid tx {}: long memo is longer than {} bytes",
+                    tx.txid(),
+                    MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH
+                );
+                warn!("{}", &msg);
+
+                return Err(Error::InvalidStacksTransaction(msg, false));
+            }
+        }
+
+        if let TransactionPayload::Coinbase(_, Some(ref recipient)) = tx.payload {
+            if !stacks_2_1_active {
+                let msg = format!(
+                    "Invalid tx {}: coinbase recipients are not active until Stacks 2.1",
+                    tx.txid()
+                );
+                warn!("{}", &msg);
+
+                return Err(Error::InvalidStacksTransaction(msg, false));
+            }
+
+            if !StacksChainState::is_valid_address_version(mainnet, recipient.version()) {
+                let msg = format!(
+                    "Invalid tx {}: coinbase recipient {} is not a {} address",
+                    tx.txid(),
+                    recipient,
+                    if mainnet { "mainnet" } else { "testnet" }
+                );
+                warn!("{}", &msg);
+
+                return Err(Error::InvalidStacksTransaction(msg, false));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pre-check that a transaction is destined for this chain and network, without checking its
+    /// authorization.
+    fn process_transaction_precheck_network(
+        config: &DBConfig,
+        tx: &StacksTransaction,
+    ) -> Result<(), Error> {
         // destined for us?
         if config.chain_id != tx.chain_id {
             let msg = format!(
@@ -790,7 +928,7 @@ impl StacksChainState {
         origin_account: &StacksAccount,
     ) -> Result<StacksTransactionReceipt, Error> {
         match tx.payload {
-            TransactionPayload::TokenTransfer(ref addr, ref amount, ref _memo) => {
+            TransactionPayload::TokenTransfer(ref addr, ref amount, ref memo, ref long_memo) => {
                 // post-conditions are not allowed for this variant, since they're non-sensical.
                 // Their presence in this variant makes the transaction invalid.
                 if tx.post_conditions.len() > 0 {
@@ -807,13 +945,29 @@ impl StacksChainState {
                 }
 
                 let cost_before = clarity_tx.cost_so_far();
-                let (value, _asset_map, events) = clarity_tx
-                    .run_stx_transfer(&origin_account.principal, addr, *amount as u128)
+                let (value, _asset_map, mut events) = clarity_tx
+                    .run_stx_transfer(
+                        &origin_account.principal,
+                        addr,
+                        *amount as u128,
+                        &BuffData {
+                            data: memo.0.to_vec(),
+                        },
+                    )
                     .map_err(Error::ClarityError)?;
 
-                let mut total_cost = clarity_tx.cost_so_far();
-                total_cost
-                    .sub(&cost_before)
+                for event in events.iter_mut() {
+                    if let StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+                        ref mut data,
+                    )) = event
+                    {
+                        data.long_memo = long_memo.clone();
+                    }
+                }
+
+                let total_cost = clarity_tx
+                    .cost_so_far()
+                    .checked_sub(&cost_before)
                     .expect("BUG: total block cost decreased");
 
                 // TODO: cost is not empty, but we need to figure out how to charge for it
@@ -849,9 +1003,9 @@ impl StacksChainState {
                     },
                 );
 
-                let mut total_cost = clarity_tx.cost_so_far();
-                total_cost
-                    .sub(&cost_before)
+                let total_cost = clarity_tx
+                    .cost_so_far()
+                    .checked_sub(&cost_before)
                     .expect("BUG: total block cost decreased");
 
                 let (result, asset_map, events) = match contract_call_resp {
@@ -945,9 +1099,9 @@ impl StacksChainState {
                             }
                             _ => {
                                 // this analysis isn't free -- convert to runtime error
-                                let mut analysis_cost = clarity_tx.cost_so_far();
-                                analysis_cost
-                                    .sub(&cost_before)
+                                let analysis_cost = clarity_tx
+                                    .cost_so_far()
+                                    .checked_sub(&cost_before)
                                     .expect("BUG: total block cost decreased");
 
                                 error!(
@@ -966,11 +1120,6 @@ impl StacksChainState {
                     }
                 };
 
-                let mut analysis_cost = clarity_tx.cost_so_far();
-                analysis_cost
-                    .sub(&cost_before)
-                    .expect("BUG: total block cost decreased");
-
                 // execution -- if this fails due to a runtime error, then the transaction is still
                 // accepted, but the contract does not materialize (but the sender is out their fee).
                 let initialize_resp = clarity_tx.initialize_smart_contract(
@@ -987,9 +1136,9 @@ impl StacksChainState {
                     },
                 );
 
-                let mut total_cost = clarity_tx.cost_so_far();
-                total_cost
-                    .sub(&cost_before)
+                let total_cost = clarity_tx
+                    .cost_so_far()
+                    .checked_sub(&cost_before)
                     .expect("BUG: total block cost decreased");
 
                 let (asset_map, events) = match initialize_resp {
@@ -1052,8 +1201,9 @@ impl StacksChainState {
                     mblock_header_1,
                     mblock_header_2,
                 )?;
-                let mut cost = clarity_tx.cost_so_far();
-                cost.sub(&cost_before)
+                let cost = clarity_tx
+                    .cost_so_far()
+                    .checked_sub(&cost_before)
                     .expect("BUG: running poison microblock tx has negative cost");
 
                 let receipt =
@@ -1061,7 +1211,7 @@ impl StacksChainState {
 
                 Ok(receipt)
             }
-            TransactionPayload::Coinbase(_) => {
+            TransactionPayload::Coinbase(..) => {
                 // no-op; not handled here
                 // NOTE: technically, post-conditions are allowed (even if they're non-sensical).
 
@@ -1080,6 +1230,60 @@ impl StacksChainState {
         debug!("Process transaction {} ({})", tx.txid(), tx.payload.name());
 
         StacksChainState::process_transaction_precheck(&clarity_block.config, tx)?;
+        StacksChainState::process_transaction_checked(clarity_block, tx, quiet)
+    }
+
+    /// Process a transaction whose signatures were already checked, e.g. together with the rest of
+    /// its block's transactions.  `verified` is the result of that check.
+    /// Return the fee and the transaction receipt.
+    pub fn process_transaction_verified(
+        clarity_block: &mut ClarityTx,
+        tx: &StacksTransaction,
+        verified: Result<(), net_error>,
+        quiet: bool,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        debug!("Process transaction {} ({})", tx.txid(), tx.payload.name());
+
+        verified.map_err(Error::NetError)?;
+        StacksChainState::process_transaction_precheck_network(&clarity_block.config, tx)?;
+        StacksChainState::process_transaction_checked(clarity_block, tx, quiet)
+    }
+
+    /// Process a transaction whose signatures may be missing or invalid, such as an unsigned
+    /// transaction that only names its origin.  This is only meant for simulating a transaction's
+    /// effects in a Clarity transaction that will be rolled back; never call it on a block that
+    /// will be committed.  Return the fee and the transaction receipt.
+    pub fn simulate_transaction(
+        clarity_block: &mut ClarityTx,
+        tx: &StacksTransaction,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        debug!("Simulate transaction {} ({})", tx.txid(), tx.payload.name());
+
+        StacksChainState::process_transaction_precheck_network(&clarity_block.config, tx)?;
+        StacksChainState::process_transaction_checked(clarity_block, tx, true)
+    }
+
+    /// Process a transaction that has already passed its pre-checks.
+    fn process_transaction_checked(
+        clarity_block: &mut ClarityTx,
+        tx: &StacksTransaction,
+        quiet: bool,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        let activation_gated = match tx.payload {
+            TransactionPayload::TokenTransfer(_, _, _, Some(_)) => true,
+            TransactionPayload::Coinbase(_, Some(_)) => true,
+            _ => false,
+        };
+        if activation_gated {
+            let mainnet = clarity_block.config.mainnet;
+            let stacks_2_1_active =
+                clarity_block.with_clarity_db_readonly(|db| db.is_stacks_2_1_active());
+            StacksChainState::process_transaction_precheck_activation(
+                mainnet,
+                stacks_2_1_active,
+                tx,
+            )?;
+        }
 
         let mut transaction = clarity_block.connection().start_transaction_processing();
         let (origin_account, payer_account) =
@@ -1163,6 +1367,7 @@ pub mod test {
                 recv_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -1221,7 +1426,12 @@ pub mod test {
         let mut tx_stx_transfer = StacksTransaction::new(
             TransactionVersion::Testnet,
             auth.clone(),
-            TransactionPayload::TokenTransfer(recv_addr.clone(), 100, TokenTransferMemo([0u8; 34])),
+            TransactionPayload::TokenTransfer(
+                recv_addr.clone(),
+                100,
+                TokenTransferMemo([1u8; 34]),
+                None,
+            ),
         );
 
         tx_stx_transfer.chain_id = 0x80000000;
@@ -1239,7 +1449,16 @@ pub mod test {
         assert_eq!(recv_account.stx_balance.amount_unlocked, 0);
         assert_eq!(recv_account.nonce, 0);
 
-        let (fee, _) = StacksChainState::process_transaction(&mut conn, &signed_tx, false).unwrap();
+        let (fee, receipt) =
+            StacksChainState::process_transaction(&mut conn, &signed_tx, false).unwrap();
+
+        // the transfer event carries the transaction's memo
+        match receipt.events.as_slice() {
+            [StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(data))] => {
+                assert_eq!(data.memo.data, vec![1u8; 34]);
+            }
+            _ => panic!("expected exactly one STX transfer event"),
+        }
 
         let account_after = StacksChainState::get_account(&mut conn, &addr.to_account_principal());
         assert_eq!(account_after.nonce, 2);
@@ -1254,6 +1473,228 @@ pub mod test {
         conn.commit_block();
     }
 
+    #[test]
+    fn process_token_transfer_stx_transaction_long_memo() {
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let recv_addr = StacksAddress {
+            version: 1,
+            bytes: Hash160([0xff; 20]),
+        };
+        let long_memo =
+            Value::Tuple(TupleData::from_data(vec![("tag".into(), Value::UInt(42))]).unwrap());
+
+        // long memos are active from the start on testnets
+        let mut chainstate = instantiate_chainstate(
+            false,
+            0x80000000,
+            "process-token-transfer-stx-transaction-long-memo",
+        );
+        let addr = auth.origin().address_testnet();
+
+        let mut tx_stx_transfer = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::TokenTransfer(
+                recv_addr.clone().into(),
+                123,
+                TokenTransferMemo([1u8; 34]),
+                Some(long_memo.clone()),
+            ),
+        );
+        tx_stx_transfer.chain_id = 0x80000000;
+        tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+        tx_stx_transfer.set_fee_rate(0);
+
+        let mut signer = StacksTransactionSigner::new(&tx_stx_transfer);
+        signer.sign_origin(&privk).unwrap();
+        let signed_tx = signer.get_tx().unwrap();
+
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+        conn.connection().as_transaction(|tx| {
+            StacksChainState::account_credit(tx, &addr.to_account_principal(), 123)
+        });
+
+        let (_, receipt) =
+            StacksChainState::process_transaction(&mut conn, &signed_tx, false).unwrap();
+
+        // the transfer event carries both memos
+        match receipt.events.as_slice() {
+            [StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(data))] => {
+                assert_eq!(data.memo.data, vec![1u8; 34]);
+                assert_eq!(data.long_memo, Some(long_memo.clone()));
+            }
+            _ => panic!("expected exactly one STX transfer event"),
+        }
+
+        let recv_account =
+            StacksChainState::get_account(&mut conn, &recv_addr.to_account_principal());
+        assert_eq!(recv_account.stx_balance.amount_unlocked, 123);
+
+        conn.commit_block();
+
+        // mainnet only accepts them once they activate
+        let mut chainstate = instantiate_chainstate(
+            true,
+            0x00000001,
+            "process-token-transfer-stx-transaction-long-memo-mainnet",
+        );
+        let addr = auth.origin().address_mainnet();
+
+        let mut tx_stx_transfer = StacksTransaction::new(
+            TransactionVersion::Mainnet,
+            auth.clone(),
+            TransactionPayload::TokenTransfer(
+                recv_addr.clone().into(),
+                123,
+                TokenTransferMemo([1u8; 34]),
+                Some(long_memo.clone()),
+            ),
+        );
+        tx_stx_transfer.chain_id = 0x00000001;
+        tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+        tx_stx_transfer.set_fee_rate(0);
+
+        let mut signer = StacksTransactionSigner::new(&tx_stx_transfer);
+        signer.sign_origin(&privk).unwrap();
+        let signed_tx = signer.get_tx().unwrap();
+
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+        conn.connection().as_transaction(|tx| {
+            StacksChainState::account_credit(tx, &addr.to_account_principal(), 123)
+        });
+
+        match StacksChainState::process_transaction(&mut conn, &signed_tx, false) {
+            Err(Error::InvalidStacksTransaction(msg, false)) => {
+                assert!(msg.contains("long memos are not active"));
+            }
+            res => panic!("Processed a long memo before activation: {:?}", &res),
+        }
+
+        assert!(
+            StacksChainState::process_transaction_precheck_activation(true, true, &signed_tx)
+                .is_ok()
+        );
+
+        conn.commit_block();
+    }
+
+    #[test]
+    fn process_coinbase_alt_recipient_activation() {
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        for mainnet in [false, true].iter() {
+            let version = if *mainnet {
+                C32_ADDRESS_VERSION_MAINNET_SINGLESIG
+            } else {
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG
+            };
+            let recipient = PrincipalData::Contract(QualifiedContractIdentifier::new(
+                StandardPrincipalData(version, [0xff; 20]),
+                "reward-splitter".into(),
+            ));
+
+            let mut tx_coinbase = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                auth.clone(),
+                TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), Some(recipient.clone())),
+            );
+            tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
+
+            let mut signer = StacksTransactionSigner::new(&tx_coinbase);
+            signer.sign_origin(&privk).unwrap();
+            let signed_tx = signer.get_tx().unwrap();
+
+            assert!(StacksChainState::process_transaction_precheck_activation(
+                *mainnet, true, &signed_tx
+            )
+            .is_ok());
+
+            match StacksChainState::process_transaction_precheck_activation(
+                *mainnet, false, &signed_tx,
+            ) {
+                Err(Error::InvalidStacksTransaction(msg, false)) => {
+                    assert!(msg.contains("coinbase recipients are not active"));
+                }
+                res => panic!(
+                    "Accepted a coinbase recipient before activation: {:?}",
+                    &res
+                ),
+            }
+
+            // the recipient must be an address on this network
+            let wrong_version = if *mainnet {
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG
+            } else {
+                C32_ADDRESS_VERSION_MAINNET_SINGLESIG
+            };
+            for wrong_recipient in [
+                PrincipalData::Standard(StandardPrincipalData(wrong_version, [0xff; 20])),
+                PrincipalData::Contract(QualifiedContractIdentifier::new(
+                    StandardPrincipalData(wrong_version, [0xff; 20]),
+                    "reward-splitter".into(),
+                )),
+            ]
+            .iter()
+            {
+                let mut tx_coinbase = StacksTransaction::new(
+                    TransactionVersion::Testnet,
+                    auth.clone(),
+                    TransactionPayload::Coinbase(
+                        CoinbasePayload([0u8; 32]),
+                        Some(wrong_recipient.clone()),
+                    ),
+                );
+                tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
+                match StacksChainState::process_transaction_precheck_activation(
+                    *mainnet,
+                    true,
+                    &tx_coinbase,
+                ) {
+                    Err(Error::InvalidStacksTransaction(msg, false)) => {
+                        assert!(msg.contains("is not a"));
+                    }
+                    res => panic!(
+                        "Accepted a coinbase recipient from the wrong network: {:?}",
+                        &res
+                    ),
+                }
+            }
+
+            // coinbases without a recipient are always accepted
+            let mut tx_coinbase = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                auth.clone(),
+                TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
+            );
+            tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
+            assert!(StacksChainState::process_transaction_precheck_activation(
+                *mainnet,
+                false,
+                &tx_coinbase
+            )
+            .is_ok());
+        }
+    }
+
     #[test]
     fn process_token_transfer_stx_transaction_invalid() {
         let mut chainstate = instantiate_chainstate(
@@ -1295,6 +1736,7 @@ pub mod test {
                 recv_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -1305,6 +1747,7 @@ pub mod test {
                 sponsor_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -1315,6 +1758,7 @@ pub mod test {
                 sponsor_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -1325,6 +1769,7 @@ pub mod test {
                 sponsor_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -1343,6 +1788,7 @@ pub mod test {
                 recv_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -1355,6 +1801,7 @@ pub mod test {
                 recv_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -1485,6 +1932,7 @@ pub mod test {
                 recv_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -7431,4 +7879,157 @@ pub mod test {
 
         conn.commit_block();
     }
+
+    #[test]
+    fn simulate_unsigned_stx_transaction() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "simulate-unsigned-stx-transaction");
+
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let addr = auth.origin().address_testnet();
+        let recv_addr = StacksAddress {
+            version: 1,
+            bytes: Hash160([0xff; 20]),
+        };
+
+        // never signed
+        let mut tx_stx_transfer = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::TokenTransfer(
+                recv_addr.clone().into(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+                None,
+            ),
+        );
+
+        tx_stx_transfer.chain_id = 0x80000000;
+        tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+        tx_stx_transfer.set_fee_rate(0);
+
+        let mut conn = chainstate.block_begin(
+            &NULL_BURN_STATE_DB,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &ConsensusHash([1u8; 20]),
+            &BlockHeaderHash([1u8; 32]),
+        );
+
+        conn.connection().as_transaction(|tx| {
+            StacksChainState::account_credit(tx, &addr.to_account_principal(), 223)
+        });
+
+        assert!(StacksChainState::process_transaction(&mut conn, &tx_stx_transfer, false).is_err());
+
+        let (fee, receipt) =
+            StacksChainState::simulate_transaction(&mut conn, &tx_stx_transfer).unwrap();
+        assert_eq!(fee, 0);
+        assert_eq!(receipt.result, Value::okay_true());
+
+        let asset_map = receipt.asset_map().to_table();
+        assert_eq!(asset_map.len(), 1);
+        assert_eq!(
+            asset_map[&addr.to_account_principal()][&AssetIdentifier::STX()],
+            AssetMapEntry::STX(123)
+        );
+
+        // a transaction for another network is still rejected
+        tx_stx_transfer.chain_id = 0x80000001;
+        assert!(StacksChainState::simulate_transaction(&mut conn, &tx_stx_transfer).is_err());
+
+        conn.rollback_block();
+    }
+
+    #[test]
+    fn receipt_asset_map_from_events() {
+        let sender = PrincipalData::from(StacksAddress {
+            version: 1,
+            bytes: Hash160([0x01; 20]),
+        });
+        let recipient = PrincipalData::from(StacksAddress {
+            version: 1,
+            bytes: Hash160([0x02; 20]),
+        });
+        let asset_id = AssetIdentifier {
+            contract_identifier: QualifiedContractIdentifier::local("tokens").unwrap(),
+            asset_name: "token".into(),
+        };
+
+        let receipt = StacksTransactionReceipt {
+            transaction: TransactionOrigin::Burn(Txid([0x01; 32])),
+            events: vec![
+                StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+                    STXTransferEventData {
+                        sender: sender.clone(),
+                        recipient: recipient.clone(),
+                        amount: 100,
+                        memo: BuffData::empty(),
+                        long_memo: None,
+                    },
+                )),
+                StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(STXBurnEventData {
+                    sender: sender.clone(),
+                    amount: 10,
+                })),
+                StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(
+                    FTTransferEventData {
+                        asset_identifier: asset_id.clone(),
+                        sender: sender.clone(),
+                        recipient: recipient.clone(),
+                        amount: 1,
+                    },
+                )),
+                StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(
+                    FTTransferEventData {
+                        asset_identifier: asset_id.clone(),
+                        sender: sender.clone(),
+                        recipient: recipient.clone(),
+                        amount: 2,
+                    },
+                )),
+                StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(
+                    NFTTransferEventData {
+                        asset_identifier: asset_id.clone(),
+                        sender: recipient.clone(),
+                        recipient: sender.clone(),
+                        value: Value::Int(1),
+                    },
+                )),
+                // mints aren't part of an asset map
+                StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(FTMintEventData {
+                    asset_identifier: asset_id.clone(),
+                    recipient: recipient.clone(),
+                    amount: 5,
+                })),
+            ],
+            post_condition_aborted: false,
+            result: Value::okay_true(),
+            stx_burned: 10,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::zero(),
+        };
+
+        let asset_map = receipt.asset_map().to_table();
+        assert_eq!(asset_map.len(), 2);
+        assert_eq!(asset_map[&sender].len(), 3);
+        assert_eq!(
+            asset_map[&sender][&AssetIdentifier::STX()],
+            AssetMapEntry::STX(100)
+        );
+        assert_eq!(
+            asset_map[&sender][&AssetIdentifier::STX_burned()],
+            AssetMapEntry::Burn(10)
+        );
+        assert_eq!(asset_map[&sender][&asset_id], AssetMapEntry::Token(3));
+        assert_eq!(asset_map[&recipient].len(), 1);
+        assert_eq!(
+            asset_map[&recipient][&asset_id],
+            AssetMapEntry::Asset(vec![Value::Int(1)])
+        );
+    }
 }
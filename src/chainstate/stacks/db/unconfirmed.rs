@@ -120,6 +120,7 @@ impl UnconfirmedState {
                 burn_dbconn,
                 &self.confirmed_chain_tip,
             );
+            StacksChainState::begin_stacks_2_1_upgrade(&mut clarity_tx);
 
             for mblock in mblocks.into_iter() {
                 if (last_mblock.is_some() && mblock.header.sequence <= last_mblock_seq)
@@ -417,6 +418,7 @@ mod test {
     use chainstate::stacks::db::test::*;
     use chainstate::stacks::db::*;
     use chainstate::stacks::miner::*;
+    use chainstate::stacks::Error as chainstate_error;
     use chainstate::stacks::*;
 
     use net::test::*;
@@ -577,6 +579,7 @@ mod test {
                             recv_addr.clone().into(),
                             1,
                             TokenTransferMemo([0u8; 34]),
+                            None,
                         ),
                     );
 
@@ -807,6 +810,7 @@ mod test {
                                 recv_addr.clone().into(),
                                 1,
                                 TokenTransferMemo([0u8; 34]),
+                                None,
                             ),
                         );
 
@@ -892,4 +896,314 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_unconfirmed_microblock_assembly_limits() {
+        let privk = StacksPrivateKey::new();
+        let addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+
+        let initial_balance = 1000000000;
+        let mut peer_config =
+            TestPeerConfig::new("test_unconfirmed_microblock_assembly_limits", 7006, 7007);
+        peer_config.initial_balances = vec![(addr.to_account_principal(), initial_balance)];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let microblock_privkey = StacksPrivateKey::new();
+        let microblock_pubkeyhash =
+            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+            .unwrap();
+
+        let (burn_ops, stacks_block, _) = peer.make_tenure(
+            |ref mut miner, ref mut sortdb, ref mut chainstate, vrf_proof, _, _| {
+                let parent_tip =
+                    StacksChainState::get_genesis_header_info(chainstate.db()).unwrap();
+                let block_builder = StacksBlockBuilder::make_block_builder(
+                    &parent_tip,
+                    vrf_proof,
+                    tip.total_burn,
+                    microblock_pubkeyhash,
+                )
+                .unwrap();
+
+                let coinbase_tx = make_coinbase(miner, 0);
+                let (anchored_block, _, _) = StacksBlockBuilder::make_anchored_block_from_txs(
+                    block_builder,
+                    chainstate,
+                    &sortdb.index_conn(),
+                    vec![coinbase_tx],
+                )
+                .unwrap();
+                (anchored_block, vec![])
+            },
+        );
+
+        let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops.clone());
+        peer.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
+
+        let canonical_tip =
+            StacksBlockHeader::make_index_block_hash(&consensus_hash, &stacks_block.block_hash());
+
+        let recv_addr =
+            StacksAddress::from_string("ST1H1B54MY50RMBRRKS7GV2ZWG79RZ1RQ1ETW4E01").unwrap();
+        let signed_txs: Vec<_> = (0..10)
+            .map(|nonce| {
+                let auth = TransactionAuth::Standard(
+                    TransactionSpendingCondition::new_singlesig_p2pkh(
+                        StacksPublicKey::from_private(&privk),
+                    )
+                    .unwrap(),
+                );
+                let mut tx_stx_transfer = StacksTransaction::new(
+                    TransactionVersion::Testnet,
+                    auth,
+                    TransactionPayload::TokenTransfer(
+                        recv_addr.clone().into(),
+                        1,
+                        TokenTransferMemo([0u8; 34]),
+                        None,
+                    ),
+                );
+                tx_stx_transfer.chain_id = 0x80000000;
+                tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+                tx_stx_transfer.set_fee_rate(0);
+                tx_stx_transfer.set_origin_nonce(nonce);
+
+                let mut signer = StacksTransactionSigner::new(&tx_stx_transfer);
+                signer.sign_origin(&privk).unwrap();
+                let signed_tx = signer.get_tx().unwrap();
+                let len = signed_tx.serialize_to_vec().len() as u64;
+                (signed_tx, len)
+            })
+            .collect();
+
+        let sortdb = peer.sortdb.take().unwrap();
+        {
+            let sort_iconn = sortdb.index_conn();
+            peer.chainstate()
+                .reload_unconfirmed_state(&sort_iconn, canonical_tip.clone())
+                .unwrap();
+
+            let mut microblock_builder = StacksMicroblockBuilder::new(
+                stacks_block.block_hash(),
+                consensus_hash.clone(),
+                peer.chainstate(),
+                &sort_iconn,
+            )
+            .unwrap();
+
+            // at most 3 transactions per microblock
+            microblock_builder.set_assembly_limits(MicroblockAssemblyLimits {
+                max_txs: Some(3),
+                max_cost_percent: None,
+            });
+            for seq in 0..2 {
+                let microblock = microblock_builder
+                    .mine_next_microblock_from_txs(signed_txs.clone(), &microblock_privkey)
+                    .unwrap();
+                assert_eq!(microblock.header.sequence, seq);
+                assert_eq!(microblock.txs.len(), 3);
+                assert!(microblock_builder.is_full());
+            }
+
+            // a microblock that may not spend anything takes no transactions
+            microblock_builder.set_assembly_limits(MicroblockAssemblyLimits {
+                max_txs: None,
+                max_cost_percent: Some(0),
+            });
+            match microblock_builder
+                .mine_next_microblock_from_txs(signed_txs.clone(), &microblock_privkey)
+            {
+                Err(chainstate_error::NoTransactionsToMine) => {}
+                Err(e) => panic!("Unexpected error: {:?}", &e),
+                Ok(_) => panic!("Mined a microblock past the assembly limits"),
+            }
+
+            // without limits, the remaining transactions all fit
+            microblock_builder.set_assembly_limits(MicroblockAssemblyLimits::default());
+            let microblock = microblock_builder
+                .mine_next_microblock_from_txs(signed_txs.clone(), &microblock_privkey)
+                .unwrap();
+            assert_eq!(microblock.header.sequence, 2);
+            assert_eq!(microblock.txs.len(), 4);
+            assert!(!microblock_builder.is_full());
+        }
+        peer.sortdb = Some(sortdb);
+    }
+
+    #[test]
+    fn test_unconfirmed_mempool_admission() {
+        let privk = StacksPrivateKey::new();
+        let addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+
+        let initial_balance = 1000000000;
+        let mut peer_config = TestPeerConfig::new("test_unconfirmed_mempool_admission", 7004, 7005);
+        peer_config.initial_balances = vec![(addr.to_account_principal(), initial_balance)];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let microblock_privkey = StacksPrivateKey::new();
+        let microblock_pubkeyhash =
+            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+            .unwrap();
+
+        let (burn_ops, stacks_block, _) = peer.make_tenure(
+            |ref mut miner, ref mut sortdb, ref mut chainstate, vrf_proof, _, _| {
+                let parent_tip =
+                    StacksChainState::get_genesis_header_info(chainstate.db()).unwrap();
+                let block_builder = StacksBlockBuilder::make_block_builder(
+                    &parent_tip,
+                    vrf_proof,
+                    tip.total_burn,
+                    microblock_pubkeyhash,
+                )
+                .unwrap();
+
+                let coinbase_tx = make_coinbase(miner, 0);
+                let (anchored_block, _, _) = StacksBlockBuilder::make_anchored_block_from_txs(
+                    block_builder,
+                    chainstate,
+                    &sortdb.index_conn(),
+                    vec![coinbase_tx],
+                )
+                .unwrap();
+                (anchored_block, vec![])
+            },
+        );
+
+        let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops.clone());
+        peer.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
+
+        let canonical_tip =
+            StacksBlockHeader::make_index_block_hash(&consensus_hash, &stacks_block.block_hash());
+
+        // only funded in the microblock stream
+        let recv_privk = StacksPrivateKey::new();
+        let recv_addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&recv_privk)],
+        )
+        .unwrap();
+
+        let make_stx_transfer =
+            |privk: &StacksPrivateKey, recipient: &StacksAddress, amount: u64| {
+                let auth = TransactionAuth::Standard(
+                    TransactionSpendingCondition::new_singlesig_p2pkh(
+                        StacksPublicKey::from_private(privk),
+                    )
+                    .unwrap(),
+                );
+                let mut tx_stx_transfer = StacksTransaction::new(
+                    TransactionVersion::Testnet,
+                    auth,
+                    TransactionPayload::TokenTransfer(
+                        recipient.clone().into(),
+                        amount,
+                        TokenTransferMemo([0u8; 34]),
+                        None,
+                    ),
+                );
+
+                tx_stx_transfer.chain_id = 0x80000000;
+                tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+                tx_stx_transfer.set_fee_rate(1000);
+                tx_stx_transfer.set_origin_nonce(0);
+
+                let mut signer = StacksTransactionSigner::new(&tx_stx_transfer);
+                signer.sign_origin(privk).unwrap();
+
+                let signed_tx = signer.get_tx().unwrap();
+                let signed_tx_len = {
+                    let mut bytes = vec![];
+                    signed_tx.consensus_serialize(&mut bytes).unwrap();
+                    bytes.len() as u64
+                };
+                (signed_tx, signed_tx_len)
+            };
+
+        // fund recv_addr in a microblock
+        let microblock = {
+            let sortdb = peer.sortdb.take().unwrap();
+            let sort_iconn = sortdb.index_conn();
+
+            peer.chainstate()
+                .reload_unconfirmed_state(&sort_iconn, canonical_tip.clone())
+                .unwrap();
+
+            let microblock = {
+                let mut microblock_builder = StacksMicroblockBuilder::new(
+                    stacks_block.block_hash(),
+                    consensus_hash.clone(),
+                    peer.chainstate(),
+                    &sort_iconn,
+                )
+                .unwrap();
+
+                microblock_builder
+                    .mine_next_microblock_from_txs(
+                        vec![make_stx_transfer(&privk, &recv_addr, 1000000)],
+                        &microblock_privkey,
+                    )
+                    .unwrap()
+            };
+
+            peer.sortdb = Some(sortdb);
+            microblock
+        };
+
+        peer.chainstate()
+            .preprocess_streamed_microblock(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+                &microblock,
+            )
+            .unwrap();
+
+        let sortdb = peer.sortdb.take().unwrap();
+        peer.chainstate()
+            .reload_unconfirmed_state(&sortdb.index_conn(), canonical_tip.clone())
+            .unwrap();
+        peer.sortdb = Some(sortdb);
+
+        // recv_addr can only pay for this in the unconfirmed state
+        let (next_tx, next_tx_len) = make_stx_transfer(&recv_privk, &addr, 1);
+        match peer.chainstate().will_admit_mempool_tx(
+            &consensus_hash,
+            &stacks_block.block_hash(),
+            &next_tx,
+            next_tx_len,
+            false,
+        ) {
+            Err(MemPoolRejection::NotEnoughFunds(..)) => {}
+            res => panic!("Expected NotEnoughFunds, got {:?}", &res),
+        }
+
+        peer.chainstate()
+            .will_admit_mempool_tx(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+                &next_tx,
+                next_tx_len,
+                true,
+            )
+            .unwrap();
+    }
 }
@@ -16,12 +16,15 @@
 
 use super::StacksAddress;
 use burnchains::Txid;
-use chainstate::stacks::StacksTransaction;
+use chainstate::burn::{BlockHeaderHash, ConsensusHash};
+use chainstate::stacks::{StacksBlockId, StacksTransaction};
 use net::StacksMessageCodec;
+use util::hash::{to_hex, Sha512Trunc256Sum};
 use vm::analysis::ContractAnalysis;
 use vm::costs::ExecutionCost;
 use vm::types::{
-    AssetIdentifier, PrincipalData, QualifiedContractIdentifier, StandardPrincipalData, Value,
+    AssetIdentifier, BuffData, PrincipalData, QualifiedContractIdentifier, StandardPrincipalData,
+    Value,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -113,6 +116,22 @@ impl StacksTransactionEvent {
                 "type": "stx_lock_event",
                 "stx_lock_event": event_data.json_serialize()
             }),
+            StacksTransactionEvent::STXEvent(STXEventType::STXUnlockEvent(event_data)) => json!({
+                "txid": format!("0x{:?}", txid),
+                "event_index": event_index,
+                "committed": committed,
+                "type": "stx_unlock_event",
+                "stx_unlock_event": event_data.json_serialize()
+            }),
+            StacksTransactionEvent::STXEvent(STXEventType::STXMinerRewardEvent(event_data)) => {
+                json!({
+                    "txid": format!("0x{:?}", txid),
+                    "event_index": event_index,
+                    "committed": committed,
+                    "type": "stx_miner_reward_event",
+                    "stx_miner_reward_event": event_data.json_serialize()
+                })
+            }
             StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(event_data)) => json!({
                 "txid": format!("0x{:?}", txid),
                 "event_index": event_index,
@@ -151,6 +170,8 @@ pub enum STXEventType {
     STXMintEvent(STXMintEventData),
     STXBurnEvent(STXBurnEventData),
     STXLockEvent(STXLockEventData),
+    STXUnlockEvent(STXUnlockEventData),
+    STXMinerRewardEvent(STXMinerRewardEventData),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -170,15 +191,24 @@ pub struct STXTransferEventData {
     pub sender: PrincipalData,
     pub recipient: PrincipalData,
     pub amount: u128,
+    pub memo: BuffData,
+    /// The long memo of the STX transfer transaction that made this transfer, if it had one
+    #[serde(default)]
+    pub long_memo: Option<Value>,
 }
 
 impl STXTransferEventData {
     pub fn json_serialize(&self) -> serde_json::Value {
-        json!({
+        let mut json = json!({
             "sender": format!("{}",self.sender),
             "recipient": format!("{}",self.recipient),
             "amount": format!("{}", self.amount),
-        })
+            "memo": format!("{}", self.memo),
+        });
+        if let Some(ref long_memo) = self.long_memo {
+            json["long_memo"] = json!(format!("0x{}", to_hex(&long_memo.serialize_to_vec())));
+        }
+        json
     }
 }
 
@@ -214,6 +244,54 @@ impl STXLockEventData {
     }
 }
 
+/// Expired locks released into an account's unlocked balance.  Recorded with the coinbase
+/// transaction of the block that released them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct STXUnlockEventData {
+    pub unlocked_amount: u128,
+    pub unlocked_address: PrincipalData,
+}
+
+impl STXUnlockEventData {
+    pub fn json_serialize(&self) -> serde_json::Value {
+        json!({
+            "unlocked_amount": format!("{}", self.unlocked_amount),
+            "unlocked_address": format!("{}", self.unlocked_address),
+        })
+    }
+}
+
+/// A matured miner reward credited to its recipient.  Recorded with the coinbase transaction of
+/// the block that credited it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct STXMinerRewardEventData {
+    pub recipient: PrincipalData,
+    pub coinbase: u128,
+    pub tx_fees_anchored: u128,
+    pub tx_fees_streamed_produced: u128,
+    pub tx_fees_streamed_confirmed: u128,
+    /// whether the recipient is a burn address
+    pub burned: bool,
+    /// the block whose mining earned the reward
+    pub from_block_consensus_hash: ConsensusHash,
+    pub from_stacks_block_hash: BlockHeaderHash,
+}
+
+impl STXMinerRewardEventData {
+    pub fn json_serialize(&self) -> serde_json::Value {
+        json!({
+            "recipient": format!("{}", self.recipient),
+            "coinbase_amount": format!("{}", self.coinbase),
+            "tx_fees_anchored": format!("{}", self.tx_fees_anchored),
+            "tx_fees_streamed_produced": format!("{}", self.tx_fees_streamed_produced),
+            "tx_fees_streamed_confirmed": format!("{}", self.tx_fees_streamed_confirmed),
+            "burned": self.burned,
+            "from_stacks_block_hash": format!("0x{}", self.from_stacks_block_hash),
+            "from_index_block_hash": format!("0x{}", StacksBlockId::new(&self.from_block_consensus_hash, &self.from_stacks_block_hash)),
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct STXBurnEventData {
     pub sender: PrincipalData,
@@ -337,3 +415,147 @@ impl SmartContractEventData {
         })
     }
 }
+
+/// Number of bytes in a block's contract event bloom filter (2048 bits)
+pub const EVENT_BLOOM_LEN: usize = 256;
+
+/// Number of bits set in a block's contract event bloom filter per inserted item
+pub const EVENT_BLOOM_NUM_HASHES: usize = 3;
+
+/// A bloom filter over the contract events emitted by a block's transactions.  For each contract
+/// event, both the emitting contract's identifier and the (contract identifier, topic) pair are
+/// inserted.  An item's bits are taken from the first EVENT_BLOOM_NUM_HASHES big-endian 16-bit
+/// words of its SHA512/256 hash, each modulo the filter's bit length.  A contract's item is its
+/// identifier as a string; a (contract, topic) item is the identifier, a 0x00 byte, then the topic.
+pub struct EventBloom(pub [u8; 256]);
+impl_array_newtype!(EventBloom, u8, 256);
+impl_array_hexstring_fmt!(EventBloom);
+impl_byte_array_newtype!(EventBloom, u8, 256);
+impl_byte_array_from_column!(EventBloom);
+
+impl EventBloom {
+    pub fn empty() -> EventBloom {
+        EventBloom([0u8; EVENT_BLOOM_LEN])
+    }
+
+    /// Build the bloom filter over all the contract events in a block's transaction receipts.
+    /// Events from transactions that were aborted by a post-condition are included, since they
+    /// are still reported to event observers.
+    pub fn from_receipts(receipts: &[StacksTransactionReceipt]) -> EventBloom {
+        let mut bloom = EventBloom::empty();
+        for receipt in receipts.iter() {
+            for event in receipt.events.iter() {
+                if let StacksTransactionEvent::SmartContractEvent(ref event_data) = event {
+                    bloom.add_contract_event(&event_data.key.0, &event_data.key.1);
+                }
+            }
+        }
+        bloom
+    }
+
+    fn contract_item(contract_id: &QualifiedContractIdentifier) -> Vec<u8> {
+        contract_id.to_string().into_bytes()
+    }
+
+    fn contract_topic_item(contract_id: &QualifiedContractIdentifier, topic: &str) -> Vec<u8> {
+        let mut item = EventBloom::contract_item(contract_id);
+        item.push(0);
+        item.extend_from_slice(topic.as_bytes());
+        item
+    }
+
+    fn bit_indexes(item: &[u8]) -> [usize; EVENT_BLOOM_NUM_HASHES] {
+        let hash = Sha512Trunc256Sum::from_data(item);
+        let mut indexes = [0usize; EVENT_BLOOM_NUM_HASHES];
+        for (i, index) in indexes.iter_mut().enumerate() {
+            let word = ((hash.0[2 * i] as usize) << 8) | (hash.0[2 * i + 1] as usize);
+            *index = word % (EVENT_BLOOM_LEN * 8);
+        }
+        indexes
+    }
+
+    fn insert(&mut self, item: &[u8]) {
+        for index in EventBloom::bit_indexes(item).iter() {
+            self.0[index / 8] |= 1 << (index % 8);
+        }
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        EventBloom::bit_indexes(item)
+            .iter()
+            .all(|index| self.0[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    pub fn add_contract_event(&mut self, contract_id: &QualifiedContractIdentifier, topic: &str) {
+        self.insert(&EventBloom::contract_item(contract_id));
+        self.insert(&EventBloom::contract_topic_item(contract_id, topic));
+    }
+
+    /// Can the block have emitted any events from this contract?  False positives are possible;
+    /// false negatives are not.
+    pub fn may_contain_contract(&self, contract_id: &QualifiedContractIdentifier) -> bool {
+        self.contains(&EventBloom::contract_item(contract_id))
+    }
+
+    /// Can the block have emitted any events with this topic from this contract?  False
+    /// positives are possible; false negatives are not.
+    pub fn may_contain_contract_topic(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+        topic: &str,
+    ) -> bool {
+        self.contains(&EventBloom::contract_topic_item(contract_id, topic))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_event_bloom() {
+        let contract_1 =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.foo")
+                .unwrap();
+        let contract_2 =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.bar")
+                .unwrap();
+
+        let bloom = EventBloom::empty();
+        assert!(!bloom.may_contain_contract(&contract_1));
+        assert!(!bloom.may_contain_contract_topic(&contract_1, "print"));
+
+        let mut bloom = EventBloom::empty();
+        bloom.add_contract_event(&contract_1, "print");
+        assert!(bloom.may_contain_contract(&contract_1));
+        assert!(bloom.may_contain_contract_topic(&contract_1, "print"));
+        assert!(!bloom.may_contain_contract(&contract_2));
+        assert!(!bloom.may_contain_contract_topic(&contract_2, "print"));
+
+        // at most 2 items, so at most 6 bits
+        let num_set: u32 = bloom.0.iter().map(|b| b.count_ones()).sum();
+        assert!(num_set > 0 && num_set <= 6);
+
+        // round-trips through hex
+        assert_eq!(EventBloom::from_hex(&bloom.to_hex()).unwrap(), bloom);
+
+        let receipt = StacksTransactionReceipt {
+            transaction: TransactionOrigin::Burn(Txid([0x01; 32])),
+            events: vec![StacksTransactionEvent::SmartContractEvent(
+                SmartContractEventData {
+                    key: (contract_2.clone(), "print".to_string()),
+                    value: Value::Int(1),
+                },
+            )],
+            post_condition_aborted: true,
+            result: Value::okay_true(),
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::zero(),
+        };
+        let bloom = EventBloom::from_receipts(&[receipt]);
+        assert!(bloom.may_contain_contract(&contract_2));
+        assert!(bloom.may_contain_contract_topic(&contract_2, "print"));
+        assert!(!bloom.may_contain_contract(&contract_1));
+    }
+}
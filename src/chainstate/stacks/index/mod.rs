@@ -569,6 +569,11 @@ mod test {
 
         assert!(proof.verify(&triepath, &marf_value, &root_hash, &root_to_block));
 
+        // same proof, checked by key and value after a round-trip through hex
+        let decoded_proof = TrieMerkleProof::from_hex(&proof.to_hex()).unwrap();
+        assert!(decoded_proof.verify_entry(key, value, &root_hash, &root_to_block));
+        assert!(!decoded_proof.verify_entry(key, "not the value", &root_hash, &root_to_block));
+
         root_to_block
     }
 
@@ -55,7 +55,10 @@ use chainstate::stacks::index::trie::Trie;
 use chainstate::stacks::index::Error;
 
 use net::{codec::read_next, StacksMessageCodec};
-use util::{hash::to_hex, log};
+use util::{
+    hash::{hex_bytes, to_hex},
+    log,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ProofTriePtr<T: MarfTrieId> {
@@ -399,6 +402,14 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         to_hex(&marf_proof)
     }
 
+    /// Decode a proof encoded by `to_hex()`
+    pub fn from_hex(hex: &str) -> Result<TrieMerkleProof<T>, ::net::Error> {
+        let bytes = hex_bytes(hex)
+            .map_err(|_| ::net::Error::DeserializeError("Invalid hex string".to_string()))?;
+        let proof = read_next(&mut &bytes[..])?;
+        Ok(TrieMerkleProof(proof))
+    }
+
     fn make_proof_hashes(
         node: &TrieNodeType,
         all_hashes: &Vec<TrieHash>,
@@ -1378,6 +1389,25 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         TrieMerkleProof::<T>::verify_proof(&self.0, &path, &marf_value, root_hash, root_to_block)
     }
 
+    /// Verify that this proof shows that `key` maps to `value` in the MARF whose root hash is
+    /// `root_hash`.  `value` is the string stored for the key in the MARF's side store -- i.e. the
+    /// hex-encoded serialization, for Clarity data.  `root_to_block` maps the root hashes of the
+    /// ancestor tries the proof passes through to their blocks (see `verify_proof()`).
+    pub fn verify_entry(
+        &self,
+        key: &str,
+        value: &str,
+        root_hash: &TrieHash,
+        root_to_block: &HashMap<TrieHash, T>,
+    ) -> bool {
+        self.verify(
+            &TriePath::from_key(key),
+            &MARFValue::from_value(value),
+            root_hash,
+            root_to_block,
+        )
+    }
+
     /// Walk down the trie pointed to by s until we reach a backptr or a leaf
     fn walk_to_leaf_or_backptr(
         storage: &mut TrieStorageConnection<T>,
@@ -1596,6 +1626,115 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
     }
 }
 
+/// Version of the `MarfProof` encoding produced by this node
+pub const MARF_PROOF_VERSION: u8 = 1;
+
+/// A MARF Merkle proof, in the form handed to light clients.
+///
+/// Version 1 `proof` bytes are the consensus serialization of a `TrieMerkleProof`: a 4-byte
+/// big-endian count of proof entries, then each entry as a type byte (0-3: node4/16/48/256
+/// segment entry, 4: leaf, 5: shunt) followed by its fields.  Encoded as JSON, the proof bytes
+/// are a hex string.  The version is bumped whenever this layout changes; verifiers must reject
+/// versions they do not know.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarfProof {
+    pub version: u8,
+    #[serde(
+        serialize_with = "marf_proof_bytes_serialize",
+        deserialize_with = "marf_proof_bytes_deserialize"
+    )]
+    pub proof: Vec<u8>,
+}
+
+fn marf_proof_bytes_serialize<S: serde::Serializer>(
+    bytes: &Vec<u8>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&to_hex(bytes))
+}
+
+fn marf_proof_bytes_deserialize<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> Result<Vec<u8>, D::Error> {
+    let hex_str: String = serde::Deserialize::deserialize(d)?;
+    let hex_str = hex_str.trim_start_matches("0x");
+    hex_bytes(hex_str).map_err(serde::de::Error::custom)
+}
+
+impl MarfProof {
+    pub fn from_trie_proof<T: MarfTrieId>(proof: &TrieMerkleProof<T>) -> MarfProof {
+        let mut bytes = vec![];
+        proof
+            .consensus_serialize(&mut bytes)
+            .expect("Write error on memory buffer");
+        MarfProof {
+            version: MARF_PROOF_VERSION,
+            proof: bytes,
+        }
+    }
+
+    /// Decode the proof.  Fails if the version is not supported or the bytes are malformed.
+    pub fn to_trie_proof<T: MarfTrieId>(&self) -> Result<TrieMerkleProof<T>, Error> {
+        if self.version != MARF_PROOF_VERSION {
+            return Err(Error::CorruptionError(format!(
+                "Unsupported MARF proof version {}",
+                self.version
+            )));
+        }
+        let mut cursor = &self.proof[..];
+        let proof: Vec<TrieMerkleProofType<T>> = read_next(&mut cursor)
+            .map_err(|e| Error::CorruptionError(format!("Malformed MARF proof: {:?}", &e)))?;
+        if cursor.len() > 0 {
+            return Err(Error::CorruptionError(
+                "Malformed MARF proof: trailing bytes".to_string(),
+            ));
+        }
+        Ok(TrieMerkleProof(proof))
+    }
+
+    /// Binary encoding: the version byte, followed by the proof bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.proof.len() + 1);
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.proof);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<MarfProof, Error> {
+        match bytes.split_first() {
+            Some((version, proof)) => Ok(MarfProof {
+                version: *version,
+                proof: proof.to_vec(),
+            }),
+            None => Err(Error::CorruptionError("Empty MARF proof".to_string())),
+        }
+    }
+}
+
+/// Verify that `proof` shows that `key` maps to `value` in the MARF whose root hash is
+/// `root_hash` -- e.g. the `state_index_root` of a Stacks block header, for the Clarity state as
+/// of that block.  `value` is the string stored for the key, i.e. the hex serialization of a
+/// Clarity value.
+///
+/// A key last written before the block is proven through the tries of the intervening ancestor
+/// blocks.  `root_to_block` must then map the trie root hashes of the block and its ancestors to
+/// their block IDs (for Stacks blocks, each header's `state_index_root` to its index block hash),
+/// and must come from headers the caller trusts: the proof does not commit to it.  It may be
+/// empty for keys written in the block itself.
+///
+/// Returns Ok(false) if the proof is well-formed but does not verify, and an error if it cannot
+/// be decoded.
+pub fn verify_marf_proof<T: MarfTrieId>(
+    root_hash: &TrieHash,
+    key: &str,
+    value: &str,
+    proof: &MarfProof,
+    root_to_block: &HashMap<TrieHash, T>,
+) -> Result<bool, Error> {
+    let proof = proof.to_trie_proof::<T>()?;
+    Ok(proof.verify_entry(key, value, root_hash, root_to_block))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1603,6 +1742,9 @@ mod test {
     use chainstate::stacks::index::test::*;
     use chainstate::stacks::index::*;
 
+    use chainstate::stacks::StacksBlockId;
+    use serde_json;
+
     #[test]
     fn verifier_catches_stale_proof() {
         use std::env;
@@ -1774,4 +1916,208 @@ mod test {
         println!("DEBUG: verify(old_v)");
         assert!(!proof_5.verify(&triepath_4, &marf_value_4, &root_hash_5, &root_to_block));
     }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct MarfProofVector {
+        description: String,
+        root_hash: TrieHash,
+        key: String,
+        value: String,
+        root_to_block: HashMap<String, StacksBlockId>,
+        proof: MarfProof,
+        valid: bool,
+    }
+
+    /// Build a small MARF and the proofs for the published test vectors from it:
+    /// a key written in the tip block, and a key written in an ancestor block.
+    fn make_marf_proof_vectors() -> Vec<MarfProofVector> {
+        let mut m = MARF::<StacksBlockId>::from_path(":memory:").unwrap();
+
+        let block_0 = StacksBlockId([0u8; 32]);
+        let block_1 = StacksBlockId([1u8; 32]);
+        let block_2 = StacksBlockId([2u8; 32]);
+
+        m.begin(&StacksBlockId::sentinel(), &block_0).unwrap();
+        m.commit().unwrap();
+
+        m.begin(&block_0, &block_1).unwrap();
+        m.insert(
+            "vm::ST000000000000000000002AMW42H.test::1::counter",
+            MARFValue::from_value("0100000000000000000000000000000001"),
+        )
+        .unwrap();
+        m.commit().unwrap();
+
+        m.begin(&block_1, &block_2).unwrap();
+        m.insert(
+            "vm::ST000000000000000000002AMW42H.test::1::greeting",
+            MARFValue::from_value("0d0000000568656c6c6f"),
+        )
+        .unwrap();
+        m.commit().unwrap();
+
+        let root_hash_2 = m.get_root_hash_at(&block_2).unwrap();
+
+        // the blocks' roots, as a light client would take them from trusted block headers
+        let root_to_block: HashMap<String, StacksBlockId> = m
+            .borrow_storage_backend()
+            .read_root_to_block_table()
+            .unwrap()
+            .into_iter()
+            .filter(|(_, block_id)| *block_id != StacksBlockId::sentinel())
+            .map(|(root_hash, block_id)| (root_hash.to_hex(), block_id))
+            .collect();
+
+        let mut vectors = vec![];
+        for (description, key, value, root_to_block) in [
+            (
+                "key written in the tip block",
+                "vm::ST000000000000000000002AMW42H.test::1::greeting",
+                "0d0000000568656c6c6f",
+                HashMap::new(),
+            ),
+            (
+                "key written in the parent of the tip block",
+                "vm::ST000000000000000000002AMW42H.test::1::counter",
+                "0100000000000000000000000000000001",
+                root_to_block,
+            ),
+        ]
+        .iter()
+        {
+            let proof = TrieMerkleProof::from_entry(
+                &mut m.borrow_storage_backend(),
+                &key.to_string(),
+                &value.to_string(),
+                &block_2,
+            )
+            .unwrap();
+            vectors.push(MarfProofVector {
+                description: description.to_string(),
+                root_hash: root_hash_2.clone(),
+                key: key.to_string(),
+                value: value.to_string(),
+                root_to_block: root_to_block.clone(),
+                proof: MarfProof::from_trie_proof(&proof),
+                valid: true,
+            });
+        }
+        vectors
+    }
+
+    fn vector_root_to_block(vector: &MarfProofVector) -> HashMap<TrieHash, StacksBlockId> {
+        vector
+            .root_to_block
+            .iter()
+            .map(|(root_hash, block_id)| (TrieHash::from_hex(root_hash).unwrap(), block_id.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn marf_proof_vectors() {
+        let vectors: Vec<MarfProofVector> =
+            serde_json::from_str(include_str!("marf-proof-vectors.json")).unwrap();
+
+        // the published proofs are the ones this node generates
+        let expected = make_marf_proof_vectors();
+        assert_eq!(
+            serde_json::to_value(&vectors).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+
+        for vector in vectors.iter() {
+            assert_eq!(
+                verify_marf_proof(
+                    &vector.root_hash,
+                    &vector.key,
+                    &vector.value,
+                    &vector.proof,
+                    &vector_root_to_block(vector)
+                )
+                .unwrap(),
+                vector.valid,
+                "{}",
+                &vector.description
+            );
+        }
+    }
+
+    #[test]
+    fn marf_proof_rejects_bad_entries() {
+        let vectors = make_marf_proof_vectors();
+        let tip_vector = &vectors[0];
+        let ancestor_vector = &vectors[1];
+        let no_blocks: HashMap<TrieHash, StacksBlockId> = HashMap::new();
+
+        // wrong value
+        assert!(!verify_marf_proof(
+            &tip_vector.root_hash,
+            &tip_vector.key,
+            "0100000000000000000000000000000002",
+            &tip_vector.proof,
+            &no_blocks
+        )
+        .unwrap());
+
+        // wrong root
+        assert!(!verify_marf_proof(
+            &TrieHash([0x11; 32]),
+            &tip_vector.key,
+            &tip_vector.value,
+            &tip_vector.proof,
+            &no_blocks
+        )
+        .unwrap());
+
+        // ancestor's root hash is not trusted
+        assert!(!verify_marf_proof(
+            &ancestor_vector.root_hash,
+            &ancestor_vector.key,
+            &ancestor_vector.value,
+            &ancestor_vector.proof,
+            &no_blocks
+        )
+        .unwrap());
+
+        // unsupported version
+        let mut proof = tip_vector.proof.clone();
+        proof.version = MARF_PROOF_VERSION + 1;
+        assert!(verify_marf_proof(
+            &tip_vector.root_hash,
+            &tip_vector.key,
+            &tip_vector.value,
+            &proof,
+            &no_blocks
+        )
+        .is_err());
+
+        // truncated or padded proof bytes
+        let mut proof = tip_vector.proof.clone();
+        proof.proof.pop();
+        assert!(proof.to_trie_proof::<StacksBlockId>().is_err());
+
+        let mut proof = tip_vector.proof.clone();
+        proof.proof.push(0);
+        assert!(proof.to_trie_proof::<StacksBlockId>().is_err());
+    }
+
+    #[test]
+    fn marf_proof_encodings() {
+        let proof = make_marf_proof_vectors()[0].proof.clone();
+
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes[0], MARF_PROOF_VERSION);
+        assert_eq!(MarfProof::from_bytes(&bytes).unwrap(), proof);
+        assert!(MarfProof::from_bytes(&[]).is_err());
+
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(serde_json::from_str::<MarfProof>(&json).unwrap(), proof);
+
+        let json = format!(
+            "{{\"version\":{},\"proof\":\"0x{}\"}}",
+            proof.version,
+            to_hex(&proof.proof)
+        );
+        assert_eq!(serde_json::from_str::<MarfProof>(&json).unwrap(), proof);
+    }
 }
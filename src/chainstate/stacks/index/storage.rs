@@ -29,7 +29,10 @@ use std::path::{Path, PathBuf};
 
 use std::iter::FromIterator;
 use std::os;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
+use lru_cache::LruCache;
 use regex::Regex;
 
 use chainstate::burn::BlockHeaderHash;
@@ -60,8 +63,11 @@ use chainstate::stacks::index::Error;
 use util::db::tx_begin_immediate;
 use util::db::tx_busy_handler;
 use util::db::Error as db_error;
+use util::db::{sqlite_open, ConnectionPool, PooledConnection, DEFAULT_CONNECTION_POOL_SIZE};
 use util::log;
 
+use monitoring;
+
 pub fn ftell<F: Seek>(f: &mut F) -> Result<u64, Error> {
     f.seek(SeekFrom::Current(0)).map_err(Error::IOError)
 }
@@ -572,6 +578,111 @@ impl<T: MarfTrieId> NodeHashReader for TrieRAM<T> {
     }
 }
 
+/// Default number of entries in each of the MARF node and node-hash caches.
+pub const DEFAULT_NODE_CACHE_SIZE: usize = 65536;
+
+/// Process-wide size of the MARF node cache.  Read whenever a TrieFileStorage is opened.
+static NODE_CACHE_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_NODE_CACHE_SIZE);
+
+/// Set the number of trie nodes (and, separately, node hashes) that each subsequently-opened
+/// TrieFileStorage caches in RAM.  0 disables the cache.
+pub fn set_node_cache_size(size: usize) {
+    NODE_CACHE_SIZE.store(size, Ordering::SeqCst);
+}
+
+pub fn get_node_cache_size() -> usize {
+    NODE_CACHE_SIZE.load(Ordering::SeqCst)
+}
+
+/// (block hash, node ID, node offset)
+type TrieNodeCacheKey = ([u8; 32], u8, u32);
+
+///
+/// In-RAM LRU cache of nodes and node hashes read from confirmed tries in SQLite.
+/// A confirmed trie is never modified once written, and its contents are determined by its
+///   block hash, so entries are keyed by block hash and never need to be invalidated.
+/// Unconfirmed tries can be rewritten in place, so they are never cached.
+/// The cache is shared by a TrieFileStorage and all read-only views reopened from it.
+///
+pub struct TrieNodeCache {
+    nodes: LruCache<TrieNodeCacheKey, (TrieNodeType, TrieHash)>,
+    hashes: LruCache<TrieNodeCacheKey, TrieHash>,
+    hits: u64,
+    misses: u64,
+}
+
+impl TrieNodeCache {
+    pub fn new(capacity: usize) -> TrieNodeCache {
+        TrieNodeCache {
+            nodes: LruCache::new(capacity),
+            hashes: LruCache::new(capacity),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn make_key<T: MarfTrieId>(bhh: &T, ptr: &TriePtr) -> TrieNodeCacheKey {
+        let mut block_bytes = [0u8; 32];
+        block_bytes.copy_from_slice(bhh.as_bytes());
+        (block_bytes, ptr.id(), ptr.ptr())
+    }
+
+    fn get_nodetype<T: MarfTrieId>(
+        &mut self,
+        bhh: &T,
+        ptr: &TriePtr,
+    ) -> Option<(TrieNodeType, TrieHash)> {
+        let res = self
+            .nodes
+            .get_mut(&TrieNodeCache::make_key(bhh, ptr))
+            .map(|entry| entry.clone());
+        self.count(res.is_some());
+        res
+    }
+
+    fn get_node_hash<T: MarfTrieId>(&mut self, bhh: &T, ptr: &TriePtr) -> Option<TrieHash> {
+        let key = TrieNodeCache::make_key(bhh, ptr);
+        let res = match self.hashes.get_mut(&key) {
+            Some(hash) => Some(hash.clone()),
+            None => self.nodes.get_mut(&key).map(|(_, hash)| hash.clone()),
+        };
+        self.count(res.is_some());
+        res
+    }
+
+    fn insert_nodetype<T: MarfTrieId>(
+        &mut self,
+        bhh: &T,
+        ptr: &TriePtr,
+        node: &TrieNodeType,
+        hash: &TrieHash,
+    ) {
+        self.nodes.insert(
+            TrieNodeCache::make_key(bhh, ptr),
+            (node.clone(), hash.clone()),
+        );
+    }
+
+    fn insert_node_hash<T: MarfTrieId>(&mut self, bhh: &T, ptr: &TriePtr, hash: &TrieHash) {
+        self.hashes
+            .insert(TrieNodeCache::make_key(bhh, ptr), hash.clone());
+    }
+
+    fn count(&mut self, hit: bool) {
+        monitoring::increment_marf_cache_counter(hit);
+        if hit {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.hashes.clear();
+    }
+}
+
 pub struct TrieSqlCursor<'a> {
     db: &'a Connection,
     block_id: u32,
@@ -603,6 +714,32 @@ impl<'a> Deref for SqliteConnection<'a> {
     }
 }
 
+/// The connection a TrieFileStorage reads and writes through.  Read-only views reopened from
+/// another storage borrow theirs from its connection pool, and return it when dropped.
+enum TrieFileStorageConn {
+    Owned(Connection),
+    Pooled(PooledConnection),
+}
+
+impl Deref for TrieFileStorageConn {
+    type Target = Connection;
+    fn deref(&self) -> &Connection {
+        match self {
+            TrieFileStorageConn::Owned(conn) => conn,
+            TrieFileStorageConn::Pooled(conn) => conn,
+        }
+    }
+}
+
+impl DerefMut for TrieFileStorageConn {
+    fn deref_mut(&mut self) -> &mut Connection {
+        match self {
+            TrieFileStorageConn::Owned(conn) => conn,
+            TrieFileStorageConn::Pooled(conn) => conn,
+        }
+    }
+}
+
 impl<'a, T: MarfTrieId> Deref for TrieStorageTransaction<'a, T> {
     type Target = TrieStorageConnection<'a, T>;
     fn deref(&self) -> &TrieStorageConnection<'a, T> {
@@ -668,6 +805,14 @@ struct TrieStorageTransientData<T: MarfTrieId> {
 
     block_hash_cache: HashMap<u32, T>,
 
+    /// Cache of nodes read from confirmed tries.  None if disabled.
+    node_cache: Option<Arc<Mutex<TrieNodeCache>>>,
+    /// Whether or not cur_block is a confirmed trie whose nodes can be cached.
+    cur_block_cacheable: bool,
+
+    /// Read-only connections for views reopened from this storage.
+    readonly_pool: Arc<ConnectionPool>,
+
     readonly: bool,
     unconfirmed: bool,
 }
@@ -678,7 +823,7 @@ struct TrieStorageTransientData<T: MarfTrieId> {
 pub struct TrieFileStorage<T: MarfTrieId> {
     pub db_path: String,
 
-    db: Connection,
+    db: TrieFileStorageConn,
     data: TrieStorageTransientData<T>,
 
     // used in testing in order to short-circuit block-height lookups
@@ -762,8 +907,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             }
         };
 
-        let mut db = Connection::open_with_flags(db_path, open_flags)?;
-        db.busy_handler(Some(tx_busy_handler))?;
+        let mut db = sqlite_open(db_path, open_flags)?;
 
         let db_path = db_path.to_string();
 
@@ -773,9 +917,18 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
 
         debug!("Opened TrieFileStorage {};", db_path);
 
+        let node_cache_size = get_node_cache_size();
+        let node_cache = if node_cache_size > 0 {
+            Some(Arc::new(Mutex::new(TrieNodeCache::new(node_cache_size))))
+        } else {
+            None
+        };
+
+        let readonly_pool = Arc::new(ConnectionPool::new(&db_path, DEFAULT_CONNECTION_POOL_SIZE));
+
         let ret = TrieFileStorage {
             db_path,
-            db,
+            db: TrieFileStorageConn::Owned(db),
 
             data: TrieStorageTransientData {
                 last_extended: None,
@@ -794,6 +947,11 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
                 trie_ancestor_hash_bytes_cache: None,
                 block_hash_cache: HashMap::new(),
 
+                node_cache: node_cache,
+                cur_block_cacheable: false,
+
+                readonly_pool: readonly_pool,
+
                 readonly: readonly,
                 unconfirmed: unconfirmed,
             },
@@ -833,15 +991,14 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
     }
 
     pub fn reopen_readonly(&self) -> Result<TrieFileStorage<T>, Error> {
-        let db = Connection::open_with_flags(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
-        db.busy_handler(Some(tx_busy_handler))?;
+        let db = ConnectionPool::get(&self.data.readonly_pool)?;
 
         trace!("Make read-only view of TrieFileStorage: {}", &self.db_path);
 
         // TODO: borrow self.last_extended and self.block_hash_cache; don't copy them
         let ret = TrieFileStorage {
             db_path: self.db_path.clone(),
-            db: db,
+            db: TrieFileStorageConn::Pooled(db),
 
             data: TrieStorageTransientData {
                 last_extended: self.data.last_extended.clone(),
@@ -860,6 +1017,11 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
                 trie_ancestor_hash_bytes_cache: None,
                 block_hash_cache: self.data.block_hash_cache.clone(),
 
+                node_cache: self.data.node_cache.clone(),
+                cur_block_cacheable: self.data.cur_block_cacheable,
+
+                readonly_pool: self.data.readonly_pool.clone(),
+
                 readonly: true,
                 unconfirmed: true,
             },
@@ -878,8 +1040,7 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
     /// reopen this transaction as a read-only marf.
     ///  _does not_ preserve the cur_block/open tip
     pub fn reopen_readonly(&self) -> Result<TrieFileStorage<T>, Error> {
-        let db = Connection::open_with_flags(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
-        db.busy_handler(Some(tx_busy_handler))?;
+        let db = ConnectionPool::get(&self.data.readonly_pool)?;
 
         trace!(
             "Make read-only view of TrieStorageTransaction: {}",
@@ -889,7 +1050,7 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
         // TODO: borrow self.last_extended and self.block_hash_cache; don't copy them
         let ret = TrieFileStorage {
             db_path: self.db_path.to_string(),
-            db: db,
+            db: TrieFileStorageConn::Pooled(db),
 
             data: TrieStorageTransientData {
                 last_extended: None,
@@ -908,6 +1069,11 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
                 trie_ancestor_hash_bytes_cache: None,
                 block_hash_cache: HashMap::new(),
 
+                node_cache: self.data.node_cache.clone(),
+                cur_block_cacheable: false,
+
+                readonly_pool: self.data.readonly_pool.clone(),
+
                 readonly: true,
                 unconfirmed: true,
             },
@@ -1125,8 +1291,15 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
 
         self.data.cur_block = T::sentinel();
         self.data.cur_block_id = None;
+        self.data.cur_block_cacheable = false;
         self.data.last_extended = None;
         self.clear_cached_ancestor_hashes_bytes();
+        if let Some(ref node_cache) = self.data.node_cache {
+            node_cache
+                .lock()
+                .expect("FATAL: MARF node cache lock is poisoned")
+                .clear();
+        }
 
         Ok(())
     }
@@ -1226,6 +1399,22 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         (nr, br, nw)
     }
 
+    #[cfg(test)]
+    pub fn node_cache_stats(&mut self) -> (u64, u64) {
+        match self.data.node_cache {
+            Some(ref node_cache) => {
+                let mut node_cache = node_cache
+                    .lock()
+                    .expect("FATAL: MARF node cache lock is poisoned");
+                let stats = (node_cache.hits, node_cache.misses);
+                node_cache.hits = 0;
+                node_cache.misses = 0;
+                stats
+            }
+            None => (0, 0),
+        }
+    }
+
     #[cfg(test)]
     pub fn leaf_stats(&mut self) -> (u64, u64) {
         let lr = self.data.read_leaf_count;
@@ -1337,6 +1526,10 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         }
 
         // opening a different Trie than the one we're extending
+        self.data.cur_block_cacheable = match self.data.node_cache {
+            Some(_) => !trie_sql::is_unconfirmed_block_identifier(&self.db, id)?,
+            None => false,
+        };
         self.data.cur_block_id = Some(id);
         self.data.cur_block = bhh.clone();
 
@@ -1355,6 +1548,7 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
             self.data.cur_block = sentinel;
             // did we write to the sentinel?
             self.data.cur_block_id = trie_sql::get_block_identifier(&self.db, bhh).ok();
+            self.data.cur_block_cacheable = false;
             return Ok(());
         }
 
@@ -1369,11 +1563,13 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         }
 
         // opening a different Trie than the one we're extending
-        self.data.cur_block_id =
-            Some(trie_sql::get_block_identifier(&self.db, bhh).map_err(|e| {
+        let (block_id, unconfirmed) = trie_sql::get_block_identifier_and_unconfirmed(&self.db, bhh)
+            .map_err(|e| {
                 warn!("Failed to load identifier for block {}", &bhh);
                 e
-            })?);
+            })?;
+        self.data.cur_block_id = Some(block_id);
+        self.data.cur_block_cacheable = !unconfirmed;
         self.data.cur_block = bhh.clone();
 
         Ok(())
@@ -1519,6 +1715,16 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         Ok(())
     }
 
+    /// Get the node cache, if it is enabled and cur_block is a confirmed trie whose nodes can be
+    /// cached.
+    fn cacheable_node_cache(&self) -> Option<Arc<Mutex<TrieNodeCache>>> {
+        if self.data.cur_block_cacheable {
+            self.data.node_cache.clone()
+        } else {
+            None
+        }
+    }
+
     pub fn read_node_hash_bytes(&mut self, ptr: &TriePtr) -> Result<TrieHash, Error> {
         if let Some((ref last_extended, ref mut trie_ram)) = self.data.last_extended {
             // special case
@@ -1529,7 +1735,20 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
 
         // some other block or ptr, or cache miss
         match self.data.cur_block_id {
-            Some(block_id) => trie_sql::get_node_hash_bytes(&self.db, block_id, ptr),
+            Some(block_id) => {
+                if let Some(node_cache) = self.cacheable_node_cache() {
+                    let mut node_cache = node_cache
+                        .lock()
+                        .expect("FATAL: MARF node cache lock is poisoned");
+                    if let Some(hash) = node_cache.get_node_hash(&self.data.cur_block, ptr) {
+                        return Ok(hash);
+                    }
+                    let hash = trie_sql::get_node_hash_bytes(&self.db, block_id, ptr)?;
+                    node_cache.insert_node_hash(&self.data.cur_block, ptr, &hash);
+                    return Ok(hash);
+                }
+                trie_sql::get_node_hash_bytes(&self.db, block_id, ptr)
+            }
             None => {
                 error!("Not found (no file is open)");
                 Err(Error::NotFoundError)
@@ -1561,7 +1780,20 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
 
         // some other block
         match self.data.cur_block_id {
-            Some(id) => trie_sql::read_node_type(&self.db, id, &clear_ptr),
+            Some(id) => {
+                if let Some(node_cache) = self.cacheable_node_cache() {
+                    let mut node_cache = node_cache
+                        .lock()
+                        .expect("FATAL: MARF node cache lock is poisoned");
+                    if let Some(res) = node_cache.get_nodetype(&self.data.cur_block, &clear_ptr) {
+                        return Ok(res);
+                    }
+                    let (node, hash) = trie_sql::read_node_type(&self.db, id, &clear_ptr)?;
+                    node_cache.insert_nodetype(&self.data.cur_block, &clear_ptr, &node, &hash);
+                    return Ok((node, hash));
+                }
+                trie_sql::read_node_type(&self.db, id, &clear_ptr)
+            }
             None => {
                 error!("Not found (no file is open)");
                 Err(Error::NotFoundError)
@@ -1900,6 +2132,127 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn node_cache_confirmed_tries() {
+        let test_name = "/tmp/node_cache_confirmed_tries";
+        if fs::metadata(&test_name).is_ok() {
+            fs::remove_file(&test_name).unwrap();
+        }
+
+        let marf_storage = TrieFileStorage::<StacksBlockId>::open(&test_name).unwrap();
+        let mut marf = MARF::<StacksBlockId>::from_storage(marf_storage);
+
+        let mut all_paths = vec![];
+        let mut parent = StacksBlockId::sentinel();
+        for j in 0..3u8 {
+            let tip = StacksBlockId([j + 1; 32]);
+            marf.begin(&parent, &tip).unwrap();
+            for i in 0..32u64 {
+                let mut path_bytes = [j; 32];
+                path_bytes[24..32].copy_from_slice(&i.to_be_bytes());
+
+                let path = TriePath::from_bytes(&path_bytes).unwrap();
+                let value = TrieLeaf::new(&vec![], &[(i as u8) ^ j; 40].to_vec());
+                marf.insert_raw(path.clone(), value.clone()).unwrap();
+                all_paths.push((path, value));
+            }
+            marf.commit().unwrap();
+            parent = tip;
+        }
+
+        let tip = parent;
+        marf.borrow_storage_backend().node_cache_stats();
+
+        // first pass fills the cache
+        for (path, expected_value) in all_paths.iter() {
+            let value = MARF::get_path(&mut marf.borrow_storage_backend(), &tip, &path)
+                .unwrap()
+                .unwrap();
+            assert_eq!(expected_value.data, value.data);
+        }
+        let (_, misses) = marf.borrow_storage_backend().node_cache_stats();
+        assert!(misses > 0);
+
+        // second pass is served entirely from the cache
+        for (path, expected_value) in all_paths.iter() {
+            let value = MARF::get_path(&mut marf.borrow_storage_backend(), &tip, &path)
+                .unwrap()
+                .unwrap();
+            assert_eq!(expected_value.data, value.data);
+        }
+        let (hits, misses) = marf.borrow_storage_backend().node_cache_stats();
+        assert!(hits > 0);
+        assert_eq!(misses, 0);
+
+        // read-only views share the cache
+        let mut ro_marf = marf.reopen_readonly().unwrap();
+        for (path, expected_value) in all_paths.iter() {
+            let value = MARF::get_path(&mut ro_marf.borrow_storage_backend(), &tip, &path)
+                .unwrap()
+                .unwrap();
+            assert_eq!(expected_value.data, value.data);
+        }
+        let (hits, misses) = ro_marf.borrow_storage_backend().node_cache_stats();
+        assert!(hits > 0);
+        assert_eq!(misses, 0);
+    }
+
+    #[test]
+    fn readonly_views_use_wal_and_pooled_connections() {
+        let test_name = "/tmp/readonly_views_use_wal_and_pooled_connections";
+        if fs::metadata(&test_name).is_ok() {
+            fs::remove_file(&test_name).unwrap();
+        }
+
+        let marf_storage = TrieFileStorage::<StacksBlockId>::open(&test_name).unwrap();
+        let mut marf = MARF::<StacksBlockId>::from_storage(marf_storage);
+
+        let journal_mode: String = marf
+            .sqlite_conn()
+            .query_row("PRAGMA journal_mode", NO_PARAMS, |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode.to_lowercase(), "wal");
+
+        let path = TriePath::from_bytes(&[0x01; 32]).unwrap();
+        let tip = StacksBlockId([0x01; 32]);
+        marf.begin(&StacksBlockId::sentinel(), &tip).unwrap();
+        marf.insert_raw(path.clone(), TrieLeaf::new(&vec![], &[0x01; 40].to_vec()))
+            .unwrap();
+        marf.commit().unwrap();
+
+        assert_eq!(
+            marf.borrow_storage_backend().data.readonly_pool.num_idle(),
+            0
+        );
+        for _ in 0..3 {
+            let mut ro_marf = marf.reopen_readonly().unwrap();
+            let value = MARF::get_path(&mut ro_marf.borrow_storage_backend(), &tip, &path)
+                .unwrap()
+                .unwrap();
+            assert_eq!(value.data.to_vec(), [0x01; 40].to_vec());
+        }
+        // the same connection was reused each time
+        assert_eq!(
+            marf.borrow_storage_backend().data.readonly_pool.num_idle(),
+            1
+        );
+
+        // a read-only view can read while another connection holds an exclusive write lock
+        let writer = sqlite_open(&test_name, OpenFlags::SQLITE_OPEN_READ_WRITE).unwrap();
+        writer.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        writer
+            .execute("DELETE FROM block_extension_locks", NO_PARAMS)
+            .unwrap();
+
+        let mut ro_marf = marf.reopen_readonly().unwrap();
+        let value = MARF::get_path(&mut ro_marf.borrow_storage_backend(), &tip, &path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(value.data.to_vec(), [0x01; 40].to_vec());
+
+        writer.execute_batch("ROLLBACK").unwrap();
+    }
+
     #[test]
     fn load_store_trie_4_4_same() {
         load_store_trie_m_n_same(4, 4, true);
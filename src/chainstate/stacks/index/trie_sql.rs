@@ -109,6 +109,29 @@ pub fn get_block_identifier<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Result
     .map_err(|e| e.into())
 }
 
+/// Get the block identifier of a trie, and whether or not it is an unconfirmed trie
+pub fn get_block_identifier_and_unconfirmed<T: MarfTrieId>(
+    conn: &Connection,
+    bhh: &T,
+) -> Result<(u32, bool), Error> {
+    conn.query_row(
+        "SELECT block_id, unconfirmed FROM marf_data WHERE block_hash = ?",
+        &[bhh],
+        |row| (row.get("block_id"), row.get("unconfirmed")),
+    )
+    .map_err(|e| e.into())
+}
+
+/// Is the trie with the given block identifier an unconfirmed trie?
+pub fn is_unconfirmed_block_identifier(conn: &Connection, local_id: u32) -> Result<bool, Error> {
+    conn.query_row(
+        "SELECT unconfirmed FROM marf_data WHERE block_id = ?",
+        &[local_id],
+        |row| row.get("unconfirmed"),
+    )
+    .map_err(|e| e.into())
+}
+
 pub fn get_confirmed_block_identifier<T: MarfTrieId>(
     conn: &Connection,
     bhh: &T,
@@ -56,6 +56,104 @@ use core::*;
 
 use vm::database::{BurnStateDB, NULL_BURN_STATE_DB};
 
+use monitoring;
+use util::get_epoch_time_ms;
+
+/// Limits on how the miner assembles an anchored block from the mempool.  These are the miner's
+/// own choices, not consensus rules -- by default, assembly fills the block up to its execution
+/// cost limit, however long that takes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockAssemblyBudget {
+    /// stop considering mempool transactions once this many milliseconds have passed
+    pub max_time_ms: Option<u64>,
+    /// fill at most this percentage of the block's execution cost limit
+    pub max_cost_percent: u64,
+    /// percentage of the (filled) cost limit that only transactions paying at least
+    /// `reserve_min_fee_rate` may use, so that high-fee transactions considered late in assembly
+    /// still fit
+    pub reserved_cost_percent: u64,
+    /// microSTX per byte
+    pub reserve_min_fee_rate: u64,
+    /// the most that a block's transactions may spend executing any one contract, as a
+    /// percentage of the (filled) cost limit
+    pub max_contract_cost_percent: Option<u64>,
+    /// contracts whose transactions are never selected
+    pub denied_contracts: HashSet<QualifiedContractIdentifier>,
+    /// if set, transactions that run a contract are only selected if it is one of these
+    pub allowed_contracts: Option<HashSet<QualifiedContractIdentifier>>,
+}
+
+impl Default for BlockAssemblyBudget {
+    fn default() -> BlockAssemblyBudget {
+        BlockAssemblyBudget {
+            max_time_ms: None,
+            max_cost_percent: 100,
+            reserved_cost_percent: 0,
+            reserve_min_fee_rate: 0,
+            max_contract_cost_percent: None,
+            denied_contracts: HashSet::new(),
+            allowed_contracts: None,
+        }
+    }
+}
+
+impl BlockAssemblyBudget {
+    /// Whether or not assembly may select a transaction that runs the given contract.  Returns
+    /// the reason it may not, if so.
+    pub fn check_contract(&self, contract_id: &QualifiedContractIdentifier) -> Result<(), &str> {
+        if self.denied_contracts.contains(contract_id) {
+            return Err("denied");
+        }
+        match self.allowed_contracts {
+            Some(ref allowed) if !allowed.contains(contract_id) => Err("not_allowed"),
+            _ => Ok(()),
+        }
+    }
+
+    /// The part of `limit` that assembly may fill
+    pub fn scale_limit(&self, limit: &ExecutionCost) -> ExecutionCost {
+        let scale = |dim: u64| -> u64 {
+            ((dim as u128) * (self.max_cost_percent.min(100) as u128) / 100) as u64
+        };
+        ExecutionCost {
+            runtime: scale(limit.runtime),
+            write_length: scale(limit.write_length),
+            write_count: scale(limit.write_count),
+            read_count: scale(limit.read_count),
+            read_length: scale(limit.read_length),
+        }
+    }
+}
+
+/// Limits on what the miner puts into each microblock it streams.  Like `BlockAssemblyBudget`,
+/// these are the miner's own choices -- by default, a microblock takes every transaction that fits
+/// in what is left of the block's budget.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MicroblockAssemblyLimits {
+    /// include at most this many transactions in a microblock
+    pub max_txs: Option<u64>,
+    /// spend at most this percentage of the block's execution cost limit on any one microblock
+    pub max_cost_percent: Option<u64>,
+}
+
+impl MicroblockAssemblyLimits {
+    /// Whether or not a microblock with `num_txs` transactions, which cost `cost` to run, can
+    /// take any more transactions
+    pub fn is_full(&self, num_txs: u64, cost: &ExecutionCost, block_limit: &ExecutionCost) -> bool {
+        if let Some(max_txs) = self.max_txs {
+            if num_txs >= max_txs {
+                return true;
+            }
+        }
+        if let Some(max_cost_percent) = self.max_cost_percent {
+            if cost.proportion_of_limit(block_limit) >= max_cost_percent {
+                return true;
+            }
+        }
+        false
+    }
+}
+
 #[derive(Clone)]
 struct MicroblockMinerRuntime {
     consumed_execution: ExecutionCost,
@@ -97,6 +195,11 @@ pub struct StacksMicroblockBuilder<'a> {
     clarity_tx: Option<ClarityTx<'a>>,
     unconfirmed: bool,
     runtime: MicroblockMinerRuntime,
+    size_limits: EpochSizeLimits,
+    block_limit: ExecutionCost,
+    assembly_limits: MicroblockAssemblyLimits,
+    /// whether the last microblock mined stopped taking transactions because it was full
+    full: bool,
 }
 
 impl<'a> StacksMicroblockBuilder<'a> {
@@ -128,6 +231,8 @@ impl<'a> StacksMicroblockBuilder<'a> {
         })?
         .block_height;
 
+        let block_limit = chainstate.block_limit.clone();
+
         // when we drop the miner, the underlying clarity instance will be rolled back
         chainstate.set_unconfirmed_dirty(true);
 
@@ -150,6 +255,10 @@ impl<'a> StacksMicroblockBuilder<'a> {
             anchor_block_height,
             runtime: runtime,
             clarity_tx: Some(clarity_tx),
+            size_limits: header_reader.size_limits.clone(),
+            block_limit,
+            assembly_limits: MicroblockAssemblyLimits::default(),
+            full: false,
             header_reader,
             unconfirmed: false,
         })
@@ -194,6 +303,7 @@ impl<'a> StacksMicroblockBuilder<'a> {
                 return Err(Error::NoSuchBlockError)?;
             };
 
+        let block_limit = chainstate.block_limit.clone();
         let mut clarity_tx = chainstate.begin_unconfirmed(burn_dbconn).ok_or_else(|| {
             warn!(
                 "Failed to begin-unconfirmed on {}/{}",
@@ -210,11 +320,26 @@ impl<'a> StacksMicroblockBuilder<'a> {
             anchor_block_height: anchored_block_height,
             runtime: runtime,
             clarity_tx: Some(clarity_tx),
+            size_limits: header_reader.size_limits.clone(),
+            block_limit,
+            assembly_limits: MicroblockAssemblyLimits::default(),
+            full: false,
             header_reader,
             unconfirmed: true,
         })
     }
 
+    /// Limit what each subsequent microblock may take
+    pub fn set_assembly_limits(&mut self, assembly_limits: MicroblockAssemblyLimits) {
+        self.assembly_limits = assembly_limits;
+    }
+
+    /// Whether the last microblock mined stopped taking transactions because it reached the
+    /// assembly limits or the block's budget, as opposed to running out of transactions
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
     fn make_next_microblock(
         &mut self,
         txs: Vec<StacksTransaction>,
@@ -268,6 +393,7 @@ impl<'a> StacksMicroblockBuilder<'a> {
         tx_len: u64,
         considered: &mut HashSet<Txid>,
         bytes_so_far: u64,
+        size_limits: &EpochSizeLimits,
     ) -> Result<bool, Error> {
         if tx.anchor_mode != TransactionAnchorMode::OffChainOnly
             && tx.anchor_mode != TransactionAnchorMode::Any
@@ -279,7 +405,16 @@ impl<'a> StacksMicroblockBuilder<'a> {
         } else {
             considered.insert(tx.txid());
         }
-        if bytes_so_far + tx_len >= MAX_EPOCH_SIZE.into() {
+        if tx_len > size_limits.max_tx_size {
+            debug!(
+                "Transaction {} is too big for a microblock ({} > {})",
+                tx.txid(),
+                tx_len,
+                size_limits.max_tx_size
+            );
+            return Ok(false);
+        }
+        if bytes_so_far + tx_len >= size_limits.max_epoch_size {
             return Err(Error::BlockTooBigError);
         }
         let quiet = !cfg!(test);
@@ -322,15 +457,27 @@ impl<'a> StacksMicroblockBuilder<'a> {
             .expect("Microblock already open and processing");
 
         let mut bytes_so_far = self.runtime.bytes_so_far;
+        let cost_before = clarity_tx.cost_so_far();
+        let mut full = false;
 
         let mut result = Ok(());
         for (tx, tx_len) in txs_and_lens.into_iter() {
+            if self.assembly_limits.is_full(
+                txs_included.len() as u64,
+                &clarity_tx.cost_so_far().saturating_sub(&cost_before),
+                &self.block_limit,
+            ) {
+                debug!("Microblock assembly limits reached");
+                full = true;
+                break;
+            }
             match StacksMicroblockBuilder::mine_next_transaction(
                 &mut clarity_tx,
                 tx.clone(),
                 tx_len,
                 &mut considered,
                 bytes_so_far,
+                &self.size_limits,
             ) {
                 Ok(true) => {
                     bytes_so_far += tx_len;
@@ -349,10 +496,12 @@ impl<'a> StacksMicroblockBuilder<'a> {
         self.runtime.bytes_so_far = bytes_so_far;
         self.clarity_tx.replace(clarity_tx);
         self.runtime.considered.replace(considered);
+        self.full = full;
 
         match result {
             Err(Error::BlockTooBigError) => {
                 info!("Block budget reached with microblocks");
+                self.full = true;
             }
             Err(e) => {
                 warn!("Error producing microblock: {}", e);
@@ -383,6 +532,11 @@ impl<'a> StacksMicroblockBuilder<'a> {
             .expect("Microblock already open and processing");
 
         let mut bytes_so_far = self.runtime.bytes_so_far;
+        let size_limits = self.size_limits.clone();
+        let block_limit = self.block_limit.clone();
+        let assembly_limits = self.assembly_limits.clone();
+        let cost_before = clarity_tx.cost_so_far();
+        let mut full = false;
 
         let result = mem_pool.iterate_candidates(
             &self.anchor_block_consensus_hash,
@@ -392,12 +546,22 @@ impl<'a> StacksMicroblockBuilder<'a> {
             |micro_txs| {
                 let mut result = Ok(());
                 for mempool_tx in micro_txs.into_iter() {
+                    if assembly_limits.is_full(
+                        txs_included.len() as u64,
+                        &clarity_tx.cost_so_far().saturating_sub(&cost_before),
+                        &block_limit,
+                    ) {
+                        debug!("Microblock assembly limits reached");
+                        full = true;
+                        break;
+                    }
                     match StacksMicroblockBuilder::mine_next_transaction(
                         &mut clarity_tx,
                         mempool_tx.tx.clone(),
                         mempool_tx.metadata.len,
                         &mut considered,
                         bytes_so_far,
+                        &size_limits,
                     ) {
                         Ok(true) => {
                             bytes_so_far += mempool_tx.metadata.len;
@@ -419,11 +583,13 @@ impl<'a> StacksMicroblockBuilder<'a> {
         self.runtime.bytes_so_far = bytes_so_far;
         self.clarity_tx.replace(clarity_tx);
         self.runtime.considered.replace(considered);
+        self.full = full;
 
         match result {
             Ok(_) => {}
             Err(Error::BlockTooBigError) => {
                 info!("Block budget reached with microblocks");
+                self.full = true;
             }
             Err(e) => {
                 warn!("Error producing microblock: {}", e);
@@ -496,6 +662,7 @@ impl StacksBlockBuilder {
             miner_privkey: StacksPrivateKey::new(), // caller should overwrite this, or refrain from mining microblocks
             miner_payouts: None,
             miner_id: miner_id,
+            size_limits: EpochSizeLimits::default(),
         }
     }
 
@@ -632,7 +799,16 @@ impl StacksBlockBuilder {
         tx: &StacksTransaction,
         tx_len: u64,
     ) -> Result<(), Error> {
-        if self.bytes_so_far + tx_len >= MAX_EPOCH_SIZE.into() {
+        if tx_len > self.size_limits.max_tx_size {
+            return Err(Error::InvalidStacksTransaction(
+                format!(
+                    "Transaction is too big ({} > {} bytes)",
+                    tx_len, self.size_limits.max_tx_size
+                ),
+                false,
+            ));
+        }
+        if self.bytes_so_far + tx_len >= self.size_limits.max_epoch_size {
             return Err(Error::BlockTooBigError);
         }
 
@@ -716,11 +892,11 @@ impl StacksBlockBuilder {
             .map_err(Error::NetError)?;
         let tx_len = tx_bytes.len() as u64;
 
-        if self.bytes_so_far + tx_len >= MAX_EPOCH_SIZE.into() {
+        if self.bytes_so_far + tx_len >= self.size_limits.max_epoch_size {
             warn!(
                 "Epoch size is {} >= {}",
                 self.bytes_so_far + tx_len,
-                MAX_EPOCH_SIZE
+                self.size_limits.max_epoch_size
             );
         }
 
@@ -1120,61 +1296,64 @@ impl StacksBlockBuilder {
         Ok(builder)
     }
 
-    /// Given access to the mempool, mine an anchored block with no more than the given execution cost.
-    ///   returns the assembled block, and the consumed execution budget.
-    pub fn build_anchored_block(
-        chainstate_handle: &StacksChainState, // not directly used; used as a handle to open other chainstates
-        burn_dbconn: &SortitionDBConn,
-        mempool: &MemPoolDB,
-        parent_stacks_header: &StacksHeaderInfo, // Stacks header we're building off of
-        total_burn: u64, // the burn so far on the burnchain (i.e. from the last burnchain block)
-        proof: VRFProof, // proof over the burnchain's last seed
-        pubkey_hash: Hash160,
-        coinbase_tx: &StacksTransaction,
-        execution_budget: ExecutionCost,
-    ) -> Result<(StacksBlock, ExecutionCost, u64), Error> {
-        if let TransactionPayload::Coinbase(..) = coinbase_tx.payload {
-        } else {
-            return Err(Error::MemPoolError(
-                "Not a coinbase transaction".to_string(),
-            ));
+    /// The contract whose code a transaction runs, if any
+    fn tx_contract_id(tx: &StacksTransaction) -> Option<QualifiedContractIdentifier> {
+        match tx.payload {
+            TransactionPayload::ContractCall(ref cc) => Some(QualifiedContractIdentifier::new(
+                cc.address.clone().into(),
+                cc.contract_name.clone(),
+            )),
+            TransactionPayload::SmartContract(ref sc) => Some(QualifiedContractIdentifier::new(
+                tx.origin_address().into(),
+                sc.name.clone(),
+            )),
+            _ => None,
         }
+    }
 
-        let (tip_consensus_hash, tip_block_hash, tip_height) = (
-            parent_stacks_header.consensus_hash.clone(),
-            parent_stacks_header.anchored_header.block_hash(),
-            parent_stacks_header.block_height,
-        );
-
-        debug!(
-            "Build anchored block off of {}/{} height {}",
-            &tip_consensus_hash, &tip_block_hash, tip_height
-        );
-
-        let (mut header_reader_chainstate, _) = chainstate_handle.reopen()?; // used for reading block headers during an epoch
-        let (mut chainstate, _) = chainstate_handle.reopen_limited(execution_budget)?; // used for processing a block up to the given limit
-
-        let mut builder = StacksBlockBuilder::make_block_builder(
-            parent_stacks_header,
-            proof,
-            total_burn,
-            pubkey_hash,
-        )?;
-
-        let mut epoch_tx = builder.epoch_begin(&mut chainstate, burn_dbconn)?;
-        builder.try_mine_tx(&mut epoch_tx, coinbase_tx)?;
-
+    /// Run the miner's transaction selection over the mempool's candidates, mining each one that
+    /// fits into the block under construction.  Transactions that are invalid or that would
+    /// exceed the block budget are skipped, and selection stops early once the assembly budget's
+    /// time runs out.  `block_limit` is the execution cost limit the block is being filled to.
+    fn select_mempool_txs(
+        builder: &mut StacksBlockBuilder,
+        epoch_tx: &mut ClarityTx,
+        mempool: &MemPoolDB,
+        header_reader_chainstate: &mut StacksChainState,
+        tip_consensus_hash: &ConsensusHash,
+        tip_block_hash: &BlockHeaderHash,
+        tip_height: u64,
+        assembly_budget: &BlockAssemblyBudget,
+        block_limit: &ExecutionCost,
+    ) -> Result<(), Error> {
         let mut considered = HashSet::new(); // txids of all transactions we looked at
         let mut mined_origin_nonces: HashMap<StacksAddress, u64> = HashMap::new(); // map addrs of mined transaction origins to the nonces we used
         let mut mined_sponsor_nonces: HashMap<StacksAddress, u64> = HashMap::new(); // map addrs of mined transaction sponsors to the nonces we used
+        let mut contract_costs: HashMap<QualifiedContractIdentifier, ExecutionCost> =
+            HashMap::new(); // execution cost spent in each contract so far
 
-        let result = mempool.iterate_candidates(
-            &tip_consensus_hash,
-            &tip_block_hash,
+        let deadline = assembly_budget
+            .max_time_ms
+            .map(|max_time_ms| get_epoch_time_ms() + (max_time_ms as u128));
+        let unreserved_percent = 100u64.saturating_sub(assembly_budget.reserved_cost_percent);
+
+        mempool.iterate_candidates(
+            tip_consensus_hash,
+            tip_block_hash,
             tip_height,
-            &mut header_reader_chainstate,
+            header_reader_chainstate,
             |available_txs| {
                 for txinfo in available_txs.into_iter() {
+                    if let Some(deadline) = deadline {
+                        if get_epoch_time_ms() >= deadline {
+                            debug!(
+                                "Block assembly time budget of {} ms exhausted",
+                                assembly_budget.max_time_ms.unwrap_or(0)
+                            );
+                            break;
+                        }
+                    }
+
                     // skip transactions early if we can
                     if considered.contains(&txinfo.tx.txid()) {
                         continue;
@@ -1194,14 +1373,64 @@ impl StacksBlockBuilder {
                         }
                     }
 
-                    considered.insert(txinfo.tx.txid());
+                    let cost_before = epoch_tx.cost_so_far();
+
+                    // only high-fee transactions may use the reserved part of the block
+                    let fee_per_byte = txinfo.metadata.fee_rate / txinfo.metadata.len.max(1);
+                    if fee_per_byte < assembly_budget.reserve_min_fee_rate
+                        && cost_before.proportion_of_limit(block_limit) >= unreserved_percent
+                    {
+                        continue;
+                    }
 
-                    match builder.try_mine_tx_with_len(
-                        &mut epoch_tx,
-                        &txinfo.tx,
-                        txinfo.metadata.len,
+                    let contract_id_opt = StacksBlockBuilder::tx_contract_id(&txinfo.tx);
+                    if let Some(ref contract_id) = contract_id_opt {
+                        if let Err(reason) = assembly_budget.check_contract(contract_id) {
+                            debug!(
+                                "Skip tx {} that runs contract {} ({})",
+                                &txinfo.tx.txid(),
+                                contract_id,
+                                reason
+                            );
+                            monitoring::increment_miner_contract_txs_skipped_counter(reason);
+                            considered.insert(txinfo.tx.txid());
+                            continue;
+                        }
+                    }
+                    if let (Some(max_contract_cost_percent), Some(contract_id)) = (
+                        assembly_budget.max_contract_cost_percent,
+                        contract_id_opt.as_ref(),
                     ) {
-                        Ok(_) => {}
+                        if let Some(contract_cost) = contract_costs.get(contract_id) {
+                            if contract_cost.proportion_of_limit(block_limit)
+                                >= max_contract_cost_percent
+                            {
+                                continue;
+                            }
+                        }
+                    }
+
+                    considered.insert(txinfo.tx.txid());
+
+                    match builder.try_mine_tx_with_len(epoch_tx, &txinfo.tx, txinfo.metadata.len) {
+                        Ok(_) => {
+                            let tx_cost = epoch_tx.cost_so_far().saturating_sub(&cost_before);
+                            if let Err(e) =
+                                mempool.record_tx_cost(&txinfo.tx, &tx_cost, block_limit)
+                            {
+                                warn!(
+                                    "Failed to record the cost of tx {}: {:?}",
+                                    &txinfo.tx.txid(),
+                                    &e
+                                );
+                            }
+                            if let Some(contract_id) = contract_id_opt {
+                                let contract_cost = contract_costs
+                                    .entry(contract_id)
+                                    .or_insert_with(ExecutionCost::zero);
+                                *contract_cost = contract_cost.saturating_add(&tx_cost);
+                            }
+                        }
                         Err(Error::BlockTooBigError) => {
                             // done mining -- our execution budget is exceeded.
                             // Make the block from the transactions we did manage to get
@@ -1227,6 +1456,94 @@ impl StacksBlockBuilder {
                 }
                 Ok(())
             },
+        )
+    }
+
+    /// Given access to the mempool, mine an anchored block with no more than the given execution cost.
+    ///   returns the assembled block, and the consumed execution budget.
+    pub fn build_anchored_block(
+        chainstate_handle: &StacksChainState, // not directly used; used as a handle to open other chainstates
+        burn_dbconn: &SortitionDBConn,
+        mempool: &MemPoolDB,
+        parent_stacks_header: &StacksHeaderInfo, // Stacks header we're building off of
+        total_burn: u64, // the burn so far on the burnchain (i.e. from the last burnchain block)
+        proof: VRFProof, // proof over the burnchain's last seed
+        pubkey_hash: Hash160,
+        coinbase_tx: &StacksTransaction,
+        execution_budget: ExecutionCost,
+    ) -> Result<(StacksBlock, ExecutionCost, u64), Error> {
+        StacksBlockBuilder::build_anchored_block_with_budget(
+            chainstate_handle,
+            burn_dbconn,
+            mempool,
+            parent_stacks_header,
+            total_burn,
+            proof,
+            pubkey_hash,
+            coinbase_tx,
+            execution_budget,
+            &BlockAssemblyBudget::default(),
+        )
+    }
+
+    /// Given access to the mempool, mine an anchored block with no more than the given execution
+    /// cost, assembling it within the given assembly budget.
+    ///   returns the assembled block, and the consumed execution budget.
+    pub fn build_anchored_block_with_budget(
+        chainstate_handle: &StacksChainState, // not directly used; used as a handle to open other chainstates
+        burn_dbconn: &SortitionDBConn,
+        mempool: &MemPoolDB,
+        parent_stacks_header: &StacksHeaderInfo, // Stacks header we're building off of
+        total_burn: u64, // the burn so far on the burnchain (i.e. from the last burnchain block)
+        proof: VRFProof, // proof over the burnchain's last seed
+        pubkey_hash: Hash160,
+        coinbase_tx: &StacksTransaction,
+        execution_budget: ExecutionCost,
+        assembly_budget: &BlockAssemblyBudget,
+    ) -> Result<(StacksBlock, ExecutionCost, u64), Error> {
+        if let TransactionPayload::Coinbase(..) = coinbase_tx.payload {
+        } else {
+            return Err(Error::MemPoolError(
+                "Not a coinbase transaction".to_string(),
+            ));
+        }
+
+        let (tip_consensus_hash, tip_block_hash, tip_height) = (
+            parent_stacks_header.consensus_hash.clone(),
+            parent_stacks_header.anchored_header.block_hash(),
+            parent_stacks_header.block_height,
+        );
+
+        debug!(
+            "Build anchored block off of {}/{} height {}",
+            &tip_consensus_hash, &tip_block_hash, tip_height
+        );
+
+        let execution_budget = assembly_budget.scale_limit(&execution_budget);
+        let (mut header_reader_chainstate, _) = chainstate_handle.reopen()?; // used for reading block headers during an epoch
+        let (mut chainstate, _) = chainstate_handle.reopen_limited(execution_budget.clone())?; // used for processing a block up to the given limit
+
+        let mut builder = StacksBlockBuilder::make_block_builder(
+            parent_stacks_header,
+            proof,
+            total_burn,
+            pubkey_hash,
+        )?;
+        builder.size_limits = chainstate_handle.size_limits.clone();
+
+        let mut epoch_tx = builder.epoch_begin(&mut chainstate, burn_dbconn)?;
+        builder.try_mine_tx(&mut epoch_tx, coinbase_tx)?;
+
+        let result = StacksBlockBuilder::select_mempool_txs(
+            &mut builder,
+            &mut epoch_tx,
+            mempool,
+            &mut header_reader_chainstate,
+            &tip_consensus_hash,
+            &tip_block_hash,
+            tip_height,
+            assembly_budget,
+            &execution_budget,
         );
 
         match result {
@@ -1245,8 +1562,80 @@ impl StacksBlockBuilder {
         let block = builder.mine_anchored_block(&mut epoch_tx);
         let size = builder.bytes_so_far;
         let consumed = builder.epoch_finish(epoch_tx);
+
+        debug!(
+            "Miner: built anchored block";
+            "block_hash" => %block.block_hash(),
+            "chain_tip" => %format!("{}/{}", &tip_consensus_hash, &tip_block_hash),
+            "height" => tip_height + 1,
+            "tx_count" => block.txs.len(),
+            "block_size" => size,
+            "execution_consumed" => %consumed,
+            "percent_full" => consumed.proportion_of_limit(&execution_budget)
+        );
+
         Ok((block, consumed, size))
     }
+
+    /// Run the miner's transaction selection against the mempool without mining a block, in
+    /// order to see what a block built off of the given parent would contain right now.  No
+    /// coinbase is included, and nothing is written to the chainstate.
+    ///   returns the selected transactions, their total fees, the consumed execution budget, and
+    ///   the size of the block they would make up.
+    pub fn simulate_anchored_block(
+        chainstate_handle: &StacksChainState, // not directly used; used as a handle to open other chainstates
+        burn_dbconn: &SortitionDBConn,
+        mempool: &MemPoolDB,
+        parent_stacks_header: &StacksHeaderInfo, // Stacks header we're building off of
+        execution_budget: ExecutionCost,
+    ) -> Result<(Vec<StacksTransaction>, u64, ExecutionCost, u64), Error> {
+        let (tip_consensus_hash, tip_block_hash, tip_height) = (
+            parent_stacks_header.consensus_hash.clone(),
+            parent_stacks_header.anchored_header.block_hash(),
+            parent_stacks_header.block_height,
+        );
+
+        debug!(
+            "Simulate anchored block off of {}/{} height {}",
+            &tip_consensus_hash, &tip_block_hash, tip_height
+        );
+
+        let (mut header_reader_chainstate, _) = chainstate_handle.reopen()?; // used for reading block headers during an epoch
+        let (mut chainstate, _) = chainstate_handle.reopen_limited(execution_budget.clone())?; // used for processing a block up to the given limit
+
+        let mut builder = StacksBlockBuilder::make_block_builder(
+            parent_stacks_header,
+            VRFProof::empty(),
+            0,
+            Hash160([0u8; 20]),
+        )?;
+        builder.size_limits = chainstate_handle.size_limits.clone();
+
+        let mut epoch_tx = builder.epoch_begin(&mut chainstate, burn_dbconn)?;
+        let result = StacksBlockBuilder::select_mempool_txs(
+            &mut builder,
+            &mut epoch_tx,
+            mempool,
+            &mut header_reader_chainstate,
+            &tip_consensus_hash,
+            &tip_block_hash,
+            tip_height,
+            &BlockAssemblyBudget::default(),
+            &execution_budget,
+        );
+
+        // never commit a simulated block
+        let consumed = epoch_tx.cost_so_far();
+        epoch_tx.rollback_block();
+        result?;
+
+        Ok((
+            builder.txs,
+            builder.total_anchored_fees,
+            consumed,
+            builder.bytes_so_far,
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -4658,7 +5047,10 @@ pub mod test {
         let mut tx_coinbase = StacksTransaction::new(
             TransactionVersion::Testnet,
             miner.as_transaction_auth().unwrap(),
-            TransactionPayload::Coinbase(CoinbasePayload([(burnchain_height % 256) as u8; 32])),
+            TransactionPayload::Coinbase(
+                CoinbasePayload([(burnchain_height % 256) as u8; 32]),
+                None,
+            ),
         );
         tx_coinbase.chain_id = 0x80000000;
         tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
@@ -4854,7 +5246,12 @@ pub mod test {
         let mut tx_stx_transfer = StacksTransaction::new(
             TransactionVersion::Testnet,
             miner.as_transaction_auth().unwrap(),
-            TransactionPayload::TokenTransfer((*recipient).clone().into(), amount, (*memo).clone()),
+            TransactionPayload::TokenTransfer(
+                (*recipient).clone().into(),
+                amount,
+                (*memo).clone(),
+                None,
+            ),
         );
 
         tx_stx_transfer.chain_id = 0x80000000;
@@ -5748,6 +6145,7 @@ pub mod test {
             recipient.clone(),
             amount,
             TokenTransferMemo([0; 34]),
+            None,
         );
         sign_standard_singlesig_tx(payload.into(), sender, nonce, fee_rate)
     }
@@ -5757,7 +6155,7 @@ pub mod test {
         nonce: u64,
         fee_rate: u64,
     ) -> StacksTransaction {
-        let payload = TransactionPayload::Coinbase(CoinbasePayload([0; 32]));
+        let payload = TransactionPayload::Coinbase(CoinbasePayload([0; 32]), None);
         sign_standard_singlesig_tx(payload.into(), sender, nonce, fee_rate)
     }
 
@@ -6005,7 +6403,7 @@ pub mod test {
             if tenure_id > 0 {
                 // transaction was mined
                 assert_eq!(stacks_block.txs.len(), 2);
-                if let TransactionPayload::TokenTransfer(ref addr, ref amount, ref memo) =
+                if let TransactionPayload::TokenTransfer(ref addr, ref amount, ref memo, _) =
                     stacks_block.txs[1].payload
                 {
                     assert_eq!(*addr, recipient.to_account_principal());
@@ -6170,7 +6568,7 @@ pub mod test {
                 // transaction was mined, even though they were staggerred by time
                 assert_eq!(stacks_block.txs.len(), 11);
                 for i in 1..11 {
-                    if let TransactionPayload::TokenTransfer(ref addr, ref amount, ref memo) =
+                    if let TransactionPayload::TokenTransfer(ref addr, ref amount, ref memo, _) =
                         stacks_block.txs[i].payload
                     {
                         assert_eq!(*addr, recipient.to_account_principal());
@@ -6398,7 +6796,412 @@ pub mod test {
                 for tx in stacks_block.txs.iter() {
                     match tx.payload {
                         TransactionPayload::Coinbase(..) => {}
-                        TransactionPayload::TokenTransfer(ref recipient, ref amount, ref memo) => {}
+                        TransactionPayload::TokenTransfer(
+                            ref recipient,
+                            ref amount,
+                            ref memo,
+                            _,
+                        ) => {}
+                        _ => {
+                            assert!(false);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_anchored_blocks_assembly_budget() {
+        let num_blocks = 7;
+        let mut privks = vec![];
+        let mut initial_balances = vec![];
+        for _ in 0..8 {
+            let pk = StacksPrivateKey::new();
+            let addr = StacksAddress::from_public_keys(
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                &AddressHashMode::SerializeP2PKH,
+                1,
+                &vec![StacksPublicKey::from_private(&pk)],
+            )
+            .unwrap();
+
+            privks.push((pk, addr.clone()));
+            initial_balances.push((addr.to_account_principal(), 10000000000));
+        }
+
+        let mut peer_config =
+            TestPeerConfig::new("test_build_anchored_blocks_assembly_budget", 2018, 2019);
+        peer_config.initial_balances = initial_balances;
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let recipient =
+            StacksAddress::from_string("ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV").unwrap();
+        let counter_contract = "
+        (define-data-var n uint u0)
+        (define-public (incr) (begin (var-set n (+ (var-get n) u1)) (ok (var-get n))))";
+
+        for tenure_id in 0..num_blocks {
+            // send transactions to the mempool
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let parent_header_hash = parent_tip.anchored_header.block_hash();
+                    let parent_consensus_hash = parent_tip.consensus_hash.clone();
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                    let mut txs = vec![];
+                    let mut assembly_budget = BlockAssemblyBudget::default();
+                    match tenure_id {
+                        1 => {
+                            // out of time before any transaction is considered
+                            txs.push(make_user_stacks_transfer(
+                                &privks[0].0,
+                                0,
+                                200,
+                                &recipient.to_account_principal(),
+                                1,
+                            ));
+                            assembly_budget.max_time_ms = Some(0);
+                        }
+                        2 => {
+                            // the whole block is reserved for high-fee transactions
+                            txs.push(make_user_stacks_transfer(
+                                &privks[1].0,
+                                0,
+                                200,
+                                &recipient.to_account_principal(),
+                                1,
+                            ));
+                            txs.push(make_user_stacks_transfer(
+                                &privks[2].0,
+                                0,
+                                100000,
+                                &recipient.to_account_principal(),
+                                1,
+                            ));
+                            assembly_budget.reserved_cost_percent = 100;
+                            assembly_budget.reserve_min_fee_rate = 100;
+                        }
+                        3 => {
+                            // no limits -- the earlier low-fee transfers get mined too
+                            txs.push(make_user_contract_publish(
+                                &privks[3].0,
+                                0,
+                                1000,
+                                "counter",
+                                counter_contract,
+                            ));
+                        }
+                        4 => {
+                            // only one call to the contract fits its cost cap
+                            for (privk, _) in privks[4..6].iter() {
+                                let payload = TransactionPayload::new_contract_call(
+                                    privks[3].1.clone(),
+                                    "counter",
+                                    "incr",
+                                    vec![],
+                                )
+                                .unwrap();
+                                txs.push(sign_standard_singlesig_tx(payload, privk, 0, 1000));
+                            }
+                            assembly_budget.max_contract_cost_percent = Some(0);
+                        }
+                        5 => {
+                            // neither the new call nor the one left over is selected
+                            let payload = TransactionPayload::new_contract_call(
+                                privks[3].1.clone(),
+                                "counter",
+                                "incr",
+                                vec![],
+                            )
+                            .unwrap();
+                            txs.push(sign_standard_singlesig_tx(payload, &privks[6].0, 0, 1000));
+                            assembly_budget.denied_contracts.insert(
+                                QualifiedContractIdentifier::new(
+                                    privks[3].1.clone().into(),
+                                    "counter".into(),
+                                ),
+                            );
+                        }
+                        6 => {
+                            // no contract is allowed, but transfers still are
+                            txs.push(make_user_stacks_transfer(
+                                &privks[7].0,
+                                0,
+                                200,
+                                &recipient.to_account_principal(),
+                                1,
+                            ));
+                            assembly_budget.allowed_contracts = Some(HashSet::new());
+                        }
+                        _ => {}
+                    }
+
+                    for tx in txs.into_iter() {
+                        mempool
+                            .submit(chainstate, &parent_consensus_hash, &parent_header_hash, tx)
+                            .unwrap();
+                    }
+
+                    let anchored_block = StacksBlockBuilder::build_anchored_block_with_budget(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        ExecutionCost::max_value(),
+                        &assembly_budget,
+                    )
+                    .unwrap();
+                    (anchored_block.0, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let origins: Vec<_> = stacks_block
+                .txs
+                .iter()
+                .skip(1)
+                .map(|tx| tx.origin_address())
+                .collect();
+            match tenure_id {
+                1 => {
+                    assert_eq!(origins, vec![]);
+                }
+                2 => {
+                    assert_eq!(origins, vec![privks[2].1.clone()]);
+                }
+                3 => {
+                    assert_eq!(origins.len(), 3);
+                    for i in 0..4 {
+                        assert_eq!(origins.contains(&privks[i].1), i != 2);
+                    }
+                }
+                4 => {
+                    assert_eq!(origins.len(), 1);
+                    match stacks_block.txs[1].payload {
+                        TransactionPayload::ContractCall(..) => {}
+                        _ => panic!("expected a contract-call"),
+                    }
+                }
+                5 => {
+                    assert_eq!(origins, vec![]);
+                }
+                6 => {
+                    assert_eq!(origins, vec![privks[7].1.clone()]);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_anchored_blocks_skip_too_big() {
+        let privk = StacksPrivateKey::from_hex(
+            "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        )
+        .unwrap();
+        let num_blocks = 3;
+        let mut privks_big = vec![];
+        let mut initial_balances = vec![];
+        for _ in 0..num_blocks {
+            let pk = StacksPrivateKey::new();
+            let addr = StacksAddress::from_public_keys(
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                &AddressHashMode::SerializeP2PKH,
+                1,
+                &vec![StacksPublicKey::from_private(&pk)],
+            )
+            .unwrap()
+            .to_account_principal();
+
+            privks_big.push(pk);
+            initial_balances.push((addr, 100000000000));
+        }
+
+        let addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+        initial_balances.push((addr.to_account_principal(), 100000000000));
+
+        let mut peer_config =
+            TestPeerConfig::new("test_build_anchored_blocks_skip_too_big", 2016, 2017);
+        peer_config.initial_balances = initial_balances;
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let recipient_addr_str = "ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV";
+        let recipient = StacksAddress::from_string(recipient_addr_str).unwrap();
+        let mut sender_nonce = 0;
+
+        for tenure_id in 0..num_blocks {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let parent_header_hash = parent_tip.anchored_header.block_hash();
+                    let parent_consensus_hash = parent_tip.consensus_hash.clone();
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                    if tenure_id > 0 {
+                        let mut big_part = vec![];
+                        for i in 0..20 {
+                            big_part.push(format!("(define-data-var var-{} int 0)", i));
+                        }
+                        let contract = format!(
+                            "{}
+                    (define-data-var bar int 0)
+                    (define-public (get-bar) (ok (var-get bar)))",
+                            big_part.join("\n")
+                        );
+
+                        // fee high enough to get considered first, but too big to mine
+                        let contract_tx = make_user_contract_publish(
+                            &privks_big[tenure_id],
+                            0,
+                            (4 * contract.len()) as u64,
+                            &format!("hello-world-{}", tenure_id),
+                            &contract,
+                        );
+                        mempool
+                            .submit(
+                                chainstate,
+                                &parent_consensus_hash,
+                                &parent_header_hash,
+                                contract_tx,
+                            )
+                            .unwrap();
+
+                        let stx_transfer = make_user_stacks_transfer(
+                            &privk,
+                            sender_nonce,
+                            300,
+                            &recipient.to_account_principal(),
+                            1,
+                        );
+                        mempool
+                            .submit(
+                                chainstate,
+                                &parent_consensus_hash,
+                                &parent_header_hash,
+                                stx_transfer,
+                            )
+                            .unwrap();
+
+                        sender_nonce += 1;
+                    }
+
+                    // big enough for the coinbase and the stx-transfer, but not the contract
+                    chainstate.size_limits = EpochSizeLimits {
+                        max_epoch_size: MAX_EPOCH_SIZE as u64,
+                        max_tx_size: 500,
+                    };
+
+                    let anchored_block = StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        ExecutionCost::max_value(),
+                    )
+                    .unwrap();
+                    (anchored_block.0, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            if tenure_id > 0 {
+                // contract was not mined, but the stx-transfer was
+                assert_eq!(stacks_block.txs.len(), 2);
+                for tx in stacks_block.txs.iter() {
+                    assert!(tx.tx_len() <= 500);
+                    match tx.payload {
+                        TransactionPayload::Coinbase(..) => {}
+                        TransactionPayload::TokenTransfer(..) => {}
                         _ => {
                             assert!(false);
                         }
@@ -172,7 +172,10 @@ pub enum Error {
     MemPoolError(String),
     PoxAlreadyLocked,
     PoxInsufficientBalance,
+    PoxNoLock,
+    PoxInvalidLockChange,
     PoxNoRewardCycle,
+    GenesisAuditFailed(String),
 }
 
 impl From<marf_error> for Error {
@@ -227,7 +230,12 @@ impl fmt::Display for Error {
             Error::NoTransactionsToMine => write!(f, "No transactions to mine"),
             Error::PoxAlreadyLocked => write!(f, "Account has already locked STX for PoX"),
             Error::PoxInsufficientBalance => write!(f, "Not enough STX to lock"),
+            Error::PoxNoLock => write!(f, "Account has no STX locked for PoX"),
+            Error::PoxInvalidLockChange => {
+                write!(f, "PoX lock can only be extended to a later height or increased")
+            }
             Error::PoxNoRewardCycle => write!(f, "No such reward cycle"),
+            Error::GenesisAuditFailed(ref s) => fmt::Display::fmt(s, f),
         }
     }
 }
@@ -257,7 +265,10 @@ impl error::Error for Error {
             Error::NoTransactionsToMine => None,
             Error::PoxAlreadyLocked => None,
             Error::PoxInsufficientBalance => None,
+            Error::PoxNoLock => None,
+            Error::PoxInvalidLockChange => None,
             Error::PoxNoRewardCycle => None,
+            Error::GenesisAuditFailed(ref _s) => None,
         }
     }
 }
@@ -287,7 +298,10 @@ impl Error {
             Error::NoTransactionsToMine => "NoTransactionsToMine",
             Error::PoxAlreadyLocked => "PoxAlreadyLocked",
             Error::PoxInsufficientBalance => "PoxInsufficientBalance",
+            Error::PoxNoLock => "PoxNoLock",
+            Error::PoxInvalidLockChange => "PoxInvalidLockChange",
             Error::PoxNoRewardCycle => "PoxNoRewardCycle",
+            Error::GenesisAuditFailed(ref _s) => "GenesisAuditFailed",
         }
     }
 
@@ -609,13 +623,18 @@ impl_byte_array_newtype!(TokenTransferMemo, u8, 34);
 impl_byte_array_serde!(TokenTransferMemo);
 pub const TOKEN_TRANSFER_MEMO_LENGTH: usize = 34; // same as it is in Stacks v1
 
+/// Maximum length of the consensus serialization of an STX transfer's long memo -- a Clarity
+/// value carried in addition to its fixed-length memo, e.g. a tuple holding a destination tag.
+pub const MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH: usize = 1024;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TransactionPayload {
-    TokenTransfer(PrincipalData, u64, TokenTransferMemo),
+    TokenTransfer(PrincipalData, u64, TokenTransferMemo, Option<Value>),
     ContractCall(TransactionContractCall),
     SmartContract(TransactionSmartContract),
     PoisonMicroblock(StacksMicroblockHeader, StacksMicroblockHeader), // the previous epoch leader sent two microblocks with the same sequence, and this is proof
-    Coinbase(CoinbasePayload),
+    /// a block's coinbase, optionally paid to a principal other than the block's miner
+    Coinbase(CoinbasePayload, Option<PrincipalData>),
 }
 
 impl TransactionPayload {
@@ -638,6 +657,8 @@ pub enum TransactionPayloadID {
     ContractCall = 2,
     PoisonMicroblock = 3,
     Coinbase = 4,
+    TokenTransferWithLongMemo = 5,
+    CoinbaseToAltRecipient = 6,
 }
 
 /// Encoding of an asset type identifier
@@ -894,11 +915,32 @@ pub struct StacksBlockBuilder {
     miner_payouts: Option<(MinerReward, Vec<MinerReward>)>,
     parent_microblock_hash: Option<BlockHeaderHash>,
     miner_id: usize,
+    size_limits: EpochSizeLimits,
 }
 
 // maximum amount of data a leader can send during its epoch (2MB)
 pub const MAX_EPOCH_SIZE: u32 = 2 * 1024 * 1024;
 
+/// Byte limits on what a leader may produce during its epoch.  Mainnet always uses the defaults;
+/// custom networks can raise or lower them to explore differently-sized blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochSizeLimits {
+    /// maximum number of bytes across the anchored block and its microblock stream
+    pub max_epoch_size: u64,
+    /// maximum length of a single transaction.  Cannot exceed MAX_TRANSACTION_LEN, since
+    /// longer transactions cannot be decoded.
+    pub max_tx_size: u64,
+}
+
+impl Default for EpochSizeLimits {
+    fn default() -> EpochSizeLimits {
+        EpochSizeLimits {
+            max_epoch_size: MAX_EPOCH_SIZE as u64,
+            max_tx_size: MAX_TRANSACTION_LEN as u64,
+        }
+    }
+}
+
 // maximum microblock size is 64KB, but note that the current leader has a space budget of
 // $MAX_EPOCH_SIZE bytes (so the average microblock size needs to be 4kb if there are 256 of them)
 pub const MAX_MICROBLOCK_SIZE: u32 = 65536;
@@ -1171,6 +1213,7 @@ pub mod test {
                 stx_address.into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
             TransactionPayload::TokenTransfer(
                 PrincipalData::from(QualifiedContractIdentifier {
@@ -1179,6 +1222,7 @@ pub mod test {
                 }),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
             TransactionPayload::ContractCall(TransactionContractCall {
                 address: StacksAddress {
@@ -1193,7 +1237,7 @@ pub mod test {
                 name: ContractName::try_from(hello_contract_name).unwrap(),
                 code_body: StacksString::from_str(hello_contract_body).unwrap(),
             }),
-            TransactionPayload::Coinbase(CoinbasePayload([0x12; 32])),
+            TransactionPayload::Coinbase(CoinbasePayload([0x12; 32]), None),
             TransactionPayload::PoisonMicroblock(mblock_header_1, mblock_header_2),
         ];
 
@@ -1204,7 +1248,7 @@ pub mod test {
                 for tx_payload in tx_payloads.iter() {
                     match tx_payload {
                         // poison microblock and coinbase must be on-chain
-                        TransactionPayload::Coinbase(_) => {
+                        TransactionPayload::Coinbase(..) => {
                             if *anchor_mode != TransactionAnchorMode::OnChainOnly {
                                 continue;
                             }
@@ -1252,7 +1296,7 @@ pub mod test {
         let mut tx_coinbase = StacksTransaction::new(
             TransactionVersion::Mainnet,
             origin_auth.clone(),
-            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
         );
 
         tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
@@ -1270,7 +1314,7 @@ pub mod test {
 
         for tx in all_txs.drain(..) {
             match tx.payload {
-                TransactionPayload::Coinbase(_) => {
+                TransactionPayload::Coinbase(..) => {
                     continue;
                 }
                 _ => {}
@@ -38,7 +38,7 @@ use util::hash::Sha512Trunc256Sum;
 use util::retry::BoundReader;
 use util::secp256k1::MessageSignature;
 use vm::ast::build_ast;
-use vm::types::{QualifiedContractIdentifier, StandardPrincipalData};
+use vm::types::{PrincipalData, QualifiedContractIdentifier, StandardPrincipalData};
 use vm::{SymbolicExpression, SymbolicExpressionType, Value};
 
 use vm::representations::{ClarityName, ContractName};
@@ -120,12 +120,24 @@ impl StacksMessageCodec for TransactionSmartContract {
 impl StacksMessageCodec for TransactionPayload {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), net_error> {
         match *self {
-            TransactionPayload::TokenTransfer(ref address, ref amount, ref memo) => {
+            TransactionPayload::TokenTransfer(ref address, ref amount, ref memo, None) => {
                 write_next(fd, &(TransactionPayloadID::TokenTransfer as u8))?;
                 write_next(fd, address)?;
                 write_next(fd, amount)?;
                 write_next(fd, memo)?;
             }
+            TransactionPayload::TokenTransfer(
+                ref address,
+                ref amount,
+                ref memo,
+                Some(ref long_memo),
+            ) => {
+                write_next(fd, &(TransactionPayloadID::TokenTransferWithLongMemo as u8))?;
+                write_next(fd, address)?;
+                write_next(fd, amount)?;
+                write_next(fd, memo)?;
+                write_next(fd, long_memo)?;
+            }
             TransactionPayload::ContractCall(ref cc) => {
                 write_next(fd, &(TransactionPayloadID::ContractCall as u8))?;
                 cc.consensus_serialize(fd)?;
@@ -139,10 +151,15 @@ impl StacksMessageCodec for TransactionPayload {
                 h1.consensus_serialize(fd)?;
                 h2.consensus_serialize(fd)?;
             }
-            TransactionPayload::Coinbase(ref buf) => {
+            TransactionPayload::Coinbase(ref buf, None) => {
                 write_next(fd, &(TransactionPayloadID::Coinbase as u8))?;
                 write_next(fd, buf)?;
             }
+            TransactionPayload::Coinbase(ref buf, Some(ref recipient)) => {
+                write_next(fd, &(TransactionPayloadID::CoinbaseToAltRecipient as u8))?;
+                write_next(fd, buf)?;
+                write_next(fd, recipient)?;
+            }
         }
         Ok(())
     }
@@ -154,7 +171,20 @@ impl StacksMessageCodec for TransactionPayload {
                 let principal = read_next(fd)?;
                 let amount = read_next(fd)?;
                 let memo = read_next(fd)?;
-                TransactionPayload::TokenTransfer(principal, amount, memo)
+                TransactionPayload::TokenTransfer(principal, amount, memo, None)
+            }
+            x if x == TransactionPayloadID::TokenTransferWithLongMemo as u8 => {
+                let principal = read_next(fd)?;
+                let amount = read_next(fd)?;
+                let memo = read_next(fd)?;
+                let long_memo: Value = read_next(fd)?;
+                if long_memo.serialize_to_vec().len() > MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH {
+                    return Err(net_error::DeserializeError(format!(
+                        "Failed to parse transaction -- long memo is longer than {} bytes",
+                        MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH
+                    )));
+                }
+                TransactionPayload::TokenTransfer(principal, amount, memo, Some(long_memo))
             }
             x if x == TransactionPayloadID::ContractCall as u8 => {
                 let payload: TransactionContractCall = read_next(fd)?;
@@ -187,7 +217,12 @@ impl StacksMessageCodec for TransactionPayload {
             }
             x if x == TransactionPayloadID::Coinbase as u8 => {
                 let payload: CoinbasePayload = read_next(fd)?;
-                TransactionPayload::Coinbase(payload)
+                TransactionPayload::Coinbase(payload, None)
+            }
+            x if x == TransactionPayloadID::CoinbaseToAltRecipient as u8 => {
+                let payload: CoinbasePayload = read_next(fd)?;
+                let recipient: PrincipalData = read_next(fd)?;
+                TransactionPayload::Coinbase(payload, Some(recipient))
             }
             _ => {
                 return Err(net_error::DeserializeError(format!(
@@ -462,7 +497,7 @@ impl StacksTransaction {
                     ));
                 }
             }
-            TransactionPayload::Coinbase(_) => {
+            TransactionPayload::Coinbase(..) => {
                 if anchor_mode != TransactionAnchorMode::OnChainOnly {
                     warn!("Invalid tx: invalid anchor mode for coinbase");
                     return Err(net_error::DeserializeError(
@@ -541,7 +576,7 @@ impl StacksTransaction {
         payload: TransactionPayload,
     ) -> StacksTransaction {
         let anchor_mode = match payload {
-            TransactionPayload::Coinbase(_) => TransactionAnchorMode::OnChainOnly,
+            TransactionPayload::Coinbase(..) => TransactionAnchorMode::OnChainOnly,
             TransactionPayload::PoisonMicroblock(_, _) => TransactionAnchorMode::OnChainOnly,
             _ => TransactionAnchorMode::Any,
         };
@@ -625,7 +660,7 @@ impl StacksTransaction {
     /// sponsored.  It does _not_ commit to the sponsored fields, so set them all to sentinel
     /// values.
     /// Return the initial sighash.
-    fn sign_begin(&self) -> Txid {
+    pub fn sign_begin(&self) -> Txid {
         let mut tx = self.clone();
         tx.auth = tx.auth.into_initial_sighash_auth();
         tx.txid()
@@ -1020,7 +1055,7 @@ mod test {
     use util::retry::LogReader;
 
     use vm::representations::{ClarityName, ContractName};
-    use vm::types::{PrincipalData, QualifiedContractIdentifier};
+    use vm::types::{PrincipalData, QualifiedContractIdentifier, TupleData};
 
     use std::error::Error;
 
@@ -1441,8 +1476,13 @@ mod test {
         // mess with payload
         let mut corrupt_tx_payload = signed_tx.clone();
         corrupt_tx_payload.payload = match corrupt_tx_payload.payload {
-            TransactionPayload::TokenTransfer(ref addr, ref amount, ref memo) => {
-                TransactionPayload::TokenTransfer(addr.clone(), amount + 1, memo.clone())
+            TransactionPayload::TokenTransfer(ref addr, ref amount, ref memo, ref long_memo) => {
+                TransactionPayload::TokenTransfer(
+                    addr.clone(),
+                    amount + 1,
+                    memo.clone(),
+                    long_memo.clone(),
+                )
             }
             TransactionPayload::ContractCall(_) => {
                 TransactionPayload::SmartContract(TransactionSmartContract {
@@ -1469,12 +1509,12 @@ mod test {
                 corrupt_h2.sequence += 1;
                 TransactionPayload::PoisonMicroblock(corrupt_h1, corrupt_h2)
             }
-            TransactionPayload::Coinbase(ref buf) => {
+            TransactionPayload::Coinbase(ref buf, ref recipient) => {
                 let mut corrupt_buf_bytes = buf.as_bytes().clone();
                 corrupt_buf_bytes[0] = (((corrupt_buf_bytes[0] as u16) + 1) % 256) as u8;
 
                 let corrupt_buf = CoinbasePayload(corrupt_buf_bytes);
-                TransactionPayload::Coinbase(corrupt_buf)
+                TransactionPayload::Coinbase(corrupt_buf, recipient.clone())
             }
         };
         assert!(corrupt_tx_payload.txid() != signed_tx.txid());
@@ -1554,8 +1594,12 @@ mod test {
             bytes: Hash160([0xff; 20]),
         });
 
-        let tt_stx =
-            TransactionPayload::TokenTransfer(addr.clone(), 123, TokenTransferMemo([1u8; 34]));
+        let tt_stx = TransactionPayload::TokenTransfer(
+            addr.clone(),
+            123,
+            TokenTransferMemo([1u8; 34]),
+            None,
+        );
 
         // wire encodings of the same
         let mut tt_stx_bytes = vec![];
@@ -1575,8 +1619,12 @@ mod test {
             name: "foo-contract".into(),
         });
 
-        let tt_stx =
-            TransactionPayload::TokenTransfer(addr.clone(), 123, TokenTransferMemo([1u8; 34]));
+        let tt_stx = TransactionPayload::TokenTransfer(
+            addr.clone(),
+            123,
+            TokenTransferMemo([1u8; 34]),
+            None,
+        );
 
         // wire encodings of the same
         let mut tt_stx_bytes = vec![];
@@ -1588,6 +1636,49 @@ mod test {
         check_codec_and_corruption::<TransactionPayload>(&tt_stx, &tt_stx_bytes);
     }
 
+    #[test]
+    fn tx_stacks_transaction_payload_tokens_long_memo() {
+        let addr = PrincipalData::from(StacksAddress {
+            version: 1,
+            bytes: Hash160([0xff; 20]),
+        });
+        let long_memo =
+            Value::Tuple(TupleData::from_data(vec![("tag".into(), Value::UInt(42))]).unwrap());
+
+        let tt_stx = TransactionPayload::TokenTransfer(
+            addr.clone(),
+            123,
+            TokenTransferMemo([1u8; 34]),
+            Some(long_memo.clone()),
+        );
+
+        // wire encodings of the same
+        let mut tt_stx_bytes = vec![];
+        tt_stx_bytes.push(TransactionPayloadID::TokenTransferWithLongMemo as u8);
+        addr.consensus_serialize(&mut tt_stx_bytes).unwrap();
+        tt_stx_bytes.append(&mut vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 123]);
+        tt_stx_bytes.append(&mut vec![1u8; 34]);
+        long_memo.consensus_serialize(&mut tt_stx_bytes).unwrap();
+
+        check_codec_and_corruption::<TransactionPayload>(&tt_stx, &tt_stx_bytes);
+
+        // long memos can't be too long
+        let long_memo = Value::buff_from(vec![0u8; MAX_TOKEN_TRANSFER_LONG_MEMO_LENGTH]).unwrap();
+        let mut tt_stx_bytes = vec![];
+        tt_stx_bytes.push(TransactionPayloadID::TokenTransferWithLongMemo as u8);
+        addr.consensus_serialize(&mut tt_stx_bytes).unwrap();
+        tt_stx_bytes.append(&mut vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 123]);
+        tt_stx_bytes.append(&mut vec![1u8; 34]);
+        long_memo.consensus_serialize(&mut tt_stx_bytes).unwrap();
+
+        match TransactionPayload::consensus_deserialize(&mut &tt_stx_bytes[..]) {
+            Err(net_error::DeserializeError(msg)) => {
+                assert!(msg.contains("long memo is longer than"));
+            }
+            res => panic!("Decoded an over-long memo: {:?}", &res),
+        }
+    }
+
     #[test]
     fn tx_stacks_transacton_payload_contracts() {
         let hello_contract_call = "hello-contract-call";
@@ -1661,7 +1752,7 @@ mod test {
 
     #[test]
     fn tx_stacks_transaction_payload_coinbase() {
-        let coinbase_payload = TransactionPayload::Coinbase(CoinbasePayload([0x12; 32]));
+        let coinbase_payload = TransactionPayload::Coinbase(CoinbasePayload([0x12; 32]), None);
         let coinbase_payload_bytes = vec![
             // payload type ID
             TransactionPayloadID::Coinbase as u8,
@@ -1706,6 +1797,40 @@ mod test {
         );
     }
 
+    #[test]
+    fn tx_stacks_transaction_payload_coinbase_alt_recipient() {
+        let standard_recipient = PrincipalData::from(StacksAddress {
+            version: 1,
+            bytes: Hash160([0xff; 20]),
+        });
+        let contract_recipient = PrincipalData::Contract(QualifiedContractIdentifier::new(
+            StandardPrincipalData(1, [0xfe; 20]),
+            "reward-splitter".into(),
+        ));
+
+        for recipient in [standard_recipient, contract_recipient].iter() {
+            let coinbase_payload =
+                TransactionPayload::Coinbase(CoinbasePayload([0x12; 32]), Some(recipient.clone()));
+
+            // wire encoding of the same
+            let mut coinbase_payload_bytes = vec![];
+            coinbase_payload_bytes.push(TransactionPayloadID::CoinbaseToAltRecipient as u8);
+            coinbase_payload_bytes.append(&mut vec![0x12; 32]);
+            recipient
+                .consensus_serialize(&mut coinbase_payload_bytes)
+                .unwrap();
+
+            // a truncated recipient fails to parse as a bad principal, not as an EOF, so only
+            // round-trip it
+            assert_eq!(coinbase_payload.serialize_to_vec(), coinbase_payload_bytes);
+            assert_eq!(
+                TransactionPayload::consensus_deserialize(&mut &coinbase_payload_bytes[..])
+                    .unwrap(),
+                coinbase_payload
+            );
+        }
+    }
+
     #[test]
     fn tx_stacks_transaction_payload_microblock_poison() {
         let header_1 = StacksMicroblockHeader {
@@ -3161,7 +3286,7 @@ mod test {
         let tx_coinbase = StacksTransaction::new(
             TransactionVersion::Mainnet,
             auth.clone(),
-            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
         );
 
         let tx_stx = StacksTransaction::new(
@@ -3171,6 +3296,7 @@ mod test {
                 stx_address.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
 
@@ -25,7 +25,7 @@ use std::process;
 
 use util::log;
 
-use chainstate::burn::BlockHeaderHash;
+use chainstate::burn::{BlockHeaderHash, ConsensusHash};
 use chainstate::stacks::index::{storage::TrieFileStorage, MarfTrieId};
 use chainstate::stacks::StacksBlockId;
 
@@ -36,13 +36,14 @@ use rusqlite::{Connection, OpenFlags, NO_PARAMS};
 
 use util::db::FromColumn;
 
-use util::hash::Sha512Trunc256Sum;
+use util::hash::{to_hex, Sha512Trunc256Sum};
 
 use vm::analysis;
 use vm::analysis::contract_interface_builder::build_contract_interface;
 use vm::analysis::{errors::CheckResult, AnalysisDatabase, ContractAnalysis};
 use vm::ast::build_ast;
 use vm::contexts::OwnedEnvironment;
+use vm::costs::profiler::CostProfile;
 use vm::costs::LimitedCostTracker;
 use vm::database::{
     ClarityDatabase, HeadersDB, MarfedKV, MemoryBackingStore, STXBalance, SqliteConnection,
@@ -50,12 +51,17 @@ use vm::database::{
 };
 use vm::errors::{Error, InterpreterResult, RuntimeErrorType};
 use vm::types::{PrincipalData, QualifiedContractIdentifier};
-use vm::{execute as vm_execute, SymbolicExpression, SymbolicExpressionType, Value};
+use vm::{
+    execute as vm_execute, ClarityVersion, SymbolicExpression, SymbolicExpressionType, Value,
+};
 
 use address::c32::c32_address;
 
 use burnchains::BurnchainHeaderHash;
 use chainstate::burn::VRFSeed;
+use chainstate::stacks::boot::{
+    BOOT_CODE_COSTS, BOOT_CODE_COSTS_2, STACKS_BOOT_COST_2_CONTRACT, STACKS_BOOT_COST_CONTRACT,
+};
 use chainstate::stacks::StacksAddress;
 
 use serde::Serialize;
@@ -87,9 +93,15 @@ where command is one of:
   eval_at_block      like `eval_at_chaintip`, but accepts a index-block-hash to evaluate at,
                      must be passed eval string via stdin.
   eval_raw           to typecheck and evaluate an expression without a contract or database context.
-  repl               to typecheck and evaluate expressions in a stdin/stdout loop.
+  repl               to typecheck and evaluate expressions, deploy and call contracts, inspect
+                     balances and mine blocks in a stdin/stdout loop, against the given VM state
+                     database or a scratch one.
   execute            to execute a public function of a defined contract.
   generate_address   to generate a random Stacks public address for testing purposes.
+
+`check` and `execute` also accept a trailing `--profile` flag, which reports the cost of each
+function and expression as JSON, or `--profile_folded`, which reports the runtime cost of each
+stack of them in the folded format that flame graph tools consume.
 ",
         invoked_by
     );
@@ -122,8 +134,13 @@ fn parse(
     contract_identifier: &QualifiedContractIdentifier,
     source_code: &str,
 ) -> Result<Vec<SymbolicExpression>, Error> {
-    let ast = build_ast(contract_identifier, source_code, &mut ())
-        .map_err(|e| RuntimeErrorType::ASTError(e))?;
+    let ast = build_ast(
+        contract_identifier,
+        source_code,
+        &mut (),
+        ClarityVersion::latest(),
+    )
+    .map_err(|e| RuntimeErrorType::ASTError(e))?;
     Ok(ast.expressions)
 }
 
@@ -132,17 +149,72 @@ fn run_analysis(
     expressions: &mut [SymbolicExpression],
     analysis_db: &mut AnalysisDatabase,
     save_contract: bool,
+    cost_track: LimitedCostTracker,
 ) -> CheckResult<ContractAnalysis> {
     analysis::run_analysis(
         contract_identifier,
         expressions,
         analysis_db,
         save_contract,
-        LimitedCostTracker::new_free(),
+        cost_track,
+        ClarityVersion::latest(),
     )
     .map_err(|(e, _)| e)
 }
 
+enum ProfileOutput {
+    Json,
+    Folded,
+}
+
+/// Strip a trailing `--profile` or `--profile_folded` flag from the arguments
+fn take_profile_flag(args: &[String]) -> (&[String], Option<ProfileOutput>) {
+    match args.last() {
+        Some(s) if s == "--profile" => (&args[..args.len() - 1], Some(ProfileOutput::Json)),
+        Some(s) if s == "--profile_folded" => {
+            (&args[..args.len() - 1], Some(ProfileOutput::Folded))
+        }
+        _ => (args, None),
+    }
+}
+
+/// Make a cost tracker which computes (and profiles) costs without limit.  The CLI's databases
+/// don't have the boot cost contracts, so their definitions are loaded from a scratch database.
+fn make_profiling_cost_tracker() -> LimitedCostTracker {
+    let mut costs_marf = MemoryBackingStore::new();
+    {
+        let mut vm_env = OwnedEnvironment::new_free(costs_marf.as_clarity_db());
+        friendly_expect(
+            vm_env.initialize_contract((*STACKS_BOOT_COST_CONTRACT).clone(), BOOT_CODE_COSTS),
+            "Failed to instantiate the cost contract.",
+        );
+        friendly_expect(
+            vm_env.initialize_contract((*STACKS_BOOT_COST_2_CONTRACT).clone(), BOOT_CODE_COSTS_2),
+            "Failed to instantiate the cost contract.",
+        );
+    }
+    let mut cost_track = friendly_expect(
+        LimitedCostTracker::new_max_limit(&mut costs_marf.as_clarity_db())
+            .map_err(|e| format!("{:?}", &e)),
+        "Failed to load the cost contract.",
+    );
+    cost_track.enable_profiling();
+    cost_track
+}
+
+fn print_profile(profile: &CostProfile, output: &ProfileOutput) {
+    match output {
+        ProfileOutput::Json => println!(
+            "{}",
+            friendly_expect(
+                serde_json::to_string(profile),
+                "Failed to serialize cost profile."
+            )
+        ),
+        ProfileOutput::Folded => print!("{}", profile.to_folded_stacks()),
+    }
+}
+
 fn create_or_open_db(path: &String) -> Connection {
     let open_flags = match fs::metadata(path) {
         Err(e) => {
@@ -405,6 +477,9 @@ impl HeadersDB for CLIHeadersDB {
             None
         }
     }
+    fn get_consensus_hash_for_block(&self, _id_bhh: &StacksBlockId) -> Option<ConsensusHash> {
+        None
+    }
     fn get_miner_address(&self, _id_bhh: &StacksBlockId) -> Option<StacksAddress> {
         None
     }
@@ -457,6 +532,369 @@ fn get_eval_input(invoked_by: &str, args: &[String]) -> EvalInput {
     };
 }
 
+const REPL_HELP: &str = "Enter a Clarity expression to typecheck and evaluate it (read-only) at the chain tip, or one of:
+
+  ::deploy [contract-identifier] [contract-definition.clar]
+                     to deploy a contract.
+  ::call [sender-address] [contract-identifier] [public-function-name] [args...]
+                     to call a public function as the given sender.
+  ::get_balance [principal]
+                     to show a principal's STX balance.
+  ::mint_stx [principal] [amount]
+                     to credit a principal with uSTX.
+  ::advance_chain_tip (count)
+                     to mine `count` (default 1) empty blocks.
+  ::block_height     to show the block height the next command will see.
+  ::help             to show this message.
+  ::quit             to end the session.
+
+Each command that changes the state is run in a new block.";
+
+/// The VM database a `repl` session runs against.  Every command that changes the state is run
+/// in a new block, so contracts, balances and the block height carry over from one command to
+/// the next -- and, if the session was given a database, to later invocations of the CLI.
+struct ReplSession {
+    vm_filename: String,
+    /// whether the database was made for this session, to be deleted when it ends
+    scratch: bool,
+}
+
+/// Split the arguments of a REPL command on whitespace, keeping parenthesized Clarity
+/// expressions and string literals together.
+fn split_repl_args(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut cur = String::new();
+    let mut depth = 0u32;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == '(' {
+            depth += 1;
+        } else if c == ')' {
+            depth = depth.saturating_sub(1);
+        } else if c.is_whitespace() && depth == 0 {
+            if !cur.is_empty() {
+                args.push(cur);
+                cur = String::new();
+            }
+            continue;
+        }
+        cur.push(c);
+    }
+    if !cur.is_empty() {
+        args.push(cur);
+    }
+    args
+}
+
+impl ReplSession {
+    /// Open a session on the given database, or on a new one in a temporary directory
+    fn open(vm_filename: Option<&String>) -> ReplSession {
+        if let Some(vm_filename) = vm_filename {
+            return ReplSession {
+                vm_filename: vm_filename.clone(),
+                scratch: false,
+            };
+        }
+
+        let mut path = env::temp_dir();
+        let random_bytes = rand::thread_rng().gen::<[u8; 16]>();
+        path.push(format!("clarity-repl-{}", to_hex(&random_bytes)));
+        let session = ReplSession {
+            vm_filename: path
+                .to_str()
+                .expect("FATAL: non-UTF-8 temporary directory")
+                .to_string(),
+            scratch: true,
+        };
+
+        let header_db = CLIHeadersDB::new(&session.vm_filename);
+        in_block(&session.vm_filename, session.open_marf(), |mut kv| {
+            kv.as_clarity_db(&header_db, &NULL_BURN_STATE_DB)
+                .initialize();
+            (kv, ())
+        });
+        session
+    }
+
+    fn open_marf(&self) -> MarfedKV {
+        friendly_expect(
+            MarfedKV::open(&self.vm_filename, None),
+            "Failed to open VM database.",
+        )
+    }
+
+    /// Run one line of input, returning what to print
+    fn run_line(&self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        if !line.starts_with("::") {
+            return self.eval(line);
+        }
+
+        let args = split_repl_args(&line[2..]);
+        match (
+            args.get(0).map(|arg| arg.as_str()).unwrap_or(""),
+            args.len(),
+        ) {
+            ("help", 1) => Ok(REPL_HELP.to_string()),
+            ("deploy", 3) => {
+                let contract_identifier = QualifiedContractIdentifier::parse(&args[1])
+                    .map_err(|e| format!("Failed to parse contract identifier: {}", e))?;
+                let content = fs::read_to_string(&args[2])
+                    .map_err(|e| format!("Error reading file {}: {}", args[2], e))?;
+                self.deploy(contract_identifier, &content)
+            }
+            ("call", n) if n >= 4 => {
+                let sender = PrincipalData::parse_standard_principal(&args[1])
+                    .map_err(|e| format!("Failed to parse sender: {}", e))?;
+                let contract_identifier = QualifiedContractIdentifier::parse(&args[2])
+                    .map_err(|e| format!("Failed to parse contract identifier: {}", e))?;
+                let mut arguments = vec![];
+                for argument in args[4..].iter() {
+                    let value = vm_execute(argument)
+                        .map_err(|e| format!("Error parsing argument \"{}\": {}", argument, e))?
+                        .ok_or_else(|| {
+                            format!("Failed to parse a value from the argument: {}", argument)
+                        })?;
+                    arguments.push(SymbolicExpression::atom_value(value));
+                }
+                self.call(
+                    PrincipalData::Standard(sender),
+                    contract_identifier,
+                    &args[3],
+                    &arguments,
+                )
+            }
+            ("get_balance", 2) => {
+                let principal = PrincipalData::parse(&args[1])
+                    .map_err(|e| format!("Failed to parse principal: {}", e))?;
+                self.get_balance(&principal)
+            }
+            ("mint_stx", 3) => {
+                let principal = PrincipalData::parse(&args[1])
+                    .map_err(|e| format!("Failed to parse principal: {}", e))?;
+                let amount = args[2]
+                    .parse::<u128>()
+                    .map_err(|e| format!("Failed to parse amount: {}", e))?;
+                self.mint_stx(&principal, amount)
+            }
+            ("advance_chain_tip", 1) => self.advance_chain_tip(1),
+            ("advance_chain_tip", 2) => {
+                let count = args[1]
+                    .parse::<u64>()
+                    .map_err(|e| format!("Failed to parse count: {}", e))?;
+                self.advance_chain_tip(count)
+            }
+            ("block_height", 1) => Ok(format!("{}", self.block_height())),
+            _ => Err(format!(
+                "Unrecognized command or wrong number of arguments: {}\n{}",
+                line, REPL_HELP
+            )),
+        }
+    }
+
+    /// Typecheck and evaluate an expression at the chain tip, discarding any writes
+    fn eval(&self, content: &str) -> Result<String, String> {
+        let contract_id = QualifiedContractIdentifier::transient();
+        let mut ast = parse(&contract_id, content).map_err(|e| format!("Parse error:\n{}", e))?;
+
+        let header_db = CLIHeadersDB::new(&self.vm_filename);
+        at_chaintip(&self.vm_filename, self.open_marf(), |mut marf| {
+            let analysis_result = {
+                let mut db = AnalysisDatabase::new(&mut marf);
+                run_analysis(
+                    &contract_id,
+                    &mut ast,
+                    &mut db,
+                    false,
+                    LimitedCostTracker::new_free(),
+                )
+            };
+            let result = match analysis_result {
+                Err(e) => Err(format!("Type check error:\n{}", e)),
+                Ok(_) => {
+                    let db = marf.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
+                    let mut vm_env =
+                        OwnedEnvironment::new_cost_limited(db, LimitedCostTracker::new_free());
+                    vm_env
+                        .execute_in_env(Value::from(contract_id.issuer.clone()), |exec_env| {
+                            exec_env.eval_raw(content)
+                        })
+                        .map(|(x, _, _)| format!("{}", x))
+                        .map_err(|e| format!("Execution error:\n{}", e))
+                }
+            };
+            (marf, result)
+        })
+    }
+
+    fn deploy(
+        &self,
+        contract_identifier: QualifiedContractIdentifier,
+        content: &str,
+    ) -> Result<String, String> {
+        let mut ast = parse(&contract_identifier, content)
+            .map_err(|e| format!("Failed to parse program:\n{}", e))?;
+
+        let header_db = CLIHeadersDB::new(&self.vm_filename);
+        in_block(&self.vm_filename, self.open_marf(), |mut marf| {
+            let analysis_result = {
+                let mut db = AnalysisDatabase::new(&mut marf);
+                run_analysis(
+                    &contract_identifier,
+                    &mut ast,
+                    &mut db,
+                    true,
+                    LimitedCostTracker::new_free(),
+                )
+            };
+            let result = match analysis_result {
+                Err(e) => Err(format!("Contract initialization error: \n{}", e)),
+                Ok(_) => {
+                    let db = marf.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
+                    let mut vm_env =
+                        OwnedEnvironment::new_cost_limited(db, LimitedCostTracker::new_free());
+                    vm_env
+                        .initialize_contract(contract_identifier, content)
+                        .map(|_| "Contract initialized!".to_string())
+                        .map_err(|e| format!("Contract initialization error: \n{}", e))
+                }
+            };
+            (marf, result)
+        })
+    }
+
+    fn call(
+        &self,
+        sender: PrincipalData,
+        contract_identifier: QualifiedContractIdentifier,
+        tx_name: &str,
+        arguments: &[SymbolicExpression],
+    ) -> Result<String, String> {
+        let header_db = CLIHeadersDB::new(&self.vm_filename);
+        let result = in_block(&self.vm_filename, self.open_marf(), |mut marf| {
+            let result = {
+                let db = marf.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
+                let mut vm_env =
+                    OwnedEnvironment::new_cost_limited(db, LimitedCostTracker::new_free());
+                vm_env.execute_transaction(
+                    Value::Principal(sender),
+                    contract_identifier,
+                    tx_name,
+                    arguments,
+                )
+            };
+            (marf, result)
+        });
+
+        match result {
+            Ok((Value::Response(data), _, events)) => {
+                if data.committed {
+                    Ok(format!(
+                        "Transaction executed and committed. Returned: {}\n{:?}",
+                        data.data, events
+                    ))
+                } else {
+                    Ok(format!("Aborted: {}", data.data))
+                }
+            }
+            Ok((x, _, _)) => Err(format!(
+                "Expected a ResponseType result from transaction. Found: {}",
+                x
+            )),
+            Err(error) => Err(format!("Transaction execution error: \n{}", error)),
+        }
+    }
+
+    fn get_balance(&self, principal: &PrincipalData) -> Result<String, String> {
+        let header_db = CLIHeadersDB::new(&self.vm_filename);
+        let (available, (locked, unlock_height)) =
+            at_chaintip(&self.vm_filename, self.open_marf(), |mut marf| {
+                let balance = {
+                    let mut db = marf.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
+                    db.begin();
+                    let balance = {
+                        let snapshot = db.get_stx_balance_snapshot(principal);
+                        (
+                            snapshot.get_available_balance(),
+                            snapshot.get_locked_balance(),
+                        )
+                    };
+                    db.roll_back();
+                    balance
+                };
+                (marf, balance)
+            });
+
+        if locked > 0 {
+            Ok(format!(
+                "{}: {} uSTX available, {} uSTX locked until burn block {}",
+                principal, available, locked, unlock_height
+            ))
+        } else {
+            Ok(format!("{}: {} uSTX available", principal, available))
+        }
+    }
+
+    fn mint_stx(&self, principal: &PrincipalData, amount: u128) -> Result<String, String> {
+        let header_db = CLIHeadersDB::new(&self.vm_filename);
+        in_block(&self.vm_filename, self.open_marf(), |mut marf| {
+            {
+                let mut db = marf.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
+                db.begin();
+                let mut snapshot = db.get_stx_balance_snapshot(principal);
+                snapshot.credit(amount);
+                snapshot.save();
+                db.commit();
+            }
+            (marf, ())
+        });
+        Ok(format!("{} credited: {} uSTX", principal, amount))
+    }
+
+    fn advance_chain_tip(&self, count: u64) -> Result<String, String> {
+        for _ in 0..count {
+            in_block(&self.vm_filename, self.open_marf(), |marf| (marf, ()));
+        }
+        Ok(format!("Block height: {}", self.block_height()))
+    }
+
+    /// The block height the next command will run at
+    fn block_height(&self) -> u32 {
+        let header_db = CLIHeadersDB::new(&self.vm_filename);
+        at_chaintip(&self.vm_filename, self.open_marf(), |mut marf| {
+            let height = {
+                let mut db = marf.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
+                db.begin();
+                let height = db.get_current_block_height();
+                db.roll_back();
+                height
+            };
+            (marf, height)
+        })
+    }
+}
+
+impl Drop for ReplSession {
+    fn drop(&mut self) {
+        if self.scratch {
+            if let Err(e) = fs::remove_dir_all(&self.vm_filename) {
+                eprintln!("Failed to remove {}: {}", &self.vm_filename, e);
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct InitialAllocation {
     principal: String,
@@ -547,13 +985,18 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
             println!("{}", addr);
         }
         "check" => {
+            let (args, profile_output) = take_profile_flag(args);
             if args.len() < 2 {
                 eprintln!(
-                    "Usage: {} {} [program-file.clar] (vm-state.db)",
+                    "Usage: {} {} [program-file.clar] (vm-state.db) (--output_analysis) (--profile|--profile_folded)",
                     invoked_by, args[0]
                 );
                 panic_test!();
             }
+            let cost_track = match profile_output {
+                Some(_) => make_profiling_cost_tracker(),
+                None => LimitedCostTracker::new_free(),
+            };
 
             let contract_id = QualifiedContractIdentifier::transient();
 
@@ -573,7 +1016,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
 
             let mut ast = friendly_expect(parse(&contract_id, &content), "Failed to parse program");
 
-            let contract_analysis = {
+            let mut contract_analysis = {
                 if args.len() >= 3 {
                     // use a persisted marf
                     let marf_kv = friendly_expect(
@@ -583,7 +1026,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
                     let result = at_chaintip(&args[2], marf_kv, |mut marf| {
                         let result = {
                             let mut db = AnalysisDatabase::new(&mut marf);
-                            run_analysis(&contract_id, &mut ast, &mut db, false)
+                            run_analysis(&contract_id, &mut ast, &mut db, false, cost_track)
                         };
                         (marf, result)
                     });
@@ -591,7 +1034,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
                 } else {
                     let mut analysis_marf = MemoryBackingStore::new();
                     let mut db = analysis_marf.as_analysis_db();
-                    run_analysis(&contract_id, &mut ast, &mut db, false)
+                    run_analysis(&contract_id, &mut ast, &mut db, false, cost_track)
                 }
             }
             .unwrap_or_else(|e| {
@@ -610,65 +1053,48 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
                     println!("Checks passed.");
                 }
             }
+
+            if let Some(ref output) = profile_output {
+                let profile = contract_analysis
+                    .take_contract_cost_tracker()
+                    .take_profile()
+                    .expect("BUG: profiling cost tracker has no profile");
+                print_profile(&profile, output);
+            }
         }
         "repl" => {
-            let mut marf = MemoryBackingStore::new();
-            let mut vm_env = OwnedEnvironment::new_cost_limited(
-                marf.as_clarity_db(),
-                LimitedCostTracker::new_free(),
-            );
-            let mut exec_env = vm_env.get_exec_environment(None);
-
-            let mut analysis_marf = MemoryBackingStore::new();
-            let mut analysis_db = analysis_marf.as_analysis_db();
-
-            let contract_id = QualifiedContractIdentifier::transient();
-
+            if args.len() > 2 {
+                eprintln!("Usage: {} {} (vm-state.db)", invoked_by, args[0]);
+                panic_test!();
+            }
+            let session = ReplSession::open(args.get(1));
             let mut stdout = io::stdout();
 
             loop {
-                let content: String = {
-                    let mut buffer = String::new();
-                    stdout.write(b"> ").unwrap_or_else(|e| {
-                        panic!("Failed to write stdout prompt string:\n{}", e);
-                    });
-                    stdout.flush().unwrap_or_else(|e| {
-                        panic!("Failed to flush stdout prompt string:\n{}", e);
-                    });
-                    match io::stdin().read_line(&mut buffer) {
-                        Ok(_) => buffer,
-                        Err(error) => {
-                            eprintln!("Error reading from stdin:\n{}", error);
-                            panic_test!();
-                        }
-                    }
-                };
-
-                let mut ast = match parse(&contract_id, &content) {
-                    Ok(val) => val,
-                    Err(error) => {
-                        println!("Parse error:\n{}", error);
-                        continue;
-                    }
-                };
-
-                match run_analysis(&contract_id, &mut ast, &mut analysis_db, true) {
-                    Ok(_) => (),
+                let mut buffer = String::new();
+                stdout.write(b"> ").unwrap_or_else(|e| {
+                    panic!("Failed to write stdout prompt string:\n{}", e);
+                });
+                stdout.flush().unwrap_or_else(|e| {
+                    panic!("Failed to flush stdout prompt string:\n{}", e);
+                });
+                match io::stdin().read_line(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(_) => {}
                     Err(error) => {
-                        println!("Type check error:\n{}", error);
-                        continue;
+                        eprintln!("Error reading from stdin:\n{}", error);
+                        panic_test!();
                     }
                 }
 
-                let eval_result = match exec_env.eval_raw(&content) {
-                    Ok(val) => val,
-                    Err(error) => {
-                        println!("Execution error:\n{}", error);
-                        continue;
-                    }
-                };
-
-                println!("{}", eval_result);
+                match buffer.trim() {
+                    "" => continue,
+                    "::quit" => break,
+                    line => match session.run_line(line) {
+                        Ok(output) => println!("{}", output),
+                        Err(error) => println!("{}", error),
+                    },
+                }
             }
         }
         "eval_raw" => {
@@ -694,7 +1120,13 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
 
             let mut ast =
                 friendly_expect(parse(&contract_id, &content), "Failed to parse program.");
-            match run_analysis(&contract_id, &mut ast, &mut analysis_db, true) {
+            match run_analysis(
+                &contract_id,
+                &mut ast,
+                &mut analysis_db,
+                true,
+                LimitedCostTracker::new_free(),
+            ) {
                 Ok(_) => {
                     let result = vm_env.get_exec_environment(None).eval_raw(&content);
                     match result {
@@ -856,7 +1288,13 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
                 let analysis_result = {
                     let mut db = AnalysisDatabase::new(&mut marf);
 
-                    run_analysis(&contract_identifier, &mut ast, &mut db, true)
+                    run_analysis(
+                        &contract_identifier,
+                        &mut ast,
+                        &mut db,
+                        true,
+                        LimitedCostTracker::new_free(),
+                    )
                 };
 
                 match analysis_result {
@@ -898,8 +1336,9 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
             }
         }
         "execute" => {
+            let (args, profile_output) = take_profile_flag(args);
             if args.len() < 5 {
-                eprintln!("Usage: {} {} [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...]", invoked_by, args[0]);
+                eprintln!("Usage: {} {} [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...] (--profile|--profile_folded)", invoked_by, args[0]);
                 panic_test!();
             }
             let vm_filename = &args[1];
@@ -941,17 +1380,25 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
                 })
                 .collect();
 
-            let result = in_block(vm_filename, marf_kv, |mut marf| {
+            let cost_track = match profile_output {
+                Some(_) => make_profiling_cost_tracker(),
+                None => LimitedCostTracker::new_free(),
+            };
+
+            let (result, profile) = in_block(vm_filename, marf_kv, |mut marf| {
                 let result = {
                     let db = marf.as_clarity_db(&header_db, &NULL_BURN_STATE_DB);
-                    let mut vm_env =
-                        OwnedEnvironment::new_cost_limited(db, LimitedCostTracker::new_free());
-                    vm_env.execute_transaction(
+                    let mut vm_env = OwnedEnvironment::new_cost_limited(db, cost_track);
+                    let result = vm_env.execute_transaction(
                         Value::Principal(sender),
                         contract_identifier,
                         &tx_name,
                         &arguments,
-                    )
+                    );
+                    let profile = vm_env
+                        .destruct()
+                        .and_then(|(_, mut cost_track)| cost_track.take_profile());
+                    (result, profile)
                 };
                 (marf, result)
             });
@@ -979,6 +1426,10 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) {
                     panic_test!();
                 }
             }
+
+            if let (Some(profile), Some(output)) = (profile, profile_output) {
+                print_profile(&profile, &output);
+            }
         }
         _ => print_usage(invoked_by),
     }
@@ -1025,6 +1476,85 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_repl_session() {
+        assert_eq!(
+            split_repl_args(" S1G2081040G2081040G2081040G208105NK8PE5.tokens token-transfer  (list u1 u2) \"a (b\\\" c\" u3"),
+            vec![
+                "S1G2081040G2081040G2081040G208105NK8PE5.tokens",
+                "token-transfer",
+                "(list u1 u2)",
+                "\"a (b\\\" c\"",
+                "u3"
+            ]
+        );
+
+        let session = ReplSession::open(None);
+        let vm_filename = session.vm_filename.clone();
+        assert!(fs::metadata(&vm_filename).is_ok());
+
+        assert_eq!(session.run_line("(+ 1 2)").unwrap(), "3");
+        assert!(session
+            .run_line("(+ 1 u2)")
+            .unwrap_err()
+            .starts_with("Type check error"));
+        assert!(session.run_line("::nope").is_err());
+
+        let height = session.block_height();
+        assert_eq!(
+            session
+                .run_line("::deploy S1G2081040G2081040G2081040G208105NK8PE5.tokens sample-contracts/tokens.clar")
+                .unwrap(),
+            "Contract initialized!"
+        );
+        assert!(session
+            .run_line("::call SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR S1G2081040G2081040G2081040G208105NK8PE5.tokens token-transfer 'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G (+ u50 u50)")
+            .unwrap()
+            .starts_with("Transaction executed and committed. Returned: u100"));
+        assert_eq!(
+            session
+                .run_line("::call SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G S1G2081040G2081040G2081040G208105NK8PE5.tokens token-transfer 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR u1000")
+                .unwrap(),
+            "Aborted: \"must transfer positive balance and possess funds\""
+        );
+
+        assert_eq!(
+            session
+                .run_line("::get_balance SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR")
+                .unwrap(),
+            "SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR: 0 uSTX available"
+        );
+        session
+            .run_line("::mint_stx SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 1000")
+            .unwrap();
+        assert_eq!(
+            session
+                .run_line("::get_balance SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR")
+                .unwrap(),
+            "SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR: 1000 uSTX available"
+        );
+        assert_eq!(
+            session
+                .run_line("(stx-get-balance 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)")
+                .unwrap(),
+            "u1000"
+        );
+
+        // each of the four commands that changed the state ran in its own block
+        assert_eq!(session.block_height(), height + 4);
+        assert_eq!(
+            session.run_line("::advance_chain_tip 3").unwrap(),
+            format!("Block height: {}", height + 7)
+        );
+        assert_eq!(
+            session.run_line("block-height").unwrap(),
+            format!("u{}", height + 7)
+        );
+
+        drop(session);
+        assert!(fs::metadata(&vm_filename).is_err());
+    }
+
     #[test]
     fn test_samples() {
         let db_name = format!("/tmp/db_{}", rand::thread_rng().gen::<i32>());
@@ -1096,6 +1626,31 @@ mod test {
             ],
         );
 
+        eprintln!("execute tokens (profiled)");
+        invoke_command(
+            "test",
+            &[
+                "execute".to_string(),
+                db_name.clone(),
+                "S1G2081040G2081040G2081040G208105NK8PE5.tokens".to_string(),
+                "mint!".to_string(),
+                "SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR".to_string(),
+                "u100".to_string(),
+                "--profile_folded".to_string(),
+            ],
+        );
+
+        eprintln!("check names (profiled)");
+        invoke_command(
+            "test",
+            &[
+                "check".to_string(),
+                "sample-contracts/names.clar".to_string(),
+                db_name.clone(),
+                "--profile".to_string(),
+            ],
+        );
+
         eprintln!("eval tokens");
         invoke_command(
             "test",
@@ -23,6 +23,8 @@ use rusqlite::Transaction;
 use rusqlite::NO_PARAMS;
 
 use std::cmp;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::mem;
 use std::ops::Deref;
 use std::ops::DerefMut;
 
@@ -33,13 +35,16 @@ use net::StacksMessageCodec;
 
 use chainstate::burn::BlockHeaderHash;
 use chainstate::stacks::{
-    db::blocks::MemPoolRejection, db::StacksChainState, index::Error as MarfError,
-    Error as ChainstateError, StacksAddress, StacksBlockHeader, StacksTransaction,
+    db::blocks::MemPoolRejection, db::blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, db::StacksChainState,
+    index::Error as MarfError, Error as ChainstateError, StacksAddress, StacksBlock,
+    StacksBlockHeader, StacksBlockId, StacksTransaction, TransactionPayload, MAX_EPOCH_SIZE,
+    MAX_TRANSACTION_LEN,
 };
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
+use util::db::query_count;
 use util::db::query_row;
 use util::db::query_rows;
 use util::db::tx_begin_immediate;
@@ -55,13 +60,285 @@ use core::FIRST_STACKS_BLOCK_HASH;
 
 use rusqlite::Error as SqliteError;
 
+use vm::clarity::ClarityConnection;
+use vm::costs::{ExecutionCost, LimitedCostTracker};
+use vm::database::NULL_BURN_STATE_DB;
+use vm::errors::{Error as clarity_error, InterpreterError};
+use vm::types::{PrincipalData, QualifiedContractIdentifier};
+use vm::{ClarityName, SymbolicExpression, Value};
+
 // maximum number of confirmations a transaction can have before it's garbage-collected
 pub const MEMPOOL_MAX_TRANSACTION_AGE: u64 = 256;
 pub const MAXIMUM_MEMPOOL_TX_CHAINING: u64 = 25;
+// by default, the mempool is garbage-collected every time the relayer processes network results
+pub const MEMPOOL_DEFAULT_GC_INTERVAL: u64 = 0;
+// by default, a replacement transaction need only pay a strictly higher fee than the one it replaces
+pub const MEMPOOL_DEFAULT_RBF_BUMP_PERCENT: u64 = 0;
+
+// most admission predicate results the mempool remembers for the current chain tip
+pub const MEMPOOL_PREDICATE_CACHE_SIZE: usize = 4096;
+// default cost budget for a single evaluation of the admission predicate
+pub const MEMPOOL_DEFAULT_PREDICATE_COST_LIMIT: ExecutionCost = ExecutionCost {
+    write_length: 0,
+    write_count: 0,
+    read_length: 100_000,
+    read_count: 30,
+    runtime: 1_000_000,
+};
+
+// number of recent anchored blocks the fee estimator samples
+pub const FEE_ESTIMATE_BLOCK_WINDOW: u64 = 10;
+// blocks that used at least this percentage of any one of their limits are considered congested
+pub const FEE_ESTIMATE_CONGESTION_PERCENT: u64 = 50;
+
+// number of transactions iterate_candidates reads from a chain tip at a time, and hands to the
+// miner at a time
+pub const MEMPOOL_CANDIDATE_PAGE_SIZE: u64 = 100;
+
+// version of the mempool DB's schema
+pub const MEMPOOL_SCHEMA_VERSION: i64 = 2;
+
+/// Node-local limits on which transactions the mempool will accept, on top of the checks that
+/// determine whether or not a transaction could be mined at all.  Operators of public nodes can
+/// tighten these to shed spam; they are not consensus rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemPoolAdmissionPolicy {
+    /// minimum fee rate, in microSTX per byte.  MINIMUM_TX_FEE_RATE_PER_BYTE is always enforced
+    /// regardless.
+    pub min_fee_rate: u64,
+    /// maximum encoded length of a single transaction
+    pub max_tx_size: u64,
+    /// maximum number of transactions an origin account may have pending in the mempool at once
+    /// (not counting one that a new transaction would replace).  None means no limit.
+    pub max_pending_per_origin: Option<u64>,
+    /// contracts which may be neither called nor deployed via this mempool
+    pub banned_contracts: HashSet<QualifiedContractIdentifier>,
+    /// read-only function which must approve every transaction this mempool accepts
+    pub admission_predicate: Option<MemPoolAdmissionPredicate>,
+}
+
+/// A read-only Clarity function that decides whether or not the mempool accepts a transaction,
+/// so operators can admit transactions based on on-chain policy (such as an allowlist registry).
+/// It is called as `(function origin sponsor contract)` at the chain tip the transaction is
+/// admitted against, with the origin as `tx-sender`, where `origin` is the transaction's origin
+/// principal, `sponsor` is `(some sponsor)` for sponsored transactions, and `contract` is
+/// `(some contract)` for contract-calls and contract deployments.  It must return `true` for the
+/// transaction to be accepted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemPoolAdmissionPredicate {
+    pub contract_id: QualifiedContractIdentifier,
+    pub function_name: ClarityName,
+    /// cost budget for a single evaluation.  Transactions whose evaluation exceeds it are
+    /// rejected.
+    pub cost_limit: ExecutionCost,
+}
+
+impl MemPoolAdmissionPredicate {
+    /// The arguments the predicate is called with for the given transaction
+    fn make_args(
+        tx: &StacksTransaction,
+    ) -> (
+        PrincipalData,
+        Option<PrincipalData>,
+        Option<QualifiedContractIdentifier>,
+    ) {
+        let origin = tx.origin_address().to_account_principal();
+        let sponsor = tx.sponsor_address().map(|addr| addr.to_account_principal());
+        let contract = match tx.payload {
+            TransactionPayload::ContractCall(ref cc) => Some(QualifiedContractIdentifier::new(
+                cc.address.into(),
+                cc.contract_name.clone(),
+            )),
+            TransactionPayload::SmartContract(ref sc) => Some(QualifiedContractIdentifier::new(
+                tx.origin_address().into(),
+                sc.name.clone(),
+            )),
+            _ => None,
+        };
+        (origin, sponsor, contract)
+    }
+
+    /// Evaluate the predicate at the given chain tip.  Returns Ok(true) if it approves, Ok(false)
+    /// if it does not, and Err(..) if it could not be evaluated (including if it exceeds its cost
+    /// budget).  Returns None if the chain tip does not exist.
+    fn evaluate(
+        &self,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        origin: &PrincipalData,
+        sponsor: &Option<PrincipalData>,
+        contract: &Option<QualifiedContractIdentifier>,
+    ) -> Option<Result<bool, String>> {
+        let args: Vec<_> = vec![
+            Value::Principal(origin.clone()),
+            match sponsor {
+                Some(ref sponsor) => Value::some(Value::Principal(sponsor.clone()))
+                    .expect("BUG: failed to construct (some principal)"),
+                None => Value::none(),
+            },
+            match contract {
+                Some(ref contract) => {
+                    Value::some(Value::Principal(PrincipalData::Contract(contract.clone())))
+                        .expect("BUG: failed to construct (some principal)")
+                }
+                None => Value::none(),
+            },
+        ]
+        .into_iter()
+        .map(SymbolicExpression::atom_value)
+        .collect();
+
+        chainstate
+            .with_read_only_clarity_tx(&NULL_BURN_STATE_DB, tip, |conn| {
+                let cost_track = conn
+                    .with_clarity_db_readonly(|clarity_db| {
+                        LimitedCostTracker::new(self.cost_limit.clone(), clarity_db)
+                    })
+                    .map_err(|_| clarity_error::from(InterpreterError::CostContractLoadFailure))?;
+
+                conn.with_readonly_clarity_env(origin.clone(), cost_track, |env| {
+                    env.execute_contract(
+                        &self.contract_id,
+                        self.function_name.as_str(),
+                        &args,
+                        true,
+                    )
+                })
+            })
+            .map(|result| match result {
+                Ok(Value::Bool(approved)) => Ok(approved),
+                Ok(value) => Err(format!("returned {}, not a bool", value)),
+                Err(e) => Err(e.to_string()),
+            })
+    }
+}
+
+impl Default for MemPoolAdmissionPolicy {
+    fn default() -> MemPoolAdmissionPolicy {
+        MemPoolAdmissionPolicy {
+            min_fee_rate: MINIMUM_TX_FEE_RATE_PER_BYTE,
+            max_tx_size: MAX_TRANSACTION_LEN as u64,
+            max_pending_per_origin: None,
+            banned_contracts: HashSet::new(),
+            admission_predicate: None,
+        }
+    }
+}
+
+impl MemPoolAdmissionPolicy {
+    /// Check a transaction against the parts of this policy that do not depend on the mempool's
+    /// contents.
+    pub fn check_tx(&self, tx: &StacksTransaction, tx_size: u64) -> Result<(), MemPoolRejection> {
+        if tx_size > self.max_tx_size {
+            return Err(MemPoolRejection::TransactionTooLarge(
+                tx_size,
+                self.max_tx_size,
+            ));
+        }
+
+        let fee = tx.get_fee_rate();
+        if fee / tx_size < self.min_fee_rate {
+            return Err(MemPoolRejection::FeeTooLow(
+                fee,
+                tx_size.saturating_mul(self.min_fee_rate),
+            ));
+        }
+
+        if !self.banned_contracts.is_empty() {
+            let contract_id = match tx.payload {
+                TransactionPayload::ContractCall(ref cc) => Some(QualifiedContractIdentifier::new(
+                    cc.address.into(),
+                    cc.contract_name.clone(),
+                )),
+                TransactionPayload::SmartContract(ref sc) => Some(
+                    QualifiedContractIdentifier::new(tx.origin_address().into(), sc.name.clone()),
+                ),
+                _ => None,
+            };
+            if let Some(contract_id) = contract_id {
+                if self.banned_contracts.contains(&contract_id) {
+                    return Err(MemPoolRejection::BannedContract(contract_id));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Limits on how long transactions stay in the mempool and how much space they may take up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemPoolGCPolicy {
+    /// number of blocks a transaction may stay in the mempool, counted from the height of the
+    /// chain tip it was admitted against.
+    pub max_tx_age: u64,
+    /// minimum number of seconds between two routine garbage-collection passes
+    pub gc_interval: u64,
+    /// maximum total length of the stored transactions, in bytes.  Once exceeded, the
+    /// transactions with the lowest fee rates are evicted.  None means no limit.
+    pub max_size: Option<u64>,
+}
+
+impl Default for MemPoolGCPolicy {
+    fn default() -> MemPoolGCPolicy {
+        MemPoolGCPolicy {
+            max_tx_age: MEMPOOL_MAX_TRANSACTION_AGE,
+            gc_interval: MEMPOOL_DEFAULT_GC_INTERVAL,
+            max_size: None,
+        }
+    }
+}
+
+/// How block assembly orders the candidate transactions it gets from the mempool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemPoolTxOrdering {
+    /// best fee rate first, keeping each origin account's transactions in nonce order
+    FeeRate,
+    /// by origin address, then origin nonce, then txid -- regardless of fees or arrival time,
+    /// so that devnets mine the same blocks from the same transactions on every run
+    Deterministic,
+}
+
+impl Default for MemPoolTxOrdering {
+    fn default() -> MemPoolTxOrdering {
+        MemPoolTxOrdering::FeeRate
+    }
+}
+
+/// What a garbage-collection pass removed, and what it left behind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemPoolGCResult {
+    /// number of transactions removed for being older than max_tx_age
+    pub expired: u64,
+    /// number of transactions evicted to bring the mempool under max_size
+    pub evicted: u64,
+    /// number of transactions left in the mempool
+    pub remaining_txs: u64,
+    /// total length of the transactions left in the mempool
+    pub remaining_bytes: u64,
+}
 
 pub struct MemPoolAdmitter {
     cur_block: BlockHeaderHash,
     cur_consensus_hash: ConsensusHash,
+    /// if true, then transactions that fail the nonce or balance checks against the confirmed
+    /// chain tip will be re-checked against the unconfirmed microblock state (if we have one).
+    admit_unconfirmed: bool,
+    /// minimum percentage by which a transaction's estimated fee must exceed that of a
+    /// conflicting transaction (same origin or sponsor nonce) in the same fork to replace it.
+    rbf_bump_percent: u64,
+    /// node-local limits on which transactions to accept
+    policy: MemPoolAdmissionPolicy,
+    /// the admission predicate's results for the chain tip they were evaluated against
+    predicate_cache_tip: StacksBlockId,
+    predicate_cache: HashMap<
+        (
+            PrincipalData,
+            Option<PrincipalData>,
+            Option<QualifiedContractIdentifier>,
+        ),
+        bool,
+    >,
 }
 
 impl MemPoolAdmitter {
@@ -69,9 +346,35 @@ impl MemPoolAdmitter {
         MemPoolAdmitter {
             cur_block,
             cur_consensus_hash,
+            admit_unconfirmed: true,
+            rbf_bump_percent: MEMPOOL_DEFAULT_RBF_BUMP_PERCENT,
+            policy: MemPoolAdmissionPolicy::default(),
+            predicate_cache_tip: StacksBlockId([0u8; 32]),
+            predicate_cache: HashMap::new(),
         }
     }
 
+    pub fn set_admit_unconfirmed(&mut self, admit_unconfirmed: bool) {
+        self.admit_unconfirmed = admit_unconfirmed;
+    }
+
+    pub fn set_rbf_bump_percent(&mut self, rbf_bump_percent: u64) {
+        self.rbf_bump_percent = rbf_bump_percent;
+    }
+
+    pub fn set_admission_policy(&mut self, policy: MemPoolAdmissionPolicy) {
+        self.policy = policy;
+        self.predicate_cache.clear();
+    }
+
+    /// Will a transaction with the given estimated fee replace a conflicting one in the same fork
+    /// with the given estimated fee?  The new fee must be strictly higher, and at least
+    /// rbf_bump_percent percent higher.
+    pub fn is_fee_bump_sufficient(&self, prior_fee: u64, new_fee: u64) -> bool {
+        let min_fee = (prior_fee as u128) * (100 + self.rbf_bump_percent as u128) / 100;
+        new_fee > prior_fee && (new_fee as u128) >= min_fee
+    }
+
     pub fn set_block(&mut self, cur_block: &BlockHeaderHash, cur_consensus_hash: ConsensusHash) {
         self.cur_consensus_hash = cur_consensus_hash.clone();
         self.cur_block = cur_block.clone();
@@ -83,7 +386,83 @@ impl MemPoolAdmitter {
         tx: &StacksTransaction,
         tx_size: u64,
     ) -> Result<(), MemPoolRejection> {
-        chainstate.will_admit_mempool_tx(&self.cur_consensus_hash, &self.cur_block, tx, tx_size)
+        self.policy.check_tx(tx, tx_size)?;
+        chainstate.will_admit_mempool_tx(
+            &self.cur_consensus_hash,
+            &self.cur_block,
+            tx,
+            tx_size,
+            self.admit_unconfirmed,
+        )?;
+        self.check_admission_predicate(chainstate, tx)
+    }
+
+    /// Ask the admission predicate, if there is one, whether or not to accept this transaction.
+    /// Its answers are cached until the chain tip changes.
+    fn check_admission_predicate(
+        &mut self,
+        chainstate: &mut StacksChainState,
+        tx: &StacksTransaction,
+    ) -> Result<(), MemPoolRejection> {
+        let predicate = match self.policy.admission_predicate {
+            Some(ref predicate) => predicate,
+            None => {
+                return Ok(());
+            }
+        };
+
+        let tip =
+            StacksBlockHeader::make_index_block_hash(&self.cur_consensus_hash, &self.cur_block);
+        if tip != self.predicate_cache_tip
+            || self.predicate_cache.len() >= MEMPOOL_PREDICATE_CACHE_SIZE
+        {
+            self.predicate_cache.clear();
+            self.predicate_cache_tip = tip.clone();
+        }
+
+        let key = MemPoolAdmissionPredicate::make_args(tx);
+        let approved = match self.predicate_cache.get(&key) {
+            Some(approved) => *approved,
+            None => {
+                let (ref origin, ref sponsor, ref contract) = key;
+                let approved = match predicate.evaluate(chainstate, &tip, origin, sponsor, contract)
+                {
+                    Some(Ok(approved)) => approved,
+                    Some(Err(e)) => {
+                        // not cached, since the failure may be transient
+                        debug!(
+                            "Failed to evaluate mempool admission predicate on {}: {:?}",
+                            &tx.txid(),
+                            &e
+                        );
+                        return Err(MemPoolRejection::AdmissionPredicateRejected(format!(
+                            "admission predicate {}::{} failed: {}",
+                            &predicate.contract_id,
+                            predicate.function_name.as_str(),
+                            &e
+                        )));
+                    }
+                    None => {
+                        return Err(MemPoolRejection::NoSuchChainTip(
+                            self.cur_consensus_hash.clone(),
+                            self.cur_block.clone(),
+                        ));
+                    }
+                };
+                self.predicate_cache.insert(key, approved);
+                approved
+            }
+        };
+
+        if approved {
+            Ok(())
+        } else {
+            Err(MemPoolRejection::AdmissionPredicateRejected(format!(
+                "rejected by admission predicate {}::{}",
+                &predicate.contract_id,
+                predicate.function_name.as_str()
+            )))
+        }
     }
 }
 
@@ -98,7 +477,8 @@ pub struct MemPoolTxMetadata {
     pub txid: Txid,
     pub len: u64,
     pub fee_rate: u64,
-    pub estimated_fee: u64, // upper bound on what the fee to pay will be
+    pub estimated_fee: u64,  // upper bound on what the fee to pay will be
+    pub estimated_cost: u64, // length, plus the byte-equivalent of the estimated execution cost
     pub consensus_hash: ConsensusHash,
     pub block_header_hash: BlockHeaderHash,
     pub block_height: u64,
@@ -115,6 +495,7 @@ impl FromRow<MemPoolTxMetadata> for MemPoolTxMetadata {
         let consensus_hash = ConsensusHash::from_column(row, "consensus_hash")?;
         let block_header_hash = BlockHeaderHash::from_column(row, "block_header_hash")?;
         let estimated_fee = u64::from_column(row, "estimated_fee")?;
+        let estimated_cost = u64::from_column(row, "estimated_cost")?;
         let fee_rate = u64::from_column(row, "fee_rate")?;
         let height = u64::from_column(row, "height")?;
         let len = u64::from_column(row, "length")?;
@@ -127,6 +508,7 @@ impl FromRow<MemPoolTxMetadata> for MemPoolTxMetadata {
         Ok(MemPoolTxMetadata {
             txid: txid,
             estimated_fee: estimated_fee,
+            estimated_cost: estimated_cost,
             fee_rate: fee_rate,
             len: len,
             consensus_hash: consensus_hash,
@@ -159,6 +541,231 @@ impl FromRow<MemPoolTxInfo> for MemPoolTxInfo {
     }
 }
 
+/// Suggested fee rates, in microSTX per byte of encoded transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeRateEstimate {
+    pub low: u64,
+    pub medium: u64,
+    pub high: u64,
+}
+
+impl FeeRateEstimate {
+    /// Scale these fee rates up to whole-transaction fees for a transaction of the given length
+    pub fn fees_for_len(&self, tx_len: u64) -> FeeRateEstimate {
+        FeeRateEstimate {
+            low: self.low.saturating_mul(tx_len),
+            medium: self.medium.saturating_mul(tx_len),
+            high: self.high.saturating_mul(tx_len),
+        }
+    }
+}
+
+/// Fee estimator.  Samples the fee rates paid by recently-mined transactions and by transactions
+/// still pending in the mempool, and tracks how full the recently-mined blocks were in each
+/// execution cost dimension (and in bytes).  If recent blocks had room to spare, the low estimate
+/// falls back to the minimum relay fee rate; otherwise, it tracks what recently got mined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeEstimator {
+    mined_fee_rates: Vec<u64>,
+    pending_fee_rates: Vec<u64>,
+    peak_cost: ExecutionCost,
+    peak_size: u64,
+}
+
+impl FeeEstimator {
+    pub fn new() -> FeeEstimator {
+        FeeEstimator {
+            mined_fee_rates: vec![],
+            pending_fee_rates: vec![],
+            peak_cost: ExecutionCost::zero(),
+            peak_size: 0,
+        }
+    }
+
+    /// Fee rate paid by a transaction with the given fee and length
+    fn fee_rate(fee: u64, len: u64) -> u64 {
+        cmp::max(fee / cmp::max(len, 1), MINIMUM_TX_FEE_RATE_PER_BYTE)
+    }
+
+    /// Find the value at the given percentile of a sorted list of samples
+    fn percentile(sorted: &[u64], pct: u64) -> Option<u64> {
+        if sorted.is_empty() {
+            return None;
+        }
+        let idx = ((sorted.len() - 1) as u64) * cmp::min(pct, 100) / 100;
+        Some(sorted[idx as usize])
+    }
+
+    /// Sample a mined anchored block, given its execution cost and encoded size.
+    /// The coinbase is not sampled, since it pays no fee.
+    pub fn add_mined_block(&mut self, block: &StacksBlock, cost: &ExecutionCost, block_size: u64) {
+        for tx in block.txs.iter() {
+            if let TransactionPayload::Coinbase(..) = tx.payload {
+                continue;
+            }
+            self.mined_fee_rates
+                .push(FeeEstimator::fee_rate(tx.get_fee_rate(), tx.tx_len()));
+        }
+
+        self.peak_cost.runtime = cmp::max(self.peak_cost.runtime, cost.runtime);
+        self.peak_cost.write_length = cmp::max(self.peak_cost.write_length, cost.write_length);
+        self.peak_cost.write_count = cmp::max(self.peak_cost.write_count, cost.write_count);
+        self.peak_cost.read_length = cmp::max(self.peak_cost.read_length, cost.read_length);
+        self.peak_cost.read_count = cmp::max(self.peak_cost.read_count, cost.read_count);
+        self.peak_size = cmp::max(self.peak_size, block_size);
+    }
+
+    /// Sample a pending mempool transaction, given its fee and encoded length
+    pub fn add_pending_tx(&mut self, fee: u64, len: u64) {
+        self.pending_fee_rates
+            .push(FeeEstimator::fee_rate(fee, len));
+    }
+
+    /// How full was the fullest recent block, as a percentage of whichever of its limits (i.e.
+    /// an execution cost dimension, or the epoch size) it came closest to?
+    pub fn congestion(&self, block_limit: &ExecutionCost) -> u64 {
+        let size_pct = self.peak_size.saturating_mul(100) / (MAX_EPOCH_SIZE as u64);
+        cmp::max(self.peak_cost.proportion_of_limit(block_limit), size_pct)
+    }
+
+    /// Suggest low, medium, and high fee rates.
+    /// * low: the minimum fee rate if recent blocks were not congested, or the 25th percentile of
+    ///   recently-mined fee rates if they were.
+    /// * medium: the median of recently-mined and pending fee rates.
+    /// * high: the 90th percentile of recently-mined and pending fee rates.
+    ///
+    /// Each estimate is at least as high as the one before it.
+    pub fn estimate(&self, block_limit: &ExecutionCost) -> FeeRateEstimate {
+        let mut mined = self.mined_fee_rates.clone();
+        mined.sort();
+
+        let mut all = self.mined_fee_rates.clone();
+        all.extend_from_slice(&self.pending_fee_rates);
+        all.sort();
+
+        let low = if self.congestion(block_limit) >= FEE_ESTIMATE_CONGESTION_PERCENT {
+            FeeEstimator::percentile(&mined, 25).unwrap_or(MINIMUM_TX_FEE_RATE_PER_BYTE)
+        } else {
+            MINIMUM_TX_FEE_RATE_PER_BYTE
+        };
+        let medium = cmp::max(low, FeeEstimator::percentile(&all, 50).unwrap_or(low));
+        let high = cmp::max(medium, FeeEstimator::percentile(&all, 90).unwrap_or(medium));
+
+        FeeRateEstimate { low, medium, high }
+    }
+}
+
+/// Priority of a mempool transaction when assembling a block: the fee it pays per unit of
+/// estimated cost, i.e. its length plus the byte-equivalent of its estimated execution cost.
+/// Ties go to the transaction that arrived first.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct MemPoolFeeRateKey {
+    fee: u64,
+    cost: u64,
+    accept_time: u64,
+    cursor: usize,
+}
+
+impl MemPoolFeeRateKey {
+    fn new(metadata: &MemPoolTxMetadata, cursor: usize) -> MemPoolFeeRateKey {
+        MemPoolFeeRateKey {
+            fee: metadata.fee_rate,
+            cost: cmp::max(metadata.estimated_cost, 1),
+            accept_time: metadata.accept_time,
+            cursor: cursor,
+        }
+    }
+}
+
+impl Ord for MemPoolFeeRateKey {
+    fn cmp(&self, other: &MemPoolFeeRateKey) -> cmp::Ordering {
+        // compare fee/cost without losing precision
+        ((self.fee as u128) * (other.cost as u128))
+            .cmp(&((other.fee as u128) * (self.cost as u128)))
+            .then_with(|| other.accept_time.cmp(&self.accept_time))
+            .then_with(|| other.cursor.cmp(&self.cursor))
+    }
+}
+
+impl PartialOrd for MemPoolFeeRateKey {
+    fn partial_cmp(&self, other: &MemPoolFeeRateKey) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Where a mempool transaction falls in the order in which iterate_candidates hands transactions
+/// to the miner.  Greater keys come first.
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord)]
+enum MemPoolCandidateKey {
+    FeeRate(MemPoolFeeRateKey),
+    /// origin address, origin nonce, txid, cursor -- least first
+    Deterministic(cmp::Reverse<(String, u64, String, usize)>),
+}
+
+impl MemPoolCandidateKey {
+    fn new(
+        ordering: MemPoolTxOrdering,
+        metadata: &MemPoolTxMetadata,
+        cursor: usize,
+    ) -> MemPoolCandidateKey {
+        match ordering {
+            MemPoolTxOrdering::FeeRate => {
+                MemPoolCandidateKey::FeeRate(MemPoolFeeRateKey::new(metadata, cursor))
+            }
+            MemPoolTxOrdering::Deterministic => MemPoolCandidateKey::Deterministic(cmp::Reverse((
+                metadata.origin_address.to_string(),
+                metadata.origin_nonce,
+                metadata.txid.to_hex(),
+                cursor,
+            ))),
+        }
+    }
+}
+
+/// A page-at-a-time cursor over the transactions at one chain tip, in the order in which
+/// iterate_candidates considers them
+struct MemPoolTipCursor {
+    consensus_hash: ConsensusHash,
+    block_header_hash: BlockHeaderHash,
+    offset: u64,
+    page: VecDeque<MemPoolTxInfo>,
+    exhausted: bool,
+}
+
+impl MemPoolTipCursor {
+    fn new(consensus_hash: ConsensusHash, block_header_hash: BlockHeaderHash) -> MemPoolTipCursor {
+        MemPoolTipCursor {
+            consensus_hash,
+            block_header_hash,
+            offset: 0,
+            page: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    /// Get the next transaction at this chain tip, reading the next page if need be
+    fn next(
+        &mut self,
+        conn: &DBConn,
+        ordering: MemPoolTxOrdering,
+    ) -> Result<Option<MemPoolTxInfo>, db_error> {
+        if self.page.is_empty() && !self.exhausted {
+            let page = MemPoolDB::get_txs_page(
+                conn,
+                ordering,
+                &self.consensus_hash,
+                &self.block_header_hash,
+                self.offset,
+                MEMPOOL_CANDIDATE_PAGE_SIZE,
+            )?;
+            self.offset += page.len() as u64;
+            self.exhausted = (page.len() as u64) < MEMPOOL_CANDIDATE_PAGE_SIZE;
+            self.page.extend(page);
+        }
+        Ok(self.page.pop_front())
+    }
+}
+
 const MEMPOOL_SQL: &'static [&'static str] = &[
     r#"
     CREATE TABLE mempool(
@@ -190,10 +797,46 @@ const MEMPOOL_SQL: &'static [&'static str] = &[
     "#,
 ];
 
+const MEMPOOL_SCHEMA_2: &'static [&'static str] = &[
+    r#"
+    -- length, plus the byte-equivalent of the estimated execution cost
+    ALTER TABLE mempool ADD COLUMN estimated_cost INTEGER NOT NULL DEFAULT 1;
+    "#,
+    r#"
+    UPDATE mempool SET estimated_cost = MAX(length, 1);
+    "#,
+    r#"
+    DROP INDEX IF EXISTS by_fee_per_byte;
+    "#,
+    r#"
+    CREATE INDEX by_fee_per_cost ON mempool(consensus_hash, block_header_hash, (fee_rate * 1.0 / estimated_cost) DESC, accept_time ASC, txid ASC);
+    "#,
+    r#"
+    -- execution costs that mined transactions were seen to incur, by what they ran
+    CREATE TABLE cost_estimates(
+        estimate_key TEXT NOT NULL,
+        cost INTEGER NOT NULL,      -- byte-equivalent of the execution cost
+        PRIMARY KEY (estimate_key)
+    );
+    "#,
+    r#"
+    CREATE TABLE schema_version(
+        version INTEGER NOT NULL
+    );
+    "#,
+    r#"
+    INSERT INTO schema_version (version) VALUES (2);
+    "#,
+];
+
 pub struct MemPoolDB {
     db: DBConn,
     path: String,
     admitter: MemPoolAdmitter,
+    gc_policy: MemPoolGCPolicy,
+    /// when we last garbage-collected
+    last_gc: u64,
+    tx_ordering: MemPoolTxOrdering,
 }
 
 pub struct MemPoolTx<'a> {
@@ -279,6 +922,7 @@ impl MemPoolTxInfo {
             len: tx_data.len() as u64,
             fee_rate: tx.get_fee_rate(),
             estimated_fee: estimated_fee,
+            estimated_cost: cmp::max(tx_data.len() as u64, 1),
             consensus_hash: consensus_hash,
             block_header_hash: block_header_hash,
             block_height: block_height,
@@ -307,6 +951,34 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Get the version of the mempool DB's schema.  DBs from before the schema was versioned
+    /// are at version 1.
+    fn get_schema_version(conn: &DBConn) -> Result<i64, db_error> {
+        let sql =
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'";
+        if query_count(conn, sql, NO_PARAMS)? == 0 {
+            return Ok(1);
+        }
+        let version: Option<i64> =
+            query_row(conn, "SELECT MAX(version) FROM schema_version", NO_PARAMS)?;
+        Ok(version.unwrap_or(1))
+    }
+
+    /// Bring the mempool DB's schema up to MEMPOOL_SCHEMA_VERSION
+    fn migrate_mempool_db(conn: &mut DBConn) -> Result<(), db_error> {
+        let tx = tx_begin_immediate(conn)?;
+
+        if MemPoolDB::get_schema_version(&tx)? < 2 {
+            info!("Migrate mempool DB to schema version 2");
+            for cmd in MEMPOOL_SCHEMA_2 {
+                tx.execute(cmd, NO_PARAMS).map_err(db_error::SqliteError)?;
+            }
+        }
+
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
     /// Open the mempool db within the chainstate directory.
     /// The chainstate must be instantiated already.
     pub fn open(
@@ -357,11 +1029,15 @@ impl MemPoolDB {
             // instantiate!
             MemPoolDB::instantiate_mempool_db(&mut conn)?;
         }
+        MemPoolDB::migrate_mempool_db(&mut conn)?;
 
         Ok(MemPoolDB {
             db: conn,
             path: db_path.to_string(),
             admitter: admitter,
+            gc_policy: MemPoolGCPolicy::default(),
+            last_gc: 0,
+            tx_ordering: MemPoolTxOrdering::default(),
         })
     }
 
@@ -371,7 +1047,7 @@ impl MemPoolDB {
         tip_consensus_hash: &ConsensusHash,
         tip_block_hash: &BlockHeaderHash,
         tip_height: u64,
-    ) -> Result<Option<(ConsensusHash, BlockHeaderHash, u64, u64)>, ChainstateError> {
+    ) -> Result<Option<(ConsensusHash, BlockHeaderHash, u64)>, ChainstateError> {
         // Walk back to the next-highest
         // ancestor of this tip, and see if we can include anything from there.
         let next_height = MemPoolDB::get_previous_block_height(&self.db, tip_height)?.unwrap_or(0);
@@ -440,37 +1116,65 @@ impl MemPoolDB {
             return Ok(None);
         }
 
-        let next_timestamp = match MemPoolDB::get_next_timestamp(
-            &self.db,
-            &next_tip_consensus_hash,
-            &next_tip_block_hash,
-            0,
-        )? {
-            Some(ts) => ts,
-            None => {
-                unreachable!("No transactions at a chain tip that exists");
-            }
-        };
-
         debug!(
-            "Will start scaning mempool at {}/{} height={} ts={}",
-            &next_tip_consensus_hash, &next_tip_block_hash, next_height, next_timestamp
+            "Will continue scanning mempool at {}/{} height={}",
+            &next_tip_consensus_hash, &next_tip_block_hash, next_height
         );
         Ok(Some((
             next_tip_consensus_hash,
             next_tip_block_hash,
             next_height,
-            next_timestamp,
         )))
     }
 
+    /// Find a transaction in this fork that `txinfo` must come after, but that
+    /// iterate_candidates has not handed out yet: one that uses the previous nonce of its origin
+    /// account or of its sponsor account.  An account's previous nonce may have been used by a
+    /// transaction it originated or by one it sponsored.
+    fn find_pending_predecessor(
+        conn: &DBConn,
+        txinfo: &MemPoolTxInfo,
+        fork_tips: &HashSet<(ConsensusHash, BlockHeaderHash)>,
+        considered: &HashSet<Txid>,
+    ) -> Result<Option<Txid>, db_error> {
+        let metadata = &txinfo.metadata;
+        let mut predecessors = vec![];
+        if metadata.origin_nonce > 0 {
+            predecessors.push((&metadata.origin_address, metadata.origin_nonce - 1));
+        }
+        if metadata.sponsor_nonce > 0
+            && (metadata.sponsor_address != metadata.origin_address
+                || metadata.sponsor_nonce != metadata.origin_nonce)
+        {
+            predecessors.push((&metadata.sponsor_address, metadata.sponsor_nonce - 1));
+        }
+
+        let sql = "SELECT * FROM mempool WHERE (origin_address = ?1 AND origin_nonce = ?2) OR (sponsor_address = ?1 AND sponsor_nonce = ?2)";
+        for (address, nonce) in predecessors.into_iter() {
+            let args: &[&dyn ToSql] = &[&address.to_string(), &u64_to_sql(nonce)?];
+            for predecessor in query_rows::<MemPoolTxMetadata, _>(conn, sql, args)?.into_iter() {
+                if predecessor.txid != metadata.txid
+                    && !considered.contains(&predecessor.txid)
+                    && fork_tips.contains(&(
+                        predecessor.consensus_hash.clone(),
+                        predecessor.block_header_hash.clone(),
+                    ))
+                {
+                    return Ok(Some(predecessor.txid));
+                }
+            }
+        }
+        Ok(None)
+    }
+
     ///
     /// Iterate over candidates in the mempool
-    ///  todo will be called once for each bundle of transactions at
-    ///  each ancestor chain tip from the given one, starting with the
-    ///  most recent chain tip and working backwards until there are
-    ///  no more transactions to consider. Each batch of transactions
-    ///  passed to todo will be sorted in nonce order.
+    ///  Streams the transactions at the given chain tip and at each of its
+    ///  ancestor chain tips to todo, a page at a time, ordered by fee paid per
+    ///  unit of estimated cost (highest first).  A transaction is held back
+    ///  until the transaction that uses the previous nonce of its origin or of
+    ///  its sponsor, if that is also a candidate, has been handed out.  With
+    ///  MemPoolTxOrdering::Deterministic, they are ordered by origin instead.
     pub fn iterate_candidates<F, E>(
         &self,
         tip_consensus_hash: &ConsensusHash,
@@ -483,95 +1187,142 @@ impl MemPoolDB {
         F: FnMut(Vec<MemPoolTxInfo>) -> Result<(), E>,
         E: From<db_error> + From<ChainstateError>,
     {
+        debug!(
+            "Begin scanning transaction mempool at {}/{} height={}",
+            tip_consensus_hash, tip_block_hash, tip_height
+        );
+
+        // this tip and its ancestors that have transactions
+        let mut cursors = vec![MemPoolTipCursor::new(
+            tip_consensus_hash.clone(),
+            tip_block_hash.clone(),
+        )];
+        let mut fork_tips = HashSet::new();
+        fork_tips.insert((tip_consensus_hash.clone(), tip_block_hash.clone()));
         let (mut tip_consensus_hash, mut tip_block_hash, mut tip_height) = (
             tip_consensus_hash.clone(),
             tip_block_hash.clone(),
             tip_height,
         );
+        while let Some((next_consensus_hash, next_block_bhh, next_height)) =
+            self.walk(chainstate, &tip_consensus_hash, &tip_block_hash, tip_height)?
+        {
+            cursors.push(MemPoolTipCursor::new(
+                next_consensus_hash.clone(),
+                next_block_bhh.clone(),
+            ));
+            fork_tips.insert((next_consensus_hash.clone(), next_block_bhh.clone()));
+            tip_consensus_hash = next_consensus_hash;
+            tip_block_hash = next_block_bhh;
+            tip_height = next_height;
+        }
 
-        debug!(
-            "Begin scanning transaction mempool at {}/{} height={}",
-            &tip_consensus_hash, &tip_block_hash, tip_height
-        );
+        // merge the tips' transactions, best first
+        let ordering = self.tx_ordering;
+        let mut heads = Vec::with_capacity(cursors.len());
+        let mut heap = BinaryHeap::new();
+        for (idx, cursor) in cursors.iter_mut().enumerate() {
+            let head = cursor.next(&self.db, ordering)?;
+            if let Some(ref txinfo) = head {
+                heap.push(MemPoolCandidateKey::new(ordering, &txinfo.metadata, idx));
+            }
+            heads.push(head);
+        }
 
-        let mut next_timestamp =
-            match MemPoolDB::get_next_timestamp(&self.db, &tip_consensus_hash, &tip_block_hash, 0)?
-            {
-                Some(ts) => ts,
-                None => {
-                    // walk back to where the first transaction we can mine can be found
-                    match self.walk(chainstate, &tip_consensus_hash, &tip_block_hash, tip_height)? {
-                        Some((
-                            next_consensus_hash,
-                            next_block_bhh,
-                            next_height,
-                            next_timestamp,
-                        )) => {
-                            tip_consensus_hash = next_consensus_hash;
-                            tip_block_hash = next_block_bhh;
-                            tip_height = next_height;
-                            next_timestamp
-                        }
-                        None => {
-                            return Ok(());
-                        }
-                    }
-                }
+        let mut considered = HashSet::new();
+        let mut deferred: HashMap<Txid, Vec<(MemPoolCandidateKey, MemPoolTxInfo)>> = HashMap::new();
+        let mut batch = vec![];
+        while let Some(key) = heap.pop() {
+            let idx = match key {
+                MemPoolCandidateKey::FeeRate(ref key) => key.cursor,
+                MemPoolCandidateKey::Deterministic(cmp::Reverse((_, _, _, cursor))) => cursor,
             };
+            let txinfo = heads[idx]
+                .take()
+                .expect("BUG: candidate key for an empty cursor");
+            heads[idx] = cursors[idx].next(&self.db, ordering)?;
+            if let Some(ref next_txinfo) = heads[idx] {
+                heap.push(MemPoolCandidateKey::new(
+                    ordering,
+                    &next_txinfo.metadata,
+                    idx,
+                ));
+            }
 
-        loop {
-            let available_txs = MemPoolDB::get_txs_at(
-                &self.db,
-                &tip_consensus_hash,
-                &tip_block_hash,
-                next_timestamp,
-            )?;
-
-            debug!(
-                "Have {} transactions at {}/{} height={} at or after {}",
-                available_txs.len(),
-                &tip_consensus_hash,
-                &tip_block_hash,
-                tip_height,
-                next_timestamp
-            );
+            // hand out this transaction, and whichever of the ones waiting on it are now ready
+            let mut ready = vec![(key, txinfo)];
+            while let Some((key, txinfo)) = ready.pop() {
+                if let Some(predecessor) =
+                    MemPoolDB::find_pending_predecessor(&self.db, &txinfo, &fork_tips, &considered)?
+                {
+                    deferred
+                        .entry(predecessor)
+                        .or_insert_with(Vec::new)
+                        .push((key, txinfo));
+                    continue;
+                }
 
-            todo(available_txs)?;
-            next_timestamp = match MemPoolDB::get_next_timestamp(
-                &self.db,
-                &tip_consensus_hash,
-                &tip_block_hash,
-                next_timestamp,
-            )? {
-                Some(ts) => ts,
-                None => {
-                    // walk back
-                    match self.walk(chainstate, &tip_consensus_hash, &tip_block_hash, tip_height)? {
-                        Some((
-                            next_consensus_hash,
-                            next_block_bhh,
-                            next_height,
-                            next_timestamp,
-                        )) => {
-                            tip_consensus_hash = next_consensus_hash;
-                            tip_block_hash = next_block_bhh;
-                            tip_height = next_height;
-                            next_timestamp
-                        }
-                        None => {
-                            // no more transactions
-                            return Ok(());
-                        }
-                    }
+                considered.insert(txinfo.metadata.txid.clone());
+                if let Some(mut waiting) = deferred.remove(&txinfo.metadata.txid) {
+                    // best last, so it's handed out first
+                    waiting.sort_by(|a, b| a.0.cmp(&b.0));
+                    ready.append(&mut waiting);
                 }
-            };
+                batch.push(txinfo);
+                if (batch.len() as u64) >= MEMPOOL_CANDIDATE_PAGE_SIZE {
+                    todo(mem::replace(&mut batch, vec![]))?;
+                }
+            }
+        }
+
+        // whatever is still waiting can't be ordered by nonce (e.g. transactions whose origin and
+        // sponsor nonces conflict), so let the miner try it anyway
+        let mut waiting: Vec<_> = deferred.drain().flat_map(|(_, txs)| txs).collect();
+        waiting.sort_by(|a, b| b.0.cmp(&a.0));
+        batch.extend(waiting.into_iter().map(|(_, txinfo)| txinfo));
+
+        debug!(
+            "Done scanning mempool: {} transactions at {} chain tips",
+            considered.len() + batch.len(),
+            cursors.len()
+        );
+        if !batch.is_empty() {
+            todo(batch)?;
         }
+        Ok(())
     }
 
     pub fn conn(&self) -> &DBConn {
         &self.db
     }
 
+    /// Set whether or not admission checks that fail against the confirmed chain tip will be
+    /// retried against the unconfirmed microblock state.
+    pub fn set_admit_unconfirmed(&mut self, admit_unconfirmed: bool) {
+        self.admitter.set_admit_unconfirmed(admit_unconfirmed);
+    }
+
+    /// Set the minimum percentage by which a transaction's fee must exceed that of a conflicting
+    /// transaction in the same fork in order to replace it.
+    pub fn set_rbf_bump_percent(&mut self, rbf_bump_percent: u64) {
+        self.admitter.set_rbf_bump_percent(rbf_bump_percent);
+    }
+
+    /// Set the node-local limits on which transactions this mempool will accept.
+    pub fn set_admission_policy(&mut self, policy: MemPoolAdmissionPolicy) {
+        self.admitter.set_admission_policy(policy);
+    }
+
+    /// Set how long transactions may stay in this mempool, and how much space they may use.
+    pub fn set_gc_policy(&mut self, gc_policy: MemPoolGCPolicy) {
+        self.gc_policy = gc_policy;
+    }
+
+    /// Set how iterate_candidates orders the transactions it hands to the miner.
+    pub fn set_tx_ordering(&mut self, tx_ordering: MemPoolTxOrdering) {
+        self.tx_ordering = tx_ordering;
+    }
+
     pub fn tx_begin<'a>(&'a mut self) -> Result<MemPoolTx<'a>, db_error> {
         let tx = tx_begin_immediate(&mut self.db)?;
         Ok(MemPoolTx::new(tx, &mut self.admitter))
@@ -636,14 +1387,137 @@ impl MemPoolDB {
         Ok(rows)
     }
 
-    /// Given a chain tip, find the highest block-height from _before_ this tip
-    pub fn get_previous_block_height(conn: &DBConn, height: u64) -> Result<Option<u64>, db_error> {
-        let sql = "SELECT height FROM mempool WHERE height < ?1 ORDER BY height DESC LIMIT 1";
-        let args: &[&dyn ToSql] = &[&u64_to_sql(height)?];
-        query_row(conn, sql, args)
+    /// Get up to `limit` transactions on a given chain tip, starting at `offset`, in the given
+    /// order: by fee paid per unit of estimated cost (highest first), or by origin.
+    pub fn get_txs_page(
+        conn: &DBConn,
+        ordering: MemPoolTxOrdering,
+        consensus_hash: &ConsensusHash,
+        block_header_hash: &BlockHeaderHash,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<MemPoolTxInfo>, db_error> {
+        let order_by = match ordering {
+            MemPoolTxOrdering::FeeRate => {
+                "(fee_rate * 1.0 / estimated_cost) DESC, accept_time ASC, txid ASC"
+            }
+            MemPoolTxOrdering::Deterministic => "origin_address ASC, origin_nonce ASC, txid ASC",
+        };
+        let sql = format!(
+            "SELECT * FROM mempool WHERE consensus_hash = ?1 AND block_header_hash = ?2 ORDER BY {} LIMIT ?3 OFFSET ?4",
+            order_by
+        );
+        let args: &[&dyn ToSql] = &[
+            consensus_hash,
+            block_header_hash,
+            &u64_to_sql(limit)?,
+            &u64_to_sql(offset)?,
+        ];
+        let rows = query_rows::<MemPoolTxInfo, _>(conn, &sql, args)?;
+        Ok(rows)
     }
 
-    /// Get chain tip(s) at a given height that have transactions
+    /// What a transaction's execution cost is estimated by: the contract function it calls, or
+    /// the fact that it is an STX transfer.  Other transactions cost about as much as their
+    /// length suggests, so they have no estimate.
+    fn get_cost_estimate_key(tx: &StacksTransaction) -> Option<String> {
+        match tx.payload {
+            TransactionPayload::TokenTransfer(..) => Some("stx-transfer".to_string()),
+            TransactionPayload::ContractCall(ref contract_call) => Some(format!(
+                "{}::{}",
+                contract_call.to_clarity_contract_id(),
+                contract_call.function_name.as_str()
+            )),
+            _ => None,
+        }
+    }
+
+    /// Express an execution cost in bytes: the share of the block limit that it uses in its most
+    /// constrained dimension, as a share of the maximum epoch size.
+    fn execution_cost_as_bytes(cost: &ExecutionCost, block_limit: &ExecutionCost) -> u64 {
+        [
+            (cost.write_length, block_limit.write_length),
+            (cost.write_count, block_limit.write_count),
+            (cost.read_length, block_limit.read_length),
+            (cost.read_count, block_limit.read_count),
+            (cost.runtime, block_limit.runtime),
+        ]
+        .iter()
+        .map(|(used, limit)| {
+            if *limit == 0 {
+                0
+            } else {
+                (*used as u128) * (MAX_EPOCH_SIZE as u128) / (*limit as u128)
+            }
+        })
+        .max()
+        .map(|bytes| cmp::min(bytes, u64::max_value() as u128) as u64)
+        .unwrap_or(0)
+    }
+
+    fn get_cost_estimate(conn: &DBConn, estimate_key: &str) -> Result<Option<u64>, db_error> {
+        query_row(
+            conn,
+            "SELECT cost FROM cost_estimates WHERE estimate_key = ?1",
+            &[&estimate_key as &dyn ToSql],
+        )
+    }
+
+    /// Estimate a transaction's cost: its length, plus the byte-equivalent of the execution cost
+    /// that transactions like it were last seen to incur.
+    fn estimate_tx_cost(conn: &DBConn, tx: &StacksTransaction, len: u64) -> Result<u64, db_error> {
+        let execution_cost = match MemPoolDB::get_cost_estimate_key(tx) {
+            Some(estimate_key) => MemPoolDB::get_cost_estimate(conn, &estimate_key)?.unwrap_or(0),
+            None => 0,
+        };
+        Ok(cmp::max(len, 1).saturating_add(execution_cost))
+    }
+
+    /// Record the execution cost that a transaction incurred when it was mined into a block with
+    /// the given limit, so that transactions like it that are admitted later are ranked by it.
+    /// Estimates are pessimistic: a cheaper run only moves the estimate halfway down.
+    pub fn record_tx_cost(
+        &self,
+        tx: &StacksTransaction,
+        cost: &ExecutionCost,
+        block_limit: &ExecutionCost,
+    ) -> Result<(), db_error> {
+        let estimate_key = match MemPoolDB::get_cost_estimate_key(tx) {
+            Some(estimate_key) => estimate_key,
+            None => {
+                return Ok(());
+            }
+        };
+        let observed = MemPoolDB::execution_cost_as_bytes(cost, block_limit);
+        let estimate = match MemPoolDB::get_cost_estimate(&self.db, &estimate_key)? {
+            Some(prior) if prior > observed => prior / 2 + observed / 2,
+            _ => observed,
+        };
+
+        let sql = "INSERT OR REPLACE INTO cost_estimates (estimate_key, cost) VALUES (?1, ?2)";
+        let args: &[&dyn ToSql] = &[&estimate_key, &u64_to_sql(estimate)?];
+        self.db.execute(sql, args).map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Get up to `limit` transactions across all chain tips, ordered by origin nonce and then by
+    /// arrival time, so that each origin's transactions can be applied in sequence.
+    pub fn get_pending_txs(conn: &DBConn, limit: u64) -> Result<Vec<MemPoolTxInfo>, db_error> {
+        let sql =
+            "SELECT * FROM mempool ORDER BY origin_nonce ASC, accept_time ASC, txid ASC LIMIT ?1";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(limit)?];
+        let rows = query_rows::<MemPoolTxInfo, _>(conn, sql, args)?;
+        Ok(rows)
+    }
+
+    /// Given a chain tip, find the highest block-height from _before_ this tip
+    pub fn get_previous_block_height(conn: &DBConn, height: u64) -> Result<Option<u64>, db_error> {
+        let sql = "SELECT height FROM mempool WHERE height < ?1 ORDER BY height DESC LIMIT 1";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(height)?];
+        query_row(conn, sql, args)
+    }
+
+    /// Get chain tip(s) at a given height that have transactions
     pub fn get_chain_tips_at_height(
         conn: &DBConn,
         height: u64,
@@ -714,7 +1588,8 @@ impl MemPoolDB {
                           consensus_hash,
                           block_header_hash,
                           height,
-                          accept_time
+                          accept_time,
+                          estimated_cost
                           FROM mempool WHERE {0}_address = ?1 AND {0}_nonce = ?2",
             if is_origin { "origin" } else { "sponsor" }
         );
@@ -747,8 +1622,33 @@ impl MemPoolDB {
         Ok(cmp::max(as_origin, as_sponsor))
     }
 
+    /// Get the distinct nonces that the given address uses in the mempool, either as an origin or
+    /// as a sponsor, in ascending order.
+    pub fn get_nonces_for_address(
+        conn: &DBConn,
+        address: &StacksAddress,
+    ) -> Result<Vec<u64>, db_error> {
+        let sql = "SELECT origin_nonce AS nonce FROM mempool WHERE origin_address = ?1 \
+                   UNION SELECT sponsor_nonce AS nonce FROM mempool WHERE sponsor_address = ?1 \
+                   ORDER BY nonce ASC";
+        let args: &[&dyn ToSql] = &[&address.to_string()];
+        query_rows(conn, sql, args)
+    }
+
+    /// Get the number of transactions the given origin address has in the mempool, other than the
+    /// one (if any) that uses the given nonce.
+    fn get_num_pending_txs_for_origin(
+        conn: &DBConn,
+        origin_address: &StacksAddress,
+        except_nonce: u64,
+    ) -> Result<u64, db_error> {
+        let sql = "SELECT COUNT(*) FROM mempool WHERE origin_address = ?1 AND origin_nonce != ?2";
+        let args: &[&dyn ToSql] = &[&origin_address.to_string(), &u64_to_sql(except_nonce)?];
+        query_count(conn, sql, args).map(|count| count as u64)
+    }
+
     /// Add a transaction to the mempool.  If it already exists, then replace it if the given fee
-    /// is higher than the one that's already there.
+    /// is higher than the one that's already there by at least the configured fee bump.
     /// Carry out the mempool admission test before adding.
     /// Don't call directly; use submit()
     fn try_add_tx<'a>(
@@ -760,6 +1660,7 @@ impl MemPoolDB {
         tx_bytes: Vec<u8>,
         estimated_fee: u64,
         fee_rate: u64,
+        estimated_cost: u64,
         height: u64,
         origin_address: &StacksAddress,
         origin_nonce: u64,
@@ -783,8 +1684,18 @@ impl MemPoolDB {
 
         // if so, is this a replace-by-fee? or a replace-in-chain-tip?
         let add_tx = if let Some(prior_tx) = prior_tx {
-            if estimated_fee > prior_tx.estimated_fee {
+            if tx
+                .admitter
+                .is_fee_bump_sufficient(prior_tx.estimated_fee, estimated_fee)
+            {
                 // is this a replace-by-fee ?
+                info!("TX replaces sponsor/origin nonce in mempool by fee";
+                      "new_txid" => %txid,
+                      "old_txid" => %prior_tx.txid,
+                      "origin_addr" => %origin_address,
+                      "origin_nonce" => origin_nonce,
+                      "new_fee" => estimated_fee,
+                      "old_fee" => prior_tx.estimated_fee);
                 true
             } else if !tx.is_block_in_fork(
                 chainstate,
@@ -796,8 +1707,8 @@ impl MemPoolDB {
                 // is this a replace-across-fork ?
                 true
             } else {
-                // there's a >= fee tx in this fork, cannot add
-                info!("TX conflicts with sponsor/origin nonce in same fork with >= fee";
+                // there's a tx in this fork whose fee we do not sufficiently exceed, cannot add
+                info!("TX conflicts with sponsor/origin nonce in same fork without sufficient fee bump";
                       "new_txid" => %txid, 
                       "old_txid" => %prior_tx.txid,
                       "origin_addr" => %origin_address,
@@ -830,8 +1741,9 @@ impl MemPoolDB {
             block_header_hash,
             height,
             accept_time,
-            tx)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)";
+            tx,
+            estimated_cost)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)";
 
         let args: &[&dyn ToSql] = &[
             &txid,
@@ -847,6 +1759,7 @@ impl MemPoolDB {
             &u64_to_sql(height)?,
             &u64_to_sql(get_epoch_time_secs())?,
             &tx_bytes,
+            &u64_to_sql(estimated_cost)?,
         ];
 
         tx.execute(sql, args)
@@ -864,6 +1777,106 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Evict the transactions with the lowest fee rates until the total length of the remaining
+    /// transactions is at most max_size bytes.  Returns the number of transactions evicted.
+    pub fn evict_to_size<'a>(tx: &mut MemPoolTx<'a>, max_size: u64) -> Result<u64, db_error> {
+        let (_, total_size) = MemPoolDB::get_total_size(tx)?;
+        if total_size <= max_size {
+            return Ok(0);
+        }
+
+        let mut evict = vec![];
+        {
+            let sql = "SELECT txid, length FROM mempool ORDER BY (fee_rate * 1.0 / length) ASC, accept_time DESC";
+            let mut stmt = tx.prepare(sql).map_err(db_error::SqliteError)?;
+            let mut rows = stmt.query(NO_PARAMS).map_err(db_error::SqliteError)?;
+            let mut size = total_size;
+            while size > max_size {
+                let row = match rows.next() {
+                    Some(row_res) => row_res.map_err(db_error::SqliteError)?,
+                    None => break,
+                };
+                let txid = Txid::from_column(&row, "txid")?;
+                let length = u64::from_column(&row, "length")?;
+                evict.push(txid);
+                size = size.saturating_sub(length);
+            }
+        }
+
+        for txid in evict.iter() {
+            tx.execute("DELETE FROM mempool WHERE txid = ?1", &[txid as &dyn ToSql])
+                .map_err(db_error::SqliteError)?;
+        }
+        Ok(evict.len() as u64)
+    }
+
+    /// Get the number of transactions in the mempool, and their total length.
+    pub fn get_total_size(conn: &DBConn) -> Result<(u64, u64), db_error> {
+        let sql = "SELECT COUNT(*), IFNULL(SUM(length), 0) FROM mempool";
+        let mut stmt = conn.prepare(sql).map_err(db_error::SqliteError)?;
+        let mut rows = stmt.query(NO_PARAMS).map_err(db_error::SqliteError)?;
+        match rows.next() {
+            Some(row_res) => {
+                let row = row_res.map_err(db_error::SqliteError)?;
+                let count: i64 = row.get(0);
+                let size: i64 = row.get(1);
+                Ok((count as u64, size as u64))
+            }
+            None => Ok((0, 0)),
+        }
+    }
+
+    /// Garbage-collect the mempool according to its GC policy, given the height of the Stacks
+    /// chain tip: drop transactions older than max_tx_age blocks, and then evict the lowest-fee
+    /// transactions until the mempool fits in max_size bytes.
+    pub fn prune(&mut self, chain_height: u64) -> Result<MemPoolGCResult, db_error> {
+        let gc_policy = self.gc_policy.clone();
+        let mut tx = self.tx_begin()?;
+
+        let (count_before, _) = MemPoolDB::get_total_size(&tx)?;
+        if chain_height > gc_policy.max_tx_age {
+            let min_height = chain_height - gc_policy.max_tx_age;
+            debug!(
+                "Remove all transactions beneath block height {}",
+                min_height
+            );
+            MemPoolDB::garbage_collect(&mut tx, min_height)?;
+        }
+        let (count_after_expiry, _) = MemPoolDB::get_total_size(&tx)?;
+
+        let evicted = match gc_policy.max_size {
+            Some(max_size) => MemPoolDB::evict_to_size(&mut tx, max_size)?,
+            None => 0,
+        };
+        let (remaining_txs, remaining_bytes) = MemPoolDB::get_total_size(&tx)?;
+        tx.commit()?;
+
+        self.last_gc = get_epoch_time_secs();
+        let result = MemPoolGCResult {
+            expired: count_before.saturating_sub(count_after_expiry),
+            evicted,
+            remaining_txs,
+            remaining_bytes,
+        };
+        if result.expired > 0 || result.evicted > 0 {
+            info!("Garbage-collected mempool";
+                  "expired" => result.expired,
+                  "evicted" => result.evicted,
+                  "remaining_txs" => result.remaining_txs,
+                  "remaining_bytes" => result.remaining_bytes);
+        }
+        Ok(result)
+    }
+
+    /// Garbage-collect the mempool if at least gc_interval seconds have passed since the last
+    /// time we did so.
+    pub fn prune_if_due(&mut self, chain_height: u64) -> Result<Option<MemPoolGCResult>, db_error> {
+        if self.last_gc + self.gc_policy.gc_interval > get_epoch_time_secs() {
+            return Ok(None);
+        }
+        self.prune(chain_height).map(Some)
+    }
+
     pub fn clear_before_height(&mut self, min_height: u64) -> Result<(), db_error> {
         let mut tx = self.tx_begin()?;
         MemPoolDB::garbage_collect(&mut tx, min_height)?;
@@ -955,12 +1968,27 @@ impl MemPoolDB {
         let estimated_fee = fee_rate
             .checked_mul(len)
             .ok_or(MemPoolRejection::Other("Fee numeric overflow".to_string()))?;
+        let estimated_cost = MemPoolDB::estimate_tx_cost(mempool_tx, &tx, len)?;
 
         if do_admission_checks {
             mempool_tx
                 .admitter
                 .set_block(&block_hash, (*consensus_hash).clone());
             mempool_tx.admitter.will_admit_tx(chainstate, &tx, len)?;
+
+            if let Some(max_pending) = mempool_tx.admitter.policy.max_pending_per_origin {
+                let num_pending = MemPoolDB::get_num_pending_txs_for_origin(
+                    mempool_tx,
+                    &origin_address,
+                    origin_nonce,
+                )?;
+                if num_pending >= max_pending {
+                    return Err(MemPoolRejection::TooManyPendingTransactions(
+                        origin_address.to_account_principal(),
+                        max_pending,
+                    ));
+                }
+            }
         }
 
         MemPoolDB::try_add_tx(
@@ -972,6 +2000,7 @@ impl MemPoolDB {
             tx_data,
             estimated_fee,
             fee_rate,
+            estimated_cost,
             height,
             &origin_address,
             origin_nonce,
@@ -1027,6 +2056,68 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Estimate fee rates for a new transaction at the given chain tip.  Samples the anchored
+    /// blocks in the last FEE_ESTIMATE_BLOCK_WINDOW blocks of this fork, and the transactions
+    /// pending in the mempool at this tip.
+    pub fn estimate_fee_rates(
+        &self,
+        chainstate: &StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+    ) -> Result<FeeRateEstimate, ChainstateError> {
+        let mut estimator = FeeEstimator::new();
+
+        let mut block_id = StacksBlockHeader::make_index_block_hash(consensus_hash, block_hash);
+        for _ in 0..FEE_ESTIMATE_BLOCK_WINDOW {
+            let header_info =
+                match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                    chainstate.db(),
+                    &block_id,
+                )? {
+                    Some(hinfo) => hinfo,
+                    None => break,
+                };
+            if header_info.block_height == 0 {
+                // boot block -- nothing was mined
+                break;
+            }
+
+            let cost =
+                StacksChainState::get_stacks_block_anchored_cost(chainstate.db(), &block_id)?
+                    .unwrap_or(ExecutionCost::zero());
+            if let Some(block) = StacksChainState::load_block(
+                &chainstate.blocks_path,
+                &header_info.consensus_hash,
+                &header_info.anchored_header.block_hash(),
+            )? {
+                estimator.add_mined_block(&block, &cost, header_info.anchored_block_size);
+            }
+
+            block_id = match StacksChainState::get_parent_block_id(chainstate.db(), &block_id)? {
+                Some(parent_id) => parent_id,
+                None => break,
+            };
+        }
+
+        let sql = "SELECT fee_rate,length FROM mempool WHERE consensus_hash = ?1 AND block_header_hash = ?2";
+        let args: &[&dyn ToSql] = &[consensus_hash, block_hash];
+        let mut stmt = self
+            .db
+            .prepare(sql)
+            .map_err(|e| ChainstateError::DBError(db_error::SqliteError(e)))?;
+        let mut rows = stmt
+            .query(args)
+            .map_err(|e| ChainstateError::DBError(db_error::SqliteError(e)))?;
+        while let Some(row_res) = rows.next() {
+            let row = row_res.map_err(|e| ChainstateError::DBError(db_error::SqliteError(e)))?;
+            let fee = u64::from_column(&row, "fee_rate")?;
+            let len = u64::from_column(&row, "length")?;
+            estimator.add_pending_tx(fee, len);
+        }
+
+        Ok(estimator.estimate(&chainstate.block_limit))
+    }
+
     /// Do we have a transaction?
     pub fn has_tx(&self, txid: &Txid) -> bool {
         match MemPoolDB::db_has_tx(self.conn(), txid) {
@@ -1071,8 +2162,20 @@ mod tests {
         C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
     };
 
-    use super::MemPoolDB;
-    use util::db::{DBConn, FromRow};
+    use super::{
+        FeeEstimator, FeeRateEstimate, MemPoolAdmissionPolicy, MemPoolAdmissionPredicate,
+        MemPoolAdmitter, MemPoolDB, MemPoolGCPolicy, MemPoolTxOrdering,
+        MEMPOOL_CANDIDATE_PAGE_SIZE, MEMPOOL_DEFAULT_PREDICATE_COST_LIMIT, MEMPOOL_SQL,
+    };
+    use rusqlite::types::ToSql;
+    use rusqlite::NO_PARAMS;
+    use std::path::PathBuf;
+    use util::db::{query_count, DBConn, FromRow};
+
+    use chainstate::stacks::db::blocks::MINIMUM_TX_FEE_RATE_PER_BYTE;
+    use chainstate::stacks::StacksBlock;
+    use chainstate::stacks::MAX_EPOCH_SIZE;
+    use vm::costs::ExecutionCost;
 
     use chainstate::burn::ConsensusHash;
     use chainstate::stacks::db::test::chainstate_path;
@@ -1216,6 +2319,7 @@ mod tests {
             tx_bytes,
             estimated_fee,
             tx.get_fee_rate(),
+            tx.tx_len(),
             height,
             &origin_address,
             origin_nonce,
@@ -1245,6 +2349,7 @@ mod tests {
             tx_bytes,
             estimated_fee,
             tx.get_fee_rate(),
+            tx.tx_len(),
             height,
             &origin_address,
             origin_nonce,
@@ -1319,6 +2424,7 @@ mod tests {
                 tx_bytes,
                 estimated_fee,
                 tx.get_fee_rate(),
+                tx.tx_len(),
                 height,
                 &origin_address,
                 origin_nonce,
@@ -1381,6 +2487,7 @@ mod tests {
                 tx_bytes,
                 estimated_fee,
                 tx.get_fee_rate(),
+                tx.tx_len(),
                 height,
                 &origin_address,
                 origin_nonce,
@@ -1446,6 +2553,7 @@ mod tests {
                 tx_bytes,
                 estimated_fee,
                 tx.get_fee_rate(),
+                tx.tx_len(),
                 height,
                 &origin_address,
                 origin_nonce,
@@ -1512,4 +2620,1241 @@ mod tests {
         .unwrap();
         assert_eq!(txs.len(), 0);
     }
+
+    #[test]
+    fn mempool_replace_by_fee_bump() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "mempool_replace_by_fee_bump");
+        let chainstate_path = chainstate_path("mempool_replace_by_fee_bump");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        // replacements must pay at least 10% more
+        mempool.set_rbf_bump_percent(10);
+
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let mut tx = txs.pop().unwrap();
+
+        let origin_address = StacksAddress {
+            version: 22,
+            bytes: Hash160::from_data(&[0; 32]),
+        };
+        let sponsor_address = StacksAddress {
+            version: 22,
+            bytes: Hash160::from_data(&[1; 32]),
+        };
+        let origin_nonce = tx.get_origin_nonce();
+        let sponsor_nonce = match tx.get_sponsor_nonce() {
+            Some(n) => n,
+            None => origin_nonce,
+        };
+        let height = 100;
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+
+        let mut try_add = |mempool_tx: &mut super::MemPoolTx, tx: &StacksTransaction| {
+            let tx_bytes = tx.serialize_to_vec();
+            let estimated_fee = tx.get_fee_rate() * (tx_bytes.len() as u64);
+            MemPoolDB::try_add_tx(
+                mempool_tx,
+                &mut chainstate,
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                tx.txid(),
+                tx_bytes,
+                estimated_fee,
+                tx.get_fee_rate(),
+                tx.tx_len(),
+                height,
+                &origin_address,
+                origin_nonce,
+                &sponsor_address,
+                sponsor_nonce,
+            )
+        };
+
+        tx.set_fee_rate(100);
+        let first_txid = tx.txid();
+        try_add(&mut mempool_tx, &tx).unwrap();
+        assert!(MemPoolDB::db_has_tx(&mempool_tx, &first_txid).unwrap());
+
+        // a higher fee that does not clear the bump is rejected
+        tx.set_fee_rate(109);
+        let small_bump_txid = tx.txid();
+        match try_add(&mut mempool_tx, &tx).unwrap_err() {
+            MemPoolRejection::ConflictingNonceInMempool => {}
+            e => panic!("Unexpected rejection: {:?}", &e),
+        }
+        assert!(MemPoolDB::db_has_tx(&mempool_tx, &first_txid).unwrap());
+        assert!(!MemPoolDB::db_has_tx(&mempool_tx, &small_bump_txid).unwrap());
+
+        // a fee that clears the bump replaces the prior transaction
+        tx.set_fee_rate(110);
+        let replacement_txid = tx.txid();
+        try_add(&mut mempool_tx, &tx).unwrap();
+        assert!(!MemPoolDB::db_has_tx(&mempool_tx, &first_txid).unwrap());
+        assert!(MemPoolDB::db_has_tx(&mempool_tx, &replacement_txid).unwrap());
+
+        let tx_info =
+            MemPoolDB::get_tx_metadata_by_address(&mempool_tx, true, &origin_address, origin_nonce)
+                .unwrap()
+                .unwrap();
+        assert_eq!(tx_info.txid, replacement_txid);
+        assert_eq!(tx_info.fee_rate, 110);
+
+        mempool_tx.commit().unwrap();
+        assert_eq!(MemPoolDB::get_all_txs(mempool.conn()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn mempool_iterate_candidates_by_fee_rate() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "mempool_iterate_candidates_by_fee_rate");
+        let chainstate_path = chainstate_path("mempool_iterate_candidates_by_fee_rate");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let mut tx = txs.pop().unwrap();
+
+        let consensus_hash = ConsensusHash([0x1; 20]);
+        let block_hash = BlockHeaderHash([0x2; 32]);
+        let height = 1;
+
+        // (origin, nonce, fee)
+        let candidates = vec![
+            (0x0a, 0, 100),
+            (0x0a, 1, 400),
+            (0x0b, 0, 200),
+            (0x0c, 0, 300),
+            (0x0d, 0, 50),
+        ];
+
+        let mut txids = vec![];
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (origin, nonce, fee) in candidates.into_iter() {
+            let origin_address = StacksAddress {
+                version: 22,
+                bytes: Hash160([origin; 20]),
+            };
+            tx.set_fee_rate(fee);
+            let tx_bytes = tx.serialize_to_vec();
+            let estimated_fee = tx.get_fee_rate() * (tx_bytes.len() as u64);
+            txids.push(tx.txid());
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &consensus_hash,
+                &block_hash,
+                tx.txid(),
+                tx_bytes,
+                estimated_fee,
+                tx.get_fee_rate(),
+                tx.tx_len(),
+                height,
+                &origin_address,
+                nonce,
+                &origin_address,
+                nonce,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let mut considered = vec![];
+        mempool
+            .iterate_candidates::<_, ChainstateError>(
+                &consensus_hash,
+                &block_hash,
+                height,
+                &mut chainstate,
+                |available_txs| {
+                    for txinfo in available_txs.into_iter() {
+                        considered.push(txinfo.metadata.txid);
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        // best-paying first, but 0x0a's second transaction waits for its first
+        assert_eq!(
+            considered,
+            vec![
+                txids[3].clone(),
+                txids[2].clone(),
+                txids[0].clone(),
+                txids[1].clone(),
+                txids[4].clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn mempool_iterate_candidates_deterministically() {
+        let mut chainstate = instantiate_chainstate(
+            false,
+            0x80000000,
+            "mempool_iterate_candidates_deterministically",
+        );
+        let chainstate_path = chainstate_path("mempool_iterate_candidates_deterministically");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+        mempool.set_tx_ordering(MemPoolTxOrdering::Deterministic);
+
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let mut tx = txs.pop().unwrap();
+
+        let consensus_hash = ConsensusHash([0x1; 20]);
+        let block_hash = BlockHeaderHash([0x2; 32]);
+        let height = 1;
+
+        // (origin, nonce, fee)
+        let candidates = vec![
+            (0x0c, 0, 300),
+            (0x0a, 1, 400),
+            (0x0b, 0, 200),
+            (0x0a, 0, 100),
+            (0x0d, 0, 50),
+        ];
+
+        let mut txids = vec![];
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (origin, nonce, fee) in candidates.into_iter() {
+            let origin_address = StacksAddress {
+                version: 22,
+                bytes: Hash160([origin; 20]),
+            };
+            tx.set_fee_rate(fee);
+            let tx_bytes = tx.serialize_to_vec();
+            let estimated_fee = tx.get_fee_rate() * (tx_bytes.len() as u64);
+            txids.push(tx.txid());
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &consensus_hash,
+                &block_hash,
+                tx.txid(),
+                tx_bytes,
+                estimated_fee,
+                tx.get_fee_rate(),
+                tx.tx_len(),
+                height,
+                &origin_address,
+                nonce,
+                &origin_address,
+                nonce,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let mut considered = vec![];
+        mempool
+            .iterate_candidates::<_, ChainstateError>(
+                &consensus_hash,
+                &block_hash,
+                height,
+                &mut chainstate,
+                |available_txs| {
+                    for txinfo in available_txs.into_iter() {
+                        considered.push(txinfo.metadata.txid);
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        // by origin and then nonce, whatever the fees
+        assert_eq!(
+            considered,
+            vec![
+                txids[3].clone(),
+                txids[1].clone(),
+                txids[2].clone(),
+                txids[0].clone(),
+                txids[4].clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn mempool_iterate_candidates_by_estimated_cost() {
+        let mut chainstate = instantiate_chainstate(
+            false,
+            0x80000000,
+            "mempool_iterate_candidates_by_estimated_cost",
+        );
+        let chainstate_path = chainstate_path("mempool_iterate_candidates_by_estimated_cost");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let mut tx = txs.pop().unwrap();
+
+        let consensus_hash = ConsensusHash([0x1; 20]);
+        let block_hash = BlockHeaderHash([0x2; 32]);
+        let height = 1;
+
+        // (origin, fee, estimated cost as a multiple of the length)
+        let candidates = vec![(0x0a, 200, 4), (0x0b, 100, 1), (0x0c, 300, 2)];
+
+        let mut txids = vec![];
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (origin, fee, cost_multiple) in candidates.into_iter() {
+            let origin_address = StacksAddress {
+                version: 22,
+                bytes: Hash160([origin; 20]),
+            };
+            tx.set_fee_rate(fee);
+            let tx_bytes = tx.serialize_to_vec();
+            let estimated_fee = tx.get_fee_rate() * (tx_bytes.len() as u64);
+            txids.push(tx.txid());
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &consensus_hash,
+                &block_hash,
+                tx.txid(),
+                tx_bytes,
+                estimated_fee,
+                tx.get_fee_rate(),
+                tx.tx_len() * cost_multiple,
+                height,
+                &origin_address,
+                0,
+                &origin_address,
+                0,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let mut considered = vec![];
+        mempool
+            .iterate_candidates::<_, ChainstateError>(
+                &consensus_hash,
+                &block_hash,
+                height,
+                &mut chainstate,
+                |available_txs| {
+                    for txinfo in available_txs.into_iter() {
+                        considered.push(txinfo.metadata.txid);
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        // by fee per unit of estimated cost, not per byte
+        assert_eq!(
+            considered,
+            vec![txids[2].clone(), txids[1].clone(), txids[0].clone()]
+        );
+    }
+
+    #[test]
+    fn mempool_iterate_candidates_waits_for_sponsor_nonce() {
+        let mut chainstate = instantiate_chainstate(
+            false,
+            0x80000000,
+            "mempool_iterate_candidates_waits_for_sponsor_nonce",
+        );
+        let chainstate_path = chainstate_path("mempool_iterate_candidates_waits_for_sponsor_nonce");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let mut tx = txs.pop().unwrap();
+
+        let consensus_hash = ConsensusHash([0x1; 20]);
+        let block_hash = BlockHeaderHash([0x2; 32]);
+        let height = 1;
+
+        // (origin, origin nonce, sponsor, sponsor nonce, fee)
+        let candidates = vec![
+            (0x0a, 0, 0x0e, 0, 100),
+            (0x0b, 0, 0x0e, 1, 400),
+            (0x0c, 0, 0x0c, 0, 200),
+            (0x0e, 2, 0x0e, 2, 300),
+        ];
+
+        let mut txids = vec![];
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (origin, origin_nonce, sponsor, sponsor_nonce, fee) in candidates.into_iter() {
+            let origin_address = StacksAddress {
+                version: 22,
+                bytes: Hash160([origin; 20]),
+            };
+            let sponsor_address = StacksAddress {
+                version: 22,
+                bytes: Hash160([sponsor; 20]),
+            };
+            tx.set_fee_rate(fee);
+            let tx_bytes = tx.serialize_to_vec();
+            let estimated_fee = tx.get_fee_rate() * (tx_bytes.len() as u64);
+            txids.push(tx.txid());
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &consensus_hash,
+                &block_hash,
+                tx.txid(),
+                tx_bytes,
+                estimated_fee,
+                tx.get_fee_rate(),
+                tx.tx_len(),
+                height,
+                &origin_address,
+                origin_nonce,
+                &sponsor_address,
+                sponsor_nonce,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let mut considered = vec![];
+        mempool
+            .iterate_candidates::<_, ChainstateError>(
+                &consensus_hash,
+                &block_hash,
+                height,
+                &mut chainstate,
+                |available_txs| {
+                    for txinfo in available_txs.into_iter() {
+                        considered.push(txinfo.metadata.txid);
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        // 0x0b's transaction waits for the one 0x0e sponsored first, and 0x0e's own transaction
+        // waits for the one it sponsored second
+        assert_eq!(
+            considered,
+            vec![
+                txids[2].clone(),
+                txids[0].clone(),
+                txids[1].clone(),
+                txids[3].clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn mempool_iterate_candidates_in_pages() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "mempool_iterate_candidates_in_pages");
+        let chainstate_path = chainstate_path("mempool_iterate_candidates_in_pages");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let mut tx = txs.pop().unwrap();
+
+        let consensus_hash = ConsensusHash([0x1; 20]);
+        let block_hash = BlockHeaderHash([0x2; 32]);
+        let height = 1;
+        let num_txs = MEMPOOL_CANDIDATE_PAGE_SIZE * 2 + MEMPOOL_CANDIDATE_PAGE_SIZE / 2;
+
+        let mut txids = vec![];
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for i in 0..num_txs {
+            let mut origin_bytes = [0u8; 20];
+            origin_bytes[0..8].copy_from_slice(&i.to_be_bytes());
+            let origin_address = StacksAddress {
+                version: 22,
+                bytes: Hash160(origin_bytes),
+            };
+            tx.set_fee_rate(100 + i);
+            let tx_bytes = tx.serialize_to_vec();
+            let estimated_fee = tx.get_fee_rate() * (tx_bytes.len() as u64);
+            txids.push(tx.txid());
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &consensus_hash,
+                &block_hash,
+                tx.txid(),
+                tx_bytes,
+                estimated_fee,
+                tx.get_fee_rate(),
+                tx.tx_len(),
+                height,
+                &origin_address,
+                0,
+                &origin_address,
+                0,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let mut batch_sizes = vec![];
+        let mut considered = vec![];
+        mempool
+            .iterate_candidates::<_, ChainstateError>(
+                &consensus_hash,
+                &block_hash,
+                height,
+                &mut chainstate,
+                |available_txs| {
+                    batch_sizes.push(available_txs.len() as u64);
+                    for txinfo in available_txs.into_iter() {
+                        considered.push(txinfo.metadata.txid);
+                    }
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        txids.reverse();
+        assert_eq!(considered, txids);
+        assert_eq!(
+            batch_sizes,
+            vec![
+                MEMPOOL_CANDIDATE_PAGE_SIZE,
+                MEMPOOL_CANDIDATE_PAGE_SIZE,
+                MEMPOOL_CANDIDATE_PAGE_SIZE / 2
+            ]
+        );
+    }
+
+    #[test]
+    fn mempool_record_tx_cost() {
+        let _chainstate = instantiate_chainstate(false, 0x80000000, "mempool_record_tx_cost");
+        let chainstate_path = chainstate_path("mempool_record_tx_cost");
+        let mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let contract_call = txs
+            .iter()
+            .find(|tx| match tx.payload {
+                TransactionPayload::ContractCall(..) => true,
+                _ => false,
+            })
+            .unwrap();
+        let smart_contract = txs
+            .iter()
+            .find(|tx| match tx.payload {
+                TransactionPayload::SmartContract(..) => true,
+                _ => false,
+            })
+            .unwrap();
+
+        let block_limit = ExecutionCost {
+            write_length: 1000,
+            write_count: 1000,
+            read_length: 1000,
+            read_count: 1000,
+            runtime: 1000,
+        };
+        let half_the_runtime = ExecutionCost {
+            write_length: 10,
+            write_count: 10,
+            read_length: 10,
+            read_count: 10,
+            runtime: 500,
+        };
+        let len = contract_call.tx_len();
+
+        // nothing is known about it yet
+        assert_eq!(
+            MemPoolDB::estimate_tx_cost(mempool.conn(), contract_call, len).unwrap(),
+            len
+        );
+
+        // costs are in terms of the most constrained dimension
+        mempool
+            .record_tx_cost(contract_call, &half_the_runtime, &block_limit)
+            .unwrap();
+        assert_eq!(
+            MemPoolDB::estimate_tx_cost(mempool.conn(), contract_call, len).unwrap(),
+            len + (MAX_EPOCH_SIZE as u64) / 2
+        );
+
+        // a cheaper run only moves the estimate halfway down...
+        mempool
+            .record_tx_cost(contract_call, &ExecutionCost::zero(), &block_limit)
+            .unwrap();
+        assert_eq!(
+            MemPoolDB::estimate_tx_cost(mempool.conn(), contract_call, len).unwrap(),
+            len + (MAX_EPOCH_SIZE as u64) / 4
+        );
+
+        // ...but a more expensive one moves it all the way up
+        mempool
+            .record_tx_cost(contract_call, &half_the_runtime, &block_limit)
+            .unwrap();
+        assert_eq!(
+            MemPoolDB::estimate_tx_cost(mempool.conn(), contract_call, len).unwrap(),
+            len + (MAX_EPOCH_SIZE as u64) / 2
+        );
+
+        // smart contracts aren't estimated
+        mempool
+            .record_tx_cost(smart_contract, &half_the_runtime, &block_limit)
+            .unwrap();
+        assert_eq!(
+            MemPoolDB::estimate_tx_cost(mempool.conn(), smart_contract, smart_contract.tx_len())
+                .unwrap(),
+            smart_contract.tx_len()
+        );
+    }
+
+    #[test]
+    fn mempool_db_migrate_schema_2() {
+        let chainstate = instantiate_chainstate(false, 0x80000000, "mempool_db_migrate_schema_2");
+        let chainstate_path = chainstate_path("mempool_db_migrate_schema_2");
+
+        let txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let tx = txs.last().unwrap();
+        let tx_bytes = tx.serialize_to_vec();
+        let origin_address = StacksAddress {
+            version: 22,
+            bytes: Hash160([0x0a; 20]),
+        };
+
+        // make a mempool DB as it was before its schema was versioned
+        let mut path = PathBuf::from(chainstate.root_path.clone());
+        path.push("mempool.db");
+        {
+            let conn = DBConn::open(&path).unwrap();
+            for cmd in MEMPOOL_SQL {
+                conn.execute(cmd, NO_PARAMS).unwrap();
+            }
+            conn.execute(
+                "CREATE INDEX by_fee_per_byte ON mempool(consensus_hash, block_header_hash, (fee_rate * 1.0 / length) DESC)",
+                NO_PARAMS,
+            )
+            .unwrap();
+
+            let sql = "INSERT INTO mempool (
+                txid,
+                origin_address,
+                origin_nonce,
+                sponsor_address,
+                sponsor_nonce,
+                estimated_fee,
+                fee_rate,
+                length,
+                consensus_hash,
+                block_header_hash,
+                height,
+                accept_time,
+                tx)
+                VALUES (?1, ?2, 0, ?2, 0, ?3, 1, ?4, ?5, ?6, 1, 0, ?7)";
+            let args: &[&dyn ToSql] = &[
+                &tx.txid(),
+                &origin_address.to_string(),
+                &(tx_bytes.len() as i64),
+                &(tx_bytes.len() as i64),
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                &tx_bytes,
+            ];
+            conn.execute(sql, args).unwrap();
+        }
+        assert_eq!(
+            MemPoolDB::get_schema_version(&DBConn::open(&path).unwrap()).unwrap(),
+            1
+        );
+
+        // opening it migrates it, and opening it again is a no-op
+        for _ in 0..2 {
+            let mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+            assert_eq!(MemPoolDB::get_schema_version(mempool.conn()).unwrap(), 2);
+
+            let txinfo = MemPoolDB::get_tx(mempool.conn(), &tx.txid())
+                .unwrap()
+                .unwrap();
+            assert_eq!(txinfo.metadata.estimated_cost, tx_bytes.len() as u64);
+
+            let index_count = |name: &str| {
+                query_count(
+                    mempool.conn(),
+                    "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = ?1",
+                    &[&name as &dyn ToSql],
+                )
+                .unwrap()
+            };
+            assert_eq!(index_count("by_fee_per_byte"), 0);
+            assert_eq!(index_count("by_fee_per_cost"), 1);
+        }
+    }
+
+    #[test]
+    fn mempool_admission_policy() {
+        let txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let mut contract_call_tx = txs
+            .iter()
+            .find(|tx| match tx.payload {
+                TransactionPayload::ContractCall(_) => true,
+                _ => false,
+            })
+            .unwrap()
+            .clone();
+        contract_call_tx.set_fee_rate(1_000_000);
+        let tx_size = contract_call_tx.serialize_to_vec().len() as u64;
+
+        let called_contract_id = match contract_call_tx.payload {
+            TransactionPayload::ContractCall(ref cc) => QualifiedContractIdentifier::new(
+                cc.address.clone().into(),
+                cc.contract_name.clone(),
+            ),
+            _ => unreachable!(),
+        };
+
+        let mut policy = MemPoolAdmissionPolicy::default();
+        policy.check_tx(&contract_call_tx, tx_size).unwrap();
+
+        // too big
+        policy.max_tx_size = tx_size - 1;
+        match policy.check_tx(&contract_call_tx, tx_size).unwrap_err() {
+            MemPoolRejection::TransactionTooLarge(actual, max) => {
+                assert_eq!(actual, tx_size);
+                assert_eq!(max, tx_size - 1);
+            }
+            e => panic!("Unexpected rejection: {:?}", &e),
+        }
+        policy.max_tx_size = tx_size;
+        policy.check_tx(&contract_call_tx, tx_size).unwrap();
+
+        // fee rate too low
+        policy.min_fee_rate = 1_000_000 / tx_size + 1;
+        match policy.check_tx(&contract_call_tx, tx_size).unwrap_err() {
+            MemPoolRejection::FeeTooLow(actual, expected) => {
+                assert_eq!(actual, 1_000_000);
+                assert_eq!(expected, tx_size * policy.min_fee_rate);
+            }
+            e => panic!("Unexpected rejection: {:?}", &e),
+        }
+        policy.min_fee_rate = 1_000_000 / tx_size;
+        policy.check_tx(&contract_call_tx, tx_size).unwrap();
+
+        // banned contract
+        policy.banned_contracts.insert(called_contract_id.clone());
+        match policy.check_tx(&contract_call_tx, tx_size).unwrap_err() {
+            MemPoolRejection::BannedContract(contract_id) => {
+                assert_eq!(contract_id, called_contract_id);
+            }
+            e => panic!("Unexpected rejection: {:?}", &e),
+        }
+
+        // a token transfer is unaffected by the banned contract
+        let mut token_transfer_tx = txs
+            .iter()
+            .find(|tx| match tx.payload {
+                TransactionPayload::TokenTransfer(..) => true,
+                _ => false,
+            })
+            .unwrap()
+            .clone();
+        token_transfer_tx.set_fee_rate(1_000_000);
+        let token_transfer_size = token_transfer_tx.serialize_to_vec().len() as u64;
+        policy.max_tx_size = token_transfer_size;
+        policy.min_fee_rate = 1;
+        policy
+            .check_tx(&token_transfer_tx, token_transfer_size)
+            .unwrap();
+    }
+
+    #[test]
+    fn mempool_admission_predicate() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "mempool_admission_predicate");
+
+        let deployer_privk = StacksPrivateKey::from_hex(SK_1).unwrap();
+        let deployer_auth = TransactionAuth::from_p2pkh(&deployer_privk).unwrap();
+        let deployer_addr = deployer_auth.origin().address_testnet();
+
+        let banned_privk = StacksPrivateKey::from_hex(SK_2).unwrap();
+        let banned_auth = TransactionAuth::from_p2pkh(&banned_privk).unwrap();
+        let banned_addr = banned_auth.origin().address_testnet();
+
+        let contract = format!(
+            "
+        (define-read-only (can-admit (origin principal) (sponsor (optional principal)) (contract (optional principal)))
+          (and (not (is-eq origin '{})) (is-none contract)))
+        (define-read-only (not-a-predicate (origin principal) (sponsor (optional principal)) (contract (optional principal)))
+          u1)",
+            &banned_addr
+        );
+
+        let mut tx_contract = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            deployer_auth.clone(),
+            TransactionPayload::new_smart_contract(&"admission".to_string(), &contract).unwrap(),
+        );
+        tx_contract.chain_id = 0x80000000;
+        tx_contract.set_fee_rate(0);
+        tx_contract.post_condition_mode = TransactionPostConditionMode::Allow;
+
+        let mut signer = StacksTransactionSigner::new(&tx_contract);
+        signer.sign_origin(&deployer_privk).unwrap();
+        let signed_tx = signer.get_tx().unwrap();
+
+        // genesis -> b_1, where b_1 deploys the predicate contract
+        let genesis = StacksChainState::get_genesis_header_info(chainstate.db()).unwrap();
+        let mut b_1_info = genesis.clone();
+        b_1_info.anchored_header.parent_block = genesis.anchored_header.block_hash();
+        b_1_info.anchored_header.total_work.work = 1;
+        b_1_info.block_height = 1;
+        b_1_info.consensus_hash = ConsensusHash([0x1; 20]);
+        let b_1 = (
+            b_1_info.consensus_hash.clone(),
+            b_1_info.anchored_header.block_hash(),
+        );
+
+        {
+            let (mut chainstate_tx, clar_tx) = chainstate.chainstate_tx_begin().unwrap();
+            let mut c_tx = StacksChainState::chainstate_block_begin(
+                &chainstate_tx,
+                clar_tx,
+                &NULL_BURN_STATE_DB,
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+                &b_1.0,
+                &b_1.1,
+            );
+            StacksChainState::process_transaction(&mut c_tx, &signed_tx, false).unwrap();
+            c_tx.commit_block();
+
+            StacksChainState::insert_stacks_block_header(
+                &mut chainstate_tx,
+                &StacksBlockHeader::make_index_block_hash(
+                    &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                    &FIRST_STACKS_BLOCK_HASH,
+                ),
+                &b_1_info,
+                &ExecutionCost::zero(),
+            )
+            .unwrap();
+            chainstate_tx.commit().unwrap();
+        }
+
+        let contract_id = QualifiedContractIdentifier::new(
+            deployer_addr.clone().into(),
+            ContractName::from("admission"),
+        );
+        let mut predicate = MemPoolAdmissionPredicate {
+            contract_id: contract_id.clone(),
+            function_name: ClarityName::from("can-admit"),
+            cost_limit: MEMPOOL_DEFAULT_PREDICATE_COST_LIMIT,
+        };
+
+        let transfer_tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            deployer_auth.clone(),
+            TransactionPayload::TokenTransfer(
+                banned_addr.to_account_principal(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+                None,
+            ),
+        );
+        let banned_transfer_tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            banned_auth.clone(),
+            TransactionPayload::TokenTransfer(
+                deployer_addr.to_account_principal(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+                None,
+            ),
+        );
+        let contract_call_tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            deployer_auth.clone(),
+            TransactionPayload::new_contract_call(
+                deployer_addr.clone(),
+                "admission",
+                "foo",
+                vec![],
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(
+            MemPoolAdmissionPredicate::make_args(&contract_call_tx),
+            (
+                deployer_addr.to_account_principal(),
+                None,
+                Some(contract_id.clone())
+            )
+        );
+
+        let mut admitter = MemPoolAdmitter::new(b_1.1.clone(), b_1.0.clone());
+
+        // no predicate admits everything
+        admitter
+            .check_admission_predicate(&mut chainstate, &banned_transfer_tx)
+            .unwrap();
+
+        let mut policy = MemPoolAdmissionPolicy::default();
+        policy.admission_predicate = Some(predicate.clone());
+        admitter.set_admission_policy(policy.clone());
+
+        admitter
+            .check_admission_predicate(&mut chainstate, &transfer_tx)
+            .unwrap();
+        for tx in [banned_transfer_tx.clone(), contract_call_tx.clone()].iter() {
+            match admitter
+                .check_admission_predicate(&mut chainstate, tx)
+                .unwrap_err()
+            {
+                MemPoolRejection::AdmissionPredicateRejected(msg) => {
+                    assert!(msg.starts_with("rejected by admission predicate"));
+                }
+                e => panic!("Unexpected rejection: {:?}", &e),
+            }
+        }
+
+        // answers are cached for this tip
+        assert_eq!(admitter.predicate_cache.len(), 3);
+        assert_eq!(
+            admitter.predicate_cache_tip,
+            StacksBlockHeader::make_index_block_hash(&b_1.0, &b_1.1)
+        );
+
+        // evaluation failures reject the transaction, and are not cached
+        for function_name in ["not-a-predicate", "no-such-function"].iter() {
+            predicate.function_name = ClarityName::from(*function_name);
+            predicate.cost_limit = MEMPOOL_DEFAULT_PREDICATE_COST_LIMIT;
+            policy.admission_predicate = Some(predicate.clone());
+            admitter.set_admission_policy(policy.clone());
+            match admitter
+                .check_admission_predicate(&mut chainstate, &transfer_tx)
+                .unwrap_err()
+            {
+                MemPoolRejection::AdmissionPredicateRejected(msg) => {
+                    assert!(msg.contains("failed"));
+                }
+                e => panic!("Unexpected rejection: {:?}", &e),
+            }
+            assert_eq!(admitter.predicate_cache.len(), 0);
+        }
+
+        // so does running out of budget
+        predicate.function_name = ClarityName::from("can-admit");
+        predicate.cost_limit = ExecutionCost::zero();
+        policy.admission_predicate = Some(predicate.clone());
+        admitter.set_admission_policy(policy.clone());
+        match admitter
+            .check_admission_predicate(&mut chainstate, &transfer_tx)
+            .unwrap_err()
+        {
+            MemPoolRejection::AdmissionPredicateRejected(msg) => {
+                assert!(msg.contains("failed"));
+            }
+            e => panic!("Unexpected rejection: {:?}", &e),
+        }
+
+        // the predicate can't be evaluated on a missing tip
+        predicate.cost_limit = MEMPOOL_DEFAULT_PREDICATE_COST_LIMIT;
+        policy.admission_predicate = Some(predicate.clone());
+        let mut admitter =
+            MemPoolAdmitter::new(BlockHeaderHash([0x2; 32]), ConsensusHash([0x2; 20]));
+        admitter.set_admission_policy(policy);
+        match admitter
+            .check_admission_predicate(&mut chainstate, &transfer_tx)
+            .unwrap_err()
+        {
+            MemPoolRejection::NoSuchChainTip(..) => {}
+            e => panic!("Unexpected rejection: {:?}", &e),
+        }
+    }
+
+    #[test]
+    fn mempool_pending_txs_for_origin() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "mempool_pending_txs_for_origin");
+        let chainstate_path = chainstate_path("mempool_pending_txs_for_origin");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let origin_address = StacksAddress {
+            version: 22,
+            bytes: Hash160::from_data(&[0; 32]),
+        };
+        let other_address = StacksAddress {
+            version: 22,
+            bytes: Hash160::from_data(&[1; 32]),
+        };
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (i, (addr, nonce)) in [
+            (&origin_address, 0),
+            (&origin_address, 1),
+            (&other_address, 0),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let mut tx = txs.pop().unwrap();
+            tx.set_origin_nonce(*nonce);
+            tx.set_fee_rate(100 + i as u64);
+            let tx_bytes = tx.serialize_to_vec();
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                tx.txid(),
+                tx_bytes.clone(),
+                tx.get_fee_rate() * (tx_bytes.len() as u64),
+                tx.get_fee_rate(),
+                tx.tx_len(),
+                100,
+                addr,
+                *nonce,
+                addr,
+                *nonce,
+            )
+            .unwrap();
+        }
+
+        // a new nonce would be the origin's third pending transaction
+        assert_eq!(
+            MemPoolDB::get_num_pending_txs_for_origin(&mempool_tx, &origin_address, 2).unwrap(),
+            2
+        );
+        // a replacement for nonce 1 would not add a pending transaction
+        assert_eq!(
+            MemPoolDB::get_num_pending_txs_for_origin(&mempool_tx, &origin_address, 1).unwrap(),
+            1
+        );
+        assert_eq!(
+            MemPoolDB::get_num_pending_txs_for_origin(&mempool_tx, &other_address, 1).unwrap(),
+            1
+        );
+        mempool_tx.commit().unwrap();
+    }
+
+    #[test]
+    fn mempool_prune() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "mempool_prune");
+        let chainstate_path = chainstate_path("mempool_prune");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+
+        // (height, fee rate) for each origin's transaction
+        let specs = [(90, 1000), (100, 100), (100, 400), (100, 300)];
+        let mut txids = vec![];
+        let mut lengths = vec![];
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (i, (height, fee_rate)) in specs.iter().enumerate() {
+            let origin_address = StacksAddress {
+                version: 22,
+                bytes: Hash160([i as u8; 20]),
+            };
+            let mut tx = txs.pop().unwrap();
+            tx.set_fee_rate(*fee_rate);
+            let tx_bytes = tx.serialize_to_vec();
+            txids.push(tx.txid());
+            lengths.push(tx_bytes.len() as u64);
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                tx.txid(),
+                tx_bytes.clone(),
+                *fee_rate,
+                *fee_rate,
+                tx.tx_len(),
+                *height,
+                &origin_address,
+                0,
+                &origin_address,
+                0,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        // expire anything below height 95, and keep only as many bytes as the two transactions
+        // with the highest fees per byte among those left
+        let mut by_fee_rate: Vec<usize> = (1..4).collect();
+        by_fee_rate.sort_by(|a, b| (specs[*b].1 * lengths[*a]).cmp(&(specs[*a].1 * lengths[*b])));
+        let kept = [by_fee_rate[0], by_fee_rate[1]];
+        let max_size = lengths[kept[0]] + lengths[kept[1]];
+
+        mempool.set_gc_policy(MemPoolGCPolicy {
+            max_tx_age: 10,
+            gc_interval: 3600,
+            max_size: Some(max_size),
+        });
+
+        let result = mempool.prune(105).unwrap();
+        assert_eq!(result.expired, 1);
+        assert_eq!(result.evicted, 1);
+        assert_eq!(result.remaining_txs, 2);
+        assert_eq!(result.remaining_bytes, max_size);
+
+        for (i, txid) in txids.iter().enumerate() {
+            assert_eq!(
+                MemPoolDB::db_has_tx(mempool.conn(), txid).unwrap(),
+                kept.contains(&i)
+            );
+        }
+
+        // not due again for another hour
+        assert!(mempool.prune_if_due(200).unwrap().is_none());
+        assert_eq!(MemPoolDB::get_all_txs(mempool.conn()).unwrap().len(), 2);
+
+        // but an explicit pass still runs
+        let result = mempool.prune(200).unwrap();
+        assert_eq!(result.expired, 2);
+        assert_eq!(result.remaining_txs, 0);
+        assert_eq!(result.remaining_bytes, 0);
+    }
+
+    #[test]
+    fn mempool_fee_estimator() {
+        let block_limit = ExecutionCost {
+            write_length: 100,
+            write_count: 100,
+            read_length: 100,
+            read_count: 100,
+            runtime: 100,
+        };
+
+        // nothing sampled yet
+        let estimator = FeeEstimator::new();
+        assert_eq!(
+            estimator.estimate(&block_limit),
+            FeeRateEstimate {
+                low: MINIMUM_TX_FEE_RATE_PER_BYTE,
+                medium: MINIMUM_TX_FEE_RATE_PER_BYTE,
+                high: MINIMUM_TX_FEE_RATE_PER_BYTE,
+            }
+        );
+
+        // the i-th transaction pays a fee rate of i + 1
+        let mut txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        txs.retain(|tx| match tx.payload {
+            TransactionPayload::Coinbase(..) => false,
+            _ => true,
+        });
+        for (i, tx) in txs.iter_mut().enumerate() {
+            let fee = tx.tx_len() * (i as u64 + 1);
+            tx.set_fee_rate(fee);
+        }
+        let num_txs = txs.len() as u64;
+        let block = StacksBlock {
+            header: StacksBlockHeader::genesis_block_header(),
+            txs: txs,
+        };
+
+        // recent blocks have plenty of room, so the low estimate is the minimum
+        let mut estimator = FeeEstimator::new();
+        estimator.add_mined_block(
+            &block,
+            &ExecutionCost {
+                runtime: 10,
+                ..ExecutionCost::zero()
+            },
+            1000,
+        );
+        assert_eq!(estimator.congestion(&block_limit), 10);
+
+        let estimate = estimator.estimate(&block_limit);
+        assert_eq!(estimate.low, MINIMUM_TX_FEE_RATE_PER_BYTE);
+        assert_eq!(estimate.medium, (num_txs - 1) * 50 / 100 + 1);
+        assert_eq!(estimate.high, (num_txs - 1) * 90 / 100 + 1);
+
+        // a recent block is congested in one dimension, so the low estimate tracks what got mined
+        estimator.add_mined_block(
+            &StacksBlock {
+                header: StacksBlockHeader::genesis_block_header(),
+                txs: vec![],
+            },
+            &ExecutionCost {
+                write_count: 60,
+                ..ExecutionCost::zero()
+            },
+            1000,
+        );
+        assert_eq!(estimator.congestion(&block_limit), 60);
+
+        let estimate = estimator.estimate(&block_limit);
+        assert_eq!(estimate.low, (num_txs - 1) * 25 / 100 + 1);
+
+        // lots of pending high-fee transactions raise the medium and high estimates
+        for _ in 0..(10 * num_txs) {
+            estimator.add_pending_tx(1000 * 100, 100);
+        }
+        let estimate = estimator.estimate(&block_limit);
+        assert_eq!(estimate.low, (num_txs - 1) * 25 / 100 + 1);
+        assert_eq!(estimate.medium, 1000);
+        assert_eq!(estimate.high, 1000);
+
+        assert_eq!(
+            estimate.fees_for_len(10),
+            FeeRateEstimate {
+                low: estimate.low * 10,
+                medium: 10000,
+                high: 10000,
+            }
+        );
+    }
 }
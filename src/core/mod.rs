@@ -23,6 +23,7 @@ use util::log;
 
 pub mod mempool;
 pub use self::mempool::MemPoolDB;
+pub mod scheduler;
 
 // fork set identifier -- to be mixed with the consensus hash (encodes the version)
 pub const SYSTEM_FORK_SET_VERSION: [u8; 4] = [23u8, 0u8, 0u8, 0u8];
@@ -39,6 +40,10 @@ pub const NETWORK_P2P_PORT: u16 = 6265;
 
 pub const MINING_COMMITMENT_WINDOW: u8 = 6;
 
+/// The number of burnchain blocks (the block itself and its ancestors) whose
+///   timestamps are used to calculate a burnchain block's median-time-past.
+pub const BURNCHAIN_MEDIAN_TIME_PAST_WINDOW: u64 = 11;
+
 /// The number of blocks which will share the block bonus
 ///   from burn blocks that occurred without a sortition.
 ///   (See: https://forum.stacks.org/t/pox-consensus-and-stx-future-supply)
@@ -67,6 +72,18 @@ pub const MICROSTACKS_PER_STACKS: u32 = 1_000_000;
 pub const POX_SUNSET_START: u64 = (FIRST_BURNCHAIN_BLOCK_HEIGHT as u64) + 100_000;
 pub const POX_SUNSET_END: u64 = POX_SUNSET_START + 400_000;
 
+/// The burnchain block heights at which the Stacks 2.1 consensus rules activate, if any.
+///   Mainnet has no activation height until one is ratified, so it stays on Stacks 2.0.  Test
+///   networks are reset with each release, so they activate from their first burnchain block.
+pub const STACKS_2_1_ACTIVATION_HEIGHT_MAINNET: Option<u64> = None;
+pub const STACKS_2_1_ACTIVATION_HEIGHT_TESTNET: Option<u64> =
+    Some(FIRST_BURNCHAIN_BLOCK_HEIGHT as u64);
+
+/// The most scheduled STX lock expirations a block sweeps into their accounts' unlocked
+///   balances.  Any more that are due stay scheduled for the next block; until they are swept,
+///   their accounts still unlock when they are next written to.
+pub const MAX_STX_UNLOCKS_PER_BLOCK: u64 = 500;
+
 pub const POX_PREPARE_WINDOW_LENGTH: u32 = 240;
 pub const POX_REWARD_CYCLE_LENGTH: u32 = 2000;
 /// The maximum amount that PoX rewards can be scaled by.
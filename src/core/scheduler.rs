@@ -0,0 +1,354 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Contract-calls that a node operator has scheduled for the node to send once the burnchain
+//! reaches a target height (e.g. a protocol's keeper calls).  The node signs and submits them
+//! with its own key; this module only keeps the queue, which survives restarts.
+
+use std::fs;
+
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use util::db::Error as db_error;
+use util::db::{query_row, query_rows, sqlite_open, tx_begin_immediate, u64_to_sql, FromRow};
+
+pub const SCHEDULER_DB_VERSION: &'static str = "1";
+
+const SCHEDULER_DB_SETUP: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE scheduled_calls(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        contract_address TEXT NOT NULL,
+        contract_name TEXT NOT NULL,
+        function_name TEXT NOT NULL,
+        -- JSON list of hex-encoded, consensus-serialized Clarity values
+        function_args TEXT NOT NULL,
+        target_burn_height INTEGER NOT NULL,
+        fee INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        txid TEXT,
+        error TEXT
+    );"#,
+    r#"
+    CREATE INDEX scheduled_calls_by_status ON scheduled_calls(status, target_burn_height);
+    "#,
+    r#"
+    CREATE TABLE db_version(version TEXT NOT NULL);
+    "#,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduledCallStatus {
+    /// waiting for its target burn height
+    Pending,
+    /// sent to the mempool
+    Submitted,
+    /// could not be sent; see the call's `error`
+    Failed,
+    /// cancelled before it was sent
+    Cancelled,
+}
+
+impl ScheduledCallStatus {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ScheduledCallStatus::Pending => "pending",
+            ScheduledCallStatus::Submitted => "submitted",
+            ScheduledCallStatus::Failed => "failed",
+            ScheduledCallStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<ScheduledCallStatus> {
+        match s {
+            "pending" => Some(ScheduledCallStatus::Pending),
+            "submitted" => Some(ScheduledCallStatus::Submitted),
+            "failed" => Some(ScheduledCallStatus::Failed),
+            "cancelled" => Some(ScheduledCallStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// A contract-call to schedule, as given on POST /v2/admin/scheduled_calls
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledCallRequest {
+    pub contract_address: String,
+    pub contract_name: String,
+    pub function_name: String,
+    /// hex-encoded, consensus-serialized Clarity values
+    pub function_args: Vec<String>,
+    pub target_burn_height: u64,
+    pub fee: u64,
+}
+
+/// A scheduled contract-call, as reported on GET /v2/admin/scheduled_calls
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledCall {
+    pub id: u64,
+    pub contract_address: String,
+    pub contract_name: String,
+    pub function_name: String,
+    pub function_args: Vec<String>,
+    pub target_burn_height: u64,
+    pub fee: u64,
+    pub status: ScheduledCallStatus,
+    /// the submitted transaction's ID, once submitted
+    pub txid: Option<String>,
+    /// why the call could not be submitted, if it failed
+    pub error: Option<String>,
+}
+
+impl FromRow<ScheduledCall> for ScheduledCall {
+    fn from_row<'a>(row: &'a Row) -> Result<ScheduledCall, db_error> {
+        let id: i64 = row.get("id");
+        let function_args_json: String = row.get("function_args");
+        let function_args =
+            serde_json::from_str(&function_args_json).map_err(|_| db_error::ParseError)?;
+        let target_burn_height: i64 = row.get("target_burn_height");
+        let fee: i64 = row.get("fee");
+        let status_str: String = row.get("status");
+        let status = ScheduledCallStatus::from_str(&status_str).ok_or(db_error::ParseError)?;
+
+        Ok(ScheduledCall {
+            id: id as u64,
+            contract_address: row.get("contract_address"),
+            contract_name: row.get("contract_name"),
+            function_name: row.get("function_name"),
+            function_args,
+            target_burn_height: target_burn_height as u64,
+            fee: fee as u64,
+            status,
+            txid: row.get("txid"),
+            error: row.get("error"),
+        })
+    }
+}
+
+pub struct ScheduledCallDB {
+    conn: Connection,
+}
+
+impl ScheduledCallDB {
+    fn instantiate(&mut self) -> Result<(), db_error> {
+        let tx = tx_begin_immediate(&mut self.conn)?;
+
+        for row_text in SCHEDULER_DB_SETUP {
+            tx.execute(row_text, NO_PARAMS)
+                .map_err(db_error::SqliteError)?;
+        }
+
+        tx.execute(
+            "INSERT INTO db_version (version) VALUES (?1)",
+            &[&SCHEDULER_DB_VERSION],
+        )
+        .map_err(db_error::SqliteError)?;
+
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Open the scheduled-call database at the given path, creating it if it doesn't exist.
+    pub fn open(path: &str) -> Result<ScheduledCallDB, db_error> {
+        let create_flag = fs::metadata(path).is_err();
+        let open_flags = if create_flag {
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        };
+        let conn = sqlite_open(path, open_flags).map_err(db_error::SqliteError)?;
+
+        let mut db = ScheduledCallDB { conn };
+        if create_flag {
+            db.instantiate()?;
+        }
+        Ok(db)
+    }
+
+    #[cfg(test)]
+    pub fn open_memory() -> Result<ScheduledCallDB, db_error> {
+        let conn = Connection::open_in_memory().map_err(db_error::SqliteError)?;
+        let mut db = ScheduledCallDB { conn };
+        db.instantiate()?;
+        Ok(db)
+    }
+
+    /// Schedule a call.  Returns the new call, which is pending.
+    pub fn add(&mut self, request: &ScheduledCallRequest) -> Result<ScheduledCall, db_error> {
+        let function_args_json = serde_json::to_string(&request.function_args)
+            .map_err(|e| db_error::SerializationError(e))?;
+        let args: &[&dyn ToSql] = &[
+            &request.contract_address,
+            &request.contract_name,
+            &request.function_name,
+            &function_args_json,
+            &u64_to_sql(request.target_burn_height)?,
+            &u64_to_sql(request.fee)?,
+            &ScheduledCallStatus::Pending.as_str(),
+        ];
+
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        tx.execute(
+            "INSERT INTO scheduled_calls \
+             (contract_address, contract_name, function_name, function_args, target_burn_height, fee, status) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+        let id = tx.last_insert_rowid();
+        tx.commit().map_err(db_error::SqliteError)?;
+
+        self.get(id as u64)?.ok_or(db_error::NotFoundError)
+    }
+
+    pub fn get(&self, id: u64) -> Result<Option<ScheduledCall>, db_error> {
+        let args: &[&dyn ToSql] = &[&u64_to_sql(id)?];
+        query_row(
+            &self.conn,
+            "SELECT * FROM scheduled_calls WHERE id = ?1",
+            args,
+        )
+    }
+
+    /// All calls, in the order they were scheduled
+    pub fn get_all(&self) -> Result<Vec<ScheduledCall>, db_error> {
+        query_rows(
+            &self.conn,
+            "SELECT * FROM scheduled_calls ORDER BY id",
+            NO_PARAMS,
+        )
+    }
+
+    /// The pending calls whose target burn height is at or below `burn_height`, oldest target
+    /// first
+    pub fn get_due(&self, burn_height: u64) -> Result<Vec<ScheduledCall>, db_error> {
+        let args: &[&dyn ToSql] = &[
+            &ScheduledCallStatus::Pending.as_str(),
+            &u64_to_sql(burn_height)?,
+        ];
+        query_rows(
+            &self.conn,
+            "SELECT * FROM scheduled_calls WHERE status = ?1 AND target_burn_height <= ?2 \
+             ORDER BY target_burn_height, id",
+            args,
+        )
+    }
+
+    fn set_status(
+        &mut self,
+        id: u64,
+        status: ScheduledCallStatus,
+        txid: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<bool, db_error> {
+        let args: &[&dyn ToSql] = &[
+            &status.as_str(),
+            &txid,
+            &error,
+            &u64_to_sql(id)?,
+            &ScheduledCallStatus::Pending.as_str(),
+        ];
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        let updated = tx
+            .execute(
+                "UPDATE scheduled_calls SET status = ?1, txid = ?2, error = ?3 \
+                 WHERE id = ?4 AND status = ?5",
+                args,
+            )
+            .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(updated > 0)
+    }
+
+    /// Cancel a pending call.  Returns the call, or None if there is no such call.  A call that
+    /// is no longer pending is returned unchanged.
+    pub fn cancel(&mut self, id: u64) -> Result<Option<ScheduledCall>, db_error> {
+        self.set_status(id, ScheduledCallStatus::Cancelled, None, None)?;
+        self.get(id)
+    }
+
+    /// Record that a pending call was submitted in the given transaction
+    pub fn set_submitted(&mut self, id: u64, txid: &str) -> Result<bool, db_error> {
+        self.set_status(id, ScheduledCallStatus::Submitted, Some(txid), None)
+    }
+
+    /// Record that a pending call could not be submitted
+    pub fn set_failed(&mut self, id: u64, error: &str) -> Result<bool, db_error> {
+        self.set_status(id, ScheduledCallStatus::Failed, None, Some(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_request(target_burn_height: u64) -> ScheduledCallRequest {
+        ScheduledCallRequest {
+            contract_address: "ST000000000000000000002AMW42H".to_string(),
+            contract_name: "pox".to_string(),
+            function_name: "get-pox-info".to_string(),
+            function_args: vec!["0100000000000000000000000000000001".to_string()],
+            target_burn_height,
+            fee: 1000,
+        }
+    }
+
+    #[test]
+    fn test_scheduled_calls_come_due() {
+        let mut db = ScheduledCallDB::open_memory().unwrap();
+        let late = db.add(&make_request(20)).unwrap();
+        let early = db.add(&make_request(10)).unwrap();
+        assert_eq!(late.status, ScheduledCallStatus::Pending);
+        assert_eq!(late.function_args, make_request(20).function_args);
+
+        assert!(db.get_due(9).unwrap().is_empty());
+        assert_eq!(db.get_due(10).unwrap(), vec![early.clone()]);
+        assert_eq!(db.get_due(25).unwrap(), vec![early.clone(), late.clone()]);
+
+        assert!(db.set_submitted(early.id, "aa").unwrap());
+        assert!(db.set_failed(late.id, "NotEnoughFunds").unwrap());
+        assert!(db.get_due(25).unwrap().is_empty());
+
+        let all = db.get_all().unwrap();
+        assert_eq!(all[0].status, ScheduledCallStatus::Failed);
+        assert_eq!(all[0].error, Some("NotEnoughFunds".to_string()));
+        assert_eq!(all[1].status, ScheduledCallStatus::Submitted);
+        assert_eq!(all[1].txid, Some("aa".to_string()));
+
+        // only pending calls change state
+        assert!(!db.set_failed(early.id, "too late").unwrap());
+    }
+
+    #[test]
+    fn test_cancel_scheduled_call() {
+        let mut db = ScheduledCallDB::open_memory().unwrap();
+        let call = db.add(&make_request(10)).unwrap();
+        let submitted = db.add(&make_request(10)).unwrap();
+        db.set_submitted(submitted.id, "bb").unwrap();
+
+        let cancelled = db.cancel(call.id).unwrap().unwrap();
+        assert_eq!(cancelled.status, ScheduledCallStatus::Cancelled);
+        assert_eq!(
+            db.cancel(submitted.id).unwrap().unwrap().status,
+            ScheduledCallStatus::Submitted
+        );
+        assert!(db.cancel(1000).unwrap().is_none());
+        assert!(db.get_due(10).unwrap().is_empty());
+    }
+}
@@ -25,6 +25,7 @@ extern crate curve25519_dalek;
 extern crate ed25519_dalek;
 extern crate rand;
 extern crate rand_chacha;
+extern crate rayon;
 extern crate rusqlite;
 extern crate secp256k1;
 extern crate serde;
@@ -32,6 +33,7 @@ extern crate tini;
 #[macro_use]
 extern crate lazy_static;
 extern crate integer_sqrt;
+extern crate lru_cache;
 extern crate mio;
 extern crate percent_encoding;
 extern crate regex;
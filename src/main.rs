@@ -23,6 +23,7 @@
 #[macro_use]
 extern crate blockstack_lib;
 extern crate rusqlite;
+extern crate serde_json;
 
 #[macro_use(o, slog_log, slog_trace, slog_debug, slog_info, slog_warn, slog_error)]
 extern crate slog;
@@ -373,6 +374,187 @@ fn main() {
         return;
     }
 
+    if argv[1] == "audit-canonical-tips" {
+        use chainstate::burn::db::sortdb::SortitionDB;
+        if argv.len() < 5 {
+            eprintln!(
+                "Usage: {} audit-canonical-tips SORTITION_DB_PATH START_HEIGHT END_HEIGHT",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let sort_path = &argv[2];
+        let start_height: u64 = argv[3].parse().expect("Failed to parse START_HEIGHT");
+        let end_height: u64 = argv[4].parse().expect("Failed to parse END_HEIGHT");
+
+        let sortition_db = SortitionDB::open(sort_path, false).unwrap();
+        let divergences = sortition_db
+            .audit_canonical_stacks_tips(start_height, end_height)
+            .unwrap();
+
+        for divergence in divergences.iter() {
+            println!(
+                "{:?} at burn height {} ({}{}): stored {}/{} (height {}), computed {}/{} (height {})",
+                &divergence.kind,
+                divergence.burn_block_height,
+                &divergence.sortition_id,
+                if divergence.is_burnchain_tip {
+                    ", burnchain tip"
+                } else {
+                    ""
+                },
+                &divergence.stored_consensus_hash,
+                &divergence.stored_block_hash,
+                divergence.stored_height,
+                &divergence.computed_consensus_hash,
+                &divergence.computed_block_hash,
+                divergence.computed_height
+            );
+        }
+        println!("{} divergence(s)", divergences.len());
+        return;
+    }
+
+    if argv[1] == "peerdb-export" {
+        use net::db::PeerDB;
+        if argv.len() < 3 {
+            eprintln!("Usage: {} peerdb-export PEER_DB_PATH", argv[0]);
+            process::exit(1);
+        }
+
+        let peerdb = PeerDB::open(&argv[2], false).unwrap();
+        let local_peer = PeerDB::get_local_peer(peerdb.conn()).unwrap();
+        let peers = PeerDB::export_peers(peerdb.conn(), local_peer.network_id).unwrap();
+        println!("{}", serde_json::to_string_pretty(&peers).unwrap());
+        return;
+    }
+
+    if argv[1] == "peerdb-import" {
+        use net::db::{PeerDB, PeerDBExportEntry};
+        if argv.len() < 4 {
+            eprintln!("Usage: {} peerdb-import PEER_DB_PATH EXPORT_PATH", argv[0]);
+            process::exit(1);
+        }
+
+        let export_json = fs::read_to_string(&argv[3]).expect("Failed to read EXPORT_PATH");
+        let peers: Vec<PeerDBExportEntry> =
+            serde_json::from_str(&export_json).expect("Failed to parse EXPORT_PATH");
+
+        let mut peerdb = PeerDB::open(&argv[2], true).unwrap();
+        let local_peer = PeerDB::get_local_peer(peerdb.conn()).unwrap();
+        let mut tx = peerdb.tx_begin().unwrap();
+        let num_imported = PeerDB::import_peers(&mut tx, local_peer.network_id, &peers).unwrap();
+        tx.commit().unwrap();
+
+        println!("Imported {} of {} peer(s)", num_imported, peers.len());
+        return;
+    }
+
+    if argv[1] == "diff-chainstate" {
+        use chainstate::burn::db::sortdb::SortitionDB;
+        use chainstate::stacks::db::StacksChainState;
+        if argv.len() < 6 {
+            eprintln!(
+                "Usage: {} diff-chainstate CHAINSTATE_PATH_A SORTITION_DB_PATH_A CHAINSTATE_PATH_B SORTITION_DB_PATH_B [MAX_BLOCKS [NUM_ACCOUNTS]]",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let max_blocks: u64 = if argv.len() > 6 {
+            argv[6].parse().expect("Failed to parse MAX_BLOCKS")
+        } else {
+            u64::max_value()
+        };
+        let num_accounts: usize = if argv.len() > 7 {
+            argv[7].parse().expect("Failed to parse NUM_ACCOUNTS")
+        } else {
+            100
+        };
+
+        let (mut chainstate_a, _) = StacksChainState::open(false, 0x80000000, &argv[2]).unwrap();
+        let sortition_db_a = SortitionDB::open(&argv[3], false).unwrap();
+        let (mut chainstate_b, _) = StacksChainState::open(false, 0x80000000, &argv[4]).unwrap();
+        let sortition_db_b = SortitionDB::open(&argv[5], false).unwrap();
+
+        let diff = StacksChainState::diff_chainstates(
+            &mut chainstate_a,
+            &sortition_db_a,
+            &mut chainstate_b,
+            &sortition_db_b,
+            max_blocks,
+            num_accounts,
+        )
+        .unwrap();
+
+        for divergence in diff.divergences.iter() {
+            println!("{:?}", divergence);
+        }
+        match diff.common_tip {
+            Some(common_tip) => println!(
+                "Common tip {} (height {}): {} block(s) and {} account(s) compared, {} divergence(s)",
+                &common_tip.index_block_hash(),
+                common_tip.block_height,
+                diff.blocks_compared,
+                diff.accounts_compared,
+                diff.divergences.len()
+            ),
+            None => println!(
+                "No common tip found; {} divergence(s)",
+                diff.divergences.len()
+            ),
+        }
+        if !diff.divergences.is_empty() {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if argv[1] == "token-snapshot" {
+        use chainstate::burn::db::sortdb::SortitionDB;
+        use chainstate::stacks::db::StacksChainState;
+        use vm::types::QualifiedContractIdentifier;
+        if argv.len() < 6 {
+            eprintln!(
+                "Usage: {} token-snapshot CHAINSTATE_PATH SORTITION_DB_PATH CONTRACT_ID BLOCK_HEIGHT",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let contract_identifier =
+            QualifiedContractIdentifier::parse(&argv[4]).expect("Failed to parse CONTRACT_ID");
+        let block_height: u64 = argv[5].parse().expect("Failed to parse BLOCK_HEIGHT");
+
+        let (mut chainstate, _) = StacksChainState::open(false, 0x80000000, &argv[2]).unwrap();
+        let sortition_db = SortitionDB::open(&argv[3], false).unwrap();
+
+        let snapshot = chainstate
+            .snapshot_token_balances(&sortition_db, &contract_identifier, block_height)
+            .unwrap();
+
+        // the CSV goes to stdout; the summary goes to stderr so the CSV can be redirected
+        snapshot.write_csv(&mut io::stdout()).unwrap();
+        eprintln!(
+            "Block {} (height {}): {} holder(s), total {}",
+            &snapshot.index_block_hash,
+            snapshot.block_height,
+            snapshot.balances.len(),
+            snapshot.total
+        );
+        if let Some(total_supply) = snapshot.total_supply {
+            if total_supply != snapshot.total {
+                eprintln!(
+                    "Warning: get-total-supply reports {}, but the balances sum to {}",
+                    total_supply, snapshot.total
+                );
+            }
+        }
+        eprintln!("Merkle root: {}", &snapshot.merkle_root());
+        return;
+    }
+
     if argv[1] == "replay-chainstate" {
         use burnchains::bitcoin::indexer::BitcoinIndexer;
         use burnchains::db::BurnchainDB;
@@ -462,12 +644,14 @@ fn main() {
 
         let mut boot_data = ChainStateBootData {
             initial_balances,
+            initial_locks: vec![],
             post_flight_callback: None,
             first_burnchain_block_hash,
             first_burnchain_block_height: first_burnchain_block_height as u32,
             first_burnchain_block_timestamp: 0,
             get_bulk_initial_lockups: None,
             get_bulk_initial_balances: None,
+            genesis_balances_audit: None,
         };
 
         let (mut new_chainstate, _) = StacksChainState::open_and_exec(
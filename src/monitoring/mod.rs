@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 #[cfg(feature = "monitoring_prom")]
 mod prometheus;
 
@@ -42,6 +44,11 @@ pub fn increment_p2p_msg_get_blocks_inv_received_counter() {
     prometheus::P2P_MSG_GET_BLOCKS_INV_RECEIVED_COUNTER.inc();
 }
 
+pub fn increment_p2p_msg_duplicate_dropped_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::P2P_MSG_DUPLICATE_DROPPED_COUNTER.inc();
+}
+
 pub fn increment_p2p_msg_nack_sent_counter() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::P2P_MSG_NACK_SENT_COUNTER.inc();
@@ -122,3 +129,113 @@ pub fn update_active_miners_count_gauge(value: i64) {
     #[cfg(feature = "monitoring_prom")]
     prometheus::ACTIVE_MINERS_COUNT_GAUGE.set(value);
 }
+
+#[allow(unused_variables)]
+pub fn increment_rpc_deprecated_calls_counter(usage: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_DEPRECATED_CALL_COUNTER
+        .with_label_values(&[usage])
+        .inc();
+}
+
+#[allow(unused_variables)]
+pub fn observe_rpc_request_duration(route: &str, duration: Duration) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_REQUEST_DURATION_HISTOGRAM
+        .with_label_values(&[route])
+        .observe(duration.as_secs_f64());
+}
+
+#[allow(unused_variables)]
+pub fn update_stacks_tip_height_gauge(value: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::STACKS_TIP_HEIGHT_GAUGE.set(value as i64);
+}
+
+#[allow(unused_variables)]
+pub fn update_burnchain_height_gauge(value: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BURNCHAIN_HEIGHT_GAUGE.set(value as i64);
+}
+
+#[allow(unused_variables)]
+pub fn update_mempool_size_gauges(num_txs: u64, num_bytes: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::MEMPOOL_TXS_GAUGE.set(num_txs as i64);
+        prometheus::MEMPOOL_BYTES_GAUGE.set(num_bytes as i64);
+    }
+}
+
+#[allow(unused_variables)]
+pub fn update_p2p_peers_gauges(num_inbound: u64, num_outbound: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::P2P_PEERS_GAUGE
+            .with_label_values(&["inbound"])
+            .set(num_inbound as i64);
+        prometheus::P2P_PEERS_GAUGE
+            .with_label_values(&["outbound"])
+            .set(num_outbound as i64);
+    }
+}
+
+#[allow(unused_variables)]
+pub fn increment_marf_cache_counter(hit: bool) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        if hit {
+            prometheus::MARF_CACHE_HITS_COUNTER.inc();
+        } else {
+            prometheus::MARF_CACHE_MISSES_COUNTER.inc();
+        }
+    }
+}
+
+#[allow(unused_variables)]
+pub fn increment_miner_sortitions_counters(won: bool) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::MINER_SORTITIONS_ENTERED_COUNTER.inc();
+        if won {
+            prometheus::MINER_SORTITIONS_WON_COUNTER.inc();
+        }
+    }
+}
+
+#[allow(unused_variables)]
+pub fn increment_block_pushes_counter(succeeded: bool) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        if succeeded {
+            prometheus::BLOCK_PUSHES_SUCCEEDED_COUNTER.inc();
+        } else {
+            prometheus::BLOCK_PUSHES_FAILED_COUNTER.inc();
+        }
+    }
+}
+
+#[allow(unused_variables)]
+pub fn update_coordinator_event_queue_depth_gauge(event: &str, depth: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::COORDINATOR_EVENT_QUEUE_DEPTH_GAUGE
+        .with_label_values(&[event])
+        .set(depth as i64);
+}
+
+#[allow(unused_variables)]
+pub fn increment_coordinator_events_dropped_counter(event: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::COORDINATOR_EVENTS_DROPPED_COUNTER
+        .with_label_values(&[event])
+        .inc();
+}
+
+/// `reason` is either "denied" or "not_allowed"
+#[allow(unused_variables)]
+pub fn increment_miner_contract_txs_skipped_counter(reason: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::MINER_CONTRACT_TXS_SKIPPED_COUNTER
+        .with_label_values(&[reason])
+        .inc();
+}
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use prometheus::{IntCounter, IntGauge};
+use prometheus::{HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
 
 lazy_static! {
     pub static ref RPC_CALL_COUNTER: IntCounter = register_int_counter!(opts!(
@@ -23,6 +23,12 @@ lazy_static! {
         labels! {"handler" => "all",}
     )).unwrap();
 
+    pub static ref RPC_DEPRECATED_CALL_COUNTER: IntCounterVec = register_int_counter_vec!(opts!(
+        "stacks_node_rpc_deprecated_requests_total",
+        "Total number of RPC requests made to deprecated endpoints, by endpoint.",
+        labels! {"handler" => "all",}
+    ), &["usage"]).unwrap();
+
     pub static ref P2P_MSG_UNAUTHENTICATED_HANDSHAKE_RECEIVED_COUNTER: IntCounter = register_int_counter!(opts!(
         "stacks_node_p2p_msg_unauthenticated_handshake_received_total",
         "Total number of authenticated Handshake messages received.",
@@ -47,6 +53,12 @@ lazy_static! {
         labels! {"handler" => "all",}
     )).unwrap();
 
+    pub static ref P2P_MSG_DUPLICATE_DROPPED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_p2p_msg_duplicate_dropped_total",
+        "Total number of pushed messages dropped as replays of a message the same neighbor pushed recently.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
     pub static ref P2P_MSG_NACK_SENT_COUNTER: IntCounter = register_int_counter!(opts!(
         "stacks_node_p2p_msg_nack_sent_total",
         "Total number of Nack messages sent.",
@@ -142,4 +154,93 @@ lazy_static! {
         "Total number of active miners.",
         labels! {"handler" => "all",}
     )).unwrap();
+
+    pub static ref RPC_REQUEST_DURATION_HISTOGRAM: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_rpc_request_duration_seconds",
+        "Time taken to handle RPC requests, by route."
+    ), &["route"]).unwrap();
+
+    pub static ref STACKS_TIP_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_stacks_tip_height",
+        "Height of the canonical Stacks chain tip.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref BURNCHAIN_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_burn_block_height",
+        "Height of the most recently processed sortition.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref MEMPOOL_TXS_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_mempool_transactions",
+        "Number of transactions in the mempool.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref MEMPOOL_BYTES_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_mempool_bytes",
+        "Total size of the transactions in the mempool, in bytes.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref P2P_PEERS_GAUGE: IntGaugeVec = register_int_gauge_vec!(opts!(
+        "stacks_node_p2p_peers",
+        "Number of connected p2p peers, by connection direction.",
+        labels! {"handler" => "all",}
+    ), &["direction"]).unwrap();
+
+    pub static ref MARF_CACHE_HITS_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_marf_cache_hits_total",
+        "Total number of MARF trie node reads served from the node cache.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref MARF_CACHE_MISSES_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_marf_cache_misses_total",
+        "Total number of cacheable MARF trie node reads that missed the node cache.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref MINER_SORTITIONS_ENTERED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_miner_sortitions_entered_total",
+        "Total number of sortitions in which this node's miner had a block-commit.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref MINER_SORTITIONS_WON_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_miner_sortitions_won_total",
+        "Total number of sortitions won by this node's miner.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref BLOCK_PUSHES_SUCCEEDED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_block_pushes_succeeded_total",
+        "Total number of mined blocks uploaded over HTTP to a configured block push URL.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref BLOCK_PUSHES_FAILED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_block_pushes_failed_total",
+        "Total number of mined blocks that could not be uploaded to a configured block push URL after all retries.",
+        labels! {"handler" => "all",}
+    )).unwrap();
+
+    pub static ref COORDINATOR_EVENT_QUEUE_DEPTH_GAUGE: IntGaugeVec = register_int_gauge_vec!(opts!(
+        "stacks_node_coordinator_event_queue_depth",
+        "Number of events waiting on the chains coordinator's event bus, by event type.",
+        labels! {"handler" => "all",}
+    ), &["event"]).unwrap();
+
+    pub static ref COORDINATOR_EVENTS_DROPPED_COUNTER: IntCounterVec = register_int_counter_vec!(opts!(
+        "stacks_node_coordinator_events_dropped_total",
+        "Total number of events the chains coordinator's event bus dropped or coalesced because their queue was full, by event type.",
+        labels! {"handler" => "all",}
+    ), &["event"]).unwrap();
+
+    pub static ref MINER_CONTRACT_TXS_SKIPPED_COUNTER: IntCounterVec = register_int_counter_vec!(opts!(
+        "stacks_node_miner_contract_txs_skipped_total",
+        "Total number of mempool transactions the miner skipped during block assembly because of its contract allow/deny lists, by reason.",
+        labels! {"handler" => "all",}
+    ), &["reason"]).unwrap();
 }
@@ -231,6 +231,68 @@ impl AtlasDB {
         Ok(res)
     }
 
+    /// Get the attachment instances in the given inventory page, and whether or not each one's
+    /// attachment is available locally.  Optionally restrict them to a single contract.
+    pub fn get_attachment_instances_at_page_index(
+        &self,
+        page_index: u32,
+        contract_id_opt: Option<&QualifiedContractIdentifier>,
+    ) -> Result<Vec<(AttachmentInstance, bool)>, db_error> {
+        let min = page_index * AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+        let max = min + AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+        let contract_id_str = contract_id_opt.map(|contract_id| contract_id.to_string());
+        let (qry, args) = match contract_id_str {
+            Some(ref contract_id) => (
+                "SELECT * FROM attachment_instances WHERE attachment_index >= ?1 AND attachment_index < ?2 AND contract_id = ?3 ORDER BY attachment_index ASC",
+                vec![&min as &dyn ToSql, &max as &dyn ToSql, contract_id as &dyn ToSql],
+            ),
+            None => (
+                "SELECT * FROM attachment_instances WHERE attachment_index >= ?1 AND attachment_index < ?2 ORDER BY attachment_index ASC",
+                vec![&min as &dyn ToSql, &max as &dyn ToSql],
+            ),
+        };
+        let mut stmt = self.conn.prepare(qry)?;
+        let mut rows = stmt.query(&args)?;
+        let mut instances = vec![];
+        while let Some(row_res) = rows.next() {
+            let row = row_res?;
+            let instance = AttachmentInstance::from_row(&row)?;
+            let is_available: i64 = row.get("is_available");
+            instances.push((instance, is_available != 0));
+        }
+        Ok(instances)
+    }
+
+    /// Get the number of attachment inventory pages, optionally for a single contract.
+    pub fn get_attachment_inv_pages_count(
+        &self,
+        contract_id_opt: Option<&QualifiedContractIdentifier>,
+    ) -> Result<u32, db_error> {
+        let contract_id_str = contract_id_opt.map(|contract_id| contract_id.to_string());
+        let (qry, args) = match contract_id_str {
+            Some(ref contract_id) => (
+                "SELECT MAX(attachment_index) FROM attachment_instances WHERE contract_id = ?1",
+                vec![contract_id as &dyn ToSql],
+            ),
+            None => (
+                "SELECT MAX(attachment_index) FROM attachment_instances",
+                vec![],
+            ),
+        };
+        let mut stmt = self.conn.prepare(qry)?;
+        let mut rows = stmt.query(&args)?;
+        match rows.next() {
+            Some(Ok(row)) => {
+                let max_index: Option<i64> = row.get(0);
+                Ok(max_index
+                    .map(|i| (i as u32) / AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE + 1)
+                    .unwrap_or(0))
+            }
+            Some(Err(e)) => Err(db_error::SqliteError(e)),
+            None => Ok(0),
+        }
+    }
+
     pub fn insert_new_attachment(&mut self, attachment: &Attachment) -> Result<(), db_error> {
         let tx = self.tx_begin()?;
         let res = tx.execute(
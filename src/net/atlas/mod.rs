@@ -73,12 +73,28 @@ pub struct AttachmentInstance {
 }
 
 impl AttachmentInstance {
-    const ATTACHMENTS_INV_PAGE_SIZE: u32 = 8;
+    pub const ATTACHMENTS_INV_PAGE_SIZE: u32 = 8;
 
     pub fn get_stacks_block_id(&self) -> StacksBlockId {
         StacksBlockHeader::make_index_block_hash(&self.consensus_hash, &self.block_header_hash)
     }
 
+    /// Get the BNS namespace this attachment was announced for, if its metadata has one.
+    pub fn get_namespace(&self) -> Option<Vec<u8>> {
+        if self.metadata.is_empty() {
+            return None;
+        }
+        match Value::try_deserialize_hex_untyped(&self.metadata) {
+            Ok(Value::Tuple(ref metadata)) => match metadata.get("namespace") {
+                Ok(Value::Sequence(SequenceData::Buffer(namespace))) => {
+                    Some(namespace.data.clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub fn try_new_from_value(
         value: &Value,
         contract_id: &QualifiedContractIdentifier,
@@ -70,6 +70,7 @@ use std::io::Write;
 
 use util::get_epoch_time_secs;
 use util::hash::to_hex;
+use util::hash::Sha512Trunc256Sum;
 use util::log;
 
 use rand;
@@ -98,6 +99,9 @@ pub const HEALTH_POINT_LIFETIME: u64 = 12 * 3600; // 12 hours
 pub const NUM_BLOCK_POINTS: usize = 32;
 pub const BLOCK_POINT_LIFETIME: u64 = 600;
 
+pub const NUM_RECENT_PUSH_DIGESTS: usize = 256;
+pub const RECENT_PUSH_DIGEST_LIFETIME: u64 = 600;
+
 pub const MAX_PEER_HEARTBEAT_INTERVAL: usize = 3600 * 6; // 6 hours
 
 /// Statistics on relayer hints in Stacks messages.  Used to deduce network choke points.
@@ -140,6 +144,7 @@ pub struct NeighborStats {
     pub msgs_tx: u64,
     pub msgs_rx: u64,
     pub msgs_rx_unsolicited: u64,
+    pub msgs_rx_duplicate: u64,
     pub msgs_err: u64,
     pub healthpoints: VecDeque<NeighborHealthPoint>,
     pub msg_rx_counts: HashMap<StacksMessageID, u64>,
@@ -147,6 +152,9 @@ pub struct NeighborStats {
     pub microblocks_push_rx_counts: VecDeque<(u64, u64)>, // (count, num bytes)
     pub transaction_push_rx_counts: VecDeque<(u64, u64)>, // (count, num bytes)
     pub relayed_messages: HashMap<NeighborAddress, RelayStats>,
+    /// digests of the signed preambles of the messages this neighbor pushed to us recently, so
+    /// replays can be dropped
+    pub recent_push_digests: VecDeque<(u64, Sha512Trunc256Sum)>,
 }
 
 impl NeighborStats {
@@ -163,6 +171,7 @@ impl NeighborStats {
             msgs_tx: 0,
             msgs_rx: 0,
             msgs_rx_unsolicited: 0,
+            msgs_rx_duplicate: 0,
             msgs_err: 0,
             healthpoints: VecDeque::new(),
             msg_rx_counts: HashMap::new(),
@@ -170,6 +179,7 @@ impl NeighborStats {
             microblocks_push_rx_counts: VecDeque::new(),
             transaction_push_rx_counts: VecDeque::new(),
             relayed_messages: HashMap::new(),
+            recent_push_digests: VecDeque::new(),
         }
     }
 
@@ -223,6 +233,34 @@ impl NeighborStats {
         }
     }
 
+    /// Record the digest of a pushed message's signed preamble.
+    /// Returns false if this neighbor already sent us the same message within the window.
+    pub fn add_push_digest(&mut self, digest: Sha512Trunc256Sum) -> bool {
+        let now = get_epoch_time_secs();
+        while let Some((ts, _)) = self.recent_push_digests.front() {
+            if *ts + RECENT_PUSH_DIGEST_LIFETIME < now {
+                self.recent_push_digests.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if self
+            .recent_push_digests
+            .iter()
+            .any(|(_, recent)| *recent == digest)
+        {
+            self.msgs_rx_duplicate += 1;
+            return false;
+        }
+
+        self.recent_push_digests.push_back((now, digest));
+        while self.recent_push_digests.len() > NUM_RECENT_PUSH_DIGESTS {
+            self.recent_push_digests.pop_front();
+        }
+        true
+    }
+
     pub fn take_relayers(&mut self) -> HashMap<NeighborAddress, RelayStats> {
         let ret = mem::replace(&mut self.relayed_messages, HashMap::new());
         ret
@@ -1103,7 +1141,10 @@ impl ConversationP2P {
             );
         }
 
-        let accept_data = HandshakeAcceptData::new(local_peer, self.heartbeat);
+        let accept_data = HandshakeAcceptData {
+            handshake: HandshakeData::from_local_peer_for(local_peer, &self.peer_addrbytes),
+            heartbeat_interval: self.heartbeat,
+        };
         let accept = StacksMessage::from_chain_view(
             self.version,
             self.network_id,
@@ -1690,6 +1731,33 @@ impl ConversationP2P {
         Ok(None)
     }
 
+    /// Pass along a pushed message, unless it replays one this neighbor pushed recently.
+    /// The signed preamble covers the sender's sequence number and the payload, so a neighbor
+    /// that deliberately pushes the same data again (with a new sequence number) is let through.
+    /// Replays still count towards the neighbor's push bandwidth, but they don't get validated
+    /// again.
+    fn forward_push_message(&mut self, msg: StacksMessage) -> Option<StacksMessage> {
+        let mut bytes = vec![];
+        msg.preamble
+            .consensus_serialize(&mut bytes)
+            .expect("BUG: failed to serialize");
+        if self
+            .stats
+            .add_push_digest(Sha512Trunc256Sum::from_data(&bytes))
+        {
+            Some(msg)
+        } else {
+            debug!(
+                "{:?}: Drop replayed {} (seq {})",
+                &self,
+                msg.payload.get_message_description(),
+                msg.request_id()
+            );
+            monitoring::increment_p2p_msg_duplicate_dropped_counter();
+            None
+        }
+    }
+
     /// Handle an inbound authenticated p2p data-plane message.
     /// Return the message if not handled
     fn handle_data_message(
@@ -1738,7 +1806,7 @@ impl ConversationP2P {
                     Some(handle) => Ok(handle),
                     None => {
                         // will forward upstream
-                        return Ok(Some(msg));
+                        return Ok(self.forward_push_message(msg));
                     }
                 }
             }
@@ -1756,7 +1824,7 @@ impl ConversationP2P {
                     Some(handle) => Ok(handle),
                     None => {
                         // will forward upstream
-                        return Ok(Some(msg));
+                        return Ok(self.forward_push_message(msg));
                     }
                 }
             }
@@ -1774,10 +1842,16 @@ impl ConversationP2P {
                     Some(handle) => Ok(handle),
                     None => {
                         // will forward upstream
-                        return Ok(Some(msg));
+                        return Ok(self.forward_push_message(msg));
                     }
                 }
             }
+            StacksMessageType::BlocksAvailable(_)
+            | StacksMessageType::MicroblocksAvailable(_)
+            | StacksMessageType::StackerDBPushChunk(_) => {
+                // will forward upstream
+                return Ok(self.forward_push_message(msg));
+            }
             _ => {
                 // all else will forward upstream
                 return Ok(Some(msg));
@@ -4068,6 +4142,34 @@ mod test {
         }
     }
 
+    #[test]
+    fn convo_push_replay_window() {
+        let mut stats = NeighborStats::new(false);
+        let digest_1 = Sha512Trunc256Sum::from_data(&[1u8; 32]);
+        let digest_2 = Sha512Trunc256Sum::from_data(&[2u8; 32]);
+
+        // first sighting is accepted; a replay is not
+        assert!(stats.add_push_digest(digest_1.clone()));
+        assert!(stats.add_push_digest(digest_2.clone()));
+        assert!(!stats.add_push_digest(digest_1.clone()));
+        assert!(!stats.add_push_digest(digest_2.clone()));
+        assert_eq!(stats.msgs_rx_duplicate, 2);
+        assert_eq!(stats.recent_push_digests.len(), 2);
+
+        // digests that have aged out of the window are accepted again
+        stats.recent_push_digests.front_mut().unwrap().0 = 0;
+        assert!(stats.add_push_digest(digest_1.clone()));
+        assert!(!stats.add_push_digest(digest_2.clone()));
+        assert_eq!(stats.msgs_rx_duplicate, 3);
+
+        // the window is bounded in size
+        for i in 0..(NUM_RECENT_PUSH_DIGESTS as u64) {
+            assert!(stats.add_push_digest(Sha512Trunc256Sum::from_data(&i.to_be_bytes())));
+        }
+        assert_eq!(stats.recent_push_digests.len(), NUM_RECENT_PUSH_DIGESTS);
+        assert!(stats.add_push_digest(digest_2));
+    }
+
     #[test]
     fn convo_process_relayers() {
         let conn_opts = ConnectionOptions::default();
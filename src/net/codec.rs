@@ -43,6 +43,7 @@ use util::hash::MerkleHashFunc;
 use util::secp256k1::{Secp256k1PrivateKey, Secp256k1PublicKey};
 
 use net::db::LocalPeer;
+use net::stackerdb::{StackerDBChunkData, StackerDBPushChunkData, STACKERDB_MAX_CHUNK_SIZE};
 use net::Error as net_error;
 use net::*;
 
@@ -727,6 +728,23 @@ impl HandshakeData {
             data_url: data_url,
         }
     }
+
+    /// Make handshake data to send to a particular peer.  If the peer can't reach our primary
+    /// data URL (e.g. it's an IPv4 URL and the peer is on IPv6), then advertise the first of our
+    /// alternative data URLs that it can reach instead.
+    pub fn from_local_peer_for(local_peer: &LocalPeer, peer_addr: &PeerAddress) -> HandshakeData {
+        let mut handshake_data = HandshakeData::from_local_peer(local_peer);
+        if !handshake_data.data_url.is_reachable_from(peer_addr) {
+            if let Some(data_url) = local_peer
+                .alt_data_urls
+                .iter()
+                .find(|url| url.is_reachable_from(peer_addr))
+            {
+                handshake_data.data_url = data_url.clone();
+            }
+        }
+        handshake_data
+    }
 }
 
 impl StacksMessageCodec for HandshakeData {
@@ -880,6 +898,48 @@ impl StacksMessageCodec for RelayData {
     }
 }
 
+impl StacksMessageCodec for StackerDBChunkData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), net_error> {
+        write_next(fd, &self.slot_id)?;
+        write_next(fd, &self.slot_version)?;
+        write_next(fd, &self.sig)?;
+        write_next(fd, &self.data)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<StackerDBChunkData, net_error> {
+        let slot_id: u32 = read_next(fd)?;
+        let slot_version: u32 = read_next(fd)?;
+        let sig: MessageSignature = read_next(fd)?;
+        let data: Vec<u8> = read_next_at_most::<_, u8>(fd, STACKERDB_MAX_CHUNK_SIZE)?;
+        Ok(StackerDBChunkData {
+            slot_id,
+            slot_version,
+            sig,
+            data,
+        })
+    }
+}
+
+impl StacksMessageCodec for StackerDBPushChunkData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), net_error> {
+        write_next(fd, &StacksAddress::from(self.contract_id.issuer.clone()))?;
+        write_next(fd, &self.contract_id.name)?;
+        write_next(fd, &self.chunk_data)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<StackerDBPushChunkData, net_error> {
+        let issuer: StacksAddress = read_next(fd)?;
+        let name: ContractName = read_next(fd)?;
+        let chunk_data: StackerDBChunkData = read_next(fd)?;
+        Ok(StackerDBPushChunkData {
+            contract_id: QualifiedContractIdentifier::new(issuer.into(), name),
+            chunk_data,
+        })
+    }
+}
+
 impl StacksMessageType {
     pub fn get_message_id(&self) -> StacksMessageID {
         match *self {
@@ -904,6 +964,7 @@ impl StacksMessageType {
             StacksMessageType::Pong(ref _m) => StacksMessageID::Pong,
             StacksMessageType::NatPunchRequest(ref _m) => StacksMessageID::NatPunchRequest,
             StacksMessageType::NatPunchReply(ref _m) => StacksMessageID::NatPunchReply,
+            StacksMessageType::StackerDBPushChunk(ref _m) => StacksMessageID::StackerDBPushChunk,
         }
     }
 
@@ -928,6 +989,7 @@ impl StacksMessageType {
             StacksMessageType::Pong(ref _m) => "Pong",
             StacksMessageType::NatPunchRequest(ref _m) => "NatPunchRequest",
             StacksMessageType::NatPunchReply(ref _m) => "NatPunchReply",
+            StacksMessageType::StackerDBPushChunk(ref _m) => "StackerDBPushChunk",
         }
     }
 
@@ -986,6 +1048,10 @@ impl StacksMessageType {
             StacksMessageType::NatPunchReply(ref m) => {
                 format!("NatPunchReply({},{}:{})", m.nonce, &m.addrbytes, m.port)
             }
+            StacksMessageType::StackerDBPushChunk(ref m) => format!(
+                "StackerDBPushChunk({},{},{})",
+                &m.contract_id, m.chunk_data.slot_id, m.chunk_data.slot_version
+            ),
         }
     }
 }
@@ -1019,6 +1085,9 @@ impl StacksMessageCodec for StacksMessageID {
             x if x == StacksMessageID::Pong as u8 => StacksMessageID::Pong,
             x if x == StacksMessageID::NatPunchRequest as u8 => StacksMessageID::NatPunchRequest,
             x if x == StacksMessageID::NatPunchReply as u8 => StacksMessageID::NatPunchReply,
+            x if x == StacksMessageID::StackerDBPushChunk as u8 => {
+                StacksMessageID::StackerDBPushChunk
+            }
             _ => {
                 return Err(net_error::DeserializeError(
                     "Unknown message ID".to_string(),
@@ -1052,6 +1121,7 @@ impl StacksMessageCodec for StacksMessageType {
             StacksMessageType::Pong(ref m) => write_next(fd, m)?,
             StacksMessageType::NatPunchRequest(ref nonce) => write_next(fd, nonce)?,
             StacksMessageType::NatPunchReply(ref m) => write_next(fd, m)?,
+            StacksMessageType::StackerDBPushChunk(ref m) => write_next(fd, m)?,
         }
         Ok(())
     }
@@ -1129,6 +1199,10 @@ impl StacksMessageCodec for StacksMessageType {
                 let m: NatPunchData = read_next(fd)?;
                 StacksMessageType::NatPunchReply(m)
             }
+            StacksMessageID::StackerDBPushChunk => {
+                let m: StackerDBPushChunkData = read_next(fd)?;
+                StacksMessageType::StackerDBPushChunk(m)
+            }
             StacksMessageID::Reserved => {
                 return Err(net_error::DeserializeError(
                     "Unsupported message ID 'reserved'".to_string(),
@@ -1963,6 +2037,50 @@ pub mod test {
         check_codec_and_corruption::<HandshakeAcceptData>(&data, &bytes);
     }
 
+    #[test]
+    fn handshake_data_url_for_peer() {
+        let mut local_peer = LocalPeer::new(
+            0x80000000,
+            0x80000000,
+            PeerAddress::from_ipv4(1, 2, 3, 4),
+            20444,
+            None,
+            u64::max_value(),
+            UrlString::try_from("http://1.2.3.4:20443").unwrap(),
+        );
+        let ipv4_peer = PeerAddress::from_ipv4(5, 6, 7, 8);
+        let ipv6_peer = PeerAddress::from_ip(&"2001:db8::1".parse().unwrap());
+
+        // no alternatives, so always advertise the primary data URL
+        assert_eq!(
+            HandshakeData::from_local_peer_for(&local_peer, &ipv6_peer).data_url,
+            local_peer.data_url
+        );
+
+        local_peer.alt_data_urls = vec![
+            UrlString::try_from("http://[2001:db8::2]:20443").unwrap(),
+            UrlString::try_from("https://node.example.com").unwrap(),
+        ];
+
+        // IPv4 peers can reach the primary URL
+        assert_eq!(
+            HandshakeData::from_local_peer_for(&local_peer, &ipv4_peer).data_url,
+            local_peer.data_url
+        );
+
+        // IPv6 peers get the first alternative they can reach
+        assert_eq!(
+            HandshakeData::from_local_peer_for(&local_peer, &ipv6_peer).data_url,
+            local_peer.alt_data_urls[0]
+        );
+
+        local_peer.alt_data_urls.remove(0);
+        assert_eq!(
+            HandshakeData::from_local_peer_for(&local_peer, &ipv6_peer).data_url,
+            local_peer.alt_data_urls[0]
+        );
+    }
+
     #[test]
     fn codec_NackData() {
         let data = NackData {
@@ -2163,6 +2281,18 @@ pub mod test {
                 port: 12345,
                 nonce: 0x12345678,
             }),
+            StacksMessageType::StackerDBPushChunk(StackerDBPushChunkData {
+                contract_id: QualifiedContractIdentifier::parse(
+                    "SP000000000000000000002Q6VF78.stackerdb",
+                )
+                .unwrap(),
+                chunk_data: StackerDBChunkData {
+                    slot_id: 1,
+                    slot_version: 2,
+                    sig: MessageSignature([0x33; 65]),
+                    data: vec![0x01, 0x02, 0x03],
+                },
+            }),
         ];
 
         let mut maximal_relayers: Vec<RelayData> = vec![];
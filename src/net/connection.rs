@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::io;
 use std::io::{Read, Write};
@@ -35,10 +35,12 @@ use mio;
 use mio::net as mio_net;
 
 use net::codec::*;
+use net::http::HttpRateLimit;
 use net::Error as net_error;
 use net::HttpRequestPreamble;
 use net::HttpResponsePreamble;
 use net::MessageSequence;
+use net::NeighborKey;
 use net::PeerAddress;
 use net::Preamble;
 use net::ProtocolFamily;
@@ -55,7 +57,10 @@ use net::neighbors::{
     WALK_MIN_DURATION, WALK_RESET_INTERVAL, WALK_RESET_PROB, WALK_RETRY_COUNT, WALK_STATE_TIMEOUT,
 };
 
-use vm::{costs::ExecutionCost, types::BOUND_VALUE_SERIALIZATION_HEX};
+use util::strings::UrlString;
+use vm::{
+    costs::ExecutionCost, types::QualifiedContractIdentifier, types::BOUND_VALUE_SERIALIZATION_HEX,
+};
 
 use chainstate::burn::ConsensusHash;
 
@@ -239,6 +244,19 @@ impl<P: ProtocolFamily> NetworkReplyHandle<P> {
         }
     }
 
+    /// Try to move buffered data along to the reader, without dropping the inner pipe (so more data
+    /// can be written afterwards).
+    /// Return the number of bytes that are still buffered.
+    pub fn try_flush_pending(&mut self) -> Result<usize, net_error> {
+        match self.request_pipe_write {
+            Some(ref mut fd) => {
+                fd.try_flush().map_err(net_error::WriteError)?;
+                Ok(fd.pending())
+            }
+            None => Ok(0),
+        }
+    }
+
     /// Try to flush the inner pipe writer.  If we succeed, drop the inner pipe.
     /// Only call this once you're done sending -- this is just to move the data along.
     /// Return true if we're done sending; false if we need to call this again.
@@ -351,6 +369,22 @@ pub struct ConnectionOptions {
     pub max_inflight_attachments: u64,
     pub read_only_call_limit: ExecutionCost,
     pub maximum_call_argument_size: u32,
+    pub mempool_view_max_txs: u64,
+    /// most queries a client may send in one POST to /v2/batch
+    pub max_batch_queries: u64,
+    pub block_proposal_token: Option<String>,
+    pub admin_token: Option<String>,
+    /// if set, RPC clients (other than on the loopback interface) must send this token (or the
+    /// admin or block proposal token) as their Authorization header
+    pub rpc_auth_token: Option<String>,
+    /// how many RPC requests each client IP address may make, across all routes
+    pub rpc_rate_limit: Option<HttpRateLimit>,
+    /// how many expensive RPC requests (see `HttpRequestType::is_expensive`) each client IP
+    /// address may make, across all expensive routes
+    pub rpc_expensive_rate_limit: Option<HttpRateLimit>,
+    /// how many RPC requests each client IP address may make to a given route, keyed by the
+    /// route as reported by `HttpRequestType::route`
+    pub rpc_route_rate_limits: HashMap<String, HttpRateLimit>,
     pub max_block_push_bandwidth: u64,
     pub max_microblocks_push_bandwidth: u64,
     pub max_transaction_push_bandwidth: u64,
@@ -366,6 +400,10 @@ pub struct ConnectionOptions {
     pub max_buffered_microblocks_available: u64,
     pub max_buffered_blocks: u64,
     pub max_buffered_microblocks: u64,
+    pub relay_neighbors: Vec<NeighborKey>,
+    pub alt_data_urls: Vec<UrlString>,
+    /// StackerDBs this node replicates
+    pub stackerdb_contracts: Vec<QualifiedContractIdentifier>,
 
     // fault injection
     pub disable_neighbor_walk: bool,
@@ -427,6 +465,14 @@ impl std::default::Default for ConnectionOptions {
                 runtime: 10000000,
             },
             maximum_call_argument_size: 20 * BOUND_VALUE_SERIALIZATION_HEX,
+            mempool_view_max_txs: 256, // most pending txs applied to answer a mempool-view query
+            max_batch_queries: 100,    // most queries answered in one /v2/batch request
+            block_proposal_token: None, // block proposal validation is disabled by default
+            admin_token: None,         // admin endpoints are disabled by default
+            rpc_auth_token: None,      // RPC clients need no authorization by default
+            rpc_rate_limit: None,      // no RPC rate limits by default
+            rpc_expensive_rate_limit: None,
+            rpc_route_rate_limits: HashMap::new(),
             max_block_push_bandwidth: 0, // infinite upload bandwidth allowed
             max_microblocks_push_bandwidth: 0, // infinite upload bandwidth allowed
             max_transaction_push_bandwidth: 0, // infinite upload bandwidth allowed
@@ -442,6 +488,9 @@ impl std::default::Default for ConnectionOptions {
             max_buffered_microblocks_available: 1,
             max_buffered_blocks: 1,
             max_buffered_microblocks: 10,
+            relay_neighbors: vec![], // upstream peers we always stay connected to, and push to
+            alt_data_urls: vec![], // data URLs to advertise to peers that can't reach the primary one
+            stackerdb_contracts: vec![], // replicate no StackerDBs by default
 
             // no faults on by default
             disable_neighbor_walk: false,
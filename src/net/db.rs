@@ -16,6 +16,8 @@
 
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use rusqlite::types::ToSql;
 use rusqlite::Row;
 use rusqlite::Transaction;
@@ -65,6 +67,69 @@ pub const PEERDB_VERSION: &'static str = "23.0.0.0";
 
 const NUM_SLOTS: usize = 8;
 
+/// A neighbor as it appears in a peer DB export, along with what this node has learned about it.
+/// Used to seed a new node's frontier from an existing node's.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerDBExportEntry {
+    pub peer_version: u32,
+    pub network_id: u32,
+    pub addrbytes: PeerAddress,
+    pub port: u16,
+    pub public_key: String,
+    pub expire_block_height: u64,
+    pub last_contact_time: u64,
+    pub allowed: i64,
+    pub denied: i64,
+    pub asn: u32,
+    pub org: u32,
+    pub in_degree: u32,
+    pub out_degree: u32,
+}
+
+impl PeerDBExportEntry {
+    pub fn from_neighbor(neighbor: &Neighbor) -> PeerDBExportEntry {
+        PeerDBExportEntry {
+            peer_version: neighbor.addr.peer_version,
+            network_id: neighbor.addr.network_id,
+            addrbytes: neighbor.addr.addrbytes.clone(),
+            port: neighbor.addr.port,
+            public_key: to_hex(&neighbor.public_key.to_bytes_compressed()),
+            expire_block_height: neighbor.expire_block,
+            last_contact_time: neighbor.last_contact_time,
+            allowed: neighbor.allowed,
+            denied: neighbor.denied,
+            asn: neighbor.asn,
+            org: neighbor.org,
+            in_degree: neighbor.in_degree,
+            out_degree: neighbor.out_degree,
+        }
+    }
+
+    pub fn to_neighbor(&self) -> Result<Neighbor, db_error> {
+        let mut public_key =
+            Secp256k1PublicKey::from_hex(&self.public_key).map_err(|_e| db_error::ParseError)?;
+        public_key.set_compressed(true);
+
+        Ok(Neighbor {
+            addr: NeighborKey {
+                peer_version: self.peer_version,
+                network_id: self.network_id,
+                addrbytes: self.addrbytes.clone(),
+                port: self.port,
+            },
+            public_key: public_key,
+            expire_block: self.expire_block_height,
+            last_contact_time: self.last_contact_time,
+            asn: self.asn,
+            org: self.org,
+            allowed: self.allowed,
+            denied: self.denied,
+            in_degree: self.in_degree,
+            out_degree: self.out_degree,
+        })
+    }
+}
+
 impl PeerAddress {
     pub fn to_bin(&self) -> String {
         to_bin(&self.0)
@@ -110,6 +175,7 @@ pub struct LocalPeer {
 
     // filled in and curated at runtime
     pub public_ip_address: Option<(PeerAddress, u16)>,
+    pub alt_data_urls: Vec<UrlString>, // other URLs we can serve data from (e.g. IPv6 or DNS)
 }
 
 impl fmt::Display for LocalPeer {
@@ -178,6 +244,7 @@ impl LocalPeer {
             services: services as u16,
             data_url: data_url,
             public_ip_address: None,
+            alt_data_urls: vec![],
         }
     }
 
@@ -230,6 +297,7 @@ impl FromRow<LocalPeer> for LocalPeer {
             services: services,
             data_url: data_url,
             public_ip_address: None,
+            alt_data_urls: vec![],
         })
     }
 }
@@ -595,6 +663,28 @@ impl PeerDB {
         Ok(db)
     }
 
+    /// Open an existing peer database as-is, without touching its local peer record or its
+    /// allow/deny lists.  Used by offline tooling.
+    pub fn open(path: &String, readwrite: bool) -> Result<PeerDB, db_error> {
+        if fs::metadata(path).is_err() {
+            return Err(db_error::NoDBError);
+        }
+        let open_flags = if readwrite {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        };
+
+        let conn =
+            Connection::open_with_flags(path, open_flags).map_err(|e| db_error::SqliteError(e))?;
+        conn.busy_handler(Some(tx_busy_handler))?;
+
+        Ok(PeerDB {
+            conn: conn,
+            readwrite: readwrite,
+        })
+    }
+
     /// Open a burn database in memory (used for testing)
     #[cfg(test)]
     pub fn connect_memory(
@@ -1349,6 +1439,56 @@ impl PeerDB {
         let rows = query_rows::<Neighbor, _>(conn, &qry, NO_PARAMS)?;
         Ok(rows)
     }
+
+    /// Export the frontier for the given network, most-recently-contacted peers first.
+    pub fn export_peers(
+        conn: &DBConn,
+        network_id: u32,
+    ) -> Result<Vec<PeerDBExportEntry>, db_error> {
+        let qry = "SELECT * FROM frontier WHERE network_id = ?1 ORDER BY last_contact_time DESC, addrbytes ASC, port ASC".to_string();
+        let args: &[&dyn ToSql] = &[&network_id];
+        let rows = query_rows::<Neighbor, _>(conn, &qry, args)?;
+        Ok(rows
+            .iter()
+            .map(|neighbor| PeerDBExportEntry::from_neighbor(neighbor))
+            .collect())
+    }
+
+    /// Seed the frontier from another node's export.  Entries for other networks are skipped.
+    /// Peers already in the frontier are left alone, since this node's own view of them is more
+    /// relevant than the exporter's.  Returns the number of peers inserted.
+    pub fn import_peers<'a>(
+        tx: &mut Transaction<'a>,
+        network_id: u32,
+        entries: &[PeerDBExportEntry],
+    ) -> Result<u64, db_error> {
+        let mut num_inserted = 0;
+        for entry in entries.iter() {
+            if entry.network_id != network_id {
+                debug!(
+                    "Skip imported peer {:?}:{}: network ID {} != {}",
+                    &entry.addrbytes, entry.port, entry.network_id, network_id
+                );
+                continue;
+            }
+
+            let neighbor = entry.to_neighbor()?;
+            let present = PeerDB::get_peer(
+                tx,
+                neighbor.addr.network_id,
+                &neighbor.addr.addrbytes,
+                neighbor.addr.port,
+            )?;
+            if present.is_some() {
+                continue;
+            }
+
+            if PeerDB::try_insert_peer(tx, &neighbor)? {
+                num_inserted += 1;
+            }
+        }
+        Ok(num_inserted)
+    }
 }
 
 #[cfg(test)]
@@ -1530,6 +1670,107 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_export_import_peers() {
+        let mut neighbors = vec![];
+        for i in 0..10 {
+            neighbors.push(Neighbor {
+                addr: NeighborKey {
+                    peer_version: 0x12345678,
+                    network_id: if i < 8 { 0x9abcdef0 } else { 0x9abcdef1 },
+                    addrbytes: PeerAddress::from_ipv4(127, 0, 0, i as u8),
+                    port: 12345 + i,
+                },
+                public_key: Secp256k1PublicKey::from_private(&Secp256k1PrivateKey::new()),
+                expire_block: 23456 + (i as u64),
+                last_contact_time: 1552509642 + (i as u64),
+                allowed: if i == 0 { -1 } else { 0 },
+                denied: if i == 1 { -1 } else { 0 },
+                asn: 34567,
+                org: 45678,
+                in_degree: i as u32,
+                out_degree: 1,
+            });
+        }
+
+        let mut src_db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &vec![],
+        )
+        .unwrap();
+        {
+            let mut tx = src_db.tx_begin().unwrap();
+            for neighbor in neighbors.iter() {
+                assert!(PeerDB::try_insert_peer(&mut tx, neighbor).unwrap());
+            }
+            tx.commit().unwrap();
+        }
+
+        // only this network's peers, most-recently-contacted first, and survives JSON
+        let exported = PeerDB::export_peers(src_db.conn(), 0x9abcdef0).unwrap();
+        assert_eq!(exported.len(), 8);
+        for (i, entry) in exported.iter().enumerate() {
+            assert_eq!(entry.to_neighbor().unwrap(), neighbors[7 - i]);
+        }
+        let json = serde_json::to_string(&exported).unwrap();
+        let exported: Vec<PeerDBExportEntry> = serde_json::from_str(&json).unwrap();
+
+        // a peer the new node already knows about is left alone
+        let mut known = neighbors[3].clone();
+        known.last_contact_time = 0;
+        let mut dest_db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://bar.com".into(),
+            &vec![],
+            &vec![],
+        )
+        .unwrap();
+        {
+            let mut tx = dest_db.tx_begin().unwrap();
+            assert!(PeerDB::try_insert_peer(&mut tx, &known).unwrap());
+            tx.commit().unwrap();
+        }
+
+        {
+            let mut tx = dest_db.tx_begin().unwrap();
+            let num_imported = PeerDB::import_peers(&mut tx, 0x9abcdef0, &exported).unwrap();
+            tx.commit().unwrap();
+            assert_eq!(num_imported, 7);
+        }
+
+        let imported = PeerDB::get_all_peers(dest_db.conn()).unwrap();
+        assert_eq!(imported.len(), 8);
+        for neighbor in neighbors[0..8].iter() {
+            let peer = PeerDB::get_peer(
+                dest_db.conn(),
+                0x9abcdef0,
+                &neighbor.addr.addrbytes,
+                neighbor.addr.port,
+            )
+            .unwrap()
+            .unwrap();
+            if neighbor.addr == known.addr {
+                assert_eq!(peer, known);
+            } else {
+                assert_eq!(&peer, neighbor);
+            }
+        }
+
+        // importing again is a no-op
+        {
+            let mut tx = dest_db.tx_begin().unwrap();
+            let num_imported = PeerDB::import_peers(&mut tx, 0x9abcdef0, &exported).unwrap();
+            tx.commit().unwrap();
+            assert_eq!(num_imported, 0);
+        }
+    }
+
     #[test]
     fn test_initial_neighbors() {
         let mut initial_neighbors = vec![];
@@ -123,6 +123,7 @@ pub const BLOCK_REREQUEST_INTERVAL: u64 = 30;
 pub enum BlockRequestKeyKind {
     Block,
     ConfirmedMicroblockStream,
+    Header,
 }
 
 #[derive(Debug, PartialEq, Clone, Hash, Eq)]
@@ -183,6 +184,11 @@ impl Requestable for BlockRequestKey {
                     self.index_block_hash,
                 )
             }
+            BlockRequestKeyKind::Header => HttpRequestType::GetHeaders(
+                HttpRequestMetadata::from_host(peer_host),
+                1,
+                Some(self.index_block_hash),
+            ),
         }
     }
 }
@@ -252,6 +258,10 @@ pub struct BlockDownloader {
     getmicroblocks_requests: HashMap<BlockRequestKey, usize>,
     blocks: HashMap<BlockRequestKey, StacksBlock>,
     microblocks: HashMap<BlockRequestKey, Vec<StacksMicroblock>>,
+    headers: HashMap<BlockRequestKey, StacksBlockHeader>,
+
+    /// Fetch only anchored block headers (and no microblocks), for a header-only node
+    headers_only: bool,
 
     /// statistics on peers' data-plane endpoints
     dead_peers: Vec<usize>,
@@ -302,6 +312,8 @@ impl BlockDownloader {
             getmicroblocks_requests: HashMap::new(),
             blocks: HashMap::new(),
             microblocks: HashMap::new(),
+            headers: HashMap::new(),
+            headers_only: false,
 
             dead_peers: vec![],
             broken_peers: vec![],
@@ -327,6 +339,7 @@ impl BlockDownloader {
         self.microblocks_to_try.clear();
         self.blocks.clear();
         self.microblocks.clear();
+        self.headers.clear();
 
         self.dead_peers.clear();
         self.broken_peers.clear();
@@ -509,6 +522,32 @@ impl BlockDownloader {
                                     self.blocks.insert(block_key, block);
                                 }
                             }
+                            HttpResponseType::Headers(_md, mut headers) => {
+                                if headers.len() != 1
+                                    || headers[0].index_block_hash() != block_key.index_block_hash
+                                {
+                                    info!(
+                                        "Invalid headers from {:?} ({:?}): did not ask for {} headers ending at {}/{}",
+                                        &block_key.neighbor,
+                                        &block_key.data_url,
+                                        headers.len(),
+                                        &block_key.consensus_hash,
+                                        &block_key.anchor_block_hash
+                                    );
+                                    self.broken_peers.push(event_id);
+                                    self.broken_neighbors.push(block_key.neighbor.clone());
+                                } else {
+                                    // got the header
+                                    let header = headers.pop().unwrap().header;
+                                    debug!(
+                                        "Got header {}: {}/{}",
+                                        &block_key.sortition_height,
+                                        &block_key.consensus_hash,
+                                        header.block_hash()
+                                    );
+                                    self.headers.insert(block_key, header);
+                                }
+                            }
                             // TODO: redirect?
                             HttpResponseType::NotFound(_, _) => {
                                 // remote peer didn't have the block
@@ -1115,13 +1154,20 @@ impl PeerNetwork {
             }
 
             let (target_consensus_hash, target_block_hash) = if !microblocks {
-                // asking for a block
-                if !PeerNetwork::need_anchored_block(
-                    &self.local_peer,
-                    chainstate,
-                    &consensus_hash,
-                    &block_hash,
-                )? {
+                // asking for a block (or just its header)
+                let need_block = match self.header_chain {
+                    Some(ref header_chain) => {
+                        !header_chain.has_header(&consensus_hash, &block_hash)?
+                            && !header_chain.has_staging_header(&consensus_hash, &block_hash)?
+                    }
+                    None => PeerNetwork::need_anchored_block(
+                        &self.local_peer,
+                        chainstate,
+                        &consensus_hash,
+                        &block_hash,
+                    )?,
+                };
+                if !need_block {
                     // we already have this block stored to disk
                     test_debug!(
                         "{:?}: Already have anchored block {}/{}",
@@ -1325,6 +1371,8 @@ impl PeerNetwork {
                     (i as u64) + start_sortition_height,
                     if microblocks {
                         BlockRequestKeyKind::ConfirmedMicroblockStream
+                    } else if downloader.headers_only {
+                        BlockRequestKeyKind::Header
                     } else {
                         BlockRequestKeyKind::Block
                     },
@@ -1363,6 +1411,10 @@ impl PeerNetwork {
         downloader: &BlockDownloader,
         start_sortition_height: u64,
     ) -> Result<HashMap<u64, VecDeque<BlockRequestKey>>, net_error> {
+        if downloader.headers_only {
+            // header-only nodes don't need microblocks
+            return Ok(HashMap::new());
+        }
         self.make_requests(sortdb, chainstate, downloader, start_sortition_height, true)
     }
 
@@ -1889,9 +1941,10 @@ impl PeerNetwork {
         })
     }
 
-    /// Process newly-fetched blocks and microblocks.
+    /// Process newly-fetched blocks, microblocks, and headers.  Headers are stored straight to the
+    /// header chain.
     /// Returns true if we've completed all requests.
-    /// Returns (done?, at-chain-tip?, blocks-we-got, microblocks-we-got) on success
+    /// Returns (done?, at-chain-tip?, blocks-we-got, microblocks-we-got, headers-we-stored) on success
     fn finish_downloads(
         &mut self,
         sortdb: &SortitionDB,
@@ -1903,11 +1956,13 @@ impl PeerNetwork {
             Option<PoxId>,
             Vec<(ConsensusHash, StacksBlock, u64)>,
             Vec<(ConsensusHash, Vec<StacksMicroblock>, u64)>,
+            Vec<(ConsensusHash, StacksBlockHeader, u64)>,
         ),
         net_error,
     > {
         let mut blocks = vec![];
         let mut microblocks = vec![];
+        let mut headers = vec![];
         let mut done = false;
         let mut at_chain_tip = false;
         let mut old_pox_id = None;
@@ -1936,6 +1991,49 @@ impl PeerNetwork {
                     .blocks_to_try
                     .remove(&request_key.sortition_height);
             }
+            for (request_key, header) in downloader.headers.drain() {
+                let download_time = now.saturating_sub(request_key.download_start);
+                let header_chain = network
+                    .header_chain
+                    .as_mut()
+                    .expect("BUG: downloaded a header, but not in header-only mode");
+                match header_chain.preprocess_header(
+                    &sortdb.index_conn(),
+                    &request_key.consensus_hash,
+                    &header,
+                    download_time,
+                ) {
+                    Ok(true) => {
+                        debug!(
+                            "Downloaded header {}/{} ({}) at sortition height {}",
+                            &request_key.consensus_hash,
+                            &request_key.anchor_block_hash,
+                            &request_key.index_block_hash,
+                            request_key.sortition_height
+                        );
+                        headers.push((request_key.consensus_hash.clone(), header, download_time));
+                        downloader.num_blocks_downloaded += 1;
+                    }
+                    Ok(false) => {}
+                    Err(chainstate_error::InvalidStacksBlock(msg)) => {
+                        info!(
+                            "Invalid header from {:?} ({:?}): {}",
+                            &request_key.neighbor, &request_key.data_url, &msg
+                        );
+                        downloader
+                            .broken_neighbors
+                            .push(request_key.neighbor.clone());
+                    }
+                    Err(e) => {
+                        return Err(e.into());
+                    }
+                }
+
+                // don't try this again
+                downloader
+                    .blocks_to_try
+                    .remove(&request_key.sortition_height);
+            }
             for (request_key, mut microblock_stream) in downloader.microblocks.drain() {
                 // NOTE: microblock streams are served in reverse order, since they're forks
                 microblock_stream.reverse();
@@ -2128,7 +2226,7 @@ impl PeerNetwork {
                 downloader.state = BlockDownloaderState::GetBlocksBegin;
             }
 
-            Ok((done, at_chain_tip, old_pox_id, blocks, microblocks))
+            Ok((done, at_chain_tip, old_pox_id, blocks, microblocks, headers))
         })
     }
 
@@ -2139,6 +2237,9 @@ impl PeerNetwork {
             self.connection_opts.download_interval,
             self.connection_opts.max_inflight_blocks,
         ));
+        if let Some(ref mut downloader) = self.block_downloader {
+            downloader.headers_only = self.header_chain.is_some();
+        }
     }
 
     /// Initialize the attachment downloader
@@ -2154,6 +2255,7 @@ impl PeerNetwork {
     /// * what's the local PoX ID when we started?  Will be Some(..) when we're done
     /// * List of blocks we downloaded
     /// * List of microblock streams we downloaded
+    /// * List of headers we downloaded and stored to the header chain
     /// * List of broken HTTP event IDs to disconnect from
     /// * List of broken p2p neighbor keys to disconnect from
     pub fn download_blocks(
@@ -2168,6 +2270,7 @@ impl PeerNetwork {
             Option<PoxId>,
             Vec<(ConsensusHash, StacksBlock, u64)>,
             Vec<(ConsensusHash, Vec<StacksMicroblock>, u64)>,
+            Vec<(ConsensusHash, StacksBlockHeader, u64)>,
             Vec<usize>,
             Vec<NeighborKey>,
         ),
@@ -2199,7 +2302,7 @@ impl PeerNetwork {
                             &self.local_peer,
                             downloader.finished_scan_at + downloader.download_interval
                         );
-                        return Ok((true, true, None, vec![], vec![], vec![], vec![]));
+                        return Ok((true, true, None, vec![], vec![], vec![], vec![], vec![]));
                     } else {
                         // start a rescan -- we've waited long enough
                         debug!(
@@ -2224,6 +2327,7 @@ impl PeerNetwork {
 
         let mut blocks = vec![];
         let mut microblocks = vec![];
+        let mut headers = vec![];
         let mut old_pox_id = None;
 
         let mut done_cycle = false;
@@ -2259,11 +2363,13 @@ impl PeerNetwork {
                         downloader_pox_id,
                         mut successful_blocks,
                         mut successful_microblocks,
+                        mut successful_headers,
                     ) = self.finish_downloads(sortdb, chainstate)?;
 
                     old_pox_id = downloader_pox_id;
                     blocks.append(&mut successful_blocks);
                     microblocks.append(&mut successful_microblocks);
+                    headers.append(&mut successful_headers);
                     done = blocks_done;
                     at_chain_tip = full_pass;
 
@@ -2297,6 +2403,7 @@ impl PeerNetwork {
             old_pox_id,
             blocks,
             microblocks,
+            headers,
             broken_http_peers,
             broken_p2p_peers,
         ))
@@ -23,24 +23,35 @@ use std::io;
 use std::io::prelude::*;
 use std::io::{Read, Write};
 use std::mem;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::str;
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 use serde_json;
 
+use burnchains::bitcoin::address::BitcoinAddress;
 use burnchains::{Address, Txid};
-use chainstate::burn::BlockHeaderHash;
+use chainstate::burn::{BlockHeaderHash, ConsensusHash};
 use chainstate::stacks::{
     StacksAddress, StacksBlock, StacksBlockId, StacksMicroblock, StacksPublicKey, StacksTransaction,
 };
-use net::atlas::{Attachment, BNS_NAME_REGEX};
+use core::scheduler::ScheduledCallRequest;
+use net::atlas::{Attachment, BNS_NAMESPACE_MAX_LEN, BNS_NAMESPACE_MIN_LEN, BNS_NAME_REGEX};
 use net::codec::{read_next, write_next};
+use net::stackerdb::{
+    SlotMetadata, StackerDBChunkAckData, StackerDBChunkData, STACKERDB_MAX_CHUNK_SIZE,
+};
+use net::AggregationCommitRequestBody;
+use net::BatchQuery;
+use net::BatchQueryBody;
+use net::BurnchainOpsQuery;
 use net::CallReadOnlyRequestBody;
 use net::ClientError;
+use net::ContractAnalysisRequestBody;
 use net::Error as net_error;
 use net::HttpContentType;
+use net::HttpDeprecation;
 use net::HttpRequestMetadata;
 use net::HttpRequestPreamble;
 use net::HttpRequestType;
@@ -53,17 +64,25 @@ use net::NeighborAddress;
 use net::PeerAddress;
 use net::PeerHost;
 use net::ProtocolFamily;
+use net::RPCDevMineRequest;
+use net::RPCStateView;
+use net::ScheduledCallsOp;
 use net::StacksHttpMessage;
 use net::StacksHttpPreamble;
 use net::StacksMessageCodec;
 use net::UnconfirmedTransactionResponse;
 use net::UnconfirmedTransactionStatus;
+use net::DEFAULT_PRINCIPAL_TRANSACTIONS_LIMIT;
 use net::HTTP_PREAMBLE_MAX_ENCODED_SIZE;
 use net::HTTP_PREAMBLE_MAX_NUM_HEADERS;
 use net::HTTP_REQUEST_ID_RESERVED;
 use net::MAX_MESSAGE_LEN;
 use net::MAX_MICROBLOCKS_UNCONFIRMED;
-use net::{GetAttachmentResponse, GetAttachmentsInvResponse, PostTransactionRequestBody};
+use net::MAX_PRINCIPAL_TRANSACTIONS_LIMIT;
+use net::{
+    GetAttachmentInstancesResponse, GetAttachmentResponse, GetAttachmentsInvResponse,
+    PostTransactionRequestBody,
+};
 
 use util::hash::hex_bytes;
 use util::hash::to_hex;
@@ -76,7 +95,7 @@ use vm::{
     ast::parser::{
         CLARITY_NAME_REGEX, CONTRACT_NAME_REGEX, PRINCIPAL_DATA_REGEX, STANDARD_PRINCIPAL_REGEX,
     },
-    types::{PrincipalData, BOUND_VALUE_SERIALIZATION_HEX},
+    types::{PrincipalData, QualifiedContractIdentifier, BOUND_VALUE_SERIALIZATION_HEX},
     ClarityName, ContractName, Value,
 };
 
@@ -94,14 +113,35 @@ use time;
 lazy_static! {
     static ref PATH_GETINFO: Regex = Regex::new(r#"^/v2/info$"#).unwrap();
     static ref PATH_GETPOXINFO: Regex = Regex::new(r#"^/v2/pox$"#).unwrap();
+    static ref PATH_GETREWARDSET: Regex = Regex::new(r#"^/v2/pox/reward_set$"#).unwrap();
+    static ref PATH_GET_POX_DELEGATIONS: Regex = Regex::new(&format!(
+        "^/v2/pox/delegations/(?P<principal>{})$",
+        *PRINCIPAL_DATA_REGEX
+    ))
+    .unwrap();
+    static ref PATH_POST_AGGREGATION_COMMIT: Regex = Regex::new(&format!(
+        "^/v2/pox/aggregation_commit/(?P<principal>{})$",
+        *PRINCIPAL_DATA_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GETBURNBLOCKINFO: Regex =
+        Regex::new(r#"^/v2/burn_blocks/([0-9]{1,20})$"#).unwrap();
+    static ref PATH_GETBURNBLOCKOPS: Regex =
+        Regex::new(r#"^/v2/burn_blocks/([0-9]{1,20})/ops$"#).unwrap();
+    static ref PATH_GETBURNOP: Regex = Regex::new(r#"^/v2/burn_ops/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETNEIGHBORS: Regex = Regex::new(r#"^/v2/neighbors$"#).unwrap();
     static ref PATH_GETBLOCK: Regex = Regex::new(r#"^/v2/blocks/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GET_BLOCK_EVENT_BLOOM: Regex =
+        Regex::new(r#"^/v2/blocks/([0-9a-f]{64})/event_bloom$"#).unwrap();
+    static ref PATH_GETHEADERS: Regex = Regex::new(r#"^/v2/headers/([0-9]{1,20})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_INDEXED: Regex =
         Regex::new(r#"^/v2/microblocks/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_CONFIRMED: Regex =
         Regex::new(r#"^/v2/microblocks/confirmed/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/microblocks/unconfirmed/([0-9a-f]{64})/([0-9]{1,5})$"#).unwrap();
+    static ref PATH_GET_UNCONFIRMED_MICROBLOCK_STREAM: Regex =
+        Regex::new(r#"^/v2/microblocks/unconfirmed$"#).unwrap();
     static ref PATH_GETTRANSACTION_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/transactions/unconfirmed/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_POSTTRANSACTION: Regex = Regex::new(r#"^/v2/transactions$"#).unwrap();
@@ -111,6 +151,46 @@ lazy_static! {
         *PRINCIPAL_DATA_REGEX
     ))
     .unwrap();
+    static ref PATH_GET_ACCOUNT_ASSETS: Regex = Regex::new(&format!(
+        "^/v2/accounts/(?P<principal>{})/assets$",
+        *PRINCIPAL_DATA_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GET_ACCOUNT_NONCES: Regex = Regex::new(&format!(
+        "^/v2/accounts/(?P<principal>{})/nonces$",
+        *PRINCIPAL_DATA_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GET_ACCOUNT_SUMMARY: Regex = Regex::new(&format!(
+        "^/v2/accounts/(?P<principal>{})/summary$",
+        *PRINCIPAL_DATA_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GET_PRINCIPAL_TRANSACTIONS: Regex = Regex::new(&format!(
+        "^/v2/addresses/(?P<principal>{})/transactions$",
+        *PRINCIPAL_DATA_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GET_PENDING_MINER_REWARDS: Regex = Regex::new(&format!(
+        "^/v2/addresses/(?P<address>{})/pending_rewards$",
+        *STANDARD_PRINCIPAL_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GET_TRANSACTION_RECEIPT: Regex =
+        Regex::new("^/v2/transactions/(?P<txid>[0-9a-f]{64})/receipt$").unwrap();
+    static ref PATH_POST_MEMPOOL_GC: Regex = Regex::new("^/v2/mempool/gc$").unwrap();
+    static ref PATH_POST_DEV_MINE: Regex = Regex::new("^/v2/dev/mine$").unwrap();
+    static ref PATH_GET_MINER_STATUS: Regex = Regex::new("^/v2/admin/miner$").unwrap();
+    static ref PATH_SCHEDULED_CALLS: Regex = Regex::new("^/v2/admin/scheduled_calls$").unwrap();
+    static ref PATH_SCHEDULED_CALL: Regex =
+        Regex::new("^/v2/admin/scheduled_calls/(?P<id>[0-9]+)$").unwrap();
+    static ref PATH_POST_SIMULATE_BLOCK: Regex =
+        Regex::new("^/v2/mempool/simulate_block$").unwrap();
+    static ref PATH_POST_BATCH: Regex = Regex::new("^/v2/batch$").unwrap();
+    static ref PATH_POST_BLOCK: Regex =
+        Regex::new("^/v2/blocks/upload/(?P<consensus_hash>[0-9a-f]{40})$").unwrap();
+    static ref PATH_POST_VALIDATE_BLOCK_PROPOSAL: Regex =
+        Regex::new("^/v2/blocks/validate/(?P<parent_consensus_hash>[0-9a-f]{40})$").unwrap();
     static ref PATH_GET_MAP_ENTRY: Regex = Regex::new(&format!(
         "^/v2/map_entry/(?P<address>{})/(?P<contract>{})/(?P<map>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
@@ -121,18 +201,44 @@ lazy_static! {
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
     ))
     .unwrap();
+    static ref PATH_GET_DATA_VAR: Regex = Regex::new(&format!(
+        "^/v2/data_var/(?P<address>{})/(?P<contract>{})/(?P<var>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
+    ))
+    .unwrap();
     static ref PATH_GET_CONTRACT_SRC: Regex = Regex::new(&format!(
         "^/v2/contracts/source/(?P<address>{})/(?P<contract>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
     ))
     .unwrap();
+    static ref PATH_GET_STACKERDB_METADATA: Regex = Regex::new(&format!(
+        "^/v2/stackerdb/(?P<address>{})/(?P<contract>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GET_STACKERDB_CHUNK: Regex = Regex::new(&format!(
+        "^/v2/stackerdb/(?P<address>{})/(?P<contract>{})/(?P<slot_id>[0-9]+)$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
+    ))
+    .unwrap();
+    static ref PATH_POST_STACKERDB_CHUNK: Regex = Regex::new(&format!(
+        "^/v2/stackerdb/(?P<address>{})/(?P<contract>{})/chunks$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
+    ))
+    .unwrap();
     static ref PATH_GET_CONTRACT_ABI: Regex = Regex::new(&format!(
         "^/v2/contracts/interface/(?P<address>{})/(?P<contract>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
     ))
     .unwrap();
     static ref PATH_GET_TRANSFER_COST: Regex = Regex::new("^/v2/fees/transfer$").unwrap();
+    static ref PATH_POST_FEE_ESTIMATE: Regex = Regex::new("^/v2/fees/transaction$").unwrap();
+    static ref PATH_POST_SIMULATE_TRANSACTION: Regex =
+        Regex::new("^/v2/transactions/simulate$").unwrap();
+    static ref PATH_POST_ANALYZE_CONTRACT: Regex = Regex::new("^/v2/contracts/analyze$").unwrap();
     static ref PATH_GET_ATTACHMENTS_INV: Regex = Regex::new("^/v2/attachments/inv$").unwrap();
+    static ref PATH_GET_ATTACHMENT_INSTANCES: Regex =
+        Regex::new("^/v2/attachments/inv/instances$").unwrap();
     static ref PATH_GET_ATTACHMENT: Regex =
         Regex::new(r#"^/v2/attachments/([0-9a-f]{40})$"#).unwrap();
     static ref PATH_OPTIONS_WILDCARD: Regex = Regex::new("^/v2/.{0,4096}$").unwrap();
@@ -861,6 +967,26 @@ fn keep_alive_headers<W: Write>(fd: &mut W, md: &HttpResponseMetadata) -> Result
     Ok(())
 }
 
+/// Write the keep-alive headers, plus the deprecation headers if the request used a deprecated
+/// endpoint or parameter.
+fn response_metadata_headers<W: Write>(
+    fd: &mut W,
+    md: &HttpResponseMetadata,
+) -> Result<(), net_error> {
+    keep_alive_headers(fd, md)?;
+    if let Some(deprecation) = md.deprecation {
+        fd.write_all(
+            format!(
+                "Deprecation: true\r\nLink: <{}>; rel=\"successor-version\"\r\n",
+                deprecation.successor
+            )
+            .as_bytes(),
+        )
+        .map_err(net_error::WriteError)?;
+    }
+    Ok(())
+}
+
 fn write_headers<W: Write>(fd: &mut W, headers: &HashMap<String, String>) -> Result<(), net_error> {
     for (ref key, ref value) in headers.iter() {
         fd.write_all(key.as_str().as_bytes())
@@ -1082,7 +1208,7 @@ impl HttpResponsePreamble {
             md.content_length.clone(),
             &HttpContentType::JSON,
             md.request_id,
-            |ref mut fd| keep_alive_headers(fd, md),
+            |ref mut fd| response_metadata_headers(fd, md),
         )
     }
 
@@ -1436,12 +1562,44 @@ impl HttpRequestType {
         )] = &[
             ("GET", &PATH_GETINFO, &HttpRequestType::parse_getinfo),
             ("GET", &PATH_GETPOXINFO, &HttpRequestType::parse_getpoxinfo),
+            (
+                "GET",
+                &PATH_GETREWARDSET,
+                &HttpRequestType::parse_getrewardset,
+            ),
+            (
+                "GET",
+                &PATH_GET_POX_DELEGATIONS,
+                &HttpRequestType::parse_get_pox_delegations,
+            ),
+            (
+                "POST",
+                &PATH_POST_AGGREGATION_COMMIT,
+                &HttpRequestType::parse_post_aggregation_commit,
+            ),
+            (
+                "GET",
+                &PATH_GETBURNBLOCKINFO,
+                &HttpRequestType::parse_getburnblockinfo,
+            ),
+            (
+                "GET",
+                &PATH_GETBURNBLOCKOPS,
+                &HttpRequestType::parse_getburnblockops,
+            ),
+            ("GET", &PATH_GETBURNOP, &HttpRequestType::parse_getburnop),
             (
                 "GET",
                 &PATH_GETNEIGHBORS,
                 &HttpRequestType::parse_getneighbors,
             ),
             ("GET", &PATH_GETBLOCK, &HttpRequestType::parse_getblock),
+            (
+                "GET",
+                &PATH_GET_BLOCK_EVENT_BLOOM,
+                &HttpRequestType::parse_get_block_event_bloom,
+            ),
+            ("GET", &PATH_GETHEADERS, &HttpRequestType::parse_getheaders),
             (
                 "GET",
                 &PATH_GETMICROBLOCKS_INDEXED,
@@ -1477,6 +1635,83 @@ impl HttpRequestType {
                 &PATH_GET_ACCOUNT,
                 &HttpRequestType::parse_get_account,
             ),
+            (
+                "GET",
+                &PATH_GET_ACCOUNT_ASSETS,
+                &HttpRequestType::parse_get_account_assets,
+            ),
+            (
+                "GET",
+                &PATH_GET_ACCOUNT_NONCES,
+                &HttpRequestType::parse_get_account_nonces,
+            ),
+            (
+                "GET",
+                &PATH_GET_ACCOUNT_SUMMARY,
+                &HttpRequestType::parse_get_account_summary,
+            ),
+            (
+                "GET",
+                &PATH_GET_TRANSACTION_RECEIPT,
+                &HttpRequestType::parse_get_transaction_receipt,
+            ),
+            (
+                "GET",
+                &PATH_GET_PRINCIPAL_TRANSACTIONS,
+                &HttpRequestType::parse_get_principal_transactions,
+            ),
+            (
+                "GET",
+                &PATH_GET_PENDING_MINER_REWARDS,
+                &HttpRequestType::parse_get_pending_miner_rewards,
+            ),
+            (
+                "GET",
+                &PATH_GET_UNCONFIRMED_MICROBLOCK_STREAM,
+                &HttpRequestType::parse_get_unconfirmed_microblock_stream,
+            ),
+            (
+                "POST",
+                &PATH_POST_MEMPOOL_GC,
+                &HttpRequestType::parse_post_mempool_gc,
+            ),
+            (
+                "POST",
+                &PATH_POST_DEV_MINE,
+                &HttpRequestType::parse_post_dev_mine,
+            ),
+            (
+                "POST",
+                &PATH_POST_SIMULATE_BLOCK,
+                &HttpRequestType::parse_post_simulate_block,
+            ),
+            ("POST", &PATH_POST_BATCH, &HttpRequestType::parse_post_batch),
+            (
+                "POST",
+                &PATH_POST_VALIDATE_BLOCK_PROPOSAL,
+                &HttpRequestType::parse_post_validate_block_proposal,
+            ),
+            ("POST", &PATH_POST_BLOCK, &HttpRequestType::parse_post_block),
+            (
+                "GET",
+                &PATH_GET_MINER_STATUS,
+                &HttpRequestType::parse_get_miner_status,
+            ),
+            (
+                "GET",
+                &PATH_SCHEDULED_CALLS,
+                &HttpRequestType::parse_get_scheduled_calls,
+            ),
+            (
+                "POST",
+                &PATH_SCHEDULED_CALLS,
+                &HttpRequestType::parse_post_scheduled_call,
+            ),
+            (
+                "DELETE",
+                &PATH_SCHEDULED_CALL,
+                &HttpRequestType::parse_delete_scheduled_call,
+            ),
             (
                 "POST",
                 &PATH_GET_MAP_ENTRY,
@@ -1487,11 +1722,31 @@ impl HttpRequestType {
                 &PATH_GET_TRANSFER_COST,
                 &HttpRequestType::parse_get_transfer_cost,
             ),
+            (
+                "POST",
+                &PATH_POST_FEE_ESTIMATE,
+                &HttpRequestType::parse_post_fee_estimate,
+            ),
+            (
+                "POST",
+                &PATH_POST_SIMULATE_TRANSACTION,
+                &HttpRequestType::parse_post_simulate_transaction,
+            ),
+            (
+                "POST",
+                &PATH_POST_ANALYZE_CONTRACT,
+                &HttpRequestType::parse_post_analyze_contract,
+            ),
             (
                 "GET",
                 &PATH_GET_CONTRACT_SRC,
                 &HttpRequestType::parse_get_contract_source,
             ),
+            (
+                "GET",
+                &PATH_GET_DATA_VAR,
+                &HttpRequestType::parse_get_data_var,
+            ),
             (
                 "GET",
                 &PATH_GET_CONTRACT_ABI,
@@ -1517,6 +1772,26 @@ impl HttpRequestType {
                 &PATH_GET_ATTACHMENTS_INV,
                 &HttpRequestType::parse_get_attachments_inv,
             ),
+            (
+                "GET",
+                &PATH_GET_ATTACHMENT_INSTANCES,
+                &HttpRequestType::parse_get_attachment_instances,
+            ),
+            (
+                "GET",
+                &PATH_GET_STACKERDB_METADATA,
+                &HttpRequestType::parse_get_stackerdb_metadata,
+            ),
+            (
+                "GET",
+                &PATH_GET_STACKERDB_CHUNK,
+                &HttpRequestType::parse_get_stackerdb_chunk,
+            ),
+            (
+                "POST",
+                &PATH_POST_STACKERDB_CHUNK,
+                &HttpRequestType::parse_post_stackerdb_chunk,
+            ),
         ];
 
         // use url::Url to parse path and query string
@@ -1598,81 +1873,28 @@ impl HttpRequestType {
         ))
     }
 
-    fn parse_getneighbors<R: Read>(
+    fn parse_getrewardset<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         _regex: &Captures,
-        _query: Option<&str>,
+        query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetNeighbors".to_string(),
+                "Invalid Http request: expected 0-length body for GetRewardSet".to_string(),
             ));
         }
 
-        Ok(HttpRequestType::GetNeighbors(
-            HttpRequestMetadata::from_preamble(preamble),
-        ))
-    }
-
-    fn parse_get_transfer_cost<R: Read>(
-        _protocol: &mut StacksHttp,
-        preamble: &HttpRequestPreamble,
-        _regex: &Captures,
-        _query: Option<&str>,
-        _fd: &mut R,
-    ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() != 0 {
-            return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetTransferCost".to_string(),
-            ));
-        }
+        let tip = HttpRequestType::get_chain_tip_query(query);
 
-        Ok(HttpRequestType::GetTransferCost(
+        Ok(HttpRequestType::GetRewardSet(
             HttpRequestMetadata::from_preamble(preamble),
+            tip,
         ))
     }
 
-    /// check whether the given option query string
-    ///   sets proof=0 (setting proof to false).
-    /// Defaults to _true_
-    fn get_proof_query(query: Option<&str>) -> bool {
-        let no_proof = if let Some(query_string) = query {
-            form_urlencoded::parse(query_string.as_bytes())
-                .find(|(key, _v)| key == "proof")
-                .map(|(_k, value)| value == "0")
-                .unwrap_or(false)
-        } else {
-            false
-        };
-
-        !no_proof
-    }
-
-    /// get the chain tip optional query argument (`tip`)
-    /// Take the first value we can parse.
-    fn get_chain_tip_query(query: Option<&str>) -> Option<StacksBlockId> {
-        match query {
-            Some(query_string) => {
-                for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
-                    if key != "tip" {
-                        continue;
-                    }
-
-                    if let Ok(tip) = StacksBlockId::from_hex(&value) {
-                        return Some(tip);
-                    }
-                }
-                return None;
-            }
-            None => {
-                return None;
-            }
-        }
-    }
-
-    fn parse_get_account<R: Read>(
+    fn parse_get_pox_delegations<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
@@ -1681,184 +1903,97 @@ impl HttpRequestType {
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetAccount".to_string(),
+                "Invalid Http request: expected 0-length body for GetPoxDelegations".to_string(),
             ));
         }
 
-        let principal = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
-            net_error::DeserializeError("Failed to parse account principal".into())
+        let delegate = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse delegate principal".into())
         })?;
 
-        let with_proof = HttpRequestType::get_proof_query(query);
         let tip = HttpRequestType::get_chain_tip_query(query);
 
-        Ok(HttpRequestType::GetAccount(
+        Ok(HttpRequestType::GetPoxDelegations(
             HttpRequestMetadata::from_preamble(preamble),
-            principal,
+            delegate,
             tip,
-            with_proof,
         ))
     }
 
-    fn parse_get_map_entry<R: Read>(
-        _protocol: &mut StacksHttp,
+    fn parse_post_aggregation_commit<R: Read>(
+        protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
         query: Option<&str>,
         fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         let content_len = preamble.get_content_length();
-        if !(content_len > 0 && content_len < (BOUND_VALUE_SERIALIZATION_HEX)) {
+        if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
             return Err(net_error::DeserializeError(format!(
-                "Invalid Http request: invalid body length for GetMapEntry ({})",
+                "Invalid Http request: invalid body length for SimulateAggregationCommit ({})",
                 content_len
             )));
         }
 
         if preamble.content_type != Some(HttpContentType::JSON) {
             return Err(net_error::DeserializeError(
-                "Invalid content-type: expected application/json".into(),
+                "Invalid content-type: expected application/json".to_string(),
             ));
         }
 
-        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
-            net_error::DeserializeError("Failed to parse contract address".into())
+        let delegate = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse delegate principal".into())
         })?;
-        let contract_name = ContractName::try_from(captures["contract"].to_string())
-            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
-        let map_name = ClarityName::try_from(captures["map"].to_string())
-            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
 
-        let value_hex: String = serde_json::from_reader(fd)
+        let body: AggregationCommitRequestBody = serde_json::from_reader(fd)
             .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
 
-        let value = Value::try_deserialize_hex_untyped(&value_hex)
-            .map_err(|_e| net_error::DeserializeError("Failed to deserialize key value".into()))?;
+        let pox_addr = BitcoinAddress::from_b58(&body.pox_address)
+            .map(|addr| StacksAddress::from_bitcoin_address(&addr))
+            .map_err(|_e| net_error::DeserializeError("Failed to parse PoX address".into()))?;
 
-        let with_proof = HttpRequestType::get_proof_query(query);
         let tip = HttpRequestType::get_chain_tip_query(query);
 
-        Ok(HttpRequestType::GetMapEntry(
+        Ok(HttpRequestType::SimulateAggregationCommit(
             HttpRequestMetadata::from_preamble(preamble),
-            contract_addr,
-            contract_name,
-            map_name,
-            value,
+            delegate,
+            pox_addr,
+            body.reward_cycle,
             tip,
-            with_proof,
         ))
     }
 
-    fn parse_call_read_only<R: Read>(
-        protocol: &mut StacksHttp,
+    fn parse_getburnblockinfo<R: Read>(
+        _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
-        query: Option<&str>,
-        fd: &mut R,
+        _query: Option<&str>,
+        _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        let content_len = preamble.get_content_length();
-        if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
-            return Err(net_error::DeserializeError(format!(
-                "Invalid Http request: invalid body length for CallReadOnly ({})",
-                content_len
-            )));
-        }
-
-        if preamble.content_type != Some(HttpContentType::JSON) {
+        if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid content-type: expected application/json".to_string(),
+                "Invalid Http request: expected 0-length body for GetBurnBlockInfo".to_string(),
             ));
         }
 
-        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
-            net_error::DeserializeError("Failed to parse contract address".into())
-        })?;
-        let contract_name = ContractName::try_from(captures["contract"].to_string())
-            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
-        let func_name = ClarityName::try_from(captures["function"].to_string())
-            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let burn_block_height = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to burn block height group".to_string(),
+            ))?
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_| {
+                net_error::DeserializeError("Failed to parse burn block height".to_string())
+            })?;
 
-        let body: CallReadOnlyRequestBody = serde_json::from_reader(fd)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        Ok(HttpRequestType::GetBurnBlockInfo(
+            HttpRequestMetadata::from_preamble(preamble),
+            burn_block_height,
+        ))
+    }
 
-        let sender = PrincipalData::parse(&body.sender)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse sender principal".into()))?;
-
-        let arguments = body
-            .arguments
-            .into_iter()
-            .map(|hex| Value::try_deserialize_hex_untyped(&hex).ok())
-            .collect::<Option<Vec<Value>>>()
-            .ok_or_else(|| {
-                net_error::DeserializeError("Failed to deserialize argument value".into())
-            })?;
-
-        let tip = HttpRequestType::get_chain_tip_query(query);
-
-        Ok(HttpRequestType::CallReadOnlyFunction(
-            HttpRequestMetadata::from_preamble(preamble),
-            contract_addr,
-            contract_name,
-            sender,
-            func_name,
-            arguments,
-            tip,
-        ))
-    }
-
-    fn parse_get_contract_arguments(
-        preamble: &HttpRequestPreamble,
-        captures: &Captures,
-    ) -> Result<(HttpRequestMetadata, StacksAddress, ContractName), net_error> {
-        if preamble.get_content_length() != 0 {
-            return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body".to_string(),
-            ));
-        }
-
-        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
-            net_error::DeserializeError("Failed to parse contract address".into())
-        })?;
-        let contract_name = ContractName::try_from(captures["contract"].to_string())
-            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
-
-        Ok((
-            HttpRequestMetadata::from_preamble(preamble),
-            contract_addr,
-            contract_name,
-        ))
-    }
-
-    fn parse_get_contract_abi<R: Read>(
-        _protocol: &mut StacksHttp,
-        preamble: &HttpRequestPreamble,
-        captures: &Captures,
-        query: Option<&str>,
-        _fd: &mut R,
-    ) -> Result<HttpRequestType, net_error> {
-        let tip = HttpRequestType::get_chain_tip_query(query);
-        HttpRequestType::parse_get_contract_arguments(preamble, captures).map(
-            |(preamble, addr, name)| HttpRequestType::GetContractABI(preamble, addr, name, tip),
-        )
-    }
-
-    fn parse_get_contract_source<R: Read>(
-        _protocol: &mut StacksHttp,
-        preamble: &HttpRequestPreamble,
-        captures: &Captures,
-        query: Option<&str>,
-        _fd: &mut R,
-    ) -> Result<HttpRequestType, net_error> {
-        let with_proof = HttpRequestType::get_proof_query(query);
-        let tip = HttpRequestType::get_chain_tip_query(query);
-        HttpRequestType::parse_get_contract_arguments(preamble, captures).map(
-            |(preamble, addr, name)| {
-                HttpRequestType::GetContractSrc(preamble, addr, name, tip, with_proof)
-            },
-        )
-    }
-
-    fn parse_getblock<R: Read>(
+    fn parse_getburnblockops<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
@@ -1867,27 +2002,28 @@ impl HttpRequestType {
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetBlock".to_string(),
+                "Invalid Http request: expected 0-length body for GetBurnchainOps".to_string(),
             ));
         }
 
-        let block_hash_str = captures
+        let burn_block_height = captures
             .get(1)
             .ok_or(net_error::DeserializeError(
-                "Failed to match path to block hash group".to_string(),
+                "Failed to match path to burn block height group".to_string(),
             ))?
-            .as_str();
-
-        let block_hash = StacksBlockId::from_hex(block_hash_str)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_| {
+                net_error::DeserializeError("Failed to parse burn block height".to_string())
+            })?;
 
-        Ok(HttpRequestType::GetBlock(
+        Ok(HttpRequestType::GetBurnchainOps(
             HttpRequestMetadata::from_preamble(preamble),
-            block_hash,
+            BurnchainOpsQuery::Height(burn_block_height),
         ))
     }
 
-    fn parse_getmicroblocks_indexed<R: Read>(
+    fn parse_getburnop<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
@@ -1896,138 +2032,111 @@ impl HttpRequestType {
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetMicroblocksIndexed"
-                    .to_string(),
+                "Invalid Http request: expected 0-length body for GetBurnchainOps".to_string(),
             ));
         }
 
-        let block_hash_str = captures
+        let txid_hex = captures
             .get(1)
             .ok_or(net_error::DeserializeError(
-                "Failed to match path to microblock hash group".to_string(),
+                "Failed to match path to txid group".to_string(),
             ))?
             .as_str();
 
-        let block_hash = StacksBlockId::from_hex(block_hash_str).map_err(|_e| {
-            net_error::DeserializeError("Failed to parse microblock hash".to_string())
-        })?;
+        let txid = Txid::from_hex(&txid_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
 
-        Ok(HttpRequestType::GetMicroblocksIndexed(
+        Ok(HttpRequestType::GetBurnchainOps(
             HttpRequestMetadata::from_preamble(preamble),
-            block_hash,
+            BurnchainOpsQuery::Txid(txid),
         ))
     }
 
-    fn parse_getmicroblocks_confirmed<R: Read>(
+    fn parse_getneighbors<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        captures: &Captures,
+        _regex: &Captures,
         _query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetMicrolocks".to_string(),
+                "Invalid Http request: expected 0-length body for GetNeighbors".to_string(),
             ));
         }
 
-        let block_hash_str = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match path to microblock hash group".to_string(),
-            ))?
-            .as_str();
-
-        let block_hash = StacksBlockId::from_hex(block_hash_str).map_err(|_e| {
-            net_error::DeserializeError("Failed to parse microblock hash".to_string())
-        })?;
-
-        Ok(HttpRequestType::GetMicroblocksConfirmed(
+        Ok(HttpRequestType::GetNeighbors(
             HttpRequestMetadata::from_preamble(preamble),
-            block_hash,
         ))
     }
 
-    fn parse_getmicroblocks_unconfirmed<R: Read>(
+    fn parse_get_transfer_cost<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        captures: &Captures,
+        _regex: &Captures,
         _query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetMicrolocksUnconfirmed"
-                    .to_string(),
+                "Invalid Http request: expected 0-length body for GetTransferCost".to_string(),
             ));
         }
 
-        let block_hash_str = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match path to microblock hash group".to_string(),
-            ))?
-            .as_str();
-
-        let min_seq_str = captures
-            .get(2)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match path to microblock minimum sequence group".to_string(),
-            ))?
-            .as_str();
-
-        let block_hash = StacksBlockId::from_hex(block_hash_str).map_err(|_e| {
-            net_error::DeserializeError("Failed to parse microblock hash".to_string())
-        })?;
-
-        let min_seq = min_seq_str.parse::<u16>().map_err(|_e| {
-            net_error::DeserializeError("Failed to parse microblock minimum sequence".to_string())
-        })?;
-
-        Ok(HttpRequestType::GetMicroblocksUnconfirmed(
+        Ok(HttpRequestType::GetTransferCost(
             HttpRequestMetadata::from_preamble(preamble),
-            block_hash,
-            min_seq,
         ))
     }
 
-    fn parse_gettransaction_unconfirmed<R: Read>(
+    fn parse_post_fee_estimate<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        regex: &Captures,
+        _regex: &Captures,
         _query: Option<&str>,
-        _fd: &mut R,
+        fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() != 0 {
+        if preamble.get_content_length() == 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetMicrolocksUnconfirmed"
+                "Invalid Http request: expected non-zero-length body for EstimateTransactionFee"
                     .to_string(),
             ));
         }
 
-        let txid_hex = regex
-            .get(1)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match path to txid group".to_string(),
-            ))?
-            .as_str();
-
-        if txid_hex.len() != 64 {
-            return Err(net_error::DeserializeError(
-                "Invalid txid: expected 64 bytes".to_string(),
-            ));
-        }
+        // content-type must be given, and must be application/octet-stream
+        match preamble.content_type {
+            None => {
+                return Err(net_error::DeserializeError(
+                    "Missing Content-Type for transaction".to_string(),
+                ));
+            }
+            Some(ref c) => {
+                if *c != HttpContentType::Bytes {
+                    return Err(net_error::DeserializeError(
+                        "Wrong Content-Type for transaction; expected application/octet-stream"
+                            .to_string(),
+                    ));
+                }
+            }
+        };
 
-        let txid = Txid::from_hex(&txid_hex)
-            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
+        let tx = StacksTransaction::consensus_deserialize(fd).map_err(|e| {
+            if let net_error::DeserializeError(msg) = e {
+                net_error::ClientError(ClientError::Message(format!(
+                    "Failed to deserialize transaction: {}",
+                    msg
+                )))
+            } else {
+                e
+            }
+        })?;
 
-        Ok(HttpRequestType::GetTransactionUnconfirmed(
+        Ok(HttpRequestType::EstimateTransactionFee(
             HttpRequestMetadata::from_preamble(preamble),
-            txid,
+            tx,
         ))
     }
 
-    fn parse_posttransaction<R: Read>(
+    fn parse_post_simulate_transaction<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         _regex: &Captures,
@@ -2036,951 +2145,3488 @@ impl HttpRequestType {
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() == 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected non-zero-length body for PostTransaction"
+                "Invalid Http request: expected non-zero-length body for SimulateTransaction"
                     .to_string(),
             ));
         }
 
+        // content-type must be given, and must be application/octet-stream
         match preamble.content_type {
             None => {
                 return Err(net_error::DeserializeError(
                     "Missing Content-Type for transaction".to_string(),
                 ));
             }
-            Some(HttpContentType::Bytes) => {
-                HttpRequestType::parse_posttransaction_octets(preamble, fd)
-            }
-            Some(HttpContentType::JSON) => {
-                HttpRequestType::parse_posttransaction_json(preamble, fd)
-            }
-            _ => {
-                return Err(net_error::DeserializeError(
-                    "Wrong Content-Type for transaction; expected application/json".to_string(),
-                ));
+            Some(ref c) => {
+                if *c != HttpContentType::Bytes {
+                    return Err(net_error::DeserializeError(
+                        "Wrong Content-Type for transaction; expected application/octet-stream"
+                            .to_string(),
+                    ));
+                }
             }
-        }
-    }
+        };
 
-    fn parse_posttransaction_octets<R: Read>(
-        preamble: &HttpRequestPreamble,
-        fd: &mut R,
-    ) -> Result<HttpRequestType, net_error> {
         let tx = StacksTransaction::consensus_deserialize(fd).map_err(|e| {
             if let net_error::DeserializeError(msg) = e {
                 net_error::ClientError(ClientError::Message(format!(
-                    "Failed to deserialize posted transaction: {}",
+                    "Failed to deserialize transaction: {}",
                     msg
                 )))
             } else {
                 e
             }
         })?;
-        Ok(HttpRequestType::PostTransaction(
+
+        Ok(HttpRequestType::SimulateTransaction(
             HttpRequestMetadata::from_preamble(preamble),
             tx,
-            None,
         ))
     }
 
-    fn parse_posttransaction_json<R: Read>(
+    fn parse_post_analyze_contract<R: Read>(
+        _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
         fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        let body: PostTransactionRequestBody = serde_json::from_reader(fd)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse body".into()))?;
-
-        let tx = {
-            let tx_bytes = hex_bytes(&body.tx)
-                .map_err(|_e| net_error::DeserializeError("Failed to parse tx".into()))?;
-            StacksTransaction::consensus_deserialize(&mut &tx_bytes[..]).map_err(|e| {
-                if let net_error::DeserializeError(msg) = e {
-                    net_error::ClientError(ClientError::Message(format!(
-                        "Failed to deserialize posted transaction: {}",
-                        msg
-                    )))
-                } else {
-                    e
-                }
-            })
-        }?;
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_MESSAGE_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for AnalyzeContract ({})",
+                content_len
+            )));
+        }
 
-        let attachment = match body.attachment {
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let body: ContractAnalysisRequestBody = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let contract_id = match body.contract_id {
+            Some(ref contract_id) => Some(
+                QualifiedContractIdentifier::parse(contract_id).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse contract identifier".into())
+                })?,
+            ),
             None => None,
-            Some(attachment_content) => {
-                let content = hex_bytes(&attachment_content).map_err(|_e| {
-                    net_error::DeserializeError("Failed to parse attachment".into())
-                })?;
-                Some(Attachment::new(content))
-            }
         };
 
-        Ok(HttpRequestType::PostTransaction(
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::AnalyzeContract(
             HttpRequestMetadata::from_preamble(preamble),
-            tx,
-            attachment,
+            body.source,
+            contract_id,
+            tip,
         ))
     }
 
-    fn parse_postmicroblock<R: Read>(
+    /// check whether the given option query string
+    ///   sets proof=0 (setting proof to false).
+    /// Defaults to _true_
+    fn get_proof_query(query: Option<&str>) -> bool {
+        let no_proof = if let Some(query_string) = query {
+            form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "proof")
+                .map(|(_k, value)| value == "0")
+                .unwrap_or(false)
+        } else {
+            false
+        };
+
+        !no_proof
+    }
+
+    /// get the state view query arguments (`unconfirmed` and `mempool`).
+    /// `mempool=1` implies `unconfirmed=1`.  Neither can be combined with `tip`.
+    fn get_state_view_query(
+        query: Option<&str>,
+        tip_opt: Option<&StacksBlockId>,
+    ) -> Result<RPCStateView, net_error> {
+        let mut view = RPCStateView::Confirmed;
+        if let Some(query_string) = query {
+            for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                if value != "1" {
+                    continue;
+                }
+                if key == "mempool" {
+                    view = RPCStateView::UnconfirmedWithMempool;
+                } else if key == "unconfirmed" && view == RPCStateView::Confirmed {
+                    view = RPCStateView::Unconfirmed;
+                }
+            }
+        }
+
+        if view != RPCStateView::Confirmed && tip_opt.is_some() {
+            return Err(net_error::ClientError(ClientError::Message(
+                "Cannot combine `tip` with `unconfirmed` or `mempool`".to_string(),
+            )));
+        }
+        Ok(view)
+    }
+
+    /// get the chain tip optional query argument (`tip`)
+    /// Take the first value we can parse.
+    fn get_chain_tip_query(query: Option<&str>) -> Option<StacksBlockId> {
+        match query {
+            Some(query_string) => {
+                for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                    if key != "tip" {
+                        continue;
+                    }
+
+                    if let Ok(tip) = StacksBlockId::from_hex(&value) {
+                        return Some(tip);
+                    }
+                }
+                return None;
+            }
+            None => {
+                return None;
+            }
+        }
+    }
+
+    /// get the chain height optional query argument (`height`): the query is evaluated at the
+    /// ancestor of the chain tip at this height.  Take the first value we can parse.  A height
+    /// can't be combined with the `unconfirmed` or `mempool` state views.
+    fn get_chain_height_query(
+        query: Option<&str>,
+        view: RPCStateView,
+    ) -> Result<Option<u64>, net_error> {
+        let height_opt = query.and_then(|query_string| {
+            form_urlencoded::parse(query_string.as_bytes())
+                .filter(|(key, _value)| key == "height")
+                .find_map(|(_key, value)| value.parse::<u64>().ok())
+        });
+        if view != RPCStateView::Confirmed && height_opt.is_some() {
+            return Err(net_error::ClientError(ClientError::Message(
+                "Cannot combine `height` with `unconfirmed` or `mempool`".to_string(),
+            )));
+        }
+        Ok(height_opt)
+    }
+
+    fn parse_get_account<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        _regex: &Captures,
+        captures: &Captures,
         query: Option<&str>,
-        fd: &mut R,
+        _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() == 0 {
+        if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected non-zero-length body for PostMicroblock"
-                    .to_string(),
+                "Invalid Http request: expected 0-length body for GetAccount".to_string(),
             ));
         }
 
-        // content-type must be given, and must be application/octet-stream
-        match preamble.content_type {
-            None => {
-                return Err(net_error::DeserializeError(
-                    "Missing Content-Type for microblock".to_string(),
-                ));
-            }
-            Some(ref c) => {
-                if *c != HttpContentType::Bytes {
-                    return Err(net_error::DeserializeError(
-                        "Wrong Content-Type for microblock; expected application/octet-stream"
-                            .to_string(),
-                    ));
-                }
-            }
-        };
+        let principal = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse account principal".into())
+        })?;
 
-        let mb = StacksMicroblock::consensus_deserialize(fd)?;
+        let with_proof = HttpRequestType::get_proof_query(query);
         let tip = HttpRequestType::get_chain_tip_query(query);
+        let view = HttpRequestType::get_state_view_query(query, tip.as_ref())?;
+        let height = HttpRequestType::get_chain_height_query(query, view)?;
 
-        Ok(HttpRequestType::PostMicroblock(
+        Ok(HttpRequestType::GetAccount(
             HttpRequestMetadata::from_preamble(preamble),
-            mb,
+            principal,
             tip,
+            height,
+            with_proof,
+            view,
         ))
     }
 
-    fn parse_get_attachment<R: Read>(
+    fn parse_get_account_assets<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
-        _query: Option<&str>,
+        query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body".to_string(),
+                "Invalid Http request: expected 0-length body for GetAccountAssets".to_string(),
             ));
         }
-        let hex_content_hash = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match path to attachment hash group".to_string(),
-            ))?
-            .as_str();
 
-        let content_hash = Hash160::from_hex(&hex_content_hash).map_err(|_| {
-            net_error::DeserializeError("Failed to construct hash160 from inputs".to_string())
+        let principal = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse account principal".into())
         })?;
 
-        Ok(HttpRequestType::GetAttachment(
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetAccountAssets(
             HttpRequestMetadata::from_preamble(preamble),
-            content_hash,
+            principal,
+            tip,
         ))
     }
 
-    fn parse_get_attachments_inv<R: Read>(
+    fn parse_get_account_nonces<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        _captures: &Captures,
+        captures: &Captures,
         query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body".to_string(),
+                "Invalid Http request: expected 0-length body for GetAccountNonces".to_string(),
             ));
         }
 
-        let mut tip = None;
-        let mut pages_indexes = HashSet::new();
+        let principal = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse account principal".into())
+        })?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetAccountNonces(
+            HttpRequestMetadata::from_preamble(preamble),
+            principal,
+            tip,
+        ))
+    }
+
+    fn parse_get_account_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetAccountSummary".to_string(),
+            ));
+        }
+
+        let principal = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse account principal".into())
+        })?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetAccountSummary(
+            HttpRequestMetadata::from_preamble(preamble),
+            principal,
+            tip,
+        ))
+    }
+
+    fn parse_get_transaction_receipt<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetTransactionReceipt"
+                    .to_string(),
+            ));
+        }
+
+        let txid = Txid::from_hex(&captures["txid"])
+            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetTransactionReceipt(
+            HttpRequestMetadata::from_preamble(preamble),
+            txid,
+            tip,
+        ))
+    }
+
+    fn parse_get_principal_transactions<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetPrincipalTransactions"
+                    .to_string(),
+            ));
+        }
+
+        let principal = PrincipalData::parse(&captures["principal"])
+            .map_err(|_e| net_error::DeserializeError("Failed to parse principal".into()))?;
 
+        let mut offset = 0;
+        let mut limit = DEFAULT_PRINCIPAL_TRANSACTIONS_LIMIT;
         if let Some(query) = query {
             for (key, value) in form_urlencoded::parse(query.as_bytes()) {
-                if key == "tip" {
-                    tip = match StacksBlockId::from_hex(&value) {
-                        Ok(tip) => Some(tip),
-                        _ => None,
-                    };
-                } else if key == "pages_indexes" {
-                    if let Ok(pages_indexes_value) = value.parse::<String>() {
-                        for entry in pages_indexes_value.split(",") {
-                            if let Ok(page_index) = entry.parse::<u32>() {
-                                pages_indexes.insert(page_index);
-                            }
-                        }
-                    }
+                if key == "offset" {
+                    offset = value.parse::<u64>().map_err(|_e| {
+                        net_error::DeserializeError("Failed to parse offset".to_string())
+                    })?;
+                } else if key == "limit" {
+                    limit = value.parse::<u64>().map_err(|_e| {
+                        net_error::DeserializeError("Failed to parse limit".to_string())
+                    })?;
                 }
             }
         }
-        Ok(HttpRequestType::GetAttachmentsInv(
+        if limit == 0 || limit > MAX_PRINCIPAL_TRANSACTIONS_LIMIT {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid limit: must be between 1 and {}",
+                MAX_PRINCIPAL_TRANSACTIONS_LIMIT
+            )));
+        }
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetPrincipalTransactions(
             HttpRequestMetadata::from_preamble(preamble),
+            principal,
+            offset,
+            limit,
             tip,
-            pages_indexes,
         ))
     }
 
-    fn parse_options_preflight<R: Read>(
+    fn parse_get_pending_miner_rewards<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        _regex: &Captures,
-        _query: Option<&str>,
+        captures: &Captures,
+        query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        Ok(HttpRequestType::OptionsPreflight(
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetPendingMinerRewards"
+                    .to_string(),
+            ));
+        }
+
+        let address = StacksAddress::from_string(&captures["address"])
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse address".into()))?;
+
+        Ok(HttpRequestType::GetPendingMinerRewards(
             HttpRequestMetadata::from_preamble(preamble),
-            preamble.path.to_string(),
+            address,
+            HttpRequestType::get_chain_tip_query(query),
         ))
     }
 
-    pub fn metadata(&self) -> &HttpRequestMetadata {
-        match *self {
-            HttpRequestType::GetInfo(ref md) => md,
-            HttpRequestType::GetPoxInfo(ref md, _) => md,
-            HttpRequestType::GetNeighbors(ref md) => md,
-            HttpRequestType::GetBlock(ref md, _) => md,
-            HttpRequestType::GetMicroblocksIndexed(ref md, _) => md,
-            HttpRequestType::GetMicroblocksConfirmed(ref md, _) => md,
-            HttpRequestType::GetMicroblocksUnconfirmed(ref md, _, _) => md,
-            HttpRequestType::GetTransactionUnconfirmed(ref md, _) => md,
-            HttpRequestType::PostTransaction(ref md, _, _) => md,
-            HttpRequestType::PostMicroblock(ref md, ..) => md,
-            HttpRequestType::GetAccount(ref md, ..) => md,
-            HttpRequestType::GetMapEntry(ref md, ..) => md,
-            HttpRequestType::GetTransferCost(ref md) => md,
-            HttpRequestType::GetContractABI(ref md, ..) => md,
-            HttpRequestType::GetContractSrc(ref md, ..) => md,
-            HttpRequestType::CallReadOnlyFunction(ref md, ..) => md,
-            HttpRequestType::OptionsPreflight(ref md, ..) => md,
-            HttpRequestType::GetAttachmentsInv(ref md, ..) => md,
-            HttpRequestType::GetAttachment(ref md, ..) => md,
-            HttpRequestType::ClientError(ref md, ..) => md,
+    fn parse_get_unconfirmed_microblock_stream<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetUnconfirmedMicroblockStream"
+                    .to_string(),
+            ));
+        }
+
+        let mut min_seq = 0;
+        if let Some(query) = query {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                if key == "seq" {
+                    min_seq = value.parse::<u16>().map_err(|_e| {
+                        net_error::DeserializeError(
+                            "Failed to parse microblock minimum sequence".to_string(),
+                        )
+                    })?;
+                }
+            }
         }
+
+        Ok(HttpRequestType::GetUnconfirmedMicroblockStream(
+            HttpRequestMetadata::from_preamble(preamble),
+            min_seq,
+            HttpRequestType::get_chain_tip_query(query),
+        ))
     }
 
-    pub fn metadata_mut(&mut self) -> &mut HttpRequestMetadata {
-        match *self {
-            HttpRequestType::GetInfo(ref mut md) => md,
-            HttpRequestType::GetPoxInfo(ref mut md, _) => md,
-            HttpRequestType::GetNeighbors(ref mut md) => md,
-            HttpRequestType::GetBlock(ref mut md, _) => md,
-            HttpRequestType::GetMicroblocksIndexed(ref mut md, _) => md,
-            HttpRequestType::GetMicroblocksConfirmed(ref mut md, _) => md,
-            HttpRequestType::GetMicroblocksUnconfirmed(ref mut md, _, _) => md,
-            HttpRequestType::GetTransactionUnconfirmed(ref mut md, _) => md,
-            HttpRequestType::PostTransaction(ref mut md, _, _) => md,
-            HttpRequestType::PostMicroblock(ref mut md, ..) => md,
-            HttpRequestType::GetAccount(ref mut md, ..) => md,
-            HttpRequestType::GetMapEntry(ref mut md, ..) => md,
-            HttpRequestType::GetTransferCost(ref mut md) => md,
-            HttpRequestType::GetContractABI(ref mut md, ..) => md,
-            HttpRequestType::GetContractSrc(ref mut md, ..) => md,
-            HttpRequestType::CallReadOnlyFunction(ref mut md, ..) => md,
-            HttpRequestType::OptionsPreflight(ref mut md, ..) => md,
-            HttpRequestType::GetAttachmentsInv(ref mut md, ..) => md,
-            HttpRequestType::GetAttachment(ref mut md, ..) => md,
-            HttpRequestType::ClientError(ref mut md, ..) => md,
+    fn parse_post_mempool_gc<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for MemPoolGC".to_string(),
+            ));
         }
+
+        Ok(HttpRequestType::MemPoolGC(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
     }
 
-    fn make_query_string(tip_opt: Option<&StacksBlockId>, with_proof: bool) -> String {
-        if let Some(tip) = tip_opt {
-            format!("?tip={}{}", tip, if with_proof { "" } else { "&proof=0" })
-        } else if !with_proof {
-            format!("?proof=0")
-        } else {
-            "".to_string()
+    fn parse_post_dev_mine<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        // the body is optional
+        let content_len = preamble.get_content_length();
+        if content_len == 0 {
+            return Ok(HttpRequestType::DevMine(
+                HttpRequestMetadata::from_preamble(preamble),
+                RPCDevMineRequest::default(),
+            ));
+        }
+        if content_len >= MAX_MESSAGE_LEN {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for DevMine ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
         }
+
+        let body: RPCDevMineRequest = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        for txid in body.txids.iter() {
+            Txid::from_hex(txid)
+                .map_err(|_e| net_error::DeserializeError("Failed to parse txid".into()))?;
+        }
+
+        Ok(HttpRequestType::DevMine(
+            HttpRequestMetadata::from_preamble(preamble),
+            body,
+        ))
     }
 
-    pub fn request_path(&self) -> String {
-        match self {
-            HttpRequestType::GetInfo(_md) => "/v2/info".to_string(),
-            HttpRequestType::GetPoxInfo(_md, tip_opt) => format!(
-                "/v2/pox{}",
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::GetNeighbors(_md) => "/v2/neighbors".to_string(),
-            HttpRequestType::GetBlock(_md, block_hash) => {
-                format!("/v2/blocks/{}", block_hash.to_hex())
-            }
-            HttpRequestType::GetMicroblocksIndexed(_md, block_hash) => {
-                format!("/v2/microblocks/{}", block_hash.to_hex())
-            }
-            HttpRequestType::GetMicroblocksConfirmed(_md, block_hash) => {
-                format!("/v2/microblocks/confirmed/{}", block_hash.to_hex())
-            }
-            HttpRequestType::GetMicroblocksUnconfirmed(_md, block_hash, min_seq) => format!(
-                "/v2/microblocks/unconfirmed/{}/{}",
-                block_hash.to_hex(),
-                min_seq
-            ),
-            HttpRequestType::GetTransactionUnconfirmed(_md, txid) => {
-                format!("/v2/transactions/unconfirmed/{}", txid)
+    fn parse_post_simulate_block<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for SimulateBlock".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::SimulateBlock(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_batch_query(body: BatchQueryBody) -> Result<BatchQuery, net_error> {
+        let parse_contract =
+            |address: &str, name: String| -> Result<(StacksAddress, ContractName), net_error> {
+                let address = StacksAddress::from_string(address).ok_or_else(|| {
+                    net_error::DeserializeError("Failed to parse contract address".into())
+                })?;
+                let name = ContractName::try_from(name).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse contract name".into())
+                })?;
+                Ok((address, name))
+            };
+        let parse_value = |hex: &str| {
+            Value::try_deserialize_hex_untyped(hex)
+                .map_err(|_e| net_error::DeserializeError("Failed to deserialize value".into()))
+        };
+
+        match body {
+            BatchQueryBody::Account { principal } => {
+                let principal = PrincipalData::parse(&principal).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse account principal".into())
+                })?;
+                Ok(BatchQuery::Account(principal))
             }
-            HttpRequestType::PostTransaction(_md, ..) => "/v2/transactions".to_string(),
-            HttpRequestType::PostMicroblock(_md, _, tip_opt) => format!(
-                "/v2/microblocks{}",
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::GetAccount(_md, principal, tip_opt, with_proof) => format!(
-                "/v2/accounts/{}{}",
-                &principal.to_string(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
-            ),
-            HttpRequestType::GetMapEntry(
-                _md,
-                contract_addr,
+            BatchQueryBody::MapEntry {
+                contract_address,
                 contract_name,
                 map_name,
-                _key,
-                tip_opt,
-                with_proof,
-            ) => format!(
-                "/v2/map_entry/{}/{}/{}{}",
-                &contract_addr.to_string(),
-                contract_name.as_str(),
-                map_name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
-            ),
-            HttpRequestType::GetTransferCost(_md) => "/v2/fees/transfer".into(),
-            HttpRequestType::GetContractABI(_, contract_addr, contract_name, tip_opt) => format!(
-                "/v2/contracts/interface/{}/{}{}",
-                contract_addr,
-                contract_name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::GetContractSrc(
-                _,
-                contract_addr,
-                contract_name,
-                tip_opt,
-                with_proof,
-            ) => format!(
-                "/v2/contracts/source/{}/{}{}",
-                contract_addr,
-                contract_name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
-            ),
-            HttpRequestType::CallReadOnlyFunction(
-                _,
-                contract_addr,
-                contract_name,
-                _,
-                func_name,
-                _,
-                tip_opt,
-            ) => format!(
-                "/v2/contracts/call-read/{}/{}/{}{}",
-                contract_addr,
-                contract_name.as_str(),
-                func_name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::OptionsPreflight(_md, path) => path.to_string(),
-            HttpRequestType::GetAttachmentsInv(_md, tip_opt, pages_indexes) => {
-                let prefix = if tip_opt.is_some() { "&" } else { "?" };
-                let pages_query = match pages_indexes.len() {
-                    0 => format!(""),
-                    1 => format!(
-                        "{}pages_indexes={}",
-                        prefix,
-                        pages_indexes.iter().next().unwrap()
-                    ),
-                    _n => {
-                        let mut indexes = pages_indexes
-                            .iter()
-                            .map(|i| format!("{}", i))
-                            .collect::<Vec<String>>();
-                        indexes.sort();
-                        format!("{}pages_indexes={}", prefix, indexes.join(","))
-                    }
-                };
-                format!(
-                    "/v2/attachments/inv{}{}",
-                    HttpRequestType::make_query_string(tip_opt.as_ref(), true),
-                    pages_query,
-                )
+                key,
+            } => {
+                let (address, name) = parse_contract(&contract_address, contract_name)?;
+                let map_name = ClarityName::try_from(map_name)
+                    .map_err(|_e| net_error::DeserializeError("Failed to parse map name".into()))?;
+                let key = parse_value(&key)?;
+                Ok(BatchQuery::MapEntry(address, name, map_name, key))
             }
-            HttpRequestType::GetAttachment(_, content_hash) => {
-                format!("/v2/attachments/{}", to_hex(&content_hash.0[..]))
+            BatchQueryBody::CallRead {
+                contract_address,
+                contract_name,
+                function_name,
+                sender,
+                arguments,
+            } => {
+                let (address, name) = parse_contract(&contract_address, contract_name)?;
+                let function_name = ClarityName::try_from(function_name).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse function name".into())
+                })?;
+                let sender = PrincipalData::parse(&sender).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse sender principal".into())
+                })?;
+                let arguments = arguments
+                    .iter()
+                    .map(|hex| parse_value(hex))
+                    .collect::<Result<Vec<Value>, net_error>>()?;
+                Ok(BatchQuery::CallRead(
+                    address,
+                    name,
+                    function_name,
+                    sender,
+                    arguments,
+                ))
             }
-            HttpRequestType::ClientError(_md, e) => match e {
-                ClientError::NotFound(path) => path.to_string(),
-                _ => "error path unknown".into(),
-            },
         }
     }
 
-    pub fn send<W: Write>(&self, _protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
-        match self {
-            HttpRequestType::PostTransaction(md, tx, attachment) => {
-                let mut tx_bytes = vec![];
-                write_next(&mut tx_bytes, tx)?;
-                let tx_hex = to_hex(&tx_bytes[..]);
+    fn parse_post_batch<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_MESSAGE_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for Batch ({})",
+                content_len
+            )));
+        }
 
-                let (content_type, request_body_bytes) = match attachment {
-                    None => {
-                        // Transaction does not include an attachment: HttpContentType::Bytes (more compressed)
-                        (Some(&HttpContentType::Bytes), tx_bytes)
-                    }
-                    Some(attachment) => {
-                        // Transaction is including an attachment: HttpContentType::JSON
-                        let request_body = PostTransactionRequestBody {
-                            tx: tx_hex,
-                            attachment: Some(to_hex(&attachment.content[..])),
-                        };
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
 
-                        let mut request_body_bytes = vec![];
-                        serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(
-                            |e| {
-                                net_error::SerializeError(format!(
-                                    "Failed to serialize read-only call to JSON: {:?}",
-                                    &e
-                                ))
-                            },
-                        )?;
-                        (Some(&HttpContentType::JSON), request_body_bytes)
-                    }
-                };
+        let body: Vec<BatchQueryBody> = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        let queries = body
+            .into_iter()
+            .map(HttpRequestType::parse_batch_query)
+            .collect::<Result<Vec<BatchQuery>, net_error>>()?;
 
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(request_body_bytes.len() as u32),
-                    content_type,
-                    empty_headers,
-                )?;
-                fd.write_all(&request_body_bytes)
-                    .map_err(net_error::WriteError)?;
+        let tip = HttpRequestType::get_chain_tip_query(query);
+        let with_proof = HttpRequestType::get_proof_query(query);
+        let view = HttpRequestType::get_state_view_query(query, tip.as_ref())?;
+
+        Ok(HttpRequestType::Batch(
+            HttpRequestMetadata::from_preamble(preamble),
+            queries,
+            tip,
+            with_proof,
+            view,
+        ))
+    }
+
+    fn parse_get_miner_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMinerStatus".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetMinerStatus(
+            HttpRequestMetadata::from_preamble(preamble),
+            preamble.headers.get("authorization").cloned(),
+        ))
+    }
+
+    fn parse_get_scheduled_calls<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for ScheduledCalls".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::ScheduledCalls(
+            HttpRequestMetadata::from_preamble(preamble),
+            ScheduledCallsOp::List,
+            preamble.headers.get("authorization").cloned(),
+        ))
+    }
+
+    fn parse_post_scheduled_call<R: Read>(
+        protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for ScheduledCalls ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let body: ScheduledCallRequest = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        // the node will have to build a transaction out of this, so check it now
+        StacksAddress::from_string(&body.contract_address).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        ContractName::try_from(body.contract_name.clone())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        ClarityName::try_from(body.function_name.clone())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse function name".into()))?;
+        for arg in body.function_args.iter() {
+            Value::try_deserialize_hex_untyped(arg).map_err(|_e| {
+                net_error::DeserializeError("Failed to deserialize argument value".into())
+            })?;
+        }
+
+        Ok(HttpRequestType::ScheduledCalls(
+            HttpRequestMetadata::from_preamble(preamble),
+            ScheduledCallsOp::Add(body),
+            preamble.headers.get("authorization").cloned(),
+        ))
+    }
+
+    fn parse_delete_scheduled_call<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for ScheduledCalls".to_string(),
+            ));
+        }
+
+        let id = captures["id"]
+            .parse::<u64>()
+            .map_err(|_e| net_error::DeserializeError("Failed to parse call ID".into()))?;
+
+        Ok(HttpRequestType::ScheduledCalls(
+            HttpRequestMetadata::from_preamble(preamble),
+            ScheduledCallsOp::Cancel(id),
+            preamble.headers.get("authorization").cloned(),
+        ))
+    }
+
+    fn parse_post_validate_block_proposal<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected non-zero-length body for ValidateBlockProposal"
+                    .to_string(),
+            ));
+        }
+
+        // content-type must be given, and must be application/octet-stream
+        match preamble.content_type {
+            None => {
+                return Err(net_error::DeserializeError(
+                    "Missing Content-Type for block".to_string(),
+                ));
             }
-            HttpRequestType::PostMicroblock(md, mb, ..) => {
-                let mut mb_bytes = vec![];
-                write_next(&mut mb_bytes, mb)?;
+            Some(ref c) => {
+                if *c != HttpContentType::Bytes {
+                    return Err(net_error::DeserializeError(
+                        "Wrong Content-Type for block; expected application/octet-stream"
+                            .to_string(),
+                    ));
+                }
+            }
+        };
 
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(mb_bytes.len() as u32),
-                    Some(&HttpContentType::Bytes),
-                    empty_headers,
-                )?;
-                fd.write_all(&mb_bytes).map_err(net_error::WriteError)?;
+        let parent_consensus_hash = ConsensusHash::from_hex(&captures["parent_consensus_hash"])
+            .map_err(|_| {
+                net_error::DeserializeError("Failed to parse parent consensus hash".to_string())
+            })?;
+
+        let block = StacksBlock::consensus_deserialize(fd).map_err(|e| {
+            if let net_error::DeserializeError(msg) = e {
+                net_error::ClientError(ClientError::Message(format!(
+                    "Failed to deserialize block: {}",
+                    msg
+                )))
+            } else {
+                e
             }
-            HttpRequestType::GetMapEntry(
-                md,
-                _contract_addr,
-                _contract_name,
-                _map_name,
-                key,
-                ..,
-            ) => {
-                let mut request_bytes = vec![];
-                key.serialize_write(&mut request_bytes)
-                    .map_err(net_error::WriteError)?;
-                let request_json = format!("\"{}\"", to_hex(&request_bytes));
+        })?;
 
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(request_json.as_bytes().len() as u32),
-                    Some(&HttpContentType::JSON),
-                    empty_headers,
-                )?;
-                fd.write_all(&request_json.as_bytes())
-                    .map_err(net_error::WriteError)?;
+        Ok(HttpRequestType::ValidateBlockProposal(
+            HttpRequestMetadata::from_preamble(preamble),
+            parent_consensus_hash,
+            block,
+            preamble.headers.get("authorization").cloned(),
+        ))
+    }
+
+    fn parse_post_block<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected non-zero-length body for PostBlock".to_string(),
+            ));
+        }
+
+        // content-type must be given, and must be application/octet-stream
+        match preamble.content_type {
+            None => {
+                return Err(net_error::DeserializeError(
+                    "Missing Content-Type for block".to_string(),
+                ));
             }
-            HttpRequestType::CallReadOnlyFunction(
-                md,
-                _contract_addr,
-                _contract_name,
-                sender,
-                _func_name,
-                func_args,
-                ..,
-            ) => {
-                let mut args = vec![];
-                for arg in func_args.iter() {
-                    let mut arg_bytes = vec![];
-                    arg.serialize_write(&mut arg_bytes)
-                        .map_err(net_error::WriteError)?;
-                    args.push(to_hex(&arg_bytes));
+            Some(ref c) => {
+                if *c != HttpContentType::Bytes {
+                    return Err(net_error::DeserializeError(
+                        "Wrong Content-Type for block; expected application/octet-stream"
+                            .to_string(),
+                    ));
                 }
+            }
+        };
+
+        let consensus_hash =
+            ConsensusHash::from_hex(&captures["consensus_hash"]).map_err(|_| {
+                net_error::DeserializeError("Failed to parse consensus hash".to_string())
+            })?;
+
+        let block = StacksBlock::consensus_deserialize(fd)?;
+
+        Ok(HttpRequestType::PostBlock(
+            HttpRequestMetadata::from_preamble(preamble),
+            consensus_hash,
+            block,
+        ))
+    }
+
+    fn parse_get_map_entry<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < (BOUND_VALUE_SERIALIZATION_HEX)) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for GetMapEntry ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".into(),
+            ));
+        }
+
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let map_name = ClarityName::try_from(captures["map"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+
+        let value_hex: String = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let value = Value::try_deserialize_hex_untyped(&value_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to deserialize key value".into()))?;
+
+        let with_proof = HttpRequestType::get_proof_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query);
+        let height = HttpRequestType::get_chain_height_query(query, RPCStateView::Confirmed)?;
+
+        Ok(HttpRequestType::GetMapEntry(
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_addr,
+            contract_name,
+            map_name,
+            value,
+            tip,
+            height,
+            with_proof,
+        ))
+    }
+
+    fn parse_call_read_only<R: Read>(
+        protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for CallReadOnly ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let func_name = ClarityName::try_from(captures["function"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+
+        let body: CallReadOnlyRequestBody = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let sender = PrincipalData::parse(&body.sender)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse sender principal".into()))?;
+
+        let arguments = body
+            .arguments
+            .into_iter()
+            .map(|hex| Value::try_deserialize_hex_untyped(&hex).ok())
+            .collect::<Option<Vec<Value>>>()
+            .ok_or_else(|| {
+                net_error::DeserializeError("Failed to deserialize argument value".into())
+            })?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+        let view = HttpRequestType::get_state_view_query(query, tip.as_ref())?;
+        let height = HttpRequestType::get_chain_height_query(query, view)?;
+
+        Ok(HttpRequestType::CallReadOnlyFunction(
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_addr,
+            contract_name,
+            sender,
+            func_name,
+            arguments,
+            tip,
+            height,
+            view,
+        ))
+    }
+
+    fn parse_get_contract_arguments(
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+    ) -> Result<(HttpRequestMetadata, StacksAddress, ContractName), net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+
+        Ok((
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_addr,
+            contract_name,
+        ))
+    }
+
+    fn parse_get_contract_abi<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let tip = HttpRequestType::get_chain_tip_query(query);
+        HttpRequestType::parse_get_contract_arguments(preamble, captures).map(
+            |(preamble, addr, name)| HttpRequestType::GetContractABI(preamble, addr, name, tip),
+        )
+    }
+
+    fn parse_get_contract_source<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let with_proof = HttpRequestType::get_proof_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query);
+        let height = HttpRequestType::get_chain_height_query(query, RPCStateView::Confirmed)?;
+        HttpRequestType::parse_get_contract_arguments(preamble, captures).map(
+            |(preamble, addr, name)| {
+                HttpRequestType::GetContractSrc(preamble, addr, name, tip, height, with_proof)
+            },
+        )
+    }
+
+    fn parse_get_data_var<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let with_proof = HttpRequestType::get_proof_query(query);
+        let tip = HttpRequestType::get_chain_tip_query(query);
+        let var_name = ClarityName::try_from(captures["var"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse data var name".into()))?;
+        HttpRequestType::parse_get_contract_arguments(preamble, captures).map(
+            |(preamble, addr, name)| {
+                HttpRequestType::GetDataVar(preamble, addr, name, var_name, tip, with_proof)
+            },
+        )
+    }
+
+    fn parse_getblock<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlock".to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to block hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        Ok(HttpRequestType::GetBlock(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+        ))
+    }
+
+    fn parse_get_block_event_bloom<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlockEventBloom".to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to block hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        Ok(HttpRequestType::GetBlockEventBloom(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+        ))
+    }
+
+    fn parse_getheaders<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetHeaders".to_string(),
+            ));
+        }
+
+        let quantity = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to header quantity group".to_string(),
+            ))?
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_| {
+                net_error::DeserializeError("Failed to parse header quantity".to_string())
+            })?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetHeaders(
+            HttpRequestMetadata::from_preamble(preamble),
+            quantity,
+            tip,
+        ))
+    }
+
+    fn parse_getmicroblocks_indexed<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicroblocksIndexed"
+                    .to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to microblock hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse microblock hash".to_string())
+        })?;
+
+        Ok(HttpRequestType::GetMicroblocksIndexed(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+        ))
+    }
+
+    fn parse_getmicroblocks_confirmed<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicrolocks".to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to microblock hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse microblock hash".to_string())
+        })?;
+
+        Ok(HttpRequestType::GetMicroblocksConfirmed(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+        ))
+    }
+
+    fn parse_getmicroblocks_unconfirmed<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicrolocksUnconfirmed"
+                    .to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to microblock hash group".to_string(),
+            ))?
+            .as_str();
+
+        let min_seq_str = captures
+            .get(2)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to microblock minimum sequence group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse microblock hash".to_string())
+        })?;
+
+        let min_seq = min_seq_str.parse::<u16>().map_err(|_e| {
+            net_error::DeserializeError("Failed to parse microblock minimum sequence".to_string())
+        })?;
+
+        Ok(HttpRequestType::GetMicroblocksUnconfirmed(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+            min_seq,
+        ))
+    }
+
+    fn parse_gettransaction_unconfirmed<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicrolocksUnconfirmed"
+                    .to_string(),
+            ));
+        }
+
+        let txid_hex = regex
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to txid group".to_string(),
+            ))?
+            .as_str();
+
+        if txid_hex.len() != 64 {
+            return Err(net_error::DeserializeError(
+                "Invalid txid: expected 64 bytes".to_string(),
+            ));
+        }
+
+        let txid = Txid::from_hex(&txid_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
+
+        Ok(HttpRequestType::GetTransactionUnconfirmed(
+            HttpRequestMetadata::from_preamble(preamble),
+            txid,
+        ))
+    }
+
+    fn parse_posttransaction<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected non-zero-length body for PostTransaction"
+                    .to_string(),
+            ));
+        }
+
+        match preamble.content_type {
+            None => {
+                return Err(net_error::DeserializeError(
+                    "Missing Content-Type for transaction".to_string(),
+                ));
+            }
+            Some(HttpContentType::Bytes) => {
+                HttpRequestType::parse_posttransaction_octets(preamble, fd)
+            }
+            Some(HttpContentType::JSON) => {
+                HttpRequestType::parse_posttransaction_json(preamble, fd)
+            }
+            _ => {
+                return Err(net_error::DeserializeError(
+                    "Wrong Content-Type for transaction; expected application/json".to_string(),
+                ));
+            }
+        }
+    }
+
+    fn parse_posttransaction_octets<R: Read>(
+        preamble: &HttpRequestPreamble,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let tx = StacksTransaction::consensus_deserialize(fd).map_err(|e| {
+            if let net_error::DeserializeError(msg) = e {
+                net_error::ClientError(ClientError::Message(format!(
+                    "Failed to deserialize posted transaction: {}",
+                    msg
+                )))
+            } else {
+                e
+            }
+        })?;
+        Ok(HttpRequestType::PostTransaction(
+            HttpRequestMetadata::from_preamble(preamble),
+            tx,
+            None,
+        ))
+    }
+
+    fn parse_posttransaction_json<R: Read>(
+        preamble: &HttpRequestPreamble,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let body: PostTransactionRequestBody = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse body".into()))?;
+
+        let tx = {
+            let tx_bytes = hex_bytes(&body.tx)
+                .map_err(|_e| net_error::DeserializeError("Failed to parse tx".into()))?;
+            StacksTransaction::consensus_deserialize(&mut &tx_bytes[..]).map_err(|e| {
+                if let net_error::DeserializeError(msg) = e {
+                    net_error::ClientError(ClientError::Message(format!(
+                        "Failed to deserialize posted transaction: {}",
+                        msg
+                    )))
+                } else {
+                    e
+                }
+            })
+        }?;
+
+        let attachment = match body.attachment {
+            None => None,
+            Some(attachment_content) => {
+                let content = hex_bytes(&attachment_content).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse attachment".into())
+                })?;
+                Some(Attachment::new(content))
+            }
+        };
+
+        Ok(HttpRequestType::PostTransaction(
+            HttpRequestMetadata::from_preamble(preamble),
+            tx,
+            attachment,
+        ))
+    }
+
+    fn parse_postmicroblock<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected non-zero-length body for PostMicroblock"
+                    .to_string(),
+            ));
+        }
+
+        // content-type must be given, and must be application/octet-stream
+        match preamble.content_type {
+            None => {
+                return Err(net_error::DeserializeError(
+                    "Missing Content-Type for microblock".to_string(),
+                ));
+            }
+            Some(ref c) => {
+                if *c != HttpContentType::Bytes {
+                    return Err(net_error::DeserializeError(
+                        "Wrong Content-Type for microblock; expected application/octet-stream"
+                            .to_string(),
+                    ));
+                }
+            }
+        };
+
+        let mb = StacksMicroblock::consensus_deserialize(fd)?;
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::PostMicroblock(
+            HttpRequestMetadata::from_preamble(preamble),
+            mb,
+            tip,
+        ))
+    }
+
+    fn parse_get_attachment<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+        let hex_content_hash = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to attachment hash group".to_string(),
+            ))?
+            .as_str();
+
+        let content_hash = Hash160::from_hex(&hex_content_hash).map_err(|_| {
+            net_error::DeserializeError("Failed to construct hash160 from inputs".to_string())
+        })?;
+
+        Ok(HttpRequestType::GetAttachment(
+            HttpRequestMetadata::from_preamble(preamble),
+            content_hash,
+        ))
+    }
+
+    fn parse_get_attachments_inv<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let mut tip = None;
+        let mut pages_indexes = HashSet::new();
+
+        if let Some(query) = query {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                if key == "tip" {
+                    tip = match StacksBlockId::from_hex(&value) {
+                        Ok(tip) => Some(tip),
+                        _ => None,
+                    };
+                } else if key == "pages_indexes" {
+                    if let Ok(pages_indexes_value) = value.parse::<String>() {
+                        for entry in pages_indexes_value.split(",") {
+                            if let Ok(page_index) = entry.parse::<u32>() {
+                                pages_indexes.insert(page_index);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(HttpRequestType::GetAttachmentsInv(
+            HttpRequestMetadata::from_preamble(preamble),
+            tip,
+            pages_indexes,
+        ))
+    }
+
+    fn parse_get_attachment_instances<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let mut tip = None;
+        let mut pages_indexes = HashSet::new();
+        let mut contract_id = None;
+        let mut namespace = None;
+
+        if let Some(query) = query {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                if key == "tip" {
+                    tip = StacksBlockId::from_hex(&value).ok();
+                } else if key == "pages_indexes" {
+                    for entry in value.split(",") {
+                        if let Ok(page_index) = entry.parse::<u32>() {
+                            pages_indexes.insert(page_index);
+                        }
+                    }
+                } else if key == "contract_id" {
+                    let parsed = QualifiedContractIdentifier::parse(&value).map_err(|_e| {
+                        net_error::DeserializeError("Failed to parse contract_id".to_string())
+                    })?;
+                    contract_id = Some(parsed);
+                } else if key == "namespace" {
+                    if value.len() < BNS_NAMESPACE_MIN_LEN
+                        || value.len() > BNS_NAMESPACE_MAX_LEN
+                        || !value.is_ascii()
+                    {
+                        return Err(net_error::DeserializeError("Invalid namespace".to_string()));
+                    }
+                    namespace = Some(value.to_string());
+                }
+            }
+        }
+        Ok(HttpRequestType::GetAttachmentInstances(
+            HttpRequestMetadata::from_preamble(preamble),
+            tip,
+            pages_indexes,
+            contract_id,
+            namespace,
+        ))
+    }
+
+    fn parse_stackerdb_contract_id(
+        captures: &Captures,
+    ) -> Result<QualifiedContractIdentifier, net_error> {
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        Ok(QualifiedContractIdentifier::new(
+            contract_addr.into(),
+            contract_name,
+        ))
+    }
+
+    fn parse_get_stackerdb_metadata<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let contract_id = HttpRequestType::parse_stackerdb_contract_id(captures)?;
+        Ok(HttpRequestType::GetStackerDBMetadata(
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_id,
+        ))
+    }
+
+    fn parse_get_stackerdb_chunk<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let contract_id = HttpRequestType::parse_stackerdb_contract_id(captures)?;
+        let slot_id = captures["slot_id"]
+            .parse::<u32>()
+            .map_err(|_e| net_error::DeserializeError("Failed to parse slot ID".into()))?;
+        Ok(HttpRequestType::GetStackerDBChunk(
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_id,
+            slot_id,
+        ))
+    }
+
+    fn parse_post_stackerdb_chunk<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        // the chunk data is hex-encoded, plus some room for the rest of the JSON
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len <= 2 * STACKERDB_MAX_CHUNK_SIZE + 1024) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for StackerDB chunk ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let contract_id = HttpRequestType::parse_stackerdb_contract_id(captures)?;
+        let chunk: StackerDBChunkData = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        Ok(HttpRequestType::PostStackerDBChunk(
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_id,
+            chunk,
+        ))
+    }
+
+    fn parse_options_preflight<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        Ok(HttpRequestType::OptionsPreflight(
+            HttpRequestMetadata::from_preamble(preamble),
+            preamble.path.to_string(),
+        ))
+    }
+
+    pub fn metadata(&self) -> &HttpRequestMetadata {
+        match *self {
+            HttpRequestType::GetInfo(ref md) => md,
+            HttpRequestType::GetPoxInfo(ref md, _) => md,
+            HttpRequestType::GetRewardSet(ref md, _) => md,
+            HttpRequestType::GetPoxDelegations(ref md, ..) => md,
+            HttpRequestType::SimulateAggregationCommit(ref md, ..) => md,
+            HttpRequestType::GetBurnBlockInfo(ref md, _) => md,
+            HttpRequestType::GetBurnchainOps(ref md, _) => md,
+            HttpRequestType::GetNeighbors(ref md) => md,
+            HttpRequestType::GetBlock(ref md, _) => md,
+            HttpRequestType::GetBlockEventBloom(ref md, _) => md,
+            HttpRequestType::GetHeaders(ref md, ..) => md,
+            HttpRequestType::GetMicroblocksIndexed(ref md, _) => md,
+            HttpRequestType::GetMicroblocksConfirmed(ref md, _) => md,
+            HttpRequestType::GetMicroblocksUnconfirmed(ref md, _, _) => md,
+            HttpRequestType::GetTransactionUnconfirmed(ref md, _) => md,
+            HttpRequestType::PostTransaction(ref md, _, _) => md,
+            HttpRequestType::PostMicroblock(ref md, ..) => md,
+            HttpRequestType::GetAccount(ref md, ..) => md,
+            HttpRequestType::GetAccountAssets(ref md, ..) => md,
+            HttpRequestType::GetAccountNonces(ref md, ..) => md,
+            HttpRequestType::GetAccountSummary(ref md, ..) => md,
+            HttpRequestType::GetTransactionReceipt(ref md, ..) => md,
+            HttpRequestType::GetPrincipalTransactions(ref md, ..) => md,
+            HttpRequestType::GetPendingMinerRewards(ref md, ..) => md,
+            HttpRequestType::GetUnconfirmedMicroblockStream(ref md, ..) => md,
+            HttpRequestType::MemPoolGC(ref md) => md,
+            HttpRequestType::DevMine(ref md, _) => md,
+            HttpRequestType::SimulateBlock(ref md) => md,
+            HttpRequestType::Batch(ref md, ..) => md,
+            HttpRequestType::ValidateBlockProposal(ref md, ..) => md,
+            HttpRequestType::GetMinerStatus(ref md, _) => md,
+            HttpRequestType::ScheduledCalls(ref md, ..) => md,
+            HttpRequestType::PostBlock(ref md, ..) => md,
+            HttpRequestType::GetMapEntry(ref md, ..) => md,
+            HttpRequestType::GetTransferCost(ref md) => md,
+            HttpRequestType::EstimateTransactionFee(ref md, _) => md,
+            HttpRequestType::SimulateTransaction(ref md, _) => md,
+            HttpRequestType::AnalyzeContract(ref md, ..) => md,
+            HttpRequestType::GetContractABI(ref md, ..) => md,
+            HttpRequestType::GetContractSrc(ref md, ..) => md,
+            HttpRequestType::GetDataVar(ref md, ..) => md,
+            HttpRequestType::CallReadOnlyFunction(ref md, ..) => md,
+            HttpRequestType::OptionsPreflight(ref md, ..) => md,
+            HttpRequestType::GetAttachmentsInv(ref md, ..) => md,
+            HttpRequestType::GetAttachmentInstances(ref md, ..) => md,
+            HttpRequestType::GetAttachment(ref md, ..) => md,
+            HttpRequestType::GetStackerDBMetadata(ref md, ..) => md,
+            HttpRequestType::GetStackerDBChunk(ref md, ..) => md,
+            HttpRequestType::PostStackerDBChunk(ref md, ..) => md,
+            HttpRequestType::ClientError(ref md, ..) => md,
+        }
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut HttpRequestMetadata {
+        match *self {
+            HttpRequestType::GetInfo(ref mut md) => md,
+            HttpRequestType::GetPoxInfo(ref mut md, _) => md,
+            HttpRequestType::GetRewardSet(ref mut md, _) => md,
+            HttpRequestType::GetPoxDelegations(ref mut md, ..) => md,
+            HttpRequestType::SimulateAggregationCommit(ref mut md, ..) => md,
+            HttpRequestType::GetBurnBlockInfo(ref mut md, _) => md,
+            HttpRequestType::GetBurnchainOps(ref mut md, _) => md,
+            HttpRequestType::GetNeighbors(ref mut md) => md,
+            HttpRequestType::GetBlock(ref mut md, _) => md,
+            HttpRequestType::GetBlockEventBloom(ref mut md, _) => md,
+            HttpRequestType::GetHeaders(ref mut md, ..) => md,
+            HttpRequestType::GetMicroblocksIndexed(ref mut md, _) => md,
+            HttpRequestType::GetMicroblocksConfirmed(ref mut md, _) => md,
+            HttpRequestType::GetMicroblocksUnconfirmed(ref mut md, _, _) => md,
+            HttpRequestType::GetTransactionUnconfirmed(ref mut md, _) => md,
+            HttpRequestType::PostTransaction(ref mut md, _, _) => md,
+            HttpRequestType::PostMicroblock(ref mut md, ..) => md,
+            HttpRequestType::GetAccount(ref mut md, ..) => md,
+            HttpRequestType::GetAccountAssets(ref mut md, ..) => md,
+            HttpRequestType::GetAccountNonces(ref mut md, ..) => md,
+            HttpRequestType::GetAccountSummary(ref mut md, ..) => md,
+            HttpRequestType::GetTransactionReceipt(ref mut md, ..) => md,
+            HttpRequestType::GetPrincipalTransactions(ref mut md, ..) => md,
+            HttpRequestType::GetPendingMinerRewards(ref mut md, ..) => md,
+            HttpRequestType::GetUnconfirmedMicroblockStream(ref mut md, ..) => md,
+            HttpRequestType::MemPoolGC(ref mut md) => md,
+            HttpRequestType::DevMine(ref mut md, _) => md,
+            HttpRequestType::SimulateBlock(ref mut md) => md,
+            HttpRequestType::Batch(ref mut md, ..) => md,
+            HttpRequestType::ValidateBlockProposal(ref mut md, ..) => md,
+            HttpRequestType::GetMinerStatus(ref mut md, _) => md,
+            HttpRequestType::ScheduledCalls(ref mut md, ..) => md,
+            HttpRequestType::PostBlock(ref mut md, ..) => md,
+            HttpRequestType::GetMapEntry(ref mut md, ..) => md,
+            HttpRequestType::GetTransferCost(ref mut md) => md,
+            HttpRequestType::EstimateTransactionFee(ref mut md, _) => md,
+            HttpRequestType::SimulateTransaction(ref mut md, _) => md,
+            HttpRequestType::AnalyzeContract(ref mut md, ..) => md,
+            HttpRequestType::GetContractABI(ref mut md, ..) => md,
+            HttpRequestType::GetContractSrc(ref mut md, ..) => md,
+            HttpRequestType::GetDataVar(ref mut md, ..) => md,
+            HttpRequestType::CallReadOnlyFunction(ref mut md, ..) => md,
+            HttpRequestType::OptionsPreflight(ref mut md, ..) => md,
+            HttpRequestType::GetAttachmentsInv(ref mut md, ..) => md,
+            HttpRequestType::GetAttachmentInstances(ref mut md, ..) => md,
+            HttpRequestType::GetAttachment(ref mut md, ..) => md,
+            HttpRequestType::GetStackerDBMetadata(ref mut md, ..) => md,
+            HttpRequestType::GetStackerDBChunk(ref mut md, ..) => md,
+            HttpRequestType::PostStackerDBChunk(ref mut md, ..) => md,
+            HttpRequestType::ClientError(ref mut md, ..) => md,
+        }
+    }
+
+    /// If this request uses a deprecated endpoint or parameter, get the deprecation notice to
+    /// surface in its response.
+    pub fn deprecation(&self) -> Option<HttpDeprecation> {
+        match self {
+            HttpRequestType::GetTransferCost(_md) => Some(HttpDeprecation {
+                usage: "GET /v2/fees/transfer",
+                successor: "/v2/fees/transaction",
+            }),
+            _ => None,
+        }
+    }
+
+    fn make_batch_query_body(query: &BatchQuery) -> Result<BatchQueryBody, net_error> {
+        let to_hex_value = |value: &Value| -> Result<String, net_error> {
+            let mut value_bytes = vec![];
+            value
+                .serialize_write(&mut value_bytes)
+                .map_err(net_error::WriteError)?;
+            Ok(to_hex(&value_bytes))
+        };
+
+        let body = match query {
+            BatchQuery::Account(principal) => BatchQueryBody::Account {
+                principal: principal.to_string(),
+            },
+            BatchQuery::MapEntry(address, name, map_name, key) => BatchQueryBody::MapEntry {
+                contract_address: address.to_string(),
+                contract_name: name.to_string(),
+                map_name: map_name.to_string(),
+                key: to_hex_value(key)?,
+            },
+            BatchQuery::CallRead(address, name, function_name, sender, arguments) => {
+                BatchQueryBody::CallRead {
+                    contract_address: address.to_string(),
+                    contract_name: name.to_string(),
+                    function_name: function_name.to_string(),
+                    sender: sender.to_string(),
+                    arguments: arguments
+                        .iter()
+                        .map(|arg| to_hex_value(arg))
+                        .collect::<Result<Vec<String>, net_error>>()?,
+                }
+            }
+        };
+        Ok(body)
+    }
+
+    fn make_query_string(tip_opt: Option<&StacksBlockId>, with_proof: bool) -> String {
+        if let Some(tip) = tip_opt {
+            format!("?tip={}{}", tip, if with_proof { "" } else { "&proof=0" })
+        } else if !with_proof {
+            format!("?proof=0")
+        } else {
+            "".to_string()
+        }
+    }
+
+    fn make_state_view_query_string(
+        tip_opt: Option<&StacksBlockId>,
+        with_proof: bool,
+        view: RPCStateView,
+    ) -> String {
+        let mut query = HttpRequestType::make_query_string(tip_opt, with_proof);
+        let view_arg = match view {
+            RPCStateView::Confirmed => {
+                return query;
+            }
+            RPCStateView::Unconfirmed => "unconfirmed=1",
+            RPCStateView::UnconfirmedWithMempool => "mempool=1",
+        };
+        query.push(if query.is_empty() { '?' } else { '&' });
+        query.push_str(view_arg);
+        query
+    }
+
+    fn make_height_query_string(mut query: String, height_opt: Option<u64>) -> String {
+        if let Some(height) = height_opt {
+            query.push(if query.is_empty() { '?' } else { '&' });
+            query.push_str(&format!("height={}", height));
+        }
+        query
+    }
+
+    pub fn request_path(&self) -> String {
+        match self {
+            HttpRequestType::GetInfo(_md) => "/v2/info".to_string(),
+            HttpRequestType::GetPoxInfo(_md, tip_opt) => format!(
+                "/v2/pox{}",
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetRewardSet(_md, tip_opt) => format!(
+                "/v2/pox/reward_set{}",
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetPoxDelegations(_md, delegate, tip_opt) => format!(
+                "/v2/pox/delegations/{}{}",
+                &delegate.to_string(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::SimulateAggregationCommit(_md, delegate, _, _, tip_opt) => format!(
+                "/v2/pox/aggregation_commit/{}{}",
+                &delegate.to_string(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetBurnBlockInfo(_md, burn_block_height) => {
+                format!("/v2/burn_blocks/{}", burn_block_height)
+            }
+            HttpRequestType::GetBurnchainOps(_md, BurnchainOpsQuery::Height(burn_block_height)) => {
+                format!("/v2/burn_blocks/{}/ops", burn_block_height)
+            }
+            HttpRequestType::GetBurnchainOps(_md, BurnchainOpsQuery::Txid(txid)) => {
+                format!("/v2/burn_ops/{}", txid.to_hex())
+            }
+            HttpRequestType::GetNeighbors(_md) => "/v2/neighbors".to_string(),
+            HttpRequestType::GetBlock(_md, block_hash) => {
+                format!("/v2/blocks/{}", block_hash.to_hex())
+            }
+            HttpRequestType::GetBlockEventBloom(_md, block_hash) => {
+                format!("/v2/blocks/{}/event_bloom", block_hash.to_hex())
+            }
+            HttpRequestType::GetHeaders(_md, quantity, tip_opt) => format!(
+                "/v2/headers/{}{}",
+                quantity,
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetMicroblocksIndexed(_md, block_hash) => {
+                format!("/v2/microblocks/{}", block_hash.to_hex())
+            }
+            HttpRequestType::GetMicroblocksConfirmed(_md, block_hash) => {
+                format!("/v2/microblocks/confirmed/{}", block_hash.to_hex())
+            }
+            HttpRequestType::GetMicroblocksUnconfirmed(_md, block_hash, min_seq) => format!(
+                "/v2/microblocks/unconfirmed/{}/{}",
+                block_hash.to_hex(),
+                min_seq
+            ),
+            HttpRequestType::GetTransactionUnconfirmed(_md, txid) => {
+                format!("/v2/transactions/unconfirmed/{}", txid)
+            }
+            HttpRequestType::PostTransaction(_md, ..) => "/v2/transactions".to_string(),
+            HttpRequestType::PostMicroblock(_md, _, tip_opt) => format!(
+                "/v2/microblocks{}",
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetAccount(_md, principal, tip_opt, height_opt, with_proof, view) => {
+                format!(
+                    "/v2/accounts/{}{}",
+                    &principal.to_string(),
+                    HttpRequestType::make_height_query_string(
+                        HttpRequestType::make_state_view_query_string(
+                            tip_opt.as_ref(),
+                            *with_proof,
+                            *view
+                        ),
+                        *height_opt
+                    )
+                )
+            }
+            HttpRequestType::GetAccountAssets(_md, principal, tip_opt) => format!(
+                "/v2/accounts/{}/assets{}",
+                &principal.to_string(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetAccountNonces(_md, principal, tip_opt) => format!(
+                "/v2/accounts/{}/nonces{}",
+                &principal.to_string(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetAccountSummary(_md, principal, tip_opt) => format!(
+                "/v2/accounts/{}/summary{}",
+                &principal.to_string(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetTransactionReceipt(_md, txid, tip_opt) => format!(
+                "/v2/transactions/{}/receipt{}",
+                txid,
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetPrincipalTransactions(_md, principal, offset, limit, tip_opt) => {
+                format!(
+                    "/v2/addresses/{}/transactions?offset={}&limit={}{}",
+                    &principal.to_string(),
+                    offset,
+                    limit,
+                    match tip_opt {
+                        Some(tip) => format!("&tip={}", tip),
+                        None => "".to_string(),
+                    }
+                )
+            }
+            HttpRequestType::GetPendingMinerRewards(_md, address, tip_opt) => format!(
+                "/v2/addresses/{}/pending_rewards{}",
+                address,
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetUnconfirmedMicroblockStream(_md, min_seq, tip_opt) => format!(
+                "/v2/microblocks/unconfirmed?seq={}{}",
+                min_seq,
+                match tip_opt {
+                    Some(tip) => format!("&tip={}", tip),
+                    None => "".to_string(),
+                }
+            ),
+            HttpRequestType::MemPoolGC(_md) => "/v2/mempool/gc".to_string(),
+            HttpRequestType::DevMine(..) => "/v2/dev/mine".to_string(),
+            HttpRequestType::GetMinerStatus(..) => "/v2/admin/miner".to_string(),
+            HttpRequestType::ScheduledCalls(_md, op, _) => match op {
+                ScheduledCallsOp::Cancel(id) => format!("/v2/admin/scheduled_calls/{}", id),
+                _ => "/v2/admin/scheduled_calls".to_string(),
+            },
+            HttpRequestType::PostBlock(_md, consensus_hash, _) => {
+                format!("/v2/blocks/upload/{}", consensus_hash)
+            }
+            HttpRequestType::SimulateBlock(_md) => "/v2/mempool/simulate_block".to_string(),
+            HttpRequestType::Batch(_md, _queries, tip_opt, with_proof, view) => format!(
+                "/v2/batch{}",
+                HttpRequestType::make_state_view_query_string(tip_opt.as_ref(), *with_proof, *view)
+            ),
+            HttpRequestType::ValidateBlockProposal(_md, parent_consensus_hash, ..) => {
+                format!("/v2/blocks/validate/{}", parent_consensus_hash)
+            }
+            HttpRequestType::GetMapEntry(
+                _md,
+                contract_addr,
+                contract_name,
+                map_name,
+                _key,
+                tip_opt,
+                height_opt,
+                with_proof,
+            ) => format!(
+                "/v2/map_entry/{}/{}/{}{}",
+                &contract_addr.to_string(),
+                contract_name.as_str(),
+                map_name.as_str(),
+                HttpRequestType::make_height_query_string(
+                    HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof),
+                    *height_opt
+                )
+            ),
+            HttpRequestType::GetTransferCost(_md) => "/v2/fees/transfer".into(),
+            HttpRequestType::EstimateTransactionFee(_md, _) => "/v2/fees/transaction".into(),
+            HttpRequestType::SimulateTransaction(_md, _) => "/v2/transactions/simulate".into(),
+            HttpRequestType::AnalyzeContract(_md, _, _, tip_opt) => format!(
+                "/v2/contracts/analyze{}",
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetContractABI(_, contract_addr, contract_name, tip_opt) => format!(
+                "/v2/contracts/interface/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetContractSrc(
+                _,
+                contract_addr,
+                contract_name,
+                tip_opt,
+                height_opt,
+                with_proof,
+            ) => format!(
+                "/v2/contracts/source/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                HttpRequestType::make_height_query_string(
+                    HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof),
+                    *height_opt
+                )
+            ),
+            HttpRequestType::GetDataVar(
+                _,
+                contract_addr,
+                contract_name,
+                var_name,
+                tip_opt,
+                with_proof,
+            ) => format!(
+                "/v2/data_var/{}/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                var_name.as_str(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
+            ),
+            HttpRequestType::CallReadOnlyFunction(
+                _,
+                contract_addr,
+                contract_name,
+                _,
+                func_name,
+                _,
+                tip_opt,
+                height_opt,
+                view,
+            ) => format!(
+                "/v2/contracts/call-read/{}/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                func_name.as_str(),
+                HttpRequestType::make_height_query_string(
+                    HttpRequestType::make_state_view_query_string(tip_opt.as_ref(), true, *view),
+                    *height_opt
+                )
+            ),
+            HttpRequestType::OptionsPreflight(_md, path) => path.to_string(),
+            HttpRequestType::GetAttachmentsInv(_md, tip_opt, pages_indexes) => {
+                let prefix = if tip_opt.is_some() { "&" } else { "?" };
+                let pages_query = match pages_indexes.len() {
+                    0 => format!(""),
+                    1 => format!(
+                        "{}pages_indexes={}",
+                        prefix,
+                        pages_indexes.iter().next().unwrap()
+                    ),
+                    _n => {
+                        let mut indexes = pages_indexes
+                            .iter()
+                            .map(|i| format!("{}", i))
+                            .collect::<Vec<String>>();
+                        indexes.sort();
+                        format!("{}pages_indexes={}", prefix, indexes.join(","))
+                    }
+                };
+                format!(
+                    "/v2/attachments/inv{}{}",
+                    HttpRequestType::make_query_string(tip_opt.as_ref(), true),
+                    pages_query,
+                )
+            }
+            HttpRequestType::GetAttachmentInstances(
+                _md,
+                tip_opt,
+                pages_indexes,
+                contract_id_opt,
+                namespace_opt,
+            ) => {
+                let mut query = vec![];
+                if let Some(tip) = tip_opt {
+                    query.push(format!("tip={}", tip));
+                }
+                if !pages_indexes.is_empty() {
+                    let mut indexes = pages_indexes.iter().cloned().collect::<Vec<u32>>();
+                    indexes.sort();
+                    let indexes = indexes
+                        .iter()
+                        .map(|i| format!("{}", i))
+                        .collect::<Vec<String>>();
+                    query.push(format!("pages_indexes={}", indexes.join(",")));
+                }
+                if let Some(contract_id) = contract_id_opt {
+                    query.push(format!("contract_id={}", contract_id));
+                }
+                if let Some(namespace) = namespace_opt {
+                    let encoded: String =
+                        form_urlencoded::byte_serialize(namespace.as_bytes()).collect();
+                    query.push(format!("namespace={}", encoded));
+                }
+                if query.is_empty() {
+                    "/v2/attachments/inv/instances".to_string()
+                } else {
+                    format!("/v2/attachments/inv/instances?{}", query.join("&"))
+                }
+            }
+            HttpRequestType::GetAttachment(_, content_hash) => {
+                format!("/v2/attachments/{}", to_hex(&content_hash.0[..]))
+            }
+            HttpRequestType::GetStackerDBMetadata(_md, contract_id) => format!(
+                "/v2/stackerdb/{}/{}",
+                StacksAddress::from(contract_id.issuer.clone()),
+                contract_id.name.as_str()
+            ),
+            HttpRequestType::GetStackerDBChunk(_md, contract_id, slot_id) => format!(
+                "/v2/stackerdb/{}/{}/{}",
+                StacksAddress::from(contract_id.issuer.clone()),
+                contract_id.name.as_str(),
+                slot_id
+            ),
+            HttpRequestType::PostStackerDBChunk(_md, contract_id, _) => format!(
+                "/v2/stackerdb/{}/{}/chunks",
+                StacksAddress::from(contract_id.issuer.clone()),
+                contract_id.name.as_str()
+            ),
+            HttpRequestType::ClientError(_md, e) => match e {
+                ClientError::NotFound(path) => path.to_string(),
+                _ => "error path unknown".into(),
+            },
+        }
+    }
+
+    /// Does this request run Clarity code or otherwise do a lot of work to answer?  These requests
+    /// can be rate-limited separately, under `rpc_expensive_rate_limit`.
+    pub fn is_expensive(&self) -> bool {
+        match self {
+            HttpRequestType::CallReadOnlyFunction(..)
+            | HttpRequestType::GetMapEntry(..)
+            | HttpRequestType::SimulateTransaction(..)
+            | HttpRequestType::AnalyzeContract(..)
+            | HttpRequestType::SimulateBlock(..)
+            | HttpRequestType::EstimateTransactionFee(..)
+            | HttpRequestType::Batch(..) => true,
+            _ => false,
+        }
+    }
+
+    /// The request's route, without any path or query parameters.  Used to label per-endpoint
+    /// metrics, so it must come from a small, fixed set of values.
+    pub fn route(&self) -> &'static str {
+        match self {
+            HttpRequestType::GetInfo(..) => "/v2/info",
+            HttpRequestType::GetPoxInfo(..) => "/v2/pox",
+            HttpRequestType::GetRewardSet(..) => "/v2/pox/reward_set",
+            HttpRequestType::GetPoxDelegations(..) => "/v2/pox/delegations/:principal",
+            HttpRequestType::SimulateAggregationCommit(..) => {
+                "/v2/pox/aggregation_commit/:principal"
+            }
+            HttpRequestType::GetBurnBlockInfo(..) => "/v2/burn_blocks/:height",
+            HttpRequestType::GetBurnchainOps(_, BurnchainOpsQuery::Height(_)) => {
+                "/v2/burn_blocks/:height/ops"
+            }
+            HttpRequestType::GetBurnchainOps(_, BurnchainOpsQuery::Txid(_)) => "/v2/burn_ops/:txid",
+            HttpRequestType::GetNeighbors(..) => "/v2/neighbors",
+            HttpRequestType::GetBlock(..) => "/v2/blocks/:block_id",
+            HttpRequestType::GetBlockEventBloom(..) => "/v2/blocks/:block_id/event_bloom",
+            HttpRequestType::GetHeaders(..) => "/v2/headers/:quantity",
+            HttpRequestType::GetMicroblocksIndexed(..) => "/v2/microblocks/:block_id",
+            HttpRequestType::GetMicroblocksConfirmed(..) => "/v2/microblocks/confirmed/:block_id",
+            HttpRequestType::GetMicroblocksUnconfirmed(..) => {
+                "/v2/microblocks/unconfirmed/:block_id/:seq"
+            }
+            HttpRequestType::GetTransactionUnconfirmed(..) => "/v2/transactions/unconfirmed/:txid",
+            HttpRequestType::PostTransaction(..) => "/v2/transactions",
+            HttpRequestType::PostMicroblock(..) => "/v2/microblocks",
+            HttpRequestType::GetAccount(..) => "/v2/accounts/:principal",
+            HttpRequestType::GetAccountAssets(..) => "/v2/accounts/:principal/assets",
+            HttpRequestType::GetAccountNonces(..) => "/v2/accounts/:principal/nonces",
+            HttpRequestType::GetAccountSummary(..) => "/v2/accounts/:principal/summary",
+            HttpRequestType::GetTransactionReceipt(..) => "/v2/transactions/:txid/receipt",
+            HttpRequestType::GetPrincipalTransactions(..) => {
+                "/v2/addresses/:principal/transactions"
+            }
+            HttpRequestType::GetPendingMinerRewards(..) => "/v2/addresses/:address/pending_rewards",
+            HttpRequestType::GetUnconfirmedMicroblockStream(..) => "/v2/microblocks/unconfirmed",
+            HttpRequestType::MemPoolGC(..) => "/v2/mempool/gc",
+            HttpRequestType::DevMine(..) => "/v2/dev/mine",
+            HttpRequestType::SimulateBlock(..) => "/v2/mempool/simulate_block",
+            HttpRequestType::Batch(..) => "/v2/batch",
+            HttpRequestType::ValidateBlockProposal(..) => "/v2/blocks/validate/:consensus_hash",
+            HttpRequestType::GetMinerStatus(..) => "/v2/admin/miner",
+            HttpRequestType::ScheduledCalls(_, ScheduledCallsOp::Cancel(_), _) => {
+                "/v2/admin/scheduled_calls/:id"
+            }
+            HttpRequestType::ScheduledCalls(..) => "/v2/admin/scheduled_calls",
+            HttpRequestType::PostBlock(..) => "/v2/blocks/upload/:consensus_hash",
+            HttpRequestType::GetMapEntry(..) => "/v2/map_entry/:principal/:contract_name/:map_name",
+            HttpRequestType::GetTransferCost(..) => "/v2/fees/transfer",
+            HttpRequestType::EstimateTransactionFee(..) => "/v2/fees/transaction",
+            HttpRequestType::SimulateTransaction(..) => "/v2/transactions/simulate",
+            HttpRequestType::AnalyzeContract(..) => "/v2/contracts/analyze",
+            HttpRequestType::GetContractABI(..) => {
+                "/v2/contracts/interface/:principal/:contract_name"
+            }
+            HttpRequestType::GetContractSrc(..) => "/v2/contracts/source/:principal/:contract_name",
+            HttpRequestType::GetDataVar(..) => "/v2/data_var/:principal/:contract_name/:var_name",
+            HttpRequestType::CallReadOnlyFunction(..) => {
+                "/v2/contracts/call-read/:principal/:contract_name/:func_name"
+            }
+            HttpRequestType::OptionsPreflight(..) => "OPTIONS",
+            HttpRequestType::GetAttachmentsInv(..) => "/v2/attachments/inv",
+            HttpRequestType::GetAttachmentInstances(..) => "/v2/attachments/inv/instances",
+            HttpRequestType::GetAttachment(..) => "/v2/attachments/:hash",
+            HttpRequestType::GetStackerDBMetadata(..) => "/v2/stackerdb/:principal/:contract_name",
+            HttpRequestType::GetStackerDBChunk(..) => {
+                "/v2/stackerdb/:principal/:contract_name/:slot_id"
+            }
+            HttpRequestType::PostStackerDBChunk(..) => {
+                "/v2/stackerdb/:principal/:contract_name/chunks"
+            }
+            HttpRequestType::ClientError(..) => "error",
+        }
+    }
+
+    pub fn send<W: Write>(&self, _protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
+        match self {
+            HttpRequestType::PostTransaction(md, tx, attachment) => {
+                let mut tx_bytes = vec![];
+                write_next(&mut tx_bytes, tx)?;
+                let tx_hex = to_hex(&tx_bytes[..]);
+
+                let (content_type, request_body_bytes) = match attachment {
+                    None => {
+                        // Transaction does not include an attachment: HttpContentType::Bytes (more compressed)
+                        (Some(&HttpContentType::Bytes), tx_bytes)
+                    }
+                    Some(attachment) => {
+                        // Transaction is including an attachment: HttpContentType::JSON
+                        let request_body = PostTransactionRequestBody {
+                            tx: tx_hex,
+                            attachment: Some(to_hex(&attachment.content[..])),
+                        };
+
+                        let mut request_body_bytes = vec![];
+                        serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(
+                            |e| {
+                                net_error::SerializeError(format!(
+                                    "Failed to serialize read-only call to JSON: {:?}",
+                                    &e
+                                ))
+                            },
+                        )?;
+                        (Some(&HttpContentType::JSON), request_body_bytes)
+                    }
+                };
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    content_type,
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::MemPoolGC(md) | HttpRequestType::SimulateBlock(md) => {
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(0),
+                    None,
+                    empty_headers,
+                )?;
+            }
+            HttpRequestType::PostBlock(md, _, block) => {
+                let mut block_bytes = vec![];
+                write_next(&mut block_bytes, block)?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(block_bytes.len() as u32),
+                    Some(&HttpContentType::Bytes),
+                    empty_headers,
+                )?;
+                fd.write_all(&block_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::ValidateBlockProposal(md, _, block, auth_opt) => {
+                let mut block_bytes = vec![];
+                write_next(&mut block_bytes, block)?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(block_bytes.len() as u32),
+                    Some(&HttpContentType::Bytes),
+                    |ref mut fd| match auth_opt {
+                        Some(auth) => fd
+                            .write_all(format!("Authorization: {}\r\n", auth).as_bytes())
+                            .map_err(net_error::WriteError),
+                        None => Ok(()),
+                    },
+                )?;
+                fd.write_all(&block_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::ScheduledCalls(md, op, auth_opt) => {
+                let (verb, body_bytes) = match op {
+                    ScheduledCallsOp::List => ("GET", None),
+                    ScheduledCallsOp::Add(request) => {
+                        let body_bytes = serde_json::to_vec(request).map_err(|e| {
+                            net_error::SerializeError(format!(
+                                "Failed to serialize scheduled call to JSON: {:?}",
+                                &e
+                            ))
+                        })?;
+                        ("POST", Some(body_bytes))
+                    }
+                    ScheduledCallsOp::Cancel(_) => ("DELETE", None),
+                };
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    verb,
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    body_bytes.as_ref().map(|bytes| bytes.len() as u32),
+                    body_bytes.as_ref().map(|_| &HttpContentType::JSON),
+                    |ref mut fd| match auth_opt {
+                        Some(auth) => fd
+                            .write_all(format!("Authorization: {}\r\n", auth).as_bytes())
+                            .map_err(net_error::WriteError),
+                        None => Ok(()),
+                    },
+                )?;
+                if let Some(body_bytes) = body_bytes {
+                    fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+                }
+            }
+            HttpRequestType::GetMinerStatus(md, auth_opt) => {
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "GET",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    None,
+                    None,
+                    |ref mut fd| match auth_opt {
+                        Some(auth) => fd
+                            .write_all(format!("Authorization: {}\r\n", auth).as_bytes())
+                            .map_err(net_error::WriteError),
+                        None => Ok(()),
+                    },
+                )?;
+            }
+            HttpRequestType::EstimateTransactionFee(md, tx)
+            | HttpRequestType::SimulateTransaction(md, tx) => {
+                let mut tx_bytes = vec![];
+                write_next(&mut tx_bytes, tx)?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(tx_bytes.len() as u32),
+                    Some(&HttpContentType::Bytes),
+                    empty_headers,
+                )?;
+                fd.write_all(&tx_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostMicroblock(md, mb, ..) => {
+                let mut mb_bytes = vec![];
+                write_next(&mut mb_bytes, mb)?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(mb_bytes.len() as u32),
+                    Some(&HttpContentType::Bytes),
+                    empty_headers,
+                )?;
+                fd.write_all(&mb_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::DevMine(md, request) => {
+                let body_bytes = serde_json::to_vec(request).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize mine request to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::Batch(md, queries, ..) => {
+                let mut body = vec![];
+                for query in queries.iter() {
+                    body.push(HttpRequestType::make_batch_query_body(query)?);
+                }
+                let body_bytes = serde_json::to_vec(&body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize batch queries to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::GetMapEntry(
+                md,
+                _contract_addr,
+                _contract_name,
+                _map_name,
+                key,
+                ..,
+            ) => {
+                let mut request_bytes = vec![];
+                key.serialize_write(&mut request_bytes)
+                    .map_err(net_error::WriteError)?;
+                let request_json = format!("\"{}\"", to_hex(&request_bytes));
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_json.as_bytes().len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_json.as_bytes())
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::CallReadOnlyFunction(
+                md,
+                _contract_addr,
+                _contract_name,
+                sender,
+                _func_name,
+                func_args,
+                ..,
+            ) => {
+                let mut args = vec![];
+                for arg in func_args.iter() {
+                    let mut arg_bytes = vec![];
+                    arg.serialize_write(&mut arg_bytes)
+                        .map_err(net_error::WriteError)?;
+                    args.push(to_hex(&arg_bytes));
+                }
+
+                let request_body = CallReadOnlyRequestBody {
+                    sender: sender.to_string(),
+                    arguments: args,
+                };
+
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize read-only call to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::AnalyzeContract(md, source, contract_id_opt, ..) => {
+                let request_body = ContractAnalysisRequestBody {
+                    source: source.clone(),
+                    contract_id: contract_id_opt
+                        .as_ref()
+                        .map(|contract_id| contract_id.to_string()),
+                };
+
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize contract analysis request to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::SimulateAggregationCommit(
+                md,
+                _delegate,
+                pox_addr,
+                reward_cycle,
+                ..,
+            ) => {
+                let request_body = AggregationCommitRequestBody {
+                    pox_address: pox_addr.to_b58(),
+                    reward_cycle: *reward_cycle,
+                };
+
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize aggregation commit to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostStackerDBChunk(md, _, chunk) => {
+                let body_bytes = serde_json::to_vec(chunk).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize StackerDB chunk to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&body_bytes).map_err(net_error::WriteError)?;
+            }
+            other_type => {
+                let md = other_type.metadata();
+                let request_path = other_type.request_path();
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "GET",
+                    &request_path,
+                    &md.peer,
+                    md.keep_alive,
+                    None,
+                    None,
+                    empty_headers,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HttpResponseType {
+    fn try_parse<R: Read, F>(
+        protocol: &mut StacksHttp,
+        regex: &Regex,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        request_path: &String,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        parser: F,
+    ) -> Result<Option<HttpResponseType>, net_error>
+    where
+        F: Fn(
+            &mut StacksHttp,
+            HttpVersion,
+            &HttpResponsePreamble,
+            &mut R,
+            Option<usize>,
+        ) -> Result<HttpResponseType, net_error>,
+    {
+        if regex.is_match(request_path) {
+            let payload = parser(protocol, request_version, preamble, fd, len_hint)?;
+            Ok(Some(payload))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn parse_error<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+    ) -> Result<HttpResponseType, net_error> {
+        if preamble.status_code < 400 || preamble.status_code > 599 {
+            return Err(net_error::DeserializeError(
+                "Inavlid response: not an error".to_string(),
+            ));
+        }
+
+        if preamble.content_type != HttpContentType::Text {
+            return Err(net_error::DeserializeError(
+                "Invalid error response: expected text/plain".to_string(),
+            ));
+        }
+
+        let mut error_text = String::new();
+        fd.read_to_string(&mut error_text)
+            .map_err(net_error::ReadError)?;
+
+        let md = HttpResponseMetadata::from_preamble(request_version, preamble);
+        let resp = match preamble.status_code {
+            400 => HttpResponseType::BadRequest(md, error_text),
+            401 => HttpResponseType::Unauthorized(md, error_text),
+            402 => HttpResponseType::PaymentRequired(md, error_text),
+            403 => HttpResponseType::Forbidden(md, error_text),
+            404 => HttpResponseType::NotFound(md, error_text),
+            429 => HttpResponseType::TooManyRequests(md, error_text),
+            500 => HttpResponseType::ServerError(md, error_text),
+            503 => HttpResponseType::ServiceUnavailable(md, error_text),
+            _ => HttpResponseType::Error(md, preamble.status_code, error_text),
+        };
+        Ok(resp)
+    }
+
+    fn parse_bytestream<R: Read, T: StacksMessageCodec>(
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        max_len: u64,
+    ) -> Result<T, net_error> {
+        // content-type has to be Bytes
+        if preamble.content_type != HttpContentType::Bytes {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/octet-stream".to_string(),
+            ));
+        }
+
+        let item: T = if preamble.is_chunked() && len_hint.is_none() {
+            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
+            read_next(&mut chunked_fd)?
+        } else {
+            let content_length_opt = match (preamble.content_length, len_hint) {
+                (Some(l), _) => Some(l as u32),
+                (None, Some(l)) => Some(l as u32),
+                (None, None) => None,
+            };
+            if let Some(content_length) = content_length_opt {
+                if (content_length as u64) > max_len {
+                    return Err(net_error::DeserializeError(
+                        "Invalid Content-Length header: too long".to_string(),
+                    ));
+                }
+
+                let mut bound_fd = BoundReader::from_reader(fd, content_length as u64);
+                read_next(&mut bound_fd)?
+            } else {
+                // unsupported headers
+                trace!("preamble: {:?}", preamble);
+                return Err(net_error::DeserializeError(
+                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
+                ));
+            }
+        };
+
+        Ok(item)
+    }
+
+    fn parse_json<R: Read, T: serde::de::DeserializeOwned>(
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        max_len: u64,
+    ) -> Result<T, net_error> {
+        // content-type has to be JSON
+        if preamble.content_type != HttpContentType::JSON {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let item_result: Result<T, serde_json::Error> = if preamble.is_chunked()
+            && len_hint.is_none()
+        {
+            let chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
+            serde_json::from_reader(chunked_fd)
+        } else {
+            let content_length_opt = match (preamble.content_length, len_hint) {
+                (Some(l), _) => Some(l as u32),
+                (None, Some(l)) => Some(l as u32),
+                (None, None) => None,
+            };
+            if let Some(content_length) = content_length_opt {
+                if (content_length as u64) > max_len {
+                    return Err(net_error::DeserializeError(
+                        "Invalid Content-Length header: too long".to_string(),
+                    ));
+                }
+                let bound_fd = BoundReader::from_reader(fd, content_length as u64);
+                serde_json::from_reader(bound_fd)
+            } else {
+                // unsupported headers
+                trace!("preamble: {:?}", preamble);
+                return Err(net_error::DeserializeError(
+                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
+                ));
+            }
+        };
+
+        item_result.map_err(|e| {
+            if e.is_eof() {
+                net_error::UnderflowError(format!("Not enough bytes to parse JSON"))
+            } else {
+                net_error::DeserializeError(format!("Failed to parse JSON: {:?}", &e))
+            }
+        })
+    }
+
+    fn parse_text<R: Read>(
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        max_len: u64,
+    ) -> Result<Vec<u8>, net_error> {
+        // content-type has to be text/plain
+        if preamble.content_type != HttpContentType::Text {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected text/plain".to_string(),
+            ));
+        }
+        let buf = if preamble.is_chunked() && len_hint.is_none() {
+            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
+            let mut buf = vec![];
+            chunked_fd
+                .read_to_end(&mut buf)
+                .map_err(net_error::ReadError)?;
+            buf
+        } else {
+            let content_length_opt = match (preamble.content_length, len_hint) {
+                (Some(l), _) => Some(l as u32),
+                (None, Some(l)) => Some(l as u32),
+                (None, None) => None,
+            };
+            if let Some(len) = content_length_opt {
+                let mut buf = vec![0u8; len as usize];
+                fd.read_exact(&mut buf).map_err(net_error::ReadError)?;
+                buf
+            } else {
+                // unsupported headers
+                trace!("preamble: {:?}", preamble);
+                return Err(net_error::DeserializeError(
+                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
+                ));
+            }
+        };
+
+        Ok(buf)
+    }
+
+    // len_hint is given by the StacksHttp protocol implementation
+    pub fn parse<R: Read>(
+        protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        request_path: String,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        if preamble.status_code >= 400 {
+            return HttpResponseType::parse_error(protocol, request_version, preamble, fd);
+        }
+
+        // TODO: make this static somehow
+        let RESPONSE_METHODS: &[(
+            &Regex,
+            &dyn Fn(
+                &mut StacksHttp,
+                HttpVersion,
+                &HttpResponsePreamble,
+                &mut R,
+                Option<usize>,
+            ) -> Result<HttpResponseType, net_error>,
+        )] = &[
+            (&PATH_GETINFO, &HttpResponseType::parse_peerinfo),
+            (&PATH_GETPOXINFO, &HttpResponseType::parse_poxinfo),
+            (&PATH_GETREWARDSET, &HttpResponseType::parse_rewardset),
+            (
+                &PATH_GET_POX_DELEGATIONS,
+                &HttpResponseType::parse_pox_delegations,
+            ),
+            (
+                &PATH_POST_AGGREGATION_COMMIT,
+                &HttpResponseType::parse_aggregation_commit,
+            ),
+            (
+                &PATH_GETBURNBLOCKINFO,
+                &HttpResponseType::parse_burnblockinfo,
+            ),
+            (
+                &PATH_GETBURNBLOCKOPS,
+                &HttpResponseType::parse_burnchain_ops,
+            ),
+            (&PATH_GETBURNOP, &HttpResponseType::parse_burnchain_ops),
+            (&PATH_GETNEIGHBORS, &HttpResponseType::parse_neighbors),
+            (&PATH_GETBLOCK, &HttpResponseType::parse_block),
+            (
+                &PATH_GET_BLOCK_EVENT_BLOOM,
+                &HttpResponseType::parse_block_event_bloom,
+            ),
+            (&PATH_GETHEADERS, &HttpResponseType::parse_headers),
+            (
+                &PATH_GETMICROBLOCKS_INDEXED,
+                &HttpResponseType::parse_microblocks,
+            ),
+            (
+                &PATH_GETMICROBLOCKS_CONFIRMED,
+                &HttpResponseType::parse_microblocks,
+            ),
+            (
+                &PATH_GETMICROBLOCKS_UNCONFIRMED,
+                &HttpResponseType::parse_microblocks_unconfirmed,
+            ),
+            (
+                &PATH_GETTRANSACTION_UNCONFIRMED,
+                &HttpResponseType::parse_transaction_unconfirmed,
+            ),
+            (&PATH_POSTTRANSACTION, &HttpResponseType::parse_txid),
+            (
+                &PATH_POSTMICROBLOCK,
+                &HttpResponseType::parse_microblock_hash,
+            ),
+            (&PATH_GET_ACCOUNT, &HttpResponseType::parse_get_account),
+            (
+                &PATH_GET_ACCOUNT_ASSETS,
+                &HttpResponseType::parse_get_account_assets,
+            ),
+            (
+                &PATH_GET_ACCOUNT_NONCES,
+                &HttpResponseType::parse_get_account_nonces,
+            ),
+            (
+                &PATH_GET_ACCOUNT_SUMMARY,
+                &HttpResponseType::parse_get_account_summary,
+            ),
+            (
+                &PATH_GET_TRANSACTION_RECEIPT,
+                &HttpResponseType::parse_get_transaction_receipt,
+            ),
+            (
+                &PATH_GET_PRINCIPAL_TRANSACTIONS,
+                &HttpResponseType::parse_get_principal_transactions,
+            ),
+            (
+                &PATH_GET_PENDING_MINER_REWARDS,
+                &HttpResponseType::parse_get_pending_miner_rewards,
+            ),
+            (
+                &PATH_GET_UNCONFIRMED_MICROBLOCK_STREAM,
+                &HttpResponseType::parse_get_unconfirmed_microblock_stream,
+            ),
+            (&PATH_POST_MEMPOOL_GC, &HttpResponseType::parse_mempool_gc),
+            (&PATH_POST_DEV_MINE, &HttpResponseType::parse_dev_mine),
+            (
+                &PATH_POST_SIMULATE_BLOCK,
+                &HttpResponseType::parse_block_simulation,
+            ),
+            (&PATH_POST_BATCH, &HttpResponseType::parse_batch),
+            (
+                &PATH_POST_VALIDATE_BLOCK_PROPOSAL,
+                &HttpResponseType::parse_block_proposal,
+            ),
+            (
+                &PATH_GET_MINER_STATUS,
+                &HttpResponseType::parse_miner_status,
+            ),
+            (
+                &PATH_SCHEDULED_CALLS,
+                &HttpResponseType::parse_scheduled_calls,
+            ),
+            (
+                &PATH_SCHEDULED_CALL,
+                &HttpResponseType::parse_scheduled_call,
+            ),
+            (
+                &PATH_POST_BLOCK,
+                &HttpResponseType::parse_stacks_block_accepted,
+            ),
+            (
+                &PATH_POST_FEE_ESTIMATE,
+                &HttpResponseType::parse_fee_estimate,
+            ),
+            (
+                &PATH_POST_SIMULATE_TRANSACTION,
+                &HttpResponseType::parse_transaction_simulation,
+            ),
+            (
+                &PATH_POST_ANALYZE_CONTRACT,
+                &HttpResponseType::parse_contract_analysis,
+            ),
+            (
+                &PATH_GET_CONTRACT_SRC,
+                &HttpResponseType::parse_get_contract_src,
+            ),
+            (&PATH_GET_DATA_VAR, &HttpResponseType::parse_get_data_var),
+            (
+                &PATH_GET_CONTRACT_ABI,
+                &HttpResponseType::parse_get_contract_abi,
+            ),
+            (
+                &PATH_POST_CALL_READ_ONLY,
+                &HttpResponseType::parse_call_read_only,
+            ),
+            (
+                &PATH_GET_ATTACHMENT,
+                &HttpResponseType::parse_get_attachment,
+            ),
+            (
+                &PATH_GET_ATTACHMENTS_INV,
+                &HttpResponseType::parse_get_attachments_inv,
+            ),
+            (
+                &PATH_GET_ATTACHMENT_INSTANCES,
+                &HttpResponseType::parse_get_attachment_instances,
+            ),
+            (
+                &PATH_GET_STACKERDB_METADATA,
+                &HttpResponseType::parse_stackerdb_metadata,
+            ),
+            (
+                &PATH_GET_STACKERDB_CHUNK,
+                &HttpResponseType::parse_stackerdb_chunk,
+            ),
+            (
+                &PATH_POST_STACKERDB_CHUNK,
+                &HttpResponseType::parse_stackerdb_chunk_ack,
+            ),
+        ];
+
+        // use url::Url to parse path and query string
+        //   Url will refuse to parse just a path, so create a dummy URL
+        let local_url = format!("http://local{}", &request_path);
+        let url = Url::parse(&local_url).map_err(|_e| {
+            net_error::DeserializeError("Http request path could not be parsed".to_string())
+        })?;
+
+        let decoded_path = percent_decode_str(url.path()).decode_utf8().map_err(|_e| {
+            net_error::DeserializeError(
+                "Http response path could not be parsed as UTF-8".to_string(),
+            )
+        })?;
+
+        for (regex, parser) in RESPONSE_METHODS.iter() {
+            match HttpResponseType::try_parse(
+                protocol,
+                regex,
+                request_version,
+                preamble,
+                &decoded_path.to_string(),
+                fd,
+                len_hint,
+                parser,
+            ) {
+                Ok(Some(request)) => {
+                    return Ok(request);
+                }
+                Ok(None) => {
+                    continue;
+                }
+                Err(e) => {
+                    test_debug!("Failed to parse {}: {:?}", &request_path, &e);
+                    return Err(e);
+                }
+            }
+        }
+
+        test_debug!(
+            "Failed to match request path '{}' to a handler",
+            &request_path
+        );
+        return Err(net_error::DeserializeError(
+            "Http response could not be parsed".to_string(),
+        ));
+    }
+
+    fn parse_peerinfo<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let peer_info =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PeerInfo(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            peer_info,
+        ))
+    }
+
+    fn parse_poxinfo<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let pox_info =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PoxInfo(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            pox_info,
+        ))
+    }
 
-                let request_body = CallReadOnlyRequestBody {
-                    sender: sender.to_string(),
-                    arguments: args,
-                };
+    fn parse_rewardset<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let reward_set =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::RewardSet(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            reward_set,
+        ))
+    }
 
-                let mut request_body_bytes = vec![];
-                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
-                    net_error::SerializeError(format!(
-                        "Failed to serialize read-only call to JSON: {:?}",
-                        &e
-                    ))
-                })?;
+    fn parse_pox_delegations<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let delegations =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PoxDelegations(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            delegations,
+        ))
+    }
 
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(request_body_bytes.len() as u32),
-                    Some(&HttpContentType::JSON),
-                    empty_headers,
-                )?;
-                fd.write_all(&request_body_bytes)
-                    .map_err(net_error::WriteError)?;
-            }
-            other_type => {
-                let md = other_type.metadata();
-                let request_path = other_type.request_path();
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "GET",
-                    &request_path,
-                    &md.peer,
-                    md.keep_alive,
-                    None,
-                    None,
-                    empty_headers,
-                )?;
-            }
-        }
-        Ok(())
+    fn parse_aggregation_commit<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let simulation =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AggregationCommitSimulation(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            simulation,
+        ))
     }
-}
 
-impl HttpResponseType {
-    fn try_parse<R: Read, F>(
-        protocol: &mut StacksHttp,
-        regex: &Regex,
+    fn parse_burnblockinfo<R: Read>(
+        _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
-        request_path: &String,
         fd: &mut R,
         len_hint: Option<usize>,
-        parser: F,
-    ) -> Result<Option<HttpResponseType>, net_error>
-    where
-        F: Fn(
-            &mut StacksHttp,
-            HttpVersion,
-            &HttpResponsePreamble,
-            &mut R,
-            Option<usize>,
-        ) -> Result<HttpResponseType, net_error>,
-    {
-        if regex.is_match(request_path) {
-            let payload = parser(protocol, request_version, preamble, fd, len_hint)?;
-            Ok(Some(payload))
-        } else {
-            Ok(None)
-        }
+    ) -> Result<HttpResponseType, net_error> {
+        let burn_block_info =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BurnBlockInfo(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            burn_block_info,
+        ))
     }
 
-    fn parse_error<R: Read>(
+    fn parse_burnchain_ops<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
+        len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        if preamble.status_code < 400 || preamble.status_code > 599 {
-            return Err(net_error::DeserializeError(
-                "Inavlid response: not an error".to_string(),
-            ));
-        }
+        let burnchain_ops =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BurnchainOps(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            burnchain_ops,
+        ))
+    }
 
-        if preamble.content_type != HttpContentType::Text {
-            return Err(net_error::DeserializeError(
-                "Invalid error response: expected text/plain".to_string(),
-            ));
-        }
+    fn parse_block_event_bloom<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let event_bloom =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockEventBloom(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            event_bloom,
+        ))
+    }
 
-        let mut error_text = String::new();
-        fd.read_to_string(&mut error_text)
-            .map_err(net_error::ReadError)?;
+    fn parse_headers<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let headers = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Headers(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            headers,
+        ))
+    }
 
-        let md = HttpResponseMetadata::from_preamble(request_version, preamble);
-        let resp = match preamble.status_code {
-            400 => HttpResponseType::BadRequest(md, error_text),
-            401 => HttpResponseType::Unauthorized(md, error_text),
-            402 => HttpResponseType::PaymentRequired(md, error_text),
-            403 => HttpResponseType::Forbidden(md, error_text),
-            404 => HttpResponseType::NotFound(md, error_text),
-            500 => HttpResponseType::ServerError(md, error_text),
-            503 => HttpResponseType::ServiceUnavailable(md, error_text),
-            _ => HttpResponseType::Error(md, preamble.status_code, error_text),
-        };
-        Ok(resp)
+    fn parse_neighbors<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let neighbors_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Neighbors(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            neighbors_data,
+        ))
     }
 
-    fn parse_bytestream<R: Read, T: StacksMessageCodec>(
+    fn parse_block<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
-        max_len: u64,
-    ) -> Result<T, net_error> {
-        // content-type has to be Bytes
-        if preamble.content_type != HttpContentType::Bytes {
-            return Err(net_error::DeserializeError(
-                "Invalid content-type: expected application/octet-stream".to_string(),
-            ));
-        }
+    ) -> Result<HttpResponseType, net_error> {
+        let block: StacksBlock =
+            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Block(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            block,
+        ))
+    }
 
-        let item: T = if preamble.is_chunked() && len_hint.is_none() {
-            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
-            read_next(&mut chunked_fd)?
-        } else {
-            let content_length_opt = match (preamble.content_length, len_hint) {
-                (Some(l), _) => Some(l as u32),
-                (None, Some(l)) => Some(l as u32),
-                (None, None) => None,
-            };
-            if let Some(content_length) = content_length_opt {
-                if (content_length as u64) > max_len {
-                    return Err(net_error::DeserializeError(
-                        "Invalid Content-Length header: too long".to_string(),
-                    ));
-                }
+    fn parse_microblocks<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let microblocks: Vec<StacksMicroblock> =
+            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Microblocks(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            microblocks,
+        ))
+    }
 
-                let mut bound_fd = BoundReader::from_reader(fd, content_length as u64);
-                read_next(&mut bound_fd)?
-            } else {
-                // unsupported headers
-                trace!("preamble: {:?}", preamble);
-                return Err(net_error::DeserializeError(
-                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
-                ));
-            }
-        };
+    fn parse_get_account<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let account_entry =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetAccount(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            account_entry,
+        ))
+    }
 
-        Ok(item)
+    fn parse_get_account_assets<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let account_assets =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetAccountAssets(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            account_assets,
+        ))
+    }
+
+    fn parse_get_account_nonces<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let account_nonces =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetAccountNonces(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            account_nonces,
+        ))
     }
 
-    fn parse_json<R: Read, T: serde::de::DeserializeOwned>(
+    fn parse_get_account_summary<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
-        max_len: u64,
-    ) -> Result<T, net_error> {
-        // content-type has to be JSON
-        if preamble.content_type != HttpContentType::JSON {
-            return Err(net_error::DeserializeError(
-                "Invalid content-type: expected application/json".to_string(),
-            ));
-        }
-
-        let item_result: Result<T, serde_json::Error> = if preamble.is_chunked()
-            && len_hint.is_none()
-        {
-            let chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
-            serde_json::from_reader(chunked_fd)
-        } else {
-            let content_length_opt = match (preamble.content_length, len_hint) {
-                (Some(l), _) => Some(l as u32),
-                (None, Some(l)) => Some(l as u32),
-                (None, None) => None,
-            };
-            if let Some(content_length) = content_length_opt {
-                if (content_length as u64) > max_len {
-                    return Err(net_error::DeserializeError(
-                        "Invalid Content-Length header: too long".to_string(),
-                    ));
-                }
-                let bound_fd = BoundReader::from_reader(fd, content_length as u64);
-                serde_json::from_reader(bound_fd)
-            } else {
-                // unsupported headers
-                trace!("preamble: {:?}", preamble);
-                return Err(net_error::DeserializeError(
-                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
-                ));
-            }
-        };
+    ) -> Result<HttpResponseType, net_error> {
+        let account_summary =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetAccountSummary(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            account_summary,
+        ))
+    }
 
-        item_result.map_err(|e| {
-            if e.is_eof() {
-                net_error::UnderflowError(format!("Not enough bytes to parse JSON"))
-            } else {
-                net_error::DeserializeError(format!("Failed to parse JSON: {:?}", &e))
-            }
-        })
+    fn parse_get_transaction_receipt<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let receipt = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::TransactionReceipt(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            receipt,
+        ))
     }
 
-    fn parse_text<R: Read>(
+    fn parse_get_principal_transactions<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
-        max_len: u64,
-    ) -> Result<Vec<u8>, net_error> {
-        // content-type has to be text/plain
-        if preamble.content_type != HttpContentType::Text {
-            return Err(net_error::DeserializeError(
-                "Invalid content-type: expected text/plain".to_string(),
-            ));
-        }
-        let buf = if preamble.is_chunked() && len_hint.is_none() {
-            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
-            let mut buf = vec![];
-            chunked_fd
-                .read_to_end(&mut buf)
-                .map_err(net_error::ReadError)?;
-            buf
-        } else {
-            let content_length_opt = match (preamble.content_length, len_hint) {
-                (Some(l), _) => Some(l as u32),
-                (None, Some(l)) => Some(l as u32),
-                (None, None) => None,
-            };
-            if let Some(len) = content_length_opt {
-                let mut buf = vec![0u8; len as usize];
-                fd.read_exact(&mut buf).map_err(net_error::ReadError)?;
-                buf
-            } else {
-                // unsupported headers
-                trace!("preamble: {:?}", preamble);
-                return Err(net_error::DeserializeError(
-                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
-                ));
-            }
-        };
+    ) -> Result<HttpResponseType, net_error> {
+        let transactions =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PrincipalTransactions(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            transactions,
+        ))
+    }
 
-        Ok(buf)
+    fn parse_get_pending_miner_rewards<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let rewards = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PendingMinerRewards(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            rewards,
+        ))
     }
 
-    // len_hint is given by the StacksHttp protocol implementation
-    pub fn parse<R: Read>(
-        protocol: &mut StacksHttp,
+    fn parse_get_unconfirmed_microblock_stream<R: Read>(
+        _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
-        request_path: String,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        if preamble.status_code >= 400 {
-            return HttpResponseType::parse_error(protocol, request_version, preamble, fd);
-        }
+        let stream = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::UnconfirmedMicroblockStream(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            stream,
+        ))
+    }
 
-        // TODO: make this static somehow
-        let RESPONSE_METHODS: &[(
-            &Regex,
-            &dyn Fn(
-                &mut StacksHttp,
-                HttpVersion,
-                &HttpResponsePreamble,
-                &mut R,
-                Option<usize>,
-            ) -> Result<HttpResponseType, net_error>,
-        )] = &[
-            (&PATH_GETINFO, &HttpResponseType::parse_peerinfo),
-            (&PATH_GETPOXINFO, &HttpResponseType::parse_poxinfo),
-            (&PATH_GETNEIGHBORS, &HttpResponseType::parse_neighbors),
-            (&PATH_GETBLOCK, &HttpResponseType::parse_block),
-            (
-                &PATH_GETMICROBLOCKS_INDEXED,
-                &HttpResponseType::parse_microblocks,
-            ),
-            (
-                &PATH_GETMICROBLOCKS_CONFIRMED,
-                &HttpResponseType::parse_microblocks,
-            ),
-            (
-                &PATH_GETMICROBLOCKS_UNCONFIRMED,
-                &HttpResponseType::parse_microblocks_unconfirmed,
-            ),
-            (
-                &PATH_GETTRANSACTION_UNCONFIRMED,
-                &HttpResponseType::parse_transaction_unconfirmed,
-            ),
-            (&PATH_POSTTRANSACTION, &HttpResponseType::parse_txid),
-            (
-                &PATH_POSTMICROBLOCK,
-                &HttpResponseType::parse_microblock_hash,
-            ),
-            (&PATH_GET_ACCOUNT, &HttpResponseType::parse_get_account),
-            (
-                &PATH_GET_CONTRACT_SRC,
-                &HttpResponseType::parse_get_contract_src,
-            ),
-            (
-                &PATH_GET_CONTRACT_ABI,
-                &HttpResponseType::parse_get_contract_abi,
-            ),
-            (
-                &PATH_POST_CALL_READ_ONLY,
-                &HttpResponseType::parse_call_read_only,
-            ),
-            (
-                &PATH_GET_ATTACHMENT,
-                &HttpResponseType::parse_get_attachment,
-            ),
-            (
-                &PATH_GET_ATTACHMENTS_INV,
-                &HttpResponseType::parse_get_attachments_inv,
-            ),
-        ];
+    fn parse_mempool_gc<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let gc_result =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MemPoolGC(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            gc_result,
+        ))
+    }
 
-        // use url::Url to parse path and query string
-        //   Url will refuse to parse just a path, so create a dummy URL
-        let local_url = format!("http://local{}", &request_path);
-        let url = Url::parse(&local_url).map_err(|_e| {
-            net_error::DeserializeError("Http request path could not be parsed".to_string())
-        })?;
+    fn parse_dev_mine<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let mine_result =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::DevMine(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            mine_result,
+        ))
+    }
 
-        let decoded_path = percent_decode_str(url.path()).decode_utf8().map_err(|_e| {
-            net_error::DeserializeError(
-                "Http response path could not be parsed as UTF-8".to_string(),
-            )
-        })?;
+    fn parse_block_simulation<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let simulation =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockSimulation(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            simulation,
+        ))
+    }
 
-        for (regex, parser) in RESPONSE_METHODS.iter() {
-            match HttpResponseType::try_parse(
-                protocol,
-                regex,
-                request_version,
-                preamble,
-                &decoded_path.to_string(),
-                fd,
-                len_hint,
-                parser,
-            ) {
-                Ok(Some(request)) => {
-                    return Ok(request);
-                }
-                Ok(None) => {
-                    continue;
-                }
-                Err(e) => {
-                    test_debug!("Failed to parse {}: {:?}", &request_path, &e);
-                    return Err(e);
-                }
-            }
-        }
+    fn parse_batch<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let batch = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Batch(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            batch,
+        ))
+    }
 
-        test_debug!(
-            "Failed to match request path '{}' to a handler",
-            &request_path
-        );
-        return Err(net_error::DeserializeError(
-            "Http response could not be parsed".to_string(),
-        ));
+    fn parse_block_proposal<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let proposal_result =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockProposal(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            proposal_result,
+        ))
     }
 
-    fn parse_peerinfo<R: Read>(
+    fn parse_stacks_block_accepted<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let peer_info =
+        let accepted_data =
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::PeerInfo(
+        Ok(HttpResponseType::StacksBlockAccepted(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            peer_info,
+            accepted_data,
         ))
     }
 
-    fn parse_poxinfo<R: Read>(
+    fn parse_miner_status<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let pox_info =
+        let miner_status =
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::PoxInfo(
+        Ok(HttpResponseType::MinerStatus(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            pox_info,
+            miner_status,
         ))
     }
 
-    fn parse_neighbors<R: Read>(
+    /// GET /v2/admin/scheduled_calls answers with every call, but POST answers with just the
+    /// new one
+    fn parse_scheduled_calls<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let neighbors_data =
+        let json: serde_json::Value =
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Neighbors(
+        let md = HttpResponseMetadata::from_preamble(request_version, preamble);
+        let parse_error =
+            |_e| net_error::DeserializeError("Failed to parse scheduled calls".to_string());
+        if json.is_array() {
+            Ok(HttpResponseType::ScheduledCalls(
+                md,
+                serde_json::from_value(json).map_err(parse_error)?,
+            ))
+        } else {
+            Ok(HttpResponseType::ScheduledCall(
+                md,
+                serde_json::from_value(json).map_err(parse_error)?,
+            ))
+        }
+    }
+
+    fn parse_scheduled_call<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let call = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::ScheduledCall(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            neighbors_data,
+            call,
         ))
     }
 
-    fn parse_block<R: Read>(
+    fn parse_fee_estimate<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let block: StacksBlock =
-            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Block(
+        let fee_estimate =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::TransactionFeeEstimate(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            block,
+            fee_estimate,
         ))
     }
 
-    fn parse_microblocks<R: Read>(
+    fn parse_transaction_simulation<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let microblocks: Vec<StacksMicroblock> =
-            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Microblocks(
+        let simulation =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::TransactionSimulation(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            microblocks,
+            simulation,
         ))
     }
 
-    fn parse_get_account<R: Read>(
+    fn parse_contract_analysis<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let account_entry =
+        let analysis =
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::GetAccount(
+        Ok(HttpResponseType::ContractAnalysis(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            account_entry,
+            analysis,
         ))
     }
 
@@ -3014,6 +5660,21 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_get_data_var<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let var_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetDataVar(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            var_data,
+        ))
+    }
+
     fn parse_get_contract_abi<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3161,6 +5822,67 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_get_attachment_instances<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let res: GetAttachmentInstancesResponse =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+
+        Ok(HttpResponseType::GetAttachmentInstances(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            res,
+        ))
+    }
+
+    fn parse_stackerdb_metadata<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let slots: Vec<SlotMetadata> =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::StackerDBMetadata(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            slots,
+        ))
+    }
+
+    fn parse_stackerdb_chunk<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let chunk: StackerDBChunkData =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::StackerDBChunk(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            chunk,
+        ))
+    }
+
+    fn parse_stackerdb_chunk_ack<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let ack: StackerDBChunkAckData =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::StackerDBChunkAck(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            ack,
+        ))
+    }
+
     fn parse_microblock_hash<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3191,6 +5913,7 @@ impl HttpResponseType {
             402 => "Payment Required",
             403 => "Forbidden",
             404 => "Not Found",
+            429 => "Too Many Requests",
             500 => "Internal Server Error",
             503 => "Service Temporarily Unavailable",
             _ => "Error",
@@ -3211,7 +5934,7 @@ impl HttpResponseType {
             Some(message.len() as u32),
             &HttpContentType::Text,
             md.request_id,
-            |ref mut fd| keep_alive_headers(fd, md),
+            |ref mut fd| response_metadata_headers(fd, md),
         )?;
         fd.write_all(message.as_bytes())
             .map_err(net_error::WriteError)?;
@@ -3222,22 +5945,54 @@ impl HttpResponseType {
         match *self {
             HttpResponseType::PeerInfo(ref md, _) => md,
             HttpResponseType::PoxInfo(ref md, _) => md,
+            HttpResponseType::RewardSet(ref md, _) => md,
+            HttpResponseType::PoxDelegations(ref md, _) => md,
+            HttpResponseType::AggregationCommitSimulation(ref md, _) => md,
+            HttpResponseType::BurnBlockInfo(ref md, _) => md,
+            HttpResponseType::BurnchainOps(ref md, _) => md,
+            HttpResponseType::BlockEventBloom(ref md, _) => md,
+            HttpResponseType::Headers(ref md, _) => md,
             HttpResponseType::Neighbors(ref md, _) => md,
             HttpResponseType::Block(ref md, _) => md,
             HttpResponseType::BlockStream(ref md) => md,
             HttpResponseType::Microblocks(ref md, _) => md,
             HttpResponseType::MicroblockStream(ref md) => md,
+            HttpResponseType::JsonStream(ref md) => md,
             HttpResponseType::TransactionID(ref md, _) => md,
             HttpResponseType::MicroblockHash(ref md, _) => md,
             HttpResponseType::TokenTransferCost(ref md, _) => md,
+            HttpResponseType::TransactionFeeEstimate(ref md, _) => md,
+            HttpResponseType::TransactionSimulation(ref md, _) => md,
+            HttpResponseType::ContractAnalysis(ref md, _) => md,
             HttpResponseType::GetMapEntry(ref md, _) => md,
             HttpResponseType::GetAccount(ref md, _) => md,
+            HttpResponseType::GetAccountAssets(ref md, _) => md,
+            HttpResponseType::GetAccountNonces(ref md, _) => md,
+            HttpResponseType::GetAccountSummary(ref md, _) => md,
+            HttpResponseType::TransactionReceipt(ref md, _) => md,
+            HttpResponseType::PrincipalTransactions(ref md, _) => md,
+            HttpResponseType::PendingMinerRewards(ref md, _) => md,
+            HttpResponseType::UnconfirmedMicroblockStream(ref md, _) => md,
+            HttpResponseType::MemPoolGC(ref md, _) => md,
+            HttpResponseType::DevMine(ref md, _) => md,
+            HttpResponseType::BlockSimulation(ref md, _) => md,
+            HttpResponseType::Batch(ref md, _) => md,
+            HttpResponseType::BlockProposal(ref md, _) => md,
+            HttpResponseType::MinerStatus(ref md, _) => md,
+            HttpResponseType::ScheduledCalls(ref md, _) => md,
+            HttpResponseType::ScheduledCall(ref md, _) => md,
+            HttpResponseType::StacksBlockAccepted(ref md, _) => md,
             HttpResponseType::GetContractABI(ref md, _) => md,
             HttpResponseType::GetContractSrc(ref md, _) => md,
+            HttpResponseType::GetDataVar(ref md, _) => md,
             HttpResponseType::CallReadOnlyFunction(ref md, _) => md,
             HttpResponseType::UnconfirmedTransaction(ref md, _) => md,
             HttpResponseType::GetAttachment(ref md, _) => md,
             HttpResponseType::GetAttachmentsInv(ref md, _) => md,
+            HttpResponseType::GetAttachmentInstances(ref md, _) => md,
+            HttpResponseType::StackerDBMetadata(ref md, _) => md,
+            HttpResponseType::StackerDBChunk(ref md, _) => md,
+            HttpResponseType::StackerDBChunkAck(ref md, _) => md,
             HttpResponseType::OptionsPreflight(ref md) => md,
             // errors
             HttpResponseType::BadRequestJSON(ref md, _) => md,
@@ -3246,6 +6001,7 @@ impl HttpResponseType {
             HttpResponseType::PaymentRequired(ref md, _) => md,
             HttpResponseType::Forbidden(ref md, _) => md,
             HttpResponseType::NotFound(ref md, _) => md,
+            HttpResponseType::TooManyRequests(ref md, _) => md,
             HttpResponseType::ServerError(ref md, _) => md,
             HttpResponseType::ServiceUnavailable(ref md, _) => md,
             HttpResponseType::Error(ref md, _, _) => md,
@@ -3318,6 +6074,70 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, account_data)?;
             }
+            HttpResponseType::GetAccountAssets(ref md, ref account_assets) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, account_assets)?;
+            }
+            HttpResponseType::GetAccountNonces(ref md, ref account_nonces) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, account_nonces)?;
+            }
+            HttpResponseType::GetAccountSummary(ref md, ref account_summary) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, account_summary)?;
+            }
+            HttpResponseType::TransactionReceipt(ref md, ref receipt) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, receipt)?;
+            }
+            HttpResponseType::PrincipalTransactions(ref md, ref transactions) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, transactions)?;
+            }
+            HttpResponseType::PendingMinerRewards(ref md, ref rewards) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, rewards)?;
+            }
+            HttpResponseType::UnconfirmedMicroblockStream(ref md, ref stream) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, stream)?;
+            }
+            HttpResponseType::MemPoolGC(ref md, ref gc_result) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, gc_result)?;
+            }
+            HttpResponseType::DevMine(ref md, ref mine_result) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, mine_result)?;
+            }
+            HttpResponseType::BlockSimulation(ref md, ref simulation) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, simulation)?;
+            }
+            HttpResponseType::Batch(ref md, ref batch) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, batch)?;
+            }
+            HttpResponseType::BlockProposal(ref md, ref proposal_result) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, proposal_result)?;
+            }
+            HttpResponseType::MinerStatus(ref md, ref miner_status) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, miner_status)?;
+            }
+            HttpResponseType::ScheduledCalls(ref md, ref calls) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, calls)?;
+            }
+            HttpResponseType::ScheduledCall(ref md, ref call) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, call)?;
+            }
+            HttpResponseType::StacksBlockAccepted(ref md, ref accepted_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, accepted_data)?;
+            }
             HttpResponseType::GetContractABI(ref md, ref data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
@@ -3326,10 +6146,26 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             }
+            HttpResponseType::GetDataVar(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
             HttpResponseType::TokenTransferCost(ref md, ref cost) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, cost)?;
             }
+            HttpResponseType::TransactionFeeEstimate(ref md, ref fee_estimate) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, fee_estimate)?;
+            }
+            HttpResponseType::TransactionSimulation(ref md, ref simulation) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, simulation)?;
+            }
+            HttpResponseType::ContractAnalysis(ref md, ref analysis) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, analysis)?;
+            }
             HttpResponseType::CallReadOnlyFunction(ref md, ref data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
@@ -3346,6 +6182,34 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, pox_info)?;
             }
+            HttpResponseType::RewardSet(ref md, ref reward_set) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, reward_set)?;
+            }
+            HttpResponseType::PoxDelegations(ref md, ref delegations) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, delegations)?;
+            }
+            HttpResponseType::AggregationCommitSimulation(ref md, ref simulation) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, simulation)?;
+            }
+            HttpResponseType::BurnBlockInfo(ref md, ref burn_block_info) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, burn_block_info)?;
+            }
+            HttpResponseType::BurnchainOps(ref md, ref burnchain_ops) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, burnchain_ops)?;
+            }
+            HttpResponseType::BlockEventBloom(ref md, ref event_bloom) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, event_bloom)?;
+            }
+            HttpResponseType::Headers(ref md, ref headers) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, headers)?;
+            }
             HttpResponseType::Neighbors(ref md, ref neighbor_data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, neighbor_data)?;
@@ -3358,6 +6222,22 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
             }
+            HttpResponseType::GetAttachmentInstances(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::StackerDBMetadata(ref md, ref slots) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, slots)?;
+            }
+            HttpResponseType::StackerDBChunk(ref md, ref chunk) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, chunk)?;
+            }
+            HttpResponseType::StackerDBChunkAck(ref md, ref ack) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, ack)?;
+            }
             HttpResponseType::Block(ref md, ref block) => {
                 HttpResponsePreamble::new_serialized(
                     fd,
@@ -3366,7 +6246,7 @@ impl HttpResponseType {
                     md.content_length.clone(),
                     &HttpContentType::Bytes,
                     md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
+                    |ref mut fd| response_metadata_headers(fd, md),
                 )?;
                 HttpResponseType::send_bytestream(protocol, md, fd, block)?;
             }
@@ -3380,7 +6260,7 @@ impl HttpResponseType {
                     None,
                     &HttpContentType::Bytes,
                     md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
+                    |ref mut fd| response_metadata_headers(fd, md),
                 )?;
             }
             HttpResponseType::Microblocks(ref md, ref microblocks) => {
@@ -3391,7 +6271,7 @@ impl HttpResponseType {
                     md.content_length.clone(),
                     &HttpContentType::Bytes,
                     md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
+                    |ref mut fd| response_metadata_headers(fd, md),
                 )?;
                 HttpResponseType::send_bytestream(protocol, md, fd, microblocks)?;
             }
@@ -3405,7 +6285,20 @@ impl HttpResponseType {
                     None,
                     &HttpContentType::Bytes,
                     md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
+                    |ref mut fd| response_metadata_headers(fd, md),
+                )?;
+            }
+            HttpResponseType::JsonStream(ref md) => {
+                // only send the preamble.  The caller will need to figure out how to send along
+                // the JSON document itself.
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    None,
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| response_metadata_headers(fd, md),
                 )?;
             }
             HttpResponseType::TransactionID(ref md, ref txid) => {
@@ -3417,7 +6310,7 @@ impl HttpResponseType {
                     md.content_length.clone(),
                     &HttpContentType::JSON,
                     md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
+                    |ref mut fd| response_metadata_headers(fd, md),
                 )?;
                 HttpResponseType::send_json(protocol, md, fd, &txid_bytes)?;
             }
@@ -3430,7 +6323,7 @@ impl HttpResponseType {
                     md.content_length.clone(),
                     &HttpContentType::JSON,
                     md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
+                    |ref mut fd| response_metadata_headers(fd, md),
                 )?;
                 HttpResponseType::send_json(protocol, md, fd, &mblock_bytes)?;
             }
@@ -3446,7 +6339,7 @@ impl HttpResponseType {
                     None,
                     &HttpContentType::Text,
                     md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
+                    |ref mut fd| response_metadata_headers(fd, md),
                 )?;
                 HttpResponseType::send_text(protocol, md, fd, "".as_bytes())?;
             }
@@ -3458,7 +6351,7 @@ impl HttpResponseType {
                     md.content_length.clone(),
                     &HttpContentType::JSON,
                     md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
+                    |ref mut fd| response_metadata_headers(fd, md),
                 )?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             }
@@ -3467,6 +6360,7 @@ impl HttpResponseType {
             HttpResponseType::PaymentRequired(_, ref msg) => self.error_response(fd, 402, msg)?,
             HttpResponseType::Forbidden(_, ref msg) => self.error_response(fd, 403, msg)?,
             HttpResponseType::NotFound(_, ref msg) => self.error_response(fd, 404, msg)?,
+            HttpResponseType::TooManyRequests(_, ref msg) => self.error_response(fd, 429, msg)?,
             HttpResponseType::ServerError(_, ref msg) => self.error_response(fd, 500, msg)?,
             HttpResponseType::ServiceUnavailable(_, ref msg) => {
                 self.error_response(fd, 503, msg)?
@@ -3530,8 +6424,15 @@ impl MessageSequence for StacksHttpMessage {
             StacksHttpMessage::Request(ref req) => match req {
                 HttpRequestType::GetInfo(_) => "HTTP(GetInfo)",
                 HttpRequestType::GetPoxInfo(_, _) => "HTTP(GetPoxInfo)",
+                HttpRequestType::GetRewardSet(_, _) => "HTTP(GetRewardSet)",
+                HttpRequestType::GetPoxDelegations(..) => "HTTP(GetPoxDelegations)",
+                HttpRequestType::SimulateAggregationCommit(..) => "HTTP(SimulateAggregationCommit)",
+                HttpRequestType::GetBurnBlockInfo(_, _) => "HTTP(GetBurnBlockInfo)",
+                HttpRequestType::GetBurnchainOps(_, _) => "HTTP(GetBurnchainOps)",
                 HttpRequestType::GetNeighbors(_) => "HTTP(GetNeighbors)",
                 HttpRequestType::GetBlock(_, _) => "HTTP(GetBlock)",
+                HttpRequestType::GetBlockEventBloom(_, _) => "HTTP(GetBlockEventBloom)",
+                HttpRequestType::GetHeaders(..) => "HTTP(GetHeaders)",
                 HttpRequestType::GetMicroblocksIndexed(_, _) => "HTTP(GetMicroblocksIndexed)",
                 HttpRequestType::GetMicroblocksConfirmed(_, _) => "HTTP(GetMicroblocksConfirmed)",
                 HttpRequestType::GetMicroblocksUnconfirmed(_, _, _) => {
@@ -3543,32 +6444,93 @@ impl MessageSequence for StacksHttpMessage {
                 HttpRequestType::PostTransaction(_, _, _) => "HTTP(PostTransaction)",
                 HttpRequestType::PostMicroblock(..) => "HTTP(PostMicroblock)",
                 HttpRequestType::GetAccount(..) => "HTTP(GetAccount)",
+                HttpRequestType::GetAccountAssets(..) => "HTTP(GetAccountAssets)",
+                HttpRequestType::GetAccountNonces(..) => "HTTP(GetAccountNonces)",
+                HttpRequestType::GetAccountSummary(..) => "HTTP(GetAccountSummary)",
+                HttpRequestType::GetTransactionReceipt(..) => "HTTP(GetTransactionReceipt)",
+                HttpRequestType::GetPrincipalTransactions(..) => "HTTP(GetPrincipalTransactions)",
+                HttpRequestType::GetPendingMinerRewards(..) => "HTTP(GetPendingMinerRewards)",
+                HttpRequestType::GetUnconfirmedMicroblockStream(..) => {
+                    "HTTP(GetUnconfirmedMicroblockStream)"
+                }
+                HttpRequestType::MemPoolGC(..) => "HTTP(MemPoolGC)",
+                HttpRequestType::DevMine(..) => "HTTP(DevMine)",
+                HttpRequestType::SimulateBlock(..) => "HTTP(SimulateBlock)",
+                HttpRequestType::Batch(..) => "HTTP(Batch)",
+                HttpRequestType::ValidateBlockProposal(..) => "HTTP(ValidateBlockProposal)",
+                HttpRequestType::GetMinerStatus(..) => "HTTP(GetMinerStatus)",
+                HttpRequestType::ScheduledCalls(..) => "HTTP(ScheduledCalls)",
+                HttpRequestType::PostBlock(..) => "HTTP(PostBlock)",
                 HttpRequestType::GetMapEntry(..) => "HTTP(GetMapEntry)",
                 HttpRequestType::GetTransferCost(_) => "HTTP(GetTransferCost)",
+                HttpRequestType::EstimateTransactionFee(..) => "HTTP(EstimateTransactionFee)",
+                HttpRequestType::SimulateTransaction(..) => "HTTP(SimulateTransaction)",
+                HttpRequestType::AnalyzeContract(..) => "HTTP(AnalyzeContract)",
                 HttpRequestType::GetContractABI(..) => "HTTP(GetContractABI)",
                 HttpRequestType::GetContractSrc(..) => "HTTP(GetContractSrc)",
+                HttpRequestType::GetDataVar(..) => "HTTP(GetDataVar)",
                 HttpRequestType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
                 HttpRequestType::GetAttachment(..) => "HTTP(GetAttachment)",
                 HttpRequestType::GetAttachmentsInv(..) => "HTTP(GetAttachmentsInv)",
+                HttpRequestType::GetAttachmentInstances(..) => "HTTP(GetAttachmentInstances)",
+                HttpRequestType::GetStackerDBMetadata(..) => "HTTP(GetStackerDBMetadata)",
+                HttpRequestType::GetStackerDBChunk(..) => "HTTP(GetStackerDBChunk)",
+                HttpRequestType::PostStackerDBChunk(..) => "HTTP(PostStackerDBChunk)",
                 HttpRequestType::OptionsPreflight(..) => "HTTP(OptionsPreflight)",
                 HttpRequestType::ClientError(..) => "HTTP(ClientError)",
             },
             StacksHttpMessage::Response(ref res) => match res {
                 HttpResponseType::TokenTransferCost(_, _) => "HTTP(TokenTransferCost)",
+                HttpResponseType::TransactionFeeEstimate(_, _) => "HTTP(TransactionFeeEstimate)",
+                HttpResponseType::TransactionSimulation(_, _) => "HTTP(TransactionSimulation)",
+                HttpResponseType::ContractAnalysis(_, _) => "HTTP(ContractAnalysis)",
                 HttpResponseType::GetMapEntry(_, _) => "HTTP(GetMapEntry)",
                 HttpResponseType::GetAccount(_, _) => "HTTP(GetAccount)",
+                HttpResponseType::GetAccountAssets(_, _) => "HTTP(GetAccountAssets)",
+                HttpResponseType::GetAccountNonces(_, _) => "HTTP(GetAccountNonces)",
+                HttpResponseType::GetAccountSummary(_, _) => "HTTP(GetAccountSummary)",
+                HttpResponseType::TransactionReceipt(_, _) => "HTTP(TransactionReceipt)",
+                HttpResponseType::PrincipalTransactions(_, _) => "HTTP(PrincipalTransactions)",
+                HttpResponseType::PendingMinerRewards(_, _) => "HTTP(PendingMinerRewards)",
+                HttpResponseType::UnconfirmedMicroblockStream(_, _) => {
+                    "HTTP(UnconfirmedMicroblockStream)"
+                }
+                HttpResponseType::MemPoolGC(_, _) => "HTTP(MemPoolGC)",
+                HttpResponseType::BlockSimulation(_, _) => "HTTP(BlockSimulation)",
+                HttpResponseType::DevMine(_, _) => "HTTP(DevMine)",
+                HttpResponseType::Batch(_, _) => "HTTP(Batch)",
+                HttpResponseType::BlockProposal(_, _) => "HTTP(BlockProposal)",
+                HttpResponseType::MinerStatus(_, _) => "HTTP(MinerStatus)",
+                HttpResponseType::ScheduledCalls(_, _) => "HTTP(ScheduledCalls)",
+                HttpResponseType::ScheduledCall(_, _) => "HTTP(ScheduledCall)",
+                HttpResponseType::StacksBlockAccepted(_, _) => "HTTP(StacksBlockAccepted)",
                 HttpResponseType::GetContractABI(..) => "HTTP(GetContractABI)",
                 HttpResponseType::GetContractSrc(..) => "HTTP(GetContractSrc)",
+                HttpResponseType::GetDataVar(..) => "HTTP(GetDataVar)",
                 HttpResponseType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
                 HttpResponseType::GetAttachment(_, _) => "HTTP(GetAttachment)",
                 HttpResponseType::GetAttachmentsInv(_, _) => "HTTP(GetAttachmentsInv)",
+                HttpResponseType::GetAttachmentInstances(_, _) => "HTTP(GetAttachmentInstances)",
+                HttpResponseType::StackerDBMetadata(_, _) => "HTTP(StackerDBMetadata)",
+                HttpResponseType::StackerDBChunk(_, _) => "HTTP(StackerDBChunk)",
+                HttpResponseType::StackerDBChunkAck(_, _) => "HTTP(StackerDBChunkAck)",
                 HttpResponseType::PeerInfo(_, _) => "HTTP(PeerInfo)",
                 HttpResponseType::PoxInfo(_, _) => "HTTP(PeerInfo)",
+                HttpResponseType::RewardSet(_, _) => "HTTP(RewardSet)",
+                HttpResponseType::PoxDelegations(_, _) => "HTTP(PoxDelegations)",
+                HttpResponseType::AggregationCommitSimulation(_, _) => {
+                    "HTTP(AggregationCommitSimulation)"
+                }
+                HttpResponseType::BurnBlockInfo(_, _) => "HTTP(BurnBlockInfo)",
+                HttpResponseType::BurnchainOps(_, _) => "HTTP(BurnchainOps)",
+                HttpResponseType::BlockEventBloom(_, _) => "HTTP(BlockEventBloom)",
+                HttpResponseType::Headers(_, _) => "HTTP(Headers)",
                 HttpResponseType::Neighbors(_, _) => "HTTP(Neighbors)",
                 HttpResponseType::Block(_, _) => "HTTP(Block)",
                 HttpResponseType::BlockStream(_) => "HTTP(BlockStream)",
                 HttpResponseType::Microblocks(_, _) => "HTTP(Microblocks)",
                 HttpResponseType::MicroblockStream(_) => "HTTP(MicroblockStream)",
+                HttpResponseType::JsonStream(_) => "HTTP(JsonStream)",
                 HttpResponseType::TransactionID(_, _) => "HTTP(Transaction)",
                 HttpResponseType::MicroblockHash(_, _) => "HTTP(Microblock)",
                 HttpResponseType::UnconfirmedTransaction(_, _) => "HTTP(UnconfirmedTransaction)",
@@ -3580,6 +6542,7 @@ impl MessageSequence for StacksHttpMessage {
                 HttpResponseType::PaymentRequired(_, _) => "HTTP(402)",
                 HttpResponseType::Forbidden(_, _) => "HTTP(403)",
                 HttpResponseType::NotFound(_, _) => "HTTP(404)",
+                HttpResponseType::TooManyRequests(_, _) => "HTTP(429)",
                 HttpResponseType::ServerError(_, _) => "HTTP(500)",
                 HttpResponseType::ServiceUnavailable(_, _) => "HTTP(503)",
                 HttpResponseType::Error(_, _, _) => "HTTP(other)",
@@ -3670,6 +6633,90 @@ struct HttpReplyData {
     stream: HttpRecvStream,
 }
 
+/// A limit on how many requests a client may make within a fixed window of time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HttpRateLimit {
+    pub max_requests: u64,
+    pub window_secs: u64,
+}
+
+impl FromStr for HttpRateLimit {
+    type Err = String;
+
+    /// Parse a limit written as `max_requests/window_secs`, e.g. `600/60`
+    fn from_str(s: &str) -> Result<HttpRateLimit, String> {
+        let mut parts = s.trim().splitn(2, '/');
+        let max_requests = parts
+            .next()
+            .and_then(|n| n.trim().parse::<u64>().ok())
+            .ok_or_else(|| format!("Invalid rate limit '{}': bad request count", s))?;
+        let window_secs = parts
+            .next()
+            .and_then(|n| n.trim().parse::<u64>().ok())
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid rate limit '{}': bad window length", s))?;
+        Ok(HttpRateLimit {
+            max_requests,
+            window_secs,
+        })
+    }
+}
+
+/// Counts the requests each client IP address makes against each rate limit.  A client's count
+/// resets at the end of each window, which starts with the client's first request against the
+/// limit.
+#[derive(Debug, Default)]
+pub struct HttpRateLimiter {
+    /// (client, bucket) --> (window start, window length, requests so far)
+    windows: HashMap<(IpAddr, &'static str), (u64, u64, u64)>,
+    last_pruned: u64,
+}
+
+impl HttpRateLimiter {
+    pub fn new() -> HttpRateLimiter {
+        HttpRateLimiter::default()
+    }
+
+    /// Count a request from `client` against the limit named by `bucket`.  Returns false if the
+    /// client has already used up the limit in the current window, in which case the request is
+    /// not counted.
+    pub fn admit(
+        &mut self,
+        client: IpAddr,
+        bucket: &'static str,
+        limit: &HttpRateLimit,
+        now: u64,
+    ) -> bool {
+        self.prune(now);
+        let window = self
+            .windows
+            .entry((client, bucket))
+            .or_insert((now, limit.window_secs, 0));
+        if window.0 + window.1 <= now {
+            *window = (now, limit.window_secs, 0);
+        }
+        if window.2 >= limit.max_requests {
+            return false;
+        }
+        window.2 += 1;
+        true
+    }
+
+    /// Forget windows that have ended, at most once a second
+    fn prune(&mut self, now: u64) {
+        if self.last_pruned == now {
+            return;
+        }
+        self.windows
+            .retain(|_, (start, len, _)| *start + *len > now);
+        self.last_pruned = now;
+    }
+
+    pub fn num_windows(&self) -> usize {
+        self.windows.len()
+    }
+}
+
 /// Stacks HTTP implementation, for bufferring up data.
 /// One of these exists per Connection<P: Protocol>.
 /// There can be at most one HTTP request in-flight (i.e. we don't do pipelining)
@@ -3685,6 +6732,9 @@ pub struct StacksHttp {
     chunk_size: usize,
     /// Maximum size of call arguments
     pub maximum_call_argument_size: u32,
+    /// Authorization header values that inbound requests may carry.  If empty, requests need
+    /// no authorization.
+    pub auth_tokens: Vec<String>,
 }
 
 impl StacksHttp {
@@ -3695,6 +6745,19 @@ impl StacksHttp {
             request_path: None,
             chunk_size: 8192,
             maximum_call_argument_size: 20 * BOUND_VALUE_SERIALIZATION_HEX,
+            auth_tokens: vec![],
+        }
+    }
+
+    /// Does this inbound request carry one of our auth tokens, if we need one?  CORS preflight
+    /// requests never carry credentials, so they are always allowed.
+    fn is_authorized(&self, preamble: &HttpRequestPreamble) -> bool {
+        if self.auth_tokens.is_empty() || preamble.verb == "OPTIONS" {
+            return true;
+        }
+        match preamble.headers.get("authorization") {
+            Some(auth) => self.auth_tokens.iter().any(|token| token == auth),
+            None => false,
         }
     }
 
@@ -3995,6 +7058,17 @@ impl ProtocolFamily for StacksHttp {
 
                 trace!("read http request payload of {} bytes", len);
 
+                if !self.is_authorized(http_request_preamble) {
+                    let req = HttpRequestType::ClientError(
+                        HttpRequestMetadata::from_preamble(http_request_preamble),
+                        ClientError::Unauthorized(
+                            "Missing or invalid Authorization header".to_string(),
+                        ),
+                    );
+                    // don't even look at the body
+                    return Ok((StacksHttpMessage::Request(req), len));
+                }
+
                 let mut cursor = io::Cursor::new(buf);
                 match HttpRequestType::parse(self, http_request_preamble, &mut cursor) {
                     Ok(data_request) => Ok((
@@ -4094,6 +7168,7 @@ mod test {
 
     use burnchains::Txid;
     use chainstate::stacks::db::blocks::test::make_sample_microblock_stream;
+    use chainstate::stacks::db::header_chain::ExtendedStacksHeader;
     use chainstate::stacks::test::make_codec_test_block;
     use chainstate::stacks::StacksAddress;
     use chainstate::stacks::StacksBlock;
@@ -4927,6 +8002,17 @@ mod test {
         }
     }
 
+    fn make_test_scheduled_call() -> ScheduledCallRequest {
+        ScheduledCallRequest {
+            contract_address: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+            contract_name: "keeper".to_string(),
+            function_name: "advance-epoch".to_string(),
+            function_args: vec!["0100000000000000000000000000000001".to_string()],
+            target_burn_height: 120,
+            fee: 500,
+        }
+    }
+
     fn make_test_transaction() -> StacksTransaction {
         let privk = StacksPrivateKey::from_hex(
             "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
@@ -4946,6 +8032,7 @@ mod test {
                 recv_addr.clone().into(),
                 123,
                 TokenTransferMemo([0u8; 34]),
+                None,
             ),
         );
         tx_stx_transfer.chain_id = 0x80000000;
@@ -5041,17 +8128,92 @@ mod test {
                 PeerAddress([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
                 12345,
             ),
-            keep_alive: true,
-        };
-        let http_request_metadata_dns = HttpRequestMetadata {
-            version: HttpVersion::Http11,
-            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
-            keep_alive: true,
-        };
-
-        let tests = vec![
-            HttpRequestType::GetNeighbors(http_request_metadata_ip.clone()),
-            HttpRequestType::GetBlock(http_request_metadata_dns.clone(), StacksBlockId([2u8; 32])),
+            keep_alive: true,
+        };
+        let http_request_metadata_dns = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
+            keep_alive: true,
+        };
+
+        let tests = vec![
+            HttpRequestType::GetNeighbors(http_request_metadata_ip.clone()),
+            HttpRequestType::GetBlock(http_request_metadata_dns.clone(), StacksBlockId([2u8; 32])),
+            HttpRequestType::GetBurnBlockInfo(http_request_metadata_ip.clone(), 666050),
+            HttpRequestType::GetBurnchainOps(
+                http_request_metadata_dns.clone(),
+                BurnchainOpsQuery::Height(666050),
+            ),
+            HttpRequestType::GetBurnchainOps(
+                http_request_metadata_ip.clone(),
+                BurnchainOpsQuery::Txid(Txid([5u8; 32])),
+            ),
+            HttpRequestType::GetRewardSet(
+                http_request_metadata_dns.clone(),
+                Some(StacksBlockId([4u8; 32])),
+            ),
+            HttpRequestType::GetPoxDelegations(
+                http_request_metadata_ip.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                None,
+            ),
+            HttpRequestType::SimulateAggregationCommit(
+                http_request_metadata_dns.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                12,
+                None,
+            ),
+            HttpRequestType::GetAccountAssets(
+                http_request_metadata_dns.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                None,
+            ),
+            HttpRequestType::GetAccountNonces(
+                http_request_metadata_ip.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                None,
+            ),
+            HttpRequestType::GetAccount(
+                http_request_metadata_dns.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                None,
+                None,
+                false,
+                RPCStateView::UnconfirmedWithMempool,
+            ),
+            HttpRequestType::GetAccount(
+                http_request_metadata_ip.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                Some(StacksBlockId([7u8; 32])),
+                Some(3),
+                true,
+                RPCStateView::Confirmed,
+            ),
+            HttpRequestType::GetBlockEventBloom(
+                http_request_metadata_ip.clone(),
+                StacksBlockId([6u8; 32]),
+            ),
+            HttpRequestType::GetHeaders(http_request_metadata_dns.clone(), 10, None),
+            HttpRequestType::GetHeaders(
+                http_request_metadata_ip.clone(),
+                1,
+                Some(StacksBlockId([7u8; 32])),
+            ),
+            HttpRequestType::GetAttachmentInstances(
+                http_request_metadata_dns.clone(),
+                Some(StacksBlockId([5u8; 32])),
+                vec![2, 0].into_iter().collect(),
+                Some(
+                    QualifiedContractIdentifier::parse(
+                        "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.bns",
+                    )
+                    .unwrap(),
+                ),
+                Some("id".to_string()),
+            ),
+            HttpRequestType::MemPoolGC(http_request_metadata_ip.clone()),
+            HttpRequestType::SimulateBlock(http_request_metadata_ip.clone()),
             HttpRequestType::GetMicroblocksIndexed(
                 http_request_metadata_ip.clone(),
                 StacksBlockId([3u8; 32]),
@@ -5061,6 +8223,115 @@ mod test {
                 make_test_transaction(),
                 None,
             ),
+            HttpRequestType::EstimateTransactionFee(
+                http_request_metadata_ip.clone(),
+                make_test_transaction(),
+            ),
+            HttpRequestType::SimulateTransaction(
+                http_request_metadata_ip.clone(),
+                make_test_transaction(),
+            ),
+            HttpRequestType::ValidateBlockProposal(
+                http_request_metadata_ip.clone(),
+                ConsensusHash([0x11; 20]),
+                make_codec_test_block(5),
+                Some("secret".to_string()),
+            ),
+            HttpRequestType::GetMinerStatus(
+                http_request_metadata_dns.clone(),
+                Some("secret".to_string()),
+            ),
+            HttpRequestType::ScheduledCalls(
+                http_request_metadata_dns.clone(),
+                ScheduledCallsOp::List,
+                Some("secret".to_string()),
+            ),
+            HttpRequestType::ScheduledCalls(
+                http_request_metadata_dns.clone(),
+                ScheduledCallsOp::Add(make_test_scheduled_call()),
+                Some("secret".to_string()),
+            ),
+            HttpRequestType::ScheduledCalls(
+                http_request_metadata_dns.clone(),
+                ScheduledCallsOp::Cancel(3),
+                None,
+            ),
+            HttpRequestType::Batch(
+                http_request_metadata_dns.clone(),
+                vec![
+                    BatchQuery::Account(
+                        PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                    ),
+                    BatchQuery::MapEntry(
+                        StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                            .unwrap(),
+                        "hello-world".into(),
+                        "unit-map".into(),
+                        Value::UInt(1),
+                    ),
+                    BatchQuery::CallRead(
+                        StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                            .unwrap(),
+                        "hello-world".into(),
+                        "get-bar".into(),
+                        PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                        vec![Value::Int(1), Value::Int(2)],
+                    ),
+                ],
+                Some(StacksBlockId([6u8; 32])),
+                false,
+                RPCStateView::Confirmed,
+            ),
+            HttpRequestType::PostBlock(
+                http_request_metadata_dns.clone(),
+                ConsensusHash([0x22; 20]),
+                make_codec_test_block(5),
+            ),
+            HttpRequestType::GetAccountSummary(
+                http_request_metadata_dns.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                Some(StacksBlockId([5u8; 32])),
+            ),
+            HttpRequestType::GetTransactionReceipt(
+                http_request_metadata_dns.clone(),
+                Txid([6u8; 32]),
+                Some(StacksBlockId([7u8; 32])),
+            ),
+            HttpRequestType::GetPrincipalTransactions(
+                http_request_metadata_dns.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world")
+                    .unwrap(),
+                20,
+                10,
+                Some(StacksBlockId([8u8; 32])),
+            ),
+            HttpRequestType::GetPrincipalTransactions(
+                http_request_metadata_dns.clone(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                0,
+                DEFAULT_PRINCIPAL_TRANSACTIONS_LIMIT,
+                None,
+            ),
+            HttpRequestType::GetPendingMinerRewards(
+                http_request_metadata_dns.clone(),
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                Some(StacksBlockId([9u8; 32])),
+            ),
+            HttpRequestType::GetPendingMinerRewards(
+                http_request_metadata_dns.clone(),
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                None,
+            ),
+            HttpRequestType::GetUnconfirmedMicroblockStream(
+                http_request_metadata_dns.clone(),
+                0,
+                None,
+            ),
+            HttpRequestType::GetUnconfirmedMicroblockStream(
+                http_request_metadata_dns.clone(),
+                5,
+                Some(StacksBlockId([9u8; 32])),
+            ),
             HttpRequestType::OptionsPreflight(http_request_metadata_ip.clone(), "/".to_string()),
         ];
 
@@ -5080,6 +8351,169 @@ mod test {
         post_transaction_preamble.set_content_type(HttpContentType::Bytes);
         post_transaction_preamble.set_content_length(tx_body.len() as u32);
 
+        let mut post_fee_estimate_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/fees/transaction".to_string(),
+            http_request_metadata_ip.peer.hostname(),
+            http_request_metadata_ip.peer.port(),
+            http_request_metadata_ip.keep_alive,
+        );
+        post_fee_estimate_preamble.set_content_type(HttpContentType::Bytes);
+        post_fee_estimate_preamble.set_content_length(tx_body.len() as u32);
+
+        let mut post_simulate_transaction_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/transactions/simulate".to_string(),
+            http_request_metadata_ip.peer.hostname(),
+            http_request_metadata_ip.peer.port(),
+            http_request_metadata_ip.keep_alive,
+        );
+        post_simulate_transaction_preamble.set_content_type(HttpContentType::Bytes);
+        post_simulate_transaction_preamble.set_content_length(tx_body.len() as u32);
+
+        let mut block_body = vec![];
+        make_codec_test_block(5)
+            .consensus_serialize(&mut block_body)
+            .unwrap();
+
+        let mut validate_block_proposal_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            format!("/v2/blocks/validate/{}", ConsensusHash([0x11; 20])),
+            http_request_metadata_ip.peer.hostname(),
+            http_request_metadata_ip.peer.port(),
+            http_request_metadata_ip.keep_alive,
+        );
+        validate_block_proposal_preamble.set_content_type(HttpContentType::Bytes);
+        validate_block_proposal_preamble.set_content_length(block_body.len() as u32);
+        validate_block_proposal_preamble
+            .headers
+            .insert("Authorization".to_string(), "secret".to_string());
+
+        let mut get_miner_status_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "GET".to_string(),
+            "/v2/admin/miner".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        get_miner_status_preamble
+            .headers
+            .insert("Authorization".to_string(), "secret".to_string());
+
+        let mut get_scheduled_calls_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "GET".to_string(),
+            "/v2/admin/scheduled_calls".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        get_scheduled_calls_preamble
+            .headers
+            .insert("Authorization".to_string(), "secret".to_string());
+
+        let scheduled_call_body = serde_json::to_vec(&make_test_scheduled_call()).unwrap();
+        let mut post_scheduled_call_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/admin/scheduled_calls".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_scheduled_call_preamble.set_content_type(HttpContentType::JSON);
+        post_scheduled_call_preamble.set_content_length(scheduled_call_body.len() as u32);
+        post_scheduled_call_preamble
+            .headers
+            .insert("Authorization".to_string(), "secret".to_string());
+
+        let delete_scheduled_call_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "DELETE".to_string(),
+            "/v2/admin/scheduled_calls/3".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+
+        let batch_body = concat!(
+            r#"[{"account":{"principal":"ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R"}},"#,
+            r#"{"map_entry":{"contract_address":"ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R","#,
+            r#""contract_name":"hello-world","map_name":"unit-map","#,
+            r#""key":"0100000000000000000000000000000001"}},"#,
+            r#"{"call_read":{"contract_address":"ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R","#,
+            r#""contract_name":"hello-world","function_name":"get-bar","#,
+            r#""sender":"ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R","#,
+            r#""arguments":["0000000000000000000000000000000001","#,
+            r#""0000000000000000000000000000000002"]}}]"#
+        )
+        .as_bytes()
+        .to_vec();
+        let mut post_batch_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            format!("/v2/batch?tip={}&proof=0", StacksBlockId([6u8; 32])),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_batch_preamble.set_content_type(HttpContentType::JSON);
+        post_batch_preamble.set_content_length(batch_body.len() as u32);
+
+        let mut post_block_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            format!("/v2/blocks/upload/{}", ConsensusHash([0x22; 20])),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_block_preamble.set_content_type(HttpContentType::Bytes);
+        post_block_preamble.set_content_length(block_body.len() as u32);
+
+        let mut mempool_gc_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/mempool/gc".to_string(),
+            http_request_metadata_ip.peer.hostname(),
+            http_request_metadata_ip.peer.port(),
+            http_request_metadata_ip.keep_alive,
+        );
+        mempool_gc_preamble.set_content_length(0);
+
+        let mut simulate_block_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/mempool/simulate_block".to_string(),
+            http_request_metadata_ip.peer.hostname(),
+            http_request_metadata_ip.peer.port(),
+            http_request_metadata_ip.keep_alive,
+        );
+        simulate_block_preamble.set_content_length(0);
+
+        let aggregation_commit_body = format!(
+            "{{\"pox_address\":\"{}\",\"reward_cycle\":12}}",
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                .unwrap()
+                .to_b58()
+        )
+        .into_bytes();
+
+        let mut post_aggregation_commit_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/pox/aggregation_commit/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_aggregation_commit_preamble.set_content_type(HttpContentType::JSON);
+        post_aggregation_commit_preamble.set_content_length(aggregation_commit_body.len() as u32);
+
         // all of these should parse
         let expected_http_preambles = vec![
             HttpRequestPreamble::new(
@@ -5098,6 +8532,123 @@ mod test {
                 http_request_metadata_dns.peer.port(),
                 http_request_metadata_dns.keep_alive,
             ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/burn_blocks/666050".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/burn_blocks/666050/ops".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!("/v2/burn_ops/{}", Txid([5u8; 32]).to_hex()),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/pox/reward_set?tip={}",
+                    StacksBlockId([4u8; 32]).to_hex()
+                ),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/pox/delegations/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            post_aggregation_commit_preamble,
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/accounts/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/assets".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/accounts/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/nonces".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/accounts/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R?proof=0&mempool=1"
+                    .to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/accounts/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R?tip={}&height=3",
+                    StacksBlockId([7u8; 32])
+                ),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!("/v2/blocks/{}/event_bloom", StacksBlockId([6u8; 32]).to_hex()),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/headers/10".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!("/v2/headers/1?tip={}", StacksBlockId([7u8; 32]).to_hex()),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/attachments/inv/instances?tip={}&pages_indexes=0,2&contract_id=ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.bns&namespace=id",
+                    StacksBlockId([5u8; 32])
+                ),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            mempool_gc_preamble,
+            simulate_block_preamble,
             HttpRequestPreamble::new(
                 HttpVersion::Http11,
                 "GET".to_string(),
@@ -5107,6 +8658,26 @@ mod test {
                 http_request_metadata_ip.keep_alive,
             ),
             post_transaction_preamble,
+            post_fee_estimate_preamble,
+            post_simulate_transaction_preamble,
+            validate_block_proposal_preamble,
+            get_miner_status_preamble,
+            get_scheduled_calls_preamble,
+            post_scheduled_call_preamble,
+            delete_scheduled_call_preamble,
+            post_batch_preamble,
+            post_block_preamble,
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/accounts/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/summary?tip={}",
+                    StacksBlockId([5u8; 32])
+                ),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
             HttpRequestPreamble::new(
                 HttpVersion::Http11,
                 "OPTIONS".to_string(),
@@ -5117,8 +8688,39 @@ mod test {
             ),
         ];
 
-        let expected_http_bodies = vec![vec![], vec![], vec![], tx_body];
-
+        let expected_http_bodies = vec![
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            aggregation_commit_body,
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            tx_body.clone(),
+            tx_body.clone(),
+            tx_body,
+            block_body.clone(),
+            vec![],
+            vec![],
+            scheduled_call_body,
+            vec![],
+            batch_body,
+            block_body,
+            vec![],
+        ];
+
         for (test, (expected_http_preamble, expected_http_body)) in tests.iter().zip(
             expected_http_preambles
                 .iter()
@@ -5156,6 +8758,335 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_http_request_route() {
+        let md = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
+            keep_alive: true,
+        };
+        let principal = PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let contract_id =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.signers")
+                .unwrap();
+
+        let tests = vec![
+            (HttpRequestType::GetInfo(md.clone()), "/v2/info"),
+            (
+                HttpRequestType::GetBlock(md.clone(), StacksBlockId([2u8; 32])),
+                "/v2/blocks/:block_id",
+            ),
+            (
+                HttpRequestType::GetBlock(md.clone(), StacksBlockId([3u8; 32])),
+                "/v2/blocks/:block_id",
+            ),
+            (
+                HttpRequestType::GetAccountNonces(
+                    md.clone(),
+                    principal.clone(),
+                    Some(StacksBlockId([4u8; 32])),
+                ),
+                "/v2/accounts/:principal/nonces",
+            ),
+            (
+                HttpRequestType::GetAccountSummary(md.clone(), principal.clone(), None),
+                "/v2/accounts/:principal/summary",
+            ),
+            (
+                HttpRequestType::GetTransactionReceipt(md.clone(), Txid([8u8; 32]), None),
+                "/v2/transactions/:txid/receipt",
+            ),
+            (
+                HttpRequestType::GetPrincipalTransactions(
+                    md.clone(),
+                    principal.clone(),
+                    0,
+                    1,
+                    None,
+                ),
+                "/v2/addresses/:principal/transactions",
+            ),
+            (
+                HttpRequestType::GetPendingMinerRewards(
+                    md.clone(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    None,
+                ),
+                "/v2/addresses/:address/pending_rewards",
+            ),
+            (
+                HttpRequestType::GetUnconfirmedMicroblockStream(md.clone(), 0, None),
+                "/v2/microblocks/unconfirmed",
+            ),
+            (
+                HttpRequestType::GetHeaders(md.clone(), 10, None),
+                "/v2/headers/:quantity",
+            ),
+            (
+                HttpRequestType::ScheduledCalls(md.clone(), ScheduledCallsOp::List, None),
+                "/v2/admin/scheduled_calls",
+            ),
+            (
+                HttpRequestType::ScheduledCalls(md.clone(), ScheduledCallsOp::Cancel(3), None),
+                "/v2/admin/scheduled_calls/:id",
+            ),
+            (
+                HttpRequestType::Batch(md.clone(), vec![], None, true, RPCStateView::Confirmed),
+                "/v2/batch",
+            ),
+            (
+                HttpRequestType::AnalyzeContract(md.clone(), "(ok 1)".to_string(), None, None),
+                "/v2/contracts/analyze",
+            ),
+            (
+                HttpRequestType::GetStackerDBMetadata(md.clone(), contract_id.clone()),
+                "/v2/stackerdb/:principal/:contract_name",
+            ),
+            (
+                HttpRequestType::GetStackerDBChunk(md.clone(), contract_id.clone(), 3),
+                "/v2/stackerdb/:principal/:contract_name/:slot_id",
+            ),
+            (
+                HttpRequestType::PostStackerDBChunk(
+                    md.clone(),
+                    contract_id,
+                    StackerDBChunkData::new(3, 1, vec![1, 2, 3]),
+                ),
+                "/v2/stackerdb/:principal/:contract_name/chunks",
+            ),
+            (
+                HttpRequestType::DevMine(md.clone(), RPCDevMineRequest::default()),
+                "/v2/dev/mine",
+            ),
+        ];
+
+        for (req, route) in tests.into_iter() {
+            assert_eq!(req.route(), route);
+            assert!(req
+                .request_path()
+                .starts_with(route.split("/:").next().unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_http_stackerdb_codec() {
+        let md = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
+            keep_alive: true,
+        };
+        let contract_id =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.signers")
+                .unwrap();
+        let mut chunk = StackerDBChunkData::new(3, 7, vec![1, 2, 3, 4]);
+        chunk.sign(&StacksPrivateKey::new()).unwrap();
+
+        let requests = vec![
+            HttpRequestType::GetStackerDBMetadata(md.clone(), contract_id.clone()),
+            HttpRequestType::GetStackerDBChunk(md.clone(), contract_id.clone(), 3),
+            HttpRequestType::PostStackerDBChunk(md.clone(), contract_id.clone(), chunk.clone()),
+        ];
+        for request in requests.into_iter() {
+            let mut http = StacksHttp::new();
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request.clone()))
+                .unwrap();
+
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            assert_eq!(message, StacksHttpMessage::Request(request));
+        }
+
+        let response_md = HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true);
+        let ack = StackerDBChunkAckData {
+            accepted: false,
+            reason: Some("slot 3 is already at version 7".to_string()),
+            metadata: Some(chunk.get_slot_metadata()),
+        };
+        let responses = vec![
+            (
+                HttpResponseType::StackerDBMetadata(
+                    response_md.clone(),
+                    vec![chunk.get_slot_metadata()],
+                ),
+                "/v2/stackerdb/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/signers",
+            ),
+            (
+                HttpResponseType::StackerDBChunk(response_md.clone(), chunk.clone()),
+                "/v2/stackerdb/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/signers/3",
+            ),
+            (
+                HttpResponseType::StackerDBChunkAck(response_md.clone(), ack.clone()),
+                "/v2/stackerdb/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/signers/chunks",
+            ),
+        ];
+        for (response, request_path) in responses.into_iter() {
+            let mut http = StacksHttp::new();
+            let mut bytes = vec![];
+            http.begin_request(HttpVersion::Http11, request_path.to_string());
+            http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone()))
+                .unwrap();
+
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let is_chunked = match preamble {
+                StacksHttpPreamble::Response(ref resp) => resp.is_chunked(),
+                StacksHttpPreamble::Request(_) => panic!("parsed a request"),
+            };
+            let (message, _) = if is_chunked {
+                let (msg_opt, len) = http
+                    .stream_payload(&preamble, &mut &bytes[offset..])
+                    .unwrap();
+                (msg_opt.unwrap().0, len)
+            } else {
+                http.read_payload(&preamble, &bytes[offset..]).unwrap()
+            };
+            match (message, response) {
+                (
+                    StacksHttpMessage::Response(HttpResponseType::StackerDBMetadata(_, slots)),
+                    HttpResponseType::StackerDBMetadata(_, expected_slots),
+                ) => assert_eq!(slots, expected_slots),
+                (
+                    StacksHttpMessage::Response(HttpResponseType::StackerDBChunk(_, chunk)),
+                    HttpResponseType::StackerDBChunk(_, expected_chunk),
+                ) => assert_eq!(chunk, expected_chunk),
+                (
+                    StacksHttpMessage::Response(HttpResponseType::StackerDBChunkAck(_, ack)),
+                    HttpResponseType::StackerDBChunkAck(_, expected_ack),
+                ) => assert_eq!(ack, expected_ack),
+                (message, _) => panic!("Unexpected message {:?}", &message),
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_dev_mine_codec() {
+        let md = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::DNS("localhost".to_string(), 20443),
+            keep_alive: true,
+        };
+        let requests = vec![
+            HttpRequestType::DevMine(md.clone(), RPCDevMineRequest::default()),
+            HttpRequestType::DevMine(
+                md.clone(),
+                RPCDevMineRequest {
+                    txids: vec![format!("{}", Txid([0x11; 32]))],
+                    sortition: Some(false),
+                },
+            ),
+        ];
+        for request in requests.into_iter() {
+            let mut http = StacksHttp::new();
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request.clone()))
+                .unwrap();
+
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            assert_eq!(message, StacksHttpMessage::Request(request));
+        }
+
+        // a body-less request is the default one, and txids have to be valid
+        let mut http = StacksHttp::new();
+        let empty = "POST /v2/dev/mine HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n";
+        let (preamble, offset) = http.read_preamble(empty.as_bytes()).unwrap();
+        let (message, _) = http
+            .read_payload(&preamble, &empty.as_bytes()[offset..])
+            .unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::DevMine(_, request)) => {
+                assert_eq!(request, RPCDevMineRequest::default())
+            }
+            _ => panic!("Unexpected message {:?}", &message),
+        }
+
+        let body = "{\"txids\":[\"not-a-txid\"]}";
+        let bad = format!(
+            "POST /v2/dev/mine HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let mut http = StacksHttp::new();
+        let (preamble, offset) = http.read_preamble(bad.as_bytes()).unwrap();
+        assert!(http
+            .read_payload(&preamble, &bad.as_bytes()[offset..])
+            .is_err());
+    }
+
+    #[test]
+    fn test_http_request_authorization() {
+        let requests = vec![
+            ("GET /v2/info HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nContent-Length: 0\r\n\r\n", false),
+            ("GET /v2/info HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nAuthorization: wrong\r\nContent-Length: 0\r\n\r\n", false),
+            ("GET /v2/info HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nAuthorization: secret\r\nContent-Length: 0\r\n\r\n", true),
+            ("GET /v2/info HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nAuthorization: admin\r\nContent-Length: 0\r\n\r\n", true),
+            ("OPTIONS / HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nContent-Length: 0\r\n\r\n", true),
+        ];
+        for (request, authorized) in requests {
+            let mut http = StacksHttp::new();
+            http.auth_tokens = vec!["secret".to_string(), "admin".to_string()];
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            let (message, _) = http
+                .read_payload(&preamble, &request.as_bytes()[offset..])
+                .unwrap();
+            match message {
+                StacksHttpMessage::Request(HttpRequestType::ClientError(
+                    _,
+                    ClientError::Unauthorized(_),
+                )) => assert!(!authorized, "{}", request),
+                StacksHttpMessage::Request(_) => assert!(authorized, "{}", request),
+                _ => panic!("Not a request"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_rate_limit_parse() {
+        assert_eq!(
+            "600/60".parse::<HttpRateLimit>().unwrap(),
+            HttpRateLimit {
+                max_requests: 600,
+                window_secs: 60
+            }
+        );
+        assert_eq!(
+            " 10 / 1 ".parse::<HttpRateLimit>().unwrap(),
+            HttpRateLimit {
+                max_requests: 10,
+                window_secs: 1
+            }
+        );
+        for bad in ["600", "600/", "/60", "600/0", "a/60", "600/b"].iter() {
+            assert!(bad.parse::<HttpRateLimit>().is_err(), "parsed {}", bad);
+        }
+    }
+
+    #[test]
+    fn test_http_rate_limiter() {
+        let client_1: IpAddr = "1.2.3.4".parse().unwrap();
+        let client_2: IpAddr = "5.6.7.8".parse().unwrap();
+        let limit = HttpRateLimit {
+            max_requests: 2,
+            window_secs: 10,
+        };
+        let mut limiter = HttpRateLimiter::new();
+
+        assert!(limiter.admit(client_1, "*", &limit, 100));
+        assert!(limiter.admit(client_1, "*", &limit, 105));
+        assert!(!limiter.admit(client_1, "*", &limit, 109));
+
+        // limits are per client and per bucket
+        assert!(limiter.admit(client_2, "*", &limit, 109));
+        assert!(limiter.admit(client_1, "/v2/info", &limit, 109));
+
+        // the window resets, and ended windows are forgotten
+        assert!(limiter.admit(client_1, "*", &limit, 110));
+        assert_eq!(limiter.num_windows(), 3);
+        assert!(limiter.admit(client_1, "*", &limit, 125));
+        assert_eq!(limiter.num_windows(), 1);
+    }
+
     #[test]
     fn test_http_request_type_codec_err() {
         let bad_content_lengths = vec![
@@ -5190,6 +9121,18 @@ mod test {
             assert!(e.is_err());
             assert!(e.unwrap_err().to_string().find("Content-Type").is_some());
         }
+
+        let bad_limits = vec![
+            "GET /v2/addresses/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/transactions?limit=0 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nContent-Length: 0\r\n\r\n",
+            "GET /v2/addresses/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/transactions?limit=201 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nContent-Length: 0\r\n\r\n",
+        ];
+        for bad_limit in bad_limits {
+            let mut http = StacksHttp::new();
+            let (preamble, offset) = http.read_preamble(bad_limit.as_bytes()).unwrap();
+            let e = http.read_payload(&preamble, &bad_limit.as_bytes()[offset..]);
+            assert!(e.is_err());
+            assert!(e.unwrap_err().to_string().find("Invalid limit").is_some());
+        }
     }
 
     #[test]
@@ -5332,6 +9275,17 @@ mod test {
                 ),
                 "/v2/transactions".to_string(),
             ),
+            (
+                HttpResponseType::Headers(
+                    HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true),
+                    vec![ExtendedStacksHeader {
+                        consensus_hash: ConsensusHash([0x2; 20]),
+                        header: test_block_info.header.clone(),
+                        parent_block_id: StacksBlockId([0x3; 32]),
+                    }],
+                ),
+                "/v2/headers/1".to_string(),
+            ),
             // errors without error messages
             (
                 HttpResponseType::BadRequest(
@@ -5368,6 +9322,13 @@ mod test {
                 ),
                 "/v2/neighbors".to_string(),
             ),
+            (
+                HttpResponseType::TooManyRequests(
+                    HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true),
+                    "".to_string(),
+                ),
+                "/v2/neighbors".to_string(),
+            ),
             (
                 HttpResponseType::ServerError(
                     HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true),
@@ -5426,6 +9387,13 @@ mod test {
                 ),
                 "/v2/neighbors".to_string(),
             ),
+            (
+                HttpResponseType::TooManyRequests(
+                    HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true),
+                    "foo".to_string(),
+                ),
+                "/v2/neighbors".to_string(),
+            ),
             (
                 HttpResponseType::ServerError(
                     HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(3), true),
@@ -5517,12 +9485,21 @@ mod test {
                 true,
                 123,
             ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                None,
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
             // errors
             HttpResponsePreamble::new_error(400, 123, None),
             HttpResponsePreamble::new_error(401, 123, None),
             HttpResponsePreamble::new_error(402, 123, None),
             HttpResponsePreamble::new_error(403, 123, None),
             HttpResponsePreamble::new_error(404, 123, None),
+            HttpResponsePreamble::new_error(429, 123, None),
             HttpResponsePreamble::new_error(500, 123, None),
             HttpResponsePreamble::new_error(503, 123, None),
             // generic error
@@ -5533,6 +9510,7 @@ mod test {
             HttpResponsePreamble::new_error(402, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(403, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(404, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(429, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(500, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(503, 123, Some("foo".to_string())),
             HttpResponsePreamble::new_error(502, 123, Some("foo".to_string())),
@@ -5555,6 +9533,7 @@ mod test {
             test_block_info_bytes,
             test_microblock_info_bytes,
             Txid([0x1; 32]).to_hex().as_bytes().to_vec(),
+            vec![],
             // errors
             vec![],
             vec![],
@@ -5564,6 +9543,7 @@ mod test {
             vec![],
             vec![],
             vec![],
+            vec![],
             // errors with messages
             "foo".as_bytes().to_vec(),
             "foo".as_bytes().to_vec(),
@@ -5573,6 +9553,7 @@ mod test {
             "foo".as_bytes().to_vec(),
             "foo".as_bytes().to_vec(),
             "foo".as_bytes().to_vec(),
+            "foo".as_bytes().to_vec(),
         ];
 
         for ((test, request_path), (expected_http_preamble, _expected_http_body)) in
@@ -5963,7 +9944,7 @@ mod test {
                 None,
                 &HttpContentType::JSON,
                 123,
-                |ref mut fd| keep_alive_headers(fd, &md),
+                |ref mut fd| response_metadata_headers(fd, &md),
             )
             .unwrap();
             responses.push(String::from_utf8(bytes).unwrap());
@@ -5998,6 +9979,41 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_http_response_deprecation_headers() {
+        let deprecated_req = HttpRequestType::GetTransferCost(HttpRequestMetadata::new(
+            "127.0.0.1".to_string(),
+            20443,
+        ));
+        let current_req =
+            HttpRequestType::GetInfo(HttpRequestMetadata::new("127.0.0.1".to_string(), 20443));
+
+        assert_eq!(
+            deprecated_req.deprecation(),
+            Some(HttpDeprecation {
+                usage: "GET /v2/fees/transfer",
+                successor: "/v2/fees/transaction",
+            })
+        );
+        assert_eq!(current_req.deprecation(), None);
+
+        let mut responses = vec![];
+        for req in [deprecated_req, current_req].iter() {
+            let mut bytes = vec![];
+            let md = HttpResponseMetadata::from(req);
+            HttpResponsePreamble::ok_JSON_from_md(&mut bytes, &md).unwrap();
+            responses.push(String::from_utf8(bytes).unwrap());
+        }
+
+        assert!(responses[0].find("Deprecation: true\r\n").is_some());
+        assert!(responses[0]
+            .find("Link: </v2/fees/transaction>; rel=\"successor-version\"\r\n")
+            .is_some());
+
+        assert!(responses[1].find("Deprecation:").is_none());
+        assert!(responses[1].find("Link:").is_none());
+    }
+
     #[test]
     fn test_http_parse_proof_tip_query() {
         let query_txt = "tip=7070f213d719143d6045e08fd80f85014a161f8bbd3a42d1251576740826a392";
@@ -6044,6 +10060,92 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_http_parse_state_view_query() {
+        let tip = StacksBlockId([1u8; 32]);
+        assert_eq!(
+            HttpRequestType::get_state_view_query(None, None).unwrap(),
+            RPCStateView::Confirmed
+        );
+        assert_eq!(
+            HttpRequestType::get_state_view_query(Some("proof=0"), None).unwrap(),
+            RPCStateView::Confirmed
+        );
+        assert_eq!(
+            HttpRequestType::get_state_view_query(Some("unconfirmed=0"), Some(&tip)).unwrap(),
+            RPCStateView::Confirmed
+        );
+        assert_eq!(
+            HttpRequestType::get_state_view_query(Some("unconfirmed=1"), None).unwrap(),
+            RPCStateView::Unconfirmed
+        );
+
+        // mempool implies unconfirmed
+        assert_eq!(
+            HttpRequestType::get_state_view_query(Some("mempool=1"), None).unwrap(),
+            RPCStateView::UnconfirmedWithMempool
+        );
+        assert_eq!(
+            HttpRequestType::get_state_view_query(Some("mempool=1&unconfirmed=1"), None).unwrap(),
+            RPCStateView::UnconfirmedWithMempool
+        );
+
+        // can't ask for a specific tip as well
+        assert!(HttpRequestType::get_state_view_query(Some("unconfirmed=1"), Some(&tip)).is_err());
+        assert!(HttpRequestType::get_state_view_query(Some("mempool=1"), Some(&tip)).is_err());
+    }
+
+    #[test]
+    fn test_http_parse_height_query() {
+        let confirmed = RPCStateView::Confirmed;
+        assert_eq!(
+            HttpRequestType::get_chain_height_query(None, confirmed).unwrap(),
+            None
+        );
+        assert_eq!(
+            HttpRequestType::get_chain_height_query(Some("proof=0"), confirmed).unwrap(),
+            None
+        );
+        assert_eq!(
+            HttpRequestType::get_chain_height_query(Some("height=12&proof=0"), confirmed).unwrap(),
+            Some(12)
+        );
+
+        // first parseable height is taken
+        assert_eq!(
+            HttpRequestType::get_chain_height_query(
+                Some("height=bad&height=3&height=4"),
+                confirmed
+            )
+            .unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            HttpRequestType::get_chain_height_query(Some("height=-1"), confirmed).unwrap(),
+            None
+        );
+
+        // can't ask for a height in the unconfirmed state
+        assert!(HttpRequestType::get_chain_height_query(
+            Some("height=3"),
+            RPCStateView::Unconfirmed
+        )
+        .is_err());
+        assert!(HttpRequestType::get_chain_height_query(
+            Some("height=3"),
+            RPCStateView::UnconfirmedWithMempool
+        )
+        .is_err());
+        assert_eq!(
+            HttpRequestType::get_chain_height_query(
+                Some("unconfirmed=1"),
+                RPCStateView::Unconfirmed
+            )
+            .unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn test_http_live_headers() {
         // headers pulled from prod
@@ -31,6 +31,8 @@ pub mod prune;
 pub mod relay;
 pub mod rpc;
 pub mod server;
+pub mod stackerdb;
+pub mod stream;
 
 use std::borrow::Borrow;
 use std::cmp::PartialEq;
@@ -63,6 +65,7 @@ use serde_json;
 use regex::Regex;
 
 use core::mempool::*;
+use core::scheduler::{ScheduledCall, ScheduledCallRequest};
 
 use burnchains::BurnchainHeaderHash;
 use burnchains::Txid;
@@ -73,16 +76,19 @@ use chainstate::burn::ConsensusHash;
 
 use chainstate::burn::db::sortdb::PoxId;
 
+use chainstate::stacks::address::AddressKind;
 use chainstate::stacks::db::blocks::MemPoolRejection;
+use chainstate::stacks::db::header_chain::ExtendedStacksHeader;
 use chainstate::stacks::{
-    Error as chain_error, StacksAddress, StacksBlock, StacksBlockId, StacksMicroblock,
-    StacksPublicKey, StacksTransaction,
+    Error as chain_error, StacksAddress, StacksBlock, StacksBlockHeader, StacksBlockId,
+    StacksMicroblock, StacksPublicKey, StacksTransaction,
 };
 
 use chainstate::stacks::Error as chainstate_error;
 
 use vm::{
-    analysis::contract_interface_builder::ContractInterface, types::PrincipalData, ClarityName,
+    analysis::contract_interface_builder::ContractInterface, costs::ExecutionCost,
+    representations::Span, types::PrincipalData, types::QualifiedContractIdentifier, ClarityName,
     ContractName, Value,
 };
 
@@ -114,6 +120,9 @@ use crate::util::hash::Sha256Sum;
 use self::dns::*;
 
 use net::atlas::{Attachment, AttachmentInstance};
+use net::stackerdb::{
+    SlotMetadata, StackerDBChunkAckData, StackerDBChunkData, StackerDBPushChunkData,
+};
 
 use core::POX_REWARD_CYCLE_LENGTH;
 
@@ -221,6 +230,8 @@ pub enum Error {
     ConnectionCycle,
     /// Requested data not found
     NotFoundError,
+    /// StackerDB chunk was rejected (bad slot, signer, version, or size)
+    StackerDBChunkRejected(String),
 }
 
 /// Enum for passing data for ClientErrors
@@ -230,6 +241,8 @@ pub enum ClientError {
     Message(String),
     /// 404
     NotFound(String),
+    /// 401: the request lacks a valid Authorization header
+    Unauthorized(String),
 }
 
 impl error::Error for ClientError {
@@ -243,6 +256,7 @@ impl fmt::Display for ClientError {
         match self {
             ClientError::Message(s) => write!(f, "{}", s),
             ClientError::NotFound(s) => write!(f, "HTTP path not matched: {}", s),
+            ClientError::Unauthorized(s) => write!(f, "Unauthorized: {}", s),
         }
     }
 }
@@ -303,6 +317,9 @@ impl fmt::Display for Error {
             Error::StaleView => write!(f, "State view is stale"),
             Error::ConnectionCycle => write!(f, "Tried to connect to myself"),
             Error::NotFoundError => write!(f, "Requested data not found"),
+            Error::StackerDBChunkRejected(ref s) => {
+                write!(f, "StackerDB chunk rejected: {}", s)
+            }
         }
     }
 }
@@ -361,6 +378,7 @@ impl error::Error for Error {
             Error::StaleView => None,
             Error::ConnectionCycle => None,
             Error::NotFoundError => None,
+            Error::StackerDBChunkRejected(ref _s) => None,
         }
     }
 }
@@ -889,6 +907,7 @@ pub enum StacksMessageType {
     Pong(PongData),
     NatPunchRequest(u32),
     NatPunchReply(NatPunchData),
+    StackerDBPushChunk(StackerDBPushChunkData),
 }
 
 /// Peer address variants
@@ -1008,6 +1027,19 @@ pub struct RPCPeerInfoData {
     pub unanchored_tip: StacksBlockId,
     pub unanchored_seq: u16,
     pub exit_at_block_height: Option<u64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genesis_audit: Option<RPCGenesisAuditData>,
+}
+
+/// The result of auditing the genesis balances, as reported on GET /v2/info
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCGenesisAuditData {
+    /// microSTX the node expected to be liquid at genesis
+    pub expected_liquid_ustx: u64,
+    /// microSTX that were actually liquid at genesis
+    pub genesis_liquid_ustx: u64,
+    pub passed: bool,
 }
 
 /// The data we return on GET /v2/pox
@@ -1024,6 +1056,187 @@ pub struct RPCPoxInfoData {
     pub total_liquid_supply_ustx: u128,
 }
 
+/// A PoX address in the reward set we return on GET /v2/pox/reward_set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCRewardSetEntry {
+    pub pox_address: String,
+    pub stacked_ustx: u128,
+    pub slots: u32,
+    /// how many more uSTX this address would need to stack to receive one more slot, at the
+    /// projected threshold
+    pub ustx_to_next_slot: u128,
+}
+
+/// The data we return on GET /v2/pox/reward_set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCRewardSetData {
+    pub reward_cycle_id: u64,
+    pub reward_cycle_start_burn_height: u64,
+    pub prepare_phase_start_burn_height: u64,
+    /// the height of the node's canonical burnchain tip
+    pub burn_block_height: u64,
+    /// how many burnchain blocks are left before the prepare phase begins
+    pub blocks_until_prepare_phase: u64,
+    pub pox_active: bool,
+    pub liquid_ustx: u128,
+    pub participation_ustx: u128,
+    pub threshold_ustx: u128,
+    pub enough_participation: bool,
+    pub reward_slots: u32,
+    pub entries: Vec<RPCRewardSetEntry>,
+}
+
+/// A stacker's delegation in the data we return on GET /v2/pox/delegations/:principal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPoxDelegation {
+    pub stacker: String,
+    pub amount_ustx: u128,
+    pub until_burn_ht: Option<u64>,
+    pub pox_address: Option<String>,
+    pub locked_ustx: u128,
+    pub unlock_height: u64,
+}
+
+/// The principal's own delegation in the data we return on GET /v2/pox/delegations/:principal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPoxDelegatedTo {
+    pub delegate: String,
+    pub amount_ustx: u128,
+    pub until_burn_ht: Option<u64>,
+    pub pox_address: Option<String>,
+}
+
+/// A delegate's uncommitted uSTX in the data we return on GET /v2/pox/delegations/:principal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPoxPartialStack {
+    pub pox_address: String,
+    pub reward_cycle: u64,
+    pub stacked_ustx: u128,
+}
+
+/// The data we return on GET /v2/pox/delegations/:principal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPoxDelegationsData {
+    pub delegate: String,
+    pub total_delegated_ustx: u128,
+    pub total_locked_ustx: u128,
+    pub delegations: Vec<RPCPoxDelegation>,
+    /// the principal's own delegation, if it is delegating
+    pub delegated_to: Option<RPCPoxDelegatedTo>,
+    /// what the principal has stacked as a delegate and not yet committed
+    pub partial_stacks: Vec<RPCPoxPartialStack>,
+}
+
+/// The data we return on POST /v2/pox/aggregation_commit/:principal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAggregationCommitData {
+    pub okay: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+    pub partial_stacked_ustx: u128,
+}
+
+/// The data we return on GET /v2/burn_blocks/:height
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBurnBlockInfoData {
+    pub burn_block_height: u64,
+    pub burn_block_hash: String,
+    pub burn_block_time: u64,
+    pub median_time_past: u64,
+    pub consensus_hash: ConsensusHash,
+}
+
+/// A processed burnchain operation, as returned by GET /v2/burn_blocks/:height/ops and
+/// GET /v2/burn_ops/:txid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RPCBurnchainOpData {
+    LeaderKeyRegister {
+        txid: String,
+        vtxindex: u32,
+        consensus_hash: ConsensusHash,
+        public_key: String,
+        address: String,
+    },
+    LeaderBlockCommit {
+        txid: String,
+        vtxindex: u32,
+        block_header_hash: String,
+        burn_fee: u64,
+        sunset_burn: u64,
+        parent_block_ptr: u32,
+        parent_vtxindex: u16,
+        key_block_ptr: u32,
+        key_vtxindex: u16,
+        commit_outs: Vec<String>,
+        /// whether or not this commit's block was chosen by the sortition
+        won_sortition: bool,
+    },
+    UserBurnSupport {
+        txid: String,
+        vtxindex: u32,
+        address: String,
+        burn_fee: u64,
+        key_block_ptr: u32,
+        key_vtxindex: u16,
+        block_header_hash_160: String,
+        /// whether or not this burn supported the block chosen by the sortition
+        won_sortition: bool,
+    },
+    StackStx {
+        txid: String,
+        vtxindex: u32,
+        sender: String,
+        reward_addr: String,
+        stacked_ustx: u128,
+        num_cycles: u8,
+    },
+    TransferStx {
+        txid: String,
+        vtxindex: u32,
+        sender: String,
+        recipient: String,
+        transfered_ustx: u128,
+        memo: String,
+    },
+}
+
+/// The data we return on GET /v2/burn_blocks/:height/ops and GET /v2/burn_ops/:txid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBurnchainOpsData {
+    pub burn_block_height: u64,
+    pub burn_block_hash: String,
+    pub consensus_hash: ConsensusHash,
+    /// whether or not this burnchain block chose a Stacks block
+    pub sortition: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winning_block_txid: Option<String>,
+    /// the block's operations, in the order they appear in the block
+    pub ops: Vec<RPCBurnchainOpData>,
+}
+
+/// Which burnchain operations a GetBurnchainOps request asks for
+#[derive(Debug, Clone, PartialEq)]
+pub enum BurnchainOpsQuery {
+    /// GET /v2/burn_blocks/:height/ops: all operations in the block at this height
+    Height(u64),
+    /// GET /v2/burn_ops/:txid: the operation with this txid
+    Txid(Txid),
+}
+
+/// The data we return on GET /v2/blocks/:index_block_hash/event_bloom
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBlockEventBloomData {
+    pub index_block_hash: String,
+    /// hex-encoded bloom filter over the block's contract events
+    pub bloom: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Copy, Hash)]
 #[repr(u8)]
 pub enum HttpVersion {
@@ -1047,6 +1260,15 @@ pub struct MapEntryResponse {
     pub marf_proof: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataVarResponse {
+    pub data: String,
+    #[serde(rename = "proof")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marf_proof: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContractSrcResponse {
     pub source: String,
@@ -1073,7 +1295,12 @@ pub struct AccountEntryResponse {
     pub balance: String,
     pub locked: String,
     pub unlock_height: u64,
+    pub total_balance: String,
     pub nonce: u64,
+    /// each of the account's locks that has not expired, if it has any besides its PoX lock
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub locks: Vec<AccountLockEntry>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub balance_proof: Option<String>,
@@ -1082,6 +1309,354 @@ pub struct AccountEntryResponse {
     pub nonce_proof: Option<String>,
 }
 
+/// One of an account's STX locks, as reported on GET /v2/accounts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountLockEntry {
+    pub amount: String,
+    pub unlock_height: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountFungibleTokenEntry {
+    pub asset_identifier: String,
+    pub balance: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountNonFungibleTokenEntry {
+    pub asset_identifier: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountAssetsResponse {
+    pub fungible_tokens: Vec<AccountFungibleTokenEntry>,
+    pub non_fungible_tokens: Vec<AccountNonFungibleTokenEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountNoncesResponse {
+    /// the account's nonce at the chain tip
+    pub confirmed_nonce: u64,
+    /// the highest nonce this account uses in the mempool, if any
+    pub last_mempool_nonce: Option<u64>,
+    /// the nonce the account's next transaction should use
+    pub next_nonce: u64,
+    /// nonces between the confirmed nonce and the highest mempool nonce that no mempool
+    /// transaction uses.  Transactions after a gap cannot be mined until it is filled.
+    pub missing_nonces: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountFirstSeenEntry {
+    pub block_height: u64,
+    pub index_block_hash: String,
+    pub txid: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountSummaryResponse {
+    pub balance: String,
+    pub locked: String,
+    pub total_balance: String,
+    pub nonce: u64,
+    /// whether the principal is a standard address, a contract, or the burn address
+    pub kind: AddressKind,
+    /// the block in which the account first sent a transaction or received STX, if it has
+    pub first_seen: Option<AccountFirstSeenEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrincipalTransactionEntry {
+    pub txid: String,
+    pub index_block_hash: String,
+    pub block_height: u64,
+    pub tx_index: u32,
+}
+
+/// The data we return on GET /v2/addresses/<principal>/transactions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrincipalTransactionsResponse {
+    pub principal: String,
+    pub offset: u64,
+    pub limit: u64,
+    /// number of transactions that involved the principal in the chain tip's fork
+    pub total: u64,
+    /// newest first
+    pub results: Vec<PrincipalTransactionEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingMinerRewardEntry {
+    pub block_hash: String,
+    pub consensus_hash: String,
+    pub index_block_hash: String,
+    pub stacks_block_height: u64,
+    /// whether the reward is for mining the block, as opposed to supporting its miner
+    pub miner: bool,
+    /// principal the reward will be paid to, if not the address queried
+    pub recipient: Option<String>,
+    pub coinbase: String,
+    pub tx_fees_anchored: String,
+    pub tx_fees_streamed: String,
+    pub burnchain_commit_burn: u64,
+    pub vtxindex: u32,
+    /// height of the Stacks block that will pay out the reward
+    pub maturity_height: u64,
+    /// earliest burnchain block height at which the reward can be paid out
+    pub earliest_maturity_burn_height: u64,
+}
+
+/// The data we return on GET /v2/addresses/<address>/pending_rewards
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingMinerRewardsResponse {
+    pub address: String,
+    /// oldest first
+    pub results: Vec<PendingMinerRewardEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnconfirmedMicroblockEntry {
+    pub microblock_hash: String,
+    pub sequence: u16,
+    pub parent_microblock_hash: String,
+    /// hex-encoded transactions, in the order they were mined
+    pub transactions: Vec<String>,
+}
+
+/// The data we return on GET /v2/microblocks/unconfirmed, and stream to WebSocket clients as new
+/// microblocks arrive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnconfirmedMicroblockStreamResponse {
+    pub parent_index_block_hash: String,
+    /// lowest sequence first
+    pub microblocks: Vec<UnconfirmedMicroblockEntry>,
+}
+
+/// The data we return on GET /v2/transactions/<txid>/receipt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionReceiptResponse {
+    pub txid: String,
+    pub index_block_hash: String,
+    pub block_height: u64,
+    pub tx_index: u32,
+    pub raw_tx: String,
+    /// hex-encoded Clarity value the transaction evaluated to
+    pub result: String,
+    pub post_condition_aborted: bool,
+    /// emitted events, in the same form as the event observer interface
+    pub events: Vec<serde_json::Value>,
+    /// assets sent or burned by each principal
+    pub asset_map: serde_json::Value,
+    pub execution_cost: ExecutionCost,
+    pub stx_burned: String,
+    pub contract_abi: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionFeeEstimateResponse {
+    pub estimated_len: u64,
+    pub fee_rates: FeeRateEstimate,
+    pub fees: FeeRateEstimate,
+}
+
+/// The body of a POST to /v2/contracts/analyze
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractAnalysisRequestBody {
+    /// Clarity source code of the contract
+    pub source: String,
+    /// the contract to analyze the source as, which matters if it refers to itself.  Defaults to a
+    /// transient contract identifier.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_id: Option<String>,
+}
+
+/// A parse, analysis, or runtime error in a contract analyzed with POST /v2/contracts/analyze
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractAnalysisError {
+    pub message: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    /// where in the source the error is, if known
+    pub spans: Vec<Span>,
+}
+
+/// The data we return on POST /v2/contracts/analyze
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractAnalysisResponse {
+    /// whether or not the contract could be deployed.  If not, `errors` says why.
+    pub okay: bool,
+    /// the contract's interface, if it passed analysis
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interface: Option<ContractInterface>,
+    pub errors: Vec<ContractAnalysisError>,
+    /// cost of parsing and analyzing the contract
+    pub analysis_cost: ExecutionCost,
+    /// cost of deploying the contract, which is the analysis cost plus the cost of evaluating its
+    /// top-level expressions.  Only given if the contract passed analysis.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deploy_cost: Option<ExecutionCost>,
+}
+
+/// The data we return on POST /v2/transactions/simulate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionSimulationResponse {
+    pub txid: String,
+    /// whether or not the transaction could be applied to the chain tip.  If not, `cause` says
+    /// why, and the remaining fields are empty.
+    pub okay: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+    /// hex-encoded Clarity value the transaction evaluated to
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    pub post_condition_aborted: bool,
+    /// emitted events, in the same form as the event observer interface
+    pub events: Vec<serde_json::Value>,
+    /// assets sent or burned by each principal
+    pub asset_map: serde_json::Value,
+    pub execution_cost: ExecutionCost,
+    pub stx_burned: String,
+    pub fee: u64,
+}
+
+/// The data we return on POST /v2/blocks/validate/<parent-consensus-hash>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockProposalResponse {
+    pub block_hash: String,
+    /// whether or not the block would be accepted as a child of the given parent.  If not,
+    /// `reason_code` and `reason` say why.
+    pub accepted: bool,
+    /// one of `UnknownParent`, `InvalidParentMicroblocks`, or `InvalidBlock`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// the anchored block's execution cost, if it was accepted
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_cost: Option<ExecutionCost>,
+}
+
+/// The data we return on POST /v2/blocks/upload/{consensus_hash}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StacksBlockAcceptedData {
+    pub stacks_block_id: StacksBlockId,
+    /// false if the block was already known, or could not be stored
+    pub accepted: bool,
+}
+
+/// How many burnchain blocks a sent block-commit is reported as in-flight on GET /v2/admin/miner
+pub const MINER_STATUS_COMMIT_WINDOW: u64 = 6;
+
+/// A VRF key the miner has registered, as reported on GET /v2/admin/miner
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMinerVRFKey {
+    pub vrf_public_key: String,
+    /// burnchain block height and vtxindex of the key's registration
+    pub block_height: u64,
+    pub op_vtxindex: u32,
+}
+
+/// A block-commit the miner has sent, as reported on GET /v2/admin/miner
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMinerBlockCommit {
+    pub txid: String,
+    pub block_hash: BlockHeaderHash,
+    pub parent_consensus_hash: ConsensusHash,
+    pub burn_fee: u64,
+    /// burnchain tip height when the commit was sent.  The commit is for the next block.
+    pub sent_at_burn_height: u64,
+    pub attempt: u64,
+    /// burnchain block the commit was mined in, if the node has processed it
+    #[serde(default)]
+    pub burn_block_height: Option<u64>,
+    #[serde(default)]
+    pub confirmations: u64,
+}
+
+/// The outcome of the last sortition the miner processed, as reported on GET /v2/admin/miner
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMinerSortitionData {
+    pub burn_block_hash: String,
+    pub burn_block_height: u64,
+    pub consensus_hash: ConsensusHash,
+    /// whether a block was chosen in this sortition at all
+    pub sortition: bool,
+    pub winning_block_txid: String,
+    pub winning_stacks_block_hash: BlockHeaderHash,
+    /// whether the winning block-commit was one of ours
+    pub won: bool,
+}
+
+/// The last anchored block the miner assembled, as reported on GET /v2/admin/miner
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMinerAssembledBlock {
+    pub block_hash: BlockHeaderHash,
+    pub parent_consensus_hash: ConsensusHash,
+    /// burnchain tip the block was assembled at
+    pub burn_block_hash: String,
+    pub num_txs: u64,
+    pub attempt: u64,
+}
+
+/// What to do on /v2/admin/scheduled_calls
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScheduledCallsOp {
+    /// GET: list all calls
+    List,
+    /// POST: schedule a call
+    Add(ScheduledCallRequest),
+    /// DELETE /v2/admin/scheduled_calls/{id}: cancel a pending call
+    Cancel(u64),
+}
+
+/// The data we return on GET /v2/admin/miner.
+/// The miner keeps this up to date as it runs; the RPC server fills in commit confirmations when
+/// it answers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMinerStatusData {
+    pub is_miner: bool,
+    pub vrf_keys: Vec<RPCMinerVRFKey>,
+    /// burnchain height at which the miner sent a VRF key registration it has not yet seen mined
+    #[serde(default)]
+    pub pending_vrf_key_registration: Option<u64>,
+    /// block-commits sent in the last `MINER_STATUS_COMMIT_WINDOW` burnchain blocks, oldest first
+    pub inflight_commits: Vec<RPCMinerBlockCommit>,
+    #[serde(default)]
+    pub last_sortition: Option<RPCMinerSortitionData>,
+    #[serde(default)]
+    pub last_assembled_block: Option<RPCMinerAssembledBlock>,
+}
+
+impl RPCMinerStatusData {
+    pub fn new(is_miner: bool) -> RPCMinerStatusData {
+        RPCMinerStatusData {
+            is_miner,
+            vrf_keys: vec![],
+            pending_vrf_key_registration: None,
+            inflight_commits: vec![],
+            last_sortition: None,
+            last_assembled_block: None,
+        }
+    }
+
+    /// Forget block-commits sent too long before the given burnchain height
+    pub fn prune_inflight_commits(&mut self, burn_block_height: u64) {
+        self.inflight_commits.retain(|commit| {
+            commit.sent_at_burn_height + MINER_STATUS_COMMIT_WINDOW >= burn_block_height
+        });
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum UnconfirmedTransactionStatus {
     Microblock {
@@ -1120,6 +1695,89 @@ pub struct AttachmentPage {
     pub inventory: Vec<u8>,
 }
 
+/// The data we return on POST /v2/mempool/gc
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolGCResponse {
+    /// number of transactions removed for having been in the mempool too long
+    pub expired: u64,
+    /// number of low-fee transactions evicted to keep the mempool under its size limit
+    pub evicted: u64,
+    /// number of transactions left in the mempool
+    pub remaining_txs: u64,
+    /// total length of the transactions left in the mempool
+    pub remaining_bytes: u64,
+}
+
+/// The body of POST /v2/dev/mine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct RPCDevMineRequest {
+    /// hex IDs of mempool transactions the block must include
+    #[serde(default)]
+    pub txids: Vec<String>,
+    /// whether the burnchain block should have a sortition (the default), or leave the miner's
+    /// block-commit out
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sortition: Option<bool>,
+}
+
+/// The data we return on POST /v2/dev/mine.  The block is mined on top of these tips.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCDevMineResponse {
+    pub burn_block_height: u64,
+    pub stacks_tip_height: u64,
+}
+
+/// A transaction the miner would select, as reported on POST /v2/mempool/simulate_block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedBlockTransaction {
+    pub txid: String,
+    pub fee: u64,
+    pub tx_len: u64,
+}
+
+/// The data we return on POST /v2/mempool/simulate_block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockSimulationResponse {
+    /// index block hash of the chain tip the block would be built on
+    pub parent_index_block_hash: String,
+    pub stacks_height: u64,
+    /// the transactions the miner would select, in the order it would mine them
+    pub txs: Vec<SimulatedBlockTransaction>,
+    pub total_fees: u64,
+    /// length of the block's header and transactions
+    pub block_size: u64,
+    pub execution_cost: ExecutionCost,
+    pub execution_budget: ExecutionCost,
+    /// largest proportion of any execution budget dimension that the block would use, in percent
+    pub percent_full: u64,
+}
+
+/// The data we return on GET /v2/attachments/inv/instances
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetAttachmentInstancesResponse {
+    pub block_id: StacksBlockId,
+    pub pages_count: u32,
+    pub pages: Vec<AttachmentInstancesPage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentInstancesPage {
+    pub index: u32,
+    pub instances: Vec<AttachmentInstanceEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentInstanceEntry {
+    pub attachment_index: u32,
+    pub content_hash: String,
+    pub contract_id: String,
+    pub block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    pub metadata: String,
+    pub is_available: bool,
+}
+
 /// Request ID to use or expect from non-Stacks HTTP clients.
 /// In particular, if a HTTP response does not contain the x-request-id header, then it's assumed
 /// to be this value.  This is needed to support fetching immutables like block and microblock data
@@ -1158,6 +1816,67 @@ pub struct CallReadOnlyRequestBody {
     pub arguments: Vec<String>,
 }
 
+/// One query in the body of a POST to /v2/batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchQueryBody {
+    Account {
+        principal: String,
+    },
+    MapEntry {
+        contract_address: String,
+        contract_name: String,
+        map_name: String,
+        key: String,
+    },
+    CallRead {
+        contract_address: String,
+        contract_name: String,
+        function_name: String,
+        sender: String,
+        arguments: Vec<String>,
+    },
+}
+
+/// A parsed /v2/batch query
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchQuery {
+    Account(PrincipalData),
+    /// contract address, contract name, map name, key
+    MapEntry(StacksAddress, ContractName, ClarityName, Value),
+    /// contract address, contract name, function name, sender, arguments
+    CallRead(
+        StacksAddress,
+        ContractName,
+        ClarityName,
+        PrincipalData,
+        Vec<Value>,
+    ),
+}
+
+/// The answer to one /v2/batch query, in the same form as its standalone endpoint's
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchQueryResult {
+    Account(AccountEntryResponse),
+    MapEntry(MapEntryResponse),
+    CallRead(CallReadOnlyResponse),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResponse {
+    /// the chain tip every query was evaluated against
+    pub tip: StacksBlockId,
+    /// one result per query, in the order they were asked
+    pub results: Vec<BatchQueryResult>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AggregationCommitRequestBody {
+    pub pox_address: String,
+    pub reward_cycle: u64,
+}
+
 /// Items in the NeighborsInfo -- combines NeighborKey and NeighborAddress
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RPCNeighbor {
@@ -1191,25 +1910,94 @@ pub struct RPCNeighborsInfo {
     pub outbound: Vec<RPCNeighbor>,
 }
 
+/// Which chain state a read-only RPC query is evaluated against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RPCStateView {
+    /// The state as of the requested (or canonical) Stacks chain tip
+    Confirmed,
+    /// The canonical chain tip's state, plus its unconfirmed microblock stream
+    Unconfirmed,
+    /// The unconfirmed state, plus the node's pending mempool transactions
+    UnconfirmedWithMempool,
+}
+
 /// All HTTP request paths we support, and the arguments they carry in their paths
 #[derive(Debug, Clone, PartialEq)]
 pub enum HttpRequestType {
     GetInfo(HttpRequestMetadata),
     GetPoxInfo(HttpRequestMetadata, Option<StacksBlockId>),
+    GetRewardSet(HttpRequestMetadata, Option<StacksBlockId>),
+    GetPoxDelegations(HttpRequestMetadata, PrincipalData, Option<StacksBlockId>),
+    SimulateAggregationCommit(
+        HttpRequestMetadata,
+        PrincipalData,
+        StacksAddress,
+        u64,
+        Option<StacksBlockId>,
+    ),
+    GetBurnBlockInfo(HttpRequestMetadata, u64),
+    GetBurnchainOps(HttpRequestMetadata, BurnchainOpsQuery),
     GetNeighbors(HttpRequestMetadata),
     GetBlock(HttpRequestMetadata, StacksBlockId),
+    GetBlockEventBloom(HttpRequestMetadata, StacksBlockId),
+    GetHeaders(HttpRequestMetadata, u64, Option<StacksBlockId>),
     GetMicroblocksIndexed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksConfirmed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksUnconfirmed(HttpRequestMetadata, StacksBlockId, u16),
     GetTransactionUnconfirmed(HttpRequestMetadata, Txid),
     PostTransaction(HttpRequestMetadata, StacksTransaction, Option<Attachment>),
     PostMicroblock(HttpRequestMetadata, StacksMicroblock, Option<StacksBlockId>),
+    /// principal, tip, height, with_proof, view.  The height picks the ancestor of the tip (or of
+    /// the canonical chain tip) to evaluate the query at.
     GetAccount(
         HttpRequestMetadata,
         PrincipalData,
         Option<StacksBlockId>,
+        Option<u64>,
+        bool,
+        RPCStateView,
+    ),
+    GetAccountAssets(HttpRequestMetadata, PrincipalData, Option<StacksBlockId>),
+    GetAccountNonces(HttpRequestMetadata, PrincipalData, Option<StacksBlockId>),
+    GetAccountSummary(HttpRequestMetadata, PrincipalData, Option<StacksBlockId>),
+    GetTransactionReceipt(HttpRequestMetadata, Txid, Option<StacksBlockId>),
+    /// principal, offset, limit, tip
+    GetPrincipalTransactions(
+        HttpRequestMetadata,
+        PrincipalData,
+        u64,
+        u64,
+        Option<StacksBlockId>,
+    ),
+    GetPendingMinerRewards(HttpRequestMetadata, StacksAddress, Option<StacksBlockId>),
+    /// get the unconfirmed microblock stream built on the chain tip, from the given sequence
+    GetUnconfirmedMicroblockStream(HttpRequestMetadata, u16, Option<StacksBlockId>),
+    MemPoolGC(HttpRequestMetadata),
+    DevMine(HttpRequestMetadata, RPCDevMineRequest),
+    PostBlock(HttpRequestMetadata, ConsensusHash, StacksBlock),
+    /// get the miner's state, authenticated with the given Authorization header value
+    GetMinerStatus(HttpRequestMetadata, Option<String>),
+    /// list, add or cancel the node's scheduled contract-calls, authenticated with the given
+    /// Authorization header value
+    ScheduledCalls(HttpRequestMetadata, ScheduledCallsOp, Option<String>),
+    SimulateBlock(HttpRequestMetadata),
+    /// run several read-only queries against one chain tip: queries, tip, with_proof, view
+    Batch(
+        HttpRequestMetadata,
+        Vec<BatchQuery>,
+        Option<StacksBlockId>,
         bool,
+        RPCStateView,
     ),
+    /// validate a proposed block against the given parent consensus hash, authenticated with the
+    /// given Authorization header value
+    ValidateBlockProposal(
+        HttpRequestMetadata,
+        ConsensusHash,
+        StacksBlock,
+        Option<String>,
+    ),
+    /// contract address, contract name, map name, key, tip, height, with_proof
     GetMapEntry(
         HttpRequestMetadata,
         StacksAddress,
@@ -1217,8 +2005,10 @@ pub enum HttpRequestType {
         ClarityName,
         Value,
         Option<StacksBlockId>,
+        Option<u64>,
         bool,
     ),
+    /// contract address, contract name, sender, function name, arguments, tip, height, view
     CallReadOnlyFunction(
         HttpRequestMetadata,
         StacksAddress,
@@ -1227,13 +2017,34 @@ pub enum HttpRequestType {
         ClarityName,
         Vec<Value>,
         Option<StacksBlockId>,
+        Option<u64>,
+        RPCStateView,
     ),
     GetTransferCost(HttpRequestMetadata),
+    EstimateTransactionFee(HttpRequestMetadata, StacksTransaction),
+    SimulateTransaction(HttpRequestMetadata, StacksTransaction),
+    /// source, contract to analyze it as, tip
+    AnalyzeContract(
+        HttpRequestMetadata,
+        String,
+        Option<QualifiedContractIdentifier>,
+        Option<StacksBlockId>,
+    ),
+    /// contract address, contract name, tip, height, with_proof
     GetContractSrc(
         HttpRequestMetadata,
         StacksAddress,
         ContractName,
         Option<StacksBlockId>,
+        Option<u64>,
+        bool,
+    ),
+    GetDataVar(
+        HttpRequestMetadata,
+        StacksAddress,
+        ContractName,
+        ClarityName,
+        Option<StacksBlockId>,
         bool,
     ),
     GetContractABI(
@@ -1245,10 +2056,38 @@ pub enum HttpRequestType {
     OptionsPreflight(HttpRequestMetadata, String),
     GetAttachment(HttpRequestMetadata, Hash160),
     GetAttachmentsInv(HttpRequestMetadata, Option<StacksBlockId>, HashSet<u32>),
+    GetAttachmentInstances(
+        HttpRequestMetadata,
+        Option<StacksBlockId>,
+        HashSet<u32>,
+        Option<QualifiedContractIdentifier>,
+        Option<String>,
+    ),
+    /// list the metadata of a StackerDB's non-empty slots
+    GetStackerDBMetadata(HttpRequestMetadata, QualifiedContractIdentifier),
+    /// get the chunk in a StackerDB slot
+    GetStackerDBChunk(HttpRequestMetadata, QualifiedContractIdentifier, u32),
+    /// write a chunk to a StackerDB slot
+    PostStackerDBChunk(
+        HttpRequestMetadata,
+        QualifiedContractIdentifier,
+        StackerDBChunkData,
+    ),
     /// catch-all for any errors we should surface from parsing
     ClientError(HttpRequestMetadata, ClientError),
 }
 
+/// A deprecated RPC endpoint or request parameter that is still served.  Responses to requests
+/// that use it carry `Deprecation` and `Link` headers pointing at its successor, and each use is
+/// counted so we know which integrators still depend on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HttpDeprecation {
+    /// the deprecated usage, e.g. "GET /v2/fees/transfer" (used as the metrics label)
+    pub usage: &'static str,
+    /// the path of the endpoint that replaces it
+    pub successor: &'static str,
+}
+
 /// The fields that Actually Matter to http responses
 #[derive(Debug, Clone, PartialEq)]
 pub struct HttpResponseMetadata {
@@ -1256,6 +2095,7 @@ pub struct HttpResponseMetadata {
     pub client_keep_alive: bool,
     pub request_id: u32,
     pub content_length: Option<u32>,
+    pub deprecation: Option<HttpDeprecation>,
 }
 
 impl HttpResponseMetadata {
@@ -1279,6 +2119,7 @@ impl HttpResponseMetadata {
             client_keep_alive: client_keep_alive,
             request_id: request_id,
             content_length: content_length,
+            deprecation: None,
         }
     }
 
@@ -1291,6 +2132,7 @@ impl HttpResponseMetadata {
             client_keep_alive: preamble.keep_alive,
             request_id: preamble.request_id,
             content_length: preamble.content_length.clone(),
+            deprecation: None,
         }
     }
 
@@ -1300,6 +2142,7 @@ impl HttpResponseMetadata {
             client_keep_alive: false,
             request_id: HttpResponseMetadata::make_request_id(),
             content_length: Some(0),
+            deprecation: None,
         }
     }
 }
@@ -1307,12 +2150,14 @@ impl HttpResponseMetadata {
 impl From<&HttpRequestType> for HttpResponseMetadata {
     fn from(req: &HttpRequestType) -> HttpResponseMetadata {
         let metadata = req.metadata();
-        HttpResponseMetadata::new(
+        let mut md = HttpResponseMetadata::new(
             metadata.version,
             HttpResponseMetadata::make_request_id(),
             None,
             metadata.keep_alive,
-        )
+        );
+        md.deprecation = req.deprecation();
+        md
     }
 }
 
@@ -1321,22 +2166,54 @@ impl From<&HttpRequestType> for HttpResponseMetadata {
 pub enum HttpResponseType {
     PeerInfo(HttpResponseMetadata, RPCPeerInfoData),
     PoxInfo(HttpResponseMetadata, RPCPoxInfoData),
+    RewardSet(HttpResponseMetadata, RPCRewardSetData),
+    PoxDelegations(HttpResponseMetadata, RPCPoxDelegationsData),
+    AggregationCommitSimulation(HttpResponseMetadata, RPCAggregationCommitData),
+    BurnBlockInfo(HttpResponseMetadata, RPCBurnBlockInfoData),
+    BurnchainOps(HttpResponseMetadata, RPCBurnchainOpsData),
+    BlockEventBloom(HttpResponseMetadata, RPCBlockEventBloomData),
+    Headers(HttpResponseMetadata, Vec<ExtendedStacksHeader>),
     Neighbors(HttpResponseMetadata, RPCNeighborsInfo),
     Block(HttpResponseMetadata, StacksBlock),
     BlockStream(HttpResponseMetadata),
     Microblocks(HttpResponseMetadata, Vec<StacksMicroblock>),
     MicroblockStream(HttpResponseMetadata),
+    JsonStream(HttpResponseMetadata),
     TransactionID(HttpResponseMetadata, Txid),
     MicroblockHash(HttpResponseMetadata, BlockHeaderHash),
     TokenTransferCost(HttpResponseMetadata, u64),
+    TransactionFeeEstimate(HttpResponseMetadata, TransactionFeeEstimateResponse),
+    TransactionSimulation(HttpResponseMetadata, TransactionSimulationResponse),
+    ContractAnalysis(HttpResponseMetadata, ContractAnalysisResponse),
     GetMapEntry(HttpResponseMetadata, MapEntryResponse),
     CallReadOnlyFunction(HttpResponseMetadata, CallReadOnlyResponse),
     GetAccount(HttpResponseMetadata, AccountEntryResponse),
+    GetAccountAssets(HttpResponseMetadata, AccountAssetsResponse),
+    GetAccountNonces(HttpResponseMetadata, AccountNoncesResponse),
+    GetAccountSummary(HttpResponseMetadata, AccountSummaryResponse),
+    TransactionReceipt(HttpResponseMetadata, TransactionReceiptResponse),
+    PrincipalTransactions(HttpResponseMetadata, PrincipalTransactionsResponse),
+    PendingMinerRewards(HttpResponseMetadata, PendingMinerRewardsResponse),
+    UnconfirmedMicroblockStream(HttpResponseMetadata, UnconfirmedMicroblockStreamResponse),
+    MemPoolGC(HttpResponseMetadata, MemPoolGCResponse),
+    DevMine(HttpResponseMetadata, RPCDevMineResponse),
+    MinerStatus(HttpResponseMetadata, RPCMinerStatusData),
+    ScheduledCalls(HttpResponseMetadata, Vec<ScheduledCall>),
+    ScheduledCall(HttpResponseMetadata, ScheduledCall),
+    StacksBlockAccepted(HttpResponseMetadata, StacksBlockAcceptedData),
+    BlockSimulation(HttpResponseMetadata, BlockSimulationResponse),
+    Batch(HttpResponseMetadata, BatchResponse),
+    BlockProposal(HttpResponseMetadata, BlockProposalResponse),
     GetContractABI(HttpResponseMetadata, ContractInterface),
     GetContractSrc(HttpResponseMetadata, ContractSrcResponse),
+    GetDataVar(HttpResponseMetadata, DataVarResponse),
     UnconfirmedTransaction(HttpResponseMetadata, UnconfirmedTransactionResponse),
     GetAttachment(HttpResponseMetadata, GetAttachmentResponse),
     GetAttachmentsInv(HttpResponseMetadata, GetAttachmentsInvResponse),
+    GetAttachmentInstances(HttpResponseMetadata, GetAttachmentInstancesResponse),
+    StackerDBMetadata(HttpResponseMetadata, Vec<SlotMetadata>),
+    StackerDBChunk(HttpResponseMetadata, StackerDBChunkData),
+    StackerDBChunkAck(HttpResponseMetadata, StackerDBChunkAckData),
     OptionsPreflight(HttpResponseMetadata),
     // peer-given error responses
     BadRequest(HttpResponseMetadata, String),
@@ -1345,6 +2222,7 @@ pub enum HttpResponseType {
     PaymentRequired(HttpResponseMetadata, String),
     Forbidden(HttpResponseMetadata, String),
     NotFound(HttpResponseMetadata, String),
+    TooManyRequests(HttpResponseMetadata, String),
     ServerError(HttpResponseMetadata, String),
     ServiceUnavailable(HttpResponseMetadata, String),
     Error(HttpResponseMetadata, u16, String),
@@ -1378,6 +2256,7 @@ pub enum StacksMessageID {
     Pong = 16,
     NatPunchRequest = 17,
     NatPunchReply = 18,
+    StackerDBPushChunk = 19,
     Reserved = 255,
 }
 
@@ -1644,6 +2523,13 @@ pub const NUM_NEIGHBORS: usize = 32;
 // maximum number of unconfirmed microblocks can get streamed to us
 pub const MAX_MICROBLOCKS_UNCONFIRMED: usize = 1024;
 
+// maximum number of headers served by GET /v2/headers
+pub const MAX_HEADERS: u64 = 2100;
+
+// default and maximum number of transactions served by one GET /v2/addresses/:principal/transactions
+pub const DEFAULT_PRINCIPAL_TRANSACTIONS_LIMIT: u64 = 50;
+pub const MAX_PRINCIPAL_TRANSACTIONS_LIMIT: u64 = 200;
+
 // how long a peer will be denied for if it misbehaves
 #[cfg(test)]
 pub const DENY_BAN_DURATION: u64 = 30; // seconds
@@ -1658,12 +2544,17 @@ pub struct NetworkResult {
     pub unhandled_messages: HashMap<NeighborKey, Vec<StacksMessage>>,
     pub blocks: Vec<(ConsensusHash, StacksBlock, u64)>, // blocks we downloaded, and time taken
     pub confirmed_microblocks: Vec<(ConsensusHash, Vec<StacksMicroblock>, u64)>, // confiremd microblocks we downloaded, and time taken
+    pub headers: Vec<(ConsensusHash, StacksBlockHeader, u64)>, // headers we downloaded to the header chain (header-only mode), and time taken
     pub pushed_transactions: HashMap<NeighborKey, Vec<(Vec<RelayData>, StacksTransaction)>>, // all transactions pushed to us and their message relay hints
     pub pushed_blocks: HashMap<NeighborKey, Vec<BlocksData>>, // all blocks pushed to us
     pub pushed_microblocks: HashMap<NeighborKey, Vec<(Vec<RelayData>, MicroblocksData)>>, // all microblocks pushed to us, and the relay hints from the message
     pub uploaded_transactions: Vec<StacksTransaction>, // transactions sent to us by the http server
+    pub uploaded_blocks: Vec<BlocksData>,              // blocks sent to us by the http server
     pub uploaded_microblocks: Vec<MicroblocksData>,    // microblocks sent to us by the http server
     pub uploaded_attachments: Vec<Attachment>,         // attachments sent to us by the http server
+    pub pushed_stackerdb_chunks:
+        HashMap<NeighborKey, Vec<(Vec<RelayData>, StackerDBPushChunkData)>>, // all StackerDB chunks pushed to us, and their message relay hints
+    pub uploaded_stackerdb_chunks: Vec<StackerDBPushChunkData>, // StackerDB chunks sent to us by the http server
     pub attachments: Vec<AttachmentInstance>,
     pub num_state_machine_passes: u64,
     pub num_inv_sync_passes: u64,
@@ -1676,12 +2567,16 @@ impl NetworkResult {
             download_pox_id: None,
             blocks: vec![],
             confirmed_microblocks: vec![],
+            headers: vec![],
             pushed_transactions: HashMap::new(),
             pushed_blocks: HashMap::new(),
             pushed_microblocks: HashMap::new(),
             uploaded_transactions: vec![],
+            uploaded_blocks: vec![],
             uploaded_microblocks: vec![],
             uploaded_attachments: vec![],
+            pushed_stackerdb_chunks: HashMap::new(),
+            uploaded_stackerdb_chunks: vec![],
             attachments: vec![],
             num_state_machine_passes: num_state_machine_passes,
             num_inv_sync_passes: num_inv_sync_passes,
@@ -1689,7 +2584,11 @@ impl NetworkResult {
     }
 
     pub fn has_blocks(&self) -> bool {
-        self.blocks.len() > 0 || self.pushed_blocks.len() > 0
+        self.blocks.len() > 0 || self.pushed_blocks.len() > 0 || self.uploaded_blocks.len() > 0
+    }
+
+    pub fn has_headers(&self) -> bool {
+        self.headers.len() > 0
     }
 
     pub fn has_microblocks(&self) -> bool {
@@ -1754,6 +2653,16 @@ impl NetworkResult {
                                 .insert(neighbor_key.clone(), vec![(message.relayers, tx_data)]);
                         }
                     }
+                    StacksMessageType::StackerDBPushChunk(chunk_data) => {
+                        if let Some(chunk_msgs) =
+                            self.pushed_stackerdb_chunks.get_mut(&neighbor_key)
+                        {
+                            chunk_msgs.push((message.relayers, chunk_data));
+                        } else {
+                            self.pushed_stackerdb_chunks
+                                .insert(neighbor_key.clone(), vec![(message.relayers, chunk_data)]);
+                        }
+                    }
                     _ => {
                         // forward along
                         if let Some(messages) = self.unhandled_messages.get_mut(&neighbor_key) {
@@ -1774,9 +2683,15 @@ impl NetworkResult {
                 StacksMessageType::Transaction(tx_data) => {
                     self.uploaded_transactions.push(tx_data);
                 }
+                StacksMessageType::Blocks(block_data) => {
+                    self.uploaded_blocks.push(block_data);
+                }
                 StacksMessageType::Microblocks(mblock_data) => {
                     self.uploaded_microblocks.push(mblock_data);
                 }
+                StacksMessageType::StackerDBPushChunk(chunk_data) => {
+                    self.uploaded_stackerdb_chunks.push(chunk_data);
+                }
                 _ => {
                     // drop
                     warn!("Dropping unknown HTTP message");
@@ -1806,6 +2721,7 @@ pub mod test {
     use net::poll::*;
     use net::relay::*;
     use net::rpc::RPCHandlerArgs;
+    use net::stackerdb::*;
     use net::Error as net_error;
 
     use core::NETWORK_P2P_PORT;
@@ -1854,7 +2770,6 @@ pub mod test {
     use std::net::*;
     use std::ops::Deref;
     use std::ops::DerefMut;
-    use std::sync::mpsc::sync_channel;
     use std::thread;
 
     use std::fs;
@@ -2084,6 +2999,7 @@ pub mod test {
         pub test_name: String,
         pub initial_balances: Vec<(PrincipalData, u64)>,
         pub initial_lockups: Vec<ChainstateAccountLockup>,
+        pub initial_locks: Vec<(PrincipalData, u64, u64)>,
         pub spending_account: TestMiner,
         pub setup_code: String,
     }
@@ -2129,6 +3045,7 @@ pub mod test {
                 test_name: "".into(),
                 initial_balances: vec![],
                 initial_lockups: vec![],
+                initial_locks: vec![],
                 spending_account: spending_account,
                 setup_code: "".into(),
             }
@@ -2287,6 +3204,9 @@ pub mod test {
             let atlasdb_path = format!("{}/atlas.db", &test_path);
             let atlasdb = AtlasDB::connect(&atlasdb_path, true).unwrap();
 
+            let stackerdbs_path = format!("{}/stackerdb.sqlite", &test_path);
+            let stackerdbs = StackerDBs::connect(&stackerdbs_path, true).unwrap();
+
             let conf = config.clone();
             let post_flight_callback = move |clarity_tx: &mut ClarityTx| {
                 if conf.setup_code.len() > 0 {
@@ -2349,6 +3269,7 @@ pub mod test {
                 config.initial_balances.clone(),
                 Some(Box::new(post_flight_callback)),
             );
+            boot_data.initial_locks = config.initial_locks.clone();
 
             if !config.initial_lockups.is_empty() {
                 let lockups = config.initial_lockups.clone();
@@ -2365,9 +3286,8 @@ pub mod test {
             )
             .unwrap();
 
-            let (tx, _) = sync_channel(100000);
             let mut coord =
-                ChainsCoordinator::test_new(&burnchain, &test_path, OnChainRewardSetProvider(), tx);
+                ChainsCoordinator::test_new(&burnchain, &test_path, OnChainRewardSetProvider());
             coord.handle_new_burnchain_block().unwrap();
 
             let mut stacks_node = TestStacksNode::from_chainstate(chainstate);
@@ -2430,6 +3350,7 @@ pub mod test {
             let mut peer_network = PeerNetwork::new(
                 peerdb,
                 atlasdb,
+                stackerdbs,
                 local_peer,
                 config.peer_version,
                 config.burnchain.clone(),
@@ -1728,7 +1728,7 @@ impl PeerNetwork {
         nk: &NeighborKey,
     ) -> Result<ReplyHandleP2P, net_error> {
         // send handshake.
-        let handshake_data = HandshakeData::from_local_peer(&self.local_peer);
+        let handshake_data = HandshakeData::from_local_peer_for(&self.local_peer, &nk.addrbytes);
 
         debug!("{:?}: send Handshake to {:?}", &self.local_peer, &nk);
 
@@ -2567,7 +2567,8 @@ impl PeerNetwork {
                     nk
                 );
 
-                let handshake_data = HandshakeData::from_local_peer(&walk.local_peer);
+                let handshake_data =
+                    HandshakeData::from_local_peer_for(&walk.local_peer, &nk.addrbytes);
                 let msg =
                     network.sign_for_peer(nk, StacksMessageType::Handshake(handshake_data))?;
                 let req_res = network.send_message(nk, msg, network.connection_opts.timeout);
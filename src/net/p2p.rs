@@ -19,6 +19,7 @@ use std::mem;
 use net::asn::ASEntry4;
 use net::atlas::AtlasDB;
 use net::db::PeerDB;
+use net::stackerdb::{StackerDBConfig, StackerDBs};
 use net::Error as net_error;
 use net::Neighbor;
 use net::NeighborKey;
@@ -81,9 +82,11 @@ use burnchains::PublicKey;
 
 use chainstate::burn::db::sortdb::{BlockHeaderCache, PoxId, SortitionDB, SortitionId};
 
+use chainstate::stacks::db::header_chain::HeaderChainDB;
 use chainstate::stacks::db::StacksChainState;
 
 use chainstate::stacks::{StacksBlockHeader, MAX_BLOCK_LEN, MAX_TRANSACTION_LEN};
+use vm::types::QualifiedContractIdentifier;
 
 use util::get_epoch_time_secs;
 use util::log;
@@ -98,6 +101,8 @@ use net::inv::*;
 use net::relay::*;
 use net::rpc::RPCHandlerArgs;
 
+use monitoring;
+
 /// inter-thread request to send a p2p message from another thread in this program.
 #[derive(Debug)]
 pub enum NetworkRequest {
@@ -217,6 +222,7 @@ pub struct PeerNetwork {
 
     pub peerdb: PeerDB,
     pub atlasdb: AtlasDB,
+    pub stackerdbs: StackerDBs,
 
     // ongoing p2p conversations (either they reached out to us, or we to them)
     pub peers: PeerMap,
@@ -274,6 +280,10 @@ pub struct PeerNetwork {
     // peer attachment downloader
     pub attachments_downloader: Option<AttachmentsDownloader>,
 
+    // header chain of a header-only node.  If set, the block downloader only fetches headers,
+    // and stores them here.
+    pub header_chain: Option<HeaderChainDB>,
+
     // do we need to do a prune at the end of the work state cycle?
     pub do_prune: bool,
 
@@ -319,6 +329,11 @@ pub struct PeerNetwork {
     // can't process yet, but might be able to process on the next chain view update
     pub pending_messages: HashMap<usize, Vec<StacksMessage>>,
 
+    // connections we opened to our relay neighbors, and when we opened them
+    relay_neighbor_connects: HashMap<NeighborKey, (usize, u64)>,
+    // relay neighbor connections we've sent a handshake on
+    relay_neighbor_handshakes: HashSet<usize>,
+
     // fault injection -- force disconnects
     fault_last_disconnect: u64,
 }
@@ -327,6 +342,7 @@ impl PeerNetwork {
     pub fn new(
         peerdb: PeerDB,
         atlasdb: AtlasDB,
+        stackerdbs: StackerDBs,
         mut local_peer: LocalPeer,
         peer_version: u32,
         burnchain: Burnchain,
@@ -343,6 +359,7 @@ impl PeerNetwork {
         let pub_ip = connection_opts.public_ip_address.clone();
         let pub_ip_learned = pub_ip.is_none();
         local_peer.public_ip_address = pub_ip.clone();
+        local_peer.alt_data_urls = connection_opts.alt_data_urls.clone();
 
         if connection_opts.disable_inbound_handshakes {
             debug!("{:?}: disable inbound handshakes", &local_peer);
@@ -358,6 +375,7 @@ impl PeerNetwork {
 
             peerdb: peerdb,
             atlasdb: atlasdb,
+            stackerdbs: stackerdbs,
 
             peers: PeerMap::new(),
             sockets: HashMap::new(),
@@ -395,6 +413,7 @@ impl PeerNetwork {
 
             block_downloader: None,
             attachments_downloader: None,
+            header_chain: None,
 
             do_prune: false,
 
@@ -427,6 +446,9 @@ impl PeerNetwork {
 
             pending_messages: HashMap::new(),
 
+            relay_neighbor_connects: HashMap::new(),
+            relay_neighbor_handshakes: HashSet::new(),
+
             fault_last_disconnect: 0,
         }
     }
@@ -938,6 +960,25 @@ impl PeerNetwork {
         );
 
         outbound_sample.append(&mut inbound_sample);
+
+        // always send to our relay neighbors, unless they already saw this message
+        for nk in self.connection_opts.relay_neighbors.iter() {
+            if let Some(convo) = self.get_convo(nk) {
+                if !convo.is_authenticated() {
+                    continue;
+                }
+                if let Some(pubkey) = convo.ref_public_key() {
+                    if relay_pubkhs.contains(&Hash160::from_node_public_key(pubkey)) {
+                        continue;
+                    }
+                }
+                let nk = convo.to_neighbor_key();
+                if !outbound_sample.contains(&nk) {
+                    outbound_sample.push(nk);
+                }
+            }
+        }
+
         let ret = self.coalesce_neighbors(outbound_sample);
 
         debug!("All recipients (out of {}): {:?}", ret.len(), &ret);
@@ -1004,6 +1045,9 @@ impl PeerNetwork {
                     StacksMessageType::Transaction(ref data) => {
                         self.sample_broadcast_peers(&relay_hints, data)
                     }
+                    StacksMessageType::StackerDBPushChunk(ref data) => {
+                        self.sample_broadcast_peers(&relay_hints, data)
+                    }
                     _ => {
                         // not suitable for broadcast
                         return Err(net_error::InvalidMessage);
@@ -1935,6 +1979,13 @@ impl PeerNetwork {
             }
         }
 
+        // don't prune relay neighbors
+        for nk in self.connection_opts.relay_neighbors.iter() {
+            if let Some(event_id) = self.get_event_id(nk) {
+                safe.insert(event_id);
+            }
+        }
+
         // if we're in the middle of a peer walk, then don't prune any outbound connections it established
         // (yet)
         match self.walk {
@@ -1949,6 +2000,84 @@ impl PeerNetwork {
         self.prune_frontier(&safe);
     }
 
+    /// Keep outbound connections open to each of our relay neighbors, regardless of whether or not
+    /// the neighbor walk would pick them.  Connect to any relay neighbor we're not connected to
+    /// (at most once per connect_timeout), and handshake with it once the socket is registered.
+    fn maintain_relay_neighbors(&mut self) {
+        let now = get_epoch_time_secs();
+        let relay_neighbors = self.connection_opts.relay_neighbors.clone();
+        for nk in relay_neighbors.iter() {
+            if let Some(event_id) = self.get_event_id(nk) {
+                self.relay_neighbor_connects.remove(nk);
+
+                let need_handshake = match self.peers.get(&event_id) {
+                    Some(convo) => {
+                        !convo.is_authenticated()
+                            && !self.relay_neighbor_handshakes.contains(&event_id)
+                    }
+                    None => false,
+                };
+                if !need_handshake {
+                    continue;
+                }
+
+                debug!(
+                    "{:?}: send Handshake to relay neighbor {:?}",
+                    &self.local_peer, nk
+                );
+                let handshake_data =
+                    HandshakeData::from_local_peer_for(&self.local_peer, &nk.addrbytes);
+                match self
+                    .sign_for_peer(nk, StacksMessageType::Handshake(handshake_data))
+                    .and_then(|msg| self.relay_signed_message(nk, msg))
+                {
+                    Ok(_) => {
+                        self.relay_neighbor_handshakes.insert(event_id);
+                    }
+                    Err(e) => {
+                        debug!(
+                            "{:?}: Failed to handshake with relay neighbor {:?}: {:?}",
+                            &self.local_peer, nk, &e
+                        );
+                    }
+                }
+                continue;
+            }
+
+            if let Some((event_id, connect_time)) = self.relay_neighbor_connects.get(nk) {
+                if self.is_connecting(*event_id)
+                    || connect_time + self.connection_opts.connect_timeout > now
+                {
+                    // still connecting, or tried too recently
+                    continue;
+                }
+            }
+
+            match self.connect_peer(nk) {
+                Ok(event_id) => {
+                    debug!(
+                        "{:?}: connecting to relay neighbor {:?} (event {})",
+                        &self.local_peer, nk, event_id
+                    );
+                    self.relay_neighbor_connects
+                        .insert(nk.clone(), (event_id, now));
+                }
+                Err(e) => {
+                    info!(
+                        "{:?}: could not connect to relay neighbor {:?}: {:?}",
+                        &self.local_peer, nk, &e
+                    );
+                    self.relay_neighbor_connects.insert(nk.clone(), (0, now));
+                }
+            }
+        }
+
+        // forget handshakes on connections that have since closed
+        let peers = &self.peers;
+        self.relay_neighbor_handshakes
+            .retain(|event_id| peers.contains_key(event_id));
+    }
+
     /// Regenerate our session private key and re-handshake with everyone.
     fn rekey(&mut self, old_local_peer_opt: Option<&LocalPeer>) -> () {
         assert!(old_local_peer_opt.is_some());
@@ -1958,7 +2087,8 @@ impl PeerNetwork {
         let mut msgs = HashMap::new();
         for (event_id, convo) in self.peers.iter_mut() {
             let nk = convo.to_neighbor_key();
-            let handshake_data = HandshakeData::from_local_peer(&self.local_peer);
+            let handshake_data =
+                HandshakeData::from_local_peer_for(&self.local_peer, &nk.addrbytes);
             let handshake = StacksMessageType::Handshake(handshake_data);
 
             debug!(
@@ -2406,6 +2536,7 @@ impl PeerNetwork {
             old_pox_id,
             mut blocks,
             mut microblocks,
+            mut headers,
             mut broken_http_peers,
             mut broken_p2p_peers,
         ) = self.download_blocks(sortdb, chainstate, dns_client)?;
@@ -2415,6 +2546,7 @@ impl PeerNetwork {
         network_result
             .confirmed_microblocks
             .append(&mut microblocks);
+        network_result.headers.append(&mut headers);
 
         if cfg!(test) {
             let mut block_set = HashSet::new();
@@ -3916,10 +4048,12 @@ impl PeerNetwork {
         }
     }
 
-    /// Get the local peer from the peer DB, but also preserve the public IP address
+    /// Get the local peer from the peer DB, but also preserve the public IP address and
+    /// alternative data URLs
     pub fn load_local_peer(&self) -> Result<LocalPeer, net_error> {
         let mut lp = PeerDB::get_local_peer(&self.peerdb.conn())?;
         lp.public_ip_address = self.local_peer.public_ip_address.clone();
+        lp.alt_data_urls = self.local_peer.alt_data_urls.clone();
         Ok(lp)
     }
 
@@ -4049,6 +4183,9 @@ impl PeerNetwork {
         // In parallel, do a neighbor walk
         self.do_network_neighbor_walk()?;
 
+        // stay connected to our relay neighbors, even if the walk and pruner didn't keep them
+        self.maintain_relay_neighbors();
+
         // remove timed-out requests from other threads
         for (_, convo) in self.peers.iter_mut() {
             convo.clear_timeouts();
@@ -4188,6 +4325,103 @@ impl PeerNetwork {
         Ok(())
     }
 
+    /// Store the StackerDB chunks our neighbors pushed to us, and broadcast the ones we accepted
+    /// along with the ones uploaded to us over HTTP (which the RPC handler already stored).
+    /// Chunks for StackerDBs we don't replicate are dropped.
+    fn process_stackerdb_chunks(
+        &mut self,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        network_result: &NetworkResult,
+    ) -> Result<(), net_error> {
+        if network_result.pushed_stackerdb_chunks.len() == 0
+            && network_result.uploaded_stackerdb_chunks.len() == 0
+        {
+            return Ok(());
+        }
+
+        let (canonical_consensus_hash, canonical_block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+        let tip = StacksBlockHeader::make_index_block_hash(
+            &canonical_consensus_hash,
+            &canonical_block_hash,
+        );
+
+        let mut configs: HashMap<QualifiedContractIdentifier, Option<StackerDBConfig>> =
+            HashMap::new();
+        let mut to_broadcast = vec![];
+
+        for (nk, chunks) in network_result.pushed_stackerdb_chunks.iter() {
+            for (relayers, chunk) in chunks.iter() {
+                if !self
+                    .connection_opts
+                    .stackerdb_contracts
+                    .contains(&chunk.contract_id)
+                {
+                    debug!(
+                        "{:?}: Drop chunk for StackerDB {} from {:?}: not replicated",
+                        &self.local_peer, &chunk.contract_id, nk
+                    );
+                    continue;
+                }
+
+                if !configs.contains_key(&chunk.contract_id) {
+                    let config = match StackerDBConfig::from_contract(
+                        chainstate,
+                        sortdb,
+                        &tip,
+                        &chunk.contract_id,
+                    ) {
+                        Ok(config) => Some(config),
+                        Err(e) => {
+                            warn!(
+                                "{:?}: Failed to load config for StackerDB {}: {:?}",
+                                &self.local_peer, &chunk.contract_id, &e
+                            );
+                            None
+                        }
+                    };
+                    configs.insert(chunk.contract_id.clone(), config);
+                }
+                let config = match configs.get(&chunk.contract_id) {
+                    Some(Some(config)) => config,
+                    _ => {
+                        continue;
+                    }
+                };
+
+                match self
+                    .stackerdbs
+                    .try_put_chunk(&chunk.contract_id, config, &chunk.chunk_data)
+                {
+                    Ok(()) => {
+                        to_broadcast.push((relayers.clone(), chunk.clone()));
+                    }
+                    Err(e) => {
+                        debug!(
+                            "{:?}: Did not store chunk for StackerDB {} from {:?}: {:?}",
+                            &self.local_peer, &chunk.contract_id, nk, &e
+                        );
+                    }
+                }
+            }
+        }
+
+        for chunk in network_result.uploaded_stackerdb_chunks.iter() {
+            to_broadcast.push((vec![], chunk.clone()));
+        }
+
+        for (relayers, chunk) in to_broadcast.into_iter() {
+            let neighbors = self.sample_broadcast_peers(&relayers, &chunk)?;
+            self.broadcast_message(
+                neighbors,
+                relayers,
+                StacksMessageType::StackerDBPushChunk(chunk),
+            );
+        }
+        Ok(())
+    }
+
     /// Top-level main-loop circuit to take.
     /// -- polls the peer network and http network server sockets to get new sockets and detect ready sockets
     /// -- carries out network conversations
@@ -4251,6 +4485,7 @@ impl PeerNetwork {
                 sortdb,
                 &network.peerdb,
                 &mut network.atlasdb,
+                &mut network.stackerdbs,
                 chainstate,
                 mempool,
                 http_poll_state,
@@ -4269,12 +4504,22 @@ impl PeerNetwork {
             p2p_poll_state,
         )?;
 
+        let num_outbound = PeerNetwork::count_outbound_conversations(&self.peers);
+        monitoring::update_p2p_peers_gauges(
+            (self.peers.len() as u64).saturating_sub(num_outbound),
+            num_outbound,
+        );
+
         if let Err(e) =
             PeerNetwork::store_transactions(mempool, chainstate, sortdb, &mut network_result)
         {
             warn!("Failed to store transactions: {:?}", &e);
         }
 
+        if let Err(e) = self.process_stackerdb_chunks(sortdb, chainstate, &network_result) {
+            warn!("Failed to process StackerDB chunks: {:?}", &e);
+        }
+
         if let Err(e) = PeerNetwork::setup_unconfirmed_state(chainstate, sortdb) {
             warn!("Failed to instantiate unconfirmed state: {:?}", &e);
         }
@@ -4292,6 +4537,7 @@ mod test {
     use burnchains::*;
     use net::codec::*;
     use net::db::*;
+    use net::test::*;
     use net::*;
     use std::thread;
     use std::time;
@@ -4383,11 +4629,13 @@ mod test {
         .unwrap();
 
         let atlasdb = AtlasDB::connect_memory().unwrap();
+        let stackerdbs = StackerDBs::connect_memory().unwrap();
 
         let local_peer = PeerDB::get_local_peer(db.conn()).unwrap();
         let p2p = PeerNetwork::new(
             db,
             atlasdb,
+            stackerdbs,
             local_peer,
             0x12345678,
             burnchain,
@@ -4650,4 +4898,66 @@ mod test {
             test_debug!("fake endpoint thread joined");
         })
     }
+
+    #[test]
+    #[ignore]
+    fn test_relay_neighbor_connect_without_walk() {
+        with_timeout(600, || {
+            let mut peer_1_config = TestPeerConfig::from_port(31900);
+            let peer_2_config = TestPeerConfig::from_port(31902);
+
+            // peer 1 never walks, and doesn't know about peer 2 in its frontier -- but peer 2 is
+            // its relay neighbor, so it connects to it anyway.
+            peer_1_config.connection_opts.disable_neighbor_walk = true;
+            peer_1_config.connection_opts.relay_neighbors = vec![peer_2_config.to_neighbor().addr];
+
+            let mut peer_1 = TestPeer::new(peer_1_config);
+            let mut peer_2 = TestPeer::new(peer_2_config);
+
+            let neighbor_2 = peer_2.to_neighbor();
+
+            let mut i = 0;
+            loop {
+                let _ = peer_1.step();
+                let _ = peer_2.step();
+
+                let authenticated = match peer_1.network.get_convo(&neighbor_2.addr) {
+                    Some(convo) => convo.is_authenticated() && convo.is_outbound(),
+                    None => false,
+                };
+                if authenticated {
+                    break;
+                }
+
+                i += 1;
+                assert!(i < 1000, "peer 1 never handshaked with its relay neighbor");
+            }
+
+            debug!("Connected to relay neighbor in {} step(s)", i);
+
+            // peer 1 never walked to peer 2
+            assert_eq!(peer_1.network.walk_total_step_count, 0);
+
+            // the relay neighbor is always a broadcast recipient
+            let tx = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                TransactionAuth::from_p2pkh(&StacksPrivateKey::new()).unwrap(),
+                TransactionPayload::TokenTransfer(
+                    PrincipalData::from(StacksAddress {
+                        version: 1,
+                        bytes: Hash160([0x11; 20]),
+                    }),
+                    123,
+                    TokenTransferMemo([0u8; 34]),
+                    None,
+                ),
+            );
+            let recipients = peer_1.network.sample_broadcast_peers(&vec![], &tx).unwrap();
+            assert!(recipients.contains(&neighbor_2.addr));
+
+            // peer 1 won't prune its relay neighbor
+            peer_1.network.prune_connections();
+            assert!(peer_1.network.get_convo(&neighbor_2.addr).is_some());
+        })
+    }
 }
@@ -60,6 +60,8 @@ use rand::Rng;
 
 use vm::costs::ExecutionCost;
 
+use monitoring;
+
 pub type BlocksAvailableMap = HashMap<BurnchainHeaderHash, (u64, ConsensusHash)>;
 
 pub const MAX_RELAYER_STATS: usize = 4096;
@@ -90,6 +92,8 @@ pub struct RelayerStats {
 
 pub struct ProcessedNetReceipts {
     pub mempool_txs_added: Vec<StacksTransaction>,
+    /// unconfirmed microblocks that were stored, by the index block hash of their parent
+    pub microblocks_added: Vec<MicroblocksData>,
 }
 
 /// Private trait for keeping track of messages that can be relayed, so we can identify the peers
@@ -979,6 +983,13 @@ impl Relayer {
             }
         }
 
+        // blocks uploaded to us will have already been stored, so just announce them
+        for uploaded_block in network_result.uploaded_blocks.iter() {
+            for (consensus_hash, _) in uploaded_block.blocks.iter() {
+                new_blocks.insert(consensus_hash.clone());
+            }
+        }
+
         let (new_microblocks, mut new_bad_neighbors) =
             Relayer::preprocess_pushed_microblocks(network_result, chainstate)?;
         bad_neighbors.append(&mut new_bad_neighbors);
@@ -1054,16 +1065,10 @@ impl Relayer {
         }
 
         // garbage-collect
-        if chain_height > MEMPOOL_MAX_TRANSACTION_AGE {
-            let min_height = chain_height - MEMPOOL_MAX_TRANSACTION_AGE;
-            let mut mempool_tx = mempool.tx_begin()?;
-
-            debug!(
-                "Remove all transactions beneath block height {}",
-                min_height
-            );
-            MemPoolDB::garbage_collect(&mut mempool_tx, min_height)?;
-            mempool_tx.commit()?;
+        let gc_result = mempool.prune_if_due(chain_height)?;
+        if ret.len() > 0 || gc_result.is_some() {
+            let (num_txs, num_bytes) = MemPoolDB::get_total_size(mempool.conn())?;
+            monitoring::update_mempool_size_gauges(num_txs, num_bytes);
         }
 
         Ok(ret)
@@ -1121,6 +1126,7 @@ impl Relayer {
         mempool: &mut MemPoolDB,
         coord_comms: Option<&CoordinatorChannels>,
     ) -> Result<ProcessedNetReceipts, net_error> {
+        let mut microblocks_added = vec![];
         match Relayer::process_new_blocks(network_result, sortdb, chainstate, coord_comms) {
             Ok((new_blocks, new_confirmed_microblocks, new_microblocks, bad_block_neighbors)) => {
                 // attempt to relay messages (note that this is all best-effort).
@@ -1173,6 +1179,7 @@ impl Relayer {
                             mblocks_msg.microblocks.len(),
                             &mblocks_msg.index_anchor_block
                         );
+                        microblocks_added.push(mblocks_msg.clone());
                         let msg = StacksMessageType::Microblocks(mblocks_msg);
                         if let Err(e) = self.p2p.broadcast_message(relayers, msg) {
                             warn!("Failed to broadcast microblock: {:?}", &e);
@@ -1211,7 +1218,10 @@ impl Relayer {
             }
         }
 
-        let receipts = ProcessedNetReceipts { mempool_txs_added };
+        let receipts = ProcessedNetReceipts {
+            mempool_txs_added,
+            microblocks_added,
+        };
 
         Ok(receipts)
     }
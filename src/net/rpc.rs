@@ -17,13 +17,16 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+use std::cmp;
 use std::fmt;
 use std::io;
 use std::io::prelude::*;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::net::SocketAddr;
+use std::time::Instant;
 
 use core::mempool::*;
+use core::scheduler::ScheduledCallDB;
 use net::atlas::{AtlasDB, Attachment, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST};
 use net::connection::ConnectionHttp;
 use net::connection::ConnectionOptions;
@@ -32,6 +35,15 @@ use net::db::PeerDB;
 use net::http::*;
 use net::p2p::PeerMap;
 use net::p2p::PeerNetwork;
+use net::relay::Relayer;
+use net::stackerdb::{
+    StackerDBChunkAckData, StackerDBChunkData, StackerDBConfig, StackerDBPushChunkData, StackerDBs,
+};
+use net::stream::{HttpResponseStream, JsonStreamData};
+use net::BatchQuery;
+use net::BatchQueryResult;
+use net::BatchResponse;
+use net::BlocksData;
 use net::ClientError;
 use net::Error as net_error;
 use net::HttpRequestMetadata;
@@ -44,6 +56,10 @@ use net::NeighborsData;
 use net::PeerAddress;
 use net::PeerHost;
 use net::ProtocolFamily;
+use net::RPCMinerStatusData;
+use net::RPCStateView;
+use net::ScheduledCallsOp;
+use net::StacksBlockAcceptedData;
 use net::StacksHttp;
 use net::StacksHttpMessage;
 use net::StacksMessageCodec;
@@ -52,16 +68,32 @@ use net::UnconfirmedTransactionResponse;
 use net::UnconfirmedTransactionStatus;
 use net::UrlString;
 use net::HTTP_REQUEST_ID_RESERVED;
+use net::MAX_HEADERS;
 use net::MAX_NEIGHBORS_DATA_LEN;
 use net::{
-    AccountEntryResponse, AttachmentPage, CallReadOnlyResponse, ContractSrcResponse,
-    GetAttachmentResponse, GetAttachmentsInvResponse, MapEntryResponse,
+    AccountAssetsResponse, AccountEntryResponse, AccountFirstSeenEntry, AccountFungibleTokenEntry,
+    AccountLockEntry, AccountNonFungibleTokenEntry, AccountNoncesResponse, AccountSummaryResponse,
+    AttachmentInstanceEntry, AttachmentInstancesPage, AttachmentPage, BlockProposalResponse,
+    BlockSimulationResponse, CallReadOnlyResponse, ContractAnalysisError, ContractAnalysisResponse,
+    ContractSrcResponse, DataVarResponse, GetAttachmentInstancesResponse, GetAttachmentResponse,
+    GetAttachmentsInvResponse, MapEntryResponse, MemPoolGCResponse, PendingMinerRewardEntry,
+    PendingMinerRewardsResponse, PrincipalTransactionEntry, PrincipalTransactionsResponse,
+    RPCDevMineRequest, RPCDevMineResponse, RPCGenesisAuditData, SimulatedBlockTransaction,
+    TransactionFeeEstimateResponse, TransactionReceiptResponse, TransactionSimulationResponse,
+    UnconfirmedMicroblockEntry, UnconfirmedMicroblockStreamResponse,
+};
+use net::{BurnchainOpsQuery, RPCBurnchainOpData, RPCBurnchainOpsData};
+use net::{
+    RPCAggregationCommitData, RPCPoxDelegatedTo, RPCPoxDelegation, RPCPoxDelegationsData,
+    RPCPoxPartialStack,
 };
+use net::{RPCBlockEventBloomData, RPCBurnBlockInfoData, RPCPeerInfoData, RPCPoxInfoData};
 use net::{RPCNeighbor, RPCNeighborsInfo};
-use net::{RPCPeerInfoData, RPCPoxInfoData};
+use net::{RPCRewardSetData, RPCRewardSetEntry};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
 
 use burnchains::Burnchain;
 use burnchains::BurnchainHeaderHash;
@@ -71,6 +103,7 @@ use burnchains::*;
 use chainstate::burn::db::sortdb::SortitionDB;
 use chainstate::burn::BlockHeaderHash;
 use chainstate::burn::ConsensusHash;
+use chainstate::stacks::address::AddressKind;
 use chainstate::stacks::db::{
     blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, BlockStreamData, StacksChainState,
 };
@@ -80,6 +113,7 @@ use monitoring;
 
 use rusqlite::{DatabaseName, NO_PARAMS};
 
+use util::db::get_ancestor_block_hash;
 use util::db::DBConn;
 use util::db::Error as db_error;
 use util::get_epoch_time_secs;
@@ -89,7 +123,9 @@ use util::hash::{hex_bytes, to_hex};
 use crate::{util::hash::Sha256Sum, version_string};
 
 use vm::{
+    analysis::contract_interface_builder::build_contract_interface,
     clarity::ClarityConnection,
+    clarity::Error as clarity_error,
     costs::{ExecutionCost, LimitedCostTracker},
     database::{
         marf::ContractCommitment, ClarityDatabase, ClaritySerializable, MarfedKV, STXBalance,
@@ -105,10 +141,20 @@ use rand::thread_rng;
 
 pub const STREAM_CHUNK_SIZE: u64 = 4096;
 
+/// Asks the node's miner to produce a block right away, on behalf of a POST /v2/dev/mine
+pub type DevMineCallback = Arc<dyn Fn(RPCDevMineRequest) + Send + Sync>;
+
 #[derive(Default)]
 pub struct RPCHandlerArgs<'a> {
     pub exit_at_block_height: Option<&'a u64>,
     pub genesis_chainstate_hash: Sha256Sum,
+    pub genesis_audit: Option<RPCGenesisAuditData>,
+    /// the miner's state, kept up to date by the miner, if this node runs one
+    pub miner_status: Option<Arc<Mutex<RPCMinerStatusData>>>,
+    /// where the node keeps the contract-calls it is scheduled to send, if it sends any
+    pub scheduled_calls_path: Option<String>,
+    /// set only if the node runs a dev burnchain, whose blocks can be mined on demand
+    pub dev_mine: Option<DevMineCallback>,
 }
 
 pub struct ConversationHttp {
@@ -127,10 +173,10 @@ pub struct ConversationHttp {
     last_response_timestamp: u64, // absolute timestamp of the last time we sent at least 1 byte in a response
     connection_time: u64,         // when this converation was instantiated
 
-    // ongoing block streams
+    // ongoing replies, and the streamed bodies of those that have them
     reply_streams: VecDeque<(
         ReplyHandleHttp,
-        Option<(HttpChunkedTransferWriterState, BlockStreamData)>,
+        Option<(HttpChunkedTransferWriterState, HttpResponseStream)>,
         bool,
     )>,
 
@@ -170,6 +216,7 @@ impl RPCPeerInfoData {
         peerdb: &PeerDB,
         exit_at_block_height: &Option<&u64>,
         genesis_chainstate_hash: &Sha256Sum,
+        genesis_audit: &Option<RPCGenesisAuditData>,
     ) -> Result<RPCPeerInfoData, net_error> {
         let burnchain_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
         let local_peer = PeerDB::get_local_peer(peerdb.conn())?;
@@ -222,6 +269,7 @@ impl RPCPeerInfoData {
             unanchored_seq: unconfirmed_seq,
             exit_at_block_height: exit_at_block_height.cloned(),
             genesis_chainstate_hash: genesis_chainstate_hash.clone(),
+            genesis_audit: genesis_audit.clone(),
         })
     }
 }
@@ -233,7 +281,8 @@ impl RPCPoxInfoData {
         tip: &StacksBlockId,
         _options: &ConnectionOptions,
     ) -> Result<RPCPoxInfoData, net_error> {
-        let contract_identifier = boot::boot_code_id("pox");
+        let contract_identifier =
+            boot::boot_code_id(chainstate.get_pox_contract_names(sortdb, tip)?[0]);
         let function = "get-pox-info";
         let cost_track = LimitedCostTracker::new_free();
         let sender = PrincipalData::Standard(StandardPrincipalData::transient());
@@ -305,7 +354,7 @@ impl RPCPoxInfoData {
         let rejection_votes_left_required = total_required.saturating_sub(current_rejection_votes);
 
         Ok(RPCPoxInfoData {
-            contract_id: boot::boot_code_id("pox").to_string(),
+            contract_id: contract_identifier.to_string(),
             first_burnchain_block_height,
             min_amount_ustx,
             prepare_cycle_length,
@@ -318,6 +367,403 @@ impl RPCPoxInfoData {
     }
 }
 
+impl RPCRewardSetData {
+    /// Project the reward set of the reward cycle after the one that contains the given Stacks
+    /// chain tip, from the stacking state as of that tip.  This is the reward set the PoX anchor
+    /// block would select if stacking state did not change before the prepare phase completes.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        burnchain: &Burnchain,
+        tip: &StacksBlockId,
+    ) -> Result<RPCRewardSetData, net_error> {
+        let tip_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            tip,
+        )?
+        .ok_or(net_error::NotFoundError)?;
+
+        // the first block of reward cycle `c` is at height `first_block_height + c * len + 1`
+        let current_reward_cycle = burnchain
+            .block_height_to_reward_cycle((tip_info.burn_header_height as u64).saturating_sub(1))
+            .unwrap_or(0);
+        let reward_cycle_id = current_reward_cycle + 1;
+        let reward_cycle_start_burn_height =
+            burnchain.reward_cycle_to_block_height(reward_cycle_id);
+        let prepare_phase_start_burn_height = reward_cycle_start_burn_height
+            .saturating_sub(burnchain.pox_constants.prepare_length as u64);
+        let burn_block_height =
+            SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?.block_height;
+
+        let pox_active = reward_cycle_start_burn_height < burnchain.pox_constants.sunset_end
+            && chainstate.is_pox_active(sortdb, tip, reward_cycle_id as u128)?;
+
+        let registered_addrs = chainstate.get_reward_addresses(
+            burnchain,
+            sortdb,
+            reward_cycle_start_burn_height,
+            tip,
+        )?;
+
+        let liquid_ustx = tip_info.total_liquid_ustx;
+        let (threshold_ustx, participation_ustx) =
+            StacksChainState::get_reward_threshold_and_participation(
+                &burnchain.pox_constants,
+                &registered_addrs,
+                liquid_ustx,
+            );
+        let enough_participation = burnchain
+            .pox_constants
+            .enough_participation(participation_ustx, liquid_ustx);
+
+        let mut entries: Vec<RPCRewardSetEntry> = vec![];
+        for (addr, stacked_ustx) in registered_addrs.iter() {
+            let pox_address = addr.to_b58();
+            match entries.iter_mut().find(|e| e.pox_address == pox_address) {
+                Some(entry) => entry.stacked_ustx += *stacked_ustx,
+                None => entries.push(RPCRewardSetEntry {
+                    pox_address,
+                    stacked_ustx: *stacked_ustx,
+                    slots: 0,
+                    ustx_to_next_slot: 0,
+                }),
+            }
+        }
+        if threshold_ustx > 0 {
+            for entry in entries.iter_mut() {
+                entry.ustx_to_next_slot = threshold_ustx - (entry.stacked_ustx % threshold_ustx);
+            }
+        }
+
+        if pox_active && enough_participation && threshold_ustx > 0 {
+            for addr in StacksChainState::make_reward_set(threshold_ustx, registered_addrs) {
+                let pox_address = addr.to_b58();
+                if let Some(entry) = entries.iter_mut().find(|e| e.pox_address == pox_address) {
+                    entry.slots += 1;
+                }
+            }
+        }
+        entries.sort_by(|a, b| a.pox_address.cmp(&b.pox_address));
+
+        Ok(RPCRewardSetData {
+            reward_cycle_id,
+            reward_cycle_start_burn_height,
+            prepare_phase_start_burn_height,
+            burn_block_height,
+            blocks_until_prepare_phase: prepare_phase_start_burn_height
+                .saturating_sub(burn_block_height),
+            pox_active,
+            liquid_ustx,
+            participation_ustx,
+            threshold_ustx,
+            enough_participation,
+            reward_slots: burnchain.pox_constants.reward_slots(),
+            entries,
+        })
+    }
+}
+
+impl RPCPoxDelegationsData {
+    /// Load every stacker that is delegating to `delegate` as of the given Stacks chain tip.
+    /// Candidate stackers come from the chainstate's PoX delegations index; each one's
+    /// delegation is then checked against the PoX contract at the chain tip, so expired,
+    /// revoked, or re-targeted delegations are not reported.
+    /// Also load `delegate`'s own delegation, and what it has stacked for the candidate
+    /// stackers but not yet committed.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        delegate: &PrincipalData,
+    ) -> Result<RPCPoxDelegationsData, net_error> {
+        let stackers = StacksChainState::get_pox_delegators(chainstate.db(), delegate)?;
+
+        let mut delegations = vec![];
+        for stacker in stackers.iter() {
+            let delegation = match chainstate.get_pox_delegation(sortdb, tip, stacker)? {
+                Some(delegation) if delegation.delegated_to == *delegate => delegation,
+                _ => {
+                    continue;
+                }
+            };
+
+            let (locked_ustx, unlock_height) = chainstate
+                .maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                    clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                        let burn_block_height =
+                            clarity_db.get_current_burnchain_block_height() as u64;
+                        clarity_db
+                            .get_account_stx_balance(stacker)
+                            .get_locked_balance_at_burn_block(burn_block_height)
+                    })
+                })
+                .ok_or(net_error::NotFoundError)?;
+
+            delegations.push(RPCPoxDelegation {
+                stacker: stacker.to_string(),
+                amount_ustx: delegation.amount_ustx,
+                until_burn_ht: delegation.until_burn_ht,
+                pox_address: delegation.pox_addr.map(|addr| addr.to_b58()),
+                locked_ustx,
+                unlock_height,
+            });
+        }
+
+        let delegated_to =
+            chainstate
+                .get_pox_delegation(sortdb, tip, delegate)?
+                .map(|delegation| RPCPoxDelegatedTo {
+                    delegate: delegation.delegated_to.to_string(),
+                    amount_ustx: delegation.amount_ustx,
+                    until_burn_ht: delegation.until_burn_ht,
+                    pox_address: delegation.pox_addr.map(|addr| addr.to_b58()),
+                });
+
+        let partial_stacks = chainstate
+            .get_pox_partial_stacks(sortdb, tip, delegate, &stackers)?
+            .into_iter()
+            .map(|partial| RPCPoxPartialStack {
+                pox_address: partial.pox_addr.to_b58(),
+                reward_cycle: partial.reward_cycle,
+                stacked_ustx: partial.stacked_ustx,
+            })
+            .collect();
+
+        Ok(RPCPoxDelegationsData {
+            delegate: delegate.to_string(),
+            total_delegated_ustx: delegations.iter().map(|d| d.amount_ustx).sum(),
+            total_locked_ustx: delegations.iter().map(|d| d.locked_ustx).sum(),
+            delegations,
+            delegated_to,
+            partial_stacks,
+        })
+    }
+}
+
+impl RPCAggregationCommitData {
+    /// Simulate `delegate` calling `stack-aggregation-commit` as of the given Stacks chain tip.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        delegate: &PrincipalData,
+        pox_addr: &StacksAddress,
+        reward_cycle: u64,
+    ) -> Result<RPCAggregationCommitData, net_error> {
+        let partial_stacked_ustx =
+            chainstate.get_partial_stacked_ustx(sortdb, tip, delegate, pox_addr, reward_cycle)?;
+
+        match chainstate.simulate_stack_aggregation_commit(
+            sortdb,
+            tip,
+            delegate,
+            pox_addr,
+            reward_cycle,
+        ) {
+            Ok(result) => Ok(RPCAggregationCommitData {
+                okay: true,
+                result: Some(format!("0x{}", result.serialize())),
+                cause: None,
+                partial_stacked_ustx,
+            }),
+            Err(chain_error::ClarityError(e)) => Ok(RPCAggregationCommitData {
+                okay: false,
+                result: None,
+                cause: Some(e.to_string()),
+                partial_stacked_ustx,
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl RPCBurnBlockInfoData {
+    /// Load the burnchain block at the given height on the canonical burnchain fork.
+    /// Returns None if there is no such block.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        burn_block_height: u64,
+    ) -> Result<Option<RPCBurnBlockInfoData>, net_error> {
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        if burn_block_height > tip.block_height {
+            return Ok(None);
+        }
+
+        let ic = sortdb.index_handle(&tip.sortition_id);
+        let snapshot = match ic.get_block_snapshot_by_height(burn_block_height)? {
+            Some(sn) => sn,
+            None => return Ok(None),
+        };
+        let median_time_past = ic
+            .get_burn_median_time_past(burn_block_height)?
+            .ok_or(net_error::NotFoundError)?;
+
+        Ok(Some(RPCBurnBlockInfoData {
+            burn_block_height,
+            burn_block_hash: snapshot.burn_header_hash.to_hex(),
+            burn_block_time: snapshot.burn_header_timestamp,
+            median_time_past,
+            consensus_hash: snapshot.consensus_hash,
+        }))
+    }
+}
+
+impl RPCBurnchainOpData {
+    pub fn txid(&self) -> &str {
+        match self {
+            RPCBurnchainOpData::LeaderKeyRegister { ref txid, .. }
+            | RPCBurnchainOpData::LeaderBlockCommit { ref txid, .. }
+            | RPCBurnchainOpData::UserBurnSupport { ref txid, .. }
+            | RPCBurnchainOpData::StackStx { ref txid, .. }
+            | RPCBurnchainOpData::TransferStx { ref txid, .. } => txid,
+        }
+    }
+
+    pub fn vtxindex(&self) -> u32 {
+        match self {
+            RPCBurnchainOpData::LeaderKeyRegister { vtxindex, .. }
+            | RPCBurnchainOpData::LeaderBlockCommit { vtxindex, .. }
+            | RPCBurnchainOpData::UserBurnSupport { vtxindex, .. }
+            | RPCBurnchainOpData::StackStx { vtxindex, .. }
+            | RPCBurnchainOpData::TransferStx { vtxindex, .. } => *vtxindex,
+        }
+    }
+}
+
+impl RPCBurnchainOpsData {
+    /// Load the operations processed in the burnchain block at the given height on the canonical
+    /// burnchain fork.  Returns None if there is no such block.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        burn_block_height: u64,
+    ) -> Result<Option<RPCBurnchainOpsData>, net_error> {
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        if burn_block_height > tip.block_height {
+            return Ok(None);
+        }
+
+        let ic = sortdb.index_handle(&tip.sortition_id);
+        let snapshot = match ic.get_block_snapshot_by_height(burn_block_height)? {
+            Some(sn) => sn,
+            None => return Ok(None),
+        };
+
+        let conn = sortdb.conn();
+        let block_commits = SortitionDB::get_block_commits_by_block(conn, &snapshot.sortition_id)?;
+        let winning_commit = if snapshot.sortition {
+            block_commits
+                .iter()
+                .find(|commit| commit.txid == snapshot.winning_block_txid)
+        } else {
+            None
+        };
+        let winning_block_hash160 =
+            Hash160::from_sha256(snapshot.winning_stacks_block_hash.as_bytes());
+
+        let mut ops = vec![];
+        for key in SortitionDB::get_leader_keys_by_block(conn, &snapshot.sortition_id)? {
+            ops.push(RPCBurnchainOpData::LeaderKeyRegister {
+                txid: key.txid.to_hex(),
+                vtxindex: key.vtxindex,
+                consensus_hash: key.consensus_hash,
+                public_key: key.public_key.to_hex(),
+                address: key.address.to_string(),
+            });
+        }
+        for user_burn in SortitionDB::get_user_burns_by_block(conn, &snapshot.sortition_id)? {
+            // same test as get_winning_user_burns_by_block()
+            let won_sortition = match winning_commit {
+                Some(commit) => {
+                    user_burn.block_header_hash_160 == winning_block_hash160
+                        && user_burn.key_vtxindex == commit.key_vtxindex
+                        && user_burn.key_block_ptr == commit.key_block_ptr
+                }
+                None => false,
+            };
+            ops.push(RPCBurnchainOpData::UserBurnSupport {
+                txid: user_burn.txid.to_hex(),
+                vtxindex: user_burn.vtxindex,
+                address: user_burn.address.to_string(),
+                burn_fee: user_burn.burn_fee,
+                key_block_ptr: user_burn.key_block_ptr,
+                key_vtxindex: user_burn.key_vtxindex,
+                block_header_hash_160: user_burn.block_header_hash_160.to_hex(),
+                won_sortition,
+            });
+        }
+        for commit in block_commits.iter() {
+            ops.push(RPCBurnchainOpData::LeaderBlockCommit {
+                txid: commit.txid.to_hex(),
+                vtxindex: commit.vtxindex,
+                block_header_hash: commit.block_header_hash.to_hex(),
+                burn_fee: commit.burn_fee,
+                sunset_burn: commit.sunset_burn,
+                parent_block_ptr: commit.parent_block_ptr,
+                parent_vtxindex: commit.parent_vtxindex,
+                key_block_ptr: commit.key_block_ptr,
+                key_vtxindex: commit.key_vtxindex,
+                commit_outs: commit.commit_outs.iter().map(|a| a.to_string()).collect(),
+                won_sortition: snapshot.sortition && commit.txid == snapshot.winning_block_txid,
+            });
+        }
+        for stack_stx in SortitionDB::get_stack_stx_ops(conn, &snapshot.burn_header_hash)? {
+            ops.push(RPCBurnchainOpData::StackStx {
+                txid: stack_stx.txid.to_hex(),
+                vtxindex: stack_stx.vtxindex,
+                sender: stack_stx.sender.to_string(),
+                reward_addr: stack_stx.reward_addr.to_string(),
+                stacked_ustx: stack_stx.stacked_ustx,
+                num_cycles: stack_stx.num_cycles,
+            });
+        }
+        for transfer_stx in SortitionDB::get_transfer_stx_ops(conn, &snapshot.burn_header_hash)? {
+            ops.push(RPCBurnchainOpData::TransferStx {
+                txid: transfer_stx.txid.to_hex(),
+                vtxindex: transfer_stx.vtxindex,
+                sender: transfer_stx.sender.to_string(),
+                recipient: transfer_stx.recipient.to_string(),
+                transfered_ustx: transfer_stx.transfered_ustx,
+                memo: to_hex(&transfer_stx.memo),
+            });
+        }
+        ops.sort_by_key(|op| op.vtxindex());
+
+        Ok(Some(RPCBurnchainOpsData {
+            burn_block_height,
+            burn_block_hash: snapshot.burn_header_hash.to_hex(),
+            consensus_hash: snapshot.consensus_hash,
+            sortition: snapshot.sortition,
+            winning_block_txid: if snapshot.sortition {
+                Some(snapshot.winning_block_txid.to_hex())
+            } else {
+                None
+            },
+            ops,
+        }))
+    }
+
+    /// Load the operation with the given txid, along with the canonical burnchain block it was
+    /// processed in.  Returns None if no block on the canonical burnchain fork has it.
+    pub fn from_db_by_txid(
+        sortdb: &SortitionDB,
+        txid: &Txid,
+    ) -> Result<Option<RPCBurnchainOpsData>, net_error> {
+        let txid_hex = txid.to_hex();
+        for burn_block_height in SortitionDB::get_burnchain_op_heights(sortdb.conn(), txid)? {
+            if let Some(mut burnchain_ops) =
+                RPCBurnchainOpsData::from_db(sortdb, burn_block_height)?
+            {
+                // the op may have been in a non-canonical block at this height
+                burnchain_ops.ops.retain(|op| op.txid() == txid_hex);
+                if burnchain_ops.ops.len() > 0 {
+                    return Ok(Some(burnchain_ops));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
 impl RPCNeighborsInfo {
     /// Load neighbor address information from the peer network
     pub fn from_p2p(
@@ -374,6 +820,32 @@ impl RPCNeighborsInfo {
     }
 }
 
+impl UnconfirmedMicroblockStreamResponse {
+    /// Describe (part of) the unconfirmed microblock stream built on an anchored block
+    pub fn from_microblocks(
+        parent_index_block_hash: &StacksBlockId,
+        microblocks: &[StacksMicroblock],
+    ) -> UnconfirmedMicroblockStreamResponse {
+        let microblocks = microblocks
+            .iter()
+            .map(|microblock| UnconfirmedMicroblockEntry {
+                microblock_hash: format!("0x{}", microblock.block_hash()),
+                sequence: microblock.header.sequence,
+                parent_microblock_hash: format!("0x{}", microblock.header.prev_block),
+                transactions: microblock
+                    .txs
+                    .iter()
+                    .map(|tx| format!("0x{}", to_hex(&tx.serialize_to_vec())))
+                    .collect(),
+            })
+            .collect();
+        UnconfirmedMicroblockStreamResponse {
+            parent_index_block_hash: format!("0x{}", parent_index_block_hash),
+            microblocks,
+        }
+    }
+}
+
 impl ConversationHttp {
     pub fn new(
         network_id: u32,
@@ -386,6 +858,17 @@ impl ConversationHttp {
     ) -> ConversationHttp {
         let mut stacks_http = StacksHttp::new();
         stacks_http.maximum_call_argument_size = conn_opts.maximum_call_argument_size;
+        if let Some(ref token) = conn_opts.rpc_auth_token {
+            // only inbound clients from elsewhere need to authenticate, so that the node's own
+            // services can still reach it
+            if outbound_url.is_none() && !peer_addr.ip().is_loopback() {
+                stacks_http.auth_tokens = vec![token.clone()]
+                    .into_iter()
+                    .chain(conn_opts.admin_token.clone())
+                    .chain(conn_opts.block_proposal_token.clone())
+                    .collect();
+            }
+        }
         ConversationHttp {
             network_id: network_id,
             connection: ConnectionHttp::new(stacks_http, conn_opts, None),
@@ -524,6 +1007,7 @@ impl ConversationHttp {
             peerdb,
             &handler_args.exit_at_block_height,
             &handler_args.genesis_chainstate_hash,
+            &handler_args.genesis_audit,
         ) {
             Ok(pi) => {
                 let response = HttpResponseType::PeerInfo(response_metadata, pi);
@@ -568,6 +1052,161 @@ impl ConversationHttp {
         }
     }
 
+    /// Handle a GET projected reward set.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getrewardset<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        burnchain: &Burnchain,
+        tip: &StacksBlockId,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        match RPCRewardSetData::from_db(sortdb, chainstate, burnchain, tip) {
+            Ok(rs) => {
+                let response = HttpResponseType::RewardSet(response_metadata, rs);
+                response.send(http, fd)
+            }
+            Err(e) => {
+                warn!("Failed to get reward set {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query reward set".to_string(),
+                );
+                response.send(http, fd)
+            }
+        }
+    }
+
+    /// Handle a GET on the stackers delegating to a PoX delegate.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_pox_delegations<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        delegate: &PrincipalData,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        match RPCPoxDelegationsData::from_db(sortdb, chainstate, tip, delegate) {
+            Ok(delegations) => {
+                let response = HttpResponseType::PoxDelegations(response_metadata, delegations);
+                response.send(http, fd)
+            }
+            Err(e) => {
+                warn!("Failed to get PoX delegations {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query PoX delegations".to_string(),
+                );
+                response.send(http, fd)
+            }
+        }
+    }
+
+    /// Handle a POST to simulate a PoX delegate's `stack-aggregation-commit`.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_post_aggregation_commit<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        delegate: &PrincipalData,
+        pox_addr: &StacksAddress,
+        reward_cycle: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        match RPCAggregationCommitData::from_db(
+            sortdb,
+            chainstate,
+            tip,
+            delegate,
+            pox_addr,
+            reward_cycle,
+        ) {
+            Ok(simulation) => {
+                let response =
+                    HttpResponseType::AggregationCommitSimulation(response_metadata, simulation);
+                response.send(http, fd)
+            }
+            Err(e) => {
+                warn!("Failed to simulate aggregation commit {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to simulate aggregation commit".to_string(),
+                );
+                response.send(http, fd)
+            }
+        }
+    }
+
+    /// Handle a GET burnchain ops, by burn block height or by txid.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getburnchainops<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        query: &BurnchainOpsQuery,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let (ops_res, not_found) = match query {
+            BurnchainOpsQuery::Height(burn_block_height) => (
+                RPCBurnchainOpsData::from_db(sortdb, *burn_block_height),
+                format!("No burnchain block at height {}", burn_block_height),
+            ),
+            BurnchainOpsQuery::Txid(txid) => (
+                RPCBurnchainOpsData::from_db_by_txid(sortdb, txid),
+                format!("No processed burnchain operation {}", txid),
+            ),
+        };
+        let response = match ops_res {
+            Ok(Some(ops)) => HttpResponseType::BurnchainOps(response_metadata, ops),
+            Ok(None) => HttpResponseType::NotFound(response_metadata, not_found),
+            Err(e) => {
+                warn!("Failed to get burnchain ops {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query burnchain operations".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET burn block info.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getburnblockinfo<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        burn_block_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match RPCBurnBlockInfoData::from_db(sortdb, burn_block_height) {
+            Ok(Some(bi)) => HttpResponseType::BurnBlockInfo(response_metadata, bi),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No burnchain block at height {}", burn_block_height),
+            ),
+            Err(e) => {
+                warn!("Failed to get burn block info {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query burn block info".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
     fn handle_getattachmentsinv<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
@@ -665,28 +1304,299 @@ impl ConversationHttp {
         }
     }
 
-    fn handle_getattachment<W: Write>(
+    /// Handle a GET for the attachment instances in a set of attachment inventory pages,
+    /// optionally restricted to a single contract and/or BNS namespace.  Only instances
+    /// announced on the fork identified by the given tip are reported.
+    fn handle_get_attachment_instances<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        atlasdb: &mut AtlasDB,
-        content_hash: Hash160,
+        atlasdb: &AtlasDB,
+        chainstate: &mut StacksChainState,
+        tip_consensus_hash: &ConsensusHash,
+        tip_block_hash: &BlockHeaderHash,
+        pages_indexes: &HashSet<u32>,
+        contract_id_opt: Option<&QualifiedContractIdentifier>,
+        namespace_opt: Option<&String>,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        match atlasdb.find_instantiated_attachment(&content_hash) {
-            Ok(Some(attachment)) => {
-                let content = GetAttachmentResponse { attachment };
-                let response = HttpResponseType::GetAttachment(response_metadata, content);
-                response.send(http, fd)
-            }
-            _ => {
-                let msg = format!("Unable to find attachment");
+        if pages_indexes.len() > MAX_ATTACHMENT_INV_PAGES_PER_REQUEST {
+            let msg = format!(
+                "Number of attachment inv pages is limited by {} per request",
+                MAX_ATTACHMENT_INV_PAGES_PER_REQUEST
+            );
+            warn!("{}", msg);
+            let response = HttpResponseType::BadRequest(response_metadata, msg.clone());
+            return response.send(http, fd);
+        }
+        if pages_indexes.is_empty() {
+            let msg = "Page indexes missing".to_string();
+            warn!("{}", msg);
+            let response = HttpResponseType::BadRequest(response_metadata, msg.clone());
+            return response.send(http, fd);
+        }
+
+        let mut pages_indexes = pages_indexes.iter().copied().collect::<Vec<u32>>();
+        pages_indexes.sort();
+        let tip = StacksBlockHeader::make_index_block_hash(tip_consensus_hash, tip_block_hash);
+
+        let pages_count = match atlasdb.get_attachment_inv_pages_count(contract_id_opt) {
+            Ok(count) => count,
+            Err(e) => {
+                let msg = "Unable to read Atlas DB".to_string();
                 warn!("{}", msg);
                 let response = HttpResponseType::ServerError(response_metadata, msg.clone());
-                response.send(http, fd)
+                response.send(http, fd)?;
+                return Err(net_error::DBError(e));
             }
-        }
-    }
+        };
+
+        let namespace_bytes = namespace_opt.map(|ns| ns.as_bytes().to_vec());
+        let mut ancestors: HashMap<u64, Option<StacksBlockId>> = HashMap::new();
+        let mut headers_tx = chainstate.index_tx_begin()?;
+        let mut pages = vec![];
+
+        for page_index in pages_indexes.into_iter() {
+            let instances = match atlasdb
+                .get_attachment_instances_at_page_index(page_index, contract_id_opt)
+            {
+                Ok(instances) => instances,
+                Err(e) => {
+                    let msg = "Unable to read Atlas DB".to_string();
+                    warn!("{}", msg);
+                    let response = HttpResponseType::ServerError(response_metadata, msg.clone());
+                    response.send(http, fd)?;
+                    return Err(net_error::DBError(e));
+                }
+            };
+
+            let mut entries = vec![];
+            for (instance, is_available) in instances.into_iter() {
+                if namespace_bytes.is_some() && instance.get_namespace() != namespace_bytes {
+                    continue;
+                }
+
+                // only report instances announced on this tip's fork
+                let ancestor = match ancestors.get(&instance.block_height) {
+                    Some(ancestor) => *ancestor,
+                    None => {
+                        let ancestor =
+                            headers_tx.get_ancestor_block_hash(instance.block_height, &tip)?;
+                        ancestors.insert(instance.block_height, ancestor);
+                        ancestor
+                    }
+                };
+                let index_block_hash = instance.get_stacks_block_id();
+                if ancestor != Some(index_block_hash) {
+                    continue;
+                }
+
+                entries.push(AttachmentInstanceEntry {
+                    attachment_index: instance.attachment_index,
+                    content_hash: to_hex(&instance.content_hash.0),
+                    contract_id: instance.contract_id.to_string(),
+                    block_height: instance.block_height,
+                    index_block_hash,
+                    metadata: instance.metadata,
+                    is_available,
+                });
+            }
+            pages.push(AttachmentInstancesPage {
+                index: page_index,
+                instances: entries,
+            });
+        }
+
+        let content = GetAttachmentInstancesResponse {
+            block_id: tip,
+            pages_count,
+            pages,
+        };
+        let response = HttpResponseType::GetAttachmentInstances(response_metadata, content);
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for an attachment.
+    /// Return a JsonStreamData for the attachment, so we can encode it as it is sent instead of
+    /// buffering the whole response.
+    fn handle_getattachment<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        atlasdb: &mut AtlasDB,
+        content_hash: Hash160,
+    ) -> Result<Option<HttpResponseStream>, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        match atlasdb.find_instantiated_attachment(&content_hash) {
+            Ok(Some(attachment)) => {
+                let response = HttpResponseType::JsonStream(response_metadata);
+                response.send(http, fd)?;
+                Ok(Some(JsonStreamData::attachment(attachment).into()))
+            }
+            _ => {
+                let msg = format!("Unable to find attachment");
+                warn!("{}", msg);
+                let response = HttpResponseType::ServerError(response_metadata, msg.clone());
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+        }
+    }
+
+    /// Handle a GET for the metadata of a StackerDB's non-empty slots.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_stackerdb_metadata<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        stackerdbs: &StackerDBs,
+        contract_id: &QualifiedContractIdentifier,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.stackerdb_contracts.contains(contract_id) {
+            let response = HttpResponseType::NotFound(
+                response_metadata,
+                format!("StackerDB {} is not replicated by this node", contract_id),
+            );
+            return response.send(http, fd);
+        }
+
+        let response = match stackerdbs.get_slots_metadata(contract_id) {
+            Ok(slots) => HttpResponseType::StackerDBMetadata(response_metadata, slots),
+            Err(e) => {
+                warn!(
+                    "Failed to load StackerDB {} metadata: {:?}",
+                    contract_id, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to load StackerDB metadata"),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the chunk in a StackerDB slot.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_stackerdb_chunk<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        stackerdbs: &StackerDBs,
+        contract_id: &QualifiedContractIdentifier,
+        slot_id: u32,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.stackerdb_contracts.contains(contract_id) {
+            let response = HttpResponseType::NotFound(
+                response_metadata,
+                format!("StackerDB {} is not replicated by this node", contract_id),
+            );
+            return response.send(http, fd);
+        }
+
+        let response = match stackerdbs.get_chunk(contract_id, slot_id) {
+            Ok(Some(chunk)) => HttpResponseType::StackerDBChunk(response_metadata, chunk),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No chunk in slot {}", slot_id),
+            ),
+            Err(e) => {
+                warn!(
+                    "Failed to load StackerDB {} slot {}: {:?}",
+                    contract_id, slot_id, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to load StackerDB chunk"),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a POST of a chunk to a StackerDB slot.  The chunk is checked against the
+    /// StackerDB's config as of the canonical chain tip.  Returns true if it was stored, in which
+    /// case it should be pushed to the peer network.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_post_stackerdb_chunk<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        stackerdbs: &mut StackerDBs,
+        contract_id: &QualifiedContractIdentifier,
+        chunk: &StackerDBChunkData,
+        options: &ConnectionOptions,
+    ) -> Result<bool, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.stackerdb_contracts.contains(contract_id) {
+            let response = HttpResponseType::NotFound(
+                response_metadata,
+                format!("StackerDB {} is not replicated by this node", contract_id),
+            );
+            return response.send(http, fd).and_then(|_| Ok(false));
+        }
+
+        let tip = match ConversationHttp::handle_load_stacks_chain_tip(
+            http, fd, req, None, sortdb, chainstate,
+        )? {
+            Some(tip) => tip,
+            None => {
+                return Ok(false);
+            }
+        };
+
+        let config = match StackerDBConfig::from_contract(chainstate, sortdb, &tip, contract_id) {
+            Ok(config) => config,
+            Err(net_error::NotFoundError) => {
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("{} is not a StackerDB contract", contract_id),
+                );
+                return response.send(http, fd).and_then(|_| Ok(false));
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to load config for StackerDB {}: {:?}",
+                    contract_id, &e
+                );
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to load StackerDB config"),
+                );
+                return response.send(http, fd).and_then(|_| Ok(false));
+            }
+        };
+
+        let (accepted, reason) = match stackerdbs.try_put_chunk(contract_id, &config, chunk) {
+            Ok(()) => (true, None),
+            Err(net_error::StackerDBChunkRejected(reason)) => (false, Some(reason)),
+            Err(e) => {
+                warn!(
+                    "Failed to store chunk for StackerDB {}: {:?}",
+                    contract_id, &e
+                );
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to store StackerDB chunk"),
+                );
+                return response.send(http, fd).and_then(|_| Ok(false));
+            }
+        };
+
+        let metadata = stackerdbs.get_slot_metadata(contract_id, chunk.slot_id)?;
+        let ack = StackerDBChunkAckData {
+            accepted,
+            reason,
+            metadata,
+        };
+        let response = HttpResponseType::StackerDBChunkAck(response_metadata, ack);
+        response.send(http, fd).and_then(|_| Ok(accepted))
+    }
 
     /// Handle a GET neighbors
     /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
@@ -773,6 +1683,78 @@ impl ConversationHttp {
         }
     }
 
+    /// Handle a GET on the bloom filter over a processed block's contract events.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_block_event_bloom<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        index_block_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response =
+            match StacksChainState::get_block_event_bloom(chainstate.db(), index_block_hash) {
+                Ok(Some(bloom)) => HttpResponseType::BlockEventBloom(
+                    response_metadata,
+                    RPCBlockEventBloomData {
+                        index_block_hash: index_block_hash.to_hex(),
+                        bloom: format!("0x{}", bloom.to_hex()),
+                    },
+                ),
+                Ok(None) => HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("No processed block {}", index_block_hash.to_hex()),
+                ),
+                Err(e) => {
+                    warn!("Failed to get block event bloom {:?}: {:?}", req, &e);
+                    HttpResponseType::ServerError(
+                        response_metadata,
+                        format!("Failed to query block {}", index_block_hash.to_hex()),
+                    )
+                }
+            };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET on a chain of up to `quantity` processed block headers ending at `tip`, in
+    /// order from the tip backwards.  Light nodes sync their header chains from this.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getheaders<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip: &StacksBlockId,
+        quantity: u64,
+        chainstate: &StacksChainState,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match StacksChainState::get_extended_header_chain(
+            chainstate.db(),
+            tip,
+            quantity.min(MAX_HEADERS),
+        ) {
+            Ok(headers) => {
+                if headers.len() == 0 && quantity > 0 {
+                    HttpResponseType::NotFound(
+                        response_metadata,
+                        format!("No processed block {}", tip.to_hex()),
+                    )
+                } else {
+                    HttpResponseType::Headers(response_metadata, headers)
+                }
+            }
+            Err(e) => {
+                warn!("Failed to load header chain {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to query headers from {}", tip.to_hex()),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
     /// Handle a GET confirmed microblock stream, by _anchor block hash_.  Start streaming the reply.
     /// The response's preamble (but not the block data) will be synchronously written to the fd
     /// (so use a fd that can buffer!)
@@ -983,2070 +1965,7248 @@ impl ConversationHttp {
         response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a GET on an existing account, given the current chain tip.  Optionally supplies a
-    /// MARF proof for each account detail loaded from the chain tip.
-    fn handle_get_account_entry<W: Write>(
+    /// Handle a POST to estimate the fee for a transaction, given the current chain tip.
+    /// Estimates are derived from recently-mined blocks and from the mempool.
+    fn handle_post_fee_estimate<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        mempool: &MemPoolDB,
+        tx: &StacksTransaction,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match mempool.estimate_fee_rates(chainstate, consensus_hash, block_hash) {
+            Ok(fee_rates) => {
+                let estimated_len = tx.tx_len();
+                let fees = fee_rates.fees_for_len(estimated_len);
+                HttpResponseType::TransactionFeeEstimate(
+                    response_metadata,
+                    TransactionFeeEstimateResponse {
+                        estimated_len,
+                        fee_rates,
+                        fees,
+                    },
+                )
+            }
+            Err(e) => {
+                warn!("Failed to estimate fee rates: {:?}", &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to estimate fee rates".to_string(),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST to simulate a transaction against the given chain tip and its unconfirmed
+    /// microblocks.  The transaction's signatures are not checked, so an unsigned transaction
+    /// can be simulated as its origin.  Nothing is committed.
+    fn handle_post_simulate_transaction<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
         tip: &StacksBlockId,
-        account: &PrincipalData,
-        with_proof: bool,
+        tx: &StacksTransaction,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
+        let txid = tx.txid();
+        let simulation_opt = chainstate.with_pending_clarity_tx(
+            &sortdb.index_conn(),
+            tip,
+            true,
+            &[],
+            |clarity_tx| StacksChainState::simulate_transaction(clarity_tx, tx),
+        )?;
 
-        let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|clarity_db| {
-                    let key = ClarityDatabase::make_key_for_account_balance(&account);
-                    let burn_block_height = clarity_db.get_current_burnchain_block_height() as u64;
-                    let (balance, balance_proof) = clarity_db
-                        .get_with_proof::<STXBalance>(&key)
-                        .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
-                        .unwrap_or_else(|| (STXBalance::zero(), "".into()));
-                    let balance_proof = if with_proof {
-                        Some(balance_proof)
-                    } else {
-                        None
-                    };
-                    let key = ClarityDatabase::make_key_for_account_nonce(&account);
-                    let (nonce, nonce_proof) = clarity_db
-                        .get_with_proof(&key)
-                        .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
-                        .unwrap_or_else(|| (0, "".into()));
-                    let nonce_proof = if with_proof { Some(nonce_proof) } else { None };
-
-                    let unlocked = balance.get_available_balance_at_burn_block(burn_block_height);
-                    let (locked, unlock_height) =
-                        balance.get_locked_balance_at_burn_block(burn_block_height);
-
-                    let balance = format!("0x{}", to_hex(&unlocked.to_be_bytes()));
-                    let locked = format!("0x{}", to_hex(&locked.to_be_bytes()));
-
-                    AccountEntryResponse {
-                        balance,
-                        locked,
-                        unlock_height,
-                        nonce,
-                        balance_proof,
-                        nonce_proof,
-                    }
-                })
-            }) {
-                Some(data) => HttpResponseType::GetAccount(response_metadata, data),
-                None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
-            };
-
+        let response = match simulation_opt {
+            Some(Ok((fee, receipt))) => {
+                let committed = !receipt.post_condition_aborted;
+                let events = receipt
+                    .events
+                    .iter()
+                    .enumerate()
+                    .map(|(event_index, event)| event.json_serialize(event_index, &txid, committed))
+                    .collect();
+                HttpResponseType::TransactionSimulation(
+                    response_metadata,
+                    TransactionSimulationResponse {
+                        txid: format!("0x{}", &txid),
+                        okay: true,
+                        cause: None,
+                        result: Some(format!("0x{}", receipt.result.serialize())),
+                        post_condition_aborted: receipt.post_condition_aborted,
+                        events,
+                        asset_map: receipt.asset_map_json(),
+                        execution_cost: receipt.execution_cost,
+                        stx_burned: receipt.stx_burned.to_string(),
+                        fee,
+                    },
+                )
+            }
+            Some(Err(e)) => HttpResponseType::TransactionSimulation(
+                response_metadata,
+                TransactionSimulationResponse {
+                    txid: format!("0x{}", &txid),
+                    okay: false,
+                    cause: Some(format!("{:?}", &e)),
+                    result: None,
+                    post_condition_aborted: false,
+                    events: vec![],
+                    asset_map: json!({}),
+                    execution_cost: ExecutionCost::zero(),
+                    stx_burned: "0".to_string(),
+                    fee: 0,
+                },
+            ),
+            None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+        };
         response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a GET on a smart contract's data map, given the current chain tip.  Optionally
-    /// supplies a MARF proof for the value.
-    fn handle_get_map_entry<W: Write>(
+    /// Handle a POST to statically analyze a contract's source against the given chain tip and
+    /// its unconfirmed microblocks, using the same parser, analysis passes, and cost functions
+    /// that a contract-publish transaction would.  The contract's top-level expressions are also
+    /// evaluated to estimate its deployment cost.  Nothing is committed.
+    fn handle_post_analyze_contract<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
         tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
-        map_name: &ClarityName,
-        key: &Value,
-        with_proof: bool,
+        source: &str,
+        contract_id_opt: Option<&QualifiedContractIdentifier>,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
-
-        let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|clarity_db| {
-                    let key = ClarityDatabase::make_key_for_data_map_entry(
-                        &contract_identifier,
-                        map_name,
-                        key,
-                    );
-                    let (value, marf_proof) = clarity_db
-                        .get_with_proof::<Value>(&key)
-                        .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
-                        .unwrap_or_else(|| {
-                            test_debug!("No value for '{}' in {}", &key, tip);
-                            (Value::none(), "".into())
-                        });
-                    let marf_proof = if with_proof {
-                        test_debug!(
-                            "Return a MARF proof of '{}' of {} bytes",
-                            &key,
-                            marf_proof.as_bytes().len()
-                        );
-                        Some(marf_proof)
-                    } else {
-                        None
-                    };
+        let contract_id = contract_id_opt
+            .cloned()
+            .unwrap_or_else(QualifiedContractIdentifier::transient);
+
+        let analysis_opt = chainstate.with_pending_clarity_tx(
+            &sortdb.index_conn(),
+            tip,
+            true,
+            &[],
+            |clarity_tx| {
+                let mut clarity = clarity_tx.connection().start_transaction_processing();
+                let cost_before = clarity.cost_so_far();
+                let analysis_result = clarity.analyze_smart_contract(&contract_id, source);
+                let analysis_cost = clarity
+                    .cost_so_far()
+                    .checked_sub(&cost_before)
+                    .expect("BUG: total block cost decreased");
+
+                let (contract_ast, contract_analysis) = match analysis_result {
+                    Ok(x) => x,
+                    Err(e) => {
+                        return ContractAnalysisResponse {
+                            okay: false,
+                            interface: None,
+                            errors: vec![ConversationHttp::make_contract_analysis_error(e)],
+                            analysis_cost,
+                            deploy_cost: None,
+                        };
+                    }
+                };
 
-                    let data = format!("0x{}", value.serialize());
-                    MapEntryResponse { data, marf_proof }
-                })
-            }) {
-                Some(data) => HttpResponseType::GetMapEntry(response_metadata, data),
-                None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
-            };
+                // evaluate the top-level expressions, and then roll them back
+                let init_result = clarity.initialize_smart_contract(
+                    &contract_id,
+                    &contract_ast,
+                    source,
+                    |_, _| true,
+                );
+                let deploy_cost = clarity
+                    .cost_so_far()
+                    .checked_sub(&cost_before)
+                    .expect("BUG: total block cost decreased");
+
+                let errors = match init_result {
+                    Ok(_) | Err(clarity_error::AbortedByCallback(..)) => vec![],
+                    Err(e) => vec![ConversationHttp::make_contract_analysis_error(e)],
+                };
+                ContractAnalysisResponse {
+                    okay: errors.is_empty(),
+                    interface: Some(build_contract_interface(&contract_analysis)),
+                    errors,
+                    analysis_cost,
+                    deploy_cost: Some(deploy_cost),
+                }
+            },
+        )?;
 
+        let response = match analysis_opt {
+            Some(analysis) => HttpResponseType::ContractAnalysis(response_metadata, analysis),
+            None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+        };
         response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a POST to run a read-only function call with the given parameters on the given chain
-    /// tip.  Returns the result of the function call.  Returns a CallReadOnlyResponse on success.
-    fn handle_readonly_function_call<W: Write>(
+    /// Describe why a contract could not be analyzed or deployed
+    fn make_contract_analysis_error(e: clarity_error) -> ContractAnalysisError {
+        let diagnostic = match e {
+            clarity_error::Analysis(e) => e.diagnostic,
+            clarity_error::Parse(e) => e.diagnostic,
+            e => {
+                return ContractAnalysisError {
+                    message: e.to_string(),
+                    suggestion: None,
+                    spans: vec![],
+                };
+            }
+        };
+        ContractAnalysisError {
+            message: diagnostic.message,
+            suggestion: diagnostic.suggestion,
+            spans: diagnostic.spans,
+        }
+    }
+
+    /// Handle a POST to validate a proposed block.  Only available if the node has a block proposal
+    /// token configured, and only to clients that present it in the Authorization header.
+    fn handle_validate_block_proposal<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
-        tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
-        function: &ClarityName,
-        sender: &PrincipalData,
-        args: &[Value],
+        parent_consensus_hash: &ConsensusHash,
+        block: &StacksBlock,
+        auth_opt: Option<&String>,
         options: &ConnectionOptions,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
-
-        let args: Vec<_> = args
-            .iter()
-            .map(|x| SymbolicExpression::atom_value(x.clone()))
-            .collect();
+        let token = match options.block_proposal_token {
+            Some(ref token) => token,
+            None => {
+                let response = HttpResponseType::Forbidden(
+                    response_metadata,
+                    "Block proposal validation is not enabled on this node".to_string(),
+                );
+                return response.send(http, fd);
+            }
+        };
+        if auth_opt != Some(token) {
+            let response = HttpResponseType::Unauthorized(
+                response_metadata,
+                "Missing or invalid Authorization header".to_string(),
+            );
+            return response.send(http, fd);
+        }
 
-        let data_opt =
-            chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                let cost_track = clarity_tx
-                    .with_clarity_db_readonly(|clarity_db| {
-                        LimitedCostTracker::new(options.read_only_call_limit.clone(), clarity_db)
-                    })
-                    .map_err(|_| {
-                        ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure)
-                    })?;
+        let block_hash = block.block_hash();
+        let (reason_code, reason, execution_cost) = match chainstate.validate_block_proposal(
+            &sortdb.index_conn(),
+            parent_consensus_hash,
+            block,
+        ) {
+            Ok(cost) => (None, None, Some(cost)),
+            Err(chain_error::NoSuchBlockError) => (
+                Some("UnknownParent"),
+                Some(format!(
+                    "Parent block {}/{} or its microblock stream is not known to this node",
+                    parent_consensus_hash, &block.header.parent_block
+                )),
+                None,
+            ),
+            Err(chain_error::InvalidStacksMicroblock(msg, _)) => {
+                (Some("InvalidParentMicroblocks"), Some(msg), None)
+            }
+            Err(chain_error::InvalidStacksBlock(msg)) => (Some("InvalidBlock"), Some(msg), None),
+            Err(e) => {
+                warn!(
+                    "Failed to validate proposed block {}: {:?}",
+                    &block_hash, &e
+                );
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to validate block proposal".to_string(),
+                );
+                return response.send(http, fd);
+            }
+        };
 
-                clarity_tx.with_readonly_clarity_env(sender.clone(), cost_track, |env| {
-                    env.execute_contract(&contract_identifier, function.as_str(), &args, true)
-                })
-            });
+        let response = HttpResponseType::BlockProposal(
+            response_metadata,
+            BlockProposalResponse {
+                block_hash: format!("{}", &block_hash),
+                accepted: reason_code.is_none(),
+                reason_code: reason_code.map(|code| code.to_string()),
+                reason,
+                execution_cost,
+            },
+        );
+        response.send(http, fd)
+    }
 
-        let response = match data_opt {
-            Some(Ok(data)) => HttpResponseType::CallReadOnlyFunction(
+    /// Handle a GET for the miner's state.  Only available if the node has an admin token
+    /// configured, and only to clients that present it in the Authorization header.
+    fn handle_get_miner_status<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        auth_opt: Option<&String>,
+        options: &ConnectionOptions,
+        handler_args: &RPCHandlerArgs,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let token = match options.admin_token {
+            Some(ref token) => token,
+            None => {
+                let response = HttpResponseType::Forbidden(
+                    response_metadata,
+                    "Admin endpoints are not enabled on this node".to_string(),
+                );
+                return response.send(http, fd);
+            }
+        };
+        if auth_opt != Some(token) {
+            let response = HttpResponseType::Unauthorized(
                 response_metadata,
-                CallReadOnlyResponse {
-                    okay: true,
-                    result: Some(format!("0x{}", data.serialize())),
-                    cause: None,
-                },
-            ),
-            Some(Err(e)) => HttpResponseType::CallReadOnlyFunction(
+                "Missing or invalid Authorization header".to_string(),
+            );
+            return response.send(http, fd);
+        }
+
+        let mut miner_status = match handler_args.miner_status {
+            Some(ref miner_status) => match miner_status.lock() {
+                Ok(miner_status) => miner_status.clone(),
+                Err(_e) => {
+                    let response = HttpResponseType::ServerError(
+                        response_metadata,
+                        "Miner status is unavailable".to_string(),
+                    );
+                    return response.send(http, fd);
+                }
+            },
+            None => RPCMinerStatusData::new(false),
+        };
+
+        // count confirmations of the commits the node has seen mined
+        let burnchain_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        let sort_handle = sortdb.index_handle_at_tip();
+        for commit in miner_status.inflight_commits.iter_mut() {
+            let txid = match Txid::from_hex(&commit.txid) {
+                Ok(txid) => txid,
+                Err(_) => continue,
+            };
+            if let Some(commit_op) = sort_handle.get_block_commit_by_txid(&txid)? {
+                if commit_op.block_height <= burnchain_tip.block_height {
+                    commit.burn_block_height = Some(commit_op.block_height);
+                    commit.confirmations = burnchain_tip.block_height - commit_op.block_height + 1;
+                }
+            }
+        }
+
+        let response = HttpResponseType::MinerStatus(response_metadata, miner_status);
+        response.send(http, fd)
+    }
+
+    /// Handle a request to list, schedule or cancel the contract-calls the node sends on its own.
+    /// Authenticated like the miner's state, and only available if the node has a call
+    /// scheduler.
+    fn handle_scheduled_calls<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        op: &ScheduledCallsOp,
+        auth_opt: Option<&String>,
+        options: &ConnectionOptions,
+        handler_args: &RPCHandlerArgs,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let token = match options.admin_token {
+            Some(ref token) => token,
+            None => {
+                let response = HttpResponseType::Forbidden(
+                    response_metadata,
+                    "Admin endpoints are not enabled on this node".to_string(),
+                );
+                return response.send(http, fd);
+            }
+        };
+        if auth_opt != Some(token) {
+            let response = HttpResponseType::Unauthorized(
                 response_metadata,
-                CallReadOnlyResponse {
-                    okay: false,
-                    result: None,
-                    cause: Some(e.to_string()),
-                },
-            ),
-            None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+                "Missing or invalid Authorization header".to_string(),
+            );
+            return response.send(http, fd);
+        }
+        let path = match handler_args.scheduled_calls_path {
+            Some(ref path) => path,
+            None => {
+                let response = HttpResponseType::Forbidden(
+                    response_metadata,
+                    "Scheduled calls are not enabled on this node".to_string(),
+                );
+                return response.send(http, fd);
+            }
         };
 
-        response.send(http, fd).map(|_| ())
+        let result = ScheduledCallDB::open(path).and_then(|mut db| match op {
+            ScheduledCallsOp::List => db
+                .get_all()
+                .map(|calls| HttpResponseType::ScheduledCalls(response_metadata.clone(), calls)),
+            ScheduledCallsOp::Add(request) => db
+                .add(request)
+                .map(|call| HttpResponseType::ScheduledCall(response_metadata.clone(), call)),
+            ScheduledCallsOp::Cancel(id) => db.cancel(*id).map(|call_opt| match call_opt {
+                Some(call) => HttpResponseType::ScheduledCall(response_metadata.clone(), call),
+                None => HttpResponseType::NotFound(
+                    response_metadata.clone(),
+                    format!("No such scheduled call: {}", id),
+                ),
+            }),
+        });
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Failed to access scheduled calls: {:?}", &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to access scheduled calls".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
     }
 
-    /// Handle a GET to fetch a contract's source code, given the chain tip.  Optionally returns a
-    /// MARF proof as well.
-    fn handle_get_contract_src<W: Write>(
+    /// Load an account's balance and nonce from the given Clarity connection.  MARF proofs are only
+    /// loaded if `with_proof` is set.
+    fn get_account_entry<C: ClarityConnection>(
+        clarity_tx: &mut C,
+        account: &PrincipalData,
+        with_proof: bool,
+    ) -> AccountEntryResponse {
+        clarity_tx.with_clarity_db_readonly(|clarity_db| {
+            let key = ClarityDatabase::make_key_for_account_balance(account);
+            let burn_block_height = clarity_db.get_current_burnchain_block_height() as u64;
+            let (balance, balance_proof) = if with_proof {
+                clarity_db
+                    .get_with_proof::<STXBalance>(&key)
+                    .map(|(a, b)| (a, Some(format!("0x{}", b.to_hex()))))
+                    .unwrap_or_else(|| (STXBalance::zero(), Some("".into())))
+            } else {
+                clarity_db
+                    .get::<STXBalance>(&key)
+                    .map(|a| (a, None))
+                    .unwrap_or_else(|| (STXBalance::zero(), None))
+            };
+            let key = ClarityDatabase::make_key_for_account_nonce(account);
+            let (nonce, nonce_proof) = if with_proof {
+                clarity_db
+                    .get_with_proof(&key)
+                    .map(|(a, b)| (a, Some(format!("0x{}", b.to_hex()))))
+                    .unwrap_or_else(|| (0, Some("".into())))
+            } else {
+                clarity_db
+                    .get(&key)
+                    .map(|a| (a, None))
+                    .unwrap_or_else(|| (0, None))
+            };
+
+            let unlocked = balance.get_available_balance_at_burn_block(burn_block_height);
+            let locked = balance.get_total_locked_balance_at_burn_block(burn_block_height);
+            let (pox_locked, pox_unlock_height) =
+                balance.get_locked_balance_at_burn_block(burn_block_height);
+            let independent_locks = balance.get_independent_locks_at_burn_block(burn_block_height);
+            let total = balance.get_total_balance();
+
+            // report when the last of the account's locks expires
+            let unlock_height = independent_locks
+                .iter()
+                .map(|lock| lock.unlock_height)
+                .fold(pox_unlock_height, cmp::max);
+            let mut locks = vec![];
+            if !independent_locks.is_empty() {
+                if pox_locked > 0 {
+                    locks.push(AccountLockEntry {
+                        amount: format!("0x{}", to_hex(&pox_locked.to_be_bytes())),
+                        unlock_height: pox_unlock_height,
+                    });
+                }
+                for lock in independent_locks.into_iter() {
+                    locks.push(AccountLockEntry {
+                        amount: format!("0x{}", to_hex(&lock.amount.to_be_bytes())),
+                        unlock_height: lock.unlock_height,
+                    });
+                }
+            }
+
+            let balance = format!("0x{}", to_hex(&unlocked.to_be_bytes()));
+            let locked = format!("0x{}", to_hex(&locked.to_be_bytes()));
+            let total_balance = format!("0x{}", to_hex(&total.to_be_bytes()));
+
+            AccountEntryResponse {
+                balance,
+                locked,
+                unlock_height,
+                total_balance,
+                nonce,
+                locks,
+                balance_proof,
+                nonce_proof,
+            }
+        })
+    }
+
+    /// Load the pending transactions to apply on top of the unconfirmed state for a
+    /// mempool-view query.
+    fn load_mempool_view_txs(
+        mempool: &MemPoolDB,
+        options: &ConnectionOptions,
+    ) -> Result<Vec<StacksTransaction>, net_error> {
+        let txs = MemPoolDB::get_pending_txs(mempool.conn(), options.mempool_view_max_txs)?
+            .into_iter()
+            .map(|tx_info| tx_info.tx)
+            .collect();
+        Ok(txs)
+    }
+
+    /// Handle a GET on an existing account, given the current chain tip.  Optionally supplies a
+    /// MARF proof for each account detail loaded from the chain tip.  If `view` includes the
+    /// mempool, the node's pending transactions are applied on top of the unconfirmed state at
+    /// the tip, and no proofs are supplied.
+    fn handle_get_account_entry<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
+        mempool: &MemPoolDB,
         tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
+        account: &PrincipalData,
         with_proof: bool,
+        view: RPCStateView,
+        options: &ConnectionOptions,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
-        let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|db| {
-                    let source = db.get_contract_src(&contract_identifier)?;
-                    let contract_commit_key =
-                        MarfedKV::make_contract_hash_key(&contract_identifier);
-                    let (contract_commit, proof) = db
-                        .get_with_proof::<ContractCommitment>(&contract_commit_key)
-                        .expect("BUG: obtained source, but couldn't get MARF proof.");
-                    let marf_proof = if with_proof {
-                        Some(proof.to_hex())
-                    } else {
-                        None
-                    };
-                    let publish_height = contract_commit.block_height;
-                    Some(ContractSrcResponse {
-                        source,
-                        publish_height,
-                        marf_proof,
-                    })
-                })
-            }) {
-                Some(Some(data)) => HttpResponseType::GetContractSrc(response_metadata, data),
-                Some(None) => HttpResponseType::NotFound(
-                    response_metadata,
-                    "No contract source data found".into(),
-                ),
-                None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
-            };
+        let data_opt = match view {
+            RPCStateView::UnconfirmedWithMempool => {
+                let pending_txs = ConversationHttp::load_mempool_view_txs(mempool, options)?;
+                chainstate.with_pending_clarity_tx(
+                    &sortdb.index_conn(),
+                    tip,
+                    true,
+                    &pending_txs,
+                    |clarity_tx| ConversationHttp::get_account_entry(clarity_tx, account, false),
+                )?
+            }
+            _ => chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                ConversationHttp::get_account_entry(clarity_tx, account, with_proof)
+            }),
+        };
+
+        let response = match data_opt {
+            Some(data) => HttpResponseType::GetAccount(response_metadata, data),
+            None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+        };
 
         response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a GET to fetch a contract's analysis data, given the chain tip.  Note that this isn't
-    /// something that's anchored to the blockchain, and can be different across different versions
-    /// of Stacks -- callers must trust the Stacks node to return correct analysis data.
-    /// Callers who don't trust the Stacks node should just fetch the contract source
-    /// code and analyze it offline.
-    fn handle_get_contract_abi<W: Write>(
+    /// Handle a GET on the fungible and non-fungible tokens held by an account, given the current
+    /// chain tip.  Candidate assets come from the chainstate's asset holdings index; each one is
+    /// then checked against the Clarity DB at the chain tip, so only current holdings are
+    /// reported.
+    fn handle_get_account_assets<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
         tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
+        account: &PrincipalData,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let holdings = match StacksChainState::get_account_asset_holdings(chainstate.db(), account)
+        {
+            Ok(holdings) => holdings,
+            Err(e) => {
+                warn!("Failed to load asset holdings {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query asset holdings".to_string(),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
 
         let response =
             match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_analysis_db_readonly(|db| {
-                    let contract = db.load_contract(&contract_identifier)?;
-                    contract.contract_interface
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let mut fungible_tokens = vec![];
+                    let mut non_fungible_tokens = vec![];
+                    for holding in holdings.into_iter() {
+                        let contract_identifier = &holding.asset_identifier.contract_identifier;
+                        let asset_name = &holding.asset_identifier.asset_name;
+                        match holding.value {
+                            None => {
+                                // the token may not exist in this fork
+                                let balance = clarity_db
+                                    .get_ft_balance(contract_identifier, asset_name, account)
+                                    .unwrap_or(0);
+                                if balance > 0 {
+                                    fungible_tokens.push(AccountFungibleTokenEntry {
+                                        asset_identifier: holding.asset_identifier.to_string(),
+                                        balance: format!("{}", balance),
+                                    });
+                                }
+                            }
+                            Some(value) => {
+                                let owner = clarity_db
+                                    .get_nft_owner(contract_identifier, asset_name, &value)
+                                    .ok();
+                                if owner.as_ref() == Some(account) {
+                                    non_fungible_tokens.push(AccountNonFungibleTokenEntry {
+                                        asset_identifier: holding.asset_identifier.to_string(),
+                                        value: format!("0x{}", value.serialize()),
+                                    });
+                                }
+                            }
+                        }
+                    }
+                    AccountAssetsResponse {
+                        fungible_tokens,
+                        non_fungible_tokens,
+                    }
                 })
             }) {
-                Some(Some(data)) => HttpResponseType::GetContractABI(response_metadata, data),
-                Some(None) => HttpResponseType::NotFound(
-                    response_metadata,
-                    "No contract interface data found".into(),
-                ),
+                Some(data) => HttpResponseType::GetAccountAssets(response_metadata, data),
                 None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
             };
 
         response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a GET unconfirmed microblock stream.  Start streaming the reply.
-    /// The response's preamble (but not the block data) will be synchronously written to the fd
-    /// (so use a fd that can buffer!)
-    /// Return a BlockStreamData struct for the block that we're sending, so we can continue to
-    /// make progress sending it.
-    fn handle_getmicroblocks_unconfirmed<W: Write>(
+    /// Handle a POST to have the node's miner produce a block right away.  Only available if the
+    /// node runs a dev burnchain, and only to clients on the loopback interface.
+    fn handle_dev_mine<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        index_anchor_block_hash: &StacksBlockId,
-        min_seq: u16,
-        chainstate: &StacksChainState,
-    ) -> Result<Option<BlockStreamData>, net_error> {
+        peer_addr: &SocketAddr,
+        request: &RPCDevMineRequest,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        mempool: &MemPoolDB,
+        handler_args: &RPCHandlerArgs,
+    ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-
-        // do we have this unconfirmed microblock stream?
-        match chainstate.has_any_staging_microblock_indexed(index_anchor_block_hash, min_seq) {
-            Ok(false) => {
-                // nope
-                let response = HttpResponseType::NotFound(
+        let dev_mine = match handler_args.dev_mine {
+            Some(ref dev_mine) if peer_addr.ip().is_loopback() => dev_mine,
+            _ => {
+                let response = HttpResponseType::Forbidden(
                     response_metadata,
-                    format!(
-                        "No such unconfirmed microblock stream for {} at or after {}",
-                        index_anchor_block_hash.to_hex(),
-                        min_seq
-                    ),
+                    "On-demand block production is only available to local clients of a dev node"
+                        .to_string(),
                 );
-                response.send(http, fd).and_then(|_| Ok(None))
+                return response.send(http, fd);
             }
-            Err(e) => {
-                // nope
-                warn!(
-                    "Failed to serve confirmed microblock stream {:?}: {:?}",
-                    req, &e
+        };
+
+        for txid_hex in request.txids.iter() {
+            let txid = Txid::from_hex(txid_hex)
+                .map_err(|_e| net_error::DeserializeError(format!("Invalid txid {}", txid_hex)))?;
+            if MemPoolDB::get_tx(mempool.conn(), &txid)?.is_none() {
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("Transaction {} is not in the mempool", txid_hex),
                 );
-                let response = HttpResponseType::ServerError(
+                return response.send(http, fd);
+            }
+        }
+
+        let burn_block_height =
+            SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?.block_height;
+        let stacks_tip_height = match chainstate.get_stacks_chain_tip(sortdb)? {
+            Some(tip) => tip.height,
+            None => 0,
+        };
+
+        dev_mine(request.clone());
+
+        let response = HttpResponseType::DevMine(
+            response_metadata,
+            RPCDevMineResponse {
+                burn_block_height,
+                stacks_tip_height,
+            },
+        );
+        response.send(http, fd)
+    }
+
+    /// Handle a POST to garbage-collect the mempool right away, instead of waiting for the next
+    /// routine pass.  Only clients on the loopback interface may do this.
+    fn handle_mempool_gc<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        peer_addr: &SocketAddr,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        mempool: &mut MemPoolDB,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !peer_addr.ip().is_loopback() {
+            let response = HttpResponseType::Forbidden(
+                response_metadata,
+                "Mempool garbage-collection is only available to local clients".to_string(),
+            );
+            return response.send(http, fd);
+        }
+
+        let chain_height = match chainstate.get_stacks_chain_tip(sortdb)? {
+            Some(tip) => tip.height,
+            None => 0,
+        };
+
+        match mempool.prune(chain_height) {
+            Ok(result) => {
+                let response = HttpResponseType::MemPoolGC(
                     response_metadata,
-                    format!(
-                        "Failed to query unconfirmed microblock stream for {} at or after {}",
-                        index_anchor_block_hash.to_hex(),
-                        min_seq
-                    ),
+                    MemPoolGCResponse {
+                        expired: result.expired,
+                        evicted: result.evicted,
+                        remaining_txs: result.remaining_txs,
+                        remaining_bytes: result.remaining_bytes,
+                    },
                 );
-                response.send(http, fd).and_then(|_| Ok(None))
+                response.send(http, fd)
             }
-            Ok(true) => {
-                // yup! start streaming it back
-                let (response, stream_opt) = match BlockStreamData::new_microblock_unconfirmed(
-                    chainstate,
-                    index_anchor_block_hash.clone(),
-                    min_seq,
-                ) {
-                    Ok(stream) => (
-                        HttpResponseType::MicroblockStream(response_metadata),
-                        Some(stream),
-                    ),
-                    Err(chain_error::NoSuchBlockError) => (
-                        HttpResponseType::NotFound(
-                            response_metadata,
-                            format!(
-                                "No such unconfirmed microblock stream starting with {}",
-                                index_anchor_block_hash.to_hex()
-                            ),
-                        ),
-                        None,
-                    ),
-                    Err(_e) => {
-                        debug!(
-                            "Failed to load unconfirmed microblock stream {}: {:?}",
-                            &index_anchor_block_hash, &_e
-                        );
-                        (
-                            HttpResponseType::ServerError(
-                                response_metadata,
-                                format!(
-                                    "Failed to query unconfirmed microblock stream {}",
-                                    index_anchor_block_hash.to_hex()
-                                ),
-                            ),
-                            None,
-                        )
-                    }
-                };
-                response.send(http, fd).and_then(|_| Ok(stream_opt))
+            Err(e) => {
+                warn!("Failed to garbage-collect mempool: {:?}", &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to garbage-collect mempool".to_string(),
+                );
+                response.send(http, fd)?;
+                Err(net_error::DBError(e))
             }
         }
     }
 
-    /// Handle a GET unconfirmed transaction.
-    /// The response will be synchronously written to the fd.
-    fn handle_gettransaction_unconfirmed<W: Write>(
+    /// Handle a POST to simulate assembling a block from the mempool.  The miner's transaction
+    /// selection is run against the canonical chain tip, but nothing is mined or stored.  Only
+    /// clients on the loopback interface may do this.
+    fn handle_simulate_block<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
+        peer_addr: &SocketAddr,
+        sortdb: &SortitionDB,
         chainstate: &StacksChainState,
         mempool: &MemPoolDB,
-        txid: &Txid,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
+        if !peer_addr.ip().is_loopback() {
+            let response = HttpResponseType::Forbidden(
+                response_metadata,
+                "Block simulation is only available to local clients".to_string(),
+            );
+            return response.send(http, fd);
+        }
 
-        // present in the unconfirmed state?
-        if let Some(ref unconfirmed) = chainstate.unconfirmed_state.as_ref() {
-            if let Some((transaction, mblock_hash, seq)) =
-                unconfirmed.get_unconfirmed_transaction(txid)
-            {
-                let response = HttpResponseType::UnconfirmedTransaction(
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+        let parent_header = match StacksChainState::get_anchored_block_header_info(
+            chainstate.db(),
+            &consensus_hash,
+            &block_hash,
+        )? {
+            Some(header) => header,
+            None => {
+                let response = HttpResponseType::NotFound(
                     response_metadata,
-                    UnconfirmedTransactionResponse {
-                        status: UnconfirmedTransactionStatus::Microblock {
-                            block_hash: mblock_hash,
-                            seq: seq,
-                        },
-                        tx: to_hex(&transaction.serialize_to_vec()),
+                    "Chain tip not found".to_string(),
+                );
+                return response.send(http, fd);
+            }
+        };
+
+        let execution_budget = chainstate.block_limit.clone();
+        match StacksBlockBuilder::simulate_anchored_block(
+            chainstate,
+            &sortdb.index_conn(),
+            mempool,
+            &parent_header,
+            execution_budget.clone(),
+        ) {
+            Ok((txs, total_fees, execution_cost, block_size)) => {
+                let percent_full = execution_cost.proportion_of_limit(&execution_budget);
+                let response = HttpResponseType::BlockSimulation(
+                    response_metadata,
+                    BlockSimulationResponse {
+                        parent_index_block_hash: format!("{}", &parent_header.index_block_hash()),
+                        stacks_height: parent_header.block_height + 1,
+                        txs: txs
+                            .iter()
+                            .map(|tx| SimulatedBlockTransaction {
+                                txid: format!("{}", &tx.txid()),
+                                fee: tx.get_fee_rate(),
+                                tx_len: tx.tx_len(),
+                            })
+                            .collect(),
+                        total_fees,
+                        block_size,
+                        execution_cost,
+                        execution_budget,
+                        percent_full,
                     },
                 );
-                return response.send(http, fd).map(|_| ());
+                response.send(http, fd)
+            }
+            Err(e) => {
+                warn!("Failed to simulate block: {:?}", &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to simulate block".to_string(),
+                );
+                response.send(http, fd)
             }
         }
+    }
 
-        // present in the mempool?
-        if let Some(txinfo) = MemPoolDB::get_tx(mempool.conn(), txid)? {
-            let response = HttpResponseType::UnconfirmedTransaction(
-                response_metadata,
-                UnconfirmedTransactionResponse {
-                    status: UnconfirmedTransactionStatus::Mempool,
-                    tx: to_hex(&txinfo.tx.serialize_to_vec()),
-                },
-            );
-            return response.send(http, fd).map(|_| ());
-        }
+    /// Handle a GET on an account's nonces, given the current chain tip.  Reports the account's
+    /// nonce at the chain tip, along with the nonces its transactions use in the mempool, so
+    /// that clients building chains of transactions can pick the next nonce and find any gaps.
+    fn handle_get_account_nonces<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        mempool: &MemPoolDB,
+        tip: &StacksBlockId,
+        account: &PrincipalData,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
 
-        // not found
-        let response = HttpResponseType::NotFound(
+        let confirmed_nonce_opt =
+            chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx
+                    .with_clarity_db_readonly(|clarity_db| clarity_db.get_account_nonce(account))
+            });
+
+        let confirmed_nonce = match confirmed_nonce_opt {
+            Some(nonce) => nonce,
+            None => {
+                let response =
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into());
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        // only standard principals can send transactions
+        let mempool_nonces = match account {
+            PrincipalData::Standard(ref principal) => {
+                let address = StacksAddress::from(principal.clone());
+                match MemPoolDB::get_nonces_for_address(mempool.conn(), &address) {
+                    Ok(nonces) => nonces,
+                    Err(e) => {
+                        warn!("Failed to load mempool nonces {:?}: {:?}", req, &e);
+                        let response = HttpResponseType::ServerError(
+                            response_metadata,
+                            "Failed to query mempool nonces".to_string(),
+                        );
+                        return response.send(http, fd).map(|_| ());
+                    }
+                }
+            }
+            PrincipalData::Contract(_) => vec![],
+        };
+
+        // mempool transactions with already-confirmed nonces can never be mined
+        let pending_nonces: HashSet<u64> = mempool_nonces
+            .into_iter()
+            .filter(|nonce| *nonce >= confirmed_nonce)
+            .collect();
+
+        let last_mempool_nonce = pending_nonces.iter().max().cloned();
+        let (next_nonce, missing_nonces) = match last_mempool_nonce {
+            Some(last_nonce) => (
+                last_nonce + 1,
+                (confirmed_nonce..last_nonce)
+                    .filter(|nonce| !pending_nonces.contains(nonce))
+                    .collect(),
+            ),
+            None => (confirmed_nonce, vec![]),
+        };
+
+        let response = HttpResponseType::GetAccountNonces(
             response_metadata,
-            format!("No such unconfirmed transaction {}", txid),
+            AccountNoncesResponse {
+                confirmed_nonce,
+                last_mempool_nonce,
+                next_nonce,
+                missing_nonces,
+            },
         );
-        return response.send(http, fd).map(|_| ());
+        response.send(http, fd).map(|_| ())
     }
 
-    /// Load up the canonical Stacks chain tip.  Note that this is subject to both burn chain block
-    /// Stacks block availability -- different nodes with different partial replicas of the Stacks chain state
-    /// will return different values here.
-    /// tip_opt is given by the HTTP request as the optional query parameter for the chain tip
-    /// hash.  It will be None if there was no paramter given.
-    /// The order of chain tips this method prefers is as follows:
-    /// * tip_opt, if it's Some(..),
-    /// * the unconfirmed canonical stacks chain tip, if initialized
-    /// * the confirmed canonical stacks chain tip
-    fn handle_load_stacks_chain_tip<W: Write>(
+    /// Handle a GET on an account's summary, given the current chain tip.  Reports the account's
+    /// balance and nonce at the chain tip, what kind of address it is, and the block in which it
+    /// first appeared in the chain tip's fork, if it has.
+    fn handle_get_account_summary<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        tip_opt: Option<&StacksBlockId>,
         sortdb: &SortitionDB,
-        chainstate: &StacksChainState,
-    ) -> Result<Option<StacksBlockId>, net_error> {
-        match tip_opt {
-            Some(tip) => Ok(Some(*tip).clone()),
-            None => match chainstate.get_stacks_chain_tip(sortdb)? {
-                Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
-                    &tip.consensus_hash,
-                    &tip.anchored_block_hash,
-                ))),
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        account: &PrincipalData,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        let entry =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                ConversationHttp::get_account_entry(clarity_tx, account, false)
+            }) {
+                Some(entry) => entry,
                 None => {
-                    let response_metadata = HttpResponseMetadata::from(req);
-                    warn!("Failed to load Stacks chain tip");
+                    let response =
+                        HttpResponseType::NotFound(response_metadata, "Chain tip not found".into());
+                    return response.send(http, fd).map(|_| ());
+                }
+            };
+
+        let first_seen = match chainstate.get_principal_first_seen(account, tip) {
+            Ok(first_seen) => first_seen.map(|first_seen| AccountFirstSeenEntry {
+                block_height: first_seen.block_height,
+                index_block_hash: format!("{}", &first_seen.index_block_hash),
+                txid: format!("{}", &first_seen.txid),
+            }),
+            Err(e) => {
+                warn!("Failed to load first-seen block {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query first-seen block".to_string(),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        let response = HttpResponseType::GetAccountSummary(
+            response_metadata,
+            AccountSummaryResponse {
+                balance: entry.balance,
+                locked: entry.locked,
+                total_balance: entry.total_balance,
+                nonce: entry.nonce,
+                kind: AddressKind::of_principal(account),
+                first_seen,
+            },
+        );
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET on the transactions that involved a principal in the chain tip's fork, newest
+    /// first, a page at a time.
+    fn handle_get_principal_transactions<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        principal: &PrincipalData,
+        offset: u64,
+        limit: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let (total, transactions) =
+            match chainstate.get_principal_transactions(principal, tip, offset, limit) {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("Failed to load transactions of {}: {:?}", principal, &e);
                     let response = HttpResponseType::ServerError(
                         response_metadata,
-                        format!("Failed to load Stacks chain tip"),
+                        "Failed to query principal transactions".to_string(),
                     );
-                    response.send(http, fd).and_then(|_| Ok(None))
+                    return response.send(http, fd).map(|_| ());
                 }
+            };
+
+        let results = transactions
+            .into_iter()
+            .map(|tx| PrincipalTransactionEntry {
+                txid: format!("0x{}", &tx.txid),
+                index_block_hash: format!("0x{}", &tx.index_block_hash),
+                block_height: tx.block_height,
+                tx_index: tx.tx_index,
+            })
+            .collect();
+
+        let response = HttpResponseType::PrincipalTransactions(
+            response_metadata,
+            PrincipalTransactionsResponse {
+                principal: principal.to_string(),
+                offset,
+                limit,
+                total,
+                results,
             },
-        }
+        );
+        response.send(http, fd).map(|_| ())
     }
 
-    fn handle_load_stacks_chain_tip_hashes<W: Write>(
+    /// Handle a GET on the miner rewards scheduled for an address in the chain tip's fork which
+    /// have not matured yet, oldest first.
+    fn handle_get_pending_miner_rewards<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        tip_opt: Option<&StacksBlockId>,
-        sortdb: &SortitionDB,
         chainstate: &StacksChainState,
-    ) -> Result<Option<(ConsensusHash, BlockHeaderHash)>, net_error> {
-        match tip_opt {
-            Some(tip) => match chainstate.get_block_header_hashes(&tip)? {
-                Some((ch, bl)) => {
-                    return Ok(Some((ch, bl)));
-                }
-                None => {}
-            },
-            None => match chainstate.get_stacks_chain_tip(sortdb)? {
-                Some(tip) => {
-                    return Ok(Some((tip.consensus_hash, tip.anchored_block_hash)));
-                }
-                None => {}
-            },
-        }
+        tip: &StacksBlockId,
+        address: &StacksAddress,
+    ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        warn!("Failed to load Stacks chain tip");
-        let response = HttpResponseType::ServerError(
-            response_metadata,
-            format!("Failed to load Stacks chain tip"),
-        );
-        response.send(http, fd).and_then(|_| Ok(None))
+        let response = match chainstate.get_pending_miner_rewards(address, tip) {
+            Ok(Some(rewards)) => {
+                let results = rewards
+                    .into_iter()
+                    .map(|reward| PendingMinerRewardEntry {
+                        index_block_hash: format!(
+                            "0x{}",
+                            StacksBlockHeader::make_index_block_hash(
+                                &reward.schedule.consensus_hash,
+                                &reward.schedule.block_hash
+                            )
+                        ),
+                        block_hash: format!("0x{}", &reward.schedule.block_hash),
+                        consensus_hash: format!("0x{}", &reward.schedule.consensus_hash),
+                        stacks_block_height: reward.schedule.stacks_block_height,
+                        miner: reward.schedule.miner,
+                        recipient: reward
+                            .schedule
+                            .recipient
+                            .as_ref()
+                            .map(|recipient| recipient.to_string()),
+                        coinbase: reward.schedule.coinbase.to_string(),
+                        tx_fees_anchored: reward.schedule.tx_fees_anchored.to_string(),
+                        tx_fees_streamed: reward.schedule.tx_fees_streamed.to_string(),
+                        burnchain_commit_burn: reward.schedule.burnchain_commit_burn,
+                        vtxindex: reward.schedule.vtxindex,
+                        maturity_height: reward.maturity_height,
+                        earliest_maturity_burn_height: reward.earliest_maturity_burn_height,
+                    })
+                    .collect();
+                HttpResponseType::PendingMinerRewards(
+                    response_metadata,
+                    PendingMinerRewardsResponse {
+                        address: address.to_string(),
+                        results,
+                    },
+                )
+            }
+            Ok(None) => {
+                HttpResponseType::NotFound(response_metadata, format!("No such chain tip {}", tip))
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to load pending miner rewards of {}: {:?}",
+                    address, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query pending miner rewards".to_string(),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a transaction.  Directly submit it to the mempool so the client can see any
-    /// rejection reasons up-front (different from how the peer network handles it).  Indicate
-    /// whether or not the transaction was accepted (and thus needs to be forwarded) in the return
-    /// value.
-    fn handle_post_transaction<W: Write>(
+    /// Handle a GET on the unconfirmed microblock stream built on the chain tip, starting at
+    /// `min_seq`.  Only the stream up to its first fork is returned.  A tip with no microblocks
+    /// yet has an empty stream.
+    fn handle_get_unconfirmed_microblock_stream<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        chainstate: &mut StacksChainState,
-        consensus_hash: ConsensusHash,
-        block_hash: BlockHeaderHash,
-        mempool: &mut MemPoolDB,
-        tx: StacksTransaction,
-        atlasdb: &mut AtlasDB,
-        attachment: Option<Attachment>,
-    ) -> Result<bool, net_error> {
-        let txid = tx.txid();
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        min_seq: u16,
+    ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let (response, accepted) = if mempool.has_tx(&txid) {
-            (
-                HttpResponseType::TransactionID(response_metadata, txid),
-                false,
-            )
-        } else {
-            match mempool.submit(chainstate, &consensus_hash, &block_hash, tx) {
-                Ok(_) => (
-                    HttpResponseType::TransactionID(response_metadata, txid),
-                    true,
-                ),
-                Err(e) => (
-                    HttpResponseType::BadRequestJSON(response_metadata, e.into_json(&txid)),
-                    false,
+        let response = match StacksChainState::load_descendant_staging_microblock_stream(
+            chainstate.db(),
+            tip,
+            min_seq,
+            u16::MAX,
+        ) {
+            Ok(microblocks_opt) => HttpResponseType::UnconfirmedMicroblockStream(
+                response_metadata,
+                UnconfirmedMicroblockStreamResponse::from_microblocks(
+                    tip,
+                    &microblocks_opt.unwrap_or(vec![]),
                 ),
+            ),
+            Err(e) => {
+                warn!(
+                    "Failed to load unconfirmed microblock stream of {}: {:?}",
+                    tip, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to query unconfirmed microblock stream of {}", tip),
+                )
             }
         };
+        response.send(http, fd).map(|_| ())
+    }
 
-        if let Some(attachment) = attachment {
-            if accepted {
-                atlasdb
-                    .insert_new_attachment(&attachment)
-                    .map_err(|e| net_error::DBError(e))?;
+    /// Handle a GET on a transaction's receipt, as recorded when the block that mined it was
+    /// processed.  Only receipts of transactions mined in the chain tip's fork are reported.
+    fn handle_get_transaction_receipt<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        txid: &Txid,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let receipt = match chainstate.get_transaction_receipt(txid, tip) {
+            Ok(Some(receipt)) => receipt,
+            Ok(None) => {
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("No receipt for transaction {} in this fork", txid),
+                );
+                return response.send(http, fd).map(|_| ());
             }
-        }
-        response.send(http, fd).and_then(|_| Ok(accepted))
+            Err(e) => {
+                warn!("Failed to load receipt of {}: {:?}", txid, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query transaction receipt".to_string(),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        let block_height = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            &receipt.index_block_hash,
+        ) {
+            Ok(Some(header)) => header.block_height,
+            Ok(None) | Err(_) => {
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load the block header of the transaction receipt".to_string(),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        let response = HttpResponseType::TransactionReceipt(
+            response_metadata,
+            TransactionReceiptResponse {
+                txid: format!("0x{}", &receipt.txid),
+                index_block_hash: format!("0x{}", &receipt.index_block_hash),
+                block_height,
+                tx_index: receipt.tx_index,
+                raw_tx: format!("0x{}", &receipt.raw_tx),
+                result: format!("0x{}", receipt.result.serialize()),
+                post_condition_aborted: receipt.post_condition_aborted,
+                events: receipt.events,
+                asset_map: receipt.asset_map,
+                execution_cost: receipt.execution_cost,
+                stx_burned: receipt.stx_burned.to_string(),
+                contract_abi: receipt.contract_abi,
+            },
+        );
+        response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a microblock.  Directly submit it to the microblock store so the client can see any
-    /// rejection reasons up-front (different from how the peer network handles it).  Indicate
-    /// whether or not the microblock was accepted (and thus needs to be forwarded) in the return
-    /// value.
-    fn handle_post_microblock<W: Write>(
+    /// Handle a GET on a smart contract's data map, given the current chain tip.  Optionally
+    /// supplies a MARF proof for the value.
+    fn handle_get_map_entry<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        consensus_hash: ConsensusHash,
-        block_hash: BlockHeaderHash,
+        sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
-        microblock: StacksMicroblock,
-    ) -> Result<bool, net_error> {
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        map_name: &ClarityName,
+        key: &Value,
+        with_proof: bool,
+    ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let (response, accepted) = match chainstate.preprocess_streamed_microblock(
-            &consensus_hash,
-            &block_hash,
-            &microblock,
-        ) {
-            Ok(accepted) => {
-                if accepted {
-                    debug!(
-                        "Accepted uploaded microblock {}/{}-{}",
-                        &consensus_hash,
-                        &block_hash,
-                        &microblock.block_hash()
-                    );
-                } else {
-                    debug!(
-                        "Did not accept microblock {}/{}-{}",
-                        &consensus_hash,
-                        &block_hash,
-                        &microblock.block_hash()
-                    );
-                }
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
-                (
-                    HttpResponseType::MicroblockHash(response_metadata, microblock.block_hash()),
-                    accepted,
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                ConversationHttp::get_map_entry(
+                    clarity_tx,
+                    &contract_identifier,
+                    map_name,
+                    key,
+                    with_proof,
                 )
-            }
-            Err(e) => (
-                HttpResponseType::BadRequestJSON(response_metadata, e.into_json()),
-                false,
-            ),
-        };
+            }) {
+                Some(data) => HttpResponseType::GetMapEntry(response_metadata, data),
+                None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+            };
 
-        response.send(http, fd).and_then(|_| Ok(accepted))
+        response.send(http, fd).map(|_| ())
     }
 
-    /// Handle an external HTTP request.
-    /// Some requests, such as those for blocks, will create new reply streams.  This method adds
-    /// those new streams into the `reply_streams` set.
-    /// Returns a StacksMessageType option -- it's Some(...) if we need to forward a message to the
-    /// peer network (like a transaction or a block or microblock)
-    pub fn handle_request(
-        &mut self,
-        req: HttpRequestType,
-        chain_view: &BurnchainView,
-        peers: &PeerMap,
+    /// Load a data map entry, and optionally a MARF proof for it, from the given Clarity
+    /// connection.  A missing entry reads as `none`.
+    fn get_map_entry<C: ClarityConnection>(
+        clarity_tx: &mut C,
+        contract_identifier: &QualifiedContractIdentifier,
+        map_name: &ClarityName,
+        key: &Value,
+        with_proof: bool,
+    ) -> MapEntryResponse {
+        clarity_tx.with_clarity_db_readonly(|clarity_db| {
+            let key =
+                ClarityDatabase::make_key_for_data_map_entry(contract_identifier, map_name, key);
+            let (value, marf_proof) = clarity_db
+                .get_with_proof::<Value>(&key)
+                .map(|(a, b)| (a, format!("0x{}", b.to_hex())))
+                .unwrap_or_else(|| {
+                    test_debug!("No value for '{}'", &key);
+                    (Value::none(), "".into())
+                });
+            let marf_proof = if with_proof {
+                test_debug!(
+                    "Return a MARF proof of '{}' of {} bytes",
+                    &key,
+                    marf_proof.as_bytes().len()
+                );
+                Some(marf_proof)
+            } else {
+                None
+            };
+
+            let data = format!("0x{}", value.serialize());
+            MapEntryResponse { data, marf_proof }
+        })
+    }
+
+    /// Run a read-only function call against the given Clarity connection, charging its cost
+    /// against the node's read-only call limit.
+    fn call_readonly_function<C: ClarityConnection>(
+        clarity_tx: &mut C,
+        contract_identifier: &QualifiedContractIdentifier,
+        function: &ClarityName,
+        sender: &PrincipalData,
+        args: &[SymbolicExpression],
+        options: &ConnectionOptions,
+    ) -> Result<Value, ClarityRuntimeError> {
+        let cost_track = clarity_tx
+            .with_clarity_db_readonly(|clarity_db| {
+                LimitedCostTracker::new(options.read_only_call_limit.clone(), clarity_db)
+            })
+            .map_err(|_| ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure))?;
+
+        clarity_tx.with_readonly_clarity_env(sender.clone(), cost_track, |env| {
+            env.execute_contract(contract_identifier, function.as_str(), args, true)
+        })
+    }
+
+    /// Handle a POST to run a read-only function call with the given parameters on the given chain
+    /// tip.  Returns the result of the function call.  Returns a CallReadOnlyResponse on success.
+    /// If `view` includes the mempool, the node's pending transactions are applied on top of the
+    /// unconfirmed state at the tip before the call is made.
+    fn handle_readonly_function_call<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
         sortdb: &SortitionDB,
-        peerdb: &PeerDB,
-        atlasdb: &mut AtlasDB,
         chainstate: &mut StacksChainState,
-        mempool: &mut MemPoolDB,
-        handler_opts: &RPCHandlerArgs,
-    ) -> Result<Option<StacksMessageType>, net_error> {
-        monitoring::increment_rpc_calls_counter();
+        mempool: &MemPoolDB,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        function: &ClarityName,
+        sender: &PrincipalData,
+        args: &[Value],
+        view: RPCStateView,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
-        let mut reply = self.connection.make_relay_handle(self.conn_id)?;
-        let keep_alive = req.metadata().keep_alive;
-        let mut ret = None;
+        let args: Vec<_> = args
+            .iter()
+            .map(|x| SymbolicExpression::atom_value(x.clone()))
+            .collect();
 
-        let stream_opt = match req {
-            HttpRequestType::GetInfo(ref _md) => {
-                ConversationHttp::handle_getinfo(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    &self.burnchain,
-                    sortdb,
-                    chainstate,
-                    peerdb,
-                    handler_opts,
-                )?;
-                None
-            }
-            HttpRequestType::GetPoxInfo(ref _md, ref tip_opt) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_opt.as_ref(),
-                    sortdb,
-                    chainstate,
-                )? {
-                    ConversationHttp::handle_getpoxinfo(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        &self.connection.options,
-                    )?;
-                }
-                None
-            }
-            HttpRequestType::GetNeighbors(ref _md) => {
-                ConversationHttp::handle_getneighbors(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    self.network_id,
-                    chain_view,
-                    peers,
-                    peerdb,
-                )?;
-                None
-            }
-            HttpRequestType::GetBlock(ref _md, ref index_block_hash) => {
-                ConversationHttp::handle_getblock(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    index_block_hash,
-                    chainstate,
-                )?
-            }
-            HttpRequestType::GetMicroblocksIndexed(ref _md, ref index_head_hash) => {
-                ConversationHttp::handle_getmicroblocks_indexed(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    index_head_hash,
-                    chainstate,
-                )?
-            }
-            HttpRequestType::GetMicroblocksConfirmed(ref _md, ref anchor_index_block_hash) => {
-                ConversationHttp::handle_getmicroblocks_confirmed(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    anchor_index_block_hash,
-                    chainstate,
+        let data_opt = match view {
+            RPCStateView::UnconfirmedWithMempool => {
+                let pending_txs = ConversationHttp::load_mempool_view_txs(mempool, options)?;
+                chainstate.with_pending_clarity_tx(
+                    &sortdb.index_conn(),
+                    tip,
+                    true,
+                    &pending_txs,
+                    |clarity_tx| {
+                        ConversationHttp::call_readonly_function(
+                            clarity_tx,
+                            &contract_identifier,
+                            function,
+                            sender,
+                            &args,
+                            options,
+                        )
+                    },
                 )?
             }
-            HttpRequestType::GetMicroblocksUnconfirmed(
-                ref _md,
-                ref index_anchor_block_hash,
-                ref min_seq,
-            ) => ConversationHttp::handle_getmicroblocks_unconfirmed(
-                &mut self.connection.protocol,
-                &mut reply,
-                &req,
-                index_anchor_block_hash,
-                *min_seq,
-                chainstate,
-            )?,
-            HttpRequestType::GetTransactionUnconfirmed(ref _md, ref txid) => {
-                ConversationHttp::handle_gettransaction_unconfirmed(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    chainstate,
-                    mempool,
-                    txid,
-                )?;
-                None
-            }
-            HttpRequestType::GetAccount(ref _md, ref principal, ref tip_opt, ref with_proof) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_opt.as_ref(),
-                    sortdb,
-                    chainstate,
-                )? {
-                    ConversationHttp::handle_get_account_entry(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        principal,
-                        *with_proof,
-                    )?;
-                }
-                None
-            }
-            HttpRequestType::GetMapEntry(
-                ref _md,
-                ref contract_addr,
-                ref contract_name,
-                ref map_name,
-                ref key,
-                ref tip_opt,
-                ref with_proof,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_opt.as_ref(),
-                    sortdb,
-                    chainstate,
-                )? {
-                    ConversationHttp::handle_get_map_entry(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        contract_addr,
-                        contract_name,
+            _ => chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                ConversationHttp::call_readonly_function(
+                    clarity_tx,
+                    &contract_identifier,
+                    function,
+                    sender,
+                    &args,
+                    options,
+                )
+            }),
+        };
+
+        let response = match data_opt {
+            Some(result) => HttpResponseType::CallReadOnlyFunction(
+                response_metadata,
+                ConversationHttp::make_call_read_response(result),
+            ),
+            None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+        };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    fn make_call_read_response(result: Result<Value, ClarityRuntimeError>) -> CallReadOnlyResponse {
+        match result {
+            Ok(data) => CallReadOnlyResponse {
+                okay: true,
+                result: Some(format!("0x{}", data.serialize())),
+                cause: None,
+            },
+            Err(e) => CallReadOnlyResponse {
+                okay: false,
+                result: None,
+                cause: Some(e.to_string()),
+            },
+        }
+    }
+
+    /// Answer each of a batch's queries from the same Clarity connection, in order
+    fn run_batch_queries<C: ClarityConnection>(
+        clarity_tx: &mut C,
+        queries: &[BatchQuery],
+        with_proof: bool,
+        options: &ConnectionOptions,
+    ) -> Vec<BatchQueryResult> {
+        queries
+            .iter()
+            .map(|query| match query {
+                BatchQuery::Account(principal) => BatchQueryResult::Account(
+                    ConversationHttp::get_account_entry(clarity_tx, principal, with_proof),
+                ),
+                BatchQuery::MapEntry(contract_addr, contract_name, map_name, key) => {
+                    let contract_identifier = QualifiedContractIdentifier::new(
+                        contract_addr.clone().into(),
+                        contract_name.clone(),
+                    );
+                    BatchQueryResult::MapEntry(ConversationHttp::get_map_entry(
+                        clarity_tx,
+                        &contract_identifier,
                         map_name,
                         key,
-                        *with_proof,
-                    )?;
-                }
-                None
-            }
-            HttpRequestType::GetTransferCost(ref _md) => {
-                ConversationHttp::handle_token_transfer_cost(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                )?;
-                None
-            }
-            HttpRequestType::GetContractABI(
-                ref _md,
-                ref contract_addr,
-                ref contract_name,
-                ref tip_opt,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_opt.as_ref(),
-                    sortdb,
-                    chainstate,
-                )? {
-                    ConversationHttp::handle_get_contract_abi(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        contract_addr,
-                        contract_name,
-                    )?;
+                        with_proof,
+                    ))
                 }
-                None
-            }
-            HttpRequestType::CallReadOnlyFunction(
-                ref _md,
-                ref ctrct_addr,
-                ref ctrct_name,
-                ref as_sender,
-                ref func_name,
-                ref args,
-                ref tip_opt,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_opt.as_ref(),
-                    sortdb,
-                    chainstate,
-                )? {
-                    ConversationHttp::handle_readonly_function_call(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        ctrct_addr,
-                        ctrct_name,
-                        func_name,
-                        as_sender,
-                        args,
-                        &self.connection.options,
-                    )?;
+                BatchQuery::CallRead(contract_addr, contract_name, function, sender, args) => {
+                    let contract_identifier = QualifiedContractIdentifier::new(
+                        contract_addr.clone().into(),
+                        contract_name.clone(),
+                    );
+                    let args: Vec<_> = args
+                        .iter()
+                        .map(|x| SymbolicExpression::atom_value(x.clone()))
+                        .collect();
+                    BatchQueryResult::CallRead(ConversationHttp::make_call_read_response(
+                        ConversationHttp::call_readonly_function(
+                            clarity_tx,
+                            &contract_identifier,
+                            function,
+                            sender,
+                            &args,
+                            options,
+                        ),
+                    ))
                 }
-                None
+            })
+            .collect()
+    }
+
+    /// Handle a POST to run a batch of read-only queries.  Every query is answered from the same
+    /// Clarity connection at the given chain tip, so the answers are consistent with each other
+    /// even if the canonical tip moves while the batch runs.  If `view` includes the mempool, the
+    /// node's pending transactions are applied on top of the unconfirmed state at the tip once,
+    /// for the whole batch, and no proofs are supplied.
+    fn handle_batch<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        mempool: &MemPoolDB,
+        tip: &StacksBlockId,
+        queries: &[BatchQuery],
+        with_proof: bool,
+        view: RPCStateView,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if queries.len() as u64 > options.max_batch_queries {
+            let response = HttpResponseType::BadRequest(
+                response_metadata,
+                format!(
+                    "Too many queries: at most {} are allowed in one batch",
+                    options.max_batch_queries
+                ),
+            );
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let results_opt = match view {
+            RPCStateView::UnconfirmedWithMempool => {
+                let pending_txs = ConversationHttp::load_mempool_view_txs(mempool, options)?;
+                chainstate.with_pending_clarity_tx(
+                    &sortdb.index_conn(),
+                    tip,
+                    true,
+                    &pending_txs,
+                    |clarity_tx| {
+                        ConversationHttp::run_batch_queries(clarity_tx, queries, false, options)
+                    },
+                )?
             }
-            HttpRequestType::GetContractSrc(
-                ref _md,
-                ref contract_addr,
-                ref contract_name,
-                ref tip_opt,
-                ref with_proof,
-            ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    tip_opt.as_ref(),
-                    sortdb,
-                    chainstate,
-                )? {
-                    ConversationHttp::handle_get_contract_src(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        sortdb,
-                        chainstate,
-                        &tip,
-                        contract_addr,
-                        contract_name,
-                        *with_proof,
-                    )?;
+            _ => chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                ConversationHttp::run_batch_queries(clarity_tx, queries, with_proof, options)
+            }),
+        };
+
+        let response = match results_opt {
+            Some(results) => HttpResponseType::Batch(
+                response_metadata,
+                BatchResponse {
+                    tip: tip.clone(),
+                    results,
+                },
+            ),
+            None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+        };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET to fetch a contract's source code, given the chain tip.  Optionally returns a
+    /// MARF proof as well.
+    /// Return a JsonStreamData for the source, so we can encode it as it is sent instead of
+    /// buffering the whole response.
+    fn handle_get_contract_src<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        with_proof: bool,
+    ) -> Result<Option<HttpResponseStream>, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let (response, stream_opt) =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|db| {
+                    let source = db.get_contract_src(&contract_identifier)?;
+                    let contract_commit_key =
+                        MarfedKV::make_contract_hash_key(&contract_identifier);
+                    let (contract_commit, proof) = db
+                        .get_with_proof::<ContractCommitment>(&contract_commit_key)
+                        .expect("BUG: obtained source, but couldn't get MARF proof.");
+                    let marf_proof = if with_proof {
+                        Some(proof.to_hex())
+                    } else {
+                        None
+                    };
+                    let publish_height = contract_commit.block_height;
+                    Some(ContractSrcResponse {
+                        source,
+                        publish_height,
+                        marf_proof,
+                    })
+                })
+            }) {
+                Some(Some(data)) => (
+                    HttpResponseType::JsonStream(response_metadata),
+                    Some(JsonStreamData::contract_src(data).into()),
+                ),
+                Some(None) => (
+                    HttpResponseType::NotFound(
+                        response_metadata,
+                        "No contract source data found".into(),
+                    ),
+                    None,
+                ),
+                None => (
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+                    None,
+                ),
+            };
+
+        response.send(http, fd).and_then(|_| Ok(stream_opt))
+    }
+
+    /// Handle a GET to fetch a contract's data var, given the chain tip.  Optionally returns a MARF
+    /// proof as well.
+    fn handle_get_data_var<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        var_name: &ClarityName,
+        with_proof: bool,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier =
+            QualifiedContractIdentifier::new((*contract_addr).into(), contract_name.clone());
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let key =
+                        ClarityDatabase::make_key_for_data_var(&contract_identifier, var_name);
+                    let (value, marf_proof) = clarity_db.get_with_proof::<Value>(&key)?;
+                    let marf_proof = if with_proof {
+                        test_debug!(
+                            "Return a MARF proof of '{}' of {} bytes",
+                            &key,
+                            marf_proof.to_hex().len() / 2
+                        );
+                        Some(format!("0x{}", marf_proof.to_hex()))
+                    } else {
+                        None
+                    };
+
+                    let data = format!("0x{}", value.serialize());
+                    Some(DataVarResponse { data, marf_proof })
+                })
+            }) {
+                Some(Some(data)) => HttpResponseType::GetDataVar(response_metadata, data),
+                Some(None) => {
+                    HttpResponseType::NotFound(response_metadata, "Data var not found".into())
                 }
-                None
+                None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET to fetch a contract's analysis data, given the chain tip.  Note that this isn't
+    /// something that's anchored to the blockchain, and can be different across different versions
+    /// of Stacks -- callers must trust the Stacks node to return correct analysis data.
+    /// Callers who don't trust the Stacks node should just fetch the contract source
+    /// code and analyze it offline.
+    fn handle_get_contract_abi<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_analysis_db_readonly(|db| {
+                    let contract = db.load_contract(&contract_identifier)?;
+                    contract.contract_interface
+                })
+            }) {
+                Some(Some(data)) => HttpResponseType::GetContractABI(response_metadata, data),
+                Some(None) => HttpResponseType::NotFound(
+                    response_metadata,
+                    "No contract interface data found".into(),
+                ),
+                None => HttpResponseType::NotFound(response_metadata, "Chain tip not found".into()),
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET unconfirmed microblock stream.  Start streaming the reply.
+    /// The response's preamble (but not the block data) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// Return a BlockStreamData struct for the block that we're sending, so we can continue to
+    /// make progress sending it.
+    fn handle_getmicroblocks_unconfirmed<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        index_anchor_block_hash: &StacksBlockId,
+        min_seq: u16,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<BlockStreamData>, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        // do we have this unconfirmed microblock stream?
+        match chainstate.has_any_staging_microblock_indexed(index_anchor_block_hash, min_seq) {
+            Ok(false) => {
+                // nope
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    format!(
+                        "No such unconfirmed microblock stream for {} at or after {}",
+                        index_anchor_block_hash.to_hex(),
+                        min_seq
+                    ),
+                );
+                response.send(http, fd).and_then(|_| Ok(None))
             }
-            HttpRequestType::PostTransaction(ref _md, ref tx, ref attachment) => {
-                match chainstate.get_stacks_chain_tip(sortdb)? {
-                    Some(tip) => {
-                        let accepted = ConversationHttp::handle_post_transaction(
-                            &mut self.connection.protocol,
-                            &mut reply,
-                            &req,
-                            chainstate,
-                            tip.consensus_hash,
-                            tip.anchored_block_hash,
-                            mempool,
-                            tx.clone(),
-                            atlasdb,
-                            attachment.clone(),
-                        )?;
-                        if accepted {
-                            // forward to peer network
-                            ret = Some(StacksMessageType::Transaction(tx.clone()));
-                        }
-                    }
-                    None => {
-                        let response_metadata = HttpResponseMetadata::from(&req);
-                        warn!("Failed to load Stacks chain tip");
-                        let response = HttpResponseType::ServerError(
+            Err(e) => {
+                // nope
+                warn!(
+                    "Failed to serve confirmed microblock stream {:?}: {:?}",
+                    req, &e
+                );
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    format!(
+                        "Failed to query unconfirmed microblock stream for {} at or after {}",
+                        index_anchor_block_hash.to_hex(),
+                        min_seq
+                    ),
+                );
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+            Ok(true) => {
+                // yup! start streaming it back
+                let (response, stream_opt) = match BlockStreamData::new_microblock_unconfirmed(
+                    chainstate,
+                    index_anchor_block_hash.clone(),
+                    min_seq,
+                ) {
+                    Ok(stream) => (
+                        HttpResponseType::MicroblockStream(response_metadata),
+                        Some(stream),
+                    ),
+                    Err(chain_error::NoSuchBlockError) => (
+                        HttpResponseType::NotFound(
                             response_metadata,
-                            format!("Failed to load Stacks chain tip"),
+                            format!(
+                                "No such unconfirmed microblock stream starting with {}",
+                                index_anchor_block_hash.to_hex()
+                            ),
+                        ),
+                        None,
+                    ),
+                    Err(_e) => {
+                        debug!(
+                            "Failed to load unconfirmed microblock stream {}: {:?}",
+                            &index_anchor_block_hash, &_e
                         );
-                        response.send(&mut self.connection.protocol, &mut reply)?;
+                        (
+                            HttpResponseType::ServerError(
+                                response_metadata,
+                                format!(
+                                    "Failed to query unconfirmed microblock stream {}",
+                                    index_anchor_block_hash.to_hex()
+                                ),
+                            ),
+                            None,
+                        )
                     }
-                }
-                None
+                };
+                response.send(http, fd).and_then(|_| Ok(stream_opt))
             }
-            HttpRequestType::GetAttachment(ref _md, ref content_hash) => {
-                ConversationHttp::handle_getattachment(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    atlasdb,
-                    content_hash.clone(),
-                )?;
-                None
+        }
+    }
+
+    /// Handle a GET unconfirmed transaction.
+    /// The response will be synchronously written to the fd.
+    fn handle_gettransaction_unconfirmed<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        mempool: &MemPoolDB,
+        txid: &Txid,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        // present in the unconfirmed state?
+        if let Some(ref unconfirmed) = chainstate.unconfirmed_state.as_ref() {
+            if let Some((transaction, mblock_hash, seq)) =
+                unconfirmed.get_unconfirmed_transaction(txid)
+            {
+                let response = HttpResponseType::UnconfirmedTransaction(
+                    response_metadata,
+                    UnconfirmedTransactionResponse {
+                        status: UnconfirmedTransactionStatus::Microblock {
+                            block_hash: mblock_hash,
+                            seq: seq,
+                        },
+                        tx: to_hex(&transaction.serialize_to_vec()),
+                    },
+                );
+                return response.send(http, fd).map(|_| ());
             }
-            HttpRequestType::GetAttachmentsInv(ref _md, ref tip_opt, ref pages_indexes) => {
-                if let Some((tip_consensus_hash, tip_block_hash)) =
-                    ConversationHttp::handle_load_stacks_chain_tip_hashes(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        tip_opt.as_ref(),
-                        sortdb,
-                        chainstate,
-                    )?
-                {
-                    ConversationHttp::handle_getattachmentsinv(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        atlasdb,
-                        chainstate,
-                        &tip_consensus_hash,
-                        &tip_block_hash,
-                        pages_indexes,
-                        &self.connection.options,
-                    )?;
+        }
+
+        // present in the mempool?
+        if let Some(txinfo) = MemPoolDB::get_tx(mempool.conn(), txid)? {
+            let response = HttpResponseType::UnconfirmedTransaction(
+                response_metadata,
+                UnconfirmedTransactionResponse {
+                    status: UnconfirmedTransactionStatus::Mempool,
+                    tx: to_hex(&txinfo.tx.serialize_to_vec()),
+                },
+            );
+            return response.send(http, fd).map(|_| ());
+        }
+
+        // not found
+        let response = HttpResponseType::NotFound(
+            response_metadata,
+            format!("No such unconfirmed transaction {}", txid),
+        );
+        return response.send(http, fd).map(|_| ());
+    }
+
+    /// Load up the canonical Stacks chain tip.  Note that this is subject to both burn chain block
+    /// Stacks block availability -- different nodes with different partial replicas of the Stacks chain state
+    /// will return different values here.
+    /// tip_opt is given by the HTTP request as the optional query parameter for the chain tip
+    /// hash.  It will be None if there was no paramter given.
+    /// The order of chain tips this method prefers is as follows:
+    /// * tip_opt, if it's Some(..),
+    /// * the unconfirmed canonical stacks chain tip, if initialized
+    /// * the confirmed canonical stacks chain tip
+    fn handle_load_stacks_chain_tip<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip_opt: Option<&StacksBlockId>,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<StacksBlockId>, net_error> {
+        match tip_opt {
+            Some(tip) => Ok(Some(*tip).clone()),
+            None => match chainstate.get_stacks_chain_tip(sortdb)? {
+                Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
+                    &tip.consensus_hash,
+                    &tip.anchored_block_hash,
+                ))),
+                None => {
+                    let response_metadata = HttpResponseMetadata::from(req);
+                    warn!("Failed to load Stacks chain tip");
+                    let response = HttpResponseType::ServerError(
+                        response_metadata,
+                        format!("Failed to load Stacks chain tip"),
+                    );
+                    response.send(http, fd).and_then(|_| Ok(None))
                 }
-                None
+            },
+        }
+    }
+
+    /// Load up the chain tip to evaluate a query against, given the query's state view.
+    /// * `RPCStateView::Confirmed` loads the tip the same way `handle_load_stacks_chain_tip` does.
+    /// * `RPCStateView::Unconfirmed` loads the unconfirmed chain tip if it is readable and
+    ///   builds on the canonical Stacks chain tip, and the canonical Stacks chain tip otherwise.
+    /// * `RPCStateView::UnconfirmedWithMempool` loads the canonical Stacks chain tip; the caller
+    ///   applies its microblock stream and the mempool on top.
+    fn handle_load_stacks_state_view_tip<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip_opt: Option<&StacksBlockId>,
+        view: RPCStateView,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<StacksBlockId>, net_error> {
+        let tip = match ConversationHttp::handle_load_stacks_chain_tip(
+            http, fd, req, tip_opt, sortdb, chainstate,
+        )? {
+            Some(tip) => tip,
+            None => {
+                return Ok(None);
             }
-            HttpRequestType::PostMicroblock(ref _md, ref mblock, ref tip_opt) => {
-                if let Some((consensus_hash, block_hash)) =
-                    ConversationHttp::handle_load_stacks_chain_tip_hashes(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        tip_opt.as_ref(),
-                        sortdb,
-                        chainstate,
-                    )?
-                {
-                    let accepted = ConversationHttp::handle_post_microblock(
-                        &mut self.connection.protocol,
-                        &mut reply,
-                        &req,
-                        consensus_hash,
-                        block_hash,
-                        chainstate,
-                        mblock.clone(),
-                    )?;
-                    if accepted {
-                        // forward to peer network
-                        let tip =
-                            StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
-                        ret = Some(StacksMessageType::Microblocks(MicroblocksData {
-                            index_anchor_block: tip,
-                            microblocks: vec![(*mblock).clone()],
-                        }));
-                    }
-                }
-                None
+        };
+        if view != RPCStateView::Unconfirmed {
+            return Ok(Some(tip));
+        }
+        match chainstate.unconfirmed_state {
+            Some(ref unconfirmed_state)
+                if unconfirmed_state.confirmed_chain_tip == tip
+                    && unconfirmed_state.is_readable() =>
+            {
+                Ok(Some(unconfirmed_state.unconfirmed_chain_tip))
             }
-            HttpRequestType::OptionsPreflight(ref _md, ref _path) => {
-                let response_metadata = HttpResponseMetadata::from(&req);
-                let response = HttpResponseType::OptionsPreflight(response_metadata);
-                response
-                    .send(&mut self.connection.protocol, &mut reply)
-                    .map(|_| ())?;
-                None
+            _ => Ok(Some(tip)),
+        }
+    }
+
+    /// Load up the chain tip to evaluate a historical query against: if `height_opt` is given,
+    /// this is the ancestor at that height of the tip `handle_load_stacks_state_view_tip` loads,
+    /// the same way `at-block` picks a block inside Clarity.  Replies with a 404 if the tip's
+    /// fork has no block at that height.
+    fn handle_load_stacks_chain_tip_at_height<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip_opt: Option<&StacksBlockId>,
+        height_opt: Option<u64>,
+        view: RPCStateView,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<StacksBlockId>, net_error> {
+        let tip = match ConversationHttp::handle_load_stacks_state_view_tip(
+            http, fd, req, tip_opt, view, sortdb, chainstate,
+        )? {
+            Some(tip) => tip,
+            None => {
+                return Ok(None);
             }
-            HttpRequestType::ClientError(ref _md, ref err) => {
-                let response_metadata = HttpResponseMetadata::from(&req);
-                let response = match err {
-                    ClientError::Message(s) => HttpResponseType::BadRequestJSON(
-                        response_metadata,
-                        serde_json::Value::String(s.to_string()),
-                    ),
-                    ClientError::NotFound(path) => {
-                        HttpResponseType::NotFound(response_metadata, path.clone())
+        };
+        let height = match height_opt {
+            Some(height) => height,
+            None => {
+                return Ok(Some(tip));
+            }
+        };
+
+        let response_metadata = HttpResponseMetadata::from(req);
+        let ancestor_res = if height < u32::max_value() as u64 {
+            get_ancestor_block_hash(&chainstate.state_index, height, &tip)
+        } else {
+            Ok(None)
+        };
+        let response = match ancestor_res {
+            Ok(Some(ancestor)) => {
+                return Ok(Some(ancestor));
+            }
+            Ok(None) | Err(db_error::NotFoundError) => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No block at height {} in the fork of {}", height, &tip),
+            ),
+            Err(e) => {
+                warn!(
+                    "Failed to load ancestor of {} at height {}: {:?}",
+                    &tip, height, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to load ancestor block"),
+                )
+            }
+        };
+        response.send(http, fd).and_then(|_| Ok(None))
+    }
+
+    fn handle_load_stacks_chain_tip_hashes<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip_opt: Option<&StacksBlockId>,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<(ConsensusHash, BlockHeaderHash)>, net_error> {
+        match tip_opt {
+            Some(tip) => match chainstate.get_block_header_hashes(&tip)? {
+                Some((ch, bl)) => {
+                    return Ok(Some((ch, bl)));
+                }
+                None => {}
+            },
+            None => match chainstate.get_stacks_chain_tip(sortdb)? {
+                Some(tip) => {
+                    return Ok(Some((tip.consensus_hash, tip.anchored_block_hash)));
+                }
+                None => {}
+            },
+        }
+        let response_metadata = HttpResponseMetadata::from(req);
+        warn!("Failed to load Stacks chain tip");
+        let response = HttpResponseType::ServerError(
+            response_metadata,
+            format!("Failed to load Stacks chain tip"),
+        );
+        response.send(http, fd).and_then(|_| Ok(None))
+    }
+
+    /// Handle a transaction.  Directly submit it to the mempool so the client can see any
+    /// rejection reasons up-front (different from how the peer network handles it).  Indicate
+    /// whether or not the transaction was accepted (and thus needs to be forwarded) in the return
+    /// value.
+    fn handle_post_transaction<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &mut StacksChainState,
+        consensus_hash: ConsensusHash,
+        block_hash: BlockHeaderHash,
+        mempool: &mut MemPoolDB,
+        tx: StacksTransaction,
+        atlasdb: &mut AtlasDB,
+        attachment: Option<Attachment>,
+    ) -> Result<bool, net_error> {
+        let txid = tx.txid();
+        let response_metadata = HttpResponseMetadata::from(req);
+        let (response, accepted) = if mempool.has_tx(&txid) {
+            (
+                HttpResponseType::TransactionID(response_metadata, txid),
+                false,
+            )
+        } else {
+            match mempool.submit(chainstate, &consensus_hash, &block_hash, tx) {
+                Ok(_) => (
+                    HttpResponseType::TransactionID(response_metadata, txid),
+                    true,
+                ),
+                Err(e) => (
+                    HttpResponseType::BadRequestJSON(response_metadata, e.into_json(&txid)),
+                    false,
+                ),
+            }
+        };
+
+        if let Some(attachment) = attachment {
+            if accepted {
+                atlasdb
+                    .insert_new_attachment(&attachment)
+                    .map_err(|e| net_error::DBError(e))?;
+            }
+        }
+        response.send(http, fd).and_then(|_| Ok(accepted))
+    }
+
+    /// Handle a microblock.  Directly submit it to the microblock store so the client can see any
+    /// rejection reasons up-front (different from how the peer network handles it).  Indicate
+    /// whether or not the microblock was accepted (and thus needs to be forwarded) in the return
+    /// value.
+    fn handle_post_microblock<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        consensus_hash: ConsensusHash,
+        block_hash: BlockHeaderHash,
+        chainstate: &mut StacksChainState,
+        microblock: StacksMicroblock,
+    ) -> Result<bool, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let (response, accepted) = match chainstate.preprocess_streamed_microblock(
+            &consensus_hash,
+            &block_hash,
+            &microblock,
+        ) {
+            Ok(accepted) => {
+                if accepted {
+                    debug!(
+                        "Accepted uploaded microblock {}/{}-{}",
+                        &consensus_hash,
+                        &block_hash,
+                        &microblock.block_hash()
+                    );
+                } else {
+                    debug!(
+                        "Did not accept microblock {}/{}-{}",
+                        &consensus_hash,
+                        &block_hash,
+                        &microblock.block_hash()
+                    );
+                }
+
+                (
+                    HttpResponseType::MicroblockHash(response_metadata, microblock.block_hash()),
+                    accepted,
+                )
+            }
+            Err(e) => (
+                HttpResponseType::BadRequestJSON(response_metadata, e.into_json()),
+                false,
+            ),
+        };
+
+        response.send(http, fd).and_then(|_| Ok(accepted))
+    }
+
+    /// Handle an uploaded anchored block.  Directly store it to the staging blocks so the client
+    /// can see whether or not it was accepted up-front (different from how the peer network
+    /// handles it).  Indicate whether or not the block was accepted (and thus needs to be
+    /// announced) in the return value.
+    fn handle_post_block<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block: &StacksBlock,
+    ) -> Result<bool, net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let stacks_block_id =
+            StacksBlockHeader::make_index_block_hash(consensus_hash, &block.block_hash());
+        let (response, accepted) = match Relayer::process_new_anchored_block(
+            &sortdb.index_conn(),
+            chainstate,
+            consensus_hash,
+            block,
+            0,
+        ) {
+            Ok(accepted) => {
+                if accepted {
+                    debug!("Accepted uploaded block {}", &stacks_block_id);
+                } else {
+                    debug!("Did not accept uploaded block {}", &stacks_block_id);
+                }
+
+                (
+                    HttpResponseType::StacksBlockAccepted(
+                        response_metadata,
+                        StacksBlockAcceptedData {
+                            stacks_block_id,
+                            accepted,
+                        },
+                    ),
+                    accepted,
+                )
+            }
+            Err(e) => (
+                HttpResponseType::BadRequestJSON(response_metadata, e.into_json()),
+                false,
+            ),
+        };
+
+        response.send(http, fd).and_then(|_| Ok(accepted))
+    }
+
+    /// Handle an external HTTP request.
+    /// Some requests, such as those for blocks, will create new reply streams.  This method adds
+    /// those new streams into the `reply_streams` set.
+    /// Returns a StacksMessageType option -- it's Some(...) if we need to forward a message to the
+    /// peer network (like a transaction or a block or microblock)
+    pub fn handle_request(
+        &mut self,
+        req: HttpRequestType,
+        chain_view: &BurnchainView,
+        peers: &PeerMap,
+        sortdb: &SortitionDB,
+        peerdb: &PeerDB,
+        atlasdb: &mut AtlasDB,
+        stackerdbs: &mut StackerDBs,
+        chainstate: &mut StacksChainState,
+        mempool: &mut MemPoolDB,
+        handler_opts: &RPCHandlerArgs,
+        rate_limiter: &mut HttpRateLimiter,
+    ) -> Result<Option<StacksMessageType>, net_error> {
+        monitoring::increment_rpc_calls_counter();
+        if let Some(deprecation) = req.deprecation() {
+            monitoring::increment_rpc_deprecated_calls_counter(deprecation.usage);
+            debug!(
+                "Handle deprecated request {} from {} (successor is {})",
+                deprecation.usage, &self.peer_addr, deprecation.successor
+            );
+        }
+
+        let route = req.route();
+        if let Some(limit) = self.exceeded_rate_limit(&req, rate_limiter) {
+            debug!(
+                "Client {} exceeded the rate limit for {} ({} requests per {} seconds)",
+                &self.peer_addr, route, limit.max_requests, limit.window_secs
+            );
+            let keep_alive = req.metadata().keep_alive;
+            let mut reply = self.connection.make_relay_handle(self.conn_id)?;
+            let response = HttpResponseType::TooManyRequests(
+                HttpResponseMetadata::from(&req),
+                format!(
+                    "Rate limit exceeded: at most {} requests per {} seconds",
+                    limit.max_requests, limit.window_secs
+                ),
+            );
+            response.send(&mut self.connection.protocol, &mut reply)?;
+            self.reply_streams.push_back((reply, None, keep_alive));
+            return Ok(None);
+        }
+
+        let start_time = Instant::now();
+        let res = self.handle_request_inner(
+            req,
+            chain_view,
+            peers,
+            sortdb,
+            peerdb,
+            atlasdb,
+            stackerdbs,
+            chainstate,
+            mempool,
+            handler_opts,
+        );
+        monitoring::observe_rpc_request_duration(route, start_time.elapsed());
+        res
+    }
+
+    /// Count this request against the client's rate limits: the limit on all requests, the limit
+    /// on expensive requests if it is one, and its route's limit.  Returns the first limit the
+    /// client has already used up, if any.  Clients on the loopback interface are not limited.
+    fn exceeded_rate_limit(
+        &self,
+        req: &HttpRequestType,
+        rate_limiter: &mut HttpRateLimiter,
+    ) -> Option<HttpRateLimit> {
+        let client = self.peer_addr.ip();
+        if client.is_loopback() {
+            return None;
+        }
+        let options = &self.connection.options;
+        let route = req.route();
+        let now = get_epoch_time_secs();
+
+        // narrowest limit first, so a request turned away by it doesn't count against the others
+        let mut limits = vec![];
+        if let Some(limit) = options.rpc_route_rate_limits.get(route) {
+            limits.push((route, *limit));
+        }
+        if req.is_expensive() {
+            if let Some(limit) = options.rpc_expensive_rate_limit {
+                limits.push(("expensive", limit));
+            }
+        }
+        if let Some(limit) = options.rpc_rate_limit {
+            limits.push(("*", limit));
+        }
+
+        for (bucket, limit) in limits.into_iter() {
+            if !rate_limiter.admit(client, bucket, &limit, now) {
+                return Some(limit);
+            }
+        }
+        None
+    }
+
+    fn handle_request_inner(
+        &mut self,
+        req: HttpRequestType,
+        chain_view: &BurnchainView,
+        peers: &PeerMap,
+        sortdb: &SortitionDB,
+        peerdb: &PeerDB,
+        atlasdb: &mut AtlasDB,
+        stackerdbs: &mut StackerDBs,
+        chainstate: &mut StacksChainState,
+        mempool: &mut MemPoolDB,
+        handler_opts: &RPCHandlerArgs,
+    ) -> Result<Option<StacksMessageType>, net_error> {
+        let mut reply = self.connection.make_relay_handle(self.conn_id)?;
+        let keep_alive = req.metadata().keep_alive;
+        let mut ret = None;
+
+        let stream_opt = match req {
+            HttpRequestType::GetInfo(ref _md) => {
+                ConversationHttp::handle_getinfo(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &self.burnchain,
+                    sortdb,
+                    chainstate,
+                    peerdb,
+                    handler_opts,
+                )?;
+                None
+            }
+            HttpRequestType::GetPoxInfo(ref _md, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_getpoxinfo(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        &self.connection.options,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetRewardSet(ref _md, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_getrewardset(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &self.burnchain,
+                        &tip,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetPoxDelegations(ref _md, ref delegate, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_pox_delegations(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        delegate,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::SimulateAggregationCommit(
+                ref _md,
+                ref delegate,
+                ref pox_addr,
+                ref reward_cycle,
+                ref tip_opt,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_post_aggregation_commit(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        delegate,
+                        pox_addr,
+                        *reward_cycle,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetBurnBlockInfo(ref _md, ref burn_block_height) => {
+                ConversationHttp::handle_getburnblockinfo(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    *burn_block_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetBurnchainOps(ref _md, ref query) => {
+                ConversationHttp::handle_getburnchainops(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    query,
+                )?;
+                None
+            }
+            HttpRequestType::GetNeighbors(ref _md) => {
+                ConversationHttp::handle_getneighbors(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    self.network_id,
+                    chain_view,
+                    peers,
+                    peerdb,
+                )?;
+                None
+            }
+            HttpRequestType::GetBlock(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_getblock(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_block_hash,
+                    chainstate,
+                )?
+                .map(HttpResponseStream::from)
+            }
+            HttpRequestType::GetBlockEventBloom(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_get_block_event_bloom(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_block_hash,
+                    chainstate,
+                )?;
+                None
+            }
+            HttpRequestType::GetHeaders(ref _md, ref quantity, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_getheaders(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        &tip,
+                        *quantity,
+                        chainstate,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetMicroblocksIndexed(ref _md, ref index_head_hash) => {
+                ConversationHttp::handle_getmicroblocks_indexed(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_head_hash,
+                    chainstate,
+                )?
+                .map(HttpResponseStream::from)
+            }
+            HttpRequestType::GetMicroblocksConfirmed(ref _md, ref anchor_index_block_hash) => {
+                ConversationHttp::handle_getmicroblocks_confirmed(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    anchor_index_block_hash,
+                    chainstate,
+                )?
+                .map(HttpResponseStream::from)
+            }
+            HttpRequestType::GetMicroblocksUnconfirmed(
+                ref _md,
+                ref index_anchor_block_hash,
+                ref min_seq,
+            ) => ConversationHttp::handle_getmicroblocks_unconfirmed(
+                &mut self.connection.protocol,
+                &mut reply,
+                &req,
+                index_anchor_block_hash,
+                *min_seq,
+                chainstate,
+            )?
+            .map(HttpResponseStream::from),
+            HttpRequestType::GetTransactionUnconfirmed(ref _md, ref txid) => {
+                ConversationHttp::handle_gettransaction_unconfirmed(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    chainstate,
+                    mempool,
+                    txid,
+                )?;
+                None
+            }
+            HttpRequestType::GetAccount(
+                ref _md,
+                ref principal,
+                ref tip_opt,
+                ref height_opt,
+                ref with_proof,
+                ref view,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip_at_height(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    *height_opt,
+                    *view,
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_account_entry(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        mempool,
+                        &tip,
+                        principal,
+                        *with_proof,
+                        *view,
+                        &self.connection.options,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetAccountAssets(ref _md, ref principal, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_account_assets(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        principal,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetAccountNonces(ref _md, ref principal, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_account_nonces(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        mempool,
+                        &tip,
+                        principal,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetAccountSummary(ref _md, ref principal, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_account_summary(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        principal,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetTransactionReceipt(ref _md, ref txid, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_transaction_receipt(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        txid,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetPrincipalTransactions(
+                ref _md,
+                ref principal,
+                ref offset,
+                ref limit,
+                ref tip_opt,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_principal_transactions(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        principal,
+                        *offset,
+                        *limit,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetPendingMinerRewards(ref _md, ref address, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_pending_miner_rewards(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        address,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetUnconfirmedMicroblockStream(ref _md, min_seq, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_unconfirmed_microblock_stream(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        min_seq,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::MemPoolGC(ref _md) => {
+                ConversationHttp::handle_mempool_gc(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &self.peer_addr,
+                    sortdb,
+                    chainstate,
+                    mempool,
+                )?;
+                None
+            }
+            HttpRequestType::DevMine(ref _md, ref request) => {
+                ConversationHttp::handle_dev_mine(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &self.peer_addr,
+                    request,
+                    sortdb,
+                    chainstate,
+                    mempool,
+                    handler_opts,
+                )?;
+                None
+            }
+            HttpRequestType::Batch(ref _md, ref queries, ref tip_opt, ref with_proof, ref view) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_state_view_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    *view,
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_batch(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        mempool,
+                        &tip,
+                        queries,
+                        *with_proof,
+                        *view,
+                        &self.connection.options,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::SimulateBlock(ref _md) => {
+                ConversationHttp::handle_simulate_block(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &self.peer_addr,
+                    sortdb,
+                    chainstate,
+                    mempool,
+                )?;
+                None
+            }
+            HttpRequestType::ValidateBlockProposal(
+                ref _md,
+                ref parent_consensus_hash,
+                ref block,
+                ref auth_opt,
+            ) => {
+                ConversationHttp::handle_validate_block_proposal(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    parent_consensus_hash,
+                    block,
+                    auth_opt.as_ref(),
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostBlock(ref _md, ref consensus_hash, ref block) => {
+                let accepted = ConversationHttp::handle_post_block(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    consensus_hash,
+                    block,
+                )?;
+                if accepted {
+                    // announce to peer network
+                    ret = Some(StacksMessageType::Blocks(BlocksData {
+                        blocks: vec![(consensus_hash.clone(), block.clone())],
+                    }));
+                }
+                None
+            }
+            HttpRequestType::GetMinerStatus(ref _md, ref auth_opt) => {
+                ConversationHttp::handle_get_miner_status(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    auth_opt.as_ref(),
+                    &self.connection.options,
+                    handler_opts,
+                )?;
+                None
+            }
+            HttpRequestType::ScheduledCalls(ref _md, ref op, ref auth_opt) => {
+                ConversationHttp::handle_scheduled_calls(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    op,
+                    auth_opt.as_ref(),
+                    &self.connection.options,
+                    handler_opts,
+                )?;
+                None
+            }
+            HttpRequestType::GetMapEntry(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref map_name,
+                ref key,
+                ref tip_opt,
+                ref height_opt,
+                ref with_proof,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip_at_height(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    *height_opt,
+                    RPCStateView::Confirmed,
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_map_entry(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        map_name,
+                        key,
+                        *with_proof,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetTransferCost(ref _md) => {
+                ConversationHttp::handle_token_transfer_cost(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                )?;
+                None
+            }
+            HttpRequestType::EstimateTransactionFee(ref _md, ref tx) => {
+                match chainstate.get_stacks_chain_tip(sortdb)? {
+                    Some(tip) => {
+                        ConversationHttp::handle_post_fee_estimate(
+                            &mut self.connection.protocol,
+                            &mut reply,
+                            &req,
+                            chainstate,
+                            &tip.consensus_hash,
+                            &tip.anchored_block_hash,
+                            mempool,
+                            tx,
+                        )?;
+                    }
+                    None => {
+                        let response_metadata = HttpResponseMetadata::from(&req);
+                        warn!("Failed to load Stacks chain tip");
+                        let response = HttpResponseType::ServerError(
+                            response_metadata,
+                            format!("Failed to load Stacks chain tip"),
+                        );
+                        response.send(&mut self.connection.protocol, &mut reply)?;
+                    }
+                }
+                None
+            }
+            HttpRequestType::AnalyzeContract(
+                ref _md,
+                ref source,
+                ref contract_id_opt,
+                ref tip_opt,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_post_analyze_contract(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        source,
+                        contract_id_opt.as_ref(),
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::SimulateTransaction(ref _md, ref tx) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    None,
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_post_simulate_transaction(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        tx,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetContractABI(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref tip_opt,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_contract_abi(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::CallReadOnlyFunction(
+                ref _md,
+                ref ctrct_addr,
+                ref ctrct_name,
+                ref as_sender,
+                ref func_name,
+                ref args,
+                ref tip_opt,
+                ref height_opt,
+                ref view,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip_at_height(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    *height_opt,
+                    *view,
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_readonly_function_call(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        mempool,
+                        &tip,
+                        ctrct_addr,
+                        ctrct_name,
+                        func_name,
+                        as_sender,
+                        args,
+                        *view,
+                        &self.connection.options,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetDataVar(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref var_name,
+                ref tip_opt,
+                ref with_proof,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_data_var(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        var_name,
+                        *with_proof,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetContractSrc(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref tip_opt,
+                ref height_opt,
+                ref with_proof,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip_at_height(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    *height_opt,
+                    RPCStateView::Confirmed,
+                    sortdb,
+                    chainstate,
+                )? {
+                    ConversationHttp::handle_get_contract_src(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        *with_proof,
+                    )?
+                } else {
+                    None
+                }
+            }
+            HttpRequestType::PostTransaction(ref _md, ref tx, ref attachment) => {
+                match chainstate.get_stacks_chain_tip(sortdb)? {
+                    Some(tip) => {
+                        let accepted = ConversationHttp::handle_post_transaction(
+                            &mut self.connection.protocol,
+                            &mut reply,
+                            &req,
+                            chainstate,
+                            tip.consensus_hash,
+                            tip.anchored_block_hash,
+                            mempool,
+                            tx.clone(),
+                            atlasdb,
+                            attachment.clone(),
+                        )?;
+                        if accepted {
+                            // forward to peer network
+                            ret = Some(StacksMessageType::Transaction(tx.clone()));
+                        }
+                    }
+                    None => {
+                        let response_metadata = HttpResponseMetadata::from(&req);
+                        warn!("Failed to load Stacks chain tip");
+                        let response = HttpResponseType::ServerError(
+                            response_metadata,
+                            format!("Failed to load Stacks chain tip"),
+                        );
+                        response.send(&mut self.connection.protocol, &mut reply)?;
+                    }
+                }
+                None
+            }
+            HttpRequestType::GetAttachment(ref _md, ref content_hash) => {
+                ConversationHttp::handle_getattachment(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    atlasdb,
+                    content_hash.clone(),
+                )?
+            }
+            HttpRequestType::GetAttachmentsInv(ref _md, ref tip_opt, ref pages_indexes) => {
+                if let Some((tip_consensus_hash, tip_block_hash)) =
+                    ConversationHttp::handle_load_stacks_chain_tip_hashes(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        tip_opt.as_ref(),
+                        sortdb,
+                        chainstate,
+                    )?
+                {
+                    ConversationHttp::handle_getattachmentsinv(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        atlasdb,
+                        chainstate,
+                        &tip_consensus_hash,
+                        &tip_block_hash,
+                        pages_indexes,
+                        &self.connection.options,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetAttachmentInstances(
+                ref _md,
+                ref tip_opt,
+                ref pages_indexes,
+                ref contract_id_opt,
+                ref namespace_opt,
+            ) => {
+                if let Some((tip_consensus_hash, tip_block_hash)) =
+                    ConversationHttp::handle_load_stacks_chain_tip_hashes(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        tip_opt.as_ref(),
+                        sortdb,
+                        chainstate,
+                    )?
+                {
+                    ConversationHttp::handle_get_attachment_instances(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        atlasdb,
+                        chainstate,
+                        &tip_consensus_hash,
+                        &tip_block_hash,
+                        pages_indexes,
+                        contract_id_opt.as_ref(),
+                        namespace_opt.as_ref(),
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetStackerDBMetadata(ref _md, ref contract_id) => {
+                ConversationHttp::handle_get_stackerdb_metadata(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    stackerdbs,
+                    contract_id,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetStackerDBChunk(ref _md, ref contract_id, ref slot_id) => {
+                ConversationHttp::handle_get_stackerdb_chunk(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    stackerdbs,
+                    contract_id,
+                    *slot_id,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostStackerDBChunk(ref _md, ref contract_id, ref chunk) => {
+                let accepted = ConversationHttp::handle_post_stackerdb_chunk(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    stackerdbs,
+                    contract_id,
+                    chunk,
+                    &self.connection.options,
+                )?;
+                if accepted {
+                    // forward to peer network
+                    ret = Some(StacksMessageType::StackerDBPushChunk(
+                        StackerDBPushChunkData {
+                            contract_id: contract_id.clone(),
+                            chunk_data: chunk.clone(),
+                        },
+                    ));
+                }
+                None
+            }
+            HttpRequestType::PostMicroblock(ref _md, ref mblock, ref tip_opt) => {
+                if let Some((consensus_hash, block_hash)) =
+                    ConversationHttp::handle_load_stacks_chain_tip_hashes(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        tip_opt.as_ref(),
+                        sortdb,
+                        chainstate,
+                    )?
+                {
+                    let accepted = ConversationHttp::handle_post_microblock(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        consensus_hash,
+                        block_hash,
+                        chainstate,
+                        mblock.clone(),
+                    )?;
+                    if accepted {
+                        // forward to peer network
+                        let tip =
+                            StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
+                        ret = Some(StacksMessageType::Microblocks(MicroblocksData {
+                            index_anchor_block: tip,
+                            microblocks: vec![(*mblock).clone()],
+                        }));
+                    }
+                }
+                None
+            }
+            HttpRequestType::OptionsPreflight(ref _md, ref _path) => {
+                let response_metadata = HttpResponseMetadata::from(&req);
+                let response = HttpResponseType::OptionsPreflight(response_metadata);
+                response
+                    .send(&mut self.connection.protocol, &mut reply)
+                    .map(|_| ())?;
+                None
+            }
+            HttpRequestType::ClientError(ref _md, ref err) => {
+                let response_metadata = HttpResponseMetadata::from(&req);
+                let response = match err {
+                    ClientError::Message(s) => HttpResponseType::BadRequestJSON(
+                        response_metadata,
+                        serde_json::Value::String(s.to_string()),
+                    ),
+                    ClientError::NotFound(path) => {
+                        HttpResponseType::NotFound(response_metadata, path.clone())
+                    }
+                    ClientError::Unauthorized(s) => {
+                        HttpResponseType::Unauthorized(response_metadata, s.clone())
+                    }
+                };
+
+                response
+                    .send(&mut self.connection.protocol, &mut reply)
+                    .map(|_| ())?;
+                None
+            }
+        };
+
+        match stream_opt {
+            None => {
+                self.reply_streams.push_back((reply, None, keep_alive));
+            }
+            Some(stream) => {
+                self.reply_streams.push_back((
+                    reply,
+                    Some((
+                        HttpChunkedTransferWriterState::new(STREAM_CHUNK_SIZE as usize),
+                        stream,
+                    )),
+                    keep_alive,
+                ));
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Make progress on outbound requests.
+    /// Return true if the connection should be kept alive after all messages are drained.
+    /// If we process a request with "Connection: close", then return false (indicating that the
+    /// connection should be severed once the conversation is drained)
+    fn send_outbound_responses(
+        &mut self,
+        chainstate: &mut StacksChainState,
+    ) -> Result<(), net_error> {
+        // send out streamed responses in the order they were requested
+        let mut drained_handle = false;
+        let mut drained_stream = false;
+        let mut broken = false;
+        let mut do_keep_alive = true;
+
+        test_debug!(
+            "{:?}: {} HTTP replies pending",
+            &self,
+            self.reply_streams.len()
+        );
+        match self.reply_streams.front_mut() {
+            Some((ref mut reply, ref mut stream_opt, ref keep_alive)) => {
+                do_keep_alive = *keep_alive;
+
+                // if we're streaming, make some progress on the stream -- but only once the
+                // connection has picked up what we streamed last time, so a slow client can't make
+                // us buffer the whole body.
+                let backpressured = match stream_opt {
+                    Some(_) => match reply.try_flush_pending() {
+                        Ok(pending) => pending > 0,
+                        Err(e) => {
+                            warn!("Broken HTTP connection: {:?}", &e);
+                            broken = true;
+                            true
+                        }
+                    },
+                    None => false,
+                };
+                match stream_opt {
+                    Some(_) if backpressured => {
+                        test_debug!("Stream is waiting on the connection");
+                    }
+                    Some((ref mut http_chunk_state, ref mut stream)) => {
+                        let mut encoder =
+                            HttpChunkedTransferWriter::from_writer_state(reply, http_chunk_state);
+                        match stream.stream_to(chainstate, &mut encoder, STREAM_CHUNK_SIZE) {
+                            Ok(nw) => {
+                                test_debug!("streamed {} bytes", nw);
+                                if nw == 0 {
+                                    // EOF -- finish chunk and stop sending.
+                                    if !encoder.corked() {
+                                        encoder.flush().map_err(|e| {
+                                            test_debug!("Write error on encoder flush: {:?}", &e);
+                                            net_error::WriteError(e)
+                                        })?;
+
+                                        encoder.cork();
+
+                                        test_debug!("stream indicates EOF");
+                                    }
+
+                                    // try moving some data to the connection only once we're done
+                                    // streaming
+                                    match reply.try_flush() {
+                                        Ok(res) => {
+                                            test_debug!("Streamed reply is drained");
+                                            drained_handle = res;
+                                        }
+                                        Err(e) => {
+                                            // dead
+                                            warn!("Broken HTTP connection: {:?}", &e);
+                                            broken = true;
+                                        }
+                                    }
+                                    drained_stream = true;
+                                }
+                            }
+                            Err(e) => {
+                                // broken -- terminate the stream.
+                                // For example, if we're streaming an unconfirmed block or
+                                // microblock, the data can get moved to the chunk store out from
+                                // under the stream.
+                                warn!("Failed to send to HTTP connection: {:?}", &e);
+                                broken = true;
+                            }
+                        }
+                    }
+                    None => {
+                        // not streamed; all data is buffered
+                        drained_stream = true;
+
+                        // try moving some data to the connection
+                        match reply.try_flush() {
+                            Ok(res) => {
+                                test_debug!("Reply is drained");
+                                drained_handle = res;
+                            }
+                            Err(e) => {
+                                // dead
+                                warn!("Broken HTTP connection: {:?}", &e);
+                                broken = true;
+                            }
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+
+        if broken || (drained_handle && drained_stream) {
+            // done with this stream
+            test_debug!(
+                "{:?}: done with stream (broken={}, drained_handle={}, drained_stream={})",
+                &self,
+                broken,
+                drained_handle,
+                drained_stream
+            );
+            self.total_reply_count += 1;
+            self.reply_streams.pop_front();
+
+            if !do_keep_alive {
+                // encountered "Connection: close"
+                self.keep_alive = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn try_send_recv_response(
+        req: ReplyHandleHttp,
+    ) -> Result<HttpResponseType, Result<ReplyHandleHttp, net_error>> {
+        match req.try_send_recv() {
+            Ok(message) => match message {
+                StacksHttpMessage::Request(_) => {
+                    warn!("Received response: not a HTTP response");
+                    return Err(Err(net_error::InvalidMessage));
+                }
+                StacksHttpMessage::Response(http_response) => Ok(http_response),
+            },
+            Err(res) => Err(res),
+        }
+    }
+
+    /// Make progress on our request/response
+    fn recv_inbound_response(&mut self) -> Result<(), net_error> {
+        // make progress on our pending request (if it exists).
+        let inprogress = self.pending_request.is_some();
+        let is_pending = self.pending_response.is_none();
+
+        let pending_request = self.pending_request.take();
+        let response = match pending_request {
+            None => Ok(self.pending_response.take()),
+            Some(req) => match ConversationHttp::try_send_recv_response(req) {
+                Ok(response) => Ok(Some(response)),
+                Err(res) => match res {
+                    Ok(handle) => {
+                        // try again
+                        self.pending_request = Some(handle);
+                        Ok(self.pending_response.take())
+                    }
+                    Err(e) => Err(e),
+                },
+            },
+        }?;
+
+        self.pending_response = response;
+
+        if inprogress && self.pending_request.is_none() {
+            test_debug!(
+                "{:?},id={}: HTTP request finished",
+                &self.peer_host,
+                self.conn_id
+            );
+        }
+
+        if is_pending && self.pending_response.is_some() {
+            test_debug!(
+                "{:?},id={}: HTTP response finished",
+                &self.peer_host,
+                self.conn_id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Try to get our response
+    pub fn try_get_response(&mut self) -> Option<HttpResponseType> {
+        self.pending_response.take()
+    }
+
+    /// Make progress on in-flight messages.
+    pub fn try_flush(&mut self, chainstate: &mut StacksChainState) -> Result<(), net_error> {
+        self.send_outbound_responses(chainstate)?;
+        self.recv_inbound_response()?;
+        Ok(())
+    }
+
+    /// Is the connection idle?
+    pub fn is_idle(&self) -> bool {
+        self.pending_response.is_none()
+            && self.connection.inbox_len() == 0
+            && self.connection.outbox_len() == 0
+            && self.reply_streams.len() == 0
+    }
+
+    /// Is the conversation out of pending data?
+    /// Don't consider it drained if we haven't received anything yet
+    pub fn is_drained(&self) -> bool {
+        ((self.total_request_count > 0 && self.total_reply_count > 0)
+            || self.pending_error_response.is_some())
+            && self.is_idle()
+    }
+
+    /// Should the connection be kept alive even if drained?
+    pub fn is_keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    /// When was the last time we got an inbound request?
+    pub fn get_last_request_time(&self) -> u64 {
+        self.last_request_timestamp
+    }
+
+    /// When was the last time we sent data as part of an outbound response?
+    pub fn get_last_response_time(&self) -> u64 {
+        self.last_response_timestamp
+    }
+
+    /// When was this converation conencted?
+    pub fn get_connection_time(&self) -> u64 {
+        self.connection_time
+    }
+
+    /// Make progress on in-flight requests and replies.
+    /// Returns the list of transactions we'll need to forward to the peer network
+    pub fn chat(
+        &mut self,
+        chain_view: &BurnchainView,
+        peers: &PeerMap,
+        sortdb: &SortitionDB,
+        peerdb: &PeerDB,
+        atlasdb: &mut AtlasDB,
+        stackerdbs: &mut StackerDBs,
+        chainstate: &mut StacksChainState,
+        mempool: &mut MemPoolDB,
+        handler_args: &RPCHandlerArgs,
+        rate_limiter: &mut HttpRateLimiter,
+    ) -> Result<Vec<StacksMessageType>, net_error> {
+        // if we have an in-flight error, then don't take any more requests.
+        if self.pending_error_response.is_some() {
+            return Ok(vec![]);
+        }
+
+        // handle in-bound HTTP request(s)
+        let num_inbound = self.connection.inbox_len();
+        let mut ret = vec![];
+        test_debug!("{:?}: {} HTTP requests pending", &self, num_inbound);
+
+        for _i in 0..num_inbound {
+            let msg = match self.connection.next_inbox_message() {
+                None => {
+                    continue;
+                }
+                Some(m) => m,
+            };
+
+            match msg {
+                StacksHttpMessage::Request(req) => {
+                    // new request
+                    self.total_request_count += 1;
+                    self.last_request_timestamp = get_epoch_time_secs();
+                    let msg_opt = self.handle_request(
+                        req,
+                        chain_view,
+                        peers,
+                        sortdb,
+                        peerdb,
+                        atlasdb,
+                        stackerdbs,
+                        chainstate,
+                        mempool,
+                        handler_args,
+                        rate_limiter,
+                    )?;
+                    if let Some(msg) = msg_opt {
+                        ret.push(msg);
+                    }
+                }
+                StacksHttpMessage::Response(resp) => {
+                    // Is there someone else waiting for this message?  If so, pass it along.
+                    // (this _should_ be our pending_request handle)
+                    match self
+                        .connection
+                        .fulfill_request(StacksHttpMessage::Response(resp))
+                    {
+                        None => {
+                            test_debug!("{:?}: Fulfilled pending HTTP request", &self);
+                        }
+                        Some(_msg) => {
+                            // unsolicited; discard
+                            test_debug!("{:?}: Dropping unsolicited HTTP response", &self);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Remove all timed-out messages, and ding the remote peer as unhealthy
+    pub fn clear_timeouts(&mut self) -> () {
+        self.connection.drain_timeouts();
+    }
+
+    /// Load data into our HTTP connection
+    pub fn recv<R: Read>(&mut self, r: &mut R) -> Result<usize, net_error> {
+        let mut total_recv = 0;
+        loop {
+            let nrecv = match self.connection.recv_data(r) {
+                Ok(nr) => nr,
+                Err(e) => {
+                    debug!("{:?}: failed to recv: {:?}", self, &e);
+                    return Err(e);
+                }
+            };
+
+            total_recv += nrecv;
+            if nrecv > 0 {
+                self.last_request_timestamp = get_epoch_time_secs();
+            } else {
+                break;
+            }
+        }
+        Ok(total_recv)
+    }
+
+    /// Write data out of our HTTP connection.  Write as much as we can
+    pub fn send<W: Write>(
+        &mut self,
+        w: &mut W,
+        chainstate: &mut StacksChainState,
+    ) -> Result<usize, net_error> {
+        let mut total_sz = 0;
+        loop {
+            // prime the Write
+            self.try_flush(chainstate)?;
+
+            let sz = match self.connection.send_data(w) {
+                Ok(sz) => sz,
+                Err(e) => {
+                    info!("{:?}: failed to send on HTTP conversation: {:?}", self, &e);
+                    return Err(e);
+                }
+            };
+
+            total_sz += sz;
+            if sz > 0 {
+                self.last_response_timestamp = get_epoch_time_secs();
+            } else {
+                break;
+            }
+        }
+        Ok(total_sz)
+    }
+
+    /// Make a new getinfo request to this endpoint
+    pub fn new_getinfo(&self) -> HttpRequestType {
+        HttpRequestType::GetInfo(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new getinfo request to this endpoint
+    pub fn new_getpoxinfo(&self, tip_opt: Option<StacksBlockId>) -> HttpRequestType {
+        HttpRequestType::GetPoxInfo(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            tip_opt,
+        )
+    }
+
+    /// Make a new getrewardset request to this endpoint
+    pub fn new_getrewardset(&self, tip_opt: Option<StacksBlockId>) -> HttpRequestType {
+        HttpRequestType::GetRewardSet(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for the stackers delegating to a PoX delegate
+    pub fn new_get_pox_delegations(
+        &self,
+        delegate: PrincipalData,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetPoxDelegations(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            delegate,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request to simulate a PoX delegate's aggregation commit
+    pub fn new_post_aggregation_commit(
+        &self,
+        delegate: PrincipalData,
+        pox_addr: StacksAddress,
+        reward_cycle: u64,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::SimulateAggregationCommit(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            delegate,
+            pox_addr,
+            reward_cycle,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for a block's contract event bloom filter
+    pub fn new_get_block_event_bloom(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
+        HttpRequestType::GetBlockEventBloom(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            index_block_hash,
+        )
+    }
+
+    /// Make a new request for a chain of block headers
+    pub fn new_getheaders(&self, quantity: u64, tip_opt: Option<StacksBlockId>) -> HttpRequestType {
+        HttpRequestType::GetHeaders(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            quantity,
+            tip_opt,
+        )
+    }
+
+    /// Make a new getburnblockinfo request to this endpoint
+    pub fn new_getburnblockinfo(&self, burn_block_height: u64) -> HttpRequestType {
+        HttpRequestType::GetBurnBlockInfo(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            burn_block_height,
+        )
+    }
+
+    /// Make a new request for the burnchain ops at a height or with a txid to this endpoint
+    pub fn new_getburnchainops(&self, query: BurnchainOpsQuery) -> HttpRequestType {
+        HttpRequestType::GetBurnchainOps(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            query,
+        )
+    }
+
+    /// Make a new getneighbors request to this endpoint
+    pub fn new_getneighbors(&self) -> HttpRequestType {
+        HttpRequestType::GetNeighbors(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new getblock request to this endpoint
+    pub fn new_getblock(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
+        HttpRequestType::GetBlock(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            index_block_hash,
+        )
+    }
+
+    /// Make a new get-microblocks request to this endpoint
+    pub fn new_getmicroblocks_indexed(
+        &self,
+        index_microblock_hash: StacksBlockId,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksIndexed(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            index_microblock_hash,
+        )
+    }
+
+    /// Make a new get-microblocks-confirmed request to this endpoint
+    pub fn new_getmicroblocks_confirmed(
+        &self,
+        index_anchor_block_hash: StacksBlockId,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksConfirmed(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            index_anchor_block_hash,
+        )
+    }
+
+    /// Make a new get-microblocks request for unconfirmed microblocks
+    pub fn new_getmicroblocks_unconfirmed(
+        &self,
+        anchored_index_block_hash: StacksBlockId,
+        min_seq: u16,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksUnconfirmed(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            anchored_index_block_hash,
+            min_seq,
+        )
+    }
+
+    /// Make a new get-unconfirmed-tx request
+    pub fn new_gettransaction_unconfirmed(&self, txid: Txid) -> HttpRequestType {
+        HttpRequestType::GetTransactionUnconfirmed(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            txid,
+        )
+    }
+
+    /// Make a new post-transaction request
+    pub fn new_post_transaction(&self, tx: StacksTransaction) -> HttpRequestType {
+        HttpRequestType::PostTransaction(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            tx,
+            None,
+        )
+    }
+
+    /// Make a new request for a transaction fee estimate
+    pub fn new_post_fee_estimate(&self, tx: StacksTransaction) -> HttpRequestType {
+        HttpRequestType::EstimateTransactionFee(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            tx,
+        )
+    }
+
+    /// Make a new request to simulate a transaction
+    pub fn new_post_simulate_transaction(&self, tx: StacksTransaction) -> HttpRequestType {
+        HttpRequestType::SimulateTransaction(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            tx,
+        )
+    }
+
+    /// Make a new request to analyze a contract without deploying it
+    pub fn new_post_analyze_contract(
+        &self,
+        source: String,
+        contract_id_opt: Option<QualifiedContractIdentifier>,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::AnalyzeContract(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            source,
+            contract_id_opt,
+            tip_opt,
+        )
+    }
+
+    /// Make a new post-microblock request
+    pub fn new_post_microblock(
+        &self,
+        mblock: StacksMicroblock,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::PostMicroblock(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            mblock,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for an account
+    pub fn new_getaccount(
+        &self,
+        principal: PrincipalData,
+        tip_opt: Option<StacksBlockId>,
+        height_opt: Option<u64>,
+        with_proof: bool,
+        view: RPCStateView,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAccount(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            principal,
+            tip_opt,
+            height_opt,
+            with_proof,
+            view,
+        )
+    }
+
+    /// Make a new request for an account's asset holdings
+    pub fn new_getaccountassets(
+        &self,
+        principal: PrincipalData,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAccountAssets(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            principal,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for an account's confirmed and mempool nonces
+    pub fn new_getaccountnonces(
+        &self,
+        principal: PrincipalData,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAccountNonces(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            principal,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for an account's balance, nonce and first-seen block
+    pub fn new_getaccountsummary(
+        &self,
+        principal: PrincipalData,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAccountSummary(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            principal,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for a mined transaction's receipt
+    pub fn new_gettransactionreceipt(
+        &self,
+        txid: Txid,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetTransactionReceipt(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            txid,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for a page of the transactions that involved a principal
+    pub fn new_getprincipaltransactions(
+        &self,
+        principal: PrincipalData,
+        offset: u64,
+        limit: u64,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetPrincipalTransactions(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            principal,
+            offset,
+            limit,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for the miner rewards of an address that have not matured yet
+    pub fn new_getpendingminerrewards(
+        &self,
+        address: StacksAddress,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetPendingMinerRewards(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            address,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for the unconfirmed microblock stream built on the chain tip
+    pub fn new_getunconfirmedmicroblockstream(
+        &self,
+        min_seq: u16,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetUnconfirmedMicroblockStream(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            min_seq,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request to garbage-collect the mempool
+    pub fn new_mempool_gc(&self) -> HttpRequestType {
+        HttpRequestType::MemPoolGC(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new request to have a dev node mine a block right away
+    pub fn new_dev_mine(&self, request: RPCDevMineRequest) -> HttpRequestType {
+        HttpRequestType::DevMine(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            request,
+        )
+    }
+
+    /// Make a new request to simulate assembling a block from the mempool
+    pub fn new_simulate_block(&self) -> HttpRequestType {
+        HttpRequestType::SimulateBlock(HttpRequestMetadata::from_host(self.peer_host.clone()))
+    }
+
+    /// Make a new request to validate a proposed block, authenticated with the given token
+    pub fn new_validate_block_proposal(
+        &self,
+        parent_consensus_hash: ConsensusHash,
+        block: StacksBlock,
+        auth_opt: Option<String>,
+    ) -> HttpRequestType {
+        HttpRequestType::ValidateBlockProposal(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            parent_consensus_hash,
+            block,
+            auth_opt,
+        )
+    }
+
+    /// Make a new request to upload an anchored block
+    pub fn new_post_block(
+        &self,
+        consensus_hash: ConsensusHash,
+        block: StacksBlock,
+    ) -> HttpRequestType {
+        HttpRequestType::PostBlock(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            consensus_hash,
+            block,
+        )
+    }
+
+    /// Make a new request for the miner's state, authenticated with the given token
+    pub fn new_get_miner_status(&self, auth_opt: Option<String>) -> HttpRequestType {
+        HttpRequestType::GetMinerStatus(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            auth_opt,
+        )
+    }
+
+    /// Make a new request to list, schedule or cancel the node's scheduled calls, authenticated
+    /// with the given token
+    pub fn new_scheduled_calls(
+        &self,
+        op: ScheduledCallsOp,
+        auth_opt: Option<String>,
+    ) -> HttpRequestType {
+        HttpRequestType::ScheduledCalls(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            op,
+            auth_opt,
+        )
+    }
+
+    /// Make a new request for a data map
+    pub fn new_getmapentry(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        map_name: ClarityName,
+        key: Value,
+        tip_opt: Option<StacksBlockId>,
+        height_opt: Option<u64>,
+        with_proof: bool,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMapEntry(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_addr,
+            contract_name,
+            map_name,
+            key,
+            tip_opt,
+            height_opt,
+            with_proof,
+        )
+    }
+
+    /// Make a new request to run a batch of read-only queries against one chain tip
+    pub fn new_batch(
+        &self,
+        queries: Vec<BatchQuery>,
+        tip_opt: Option<StacksBlockId>,
+        with_proof: bool,
+        view: RPCStateView,
+    ) -> HttpRequestType {
+        HttpRequestType::Batch(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            queries,
+            tip_opt,
+            with_proof,
+            view,
+        )
+    }
+
+    /// Make a new request to get a contract's source
+    pub fn new_getcontractsrc(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        tip_opt: Option<StacksBlockId>,
+        height_opt: Option<u64>,
+        with_proof: bool,
+    ) -> HttpRequestType {
+        HttpRequestType::GetContractSrc(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_addr,
+            contract_name,
+            tip_opt,
+            height_opt,
+            with_proof,
+        )
+    }
+
+    /// Make a new request to get a contract's data var
+    pub fn new_getdatavar(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        var_name: ClarityName,
+        tip_opt: Option<StacksBlockId>,
+        with_proof: bool,
+    ) -> HttpRequestType {
+        HttpRequestType::GetDataVar(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_addr,
+            contract_name,
+            var_name,
+            tip_opt,
+            with_proof,
+        )
+    }
+
+    /// Make a new request to get a contract's ABI
+    pub fn new_getcontractabi(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetContractABI(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_addr,
+            contract_name,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request to run a read-only function
+    pub fn new_callreadonlyfunction(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        sender: PrincipalData,
+        function_name: ClarityName,
+        function_args: Vec<Value>,
+        tip_opt: Option<StacksBlockId>,
+        height_opt: Option<u64>,
+        view: RPCStateView,
+    ) -> HttpRequestType {
+        HttpRequestType::CallReadOnlyFunction(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_addr,
+            contract_name,
+            sender,
+            function_name,
+            function_args,
+            tip_opt,
+            height_opt,
+            view,
+        )
+    }
+
+    /// Make a new request for attachment inventory page
+    pub fn new_getattachmentsinv(
+        &self,
+        tip_opt: Option<StacksBlockId>,
+        pages_indexes: HashSet<u32>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAttachmentsInv(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            tip_opt,
+            pages_indexes,
+        )
+    }
+
+    /// Make a new request for the attachment instances in a set of attachment inventory pages
+    pub fn new_getattachmentinstances(
+        &self,
+        tip_opt: Option<StacksBlockId>,
+        pages_indexes: HashSet<u32>,
+        contract_id_opt: Option<QualifiedContractIdentifier>,
+        namespace_opt: Option<String>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAttachmentInstances(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            tip_opt,
+            pages_indexes,
+            contract_id_opt,
+            namespace_opt,
+        )
+    }
+
+    /// Make a new request for the metadata of a StackerDB's slots
+    pub fn new_get_stackerdb_metadata(
+        &self,
+        contract_id: QualifiedContractIdentifier,
+    ) -> HttpRequestType {
+        HttpRequestType::GetStackerDBMetadata(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_id,
+        )
+    }
+
+    /// Make a new request for the chunk in a StackerDB slot
+    pub fn new_get_stackerdb_chunk(
+        &self,
+        contract_id: QualifiedContractIdentifier,
+        slot_id: u32,
+    ) -> HttpRequestType {
+        HttpRequestType::GetStackerDBChunk(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_id,
+            slot_id,
+        )
+    }
+
+    /// Make a new request to write a chunk to a StackerDB slot
+    pub fn new_post_stackerdb_chunk(
+        &self,
+        contract_id: QualifiedContractIdentifier,
+        chunk: StackerDBChunkData,
+    ) -> HttpRequestType {
+        HttpRequestType::PostStackerDBChunk(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_id,
+            chunk,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use net::codec::*;
+    use net::http::*;
+    use net::test::*;
+    use net::*;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::iter::FromIterator;
+
+    use burnchains::Burnchain;
+    use burnchains::BurnchainHeaderHash;
+    use burnchains::BurnchainView;
+
+    use burnchains::*;
+    use chainstate::burn::BlockHeaderHash;
+    use chainstate::burn::ConsensusHash;
+    use chainstate::stacks::db::blocks::test::*;
+    use chainstate::stacks::db::BlockStreamData;
+    use chainstate::stacks::db::StacksChainState;
+    use chainstate::stacks::db::MINER_REWARD_MATURITY;
+    use chainstate::stacks::events::EventBloom;
+    use chainstate::stacks::index::proofs::TrieMerkleProof;
+    use chainstate::stacks::index::TrieHash;
+    use chainstate::stacks::miner::*;
+    use chainstate::stacks::test::*;
+    use chainstate::stacks::Error as chain_error;
+    use chainstate::stacks::*;
+
+    use address::*;
+
+    use util::get_epoch_time_secs;
+    use util::hash::hex_bytes;
+    use util::pipe::*;
+
+    use std::convert::TryInto;
+
+    use core::mempool::MemPoolGCPolicy;
+    use core::scheduler::{ScheduledCallRequest, ScheduledCallStatus};
+    use core::FIRST_BURNCHAIN_CONSENSUS_HASH;
+    use net::atlas::AttachmentInstance;
+    use vm::database::ClaritySerializable;
+    use vm::types::*;
+
+    const TEST_CONTRACT: &'static str = "
+        (define-data-var bar int 0)
+        (define-map unit-map { account: principal } { units: int })
+        (define-public (get-bar) (ok (var-get bar)))
+        (define-public (set-bar (x int) (y int))
+          (begin (var-set bar (/ x y)) (ok (var-get bar))))
+        (define-public (add-unit)
+          (begin 
+            (map-set unit-map { account: tx-sender } { units: 1 } )
+            (ok 1)))
+        (define-fungible-token stackaroos)
+        (define-non-fungible-token stacka-nfts (buff 1))
+        (begin
+          (map-set unit-map { account: 'ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R } { units: 123 })
+          (unwrap-panic (ft-mint? stackaroos u100 'ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R))
+          (unwrap-panic (nft-mint? stacka-nfts 0x01 'ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R)))";
+
+    fn convo_send_recv(
+        sender: &mut ConversationHttp,
+        sender_chainstate: &mut StacksChainState,
+        receiver: &mut ConversationHttp,
+        receiver_chainstate: &mut StacksChainState,
+    ) -> () {
+        let (mut pipe_read, mut pipe_write) = Pipe::new();
+        pipe_read.set_nonblocking(true);
+
+        loop {
+            let res = true;
+
+            sender.try_flush(sender_chainstate).unwrap();
+            receiver.try_flush(receiver_chainstate).unwrap();
+
+            pipe_write.try_flush().unwrap();
+
+            let all_relays_flushed =
+                receiver.num_pending_outbound() == 0 && sender.num_pending_outbound() == 0;
+
+            let nw = sender.send(&mut pipe_write, sender_chainstate).unwrap();
+            let nr = receiver.recv(&mut pipe_read).unwrap();
+
+            test_debug!(
+                "res = {}, all_relays_flushed = {} ({},{}), nr = {}, nw = {}",
+                res,
+                all_relays_flushed,
+                receiver.num_pending_outbound(),
+                sender.num_pending_outbound(),
+                nr,
+                nw
+            );
+            if res && all_relays_flushed && nr == 0 && nw == 0 {
+                test_debug!("Breaking send_recv");
+                break;
+            }
+        }
+    }
+
+    fn test_rpc<F, C>(
+        test_name: &str,
+        peer_1_p2p: u16,
+        peer_1_http: u16,
+        peer_2_p2p: u16,
+        peer_2_http: u16,
+        make_request: F,
+        check_result: C,
+    ) -> ()
+    where
+        F: FnOnce(
+            &mut TestPeer,
+            &mut ConversationHttp,
+            &mut TestPeer,
+            &mut ConversationHttp,
+        ) -> HttpRequestType,
+        C: FnOnce(&HttpRequestType, &HttpResponseType, &mut TestPeer, &mut TestPeer) -> bool,
+    {
+        test_rpc_with_handler_args(
+            test_name,
+            peer_1_p2p,
+            peer_1_http,
+            peer_2_p2p,
+            peer_2_http,
+            &RPCHandlerArgs::default(),
+            make_request,
+            check_result,
+        )
+    }
+
+    /// Like `test_rpc`, but the server answers with the given handler args
+    fn test_rpc_with_handler_args<F, C>(
+        test_name: &str,
+        peer_1_p2p: u16,
+        peer_1_http: u16,
+        peer_2_p2p: u16,
+        peer_2_http: u16,
+        server_handler_args: &RPCHandlerArgs,
+        make_request: F,
+        check_result: C,
+    ) -> ()
+    where
+        F: FnOnce(
+            &mut TestPeer,
+            &mut ConversationHttp,
+            &mut TestPeer,
+            &mut ConversationHttp,
+        ) -> HttpRequestType,
+        C: FnOnce(&HttpRequestType, &HttpResponseType, &mut TestPeer, &mut TestPeer) -> bool,
+    {
+        let mut peer_1_config = TestPeerConfig::new(test_name, peer_1_p2p, peer_1_http);
+        let mut peer_2_config = TestPeerConfig::new(test_name, peer_2_p2p, peer_2_http);
+
+        // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R
+        let privk1 = StacksPrivateKey::from_hex(
+            "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+        )
+        .unwrap();
+
+        // STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW
+        let privk2 = StacksPrivateKey::from_hex(
+            "94c319327cc5cd04da7147d32d836eb2e4c44f4db39aa5ede7314a761183d0c701",
+        )
+        .unwrap();
+        let microblock_privkey = StacksPrivateKey::new();
+        let microblock_pubkeyhash =
+            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+
+        let addr1 = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk1)],
+        )
+        .unwrap();
+        let addr2 = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk2)],
+        )
+        .unwrap();
+
+        peer_1_config.initial_balances = vec![
+            (addr1.to_account_principal(), 1000000000),
+            (addr2.to_account_principal(), 1000000000),
+        ];
+
+        peer_2_config.initial_balances = vec![
+            (addr1.to_account_principal(), 1000000000),
+            (addr2.to_account_principal(), 1000000000),
+        ];
+
+        peer_1_config.add_neighbor(&peer_2_config.to_neighbor());
+        peer_2_config.add_neighbor(&peer_1_config.to_neighbor());
+
+        let mut peer_1 = TestPeer::new(peer_1_config);
+        let mut peer_2 = TestPeer::new(peer_2_config);
+
+        // mine one block with a contract in it
+        // first the coinbase
+        // make a coinbase for this miner
+        let mut tx_coinbase = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::Coinbase(CoinbasePayload([0x00; 32]), None),
+        );
+        tx_coinbase.chain_id = 0x80000000;
+        tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
+        tx_coinbase.auth.set_origin_nonce(0);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx_coinbase);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_coinbase_signed = tx_signer.get_tx().unwrap();
+
+        // next the contract
+        let contract = TEST_CONTRACT.clone();
+        let mut tx_contract = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::new_smart_contract(&format!("hello-world"), &contract.to_string())
+                .unwrap(),
+        );
+
+        tx_contract.chain_id = 0x80000000;
+        tx_contract.auth.set_origin_nonce(1);
+        tx_contract.set_fee_rate(0);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx_contract);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_contract_signed = tx_signer.get_tx().unwrap();
+
+        // update account and state in a microblock that will be unconfirmed
+        let mut tx_cc = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::new_contract_call(addr1.clone(), "hello-world", "add-unit", vec![])
+                .unwrap(),
+        );
+
+        tx_cc.chain_id = 0x80000000;
+        tx_cc.auth.set_origin_nonce(2);
+        tx_cc.set_fee_rate(123);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx_cc);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_cc_signed = tx_signer.get_tx().unwrap();
+        let tx_cc_len = {
+            let mut bytes = vec![];
+            tx_cc_signed.consensus_serialize(&mut bytes).unwrap();
+            bytes.len() as u64
+        };
+
+        // make an unconfirmed contract
+        let unconfirmed_contract = "(define-read-only (ro-test) (ok 1))";
+        let mut tx_unconfirmed_contract = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk1).unwrap(),
+            TransactionPayload::new_smart_contract(
+                &format!("hello-world-unconfirmed"),
+                &unconfirmed_contract.to_string(),
+            )
+            .unwrap(),
+        );
+
+        tx_unconfirmed_contract.chain_id = 0x80000000;
+        tx_unconfirmed_contract.auth.set_origin_nonce(3);
+        tx_unconfirmed_contract.set_fee_rate(0);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx_unconfirmed_contract);
+        tx_signer.sign_origin(&privk1).unwrap();
+        let tx_unconfirmed_contract_signed = tx_signer.get_tx().unwrap();
+        let tx_unconfirmed_contract_len = {
+            let mut bytes = vec![];
+            tx_unconfirmed_contract_signed
+                .consensus_serialize(&mut bytes)
+                .unwrap();
+            bytes.len() as u64
+        };
+
+        let tip =
+            SortitionDB::get_canonical_burn_chain_tip(&peer_1.sortdb.as_ref().unwrap().conn())
+                .unwrap();
+        let mut anchor_cost = ExecutionCost::zero();
+        let mut anchor_size = 0;
+
+        // make a block and a microblock.
+        // Put the coinbase and smart-contract in the anchored block.
+        // Put the contract-call in the microblock
+        let (burn_ops, stacks_block, microblocks) = peer_1.make_tenure(
+            |ref mut miner, ref mut sortdb, ref mut chainstate, vrf_proof, ref parent_opt, _| {
+                let parent_tip = match parent_opt {
+                    None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                    Some(block) => {
+                        let ic = sortdb.index_conn();
+                        let snapshot = SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                            &ic,
+                            &tip.sortition_id,
+                            &block.block_hash(),
+                        )
+                        .unwrap()
+                        .unwrap(); // succeeds because we don't fork
+                        StacksChainState::get_anchored_block_header_info(
+                            chainstate.db(),
+                            &snapshot.consensus_hash,
+                            &snapshot.winning_stacks_block_hash,
+                        )
+                        .unwrap()
+                        .unwrap()
+                    }
+                };
+
+                let block_builder = StacksBlockBuilder::make_block_builder(
+                    &parent_tip,
+                    vrf_proof,
+                    tip.total_burn,
+                    microblock_pubkeyhash,
+                )
+                .unwrap();
+                let (anchored_block, anchored_block_size, anchored_block_cost) =
+                    StacksBlockBuilder::make_anchored_block_from_txs(
+                        block_builder,
+                        chainstate,
+                        &sortdb.index_conn(),
+                        vec![tx_coinbase_signed.clone(), tx_contract_signed.clone()],
+                    )
+                    .unwrap();
+
+                anchor_size = anchored_block_size;
+                anchor_cost = anchored_block_cost;
+
+                (anchored_block, vec![])
+            },
+        );
+
+        let (_, _, consensus_hash) = peer_1.next_burnchain_block(burn_ops.clone());
+        peer_2.next_burnchain_block(burn_ops.clone());
+
+        peer_1.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
+        peer_2.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
+
+        // build 1-block microblock stream with the contract-call and the unconfirmed contract
+        let microblock = {
+            let sortdb = peer_1.sortdb.take().unwrap();
+            PeerNetwork::setup_unconfirmed_state(peer_1.chainstate(), &sortdb).unwrap();
+            let mblock = {
+                let sort_iconn = sortdb.index_conn();
+                let mut microblock_builder = StacksMicroblockBuilder::new(
+                    stacks_block.block_hash(),
+                    consensus_hash.clone(),
+                    peer_1.chainstate(),
+                    &sort_iconn,
+                )
+                .unwrap();
+                let microblock = microblock_builder
+                    .mine_next_microblock_from_txs(
+                        vec![
+                            (tx_cc_signed, tx_cc_len),
+                            (tx_unconfirmed_contract_signed, tx_unconfirmed_contract_len),
+                        ],
+                        &microblock_privkey,
+                    )
+                    .unwrap();
+                microblock
+            };
+            peer_1.sortdb = Some(sortdb);
+            mblock
+        };
+
+        // store microblock stream
+        peer_1
+            .chainstate()
+            .preprocess_streamed_microblock(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+                &microblock,
+            )
+            .unwrap();
+        peer_2
+            .chainstate()
+            .preprocess_streamed_microblock(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+                &microblock,
+            )
+            .unwrap();
+
+        // process microblock stream to generate unconfirmed state
+        let canonical_tip =
+            StacksBlockHeader::make_index_block_hash(&consensus_hash, &stacks_block.block_hash());
+        let sortdb1 = peer_1.sortdb.take().unwrap();
+        let sortdb2 = peer_2.sortdb.take().unwrap();
+        peer_1
+            .chainstate()
+            .reload_unconfirmed_state(&sortdb1.index_conn(), canonical_tip.clone())
+            .unwrap();
+        peer_2
+            .chainstate()
+            .reload_unconfirmed_state(&sortdb2.index_conn(), canonical_tip.clone())
+            .unwrap();
+        peer_1.sortdb = Some(sortdb1);
+        peer_2.sortdb = Some(sortdb2);
+
+        let view_1 = peer_1.get_burnchain_view().unwrap();
+        let view_2 = peer_2.get_burnchain_view().unwrap();
+
+        let mut convo_1 = ConversationHttp::new(
+            peer_1.config.network_id,
+            &peer_1.config.burnchain,
+            format!("127.0.0.1:{}", peer_1_http)
+                .parse::<SocketAddr>()
+                .unwrap(),
+            Some(UrlString::try_from(format!("http://peer1.com")).unwrap()),
+            peer_1.to_peer_host(),
+            &peer_1.config.connection_opts,
+            0,
+        );
+
+        let mut convo_2 = ConversationHttp::new(
+            peer_2.config.network_id,
+            &peer_2.config.burnchain,
+            format!("127.0.0.1:{}", peer_2_http)
+                .parse::<SocketAddr>()
+                .unwrap(),
+            Some(UrlString::try_from(format!("http://peer2.com")).unwrap()),
+            peer_2.to_peer_host(),
+            &peer_2.config.connection_opts,
+            1,
+        );
+
+        let req = make_request(&mut peer_1, &mut convo_1, &mut peer_2, &mut convo_2);
+
+        convo_1.send_request(req.clone()).unwrap();
+
+        test_debug!("convo1 sends to convo2");
+        convo_send_recv(
+            &mut convo_1,
+            peer_1.chainstate(),
+            &mut convo_2,
+            peer_2.chainstate(),
+        );
+
+        // hack around the borrow-checker
+        let mut peer_1_sortdb = peer_1.sortdb.take().unwrap();
+        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
+        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
+
+        PeerNetwork::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
+            .unwrap();
+
+        convo_1
+            .chat(
+                &view_1,
+                &PeerMap::new(),
+                &mut peer_1_sortdb,
+                &peer_1.network.peerdb,
+                &mut peer_1.network.atlasdb,
+                &mut peer_1.network.stackerdbs,
+                &mut peer_1_stacks_node.chainstate,
+                &mut peer_1_mempool,
+                &RPCHandlerArgs::default(),
+                &mut HttpRateLimiter::new(),
+            )
+            .unwrap();
+
+        peer_1.sortdb = Some(peer_1_sortdb);
+        peer_1.stacks_node = Some(peer_1_stacks_node);
+        peer_1.mempool = Some(peer_1_mempool);
+
+        test_debug!("convo2 sends to convo1");
+
+        // hack around the borrow-checker
+        let mut peer_2_sortdb = peer_2.sortdb.take().unwrap();
+        let mut peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
+        let mut peer_2_mempool = peer_2.mempool.take().unwrap();
+
+        PeerNetwork::setup_unconfirmed_state(&mut peer_2_stacks_node.chainstate, &peer_2_sortdb)
+            .unwrap();
+
+        convo_2
+            .chat(
+                &view_2,
+                &PeerMap::new(),
+                &mut peer_2_sortdb,
+                &peer_2.network.peerdb,
+                &mut peer_2.network.atlasdb,
+                &mut peer_2.network.stackerdbs,
+                &mut peer_2_stacks_node.chainstate,
+                &mut peer_2_mempool,
+                server_handler_args,
+                &mut HttpRateLimiter::new(),
+            )
+            .unwrap();
+
+        peer_2.sortdb = Some(peer_2_sortdb);
+        peer_2.stacks_node = Some(peer_2_stacks_node);
+        peer_2.mempool = Some(peer_2_mempool);
+
+        convo_send_recv(
+            &mut convo_2,
+            peer_2.chainstate(),
+            &mut convo_1,
+            peer_1.chainstate(),
+        );
+
+        test_debug!("flush convo1");
+
+        // hack around the borrow-checker
+        convo_send_recv(
+            &mut convo_1,
+            peer_1.chainstate(),
+            &mut convo_2,
+            peer_2.chainstate(),
+        );
+
+        let mut peer_1_sortdb = peer_1.sortdb.take().unwrap();
+        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
+        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
+
+        PeerNetwork::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
+            .unwrap();
+
+        convo_1
+            .chat(
+                &view_1,
+                &PeerMap::new(),
+                &mut peer_1_sortdb,
+                &peer_1.network.peerdb,
+                &mut peer_1.network.atlasdb,
+                &mut peer_1.network.stackerdbs,
+                &mut peer_1_stacks_node.chainstate,
+                &mut peer_1_mempool,
+                &RPCHandlerArgs::default(),
+                &mut HttpRateLimiter::new(),
+            )
+            .unwrap();
+
+        peer_1.sortdb = Some(peer_1_sortdb);
+        peer_1.stacks_node = Some(peer_1_stacks_node);
+        peer_1.mempool = Some(peer_1_mempool);
+
+        convo_1.try_flush(peer_1.chainstate()).unwrap();
+
+        // should have gotten a reply
+        let resp_opt = convo_1.try_get_response();
+        assert!(resp_opt.is_some());
+
+        let resp = resp_opt.unwrap();
+        assert!(check_result(&req, &resp, &mut peer_1, &mut peer_2));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_getinfo() {
+        let peer_server_info = RefCell::new(None);
+        test_rpc(
+            "test_rpc_getinfo",
+            40000,
+            40001,
+            50000,
+            50001,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_info = RPCPeerInfoData::from_db(
+                    &peer_server.config.burnchain,
+                    peer_server.sortdb.as_mut().unwrap(),
+                    &peer_server.stacks_node.as_ref().unwrap().chainstate,
+                    &peer_server.network.peerdb,
+                    &None,
+                    &Sha256Sum::zero(),
+                    &None,
+                )
+                .unwrap();
+
+                *peer_server_info.borrow_mut() = Some(peer_info);
+
+                convo_client.new_getinfo()
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PeerInfo(response_md, peer_data) => {
+                        assert_eq!(Some((*peer_data).clone()), *peer_server_info.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_getpoxinfo() {
+        let pox_server_info = RefCell::new(None);
+        test_rpc(
+            "test_rpc_getpoxinfo",
+            40000,
+            40001,
+            50000,
+            50001,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = {
+                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                    StacksBlockHeader::make_index_block_hash(
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                    )
+                };
+                let pox_info = RPCPoxInfoData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &stacks_block_id,
+                    &ConnectionOptions::default(),
+                )
+                .unwrap();
+                *pox_server_info.borrow_mut() = Some(pox_info);
+                convo_client.new_getpoxinfo(None)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PoxInfo(response_md, pox_data) => {
+                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
                     }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_getrewardset() {
+        let reward_set_server_info = RefCell::new(None);
+        test_rpc(
+            "test_rpc_getrewardset",
+            40204,
+            40205,
+            50204,
+            50205,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let burnchain = peer_server.config.burnchain.clone();
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = {
+                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                    StacksBlockHeader::make_index_block_hash(
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                    )
                 };
+                let reward_set = RPCRewardSetData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &burnchain,
+                    &stacks_block_id,
+                )
+                .unwrap();
 
-                response
-                    .send(&mut self.connection.protocol, &mut reply)
-                    .map(|_| ())?;
-                None
-            }
-        };
+                // nobody has stacked yet
+                assert!(reward_set.entries.is_empty());
+                assert_eq!(reward_set.participation_ustx, 0);
+                assert!(!reward_set.enough_participation);
+                assert_eq!(
+                    reward_set.reward_cycle_start_burn_height,
+                    burnchain.reward_cycle_to_block_height(reward_set.reward_cycle_id)
+                );
+                assert_eq!(
+                    reward_set.prepare_phase_start_burn_height
+                        + burnchain.pox_constants.prepare_length as u64,
+                    reward_set.reward_cycle_start_burn_height
+                );
+                assert_eq!(
+                    reward_set.blocks_until_prepare_phase,
+                    reward_set
+                        .prepare_phase_start_burn_height
+                        .saturating_sub(reward_set.burn_block_height)
+                );
 
-        match stream_opt {
-            None => {
-                self.reply_streams.push_back((reply, None, keep_alive));
-            }
-            Some(stream) => {
-                self.reply_streams.push_back((
-                    reply,
-                    Some((
-                        HttpChunkedTransferWriterState::new(STREAM_CHUNK_SIZE as usize),
-                        stream,
-                    )),
-                    keep_alive,
-                ));
-            }
-        }
-        Ok(ret)
+                *reward_set_server_info.borrow_mut() = Some(reward_set);
+                convo_client.new_getrewardset(None)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::RewardSet(response_md, reward_set) => {
+                        assert_eq!(
+                            Some((*reward_set).clone()),
+                            *reward_set_server_info.borrow()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// Make progress on outbound requests.
-    /// Return true if the connection should be kept alive after all messages are drained.
-    /// If we process a request with "Connection: close", then return false (indicating that the
-    /// connection should be severed once the conversation is drained)
-    fn send_outbound_responses(
-        &mut self,
-        chainstate: &mut StacksChainState,
-    ) -> Result<(), net_error> {
-        // send out streamed responses in the order they were requested
-        let mut drained_handle = false;
-        let mut drained_stream = false;
-        let mut broken = false;
-        let mut do_keep_alive = true;
+    #[test]
+    #[ignore]
+    fn test_rpc_pox_delegations_and_aggregation_commit() {
+        let delegate = PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let pox_addr =
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
 
-        test_debug!(
-            "{:?}: {} HTTP replies pending",
-            &self,
-            self.reply_streams.len()
+        let delegations_server_info = RefCell::new(None);
+        test_rpc(
+            "test_rpc_get_pox_delegations",
+            40206,
+            40207,
+            50206,
+            50207,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = {
+                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                    StacksBlockHeader::make_index_block_hash(
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                    )
+                };
+                let delegations = RPCPoxDelegationsData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &stacks_block_id,
+                    &delegate,
+                )
+                .unwrap();
+
+                // nobody has delegated yet
+                assert!(delegations.delegations.is_empty());
+                assert_eq!(delegations.total_delegated_ustx, 0);
+                assert!(delegations.delegated_to.is_none());
+                assert!(delegations.partial_stacks.is_empty());
+
+                *delegations_server_info.borrow_mut() = Some(delegations);
+                convo_client.new_get_pox_delegations(delegate.clone(), None)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PoxDelegations(response_md, delegations) => {
+                        assert_eq!(
+                            Some((*delegations).clone()),
+                            *delegations_server_info.borrow()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
-        match self.reply_streams.front_mut() {
-            Some((ref mut reply, ref mut stream_opt, ref keep_alive)) => {
-                do_keep_alive = *keep_alive;
 
-                // if we're streaming, make some progress on the stream
-                match stream_opt {
-                    Some((ref mut http_chunk_state, ref mut stream)) => {
-                        let mut encoder =
-                            HttpChunkedTransferWriter::from_writer_state(reply, http_chunk_state);
-                        match stream.stream_to(chainstate, &mut encoder, STREAM_CHUNK_SIZE) {
-                            Ok(nw) => {
-                                test_debug!("streamed {} bytes", nw);
-                                if nw == 0 {
-                                    // EOF -- finish chunk and stop sending.
-                                    if !encoder.corked() {
-                                        encoder.flush().map_err(|e| {
-                                            test_debug!("Write error on encoder flush: {:?}", &e);
-                                            net_error::WriteError(e)
-                                        })?;
+        let simulation_server_info = RefCell::new(None);
+        test_rpc(
+            "test_rpc_post_aggregation_commit",
+            40208,
+            40209,
+            50208,
+            50209,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = {
+                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                    StacksBlockHeader::make_index_block_hash(
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                    )
+                };
+                let simulation = RPCAggregationCommitData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &stacks_block_id,
+                    &delegate,
+                    &pox_addr,
+                    1,
+                )
+                .unwrap();
 
-                                        encoder.cork();
+                // nothing to commit, so the call returns an error
+                assert!(simulation.okay);
+                assert_eq!(simulation.partial_stacked_ustx, 0);
+                let result =
+                    Value::try_deserialize_hex_untyped(simulation.result.as_ref().unwrap())
+                        .unwrap();
+                assert!(result.expect_result().is_err());
 
-                                        test_debug!("stream indicates EOF");
-                                    }
+                *simulation_server_info.borrow_mut() = Some(simulation);
+                convo_client.new_post_aggregation_commit(
+                    delegate.clone(),
+                    pox_addr.clone(),
+                    1,
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::AggregationCommitSimulation(response_md, simulation) => {
+                        assert_eq!(
+                            Some((*simulation).clone()),
+                            *simulation_server_info.borrow()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-                                    // try moving some data to the connection only once we're done
-                                    // streaming
-                                    match reply.try_flush() {
-                                        Ok(res) => {
-                                            test_debug!("Streamed reply is drained");
-                                            drained_handle = res;
-                                        }
-                                        Err(e) => {
-                                            // dead
-                                            warn!("Broken HTTP connection: {:?}", &e);
-                                            broken = true;
-                                        }
-                                    }
-                                    drained_stream = true;
-                                }
-                            }
-                            Err(e) => {
-                                // broken -- terminate the stream.
-                                // For example, if we're streaming an unconfirmed block or
-                                // microblock, the data can get moved to the chunk store out from
-                                // under the stream.
-                                warn!("Failed to send to HTTP connection: {:?}", &e);
-                                broken = true;
-                            }
+    #[test]
+    #[ignore]
+    fn test_rpc_get_block_event_bloom() {
+        let server_bloom = RefCell::new(None);
+        test_rpc(
+            "test_rpc_get_block_event_bloom",
+            40222,
+            40223,
+            50222,
+            50223,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(
+                        peer_server.sortdb.as_ref().unwrap().conn(),
+                    )
+                    .unwrap();
+                let tip = StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
+                let bloom =
+                    StacksChainState::get_block_event_bloom(peer_server.chainstate().db(), &tip)
+                        .unwrap()
+                        .unwrap();
+                *server_bloom.borrow_mut() = Some(bloom);
+                convo_client.new_get_block_event_bloom(tip)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::BlockEventBloom(response_md, data) => {
+                        let bloom = EventBloom::from_hex(&data.bloom[2..]).unwrap();
+                        assert_eq!(Some(bloom), *server_bloom.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_getheaders() {
+        let server_headers = RefCell::new(None);
+        test_rpc(
+            "test_rpc_getheaders",
+            40234,
+            40235,
+            50234,
+            50235,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(
+                        peer_server.sortdb.as_ref().unwrap().conn(),
+                    )
+                    .unwrap();
+                let tip = StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
+                let headers = StacksChainState::get_extended_header_chain(
+                    peer_server.chainstate().db(),
+                    &tip,
+                    100,
+                )
+                .unwrap();
+                *server_headers.borrow_mut() = Some(headers);
+                convo_client.new_getheaders(100, None)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Headers(response_md, headers) => {
+                        assert!(headers.len() > 0);
+                        assert_eq!(Some((*headers).clone()), *server_headers.borrow());
+                        for i in 1..headers.len() {
+                            assert_eq!(
+                                headers[i - 1].parent_block_id,
+                                headers[i].index_block_hash()
+                            );
                         }
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
                     }
-                    None => {
-                        // not streamed; all data is buffered
-                        drained_stream = true;
+                }
+            },
+        );
+    }
 
-                        // try moving some data to the connection
-                        match reply.try_flush() {
-                            Ok(res) => {
-                                test_debug!("Reply is drained");
-                                drained_handle = res;
-                            }
-                            Err(e) => {
-                                // dead
-                                warn!("Broken HTTP connection: {:?}", &e);
-                                broken = true;
-                            }
-                        }
+    #[test]
+    #[ignore]
+    fn test_rpc_getburnblockinfo() {
+        let burn_block_server_info = RefCell::new(None);
+        test_rpc(
+            "test_rpc_getburnblockinfo",
+            40190,
+            40191,
+            50190,
+            50191,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+                let burn_block_info = RPCBurnBlockInfoData::from_db(sortdb, tip.block_height)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(
+                    burn_block_info.burn_block_hash,
+                    tip.burn_header_hash.to_hex()
+                );
+                assert!(burn_block_info.median_time_past <= burn_block_info.burn_block_time);
+                *burn_block_server_info.borrow_mut() = Some(burn_block_info);
+                convo_client.new_getburnblockinfo(tip.block_height)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::BurnBlockInfo(response_md, burn_block_data) => {
+                        assert_eq!(
+                            Some((*burn_block_data).clone()),
+                            *burn_block_server_info.borrow()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
                     }
                 }
-            }
-            None => {}
-        }
+            },
+        );
+    }
 
-        if broken || (drained_handle && drained_stream) {
-            // done with this stream
-            test_debug!(
-                "{:?}: done with stream (broken={}, drained_handle={}, drained_stream={})",
-                &self,
-                broken,
-                drained_handle,
-                drained_stream
-            );
-            self.total_reply_count += 1;
-            self.reply_streams.pop_front();
+    #[test]
+    #[ignore]
+    fn test_rpc_getburnchainops() {
+        let server_ops = RefCell::new(None);
+        test_rpc(
+            "test_rpc_getburnchainops",
+            40290,
+            40291,
+            50290,
+            50291,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+                let burnchain_ops = RPCBurnchainOpsData::from_db(sortdb, tip.block_height)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(burnchain_ops.burn_block_hash, tip.burn_header_hash.to_hex());
+                assert!(tip.sortition);
+
+                // the winning commit is there, and can be looked up on its own
+                let winning_txid = tip.winning_block_txid.to_hex();
+                let winner = burnchain_ops
+                    .ops
+                    .iter()
+                    .find(|op| op.txid() == winning_txid)
+                    .unwrap()
+                    .clone();
+                match winner {
+                    RPCBurnchainOpData::LeaderBlockCommit { won_sortition, .. } => {
+                        assert!(won_sortition)
+                    }
+                    _ => panic!("winning op is not a block commit: {:?}", &winner),
+                }
 
-            if !do_keep_alive {
-                // encountered "Connection: close"
-                self.keep_alive = false;
-            }
-        }
+                let by_txid = RPCBurnchainOpsData::from_db_by_txid(sortdb, &tip.winning_block_txid)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(by_txid.burn_block_height, tip.block_height);
+                assert_eq!(by_txid.ops, vec![winner]);
+                assert!(
+                    RPCBurnchainOpsData::from_db_by_txid(sortdb, &Txid([0xfe; 32]))
+                        .unwrap()
+                        .is_none()
+                );
+                assert!(RPCBurnchainOpsData::from_db(sortdb, tip.block_height + 1)
+                    .unwrap()
+                    .is_none());
 
-        Ok(())
+                *server_ops.borrow_mut() = Some(by_txid);
+                convo_client.new_getburnchainops(BurnchainOpsQuery::Txid(tip.winning_block_txid))
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::BurnchainOps(response_md, burnchain_ops) => {
+                        assert_eq!(Some((*burnchain_ops).clone()), *server_ops.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    pub fn try_send_recv_response(
-        req: ReplyHandleHttp,
-    ) -> Result<HttpResponseType, Result<ReplyHandleHttp, net_error>> {
-        match req.try_send_recv() {
-            Ok(message) => match message {
-                StacksHttpMessage::Request(_) => {
-                    warn!("Received response: not a HTTP response");
-                    return Err(Err(net_error::InvalidMessage));
+    #[test]
+    #[ignore]
+    fn test_rpc_getneighbors() {
+        test_rpc(
+            "test_rpc_getneighbors",
+            40010,
+            40011,
+            50010,
+            50011,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| { convo_client.new_getneighbors() },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Neighbors(response_md, neighbor_info) => {
+                        assert_eq!(neighbor_info.sample.len(), 1);
+                        assert_eq!(neighbor_info.sample[0].port, peer_client.config.server_port); // we see ourselves as the neighbor
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
                 }
-                StacksHttpMessage::Response(http_response) => Ok(http_response),
             },
-            Err(res) => Err(res),
-        }
+        );
     }
 
-    /// Make progress on our request/response
-    fn recv_inbound_response(&mut self) -> Result<(), net_error> {
-        // make progress on our pending request (if it exists).
-        let inprogress = self.pending_request.is_some();
-        let is_pending = self.pending_response.is_none();
+    #[test]
+    #[ignore]
+    fn test_rpc_unconfirmed_getblock() {
+        let server_block_cell = RefCell::new(None);
 
-        let pending_request = self.pending_request.take();
-        let response = match pending_request {
-            None => Ok(self.pending_response.take()),
-            Some(req) => match ConversationHttp::try_send_recv_response(req) {
-                Ok(response) => Ok(Some(response)),
-                Err(res) => match res {
-                    Ok(handle) => {
-                        // try again
-                        self.pending_request = Some(handle);
-                        Ok(self.pending_response.take())
+        test_rpc(
+            "test_rpc_unconfirmed_getblock",
+            40020,
+            40021,
+            50020,
+            50021,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // have "server" peer store a block to staging
+                let peer_server_block = make_codec_test_block(25);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block.block_hash(),
+                );
+
+                test_debug!("Store peer server index block {:?}", &index_block_hash);
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &peer_server_consensus_hash,
+                    &peer_server_block,
+                    &ConsensusHash([0x03; 20]),
+                    456,
+                    123,
+                );
+
+                *server_block_cell.borrow_mut() = Some(peer_server_block);
+
+                // now ask for it
+                convo_client.new_getblock(index_block_hash)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Block(response_md, block_info) => {
+                        assert_eq!(
+                            block_info.block_hash(),
+                            (*server_block_cell.borrow()).as_ref().unwrap().block_hash()
+                        );
+                        true
                     }
-                    Err(e) => Err(e),
-                },
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
             },
-        }?;
+        );
+    }
 
-        self.pending_response = response;
+    #[test]
+    #[ignore]
+    fn test_rpc_confirmed_getblock() {
+        let server_block_cell = RefCell::new(None);
 
-        if inprogress && self.pending_request.is_none() {
-            test_debug!(
-                "{:?},id={}: HTTP request finished",
-                &self.peer_host,
-                self.conn_id
-            );
-        }
+        test_rpc(
+            "test_rpc_confirmed_getblock",
+            40030,
+            40031,
+            50030,
+            50031,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // have "server" peer store a block to staging
+                let peer_server_block = make_codec_test_block(25);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block.block_hash(),
+                );
 
-        if is_pending && self.pending_response.is_some() {
-            test_debug!(
-                "{:?},id={}: HTTP response finished",
-                &self.peer_host,
-                self.conn_id
-            );
-        }
+                test_debug!("Store peer server index block {:?}", &index_block_hash);
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &peer_server_consensus_hash,
+                    &peer_server_block,
+                    &ConsensusHash([0x03; 20]),
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &peer_server_consensus_hash,
+                    &peer_server_block.block_hash(),
+                    true,
+                );
 
-        Ok(())
-    }
+                *server_block_cell.borrow_mut() = Some(peer_server_block);
 
-    /// Try to get our response
-    pub fn try_get_response(&mut self) -> Option<HttpResponseType> {
-        self.pending_response.take()
+                // now ask for it
+                convo_client.new_getblock(index_block_hash)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Block(response_md, block_info) => {
+                        assert_eq!(
+                            block_info.block_hash(),
+                            (*server_block_cell.borrow()).as_ref().unwrap().block_hash()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// Make progress on in-flight messages.
-    pub fn try_flush(&mut self, chainstate: &mut StacksChainState) -> Result<(), net_error> {
-        self.send_outbound_responses(chainstate)?;
-        self.recv_inbound_response()?;
-        Ok(())
-    }
+    #[test]
+    #[ignore]
+    fn test_rpc_get_indexed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-    /// Is the connection idle?
-    pub fn is_idle(&self) -> bool {
-        self.pending_response.is_none()
-            && self.connection.inbox_len() == 0
-            && self.connection.outbox_len() == 0
-            && self.reply_streams.len() == 0
-    }
+        test_rpc(
+            "test_rpc_indexed_microblocks",
+            40040,
+            40041,
+            50040,
+            50041,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-    /// Is the conversation out of pending data?
-    /// Don't consider it drained if we haven't received anything yet
-    pub fn is_drained(&self) -> bool {
-        ((self.total_request_count > 0 && self.total_reply_count > 0)
-            || self.pending_error_response.is_some())
-            && self.is_idle()
-    }
+                let parent_block = make_codec_test_block(25);
+                let parent_consensus_hash = ConsensusHash([0x02; 20]);
+                let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &parent_consensus_hash,
+                    &parent_block.block_hash(),
+                );
 
-    /// Should the connection be kept alive even if drained?
-    pub fn is_keep_alive(&self) -> bool {
-        self.keep_alive
-    }
+                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
+                mblocks.truncate(15);
 
-    /// When was the last time we got an inbound request?
-    pub fn get_last_request_time(&self) -> u64 {
-        self.last_request_timestamp
-    }
+                let mut child_block = make_codec_test_block(25);
+                let child_consensus_hash = ConsensusHash([0x03; 20]);
 
-    /// When was the last time we sent data as part of an outbound response?
-    pub fn get_last_response_time(&self) -> u64 {
-        self.last_response_timestamp
-    }
+                child_block.header.parent_block = parent_block.block_hash();
+                child_block.header.parent_microblock =
+                    mblocks.last().as_ref().unwrap().block_hash();
+                child_block.header.parent_microblock_sequence =
+                    mblocks.last().as_ref().unwrap().header.sequence;
 
-    /// When was this converation conencted?
-    pub fn get_connection_time(&self) -> u64 {
-        self.connection_time
-    }
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block,
+                    &ConsensusHash([0x01; 20]),
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block.block_hash(),
+                    true,
+                );
 
-    /// Make progress on in-flight requests and replies.
-    /// Returns the list of transactions we'll need to forward to the peer network
-    pub fn chat(
-        &mut self,
-        chain_view: &BurnchainView,
-        peers: &PeerMap,
-        sortdb: &SortitionDB,
-        peerdb: &PeerDB,
-        atlasdb: &mut AtlasDB,
-        chainstate: &mut StacksChainState,
-        mempool: &mut MemPoolDB,
-        handler_args: &RPCHandlerArgs,
-    ) -> Result<Vec<StacksMessageType>, net_error> {
-        // if we have an in-flight error, then don't take any more requests.
-        if self.pending_error_response.is_some() {
-            return Ok(vec![]);
-        }
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block,
+                    &parent_consensus_hash,
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                    true,
+                );
 
-        // handle in-bound HTTP request(s)
-        let num_inbound = self.connection.inbox_len();
-        let mut ret = vec![];
-        test_debug!("{:?}: {} HTTP requests pending", &self, num_inbound);
+                let index_microblock_hash = StacksBlockHeader::make_index_block_hash(
+                    &parent_consensus_hash,
+                    &mblocks.last().as_ref().unwrap().block_hash(),
+                );
 
-        for _i in 0..num_inbound {
-            let msg = match self.connection.next_inbox_message() {
-                None => {
-                    continue;
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &parent_consensus_hash,
+                        &parent_block.block_hash(),
+                        &mblock,
+                    );
                 }
-                Some(m) => m,
-            };
 
-            match msg {
-                StacksHttpMessage::Request(req) => {
-                    // new request
-                    self.total_request_count += 1;
-                    self.last_request_timestamp = get_epoch_time_secs();
-                    let msg_opt = self.handle_request(
-                        req,
-                        chain_view,
-                        peers,
-                        sortdb,
-                        peerdb,
-                        atlasdb,
-                        chainstate,
-                        mempool,
-                        handler_args,
-                    )?;
-                    if let Some(msg) = msg_opt {
-                        ret.push(msg);
+                set_microblocks_processed(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                    &mblocks.last().as_ref().unwrap().block_hash(),
+                );
+
+                *server_microblocks_cell.borrow_mut() = mblocks;
+
+                convo_client.new_getmicroblocks_indexed(index_microblock_hash)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match (*http_response).clone() {
+                    HttpResponseType::Microblocks(_, mut microblocks) => {
+                        microblocks.reverse();
+                        assert_eq!(microblocks.len(), (*server_microblocks_cell.borrow()).len());
+                        assert_eq!(microblocks, *server_microblocks_cell.borrow());
+                        true
                     }
-                }
-                StacksHttpMessage::Response(resp) => {
-                    // Is there someone else waiting for this message?  If so, pass it along.
-                    // (this _should_ be our pending_request handle)
-                    match self
-                        .connection
-                        .fulfill_request(StacksHttpMessage::Response(resp))
-                    {
-                        None => {
-                            test_debug!("{:?}: Fulfilled pending HTTP request", &self);
-                        }
-                        Some(_msg) => {
-                            // unsolicited; discard
-                            test_debug!("{:?}: Dropping unsolicited HTTP response", &self);
-                        }
+                    _ => {
+                        error!("Invalid response: {:?}", http_response);
+                        false
                     }
                 }
-            }
-        }
-
-        Ok(ret)
+            },
+        );
     }
 
-    /// Remove all timed-out messages, and ding the remote peer as unhealthy
-    pub fn clear_timeouts(&mut self) -> () {
-        self.connection.drain_timeouts();
-    }
+    #[test]
+    #[ignore]
+    fn test_rpc_get_confirmed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-    /// Load data into our HTTP connection
-    pub fn recv<R: Read>(&mut self, r: &mut R) -> Result<usize, net_error> {
-        let mut total_recv = 0;
-        loop {
-            let nrecv = match self.connection.recv_data(r) {
-                Ok(nr) => nr,
-                Err(e) => {
-                    debug!("{:?}: failed to recv: {:?}", self, &e);
-                    return Err(e);
-                }
-            };
+        test_rpc(
+            "test_rpc_confirmed_microblocks",
+            40042,
+            40043,
+            50042,
+            50043,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
+
+                let parent_block = make_codec_test_block(25);
+                let parent_consensus_hash = ConsensusHash([0x02; 20]);
 
-            total_recv += nrecv;
-            if nrecv > 0 {
-                self.last_request_timestamp = get_epoch_time_secs();
-            } else {
-                break;
-            }
-        }
-        Ok(total_recv)
-    }
+                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
+                mblocks.truncate(15);
 
-    /// Write data out of our HTTP connection.  Write as much as we can
-    pub fn send<W: Write>(
-        &mut self,
-        w: &mut W,
-        chainstate: &mut StacksChainState,
-    ) -> Result<usize, net_error> {
-        let mut total_sz = 0;
-        loop {
-            // prime the Write
-            self.try_flush(chainstate)?;
+                let mut child_block = make_codec_test_block(25);
+                let child_consensus_hash = ConsensusHash([0x03; 20]);
 
-            let sz = match self.connection.send_data(w) {
-                Ok(sz) => sz,
-                Err(e) => {
-                    info!("{:?}: failed to send on HTTP conversation: {:?}", self, &e);
-                    return Err(e);
-                }
-            };
+                child_block.header.parent_block = parent_block.block_hash();
+                child_block.header.parent_microblock =
+                    mblocks.last().as_ref().unwrap().block_hash();
+                child_block.header.parent_microblock_sequence =
+                    mblocks.last().as_ref().unwrap().header.sequence;
 
-            total_sz += sz;
-            if sz > 0 {
-                self.last_response_timestamp = get_epoch_time_secs();
-            } else {
-                break;
-            }
-        }
-        Ok(total_sz)
-    }
+                let child_index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                );
 
-    /// Make a new getinfo request to this endpoint
-    pub fn new_getinfo(&self) -> HttpRequestType {
-        HttpRequestType::GetInfo(HttpRequestMetadata::from_host(self.peer_host.clone()))
-    }
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block,
+                    &ConsensusHash([0x01; 20]),
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &parent_consensus_hash,
+                    &parent_block.block_hash(),
+                    true,
+                );
 
-    /// Make a new getinfo request to this endpoint
-    pub fn new_getpoxinfo(&self, tip_opt: Option<StacksBlockId>) -> HttpRequestType {
-        HttpRequestType::GetPoxInfo(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            tip_opt,
-        )
-    }
+                store_staging_block(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block,
+                    &parent_consensus_hash,
+                    456,
+                    123,
+                );
+                set_block_processed(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                    true,
+                );
 
-    /// Make a new getneighbors request to this endpoint
-    pub fn new_getneighbors(&self) -> HttpRequestType {
-        HttpRequestType::GetNeighbors(HttpRequestMetadata::from_host(self.peer_host.clone()))
-    }
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &parent_consensus_hash,
+                        &parent_block.block_hash(),
+                        &mblock,
+                    );
+                }
 
-    /// Make a new getblock request to this endpoint
-    pub fn new_getblock(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
-        HttpRequestType::GetBlock(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            index_block_hash,
-        )
-    }
+                set_microblocks_processed(
+                    peer_server.chainstate(),
+                    &child_consensus_hash,
+                    &child_block.block_hash(),
+                    &mblocks.last().as_ref().unwrap().block_hash(),
+                );
 
-    /// Make a new get-microblocks request to this endpoint
-    pub fn new_getmicroblocks_indexed(
-        &self,
-        index_microblock_hash: StacksBlockId,
-    ) -> HttpRequestType {
-        HttpRequestType::GetMicroblocksIndexed(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            index_microblock_hash,
-        )
-    }
+                *server_microblocks_cell.borrow_mut() = mblocks;
 
-    /// Make a new get-microblocks-confirmed request to this endpoint
-    pub fn new_getmicroblocks_confirmed(
-        &self,
-        index_anchor_block_hash: StacksBlockId,
-    ) -> HttpRequestType {
-        HttpRequestType::GetMicroblocksConfirmed(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            index_anchor_block_hash,
-        )
+                convo_client.new_getmicroblocks_confirmed(child_index_block_hash)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match (*http_response).clone() {
+                    HttpResponseType::Microblocks(_, mut microblocks) => {
+                        microblocks.reverse();
+                        assert_eq!(microblocks.len(), (*server_microblocks_cell.borrow()).len());
+                        assert_eq!(microblocks, *server_microblocks_cell.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// Make a new get-microblocks request for unconfirmed microblocks
-    pub fn new_getmicroblocks_unconfirmed(
-        &self,
-        anchored_index_block_hash: StacksBlockId,
-        min_seq: u16,
-    ) -> HttpRequestType {
-        HttpRequestType::GetMicroblocksUnconfirmed(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            anchored_index_block_hash,
-            min_seq,
-        )
-    }
+    #[test]
+    #[ignore]
+    fn test_rpc_unconfirmed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-    /// Make a new get-unconfirmed-tx request
-    pub fn new_gettransaction_unconfirmed(&self, txid: Txid) -> HttpRequestType {
-        HttpRequestType::GetTransactionUnconfirmed(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            txid,
-        )
-    }
+        test_rpc(
+            "test_rpc_unconfirmed_microblocks",
+            40050,
+            40051,
+            50050,
+            50051,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-    /// Make a new post-transaction request
-    pub fn new_post_transaction(&self, tx: StacksTransaction) -> HttpRequestType {
-        HttpRequestType::PostTransaction(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            tx,
-            None,
-        )
-    }
+                let consensus_hash = ConsensusHash([0x02; 20]);
+                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
+                let index_block_hash =
+                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
 
-    /// Make a new post-microblock request
-    pub fn new_post_microblock(
-        &self,
-        mblock: StacksMicroblock,
-        tip_opt: Option<StacksBlockId>,
-    ) -> HttpRequestType {
-        HttpRequestType::PostMicroblock(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            mblock,
-            tip_opt,
-        )
-    }
+                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+                mblocks.truncate(15);
 
-    /// Make a new request for an account
-    pub fn new_getaccount(
-        &self,
-        principal: PrincipalData,
-        tip_opt: Option<StacksBlockId>,
-        with_proof: bool,
-    ) -> HttpRequestType {
-        HttpRequestType::GetAccount(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            principal,
-            tip_opt,
-            with_proof,
-        )
-    }
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &anchored_block_hash,
+                        &mblock,
+                    );
+                }
 
-    /// Make a new request for a data map
-    pub fn new_getmapentry(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        map_name: ClarityName,
-        key: Value,
-        tip_opt: Option<StacksBlockId>,
-        with_proof: bool,
-    ) -> HttpRequestType {
-        HttpRequestType::GetMapEntry(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            contract_addr,
-            contract_name,
-            map_name,
-            key,
-            tip_opt,
-            with_proof,
-        )
-    }
+                *server_microblocks_cell.borrow_mut() = mblocks;
 
-    /// Make a new request to get a contract's source
-    pub fn new_getcontractsrc(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        tip_opt: Option<StacksBlockId>,
-        with_proof: bool,
-    ) -> HttpRequestType {
-        HttpRequestType::GetContractSrc(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            contract_addr,
-            contract_name,
-            tip_opt,
-            with_proof,
-        )
+                // start at seq 5
+                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 5)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Microblocks(response_md, microblocks) => {
+                        assert_eq!(microblocks.len(), 10);
+                        assert_eq!(
+                            *microblocks,
+                            (*server_microblocks_cell.borrow())[5..].to_vec()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    /// Make a new request to get a contract's ABI
-    pub fn new_getcontractabi(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        tip_opt: Option<StacksBlockId>,
-    ) -> HttpRequestType {
-        HttpRequestType::GetContractABI(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            contract_addr,
-            contract_name,
-            tip_opt,
-        )
-    }
+    #[test]
+    #[ignore]
+    fn test_rpc_get_unconfirmed_microblock_stream() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-    /// Make a new request to run a read-only function
-    pub fn new_callreadonlyfunction(
-        &self,
-        contract_addr: StacksAddress,
-        contract_name: ContractName,
-        sender: PrincipalData,
-        function_name: ClarityName,
-        function_args: Vec<Value>,
-        tip_opt: Option<StacksBlockId>,
-    ) -> HttpRequestType {
-        HttpRequestType::CallReadOnlyFunction(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            contract_addr,
-            contract_name,
-            sender,
-            function_name,
-            function_args,
-            tip_opt,
-        )
-    }
+        test_rpc(
+            "test_rpc_get_unconfirmed_microblock_stream",
+            40292,
+            40293,
+            50292,
+            50293,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-    /// Make a new request for attachment inventory page
-    pub fn new_getattachmentsinv(
-        &self,
-        tip_opt: Option<StacksBlockId>,
-        pages_indexes: HashSet<u32>,
-    ) -> HttpRequestType {
-        HttpRequestType::GetAttachmentsInv(
-            HttpRequestMetadata::from_host(self.peer_host.clone()),
-            tip_opt,
-            pages_indexes,
-        )
-    }
-}
+                let consensus_hash = ConsensusHash([0x02; 20]);
+                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
+                let index_block_hash =
+                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use net::codec::*;
-    use net::http::*;
-    use net::test::*;
-    use net::*;
-    use std::cell::RefCell;
-    use std::iter::FromIterator;
+                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+                mblocks.truncate(15);
 
-    use burnchains::Burnchain;
-    use burnchains::BurnchainHeaderHash;
-    use burnchains::BurnchainView;
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &anchored_block_hash,
+                        &mblock,
+                    );
+                }
 
-    use burnchains::*;
-    use chainstate::burn::BlockHeaderHash;
-    use chainstate::burn::ConsensusHash;
-    use chainstate::stacks::db::blocks::test::*;
-    use chainstate::stacks::db::BlockStreamData;
-    use chainstate::stacks::db::StacksChainState;
-    use chainstate::stacks::miner::*;
-    use chainstate::stacks::test::*;
-    use chainstate::stacks::Error as chain_error;
-    use chainstate::stacks::*;
+                *server_microblocks_cell.borrow_mut() = mblocks;
 
-    use address::*;
+                // start at seq 5
+                convo_client.new_getunconfirmedmicroblockstream(5, Some(index_block_hash))
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::UnconfirmedMicroblockStream(response_md, data) => {
+                        let mblocks = &(*server_microblocks_cell.borrow())[5..];
+                        assert_eq!(data.microblocks.len(), 10);
+                        for (entry, mblock) in data.microblocks.iter().zip(mblocks.iter()) {
+                            assert_eq!(entry.microblock_hash, format!("0x{}", mblock.block_hash()));
+                            assert_eq!(entry.sequence, mblock.header.sequence);
+                            assert_eq!(
+                                entry.parent_microblock_hash,
+                                format!("0x{}", mblock.header.prev_block)
+                            );
+                            assert_eq!(entry.transactions.len(), mblock.txs.len());
+                        }
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-    use util::get_epoch_time_secs;
-    use util::hash::hex_bytes;
-    use util::pipe::*;
+    #[test]
+    #[ignore]
+    fn test_rpc_unconfirmed_transaction() {
+        let last_txid = RefCell::new(Txid([0u8; 32]));
+        let last_mblock = RefCell::new(BlockHeaderHash([0u8; 32]));
 
-    use std::convert::TryInto;
+        test_rpc(
+            "test_rpc_unconfirmed_transaction",
+            40052,
+            40053,
+            50052,
+            50053,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-    use vm::types::*;
+                let sortdb = peer_server.sortdb.take().unwrap();
+                PeerNetwork::setup_unconfirmed_state(peer_server.chainstate(), &sortdb).unwrap();
+                peer_server.sortdb = Some(sortdb);
 
-    const TEST_CONTRACT: &'static str = "
-        (define-data-var bar int 0)
-        (define-map unit-map { account: principal } { units: int })
-        (define-public (get-bar) (ok (var-get bar)))
-        (define-public (set-bar (x int) (y int))
-          (begin (var-set bar (/ x y)) (ok (var-get bar))))
-        (define-public (add-unit)
-          (begin 
-            (map-set unit-map { account: tx-sender } { units: 1 } )
-            (ok 1)))
-        (begin
-          (map-set unit-map { account: 'ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R } { units: 123 }))";
+                assert!(peer_server.chainstate().unconfirmed_state.is_some());
+                let (txid, mblock_hash) = match peer_server.chainstate().unconfirmed_state {
+                    Some(ref unconfirmed) => {
+                        assert!(unconfirmed.mined_txs.len() > 0);
+                        let mut txid = Txid([0u8; 32]);
+                        let mut mblock_hash = BlockHeaderHash([0u8; 32]);
+                        for (next_txid, (_, mbh, ..)) in unconfirmed.mined_txs.iter() {
+                            txid = next_txid.clone();
+                            mblock_hash = mbh.clone();
+                            break;
+                        }
+                        (txid, mblock_hash)
+                    }
+                    None => {
+                        panic!("No unconfirmed state");
+                    }
+                };
 
-    fn convo_send_recv(
-        sender: &mut ConversationHttp,
-        sender_chainstate: &mut StacksChainState,
-        receiver: &mut ConversationHttp,
-        receiver_chainstate: &mut StacksChainState,
-    ) -> () {
-        let (mut pipe_read, mut pipe_write) = Pipe::new();
-        pipe_read.set_nonblocking(true);
+                *last_txid.borrow_mut() = txid.clone();
+                *last_mblock.borrow_mut() = mblock_hash.clone();
 
-        loop {
-            let res = true;
+                convo_client.new_gettransaction_unconfirmed(txid)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::UnconfirmedTransaction(response_md, unconfirmed_resp) => {
+                        assert_eq!(
+                            unconfirmed_resp.status,
+                            UnconfirmedTransactionStatus::Microblock {
+                                block_hash: (*last_mblock.borrow()).clone(),
+                                seq: 0
+                            }
+                        );
+                        let tx = StacksTransaction::consensus_deserialize(
+                            &mut &hex_bytes(&unconfirmed_resp.tx).unwrap()[..],
+                        )
+                        .unwrap();
+                        assert_eq!(tx.txid(), *last_txid.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_getblock() {
+        test_rpc(
+            "test_rpc_missing_getblock",
+            40060,
+            40061,
+            50060,
+            50061,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block_hash,
+                );
 
-            sender.try_flush(sender_chainstate).unwrap();
-            receiver.try_flush(receiver_chainstate).unwrap();
+                // now ask for it
+                convo_client.new_getblock(index_block_hash)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-            pipe_write.try_flush().unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_index_getmicroblocks() {
+        test_rpc(
+            "test_rpc_missing_index_getmicroblocks",
+            40070,
+            40071,
+            50070,
+            50071,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block_hash,
+                );
 
-            let all_relays_flushed =
-                receiver.num_pending_outbound() == 0 && sender.num_pending_outbound() == 0;
+                // now ask for it
+                convo_client.new_getmicroblocks_indexed(index_block_hash)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-            let nw = sender.send(&mut pipe_write, sender_chainstate).unwrap();
-            let nr = receiver.recv(&mut pipe_read).unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_confirmed_getmicroblocks() {
+        test_rpc(
+            "test_rpc_missing_confirmed_getmicroblocks",
+            40070,
+            40071,
+            50070,
+            50071,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
+                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &peer_server_consensus_hash,
+                    &peer_server_block_hash,
+                );
 
-            test_debug!(
-                "res = {}, all_relays_flushed = {} ({},{}), nr = {}, nw = {}",
-                res,
-                all_relays_flushed,
-                receiver.num_pending_outbound(),
-                sender.num_pending_outbound(),
-                nr,
-                nw
-            );
-            if res && all_relays_flushed && nr == 0 && nw == 0 {
-                test_debug!("Breaking send_recv");
-                break;
-            }
-        }
+                // now ask for it
+                convo_client.new_getmicroblocks_confirmed(index_block_hash)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
     }
 
-    fn test_rpc<F, C>(
-        test_name: &str,
-        peer_1_p2p: u16,
-        peer_1_http: u16,
-        peer_2_p2p: u16,
-        peer_2_http: u16,
-        make_request: F,
-        check_result: C,
-    ) -> ()
-    where
-        F: FnOnce(
-            &mut TestPeer,
-            &mut ConversationHttp,
-            &mut TestPeer,
-            &mut ConversationHttp,
-        ) -> HttpRequestType,
-        C: FnOnce(&HttpRequestType, &HttpResponseType, &mut TestPeer, &mut TestPeer) -> bool,
-    {
-        let mut peer_1_config = TestPeerConfig::new(test_name, peer_1_p2p, peer_1_http);
-        let mut peer_2_config = TestPeerConfig::new(test_name, peer_2_p2p, peer_2_http);
-
-        // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R
-        let privk1 = StacksPrivateKey::from_hex(
-            "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
-        )
-        .unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_missing_unconfirmed_microblocks() {
+        let server_microblocks_cell = RefCell::new(vec![]);
 
-        // STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW
-        let privk2 = StacksPrivateKey::from_hex(
-            "94c319327cc5cd04da7147d32d836eb2e4c44f4db39aa5ede7314a761183d0c701",
-        )
-        .unwrap();
-        let microblock_privkey = StacksPrivateKey::new();
-        let microblock_pubkeyhash =
-            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privkey));
+        test_rpc(
+            "test_rpc_missing_unconfirmed_microblocks",
+            40080,
+            40081,
+            50080,
+            50081,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
 
-        let addr1 = StacksAddress::from_public_keys(
-            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
-            &AddressHashMode::SerializeP2PKH,
-            1,
-            &vec![StacksPublicKey::from_private(&privk1)],
-        )
-        .unwrap();
-        let addr2 = StacksAddress::from_public_keys(
-            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
-            &AddressHashMode::SerializeP2PKH,
-            1,
-            &vec![StacksPublicKey::from_private(&privk2)],
-        )
-        .unwrap();
+                let consensus_hash = ConsensusHash([0x02; 20]);
+                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
+                let index_block_hash =
+                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
 
-        peer_1_config.initial_balances = vec![
-            (addr1.to_account_principal(), 1000000000),
-            (addr2.to_account_principal(), 1000000000),
-        ];
+                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+                mblocks.truncate(15);
 
-        peer_2_config.initial_balances = vec![
-            (addr1.to_account_principal(), 1000000000),
-            (addr2.to_account_principal(), 1000000000),
-        ];
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &anchored_block_hash,
+                        &mblock,
+                    );
+                }
 
-        peer_1_config.add_neighbor(&peer_2_config.to_neighbor());
-        peer_2_config.add_neighbor(&peer_1_config.to_neighbor());
+                *server_microblocks_cell.borrow_mut() = mblocks;
 
-        let mut peer_1 = TestPeer::new(peer_1_config);
-        let mut peer_2 = TestPeer::new(peer_2_config);
+                // start at seq 16 (which doesn't exist)
+                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 16)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        // mine one block with a contract in it
-        // first the coinbase
-        // make a coinbase for this miner
-        let mut tx_coinbase = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            TransactionAuth::from_p2pkh(&privk1).unwrap(),
-            TransactionPayload::Coinbase(CoinbasePayload([0x00; 32])),
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_src() {
+        test_rpc(
+            "test_rpc_get_contract_src",
+            40090,
+            40091,
+            50090,
+            50091,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getcontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    None,
+                    None,
+                    false,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetContractSrc(response_md, data) => {
+                        assert_eq!(data.source, TEST_CONTRACT);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
-        tx_coinbase.chain_id = 0x80000000;
-        tx_coinbase.anchor_mode = TransactionAnchorMode::OnChainOnly;
-        tx_coinbase.auth.set_origin_nonce(0);
-
-        let mut tx_signer = StacksTransactionSigner::new(&tx_coinbase);
-        tx_signer.sign_origin(&privk1).unwrap();
-        let tx_coinbase_signed = tx_signer.get_tx().unwrap();
+    }
 
-        // next the contract
-        let contract = TEST_CONTRACT.clone();
-        let mut tx_contract = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            TransactionAuth::from_p2pkh(&privk1).unwrap(),
-            TransactionPayload::new_smart_contract(&format!("hello-world"), &contract.to_string())
-                .unwrap(),
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_src_unconfirmed() {
+        test_rpc(
+            "test_rpc_get_contract_src_unconfirmed",
+            40100,
+            40101,
+            50100,
+            50101,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                convo_client.new_getcontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    Some(unconfirmed_tip),
+                    None,
+                    false,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetContractSrc(response_md, data) => {
+                        assert_eq!(data.source, TEST_CONTRACT);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        tx_contract.chain_id = 0x80000000;
-        tx_contract.auth.set_origin_nonce(1);
-        tx_contract.set_fee_rate(0);
-
-        let mut tx_signer = StacksTransactionSigner::new(&tx_contract);
-        tx_signer.sign_origin(&privk1).unwrap();
-        let tx_contract_signed = tx_signer.get_tx().unwrap();
-
-        // update account and state in a microblock that will be unconfirmed
-        let mut tx_cc = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            TransactionAuth::from_p2pkh(&privk1).unwrap(),
-            TransactionPayload::new_contract_call(addr1.clone(), "hello-world", "add-unit", vec![])
-                .unwrap(),
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account() {
+        test_rpc(
+            "test_rpc_get_account",
+            40110,
+            40111,
+            50110,
+            50111,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    None,
+                    false,
+                    RPCStateView::Confirmed,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 2);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000);
+                        let locked = u128::from_str_radix(&data.locked[2..], 16).unwrap();
+                        assert_eq!(locked, 0);
+                        assert_eq!(data.unlock_height, 0);
+                        let total_balance =
+                            u128::from_str_radix(&data.total_balance[2..], 16).unwrap();
+                        assert_eq!(total_balance, balance + locked);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        tx_cc.chain_id = 0x80000000;
-        tx_cc.auth.set_origin_nonce(2);
-        tx_cc.set_fee_rate(123);
-
-        let mut tx_signer = StacksTransactionSigner::new(&tx_cc);
-        tx_signer.sign_origin(&privk1).unwrap();
-        let tx_cc_signed = tx_signer.get_tx().unwrap();
-        let tx_cc_len = {
-            let mut bytes = vec![];
-            tx_cc_signed.consensus_serialize(&mut bytes).unwrap();
-            bytes.len() as u64
-        };
-
-        // make an unconfirmed contract
-        let unconfirmed_contract = "(define-read-only (ro-test) (ok 1))";
-        let mut tx_unconfirmed_contract = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            TransactionAuth::from_p2pkh(&privk1).unwrap(),
-            TransactionPayload::new_smart_contract(
-                &format!("hello-world-unconfirmed"),
-                &unconfirmed_contract.to_string(),
-            )
-            .unwrap(),
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_at_height() {
+        test_rpc(
+            "test_rpc_get_account_at_height",
+            40262,
+            40263,
+            50262,
+            50263,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    Some(0),
+                    false,
+                    RPCStateView::Confirmed,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    // the account as of the boot block, before it sent any transactions
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 0);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        tx_unconfirmed_contract.chain_id = 0x80000000;
-        tx_unconfirmed_contract.auth.set_origin_nonce(3);
-        tx_unconfirmed_contract.set_fee_rate(0);
-
-        let mut tx_signer = StacksTransactionSigner::new(&tx_unconfirmed_contract);
-        tx_signer.sign_origin(&privk1).unwrap();
-        let tx_unconfirmed_contract_signed = tx_signer.get_tx().unwrap();
-        let tx_unconfirmed_contract_len = {
-            let mut bytes = vec![];
-            tx_unconfirmed_contract_signed
-                .consensus_serialize(&mut bytes)
-                .unwrap();
-            bytes.len() as u64
-        };
-
-        let tip =
-            SortitionDB::get_canonical_burn_chain_tip(&peer_1.sortdb.as_ref().unwrap().conn())
-                .unwrap();
-        let mut anchor_cost = ExecutionCost::zero();
-        let mut anchor_size = 0;
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_src_at_height() {
+        test_rpc(
+            "test_rpc_get_contract_src_at_height",
+            40264,
+            40265,
+            50264,
+            50265,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getcontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    None,
+                    Some(0),
+                    false,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    // the contract was deployed after the boot block
+                    HttpResponseType::NotFound(..) => true,
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        // make a block and a microblock.
-        // Put the coinbase and smart-contract in the anchored block.
-        // Put the contract-call in the microblock
-        let (burn_ops, stacks_block, microblocks) = peer_1.make_tenure(
-            |ref mut miner, ref mut sortdb, ref mut chainstate, vrf_proof, ref parent_opt, _| {
-                let parent_tip = match parent_opt {
-                    None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
-                    Some(block) => {
-                        let ic = sortdb.index_conn();
-                        let snapshot = SortitionDB::get_block_snapshot_for_winning_stacks_block(
-                            &ic,
-                            &tip.sortition_id,
-                            &block.block_hash(),
-                        )
-                        .unwrap()
-                        .unwrap(); // succeeds because we don't fork
-                        StacksChainState::get_anchored_block_header_info(
-                            chainstate.db(),
-                            &snapshot.consensus_hash,
-                            &snapshot.winning_stacks_block_hash,
-                        )
+    #[test]
+    #[ignore]
+    fn test_rpc_call_read_only_at_missing_height() {
+        test_rpc(
+            "test_rpc_call_read_only_at_missing_height",
+            40266,
+            40267,
+            50266,
+            50267,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_callreadonlyfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
                         .unwrap()
+                        .to_account_principal(),
+                    "ro-test".try_into().unwrap(),
+                    vec![],
+                    None,
+                    Some(100),
+                    RPCStateView::Confirmed,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    // the chain is nowhere near this tall
+                    HttpResponseType::NotFound(_, msg) => {
+                        assert!(msg.contains("No block at height 100"));
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_assets() {
+        test_rpc(
+            "test_rpc_get_account_assets",
+            40200,
+            40201,
+            50200,
+            50201,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccountassets(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
                         .unwrap()
+                        .to_account_principal(),
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccountAssets(response_md, data) => {
+                        assert_eq!(data.fungible_tokens.len(), 1);
+                        assert!(data.fungible_tokens[0]
+                            .asset_identifier
+                            .ends_with(".hello-world::stackaroos"));
+                        assert_eq!(data.fungible_tokens[0].balance, "100");
+
+                        assert_eq!(data.non_fungible_tokens.len(), 1);
+                        assert!(data.non_fungible_tokens[0]
+                            .asset_identifier
+                            .ends_with(".hello-world::stacka-nfts"));
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.non_fungible_tokens[0].value)
+                                .unwrap(),
+                            Value::buff_from(vec![0x01]).unwrap()
+                        );
+                        true
                     }
-                };
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-                let block_builder = StacksBlockBuilder::make_block_builder(
-                    &parent_tip,
-                    vrf_proof,
-                    tip.total_burn,
-                    microblock_pubkeyhash,
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_nonces() {
+        test_rpc(
+            "test_rpc_get_account_nonces",
+            40210,
+            40211,
+            50210,
+            50211,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R, which has nonce 2 at the chain tip
+                let privk = StacksPrivateKey::from_hex(
+                    "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
                 )
                 .unwrap();
-                let (anchored_block, anchored_block_size, anchored_block_cost) =
-                    StacksBlockBuilder::make_anchored_block_from_txs(
-                        block_builder,
-                        chainstate,
-                        &sortdb.index_conn(),
-                        vec![tx_coinbase_signed.clone(), tx_contract_signed.clone()],
+
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(
+                        peer_server.sortdb.as_ref().unwrap().conn(),
                     )
                     .unwrap();
 
-                anchor_size = anchored_block_size;
-                anchor_cost = anchored_block_cost;
+                // leave a gap at nonces 2 and 4
+                let mut mempool = peer_server.mempool.take().unwrap();
+                for nonce in [3, 5].iter() {
+                    let mut tx = StacksTransaction::new(
+                        TransactionVersion::Testnet,
+                        TransactionAuth::from_p2pkh(&privk).unwrap(),
+                        TransactionPayload::TokenTransfer(
+                            StacksAddress::from_string("STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW")
+                                .unwrap()
+                                .to_account_principal(),
+                            123,
+                            TokenTransferMemo([0u8; 34]),
+                            None,
+                        ),
+                    );
+                    tx.chain_id = 0x80000000;
+                    tx.auth.set_origin_nonce(*nonce);
+                    tx.set_fee_rate(1000);
+
+                    let mut tx_signer = StacksTransactionSigner::new(&tx);
+                    tx_signer.sign_origin(&privk).unwrap();
+                    let tx_signed = tx_signer.get_tx().unwrap();
+
+                    mempool
+                        .submit_raw(
+                            peer_server.chainstate(),
+                            &consensus_hash,
+                            &block_hash,
+                            tx_signed.serialize_to_vec(),
+                        )
+                        .unwrap();
+                }
+                peer_server.mempool = Some(mempool);
 
-                (anchored_block, vec![])
+                convo_client.new_getaccountnonces(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccountNonces(response_md, data) => {
+                        assert_eq!(data.confirmed_nonce, 2);
+                        assert_eq!(data.last_mempool_nonce, Some(5));
+                        assert_eq!(data.next_nonce, 6);
+                        assert_eq!(data.missing_nonces, vec![2, 4]);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
             },
         );
+    }
 
-        let (_, _, consensus_hash) = peer_1.next_burnchain_block(burn_ops.clone());
-        peer_2.next_burnchain_block(burn_ops.clone());
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_summary() {
+        test_rpc(
+            "test_rpc_get_account_summary",
+            40244,
+            40245,
+            50244,
+            50245,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccountsummary(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccountSummary(response_md, data) => {
+                        // first seen sending the coinbase of the first block
+                        let block = load_canonical_stacks_block(peer_server);
+                        let first_seen = data.first_seen.as_ref().unwrap();
+                        assert_eq!(first_seen.block_height, 1);
+                        assert_eq!(first_seen.txid, format!("{}", &block.txs[0].txid()));
+                        assert_eq!(data.nonce, 2);
+                        assert_eq!(data.kind, AddressKind::Standard);
+
+                        // only credited at genesis
+                        let (consensus_hash, block_hash) =
+                            SortitionDB::get_canonical_stacks_chain_tip_hash(
+                                peer_server.sortdb.as_ref().unwrap().conn(),
+                            )
+                            .unwrap();
+                        let tip =
+                            StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
+                        let addr2 =
+                            StacksAddress::from_string("STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW")
+                                .unwrap()
+                                .to_account_principal();
+                        assert!(peer_server
+                            .chainstate()
+                            .get_principal_first_seen(&addr2, &tip)
+                            .unwrap()
+                            .is_none());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        peer_1.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
-        peer_2.process_stacks_epoch_at_tip(&stacks_block, &vec![]);
+    #[test]
+    #[ignore]
+    fn test_rpc_get_transaction_receipt() {
+        test_rpc(
+            "test_rpc_get_transaction_receipt",
+            40246,
+            40247,
+            50246,
+            50247,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let block = load_canonical_stacks_block(peer_server);
+                convo_client.new_gettransactionreceipt(block.txs[0].txid(), None)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::TransactionReceipt(response_md, data) => {
+                        let block = load_canonical_stacks_block(peer_server);
+                        let (consensus_hash, block_hash) =
+                            SortitionDB::get_canonical_stacks_chain_tip_hash(
+                                peer_server.sortdb.as_ref().unwrap().conn(),
+                            )
+                            .unwrap();
+                        let tip =
+                            StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
 
-        // build 1-block microblock stream with the contract-call and the unconfirmed contract
-        let microblock = {
-            let sortdb = peer_1.sortdb.take().unwrap();
-            PeerNetwork::setup_unconfirmed_state(peer_1.chainstate(), &sortdb).unwrap();
-            let mblock = {
-                let sort_iconn = sortdb.index_conn();
-                let mut microblock_builder = StacksMicroblockBuilder::new(
-                    stacks_block.block_hash(),
-                    consensus_hash.clone(),
-                    peer_1.chainstate(),
-                    &sort_iconn,
-                )
-                .unwrap();
-                let microblock = microblock_builder
-                    .mine_next_microblock_from_txs(
-                        vec![
-                            (tx_cc_signed, tx_cc_len),
-                            (tx_unconfirmed_contract_signed, tx_unconfirmed_contract_len),
-                        ],
-                        &microblock_privkey,
-                    )
-                    .unwrap();
-                microblock
-            };
-            peer_1.sortdb = Some(sortdb);
-            mblock
-        };
+                        assert_eq!(data.txid, format!("0x{}", &block.txs[0].txid()));
+                        assert_eq!(data.index_block_hash, format!("0x{}", &tip));
+                        assert_eq!(data.block_height, 1);
+                        assert_eq!(data.tx_index, 0);
+                        assert_eq!(
+                            data.raw_tx,
+                            format!("0x{}", to_hex(&block.txs[0].serialize_to_vec()))
+                        );
+                        assert!(!data.post_condition_aborted);
+                        assert_eq!(data.asset_map, json!({}));
+                        assert_eq!(data.stx_burned, "0");
+                        assert!(data.contract_abi.is_none());
+
+                        // not mined
+                        assert!(peer_server
+                            .chainstate()
+                            .get_transaction_receipt(&Txid([0x11; 32]), &tip)
+                            .unwrap()
+                            .is_none());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        // store microblock stream
-        peer_1
-            .chainstate()
-            .preprocess_streamed_microblock(
-                &consensus_hash,
-                &stacks_block.block_hash(),
-                &microblock,
-            )
-            .unwrap();
-        peer_2
-            .chainstate()
-            .preprocess_streamed_microblock(
-                &consensus_hash,
-                &stacks_block.block_hash(),
-                &microblock,
-            )
-            .unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_get_principal_transactions() {
+        test_rpc(
+            "test_rpc_get_principal_transactions",
+            40248,
+            40249,
+            50248,
+            50249,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getprincipaltransactions(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    0,
+                    1,
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PrincipalTransactions(response_md, data) => {
+                        // sent the coinbase and the contract in the first block; newest first
+                        let block = load_canonical_stacks_block(peer_server);
+                        assert_eq!(data.total, 2);
+                        assert_eq!(data.offset, 0);
+                        assert_eq!(data.limit, 1);
+                        assert_eq!(data.results.len(), 1);
+                        assert_eq!(data.results[0].txid, format!("0x{}", &block.txs[1].txid()));
+                        assert_eq!(data.results[0].block_height, 1);
+                        assert_eq!(data.results[0].tx_index, 1);
+
+                        let (consensus_hash, block_hash) =
+                            SortitionDB::get_canonical_stacks_chain_tip_hash(
+                                peer_server.sortdb.as_ref().unwrap().conn(),
+                            )
+                            .unwrap();
+                        let tip =
+                            StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
+                        let addr =
+                            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                                .unwrap()
+                                .to_account_principal();
+                        let (total, page) = peer_server
+                            .chainstate()
+                            .get_principal_transactions(&addr, &tip, 1, 10)
+                            .unwrap();
+                        assert_eq!(total, 2);
+                        assert_eq!(page.len(), 1);
+                        assert_eq!(page[0].txid, block.txs[0].txid());
+
+                        // the deployed contract is involved too
+                        let contract = PrincipalData::parse(
+                            "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world",
+                        )
+                        .unwrap();
+                        let (total, page) = peer_server
+                            .chainstate()
+                            .get_principal_transactions(&contract, &tip, 0, 10)
+                            .unwrap();
+                        assert_eq!(total, 1);
+                        assert_eq!(page[0].txid, block.txs[1].txid());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        // process microblock stream to generate unconfirmed state
-        let canonical_tip =
-            StacksBlockHeader::make_index_block_hash(&consensus_hash, &stacks_block.block_hash());
-        let sortdb1 = peer_1.sortdb.take().unwrap();
-        let sortdb2 = peer_2.sortdb.take().unwrap();
-        peer_1
-            .chainstate()
-            .reload_unconfirmed_state(&sortdb1.index_conn(), canonical_tip.clone())
-            .unwrap();
-        peer_2
-            .chainstate()
-            .reload_unconfirmed_state(&sortdb2.index_conn(), canonical_tip.clone())
-            .unwrap();
-        peer_1.sortdb = Some(sortdb1);
-        peer_2.sortdb = Some(sortdb2);
+    #[test]
+    #[ignore]
+    fn test_rpc_get_pending_miner_rewards() {
+        test_rpc(
+            "test_rpc_get_pending_miner_rewards",
+            40278,
+            40279,
+            50278,
+            50279,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getpendingminerrewards(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PendingMinerRewards(response_md, data) => {
+                        // mined the only block, whose reward is paid out by the block at height
+                        // MINER_REWARD_MATURITY + 2
+                        let block = load_canonical_stacks_block(peer_server);
+                        assert_eq!(data.results.len(), 1);
+                        assert_eq!(
+                            data.results[0].block_hash,
+                            format!("0x{}", &block.block_hash())
+                        );
+                        assert_eq!(data.results[0].stacks_block_height, 1);
+                        assert!(data.results[0].miner);
+                        assert_eq!(data.results[0].maturity_height, MINER_REWARD_MATURITY + 2);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        let view_1 = peer_1.get_burnchain_view().unwrap();
-        let view_2 = peer_2.get_burnchain_view().unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_post_fee_estimate() {
+        let privk = StacksPrivateKey::new();
+        let mut tx_stx_transfer = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk).unwrap(),
+            TransactionPayload::TokenTransfer(
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                    .unwrap()
+                    .to_account_principal(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+                None,
+            ),
+        );
+        tx_stx_transfer.chain_id = 0x80000000;
+        tx_stx_transfer.set_fee_rate(0);
 
-        let mut convo_1 = ConversationHttp::new(
-            peer_1.config.network_id,
-            &peer_1.config.burnchain,
-            format!("127.0.0.1:{}", peer_1_http)
-                .parse::<SocketAddr>()
-                .unwrap(),
-            Some(UrlString::try_from(format!("http://peer1.com")).unwrap()),
-            peer_1.to_peer_host(),
-            &peer_1.config.connection_opts,
-            0,
+        let mut tx_signer = StacksTransactionSigner::new(&tx_stx_transfer);
+        tx_signer.sign_origin(&privk).unwrap();
+        let tx_stx_transfer_signed = tx_signer.get_tx().unwrap();
+        let tx_stx_transfer_len = tx_stx_transfer_signed.tx_len();
+
+        test_rpc(
+            "test_rpc_post_fee_estimate",
+            40202,
+            40203,
+            50202,
+            50203,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_post_fee_estimate(tx_stx_transfer_signed)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::TransactionFeeEstimate(response_md, data) => {
+                        assert_eq!(data.estimated_len, tx_stx_transfer_len);
+
+                        // the only block mined so far has plenty of room, so the low estimate
+                        // is the minimum fee rate.
+                        assert_eq!(data.fee_rates.low, MINIMUM_TX_FEE_RATE_PER_BYTE);
+                        assert!(data.fee_rates.low <= data.fee_rates.medium);
+                        assert!(data.fee_rates.medium <= data.fee_rates.high);
+
+                        assert_eq!(data.fees.low, data.fee_rates.low * tx_stx_transfer_len);
+                        assert_eq!(
+                            data.fees.medium,
+                            data.fee_rates.medium * tx_stx_transfer_len
+                        );
+                        assert_eq!(data.fees.high, data.fee_rates.high * tx_stx_transfer_len);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        let mut convo_2 = ConversationHttp::new(
-            peer_2.config.network_id,
-            &peer_2.config.burnchain,
-            format!("127.0.0.1:{}", peer_2_http)
-                .parse::<SocketAddr>()
-                .unwrap(),
-            Some(UrlString::try_from(format!("http://peer2.com")).unwrap()),
-            peer_2.to_peer_host(),
-            &peer_2.config.connection_opts,
-            1,
+    #[test]
+    #[ignore]
+    fn test_rpc_post_analyze_contract() {
+        let contract_id =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.counter")
+                .unwrap();
+        test_rpc(
+            "test_rpc_post_analyze_contract",
+            40274,
+            40275,
+            50274,
+            50275,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let source = "(define-data-var n int 1)
+                    (define-public (incr) (begin (var-set n (+ (var-get n) 1)) (ok (var-get n))))
+                    (var-set n 2)";
+                convo_client.new_post_analyze_contract(
+                    source.to_string(),
+                    Some(contract_id.clone()),
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                match http_response {
+                    HttpResponseType::ContractAnalysis(_, analysis) => {
+                        assert!(analysis.okay);
+                        assert_eq!(analysis.errors, vec![]);
+
+                        let interface = analysis.interface.as_ref().unwrap();
+                        assert_eq!(interface.functions.len(), 1);
+                        assert_eq!(interface.functions[0].name, "incr");
+                        assert_eq!(interface.variables.len(), 1);
+
+                        let deploy_cost = analysis.deploy_cost.as_ref().unwrap();
+                        assert!(analysis.analysis_cost.runtime > 0);
+                        assert!(deploy_cost.runtime > analysis.analysis_cost.runtime);
+                        assert!(deploy_cost.write_count > 0);
+
+                        // nothing was deployed
+                        let sortdb = peer_server.sortdb.take().unwrap();
+                        let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                        let tip = chainstate.get_stacks_chain_tip(&sortdb).unwrap().unwrap();
+                        let tip = StacksBlockHeader::make_index_block_hash(
+                            &tip.consensus_hash,
+                            &tip.anchored_block_hash,
+                        );
+                        let deployed = chainstate
+                            .maybe_read_only_clarity_tx(&sortdb.index_conn(), &tip, |clarity_tx| {
+                                clarity_tx
+                                    .with_clarity_db_readonly(|db| db.has_contract(&contract_id))
+                            })
+                            .unwrap();
+                        peer_server.sortdb = Some(sortdb);
+                        assert!(!deployed);
+                        true
+                    }
+                    _ => false,
+                }
+            },
         );
+    }
 
-        let req = make_request(&mut peer_1, &mut convo_1, &mut peer_2, &mut convo_2);
+    #[test]
+    #[ignore]
+    fn test_rpc_post_analyze_contract_errors() {
+        test_rpc(
+            "test_rpc_post_analyze_contract_errors",
+            40276,
+            40277,
+            50276,
+            50277,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let source =
+                    "(define-read-only (ok-fn) (ok 1))\n(define-read-only (bad-fn) (+ 1 u1))";
+                convo_client.new_post_analyze_contract(source.to_string(), None, None)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                match http_response {
+                    HttpResponseType::ContractAnalysis(_, analysis) => {
+                        assert!(!analysis.okay);
+                        assert!(analysis.interface.is_none());
+                        assert!(analysis.deploy_cost.is_none());
+                        assert_eq!(analysis.errors.len(), 1);
+                        assert!(analysis.errors[0].spans.len() > 0);
+                        assert_eq!(analysis.errors[0].spans[0].start_line, 2);
+                        true
+                    }
+                    _ => false,
+                }
+            },
+        );
+    }
 
-        convo_1.send_request(req.clone()).unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_post_simulate_transaction() {
+        // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R, which has nonce 4 once its unconfirmed
+        // microblocks are applied
+        let privk = StacksPrivateKey::from_hex(
+            "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+        )
+        .unwrap();
 
-        test_debug!("convo1 sends to convo2");
-        convo_send_recv(
-            &mut convo_1,
-            peer_1.chainstate(),
-            &mut convo_2,
-            peer_2.chainstate(),
+        // not signed
+        let mut tx_stx_transfer = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk).unwrap(),
+            TransactionPayload::TokenTransfer(
+                StacksAddress::from_string("STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW")
+                    .unwrap()
+                    .to_account_principal(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+                None,
+            ),
         );
+        tx_stx_transfer.chain_id = 0x80000000;
+        tx_stx_transfer.auth.set_origin_nonce(4);
+        tx_stx_transfer.set_fee_rate(1000);
+        let txid = tx_stx_transfer.txid();
 
-        // hack around the borrow-checker
-        let mut peer_1_sortdb = peer_1.sortdb.take().unwrap();
-        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
-        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
+        test_rpc(
+            "test_rpc_post_simulate_transaction",
+            40224,
+            40225,
+            50224,
+            50225,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_post_simulate_transaction(tx_stx_transfer)
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::TransactionSimulation(response_md, data) => {
+                        assert!(data.okay);
+                        assert_eq!(data.txid, format!("0x{}", &txid));
+                        assert_eq!(
+                            data.result,
+                            Some(format!(
+                                "0x{}",
+                                ClaritySerializable::serialize(&Value::okay_true())
+                            ))
+                        );
+                        assert!(!data.post_condition_aborted);
+                        assert_eq!(data.fee, 1000);
+                        assert_eq!(data.stx_burned, "0");
 
-        PeerNetwork::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
-            .unwrap();
+                        assert_eq!(data.events.len(), 1);
+                        assert_eq!(data.events[0]["type"], "stx_transfer_event");
+                        assert_eq!(data.events[0]["stx_transfer_event"]["amount"], "123");
+                        assert_eq!(
+                            data.asset_map,
+                            json!({
+                                "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R": { "stx": "123" }
+                            })
+                        );
 
-        convo_1
-            .chat(
-                &view_1,
-                &PeerMap::new(),
-                &mut peer_1_sortdb,
-                &peer_1.network.peerdb,
-                &mut peer_1.network.atlasdb,
-                &mut peer_1_stacks_node.chainstate,
-                &mut peer_1_mempool,
-                &RPCHandlerArgs::default(),
-            )
-            .unwrap();
+                        // nothing was committed
+                        let sortdb = peer_server.sortdb.take().unwrap();
+                        let account = peer_server
+                            .chainstate()
+                            .with_read_only_unconfirmed_clarity_tx(&sortdb.index_conn(), |conn| {
+                                StacksChainState::get_account(
+                                    conn,
+                                    &StacksAddress::from_string(
+                                        "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R",
+                                    )
+                                    .unwrap()
+                                    .to_account_principal(),
+                                )
+                            })
+                            .unwrap();
+                        peer_server.sortdb = Some(sortdb);
+                        assert_eq!(account.nonce, 4);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        peer_1.sortdb = Some(peer_1_sortdb);
-        peer_1.stacks_node = Some(peer_1_stacks_node);
-        peer_1.mempool = Some(peer_1_mempool);
+    /// Load the server's canonical Stacks chain tip block
+    fn load_canonical_stacks_block(peer: &mut TestPeer) -> StacksBlock {
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(peer.sortdb.as_ref().unwrap().conn())
+                .unwrap();
+        let blocks_path = peer.chainstate().blocks_path.clone();
+        StacksChainState::load_block(&blocks_path, &consensus_hash, &block_hash)
+            .unwrap()
+            .unwrap()
+    }
 
-        test_debug!("convo2 sends to convo1");
+    #[test]
+    #[ignore]
+    fn test_rpc_validate_block_proposal() {
+        let expected_block_hash = RefCell::new(None);
+        test_rpc(
+            "test_rpc_validate_block_proposal",
+            40226,
+            40227,
+            50226,
+            50227,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_server.connection.options.block_proposal_token = Some("secret".into());
+
+                // re-validate the tip block against its parent
+                let block = load_canonical_stacks_block(peer_server);
+                *expected_block_hash.borrow_mut() = Some(block.block_hash());
+                convo_client.new_validate_block_proposal(
+                    FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                    block,
+                    Some("secret".into()),
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::BlockProposal(response_md, data) => {
+                        assert_eq!(
+                            data.block_hash,
+                            format!("{}", expected_block_hash.borrow().as_ref().unwrap())
+                        );
+                        assert!(data.accepted);
+                        assert!(data.reason_code.is_none());
+                        assert!(data.reason.is_none());
+                        assert!(data.execution_cost.is_some());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        // hack around the borrow-checker
-        let mut peer_2_sortdb = peer_2.sortdb.take().unwrap();
-        let mut peer_2_stacks_node = peer_2.stacks_node.take().unwrap();
-        let mut peer_2_mempool = peer_2.mempool.take().unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_validate_block_proposal_rejected() {
+        test_rpc(
+            "test_rpc_validate_block_proposal_rejected",
+            40228,
+            40229,
+            50228,
+            50229,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_server.connection.options.block_proposal_token = Some("secret".into());
+
+                // the tip block, but with the wrong state root
+                let mut block = load_canonical_stacks_block(peer_server);
+                block.header.state_index_root = TrieHash([0x11; 32]);
+                convo_client.new_validate_block_proposal(
+                    FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                    block,
+                    Some("secret".into()),
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::BlockProposal(response_md, data) => {
+                        assert!(!data.accepted);
+                        assert_eq!(data.reason_code, Some("InvalidBlock".to_string()));
+                        assert!(data
+                            .reason
+                            .as_ref()
+                            .unwrap()
+                            .contains("state root mismatch"));
+                        assert!(data.execution_cost.is_none());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        PeerNetwork::setup_unconfirmed_state(&mut peer_2_stacks_node.chainstate, &peer_2_sortdb)
-            .unwrap();
+    #[test]
+    #[ignore]
+    fn test_rpc_validate_block_proposal_unauthorized() {
+        test_rpc(
+            "test_rpc_validate_block_proposal_unauthorized",
+            40230,
+            40231,
+            50230,
+            50231,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_server.connection.options.block_proposal_token = Some("secret".into());
 
-        convo_2
-            .chat(
-                &view_2,
-                &PeerMap::new(),
-                &mut peer_2_sortdb,
-                &peer_2.network.peerdb,
-                &mut peer_2.network.atlasdb,
-                &mut peer_2_stacks_node.chainstate,
-                &mut peer_2_mempool,
-                &RPCHandlerArgs::default(),
-            )
-            .unwrap();
+                let block = load_canonical_stacks_block(peer_server);
+                convo_client.new_validate_block_proposal(
+                    FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                    block,
+                    Some("wrong".into()),
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                match http_response {
+                    HttpResponseType::Unauthorized(_, _) => true,
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        peer_2.sortdb = Some(peer_2_sortdb);
-        peer_2.stacks_node = Some(peer_2_stacks_node);
-        peer_2.mempool = Some(peer_2_mempool);
+    #[test]
+    #[ignore]
+    fn test_rpc_get_miner_status() {
+        let miner_status = Arc::new(Mutex::new(RPCMinerStatusData::new(true)));
+        let handler_args = RPCHandlerArgs {
+            miner_status: Some(miner_status.clone()),
+            ..RPCHandlerArgs::default()
+        };
+        let expected_burn_height = RefCell::new(0);
+        test_rpc_with_handler_args(
+            "test_rpc_get_miner_status",
+            40238,
+            40239,
+            50238,
+            50239,
+            &handler_args,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_server.connection.options.admin_token = Some("secret".into());
+
+                // one commit the server has seen mined, and one it has not
+                let tip = SortitionDB::get_canonical_burn_chain_tip(
+                    peer_server.sortdb.as_ref().unwrap().conn(),
+                )
+                .unwrap();
+                *expected_burn_height.borrow_mut() = tip.block_height;
+
+                let mut status = miner_status.lock().unwrap();
+                for txid in [tip.winning_block_txid.clone(), Txid([0x11; 32])].iter() {
+                    status.inflight_commits.push(RPCMinerBlockCommit {
+                        txid: format!("{}", txid),
+                        block_hash: BlockHeaderHash([0x22; 32]),
+                        parent_consensus_hash: ConsensusHash([0x33; 20]),
+                        burn_fee: 1000,
+                        sent_at_burn_height: tip.block_height - 1,
+                        attempt: 1,
+                        burn_block_height: None,
+                        confirmations: 0,
+                    });
+                }
 
-        convo_send_recv(
-            &mut convo_2,
-            peer_2.chainstate(),
-            &mut convo_1,
-            peer_1.chainstate(),
+                convo_client.new_get_miner_status(Some("secret".into()))
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                match http_response {
+                    HttpResponseType::MinerStatus(_, ref status) => {
+                        assert!(status.is_miner);
+                        assert_eq!(status.inflight_commits.len(), 2);
+                        assert_eq!(
+                            status.inflight_commits[0].burn_block_height,
+                            Some(*expected_burn_height.borrow())
+                        );
+                        assert_eq!(status.inflight_commits[0].confirmations, 1);
+                        assert_eq!(status.inflight_commits[1].burn_block_height, None);
+                        assert_eq!(status.inflight_commits[1].confirmations, 0);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
         );
+    }
 
-        test_debug!("flush convo1");
+    #[test]
+    #[ignore]
+    fn test_rpc_get_miner_status_disabled() {
+        test_rpc(
+            "test_rpc_get_miner_status_disabled",
+            40240,
+            40241,
+            50240,
+            50241,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_get_miner_status(Some("secret".into()))
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                match http_response {
+                    HttpResponseType::Forbidden(_, _) => true,
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-        // hack around the borrow-checker
-        convo_send_recv(
-            &mut convo_1,
-            peer_1.chainstate(),
-            &mut convo_2,
-            peer_2.chainstate(),
+    fn make_test_convo(peer_addr: &str, conn_opts: &ConnectionOptions) -> ConversationHttp {
+        let burnchain = Burnchain::default_unittest(0, &BurnchainHeaderHash([0u8; 32]));
+        ConversationHttp::new(
+            0x80000000,
+            &burnchain,
+            peer_addr.parse::<SocketAddr>().unwrap(),
+            None,
+            PeerHost::from_host_port("localhost".to_string(), 20443),
+            conn_opts,
+            1,
+        )
+    }
+
+    #[test]
+    fn test_rpc_rate_limits() {
+        let mut conn_opts = ConnectionOptions::default();
+        conn_opts.rpc_rate_limit = Some(HttpRateLimit {
+            max_requests: 4,
+            window_secs: 60,
+        });
+        conn_opts.rpc_expensive_rate_limit = Some(HttpRateLimit {
+            max_requests: 1,
+            window_secs: 60,
+        });
+        conn_opts.rpc_route_rate_limits.insert(
+            "/v2/info".to_string(),
+            HttpRateLimit {
+                max_requests: 2,
+                window_secs: 60,
+            },
         );
 
-        let mut peer_1_sortdb = peer_1.sortdb.take().unwrap();
-        let mut peer_1_stacks_node = peer_1.stacks_node.take().unwrap();
-        let mut peer_1_mempool = peer_1.mempool.take().unwrap();
+        let md = HttpRequestMetadata::new("localhost".to_string(), 20443);
+        let get_info = HttpRequestType::GetInfo(md.clone());
+        let get_pox = HttpRequestType::GetPoxInfo(md.clone(), None);
+        let call_read = HttpRequestType::CallReadOnlyFunction(
+            md.clone(),
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+            "hello-world".into(),
+            PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+            "get-bar".into(),
+            vec![],
+            None,
+            None,
+            RPCStateView::Confirmed,
+        );
 
-        PeerNetwork::setup_unconfirmed_state(&mut peer_1_stacks_node.chainstate, &peer_1_sortdb)
-            .unwrap();
+        let convo = make_test_convo("1.2.3.4:5678", &conn_opts);
+        let mut limiter = HttpRateLimiter::new();
+
+        // per-route limit
+        assert!(convo.exceeded_rate_limit(&get_info, &mut limiter).is_none());
+        assert!(convo.exceeded_rate_limit(&get_info, &mut limiter).is_none());
+        assert_eq!(
+            convo
+                .exceeded_rate_limit(&get_info, &mut limiter)
+                .unwrap()
+                .max_requests,
+            2
+        );
 
-        convo_1
-            .chat(
-                &view_1,
-                &PeerMap::new(),
-                &mut peer_1_sortdb,
-                &peer_1.network.peerdb,
-                &mut peer_1.network.atlasdb,
-                &mut peer_1_stacks_node.chainstate,
-                &mut peer_1_mempool,
-                &RPCHandlerArgs::default(),
-            )
-            .unwrap();
+        // expensive-request limit
+        assert!(convo
+            .exceeded_rate_limit(&call_read, &mut limiter)
+            .is_none());
+        assert_eq!(
+            convo
+                .exceeded_rate_limit(&call_read, &mut limiter)
+                .unwrap()
+                .max_requests,
+            1
+        );
 
-        peer_1.sortdb = Some(peer_1_sortdb);
-        peer_1.stacks_node = Some(peer_1_stacks_node);
-        peer_1.mempool = Some(peer_1_mempool);
+        // overall limit, which only the admitted requests above counted against
+        assert!(convo.exceeded_rate_limit(&get_pox, &mut limiter).is_none());
+        assert_eq!(
+            convo
+                .exceeded_rate_limit(&get_pox, &mut limiter)
+                .unwrap()
+                .max_requests,
+            4
+        );
 
-        convo_1.try_flush(peer_1.chainstate()).unwrap();
+        // local clients are never limited
+        let local_convo = make_test_convo("127.0.0.1:5678", &conn_opts);
+        for _ in 0..10 {
+            assert!(local_convo
+                .exceeded_rate_limit(&call_read, &mut limiter)
+                .is_none());
+        }
+    }
 
-        // should have gotten a reply
-        let resp_opt = convo_1.try_get_response();
-        assert!(resp_opt.is_some());
+    #[test]
+    fn test_rpc_auth_tokens() {
+        let mut conn_opts = ConnectionOptions::default();
+        assert!(make_test_convo("1.2.3.4:5678", &conn_opts)
+            .connection
+            .protocol
+            .auth_tokens
+            .is_empty());
+
+        conn_opts.rpc_auth_token = Some("secret".to_string());
+        conn_opts.admin_token = Some("admin".to_string());
+        assert_eq!(
+            make_test_convo("1.2.3.4:5678", &conn_opts)
+                .connection
+                .protocol
+                .auth_tokens,
+            vec!["secret".to_string(), "admin".to_string()]
+        );
+        assert!(make_test_convo("127.0.0.1:5678", &conn_opts)
+            .connection
+            .protocol
+            .auth_tokens
+            .is_empty());
+    }
 
-        let resp = resp_opt.unwrap();
-        assert!(check_result(&req, &resp, &mut peer_1, &mut peer_2));
+    fn make_scheduled_calls_path(test_name: &str) -> String {
+        let path = format!("/tmp/{}.sqlite", test_name);
+        if fs::metadata(&path).is_ok() {
+            fs::remove_file(&path).unwrap();
+        }
+        path
     }
 
     #[test]
     #[ignore]
-    fn test_rpc_getinfo() {
-        let peer_server_info = RefCell::new(None);
-        test_rpc(
-            "test_rpc_getinfo",
-            40000,
-            40001,
-            50000,
-            50001,
+    fn test_rpc_add_scheduled_call() {
+        let path = make_scheduled_calls_path("test_rpc_add_scheduled_call");
+        let handler_args = RPCHandlerArgs {
+            scheduled_calls_path: Some(path.clone()),
+            ..RPCHandlerArgs::default()
+        };
+        let request = ScheduledCallRequest {
+            contract_address: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+            contract_name: "keeper".to_string(),
+            function_name: "advance-epoch".to_string(),
+            function_args: vec!["0100000000000000000000000000000001".to_string()],
+            target_burn_height: 120,
+            fee: 500,
+        };
+        test_rpc_with_handler_args(
+            "test_rpc_add_scheduled_call",
+            40250,
+            40251,
+            50250,
+            50251,
+            &handler_args,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_info = RPCPeerInfoData::from_db(
-                    &peer_server.config.burnchain,
-                    peer_server.sortdb.as_mut().unwrap(),
-                    &peer_server.stacks_node.as_ref().unwrap().chainstate,
-                    &peer_server.network.peerdb,
-                    &None,
-                    &Sha256Sum::zero(),
+                convo_server.connection.options.admin_token = Some("secret".into());
+                convo_client.new_scheduled_calls(
+                    ScheduledCallsOp::Add(request.clone()),
+                    Some("secret".into()),
                 )
-                .unwrap();
-
-                *peer_server_info.borrow_mut() = Some(peer_info);
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                match http_response {
+                    HttpResponseType::ScheduledCall(_, ref call) => {
+                        assert_eq!(call.status, ScheduledCallStatus::Pending);
+                        assert_eq!(call.function_args, request.function_args);
+                        assert_eq!(call.target_burn_height, 120);
+                        let db = ScheduledCallDB::open(&path).unwrap();
+                        assert_eq!(db.get_all().unwrap(), vec![call.clone()]);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
 
-                convo_client.new_getinfo()
+    #[test]
+    #[ignore]
+    fn test_rpc_cancel_scheduled_call() {
+        let path = make_scheduled_calls_path("test_rpc_cancel_scheduled_call");
+        let handler_args = RPCHandlerArgs {
+            scheduled_calls_path: Some(path.clone()),
+            ..RPCHandlerArgs::default()
+        };
+        test_rpc_with_handler_args(
+            "test_rpc_cancel_scheduled_call",
+            40252,
+            40253,
+            50252,
+            50253,
+            &handler_args,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_server.connection.options.admin_token = Some("secret".into());
+                let call = ScheduledCallDB::open(&path)
+                    .unwrap()
+                    .add(&ScheduledCallRequest {
+                        contract_address: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+                        contract_name: "keeper".to_string(),
+                        function_name: "advance-epoch".to_string(),
+                        function_args: vec![],
+                        target_burn_height: 120,
+                        fee: 500,
+                    })
+                    .unwrap();
+                convo_client
+                    .new_scheduled_calls(ScheduledCallsOp::Cancel(call.id), Some("secret".into()))
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::PeerInfo(response_md, peer_data) => {
-                        assert_eq!(Some((*peer_data).clone()), *peer_server_info.borrow());
+                    HttpResponseType::ScheduledCall(_, ref call) => {
+                        assert_eq!(call.status, ScheduledCallStatus::Cancelled);
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3056,46 +9216,148 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getpoxinfo() {
-        let pox_server_info = RefCell::new(None);
+    fn test_rpc_scheduled_calls_disabled() {
         test_rpc(
-            "test_rpc_getpoxinfo",
-            40000,
-            40001,
-            50000,
-            50001,
+            "test_rpc_scheduled_calls_disabled",
+            40254,
+            40255,
+            50254,
+            50255,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
-                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
-                let stacks_block_id = {
-                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
-                    StacksBlockHeader::make_index_block_hash(
-                        &tip.consensus_hash,
-                        &tip.anchored_block_hash,
+                convo_server.connection.options.admin_token = Some("secret".into());
+                convo_client.new_scheduled_calls(ScheduledCallsOp::List, Some("secret".into()))
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                match http_response {
+                    HttpResponseType::Forbidden(_, _) => true,
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    fn make_test_batch_queries() -> Vec<BatchQuery> {
+        let addr = StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        vec![
+            BatchQuery::Account(addr.to_account_principal()),
+            BatchQuery::MapEntry(
+                addr.clone(),
+                "hello-world".try_into().unwrap(),
+                "unit-map".try_into().unwrap(),
+                Value::Tuple(
+                    TupleData::from_data(vec![(
+                        "account".into(),
+                        Value::Principal(addr.to_account_principal()),
+                    )])
+                    .unwrap(),
+                ),
+            ),
+            BatchQuery::CallRead(
+                addr.clone(),
+                "hello-world-unconfirmed".try_into().unwrap(),
+                "ro-test".try_into().unwrap(),
+                addr.to_account_principal(),
+                vec![],
+            ),
+        ]
+    }
+
+    /// Check a batch's results: the account's nonce, the units in its map entry, and the result
+    /// of the read-only call, if it succeeded
+    fn check_batch_results(
+        batch: &BatchResponse,
+        nonce: u64,
+        units: i128,
+        call_result: Option<Value>,
+    ) {
+        assert_eq!(batch.results.len(), 3);
+        match batch.results[0] {
+            BatchQueryResult::Account(ref account) => {
+                assert_eq!(account.nonce, nonce);
+                assert!(account.balance_proof.is_none());
+            }
+            _ => panic!("Expected an account"),
+        }
+        match batch.results[1] {
+            BatchQueryResult::MapEntry(ref entry) => {
+                assert_eq!(
+                    Value::try_deserialize_hex_untyped(&entry.data).unwrap(),
+                    Value::some(Value::Tuple(
+                        TupleData::from_data(vec![("units".into(), Value::Int(units))]).unwrap()
+                    ))
+                    .unwrap()
+                );
+                assert!(entry.marf_proof.is_none());
+            }
+            _ => panic!("Expected a map entry"),
+        }
+        match (&batch.results[2], call_result) {
+            (BatchQueryResult::CallRead(ref call), Some(value)) => {
+                assert!(call.okay);
+                assert_eq!(
+                    Value::try_deserialize_hex_untyped(call.result.as_ref().unwrap()).unwrap(),
+                    value
+                );
+            }
+            // a failed call doesn't fail the batch
+            (BatchQueryResult::CallRead(ref call), None) => {
+                assert!(!call.okay);
+                assert!(call
+                    .cause
+                    .as_ref()
+                    .unwrap()
+                    .find("NoSuchContract")
+                    .is_some());
+            }
+            _ => panic!("Expected a read-only call"),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_batch() {
+        let expected_tip = RefCell::new(None);
+        test_rpc(
+            "test_rpc_batch",
+            40256,
+            40257,
+            50256,
+            50257,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(
+                        peer_server.sortdb.as_ref().unwrap().conn(),
                     )
-                };
-                let pox_info = RPCPoxInfoData::from_db(
-                    &mut sortdb,
-                    chainstate,
-                    &stacks_block_id,
-                    &ConnectionOptions::default(),
+                    .unwrap();
+                *expected_tip.borrow_mut() = Some(StacksBlockHeader::make_index_block_hash(
+                    &consensus_hash,
+                    &block_hash,
+                ));
+                convo_client.new_batch(
+                    make_test_batch_queries(),
+                    None,
+                    false,
+                    RPCStateView::Confirmed,
                 )
-                .unwrap();
-                *pox_server_info.borrow_mut() = Some(pox_info);
-                convo_client.new_getpoxinfo(None)
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::PoxInfo(response_md, pox_data) => {
-                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                    HttpResponseType::Batch(_, batch) => {
+                        assert_eq!(Some(batch.tip.clone()), *expected_tip.borrow());
+                        check_batch_results(batch, 2, 123, None);
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3105,27 +9367,32 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getneighbors() {
+    fn test_rpc_batch_unconfirmed() {
         test_rpc(
-            "test_rpc_getneighbors",
-            40010,
-            40011,
-            50010,
-            50011,
+            "test_rpc_batch_unconfirmed",
+            40260,
+            40261,
+            50260,
+            50261,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
-             ref mut convo_server| { convo_client.new_getneighbors() },
+             ref mut convo_server| {
+                convo_client.new_batch(
+                    make_test_batch_queries(),
+                    None,
+                    false,
+                    RPCStateView::Unconfirmed,
+                )
+            },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Neighbors(response_md, neighbor_info) => {
-                        assert_eq!(neighbor_info.sample.len(), 1);
-                        assert_eq!(neighbor_info.sample[0].port, peer_client.config.server_port); // we see ourselves as the neighbor
+                    HttpResponseType::Batch(_, batch) => {
+                        check_batch_results(batch, 4, 1, Some(Value::okay(Value::Int(1)).unwrap()));
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3135,54 +9402,33 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_unconfirmed_getblock() {
-        let server_block_cell = RefCell::new(None);
-
+    fn test_rpc_batch_too_many_queries() {
         test_rpc(
-            "test_rpc_unconfirmed_getblock",
-            40020,
-            40021,
-            50020,
-            50021,
+            "test_rpc_batch_too_many_queries",
+            40258,
+            40259,
+            50258,
+            50259,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                // have "server" peer store a block to staging
-                let peer_server_block = make_codec_test_block(25);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block.block_hash(),
-                );
-
-                test_debug!("Store peer server index block {:?}", &index_block_hash);
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &peer_server_consensus_hash,
-                    &peer_server_block,
-                    &ConsensusHash([0x03; 20]),
-                    456,
-                    123,
-                );
-
-                *server_block_cell.borrow_mut() = Some(peer_server_block);
-
-                // now ask for it
-                convo_client.new_getblock(index_block_hash)
+                convo_server.connection.options.max_batch_queries = 2;
+                convo_client.new_batch(
+                    make_test_batch_queries(),
+                    None,
+                    false,
+                    RPCStateView::Confirmed,
+                )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Block(response_md, block_info) => {
-                        assert_eq!(
-                            block_info.block_hash(),
-                            (*server_block_cell.borrow()).as_ref().unwrap().block_hash()
-                        );
+                    HttpResponseType::BadRequest(_, msg) => {
+                        assert!(msg.find("Too many queries").is_some());
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3192,60 +9438,42 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_confirmed_getblock() {
-        let server_block_cell = RefCell::new(None);
-
+    fn test_rpc_post_block_already_known() {
+        let expected_block_id = RefCell::new(None);
         test_rpc(
-            "test_rpc_confirmed_getblock",
-            40030,
-            40031,
-            50030,
-            50031,
+            "test_rpc_post_block_already_known",
+            40242,
+            40243,
+            50242,
+            50243,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                // have "server" peer store a block to staging
-                let peer_server_block = make_codec_test_block(25);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block.block_hash(),
-                );
-
-                test_debug!("Store peer server index block {:?}", &index_block_hash);
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &peer_server_consensus_hash,
-                    &peer_server_block,
-                    &ConsensusHash([0x03; 20]),
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &peer_server_consensus_hash,
-                    &peer_server_block.block_hash(),
-                    true,
-                );
-
-                *server_block_cell.borrow_mut() = Some(peer_server_block);
-
-                // now ask for it
-                convo_client.new_getblock(index_block_hash)
+                // re-upload the server's tip block
+                let (consensus_hash, _) = SortitionDB::get_canonical_stacks_chain_tip_hash(
+                    peer_server.sortdb.as_ref().unwrap().conn(),
+                )
+                .unwrap();
+                let block = load_canonical_stacks_block(peer_server);
+                *expected_block_id.borrow_mut() = Some(StacksBlockHeader::make_index_block_hash(
+                    &consensus_hash,
+                    &block.block_hash(),
+                ));
+                convo_client.new_post_block(consensus_hash, block)
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Block(response_md, block_info) => {
+                    HttpResponseType::StacksBlockAccepted(_, data) => {
                         assert_eq!(
-                            block_info.block_hash(),
-                            (*server_block_cell.borrow()).as_ref().unwrap().block_hash()
+                            Some(data.stacks_block_id.clone()),
+                            *expected_block_id.borrow()
                         );
+                        assert!(!data.accepted);
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3255,109 +9483,45 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_indexed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
-
+    fn test_rpc_get_account_unconfirmed() {
         test_rpc(
-            "test_rpc_indexed_microblocks",
-            40040,
-            40041,
-            50040,
-            50041,
+            "test_rpc_get_account_unconfirmed",
+            40120,
+            40121,
+            50120,
+            50121,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    Some(unconfirmed_tip),
+                    None,
+                    false,
+                    RPCStateView::Confirmed,
                 )
-                .unwrap();
-
-                let parent_block = make_codec_test_block(25);
-                let parent_consensus_hash = ConsensusHash([0x02; 20]);
-                let parent_index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &parent_consensus_hash,
-                    &parent_block.block_hash(),
-                );
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
-                mblocks.truncate(15);
-
-                let mut child_block = make_codec_test_block(25);
-                let child_consensus_hash = ConsensusHash([0x03; 20]);
-
-                child_block.header.parent_block = parent_block.block_hash();
-                child_block.header.parent_microblock =
-                    mblocks.last().as_ref().unwrap().block_hash();
-                child_block.header.parent_microblock_sequence =
-                    mblocks.last().as_ref().unwrap().header.sequence;
-
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &parent_consensus_hash,
-                    &parent_block,
-                    &ConsensusHash([0x01; 20]),
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &parent_consensus_hash,
-                    &parent_block.block_hash(),
-                    true,
-                );
-
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block,
-                    &parent_consensus_hash,
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                    true,
-                );
-
-                let index_microblock_hash = StacksBlockHeader::make_index_block_hash(
-                    &parent_consensus_hash,
-                    &mblocks.last().as_ref().unwrap().block_hash(),
-                );
-
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
-                        peer_server.chainstate(),
-                        &parent_consensus_hash,
-                        &parent_block.block_hash(),
-                        &mblock,
-                    );
-                }
-
-                set_microblocks_processed(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                    &mblocks.last().as_ref().unwrap().block_hash(),
-                );
-
-                *server_microblocks_cell.borrow_mut() = mblocks;
-
-                convo_client.new_getmicroblocks_indexed(index_microblock_hash)
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
-                match (*http_response).clone() {
-                    HttpResponseType::Microblocks(_, mut microblocks) => {
-                        microblocks.reverse();
-                        assert_eq!(microblocks.len(), (*server_microblocks_cell.borrow()).len());
-                        assert_eq!(microblocks, *server_microblocks_cell.borrow());
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 4);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000 - 123);
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3367,105 +9531,40 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_confirmed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
-
+    fn test_rpc_get_account_unconfirmed_view() {
         test_rpc(
-            "test_rpc_confirmed_microblocks",
-            40042,
-            40043,
-            50042,
-            50043,
+            "test_rpc_get_account_unconfirmed_view",
+            40216,
+            40217,
+            50216,
+            50217,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
-                )
-                .unwrap();
-
-                let parent_block = make_codec_test_block(25);
-                let parent_consensus_hash = ConsensusHash([0x02; 20]);
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &parent_block.block_hash());
-                mblocks.truncate(15);
-
-                let mut child_block = make_codec_test_block(25);
-                let child_consensus_hash = ConsensusHash([0x03; 20]);
-
-                child_block.header.parent_block = parent_block.block_hash();
-                child_block.header.parent_microblock =
-                    mblocks.last().as_ref().unwrap().block_hash();
-                child_block.header.parent_microblock_sequence =
-                    mblocks.last().as_ref().unwrap().header.sequence;
-
-                let child_index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                );
-
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &parent_consensus_hash,
-                    &parent_block,
-                    &ConsensusHash([0x01; 20]),
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &parent_consensus_hash,
-                    &parent_block.block_hash(),
-                    true,
-                );
-
-                store_staging_block(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block,
-                    &parent_consensus_hash,
-                    456,
-                    123,
-                );
-                set_block_processed(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                    true,
-                );
-
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
-                        peer_server.chainstate(),
-                        &parent_consensus_hash,
-                        &parent_block.block_hash(),
-                        &mblock,
-                    );
-                }
-
-                set_microblocks_processed(
-                    peer_server.chainstate(),
-                    &child_consensus_hash,
-                    &child_block.block_hash(),
-                    &mblocks.last().as_ref().unwrap().block_hash(),
-                );
-
-                *server_microblocks_cell.borrow_mut() = mblocks;
-
-                convo_client.new_getmicroblocks_confirmed(child_index_block_hash)
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    None,
+                    false,
+                    RPCStateView::Unconfirmed,
+                )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
-                match (*http_response).clone() {
-                    HttpResponseType::Microblocks(_, mut microblocks) => {
-                        microblocks.reverse();
-                        assert_eq!(microblocks.len(), (*server_microblocks_cell.borrow()).len());
-                        assert_eq!(microblocks, *server_microblocks_cell.borrow());
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 4);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000 - 123);
+                        assert!(data.balance_proof.is_none());
+                        assert!(data.nonce_proof.is_none());
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3475,59 +9574,88 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_unconfirmed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
-
+    fn test_rpc_get_account_mempool_view() {
         test_rpc(
-            "test_rpc_unconfirmed_microblocks",
-            40050,
-            40051,
-            50050,
-            50051,
+            "test_rpc_get_account_mempool_view",
+            40218,
+            40219,
+            50218,
+            50219,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
+                // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R, which has nonce 4 in the
+                // unconfirmed state
                 let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                    "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
                 )
                 .unwrap();
 
-                let consensus_hash = ConsensusHash([0x02; 20]);
-                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
-                let index_block_hash =
-                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
-                mblocks.truncate(15);
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(
+                        peer_server.sortdb.as_ref().unwrap().conn(),
+                    )
+                    .unwrap();
 
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
-                        peer_server.chainstate(),
-                        &consensus_hash,
-                        &anchored_block_hash,
-                        &mblock,
+                // a pending transfer at the next nonce, and one that can't be applied
+                let mut mempool = peer_server.mempool.take().unwrap();
+                for nonce in [4, 6].iter() {
+                    let mut tx = StacksTransaction::new(
+                        TransactionVersion::Testnet,
+                        TransactionAuth::from_p2pkh(&privk).unwrap(),
+                        TransactionPayload::TokenTransfer(
+                            StacksAddress::from_string("STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW")
+                                .unwrap()
+                                .to_account_principal(),
+                            123,
+                            TokenTransferMemo([0u8; 34]),
+                            None,
+                        ),
                     );
+                    tx.chain_id = 0x80000000;
+                    tx.auth.set_origin_nonce(*nonce);
+                    tx.set_fee_rate(1000);
+
+                    let mut tx_signer = StacksTransactionSigner::new(&tx);
+                    tx_signer.sign_origin(&privk).unwrap();
+                    let tx_signed = tx_signer.get_tx().unwrap();
+
+                    mempool
+                        .submit_raw(
+                            peer_server.chainstate(),
+                            &consensus_hash,
+                            &block_hash,
+                            tx_signed.serialize_to_vec(),
+                        )
+                        .unwrap();
                 }
+                peer_server.mempool = Some(mempool);
 
-                *server_microblocks_cell.borrow_mut() = mblocks;
-
-                // start at seq 5
-                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 5)
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    None,
+                    true,
+                    RPCStateView::UnconfirmedWithMempool,
+                )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Microblocks(response_md, microblocks) => {
-                        assert_eq!(microblocks.len(), 10);
-                        assert_eq!(
-                            *microblocks,
-                            (*server_microblocks_cell.borrow())[5..].to_vec()
-                        );
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 5);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000 - 123 - 123 - 1000);
+                        // no proofs for state that isn't in the MARF
+                        assert!(data.balance_proof.is_none());
+                        assert!(data.nonce_proof.is_none());
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3537,72 +9665,51 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_unconfirmed_transaction() {
-        let last_txid = RefCell::new(Txid([0u8; 32]));
-        let last_mblock = RefCell::new(BlockHeaderHash([0u8; 32]));
-
+    fn test_rpc_get_map_entry() {
         test_rpc(
-            "test_rpc_unconfirmed_transaction",
-            40052,
-            40053,
-            50052,
-            50053,
+            "test_rpc_get_map_entry",
+            40130,
+            40131,
+            50130,
+            50131,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                let principal =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal();
+                convo_client.new_getmapentry(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "unit-map".try_into().unwrap(),
+                    Value::Tuple(
+                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
+                            .unwrap(),
+                    ),
+                    None,
+                    None,
+                    false,
                 )
-                .unwrap();
-
-                let sortdb = peer_server.sortdb.take().unwrap();
-                PeerNetwork::setup_unconfirmed_state(peer_server.chainstate(), &sortdb).unwrap();
-                peer_server.sortdb = Some(sortdb);
-
-                assert!(peer_server.chainstate().unconfirmed_state.is_some());
-                let (txid, mblock_hash) = match peer_server.chainstate().unconfirmed_state {
-                    Some(ref unconfirmed) => {
-                        assert!(unconfirmed.mined_txs.len() > 0);
-                        let mut txid = Txid([0u8; 32]);
-                        let mut mblock_hash = BlockHeaderHash([0u8; 32]);
-                        for (next_txid, (_, mbh, ..)) in unconfirmed.mined_txs.iter() {
-                            txid = next_txid.clone();
-                            mblock_hash = mbh.clone();
-                            break;
-                        }
-                        (txid, mblock_hash)
-                    }
-                    None => {
-                        panic!("No unconfirmed state");
-                    }
-                };
-
-                *last_txid.borrow_mut() = txid.clone();
-                *last_mblock.borrow_mut() = mblock_hash.clone();
-
-                convo_client.new_gettransaction_unconfirmed(txid)
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::UnconfirmedTransaction(response_md, unconfirmed_resp) => {
+                    HttpResponseType::GetMapEntry(response_md, data) => {
                         assert_eq!(
-                            unconfirmed_resp.status,
-                            UnconfirmedTransactionStatus::Microblock {
-                                block_hash: (*last_mblock.borrow()).clone(),
-                                seq: 0
-                            }
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::some(Value::Tuple(
+                                TupleData::from_data(vec![("units".into(), Value::Int(123))])
+                                    .unwrap()
+                            ))
+                            .unwrap()
                         );
-                        let tx = StacksTransaction::consensus_deserialize(
-                            &mut &hex_bytes(&unconfirmed_resp.tx).unwrap()[..],
-                        )
-                        .unwrap();
-                        assert_eq!(tx.txid(), *last_txid.borrow());
                         true
                     }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3612,33 +9719,73 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_missing_getblock() {
+    fn test_rpc_get_data_var() {
         test_rpc(
-            "test_rpc_missing_getblock",
-            40060,
-            40061,
-            50060,
-            50061,
+            "test_rpc_get_data_var",
+            40232,
+            40233,
+            50232,
+            50233,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block_hash,
-                );
-
-                // now ask for it
-                convo_client.new_getblock(index_block_hash)
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(
+                        peer_server.sortdb.as_ref().unwrap().conn(),
+                    )
+                    .unwrap();
+                convo_client.new_getdatavar(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "bar".try_into().unwrap(),
+                    Some(StacksBlockHeader::make_index_block_hash(
+                        &consensus_hash,
+                        &block_hash,
+                    )),
+                    true,
+                )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::GetDataVar(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::Int(0)
+                        );
+
+                        // the proof checks out against the block's state root
+                        let block = load_canonical_stacks_block(peer_server);
+                        let contract_identifier = QualifiedContractIdentifier::new(
+                            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                                .unwrap()
+                                .into(),
+                            "hello-world".into(),
+                        );
+                        let key =
+                            ClarityDatabase::make_key_for_data_var(&contract_identifier, "bar");
+                        let proof = TrieMerkleProof::<StacksBlockId>::from_hex(
+                            &data.marf_proof.as_ref().unwrap()[2..],
+                        )
+                        .unwrap();
+                        assert!(proof.verify_entry(
+                            &key,
+                            &data.data[2..],
+                            &block.header.state_index_root,
+                            &HashMap::new()
+                        ));
+                        assert!(!proof.verify_entry(
+                            &key,
+                            &ClaritySerializable::serialize(&Value::Int(1)),
+                            &block.header.state_index_root,
+                            &HashMap::new()
+                        ));
+                        true
+                    }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3648,33 +9795,58 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_missing_index_getmicroblocks() {
+    fn test_rpc_get_map_entry_unconfirmed() {
         test_rpc(
-            "test_rpc_missing_index_getmicroblocks",
-            40070,
-            40071,
-            50070,
-            50071,
+            "test_rpc_get_map_entry_unconfirmed",
+            40140,
+            40141,
+            50140,
+            50141,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block_hash,
-                );
-
-                // now ask for it
-                convo_client.new_getmicroblocks_indexed(index_block_hash)
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                let principal =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal();
+                convo_client.new_getmapentry(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "unit-map".try_into().unwrap(),
+                    Value::Tuple(
+                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
+                            .unwrap(),
+                    ),
+                    Some(unconfirmed_tip),
+                    None,
+                    false,
+                )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::GetMapEntry(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::some(Value::Tuple(
+                                TupleData::from_data(vec![("units".into(), Value::Int(1))])
+                                    .unwrap()
+                            ))
+                            .unwrap()
+                        );
+                        true
+                    }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3684,33 +9856,33 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_missing_confirmed_getmicroblocks() {
+    fn test_rpc_get_contract_abi() {
         test_rpc(
-            "test_rpc_missing_confirmed_getmicroblocks",
-            40070,
-            40071,
-            50070,
-            50071,
+            "test_rpc_get_contract_abi",
+            40150,
+            40151,
+            50150,
+            50151,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_server_block_hash = BlockHeaderHash([0x04; 32]);
-                let peer_server_consensus_hash = ConsensusHash([0x02; 20]);
-                let index_block_hash = StacksBlockHeader::make_index_block_hash(
-                    &peer_server_consensus_hash,
-                    &peer_server_block_hash,
-                );
-
-                // now ask for it
-                convo_client.new_getmicroblocks_confirmed(index_block_hash)
+                convo_client.new_getcontractabi(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    None,
+                )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::NotFound(..) => {
+                        // not confirmed yet
+                        true
+                    }
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3720,52 +9892,37 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_missing_unconfirmed_microblocks() {
-        let server_microblocks_cell = RefCell::new(vec![]);
-
+    fn test_rpc_get_contract_abi_unconfirmed() {
         test_rpc(
-            "test_rpc_missing_unconfirmed_microblocks",
-            40080,
-            40081,
-            50080,
-            50081,
+            "test_rpc_get_contract_abi_unconfirmed",
+            40160,
+            40161,
+            50160,
+            50161,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let privk = StacksPrivateKey::from_hex(
-                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                convo_client.new_getcontractabi(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    Some(unconfirmed_tip),
                 )
-                .unwrap();
-
-                let consensus_hash = ConsensusHash([0x02; 20]);
-                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
-                let index_block_hash =
-                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
-
-                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
-                mblocks.truncate(15);
-
-                for mblock in mblocks.iter() {
-                    store_staging_microblock(
-                        peer_server.chainstate(),
-                        &consensus_hash,
-                        &anchored_block_hash,
-                        &mblock,
-                    );
-                }
-
-                *server_microblocks_cell.borrow_mut() = mblocks;
-
-                // start at seq 16 (which doesn't exist)
-                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 16)
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::NotFound(response_md, msg) => true,
+                    HttpResponseType::GetContractABI(response_md, data) => true,
                     _ => {
-                        error!("Invalid response: {:?}", &http_response);
+                        error!("Invalid response; {:?}", &http_response);
                         false
                     }
                 }
@@ -3775,30 +9932,39 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_src() {
+    fn test_rpc_call_read_only() {
         test_rpc(
-            "test_rpc_get_contract_src",
-            40090,
-            40091,
-            50090,
-            50091,
+            "test_rpc_call_read_only",
+            40170,
+            40171,
+            50170,
+            50171,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getcontractsrc(
+                convo_client.new_callreadonlyfunction(
                     StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
                         .unwrap(),
-                    "hello-world".try_into().unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    "ro-test".try_into().unwrap(),
+                    vec![],
                     None,
-                    false,
+                    None,
+                    RPCStateView::Confirmed,
                 )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetContractSrc(response_md, data) => {
-                        assert_eq!(data.source, TEST_CONTRACT);
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(data.cause.is_some());
+                        assert!(data.cause.clone().unwrap().find("NoSuchContract").is_some());
+                        assert!(!data.okay);
+                        assert!(data.result.is_none());
                         true
                     }
                     _ => {
@@ -3812,13 +9978,13 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_src_unconfirmed() {
+    fn test_rpc_call_read_only_unconfirmed() {
         test_rpc(
-            "test_rpc_get_contract_src_unconfirmed",
-            40100,
-            40101,
-            50100,
-            50101,
+            "test_rpc_call_read_only_unconfirmed",
+            40180,
+            40181,
+            50180,
+            50181,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
@@ -3830,19 +9996,31 @@ mod test {
                     .unwrap()
                     .unconfirmed_chain_tip
                     .clone();
-                convo_client.new_getcontractsrc(
+                convo_client.new_callreadonlyfunction(
                     StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
                         .unwrap(),
-                    "hello-world".try_into().unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    "ro-test".try_into().unwrap(),
+                    vec![],
                     Some(unconfirmed_tip),
-                    false,
+                    None,
+                    RPCStateView::Confirmed,
                 )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetContractSrc(response_md, data) => {
-                        assert_eq!(data.source, TEST_CONTRACT);
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(data.okay);
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
+                                .unwrap(),
+                            Value::okay(Value::Int(1)).unwrap()
+                        );
+                        assert!(data.cause.is_none());
                         true
                     }
                     _ => {
@@ -3856,32 +10034,41 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_account() {
+    fn test_rpc_call_read_only_unconfirmed_view() {
         test_rpc(
-            "test_rpc_get_account",
-            40110,
-            40111,
-            50110,
-            50111,
+            "test_rpc_call_read_only_unconfirmed_view",
+            40220,
+            40221,
+            50220,
+            50221,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getaccount(
+                convo_client.new_callreadonlyfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world-unconfirmed".try_into().unwrap(),
                     StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
                         .unwrap()
                         .to_account_principal(),
+                    "ro-test".try_into().unwrap(),
+                    vec![],
                     None,
-                    false,
+                    None,
+                    RPCStateView::Unconfirmed,
                 )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetAccount(response_md, data) => {
-                        assert_eq!(data.nonce, 2);
-                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
-                        assert_eq!(balance, 1000000000);
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(data.okay);
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
+                                .unwrap(),
+                            Value::okay(Value::Int(1)).unwrap()
+                        );
                         true
                     }
                     _ => {
@@ -3895,45 +10082,32 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_account_unconfirmed() {
+    fn test_rpc_getattachmentsinv_limit_reached() {
         test_rpc(
-            "test_rpc_get_account_unconfirmed",
-            40120,
-            40121,
-            50120,
-            50121,
+            "test_rpc_getattachmentsinv",
+            40000,
+            40001,
+            50000,
+            50001,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                convo_client.new_getaccount(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    Some(unconfirmed_tip),
-                    false,
-                )
+                let pages_indexes = HashSet::from_iter(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+                convo_client.new_getattachmentsinv(None, pages_indexes)
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
+                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::GetAccount(response_md, data) => {
-                        assert_eq!(data.nonce, 4);
-                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
-                        assert_eq!(balance, 1000000000 - 123);
+                    HttpResponseType::ServerError(_, msg) => {
+                        assert_eq!(
+                            msg,
+                            "Number of attachment inv pages is limited by 8 per request"
+                        );
                         true
                     }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+                    _ => false,
                 }
             },
         );
@@ -3941,46 +10115,119 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_map_entry() {
+    fn test_rpc_mempool_gc() {
         test_rpc(
-            "test_rpc_get_map_entry",
-            40130,
-            40131,
-            50130,
-            50131,
+            "test_rpc_mempool_gc",
+            40214,
+            40215,
+            50214,
+            50215,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let principal =
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal();
-                convo_client.new_getmapentry(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    "unit-map".try_into().unwrap(),
-                    Value::Tuple(
-                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
-                            .unwrap(),
-                    ),
-                    None,
-                    false,
+                let privk = StacksPrivateKey::from_hex(
+                    "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
                 )
+                .unwrap();
+
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(
+                        peer_server.sortdb.as_ref().unwrap().conn(),
+                    )
+                    .unwrap();
+
+                let mut tx = StacksTransaction::new(
+                    TransactionVersion::Testnet,
+                    TransactionAuth::from_p2pkh(&privk).unwrap(),
+                    TransactionPayload::TokenTransfer(
+                        StacksAddress::from_string("STVN97YYA10MY5F6KQJHKNYJNM24C4A1AT39WRW")
+                            .unwrap()
+                            .to_account_principal(),
+                        123,
+                        TokenTransferMemo([0u8; 34]),
+                        None,
+                    ),
+                );
+                tx.chain_id = 0x80000000;
+                tx.auth.set_origin_nonce(2);
+                tx.set_fee_rate(1000);
+
+                let mut tx_signer = StacksTransactionSigner::new(&tx);
+                tx_signer.sign_origin(&privk).unwrap();
+                let tx_signed = tx_signer.get_tx().unwrap();
+
+                // the mempool may hold no transactions at all
+                let mut mempool = peer_server.mempool.take().unwrap();
+                mempool
+                    .submit_raw(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &block_hash,
+                        tx_signed.serialize_to_vec(),
+                    )
+                    .unwrap();
+                mempool.set_gc_policy(MemPoolGCPolicy {
+                    max_size: Some(0),
+                    ..MemPoolGCPolicy::default()
+                });
+                peer_server.mempool = Some(mempool);
+
+                convo_client.new_mempool_gc()
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                println!("{:?}", http_response);
+                match http_response {
+                    HttpResponseType::MemPoolGC(_, response) => {
+                        assert_eq!(response.expired, 0);
+                        assert_eq!(response.evicted, 1);
+                        assert_eq!(response.remaining_txs, 0);
+                        assert_eq!(response.remaining_bytes, 0);
+                        true
+                    }
+                    _ => false,
+                }
             },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_dev_mine() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let requests_sink = requests.clone();
+        let dev_mine: DevMineCallback = Arc::new(move |request: RPCDevMineRequest| {
+            requests_sink.lock().unwrap().push(request);
+        });
+        let handler_args = RPCHandlerArgs {
+            dev_mine: Some(dev_mine),
+            ..RPCHandlerArgs::default()
+        };
+        let request = RPCDevMineRequest {
+            txids: vec![],
+            sortition: Some(false),
+        };
+        test_rpc_with_handler_args(
+            "test_rpc_dev_mine",
+            40280,
+            40281,
+            50280,
+            50281,
+            &handler_args,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| { convo_client.new_dev_mine(request.clone()) },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetMapEntry(response_md, data) => {
-                        assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::some(Value::Tuple(
-                                TupleData::from_data(vec![("units".into(), Value::Int(123))])
-                                    .unwrap()
-                            ))
-                            .unwrap()
-                        );
+                    HttpResponseType::DevMine(_, ref response) => {
+                        let burn_tip = SortitionDB::get_canonical_burn_chain_tip(
+                            peer_server.sortdb.as_ref().unwrap().conn(),
+                        )
+                        .unwrap();
+                        assert_eq!(response.burn_block_height, burn_tip.block_height);
+                        assert_eq!(*requests.lock().unwrap(), vec![request.clone()]);
                         true
                     }
                     _ => {
@@ -3994,55 +10241,33 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_map_entry_unconfirmed() {
-        test_rpc(
-            "test_rpc_get_map_entry_unconfirmed",
-            40140,
-            40141,
-            50140,
-            50141,
+    fn test_rpc_dev_mine_missing_tx() {
+        let dev_mine: DevMineCallback = Arc::new(|_request: RPCDevMineRequest| {
+            panic!("BUG: mined a block for a missing transaction");
+        });
+        let handler_args = RPCHandlerArgs {
+            dev_mine: Some(dev_mine),
+            ..RPCHandlerArgs::default()
+        };
+        test_rpc_with_handler_args(
+            "test_rpc_dev_mine_missing_tx",
+            40282,
+            40283,
+            50282,
+            50283,
+            &handler_args,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                let principal =
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal();
-                convo_client.new_getmapentry(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world".try_into().unwrap(),
-                    "unit-map".try_into().unwrap(),
-                    Value::Tuple(
-                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
-                            .unwrap(),
-                    ),
-                    Some(unconfirmed_tip),
-                    false,
-                )
+                convo_client.new_dev_mine(RPCDevMineRequest {
+                    txids: vec![format!("{}", Txid([0x11; 32]))],
+                    sortition: None,
+                })
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetMapEntry(response_md, data) => {
-                        assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::some(Value::Tuple(
-                                TupleData::from_data(vec![("units".into(), Value::Int(1))])
-                                    .unwrap()
-                            ))
-                            .unwrap()
-                        );
-                        true
-                    }
+                    HttpResponseType::NotFound(..) => true,
                     _ => {
                         error!("Invalid response; {:?}", &http_response);
                         false
@@ -4054,35 +10279,105 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_abi() {
+    fn test_rpc_simulate_block() {
+        let privk = StacksPrivateKey::from_hex(
+            "94c319327cc5cd04da7147d32d836eb2e4c44f4db39aa5ede7314a761183d0c701",
+        )
+        .unwrap();
+
+        let mut tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk).unwrap(),
+            TransactionPayload::TokenTransfer(
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                    .unwrap()
+                    .to_account_principal(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+                None,
+            ),
+        );
+        tx.chain_id = 0x80000000;
+        tx.auth.set_origin_nonce(0);
+        tx.set_fee_rate(1000);
+
+        let mut tx_signer = StacksTransactionSigner::new(&tx);
+        tx_signer.sign_origin(&privk).unwrap();
+        let tx_signed = tx_signer.get_tx().unwrap();
+        let expected_txid = tx_signed.txid();
+        let expected_len = tx_signed.tx_len();
+
         test_rpc(
-            "test_rpc_get_contract_abi",
-            40150,
-            40151,
-            50150,
-            50151,
+            "test_rpc_simulate_block",
+            40236,
+            40237,
+            50236,
+            50237,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getcontractabi(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    None,
-                )
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(
+                        peer_server.sortdb.as_ref().unwrap().conn(),
+                    )
+                    .unwrap();
+
+                let mut mempool = peer_server.mempool.take().unwrap();
+                mempool
+                    .submit_raw(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &block_hash,
+                        tx_signed.serialize_to_vec(),
+                    )
+                    .unwrap();
+                peer_server.mempool = Some(mempool);
+
+                convo_client.new_simulate_block()
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
+                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::NotFound(..) => {
-                        // not confirmed yet
+                    HttpResponseType::BlockSimulation(_, response) => {
+                        let (consensus_hash, block_hash) =
+                            SortitionDB::get_canonical_stacks_chain_tip_hash(
+                                peer_server.sortdb.as_ref().unwrap().conn(),
+                            )
+                            .unwrap();
+                        assert_eq!(
+                            response.parent_index_block_hash,
+                            format!(
+                                "{}",
+                                StacksBlockHeader::make_index_block_hash(
+                                    &consensus_hash,
+                                    &block_hash
+                                )
+                            )
+                        );
+                        assert_eq!(response.stacks_height, 2);
+                        assert_eq!(response.txs.len(), 1);
+                        assert_eq!(response.txs[0].txid, format!("{}", &expected_txid));
+                        assert_eq!(response.txs[0].fee, 1000);
+                        assert_eq!(response.txs[0].tx_len, expected_len);
+                        assert_eq!(response.total_fees, 1000);
+                        assert!(response.block_size > expected_len);
+                        assert_eq!(
+                            response.percent_full,
+                            response
+                                .execution_cost
+                                .proportion_of_limit(&response.execution_budget)
+                        );
+
+                        // the simulation leaves the mempool alone
+                        let mempool = peer_server.mempool.as_ref().unwrap();
+                        assert!(MemPoolDB::get_tx(mempool.conn(), &expected_txid)
+                            .unwrap()
+                            .is_some());
                         true
                     }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+                    _ => false,
                 }
             },
         );
@@ -4090,83 +10385,177 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_contract_abi_unconfirmed() {
+    fn test_rpc_get_attachment_instances() {
         test_rpc(
-            "test_rpc_get_contract_abi_unconfirmed",
-            40160,
-            40161,
-            50160,
-            50161,
+            "test_rpc_get_attachment_instances",
+            40212,
+            40213,
+            50212,
+            50213,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
+                let sortdb = peer_server.sortdb.take().unwrap();
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn()).unwrap();
+                let block_height = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())
                     .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                convo_client.new_getcontractabi(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                    .canonical_stacks_tip_height;
+                peer_server.sortdb = Some(sortdb);
+
+                let bns = QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.bns")
+                    .unwrap();
+                let other = QualifiedContractIdentifier::parse(
+                    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.other",
+                )
+                .unwrap();
+                let namespace_metadata = |namespace: &str| {
+                    let metadata = Value::Tuple(
+                        TupleData::from_data(vec![(
+                            "namespace".into(),
+                            Value::buff_from(namespace.as_bytes().to_vec()).unwrap(),
+                        )])
                         .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    Some(unconfirmed_tip),
+                    );
+                    ClaritySerializable::serialize(&metadata)
+                };
+
+                let instances = vec![
+                    // on the canonical fork, in the requested contract and namespace
+                    (
+                        0,
+                        bns.clone(),
+                        namespace_metadata("id"),
+                        consensus_hash.clone(),
+                    ),
+                    // wrong namespace
+                    (
+                        1,
+                        bns.clone(),
+                        namespace_metadata("btc"),
+                        consensus_hash.clone(),
+                    ),
+                    // wrong contract
+                    (2, other, namespace_metadata("id"), consensus_hash.clone()),
+                    // not on the canonical fork
+                    (
+                        3,
+                        bns.clone(),
+                        namespace_metadata("id"),
+                        ConsensusHash([0xaa; 20]),
+                    ),
+                    // on a later page
+                    (
+                        9,
+                        bns.clone(),
+                        namespace_metadata("id"),
+                        consensus_hash.clone(),
+                    ),
+                ];
+                for (attachment_index, contract_id, metadata, ch) in instances.into_iter() {
+                    let instance = AttachmentInstance {
+                        content_hash: Hash160([attachment_index as u8; 20]),
+                        attachment_index,
+                        block_height,
+                        consensus_hash: ch,
+                        block_header_hash: block_hash.clone(),
+                        metadata,
+                        contract_id,
+                    };
+                    peer_server
+                        .network
+                        .atlasdb
+                        .insert_new_attachment_instance(&instance, attachment_index == 0)
+                        .unwrap();
+                }
+
+                convo_client.new_getattachmentinstances(
+                    None,
+                    HashSet::from_iter(vec![0]),
+                    Some(bns),
+                    Some("id".to_string()),
                 )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
+                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::GetContractABI(response_md, data) => true,
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
+                    HttpResponseType::GetAttachmentInstances(_, response) => {
+                        assert_eq!(response.pages_count, 2);
+                        assert_eq!(response.pages.len(), 1);
+                        assert_eq!(response.pages[0].index, 0);
+                        assert_eq!(response.pages[0].instances.len(), 1);
+
+                        let entry = &response.pages[0].instances[0];
+                        assert_eq!(entry.attachment_index, 0);
+                        assert_eq!(entry.content_hash, to_hex(&[0u8; 20]));
+                        assert_eq!(entry.contract_id, "ST000000000000000000002AMW42H.bns");
+                        assert_eq!(entry.index_block_hash, response.block_id);
+                        assert!(entry.is_available);
+                        true
                     }
+                    _ => false,
                 }
             },
         );
     }
 
+    fn make_test_stackerdb_chunk(
+        peer_server: &mut TestPeer,
+        contract_id: &QualifiedContractIdentifier,
+    ) -> StackerDBChunkData {
+        let privk = StacksPrivateKey::new();
+        let signer = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+        let config = StackerDBConfig {
+            chunk_size: 16,
+            signers: vec![(signer, 2)],
+        };
+        let mut chunk = StackerDBChunkData::new(1, 3, vec![1, 2, 3]);
+        chunk.sign(&privk).unwrap();
+        peer_server
+            .network
+            .stackerdbs
+            .try_put_chunk(contract_id, &config, &chunk)
+            .unwrap();
+        chunk
+    }
+
     #[test]
     #[ignore]
-    fn test_rpc_call_read_only() {
+    fn test_rpc_get_stackerdb_chunk() {
+        let contract_id =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.signers")
+                .unwrap();
+        let expected_chunk = RefCell::new(None);
         test_rpc(
-            "test_rpc_call_read_only",
-            40170,
-            40171,
-            50170,
-            50171,
+            "test_rpc_get_stackerdb_chunk",
+            40268,
+            40269,
+            50268,
+            50269,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_callreadonlyfunction(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    "ro-test".try_into().unwrap(),
-                    vec![],
-                    None,
-                )
+                convo_server.connection.options.stackerdb_contracts = vec![contract_id.clone()];
+                *expected_chunk.borrow_mut() =
+                    Some(make_test_stackerdb_chunk(peer_server, &contract_id));
+                convo_client.new_get_stackerdb_chunk(contract_id.clone(), 1)
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
-                        assert!(data.cause.is_some());
-                        assert!(data.cause.clone().unwrap().find("NoSuchContract").is_some());
-                        assert!(!data.okay);
-                        assert!(data.result.is_none());
+                    HttpResponseType::StackerDBChunk(_, chunk) => {
+                        assert_eq!(Some(chunk), expected_chunk.borrow().as_ref());
                         true
                     }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+                    _ => false,
                 }
             },
         );
@@ -4174,53 +10563,34 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_call_read_only_unconfirmed() {
+    fn test_rpc_get_stackerdb_metadata() {
+        let contract_id =
+            QualifiedContractIdentifier::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.signers")
+                .unwrap();
+        let expected_chunk = RefCell::new(None);
         test_rpc(
-            "test_rpc_call_read_only_unconfirmed",
-            40180,
-            40181,
-            50180,
-            50181,
+            "test_rpc_get_stackerdb_metadata",
+            40270,
+            40271,
+            50270,
+            50271,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                convo_client.new_callreadonlyfunction(
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap(),
-                    "hello-world-unconfirmed".try_into().unwrap(),
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
-                    "ro-test".try_into().unwrap(),
-                    vec![],
-                    Some(unconfirmed_tip),
-                )
+                convo_server.connection.options.stackerdb_contracts = vec![contract_id.clone()];
+                *expected_chunk.borrow_mut() =
+                    Some(make_test_stackerdb_chunk(peer_server, &contract_id));
+                convo_client.new_get_stackerdb_metadata(contract_id.clone())
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
-                        assert!(data.okay);
-                        assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
-                                .unwrap(),
-                            Value::okay(Value::Int(1)).unwrap()
-                        );
-                        assert!(data.cause.is_none());
+                    HttpResponseType::StackerDBMetadata(_, slots) => {
+                        let chunk = expected_chunk.borrow().clone().unwrap();
+                        assert_eq!(slots, &vec![chunk.get_slot_metadata()]);
                         true
                     }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+                    _ => false,
                 }
             },
         );
@@ -4228,31 +10598,27 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getattachmentsinv_limit_reached() {
+    fn test_rpc_get_stackerdb_not_replicated() {
         test_rpc(
-            "test_rpc_getattachmentsinv",
-            40000,
-            40001,
-            50000,
-            50001,
+            "test_rpc_get_stackerdb_not_replicated",
+            40272,
+            40273,
+            50272,
+            50273,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let pages_indexes = HashSet::from_iter(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
-                convo_client.new_getattachmentsinv(None, pages_indexes)
+                let contract_id = QualifiedContractIdentifier::parse(
+                    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.signers",
+                )
+                .unwrap();
+                make_test_stackerdb_chunk(peer_server, &contract_id);
+                convo_client.new_get_stackerdb_chunk(contract_id, 1)
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
-                let req_md = http_request.metadata().clone();
-                println!("{:?}", http_response);
                 match http_response {
-                    HttpResponseType::ServerError(_, msg) => {
-                        assert_eq!(
-                            msg,
-                            "Number of attachment inv pages is limited by 8 per request"
-                        );
-                        true
-                    }
+                    HttpResponseType::NotFound(..) => true,
                     _ => false,
                 }
             },
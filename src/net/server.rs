@@ -38,6 +38,7 @@ use net::http::*;
 use net::p2p::PeerMap;
 use net::poll::*;
 use net::rpc::*;
+use net::stackerdb::StackerDBs;
 use net::Error as net_error;
 use net::*;
 
@@ -81,6 +82,9 @@ pub struct HttpPeer {
 
     // connection options
     pub connection_opts: ConnectionOptions,
+
+    // RPC clients' request counts
+    pub rate_limiter: HttpRateLimiter,
 }
 
 impl HttpPeer {
@@ -102,6 +106,7 @@ impl HttpPeer {
 
             burnchain: burnchain,
             connection_opts: conn_opts,
+            rate_limiter: HttpRateLimiter::new(),
         }
     }
 
@@ -435,12 +440,14 @@ impl HttpPeer {
         sortdb: &SortitionDB,
         peerdb: &PeerDB,
         atlasdb: &mut AtlasDB,
+        stackerdbs: &mut StackerDBs,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
         event_id: usize,
         client_sock: &mut mio_net::TcpStream,
         convo: &mut ConversationHttp,
         handler_args: &RPCHandlerArgs,
+        rate_limiter: &mut HttpRateLimiter,
     ) -> Result<(bool, Vec<StacksMessageType>), net_error> {
         // get incoming bytes and update the state of this conversation.
         let mut convo_dead = false;
@@ -510,9 +517,11 @@ impl HttpPeer {
             sortdb,
             peerdb,
             atlasdb,
+            stackerdbs,
             chainstate,
             mempool,
             handler_args,
+            rate_limiter,
         ) {
             Ok(msgs) => msgs,
             Err(e) => {
@@ -589,6 +598,7 @@ impl HttpPeer {
         sortdb: &SortitionDB,
         peerdb: &PeerDB,
         atlasdb: &mut AtlasDB,
+        stackerdbs: &mut StackerDBs,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
         handler_args: &RPCHandlerArgs,
@@ -620,12 +630,14 @@ impl HttpPeer {
                         sortdb,
                         peerdb,
                         atlasdb,
+                        stackerdbs,
                         chainstate,
                         mempool,
                         *event_id,
                         client_sock,
                         convo,
                         handler_args,
+                        &mut self.rate_limiter,
                     ) {
                         Ok((alive, mut new_msgs)) => {
                             if !alive {
@@ -688,6 +700,7 @@ impl HttpPeer {
         sortdb: &SortitionDB,
         peerdb: &PeerDB,
         atlasdb: &mut AtlasDB,
+        stackerdbs: &mut StackerDBs,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
         mut poll_state: NetworkPollState,
@@ -709,6 +722,7 @@ impl HttpPeer {
             sortdb,
             peerdb,
             atlasdb,
+            stackerdbs,
             chainstate,
             mempool,
             handler_args,
@@ -0,0 +1,269 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+use rusqlite::Transaction;
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use net::Error as net_error;
+
+use util::db::tx_begin_immediate;
+use util::db::Error as db_error;
+use util::db::{query_row, query_rows, u64_to_sql, FromColumn, FromRow};
+use util::get_epoch_time_secs;
+use util::hash::Sha512Trunc256Sum;
+use util::secp256k1::MessageSignature;
+
+use vm::types::QualifiedContractIdentifier;
+
+use super::{SlotMetadata, StackerDBChunkData, StackerDBConfig};
+
+pub const STACKERDB_VERSION: &'static str = "1";
+
+const STACKERDB_SETUP: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE chunks(
+        contract_id TEXT NOT NULL,
+        slot_id INTEGER NOT NULL,
+        slot_version INTEGER NOT NULL,
+        data_hash TEXT NOT NULL,
+        signature TEXT NOT NULL,
+        data BLOB NOT NULL,
+        write_time INTEGER NOT NULL,
+        PRIMARY KEY(contract_id, slot_id)
+    );"#,
+    r#"
+    CREATE TABLE db_version(version TEXT NOT NULL);
+    "#,
+];
+
+impl FromRow<SlotMetadata> for SlotMetadata {
+    fn from_row<'a>(row: &'a Row) -> Result<SlotMetadata, db_error> {
+        let slot_id: u32 = row.get("slot_id");
+        let slot_version: u32 = row.get("slot_version");
+        let data_hash_hex: String = row.get("data_hash");
+        let data_hash =
+            Sha512Trunc256Sum::from_hex(&data_hash_hex).map_err(|_| db_error::TypeError)?;
+        let signature = MessageSignature::from_column(row, "signature")?;
+        Ok(SlotMetadata {
+            slot_id,
+            slot_version,
+            data_hash,
+            signature,
+        })
+    }
+}
+
+impl FromRow<StackerDBChunkData> for StackerDBChunkData {
+    fn from_row<'a>(row: &'a Row) -> Result<StackerDBChunkData, db_error> {
+        let slot_id: u32 = row.get("slot_id");
+        let slot_version: u32 = row.get("slot_version");
+        let sig = MessageSignature::from_column(row, "signature")?;
+        let data: Vec<u8> = row.get("data");
+        Ok(StackerDBChunkData {
+            slot_id,
+            slot_version,
+            sig,
+            data,
+        })
+    }
+}
+
+/// The chunks of every StackerDB this node replicates
+#[derive(Debug)]
+pub struct StackerDBs {
+    pub conn: Connection,
+    pub readwrite: bool,
+}
+
+impl StackerDBs {
+    fn instantiate(&mut self) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+
+        for row_text in STACKERDB_SETUP {
+            tx.execute(row_text, NO_PARAMS)
+                .map_err(db_error::SqliteError)?;
+        }
+
+        tx.execute(
+            "INSERT INTO db_version (version) VALUES (?1)",
+            &[&STACKERDB_VERSION],
+        )
+        .map_err(db_error::SqliteError)?;
+
+        tx.commit().map_err(db_error::SqliteError)?;
+
+        Ok(())
+    }
+
+    // Open the StackerDB database at the given path.  Open read-only or read/write.
+    // If opened for read/write and it doesn't exist, instantiate it.
+    pub fn connect(path: &String, readwrite: bool) -> Result<StackerDBs, db_error> {
+        let mut create_flag = false;
+        let open_flags = if fs::metadata(path).is_err() {
+            // need to create
+            if readwrite {
+                create_flag = true;
+                OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+            } else {
+                return Err(db_error::NoDBError);
+            }
+        } else {
+            // can just open
+            if readwrite {
+                OpenFlags::SQLITE_OPEN_READ_WRITE
+            } else {
+                OpenFlags::SQLITE_OPEN_READ_ONLY
+            }
+        };
+        let conn =
+            Connection::open_with_flags(path, open_flags).map_err(|e| db_error::SqliteError(e))?;
+
+        let mut db = StackerDBs {
+            conn: conn,
+            readwrite: readwrite,
+        };
+        if create_flag {
+            db.instantiate()?;
+        }
+        Ok(db)
+    }
+
+    // Open a StackerDB database in memory (used for testing)
+    #[cfg(test)]
+    pub fn connect_memory() -> Result<StackerDBs, db_error> {
+        let conn = Connection::open_in_memory().map_err(|e| db_error::SqliteError(e))?;
+
+        let mut db = StackerDBs {
+            conn: conn,
+            readwrite: true,
+        };
+
+        db.instantiate()?;
+        Ok(db)
+    }
+
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn tx_begin<'a>(&'a mut self) -> Result<Transaction<'a>, db_error> {
+        if !self.readwrite {
+            return Err(db_error::ReadOnly);
+        }
+
+        let tx = tx_begin_immediate(&mut self.conn)?;
+        Ok(tx)
+    }
+
+    /// Get the metadata of every non-empty slot in a StackerDB, ordered by slot ID
+    pub fn get_slots_metadata(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+    ) -> Result<Vec<SlotMetadata>, db_error> {
+        let qry = "SELECT slot_id, slot_version, data_hash, signature FROM chunks WHERE contract_id = ?1 ORDER BY slot_id ASC";
+        let args: &[&dyn ToSql] = &[&contract_id.to_string()];
+        query_rows(&self.conn, qry, args)
+    }
+
+    /// Get the metadata of a slot, if it holds a chunk
+    pub fn get_slot_metadata(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+        slot_id: u32,
+    ) -> Result<Option<SlotMetadata>, db_error> {
+        let qry = "SELECT slot_id, slot_version, data_hash, signature FROM chunks WHERE contract_id = ?1 AND slot_id = ?2";
+        let args: &[&dyn ToSql] = &[&contract_id.to_string(), &slot_id];
+        query_row(&self.conn, qry, args)
+    }
+
+    /// Get the chunk a slot holds, if any
+    pub fn get_chunk(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+        slot_id: u32,
+    ) -> Result<Option<StackerDBChunkData>, db_error> {
+        let qry = "SELECT slot_id, slot_version, signature, data FROM chunks WHERE contract_id = ?1 AND slot_id = ?2";
+        let args: &[&dyn ToSql] = &[&contract_id.to_string(), &slot_id];
+        query_row(&self.conn, qry, args)
+    }
+
+    /// Store a chunk in its slot, if the StackerDB's config allows it: the slot must exist, the
+    /// chunk must fit, it must be signed by the slot's signer, and its version must be higher
+    /// than the version already stored.  Returns `StackerDBChunkRejected` otherwise.
+    pub fn try_put_chunk(
+        &mut self,
+        contract_id: &QualifiedContractIdentifier,
+        config: &StackerDBConfig,
+        chunk: &StackerDBChunkData,
+    ) -> Result<(), net_error> {
+        let signer = config.slot_signer(chunk.slot_id).ok_or_else(|| {
+            net_error::StackerDBChunkRejected(format!(
+                "no such slot {} (the StackerDB has {} slots)",
+                chunk.slot_id,
+                config.num_slots()
+            ))
+        })?;
+
+        if (chunk.data.len() as u64) > config.chunk_size {
+            return Err(net_error::StackerDBChunkRejected(format!(
+                "chunk is {} bytes, but the maximum is {}",
+                chunk.data.len(),
+                config.chunk_size
+            )));
+        }
+
+        if !chunk.verify(signer) {
+            return Err(net_error::StackerDBChunkRejected(format!(
+                "chunk for slot {} is not signed by {}",
+                chunk.slot_id, signer
+            )));
+        }
+
+        let tx = self.tx_begin()?;
+        let qry = "SELECT slot_version FROM chunks WHERE contract_id = ?1 AND slot_id = ?2";
+        let args: &[&dyn ToSql] = &[&contract_id.to_string(), &chunk.slot_id];
+        let cur_version: Option<u64> = query_row(&tx, qry, args)?;
+        if let Some(cur_version) = cur_version {
+            if (chunk.slot_version as u64) <= cur_version {
+                return Err(net_error::StackerDBChunkRejected(format!(
+                    "slot {} is already at version {}",
+                    chunk.slot_id, cur_version
+                )));
+            }
+        }
+
+        let args: &[&dyn ToSql] = &[
+            &contract_id.to_string(),
+            &chunk.slot_id,
+            &chunk.slot_version,
+            &chunk.data_hash().to_hex(),
+            &chunk.sig.to_hex(),
+            &chunk.data,
+            &u64_to_sql(get_epoch_time_secs())?,
+        ];
+        tx.execute(
+            "INSERT OR REPLACE INTO chunks (contract_id, slot_id, slot_version, data_hash, signature, data, write_time) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+}
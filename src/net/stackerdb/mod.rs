@@ -0,0 +1,376 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! StackerDB: replicated, signed key-value stores for off-chain coordination.
+//!
+//! A StackerDB is a fixed-size array of slots whose layout is controlled by a smart contract.
+//! The contract decides who may write to each slot and how big a slot's chunk may be, by
+//! implementing two read-only functions:
+//!
+//! * `(stackerdb-get-signer-slots)` returns `(ok (list { signer: principal, num-slots: uint }))`.
+//!   Slots are handed out in list order, so the first signer owns slots `0..num-slots`, the second
+//!   owns the next `num-slots` slots, and so on.
+//! * `(stackerdb-get-config)` returns `(ok { chunk-size: uint })`, the largest chunk any slot can
+//!   hold.
+//!
+//! Each slot holds a single chunk of opaque data, signed by the slot's signer over its slot ID,
+//! its version, and the hash of its data.  A chunk replaces the slot's current chunk only if its
+//! version is higher, so the store converges no matter what order nodes see writes in.  Nodes
+//! replicate the StackerDBs listed in their config by pushing newly-accepted chunks to their
+//! neighbors, and expose them over RPC.
+
+pub mod db;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::db::StackerDBs;
+
+use chainstate::burn::db::sortdb::SortitionDB;
+use chainstate::stacks::db::StacksChainState;
+use chainstate::stacks::{StacksAddress, StacksBlockId, StacksPrivateKey, StacksPublicKey};
+
+use net::relay::RelayPayload;
+use net::Error as net_error;
+use net::StacksMessageCodec;
+
+use util::hash::{hex_bytes, to_hex, Hash160, Sha512Trunc256Sum};
+use util::secp256k1::MessageSignature;
+
+use vm::clarity::{ClarityConnection, Error as clarity_error};
+use vm::costs::LimitedCostTracker;
+use vm::types::{PrincipalData, QualifiedContractIdentifier, SequenceData, Value};
+
+use burnchains::PrivateKey;
+
+use serde::de::Error as de_Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Largest chunk any StackerDB slot may hold, whatever its contract says
+pub const STACKERDB_MAX_CHUNK_SIZE: u32 = 1024 * 1024;
+/// Most slots a single StackerDB may have
+pub const STACKERDB_MAX_SLOTS: u32 = 4096;
+
+/// Read-only function a StackerDB contract implements to list its signers and their slots
+pub const STACKERDB_SLOTS_FUNCTION: &'static str = "stackerdb-get-signer-slots";
+/// Read-only function a StackerDB contract implements to describe its chunks
+pub const STACKERDB_CONFIG_FUNCTION: &'static str = "stackerdb-get-config";
+
+/// The slot layout of a StackerDB, as reported by its contract
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackerDBConfig {
+    /// Largest chunk a slot may hold
+    pub chunk_size: u64,
+    /// Each signer and how many consecutive slots it owns, in slot order
+    pub signers: Vec<(StacksAddress, u32)>,
+}
+
+/// Everything about a slot's chunk except its data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlotMetadata {
+    pub slot_id: u32,
+    pub slot_version: u32,
+    pub data_hash: Sha512Trunc256Sum,
+    pub signature: MessageSignature,
+}
+
+/// A chunk of data to store in a StackerDB slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StackerDBChunkData {
+    pub slot_id: u32,
+    pub slot_version: u32,
+    pub sig: MessageSignature,
+    #[serde(
+        serialize_with = "stackerdb_chunk_data_serialize",
+        deserialize_with = "stackerdb_chunk_data_deserialize"
+    )]
+    pub data: Vec<u8>,
+}
+
+/// A chunk pushed to a neighbor, along with the StackerDB it belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackerDBPushChunkData {
+    pub contract_id: QualifiedContractIdentifier,
+    pub chunk_data: StackerDBChunkData,
+}
+
+/// The outcome of uploading a chunk over RPC
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StackerDBChunkAckData {
+    pub accepted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The slot's metadata after the upload was considered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<SlotMetadata>,
+}
+
+fn stackerdb_chunk_data_serialize<S: Serializer>(data: &Vec<u8>, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&to_hex(data))
+}
+
+fn stackerdb_chunk_data_deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+    let hex_str = String::deserialize(d)?;
+    hex_bytes(&hex_str).map_err(de_Error::custom)
+}
+
+impl SlotMetadata {
+    /// The digest a slot's signer signs: sha512/256(slot_id || slot_version || data_hash)
+    pub fn auth_digest(&self) -> Sha512Trunc256Sum {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&self.slot_id.to_be_bytes());
+        bytes.extend_from_slice(&self.slot_version.to_be_bytes());
+        bytes.extend_from_slice(self.data_hash.as_bytes());
+        Sha512Trunc256Sum::from_data(&bytes)
+    }
+
+    /// Was this metadata signed by the given address?
+    pub fn verify(&self, addr: &StacksAddress) -> bool {
+        match StacksPublicKey::recover_to_pubkey(self.auth_digest().as_bytes(), &self.signature) {
+            Ok(pubkey) => Hash160::from_node_public_key(&pubkey) == addr.bytes,
+            Err(_) => false,
+        }
+    }
+}
+
+impl StackerDBChunkData {
+    pub fn new(slot_id: u32, slot_version: u32, data: Vec<u8>) -> StackerDBChunkData {
+        StackerDBChunkData {
+            slot_id,
+            slot_version,
+            sig: MessageSignature::empty(),
+            data,
+        }
+    }
+
+    pub fn data_hash(&self) -> Sha512Trunc256Sum {
+        Sha512Trunc256Sum::from_data(&self.data)
+    }
+
+    pub fn get_slot_metadata(&self) -> SlotMetadata {
+        SlotMetadata {
+            slot_id: self.slot_id,
+            slot_version: self.slot_version,
+            data_hash: self.data_hash(),
+            signature: self.sig.clone(),
+        }
+    }
+
+    pub fn sign(&mut self, privk: &StacksPrivateKey) -> Result<(), net_error> {
+        let digest = self.get_slot_metadata().auth_digest();
+        self.sig = privk
+            .sign(digest.as_bytes())
+            .map_err(|se| net_error::SigningError(se.to_string()))?;
+        Ok(())
+    }
+
+    /// Was this chunk signed by the given address?
+    pub fn verify(&self, addr: &StacksAddress) -> bool {
+        self.get_slot_metadata().verify(addr)
+    }
+}
+
+impl RelayPayload for StackerDBPushChunkData {
+    fn get_digest(&self) -> Sha512Trunc256Sum {
+        let mut bytes = vec![];
+        self.consensus_serialize(&mut bytes)
+            .expect("BUG: failed to serialize");
+        Sha512Trunc256Sum::from_data(&bytes)
+    }
+    fn get_id(&self) -> String {
+        format!(
+            "StackerDBPushChunk({},{},{})",
+            &self.contract_id, self.chunk_data.slot_id, self.chunk_data.slot_version
+        )
+    }
+}
+
+impl StackerDBConfig {
+    pub fn num_slots(&self) -> u32 {
+        self.signers
+            .iter()
+            .fold(0, |total, (_, num_slots)| total + *num_slots)
+    }
+
+    /// Who may write to the given slot?
+    pub fn slot_signer(&self, slot_id: u32) -> Option<&StacksAddress> {
+        let mut first_slot = 0;
+        for (signer, num_slots) in self.signers.iter() {
+            if slot_id < first_slot + *num_slots {
+                return Some(signer);
+            }
+            first_slot += *num_slots;
+        }
+        None
+    }
+
+    /// Load a StackerDB's slot layout from its contract, as of the given chain tip.
+    /// Fails with `NotFoundError` if the contract does not exist or does not implement the
+    /// StackerDB functions.
+    pub fn from_contract(
+        chainstate: &mut StacksChainState,
+        sortdb: &SortitionDB,
+        tip: &StacksBlockId,
+        contract_id: &QualifiedContractIdentifier,
+    ) -> Result<StackerDBConfig, net_error> {
+        let res = chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+            let signers_value =
+                StackerDBConfig::call_getter(clarity_tx, contract_id, STACKERDB_SLOTS_FUNCTION)?;
+            let config_value =
+                StackerDBConfig::call_getter(clarity_tx, contract_id, STACKERDB_CONFIG_FUNCTION)?;
+            Ok((signers_value, config_value))
+        });
+
+        let (signers_value, config_value) = match res {
+            Some(Ok(values)) => values,
+            Some(Err(e)) => {
+                return Err(e);
+            }
+            None => {
+                return Err(net_error::NotFoundError);
+            }
+        };
+
+        let signers = StackerDBConfig::parse_signer_slots(contract_id, signers_value)?;
+        let chunk_size = StackerDBConfig::parse_chunk_size(contract_id, config_value)?;
+        Ok(StackerDBConfig {
+            chunk_size,
+            signers,
+        })
+    }
+
+    fn call_getter<C: ClarityConnection>(
+        conn: &mut C,
+        contract_id: &QualifiedContractIdentifier,
+        function: &str,
+    ) -> Result<Value, net_error> {
+        let defined = conn.with_clarity_db_readonly(|clarity_db| {
+            clarity_db
+                .get_contract(contract_id)
+                .map(|contract| {
+                    contract
+                        .contract_context
+                        .lookup_function(function)
+                        .is_some()
+                })
+                .unwrap_or(false)
+        });
+        if !defined {
+            return Err(net_error::NotFoundError);
+        }
+
+        let sender = PrincipalData::Contract(contract_id.clone());
+        conn.with_readonly_clarity_env(sender, LimitedCostTracker::new_free(), |env| {
+            env.execute_contract(contract_id, function, &[], true)
+        })
+        .map_err(|e| net_error::ClarityError(clarity_error::Interpreter(e)))
+    }
+
+    fn parse_signer_slots(
+        contract_id: &QualifiedContractIdentifier,
+        value: Value,
+    ) -> Result<Vec<(StacksAddress, u32)>, net_error> {
+        let bad_value = net_error::ChainstateError(format!(
+            "{}::{} returned {}, expected (ok (list {{ signer: principal, num-slots: uint }}))",
+            contract_id, STACKERDB_SLOTS_FUNCTION, &value
+        ));
+
+        let entries = match value {
+            Value::Response(data) if data.committed => match *data.data {
+                Value::Sequence(SequenceData::List(list)) => list.data,
+                _ => {
+                    return Err(bad_value);
+                }
+            },
+            _ => {
+                return Err(bad_value);
+            }
+        };
+
+        let mut signers = vec![];
+        let mut total_slots: u128 = 0;
+        for entry in entries.into_iter() {
+            let tuple = match entry {
+                Value::Tuple(tuple) => tuple,
+                _ => {
+                    return Err(bad_value);
+                }
+            };
+            let signer = match tuple.get("signer") {
+                Ok(Value::Principal(PrincipalData::Standard(principal))) => {
+                    StacksAddress::from(principal.clone())
+                }
+                _ => {
+                    return Err(bad_value);
+                }
+            };
+            let num_slots = match tuple.get("num-slots") {
+                Ok(Value::UInt(num_slots)) => *num_slots,
+                _ => {
+                    return Err(bad_value);
+                }
+            };
+
+            total_slots += num_slots;
+            if total_slots > STACKERDB_MAX_SLOTS as u128 {
+                return Err(net_error::ChainstateError(format!(
+                    "{}::{} defines too many slots (more than {})",
+                    contract_id, STACKERDB_SLOTS_FUNCTION, STACKERDB_MAX_SLOTS
+                )));
+            }
+            signers.push((signer, num_slots as u32));
+        }
+        Ok(signers)
+    }
+
+    fn parse_chunk_size(
+        contract_id: &QualifiedContractIdentifier,
+        value: Value,
+    ) -> Result<u64, net_error> {
+        let bad_value = net_error::ChainstateError(format!(
+            "{}::{} returned {}, expected (ok {{ chunk-size: uint }})",
+            contract_id, STACKERDB_CONFIG_FUNCTION, &value
+        ));
+
+        let tuple = match value {
+            Value::Response(data) if data.committed => match *data.data {
+                Value::Tuple(tuple) => tuple,
+                _ => {
+                    return Err(bad_value);
+                }
+            },
+            _ => {
+                return Err(bad_value);
+            }
+        };
+
+        match tuple.get("chunk-size") {
+            Ok(Value::UInt(chunk_size)) => {
+                if *chunk_size > STACKERDB_MAX_CHUNK_SIZE as u128 {
+                    return Err(net_error::ChainstateError(format!(
+                        "{}::{} chunk size {} exceeds the maximum of {}",
+                        contract_id,
+                        STACKERDB_CONFIG_FUNCTION,
+                        chunk_size,
+                        STACKERDB_MAX_CHUNK_SIZE
+                    )));
+                }
+                Ok(*chunk_size as u64)
+            }
+            _ => Err(bad_value),
+        }
+    }
+}
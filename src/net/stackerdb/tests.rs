@@ -0,0 +1,184 @@
+use super::{StackerDBChunkData, StackerDBConfig, StackerDBs};
+use address::AddressHashMode;
+use chainstate::stacks::{
+    StacksAddress, StacksPrivateKey, StacksPublicKey, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+};
+use net::Error as net_error;
+use vm::types::QualifiedContractIdentifier;
+
+fn addr_from_privk(privk: &StacksPrivateKey) -> StacksAddress {
+    StacksAddress::from_public_keys(
+        C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![StacksPublicKey::from_private(privk)],
+    )
+    .unwrap()
+}
+
+fn signed_chunk(
+    privk: &StacksPrivateKey,
+    slot_id: u32,
+    slot_version: u32,
+    data: Vec<u8>,
+) -> StackerDBChunkData {
+    let mut chunk = StackerDBChunkData::new(slot_id, slot_version, data);
+    chunk.sign(privk).unwrap();
+    chunk
+}
+
+fn is_rejected(res: Result<(), net_error>) -> bool {
+    match res {
+        Err(net_error::StackerDBChunkRejected(_)) => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn test_stackerdb_config_slot_signers() {
+    let addr_1 = addr_from_privk(&StacksPrivateKey::new());
+    let addr_2 = addr_from_privk(&StacksPrivateKey::new());
+    let config = StackerDBConfig {
+        chunk_size: 16,
+        signers: vec![(addr_1.clone(), 2), (addr_2.clone(), 3)],
+    };
+
+    assert_eq!(config.num_slots(), 5);
+    assert_eq!(config.slot_signer(0), Some(&addr_1));
+    assert_eq!(config.slot_signer(1), Some(&addr_1));
+    assert_eq!(config.slot_signer(2), Some(&addr_2));
+    assert_eq!(config.slot_signer(4), Some(&addr_2));
+    assert_eq!(config.slot_signer(5), None);
+}
+
+#[test]
+fn test_stackerdb_chunk_signature() {
+    let privk = StacksPrivateKey::new();
+    let addr = addr_from_privk(&privk);
+    let other_addr = addr_from_privk(&StacksPrivateKey::new());
+
+    let mut chunk = signed_chunk(&privk, 1, 2, vec![1, 2, 3]);
+    assert!(chunk.verify(&addr));
+    assert!(!chunk.verify(&other_addr));
+    assert!(chunk.get_slot_metadata().verify(&addr));
+
+    // signature covers the data, version, and slot
+    chunk.data = vec![1, 2, 4];
+    assert!(!chunk.verify(&addr));
+
+    let mut chunk = signed_chunk(&privk, 1, 2, vec![1, 2, 3]);
+    chunk.slot_version = 3;
+    assert!(!chunk.verify(&addr));
+
+    let mut chunk = signed_chunk(&privk, 1, 2, vec![1, 2, 3]);
+    chunk.slot_id = 0;
+    assert!(!chunk.verify(&addr));
+}
+
+#[test]
+fn test_stackerdb_put_get_chunks() {
+    let mut stackerdbs = StackerDBs::connect_memory().unwrap();
+    let contract_id =
+        QualifiedContractIdentifier::parse("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR.signers")
+            .unwrap();
+    let other_contract_id =
+        QualifiedContractIdentifier::parse("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR.miners")
+            .unwrap();
+
+    let privk_1 = StacksPrivateKey::new();
+    let privk_2 = StacksPrivateKey::new();
+    let config = StackerDBConfig {
+        chunk_size: 8,
+        signers: vec![
+            (addr_from_privk(&privk_1), 1),
+            (addr_from_privk(&privk_2), 1),
+        ],
+    };
+
+    assert_eq!(stackerdbs.get_slots_metadata(&contract_id).unwrap(), vec![]);
+    assert_eq!(stackerdbs.get_chunk(&contract_id, 0).unwrap(), None);
+
+    let chunk_0 = signed_chunk(&privk_1, 0, 1, vec![1, 2, 3]);
+    let chunk_1 = signed_chunk(&privk_2, 1, 1, vec![4, 5, 6]);
+    stackerdbs
+        .try_put_chunk(&contract_id, &config, &chunk_0)
+        .unwrap();
+    stackerdbs
+        .try_put_chunk(&contract_id, &config, &chunk_1)
+        .unwrap();
+
+    assert_eq!(
+        stackerdbs.get_chunk(&contract_id, 0).unwrap(),
+        Some(chunk_0.clone())
+    );
+    assert_eq!(
+        stackerdbs.get_slots_metadata(&contract_id).unwrap(),
+        vec![chunk_0.get_slot_metadata(), chunk_1.get_slot_metadata()]
+    );
+    assert_eq!(
+        stackerdbs.get_slots_metadata(&other_contract_id).unwrap(),
+        vec![]
+    );
+
+    // same or older versions are rejected
+    let stale_chunk = signed_chunk(&privk_1, 0, 1, vec![7, 8, 9]);
+    assert!(is_rejected(stackerdbs.try_put_chunk(
+        &contract_id,
+        &config,
+        &stale_chunk
+    )));
+
+    // newer versions replace the chunk
+    let new_chunk = signed_chunk(&privk_1, 0, 5, vec![7, 8, 9]);
+    stackerdbs
+        .try_put_chunk(&contract_id, &config, &new_chunk)
+        .unwrap();
+    assert_eq!(
+        stackerdbs.get_chunk(&contract_id, 0).unwrap(),
+        Some(new_chunk.clone())
+    );
+    assert_eq!(
+        stackerdbs.get_slot_metadata(&contract_id, 0).unwrap(),
+        Some(new_chunk.get_slot_metadata())
+    );
+
+    let older_chunk = signed_chunk(&privk_1, 0, 4, vec![1]);
+    assert!(is_rejected(stackerdbs.try_put_chunk(
+        &contract_id,
+        &config,
+        &older_chunk
+    )));
+
+    // wrong signer
+    let bad_signer_chunk = signed_chunk(&privk_2, 0, 6, vec![1]);
+    assert!(is_rejected(stackerdbs.try_put_chunk(
+        &contract_id,
+        &config,
+        &bad_signer_chunk
+    )));
+
+    // no such slot
+    let no_slot_chunk = signed_chunk(&privk_2, 2, 1, vec![1]);
+    assert!(is_rejected(stackerdbs.try_put_chunk(
+        &contract_id,
+        &config,
+        &no_slot_chunk
+    )));
+
+    // too big
+    let oversize_chunk = signed_chunk(&privk_2, 1, 2, vec![0; 9]);
+    assert!(is_rejected(stackerdbs.try_put_chunk(
+        &contract_id,
+        &config,
+        &oversize_chunk
+    )));
+
+    assert_eq!(
+        stackerdbs.get_chunk(&contract_id, 0).unwrap(),
+        Some(new_chunk)
+    );
+    assert_eq!(
+        stackerdbs.get_chunk(&contract_id, 1).unwrap(),
+        Some(chunk_1)
+    );
+}
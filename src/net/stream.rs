@@ -0,0 +1,267 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Response bodies that the RPC server writes out a chunk at a time, instead of buffering them
+//! whole in the reply pipe.  The conversation only asks a stream for its next chunk once the
+//! previous one has been picked up by the socket, so a slow or bursty client holds on to at most
+//! a couple of chunks of encoded data per request.
+
+use std::collections::VecDeque;
+use std::io::Write;
+
+use chainstate::stacks::db::{BlockStreamData, StacksChainState};
+use net::atlas::Attachment;
+use net::ContractSrcResponse;
+use net::Error as net_error;
+
+/// A response body that gets sent as a chunked transfer
+#[derive(Debug, PartialEq, Clone)]
+pub enum HttpResponseStream {
+    /// block or microblock data, read from the chainstate as it is sent
+    Block(BlockStreamData),
+    /// a JSON document, encoded as it is sent
+    Json(JsonStreamData),
+}
+
+impl HttpResponseStream {
+    /// Write up to (about) `count` more bytes of the body to `fd`.
+    /// Returns the number of bytes written; 0 means the body has been completely sent.
+    pub fn stream_to<W: Write>(
+        &mut self,
+        chainstate: &mut StacksChainState,
+        fd: &mut W,
+        count: u64,
+    ) -> Result<u64, net_error> {
+        match self {
+            HttpResponseStream::Block(ref mut stream) => stream
+                .stream_to(chainstate, fd, count)
+                .map_err(net_error::from),
+            HttpResponseStream::Json(ref mut stream) => stream.stream_to(fd, count),
+        }
+    }
+}
+
+impl From<BlockStreamData> for HttpResponseStream {
+    fn from(stream: BlockStreamData) -> HttpResponseStream {
+        HttpResponseStream::Block(stream)
+    }
+}
+
+impl From<JsonStreamData> for HttpResponseStream {
+    fn from(stream: JsonStreamData) -> HttpResponseStream {
+        HttpResponseStream::Json(stream)
+    }
+}
+
+/// A piece of a JSON document.  Only the (potentially large) values get encoded lazily.
+#[derive(Debug, PartialEq, Clone)]
+enum JsonSegment {
+    /// already-encoded JSON text
+    Raw(Vec<u8>),
+    /// a byte vector, encoded the way serde encodes a `Vec<u8>` (an array of numbers)
+    Bytes(Vec<u8>),
+    /// a string, encoded as a JSON string literal (including the quotes)
+    Str(String),
+}
+
+impl JsonSegment {
+    fn len(&self) -> usize {
+        match self {
+            JsonSegment::Raw(ref bytes) | JsonSegment::Bytes(ref bytes) => bytes.len(),
+            JsonSegment::Str(ref s) => s.len(),
+        }
+    }
+}
+
+/// Opaque structure for encoding a JSON document into a stream, piece by piece.
+/// The encoded document is the same one `serde_json::to_string()` would produce.
+#[derive(Debug, PartialEq, Clone)]
+pub struct JsonStreamData {
+    segments: VecDeque<JsonSegment>,
+    offset: usize, // offset into the front segment's data
+}
+
+impl JsonStreamData {
+    fn new() -> JsonStreamData {
+        JsonStreamData {
+            segments: VecDeque::new(),
+            offset: 0,
+        }
+    }
+
+    fn raw(mut self, json: &str) -> JsonStreamData {
+        self.segments
+            .push_back(JsonSegment::Raw(json.as_bytes().to_vec()));
+        self
+    }
+
+    fn bytes(mut self, bytes: Vec<u8>) -> JsonStreamData {
+        self = self.raw("[");
+        self.segments.push_back(JsonSegment::Bytes(bytes));
+        self.raw("]")
+    }
+
+    fn string(mut self, s: String) -> JsonStreamData {
+        self = self.raw("\"");
+        self.segments.push_back(JsonSegment::Str(s));
+        self.raw("\"")
+    }
+
+    /// The body of a GET /v2/attachments/{hash} response (a `GetAttachmentResponse`)
+    pub fn attachment(attachment: Attachment) -> JsonStreamData {
+        JsonStreamData::new()
+            .raw("{\"attachment\":{\"content\":")
+            .bytes(attachment.content)
+            .raw("}}")
+    }
+
+    /// The body of a GET /v2/contracts/source response
+    pub fn contract_src(data: ContractSrcResponse) -> JsonStreamData {
+        let mut stream = JsonStreamData::new()
+            .raw("{\"source\":")
+            .string(data.source)
+            .raw(&format!(",\"publish_height\":{}", data.publish_height));
+        if let Some(proof) = data.marf_proof {
+            stream = stream.raw(",\"proof\":").string(proof);
+        }
+        stream.raw("}")
+    }
+
+    /// Encode up to `count` more bytes of the front segment into `buf`
+    fn encode_front(&mut self, buf: &mut Vec<u8>, count: usize) {
+        let offset = self.offset;
+        match self.segments.front() {
+            Some(JsonSegment::Raw(ref bytes)) => {
+                let end = (offset + count).min(bytes.len());
+                buf.extend_from_slice(&bytes[offset..end]);
+                self.offset = end;
+            }
+            Some(JsonSegment::Bytes(ref bytes)) => {
+                let mut i = offset;
+                let limit = buf.len() + count;
+                while i < bytes.len() && buf.len() < limit {
+                    if i > 0 {
+                        buf.push(b',');
+                    }
+                    buf.extend_from_slice(bytes[i].to_string().as_bytes());
+                    i += 1;
+                }
+                self.offset = i;
+            }
+            Some(JsonSegment::Str(ref s)) => {
+                let mut end = (offset + count).min(s.len());
+                while !s.is_char_boundary(end) {
+                    end += 1;
+                }
+                // serde_json escapes a string slice the same way it escapes the whole string, so
+                // just drop the quotes it adds to each slice.
+                let escaped = serde_json::to_string(&s[offset..end])
+                    .expect("FATAL: failed to encode a string as JSON");
+                buf.extend_from_slice(&escaped.as_bytes()[1..escaped.len() - 1]);
+                self.offset = end;
+            }
+            None => {}
+        }
+    }
+
+    /// Write up to (about) `count` more bytes of the document to `fd`.
+    /// Returns the number of bytes written; 0 means the document has been completely sent.
+    pub fn stream_to<W: Write>(&mut self, fd: &mut W, count: u64) -> Result<u64, net_error> {
+        let count = count as usize;
+        let mut buf = Vec::with_capacity(count);
+        while buf.len() < count {
+            let len = match self.segments.front() {
+                Some(segment) => segment.len(),
+                None => break,
+            };
+            if self.offset < len {
+                let remaining = count - buf.len();
+                self.encode_front(&mut buf, remaining);
+            }
+            if self.offset >= len {
+                self.segments.pop_front();
+                self.offset = 0;
+            }
+        }
+
+        fd.write_all(&buf).map_err(net_error::WriteError)?;
+        Ok(buf.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use net::GetAttachmentResponse;
+
+    fn stream_all(mut stream: JsonStreamData, count: u64) -> String {
+        let mut out = vec![];
+        loop {
+            let nw = stream.stream_to(&mut out, count).unwrap();
+            if nw == 0 {
+                break;
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn stream_attachment_json() {
+        let attachments = vec![
+            Attachment::new(vec![]),
+            Attachment::new(vec![0]),
+            Attachment::new((0..=255).collect()),
+            Attachment::new((0..10000).map(|i| (i % 251) as u8).collect()),
+        ];
+        for attachment in attachments.into_iter() {
+            let expected = serde_json::to_string(&GetAttachmentResponse {
+                attachment: attachment.clone(),
+            })
+            .unwrap();
+            for count in [1, 2, 7, 4096, 1000000].iter() {
+                let json = stream_all(JsonStreamData::attachment(attachment.clone()), *count);
+                assert_eq!(json, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn stream_contract_src_json() {
+        let sources = vec![
+            "".to_string(),
+            "(define-data-var x int 0)".to_string(),
+            "(define-constant greeting \"hi \\\"there\\\"\")\n\t;; caf\u{e9} \u{1f600}\u{0}"
+                .repeat(500),
+        ];
+        for source in sources.into_iter() {
+            for marf_proof in [None, Some("0011223344".to_string())].iter() {
+                let data = ContractSrcResponse {
+                    source: source.clone(),
+                    publish_height: 12345,
+                    marf_proof: marf_proof.clone(),
+                };
+                let expected = serde_json::to_string(&data).unwrap();
+                for count in [1, 2, 7, 4096, 1000000].iter() {
+                    let json = stream_all(JsonStreamData::contract_src(data.clone()), *count);
+                    assert_eq!(json, expected);
+
+                    let parsed: ContractSrcResponse = serde_json::from_str(&json).unwrap();
+                    assert_eq!(parsed, data);
+                }
+            }
+        }
+    }
+}
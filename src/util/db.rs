@@ -24,12 +24,13 @@ use std::io::Error as IOError;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use util::hash::to_hex;
 use util::sleep_ms;
 
 use chainstate::burn::BlockHeaderHash;
-use vm::types::QualifiedContractIdentifier;
+use vm::types::{PrincipalData, QualifiedContractIdentifier};
 
 use rusqlite::types::{
     FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value as RusqliteValue,
@@ -37,6 +38,7 @@ use rusqlite::types::{
 };
 use rusqlite::Connection;
 use rusqlite::Error as sqlite_error;
+use rusqlite::OpenFlags;
 use rusqlite::Row;
 use rusqlite::Transaction;
 use rusqlite::TransactionBehavior;
@@ -203,6 +205,13 @@ impl FromColumn<QualifiedContractIdentifier> for QualifiedContractIdentifier {
     }
 }
 
+impl FromColumn<PrincipalData> for PrincipalData {
+    fn from_column<'a>(row: &'a Row, column_name: &str) -> Result<PrincipalData, Error> {
+        let value: String = row.get(column_name);
+        PrincipalData::parse(&value).map_err(|_| Error::ParseError)
+    }
+}
+
 pub fn u64_to_sql(x: u64) -> Result<i64, Error> {
     if x > (i64::max_value() as u64) {
         return Err(Error::ParseError);
@@ -519,6 +528,113 @@ pub fn tx_begin_immediate<'a>(conn: &'a mut Connection) -> Result<DBTx<'a>, Erro
     Ok(tx)
 }
 
+/// Open a sqlite connection with the busy handler installed.  Read-write connections to on-disk
+/// databases are switched to write-ahead logging, so that readers do not block behind a writer's
+/// transaction (and vice versa).  The journal mode is persistent, so read-only connections opened
+/// later will use it too.
+pub fn sqlite_open(path: &str, flags: OpenFlags) -> Result<Connection, sqlite_error> {
+    let conn = Connection::open_with_flags(path, flags)?;
+    conn.busy_handler(Some(tx_busy_handler))?;
+    if path != ":memory:" && flags.contains(OpenFlags::SQLITE_OPEN_READ_WRITE) {
+        let journal_mode: String =
+            conn.query_row("PRAGMA journal_mode = WAL", NO_PARAMS, |row| row.get(0))?;
+        if journal_mode.to_lowercase() != "wal" {
+            warn!(
+                "Failed to enable write-ahead logging on {}: journal mode is {}",
+                path, &journal_mode
+            );
+        }
+    }
+    Ok(conn)
+}
+
+/// Default number of idle connections a ConnectionPool keeps around
+pub const DEFAULT_CONNECTION_POOL_SIZE: usize = 8;
+
+/// A pool of read-only connections to a single database.  Readers that would otherwise open a new
+/// connection (and re-parse the schema) for every query take one from the pool instead, and hand
+/// it back when they are done.
+pub struct ConnectionPool {
+    path: String,
+    max_idle: usize,
+    idle: Mutex<Vec<Connection>>,
+}
+
+/// A read-only connection taken from a ConnectionPool.  It is returned to the pool when dropped.
+pub struct PooledConnection {
+    conn: Option<Connection>,
+    pool: Arc<ConnectionPool>,
+}
+
+impl ConnectionPool {
+    pub fn new(path: &str, max_idle: usize) -> ConnectionPool {
+        ConnectionPool {
+            path: path.to_string(),
+            max_idle: max_idle,
+            idle: Mutex::new(vec![]),
+        }
+    }
+
+    /// Take an idle connection from the pool, or open a new one if there are none.
+    pub fn get(pool: &Arc<ConnectionPool>) -> Result<PooledConnection, Error> {
+        let idle_conn = pool
+            .idle
+            .lock()
+            .expect("FATAL: connection pool lock is poisoned")
+            .pop();
+        let conn = match idle_conn {
+            Some(conn) => conn,
+            None => sqlite_open(&pool.path, OpenFlags::SQLITE_OPEN_READ_ONLY)?,
+        };
+        Ok(PooledConnection {
+            conn: Some(conn),
+            pool: pool.clone(),
+        })
+    }
+
+    pub fn num_idle(&self) -> usize {
+        self.idle
+            .lock()
+            .expect("FATAL: connection pool lock is poisoned")
+            .len()
+    }
+
+    fn put(&self, conn: Connection) {
+        let mut idle = self
+            .idle
+            .lock()
+            .expect("FATAL: connection pool lock is poisoned");
+        if idle.len() < self.max_idle {
+            idle.push(conn);
+        }
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+    fn deref(&self) -> &Connection {
+        self.conn
+            .as_ref()
+            .expect("BUG: pooled connection used after release")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn
+            .as_mut()
+            .expect("BUG: pooled connection used after release")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.put(conn);
+        }
+    }
+}
+
 /// Get the ancestor block hash of a block of a given height, given a descendent block hash.
 pub fn get_ancestor_block_hash<T: MarfTrieId>(
     index: &MARF<T>,
@@ -227,6 +227,11 @@ impl PipeWrite {
         Ok(buf.len())
     }
 
+    /// How many bytes have been written but not yet handed off to the reader?
+    pub fn pending(&self) -> usize {
+        self.buf.as_ref().map(|buf| buf.len()).unwrap_or(0)
+    }
+
     /// Try and flush all data to the reader.
     /// Return True if we succeeded; False if not.
     pub fn try_flush(&mut self) -> io::Result<bool> {
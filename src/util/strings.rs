@@ -26,6 +26,7 @@ use std::ops::DerefMut;
 
 use net::codec::{read_next, read_next_at_most, write_next};
 use net::Error as net_error;
+use net::PeerAddress;
 use net::StacksMessageCodec;
 use net::MAX_MESSAGE_LEN;
 
@@ -377,6 +378,49 @@ impl UrlString {
         }
     }
 
+    /// Is this URL's host publicly routable?  i.e. is it a domain name, or an IP address that is
+    /// not a loopback, private, or link-local address?
+    pub fn has_public_host(&self) -> bool {
+        if !self.has_routable_host() {
+            return false;
+        }
+        let url = match url::Url::parse(self.as_str()) {
+            Ok(x) => x,
+            Err(_) => {
+                return false;
+            }
+        };
+        match url.host() {
+            Some(url::Host::Domain(domain)) => domain != "localhost",
+            Some(url::Host::Ipv4(addr)) => {
+                !(addr.is_loopback() || addr.is_private() || addr.is_link_local())
+            }
+            Some(url::Host::Ipv6(addr)) => !addr.is_loopback(),
+            None => false,
+        }
+    }
+
+    /// Can a peer at the given address plausibly fetch data from this URL?
+    /// A URL whose host is a domain name can be resolved from anywhere, but a URL whose host is
+    /// an IP address is only usable by peers on the same address family.
+    pub fn is_reachable_from(&self, peer_addr: &PeerAddress) -> bool {
+        if self.is_empty() || !self.has_routable_host() {
+            return false;
+        }
+        let url = match url::Url::parse(self.as_str()) {
+            Ok(x) => x,
+            Err(_) => {
+                return false;
+            }
+        };
+        match url.host() {
+            Some(url::Host::Domain(_)) => true,
+            Some(url::Host::Ipv4(_)) => peer_addr.is_ipv4(),
+            Some(url::Host::Ipv6(_)) => !peer_addr.is_ipv4(),
+            None => false,
+        }
+    }
+
     /// Get the port. Returns 0 for unknown
     pub fn get_port(&self) -> Option<u16> {
         let url = match url::Url::parse(&self.to_string()) {
@@ -515,4 +559,42 @@ mod test {
         assert_eq!(url.path(), "/v2/info");
         assert_eq!(url.scheme(), "http");
     }
+
+    #[test]
+    fn test_url_reachability() {
+        let ipv4_peer = PeerAddress::from_ipv4(1, 2, 3, 4);
+        let ipv6_peer = PeerAddress::from_ip(&"2001:db8::1".parse().unwrap());
+
+        let ipv4_url = UrlString::try_from("http://1.2.3.4:20443").unwrap();
+        let ipv6_url = UrlString::try_from("http://[2001:db8::2]:20443").unwrap();
+        let domain_url = UrlString::try_from("https://node.example.com").unwrap();
+        let anynet_url = UrlString::try_from("http://0.0.0.0:20443").unwrap();
+        let empty_url = UrlString::try_from("").unwrap();
+
+        assert!(ipv4_url.is_reachable_from(&ipv4_peer));
+        assert!(!ipv4_url.is_reachable_from(&ipv6_peer));
+        assert!(!ipv6_url.is_reachable_from(&ipv4_peer));
+        assert!(ipv6_url.is_reachable_from(&ipv6_peer));
+        assert!(domain_url.is_reachable_from(&ipv4_peer));
+        assert!(domain_url.is_reachable_from(&ipv6_peer));
+        assert!(!anynet_url.is_reachable_from(&ipv4_peer));
+        assert!(!empty_url.is_reachable_from(&ipv4_peer));
+
+        assert!(ipv4_url.has_public_host());
+        assert!(ipv6_url.has_public_host());
+        assert!(domain_url.has_public_host());
+        assert!(!anynet_url.has_public_host());
+        assert!(!UrlString::try_from("http://127.0.0.1:20443")
+            .unwrap()
+            .has_public_host());
+        assert!(!UrlString::try_from("http://192.168.1.10:20443")
+            .unwrap()
+            .has_public_host());
+        assert!(!UrlString::try_from("http://[::1]:20443")
+            .unwrap()
+            .has_public_host());
+        assert!(!UrlString::try_from("http://localhost:20443")
+            .unwrap()
+            .has_public_host());
+    }
 }
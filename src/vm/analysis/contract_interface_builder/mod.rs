@@ -16,8 +16,11 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 use vm::analysis::types::ContractAnalysis;
-use vm::types::{FixedFunction, FunctionArg, FunctionType, TupleTypeSignature, TypeSignature};
-use vm::ClarityName;
+use vm::functions::define::DefineFunctionsParsed;
+use vm::types::{
+    FixedFunction, FunctionArg, FunctionSignature, FunctionType, TupleTypeSignature, TypeSignature,
+};
+use vm::{ClarityName, SymbolicExpression};
 
 pub fn build_contract_interface(contract_analysis: &ContractAnalysis) -> ContractInterface {
     let mut contract_interface = ContractInterface::new();
@@ -31,13 +34,14 @@ pub fn build_contract_interface(contract_analysis: &ContractAnalysis) -> Contrac
         map_types,
         fungible_tokens,
         non_fungible_tokens,
-        defined_traits: _,
-        implemented_traits: _,
-        expressions: _,
+        defined_traits,
+        implemented_traits,
+        expressions,
         contract_identifier: _,
         type_map: _,
         cost_track: _,
         contract_interface: _,
+        clarity_version: _,
     } = contract_analysis;
 
     contract_interface
@@ -89,6 +93,19 @@ pub fn build_contract_interface(contract_analysis: &ContractAnalysis) -> Contrac
             fungible_tokens,
         ));
 
+    contract_interface
+        .defined_traits
+        .append(&mut ContractInterfaceDefinedTrait::from_map(defined_traits));
+
+    contract_interface.implemented_traits = implemented_traits
+        .iter()
+        .map(|trait_id| trait_id.to_string())
+        .collect();
+
+    contract_interface.error_constants.append(
+        &mut ContractInterfaceErrorConstant::from_expressions(expressions, variable_types),
+    );
+
     contract_interface
 }
 
@@ -347,6 +364,94 @@ impl ContractInterfaceMap {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractInterfaceTraitFunction {
+    pub name: String,
+    pub args: Vec<ContractInterfaceAtomType>,
+    pub outputs: ContractInterfaceFunctionOutput,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractInterfaceDefinedTrait {
+    pub name: String,
+    pub functions: Vec<ContractInterfaceTraitFunction>,
+}
+
+impl ContractInterfaceDefinedTrait {
+    pub fn from_map(
+        map: &BTreeMap<ClarityName, BTreeMap<ClarityName, FunctionSignature>>,
+    ) -> Vec<ContractInterfaceDefinedTrait> {
+        map.iter()
+            .map(|(name, functions)| ContractInterfaceDefinedTrait {
+                name: name.clone().into(),
+                functions: functions
+                    .iter()
+                    .map(|(fn_name, signature)| ContractInterfaceTraitFunction {
+                        name: fn_name.clone().into(),
+                        args: signature
+                            .args
+                            .iter()
+                            .map(ContractInterfaceAtomType::from_type_signature)
+                            .collect(),
+                        outputs: ContractInterfaceFunctionOutput {
+                            type_f: ContractInterfaceAtomType::from_type_signature(
+                                &signature.returns,
+                            ),
+                        },
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// A constant defined as an `(err ...)` response, e.g. `(define-constant ERR-NOT-OWNER (err u100))`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractInterfaceErrorConstant {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_f: ContractInterfaceAtomType,
+    /// the error value, if it is given as a literal
+    pub value: Option<String>,
+}
+
+impl ContractInterfaceErrorConstant {
+    pub fn from_expressions(
+        expressions: &[SymbolicExpression],
+        variable_types: &BTreeMap<ClarityName, TypeSignature>,
+    ) -> Vec<ContractInterfaceErrorConstant> {
+        let mut error_constants = vec![];
+        for expr in expressions.iter() {
+            let (name, value) = match DefineFunctionsParsed::try_parse(expr) {
+                Ok(Some(DefineFunctionsParsed::Constant { name, value })) => (name, value),
+                _ => continue,
+            };
+            let err_value = match value.match_list() {
+                Some(list)
+                    if list.len() == 2
+                        && list[0].match_atom().map(|a| a.as_str()) == Some("err") =>
+                {
+                    &list[1]
+                }
+                _ => continue,
+            };
+            let type_sig = match variable_types.get(name) {
+                Some(type_sig) => type_sig,
+                None => continue,
+            };
+            error_constants.push(ContractInterfaceErrorConstant {
+                name: name.clone().into(),
+                type_f: ContractInterfaceAtomType::from_type_signature(type_sig),
+                value: err_value
+                    .match_literal_value()
+                    .or_else(|| err_value.match_atom_value())
+                    .map(|v| v.to_string()),
+            });
+        }
+        error_constants
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContractInterface {
     pub functions: Vec<ContractInterfaceFunction>,
@@ -354,6 +459,13 @@ pub struct ContractInterface {
     pub maps: Vec<ContractInterfaceMap>,
     pub fungible_tokens: Vec<ContractInterfaceFungibleTokens>,
     pub non_fungible_tokens: Vec<ContractInterfaceNonFungibleTokens>,
+    // interfaces stored before these fields were added don't have them
+    #[serde(default)]
+    pub defined_traits: Vec<ContractInterfaceDefinedTrait>,
+    #[serde(default)]
+    pub implemented_traits: Vec<String>,
+    #[serde(default)]
+    pub error_constants: Vec<ContractInterfaceErrorConstant>,
 }
 
 impl ContractInterface {
@@ -364,6 +476,9 @@ impl ContractInterface {
             maps: Vec::new(),
             fungible_tokens: Vec::new(),
             non_fungible_tokens: Vec::new(),
+            defined_traits: Vec::new(),
+            implemented_traits: Vec::new(),
+            error_constants: Vec::new(),
         }
     }
 
@@ -125,6 +125,10 @@ pub enum CheckErrors {
     NoSuchBlockInfoProperty(String),
     GetBlockInfoExpectPropertyName,
 
+    // get-burn-block-info? errors
+    NoSuchBurnBlockInfoProperty(String),
+    GetBurnBlockInfoExpectPropertyName,
+
     NameAlreadyUsed(String),
 
     // expect a function, or applying a function to a list
@@ -364,6 +368,8 @@ impl DiagnosableError for CheckErrors {
             CheckErrors::ContractCallExpectName => format!("missing contract name for call"),
             CheckErrors::NoSuchBlockInfoProperty(property_name) => format!("use of block unknown property '{}'", property_name),
             CheckErrors::GetBlockInfoExpectPropertyName => format!("missing property name for block info introspection"),
+            CheckErrors::NoSuchBurnBlockInfoProperty(property_name) => format!("use of burn block unknown property '{}'", property_name),
+            CheckErrors::GetBurnBlockInfoExpectPropertyName => format!("missing property name for burn block info introspection"),
             CheckErrors::NameAlreadyUsed(name) => format!("defining '{}' conflicts with previous value", name),
             CheckErrors::NonFunctionApplication => format!("expecting expression of type function"),
             CheckErrors::ExpectedListApplication => format!("expecting expression of type list"),
@@ -424,6 +430,9 @@ impl DiagnosableError for CheckErrors {
             CheckErrors::NoSuchBlockInfoProperty(_) => Some(format!(
                 "properties available: time, header-hash, burnchain-header-hash, vrf-seed"
             )),
+            CheckErrors::NoSuchBurnBlockInfoProperty(_) => Some(format!(
+                "properties available: header-hash, time, median-time-past"
+            )),
             _ => None,
         }
     }
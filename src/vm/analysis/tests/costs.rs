@@ -75,14 +75,23 @@ pub fn test_tracked_costs(prog: &str) -> ExecutionCost {
     let trait_contract_id =
         QualifiedContractIdentifier::new(p1_principal.clone(), "contract-trait".into());
 
-    clarity_instance
-        .begin_test_genesis_block(
+    {
+        let mut conn = clarity_instance.begin_test_genesis_block(
             &StacksBlockId::sentinel(),
             &StacksBlockId([0 as u8; 32]),
             &NULL_HEADER_DB,
             &NULL_BURN_STATE_DB,
-        )
-        .commit_block();
+        );
+        // deploy Clarity 2 contracts, so that every native can be tested
+        conn.as_transaction(|tx| {
+            tx.with_clarity_db(|db| {
+                db.set_stacks_2_1_active(0);
+                Ok(())
+            })
+        })
+        .unwrap();
+        conn.commit_block();
+    }
 
     {
         let mut conn = clarity_instance.begin_block(
@@ -24,7 +24,7 @@ use vm::costs::{
     analysis_typecheck_cost, cost_functions, runtime_cost, ClarityCostFunctionReference,
     CostErrors, CostOverflowingMath, CostTracker, ExecutionCost, LimitedCostTracker,
 };
-use vm::functions::define::DefineFunctionsParsed;
+use vm::functions::define::{DefineFunctions, DefineFunctionsParsed};
 use vm::functions::NativeFunctions;
 use vm::representations::SymbolicExpressionType::{
     Atom, AtomValue, Field, List, LiteralValue, TraitReference,
@@ -36,6 +36,7 @@ use vm::types::{
     QualifiedContractIdentifier, TupleTypeSignature, TypeSignature, Value,
 };
 use vm::variables::NativeVariables;
+use vm::ClarityVersion;
 
 pub use super::types::{AnalysisPass, ContractAnalysis};
 use super::AnalysisDatabase;
@@ -49,6 +50,7 @@ pub use super::errors::{
 };
 use vm::contexts::Environment;
 use vm::costs::cost_functions::ClarityCostFunction;
+use vm::costs::profiler::ProfileFrame;
 
 #[cfg(test)]
 mod tests;
@@ -71,10 +73,12 @@ Is illegally typed in our language.
 
 pub struct TypeChecker<'a, 'b> {
     pub type_map: TypeMap,
+    contract_identifier: QualifiedContractIdentifier,
     contract_context: ContractContext,
     function_return_tracker: Option<Option<TypeSignature>>,
     db: &'a mut AnalysisDatabase<'b>,
     pub cost_track: LimitedCostTracker,
+    clarity_version: ClarityVersion,
 }
 
 impl CostTracker for TypeChecker<'_, '_> {
@@ -115,7 +119,12 @@ impl AnalysisPass for TypeChecker<'_, '_> {
         analysis_db: &mut AnalysisDatabase,
     ) -> CheckResult<()> {
         let cost_track = contract_analysis.take_contract_cost_tracker();
-        let mut command = TypeChecker::new(analysis_db, cost_track);
+        let mut command = TypeChecker::new(
+            analysis_db,
+            cost_track,
+            contract_analysis.contract_identifier.clone(),
+            contract_analysis.clarity_version,
+        );
         // run the analysis, and replace the cost tracker whether or not the
         //   analysis succeeded.
         match command.run(contract_analysis) {
@@ -317,10 +326,14 @@ impl<'a, 'b> TypeChecker<'a, 'b> {
     fn new(
         db: &'a mut AnalysisDatabase<'b>,
         cost_track: LimitedCostTracker,
+        contract_identifier: QualifiedContractIdentifier,
+        clarity_version: ClarityVersion,
     ) -> TypeChecker<'a, 'b> {
         Self {
             db,
             cost_track,
+            contract_identifier,
+            clarity_version,
             contract_context: ContractContext::new(),
             function_return_tracker: None,
             type_map: TypeMap::new(),
@@ -385,7 +398,17 @@ impl<'a, 'b> TypeChecker<'a, 'b> {
         let mut local_context = TypingContext::new();
 
         for exp in contract_analysis.expressions.iter() {
+            // non-definitions are profiled by `type_check`
+            let profiling =
+                self.cost_track.is_profiling() && DefineFunctions::try_parse(exp).is_some();
+            if profiling {
+                let frame = ProfileFrame::expression(&self.contract_identifier, exp);
+                self.cost_track.profile_enter(frame);
+            }
             let mut result_res = self.try_type_check_define(&exp, &mut local_context);
+            if profiling {
+                self.cost_track.profile_exit();
+            }
             if let Err(ref mut error) = result_res {
                 if !error.has_expression() {
                     error.set_expression(&exp);
@@ -447,6 +470,23 @@ impl<'a, 'b> TypeChecker<'a, 'b> {
 
     // Type checks an expression, recursively type checking its subexpressions
     pub fn type_check(&mut self, expr: &SymbolicExpression, context: &TypingContext) -> TypeResult {
+        let profiling = self.cost_track.is_profiling() && expr.match_list().is_some();
+        if profiling {
+            let frame = ProfileFrame::expression(&self.contract_identifier, expr);
+            self.cost_track.profile_enter(frame);
+        }
+        let result = self.visit_type_check(expr, context);
+        if profiling {
+            self.cost_track.profile_exit();
+        }
+        result
+    }
+
+    fn visit_type_check(
+        &mut self,
+        expr: &SymbolicExpression,
+        context: &TypingContext,
+    ) -> TypeResult {
         runtime_cost(ClarityCostFunction::AnalysisVisit, self, 0)?;
 
         let mut result = self.inner_type_check(expr, context);
@@ -608,7 +648,9 @@ impl<'a, 'b> TypeChecker<'a, 'b> {
         args: &[SymbolicExpression],
         context: &TypingContext,
     ) -> Option<TypeResult> {
-        if let Some(ref native_function) = NativeFunctions::lookup_by_name(function) {
+        if let Some(ref native_function) =
+            NativeFunctions::lookup_by_name_at_version(function, self.clarity_version)
+        {
             let typed_function = TypedNativeFunction::type_native_function(native_function);
             Some(typed_function.type_check_appliction(self, args, context))
         } else {
@@ -19,7 +19,9 @@ use vm::analysis::errors::{check_argument_count, CheckError, CheckErrors, CheckR
 use vm::costs::cost_functions::ClarityCostFunction;
 use vm::costs::{cost_functions, runtime_cost};
 use vm::representations::SymbolicExpression;
-use vm::types::{BlockInfoProperty, TupleTypeSignature, TypeSignature, MAX_VALUE_SIZE};
+use vm::types::{
+    BlockInfoProperty, SequenceSubtype, TupleTypeSignature, TypeSignature, MAX_VALUE_SIZE,
+};
 
 pub fn check_special_get_owner(
     checker: &mut TypeChecker,
@@ -99,6 +101,66 @@ pub fn check_special_mint_asset(
     )
 }
 
+/// Check that `assets` is a list of assets of `expected_asset_type`, and return the type of the
+/// per-item results of a batch NFT operation over it:
+/// `(response (list N (response bool uint)) (list N (response bool uint)))`
+fn check_nft_batch_assets(
+    checker: &mut TypeChecker,
+    assets: &SymbolicExpression,
+    context: &TypingContext,
+    expected_asset_type: &TypeSignature,
+) -> TypeResult {
+    let assets_type = checker.type_check(assets, context)?;
+    let max_len = match assets_type {
+        TypeSignature::SequenceType(SequenceSubtype::ListType(ref list_data)) => {
+            list_data.get_max_len()
+        }
+        _ => return Err(CheckErrors::ExpectedListApplication.into()),
+    };
+
+    let expected_assets_type = TypeSignature::list_of(expected_asset_type.clone(), max_len)?;
+    if !expected_assets_type.admits_type(&assets_type) {
+        return Err(CheckErrors::TypeError(expected_assets_type, assets_type).into());
+    }
+
+    let results_type = TypeSignature::list_of(
+        TypeSignature::new_response(TypeSignature::BoolType, TypeSignature::UIntType)?,
+        max_len,
+    )?;
+    Ok(TypeSignature::new_response(
+        results_type.clone(),
+        results_type,
+    )?)
+}
+
+pub fn check_special_mint_asset_many(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(3, args)?;
+
+    let asset_name = args[0].match_atom().ok_or(CheckErrors::BadTokenName)?;
+
+    let expected_owner_type: TypeSignature = TypeSignature::PrincipalType;
+    let expected_asset_type = checker
+        .contract_context
+        .get_nft_type(asset_name)
+        .ok_or(CheckErrors::NoSuchNFT(asset_name.to_string()))?
+        .clone();
+
+    runtime_cost(
+        ClarityCostFunction::AnalysisTypeLookup,
+        checker,
+        expected_asset_type.type_size()?,
+    )?;
+
+    let return_type = check_nft_batch_assets(checker, &args[1], context, &expected_asset_type)?;
+    checker.type_check_expects(&args[2], context, &expected_owner_type)?;
+
+    Ok(return_type)
+}
+
 pub fn check_special_mint_token(
     checker: &mut TypeChecker,
     args: &[SymbolicExpression],
@@ -158,6 +220,35 @@ pub fn check_special_transfer_asset(
     )
 }
 
+pub fn check_special_transfer_asset_many(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(4, args)?;
+
+    let token_name = args[0].match_atom().ok_or(CheckErrors::BadTokenName)?;
+
+    let expected_owner_type: TypeSignature = TypeSignature::PrincipalType;
+    let expected_asset_type = checker
+        .contract_context
+        .get_nft_type(token_name)
+        .ok_or(CheckErrors::NoSuchNFT(token_name.to_string()))?
+        .clone();
+
+    runtime_cost(
+        ClarityCostFunction::AnalysisTypeLookup,
+        checker,
+        expected_asset_type.type_size()?,
+    )?;
+
+    let return_type = check_nft_batch_assets(checker, &args[1], context, &expected_asset_type)?;
+    checker.type_check_expects(&args[2], context, &expected_owner_type)?; // owner
+    checker.type_check_expects(&args[3], context, &expected_owner_type)?; // recipient
+
+    Ok(return_type)
+}
+
 pub fn check_special_transfer_token(
     checker: &mut TypeChecker,
     args: &[SymbolicExpression],
@@ -22,9 +22,9 @@ use vm::analysis::errors::{CheckError, CheckErrors, CheckResult};
 use vm::errors::{Error as InterpError, RuntimeErrorType};
 use vm::functions::{handle_binding_list, NativeFunctions};
 use vm::types::{
-    BlockInfoProperty, FixedFunction, FunctionArg, FunctionSignature, FunctionType, PrincipalData,
-    TupleTypeSignature, TypeSignature, Value, BUFF_20, BUFF_32, BUFF_33, BUFF_64, BUFF_65,
-    MAX_VALUE_SIZE,
+    BlockInfoProperty, BurnBlockInfoProperty, FixedFunction, FunctionArg, FunctionSignature,
+    FunctionType, PrincipalData, TupleTypeSignature, TypeSignature, Value, BUFF_20, BUFF_32,
+    BUFF_33, BUFF_34, BUFF_64, BUFF_65, MAX_VALUE_SIZE,
 };
 use vm::{ClarityName, SymbolicExpression, SymbolicExpressionType};
 
@@ -464,6 +464,18 @@ fn check_contract_of(
     Ok(TypeSignature::PrincipalType)
 }
 
+fn check_is_reentrant(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    _context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(0, args)?;
+
+    runtime_cost(ClarityCostFunction::ContractOf, checker, 1)?;
+
+    Ok(TypeSignature::BoolType)
+}
+
 fn check_principal_of(
     checker: &mut TypeChecker,
     args: &[SymbolicExpression],
@@ -518,6 +530,27 @@ fn check_get_block_info(
     Ok(TypeSignature::new_option(block_info_prop.type_result())?)
 }
 
+fn check_get_burn_block_info(
+    checker: &mut TypeChecker,
+    args: &[SymbolicExpression],
+    context: &TypingContext,
+) -> TypeResult {
+    check_argument_count(2, args)?;
+
+    let block_info_prop_str = args[0].match_atom().ok_or(CheckError::new(
+        CheckErrors::GetBurnBlockInfoExpectPropertyName,
+    ))?;
+
+    let block_info_prop =
+        BurnBlockInfoProperty::lookup_by_name(block_info_prop_str).ok_or(CheckError::new(
+            CheckErrors::NoSuchBurnBlockInfoProperty(block_info_prop_str.to_string()),
+        ))?;
+
+    checker.type_check_expects(&args[1], context, &TypeSignature::UIntType)?;
+
+    Ok(TypeSignature::new_option(block_info_prop.type_result())?)
+}
+
 impl TypedNativeFunction {
     pub fn type_check_appliction(
         &self,
@@ -626,6 +659,20 @@ impl TypedNativeFunction {
                 )],
                 returns: TypeSignature::UIntType,
             }))),
+            GetStxAccount => Simple(SimpleNativeFunction(FunctionType::Fixed(FixedFunction {
+                args: vec![FunctionArg::new(
+                    TypeSignature::PrincipalType,
+                    ClarityName::try_from("owner".to_owned())
+                        .expect("FAIL: ClarityName failed to accept default arg name"),
+                )],
+                returns: TupleTypeSignature::try_from(vec![
+                    ("unlocked".into(), TypeSignature::UIntType),
+                    ("locked".into(), TypeSignature::UIntType),
+                    ("unlock-height".into(), TypeSignature::UIntType),
+                ])
+                .expect("FAIL: failed to construct stx-account type signature")
+                .into(),
+            }))),
             StxTransfer => Simple(SimpleNativeFunction(FunctionType::Fixed(FixedFunction {
                 args: vec![
                     FunctionArg::new(
@@ -650,6 +697,35 @@ impl TypedNativeFunction {
                 )
                 .unwrap(),
             }))),
+            StxTransferMemo => Simple(SimpleNativeFunction(FunctionType::Fixed(FixedFunction {
+                args: vec![
+                    FunctionArg::new(
+                        TypeSignature::UIntType,
+                        ClarityName::try_from("amount".to_owned())
+                            .expect("FAIL: ClarityName failed to accept default arg name"),
+                    ),
+                    FunctionArg::new(
+                        TypeSignature::PrincipalType,
+                        ClarityName::try_from("sender".to_owned())
+                            .expect("FAIL: ClarityName failed to accept default arg name"),
+                    ),
+                    FunctionArg::new(
+                        TypeSignature::PrincipalType,
+                        ClarityName::try_from("recipient".to_owned())
+                            .expect("FAIL: ClarityName failed to accept default arg name"),
+                    ),
+                    FunctionArg::new(
+                        BUFF_34,
+                        ClarityName::try_from("memo".to_owned())
+                            .expect("FAIL: ClarityName failed to accept default arg name"),
+                    ),
+                ],
+                returns: TypeSignature::new_response(
+                    TypeSignature::BoolType,
+                    TypeSignature::UIntType,
+                )
+                .unwrap(),
+            }))),
             StxBurn => Simple(SimpleNativeFunction(FunctionType::Fixed(FixedFunction {
                 args: vec![
                     FunctionArg::new(
@@ -674,6 +750,12 @@ impl TypedNativeFunction {
             TransferToken => Special(SpecialNativeFunction(&assets::check_special_transfer_token)),
             TransferAsset => Special(SpecialNativeFunction(&assets::check_special_transfer_asset)),
             MintAsset => Special(SpecialNativeFunction(&assets::check_special_mint_asset)),
+            MintAssetMany => Special(SpecialNativeFunction(
+                &assets::check_special_mint_asset_many,
+            )),
+            TransferAssetMany => Special(SpecialNativeFunction(
+                &assets::check_special_transfer_asset_many,
+            )),
             MintToken => Special(SpecialNativeFunction(&assets::check_special_mint_token)),
             Equals => Special(SpecialNativeFunction(&check_special_equals)),
             If => Special(SpecialNativeFunction(&check_special_if)),
@@ -702,8 +784,10 @@ impl TypedNativeFunction {
             AsContract => Special(SpecialNativeFunction(&check_special_as_contract)),
             ContractCall => Special(SpecialNativeFunction(&check_contract_call)),
             ContractOf => Special(SpecialNativeFunction(&check_contract_of)),
+            IsReentrant => Special(SpecialNativeFunction(&check_is_reentrant)),
             PrincipalOf => Special(SpecialNativeFunction(&check_principal_of)),
             GetBlockInfo => Special(SpecialNativeFunction(&check_get_block_info)),
+            GetBurnBlockInfo => Special(SpecialNativeFunction(&check_get_burn_block_info)),
             ConsSome => Special(SpecialNativeFunction(&options::check_special_some)),
             ConsOkay => Special(SpecialNativeFunction(&options::check_special_okay)),
             ConsError => Special(SpecialNativeFunction(&options::check_special_error)),
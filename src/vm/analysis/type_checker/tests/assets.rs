@@ -20,6 +20,9 @@ use vm::analysis::{mem_type_check, AnalysisDatabase};
 use vm::ast::parse;
 use vm::database::MemoryBackingStore;
 use vm::types::{QualifiedContractIdentifier, SequenceSubtype, StringSubtype, TypeSignature};
+use vm::ClarityVersion;
+
+use super::type_check_version_helper;
 
 fn string_ascii_type(size: u32) -> TypeSignature {
     TypeSignature::SequenceType(SequenceSubtype::StringType(StringSubtype::ASCII(
@@ -149,6 +152,12 @@ fn test_bad_asset_usage() {
         "(define-non-fungible-token stackaroos integer)",
         "(ft-mint? stackaroos 100 tx-sender)",
         "(ft-transfer? stackaroos 1 tx-sender tx-sender)",
+        "(nft-mint-many? stackoos (list \"a\") tx-sender)",
+        "(nft-mint-many? stacka-nfts \"a\" tx-sender)",
+        "(nft-mint-many? stacka-nfts (list u1 u2) tx-sender)",
+        "(nft-mint-many? stacka-nfts (list \"a\") u2)",
+        "(nft-transfer-many? stacka-nfts (list \"12345678901\") tx-sender tx-sender)",
+        "(nft-transfer-many? stacka-nfts (list \"a\") tx-sender u2)",
     ];
 
     let expected = [
@@ -184,6 +193,18 @@ fn test_bad_asset_usage() {
         CheckErrors::DefineNFTBadSignature.into(),
         CheckErrors::TypeError(TypeSignature::UIntType, TypeSignature::IntType),
         CheckErrors::TypeError(TypeSignature::UIntType, TypeSignature::IntType),
+        CheckErrors::NoSuchNFT("stackoos".to_string()),
+        CheckErrors::ExpectedListApplication,
+        CheckErrors::TypeError(
+            TypeSignature::list_of(string_ascii_type(10), 2).unwrap(),
+            TypeSignature::list_of(TypeSignature::UIntType, 2).unwrap(),
+        ),
+        CheckErrors::TypeError(TypeSignature::PrincipalType, TypeSignature::UIntType),
+        CheckErrors::TypeError(
+            TypeSignature::list_of(string_ascii_type(10), 1).unwrap(),
+            TypeSignature::list_of(string_ascii_type(11), 1).unwrap(),
+        ),
+        CheckErrors::TypeError(TypeSignature::PrincipalType, TypeSignature::UIntType),
     ];
 
     for (script, expected_err) in bad_scripts.iter().zip(expected.iter()) {
@@ -193,3 +214,46 @@ fn test_bad_asset_usage() {
         assert_eq!(&actual_err.err, expected_err);
     }
 }
+
+#[test]
+fn test_nft_batch_ops() {
+    let batch_result_type = |len| {
+        let results = TypeSignature::list_of(
+            TypeSignature::new_response(TypeSignature::BoolType, TypeSignature::UIntType).unwrap(),
+            len,
+        )
+        .unwrap();
+        TypeSignature::new_response(results.clone(), results).unwrap()
+    };
+
+    let good_scripts = [
+        "(nft-mint-many? stacka-nfts (list \"a\" \"b\") tx-sender)",
+        "(nft-mint-many? stacka-nfts (list) tx-sender)",
+        "(nft-transfer-many? stacka-nfts (list \"a\" \"b\" \"c\") tx-sender 'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G)",
+    ];
+    let expected = [
+        batch_result_type(2),
+        batch_result_type(0),
+        batch_result_type(3),
+    ];
+
+    for (script, expected_type) in good_scripts.iter().zip(expected.iter()) {
+        let tokens_contract = format!("{}\n{}", FIRST_CLASS_TOKENS, script);
+        let (type_opt, _) = mem_type_check(&tokens_contract).unwrap();
+        assert_eq!(&type_opt.unwrap(), expected_type);
+    }
+
+    // the batch operations were added in Clarity 2
+    for (script, function_name) in good_scripts
+        .iter()
+        .zip(["nft-mint-many?", "nft-mint-many?", "nft-transfer-many?"].iter())
+    {
+        let tokens_contract = format!("{}\n{}", FIRST_CLASS_TOKENS, script);
+        assert_eq!(
+            CheckErrors::UnknownFunction(function_name.to_string()),
+            type_check_version_helper(&tokens_contract, ClarityVersion::Clarity1)
+                .unwrap_err()
+                .err
+        );
+    }
+}
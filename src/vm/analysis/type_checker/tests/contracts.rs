@@ -17,7 +17,7 @@
 use assert_json_diff;
 use serde_json;
 
-use vm::analysis::errors::CheckErrors;
+use vm::analysis::errors::{CheckError, CheckErrors};
 use vm::analysis::mem_type_check;
 use vm::analysis::type_check;
 use vm::analysis::{contract_interface_builder::build_contract_interface, AnalysisDatabase};
@@ -367,7 +367,10 @@ fn test_names_tokens_contracts_interface() {
             { "name": "d-var3", "access": "variable", "type": { "buffer": { "length": 5 } } }
         ],
         "fungible_tokens": [],
-        "non_fungible_tokens": []
+        "non_fungible_tokens": [],
+        "defined_traits": [],
+        "implemented_traits": [],
+        "error_constants": []
     }"#).unwrap();
 
     eprintln!("{}", test_contract_json_str);
@@ -375,6 +378,101 @@ fn test_names_tokens_contracts_interface() {
     assert_json_eq!(test_contract_json, test_contract_json_expected);
 }
 
+#[test]
+fn test_contract_interface_traits_errors_tokens() {
+    let trait_contract_id = QualifiedContractIdentifier::local("token-trait").unwrap();
+    let impl_contract_id = QualifiedContractIdentifier::local("token").unwrap();
+
+    let trait_src = "
+        (define-trait token-trait
+          ((transfer? (principal principal uint) (response uint uint))
+           (get-balance (principal) (response uint uint))))";
+    let impl_src = "
+        (impl-trait .token-trait.token-trait)
+        (define-fungible-token stackaroo)
+        (define-non-fungible-token stacka-nft (buff 10))
+        (define-constant owner tx-sender)
+        (define-constant ERR-NOT-OWNER (err u100))
+        (define-constant ERR-BAD-NAME (err \"bad name\"))
+        (define-constant err-computed (err (+ u1 u2)))
+        (define-public (transfer? (sender principal) (recipient principal) (amount uint))
+          (begin
+            (asserts! (is-eq sender tx-sender) ERR-NOT-OWNER)
+            (ok amount)))
+        (define-public (get-balance (who principal))
+          (ok (ft-get-balance stackaroo who)))";
+
+    let mut trait_contract = parse(&trait_contract_id, trait_src).unwrap();
+    let mut impl_contract = parse(&impl_contract_id, impl_src).unwrap();
+    let mut marf = MemoryBackingStore::new();
+    let mut db = marf.as_analysis_db();
+
+    let (trait_analysis, impl_analysis) = db
+        .execute(|db| {
+            let trait_analysis = type_check(&trait_contract_id, &mut trait_contract, db, true)?;
+            let impl_analysis = type_check(&impl_contract_id, &mut impl_contract, db, true)?;
+            Ok::<_, CheckError>((trait_analysis, impl_analysis))
+        })
+        .unwrap();
+
+    let trait_json: serde_json::Value =
+        serde_json::from_str(&build_contract_interface(&trait_analysis).serialize()).unwrap();
+    let trait_json_expected: serde_json::Value = serde_json::from_str(
+        r#"[{
+            "name": "token-trait",
+            "functions": [
+                { "name": "get-balance",
+                  "args": [ "principal" ],
+                  "outputs": { "type": { "response": { "ok": "uint128", "error": "uint128" } } }
+                },
+                { "name": "transfer?",
+                  "args": [ "principal", "principal", "uint128" ],
+                  "outputs": { "type": { "response": { "ok": "uint128", "error": "uint128" } } }
+                }
+            ]
+        }]"#,
+    )
+    .unwrap();
+    assert_json_eq!(trait_json["defined_traits"].clone(), trait_json_expected);
+
+    let impl_json: serde_json::Value =
+        serde_json::from_str(&build_contract_interface(&impl_analysis).serialize()).unwrap();
+    let impl_json_expected: serde_json::Value = serde_json::from_str(
+        r#"{
+            "defined_traits": [],
+            "implemented_traits": [ "S1G2081040G2081040G2081040G208105NK8PE5.token-trait.token-trait" ],
+            "error_constants": [
+                { "name": "ERR-NOT-OWNER",
+                  "type": { "response": { "ok": "none", "error": "uint128" } },
+                  "value": "u100"
+                },
+                { "name": "ERR-BAD-NAME",
+                  "type": { "response": { "ok": "none", "error": { "string-ascii": { "length": 8 } } } },
+                  "value": "\"bad name\""
+                },
+                { "name": "err-computed",
+                  "type": { "response": { "ok": "none", "error": "uint128" } },
+                  "value": null
+                }
+            ],
+            "fungible_tokens": [ { "name": "stackaroo" } ],
+            "non_fungible_tokens": [ { "name": "stacka-nft", "type": { "buffer": { "length": 10 } } } ]
+        }"#,
+    )
+    .unwrap();
+    for field in [
+        "defined_traits",
+        "implemented_traits",
+        "error_constants",
+        "fungible_tokens",
+        "non_fungible_tokens",
+    ]
+    .iter()
+    {
+        assert_json_eq!(impl_json[field].clone(), impl_json_expected[field].clone());
+    }
+}
+
 #[test]
 fn test_names_tokens_contracts() {
     let tokens_contract_id = QualifiedContractIdentifier::local("tokens").unwrap();
@@ -16,6 +16,7 @@
 
 use vm::analysis::errors::CheckErrors;
 use vm::analysis::mem_type_check;
+use vm::analysis::run_analysis;
 use vm::analysis::type_check;
 use vm::analysis::type_checker::{TypeChecker, TypeResult, TypingContext};
 use vm::analysis::types::ContractAnalysis;
@@ -23,15 +24,17 @@ use vm::analysis::AnalysisDatabase;
 use vm::ast::errors::ParseErrors;
 use vm::ast::{build_ast, parse};
 use vm::contexts::OwnedEnvironment;
+use vm::costs::LimitedCostTracker;
 use vm::representations::SymbolicExpression;
 use vm::types::{
     FixedFunction, FunctionType, PrincipalData, QualifiedContractIdentifier, TypeSignature, Value,
-    BUFF_32, BUFF_64,
+    BUFF_32, BUFF_34, BUFF_64,
 };
 
 use vm::database::MemoryBackingStore;
 use vm::types::TypeSignature::{BoolType, IntType, PrincipalType, SequenceType, UIntType};
 use vm::types::{SequenceSubtype::*, StringSubtype::*};
+use vm::ClarityVersion;
 
 use std::convert::TryInto;
 
@@ -42,6 +45,32 @@ fn type_check_helper(exp: &str) -> TypeResult {
     mem_type_check(exp).map(|(type_sig_opt, _)| type_sig_opt.unwrap())
 }
 
+/// Type check a snippet as a contract written in the given Clarity version
+fn type_check_version_helper(exp: &str, version: ClarityVersion) -> TypeResult {
+    let contract_identifier = QualifiedContractIdentifier::transient();
+    let mut contract = build_ast(&contract_identifier, exp, &mut (), version)
+        .unwrap()
+        .expressions;
+    let mut marf = MemoryBackingStore::new();
+    let mut analysis_db = marf.as_analysis_db();
+    let contract_analysis = run_analysis(
+        &contract_identifier,
+        &mut contract,
+        &mut analysis_db,
+        false,
+        LimitedCostTracker::new_free(),
+        version,
+    )
+    .map_err(|(e, _)| e)?;
+    Ok(contract_analysis
+        .type_map
+        .as_ref()
+        .unwrap()
+        .get_type(&contract_analysis.expressions.last().unwrap())
+        .cloned()
+        .unwrap())
+}
+
 fn buff_type(size: u32) -> TypeSignature {
     TypeSignature::SequenceType(BufferType(size.try_into().unwrap())).into()
 }
@@ -94,6 +123,50 @@ fn test_get_block_info() {
     }
 }
 
+#[test]
+fn test_get_burn_block_info() {
+    let good = [
+        "(get-burn-block-info? time u1)",
+        "(get-burn-block-info? median-time-past (* u2 u3))",
+        "(get-burn-block-info? header-hash u1)",
+    ];
+    let expected = ["(optional uint)", "(optional uint)", "(optional (buff 32))"];
+
+    let bad = [
+        "(get-burn-block-info? none u1)",
+        "(get-burn-block-info? time true)",
+        "(get-burn-block-info? time 1)",
+        "(get-burn-block-info? time)",
+        "(get-burn-block-info? u1 u1)",
+    ];
+    let bad_expected = [
+        CheckErrors::NoSuchBurnBlockInfoProperty("none".to_string()),
+        CheckErrors::TypeError(UIntType, BoolType),
+        CheckErrors::TypeError(UIntType, IntType),
+        CheckErrors::IncorrectArgumentCount(2, 1),
+        CheckErrors::GetBurnBlockInfoExpectPropertyName,
+    ];
+
+    for (good_test, expected) in good.iter().zip(expected.iter()) {
+        assert_eq!(
+            expected,
+            &format!("{}", type_check_helper(&good_test).unwrap())
+        );
+    }
+
+    for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
+        assert_eq!(expected, &type_check_helper(&bad_test).unwrap_err().err);
+    }
+
+    // get-burn-block-info? was added in Clarity 2
+    assert_eq!(
+        CheckErrors::UnknownFunction("get-burn-block-info?".to_string()),
+        type_check_version_helper(good[0], ClarityVersion::Clarity1)
+            .unwrap_err()
+            .err
+    );
+}
+
 #[test]
 fn test_define_trait() {
     let good = [
@@ -133,7 +206,13 @@ fn test_define_trait() {
 
     let contract_identifier = QualifiedContractIdentifier::transient();
     for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
-        let res = build_ast(&contract_identifier, bad_test, &mut ()).unwrap_err();
+        let res = build_ast(
+            &contract_identifier,
+            bad_test,
+            &mut (),
+            ClarityVersion::latest(),
+        )
+        .unwrap_err();
         assert_eq!(expected, &res.err);
     }
 }
@@ -155,7 +234,13 @@ fn test_use_trait() {
 
     let contract_identifier = QualifiedContractIdentifier::transient();
     for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
-        let res = build_ast(&contract_identifier, bad_test, &mut ()).unwrap_err();
+        let res = build_ast(
+            &contract_identifier,
+            bad_test,
+            &mut (),
+            ClarityVersion::latest(),
+        )
+        .unwrap_err();
         assert_eq!(expected, &res.err);
     }
 }
@@ -170,7 +255,13 @@ fn test_impl_trait() {
 
     let contract_identifier = QualifiedContractIdentifier::transient();
     for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
-        let res = build_ast(&contract_identifier, bad_test, &mut ()).unwrap_err();
+        let res = build_ast(
+            &contract_identifier,
+            bad_test,
+            &mut (),
+            ClarityVersion::latest(),
+        )
+        .unwrap_err();
         assert_eq!(expected, &res.err);
     }
 }
@@ -181,8 +272,16 @@ fn test_stx_ops() {
         "(stx-burn? u10 'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G)",
         "(stx-transfer? u10 tx-sender 'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G)",
         "(stx-get-balance 'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G)",
+        "(stx-account 'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G)",
+        "(stx-transfer-memo? u10 tx-sender 'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G 0x0102)",
+    ];
+    let expected = [
+        "(response bool uint)",
+        "(response bool uint)",
+        "uint",
+        "(tuple (locked uint) (unlock-height uint) (unlocked uint))",
+        "(response bool uint)",
     ];
-    let expected = ["(response bool uint)", "(response bool uint)", "uint"];
 
     let bad = [
         "(stx-transfer? u4 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
@@ -194,7 +293,11 @@ fn test_stx_ops() {
         "(stx-burn? u4 true)",
         "(stx-burn? u4 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
         "(stx-get-balance true)",
-        "(stx-get-balance 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)"
+        "(stx-get-balance 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
+        "(stx-account true)",
+        "(stx-account 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
+        "(stx-transfer-memo? u4 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
+        "(stx-transfer-memo? u4 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR u1)",
     ];
     let bad_expected = [
         CheckErrors::IncorrectArgumentCount(3, 2),
@@ -207,6 +310,10 @@ fn test_stx_ops() {
         CheckErrors::IncorrectArgumentCount(2, 3),
         CheckErrors::TypeError(PrincipalType, BoolType),
         CheckErrors::IncorrectArgumentCount(1, 2),
+        CheckErrors::TypeError(PrincipalType, BoolType),
+        CheckErrors::IncorrectArgumentCount(1, 2),
+        CheckErrors::IncorrectArgumentCount(4, 3),
+        CheckErrors::TypeError(BUFF_34.clone(), UIntType),
     ];
 
     for (good_test, expected) in good.iter().zip(expected.iter()) {
@@ -219,6 +326,14 @@ fn test_stx_ops() {
     for (bad_test, expected) in bad.iter().zip(bad_expected.iter()) {
         assert_eq!(expected, &type_check_helper(&bad_test).unwrap_err().err);
     }
+
+    // stx-transfer-memo? was added in Clarity 2
+    assert_eq!(
+        CheckErrors::UnknownFunction("stx-transfer-memo?".to_string()),
+        type_check_version_helper(good[4], ClarityVersion::Clarity1)
+            .unwrap_err()
+            .err
+    );
 }
 
 #[test]
@@ -431,6 +546,26 @@ fn test_at_block() {
     }
 }
 
+#[test]
+fn test_is_reentrant() {
+    assert_eq!(
+        "bool",
+        &format!("{}", type_check_helper("(is-reentrant)").unwrap())
+    );
+    assert_eq!(
+        CheckErrors::IncorrectArgumentCount(0, 1),
+        type_check_helper("(is-reentrant u1)").unwrap_err().err
+    );
+
+    // is-reentrant was added in Clarity 2
+    assert_eq!(
+        CheckErrors::UnknownFunction("is-reentrant".to_string()),
+        type_check_version_helper("(is-reentrant)", ClarityVersion::Clarity1)
+            .unwrap_err()
+            .err
+    );
+}
+
 #[test]
 fn test_trait_reference_unknown() {
     let bad = [(
@@ -440,7 +575,13 @@ fn test_trait_reference_unknown() {
 
     let contract_identifier = QualifiedContractIdentifier::transient();
     for (bad_test, expected) in bad.iter() {
-        let res = build_ast(&contract_identifier, bad_test, &mut ()).unwrap_err();
+        let res = build_ast(
+            &contract_identifier,
+            bad_test,
+            &mut (),
+            ClarityVersion::latest(),
+        )
+        .unwrap_err();
         assert_eq!(expected, &res.err);
     }
 }
@@ -22,7 +22,7 @@ use vm::analysis::type_checker::contexts::TypeMap;
 use vm::costs::{CostTracker, ExecutionCost, LimitedCostTracker};
 use vm::types::signatures::FunctionSignature;
 use vm::types::{FunctionType, QualifiedContractIdentifier, TraitIdentifier, TypeSignature};
-use vm::{ClarityName, SymbolicExpression};
+use vm::{ClarityName, ClarityVersion, SymbolicExpression};
 
 const DESERIALIZE_FAIL_MESSAGE: &str =
     "PANIC: Failed to deserialize bad database data in contract analysis.";
@@ -50,6 +50,8 @@ pub struct ContractAnalysis {
     pub defined_traits: BTreeMap<ClarityName, BTreeMap<ClarityName, FunctionSignature>>,
     pub implemented_traits: BTreeSet<TraitIdentifier>,
     pub contract_interface: Option<ContractInterface>,
+    #[serde(default)]
+    pub clarity_version: ClarityVersion,
     #[serde(skip)]
     pub expressions: Vec<SymbolicExpression>,
     #[serde(skip)]
@@ -63,10 +65,12 @@ impl ContractAnalysis {
         contract_identifier: QualifiedContractIdentifier,
         expressions: Vec<SymbolicExpression>,
         cost_track: LimitedCostTracker,
+        clarity_version: ClarityVersion,
     ) -> ContractAnalysis {
         ContractAnalysis {
             contract_identifier,
             expressions,
+            clarity_version,
             type_map: None,
             contract_interface: None,
             private_function_types: BTreeMap::new(),
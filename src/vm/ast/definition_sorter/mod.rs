@@ -28,6 +28,7 @@ use vm::representations::PreSymbolicExpressionType::{
 };
 use vm::representations::{ClarityName, PreSymbolicExpression};
 use vm::types::Value;
+use vm::ClarityVersion;
 
 #[cfg(test)]
 mod tests;
@@ -35,13 +36,15 @@ mod tests;
 pub struct DefinitionSorter {
     graph: Graph,
     top_level_expressions_map: HashMap<ClarityName, TopLevelExpressionIndex>,
+    clarity_version: ClarityVersion,
 }
 
 impl<'a> DefinitionSorter {
-    fn new() -> Self {
+    fn new(clarity_version: ClarityVersion) -> Self {
         Self {
             top_level_expressions_map: HashMap::new(),
             graph: Graph::new(),
+            clarity_version,
         }
     }
 
@@ -49,13 +52,13 @@ impl<'a> DefinitionSorter {
         contract_ast: &mut ContractAST,
         accounting: &mut T,
     ) -> ParseResult<()> {
-        let mut pass = DefinitionSorter::new();
+        let mut pass = DefinitionSorter::new(contract_ast.clarity_version);
         pass.run(contract_ast, accounting)?;
         Ok(())
     }
 
     pub fn run_pass_free(contract_ast: &mut ContractAST) -> ParseResult<()> {
-        let mut pass = DefinitionSorter::new();
+        let mut pass = DefinitionSorter::new(contract_ast.clarity_version);
         pass.run(contract_ast, &mut LimitedCostTracker::new_free())?;
         Ok(())
     }
@@ -210,7 +213,10 @@ impl<'a> DefinitionSorter {
                                 }
                             }
                         } else if let Some(native_function) =
-                            NativeFunctions::lookup_by_name(function_name)
+                            NativeFunctions::lookup_by_name_at_version(
+                                function_name,
+                                self.clarity_version,
+                            )
                         {
                             match native_function {
                                 NativeFunctions::ContractCall => {
@@ -28,6 +28,7 @@ use vm::errors::{Error, RuntimeErrorType};
 
 use vm::representations::SymbolicExpression;
 use vm::types::QualifiedContractIdentifier;
+use vm::ClarityVersion;
 
 use self::definition_sorter::DefinitionSorter;
 use self::errors::ParseResult;
@@ -44,7 +45,12 @@ pub fn parse(
     contract_identifier: &QualifiedContractIdentifier,
     source_code: &str,
 ) -> Result<Vec<SymbolicExpression>, Error> {
-    let ast = build_ast(contract_identifier, source_code, &mut ())?;
+    let ast = build_ast(
+        contract_identifier,
+        source_code,
+        &mut (),
+        ClarityVersion::latest(),
+    )?;
     Ok(ast.expressions)
 }
 
@@ -52,6 +58,7 @@ pub fn build_ast<T: CostTracker>(
     contract_identifier: &QualifiedContractIdentifier,
     source_code: &str,
     cost_track: &mut T,
+    clarity_version: ClarityVersion,
 ) -> ParseResult<ContractAST> {
     runtime_cost(
         ClarityCostFunction::AstParse,
@@ -59,7 +66,11 @@ pub fn build_ast<T: CostTracker>(
         source_code.len() as u64,
     )?;
     let pre_expressions = parser::parse(source_code)?;
-    let mut contract_ast = ContractAST::new(contract_identifier.clone(), pre_expressions);
+    let mut contract_ast = ContractAST::new(
+        contract_identifier.clone(),
+        pre_expressions,
+        clarity_version,
+    );
     StackDepthChecker::run_pass(&mut contract_ast)?;
     ExpressionIdentifier::run_pre_expression_pass(&mut contract_ast)?;
     DefinitionSorter::run_pass(&mut contract_ast, cost_track)?;
@@ -115,6 +126,7 @@ mod tests {
             &QualifiedContractIdentifier::transient(),
             &progn,
             &mut cost_track,
+            ClarityVersion::latest(),
         )
         .unwrap();
 
@@ -142,6 +154,7 @@ mod tests {
             &QualifiedContractIdentifier::transient(),
             &progn,
             &mut cost_track,
+            ClarityVersion::latest(),
         )
         .unwrap()
         .expressions;
@@ -20,7 +20,7 @@ use vm::ast::errors::ParseResult;
 use vm::representations::{PreSymbolicExpression, SymbolicExpression, TraitDefinition};
 use vm::types::signatures::FunctionSignature;
 use vm::types::{QualifiedContractIdentifier, TraitIdentifier};
-use vm::ClarityName;
+use vm::{ClarityName, ClarityVersion};
 
 pub trait BuildASTPass {
     fn run_pass(contract_ast: &mut ContractAST) -> ParseResult<()>;
@@ -34,16 +34,20 @@ pub struct ContractAST {
     pub top_level_expression_sorting: Option<Vec<usize>>,
     pub referenced_traits: HashMap<ClarityName, TraitDefinition>,
     pub implemented_traits: HashSet<TraitIdentifier>,
+    #[serde(default)]
+    pub clarity_version: ClarityVersion,
 }
 
 impl ContractAST {
     pub fn new(
         contract_identifier: QualifiedContractIdentifier,
         pre_expressions: Vec<PreSymbolicExpression>,
+        clarity_version: ClarityVersion,
     ) -> ContractAST {
         ContractAST {
             contract_identifier,
             pre_expressions,
+            clarity_version,
             expressions: Vec::new(),
             top_level_expression_sorting: Some(Vec::new()),
             referenced_traits: HashMap::new(),
@@ -26,6 +26,7 @@ use vm::costs::{cost_functions, runtime_cost, SimpleCostSpecification};
 use vm::analysis::errors::CheckErrors;
 use vm::contexts::ContractContext;
 use vm::costs::cost_functions::ClarityCostFunction;
+use vm::costs::profiler::ProfileFrame;
 use vm::errors::{check_argument_count, Error, InterpreterResult as Result};
 use vm::representations::{ClarityName, SymbolicExpression};
 use vm::types::Value::UInt;
@@ -116,6 +117,20 @@ impl DefinedFunction {
     }
 
     pub fn execute_apply(&self, args: &[Value], env: &mut Environment) -> Result<Value> {
+        let profiling = env.global_context.cost_track.is_profiling();
+        if profiling {
+            let frame =
+                ProfileFrame::function(&env.contract_context.contract_identifier, &self.name);
+            env.global_context.cost_track.profile_enter(frame);
+        }
+        let result = self.inner_execute_apply(args, env);
+        if profiling {
+            env.global_context.cost_track.profile_exit();
+        }
+        result
+    }
+
+    fn inner_execute_apply(&self, args: &[Value], env: &mut Environment) -> Result<Value> {
         runtime_cost(
             ClarityCostFunction::UserFunctionApplication,
             env,
@@ -28,7 +28,7 @@ use vm::database::{
 use vm::errors::Error as InterpreterError;
 use vm::representations::SymbolicExpression;
 use vm::types::{
-    AssetIdentifier, PrincipalData, QualifiedContractIdentifier, TypeSignature, Value,
+    AssetIdentifier, BuffData, PrincipalData, QualifiedContractIdentifier, TypeSignature, Value,
 };
 
 use chainstate::burn::BlockHeaderHash;
@@ -40,7 +40,9 @@ use chainstate::stacks::StacksBlockId;
 use chainstate::stacks::StacksMicroblockHeader;
 
 #[cfg(test)]
-use chainstate::stacks::boot::{BOOT_CODE_COSTS, STACKS_BOOT_COST_CONTRACT};
+use chainstate::stacks::boot::{
+    BOOT_CODE_COSTS, BOOT_CODE_COSTS_2, STACKS_BOOT_COST_2_CONTRACT, STACKS_BOOT_COST_CONTRACT,
+};
 
 use std::error;
 use std::fmt;
@@ -228,6 +230,16 @@ impl ClarityBlockConnection<'_> {
             None => ExecutionCost::zero(),
         }
     }
+
+    /// Take the MARF key/value pairs written by this block so far.
+    pub fn take_marf_writes(&mut self) -> Vec<(String, String)> {
+        self.datastore.take_block_writes()
+    }
+
+    /// Take the contract metadata key/value pairs written by this block so far.
+    pub fn take_metadata_writes(&mut self) -> Vec<(String, String)> {
+        self.datastore.take_block_metadata_writes()
+    }
 }
 
 impl ClarityInstance {
@@ -349,17 +361,19 @@ impl ClarityInstance {
         };
 
         conn.as_transaction(|clarity_db| {
-            let (ast, _) = clarity_db
-                .analyze_smart_contract(&*STACKS_BOOT_COST_CONTRACT, BOOT_CODE_COSTS)
-                .unwrap();
-            clarity_db
-                .initialize_smart_contract(
-                    &*STACKS_BOOT_COST_CONTRACT,
-                    &ast,
-                    BOOT_CODE_COSTS,
-                    |_, _| false,
-                )
-                .unwrap();
+            for (contract_id, code) in [
+                (&*STACKS_BOOT_COST_CONTRACT, BOOT_CODE_COSTS),
+                (&*STACKS_BOOT_COST_2_CONTRACT, BOOT_CODE_COSTS_2),
+            ]
+            .iter()
+            {
+                let (ast, _) = clarity_db
+                    .analyze_smart_contract(contract_id, code)
+                    .unwrap();
+                clarity_db
+                    .initialize_smart_contract(contract_id, &ast, code, |_, _| false)
+                    .unwrap();
+            }
         });
 
         conn
@@ -751,15 +765,39 @@ impl<'a> ClarityTransactionConnection<'a> {
         }
     }
 
-    /// Analyze a provided smart contract, but do not write the analysis to the AnalysisDatabase
+    /// Reload the cost functions of the boot cost contracts into the block's cost tracker.
+    /// Used when a network upgrade deploys a new cost contract partway through a block.
+    pub fn reload_boot_costs(&mut self) -> Result<(), Error> {
+        using!(
+            self.cost_track,
+            "cost tracker",
+            |mut cost_track: LimitedCostTracker| {
+                let result = self.with_clarity_db(|db| {
+                    cost_track
+                        .load_boot_costs(db)
+                        .map_err(|e| InterpreterError::from(e).into())
+                });
+                (cost_track, result)
+            }
+        )
+    }
+
+    /// Analyze a provided smart contract, but do not write the analysis to the AnalysisDatabase.
+    /// The contract is written in the Clarity version that this fork deploys contracts with.
     pub fn analyze_smart_contract(
         &mut self,
         identifier: &QualifiedContractIdentifier,
         contract_content: &str,
     ) -> Result<(ContractAST, ContractAnalysis), Error> {
+        let clarity_version = self.with_clarity_db_readonly(|db| db.get_clarity_version());
         using!(self.cost_track, "cost tracker", |mut cost_track| {
             self.inner_with_analysis_db(|db| {
-                let ast_result = ast::build_ast(identifier, contract_content, &mut cost_track);
+                let ast_result = ast::build_ast(
+                    identifier,
+                    contract_content,
+                    &mut cost_track,
+                    clarity_version,
+                );
 
                 let mut contract_ast = match ast_result {
                     Ok(x) => x,
@@ -772,6 +810,7 @@ impl<'a> ClarityTransactionConnection<'a> {
                     db,
                     false,
                     cost_track,
+                    clarity_version,
                 );
 
                 match result {
@@ -867,9 +906,14 @@ impl<'a> ClarityTransactionConnection<'a> {
         from: &PrincipalData,
         to: &PrincipalData,
         amount: u128,
+        memo: &BuffData,
     ) -> Result<(Value, AssetMap, Vec<StacksTransactionEvent>), Error> {
         self.with_abort_callback(
-            |vm_env| vm_env.stx_transfer(from, to, amount).map_err(Error::from),
+            |vm_env| {
+                vm_env
+                    .stx_transfer(from, to, amount, memo)
+                    .map_err(Error::from)
+            },
             |_, _| false,
         )
         .and_then(|(value, assets, events, _)| Ok((value, assets, events)))
@@ -1016,6 +1060,8 @@ impl<'a> ClarityTransactionConnection<'a> {
 mod tests {
     use super::*;
     use chainstate::stacks::index::storage::TrieFileStorage;
+    use chainstate::stacks::StacksBlockHeader;
+    use core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
     use rusqlite::NO_PARAMS;
     use std::fs;
     use vm::analysis::errors::CheckErrors;
@@ -1256,6 +1302,110 @@ mod tests {
         assert!(marf.get_contract_hash(&contract_identifier).is_ok());
     }
 
+    #[test]
+    pub fn test_clarity_version_gates_natives() {
+        let marf = MarfedKV::temporary();
+        let mut clarity_instance = ClarityInstance::new(marf, ExecutionCost::max_value());
+
+        let shadowing_id = QualifiedContractIdentifier::local("shadowing").unwrap();
+        let native_id = QualifiedContractIdentifier::local("native").unwrap();
+
+        // defines its own stx-account, which Clarity 1 allows
+        let shadowing_contract =
+            "(define-read-only (stx-account (p principal)) (stx-get-balance p))
+             (define-read-only (get-account) (stx-account tx-sender))";
+        // uses the stx-account native, which Clarity 2 added
+        let native_contract = "(define-read-only (get-account) (stx-account tx-sender))";
+
+        // stx-account reads the burnchain block height, so build on the first burnchain block
+        let genesis_id = StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        );
+        clarity_instance
+            .begin_test_genesis_block(
+                &StacksBlockId::sentinel(),
+                &genesis_id,
+                &NULL_HEADER_DB,
+                &NULL_BURN_STATE_DB,
+            )
+            .commit_block();
+
+        let mut conn = clarity_instance.begin_block(
+            &genesis_id,
+            &StacksBlockId([1 as u8; 32]),
+            &NULL_HEADER_DB,
+            &NULL_BURN_STATE_DB,
+        );
+
+        let deploy = |conn: &mut ClarityBlockConnection,
+                      contract_id: &QualifiedContractIdentifier,
+                      contract: &str| {
+            conn.as_transaction(|tx| {
+                let (ct_ast, ct_analysis) = tx.analyze_smart_contract(contract_id, contract)?;
+                tx.initialize_smart_contract(contract_id, &ct_ast, contract, |_, _| false)?;
+                tx.save_analysis(contract_id, &ct_analysis)
+                    .expect("FATAL: failed to store contract analysis");
+                Ok::<_, Error>(())
+            })
+        };
+        let get_account = |conn: &mut ClarityBlockConnection,
+                           contract_id: &QualifiedContractIdentifier| {
+            conn.as_transaction(|tx| {
+                tx.run_contract_call(
+                    &StandardPrincipalData::transient().into(),
+                    contract_id,
+                    "get-account",
+                    &[],
+                    |_, _| false,
+                )
+            })
+            .unwrap()
+            .0
+        };
+
+        // before Stacks 2.1, contracts are Clarity 1 contracts
+        deploy(&mut conn, &shadowing_id, shadowing_contract).unwrap();
+        assert_eq!(get_account(&mut conn, &shadowing_id), Value::UInt(0));
+
+        match deploy(&mut conn, &native_id, native_contract).unwrap_err() {
+            Error::Analysis(e) => assert_eq!(
+                e.err,
+                CheckErrors::UnknownFunction("stx-account".to_string())
+            ),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        conn.as_transaction(|tx| {
+            tx.with_clarity_db(|db| {
+                db.set_stacks_2_1_active(0);
+                Ok(())
+            })
+        })
+        .unwrap();
+
+        // after Stacks 2.1, new contracts are Clarity 2 contracts and can use the native...
+        deploy(&mut conn, &native_id, native_contract).unwrap();
+        assert!(get_account(&mut conn, &native_id)
+            .expect_tuple()
+            .get("locked")
+            .is_ok());
+
+        // ...but can no longer shadow it
+        let shadowing_2_id = QualifiedContractIdentifier::local("shadowing-2").unwrap();
+        match deploy(&mut conn, &shadowing_2_id, shadowing_contract).unwrap_err() {
+            Error::Interpreter(InterpreterError::Unchecked(CheckErrors::NameAlreadyUsed(name))) => {
+                assert_eq!(name, "stx-account")
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // and the Clarity 1 contract keeps calling its own function
+        assert_eq!(get_account(&mut conn, &shadowing_id), Value::UInt(0));
+
+        conn.commit_block();
+    }
+
     #[test]
     pub fn test_block_roll_back() {
         let marf = MarfedKV::temporary();
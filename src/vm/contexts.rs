@@ -34,10 +34,10 @@ use vm::representations::{ClarityName, ContractName, SymbolicExpression};
 use vm::stx_transfer_consolidated;
 use vm::types::signatures::FunctionSignature;
 use vm::types::{
-    AssetIdentifier, PrincipalData, QualifiedContractIdentifier, TraitIdentifier, TypeSignature,
-    Value,
+    AssetIdentifier, BuffData, PrincipalData, QualifiedContractIdentifier, TraitIdentifier,
+    TypeSignature, Value,
 };
-use vm::{eval, is_reserved};
+use vm::{eval, is_reserved, ClarityVersion};
 
 use chainstate::burn::{BlockHeaderHash, VRFSeed};
 use chainstate::stacks::db::StacksChainState;
@@ -118,6 +118,8 @@ pub struct ContractContext {
     //  used for ensuring that they never are defined twice.
     pub persisted_names: HashSet<ClarityName>,
     pub data_size: u64,
+    #[serde(default)]
+    pub clarity_version: ClarityVersion,
 }
 
 pub struct LocalContext<'a> {
@@ -132,6 +134,8 @@ pub struct CallStack {
     stack: Vec<FunctionIdentifier>,
     set: HashSet<FunctionIdentifier>,
     apply_depth: usize,
+    // how many public function calls into each contract are in progress
+    contract_entries: HashMap<QualifiedContractIdentifier, usize>,
 }
 
 pub type StackTrace = Vec<FunctionIdentifier>;
@@ -437,7 +441,10 @@ impl<'a> OwnedEnvironment<'a> {
     pub fn new(database: ClarityDatabase<'a>) -> OwnedEnvironment<'a> {
         OwnedEnvironment {
             context: GlobalContext::new(database, LimitedCostTracker::new_free()),
-            default_contract: ContractContext::new(QualifiedContractIdentifier::transient()),
+            default_contract: ContractContext::new(
+                QualifiedContractIdentifier::transient(),
+                ClarityVersion::latest(),
+            ),
             call_stack: CallStack::new(),
         }
     }
@@ -449,7 +456,10 @@ impl<'a> OwnedEnvironment<'a> {
 
         OwnedEnvironment {
             context: GlobalContext::new(database, cost_track),
-            default_contract: ContractContext::new(QualifiedContractIdentifier::transient()),
+            default_contract: ContractContext::new(
+                QualifiedContractIdentifier::transient(),
+                ClarityVersion::latest(),
+            ),
             call_stack: CallStack::new(),
         }
     }
@@ -457,7 +467,10 @@ impl<'a> OwnedEnvironment<'a> {
     pub fn new_free(database: ClarityDatabase<'a>) -> OwnedEnvironment<'a> {
         OwnedEnvironment {
             context: GlobalContext::new(database, LimitedCostTracker::new_free()),
-            default_contract: ContractContext::new(QualifiedContractIdentifier::transient()),
+            default_contract: ContractContext::new(
+                QualifiedContractIdentifier::transient(),
+                ClarityVersion::latest(),
+            ),
             call_stack: CallStack::new(),
         }
     }
@@ -468,7 +481,10 @@ impl<'a> OwnedEnvironment<'a> {
     ) -> OwnedEnvironment<'a> {
         OwnedEnvironment {
             context: GlobalContext::new(database, cost_tracker),
-            default_contract: ContractContext::new(QualifiedContractIdentifier::transient()),
+            default_contract: ContractContext::new(
+                QualifiedContractIdentifier::transient(),
+                ClarityVersion::latest(),
+            ),
             call_stack: CallStack::new(),
         }
     }
@@ -558,9 +574,10 @@ impl<'a> OwnedEnvironment<'a> {
         from: &PrincipalData,
         to: &PrincipalData,
         amount: u128,
+        memo: &BuffData,
     ) -> Result<(Value, AssetMap, Vec<StacksTransactionEvent>)> {
         self.execute_in_env(Value::Principal(from.clone()), |exec_env| {
-            exec_env.stx_transfer(from, to, amount)
+            exec_env.stx_transfer(from, to, amount, memo)
         })
     }
 
@@ -762,7 +779,8 @@ impl<'a, 'b> Environment<'a, 'b> {
         contract_identifier: &QualifiedContractIdentifier,
         program: &str,
     ) -> Result<Value> {
-        let parsed = ast::build_ast(contract_identifier, program, self)?.expressions;
+        let parsed = ast::build_ast(contract_identifier, program, self, ClarityVersion::latest())?
+            .expressions;
 
         if parsed.len() < 1 {
             return Err(RuntimeErrorType::ParseError(
@@ -798,7 +816,8 @@ impl<'a, 'b> Environment<'a, 'b> {
     pub fn eval_raw(&mut self, program: &str) -> Result<Value> {
         let contract_id = QualifiedContractIdentifier::transient();
 
-        let parsed = ast::build_ast(&contract_id, program, self)?.expressions;
+        let parsed =
+            ast::build_ast(&contract_id, program, self, ClarityVersion::latest())?.expressions;
         if parsed.len() < 1 {
             return Err(RuntimeErrorType::ParseError(
                 "Expected a program of at least length 1".to_string(),
@@ -869,7 +888,9 @@ impl<'a, 'b> Environment<'a, 'b> {
                 return Err(CheckErrors::CircularReference(vec![func_identifier.to_string()]).into())
             }
             self.call_stack.insert(&func_identifier, true);
+            self.call_stack.enter_contract(contract_identifier);
             let res = self.execute_function_as_transaction(&func, &args, Some(&contract.contract_context));
+            self.call_stack.exit_contract(contract_identifier)?;
             self.call_stack.remove(&func_identifier, true)?;
 
             match res {
@@ -952,7 +973,12 @@ impl<'a, 'b> Environment<'a, 'b> {
         contract_identifier: QualifiedContractIdentifier,
         contract_content: &str,
     ) -> Result<()> {
-        let contract_ast = ast::build_ast(&contract_identifier, contract_content, self)?;
+        let contract_ast = ast::build_ast(
+            &contract_identifier,
+            contract_content,
+            self,
+            ClarityVersion::latest(),
+        )?;
         self.initialize_contract_from_ast(contract_identifier, &contract_ast, &contract_content)
     }
 
@@ -1030,9 +1056,10 @@ impl<'a, 'b> Environment<'a, 'b> {
         from: &PrincipalData,
         to: &PrincipalData,
         amount: u128,
+        memo: &BuffData,
     ) -> Result<Value> {
         self.global_context.begin();
-        let result = stx_transfer_consolidated(self, from, to, amount);
+        let result = stx_transfer_consolidated(self, from, to, amount, memo);
         match result {
             Ok(value) => match value.clone().expect_result() {
                 Ok(_) => {
@@ -1094,11 +1121,14 @@ impl<'a, 'b> Environment<'a, 'b> {
         sender: PrincipalData,
         recipient: PrincipalData,
         amount: u128,
+        memo: BuffData,
     ) -> Result<()> {
         let event_data = STXTransferEventData {
             sender,
             recipient,
             amount,
+            memo,
+            long_memo: None,
         };
 
         if let Some(batch) = self.global_context.event_batches.last_mut() {
@@ -1380,7 +1410,10 @@ impl<'a> GlobalContext<'a> {
 }
 
 impl ContractContext {
-    pub fn new(contract_identifier: QualifiedContractIdentifier) -> Self {
+    pub fn new(
+        contract_identifier: QualifiedContractIdentifier,
+        clarity_version: ClarityVersion,
+    ) -> Self {
         Self {
             contract_identifier,
             variables: HashMap::new(),
@@ -1389,9 +1422,14 @@ impl ContractContext {
             implemented_traits: HashSet::new(),
             persisted_names: HashSet::new(),
             data_size: 0,
+            clarity_version,
         }
     }
 
+    pub fn get_clarity_version(&self) -> ClarityVersion {
+        self.clarity_version
+    }
+
     pub fn lookup_variable(&self, name: &str) -> Option<&Value> {
         self.variables.get(name)
     }
@@ -1412,7 +1450,7 @@ impl ContractContext {
     }
 
     pub fn is_name_used(&self, name: &str) -> bool {
-        is_reserved(name)
+        is_reserved(name, self.clarity_version)
             || self.variables.contains_key(name)
             || self.functions.contains_key(name)
             || self.persisted_names.contains(name)
@@ -1480,6 +1518,7 @@ impl CallStack {
             stack: Vec::new(),
             set: HashSet::new(),
             apply_depth: 0,
+            contract_entries: HashMap::new(),
         }
     }
 
@@ -1506,6 +1545,38 @@ impl CallStack {
         self.apply_depth -= 1;
     }
 
+    /// Record that a public function of `contract` was called, either by a transaction or by
+    /// `contract-call?`.
+    pub fn enter_contract(&mut self, contract: &QualifiedContractIdentifier) {
+        *self.contract_entries.entry(contract.clone()).or_insert(0) += 1;
+    }
+
+    pub fn exit_contract(&mut self, contract: &QualifiedContractIdentifier) -> Result<()> {
+        match self.contract_entries.get_mut(contract) {
+            Some(entries) if *entries > 1 => {
+                *entries -= 1;
+                Ok(())
+            }
+            Some(_) => {
+                self.contract_entries.remove(contract);
+                Ok(())
+            }
+            None => Err(InterpreterError::InterpreterError(
+                "Tried to exit a contract that was not entered.".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Is a public function of `contract` being called while another call into it is still in
+    /// progress further down the stack?
+    pub fn is_reentrant(&self, contract: &QualifiedContractIdentifier) -> bool {
+        self.contract_entries
+            .get(contract)
+            .map(|entries| *entries > 1)
+            .unwrap_or(false)
+    }
+
     pub fn remove(&mut self, function: &FunctionIdentifier, tracked: bool) -> Result<()> {
         if let Some(removed) = self.stack.pop() {
             if removed != *function {
@@ -119,6 +119,7 @@ define_named_enum!(ClarityCostFunction {
     ContractStorage("cost_contract_storage"),
     BlockInfo("cost_block_info"),
     StxBalance("cost_stx_balance"),
+    StxGetAccount("cost_stx_account"),
     StxTransfer("cost_stx_transfer"),
     FtMint("cost_ft_mint"),
     FtTransfer("cost_ft_transfer"),
@@ -128,3 +129,14 @@ define_named_enum!(ClarityCostFunction {
     NftOwner("cost_nft_owner"),
     PoisonMicroblock("poison_microblock"),
 });
+
+impl ClarityCostFunction {
+    /// Was this cost function added in Stacks 2.1?  If so, it is defined by the `costs-2` boot
+    /// contract instead of the `costs` boot contract.
+    pub fn is_stacks_2_1(&self) -> bool {
+        match self {
+            ClarityCostFunction::StxGetAccount => true,
+            _ => false,
+        }
+    }
+}
@@ -16,6 +16,7 @@
 
 pub mod constants;
 pub mod cost_functions;
+pub mod profiler;
 
 use regex::internal::Exec;
 use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
@@ -24,11 +25,12 @@ use std::{cmp, fmt};
 
 use std::collections::{BTreeMap, HashMap};
 
-use chainstate::stacks::boot::STACKS_BOOT_COST_CONTRACT;
+use chainstate::stacks::boot::{STACKS_BOOT_COST_2_CONTRACT, STACKS_BOOT_COST_CONTRACT};
 
 use vm::ast::ContractAST;
 use vm::contexts::{ContractContext, Environment, GlobalContext, OwnedEnvironment};
 use vm::costs::cost_functions::ClarityCostFunction;
+use vm::costs::profiler::{CostProfile, CostProfiler, ProfileFrame};
 use vm::database::{marf::NullBackingStore, ClarityDatabase, MemoryBackingStore};
 use vm::errors::{Error, InterpreterResult};
 use vm::types::Value::UInt;
@@ -162,6 +164,7 @@ pub struct LimitedCostTracker {
     memory: u64,
     memory_limit: u64,
     free: bool,
+    profiler: Option<CostProfiler>,
 }
 
 impl fmt::Debug for LimitedCostTracker {
@@ -208,6 +211,7 @@ impl LimitedCostTracker {
             total: ExecutionCost::zero(),
             memory: 0,
             free: false,
+            profiler: None,
         };
         cost_tracker.load_boot_costs(clarity_db)?;
         Ok(cost_tracker)
@@ -233,6 +237,7 @@ impl LimitedCostTracker {
             total: ExecutionCost::zero(),
             memory: 0,
             free: false,
+            profiler: None,
         };
         cost_tracker.load_boot_costs(clarity_db)?;
         Ok(cost_tracker)
@@ -248,32 +253,50 @@ impl LimitedCostTracker {
             memory: 0,
             memory_limit: CLARITY_MEMORY_LIMIT,
             free: true,
+            profiler: None,
         }
     }
     pub fn load_boot_costs(&mut self, clarity_db: &mut ClarityDatabase) -> Result<()> {
+        if self.free {
+            // a free tracker never computes costs, so it has nothing to load
+            return Ok(());
+        }
+
         let boot_costs_id = (*STACKS_BOOT_COST_CONTRACT).clone();
+        let boot_costs_2_id = (*STACKS_BOOT_COST_2_CONTRACT).clone();
 
         clarity_db.begin();
 
+        // the costs-2 contract is deployed when the chain upgrades to Stacks 2.1.  Until then,
+        //  the natives it prices can't be used, so its cost functions are left undefined.
+        let has_costs_2 = clarity_db.has_contract(&boot_costs_2_id);
+
         let mut cost_contracts = HashMap::new();
         let mut m = HashMap::new();
         for f in ClarityCostFunction::ALL.iter() {
+            let cost_contract_id = if !f.is_stacks_2_1() {
+                &boot_costs_id
+            } else if has_costs_2 {
+                &boot_costs_2_id
+            } else {
+                continue;
+            };
             m.insert(
                 f,
-                ClarityCostFunctionReference::new(boot_costs_id.clone(), f.get_name()),
+                ClarityCostFunctionReference::new(cost_contract_id.clone(), f.get_name()),
             );
-            if !cost_contracts.contains_key(&boot_costs_id) {
-                let contract_context = match clarity_db.get_contract(&boot_costs_id) {
+            if !cost_contracts.contains_key(cost_contract_id) {
+                let contract_context = match clarity_db.get_contract(cost_contract_id) {
                     Ok(contract) => contract.contract_context,
                     Err(e) => {
                         error!("Failed to load intended Clarity cost contract";
-                               "contract" => %boot_costs_id.to_string(),
+                               "contract" => %cost_contract_id.to_string(),
                                "error" => %format!("{:?}", e));
                         clarity_db.roll_back();
                         return Err(CostErrors::CostContractLoadFailure);
                     }
                 };
-                cost_contracts.insert(boot_costs_id.clone(), contract_context);
+                cost_contracts.insert(cost_contract_id.clone(), contract_context);
             }
         }
 
@@ -310,6 +333,29 @@ impl LimitedCostTracker {
     pub fn get_limit(&self) -> ExecutionCost {
         self.limit.clone()
     }
+
+    /// Start recording which functions and expressions this tracker's costs are incurred in.
+    pub fn enable_profiling(&mut self) {
+        self.profiler = Some(CostProfiler::new(&self.total));
+    }
+    pub fn is_profiling(&self) -> bool {
+        self.profiler.is_some()
+    }
+    /// Stop profiling, and return what the profiler found (if it was enabled).
+    pub fn take_profile(&mut self) -> Option<CostProfile> {
+        let total = &self.total;
+        self.profiler.take().map(|profiler| profiler.finish(total))
+    }
+    pub fn profile_enter(&mut self, frame: ProfileFrame) {
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.enter(frame, &self.total);
+        }
+    }
+    pub fn profile_exit(&mut self) {
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.exit(&self.total);
+        }
+    }
 }
 
 fn parse_cost(
@@ -529,6 +575,99 @@ pub struct SimpleCostSpecification {
     pub runtime: CostFunctions,
 }
 
+/// One summand of the memory a native function charges to the cost tracker.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub enum MemoryChargeTerm {
+    /// A fixed number of bytes.
+    Constant(u64),
+    /// The memory use of the named argument or bound value, as reported by `get_memory_use()`.
+    ValueSize(&'static str),
+    /// The size of the declared type of the asset being minted or transferred.
+    AssetTypeSize,
+}
+
+/// The formula for the memory a native function charges each time it is evaluated.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct MemoryChargeFormula {
+    pub terms: Vec<MemoryChargeTerm>,
+    /// If true, the terms are charged once per element of the function's list argument.
+    pub per_list_item: bool,
+    /// If true, the charge is dropped again once the function returns, instead of being
+    /// held until the end of the transaction.
+    pub scoped: bool,
+}
+
+impl MemoryChargeFormula {
+    pub fn none() -> MemoryChargeFormula {
+        MemoryChargeFormula {
+            terms: vec![],
+            per_list_item: false,
+            scoped: false,
+        }
+    }
+
+    pub fn new(terms: Vec<MemoryChargeTerm>) -> MemoryChargeFormula {
+        MemoryChargeFormula {
+            terms,
+            per_list_item: false,
+            scoped: false,
+        }
+    }
+
+    pub fn per_list_item(mut self) -> MemoryChargeFormula {
+        self.per_list_item = true;
+        self
+    }
+
+    pub fn scoped(mut self) -> MemoryChargeFormula {
+        self.scoped = true;
+        self
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The sum of the formula's constant terms, i.e. the number of bytes charged regardless
+    /// of the values involved.
+    pub fn fixed_bytes(&self) -> u64 {
+        self.terms
+            .iter()
+            .map(|term| match term {
+                MemoryChargeTerm::Constant(bytes) => *bytes,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+impl fmt::Display for MemoryChargeTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemoryChargeTerm::Constant(bytes) => write!(f, "{}", bytes),
+            MemoryChargeTerm::ValueSize(name) => write!(f, "size({})", name),
+            MemoryChargeTerm::AssetTypeSize => write!(f, "size(asset-type)"),
+        }
+    }
+}
+
+impl fmt::Display for MemoryChargeFormula {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        let terms: Vec<_> = self.terms.iter().map(|term| term.to_string()).collect();
+        write!(f, "{}", terms.join(" + "))?;
+        if self.per_list_item {
+            write!(f, " per list item")?;
+        }
+        if self.scoped {
+            write!(f, " (scoped)")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct ExecutionCost {
     pub write_length: u64,
@@ -645,6 +784,84 @@ impl ExecutionCost {
         Ok(())
     }
 
+    /// Returns the sum of this cost and `other`, or None if any dimension overflows.
+    pub fn checked_add(&self, other: &ExecutionCost) -> Option<ExecutionCost> {
+        let mut result = self.clone();
+        result.add(other).ok().map(|_| result)
+    }
+
+    /// Returns this cost minus `other`, or None if any dimension of `other` is larger.
+    pub fn checked_sub(&self, other: &ExecutionCost) -> Option<ExecutionCost> {
+        let mut result = self.clone();
+        result.sub(other).ok().map(|_| result)
+    }
+
+    /// Returns this cost multiplied by `times`, or None if any dimension overflows.
+    pub fn checked_mul(&self, times: u64) -> Option<ExecutionCost> {
+        let mut result = self.clone();
+        result.multiply(times).ok().map(|_| result)
+    }
+
+    /// Returns the sum of this cost and `other`, clamping each dimension at u64::MAX.
+    pub fn saturating_add(&self, other: &ExecutionCost) -> ExecutionCost {
+        Self {
+            runtime: self.runtime.saturating_add(other.runtime),
+            write_length: self.write_length.saturating_add(other.write_length),
+            write_count: self.write_count.saturating_add(other.write_count),
+            read_count: self.read_count.saturating_add(other.read_count),
+            read_length: self.read_length.saturating_add(other.read_length),
+        }
+    }
+
+    /// Returns this cost minus `other`, clamping each dimension at 0.
+    pub fn saturating_sub(&self, other: &ExecutionCost) -> ExecutionCost {
+        Self {
+            runtime: self.runtime.saturating_sub(other.runtime),
+            write_length: self.write_length.saturating_sub(other.write_length),
+            write_count: self.write_count.saturating_sub(other.write_count),
+            read_count: self.read_count.saturating_sub(other.read_count),
+            read_length: self.read_length.saturating_sub(other.read_length),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == ExecutionCost::zero()
+    }
+
+    /// Returns the percentage of `limit` consumed by this cost, in whichever
+    ///  dimension is closest to (or furthest past) its limit.  A dimension
+    ///  with a limit of 0 counts as 100% consumed if this cost uses any of it.
+    pub fn proportion_of_limit(&self, limit: &ExecutionCost) -> u64 {
+        fn percent(used: u64, limit: u64) -> u64 {
+            if limit == 0 {
+                if used == 0 {
+                    0
+                } else {
+                    100
+                }
+            } else {
+                let pct = (used as u128) * 100 / (limit as u128);
+                if pct > u64::max_value() as u128 {
+                    u64::max_value()
+                } else {
+                    pct as u64
+                }
+            }
+        }
+
+        [
+            percent(self.runtime, limit.runtime),
+            percent(self.write_length, limit.write_length),
+            percent(self.write_count, limit.write_count),
+            percent(self.read_count, limit.read_count),
+            percent(self.read_length, limit.read_length),
+        ]
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0)
+    }
+
     /// Returns whether or not this cost exceeds any dimension of the
     ///  other cost.
     pub fn exceeds(&self, other: &ExecutionCost) -> bool {
@@ -759,6 +976,111 @@ mod unit_tests {
         assert_eq!(0u64.cost_overflow_sub(1), Err(CostErrors::CostOverflow));
     }
 
+    #[test]
+    fn test_execution_cost_arithmetic() {
+        let a = ExecutionCost {
+            runtime: 100,
+            write_length: 10,
+            write_count: 1,
+            read_count: 2,
+            read_length: 20,
+        };
+        let b = ExecutionCost {
+            runtime: 50,
+            write_length: 20,
+            write_count: 1,
+            read_count: 1,
+            read_length: 5,
+        };
+
+        assert_eq!(
+            a.checked_add(&b),
+            Some(ExecutionCost {
+                runtime: 150,
+                write_length: 30,
+                write_count: 2,
+                read_count: 3,
+                read_length: 25,
+            })
+        );
+        assert_eq!(a.checked_add(&ExecutionCost::max_value()), None);
+        assert_eq!(
+            a.saturating_add(&ExecutionCost::max_value()),
+            ExecutionCost::max_value()
+        );
+
+        // write_length would go negative
+        assert_eq!(a.checked_sub(&b), None);
+        assert_eq!(
+            a.saturating_sub(&b),
+            ExecutionCost {
+                runtime: 50,
+                write_length: 0,
+                write_count: 0,
+                read_count: 1,
+                read_length: 15,
+            }
+        );
+        assert_eq!(a.checked_sub(&a), Some(ExecutionCost::zero()));
+        assert!(a.checked_sub(&a).unwrap().is_zero());
+
+        assert_eq!(
+            b.checked_mul(2),
+            Some(ExecutionCost {
+                runtime: 100,
+                write_length: 40,
+                write_count: 2,
+                read_count: 2,
+                read_length: 10,
+            })
+        );
+        assert_eq!(
+            ExecutionCost::runtime(2).checked_mul(u64::max_value()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_execution_cost_proportion_of_limit() {
+        let limit = ExecutionCost {
+            runtime: 1000,
+            write_length: 100,
+            write_count: 10,
+            read_count: 10,
+            read_length: 100,
+        };
+
+        assert_eq!(ExecutionCost::zero().proportion_of_limit(&limit), 0);
+        // write_count is the most-consumed dimension
+        let cost = ExecutionCost {
+            runtime: 100,
+            write_length: 10,
+            write_count: 5,
+            read_count: 1,
+            read_length: 10,
+        };
+        assert_eq!(cost.proportion_of_limit(&limit), 50);
+        assert_eq!(limit.proportion_of_limit(&limit), 100);
+        assert_eq!(
+            ExecutionCost::runtime(3000).proportion_of_limit(&limit),
+            300
+        );
+
+        // a zero limit is only "consumed" if the cost uses it
+        assert_eq!(
+            ExecutionCost::zero().proportion_of_limit(&ExecutionCost::zero()),
+            0
+        );
+        assert_eq!(
+            ExecutionCost::runtime(1).proportion_of_limit(&ExecutionCost::zero()),
+            100
+        );
+        assert_eq!(
+            ExecutionCost::max_value().proportion_of_limit(&ExecutionCost::runtime(1)),
+            u64::max_value()
+        );
+    }
+
     #[test]
     fn test_simple_log2s() {
         let inputs = [
@@ -0,0 +1,312 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use vm::costs::ExecutionCost;
+use vm::functions::define::DefineFunctions;
+use vm::types::QualifiedContractIdentifier;
+use vm::{ClarityName, SymbolicExpression};
+
+/// Label of the costs a profiler records outside of any frame
+pub const PROFILE_TOP_LEVEL_LABEL: &str = "<top-level>";
+
+/// A unit of Clarity code whose cost the profiler reports on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProfileFrame {
+    /// A call to a user-defined function
+    Function {
+        contract_identifier: QualifiedContractIdentifier,
+        function_name: ClarityName,
+    },
+    /// The evaluation (or type-checking) of a function application, i.e. `(name ...)`.  Top-level
+    /// `define-*` expressions are named after the definition, e.g. `define-public transfer`.
+    Expression {
+        contract_identifier: QualifiedContractIdentifier,
+        expression_id: u64,
+        name: String,
+        line: u32,
+        column: u32,
+    },
+}
+
+#[cfg(feature = "developer-mode")]
+fn expression_location(exp: &SymbolicExpression) -> (u32, u32) {
+    (exp.span.start_line, exp.span.start_column)
+}
+
+#[cfg(not(feature = "developer-mode"))]
+fn expression_location(_exp: &SymbolicExpression) -> (u32, u32) {
+    (0, 0)
+}
+
+impl ProfileFrame {
+    pub fn function(
+        contract_identifier: &QualifiedContractIdentifier,
+        function_name: &ClarityName,
+    ) -> ProfileFrame {
+        ProfileFrame::Function {
+            contract_identifier: contract_identifier.clone(),
+            function_name: function_name.clone(),
+        }
+    }
+
+    pub fn expression(
+        contract_identifier: &QualifiedContractIdentifier,
+        exp: &SymbolicExpression,
+    ) -> ProfileFrame {
+        let name = match DefineFunctions::try_parse(exp) {
+            Some((define_type, args)) => {
+                let defined_name = args.first().and_then(|arg| {
+                    arg.match_atom().or_else(|| {
+                        arg.match_list()
+                            .and_then(|signature| signature.first())
+                            .and_then(|name| name.match_atom())
+                    })
+                });
+                match defined_name {
+                    Some(defined_name) => {
+                        format!("{} {}", define_type.get_name_str(), defined_name.as_str())
+                    }
+                    None => define_type.get_name(),
+                }
+            }
+            None => exp
+                .match_list()
+                .and_then(|list| list.first())
+                .and_then(|name| name.match_atom())
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| "?".to_string()),
+        };
+        let (line, column) = expression_location(exp);
+        ProfileFrame::Expression {
+            contract_identifier: contract_identifier.clone(),
+            expression_id: exp.id,
+            name,
+            line,
+            column,
+        }
+    }
+}
+
+impl fmt::Display for ProfileFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileFrame::Function {
+                contract_identifier,
+                function_name,
+            } => write!(f, "{}::{}", contract_identifier, function_name.as_str()),
+            ProfileFrame::Expression {
+                name, line, column, ..
+            } => {
+                if *line > 0 {
+                    write!(f, "({})@{}:{}", name, line, column)
+                } else {
+                    write!(f, "({})", name)
+                }
+            }
+        }
+    }
+}
+
+/// Cost of all the evaluations of one frame
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProfileEntry {
+    pub contract_identifier: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    pub calls: u64,
+    /// cost of the frame, including the frames it entered
+    pub cost: ExecutionCost,
+}
+
+/// What a `CostProfiler` found, once it is done.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostProfile {
+    pub total: ExecutionCost,
+    /// user-defined functions, most expensive (by runtime) first
+    pub functions: Vec<ProfileEntry>,
+    /// function applications, most expensive (by runtime) first
+    pub expressions: Vec<ProfileEntry>,
+    /// `;`-separated stacks of frames and the cost incurred in the innermost frame, sorted by
+    /// stack
+    pub stacks: Vec<(String, ExecutionCost)>,
+}
+
+impl CostProfile {
+    /// Render the runtime cost of each stack in the "folded stacks" format that flame graph
+    /// tools (such as `flamegraph.pl` and `inferno`) consume.
+    pub fn to_folded_stacks(&self) -> String {
+        let mut out = String::new();
+        for (stack, cost) in self.stacks.iter() {
+            if cost.runtime > 0 {
+                out.push_str(&format!("{} {}\n", stack, cost.runtime));
+            }
+        }
+        out
+    }
+}
+
+/// Records where a cost tracker's costs were incurred.  The interpreter and type checker enter a
+/// frame whenever they start on a function call or function application, and leave it when they
+/// are done; each cost the tracker adds in the meantime is charged to the innermost frame (its
+/// "self" cost) and to every frame enclosing it.
+#[derive(Debug, Clone)]
+pub struct CostProfiler {
+    /// frames currently being evaluated, and the tracker's total when each was entered
+    stack: Vec<(ProfileFrame, ExecutionCost)>,
+    /// `;`-separated labels of the frames in `stack`, and the length of `path` before each
+    path: String,
+    path_lens: Vec<usize>,
+    started_at: ExecutionCost,
+    /// the tracker's total when the profiler last attributed a cost
+    last_total: ExecutionCost,
+    entries: HashMap<ProfileFrame, (u64, ExecutionCost)>,
+    self_costs: HashMap<String, ExecutionCost>,
+}
+
+fn cost_since(total: &ExecutionCost, since: &ExecutionCost) -> ExecutionCost {
+    // the miner can roll a tracker's total back, which the profiler just ignores
+    total.checked_sub(since).unwrap_or_else(ExecutionCost::zero)
+}
+
+fn accumulate(sum: &mut ExecutionCost, cost: &ExecutionCost) {
+    *sum = sum
+        .checked_add(cost)
+        .unwrap_or_else(ExecutionCost::max_value);
+}
+
+impl CostProfiler {
+    pub fn new(total: &ExecutionCost) -> CostProfiler {
+        CostProfiler {
+            stack: vec![],
+            path: String::new(),
+            path_lens: vec![],
+            started_at: total.clone(),
+            last_total: total.clone(),
+            entries: HashMap::new(),
+            self_costs: HashMap::new(),
+        }
+    }
+
+    /// Charge whatever was spent since the last frame change to the current stack
+    fn attribute(&mut self, total: &ExecutionCost) {
+        let cost = cost_since(total, &self.last_total);
+        if cost != ExecutionCost::zero() {
+            let path = if !self.path.is_empty() {
+                self.path.clone()
+            } else {
+                PROFILE_TOP_LEVEL_LABEL.to_string()
+            };
+            accumulate(
+                self.self_costs
+                    .entry(path)
+                    .or_insert_with(ExecutionCost::zero),
+                &cost,
+            );
+        }
+        self.last_total = total.clone();
+    }
+
+    pub fn enter(&mut self, frame: ProfileFrame, total: &ExecutionCost) {
+        self.attribute(total);
+        self.path_lens.push(self.path.len());
+        if !self.path.is_empty() {
+            self.path.push(';');
+        }
+        // `;` separates frames in folded stacks, so it can't appear in a label
+        self.path.push_str(&frame.to_string().replace(';', ":"));
+        self.stack.push((frame, total.clone()));
+    }
+
+    pub fn exit(&mut self, total: &ExecutionCost) {
+        self.attribute(total);
+        if let Some((frame, entered_at)) = self.stack.pop() {
+            let (calls, cost) = self
+                .entries
+                .entry(frame)
+                .or_insert_with(|| (0, ExecutionCost::zero()));
+            *calls += 1;
+            accumulate(cost, &cost_since(total, &entered_at));
+        }
+        if let Some(len) = self.path_lens.pop() {
+            self.path.truncate(len);
+        }
+    }
+
+    /// Finish profiling, given the tracker's final total.
+    pub fn finish(mut self, total: &ExecutionCost) -> CostProfile {
+        self.attribute(total);
+
+        let mut functions = vec![];
+        let mut expressions = vec![];
+        for (frame, (calls, cost)) in self.entries.into_iter() {
+            match frame {
+                ProfileFrame::Function {
+                    contract_identifier,
+                    function_name,
+                } => functions.push(ProfileEntry {
+                    contract_identifier: contract_identifier.to_string(),
+                    name: function_name.to_string(),
+                    line: None,
+                    column: None,
+                    calls,
+                    cost,
+                }),
+                ProfileFrame::Expression {
+                    contract_identifier,
+                    name,
+                    line,
+                    column,
+                    ..
+                } => expressions.push(ProfileEntry {
+                    contract_identifier: contract_identifier.to_string(),
+                    name,
+                    line: Some(line),
+                    column: Some(column),
+                    calls,
+                    cost,
+                }),
+            }
+        }
+
+        let by_runtime = |a: &ProfileEntry, b: &ProfileEntry| {
+            b.cost
+                .runtime
+                .cmp(&a.cost.runtime)
+                .then_with(|| a.contract_identifier.cmp(&b.contract_identifier))
+                .then_with(|| a.line.cmp(&b.line))
+                .then_with(|| a.column.cmp(&b.column))
+                .then_with(|| a.name.cmp(&b.name))
+        };
+        functions.sort_by(by_runtime);
+        expressions.sort_by(by_runtime);
+
+        let mut stacks: Vec<_> = self.self_costs.into_iter().collect();
+        stacks.sort_by(|a, b| a.0.cmp(&b.0));
+
+        CostProfile {
+            total: cost_since(total, &self.started_at),
+            functions,
+            expressions,
+            stacks,
+        }
+    }
+}
@@ -15,7 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use rusqlite::OptionalExtension;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 
 use vm::contracts::Contract;
@@ -28,6 +28,7 @@ use vm::types::{
     OptionalData, PrincipalData, QualifiedContractIdentifier, StandardPrincipalData, TupleData,
     TupleTypeSignature, TypeSignature, Value, NONE,
 };
+use vm::version::ClarityVersion;
 
 use burnchains::BurnchainHeaderHash;
 use chainstate::burn::{BlockHeaderHash, ConsensusHash, VRFSeed};
@@ -42,7 +43,7 @@ use vm::costs::CostOverflowingMath;
 use vm::database::structures::{
     ClarityDeserializable, ClaritySerializable, ContractMetadata, DataMapMetadata,
     DataVariableMetadata, FungibleTokenMetadata, NonFungibleTokenMetadata, STXBalance,
-    STXBalanceSnapshot, SimmedBlock,
+    STXBalanceSnapshot, STXUnlockHeights, SimmedBlock,
 };
 use vm::database::RollbackWrapper;
 use vm::database::{ClarityBackingStore, MarfedKV};
@@ -94,6 +95,7 @@ pub trait HeadersDB {
     fn get_vrf_seed_for_block(&self, id_bhh: &StacksBlockId) -> Option<VRFSeed>;
     fn get_burn_block_time_for_block(&self, id_bhh: &StacksBlockId) -> Option<u64>;
     fn get_burn_block_height_for_block(&self, id_bhh: &StacksBlockId) -> Option<u32>;
+    fn get_consensus_hash_for_block(&self, id_bhh: &StacksBlockId) -> Option<ConsensusHash>;
     fn get_miner_address(&self, id_bhh: &StacksBlockId) -> Option<StacksAddress>;
     fn get_total_liquid_ustx(&self, id_bhh: &StacksBlockId) -> u128;
 }
@@ -105,6 +107,12 @@ pub trait BurnStateDB {
         height: u32,
         sortition_id: &SortitionId,
     ) -> Option<BurnchainHeaderHash>;
+    fn get_burn_block_time(&self, height: u32, sortition_id: &SortitionId) -> Option<u64>;
+    fn get_burn_median_time_past(&self, height: u32, sortition_id: &SortitionId) -> Option<u64>;
+    fn get_sortition_id_from_consensus_hash(
+        &self,
+        consensus_hash: &ConsensusHash,
+    ) -> Option<SortitionId>;
 }
 
 fn get_stacks_header_info(conn: &DBConn, id_bhh: &StacksBlockId) -> Option<StacksHeaderInfo> {
@@ -150,6 +158,10 @@ impl HeadersDB for DBConn {
         get_stacks_header_info(self, id_bhh).map(|x| x.burn_header_height)
     }
 
+    fn get_consensus_hash_for_block(&self, id_bhh: &StacksBlockId) -> Option<ConsensusHash> {
+        get_stacks_header_info(self, id_bhh).map(|x| x.consensus_hash)
+    }
+
     fn get_vrf_seed_for_block(&self, id_bhh: &StacksBlockId) -> Option<VRFSeed> {
         get_stacks_header_info(self, id_bhh).map(|x| VRFSeed::from_proof(&x.anchored_header.proof))
     }
@@ -184,6 +196,9 @@ impl HeadersDB for &dyn HeadersDB {
     fn get_burn_block_height_for_block(&self, bhh: &StacksBlockId) -> Option<u32> {
         (*self).get_burn_block_height_for_block(bhh)
     }
+    fn get_consensus_hash_for_block(&self, bhh: &StacksBlockId) -> Option<ConsensusHash> {
+        (*self).get_consensus_hash_for_block(bhh)
+    }
     fn get_miner_address(&self, bhh: &StacksBlockId) -> Option<StacksAddress> {
         (*self).get_miner_address(bhh)
     }
@@ -192,6 +207,26 @@ impl HeadersDB for &dyn HeadersDB {
     }
 }
 
+/// Run a read-only query against the sortition fork identified by `sortition_id`, from within an
+/// open sortition DB transaction.
+fn with_readonly_sortition_handle<F, R>(
+    tx: &SortitionHandleTx,
+    sortition_id: &SortitionId,
+    query: F,
+) -> R
+where
+    F: FnOnce(&SortitionHandleConn) -> R,
+{
+    let readonly_marf = tx
+        .index()
+        .reopen_readonly()
+        .expect("BUG: failure trying to get a read-only interface into the sortition db.");
+    let mut context = tx.context.clone();
+    context.chain_tip = *sortition_id;
+    let db_handle = SortitionHandleConn::new(&readonly_marf, context);
+    query(&db_handle)
+}
+
 impl BurnStateDB for SortitionHandleTx<'_> {
     fn get_burn_block_height(&self, sortition_id: &SortitionId) -> Option<u32> {
         match SortitionDB::get_block_snapshot(self.tx(), sortition_id) {
@@ -205,17 +240,39 @@ impl BurnStateDB for SortitionHandleTx<'_> {
         height: u32,
         sortition_id: &SortitionId,
     ) -> Option<BurnchainHeaderHash> {
-        let readonly_marf = self
-            .index()
-            .reopen_readonly()
-            .expect("BUG: failure trying to get a read-only interface into the sortition db.");
-        let mut context = self.context.clone();
-        context.chain_tip = sortition_id.clone();
-        let db_handle = SortitionHandleConn::new(&readonly_marf, context);
-        match db_handle.get_block_snapshot_by_height(height as u64) {
-            Ok(Some(x)) => Some(x.burn_header_hash),
-            _ => return None,
-        }
+        with_readonly_sortition_handle(self, sortition_id, |db_handle| {
+            match db_handle.get_block_snapshot_by_height(height as u64) {
+                Ok(Some(x)) => Some(x.burn_header_hash),
+                _ => None,
+            }
+        })
+    }
+
+    fn get_burn_block_time(&self, height: u32, sortition_id: &SortitionId) -> Option<u64> {
+        with_readonly_sortition_handle(self, sortition_id, |db_handle| {
+            match db_handle.get_block_snapshot_by_height(height as u64) {
+                Ok(Some(x)) => Some(x.burn_header_timestamp),
+                _ => None,
+            }
+        })
+    }
+
+    fn get_burn_median_time_past(&self, height: u32, sortition_id: &SortitionId) -> Option<u64> {
+        with_readonly_sortition_handle(self, sortition_id, |db_handle| {
+            db_handle
+                .get_burn_median_time_past(height as u64)
+                .ok()
+                .flatten()
+        })
+    }
+
+    fn get_sortition_id_from_consensus_hash(
+        &self,
+        consensus_hash: &ConsensusHash,
+    ) -> Option<SortitionId> {
+        SortitionDB::get_sortition_id_by_consensus(self.tx(), consensus_hash)
+            .ok()
+            .flatten()
     }
 }
 
@@ -238,6 +295,31 @@ impl BurnStateDB for SortitionDBConn<'_> {
             _ => return None,
         }
     }
+
+    fn get_burn_block_time(&self, height: u32, sortition_id: &SortitionId) -> Option<u64> {
+        let db_handle = SortitionHandleConn::open_reader(self, sortition_id).ok()?;
+        match db_handle.get_block_snapshot_by_height(height as u64) {
+            Ok(Some(x)) => Some(x.burn_header_timestamp),
+            _ => None,
+        }
+    }
+
+    fn get_burn_median_time_past(&self, height: u32, sortition_id: &SortitionId) -> Option<u64> {
+        let db_handle = SortitionHandleConn::open_reader(self, sortition_id).ok()?;
+        db_handle
+            .get_burn_median_time_past(height as u64)
+            .ok()
+            .flatten()
+    }
+
+    fn get_sortition_id_from_consensus_hash(
+        &self,
+        consensus_hash: &ConsensusHash,
+    ) -> Option<SortitionId> {
+        SortitionDB::get_sortition_id_by_consensus(self.conn(), consensus_hash)
+            .ok()
+            .flatten()
+    }
 }
 
 impl BurnStateDB for &dyn BurnStateDB {
@@ -252,6 +334,21 @@ impl BurnStateDB for &dyn BurnStateDB {
     ) -> Option<BurnchainHeaderHash> {
         (*self).get_burn_header_hash(height, sortition_id)
     }
+
+    fn get_burn_block_time(&self, height: u32, sortition_id: &SortitionId) -> Option<u64> {
+        (*self).get_burn_block_time(height, sortition_id)
+    }
+
+    fn get_burn_median_time_past(&self, height: u32, sortition_id: &SortitionId) -> Option<u64> {
+        (*self).get_burn_median_time_past(height, sortition_id)
+    }
+
+    fn get_sortition_id_from_consensus_hash(
+        &self,
+        consensus_hash: &ConsensusHash,
+    ) -> Option<SortitionId> {
+        (*self).get_sortition_id_from_consensus_hash(consensus_hash)
+    }
 }
 
 pub struct NullHeadersDB {}
@@ -318,6 +415,18 @@ impl HeadersDB for NullHeadersDB {
             None
         }
     }
+    fn get_consensus_hash_for_block(&self, id_bhh: &StacksBlockId) -> Option<ConsensusHash> {
+        if *id_bhh
+            == StacksBlockHeader::make_index_block_hash(
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+            )
+        {
+            Some(FIRST_BURNCHAIN_CONSENSUS_HASH)
+        } else {
+            None
+        }
+    }
     fn get_miner_address(&self, _id_bhh: &StacksBlockId) -> Option<StacksAddress> {
         None
     }
@@ -338,6 +447,21 @@ impl BurnStateDB for NullBurnStateDB {
     ) -> Option<BurnchainHeaderHash> {
         None
     }
+
+    fn get_burn_block_time(&self, _height: u32, _sortition_id: &SortitionId) -> Option<u64> {
+        None
+    }
+
+    fn get_burn_median_time_past(&self, _height: u32, _sortition_id: &SortitionId) -> Option<u64> {
+        None
+    }
+
+    fn get_sortition_id_from_consensus_hash(
+        &self,
+        _consensus_hash: &ConsensusHash,
+    ) -> Option<SortitionId> {
+        None
+    }
 }
 
 impl<'a> ClarityDatabase<'a> {
@@ -784,6 +908,13 @@ impl<'a> ClarityDatabase<'a> {
             .ok_or(CheckErrors::NoSuchDataVariable(variable_name.to_string()).into())
     }
 
+    pub fn make_key_for_data_var(
+        contract_identifier: &QualifiedContractIdentifier,
+        variable_name: &str,
+    ) -> String {
+        ClarityDatabase::make_key_for_trip(contract_identifier, StoreType::Variable, variable_name)
+    }
+
     pub fn set_variable(
         &mut self,
         contract_identifier: &QualifiedContractIdentifier,
@@ -795,11 +926,7 @@ impl<'a> ClarityDatabase<'a> {
             return Err(CheckErrors::TypeValueError(variable_descriptor.value_type, value).into());
         }
 
-        let key = ClarityDatabase::make_key_for_trip(
-            contract_identifier,
-            StoreType::Variable,
-            variable_name,
-        );
+        let key = ClarityDatabase::make_key_for_data_var(contract_identifier, variable_name);
 
         self.put(&key, &value);
 
@@ -813,11 +940,7 @@ impl<'a> ClarityDatabase<'a> {
     ) -> Result<Value> {
         let variable_descriptor = self.load_variable(contract_identifier, variable_name)?;
 
-        let key = ClarityDatabase::make_key_for_trip(
-            contract_identifier,
-            StoreType::Variable,
-            variable_name,
-        );
+        let key = ClarityDatabase::make_key_for_data_var(contract_identifier, variable_name);
 
         let result = self.get_value(&key, &variable_descriptor.value_type);
 
@@ -1262,6 +1385,123 @@ impl<'a> ClarityDatabase<'a> {
     }
 }
 
+// network upgrades, recorded in the fork that carried them out
+impl<'a> ClarityDatabase<'a> {
+    const STACKS_2_1_ACTIVATION_KEY: &'static str = "vm-upgrades::stacks-2.1";
+
+    /// Has this fork upgraded to Stacks 2.1?
+    pub fn is_stacks_2_1_active(&mut self) -> bool {
+        self.get::<u64>(ClarityDatabase::STACKS_2_1_ACTIVATION_KEY)
+            .is_some()
+    }
+
+    /// The Clarity version of the contracts that this fork deploys from now on
+    pub fn get_clarity_version(&mut self) -> ClarityVersion {
+        if self.is_stacks_2_1_active() {
+            ClarityVersion::Clarity2
+        } else {
+            ClarityVersion::Clarity1
+        }
+    }
+
+    /// Record that this fork upgraded to Stacks 2.1 in a block built at the given burnchain
+    /// block height
+    pub fn set_stacks_2_1_active(&mut self, burn_block_height: u64) {
+        self.put(
+            ClarityDatabase::STACKS_2_1_ACTIVATION_KEY,
+            &burn_block_height,
+        );
+    }
+}
+
+// schedule of STX lock expirations, so expired locks can be swept into accounts' unlocked balances
+impl<'a> ClarityDatabase<'a> {
+    const STX_UNLOCK_HEIGHTS_KEY: &'static str = "vm-stx-unlocks::heights";
+
+    fn make_key_for_stx_unlocks_count(unlock_burn_height: u64) -> String {
+        format!("vm-stx-unlocks::{}::count", unlock_burn_height)
+    }
+
+    fn make_key_for_stx_unlock(unlock_burn_height: u64, index: u64) -> String {
+        format!("vm-stx-unlocks::{}::{}", unlock_burn_height, index)
+    }
+
+    /// Record that some of the principal's STX unlock at the given burn height.  A principal may
+    /// be recorded more than once, or at a height its lock was since moved from; sweeping an
+    /// account with nothing left to unlock does nothing.
+    /// Locks are only scheduled once the fork has upgraded to Stacks 2.1.  Until then, and for
+    /// locks made before then, expired locks are only released when their account is next
+    /// written to.
+    pub fn schedule_stx_unlock(&mut self, principal: &PrincipalData, unlock_burn_height: u64) {
+        if !self.is_stacks_2_1_active() {
+            return;
+        }
+
+        let count_key = ClarityDatabase::make_key_for_stx_unlocks_count(unlock_burn_height);
+        let count: u64 = self.get(&count_key).unwrap_or(0);
+        if count == 0 {
+            let mut pending: STXUnlockHeights = self
+                .get(ClarityDatabase::STX_UNLOCK_HEIGHTS_KEY)
+                .unwrap_or_default();
+            if let Err(index) = pending.heights.binary_search(&unlock_burn_height) {
+                pending.heights.insert(index, unlock_burn_height);
+                self.put(ClarityDatabase::STX_UNLOCK_HEIGHTS_KEY, &pending);
+            }
+        }
+
+        let key = ClarityDatabase::make_key_for_stx_unlock(unlock_burn_height, count);
+        self.put(&key, principal);
+        self.put(&count_key, &(count + 1));
+    }
+
+    /// Remove and return the principals of up to `max_unlocks` of the locks scheduled to expire
+    /// at or before the given burn height, earliest first and without duplicates.  The rest stay
+    /// scheduled.
+    pub fn take_stx_unlocks(
+        &mut self,
+        burn_block_height: u64,
+        max_unlocks: u64,
+    ) -> Vec<PrincipalData> {
+        let mut pending: STXUnlockHeights = self
+            .get(ClarityDatabase::STX_UNLOCK_HEIGHTS_KEY)
+            .unwrap_or_default();
+
+        let mut principals = vec![];
+        let mut seen = HashSet::new();
+        let mut num_taken = 0;
+        let mut num_drained = 0;
+        for unlock_burn_height in pending.heights.iter() {
+            if *unlock_burn_height > burn_block_height || num_taken >= max_unlocks {
+                break;
+            }
+
+            let count_key = ClarityDatabase::make_key_for_stx_unlocks_count(*unlock_burn_height);
+            let mut count: u64 = self.get(&count_key).unwrap_or(0);
+            while count > 0 && num_taken < max_unlocks {
+                count -= 1;
+                num_taken += 1;
+                let key = ClarityDatabase::make_key_for_stx_unlock(*unlock_burn_height, count);
+                let principal: PrincipalData = self
+                    .get(&key)
+                    .expect("CORRUPTION: missing scheduled STX unlock");
+                if seen.insert(principal.clone()) {
+                    principals.push(principal);
+                }
+            }
+            self.put(&count_key, &count);
+            if count == 0 {
+                num_drained += 1;
+            }
+        }
+
+        if num_drained > 0 {
+            pending.heights.drain(0..num_drained);
+            self.put(ClarityDatabase::STX_UNLOCK_HEIGHTS_KEY, &pending);
+        }
+        principals
+    }
+}
+
 // access burnchain state
 impl<'a> ClarityDatabase<'a> {
     pub fn get_burn_block_height(&self, sortition_id: &SortitionId) -> Option<u32> {
@@ -1276,4 +1516,53 @@ impl<'a> ClarityDatabase<'a> {
         self.burn_state_db
             .get_burn_header_hash(height, sortition_id)
     }
+
+    /// Get the sortition ID of the burnchain block in which the parent of the current Stacks
+    /// block was chosen.  Burnchain state is only visible to Clarity up to this sortition.
+    fn get_sortition_id_for_stacks_tip(&mut self) -> Option<SortitionId> {
+        let cur_stacks_height = self.store.get_current_block_height();
+        let last_mined_bhh = if cur_stacks_height == 0 {
+            StacksBlockHeader::make_index_block_hash(
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+            )
+        } else {
+            self.get_index_block_header_hash(cur_stacks_height - 1)
+        };
+        let consensus_hash = self
+            .headers_db
+            .get_consensus_hash_for_block(&last_mined_bhh)?;
+        self.burn_state_db
+            .get_sortition_id_from_consensus_hash(&consensus_hash)
+    }
+
+    /// Get the header hash of the burnchain block at the given burnchain height, as seen from the
+    /// current Stacks block.  Returns None if no such block is visible.
+    pub fn get_burnchain_block_header_hash_for_burnchain_height(
+        &mut self,
+        height: u32,
+    ) -> Option<BurnchainHeaderHash> {
+        let sortition_id = self.get_sortition_id_for_stacks_tip()?;
+        self.burn_state_db
+            .get_burn_header_hash(height, &sortition_id)
+    }
+
+    /// Get the timestamp of the burnchain block at the given burnchain height, as seen from the
+    /// current Stacks block.  Returns None if no such block is visible.
+    pub fn get_burnchain_block_time_for_burnchain_height(&mut self, height: u32) -> Option<u64> {
+        let sortition_id = self.get_sortition_id_for_stacks_tip()?;
+        self.burn_state_db
+            .get_burn_block_time(height, &sortition_id)
+    }
+
+    /// Get the median-time-past of the burnchain block at the given burnchain height, as seen
+    /// from the current Stacks block.  Returns None if no such block is visible.
+    pub fn get_burnchain_median_time_past_for_burnchain_height(
+        &mut self,
+        height: u32,
+    ) -> Option<u64> {
+        let sortition_id = self.get_sortition_id_for_stacks_tip()?;
+        self.burn_state_db
+            .get_burn_median_time_past(height, &sortition_id)
+    }
 }
@@ -53,6 +53,10 @@ pub struct MarfedKV {
     // Since the MARF only stores 32 bytes of value,
     //   we need another storage
     side_store: SqliteConnection,
+    // key/value pairs written to the currently-open block, in write order
+    block_writes: Vec<(String, String)>,
+    // contract metadata key/value pairs written to the currently-open block, in write order
+    block_metadata_writes: Vec<(String, String)>,
 }
 
 pub struct MemoryBackingStore {
@@ -216,6 +220,8 @@ impl MarfedKV {
             marf,
             chain_tip,
             side_store,
+            block_writes: vec![],
+            block_metadata_writes: vec![],
         })
     }
 
@@ -230,6 +236,8 @@ impl MarfedKV {
             marf,
             chain_tip,
             side_store,
+            block_writes: vec![],
+            block_metadata_writes: vec![],
         })
     }
 
@@ -255,6 +263,8 @@ impl MarfedKV {
             marf,
             chain_tip,
             side_store,
+            block_writes: vec![],
+            block_metadata_writes: vec![],
         }
     }
 
@@ -297,6 +307,8 @@ impl MarfedKV {
             .expect("ERROR: Failed to get open MARF")
             .clone();
         self.side_store.begin(&self.chain_tip);
+        self.block_writes.clear();
+        self.block_metadata_writes.clear();
     }
 
     pub fn begin_unconfirmed(&mut self, current: &StacksBlockId) {
@@ -317,18 +329,36 @@ impl MarfedKV {
             .expect("ERROR: Failed to get open MARF")
             .clone();
         self.side_store.begin(&self.chain_tip);
+        self.block_writes.clear();
+        self.block_metadata_writes.clear();
     }
 
     pub fn rollback(&mut self) {
         self.marf.drop_current();
         self.side_store.rollback(&self.chain_tip);
         self.chain_tip = StacksBlockId::sentinel();
+        self.block_writes.clear();
+        self.block_metadata_writes.clear();
     }
 
     pub fn rollback_unconfirmed(&mut self) {
         self.marf.drop_unconfirmed();
         self.side_store.rollback_unconfirmed(&self.chain_tip);
         self.chain_tip = StacksBlockId::sentinel();
+        self.block_writes.clear();
+        self.block_metadata_writes.clear();
+    }
+
+    /// Take the key/value pairs written to the currently-open block so far.
+    /// Used to hand a block's MARF deltas to indexers once it has been processed.
+    pub fn take_block_writes(&mut self) -> Vec<(String, String)> {
+        std::mem::replace(&mut self.block_writes, vec![])
+    }
+
+    /// Take the contract metadata key/value pairs written to the currently-open block so far.
+    /// Keys are as stored in the side store's metadata table.
+    pub fn take_block_metadata_writes(&mut self) -> Vec<(String, String)> {
+        std::mem::replace(&mut self.block_metadata_writes, vec![])
     }
 
     #[cfg(test)]
@@ -427,6 +457,17 @@ impl ClarityBackingStore for MarfedKV {
         &mut self.side_store
     }
 
+    fn insert_metadata(&mut self, contract: &QualifiedContractIdentifier, key: &str, value: &str) {
+        let bhh = self.get_open_chain_tip();
+        let contract_hash = contract.to_string();
+        self.block_metadata_writes.push((
+            SqliteConnection::make_metadata_key(&contract_hash, key),
+            value.to_string(),
+        ));
+        self.side_store
+            .insert_metadata(&bhh, &contract_hash, key, value)
+    }
+
     fn set_block_hash(&mut self, bhh: StacksBlockId) -> Result<StacksBlockId> {
         self.marf
             .check_ancestor_block_hash(&bhh)
@@ -563,13 +604,20 @@ impl ClarityBackingStore for MarfedKV {
     fn put_all(&mut self, mut items: Vec<(String, String)>) {
         let mut keys = Vec::new();
         let mut values = Vec::new();
+        let mut side_items = Vec::new();
         for (key, value) in items.drain(..) {
             trace!("MarfedKV put '{}' = '{}'", &key, &value);
             let marf_value = MARFValue::from_value(&value);
-            self.side_store.put(&marf_value.to_hex(), &value);
+            side_items.push((marf_value.to_hex(), value.clone()));
+            self.block_writes.push((key.clone(), value));
             keys.push(key);
             values.push(marf_value);
         }
+        self.side_store.put_all(
+            side_items
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
         self.marf
             .insert_batch(&keys, values)
             .expect("ERROR: Unexpected MARF Failure");
@@ -636,9 +684,11 @@ impl ClarityBackingStore for MemoryBackingStore {
     }
 
     fn put_all(&mut self, mut items: Vec<(String, String)>) {
-        for (key, value) in items.drain(..) {
-            self.side_store.put(&key, &value);
-        }
+        self.side_store.put_all(
+            items
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
     }
 }
 
@@ -49,6 +49,27 @@ fn sqlite_put(conn: &Connection, key: &str, value: &str) {
     };
 }
 
+/// Insert or replace many entries, preparing the statement only once
+fn sqlite_put_all<'a, I>(conn: &Connection, items: I)
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut stmt = match conn.prepare("REPLACE INTO data_table (key, value) VALUES (?, ?)") {
+        Ok(stmt) => stmt,
+        Err(e) => {
+            error!("Failed to prepare insert/replace: {:?}", &e);
+            panic!(SQL_FAIL_MESSAGE);
+        }
+    };
+    for (key, value) in items {
+        let params: [&dyn ToSql; 2] = [&key, &value];
+        if let Err(e) = stmt.execute(&params) {
+            error!("Failed to insert/replace ({},{}): {:?}", key, value, &e);
+            panic!(SQL_FAIL_MESSAGE);
+        }
+    }
+}
+
 fn sqlite_get(conn: &Connection, key: &str) -> Option<String> {
     trace!("sqlite_get {}", key);
     let params: [&dyn ToSql; 1] = [&key];
@@ -79,10 +100,23 @@ impl SqliteConnection {
         sqlite_put(&self.conn, key, value)
     }
 
+    /// Store many key/value pairs with a single prepared statement
+    pub fn put_all<'a, I>(&mut self, items: I)
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        sqlite_put_all(&self.conn, items)
+    }
+
     pub fn get(&mut self, key: &str) -> Option<String> {
         sqlite_get(&self.conn, key)
     }
 
+    /// The key a contract's metadata entry is stored under in the metadata table
+    pub fn make_metadata_key(contract_hash: &str, key: &str) -> String {
+        format!("clr-meta::{}::{}", contract_hash, key)
+    }
+
     pub fn insert_metadata(
         &mut self,
         bhh: &StacksBlockId,
@@ -90,7 +124,7 @@ impl SqliteConnection {
         key: &str,
         value: &str,
     ) {
-        let key = format!("clr-meta::{}::{}", contract_hash, key);
+        let key = SqliteConnection::make_metadata_key(contract_hash, key);
         let params: [&dyn ToSql; 3] = [&bhh, &key, &value.to_string()];
 
         match self.conn.execute(
@@ -131,7 +165,7 @@ impl SqliteConnection {
         contract_hash: &str,
         key: &str,
     ) -> Option<String> {
-        let key = format!("clr-meta::{}::{}", contract_hash, key);
+        let key = SqliteConnection::make_metadata_key(contract_hash, key);
         let params: [&dyn ToSql; 2] = [&bhh, &key];
 
         match self
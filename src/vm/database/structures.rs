@@ -104,17 +104,38 @@ pub struct SimmedBlock {
 
 clarity_serializable!(SimmedBlock);
 
+/// The burnchain block heights at which some accounts' STX locks expire, and which have not yet
+/// been swept, in ascending order
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct STXUnlockHeights {
+    pub heights: Vec<u64>,
+}
+
+clarity_serializable!(STXUnlockHeights);
+
 clarity_serializable!(PrincipalData);
 clarity_serializable!(i128);
 clarity_serializable!(u128);
 clarity_serializable!(u64);
 clarity_serializable!(Contract);
 
+/// An amount of STX that an account cannot spend until a burnchain block height, e.g. a vesting
+/// schedule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct STXLock {
+    pub amount: u128,
+    pub unlock_height: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct STXBalance {
     pub amount_unlocked: u128,
+    /// the PoX lock
     pub amount_locked: u128,
     pub unlock_height: u64,
+    /// locks held independently of the PoX lock, each with its own unlock height
+    #[serde(default)]
+    pub locks: Vec<STXLock>,
 }
 
 /// Lifetime-limited handle to an uncommitted balance structure.
@@ -128,6 +149,11 @@ pub struct STXBalanceSnapshot<'db, 'conn> {
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// A balance is encoded as 40 bytes (`amount_unlocked`, `amount_locked`, `unlock_height`),
+/// followed by 24 bytes (`amount`, `unlock_height`) for each independent lock.  Balances without
+/// independent locks keep the original 40-byte encoding, and independent locks can only come
+/// from the genesis configuration of a chain that starts out on Stacks 2.1, so chains that
+/// predate them never contain the longer encoding.
 impl ClaritySerializable for STXBalance {
     fn serialize(&self) -> String {
         let mut buffer = Vec::new();
@@ -140,6 +166,14 @@ impl ClaritySerializable for STXBalance {
         buffer
             .write_all(&self.unlock_height.to_be_bytes())
             .expect("STXBalance serialization: failed writing unlock_height.");
+        for lock in self.locks.iter() {
+            buffer
+                .write_all(&lock.amount.to_be_bytes())
+                .expect("STXBalance serialization: failed writing lock amount.");
+            buffer
+                .write_all(&lock.unlock_height.to_be_bytes())
+                .expect("STXBalance serialization: failed writing lock unlock_height.");
+        }
         to_hex(buffer.as_slice())
     }
 }
@@ -147,7 +181,8 @@ impl ClaritySerializable for STXBalance {
 impl ClarityDeserializable<STXBalance> for STXBalance {
     fn deserialize(input: &str) -> Self {
         let bytes = hex_bytes(&input).expect("STXBalance deserialization: failed decoding bytes.");
-        assert_eq!(bytes.len(), STXBalance::size_of);
+        assert!(bytes.len() >= STXBalance::size_of);
+        assert_eq!((bytes.len() - STXBalance::size_of) % STXLock::size_of, 0);
 
         let amount_unlocked = u128::from_be_bytes(
             bytes[0..16]
@@ -165,10 +200,27 @@ impl ClarityDeserializable<STXBalance> for STXBalance {
                 .expect("STXBalance deserialization: failed reading unlock_height."),
         );
 
+        let locks = bytes[STXBalance::size_of..]
+            .chunks(STXLock::size_of)
+            .map(|lock_bytes| STXLock {
+                amount: u128::from_be_bytes(
+                    lock_bytes[0..16]
+                        .try_into()
+                        .expect("STXBalance deserialization: failed reading lock amount."),
+                ),
+                unlock_height: u64::from_be_bytes(
+                    lock_bytes[16..24]
+                        .try_into()
+                        .expect("STXBalance deserialization: failed reading lock unlock_height."),
+                ),
+            })
+            .collect();
+
         STXBalance {
             amount_unlocked,
             amount_locked,
             unlock_height,
+            locks,
         }
     }
 }
@@ -221,13 +273,24 @@ impl<'db, 'conn> STXBalanceSnapshot<'db, 'conn> {
     }
 
     pub fn get_available_balance(&self) -> u128 {
-        if self.has_unlockable_tokens() {
-            self.balance.get_total_balance()
-        } else {
-            self.balance.amount_unlocked
-        }
+        self.balance
+            .get_available_balance_at_burn_block(self.burn_block_height)
+    }
+
+    /// Get the amount of STX that is still locked by PoX, and the burn height at which it unlocks.
+    /// Returns (0, 0) if the lock has expired.
+    pub fn get_locked_balance(&self) -> (u128, u64) {
+        self.balance
+            .get_locked_balance_at_burn_block(self.burn_block_height)
     }
 
+    /// Get the locks other than the PoX lock that have not yet expired
+    pub fn get_independent_locks(&self) -> Vec<STXLock> {
+        self.balance
+            .get_independent_locks_at_burn_block(self.burn_block_height)
+    }
+
+    /// Does the account have STX locked by PoX?
     pub fn has_locked_tokens(&self) -> bool {
         self.balance
             .has_locked_tokens_at_burn_block(self.burn_block_height)
@@ -299,9 +362,109 @@ impl<'db, 'conn> STXBalanceSnapshot<'db, 'conn> {
             .expect("STX underflow");
 
         self.balance.amount_locked = amount_to_lock;
+        self.db_ref
+            .schedule_stx_unlock(&self.principal, unlock_burn_height);
+    }
+
+    /// Move the unlock height of the account's current lock later
+    pub fn extend_lock_tokens(&mut self, unlock_burn_height: u64) {
+        let unlocked = self.unlock_available_tokens_if_any();
+        if unlocked > 0 {
+            debug!("Consolidated after extend-token-lock");
+        }
+
+        if !self.has_locked_tokens() {
+            // caller needs to have checked this
+            panic!("FATAL: account does not have locked tokens");
+        }
+
+        if unlock_burn_height <= self.balance.unlock_height {
+            // caller needs to have checked this
+            panic!("FATAL: cannot move an unlock burn height earlier");
+        }
+
+        self.balance.unlock_height = unlock_burn_height;
+        self.db_ref
+            .schedule_stx_unlock(&self.principal, unlock_burn_height);
+    }
+
+    /// Lock more of the account's unlocked tokens, so that `new_total_locked` are locked until the
+    /// current lock's unlock height
+    pub fn increase_lock_tokens(&mut self, new_total_locked: u128) {
+        // the extra uSTX may come from locks that have expired but not yet been released
+        let unlocked = self.unlock_available_tokens_if_any();
+        if unlocked > 0 {
+            debug!("Consolidated after increase-token-lock");
+        }
+
+        if !self.has_locked_tokens() {
+            // caller needs to have checked this
+            panic!("FATAL: account does not have locked tokens");
+        }
+
+        if new_total_locked <= self.balance.amount_locked {
+            // caller needs to have checked this
+            panic!("FATAL: cannot decrease the amount of locked tokens");
+        }
+
+        let amount_to_lock = new_total_locked - self.balance.amount_locked;
+        self.balance.amount_unlocked = self
+            .balance
+            .amount_unlocked
+            .checked_sub(amount_to_lock)
+            .expect("STX underflow");
+
+        self.balance.amount_locked = new_total_locked;
     }
 
-    fn unlock_available_tokens_if_any(&mut self) -> u128 {
+    /// Lock some of the account's unlocked tokens until the given burn height, independently of
+    /// its PoX lock and any other locks.  Locks that expire at the same height are merged.
+    pub fn add_lock(&mut self, amount_to_lock: u128, unlock_burn_height: u64) {
+        let unlocked = self.unlock_available_tokens_if_any();
+        if unlocked > 0 {
+            debug!("Consolidated after account-token-lock");
+        }
+
+        // caller needs to have checked this
+        assert!(amount_to_lock > 0, "BUG: cannot lock 0 tokens");
+
+        if unlock_burn_height <= self.burn_block_height {
+            // caller needs to have checked this
+            panic!("FATAL: cannot set a lock with expired unlock burn height");
+        }
+
+        self.balance.amount_unlocked = self
+            .balance
+            .amount_unlocked
+            .checked_sub(amount_to_lock)
+            .expect("STX underflow");
+
+        match self
+            .balance
+            .locks
+            .iter_mut()
+            .find(|lock| lock.unlock_height == unlock_burn_height)
+        {
+            Some(lock) => {
+                lock.amount = lock
+                    .amount
+                    .checked_add(amount_to_lock)
+                    .expect("STX overflow");
+            }
+            None => {
+                self.balance.locks.push(STXLock {
+                    amount: amount_to_lock,
+                    unlock_height: unlock_burn_height,
+                });
+            }
+        }
+        self.db_ref
+            .schedule_stx_unlock(&self.principal, unlock_burn_height);
+    }
+
+    /// Release every lock that has expired into the account's unlocked balance.
+    /// Returns the number of uSTX released.
+    pub fn unlock_available_tokens_if_any(&mut self) -> u128 {
         if !self
             .balance
             .has_unlockable_tokens_at_burn_block(self.burn_block_height)
@@ -309,20 +472,39 @@ impl<'db, 'conn> STXBalanceSnapshot<'db, 'conn> {
             return 0;
         }
 
-        let unlocked = self.balance.amount_locked;
-        self.balance.unlock_height = 0;
+        let mut unlocked = 0u128;
+        if self.balance.amount_locked > 0 && self.balance.unlock_height <= self.burn_block_height {
+            unlocked = self.balance.amount_locked;
+            self.balance.unlock_height = 0;
+            self.balance.amount_locked = 0;
+        }
+
+        let burn_block_height = self.burn_block_height;
+        for lock in self.balance.locks.iter() {
+            if lock.unlock_height <= burn_block_height {
+                unlocked = unlocked.checked_add(lock.amount).expect("STX overflow");
+            }
+        }
+        self.balance
+            .locks
+            .retain(|lock| lock.unlock_height > burn_block_height);
+
         self.balance.amount_unlocked = self
             .balance
             .amount_unlocked
             .checked_add(unlocked)
             .expect("STX overflow");
-        self.balance.amount_locked = 0;
         unlocked
     }
 }
 
 // NOTE: do _not_ add mutation methods to this struct. Put them in STXBalanceSnapshot!
+impl STXLock {
+    pub const size_of: usize = 24;
+}
+
 impl STXBalance {
+    /// the size of a balance without independent locks
     pub const size_of: usize = 40;
 
     pub fn zero() -> STXBalance {
@@ -330,6 +512,7 @@ impl STXBalance {
             amount_unlocked: 0,
             amount_locked: 0,
             unlock_height: 0,
+            locks: vec![],
         }
     }
 
@@ -338,40 +521,128 @@ impl STXBalance {
             amount_unlocked,
             amount_locked: 0,
             unlock_height: 0,
+            locks: vec![],
         }
     }
 
+    /// How many STX the account can spend: its unlocked STX, plus those in every lock that has
+    /// expired
     pub fn get_available_balance_at_burn_block(&self, burn_block_height: u64) -> u128 {
-        if self.has_unlockable_tokens_at_burn_block(burn_block_height) {
-            self.get_total_balance()
-        } else {
-            self.amount_unlocked
+        let mut available = self.amount_unlocked;
+        if self.amount_locked > 0 && self.unlock_height <= burn_block_height {
+            available = available
+                .checked_add(self.amount_locked)
+                .expect("STX overflow");
         }
+        for lock in self.locks.iter() {
+            if lock.unlock_height <= burn_block_height {
+                available = available.checked_add(lock.amount).expect("STX overflow");
+            }
+        }
+        available
     }
 
+    /// The PoX lock's amount and unlock height, or (0, 0) if it has expired
     pub fn get_locked_balance_at_burn_block(&self, burn_block_height: u64) -> (u128, u64) {
-        if self.has_unlockable_tokens_at_burn_block(burn_block_height) {
-            (0, 0)
-        } else {
+        if self.has_locked_tokens_at_burn_block(burn_block_height) {
             (self.amount_locked, self.unlock_height)
+        } else {
+            (0, 0)
         }
     }
 
+    /// The locks other than the PoX lock that have not expired
+    pub fn get_independent_locks_at_burn_block(&self, burn_block_height: u64) -> Vec<STXLock> {
+        self.locks
+            .iter()
+            .filter(|lock| lock.unlock_height > burn_block_height)
+            .cloned()
+            .collect()
+    }
+
+    /// How many STX are in locks, PoX or otherwise, that have not expired
+    pub fn get_total_locked_balance_at_burn_block(&self, burn_block_height: u64) -> u128 {
+        self.get_total_balance() - self.get_available_balance_at_burn_block(burn_block_height)
+    }
+
     pub fn get_total_balance(&self) -> u128 {
-        self.amount_unlocked
-            .checked_add(self.amount_locked)
-            .expect("STX overflow")
+        self.locks.iter().fold(
+            self.amount_unlocked
+                .checked_add(self.amount_locked)
+                .expect("STX overflow"),
+            |total, lock| total.checked_add(lock.amount).expect("STX overflow"),
+        )
     }
 
+    /// Does the account have STX locked by PoX?
     pub fn has_locked_tokens_at_burn_block(&self, burn_block_height: u64) -> bool {
         self.amount_locked > 0 && self.unlock_height > burn_block_height
     }
 
+    /// Does the account have STX in any expired lock, PoX or otherwise?
     pub fn has_unlockable_tokens_at_burn_block(&self, burn_block_height: u64) -> bool {
-        self.amount_locked > 0 && self.unlock_height <= burn_block_height
+        (self.amount_locked > 0 && self.unlock_height <= burn_block_height)
+            || self
+                .locks
+                .iter()
+                .any(|lock| lock.unlock_height <= burn_block_height)
     }
 
     pub fn can_transfer_at_burn_block(&self, amount: u128, burn_block_height: u64) -> bool {
         self.get_available_balance_at_burn_block(burn_block_height) >= amount
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stx_balance_serialization() {
+        // a balance without independent locks keeps the original 40-byte encoding
+        let legacy = STXBalance {
+            amount_unlocked: 1,
+            amount_locked: 2,
+            unlock_height: 3,
+            locks: vec![],
+        };
+        let legacy_hex = legacy.serialize();
+        assert_eq!(
+            legacy_hex,
+            "00000000000000000000000000000001000000000000000000000000000000020000000000000003"
+        );
+        assert_eq!(STXBalance::deserialize(&legacy_hex), legacy);
+
+        // each independent lock adds 24 bytes
+        let with_locks = STXBalance {
+            amount_unlocked: 1,
+            amount_locked: 2,
+            unlock_height: 3,
+            locks: vec![
+                STXLock {
+                    amount: 4,
+                    unlock_height: 5,
+                },
+                STXLock {
+                    amount: 6,
+                    unlock_height: 7,
+                },
+            ],
+        };
+        let with_locks_hex = with_locks.serialize();
+        assert_eq!(
+            with_locks_hex.len(),
+            2 * (STXBalance::size_of + 2 * STXLock::size_of)
+        );
+        assert!(with_locks_hex.starts_with(&legacy_hex));
+        assert_eq!(STXBalance::deserialize(&with_locks_hex), with_locks);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_stx_balance_deserialize_partial_lock() {
+        let mut hex = STXBalance::zero().serialize();
+        hex.push_str("00000000000000000000000000000004");
+        STXBalance::deserialize(&hex);
+    }
+}
@@ -1,4 +1,4 @@
-use chainstate::stacks::boot::STACKS_BOOT_CODE_MAINNET;
+use chainstate::stacks::boot::{STACKS_2_1_BOOT_CODE_MAINNET, STACKS_BOOT_CODE_MAINNET};
 use vm::analysis::{mem_type_check, ContractAnalysis};
 use vm::docs::{get_input_type_string, get_output_type_string, get_signature};
 use vm::types::{FunctionType, Value};
@@ -52,6 +52,21 @@ This is the self-service interface.  tx-sender will be the Stacker.
    method still requires stacking over the _absolute minimum_ amount, which can be obtained by calling `get-stacking-minimum`.
 
 The tokens will unlock and be returned to the Stacker (tx-sender) automatically."),
+        ("stack-extend", "Extend an active Stacking lock by `extend-count` more reward cycles, without first unlocking.
+The extra cycles pay out to the same PoX address.
+This is the self-service interface.  tx-sender will be the Stacker.
+
+* The given stacker must be stacking, and must have stacked via `stack-stx`.
+* The stacked amount must still meet the minimum uSTX threshold.
+* The lock may not reach more than 12 reward cycles past the current one.
+
+The tokens will unlock and be returned to the Stacker (tx-sender) at the end of the extended lock period."),
+        ("stack-increase", "Lock up `increase-by` more uSTX for the rest of an active Stacking lock, without first unlocking.
+The uSTX count towards every reward cycle of the lock that has not yet begun.
+This is the self-service interface.  tx-sender will be the Stacker.
+
+* The given stacker must be stacking, and must have stacked via `stack-stx`.
+* The Stacker must have `increase-by` unlocked uSTX."),
         ("revoke-delegate-stx", "Revoke a Stacking delegate relationship. A particular Stacker may only have one delegate,
 so this method does not take any parameters, and just revokes the Stacker's current delegate (if one exists)."),
         ("delegate-stx", "Delegate to `delegate-to` the ability to stack from a given address.
@@ -92,13 +107,23 @@ is expired, or if there's never been such a stacker, then returns none."),
         "get-reward-set-pox-address",
     ];
 
-    HashMap::from_iter(vec![(
-        "pox",
-        ContractSupportDocs {
-            descriptions: HashMap::from_iter(pox_descriptions.into_iter()),
-            skip_func_display: HashSet::from_iter(pox_skip_display.into_iter()),
-        },
-    )])
+    // pox-2 has the same functions as pox, plus stack-extend and stack-increase
+    HashMap::from_iter(vec![
+        (
+            "pox",
+            ContractSupportDocs {
+                descriptions: HashMap::from_iter(pox_descriptions.clone().into_iter()),
+                skip_func_display: HashSet::from_iter(pox_skip_display.clone().into_iter()),
+            },
+        ),
+        (
+            "pox-2",
+            ContractSupportDocs {
+                descriptions: HashMap::from_iter(pox_descriptions.into_iter()),
+                skip_func_display: HashSet::from_iter(pox_skip_display.into_iter()),
+            },
+        ),
+    ])
 }
 
 fn make_func_ref(func_name: &str, func_type: &FunctionType, description: &str) -> FunctionRef {
@@ -126,7 +151,10 @@ fn produce_docs() -> BTreeMap<String, ContractRef> {
     let mut docs = BTreeMap::new();
     let support_docs = make_contract_support_docs();
 
-    for (contract_name, content) in STACKS_BOOT_CODE_MAINNET.iter() {
+    for (contract_name, content) in STACKS_BOOT_CODE_MAINNET
+        .iter()
+        .chain(STACKS_2_1_BOOT_CODE_MAINNET.iter())
+    {
         let (_, contract_analysis) =
             mem_type_check(content).expect("BUG: failed to type check boot contract");
 
@@ -815,6 +815,25 @@ const CONTRACT_OF_API: SpecialAPI = SpecialAPI {
 "
 };
 
+const IS_REENTRANT_API: SpecialAPI = SpecialAPI {
+    input_type: "",
+    output_type: "bool",
+    signature: "(is-reentrant)",
+    description: "The `is-reentrant` function returns `true` if the current contract has been re-entered: that is,
+one of its public functions was called (with `contract-call?`) while an earlier call into one of its public
+functions has not yet returned. For example, if `.exchange` calls a token contract passed in as a trait, and that
+token contract calls back into `.exchange`, then `(is-reentrant)` is `true` in the callback. Contracts can use it to
+reject re-entrant calls without keeping a lock in a data var. It always returns `false` when called from the top-level
+of a contract.",
+    example: "
+(define-public (withdraw (amount uint))
+  (begin
+    (asserts! (not (is-reentrant)) (err u1))
+    (ok amount)))
+(is-reentrant) ;; Returns false
+"
+};
+
 const PRINCIPAL_OF_API: SpecialAPI = SpecialAPI {
     input_type: "(buff 33)",
     output_type: "(response principal uint)",
@@ -1116,6 +1135,31 @@ The `id-header-hash` is the block identifier value that must be used as input to
 "
 };
 
+const GET_BURN_BLOCK_INFO_API: SpecialAPI = SpecialAPI {
+    input_type: "BurnBlockInfoPropertyName, BurnBlockHeightInt",
+    output_type: "(optional buff) | (optional uint)",
+    signature: "(get-burn-block-info? prop-name burn-block-height-expr)",
+    description: "The `get-burn-block-info?` function fetches data for a burnchain block of the given burnchain
+block height. The value and type returned are determined by the specified `BurnBlockInfoPropertyName`. If the provided
+`BurnBlockHeightInt` does not correspond to a burnchain block known to the current Stacks block's parent, the function
+returns `none`. The currently available property names are `header-hash`, `time`, and `median-time-past`.
+
+The `header-hash` property returns a 32-byte buffer of the burnchain block header hash.
+
+The `time` property returns an integer value of the burnchain block header time field. This is a Unix epoch
+timestamp in seconds, and like `get-block-info? time`, it does not increase monotonically.
+
+The `median-time-past` property returns the median of the header times of the burnchain block and its 10 ancestors.
+Unlike `time`, this value never decreases from one burnchain block to the next, which makes it a better clock for
+contracts than either `time` or `burn-block-height`. See [BIP113](https://github.com/bitcoin/bips/blob/master/bip-0113.mediawiki)
+for more information.
+",
+    example: "(get-burn-block-info? time u677050) ;; Returns (some u1557860301)
+(get-burn-block-info? median-time-past u677050) ;; Returns (some u1557857301)
+(get-burn-block-info? header-hash u677050) ;; Returns (some 0xe67141016c88a7f1203eca0b4312f2ed141531f59303a1c267d7d83ab6b977d8)
+"
+};
+
 const DEFINE_TOKEN_API: DefineAPI = DefineAPI {
     input_type: "TokenName, <uint>",
     output_type: "Not Applicable",
@@ -1375,6 +1419,24 @@ Otherwise, on successfuly mint, it returns `(ok true)`.
 "
 };
 
+const MINT_ASSET_MANY: SpecialAPI = SpecialAPI {
+    input_type: "AssetName, (list A), principal",
+    output_type: "(response (list (response bool uint)) (list (response bool uint)))",
+    signature: "(nft-mint-many? asset-class asset-identifiers recipient)",
+    description: "`nft-mint-many?` mints each asset in the list `asset-identifiers` to the `recipient` principal, in order,
+as if by `nft-mint?`. Each asset is priced the same as a separate `nft-mint?`.
+
+This function returns a list with the result of each mint: `(ok true)` if the asset was minted, or `(err u1)` if it
+_already exists_. If every asset was minted, the list is wrapped in `ok`; otherwise, it is wrapped in `err`, and
+none of the assets are minted.
+",
+    example: "
+(define-non-fungible-token stackaroo (string-ascii 40))
+(nft-mint-many? stackaroo (list \"Roo\" \"Too\") 'SPAXYA5XS51713FDTQ8H94EJ4V579CXMTRNBZKSF) ;; returns (ok ((ok true) (ok true)))
+(nft-mint-many? stackaroo (list \"Roo\" \"Kanga\") 'SPAXYA5XS51713FDTQ8H94EJ4V579CXMTRNBZKSF) ;; returns (err ((err u1) (ok true)))
+"
+};
+
 const GET_OWNER: SpecialAPI = SpecialAPI {
     input_type: "AssetName, A",
     output_type: "(optional principal)",
@@ -1449,6 +1511,31 @@ one of the following error codes:
 "
 };
 
+const ASSET_TRANSFER_MANY: SpecialAPI = SpecialAPI {
+    input_type: "AssetName, (list A), principal, principal",
+    output_type: "(response (list (response bool uint)) (list (response bool uint)))",
+    signature: "(nft-transfer-many? asset-class asset-identifiers sender recipient)",
+    description: "`nft-transfer-many?` transfers each asset in the list `asset-identifiers` from `sender` to `recipient`,
+in order, as if by `nft-transfer?`. Each asset is priced the same as a separate `nft-transfer?`.
+
+This function returns a list with the result of each transfer: `(ok true)` if the asset was transferred, or one of
+the error codes of `nft-transfer?`:
+
+`(err u1)` -- `sender` does not own the asset
+`(err u2)` -- `sender` and `recipient` are the same principal
+`(err u3)` -- asset identified by asset-identifier does not exist
+
+If every asset was transferred, the list is wrapped in `ok`; otherwise, it is wrapped in `err`, and none of the
+assets are transferred.
+",
+    example: "
+(define-non-fungible-token stackaroo (string-ascii 40))
+(nft-mint-many? stackaroo (list \"Roo\" \"Too\") 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)
+(nft-transfer-many? stackaroo (list \"Roo\" \"Too\") 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SPAXYA5XS51713FDTQ8H94EJ4V579CXMTRNBZKSF) ;; returns (ok ((ok true) (ok true)))
+(nft-transfer-many? stackaroo (list \"Roo\" \"Stacka\") 'SPAXYA5XS51713FDTQ8H94EJ4V579CXMTRNBZKSF 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR) ;; returns (err ((ok true) (err u3)))
+"
+};
+
 const STX_GET_BALANCE: SimpleFunctionAPI = SimpleFunctionAPI {
     name: None,
     signature: "(stx-get-balance owner)",
@@ -1463,6 +1550,23 @@ principal isn't materialized, it returns 0.
 ",
 };
 
+const STX_GET_ACCOUNT: SimpleFunctionAPI = SimpleFunctionAPI {
+    name: None,
+    signature: "(stx-account owner)",
+    description: "`stx-account` is used to query the STX account of the `owner` principal.
+
+This function returns a tuple with the canonical account representation for an STX account.
+This includes the current amount of unlocked STX, the current amount of locked STX, and the
+unlock height for any locked STX, all denominated in micro-STX. If the lock has expired, the
+locked STX are counted as unlocked. In the event that the `owner` principal isn't materialized,
+all three fields are 0.
+",
+    example: "
+(stx-account 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR) ;; Returns (tuple (locked u0) (unlock-height u0) (unlocked u0))
+(stx-account (as-contract tx-sender)) ;; Returns (tuple (locked u0) (unlock-height u0) (unlocked u1000))
+",
+};
+
 const STX_TRANSFER: SimpleFunctionAPI = SimpleFunctionAPI {
     name: None,
     signature: "(stx-transfer? amount sender recipient)",
@@ -1485,6 +1589,23 @@ one of the following error codes:
 "
 };
 
+const STX_TRANSFER_MEMO: SimpleFunctionAPI = SimpleFunctionAPI {
+    name: None,
+    signature: "(stx-transfer-memo? amount sender recipient memo)",
+    description: "`stx-transfer-memo?` is similar to `stx-transfer?`, except that it adds a `memo` field to the
+STX transfer event that it emits. This lets the recipient (e.g. an exchange) attribute a deposit to an account
+without the sender having to emit a separate `print` event.
+
+This function returns (ok true) if the transfer is successful, or, on an error, returns the same codes as `stx-transfer?`.
+",
+    example: "
+(as-contract
+  (stx-transfer-memo? u60 tx-sender 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 0x010203)) ;; Returns (ok true)
+(as-contract
+  (stx-transfer-memo? u50 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR tx-sender 0x010203)) ;; Returns (err u4)
+"
+};
+
 const STX_BURN: SimpleFunctionAPI = SimpleFunctionAPI {
     name: None,
     signature: "(stx-burn? amount sender)",
@@ -1561,9 +1682,11 @@ fn make_api_reference(function: &NativeFunctions) -> FunctionAPI {
         Print => make_for_special(&PRINT_API, name),
         ContractCall => make_for_special(&CONTRACT_CALL_API, name),
         ContractOf => make_for_special(&CONTRACT_OF_API, name),
+        IsReentrant => make_for_special(&IS_REENTRANT_API, name),
         PrincipalOf => make_for_special(&PRINCIPAL_OF_API, name),
         AsContract => make_for_special(&AS_CONTRACT_API, name),
         GetBlockInfo => make_for_special(&GET_BLOCK_INFO_API, name),
+        GetBurnBlockInfo => make_for_special(&GET_BURN_BLOCK_INFO_API, name),
         ConsOkay => make_for_special(&CONS_OK_API, name),
         ConsError => make_for_special(&CONS_ERR_API, name),
         ConsSome => make_for_special(&CONS_SOME_API, name),
@@ -1580,14 +1703,18 @@ fn make_api_reference(function: &NativeFunctions) -> FunctionAPI {
         IsErr => make_for_special(&IS_ERR_API, name),
         IsSome => make_for_special(&IS_SOME_API, name),
         MintAsset => make_for_special(&MINT_ASSET, name),
+        MintAssetMany => make_for_special(&MINT_ASSET_MANY, name),
         MintToken => make_for_special(&MINT_TOKEN, name),
         GetTokenBalance => make_for_special(&GET_BALANCE, name),
         GetAssetOwner => make_for_special(&GET_OWNER, name),
         TransferToken => make_for_special(&TOKEN_TRANSFER, name),
         TransferAsset => make_for_special(&ASSET_TRANSFER, name),
+        TransferAssetMany => make_for_special(&ASSET_TRANSFER_MANY, name),
         AtBlock => make_for_special(&AT_BLOCK, name),
         GetStxBalance => make_for_simple_native(&STX_GET_BALANCE, &GetStxBalance, name),
+        GetStxAccount => make_for_simple_native(&STX_GET_ACCOUNT, &GetStxAccount, name),
         StxTransfer => make_for_simple_native(&STX_TRANSFER, &StxTransfer, name),
+        StxTransferMemo => make_for_simple_native(&STX_TRANSFER_MEMO, &StxTransferMemo, name),
         StxBurn => make_for_simple_native(&STX_BURN, &StxBurn, name),
     }
 }
@@ -1684,7 +1811,7 @@ mod test {
     use super::make_json_api_reference;
     use burnchains::BurnchainHeaderHash;
     use chainstate::burn::db::sortdb::SortitionId;
-    use chainstate::burn::{BlockHeaderHash, VRFSeed};
+    use chainstate::burn::{BlockHeaderHash, ConsensusHash, VRFSeed};
     use chainstate::stacks::{index::MarfTrieId, StacksAddress, StacksBlockId};
 
     use vm::{
@@ -1693,8 +1820,8 @@ mod test {
         database::{BurnStateDB, HeadersDB, MarfedKV, STXBalance},
         eval_all, execute,
         types::PrincipalData,
-        ContractContext, Error, GlobalContext, LimitedCostTracker, QualifiedContractIdentifier,
-        Value,
+        ClarityVersion, ContractContext, Error, GlobalContext, LimitedCostTracker,
+        QualifiedContractIdentifier, Value,
     };
 
     struct DocHeadersDB {}
@@ -1732,6 +1859,9 @@ mod test {
         fn get_burn_block_height_for_block(&self, _id_bhh: &StacksBlockId) -> Option<u32> {
             Some(567890)
         }
+        fn get_consensus_hash_for_block(&self, _id_bhh: &StacksBlockId) -> Option<ConsensusHash> {
+            Some(ConsensusHash([0; 20]))
+        }
         fn get_miner_address(&self, _id_bhh: &StacksBlockId) -> Option<StacksAddress> {
             None
         }
@@ -1759,6 +1889,22 @@ mod test {
                 .unwrap(),
             )
         }
+        fn get_burn_block_time(&self, _height: u32, _sortition_id: &SortitionId) -> Option<u64> {
+            Some(1557860301)
+        }
+        fn get_burn_median_time_past(
+            &self,
+            _height: u32,
+            _sortition_id: &SortitionId,
+        ) -> Option<u64> {
+            Some(1557857301)
+        }
+        fn get_sortition_id_from_consensus_hash(
+            &self,
+            _consensus_hash: &ConsensusHash,
+        ) -> Option<SortitionId> {
+            Some(SortitionId([0; 32]))
+        }
     }
 
     fn docs_execute(marf: &mut MarfedKV, program: &str) {
@@ -1784,7 +1930,8 @@ mod test {
 
         let conn = marf.as_clarity_db(&DOC_HEADER_DB, &DOC_POX_STATE_DB);
         let contract_id = QualifiedContractIdentifier::local("docs-test").unwrap();
-        let mut contract_context = ContractContext::new(contract_id.clone());
+        let mut contract_context =
+            ContractContext::new(contract_id.clone(), ClarityVersion::latest());
         let mut global_context = GlobalContext::new(conn, LimitedCostTracker::new_free());
 
         global_context
@@ -1800,9 +1947,14 @@ mod test {
                     eprintln!("{}", segment);
 
                     let result = {
-                        let parsed = ast::build_ast(&contract_id, segment, &mut ())
-                            .unwrap()
-                            .expressions;
+                        let parsed = ast::build_ast(
+                            &contract_id,
+                            segment,
+                            &mut (),
+                            ClarityVersion::latest(),
+                        )
+                        .unwrap()
+                        .expressions;
                         eval_all(&parsed, &mut contract_context, g).unwrap()
                     };
 
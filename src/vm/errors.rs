@@ -93,6 +93,8 @@ pub enum RuntimeErrorType {
     UnknownBlockHeaderHash(BlockHeaderHash),
     BadBlockHash(Vec<u8>),
     UnwrapFailure,
+    // the node could not carry out a lock that PoX asked for
+    PoxLockFailure(String),
 }
 
 #[derive(Debug, PartialEq)]
@@ -23,9 +23,10 @@ use vm::errors::{
     check_argument_count, CheckErrors, Error, InterpreterError, InterpreterResult as Result,
     RuntimeErrorType,
 };
-use vm::representations::SymbolicExpression;
+use vm::representations::{ClarityName, SymbolicExpression};
 use vm::types::{
-    AssetIdentifier, BlockInfoProperty, BuffData, OptionalData, PrincipalData, TypeSignature, Value,
+    AssetIdentifier, BlockInfoProperty, BuffData, ListTypeData, OptionalData, PrincipalData,
+    SequenceData, TupleData, TypeSignature, Value,
 };
 use vm::{eval, Environment, LocalContext};
 
@@ -86,6 +87,39 @@ pub fn special_stx_balance(
     }
 }
 
+pub fn special_stx_account(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    check_argument_count(1, args)?;
+
+    runtime_cost(ClarityCostFunction::StxGetAccount, env, 0)?;
+
+    let owner = eval(&args[0], env, context)?;
+
+    if let Value::Principal(ref principal) = owner {
+        let (unlocked, locked, unlock_height) = {
+            let snapshot = env
+                .global_context
+                .database
+                .get_stx_balance_snapshot(principal);
+            let (locked, unlock_height) = snapshot.get_locked_balance();
+            (snapshot.get_available_balance(), locked, unlock_height)
+        };
+        Ok(Value::Tuple(
+            TupleData::from_data(vec![
+                ("unlocked".into(), Value::UInt(unlocked)),
+                ("locked".into(), Value::UInt(locked)),
+                ("unlock-height".into(), Value::UInt(unlock_height as u128)),
+            ])
+            .expect("FATAL: failed to construct stx-account tuple"),
+        ))
+    } else {
+        Err(CheckErrors::TypeValueError(TypeSignature::PrincipalType, owner).into())
+    }
+}
+
 /// Do a "consolidated" STX transfer.
 /// If the 'from' principal has locked STX, and they have unlocked, then process the STX unlock
 /// and update its balance in addition to spending tokens out of it.
@@ -94,6 +128,7 @@ pub fn stx_transfer_consolidated(
     from: &PrincipalData,
     to: &PrincipalData,
     amount: u128,
+    memo: &BuffData,
 ) -> Result<Value> {
     if amount <= 0 {
         return clarity_ecode!(StxErrorCodes::NON_POSITIVE_AMOUNT);
@@ -129,7 +164,7 @@ pub fn stx_transfer_consolidated(
     sender_snapshot.transfer_to(to, amount)?;
 
     env.global_context.log_stx_transfer(&from, amount)?;
-    env.register_stx_transfer_event(from.clone(), to.clone(), amount)?;
+    env.register_stx_transfer_event(from.clone(), to.clone(), amount, memo.clone())?;
     Ok(Value::okay_true())
 }
 
@@ -149,7 +184,34 @@ pub fn special_stx_transfer(
     if let (Value::Principal(ref from), Value::Principal(ref to), Value::UInt(amount)) =
         (&from_val, to_val, amount_val)
     {
-        stx_transfer_consolidated(env, from, to, amount)
+        stx_transfer_consolidated(env, from, to, amount, &BuffData::empty())
+    } else {
+        Err(CheckErrors::BadTransferSTXArguments.into())
+    }
+}
+
+pub fn special_stx_transfer_memo(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    check_argument_count(4, args)?;
+
+    runtime_cost(ClarityCostFunction::StxTransfer, env, 0)?;
+
+    let amount_val = eval(&args[0], env, context)?;
+    let from_val = eval(&args[1], env, context)?;
+    let to_val = eval(&args[2], env, context)?;
+    let memo_val = eval(&args[3], env, context)?;
+
+    if let (
+        Value::Principal(ref from),
+        Value::Principal(ref to),
+        Value::UInt(amount),
+        Value::Sequence(SequenceData::Buffer(ref memo)),
+    ) = (&from_val, to_val, amount_val, memo_val)
+    {
+        stx_transfer_consolidated(env, from, to, amount, memo)
     } else {
         Err(CheckErrors::BadTransferSTXArguments.into())
     }
@@ -279,38 +341,132 @@ pub fn special_mint_asset(
     }
 
     if let Value::Principal(ref to_principal) = to {
-        match env.global_context.database.get_nft_owner(
-            &env.contract_context.contract_identifier,
-            asset_name,
-            &asset,
-        ) {
-            Err(Error::Runtime(RuntimeErrorType::NoSuchToken, _)) => Ok(()),
-            Ok(_owner) => return clarity_ecode!(MintAssetErrorCodes::ALREADY_EXIST),
-            Err(e) => Err(e),
-        }?;
+        mint_asset(env, asset_name, &expected_asset_type, asset, to_principal)
+    } else {
+        Err(CheckErrors::TypeValueError(TypeSignature::PrincipalType, to).into())
+    }
+}
 
-        env.add_memory(TypeSignature::PrincipalType.size() as u64)?;
-        env.add_memory(expected_asset_type.size() as u64)?;
+/// Mint a single asset, whose type has already been checked, to `to_principal`.
+fn mint_asset(
+    env: &mut Environment,
+    asset_name: &ClarityName,
+    expected_asset_type: &TypeSignature,
+    asset: Value,
+    to_principal: &PrincipalData,
+) -> Result<Value> {
+    match env.global_context.database.get_nft_owner(
+        &env.contract_context.contract_identifier,
+        asset_name,
+        &asset,
+    ) {
+        Err(Error::Runtime(RuntimeErrorType::NoSuchToken, _)) => Ok(()),
+        Ok(_owner) => return clarity_ecode!(MintAssetErrorCodes::ALREADY_EXIST),
+        Err(e) => Err(e),
+    }?;
 
-        env.global_context.database.set_nft_owner(
-            &env.contract_context.contract_identifier,
-            asset_name,
-            &asset,
-            to_principal,
-        )?;
+    env.add_memory(TypeSignature::PrincipalType.size() as u64)?;
+    env.add_memory(expected_asset_type.size() as u64)?;
 
-        let asset_identifier = AssetIdentifier {
-            contract_identifier: env.contract_context.contract_identifier.clone(),
-            asset_name: asset_name.clone(),
-        };
-        env.register_nft_mint_event(to_principal.clone(), asset, asset_identifier)?;
+    env.global_context.database.set_nft_owner(
+        &env.contract_context.contract_identifier,
+        asset_name,
+        &asset,
+        to_principal,
+    )?;
 
-        Ok(Value::okay_true())
+    let asset_identifier = AssetIdentifier {
+        contract_identifier: env.contract_context.contract_identifier.clone(),
+        asset_name: asset_name.clone(),
+    };
+    env.register_nft_mint_event(to_principal.clone(), asset, asset_identifier)?;
+
+    Ok(Value::okay_true())
+}
+
+/// Wrap the per-item results of a batch NFT operation: `(ok results)` if every item
+/// succeeded, and `(err results)` otherwise, in which case the caller rolls the batch back.
+fn nft_batch_result(results: Vec<Value>) -> Result<Value> {
+    let all_okay = results.iter().all(|result| match result {
+        Value::Response(ref data) => data.committed,
+        _ => false,
+    });
+    let results_type = ListTypeData::new_list(
+        TypeSignature::new_response(TypeSignature::BoolType, TypeSignature::UIntType)?,
+        results.len() as u32,
+    )?;
+    let results = Value::list_with_type(results, results_type)?;
+    if all_okay {
+        Value::okay(results)
+    } else {
+        Value::error(results)
+    }
+}
+
+pub fn special_mint_asset_many(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    check_argument_count(3, args)?;
+
+    let asset_name = args[0].match_atom().ok_or(CheckErrors::BadTokenName)?;
+
+    let assets = eval(&args[1], env, context)?;
+    let to = eval(&args[2], env, context)?;
+
+    let expected_asset_type = env
+        .global_context
+        .database
+        .get_nft_key_type(&env.contract_context.contract_identifier, asset_name)?;
+
+    let assets = match assets {
+        Value::Sequence(SequenceData::List(list_data)) => list_data.data,
+        _ => return Err(CheckErrors::ExpectedListApplication.into()),
+    };
+
+    if let Value::Principal(ref to_principal) = to {
+        // the batch is all-or-nothing: if any item fails, none of them are minted
+        env.global_context.begin();
+        let result = mint_asset_batch(env, asset_name, expected_asset_type, assets, to_principal);
+        env.global_context.handle_tx_result(result)
     } else {
         Err(CheckErrors::TypeValueError(TypeSignature::PrincipalType, to).into())
     }
 }
 
+/// Mint each of `assets` to `to_principal`, and wrap the per-item results.
+fn mint_asset_batch(
+    env: &mut Environment,
+    asset_name: &ClarityName,
+    expected_asset_type: TypeSignature,
+    assets: Vec<Value>,
+    to_principal: &PrincipalData,
+) -> Result<Value> {
+    let mut results = Vec::with_capacity(assets.len());
+    for asset in assets.into_iter() {
+        // priced per item, the same as nft-mint?
+        runtime_cost(
+            ClarityCostFunction::NftMint,
+            env,
+            expected_asset_type.size(),
+        )?;
+
+        if !expected_asset_type.admits(&asset) {
+            return Err(CheckErrors::TypeValueError(expected_asset_type, asset).into());
+        }
+
+        results.push(mint_asset(
+            env,
+            asset_name,
+            &expected_asset_type,
+            asset,
+            to_principal,
+        )?);
+    }
+    nft_batch_result(results)
+}
+
 pub fn special_transfer_asset(
     args: &[SymbolicExpression],
     env: &mut Environment,
@@ -340,58 +496,153 @@ pub fn special_transfer_asset(
     }
 
     if let (Value::Principal(ref from_principal), Value::Principal(ref to_principal)) = (from, to) {
-        if from_principal == to_principal {
-            return clarity_ecode!(TransferAssetErrorCodes::SENDER_IS_RECIPIENT);
-        }
-
-        let current_owner = match env.global_context.database.get_nft_owner(
-            &env.contract_context.contract_identifier,
+        transfer_asset(
+            env,
             asset_name,
-            &asset,
-        ) {
-            Ok(owner) => Ok(owner),
-            Err(Error::Runtime(RuntimeErrorType::NoSuchToken, _)) => {
-                return clarity_ecode!(TransferAssetErrorCodes::DOES_NOT_EXIST)
-            }
-            Err(e) => Err(e),
-        }?;
-
-        if current_owner != *from_principal {
-            return clarity_ecode!(TransferAssetErrorCodes::NOT_OWNED_BY);
+            &expected_asset_type,
+            asset,
+            from_principal,
+            to_principal,
+        )
+    } else {
+        Err(CheckErrors::BadTransferNFTArguments.into())
+    }
+}
+
+/// Transfer a single asset, whose type has already been checked, from `from_principal` to
+/// `to_principal`.
+fn transfer_asset(
+    env: &mut Environment,
+    asset_name: &ClarityName,
+    expected_asset_type: &TypeSignature,
+    asset: Value,
+    from_principal: &PrincipalData,
+    to_principal: &PrincipalData,
+) -> Result<Value> {
+    if from_principal == to_principal {
+        return clarity_ecode!(TransferAssetErrorCodes::SENDER_IS_RECIPIENT);
+    }
+
+    let current_owner = match env.global_context.database.get_nft_owner(
+        &env.contract_context.contract_identifier,
+        asset_name,
+        &asset,
+    ) {
+        Ok(owner) => Ok(owner),
+        Err(Error::Runtime(RuntimeErrorType::NoSuchToken, _)) => {
+            return clarity_ecode!(TransferAssetErrorCodes::DOES_NOT_EXIST)
         }
+        Err(e) => Err(e),
+    }?;
 
-        env.add_memory(TypeSignature::PrincipalType.size() as u64)?;
-        env.add_memory(expected_asset_type.size() as u64)?;
+    if current_owner != *from_principal {
+        return clarity_ecode!(TransferAssetErrorCodes::NOT_OWNED_BY);
+    }
 
-        env.global_context.database.set_nft_owner(
-            &env.contract_context.contract_identifier,
-            asset_name,
-            &asset,
-            to_principal,
-        )?;
+    env.add_memory(TypeSignature::PrincipalType.size() as u64)?;
+    env.add_memory(expected_asset_type.size() as u64)?;
 
-        env.global_context.log_asset_transfer(
-            from_principal,
-            &env.contract_context.contract_identifier,
+    env.global_context.database.set_nft_owner(
+        &env.contract_context.contract_identifier,
+        asset_name,
+        &asset,
+        to_principal,
+    )?;
+
+    env.global_context.log_asset_transfer(
+        from_principal,
+        &env.contract_context.contract_identifier,
+        asset_name,
+        asset.clone(),
+    );
+
+    let asset_identifier = AssetIdentifier {
+        contract_identifier: env.contract_context.contract_identifier.clone(),
+        asset_name: asset_name.clone(),
+    };
+    env.register_nft_transfer_event(
+        from_principal.clone(),
+        to_principal.clone(),
+        asset,
+        asset_identifier,
+    )?;
+
+    Ok(Value::okay_true())
+}
+
+pub fn special_transfer_asset_many(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    check_argument_count(4, args)?;
+
+    let asset_name = args[0].match_atom().ok_or(CheckErrors::BadTokenName)?;
+
+    let assets = eval(&args[1], env, context)?;
+    let from = eval(&args[2], env, context)?;
+    let to = eval(&args[3], env, context)?;
+
+    let expected_asset_type = env
+        .global_context
+        .database
+        .get_nft_key_type(&env.contract_context.contract_identifier, asset_name)?;
+
+    let assets = match assets {
+        Value::Sequence(SequenceData::List(list_data)) => list_data.data,
+        _ => return Err(CheckErrors::ExpectedListApplication.into()),
+    };
+
+    if let (Value::Principal(ref from_principal), Value::Principal(ref to_principal)) = (from, to) {
+        // the batch is all-or-nothing: if any item fails, none of them are transferred
+        env.global_context.begin();
+        let result = transfer_asset_batch(
+            env,
             asset_name,
-            asset.clone(),
+            expected_asset_type,
+            assets,
+            from_principal,
+            to_principal,
         );
+        env.global_context.handle_tx_result(result)
+    } else {
+        Err(CheckErrors::BadTransferNFTArguments.into())
+    }
+}
 
-        let asset_identifier = AssetIdentifier {
-            contract_identifier: env.contract_context.contract_identifier.clone(),
-            asset_name: asset_name.clone(),
-        };
-        env.register_nft_transfer_event(
-            from_principal.clone(),
-            to_principal.clone(),
-            asset,
-            asset_identifier,
+/// Transfer each of `assets` from `from_principal` to `to_principal`, and wrap the per-item
+/// results.
+fn transfer_asset_batch(
+    env: &mut Environment,
+    asset_name: &ClarityName,
+    expected_asset_type: TypeSignature,
+    assets: Vec<Value>,
+    from_principal: &PrincipalData,
+    to_principal: &PrincipalData,
+) -> Result<Value> {
+    let mut results = Vec::with_capacity(assets.len());
+    for asset in assets.into_iter() {
+        // priced per item, the same as nft-transfer?
+        runtime_cost(
+            ClarityCostFunction::NftTransfer,
+            env,
+            expected_asset_type.size(),
         )?;
 
-        Ok(Value::okay_true())
-    } else {
-        Err(CheckErrors::BadTransferNFTArguments.into())
+        if !expected_asset_type.admits(&asset) {
+            return Err(CheckErrors::TypeValueError(expected_asset_type, asset).into());
+        }
+
+        results.push(transfer_asset(
+            env,
+            asset_name,
+            &expected_asset_type,
+            asset,
+            from_principal,
+            to_principal,
+        )?);
     }
+    nft_batch_result(results)
 }
 
 pub fn special_transfer_token(
@@ -30,8 +30,8 @@ use vm::errors::{
 };
 use vm::representations::{SymbolicExpression, SymbolicExpressionType};
 use vm::types::{
-    BlockInfoProperty, BuffData, OptionalData, PrincipalData, SequenceData, TypeSignature, Value,
-    BUFF_32,
+    BlockInfoProperty, BuffData, BurnBlockInfoProperty, OptionalData, PrincipalData, SequenceData,
+    TypeSignature, Value, BUFF_32,
 };
 use vm::{eval, Environment, LocalContext};
 
@@ -497,3 +497,61 @@ pub fn special_get_block_info(
 
     Ok(Value::some(result)?)
 }
+
+pub fn special_get_burn_block_info(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    // (get-burn-block-info? property-name burn-block-height-int)
+    runtime_cost(ClarityCostFunction::BlockInfo, env, 0)?;
+
+    check_argument_count(2, args)?;
+
+    // Handle the block property name input arg.
+    let property_name = args[0]
+        .match_atom()
+        .ok_or(CheckErrors::GetBurnBlockInfoExpectPropertyName)?;
+
+    let block_info_prop = BurnBlockInfoProperty::lookup_by_name(property_name)
+        .ok_or(CheckErrors::GetBurnBlockInfoExpectPropertyName)?;
+
+    // Handle the burn-block-height input arg clause.
+    let height_eval = eval(&args[1], env, context)?;
+    let height_value = match height_eval {
+        Value::UInt(result) => Ok(result),
+        x => Err(CheckErrors::TypeValueError(TypeSignature::UIntType, x)),
+    }?;
+
+    let height_value = match u32::try_from(height_value) {
+        Ok(result) => result,
+        _ => return Ok(Value::none()),
+    };
+
+    let result = match block_info_prop {
+        BurnBlockInfoProperty::HeaderHash => env
+            .global_context
+            .database
+            .get_burnchain_block_header_hash_for_burnchain_height(height_value)
+            .map(|burnchain_header_hash| {
+                Value::Sequence(SequenceData::Buffer(BuffData {
+                    data: burnchain_header_hash.as_bytes().to_vec(),
+                }))
+            }),
+        BurnBlockInfoProperty::Time => env
+            .global_context
+            .database
+            .get_burnchain_block_time_for_burnchain_height(height_value)
+            .map(|block_time| Value::UInt(block_time as u128)),
+        BurnBlockInfoProperty::MedianTimePast => env
+            .global_context
+            .database
+            .get_burnchain_median_time_past_for_burnchain_height(height_value)
+            .map(|median_time_past| Value::UInt(median_time_past as u128)),
+    };
+
+    match result {
+        Some(value) => Ok(Value::some(value)?),
+        None => Ok(Value::none()),
+    }
+}
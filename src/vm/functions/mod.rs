@@ -28,8 +28,10 @@ pub mod tuples;
 use util::hash;
 use vm::callables::{CallableType, NativeHandle};
 use vm::costs::{
-    constants as cost_constants, cost_functions, runtime_cost, CostTracker, MemoryConsumer,
+    constants as cost_constants, cost_functions, runtime_cost, CostTracker, MemoryChargeFormula,
+    MemoryConsumer,
 };
+use vm::database::STXBalance;
 use vm::errors::{
     check_argument_count, check_arguments_at_least, CheckErrors, Error,
     InterpreterResult as Result, RuntimeErrorType, ShortReturnType,
@@ -41,7 +43,7 @@ use vm::types::{
     BuffData, CharType, PrincipalData, ResponseData, SequenceData, TypeSignature, Value, BUFF_32,
     BUFF_33, BUFF_65,
 };
-use vm::{eval, Environment, LocalContext};
+use vm::{eval, ClarityVersion, Environment, LocalContext};
 
 use address::AddressHashMode;
 use chainstate::stacks::{StacksAddress, C32_ADDRESS_VERSION_TESTNET_SINGLESIG};
@@ -101,9 +103,11 @@ define_named_enum!(NativeFunctions {
     ContractCall("contract-call?"),
     AsContract("as-contract"),
     ContractOf("contract-of"),
+    IsReentrant("is-reentrant"),
     PrincipalOf("principal-of?"),
     AtBlock("at-block"),
     GetBlockInfo("get-block-info?"),
+    GetBurnBlockInfo("get-burn-block-info?"),
     ConsError("err"),
     ConsOkay("ok"),
     ConsSome("some"),
@@ -124,17 +128,108 @@ define_named_enum!(NativeFunctions {
     GetAssetOwner("nft-get-owner?"),
     TransferToken("ft-transfer?"),
     TransferAsset("nft-transfer?"),
+    TransferAssetMany("nft-transfer-many?"),
     MintAsset("nft-mint?"),
+    MintAssetMany("nft-mint-many?"),
     MintToken("ft-mint?"),
     GetStxBalance("stx-get-balance"),
+    GetStxAccount("stx-account"),
     StxTransfer("stx-transfer?"),
+    StxTransferMemo("stx-transfer-memo?"),
     StxBurn("stx-burn?"),
 });
 
-pub fn lookup_reserved_functions(name: &str) -> Option<CallableType> {
+impl NativeFunctions {
+    /// The first Clarity version in which this native is available
+    pub fn get_min_version(&self) -> ClarityVersion {
+        use vm::functions::NativeFunctions::*;
+        match self {
+            GetBurnBlockInfo | GetStxAccount | StxTransferMemo | MintAssetMany
+            | TransferAssetMany | IsReentrant => ClarityVersion::Clarity2,
+            _ => ClarityVersion::Clarity1,
+        }
+    }
+
+    /// Look up a native by name, if it is available in the given Clarity version
+    pub fn lookup_by_name_at_version(
+        name: &str,
+        version: ClarityVersion,
+    ) -> Option<NativeFunctions> {
+        NativeFunctions::lookup_by_name(name).filter(|native| native.get_min_version() <= version)
+    }
+}
+
+/// Report the formula for the memory that a native function charges to the cost tracker,
+/// mirroring the `add_memory()` calls in its implementation. Every native is listed
+/// explicitly, so that adding a native requires deciding what it charges here.
+pub fn native_memory_charge(function: &NativeFunctions) -> MemoryChargeFormula {
+    use vm::costs::MemoryChargeTerm::*;
+    use vm::functions::NativeFunctions::*;
+
+    let principal_size = Constant(TypeSignature::PrincipalType.size() as u64);
+    let uint_size = Constant(TypeSignature::UIntType.size() as u64);
+    let balance_size = Constant(STXBalance::size_of as u64);
+
+    match function {
+        StxTransfer | StxTransferMemo => MemoryChargeFormula::new(vec![
+            principal_size.clone(),
+            principal_size,
+            balance_size.clone(),
+            balance_size,
+        ]),
+        StxBurn => MemoryChargeFormula::new(vec![principal_size, balance_size]),
+        MintToken => MemoryChargeFormula::new(vec![principal_size, uint_size]),
+        TransferToken => MemoryChargeFormula::new(vec![
+            principal_size.clone(),
+            principal_size,
+            uint_size.clone(),
+            uint_size,
+        ]),
+        MintAsset | TransferAsset => MemoryChargeFormula::new(vec![principal_size, AssetTypeSize]),
+        MintAssetMany | TransferAssetMany => {
+            MemoryChargeFormula::new(vec![principal_size, AssetTypeSize]).per_list_item()
+        }
+        SetVar => MemoryChargeFormula::new(vec![ValueSize("value")]),
+        SetEntry | InsertEntry => {
+            MemoryChargeFormula::new(vec![ValueSize("key"), ValueSize("value")])
+        }
+        DeleteEntry => MemoryChargeFormula::new(vec![ValueSize("key")]),
+        Let => MemoryChargeFormula::new(vec![ValueSize("binding")])
+            .per_list_item()
+            .scoped(),
+        Match => MemoryChargeFormula::new(vec![ValueSize("bound-value")]).scoped(),
+        AsContract => {
+            MemoryChargeFormula::new(vec![Constant(cost_constants::AS_CONTRACT_MEMORY)]).scoped()
+        }
+        AtBlock => {
+            MemoryChargeFormula::new(vec![Constant(cost_constants::AT_BLOCK_MEMORY)]).scoped()
+        }
+        Add | Subtract | Multiply | Divide | CmpGeq | CmpLeq | CmpLess | CmpGreater | ToInt
+        | ToUInt | Modulo | Power | Sqrti | Log2 | BitwiseXOR | And | Or | Not | Equals | If
+        | Map | Fold | Append | Concat | AsMaxLen | Len | ElementAt | IndexOf | ListCons
+        | FetchVar | FetchEntry | TupleCons | TupleGet | TupleMerge | Begin | Hash160 | Sha256
+        | Sha512 | Sha512Trunc256 | Keccak256 | Secp256k1Recover | Secp256k1Verify | Print
+        | ContractCall | ContractOf | IsReentrant | PrincipalOf | GetBlockInfo
+        | GetBurnBlockInfo | ConsError | ConsOkay | ConsSome | DefaultTo | Asserts | UnwrapRet
+        | UnwrapErrRet | Unwrap | UnwrapErr | TryRet | IsOkay | IsNone | IsErr | IsSome
+        | Filter | GetTokenBalance | GetAssetOwner | GetStxBalance | GetStxAccount => {
+            MemoryChargeFormula::none()
+        }
+    }
+}
+
+/// The memory charge formula of every native function, keyed by its Clarity name.
+pub fn native_memory_charge_table() -> Vec<(&'static str, MemoryChargeFormula)> {
+    NativeFunctions::ALL
+        .iter()
+        .map(|function| (function.get_name_str(), native_memory_charge(function)))
+        .collect()
+}
+
+pub fn lookup_reserved_functions(name: &str, version: ClarityVersion) -> Option<CallableType> {
     use vm::callables::CallableType::{NativeFunction, SpecialFunction};
     use vm::functions::NativeFunctions::*;
-    if let Some(native_function) = NativeFunctions::lookup_by_name(name) {
+    if let Some(native_function) = NativeFunctions::lookup_by_name_at_version(name, version) {
         let callable = match native_function {
             Add => NativeFunction(
                 "native_add",
@@ -303,10 +398,15 @@ pub fn lookup_reserved_functions(name: &str) -> Option<CallableType> {
             }
             AsContract => SpecialFunction("special_as-contract", &special_as_contract),
             ContractOf => SpecialFunction("special_contract-of", &special_contract_of),
+            IsReentrant => SpecialFunction("special_is-reentrant", &special_is_reentrant),
             PrincipalOf => SpecialFunction("special_principal-of", &crypto::special_principal_of),
             GetBlockInfo => {
                 SpecialFunction("special_get_block_info", &database::special_get_block_info)
             }
+            GetBurnBlockInfo => SpecialFunction(
+                "special_get_burn_block_info",
+                &database::special_get_burn_block_info,
+            ),
             ConsSome => NativeFunction(
                 "native_some",
                 NativeHandle::SingleArg(&options::native_some),
@@ -375,10 +475,17 @@ pub fn lookup_reserved_functions(name: &str) -> Option<CallableType> {
                 ClarityCostFunction::TryRet,
             ),
             MintAsset => SpecialFunction("special_mint_asset", &assets::special_mint_asset),
+            MintAssetMany => {
+                SpecialFunction("special_mint_asset_many", &assets::special_mint_asset_many)
+            }
             MintToken => SpecialFunction("special_mint_token", &assets::special_mint_token),
             TransferAsset => {
                 SpecialFunction("special_transfer_asset", &assets::special_transfer_asset)
             }
+            TransferAssetMany => SpecialFunction(
+                "special_transfer_asset_many",
+                &assets::special_transfer_asset_many,
+            ),
             TransferToken => {
                 SpecialFunction("special_transfer_token", &assets::special_transfer_token)
             }
@@ -386,7 +493,12 @@ pub fn lookup_reserved_functions(name: &str) -> Option<CallableType> {
             GetAssetOwner => SpecialFunction("special_get_owner", &assets::special_get_owner),
             AtBlock => SpecialFunction("special_at_block", &database::special_at_block),
             GetStxBalance => SpecialFunction("special_stx_balance", &assets::special_stx_balance),
+            GetStxAccount => SpecialFunction("special_stx_account", &assets::special_stx_account),
             StxTransfer => SpecialFunction("special_stx_transfer", &assets::special_stx_transfer),
+            StxTransferMemo => SpecialFunction(
+                "special_stx_transfer_memo",
+                &assets::special_stx_transfer_memo,
+            ),
             StxBurn => SpecialFunction("special_stx_burn", &assets::special_stx_burn),
         };
         Some(callable)
@@ -547,7 +659,7 @@ fn special_let(
 
     finally_drop_memory!( env, memory_use; {
         handle_binding_list::<_, Error>(bindings, |binding_name, var_sexp| {
-            if is_reserved(binding_name) ||
+            if is_reserved(binding_name, env.contract_context.get_clarity_version()) ||
                 env.contract_context.lookup_function(binding_name).is_some() ||
                 inner_context.lookup_variable(binding_name).is_some() {
                     return Err(CheckErrors::NameAlreadyUsed(binding_name.clone().into()).into())
@@ -597,6 +709,22 @@ fn special_as_contract(
     result
 }
 
+fn special_is_reentrant(
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    _context: &LocalContext,
+) -> Result<Value> {
+    // (is-reentrant)
+    check_argument_count(0, args)?;
+
+    runtime_cost(ClarityCostFunction::ContractOf, env, 0)?;
+
+    let contract_identifier = &env.contract_context.contract_identifier;
+    Ok(Value::Bool(
+        env.call_stack.is_reentrant(contract_identifier),
+    ))
+}
+
 fn special_contract_of(
     args: &[SymbolicExpression],
     env: &mut Environment,
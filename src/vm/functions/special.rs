@@ -69,25 +69,90 @@ fn parse_pox_stacking_result(
     }
 }
 
-/// Handle special cases when calling into the PoX API contract
+fn parse_pox_extend_result(result: &Value) -> std::result::Result<(PrincipalData, u64), i128> {
+    match result.clone().expect_result() {
+        Ok(res) => {
+            // should have gotten back (ok (tuple (stacker principal) (unlock-burn-height uint)))
+            let tuple_data = res.expect_tuple();
+            let stacker = tuple_data
+                .get("stacker")
+                .expect(&format!("FATAL: no 'stacker'"))
+                .to_owned()
+                .expect_principal();
+
+            let unlock_burn_height = tuple_data
+                .get("unlock-burn-height")
+                .expect(&format!("FATAL: no 'unlock-burn-height'"))
+                .to_owned()
+                .expect_u128()
+                .try_into()
+                .expect("FATAL: 'unlock-burn-height' overflow");
+
+            Ok((stacker, unlock_burn_height))
+        }
+        Err(e) => Err(e.expect_i128()),
+    }
+}
+
+fn parse_pox_increase_result(result: &Value) -> std::result::Result<(PrincipalData, u128), i128> {
+    match result.clone().expect_result() {
+        Ok(res) => {
+            // should have gotten back (ok (tuple (stacker principal) (total-locked uint)))
+            let tuple_data = res.expect_tuple();
+            let stacker = tuple_data
+                .get("stacker")
+                .expect(&format!("FATAL: no 'stacker'"))
+                .to_owned()
+                .expect_principal();
+
+            let total_locked = tuple_data
+                .get("total-locked")
+                .expect(&format!("FATAL: no 'total-locked'"))
+                .to_owned()
+                .expect_u128();
+
+            Ok((stacker, total_locked))
+        }
+        Err(e) => Err(e.expect_i128()),
+    }
+}
+
+/// Report the state of an account's PoX lock after it was created or changed
+fn push_stx_lock_event(
+    global_context: &mut GlobalContext,
+    locked_address: PrincipalData,
+    locked_amount: u128,
+    unlock_height: u64,
+) {
+    if let Some(batch) = global_context.event_batches.last_mut() {
+        batch.events.push(StacksTransactionEvent::STXEvent(
+            STXEventType::STXLockEvent(STXLockEventData {
+                locked_amount,
+                unlock_height,
+                locked_address,
+            }),
+        ));
+    }
+}
+
+/// Handle special cases when calling into a PoX API contract
 fn handle_pox_api_contract_call(
     global_context: &mut GlobalContext,
     _sender_opt: Option<&PrincipalData>,
+    contract_id: &QualifiedContractIdentifier,
     function_name: &str,
     value: &Value,
 ) -> Result<()> {
     if function_name == "stack-stx" || function_name == "delegate-stack-stx" {
         debug!(
             "Handle special-case contract-call to {:?} {} (which returned {:?})",
-            boot_code_id("pox"),
-            function_name,
-            value
+            contract_id, function_name, value
         );
 
         match parse_pox_stacking_result(value) {
             Ok((stacker, locked_amount, unlock_height)) => {
-                // if this fails, then there's a bug in the contract (since it already does
-                // the necessary checks)
+                // the contract already does the necessary checks, but if the account disagrees,
+                // fail the transaction instead of the node
                 match StacksChainState::pox_lock(
                     &mut global_context.database,
                     &stacker,
@@ -95,21 +160,15 @@ fn handle_pox_api_contract_call(
                     unlock_height as u64,
                 ) {
                     Ok(_) => {
-                        if let Some(batch) = global_context.event_batches.last_mut() {
-                            batch.events.push(StacksTransactionEvent::STXEvent(
-                                STXEventType::STXLockEvent(STXLockEventData {
-                                    locked_amount,
-                                    unlock_height,
-                                    locked_address: stacker,
-                                }),
-                            ));
-                        }
+                        push_stx_lock_event(global_context, stacker, locked_amount, unlock_height);
                     }
                     Err(e) => {
-                        panic!(
-                            "FATAL: failed to lock {} from {} until {}: '{:?}'",
+                        let msg = format!(
+                            "failed to lock {} from {} until {}: '{:?}'",
                             locked_amount, stacker, unlock_height, &e
                         );
+                        warn!("{}", &msg);
+                        return Err(RuntimeErrorType::PoxLockFailure(msg).into());
                     }
                 }
 
@@ -120,6 +179,62 @@ fn handle_pox_api_contract_call(
                 return Ok(());
             }
         }
+    } else if function_name == "stack-extend" {
+        debug!(
+            "Handle special-case contract-call to {:?} {} (which returned {:?})",
+            contract_id, function_name, value
+        );
+
+        if let Ok((stacker, unlock_height)) = parse_pox_extend_result(value) {
+            // the contract already does the necessary checks, but if the account disagrees,
+            // fail the transaction instead of the node
+            match StacksChainState::pox_lock_extend(
+                &mut global_context.database,
+                &stacker,
+                unlock_height,
+            ) {
+                Ok((locked_amount, unlock_height)) => {
+                    push_stx_lock_event(global_context, stacker, locked_amount, unlock_height);
+                }
+                Err(e) => {
+                    let msg = format!(
+                        "failed to extend lock from {} until {}: '{:?}'",
+                        stacker, unlock_height, &e
+                    );
+                    warn!("{}", &msg);
+                    return Err(RuntimeErrorType::PoxLockFailure(msg).into());
+                }
+            }
+        }
+        return Ok(());
+    } else if function_name == "stack-increase" {
+        debug!(
+            "Handle special-case contract-call to {:?} {} (which returned {:?})",
+            contract_id, function_name, value
+        );
+
+        if let Ok((stacker, total_locked)) = parse_pox_increase_result(value) {
+            // the contract already does the necessary checks, but if the account disagrees,
+            // fail the transaction instead of the node
+            match StacksChainState::pox_lock_increase(
+                &mut global_context.database,
+                &stacker,
+                total_locked,
+            ) {
+                Ok((locked_amount, unlock_height)) => {
+                    push_stx_lock_event(global_context, stacker, locked_amount, unlock_height);
+                }
+                Err(e) => {
+                    let msg = format!(
+                        "failed to increase lock from {} to {}: '{:?}'",
+                        stacker, total_locked, &e
+                    );
+                    warn!("{}", &msg);
+                    return Err(RuntimeErrorType::PoxLockFailure(msg).into());
+                }
+            }
+        }
+        return Ok(());
     }
     // nothing to do
     Ok(())
@@ -133,8 +248,14 @@ pub fn handle_contract_call_special_cases(
     function_name: &str,
     result: &Value,
 ) -> Result<()> {
-    if *contract_id == boot_code_id("pox") {
-        return handle_pox_api_contract_call(global_context, sender, function_name, result);
+    if *contract_id == boot_code_id("pox") || *contract_id == boot_code_id("pox-2") {
+        return handle_pox_api_contract_call(
+            global_context,
+            sender,
+            contract_id,
+            function_name,
+            result,
+        );
     }
     // TODO: insert more special cases here, as needed
     Ok(())
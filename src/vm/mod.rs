@@ -35,6 +35,7 @@ pub mod representations;
 mod callables;
 mod functions;
 mod variables;
+pub mod version;
 
 pub mod analysis;
 pub mod docs;
@@ -60,11 +61,15 @@ use vm::types::{PrincipalData, QualifiedContractIdentifier, TraitIdentifier, Typ
 pub use vm::representations::{
     ClarityName, ContractName, SymbolicExpression, SymbolicExpressionType,
 };
+pub use vm::version::ClarityVersion;
 
 use std::convert::{TryFrom, TryInto};
 pub use vm::contexts::MAX_CONTEXT_DEPTH;
 use vm::costs::cost_functions::ClarityCostFunction;
-pub use vm::functions::stx_transfer_consolidated;
+use vm::costs::profiler::ProfileFrame;
+pub use vm::functions::{
+    native_memory_charge, native_memory_charge_table, stx_transfer_consolidated,
+};
 
 const MAX_CALL_STACK_DEPTH: usize = 64;
 
@@ -105,7 +110,9 @@ fn lookup_variable(name: &str, context: &LocalContext, env: &mut Environment) ->
 pub fn lookup_function(name: &str, env: &mut Environment) -> Result<CallableType> {
     runtime_cost(ClarityCostFunction::LookupFunction, env, 0)?;
 
-    if let Some(result) = functions::lookup_reserved_functions(name) {
+    if let Some(result) =
+        functions::lookup_reserved_functions(name, env.contract_context.get_clarity_version())
+    {
         Ok(result)
     } else {
         let user_function = env
@@ -217,15 +224,26 @@ pub fn eval<'a>(
             let function_name = function_variable
                 .match_atom()
                 .ok_or(CheckErrors::BadFunctionName)?;
-            let f = lookup_function(&function_name, env)?;
-            apply(&f, &rest, env, context)
+
+            let profiling = env.global_context.cost_track.is_profiling();
+            if profiling {
+                let frame =
+                    ProfileFrame::expression(&env.contract_context.contract_identifier, exp);
+                env.global_context.cost_track.profile_enter(frame);
+            }
+            let result =
+                lookup_function(&function_name, env).and_then(|f| apply(&f, &rest, env, context));
+            if profiling {
+                env.global_context.cost_track.profile_exit();
+            }
+            result
         }
         TraitReference(_, _) | Field(_) => unreachable!("can't be evaluated"),
     }
 }
 
-pub fn is_reserved(name: &str) -> bool {
-    if let Some(_result) = functions::lookup_reserved_functions(name) {
+pub fn is_reserved(name: &str, version: ClarityVersion) -> bool {
+    if let Some(_result) = functions::lookup_reserved_functions(name, version) {
         true
     } else if variables::is_reserved_name(name) {
         true
@@ -349,12 +367,13 @@ fn eval_all(
  */
 pub fn execute(program: &str) -> Result<Option<Value>> {
     let contract_id = QualifiedContractIdentifier::transient();
-    let mut contract_context = ContractContext::new(contract_id.clone());
+    let mut contract_context = ContractContext::new(contract_id.clone(), ClarityVersion::latest());
     let mut marf = MemoryBackingStore::new();
     let conn = marf.as_clarity_db();
     let mut global_context = GlobalContext::new(conn, LimitedCostTracker::new_free());
     global_context.execute(|g| {
-        let parsed = ast::build_ast(&contract_id, program, &mut ())?.expressions;
+        let parsed =
+            ast::build_ast(&contract_id, program, &mut (), ClarityVersion::latest())?.expressions;
         eval_all(&parsed, &mut contract_context, g)
     })
 }
@@ -369,6 +388,7 @@ mod test {
     use vm::eval;
     use vm::execute;
     use vm::types::{QualifiedContractIdentifier, TypeSignature};
+    use vm::ClarityVersion;
     use vm::{
         CallStack, ContractContext, Environment, GlobalContext, LocalContext, SymbolicExpression,
         Value,
@@ -403,7 +423,10 @@ mod test {
         );
 
         let context = LocalContext::new();
-        let mut contract_context = ContractContext::new(QualifiedContractIdentifier::transient());
+        let mut contract_context = ContractContext::new(
+            QualifiedContractIdentifier::transient(),
+            ClarityVersion::latest(),
+        );
 
         let mut marf = MemoryBackingStore::new();
         let mut global_context =
@@ -18,6 +18,7 @@ use chainstate::stacks::events::StacksTransactionEvent;
 use util::hash::hex_bytes;
 use vm::contexts::{AssetMap, AssetMapEntry, GlobalContext, OwnedEnvironment};
 use vm::contracts::Contract;
+use vm::database::STXBalance;
 use vm::errors::{CheckErrors, Error, RuntimeErrorType};
 use vm::execute as vm_execute;
 use vm::representations::SymbolicExpression;
@@ -25,7 +26,9 @@ use vm::tests::{
     execute, is_committed, is_err_code, symbols_from_values, with_marfed_environment,
     with_memory_environment,
 };
-use vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier, ResponseData, Value};
+use vm::types::{
+    AssetIdentifier, PrincipalData, QualifiedContractIdentifier, ResponseData, TupleData, Value,
+};
 
 const FIRST_CLASS_TOKENS: &str = "(define-fungible-token stackaroos)
          (define-read-only (my-ft-get-balance (account principal))
@@ -116,6 +119,20 @@ const ASSET_NAMES: &str =
                     (err u3))
                   (err u4))))";
 
+const BATCH_TICKETS: &str = "(define-non-fungible-token tickets int)
+         (define-public (mint-many (ids (list 10 int)))
+           (nft-mint-many? tickets ids tx-sender))
+         (define-public (mint-what-you-can (ids (list 10 int)))
+           (match (nft-mint-many? tickets ids tx-sender)
+             results (ok results)
+             results (ok results)))
+         (define-public (transfer-many (ids (list 10 int)) (recipient principal))
+           (nft-transfer-many? tickets ids tx-sender recipient))
+         (define-public (transfer-what-you-can (ids (list 10 int)) (recipient principal))
+           (match (nft-transfer-many? tickets ids tx-sender recipient)
+             results (ok results)
+             results (ok results)))";
+
 fn execute_transaction(
     env: &mut OwnedEnvironment,
     issuer: Value,
@@ -130,6 +147,7 @@ fn test_native_stx_ops(owned_env: &mut OwnedEnvironment) {
     let contract = "(define-public (burn-stx (amount uint) (p principal)) (stx-burn? amount p))
                     (define-public (xfer-stx (amount uint) (p principal) (t principal)) (stx-transfer? amount p t))
                     (define-read-only (balance-stx (p principal)) (stx-get-balance p))
+                    (define-read-only (account-stx (p principal)) (stx-account p))
                     (define-public (to-contract (amount uint) (p principal))
                       (let ((contract-principal (as-contract tx-sender)))
                         (stx-transfer? amount p contract-principal)))
@@ -414,6 +432,29 @@ fn test_native_stx_ops(owned_env: &mut OwnedEnvironment) {
 
     assert_eq!(result, Value::UInt(10));
 
+    // and with stx-account
+
+    let (result, _asset_map, _events) = execute_transaction(
+        owned_env,
+        p2.clone(),
+        &token_contract_id,
+        "account-stx",
+        &symbols_from_values(vec![contract_principal.clone()]),
+    )
+    .unwrap();
+
+    assert_eq!(
+        result,
+        Value::from(
+            TupleData::from_data(vec![
+                ("unlocked".into(), Value::UInt(10)),
+                ("locked".into(), Value::UInt(0)),
+                ("unlock-height".into(), Value::UInt(0)),
+            ])
+            .unwrap()
+        )
+    );
+
     // now let's do a contract -> user transfer
 
     let (result, asset_map, _events) = execute_transaction(
@@ -519,6 +560,97 @@ fn test_native_stx_ops(owned_env: &mut OwnedEnvironment) {
     );
 }
 
+fn test_stx_account_locks(owned_env: &mut OwnedEnvironment) {
+    let contract = "(define-read-only (account-stx (p principal)) (stx-account p))";
+
+    let p1 = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+    let p2 = execute("'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G");
+    let p3 = execute("'SP3X6QWWETNBZWGBK6DRGTR1KX50S74D3433WDGJY");
+
+    let p1_principal = match p1 {
+        Value::Principal(PrincipalData::Standard(ref data)) => data.clone(),
+        _ => panic!(),
+    };
+
+    let p2_principal = match p2 {
+        Value::Principal(ref data) => data.clone(),
+        _ => panic!(),
+    };
+
+    let p3_principal = match p3 {
+        Value::Principal(ref data) => data.clone(),
+        _ => panic!(),
+    };
+
+    let contract_id = QualifiedContractIdentifier::new(p1_principal.clone(), "accounts".into());
+    owned_env
+        .initialize_contract(contract_id.clone(), contract)
+        .unwrap();
+
+    // p2 has a lock that is still live, and p3 has a lock that has expired but that has not
+    //  been swept yet
+    let (burn_height, _, _) = owned_env
+        .execute_in_env::<_, _, Error>(p1.clone(), |env| {
+            let burn_height = env
+                .global_context
+                .database
+                .get_current_burnchain_block_height() as u64;
+            for (principal, unlock_height) in [
+                (&p2_principal, burn_height + 10),
+                (&p3_principal, burn_height),
+            ]
+            .iter()
+            {
+                let mut snapshot = env
+                    .global_context
+                    .database
+                    .get_stx_balance_snapshot(principal);
+                snapshot.set_balance(STXBalance {
+                    amount_unlocked: 100,
+                    amount_locked: 1000,
+                    unlock_height: *unlock_height,
+                    locks: vec![],
+                });
+                snapshot.save();
+            }
+            Ok(burn_height)
+        })
+        .unwrap();
+
+    let account_tuple = |unlocked: u128, locked: u128, unlock_height: u64| {
+        Value::from(
+            TupleData::from_data(vec![
+                ("unlocked".into(), Value::UInt(unlocked)),
+                ("locked".into(), Value::UInt(locked)),
+                ("unlock-height".into(), Value::UInt(unlock_height as u128)),
+            ])
+            .unwrap(),
+        )
+    };
+
+    // a live lock is reported as locked, along with its unlock height
+    let (result, _asset_map, _events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &contract_id,
+        "account-stx",
+        &symbols_from_values(vec![p2.clone()]),
+    )
+    .unwrap();
+    assert_eq!(result, account_tuple(100, 1000, burn_height + 10));
+
+    // an expired lock is reported as unlocked, even though it hasn't been swept
+    let (result, _asset_map, _events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &contract_id,
+        "account-stx",
+        &symbols_from_values(vec![p3.clone()]),
+    )
+    .unwrap();
+    assert_eq!(result, account_tuple(1100, 0, 0));
+}
+
 fn test_simple_token_system(owned_env: &mut OwnedEnvironment) {
     let tokens_contract = FIRST_CLASS_TOKENS;
 
@@ -822,6 +954,163 @@ fn test_overlapping_nfts(owned_env: &mut OwnedEnvironment) {
         .unwrap();
 }
 
+fn test_nft_batch_ops(owned_env: &mut OwnedEnvironment) {
+    let p1 = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+    let p2 = execute("'SM2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQVX8X0G");
+
+    let p1_principal = match p1 {
+        Value::Principal(PrincipalData::Standard(ref data)) => data.clone(),
+        _ => panic!(),
+    };
+
+    let tickets_contract_id =
+        QualifiedContractIdentifier::new(p1_principal.clone(), "tickets".into());
+    let tickets_identifier = AssetIdentifier {
+        contract_identifier: tickets_contract_id.clone(),
+        asset_name: "tickets".into(),
+    };
+
+    owned_env
+        .initialize_contract(tickets_contract_id.clone(), BATCH_TICKETS)
+        .unwrap();
+
+    let (result, asset_map, events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &tickets_contract_id,
+        "mint-many",
+        &symbols_from_values(vec![execute("(list 1 2 3)")]),
+    )
+    .unwrap();
+
+    assert_eq!(result, execute("(ok (list (ok true) (ok true) (ok true)))"));
+    assert_eq!(events.len(), 3);
+    assert_eq!(asset_map.to_table().len(), 0);
+
+    // one already exists, so the whole call errors out and rolls back
+    let (result, _asset_map, events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &tickets_contract_id,
+        "mint-many",
+        &symbols_from_values(vec![execute("(list 3 4)")]),
+    )
+    .unwrap();
+
+    assert_eq!(result, execute("(err (list (err u1) (ok true)))"));
+    assert_eq!(events.len(), 0);
+
+    // transfers report each item's error code
+    let (result, _asset_map, _events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &tickets_contract_id,
+        "transfer-many",
+        &symbols_from_values(vec![execute("(list 1 4)"), p1.clone()]),
+    )
+    .unwrap();
+
+    assert_eq!(result, execute("(err (list (err u2) (err u2)))"));
+
+    // a failed batch is rolled back, even if the caller doesn't abort
+    let (result, asset_map, events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &tickets_contract_id,
+        "mint-what-you-can",
+        &symbols_from_values(vec![execute("(list 5 3)")]),
+    )
+    .unwrap();
+
+    assert_eq!(result, execute("(ok (list (ok true) (err u1)))"));
+    assert_eq!(events.len(), 0);
+    assert_eq!(asset_map.to_table().len(), 0);
+
+    let (result, asset_map, events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &tickets_contract_id,
+        "transfer-what-you-can",
+        &symbols_from_values(vec![execute("(list 1 4 2)"), p2.clone()]),
+    )
+    .unwrap();
+
+    assert_eq!(result, execute("(ok (list (ok true) (err u3) (ok true)))"));
+    assert_eq!(events.len(), 0);
+    assert_eq!(asset_map.to_table().len(), 0);
+
+    {
+        let mut env = owned_env.get_exec_environment(None);
+        for (ticket, owner) in [(1, &p1), (2, &p1), (3, &p1)].iter() {
+            assert_eq!(
+                env.eval_read_only(
+                    &tickets_contract_id,
+                    &format!("(nft-get-owner? tickets {})", ticket)
+                )
+                .unwrap(),
+                Value::some((*owner).clone()).unwrap()
+            );
+        }
+        assert_eq!(
+            env.eval_read_only(&tickets_contract_id, "(nft-get-owner? tickets 5)")
+                .unwrap(),
+            Value::none()
+        );
+    }
+
+    let (result, asset_map, events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &tickets_contract_id,
+        "transfer-many",
+        &symbols_from_values(vec![execute("(list 1 2)"), p2.clone()]),
+    )
+    .unwrap();
+
+    assert_eq!(result, execute("(ok (list (ok true) (ok true)))"));
+    assert_eq!(events.len(), 2);
+    let table = asset_map.to_table();
+    assert_eq!(
+        table[&p1.clone().expect_principal()][&tickets_identifier],
+        AssetMapEntry::Asset(vec![Value::Int(1), Value::Int(2)])
+    );
+
+    let (result, _asset_map, _events) = execute_transaction(
+        owned_env,
+        p1.clone(),
+        &tickets_contract_id,
+        "transfer-many",
+        &symbols_from_values(vec![execute("(list 1 3)"), p2.clone()]),
+    )
+    .unwrap();
+
+    assert_eq!(result, execute("(err (list (err u1) (ok true)))"));
+
+    {
+        let mut env = owned_env.get_exec_environment(None);
+        for (ticket, owner) in [(1, &p2), (2, &p2), (3, &p1)].iter() {
+            assert_eq!(
+                env.eval_read_only(
+                    &tickets_contract_id,
+                    &format!("(nft-get-owner? tickets {})", ticket)
+                )
+                .unwrap(),
+                Value::some((*owner).clone()).unwrap()
+            );
+        }
+        for ticket in [4, 5].iter() {
+            assert_eq!(
+                env.eval_read_only(
+                    &tickets_contract_id,
+                    &format!("(nft-get-owner? tickets {})", ticket)
+                )
+                .unwrap(),
+                Value::none()
+            );
+        }
+    }
+}
+
 fn test_simple_naming_system(owned_env: &mut OwnedEnvironment) {
     let tokens_contract = FIRST_CLASS_TOKENS;
 
@@ -1114,6 +1403,8 @@ fn test_all() {
         test_simple_naming_system,
         total_supply,
         test_native_stx_ops,
+        test_stx_account_locks,
+        test_nft_batch_ops,
     ];
     for test in to_test.iter() {
         with_memory_environment(test, true);
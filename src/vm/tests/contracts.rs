@@ -37,6 +37,7 @@ use vm::types::{
 };
 
 use vm::tests::{execute, symbols_from_values, with_marfed_environment, with_memory_environment};
+use vm::ClarityVersion;
 
 const FACTORIAL_CONTRACT: &str = "(define-map factorials { id: int } { current: int, index: int })
          (define-private (init-factorial (id int) (factorial int))
@@ -201,7 +202,13 @@ fn test_simple_token_system() {
 
         let tokens_contract = SIMPLE_TOKENS;
 
-        let contract_ast = ast::build_ast(&contract_identifier, tokens_contract, &mut ()).unwrap();
+        let contract_ast = ast::build_ast(
+            &contract_identifier,
+            tokens_contract,
+            &mut (),
+            ClarityVersion::latest(),
+        )
+        .unwrap();
 
         block.as_transaction(|tx| {
             tx.initialize_smart_contract(
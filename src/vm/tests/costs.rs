@@ -26,7 +26,9 @@ use vm::tests::{
     execute, is_committed, is_err_code, symbols_from_values, with_marfed_environment,
     with_memory_environment,
 };
+use vm::types::TypeSignature;
 use vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier, ResponseData, Value};
+use vm::{native_memory_charge, native_memory_charge_table};
 
 use chainstate::burn::BlockHeaderHash;
 use chainstate::stacks::events::StacksTransactionEvent;
@@ -34,7 +36,9 @@ use chainstate::stacks::index::storage::TrieFileStorage;
 use chainstate::stacks::index::MarfTrieId;
 use chainstate::stacks::StacksBlockId;
 use vm::contexts::Environment;
-use vm::costs::{ClarityCostFunctionReference, ExecutionCost, LimitedCostTracker};
+use vm::costs::{
+    ClarityCostFunctionReference, ExecutionCost, LimitedCostTracker, MemoryChargeTerm,
+};
 use vm::database::{
     ClarityDatabase, MarfedKV, MemoryBackingStore, NULL_BURN_STATE_DB, NULL_HEADER_DB,
 };
@@ -97,9 +101,11 @@ pub fn get_simple_test(function: &NativeFunctions) -> &'static str {
         Print => "(print 1)",
         ContractCall => "(contract-call? .contract-other foo-exec 1)",
         ContractOf => "(contract-of contract)",
+        IsReentrant => "(is-reentrant)",
         PrincipalOf => "(principal-of? 0x03adb8de4bfb65db2cfd6120d55c6526ae9c52e675db7e47308636534ba7786110)",
         AsContract => "(as-contract 1)",
         GetBlockInfo => "(get-block-info? time u1)",
+        GetBurnBlockInfo => "(get-burn-block-info? time u1)",
         ConsOkay => "(ok 1)",
         ConsError => "(err 1)",
         ConsSome => "(some 1)",
@@ -117,13 +123,17 @@ pub fn get_simple_test(function: &NativeFunctions) -> &'static str {
         IsSome => "(is-some (some 1))",
         MintAsset => "(ft-mint? ft-foo u1 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
         MintToken => "(nft-mint? nft-foo 1 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
+        MintAssetMany => "(nft-mint-many? nft-foo (list 1 2) 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
         GetTokenBalance => "(ft-get-balance ft-foo 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
         GetAssetOwner => "(nft-get-owner? nft-foo 1)",
         TransferToken => "(ft-transfer? ft-foo u1 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
         TransferAsset => "(nft-transfer? nft-foo 1 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
+        TransferAssetMany => "(nft-transfer-many? nft-foo (list 1 2) 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
         AtBlock => "(at-block 0x55c9861be5cff984a20ce6d99d4aa65941412889bdc665094136429b84f8c2ee 1)",   // first stacksblockid
         GetStxBalance => "(stx-get-balance 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
+        GetStxAccount => "(stx-account 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
         StxTransfer => "(stx-transfer? u1 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
+        StxTransferMemo => "(stx-transfer-memo? u1 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR 0x010203)",
         StxBurn => "(stx-burn? u1 'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR)",
     }
 }
@@ -238,6 +248,45 @@ fn test_all() {
     }
 }
 
+#[test]
+fn test_native_memory_charges() {
+    let table = native_memory_charge_table();
+    assert_eq!(table.len(), NativeFunctions::ALL.len());
+
+    let principal_size = TypeSignature::PrincipalType.size() as u64;
+    let uint_size = TypeSignature::UIntType.size() as u64;
+
+    // a balance snapshot is charged at the size of a serialized STX balance
+    let stx_transfer = native_memory_charge(&NativeFunctions::StxTransfer);
+    assert_eq!(stx_transfer.fixed_bytes(), 2 * principal_size + 2 * 40);
+    assert_eq!(
+        native_memory_charge(&NativeFunctions::StxTransferMemo),
+        stx_transfer
+    );
+    assert_eq!(
+        native_memory_charge(&NativeFunctions::StxBurn).fixed_bytes(),
+        principal_size + 40
+    );
+    assert_eq!(
+        native_memory_charge(&NativeFunctions::TransferToken).fixed_bytes(),
+        2 * principal_size + 2 * uint_size
+    );
+
+    let nft_mint_many = native_memory_charge(&NativeFunctions::MintAssetMany);
+    assert!(nft_mint_many.per_list_item);
+    assert!(nft_mint_many
+        .terms
+        .contains(&MemoryChargeTerm::AssetTypeSize));
+
+    let map_set = native_memory_charge(&NativeFunctions::SetEntry);
+    assert_eq!(map_set.fixed_bytes(), 0);
+    assert_eq!(map_set.to_string(), "size(key) + size(value)");
+
+    assert!(native_memory_charge(&NativeFunctions::AtBlock).scoped);
+    assert!(native_memory_charge(&NativeFunctions::Add).is_none());
+    assert_eq!(native_memory_charge(&NativeFunctions::Add).to_string(), "0");
+}
+
 #[test]
 fn test_cost_contract_short_circuits() {
     let marf_kv = MarfedKV::temporary();
@@ -429,3 +478,112 @@ fn test_cost_contract_short_circuits() {
     assert_eq!(with_interposing_5, with_interposing_10);
     assert!(without_interposing_5 != without_interposing_10);
 }
+
+#[test]
+fn test_cost_profiler() {
+    let contract = "(define-map balances principal uint)
+        (define-private (get-balance (who principal))
+          (default-to u0 (map-get? balances who)))
+        (define-public (credit (who principal) (amount uint))
+          (ok (map-set balances who (+ (get-balance who) amount))))
+        (define-public (credit-twice (who principal))
+          (begin (try! (credit who u1)) (credit who u2)))";
+
+    let p1 = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+    let p1_principal = match p1 {
+        Value::Principal(PrincipalData::Standard(ref data)) => data.clone(),
+        _ => panic!(),
+    };
+    let contract_id = QualifiedContractIdentifier::new(p1_principal, "ledger".into());
+
+    let marf_kv = MarfedKV::temporary();
+    let mut clarity_instance = ClarityInstance::new(marf_kv, ExecutionCost::max_value());
+    clarity_instance
+        .begin_test_genesis_block(
+            &StacksBlockId::sentinel(),
+            &StacksBlockHeader::make_index_block_hash(
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+            ),
+            &NULL_HEADER_DB,
+            &NULL_BURN_STATE_DB,
+        )
+        .commit_block();
+
+    let mut marf_kv = clarity_instance.destroy();
+    marf_kv.begin(
+        &StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        ),
+        &StacksBlockId([1 as u8; 32]),
+    );
+
+    let mut db = marf_kv.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB);
+    let mut cost_tracker = LimitedCostTracker::new_max_limit(&mut db).unwrap();
+    cost_tracker.enable_profiling();
+    let mut owned_env = OwnedEnvironment::new_cost_limited(db, cost_tracker);
+
+    owned_env
+        .initialize_contract(contract_id.clone(), contract)
+        .unwrap();
+    execute_transaction(
+        &mut owned_env,
+        p1.clone(),
+        &contract_id,
+        "credit-twice",
+        &symbols_from_values(vec![p1.clone()]),
+    )
+    .unwrap();
+
+    let (_db, mut tracker) = owned_env.destruct().unwrap();
+    let profile = tracker.take_profile().unwrap();
+    assert!(!tracker.is_profiling());
+    assert_eq!(profile.total, tracker.get_total());
+
+    let function = |name: &str| {
+        profile
+            .functions
+            .iter()
+            .find(|entry| entry.name == name)
+            .unwrap()
+            .clone()
+    };
+    let credit_twice = function("credit-twice");
+    let credit = function("credit");
+    let get_balance = function("get-balance");
+    assert_eq!(credit_twice.calls, 1);
+    assert_eq!(credit.calls, 2);
+    assert_eq!(get_balance.calls, 2);
+    assert_eq!(credit.contract_identifier, contract_id.to_string());
+    // costs include those of the functions called
+    assert!(credit_twice.cost.runtime > credit.cost.runtime);
+    assert!(credit.cost.runtime > get_balance.cost.runtime);
+    assert_eq!(profile.functions[0], credit_twice);
+
+    let map_set = profile
+        .expressions
+        .iter()
+        .find(|entry| entry.name == "map-set")
+        .unwrap();
+    assert_eq!(map_set.calls, 2);
+    assert_eq!(map_set.cost.write_count, 2);
+    assert_eq!(map_set.line, Some(5));
+
+    // every cost is charged to exactly one stack
+    let mut stacks_total = ExecutionCost::zero();
+    for (_, cost) in profile.stacks.iter() {
+        stacks_total.add(cost).unwrap();
+    }
+    assert_eq!(stacks_total, profile.total);
+
+    let folded = profile.to_folded_stacks();
+    let credit_stack = format!(
+        "{}::credit-twice;(begin)@7:11;(try!)@7:18;(credit)@7:24;{}::credit;",
+        &contract_id, &contract_id
+    );
+    let get_balance_stack = "::get-balance;(default-to)@3:11;(map-get?)@3:26 ";
+    assert!(folded
+        .lines()
+        .any(|line| line.contains(&credit_stack) && line.contains(get_balance_stack)));
+}
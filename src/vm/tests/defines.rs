@@ -19,6 +19,7 @@ use vm::ast::errors::ParseErrors;
 use vm::errors::{CheckErrors, Error, RuntimeErrorType};
 use vm::execute;
 use vm::types::{QualifiedContractIdentifier, TypeSignature, Value};
+use vm::ClarityVersion;
 
 fn assert_eq_err(e1: CheckErrors, e2: Error) {
     let e1: Error = e1.into();
@@ -188,7 +189,13 @@ fn test_recursive_panic() {
               (* a (factorial (- a 1)))))
          (factorial 10)";
 
-    let err = build_ast(&QualifiedContractIdentifier::transient(), tests, &mut ()).unwrap_err();
+    let err = build_ast(
+        &QualifiedContractIdentifier::transient(),
+        tests,
+        &mut (),
+        ClarityVersion::latest(),
+    )
+    .unwrap_err();
     match err.err {
         ParseErrors::CircularReference(_) => {}
         _ => panic!("{:?}", err),
@@ -27,7 +27,9 @@ use vm::types::signatures::BufferLength;
 use vm::types::{BuffData, QualifiedContractIdentifier, TypeSignature};
 use vm::types::{PrincipalData, ResponseData, SequenceData, SequenceSubtype};
 use vm::{eval, execute as vm_execute};
-use vm::{CallStack, ContractContext, Environment, GlobalContext, LocalContext, Value};
+use vm::{
+    CallStack, ClarityVersion, ContractContext, Environment, GlobalContext, LocalContext, Value,
+};
 
 use address::c32;
 use address::AddressHashMode;
@@ -377,7 +379,10 @@ fn test_simple_if_functions() {
         );
 
         let context = LocalContext::new();
-        let mut contract_context = ContractContext::new(QualifiedContractIdentifier::transient());
+        let mut contract_context = ContractContext::new(
+            QualifiedContractIdentifier::transient(),
+            ClarityVersion::latest(),
+        );
         let mut marf = MemoryBackingStore::new();
         let mut global_context =
             GlobalContext::new(marf.as_clarity_db(), LimitedCostTracker::new_free());
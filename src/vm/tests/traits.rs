@@ -36,6 +36,7 @@ fn test_all() {
         test_dynamic_dispatch_mismatched_args,
         test_dynamic_dispatch_mismatched_returned,
         test_reentrant_dynamic_dispatch,
+        test_is_reentrant_dynamic_dispatch,
         test_readwrite_dynamic_dispatch,
         test_readwrite_violation_dynamic_dispatch,
         test_bad_call_with_trait,
@@ -573,6 +574,101 @@ fn test_reentrant_dynamic_dispatch(owned_env: &mut OwnedEnvironment) {
     }
 }
 
+fn test_is_reentrant_dynamic_dispatch(owned_env: &mut OwnedEnvironment) {
+    let dispatching_contract = "(define-trait trait-1 (
+            (get-1 (uint) (response bool uint))))
+        (define-public (wrapped-get-1 (contract <trait-1>))
+            (contract-call? contract get-1 u0))
+        (define-public (callback)
+            (ok (is-reentrant)))
+        (define-public (guarded-callback)
+            (begin
+                (asserts! (not (is-reentrant)) (err u2))
+                (ok false)))";
+    let target_contract =
+        "(define-public (get-1 (x uint)) (contract-call? .dispatching-contract callback))";
+    let guarded_target_contract =
+        "(define-public (get-1 (x uint)) (contract-call? .dispatching-contract guarded-callback))";
+
+    let p1 = execute("'SZ2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKQ9H6DPR");
+
+    {
+        let mut env = owned_env.get_exec_environment(None);
+        env.initialize_contract(
+            QualifiedContractIdentifier::local("dispatching-contract").unwrap(),
+            dispatching_contract,
+        )
+        .unwrap();
+        env.initialize_contract(
+            QualifiedContractIdentifier::local("target-contract").unwrap(),
+            target_contract,
+        )
+        .unwrap();
+        env.initialize_contract(
+            QualifiedContractIdentifier::local("guarded-target-contract").unwrap(),
+            guarded_target_contract,
+        )
+        .unwrap();
+    }
+
+    {
+        let mut env = owned_env.get_exec_environment(Some(p1.clone()));
+
+        // not re-entrant when called directly
+        assert_eq!(
+            env.execute_contract(
+                &QualifiedContractIdentifier::local("dispatching-contract").unwrap(),
+                "callback",
+                &[],
+                false
+            )
+            .unwrap(),
+            Value::okay(Value::Bool(false)).unwrap()
+        );
+
+        // re-entrant when called back through the trait
+        let target_contract = Value::from(PrincipalData::Contract(
+            QualifiedContractIdentifier::local("target-contract").unwrap(),
+        ));
+        assert_eq!(
+            env.execute_contract(
+                &QualifiedContractIdentifier::local("dispatching-contract").unwrap(),
+                "wrapped-get-1",
+                &symbols_from_values(vec![target_contract]),
+                false
+            )
+            .unwrap(),
+            Value::okay(Value::Bool(true)).unwrap()
+        );
+
+        let guarded_target_contract = Value::from(PrincipalData::Contract(
+            QualifiedContractIdentifier::local("guarded-target-contract").unwrap(),
+        ));
+        assert_eq!(
+            env.execute_contract(
+                &QualifiedContractIdentifier::local("dispatching-contract").unwrap(),
+                "wrapped-get-1",
+                &symbols_from_values(vec![guarded_target_contract]),
+                false
+            )
+            .unwrap(),
+            Value::error(Value::UInt(2)).unwrap()
+        );
+
+        // the contract is no longer entered once the calls return
+        assert_eq!(
+            env.execute_contract(
+                &QualifiedContractIdentifier::local("dispatching-contract").unwrap(),
+                "callback",
+                &[],
+                false
+            )
+            .unwrap(),
+            Value::okay(Value::Bool(false)).unwrap()
+        );
+    }
+}
+
 fn test_readwrite_dynamic_dispatch(owned_env: &mut OwnedEnvironment) {
     let dispatching_contract = "(define-trait trait-1 (
             (get-1 (uint) (response uint uint))))
@@ -37,7 +37,7 @@ pub use vm::types::signatures::{
     parse_name_type_pairs, AssetIdentifier, BufferLength, FixedFunction, FunctionArg,
     FunctionSignature, FunctionType, ListTypeData, SequenceSubtype, StringSubtype,
     StringUTF8Length, TupleTypeSignature, TypeSignature, BUFF_1, BUFF_20, BUFF_32, BUFF_33,
-    BUFF_64, BUFF_65,
+    BUFF_34, BUFF_64, BUFF_65,
 };
 
 pub const MAX_VALUE_SIZE: u32 = 1024 * 1024; // 1MB
@@ -577,6 +577,12 @@ define_named_enum!(BlockInfoProperty {
     MinerAddress("miner-address"),
 });
 
+define_named_enum!(BurnBlockInfoProperty {
+    HeaderHash("header-hash"),
+    Time("time"),
+    MedianTimePast("median-time-past"),
+});
+
 impl OptionalData {
     pub fn type_signature(&self) -> TypeSignature {
         let type_result = match self.data {
@@ -614,6 +620,16 @@ impl BlockInfoProperty {
     }
 }
 
+impl BurnBlockInfoProperty {
+    pub fn type_result(&self) -> TypeSignature {
+        use self::BurnBlockInfoProperty::*;
+        match self {
+            HeaderHash => BUFF_32.clone(),
+            Time | MedianTimePast => TypeSignature::UIntType,
+        }
+    }
+}
+
 impl PartialEq for ListData {
     fn eq(&self, other: &ListData) -> bool {
         self.data == other.data
@@ -961,6 +977,10 @@ impl Value {
 }
 
 impl BuffData {
+    pub fn empty() -> Self {
+        Self { data: Vec::new() }
+    }
+
     pub fn len(&self) -> BufferLength {
         self.data.len().try_into().unwrap()
     }
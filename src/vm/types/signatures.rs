@@ -129,6 +129,7 @@ pub const BUFF_64: TypeSignature = SequenceType(SequenceSubtype::BufferType(Buff
 pub const BUFF_65: TypeSignature = SequenceType(SequenceSubtype::BufferType(BufferLength(65)));
 pub const BUFF_32: TypeSignature = SequenceType(SequenceSubtype::BufferType(BufferLength(32)));
 pub const BUFF_33: TypeSignature = SequenceType(SequenceSubtype::BufferType(BufferLength(33)));
+pub const BUFF_34: TypeSignature = SequenceType(SequenceSubtype::BufferType(BufferLength(34)));
 pub const BUFF_20: TypeSignature = SequenceType(SequenceSubtype::BufferType(BufferLength(20)));
 pub const BUFF_1: TypeSignature = SequenceType(SequenceSubtype::BufferType(BufferLength(1)));
 
@@ -0,0 +1,48 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+/// The version of the Clarity language that a contract is written in.  Natives added after the
+/// first version are only visible to contracts of a later version, so they cannot collide with
+/// the names that earlier contracts already use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ClarityVersion {
+    Clarity1,
+    Clarity2,
+}
+
+impl ClarityVersion {
+    pub fn latest() -> ClarityVersion {
+        ClarityVersion::Clarity2
+    }
+}
+
+/// Contracts stored before Clarity had versions are Clarity 1 contracts.
+impl Default for ClarityVersion {
+    fn default() -> ClarityVersion {
+        ClarityVersion::Clarity1
+    }
+}
+
+impl fmt::Display for ClarityVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClarityVersion::Clarity1 => write!(f, "Clarity 1"),
+            ClarityVersion::Clarity2 => write!(f, "Clarity 2"),
+        }
+    }
+}
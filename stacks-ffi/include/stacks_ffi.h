@@ -0,0 +1,68 @@
+/*
+ * C declarations for libstacks_ffi.
+ *
+ * All functions return one of the STACKS_FFI_* status codes.  Variable-size
+ * outputs use caller-owned buffers: pass the buffer capacity in *out_len; on
+ * return *out_len holds the size the output needs (including the trailing NUL
+ * for strings).  If the buffer is too small, nothing is written and
+ * STACKS_FFI_ERR_BUFFER_TOO_SMALL is returned.  The library never allocates
+ * memory on behalf of the caller.
+ */
+
+#ifndef STACKS_FFI_H
+#define STACKS_FFI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define STACKS_FFI_ABI_VERSION 1
+
+#define STACKS_FFI_OK 0
+#define STACKS_FFI_ERR_NULL_POINTER -1
+#define STACKS_FFI_ERR_INVALID_INPUT -2
+#define STACKS_FFI_ERR_BUFFER_TOO_SMALL -3
+#define STACKS_FFI_ERR_INTERNAL -4
+
+#define STACKS_FFI_HASH160_LEN 20
+#define STACKS_FFI_SIGHASH_LEN 32
+#define STACKS_FFI_SIGNATURE_LEN 65
+
+#define STACKS_FFI_AUTH_STANDARD 0x04
+#define STACKS_FFI_AUTH_SPONSORED 0x05
+
+uint32_t stacks_ffi_abi_version(void);
+
+/* addresses */
+int stacks_address_encode(uint8_t version, const uint8_t *hash160,
+                          char *out, size_t *out_len);
+int stacks_address_decode(const char *address, uint8_t *version_out,
+                          uint8_t *hash160_out);
+int stacks_address_from_public_key(uint8_t version, const uint8_t *pubkey,
+                                   size_t pubkey_len, char *out,
+                                   size_t *out_len);
+
+/* transaction codec */
+int stacks_tx_to_json(const uint8_t *tx_bytes, size_t tx_len, char *out,
+                      size_t *out_len);
+int stacks_tx_from_json(const char *json, uint8_t *out, size_t *out_len);
+int stacks_tx_txid(const uint8_t *tx_bytes, size_t tx_len, uint8_t *txid_out);
+
+/* sighashes */
+int stacks_tx_initial_sighash(const uint8_t *tx_bytes, size_t tx_len,
+                              uint8_t *sighash_out);
+int stacks_tx_presign_sighash(const uint8_t *cur_sighash, uint8_t auth_flag,
+                              uint64_t fee_rate, uint64_t nonce,
+                              uint8_t *sighash_out);
+int stacks_tx_postsign_sighash(const uint8_t *cur_sighash,
+                               const uint8_t *pubkey, size_t pubkey_len,
+                               const uint8_t *signature, uint8_t *sighash_out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* STACKS_FFI_H */
@@ -0,0 +1,552 @@
+// Copyright (C) 2013-2020 Blocstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! C ABI over the consensus-critical codecs of the Stacks node.
+//!
+//! Wallets and HSMs that cannot link Rust directly can use this library to encode and decode
+//! addresses, (de)serialize transactions, and compute the sighashes a signer must commit to,
+//! using exactly the same code the node runs.  The matching C declarations live in
+//! `include/stacks_ffi.h`.
+//!
+//! Conventions:
+//! * Every function returns one of the `STACKS_FFI_*` status codes.
+//! * Fixed-size outputs (hashes, signatures) are written to caller-owned buffers of the
+//!   documented size.
+//! * Variable-size outputs are written to a caller-owned buffer `out` whose capacity is passed in
+//!   `*out_len`.  On return, `*out_len` holds the number of bytes the output needs (including the
+//!   trailing NUL for strings).  If the buffer is too small, nothing is written and
+//!   `STACKS_FFI_ERR_BUFFER_TOO_SMALL` is returned, so the caller can retry.
+//! * No memory is ever allocated on behalf of the caller, and panics never cross the ABI.
+
+extern crate serde_json;
+extern crate stacks;
+
+use std::ffi::CStr;
+use std::io::Cursor;
+use std::os::raw::{c_char, c_int};
+use std::panic;
+use std::ptr;
+use std::slice;
+
+use stacks::address::AddressHashMode;
+use stacks::burnchains::{Address, Txid};
+use stacks::chainstate::stacks::{
+    StacksAddress, StacksPublicKey, StacksTransaction, TransactionAuthFlags,
+    TransactionSpendingCondition,
+};
+use stacks::net::StacksMessageCodec;
+use stacks::util::hash::Hash160;
+use stacks::util::secp256k1::MessageSignature;
+
+/// Version of the C ABI.  Bumped whenever a function signature or status code changes.
+pub const STACKS_FFI_ABI_VERSION: u32 = 1;
+
+pub const STACKS_FFI_OK: c_int = 0;
+/// A required pointer argument was NULL.
+pub const STACKS_FFI_ERR_NULL_POINTER: c_int = -1;
+/// An input could not be decoded (bad address, bad transaction bytes, bad key, ...).
+pub const STACKS_FFI_ERR_INVALID_INPUT: c_int = -2;
+/// The output buffer is too small; `*out_len` holds the required size.
+pub const STACKS_FFI_ERR_BUFFER_TOO_SMALL: c_int = -3;
+/// The library panicked.  This is always a bug.
+pub const STACKS_FFI_ERR_INTERNAL: c_int = -4;
+
+/// Length of a Stacks address hash (hash160).
+pub const STACKS_FFI_HASH160_LEN: usize = 20;
+/// Length of a txid or sighash.
+pub const STACKS_FFI_SIGHASH_LEN: usize = 32;
+/// Length of a recoverable message signature.
+pub const STACKS_FFI_SIGNATURE_LEN: usize = 65;
+
+/// Run an FFI body, converting a panic into STACKS_FFI_ERR_INTERNAL.
+fn ffi_guard<F>(f: F) -> c_int
+where
+    F: FnOnce() -> Result<(), c_int> + panic::UnwindSafe,
+{
+    match panic::catch_unwind(f) {
+        Ok(Ok(())) => STACKS_FFI_OK,
+        Ok(Err(code)) => code,
+        Err(_) => STACKS_FFI_ERR_INTERNAL,
+    }
+}
+
+unsafe fn input_slice<'a>(data: *const u8, len: usize) -> Result<&'a [u8], c_int> {
+    if data.is_null() {
+        if len == 0 {
+            return Ok(&[]);
+        }
+        return Err(STACKS_FFI_ERR_NULL_POINTER);
+    }
+    Ok(slice::from_raw_parts(data, len))
+}
+
+unsafe fn input_str<'a>(s: *const c_char) -> Result<&'a str, c_int> {
+    if s.is_null() {
+        return Err(STACKS_FFI_ERR_NULL_POINTER);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| STACKS_FFI_ERR_INVALID_INPUT)
+}
+
+unsafe fn write_fixed(out: *mut u8, bytes: &[u8]) -> Result<(), c_int> {
+    if out.is_null() {
+        return Err(STACKS_FFI_ERR_NULL_POINTER);
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    Ok(())
+}
+
+unsafe fn write_buffer(out: *mut u8, out_len: *mut usize, bytes: &[u8]) -> Result<(), c_int> {
+    if out_len.is_null() {
+        return Err(STACKS_FFI_ERR_NULL_POINTER);
+    }
+    let capacity = *out_len;
+    *out_len = bytes.len();
+    if capacity < bytes.len() {
+        return Err(STACKS_FFI_ERR_BUFFER_TOO_SMALL);
+    }
+    if bytes.len() > 0 {
+        write_fixed(out, bytes)?;
+    }
+    Ok(())
+}
+
+unsafe fn write_string(out: *mut c_char, out_len: *mut usize, s: &str) -> Result<(), c_int> {
+    let mut bytes = Vec::with_capacity(s.len() + 1);
+    bytes.extend_from_slice(s.as_bytes());
+    bytes.push(0);
+    write_buffer(out as *mut u8, out_len, &bytes)
+}
+
+/// Decode a transaction, rejecting trailing bytes.
+fn decode_tx(tx_bytes: &[u8]) -> Result<StacksTransaction, c_int> {
+    let mut cursor = Cursor::new(tx_bytes);
+    let tx = StacksTransaction::consensus_deserialize(&mut cursor)
+        .map_err(|_| STACKS_FFI_ERR_INVALID_INPUT)?;
+    if (cursor.position() as usize) != tx_bytes.len() {
+        return Err(STACKS_FFI_ERR_INVALID_INPUT);
+    }
+    Ok(tx)
+}
+
+fn auth_flag_from_u8(flag: u8) -> Result<TransactionAuthFlags, c_int> {
+    if flag == TransactionAuthFlags::AuthStandard as u8 {
+        Ok(TransactionAuthFlags::AuthStandard)
+    } else if flag == TransactionAuthFlags::AuthSponsored as u8 {
+        Ok(TransactionAuthFlags::AuthSponsored)
+    } else {
+        Err(STACKS_FFI_ERR_INVALID_INPUT)
+    }
+}
+
+/// Get the ABI version of this library.
+#[no_mangle]
+pub extern "C" fn stacks_ffi_abi_version() -> u32 {
+    STACKS_FFI_ABI_VERSION
+}
+
+/// Encode a version byte and a 20-byte hash160 as a c32check Stacks address string.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_address_encode(
+    version: u8,
+    hash160: *const u8,
+    out: *mut c_char,
+    out_len: *mut usize,
+) -> c_int {
+    ffi_guard(|| {
+        let bytes = input_slice(hash160, STACKS_FFI_HASH160_LEN)?;
+        if version >= 32 {
+            return Err(STACKS_FFI_ERR_INVALID_INPUT);
+        }
+        let mut hash_bytes = [0u8; 20];
+        hash_bytes.copy_from_slice(bytes);
+        let addr = StacksAddress {
+            version,
+            bytes: Hash160(hash_bytes),
+        };
+        write_string(out, out_len, &addr.to_string())
+    })
+}
+
+/// Decode a c32check Stacks address string into its version byte and 20-byte hash160.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_address_decode(
+    address: *const c_char,
+    version_out: *mut u8,
+    hash160_out: *mut u8,
+) -> c_int {
+    ffi_guard(|| {
+        let addr_str = input_str(address)?;
+        let addr = StacksAddress::from_string(addr_str).ok_or(STACKS_FFI_ERR_INVALID_INPUT)?;
+        if version_out.is_null() {
+            return Err(STACKS_FFI_ERR_NULL_POINTER);
+        }
+        write_fixed(hash160_out, addr.bytes.as_bytes())?;
+        *version_out = addr.version;
+        Ok(())
+    })
+}
+
+/// Compute the single-signature (p2pkh) Stacks address for a compressed or uncompressed
+/// secp256k1 public key.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_address_from_public_key(
+    version: u8,
+    pubkey: *const u8,
+    pubkey_len: usize,
+    out: *mut c_char,
+    out_len: *mut usize,
+) -> c_int {
+    ffi_guard(|| {
+        let pubkey_bytes = input_slice(pubkey, pubkey_len)?;
+        if version >= 32 {
+            return Err(STACKS_FFI_ERR_INVALID_INPUT);
+        }
+        let pubk =
+            StacksPublicKey::from_slice(pubkey_bytes).map_err(|_| STACKS_FFI_ERR_INVALID_INPUT)?;
+        let addr = StacksAddress::from_public_keys(
+            version,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![pubk],
+        )
+        .ok_or(STACKS_FFI_ERR_INVALID_INPUT)?;
+        write_string(out, out_len, &addr.to_string())
+    })
+}
+
+/// Decode a consensus-serialized transaction into its JSON representation.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_tx_to_json(
+    tx_bytes: *const u8,
+    tx_len: usize,
+    out: *mut c_char,
+    out_len: *mut usize,
+) -> c_int {
+    ffi_guard(|| {
+        let tx = decode_tx(input_slice(tx_bytes, tx_len)?)?;
+        let json = serde_json::to_string(&tx).map_err(|_| STACKS_FFI_ERR_INTERNAL)?;
+        write_string(out, out_len, &json)
+    })
+}
+
+/// Encode the JSON representation of a transaction (as produced by `stacks_tx_to_json`) into its
+/// consensus serialization.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_tx_from_json(
+    json: *const c_char,
+    out: *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    ffi_guard(|| {
+        let json_str = input_str(json)?;
+        let tx: StacksTransaction =
+            serde_json::from_str(json_str).map_err(|_| STACKS_FFI_ERR_INVALID_INPUT)?;
+        let mut bytes = vec![];
+        tx.consensus_serialize(&mut bytes)
+            .map_err(|_| STACKS_FFI_ERR_INVALID_INPUT)?;
+        write_buffer(out, out_len, &bytes)
+    })
+}
+
+/// Compute the txid of a consensus-serialized transaction.  Writes 32 bytes to `txid_out`.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_tx_txid(
+    tx_bytes: *const u8,
+    tx_len: usize,
+    txid_out: *mut u8,
+) -> c_int {
+    ffi_guard(|| {
+        let tx = decode_tx(input_slice(tx_bytes, tx_len)?)?;
+        write_fixed(txid_out, tx.txid().as_bytes())
+    })
+}
+
+/// Compute the initial sighash of a consensus-serialized transaction -- the txid of the
+/// transaction with its authorization cleared.  Signing starts from this value.  Writes 32 bytes
+/// to `sighash_out`.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_tx_initial_sighash(
+    tx_bytes: *const u8,
+    tx_len: usize,
+    sighash_out: *mut u8,
+) -> c_int {
+    ffi_guard(|| {
+        let tx = decode_tx(input_slice(tx_bytes, tx_len)?)?;
+        write_fixed(sighash_out, tx.sign_begin().as_bytes())
+    })
+}
+
+/// Compute the pre-sign sighash: the digest a signer actually signs, given the current sighash,
+/// the auth flag (0x04 standard, 0x05 sponsored), and the spending condition's fee rate and nonce.
+/// Writes 32 bytes to `sighash_out`.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_tx_presign_sighash(
+    cur_sighash: *const u8,
+    auth_flag: u8,
+    fee_rate: u64,
+    nonce: u64,
+    sighash_out: *mut u8,
+) -> c_int {
+    ffi_guard(|| {
+        let cur = Txid::from_bytes(input_slice(cur_sighash, STACKS_FFI_SIGHASH_LEN)?)
+            .ok_or(STACKS_FFI_ERR_INVALID_INPUT)?;
+        let flag = auth_flag_from_u8(auth_flag)?;
+        let next = TransactionSpendingCondition::make_sighash_presign(&cur, &flag, fee_rate, nonce);
+        write_fixed(sighash_out, next.as_bytes())
+    })
+}
+
+/// Compute the post-sign sighash: the value the next signer (if any) continues from, given the
+/// current sighash, the signer's public key, and its 65-byte recoverable signature.  Writes 32
+/// bytes to `sighash_out`.
+#[no_mangle]
+pub unsafe extern "C" fn stacks_tx_postsign_sighash(
+    cur_sighash: *const u8,
+    pubkey: *const u8,
+    pubkey_len: usize,
+    signature: *const u8,
+    sighash_out: *mut u8,
+) -> c_int {
+    ffi_guard(|| {
+        let cur = Txid::from_bytes(input_slice(cur_sighash, STACKS_FFI_SIGHASH_LEN)?)
+            .ok_or(STACKS_FFI_ERR_INVALID_INPUT)?;
+        let pubk = StacksPublicKey::from_slice(input_slice(pubkey, pubkey_len)?)
+            .map_err(|_| STACKS_FFI_ERR_INVALID_INPUT)?;
+        let sig = MessageSignature::from_bytes(input_slice(signature, STACKS_FFI_SIGNATURE_LEN)?)
+            .ok_or(STACKS_FFI_ERR_INVALID_INPUT)?;
+        let next = TransactionSpendingCondition::make_sighash_postsign(&cur, &pubk, &sig);
+        write_fixed(sighash_out, next.as_bytes())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    use stacks::chainstate::stacks::{
+        StacksPrivateKey, StacksTransactionSigner, TokenTransferMemo, TransactionAuth,
+        TransactionPayload, TransactionVersion, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+    };
+    use stacks::vm::types::PrincipalData;
+
+    fn make_signed_tx() -> (StacksTransaction, StacksPrivateKey) {
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let recipient = PrincipalData::from(StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x11; 20]),
+        });
+        let mut tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth,
+            TransactionPayload::TokenTransfer(recipient, 123, TokenTransferMemo([0u8; 34]), None),
+        );
+        tx.chain_id = 0x80000000;
+        tx.set_fee_rate(180);
+        tx.set_origin_nonce(7);
+
+        let mut signer = StacksTransactionSigner::new(&tx);
+        signer.sign_origin(&privk).unwrap();
+        (signer.get_tx().unwrap(), privk)
+    }
+
+    fn tx_bytes(tx: &StacksTransaction) -> Vec<u8> {
+        let mut bytes = vec![];
+        tx.consensus_serialize(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_address_roundtrip() {
+        let hash = [0x42u8; 20];
+        let mut needed = 0;
+        let rc = unsafe {
+            stacks_address_encode(
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                hash.as_ptr(),
+                ptr::null_mut(),
+                &mut needed,
+            )
+        };
+        assert_eq!(rc, STACKS_FFI_ERR_BUFFER_TOO_SMALL);
+
+        let mut buf = vec![0u8; needed];
+        let mut len = buf.len();
+        let rc = unsafe {
+            stacks_address_encode(
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                hash.as_ptr(),
+                buf.as_mut_ptr() as *mut c_char,
+                &mut len,
+            )
+        };
+        assert_eq!(rc, STACKS_FFI_OK);
+        assert_eq!(len, needed);
+
+        let expected = StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160(hash),
+        }
+        .to_string();
+        let addr = CStr::from_bytes_with_nul(&buf).unwrap();
+        assert_eq!(addr.to_str().unwrap(), expected);
+
+        let mut version = 0u8;
+        let mut decoded = [0u8; 20];
+        let rc =
+            unsafe { stacks_address_decode(addr.as_ptr(), &mut version, decoded.as_mut_ptr()) };
+        assert_eq!(rc, STACKS_FFI_OK);
+        assert_eq!(version, C32_ADDRESS_VERSION_TESTNET_SINGLESIG);
+        assert_eq!(decoded, hash);
+
+        let bad = CString::new("not-an-address").unwrap();
+        let rc = unsafe { stacks_address_decode(bad.as_ptr(), &mut version, decoded.as_mut_ptr()) };
+        assert_eq!(rc, STACKS_FFI_ERR_INVALID_INPUT);
+
+        let rc = unsafe { stacks_address_decode(ptr::null(), &mut version, decoded.as_mut_ptr()) };
+        assert_eq!(rc, STACKS_FFI_ERR_NULL_POINTER);
+    }
+
+    #[test]
+    fn test_address_from_public_key() {
+        let (tx, privk) = make_signed_tx();
+        let pubk = StacksPublicKey::from_private(&privk);
+        let pubk_bytes = pubk.to_bytes_compressed();
+
+        let mut buf = vec![0u8; 64];
+        let mut len = buf.len();
+        let rc = unsafe {
+            stacks_address_from_public_key(
+                C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                pubk_bytes.as_ptr(),
+                pubk_bytes.len(),
+                buf.as_mut_ptr() as *mut c_char,
+                &mut len,
+            )
+        };
+        assert_eq!(rc, STACKS_FFI_OK);
+        let addr = CStr::from_bytes_with_nul(&buf[..len]).unwrap();
+        assert_eq!(addr.to_str().unwrap(), tx.origin_address().to_string());
+    }
+
+    #[test]
+    fn test_tx_json_roundtrip() {
+        let (tx, _) = make_signed_tx();
+        let bytes = tx_bytes(&tx);
+
+        let mut json_len = 0;
+        let rc = unsafe {
+            stacks_tx_to_json(bytes.as_ptr(), bytes.len(), ptr::null_mut(), &mut json_len)
+        };
+        assert_eq!(rc, STACKS_FFI_ERR_BUFFER_TOO_SMALL);
+
+        let mut json = vec![0u8; json_len];
+        let rc = unsafe {
+            stacks_tx_to_json(
+                bytes.as_ptr(),
+                bytes.len(),
+                json.as_mut_ptr() as *mut c_char,
+                &mut json_len,
+            )
+        };
+        assert_eq!(rc, STACKS_FFI_OK);
+
+        let json_cstr = CStr::from_bytes_with_nul(&json).unwrap();
+        let mut out = vec![0u8; bytes.len()];
+        let mut out_len = out.len();
+        let rc = unsafe { stacks_tx_from_json(json_cstr.as_ptr(), out.as_mut_ptr(), &mut out_len) };
+        assert_eq!(rc, STACKS_FFI_OK);
+        assert_eq!(out_len, bytes.len());
+        assert_eq!(out, bytes);
+
+        // trailing bytes are rejected
+        let mut padded = bytes.clone();
+        padded.push(0);
+        let mut json_len = json.len();
+        let rc = unsafe {
+            stacks_tx_to_json(
+                padded.as_ptr(),
+                padded.len(),
+                json.as_mut_ptr() as *mut c_char,
+                &mut json_len,
+            )
+        };
+        assert_eq!(rc, STACKS_FFI_ERR_INVALID_INPUT);
+    }
+
+    #[test]
+    fn test_tx_sighashes() {
+        let (tx, privk) = make_signed_tx();
+        let bytes = tx_bytes(&tx);
+
+        let mut txid = [0u8; 32];
+        let rc = unsafe { stacks_tx_txid(bytes.as_ptr(), bytes.len(), txid.as_mut_ptr()) };
+        assert_eq!(rc, STACKS_FFI_OK);
+        assert_eq!(&txid, tx.txid().as_bytes());
+
+        let mut initial = [0u8; 32];
+        let rc =
+            unsafe { stacks_tx_initial_sighash(bytes.as_ptr(), bytes.len(), initial.as_mut_ptr()) };
+        assert_eq!(rc, STACKS_FFI_OK);
+        assert_eq!(&initial, tx.sign_begin().as_bytes());
+
+        let mut presign = [0u8; 32];
+        let rc = unsafe {
+            stacks_tx_presign_sighash(
+                initial.as_ptr(),
+                TransactionAuthFlags::AuthStandard as u8,
+                tx.get_fee_rate(),
+                7,
+                presign.as_mut_ptr(),
+            )
+        };
+        assert_eq!(rc, STACKS_FFI_OK);
+
+        // the origin's signature must be over the pre-sign sighash
+        let sig = match tx.auth {
+            TransactionAuth::Standard(TransactionSpendingCondition::Singlesig(ref data)) => {
+                data.signature.clone()
+            }
+            _ => panic!("expected a singlesig standard auth"),
+        };
+        let pubk = StacksPublicKey::recover_to_pubkey(&presign, &sig).unwrap();
+        assert_eq!(pubk, StacksPublicKey::from_private(&privk));
+
+        let mut postsign = [0u8; 32];
+        let pubk_bytes = pubk.to_bytes_compressed();
+        let rc = unsafe {
+            stacks_tx_postsign_sighash(
+                initial.as_ptr(),
+                pubk_bytes.as_ptr(),
+                pubk_bytes.len(),
+                sig.as_bytes().as_ptr(),
+                postsign.as_mut_ptr(),
+            )
+        };
+        assert_eq!(rc, STACKS_FFI_OK);
+
+        let rc = unsafe {
+            stacks_tx_presign_sighash(initial.as_ptr(), 0x07, 0, 0, presign.as_mut_ptr())
+        };
+        assert_eq!(rc, STACKS_FFI_ERR_INVALID_INPUT);
+    }
+}
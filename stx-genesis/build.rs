@@ -23,6 +23,8 @@ fn main() {
         .expect("failed to verify and output chainstate.txt.sha256 hash");
     write_archives(true).expect("failed to write chainstate test data archives");
     write_archives(false).expect("failed to write chainstate prod data archives");
+    write_balances_total(true).expect("failed to write chainstate test balances total");
+    write_balances_total(false).expect("failed to write chainstate prod balances total");
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=chainstate-test.txt.sha256");
     println!("cargo:rerun-if-changed=chainstate-test.txt");
@@ -83,6 +85,46 @@ fn write_archive(
     Ok(())
 }
 
+/// Write out the number of genesis balances and the microSTX they add up to, so the node can
+/// check that it imported all of them.
+fn write_balances_total(test_data: bool) -> std::io::Result<()> {
+    let out_dir = env::var_os("OUT_DIR").unwrap();
+    let reader = BufReader::new(open_chainstate_file(test_data));
+    let out_file_name = if test_data {
+        "account_balances-test.total"
+    } else {
+        "account_balances.total"
+    };
+
+    let section_header = "-----BEGIN STX BALANCES-----";
+    let section_footer = "-----END STX BALANCES-----";
+
+    let mut count = 0u64;
+    let mut total_ustx = 0u128;
+    for line in reader
+        .lines()
+        .map(|line| line.unwrap())
+        .skip_while(|line| !line.eq(section_header))
+        // skip table header line
+        .skip(2)
+        .take_while(|line| !line.eq(section_footer))
+    {
+        let amount = line
+            .split(",")
+            .nth(1)
+            .expect("FATAL ERROR: genesis balance is missing its amount")
+            .parse::<u64>()
+            .expect("FATAL ERROR: genesis balance amount is not a number");
+        count += 1;
+        total_ustx += amount as u128;
+    }
+
+    let mut out_file = File::create(Path::new(&out_dir).join(out_file_name))?;
+    write!(out_file, "{},{}", count, total_ustx)?;
+    out_file.flush()?;
+    Ok(())
+}
+
 fn sha256_digest<R: Read>(mut reader: R) -> String {
     let mut hasher = Sha256::new();
     let mut buffer = [0; 1024];
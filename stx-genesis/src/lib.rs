@@ -1,8 +1,11 @@
+use std::fs;
 use std::io::prelude::*;
 use std::io::{self, BufReader};
+use std::sync::Arc;
 
 use libflate::deflate;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct GenesisAccountBalance {
     /// A STX or BTC address (BTC addresses should be converted to STX when used).
     pub address: String,
@@ -10,6 +13,7 @@ pub struct GenesisAccountBalance {
     pub amount: u64,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct GenesisAccountLockup {
     /// A STX or BTC address (BTC addresses should be converted to STX when used).
     pub address: String,
@@ -19,6 +23,7 @@ pub struct GenesisAccountLockup {
     pub block_height: u64,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct GenesisNamespace {
     pub namespace_id: String,
     pub address: String,
@@ -32,6 +37,7 @@ pub struct GenesisNamespace {
     pub lifetime: String,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct GenesisName {
     pub name: String,
     pub address: String,
@@ -40,49 +46,229 @@ pub struct GenesisName {
     pub zonefile_hash: String,
 }
 
+/// The number of genesis balances, and the microSTX they add up to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenesisBalancesTotal {
+    pub count: u64,
+    pub total_ustx: u128,
+}
+
 pub static GENESIS_CHAINSTATE_HASH: &str =
     include_str!(concat!(env!("OUT_DIR"), "/chainstate.txt.sha256"));
 
+/// Genesis data loaded at runtime, from a file in the same format as chainstate.txt
+#[derive(Debug, Clone, PartialEq)]
+struct CustomGenesisData {
+    balances: Vec<GenesisAccountBalance>,
+    lockups: Vec<GenesisAccountLockup>,
+    namespaces: Vec<GenesisNamespace>,
+    names: Vec<GenesisName>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum GenesisSource {
+    /// the chainstate.txt (or chainstate-test.txt) compiled into this crate
+    Bundled {
+        use_test_chainstate_data: bool,
+    },
+    Custom(Arc<CustomGenesisData>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct GenesisData {
-    use_test_chainstate_data: bool,
+    source: GenesisSource,
 }
 
 impl GenesisData {
     pub fn new(use_test_chainstate_data: bool) -> GenesisData {
         GenesisData {
-            use_test_chainstate_data,
+            source: GenesisSource::Bundled {
+                use_test_chainstate_data,
+            },
         }
     }
-    pub fn read_balances(&self) -> Box<dyn Iterator<Item = GenesisAccountBalance>> {
-        read_balances(if self.use_test_chainstate_data {
-            include_bytes!(concat!(env!("OUT_DIR"), "/account_balances-test.gz"))
-        } else {
-            include_bytes!(concat!(env!("OUT_DIR"), "/account_balances.gz"))
+
+    /// Load genesis data from the text of a chainstate.txt-formatted file.  Sections that are
+    /// missing are treated as empty.
+    pub fn from_chainstate_text(text: &str) -> io::Result<GenesisData> {
+        let data = CustomGenesisData {
+            balances: parse_section(text, "STX BALANCES", GenesisAccountBalance::from_cols)?,
+            lockups: parse_section(text, "STX VESTING", GenesisAccountLockup::from_cols)?,
+            namespaces: parse_section(text, "NAMESPACES", GenesisNamespace::from_cols)?,
+            names: parse_section(text, "NAMES", GenesisName::from_cols)?,
+        };
+        Ok(GenesisData {
+            source: GenesisSource::Custom(Arc::new(data)),
         })
     }
+
+    /// Load genesis data from a chainstate.txt-formatted file at `path`
+    pub fn from_file(path: &str) -> io::Result<GenesisData> {
+        GenesisData::from_chainstate_text(&fs::read_to_string(path)?)
+    }
+
+    pub fn read_balances(&self) -> Box<dyn Iterator<Item = GenesisAccountBalance>> {
+        match self.source {
+            GenesisSource::Bundled {
+                use_test_chainstate_data,
+            } => read_balances(if use_test_chainstate_data {
+                include_bytes!(concat!(env!("OUT_DIR"), "/account_balances-test.gz"))
+            } else {
+                include_bytes!(concat!(env!("OUT_DIR"), "/account_balances.gz"))
+            }),
+            GenesisSource::Custom(ref data) => Box::new(data.balances.clone().into_iter()),
+        }
+    }
+    pub fn read_balances_total(&self) -> GenesisBalancesTotal {
+        match self.source {
+            GenesisSource::Bundled {
+                use_test_chainstate_data,
+            } => read_balances_total(if use_test_chainstate_data {
+                include_str!(concat!(env!("OUT_DIR"), "/account_balances-test.total"))
+            } else {
+                include_str!(concat!(env!("OUT_DIR"), "/account_balances.total"))
+            }),
+            GenesisSource::Custom(ref data) => GenesisBalancesTotal {
+                count: data.balances.len() as u64,
+                total_ustx: data
+                    .balances
+                    .iter()
+                    .map(|balance| balance.amount as u128)
+                    .sum(),
+            },
+        }
+    }
     pub fn read_lockups(&self) -> Box<dyn Iterator<Item = GenesisAccountLockup>> {
-        read_lockups(if self.use_test_chainstate_data {
-            include_bytes!(concat!(env!("OUT_DIR"), "/account_lockups-test.gz"))
-        } else {
-            include_bytes!(concat!(env!("OUT_DIR"), "/account_lockups.gz"))
-        })
+        match self.source {
+            GenesisSource::Bundled {
+                use_test_chainstate_data,
+            } => read_lockups(if use_test_chainstate_data {
+                include_bytes!(concat!(env!("OUT_DIR"), "/account_lockups-test.gz"))
+            } else {
+                include_bytes!(concat!(env!("OUT_DIR"), "/account_lockups.gz"))
+            }),
+            GenesisSource::Custom(ref data) => Box::new(data.lockups.clone().into_iter()),
+        }
     }
     pub fn read_namespaces(&self) -> Box<dyn Iterator<Item = GenesisNamespace>> {
-        read_namespaces(if self.use_test_chainstate_data {
-            include_bytes!(concat!(env!("OUT_DIR"), "/namespaces-test.gz"))
-        } else {
-            include_bytes!(concat!(env!("OUT_DIR"), "/namespaces.gz"))
-        })
+        match self.source {
+            GenesisSource::Bundled {
+                use_test_chainstate_data,
+            } => read_namespaces(if use_test_chainstate_data {
+                include_bytes!(concat!(env!("OUT_DIR"), "/namespaces-test.gz"))
+            } else {
+                include_bytes!(concat!(env!("OUT_DIR"), "/namespaces.gz"))
+            }),
+            GenesisSource::Custom(ref data) => Box::new(data.namespaces.clone().into_iter()),
+        }
     }
     pub fn read_names(&self) -> Box<dyn Iterator<Item = GenesisName>> {
-        read_names(if self.use_test_chainstate_data {
-            include_bytes!(concat!(env!("OUT_DIR"), "/names-test.gz"))
-        } else {
-            include_bytes!(concat!(env!("OUT_DIR"), "/names.gz"))
+        match self.source {
+            GenesisSource::Bundled {
+                use_test_chainstate_data,
+            } => read_names(if use_test_chainstate_data {
+                include_bytes!(concat!(env!("OUT_DIR"), "/names-test.gz"))
+            } else {
+                include_bytes!(concat!(env!("OUT_DIR"), "/names.gz"))
+            }),
+            GenesisSource::Custom(ref data) => Box::new(data.names.clone().into_iter()),
+        }
+    }
+}
+
+fn parse_col<T: std::str::FromStr>(cols: &[String], i: usize, name: &str) -> Result<T, String> {
+    let col = cols
+        .get(i)
+        .ok_or_else(|| format!("missing column `{}`", name))?;
+    col.parse::<T>()
+        .map_err(|_| format!("invalid `{}`: {}", name, col))
+}
+
+impl GenesisAccountBalance {
+    fn from_cols(cols: Vec<String>) -> Result<GenesisAccountBalance, String> {
+        Ok(GenesisAccountBalance {
+            address: parse_col(&cols, 0, "address")?,
+            amount: parse_col(&cols, 1, "balance")?,
         })
     }
 }
 
+impl GenesisAccountLockup {
+    fn from_cols(cols: Vec<String>) -> Result<GenesisAccountLockup, String> {
+        Ok(GenesisAccountLockup {
+            address: parse_col(&cols, 0, "address")?,
+            amount: parse_col(&cols, 1, "value")?,
+            block_height: parse_col(&cols, 2, "blocks")?,
+        })
+    }
+}
+
+impl GenesisNamespace {
+    fn from_cols(cols: Vec<String>) -> Result<GenesisNamespace, String> {
+        Ok(GenesisNamespace {
+            namespace_id: parse_col(&cols, 0, "namespace_id")?,
+            address: parse_col(&cols, 1, "address")?,
+            reveal_block: parse_col(&cols, 2, "reveal_block")?,
+            ready_block: parse_col(&cols, 3, "ready_block")?,
+            buckets: parse_col(&cols, 4, "buckets")?,
+            base: parse_col(&cols, 5, "base")?,
+            coeff: parse_col(&cols, 6, "coeff")?,
+            nonalpha_discount: parse_col(&cols, 7, "nonalpha_discount")?,
+            no_vowel_discount: parse_col(&cols, 8, "no_vowel_discount")?,
+            lifetime: parse_col(&cols, 9, "lifetime")?,
+        })
+    }
+}
+
+impl GenesisName {
+    fn from_cols(cols: Vec<String>) -> Result<GenesisName, String> {
+        Ok(GenesisName {
+            name: parse_col(&cols, 0, "name")?,
+            address: parse_col(&cols, 1, "address")?,
+            registered_at: parse_col(&cols, 2, "registered_at")?,
+            expire_block: parse_col(&cols, 3, "expire_block")?,
+            zonefile_hash: parse_col(&cols, 4, "zonefile_hash")?,
+        })
+    }
+}
+
+/// Parse the rows of a `-----BEGIN <section_name>-----` section, skipping its table header line
+fn parse_section<T, F>(text: &str, section_name: &str, parse_row: F) -> io::Result<Vec<T>>
+where
+    F: Fn(Vec<String>) -> Result<T, String>,
+{
+    let section_header = format!("-----BEGIN {}-----", section_name);
+    let section_footer = format!("-----END {}-----", section_name);
+
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .skip_while(|(_, line)| *line != section_header);
+    if lines.next().is_none() {
+        return Ok(vec![]);
+    }
+
+    let mut rows = vec![];
+    // skip table header line
+    for (lineno, line) in lines.skip(1) {
+        if line == section_footer {
+            return Ok(rows);
+        }
+        let cols = line.split(",").map(String::from).collect();
+        let row = parse_row(cols).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} line {}: {}", section_name, lineno + 1, e),
+            )
+        })?;
+        rows.push(row);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("missing {}", section_footer),
+    ))
+}
+
 fn iter_deflated_csv(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = Vec<String>>> {
     let cursor = io::Cursor::new(deflate_bytes);
     let deflate_decoder = deflate::Decoder::new(cursor);
@@ -95,46 +281,33 @@ fn iter_deflated_csv(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = Ve
 }
 
 fn read_balances(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = GenesisAccountBalance>> {
-    let balances = iter_deflated_csv(deflate_bytes).map(|cols| GenesisAccountBalance {
-        address: cols[0].to_string(),
-        amount: cols[1].parse::<u64>().unwrap(),
-    });
+    let balances = iter_deflated_csv(deflate_bytes)
+        .map(|cols| GenesisAccountBalance::from_cols(cols).unwrap());
     return Box::new(balances);
 }
 
+fn read_balances_total(total: &'static str) -> GenesisBalancesTotal {
+    let cols: Vec<&str> = total.split(",").collect();
+    GenesisBalancesTotal {
+        count: cols[0].parse::<u64>().unwrap(),
+        total_ustx: cols[1].parse::<u128>().unwrap(),
+    }
+}
+
 fn read_lockups(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = GenesisAccountLockup>> {
-    let lockups = iter_deflated_csv(deflate_bytes).map(|cols| GenesisAccountLockup {
-        address: cols[0].to_string(),
-        amount: cols[1].parse::<u64>().unwrap(),
-        block_height: cols[2].parse::<u64>().unwrap(),
-    });
+    let lockups =
+        iter_deflated_csv(deflate_bytes).map(|cols| GenesisAccountLockup::from_cols(cols).unwrap());
     return Box::new(lockups);
 }
 
 fn read_namespaces(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = GenesisNamespace>> {
-    let namespaces = iter_deflated_csv(deflate_bytes).map(|cols| GenesisNamespace {
-        namespace_id: cols[0].to_string(),
-        address: cols[1].to_string(),
-        reveal_block: cols[2].parse::<i64>().unwrap(),
-        ready_block: cols[3].parse::<i64>().unwrap(),
-        buckets: cols[4].to_string(),
-        base: cols[5].to_string(),
-        coeff: cols[6].to_string(),
-        nonalpha_discount: cols[7].to_string(),
-        no_vowel_discount: cols[8].to_string(),
-        lifetime: cols[9].to_string(),
-    });
+    let namespaces =
+        iter_deflated_csv(deflate_bytes).map(|cols| GenesisNamespace::from_cols(cols).unwrap());
     return Box::new(namespaces);
 }
 
 fn read_names(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = GenesisName>> {
-    let names = iter_deflated_csv(deflate_bytes).map(|cols| GenesisName {
-        name: cols[0].to_string(),
-        address: cols[1].to_string(),
-        registered_at: cols[2].parse::<i64>().unwrap(),
-        expire_block: cols[3].parse::<i64>().unwrap(),
-        zonefile_hash: cols[4].to_string(),
-    });
+    let names = iter_deflated_csv(deflate_bytes).map(|cols| GenesisName::from_cols(cols).unwrap());
     return Box::new(names);
 }
 
@@ -154,6 +327,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_balances_total_read() {
+        for use_test_chainstate_data in [false, true].iter() {
+            let genesis_data = GenesisData::new(*use_test_chainstate_data);
+            let mut count = 0;
+            let mut total_ustx = 0;
+            for balance in genesis_data.read_balances() {
+                count += 1;
+                total_ustx += balance.amount as u128;
+            }
+            let expected = genesis_data.read_balances_total();
+            assert_eq!(count, expected.count);
+            assert_eq!(total_ustx, expected.total_ustx);
+        }
+    }
+
     #[test]
     fn test_lockups_read() {
         for lockup in GenesisData::new(false).read_lockups() {
@@ -183,4 +372,54 @@ mod tests {
             assert!(name.registered_at > 0);
         }
     }
+
+    #[test]
+    fn test_custom_genesis_data() {
+        let bundled = GenesisData::new(true);
+        let custom =
+            GenesisData::from_chainstate_text(include_str!("../chainstate-test.txt")).unwrap();
+
+        assert_eq!(
+            custom.read_balances().collect::<Vec<_>>(),
+            bundled.read_balances().collect::<Vec<_>>()
+        );
+        assert_eq!(custom.read_balances_total(), bundled.read_balances_total());
+        assert_eq!(
+            custom.read_lockups().collect::<Vec<_>>(),
+            bundled.read_lockups().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            custom.read_namespaces().collect::<Vec<_>>(),
+            bundled.read_namespaces().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            custom.read_names().collect::<Vec<_>>(),
+            bundled.read_names().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_custom_genesis_data_sections() {
+        let text = "-----BEGIN STX BALANCES-----\n\
+                    address,balance\n\
+                    ST2JHG361ZXG51QTKY2NQCVBPPRRE2KZB1HR05NNC,1000000\n\
+                    -----END STX BALANCES-----\n";
+        let data = GenesisData::from_chainstate_text(text).unwrap();
+        assert_eq!(
+            data.read_balances_total(),
+            GenesisBalancesTotal {
+                count: 1,
+                total_ustx: 1000000
+            }
+        );
+        assert_eq!(data.read_lockups().count(), 0);
+        assert_eq!(data.read_namespaces().count(), 0);
+        assert_eq!(data.read_names().count(), 0);
+
+        // malformed rows and unterminated sections are rejected
+        let bad_amount = text.replace("1000000", "lots");
+        assert!(GenesisData::from_chainstate_text(&bad_amount).is_err());
+        let unterminated = text.replace("-----END STX BALANCES-----", "");
+        assert!(GenesisData::from_chainstate_text(&unterminated).is_err());
+    }
 }
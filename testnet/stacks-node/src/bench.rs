@@ -0,0 +1,309 @@
+use std::cmp;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rand::{thread_rng, Rng};
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::StacksBlockHeader;
+use stacks::core::{EMPTY_MICROBLOCK_PARENT_HASH, FIRST_BURNCHAIN_CONSENSUS_HASH};
+
+use super::burnchains::BitcoinRegtestController;
+use super::config::Config;
+use super::neon_node::TESTNET_CHAIN_ID;
+use super::run_loop::neon::make_chainstate_boot_data;
+
+/// Results of replaying a node's blocks into a scratch chainstate
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchSyncReport {
+    pub num_blocks: u64,
+    pub num_microblocks: u64,
+    pub num_txs: u64,
+    /// Time spent loading blocks and microblocks from the node's chainstate
+    pub load_time: Duration,
+    /// Time spent validating and storing blocks and microblocks as staging data
+    pub preprocess_time: Duration,
+    /// Time spent processing the staged blocks
+    pub process_time: Duration,
+    /// Per-block MARF commit latencies, sorted
+    pub marf_commit_times: Vec<Duration>,
+    pub cpu_user_time: Duration,
+    pub cpu_system_time: Duration,
+}
+
+impl BenchSyncReport {
+    pub fn total_time(&self) -> Duration {
+        self.load_time + self.preprocess_time + self.process_time
+    }
+
+    pub fn blocks_per_sec(&self) -> f64 {
+        let secs = self.total_time().as_secs_f64();
+        if secs > 0.0 {
+            (self.num_blocks as f64) / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// The `pct`th percentile MARF commit latency
+    pub fn marf_commit_percentile(&self, pct: usize) -> Duration {
+        if self.marf_commit_times.len() == 0 {
+            return Duration::default();
+        }
+        let idx = cmp::min(
+            (self.marf_commit_times.len() * pct) / 100,
+            self.marf_commit_times.len() - 1,
+        );
+        self.marf_commit_times[idx]
+    }
+
+    pub fn print(&self) {
+        let to_ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        println!(
+            "Replayed {} blocks ({} microblocks, {} transactions) in {:.3}s: {:.2} blocks/sec",
+            self.num_blocks,
+            self.num_microblocks,
+            self.num_txs,
+            self.total_time().as_secs_f64(),
+            self.blocks_per_sec()
+        );
+        println!(
+            "MARF commit latency (ms): p50 {:.3}, p90 {:.3}, p99 {:.3}, max {:.3}",
+            to_ms(self.marf_commit_percentile(50)),
+            to_ms(self.marf_commit_percentile(90)),
+            to_ms(self.marf_commit_percentile(99)),
+            to_ms(self.marf_commit_percentile(100))
+        );
+        println!(
+            "Wall time (s): load {:.3}, preprocess {:.3}, process {:.3}",
+            self.load_time.as_secs_f64(),
+            self.preprocess_time.as_secs_f64(),
+            self.process_time.as_secs_f64()
+        );
+        println!(
+            "CPU time (s): user {:.3}, system {:.3}",
+            self.cpu_user_time.as_secs_f64(),
+            self.cpu_system_time.as_secs_f64()
+        );
+    }
+}
+
+/// User and system CPU time consumed by this process so far
+fn get_cpu_times() -> (Duration, Duration) {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return (Duration::default(), Duration::default());
+    }
+    let to_duration = |tv: libc::timeval| {
+        Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+    };
+    (to_duration(usage.ru_utime), to_duration(usage.ru_stime))
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> Result<(), String> {
+    fs::create_dir_all(dest).map_err(|e| format!("Failed to create {:?}: {}", dest, e))?;
+    for entry in fs::read_dir(src).map_err(|e| format!("Failed to read {:?}: {}", src, e))? {
+        let entry = entry.map_err(|e| format!("Failed to read {:?}: {}", src, e))?;
+        let path = entry.path();
+        let dest_path = dest.join(entry.file_name());
+        if path.is_dir() {
+            copy_dir(&path, &dest_path)?;
+        } else {
+            fs::copy(&path, &dest_path)
+                .map_err(|e| format!("Failed to copy {:?}: {}", &path, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Replay the first `num_blocks` blocks of the node's canonical Stacks fork -- along with the
+/// microblocks they confirm -- into a fresh chainstate in a scratch directory, timing each step.
+/// The node's own chainstate and sortition DB are only read; block processing runs against a
+/// copy of the sortition DB.  The scratch directory is removed afterwards.
+pub fn bench_sync(conf: &Config, num_blocks: u64) -> Result<BenchSyncReport, String> {
+    let chainstate_path = conf.get_chainstate_path();
+    let burn_db_path = conf.get_burn_db_file_path();
+    if fs::metadata(&chainstate_path).is_err() || fs::metadata(&burn_db_path).is_err() {
+        return Err(format!("No chainstate found in {}", &conf.node.working_dir));
+    }
+
+    let scratch_dir =
+        std::env::temp_dir().join(format!("stacks-bench-sync-{}", thread_rng().gen::<u64>()));
+    let res = bench_sync_into(conf, num_blocks, &scratch_dir);
+    if let Err(e) = fs::remove_dir_all(&scratch_dir) {
+        warn!("Failed to remove {:?}: {}", &scratch_dir, &e);
+    }
+    res
+}
+
+fn bench_sync_into(
+    conf: &Config,
+    num_blocks: u64,
+    scratch_dir: &Path,
+) -> Result<BenchSyncReport, String> {
+    let sortdb = SortitionDB::open(&conf.get_burn_db_file_path(), false)
+        .map_err(|e| format!("Failed to open sortition DB: {}", e))?;
+    let (mut chainstate, _) =
+        StacksChainState::open(false, TESTNET_CHAIN_ID, &conf.get_chainstate_path())
+            .map_err(|e| format!("Failed to open chainstate: {}", e))?;
+
+    let tip = chainstate
+        .get_stacks_chain_tip(&sortdb)
+        .map_err(|e| format!("Failed to load the Stacks chain tip: {}", e))?
+        .ok_or("No Stacks blocks have been processed".to_string())?;
+    let tip_index_hash =
+        StacksBlockHeader::make_index_block_hash(&tip.consensus_hash, &tip.anchored_block_hash);
+    let num_blocks = cmp::min(num_blocks, tip.height);
+
+    // block processing writes to the sortition DB, so run it against a copy
+    let scratch_burn_db_path = scratch_dir.join("sortition.db");
+    copy_dir(
+        Path::new(&conf.get_burn_db_file_path()),
+        &scratch_burn_db_path,
+    )?;
+    let mut scratch_sortdb = SortitionDB::open(
+        &scratch_burn_db_path
+            .to_str()
+            .ok_or("Non-UTF-8 scratch path".to_string())?,
+        true,
+    )
+    .map_err(|e| format!("Failed to open scratch sortition DB: {}", e))?;
+
+    let burnchain_config = BitcoinRegtestController::new_dummy(conf.clone()).get_burnchain();
+    let mut boot_data = make_chainstate_boot_data(conf, &burnchain_config);
+    let scratch_chainstate_path = scratch_dir.join("chainstate");
+    let (mut scratch_chainstate, _) = StacksChainState::open_and_exec(
+        false,
+        TESTNET_CHAIN_ID,
+        &scratch_chainstate_path
+            .to_str()
+            .ok_or("Non-UTF-8 scratch path".to_string())?,
+        Some(&mut boot_data),
+        conf.block_limit.clone(),
+    )
+    .map_err(|e| format!("Failed to boot scratch chainstate: {}", e))?;
+    scratch_chainstate.size_limits = conf.epoch_size_limits.clone();
+
+    let mut report = BenchSyncReport {
+        num_blocks: 0,
+        num_microblocks: 0,
+        num_txs: 0,
+        load_time: Duration::default(),
+        preprocess_time: Duration::default(),
+        process_time: Duration::default(),
+        marf_commit_times: vec![],
+        cpu_user_time: Duration::default(),
+        cpu_system_time: Duration::default(),
+    };
+
+    let (start_user_time, start_system_time) = get_cpu_times();
+    let mut parent_consensus_hash = FIRST_BURNCHAIN_CONSENSUS_HASH.clone();
+    for height in 1..=num_blocks {
+        let load_start = Instant::now();
+        let header = {
+            let mut tx = chainstate
+                .index_tx_begin()
+                .map_err(|e| format!("Failed to begin chainstate transaction: {}", e))?;
+            StacksChainState::get_index_tip_ancestor(&mut tx, &tip_index_hash, height)
+                .map_err(|e| format!("Failed to load block header at height {}: {}", height, e))?
+                .ok_or(format!("No block header at height {}", height))?
+        };
+        let index_block_hash = header.index_block_hash();
+
+        let block = StacksChainState::load_block(
+            &chainstate.blocks_path,
+            &header.consensus_hash,
+            &header.anchored_header.block_hash(),
+        )
+        .map_err(|e| format!("Failed to load block {}: {}", &index_block_hash, e))?
+        .ok_or(format!("Block {} is invalid", &index_block_hash))?;
+
+        let microblocks = if block.header.parent_microblock != EMPTY_MICROBLOCK_PARENT_HASH
+            || block.header.parent_microblock_sequence != 0
+        {
+            StacksChainState::load_processed_microblock_stream_fork(
+                chainstate.db(),
+                &parent_consensus_hash,
+                &block.header.parent_block,
+                &block.header.parent_microblock,
+            )
+            .map_err(|e| format!("Failed to load microblocks of {}: {}", &index_block_hash, e))?
+            .ok_or(format!(
+                "Missing microblocks confirmed by {}",
+                &index_block_hash
+            ))?
+        } else {
+            vec![]
+        };
+        report.load_time += load_start.elapsed();
+
+        let preprocess_start = Instant::now();
+        for microblock in microblocks.iter() {
+            scratch_chainstate
+                .preprocess_streamed_microblock(
+                    &parent_consensus_hash,
+                    &block.header.parent_block,
+                    microblock,
+                )
+                .map_err(|e| {
+                    format!(
+                        "Failed to store microblock {}: {}",
+                        &microblock.block_hash(),
+                        e
+                    )
+                })?;
+        }
+        scratch_chainstate
+            .preprocess_anchored_block(
+                &scratch_sortdb.index_conn(),
+                &header.consensus_hash,
+                &block,
+                &parent_consensus_hash,
+                0,
+            )
+            .map_err(|e| format!("Failed to store block {}: {}", &index_block_hash, e))?;
+        report.preprocess_time += preprocess_start.elapsed();
+
+        let process_start = Instant::now();
+        let receipts = scratch_chainstate
+            .process_blocks(scratch_sortdb.tx_begin_at_tip(), 1)
+            .map_err(|e| format!("Failed to process block {}: {}", &index_block_hash, e))?;
+        report.process_time += process_start.elapsed();
+
+        let epoch_receipt = receipts
+            .into_iter()
+            .find_map(|(epoch_receipt_opt, _)| epoch_receipt_opt)
+            .ok_or(format!("Block {} was not processed", &index_block_hash))?;
+        if epoch_receipt.header.index_block_hash() != index_block_hash {
+            return Err(format!(
+                "Processed block {} instead of {}",
+                &epoch_receipt.header.index_block_hash(),
+                &index_block_hash
+            ));
+        }
+
+        debug!(
+            "Replayed block {} at height {} ({} transactions) in {:?}",
+            &index_block_hash,
+            height,
+            epoch_receipt.tx_receipts.len(),
+            process_start.elapsed()
+        );
+
+        report.num_blocks += 1;
+        report.num_microblocks += microblocks.len() as u64;
+        report.num_txs += epoch_receipt.tx_receipts.len() as u64;
+        report
+            .marf_commit_times
+            .push(epoch_receipt.marf_commit_time);
+        parent_consensus_hash = header.consensus_hash;
+    }
+    let (end_user_time, end_system_time) = get_cpu_times();
+    report.cpu_user_time = end_user_time - start_user_time;
+    report.cpu_system_time = end_system_time - start_system_time;
+    report.marf_commit_times.sort();
+
+    Ok(report)
+}
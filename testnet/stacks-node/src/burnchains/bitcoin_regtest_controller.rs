@@ -12,8 +12,10 @@ use serde_json::value::RawValue;
 
 use std::cmp;
 
+use super::super::config::BurnchainFeeMode;
 use super::super::operations::BurnchainOpSigner;
 use super::super::Config;
+use super::utxo::{UTXOWallet, P2PKH_INPUT_SIZE};
 use super::{BurnchainController, BurnchainTip, Error as BurnchainControllerError};
 
 use stacks::burnchains::bitcoin::address::{BitcoinAddress, BitcoinAddressType};
@@ -28,6 +30,7 @@ use stacks::burnchains::BurnchainStateTransitionOps;
 use stacks::burnchains::Error as burnchain_error;
 use stacks::burnchains::PoxConstants;
 use stacks::burnchains::PublicKey;
+use stacks::burnchains::Txid;
 use stacks::burnchains::{Burnchain, BurnchainParameters};
 use stacks::chainstate::burn::db::sortdb::SortitionDB;
 use stacks::chainstate::burn::operations::{
@@ -59,12 +62,25 @@ pub struct BitcoinRegtestController {
     chain_tip: Option<BurnchainTip>,
     use_coordinator: Option<CoordinatorChannels>,
     burnchain_config: Option<Burnchain>,
-    last_utxos: Vec<UTXO>,
-    last_tx_len: u64,
+    utxo_wallet: UTXOWallet,
+    last_txid: Option<Txid>,
     min_relay_fee: u64, // satoshis/byte
+    /// fee rate (satoshis/byte) of the last transaction built, unless it paid a flat fee
+    last_fee_rate: Option<u64>,
+    /// fee rate of the last block-commit built
+    last_commit_fee_rate: Option<u64>,
 }
 
-const DUST_UTXO_LIMIT: u64 = 5500;
+pub const DUST_UTXO_LIMIT: u64 = 5500;
+
+/// If not configured, warn when the miner can't afford this many more block-commits
+const LOW_FUNDS_COMMITS: u64 = 10;
+
+/// Approximate size of a single-input block-commit, for budgeting its fee before it's built
+const ESTIMATED_COMMIT_TX_LEN: u64 = 350;
+
+/// Size of a p2pkh output, in bytes
+const P2PKH_OUTPUT_SIZE: u64 = 34;
 
 impl BitcoinRegtestController {
     pub fn new(config: Config, coordinator_channel: Option<CoordinatorChannels>) -> Self {
@@ -109,9 +125,14 @@ impl BitcoinRegtestController {
                 spv_headers_path: burnchain_config.spv_headers_path,
                 first_block: burnchain_params.first_block_height,
                 magic_bytes: burnchain_config.magic_bytes,
+                sync_mode: burnchain_config.sync_mode,
+                spv_checkpoints: burnchain_config.spv_checkpoints,
+                spv_download_threads: burnchain_config.spv_download_threads,
             }
         };
 
+        let utxo_wallet = BitcoinRegtestController::open_utxo_wallet(&config);
+
         Self {
             use_coordinator: coordinator_channel,
             config,
@@ -120,9 +141,11 @@ impl BitcoinRegtestController {
             burnchain_db: None,
             chain_tip: None,
             burnchain_config,
-            last_utxos: vec![],
-            last_tx_len: 0,
+            utxo_wallet,
+            last_txid: None,
             min_relay_fee: 1024, // TODO: learn from bitcoind
+            last_fee_rate: None,
+            last_commit_fee_rate: None,
         }
     }
 
@@ -146,9 +169,16 @@ impl BitcoinRegtestController {
                 spv_headers_path: burnchain_config.spv_headers_path,
                 first_block: burnchain_params.first_block_height,
                 magic_bytes: burnchain_config.magic_bytes,
+                sync_mode: burnchain_config.sync_mode,
+                spv_checkpoints: burnchain_config.spv_checkpoints,
+                spv_download_threads: burnchain_config.spv_download_threads,
             }
         };
 
+        // the neon miner submits its burnchain ops through a dummy controller, so it needs the
+        // persistent wallet too
+        let utxo_wallet = BitcoinRegtestController::open_utxo_wallet(&config);
+
         Self {
             use_coordinator: None,
             config,
@@ -157,12 +187,91 @@ impl BitcoinRegtestController {
             burnchain_db: None,
             chain_tip: None,
             burnchain_config: None,
-            last_utxos: vec![],
-            last_tx_len: 0,
+            utxo_wallet,
+            last_txid: None,
             min_relay_fee: 1024, // TODO: learn from bitcoind
+            last_fee_rate: None,
+            last_commit_fee_rate: None,
         }
     }
 
+    fn open_utxo_wallet(config: &Config) -> UTXOWallet {
+        if let Err(e) = std::fs::create_dir_all(&config.node.get_burnchain_path()) {
+            warn!(
+                "Unable to create burnchain workdir for the UTXO wallet: {}",
+                e
+            );
+        }
+        let path = format!("{}/utxo-wallet.json", config.node.get_burnchain_path());
+        let low_funds_threshold = config.burnchain.utxo_low_funds_threshold.unwrap_or(
+            LOW_FUNDS_COMMITS
+                * (config.burnchain.burn_fee_cap + config.burnchain.burnchain_op_tx_fee),
+        );
+        UTXOWallet::open(
+            Some(path),
+            config.burnchain.utxo_max_chains,
+            config.burnchain.utxo_max_dust_inputs,
+            low_funds_threshold,
+        )
+    }
+
+    /// Fee rate (satoshis/byte) to pay for the next transaction, or `None` if it pays the flat
+    /// `burnchain_op_tx_fee`
+    fn get_fee_rate(&self) -> Option<u64> {
+        let burnchain_config = &self.config.burnchain;
+        match burnchain_config.fee_mode {
+            BurnchainFeeMode::Flat => None,
+            BurnchainFeeMode::Static => Some(burnchain_config.satoshis_per_byte),
+            BurnchainFeeMode::Estimate => {
+                let rate = match BitcoinRPCRequest::estimate_smart_fee(
+                    &self.config,
+                    burnchain_config.fee_estimate_target,
+                ) {
+                    Ok(Some(rate)) => rate,
+                    Ok(None) => {
+                        debug!(
+                            "No fee estimate from bitcoind; using {} sats/byte",
+                            burnchain_config.satoshis_per_byte
+                        );
+                        burnchain_config.satoshis_per_byte
+                    }
+                    Err(e) => {
+                        warn!("Bitcoin RPC failure: error estimating fee {:?}", e);
+                        burnchain_config.satoshis_per_byte
+                    }
+                };
+                let rate = match burnchain_config.max_satoshis_per_byte {
+                    Some(max_rate) => cmp::min(rate, max_rate),
+                    None => rate,
+                };
+                Some(cmp::max(rate, 1))
+            }
+        }
+    }
+
+    /// Whether the burnchain fee rate has risen far enough since the last block-commit was built
+    /// that it ought to be replaced with a better-paying one.  Only ever true in `estimate` mode.
+    pub fn commit_fee_rate_rose(&self) -> bool {
+        if self.config.burnchain.fee_mode != BurnchainFeeMode::Estimate {
+            return false;
+        }
+        let last_rate = match self.last_commit_fee_rate {
+            Some(rate) => rate,
+            None => return false,
+        };
+        let rate = match self.get_fee_rate() {
+            Some(rate) => rate,
+            None => return false,
+        };
+        rate > last_rate
+            && rate * 100 >= last_rate * (100 + self.config.burnchain.rbf_fee_increase_percent)
+    }
+
+    /// Txid of the last transaction successfully sent to bitcoind
+    pub fn get_last_txid(&self) -> Option<Txid> {
+        self.last_txid.clone()
+    }
+
     fn default_burnchain(&self) -> Burnchain {
         let (network_name, _network_type) = self.config.burnchain.get_bitcoin_network();
         match &self.burnchain_config {
@@ -366,73 +475,48 @@ impl BitcoinRegtestController {
 
         sleep_ms(1000);
 
-        let min_conf = 0;
-        let max_conf = 9999999;
-        let minimum_amount = ParsedUTXO::sat_to_serialized_btc(1);
-
-        let payload = BitcoinRPCRequest {
-            method: "listunspent".to_string(),
-            params: vec![
-                min_conf.into(),
-                max_conf.into(),
-                filter_addresses.clone().into(),
-                true.into(),
-                json!({ "minimumAmount": minimum_amount }),
-            ],
-            id: "stacks".to_string(),
-            jsonrpc: "2.0".to_string(),
-        };
-
-        let mut res = BitcoinRPCRequest::send(&self.config, payload).unwrap();
-        let mut result_vec = vec![];
-
-        if let Some(ref mut object) = res.as_object_mut() {
-            match object.get_mut("result") {
-                Some(serde_json::Value::Array(entries)) => {
-                    while let Some(entry) = entries.pop() {
-                        let parsed_utxo: ParsedUTXO = match serde_json::from_value(entry) {
-                            Ok(utxo) => utxo,
-                            Err(err) => {
-                                warn!("Failed parsing UTXO: {}", err);
-                                continue;
-                            }
-                        };
-                        let amount = match parsed_utxo.get_sat_amount() {
-                            Some(amount) => amount,
-                            None => continue,
-                        };
-
-                        if amount < 1 {
-                            continue;
-                        }
-
-                        let script_pub_key = match parsed_utxo.get_script_pub_key() {
-                            Some(script_pub_key) => script_pub_key,
-                            None => {
-                                continue;
-                            }
-                        };
+        BitcoinRPCRequest::list_all_unspent(&self.config, filter_addresses, true).unwrap()
+    }
 
-                        let txid = match parsed_utxo.get_txid() {
-                            Some(amount) => amount,
-                            None => continue,
-                        };
+    /// Reload the outputs the miner can spend from bitcoind into the UTXO wallet
+    fn refresh_utxo_wallet(&mut self, public_key: &Secp256k1PublicKey) {
+        let pkh = Hash160::from_data(&public_key.to_bytes())
+            .to_bytes()
+            .to_vec();
+        let (_, network_id) = self.config.burnchain.get_bitcoin_network();
+        let address =
+            BitcoinAddress::from_bytes(network_id, BitcoinAddressType::PublicKeyHash, &pkh)
+                .expect("Public key incorrect");
+        let filter_addresses = vec![address.to_b58()];
 
-                        result_vec.push(UTXO {
-                            txid,
-                            vout: parsed_utxo.vout,
-                            script_pub_key,
-                            amount,
-                        });
-                    }
+        let mut imported = false;
+        let utxos = loop {
+            let utxos = match BitcoinRPCRequest::list_all_unspent(
+                &self.config,
+                filter_addresses.clone(),
+                false,
+            ) {
+                Ok(utxos) => utxos,
+                Err(e) => {
+                    error!("Bitcoin RPC failure: error listing utxos {:?}", e);
+                    sleep_ms(5000);
+                    continue;
                 }
-                _ => {
-                    warn!("Failed to get UTXOs");
+            };
+
+            if utxos.len() == 0 && !imported {
+                // as in get_utxos(), only import the address on regtest
+                if let BitcoinNetworkType::Regtest = network_id {
+                    let _result = BitcoinRPCRequest::import_public_key(&self.config, &public_key);
+                    sleep_ms(1000);
+                    imported = true;
+                    continue;
                 }
             }
-        }
+            break utxos;
+        };
 
-        result_vec
+        self.utxo_wallet.set_spendable(utxos);
     }
 
     pub fn get_utxos(
@@ -531,7 +615,8 @@ impl BitcoinRegtestController {
     ) -> Option<Transaction> {
         let public_key = signer.get_public_key();
 
-        let (mut tx, utxos) = self.prepare_tx(&public_key, DUST_UTXO_LIMIT, attempt)?;
+        let (mut tx, utxos) =
+            self.prepare_tx(&public_key, "leader_key_register", DUST_UTXO_LIMIT, attempt)?;
 
         // Serialize the payload
         let op_bytes = {
@@ -559,7 +644,15 @@ impl BitcoinRegtestController {
 
         tx.output.push(identifier_output);
 
-        self.finalize_tx(&mut tx, DUST_UTXO_LIMIT, utxos, signer, attempt)?;
+        self.finalize_tx(
+            &mut tx,
+            "leader_key_register",
+            DUST_UTXO_LIMIT,
+            utxos,
+            signer,
+            attempt,
+            None,
+        )?;
 
         increment_btc_ops_sent_counter();
 
@@ -638,7 +731,7 @@ impl BitcoinRegtestController {
                 vec![utxo],
             )
         } else {
-            self.prepare_tx(&public_key, DUST_UTXO_LIMIT, 1)?
+            self.prepare_tx(&public_key, "transfer_stx", DUST_UTXO_LIMIT, 1)?
         };
 
         // Serialize the payload
@@ -660,7 +753,15 @@ impl BitcoinRegtestController {
         tx.output
             .push(payload.recipient.to_bitcoin_tx_out(DUST_UTXO_LIMIT));
 
-        self.finalize_tx(&mut tx, DUST_UTXO_LIMIT, utxos, signer, 1)?;
+        self.finalize_tx(
+            &mut tx,
+            "transfer_stx",
+            DUST_UTXO_LIMIT,
+            utxos,
+            signer,
+            1,
+            None,
+        )?;
 
         increment_btc_ops_sent_counter();
 
@@ -690,7 +791,7 @@ impl BitcoinRegtestController {
         let public_key = signer.get_public_key();
 
         let output_amt = 2 * (self.config.burnchain.burnchain_op_tx_fee + DUST_UTXO_LIMIT);
-        let (mut tx, utxos) = self.prepare_tx(&public_key, output_amt, 1)?;
+        let (mut tx, utxos) = self.prepare_tx(&public_key, "pre_stx", output_amt, 1)?;
 
         // Serialize the payload
         let op_bytes = {
@@ -710,7 +811,7 @@ impl BitcoinRegtestController {
         tx.output = vec![consensus_output];
         tx.output.push(payload.output.to_bitcoin_tx_out(output_amt));
 
-        self.finalize_tx(&mut tx, output_amt, utxos, signer, 1)?;
+        self.finalize_tx(&mut tx, "pre_stx", output_amt, utxos, signer, 1, None)?;
 
         increment_btc_ops_sent_counter();
 
@@ -730,7 +831,18 @@ impl BitcoinRegtestController {
     ) -> Option<Transaction> {
         let public_key = signer.get_public_key();
 
-        let (mut tx, utxos) = self.prepare_tx(&public_key, payload.burn_fee, attempt)?;
+        // commits building off of different parents can be in flight at the same time, each
+        // RBF'ing its own transactions
+        let chain_id = if self.config.burnchain.utxo_max_chains > 1 {
+            format!(
+                "block_commit:{}:{}",
+                payload.parent_block_ptr, payload.parent_vtxindex
+            )
+        } else {
+            "block_commit".to_string()
+        };
+
+        let (mut tx, utxos) = self.prepare_tx(&public_key, &chain_id, payload.burn_fee, attempt)?;
 
         // Serialize the payload
         let op_bytes = {
@@ -769,14 +881,25 @@ impl BitcoinRegtestController {
                 .push(commit_to.to_bitcoin_tx_out(value_per_transfer));
         }
 
+        // whatever the budget leaves over after the commitment itself can go to fees
+        let max_fee = self
+            .config
+            .burnchain
+            .max_burn_per_block
+            .map(|max_burn| max_burn.saturating_sub(payload.burn_fee + sunset_burn));
+
         self.finalize_tx(
             &mut tx,
+            &chain_id,
             payload.burn_fee + sunset_burn,
             utxos,
             signer,
             attempt,
+            max_fee,
         )?;
 
+        self.last_commit_fee_rate = self.last_fee_rate;
+
         increment_btc_ops_sent_counter();
 
         info!(
@@ -788,30 +911,42 @@ impl BitcoinRegtestController {
         Some(tx)
     }
 
+    /// Fee to cover one more p2pkh input at the minimum relay fee
+    fn input_fee(&self) -> u64 {
+        (P2PKH_INPUT_SIZE * self.min_relay_fee) / 1000
+    }
+
     fn prepare_tx(
         &mut self,
         public_key: &Secp256k1PublicKey,
+        chain_id: &str,
         ops_fee: u64,
         attempt: u64,
     ) -> Option<(Transaction, Vec<UTXO>)> {
-        let tx_fee = self.config.burnchain.burnchain_op_tx_fee;
+        let tx_fee = match self.get_fee_rate() {
+            Some(rate) => rate * ESTIMATED_COMMIT_TX_LEN,
+            None => self.config.burnchain.burnchain_op_tx_fee,
+        };
         let amount_required = tx_fee + ops_fee;
 
-        let utxos = if attempt > 1 && self.last_utxos.len() > 0 {
+        let utxos = match self.utxo_wallet.get_chain(chain_id) {
             // in RBF, you have to consume the same UTXOs
-            self.last_utxos.clone()
-        } else {
-            // Fetch some UTXOs
-            let new_utxos = match self.get_utxos(&public_key, amount_required) {
-                Some(utxos) => utxos,
-                None => {
-                    debug!("No UTXOs for {}", &public_key.to_hex());
-                    return None;
+            Some(chain) if attempt > 1 && chain.utxos.len() > 0 => chain.utxos.clone(),
+            _ => {
+                // Fetch some UTXOs
+                self.refresh_utxo_wallet(public_key);
+                let input_fee = self.input_fee();
+                match self
+                    .utxo_wallet
+                    .reserve(chain_id, amount_required, input_fee)
+                {
+                    Some(utxos) => utxos,
+                    None => {
+                        debug!("No UTXOs for {}", &public_key.to_hex());
+                        return None;
+                    }
                 }
-            };
-            self.last_utxos = new_utxos.clone();
-            self.last_tx_len = 0;
-            new_utxos
+            }
         };
 
         // Prepare a backbone for the tx
@@ -825,35 +960,63 @@ impl BitcoinRegtestController {
         Some((transaction, utxos))
     }
 
+    /// Add the change output to `tx`, then sign it.  Returns the fee it pays, or `None` if it
+    /// can't be funded (or would pay more than `max_fee`).
     fn finalize_tx(
         &mut self,
         tx: &mut Transaction,
+        chain_id: &str,
         total_spent: u64,
-        mut utxos: Vec<UTXO>,
+        utxos_consumed: Vec<UTXO>,
         signer: &mut BurnchainOpSigner,
         attempt: u64,
-    ) -> Option<()> {
-        // spend UTXOs in decreasing order
-        utxos.sort_by(|u1, u2| u1.amount.cmp(&u2.amount));
-        utxos.reverse();
+        max_fee: Option<u64>,
+    ) -> Option<u64> {
+        let (last_tx_len, last_fee) = self
+            .utxo_wallet
+            .get_chain(chain_id)
+            .map(|chain| (chain.last_tx_len, chain.last_fee))
+            .unwrap_or((0, 0));
+
+        let fee_rate = self.get_fee_rate();
+        let tx_fee = match fee_rate {
+            None => {
+                // RBF, plus the cost of any inputs beyond the first (e.g. consolidated dust)
+                self.config.burnchain.burnchain_op_tx_fee
+                    + ((attempt.saturating_sub(1) * last_tx_len * self.min_relay_fee) / 1000)
+                    + (utxos_consumed.len().saturating_sub(1) as u64) * self.input_fee()
+            }
+            Some(rate) => {
+                // the outputs so far, plus the change output and the signed inputs
+                let tx_len = SerializedTx::new(tx.clone()).bytes.len() as u64
+                    + P2PKH_OUTPUT_SIZE
+                    + (utxos_consumed.len() as u64) * P2PKH_INPUT_SIZE;
+                let fee = rate * tx_len;
+                if attempt > 1 && last_fee > 0 {
+                    // RBF: the replacement has to pay for its own relay on top of what the
+                    // transaction it replaces paid
+                    cmp::max(fee, last_fee + (tx_len * self.min_relay_fee) / 1000)
+                } else {
+                    fee
+                }
+            }
+        };
 
-        // RBF
-        let tx_fee = self.config.burnchain.burnchain_op_tx_fee
-            + ((attempt.saturating_sub(1) * self.last_tx_len * self.min_relay_fee) / 1000);
+        if let Some(max_fee) = max_fee {
+            if tx_fee > max_fee {
+                warn!(
+                    "Transaction fee {} exceeds the {} left in the burn budget; not sending it",
+                    tx_fee, max_fee
+                );
+                return None;
+            }
+        }
+        self.last_fee_rate = fee_rate;
 
         let public_key = signer.get_public_key();
-        let mut total_consumed = 0;
 
-        // select UTXOs until we have enough to cover the cost
-        let mut utxos_consumed = vec![];
-        for utxo in utxos.into_iter() {
-            total_consumed += utxo.amount;
-            utxos_consumed.push(utxo);
-
-            if total_consumed >= total_spent + tx_fee {
-                break;
-            }
-        }
+        // the UTXO wallet already selected enough to cover the cost
+        let total_consumed: u64 = utxos_consumed.iter().map(|utxo| utxo.amount).sum();
 
         // Append the change output
         let change_address_hash = Hash160::from_data(&public_key.to_bytes());
@@ -913,9 +1076,10 @@ impl BitcoinRegtestController {
         let tx_bytes = SerializedTx::new(tx.clone());
         debug!("Send transaction: {:?}", tx_bytes.to_hex());
 
-        self.last_tx_len = tx_bytes.bytes.len() as u64;
+        self.utxo_wallet
+            .set_last_tx(chain_id, tx_bytes.bytes.len() as u64, tx_fee);
 
-        Some(())
+        Some(tx_fee)
     }
 
     fn build_user_burn_support_tx(
@@ -1140,12 +1304,20 @@ impl BurnchainController for BitcoinRegtestController {
             BlockstackOperationType::StackStx(_payload) => unimplemented!(),
         };
 
-        let transaction = match transaction {
-            Some(tx) => SerializedTx::new(tx),
+        let (transaction, txid) = match transaction {
+            Some(tx) => {
+                let txid = Txid::from_vec_be(&tx.txid().as_bytes().to_vec())
+                    .expect("BUG: bitcoin txid is not 32 bytes");
+                (SerializedTx::new(tx), txid)
+            }
             _ => return false,
         };
 
-        self.send_transaction(transaction)
+        let sent = self.send_transaction(transaction);
+        if sent {
+            self.last_txid = Some(txid);
+        }
+        sent
     }
 
     #[cfg(test)]
@@ -1212,7 +1384,7 @@ pub struct ParsedUTXO {
     safe: bool,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct UTXO {
     pub txid: Sha256dHash,
     pub vout: u32,
@@ -1343,6 +1515,102 @@ impl BitcoinRPCRequest {
         Ok(())
     }
 
+    /// Ask bitcoind for the fee rate (satoshis/byte) that gets a transaction confirmed within
+    /// `conf_target` blocks.  `None` if it doesn't have enough data to say.
+    pub fn estimate_smart_fee(config: &Config, conf_target: u64) -> RPCResult<Option<u64>> {
+        let payload = BitcoinRPCRequest {
+            method: "estimatesmartfee".to_string(),
+            params: vec![conf_target.into()],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let res = BitcoinRPCRequest::send(&config, payload)?;
+
+        // the fee rate is in BTC/kB
+        let rate = res
+            .get("result")
+            .and_then(|result| result.get("feerate"))
+            .and_then(|feerate| ParsedUTXO::serialized_btc_to_sat(&feerate.to_string()))
+            .map(|sats_per_kb| (sats_per_kb + 999) / 1000);
+        Ok(rate)
+    }
+
+    /// List every output (of at least 1 satoshi) held by the given addresses
+    pub fn list_all_unspent(
+        config: &Config,
+        addresses: Vec<String>,
+        include_unsafe: bool,
+    ) -> RPCResult<Vec<UTXO>> {
+        let min_conf = 0;
+        let max_conf = 9999999;
+        let minimum_amount = ParsedUTXO::sat_to_serialized_btc(1);
+
+        let payload = BitcoinRPCRequest {
+            method: "listunspent".to_string(),
+            params: vec![
+                min_conf.into(),
+                max_conf.into(),
+                addresses.into(),
+                include_unsafe.into(),
+                json!({ "minimumAmount": minimum_amount }),
+            ],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let mut res = BitcoinRPCRequest::send(&config, payload)?;
+        let mut result_vec = vec![];
+
+        if let Some(ref mut object) = res.as_object_mut() {
+            match object.get_mut("result") {
+                Some(serde_json::Value::Array(entries)) => {
+                    while let Some(entry) = entries.pop() {
+                        let parsed_utxo: ParsedUTXO = match serde_json::from_value(entry) {
+                            Ok(utxo) => utxo,
+                            Err(err) => {
+                                warn!("Failed parsing UTXO: {}", err);
+                                continue;
+                            }
+                        };
+                        let amount = match parsed_utxo.get_sat_amount() {
+                            Some(amount) => amount,
+                            None => continue,
+                        };
+
+                        if amount < 1 {
+                            continue;
+                        }
+
+                        let script_pub_key = match parsed_utxo.get_script_pub_key() {
+                            Some(script_pub_key) => script_pub_key,
+                            None => {
+                                continue;
+                            }
+                        };
+
+                        let txid = match parsed_utxo.get_txid() {
+                            Some(amount) => amount,
+                            None => continue,
+                        };
+
+                        result_vec.push(UTXO {
+                            txid,
+                            vout: parsed_utxo.vout,
+                            script_pub_key,
+                            amount,
+                        });
+                    }
+                }
+                _ => {
+                    warn!("Failed to get UTXOs");
+                }
+            }
+        }
+
+        Ok(result_vec)
+    }
+
     pub fn list_unspent(
         config: &Config,
         addresses: Vec<String>,
@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
-use std::time::Instant;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
 use super::super::operations::BurnchainOpSigner;
 use super::super::Config;
@@ -16,16 +17,156 @@ use stacks::chainstate::burn::operations::{
     TransferStxOp, UserBurnSupportOp,
 };
 use stacks::chainstate::burn::BlockSnapshot;
+use stacks::net::RPCDevMineRequest;
 use stacks::util::get_epoch_time_secs;
 use stacks::util::hash::Sha256Sum;
 
+/// A request for the next dev burnchain block
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevBlockRequest {
+    /// if false, the block leaves out the queued block-commits, so that it has no sortition winner
+    pub sortition: bool,
+    /// mempool transactions that the Stacks block committed to in it should include
+    pub txids: Vec<Txid>,
+}
+
+impl Default for DevBlockRequest {
+    fn default() -> DevBlockRequest {
+        DevBlockRequest {
+            sortition: true,
+            txids: vec![],
+        }
+    }
+}
+
+impl From<RPCDevMineRequest> for DevBlockRequest {
+    fn from(request: RPCDevMineRequest) -> DevBlockRequest {
+        DevBlockRequest {
+            sortition: request.sortition.unwrap_or(true),
+            // already validated by the RPC handler
+            txids: request
+                .txids
+                .iter()
+                .filter_map(|txid| Txid::from_hex(txid).ok())
+                .collect(),
+        }
+    }
+}
+
+struct DevBlockState {
+    /// requested blocks, oldest first
+    requests: VecDeque<DevBlockRequest>,
+    /// set once the run loop has taken a request and committed to a Stacks block for it: the
+    /// next block is mined right away, with or without a sortition
+    ready: Option<bool>,
+}
+
+/// Lets other threads ask a dev-mode `MocknetController` to mine its next burnchain block right
+/// away, instead of waiting for `burnchain.dev_block_time_ms` to elapse.
+///
+/// The helium run loop takes each request before it assembles the Stacks block for it (so the
+/// block includes whatever the mempool holds by the time of the request), and then releases the
+/// burnchain block.  Requests made while the run loop isn't mining go straight to the controller.
+#[derive(Clone)]
+pub struct DevBlockTrigger {
+    state: Arc<(Mutex<DevBlockState>, Condvar)>,
+}
+
+impl DevBlockTrigger {
+    pub fn new() -> DevBlockTrigger {
+        let state = DevBlockState {
+            requests: VecDeque::new(),
+            ready: None,
+        };
+        DevBlockTrigger {
+            state: Arc::new((Mutex::new(state), Condvar::new())),
+        }
+    }
+
+    /// Mine the next burnchain block now.  If `sortition` is false, the block leaves out the
+    /// queued block-commits, so that it has no sortition winner.
+    pub fn mine_block(&self, sortition: bool) {
+        self.request(DevBlockRequest {
+            sortition,
+            txids: vec![],
+        })
+    }
+
+    /// Mine the next burnchain block now, as described by `request`.
+    pub fn request(&self, request: DevBlockRequest) {
+        let (ref state, ref cvar) = *self.state;
+        state
+            .lock()
+            .expect("FATAL: dev block trigger lock poisoned")
+            .requests
+            .push_back(request);
+        cvar.notify_all();
+    }
+
+    /// Wait until `take` gets something out of the state, or until `deadline`.
+    fn wait<T, F>(&self, deadline: Instant, mut take: F) -> Option<T>
+    where
+        F: FnMut(&mut DevBlockState) -> Option<T>,
+    {
+        let (ref state, ref cvar) = *self.state;
+        let mut state = state
+            .lock()
+            .expect("FATAL: dev block trigger lock poisoned");
+        loop {
+            if let Some(taken) = take(&mut state) {
+                return Some(taken);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            state = cvar
+                .wait_timeout(state, deadline - now)
+                .expect("FATAL: dev block trigger lock poisoned")
+                .0;
+        }
+    }
+
+    /// Wait until a block is requested, or until `deadline`, and take the request.
+    /// Returns None if nothing was requested in time.
+    pub fn wait_for_request(&self, deadline: Instant) -> Option<DevBlockRequest> {
+        self.wait(deadline, |state| state.requests.pop_front())
+    }
+
+    /// Have the controller mine its next block right away.  Called once the run loop has
+    /// committed to a Stacks block for a request it took with `wait_for_request()`.
+    pub fn release(&self, sortition: bool) {
+        let (ref state, ref cvar) = *self.state;
+        state
+            .lock()
+            .expect("FATAL: dev block trigger lock poisoned")
+            .ready = Some(sortition);
+        cvar.notify_all();
+    }
+
+    /// Wait until the next block is released or requested, or until `deadline`.  Returns whether
+    /// the block should have a sortition, or None if nothing happened in time.
+    fn wait_until(&self, deadline: Instant) -> Option<bool> {
+        self.wait(deadline, |state| match state.ready.take() {
+            Some(sortition) => Some(sortition),
+            None => state.requests.pop_front().map(|request| request.sortition),
+        })
+    }
+}
+
 /// MocknetController is simulating a simplistic burnchain.
+///
+/// In `mocknet` mode, it mines a block as soon as the node syncs.  In `dev` mode, it mines one
+/// every `burnchain.dev_block_time_ms` (or sooner, if asked to through its `DevBlockTrigger`),
+/// and leaves the node's block-commits out of the blocks listed in
+/// `burnchain.dev_missed_sortitions` so that they have no sortition.
 pub struct MocknetController {
     config: Config,
     burnchain: Burnchain,
     db: Option<SortitionDB>,
     chain_tip: Option<BurnchainTip>,
     queued_operations: VecDeque<BlockstackOperationType>,
+    dev_block_trigger: Option<DevBlockTrigger>,
 }
 
 impl MocknetController {
@@ -33,6 +174,12 @@ impl MocknetController {
         Box::new(Self::new(config))
     }
 
+    pub fn dev(config: Config, block_trigger: DevBlockTrigger) -> Box<dyn BurnchainController> {
+        let mut controller = Self::new(config);
+        controller.dev_block_trigger = Some(block_trigger);
+        Box::new(controller)
+    }
+
     fn new(config: Config) -> Self {
         debug!("Opening Burnchain at {}", &config.get_burn_db_path());
         let burnchain = Burnchain::regtest(&config.get_burn_db_path());
@@ -43,9 +190,31 @@ impl MocknetController {
             db: None,
             queued_operations: VecDeque::new(),
             chain_tip: None,
+            dev_block_trigger: None,
         }
     }
 
+    /// In dev mode, wait until it is time to mine the block after `chain_tip` (or until one is
+    /// requested), and decide whether that block gets a sortition.
+    fn wait_for_dev_block(&self, chain_tip: &BurnchainTip) -> bool {
+        let block_trigger = match self.dev_block_trigger {
+            Some(ref block_trigger) => block_trigger,
+            None => return true,
+        };
+
+        let block_height = chain_tip.block_snapshot.block_height + 1;
+        let deadline =
+            chain_tip.received_at + Duration::from_millis(self.config.burnchain.dev_block_time_ms);
+        let requested_sortition = block_trigger.wait_until(deadline).unwrap_or(true);
+
+        requested_sortition
+            && !self
+                .config
+                .burnchain
+                .dev_missed_sortitions
+                .contains(&block_height)
+    }
+
     fn build_next_block_header(current_block: &BlockSnapshot) -> BurnchainBlockHeader {
         let curr_hash = &current_block.burn_header_hash.to_bytes()[..];
         let next_hash = Sha256Sum::from_data(&curr_hash);
@@ -130,11 +299,21 @@ impl BurnchainController for MocknetController {
         let chain_tip = self.get_chain_tip();
 
         // Simulating mining
+        let sortition = self.wait_for_dev_block(&chain_tip);
         let next_block_header = Self::build_next_block_header(&chain_tip.block_snapshot);
         let mut vtxindex = 1;
         let mut ops = vec![];
 
         while let Some(payload) = self.queued_operations.pop_front() {
+            if let BlockstackOperationType::LeaderBlockCommit(ref payload) = payload {
+                if !sortition {
+                    info!(
+                        "Leaving block-commit for {} out of burnchain block {}, so it has no sortition",
+                        &payload.block_header_hash, next_block_header.block_height
+                    );
+                    continue;
+                }
+            }
             let txid = Txid(
                 Sha256Sum::from_data(
                     format!("{}::{}", next_block_header.block_height, vtxindex).as_bytes(),
@@ -1,8 +1,9 @@
 pub mod bitcoin_regtest_controller;
 pub mod mocknet_controller;
+pub mod utxo;
 
 pub use self::bitcoin_regtest_controller::BitcoinRegtestController;
-pub use self::mocknet_controller::MocknetController;
+pub use self::mocknet_controller::{DevBlockRequest, DevBlockTrigger, MocknetController};
 
 use super::operations::BurnchainOpSigner;
 
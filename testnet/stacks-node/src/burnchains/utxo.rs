@@ -0,0 +1,463 @@
+//! The miner's UTXO wallet: the set of outputs it can spend on burnchain operations, and which
+//! of them are tied up in operations that are still in flight.
+//!
+//! Every burnchain transaction the miner sends belongs to a *chain*: the transaction and all of
+//! the RBF replacements that follow it (e.g. successive block-commits for the same sortition).
+//! A chain reserves the outputs its first transaction spent, so that its replacements spend the
+//! same outputs (which RBF requires) and no other chain tries to spend them in the meantime.
+//! Up to `max_chains` chains can hold outputs at once; starting one more releases the outputs of
+//! the least-recently-used chain.
+//!
+//! Outputs too small to fund an operation on their own are swept into the change of the next
+//! transaction, a few at a time, as long as they are worth more than the fee to spend them.
+//!
+//! The wallet is stored as JSON next to the burnchain state, so a restarted miner still knows
+//! which outputs its in-flight transactions spent.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use stacks::deps::bitcoin::blockdata::script::Script;
+use stacks::deps::bitcoin::util::hash::Sha256dHash;
+use stacks::util::hash::{hex_bytes, to_hex};
+
+use super::bitcoin_regtest_controller::{DUST_UTXO_LIMIT, UTXO};
+
+/// Approximate size of a signed p2pkh input, in bytes
+pub const P2PKH_INPUT_SIZE: u64 = 148;
+
+/// Outputs a chain has reserved, and what it needs to know to replace its last transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UTXOChain {
+    pub utxos: Vec<UTXO>,
+    /// length of the last transaction this chain sent, for computing the RBF fee bump
+    pub last_tx_len: u64,
+    /// fee the last transaction paid, which a replacement has to outbid
+    pub last_fee: u64,
+    /// when this chain was last used, relative to the others
+    last_used: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StoredUTXO {
+    txid: String,
+    vout: u32,
+    script_pub_key: String,
+    amount: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StoredChain {
+    utxos: Vec<StoredUTXO>,
+    last_tx_len: u64,
+    #[serde(default)]
+    last_fee: u64,
+    last_used: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+struct StoredWallet {
+    spendable: Vec<StoredUTXO>,
+    chains: BTreeMap<String, StoredChain>,
+}
+
+impl StoredUTXO {
+    fn from_utxo(utxo: &UTXO) -> StoredUTXO {
+        StoredUTXO {
+            txid: to_hex(utxo.txid.as_bytes()),
+            vout: utxo.vout,
+            script_pub_key: to_hex(utxo.script_pub_key.as_bytes()),
+            amount: utxo.amount,
+        }
+    }
+
+    fn to_utxo(&self) -> Option<UTXO> {
+        let txid_bytes = hex_bytes(&self.txid).ok()?;
+        if txid_bytes.len() != 32 {
+            return None;
+        }
+        let script_pub_key: Script = hex_bytes(&self.script_pub_key).ok()?.into();
+        Some(UTXO {
+            txid: Sha256dHash::from(&txid_bytes[..]),
+            vout: self.vout,
+            script_pub_key,
+            amount: self.amount,
+        })
+    }
+}
+
+pub struct UTXOWallet {
+    /// where the wallet is persisted; `None` keeps it in memory only
+    path: Option<String>,
+    /// outputs bitcoind last reported as spendable
+    spendable: Vec<UTXO>,
+    chains: BTreeMap<String, UTXOChain>,
+    max_chains: usize,
+    max_dust_inputs: usize,
+    low_funds_threshold: u64,
+}
+
+impl UTXOWallet {
+    /// Open the wallet stored at `path`, or start an empty one if there is none (or it can't
+    /// be read).
+    pub fn open(
+        path: Option<String>,
+        max_chains: usize,
+        max_dust_inputs: usize,
+        low_funds_threshold: u64,
+    ) -> UTXOWallet {
+        let stored = match path {
+            Some(ref path) => match fs::read_to_string(path) {
+                Ok(contents) => match serde_json::from_str::<StoredWallet>(&contents) {
+                    Ok(stored) => stored,
+                    Err(e) => {
+                        warn!("Failed to parse UTXO wallet {}, starting over: {}", path, e);
+                        StoredWallet::default()
+                    }
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => StoredWallet::default(),
+                Err(e) => {
+                    warn!("Failed to read UTXO wallet {}, starting over: {}", path, e);
+                    StoredWallet::default()
+                }
+            },
+            None => StoredWallet::default(),
+        };
+
+        let spendable = stored
+            .spendable
+            .iter()
+            .filter_map(|utxo| utxo.to_utxo())
+            .collect();
+        let chains = stored
+            .chains
+            .into_iter()
+            .map(|(chain_id, chain)| {
+                let utxos = chain
+                    .utxos
+                    .iter()
+                    .filter_map(|utxo| utxo.to_utxo())
+                    .collect();
+                (
+                    chain_id,
+                    UTXOChain {
+                        utxos,
+                        last_tx_len: chain.last_tx_len,
+                        last_fee: chain.last_fee,
+                        last_used: chain.last_used,
+                    },
+                )
+            })
+            .collect();
+
+        UTXOWallet {
+            path,
+            spendable,
+            chains,
+            max_chains: max_chains.max(1),
+            max_dust_inputs,
+            low_funds_threshold,
+        }
+    }
+
+    fn save(&self) {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return,
+        };
+        let stored = StoredWallet {
+            spendable: self.spendable.iter().map(StoredUTXO::from_utxo).collect(),
+            chains: self
+                .chains
+                .iter()
+                .map(|(chain_id, chain)| {
+                    (
+                        chain_id.clone(),
+                        StoredChain {
+                            utxos: chain.utxos.iter().map(StoredUTXO::from_utxo).collect(),
+                            last_tx_len: chain.last_tx_len,
+                            last_fee: chain.last_fee,
+                            last_used: chain.last_used,
+                        },
+                    )
+                })
+                .collect(),
+        };
+        let contents = serde_json::to_string(&stored).expect("FATAL: failed to encode wallet");
+
+        // write to a temporary file and rename it over, so a crash can't leave a torn wallet
+        let tmp_path = format!("{}.tmp", path);
+        if let Err(e) = fs::write(&tmp_path, contents).and_then(|_| fs::rename(&tmp_path, path)) {
+            warn!("Failed to store UTXO wallet {}: {}", path, e);
+        }
+    }
+
+    fn is_reserved(&self, utxo: &UTXO) -> bool {
+        self.chains.values().any(|chain| {
+            chain
+                .utxos
+                .iter()
+                .any(|reserved| reserved.txid == utxo.txid && reserved.vout == utxo.vout)
+        })
+    }
+
+    /// Replace the set of spendable outputs with what bitcoind currently reports.  Returns
+    /// `false` (and logs a warning) if the outputs not reserved by any chain add up to less than
+    /// the low-funds threshold.
+    pub fn set_spendable(&mut self, utxos: Vec<UTXO>) -> bool {
+        self.spendable = utxos;
+        self.save();
+
+        let available = self.get_available_balance();
+        if available < self.low_funds_threshold {
+            warn!(
+                "Miner is low on burnchain funds: {} sats available, {} sats recommended",
+                available, self.low_funds_threshold
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Total value of the spendable outputs no chain has reserved
+    pub fn get_available_balance(&self) -> u64 {
+        self.spendable
+            .iter()
+            .filter(|utxo| !self.is_reserved(utxo))
+            .map(|utxo| utxo.amount)
+            .sum()
+    }
+
+    pub fn get_chain(&self, chain_id: &str) -> Option<&UTXOChain> {
+        self.chains.get(chain_id)
+    }
+
+    /// Start (or restart) a chain: release whatever it held before, then reserve enough
+    /// unreserved outputs to cover `amount_required`, largest first.  Every input after the first
+    /// costs another `input_fee`.  Dust outputs worth more than `input_fee` are added on top, up
+    /// to the consolidation limit.  Returns `None`, reserving nothing, if the available outputs
+    /// don't cover `amount_required`.
+    pub fn reserve(
+        &mut self,
+        chain_id: &str,
+        amount_required: u64,
+        input_fee: u64,
+    ) -> Option<Vec<UTXO>> {
+        self.chains.remove(chain_id);
+
+        let mut candidates: Vec<UTXO> = self
+            .spendable
+            .iter()
+            .filter(|utxo| !self.is_reserved(utxo))
+            .cloned()
+            .collect();
+        candidates.sort_by(|u1, u2| u2.amount.cmp(&u1.amount));
+
+        let (mut dust, funding): (Vec<UTXO>, Vec<UTXO>) = candidates
+            .into_iter()
+            .partition(|utxo| utxo.amount < DUST_UTXO_LIMIT);
+
+        let mut selected = vec![];
+        let mut total = 0;
+        let required =
+            |num_inputs: usize| amount_required + (num_inputs.saturating_sub(1) as u64) * input_fee;
+        for utxo in funding.into_iter() {
+            if selected.len() > 0 && total >= required(selected.len()) {
+                break;
+            }
+            total += utxo.amount;
+            selected.push(utxo);
+        }
+        if selected.len() == 0 || total < required(selected.len()) {
+            debug!(
+                "Available outputs cover {} of the {} sats required",
+                total,
+                required(selected.len())
+            );
+            return None;
+        }
+
+        dust.retain(|utxo| utxo.amount > input_fee);
+        dust.truncate(self.max_dust_inputs);
+        if dust.len() > 0 {
+            debug!("Consolidating {} dust outputs into change", dust.len());
+        }
+        selected.append(&mut dust);
+
+        // make room for the new chain
+        while self.chains.len() >= self.max_chains {
+            let oldest = self
+                .chains
+                .iter()
+                .min_by_key(|(_, chain)| chain.last_used)
+                .map(|(chain_id, _)| chain_id.clone())
+                .expect("BUG: no chains to evict");
+            debug!("Releasing UTXOs of chain {}", &oldest);
+            self.chains.remove(&oldest);
+        }
+
+        let last_used = self.next_use();
+        self.chains.insert(
+            chain_id.to_string(),
+            UTXOChain {
+                utxos: selected.clone(),
+                last_tx_len: 0,
+                last_fee: 0,
+                last_used,
+            },
+        );
+        self.save();
+        Some(selected)
+    }
+
+    /// Record the length and fee of the transaction a chain just built
+    pub fn set_last_tx(&mut self, chain_id: &str, tx_len: u64, fee: u64) {
+        let last_used = self.next_use();
+        if let Some(chain) = self.chains.get_mut(chain_id) {
+            chain.last_tx_len = tx_len;
+            chain.last_fee = fee;
+            chain.last_used = last_used;
+            self.save();
+        }
+    }
+
+    /// Give up on a chain, making its outputs available to others
+    pub fn release(&mut self, chain_id: &str) {
+        if self.chains.remove(chain_id).is_some() {
+            self.save();
+        }
+    }
+
+    fn next_use(&self) -> u64 {
+        self.chains
+            .values()
+            .map(|chain| chain.last_used + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn make_utxo(seed: u8, vout: u32, amount: u64) -> UTXO {
+        UTXO {
+            txid: Sha256dHash::from(&[seed; 32][..]),
+            vout,
+            script_pub_key: vec![0x76, 0xa9, seed].into(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_reserve_largest_first() {
+        let mut wallet = UTXOWallet::open(None, 2, 0, 0);
+        wallet.set_spendable(vec![
+            make_utxo(1, 0, 10_000),
+            make_utxo(2, 0, 50_000),
+            make_utxo(3, 0, 30_000),
+        ]);
+
+        let utxos = wallet.reserve("a", 60_000, 0).unwrap();
+        assert_eq!(
+            utxos.iter().map(|u| u.amount).collect::<Vec<_>>(),
+            vec![50_000, 30_000]
+        );
+        assert_eq!(wallet.get_available_balance(), 10_000);
+
+        // a second chain can't touch the first chain's outputs
+        assert!(wallet.reserve("b", 20_000, 0).is_none());
+        let utxos = wallet.reserve("b", 10_000, 0).unwrap();
+        assert_eq!(utxos, vec![make_utxo(1, 0, 10_000)]);
+        assert_eq!(wallet.get_available_balance(), 0);
+
+        // restarting a chain releases what it held first
+        let utxos = wallet.reserve("a", 80_000, 0).unwrap();
+        assert_eq!(utxos.len(), 2);
+        assert_eq!(wallet.get_chain("a").unwrap().utxos, utxos);
+
+        wallet.release("b");
+        assert_eq!(wallet.get_available_balance(), 10_000);
+    }
+
+    #[test]
+    fn test_reserve_evicts_least_recently_used() {
+        let mut wallet = UTXOWallet::open(None, 2, 0, 0);
+        wallet.set_spendable(vec![
+            make_utxo(1, 0, 10_000),
+            make_utxo(2, 0, 10_000),
+            make_utxo(3, 0, 10_000),
+        ]);
+
+        wallet.reserve("a", 10_000, 0).unwrap();
+        wallet.reserve("b", 10_000, 0).unwrap();
+        wallet.set_last_tx("a", 250, 1_000);
+
+        // "b" was used least recently, so it gives way
+        wallet.reserve("c", 10_000, 0).unwrap();
+        assert!(wallet.get_chain("a").is_some());
+        assert!(wallet.get_chain("b").is_none());
+        assert!(wallet.get_chain("c").is_some());
+        assert_eq!(wallet.get_chain("a").unwrap().last_tx_len, 250);
+        assert_eq!(wallet.get_available_balance(), 10_000);
+    }
+
+    #[test]
+    fn test_reserve_consolidates_dust() {
+        let mut wallet = UTXOWallet::open(None, 1, 2, 0);
+        wallet.set_spendable(vec![
+            make_utxo(1, 0, 100_000),
+            make_utxo(2, 0, 100),
+            make_utxo(3, 0, 4_000),
+            make_utxo(4, 0, 3_000),
+            make_utxo(5, 0, 2_000),
+        ]);
+
+        let utxos = wallet.reserve("a", 20_000, 148).unwrap();
+        assert_eq!(
+            utxos.iter().map(|u| u.amount).collect::<Vec<_>>(),
+            vec![100_000, 4_000, 3_000]
+        );
+
+        // dust alone never funds an operation
+        wallet.set_spendable(vec![make_utxo(3, 0, 4_000), make_utxo(4, 0, 3_000)]);
+        assert!(wallet.reserve("a", 1_000, 148).is_none());
+    }
+
+    #[test]
+    fn test_low_funds() {
+        let mut wallet = UTXOWallet::open(None, 1, 0, 50_000);
+        assert!(wallet.set_spendable(vec![make_utxo(1, 0, 60_000)]));
+        wallet.reserve("a", 20_000, 0).unwrap();
+        assert!(!wallet.set_spendable(vec![make_utxo(1, 0, 60_000), make_utxo(2, 0, 10_000)]));
+    }
+
+    #[test]
+    fn test_persistence() {
+        let path = "/tmp/stacks-node-tests/utxo-wallet-persistence.json";
+        let _ = fs::create_dir_all("/tmp/stacks-node-tests");
+        let _ = fs::remove_file(path);
+
+        {
+            let mut wallet = UTXOWallet::open(Some(path.to_string()), 2, 0, 0);
+            wallet.set_spendable(vec![make_utxo(1, 0, 10_000), make_utxo(2, 1, 20_000)]);
+            wallet.reserve("a", 15_000, 0).unwrap();
+            wallet.set_last_tx("a", 300, 2_000);
+        }
+
+        let wallet = UTXOWallet::open(Some(path.to_string()), 2, 0, 0);
+        let chain = wallet.get_chain("a").unwrap();
+        assert_eq!(chain.utxos, vec![make_utxo(2, 1, 20_000)]);
+        assert_eq!(chain.last_tx_len, 300);
+        assert_eq!(chain.last_fee, 2_000);
+        assert_eq!(wallet.get_available_balance(), 10_000);
+
+        // a corrupt wallet is replaced with an empty one
+        fs::write(path, "not json").unwrap();
+        let wallet = UTXOWallet::open(Some(path.to_string()), 2, 0, 0);
+        assert!(wallet.get_chain("a").is_none());
+        assert_eq!(wallet.get_available_balance(), 0);
+    }
+}
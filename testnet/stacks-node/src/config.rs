@@ -1,21 +1,41 @@
-use std::convert::TryInto;
+use std::collections::{HashMap, HashSet};
+use std::convert::{TryFrom, TryInto};
 use std::fs;
 use std::net::{SocketAddr, ToSocketAddrs};
 
 use rand::RngCore;
 
+use stacks::burnchains::bitcoin::indexer::{BitcoinSyncMode, DEFAULT_SPV_DOWNLOAD_THREADS};
+use stacks::burnchains::bitcoin::spv::SpvCheckpoint;
 use stacks::burnchains::bitcoin::BitcoinNetworkType;
 use stacks::burnchains::{MagicBytes, BLOCKSTACK_MAGIC_MAINNET};
+use stacks::chainstate::stacks::db::audit::{
+    DEFAULT_AUDIT_LOG_MAX_FILES, DEFAULT_AUDIT_LOG_MAX_FILE_BYTES,
+};
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::index::storage::DEFAULT_NODE_CACHE_SIZE;
+use stacks::chainstate::stacks::miner::{BlockAssemblyBudget, MicroblockAssemblyLimits};
+use stacks::chainstate::stacks::{EpochSizeLimits, MAX_TRANSACTION_LEN};
+use stacks::core::mempool::{
+    MemPoolAdmissionPolicy, MemPoolAdmissionPredicate, MemPoolGCPolicy, MemPoolTxOrdering,
+    MEMPOOL_DEFAULT_PREDICATE_COST_LIMIT, MEMPOOL_DEFAULT_RBF_BUMP_PERCENT,
+};
 use stacks::net::connection::ConnectionOptions;
+use stacks::net::http::HttpRateLimit;
 use stacks::net::{Neighbor, NeighborKey, PeerAddress};
 use stacks::util::hash::{hex_bytes, to_hex};
 use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::util::secp256k1::Secp256k1PublicKey;
+use stacks::util::strings::UrlString;
 use stacks::vm::costs::ExecutionCost;
 use stacks::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier};
+use stacks::vm::ClarityName;
+
+use http_types::Url;
 
 use super::neon_node::TESTNET_PEER_VERSION;
 use super::node::TESTNET_CHAIN_ID;
+use super::webhooks::Template;
 
 const MINIMUM_DUST_FEE: u64 = 5500;
 
@@ -25,6 +45,7 @@ pub struct ConfigFile {
     pub node: Option<NodeConfigFile>,
     pub ustx_balance: Option<Vec<InitialBalanceFile>>,
     pub events_observer: Option<Vec<EventObserverConfigFile>>,
+    pub webhook: Option<Vec<WebhookConfigFile>>,
     pub connection_options: Option<ConnectionOptionsFile>,
     pub block_limit: Option<BlockLimitFile>,
 }
@@ -81,6 +102,86 @@ mod tests {
             "STRYYQQ9M8KAF4NS7WNZQYY59X93XEKR31JP64CP"
         );
     }
+
+    #[test]
+    fn should_parse_event_keys() {
+        let contract_id =
+            QualifiedContractIdentifier::parse("STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.names")
+                .unwrap();
+
+        assert_eq!(EventKeyType::from_string("*"), Some(EventKeyType::AnyEvent));
+        assert_eq!(
+            EventKeyType::from_string("stx"),
+            Some(EventKeyType::STXEvent)
+        );
+        assert_eq!(EventKeyType::from_string("ft"), Some(EventKeyType::FTEvent));
+        assert_eq!(
+            EventKeyType::from_string("nft"),
+            Some(EventKeyType::NFTEvent)
+        );
+        assert_eq!(
+            EventKeyType::from_string("print"),
+            Some(EventKeyType::PrintEvent)
+        );
+        assert_eq!(
+            EventKeyType::from_string("miner_rewards"),
+            Some(EventKeyType::MinerRewards)
+        );
+        assert_eq!(
+            EventKeyType::from_string("STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.names"),
+            Some(EventKeyType::ContractEvent(contract_id.clone()))
+        );
+        assert_eq!(
+            EventKeyType::from_string("STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.names.names"),
+            Some(EventKeyType::AssetEvent(AssetIdentifier {
+                contract_identifier: contract_id.clone(),
+                asset_name: "names".to_string().try_into().unwrap(),
+            }))
+        );
+        assert_eq!(
+            EventKeyType::from_string("STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.names::print"),
+            Some(EventKeyType::SmartContractEvent((
+                contract_id,
+                "print".to_string()
+            )))
+        );
+        assert_eq!(EventKeyType::from_string("names"), None);
+        assert_eq!(EventKeyType::from_string("stx_events"), None);
+        assert_eq!(EventKeyType::from_string("not-an-address.names"), None);
+    }
+
+    #[test]
+    fn should_load_burnchain_fee_settings() {
+        let config = Config::from_config_file(ConfigFile::from_str(
+            r#"
+            [burnchain]
+            mode = "xenon"
+            peer_host = "127.0.0.1"
+            "#,
+        ));
+        assert_eq!(config.burnchain.fee_mode, BurnchainFeeMode::Flat);
+        assert_eq!(config.burnchain.max_burn_per_block, None);
+
+        let config = Config::from_config_file(ConfigFile::from_str(
+            r#"
+            [burnchain]
+            mode = "xenon"
+            peer_host = "127.0.0.1"
+            fee_mode = "estimate"
+            satoshis_per_byte = 5
+            max_satoshis_per_byte = 100
+            fee_estimate_target = 3
+            max_burn_per_block = 50000
+            rbf_fee_increase_percent = 10
+            "#,
+        ));
+        assert_eq!(config.burnchain.fee_mode, BurnchainFeeMode::Estimate);
+        assert_eq!(config.burnchain.satoshis_per_byte, 5);
+        assert_eq!(config.burnchain.max_satoshis_per_byte, Some(100));
+        assert_eq!(config.burnchain.fee_estimate_target, 3);
+        assert_eq!(config.burnchain.max_burn_per_block, Some(50000));
+        assert_eq!(config.burnchain.rbf_fee_increase_percent, 10);
+    }
 }
 
 impl ConfigFile {
@@ -308,6 +409,25 @@ impl ConfigFile {
         }
     }
 
+    pub fn dev() -> ConfigFile {
+        let burnchain = BurnchainConfigFile {
+            mode: Some("dev".to_string()),
+            commit_anchor_block_within: Some(0),
+            ..BurnchainConfigFile::default()
+        };
+
+        let node = NodeConfigFile {
+            miner: Some(false),
+            ..NodeConfigFile::default()
+        };
+
+        ConfigFile {
+            burnchain: Some(burnchain),
+            node: Some(node),
+            ..ConfigFile::default()
+        }
+    }
+
     pub fn mocknet() -> ConfigFile {
         let burnchain = BurnchainConfigFile {
             mode: Some("mocknet".to_string()),
@@ -334,8 +454,10 @@ pub struct Config {
     pub node: NodeConfig,
     pub initial_balances: Vec<InitialBalance>,
     pub events_observers: Vec<EventObserverConfig>,
+    pub webhooks: Vec<WebhookConfig>,
     pub connection_options: ConnectionOptions,
     pub block_limit: ExecutionCost,
+    pub epoch_size_limits: EpochSizeLimits,
 }
 
 lazy_static! {
@@ -394,10 +516,19 @@ impl Config {
                     p2p_address: node.p2p_address.unwrap_or(rpc_bind.clone()),
                     bootstrap_node: None,
                     deny_nodes: vec![],
+                    relay_nodes: vec![],
                     data_url: match node.data_url {
                         Some(data_url) => data_url,
                         None => format!("http://{}", rpc_bind),
                     },
+                    alt_data_urls: match node.alt_data_urls {
+                        Some(alt_data_urls) => alt_data_urls
+                            .split(",")
+                            .filter(|url| url.len() > 0)
+                            .map(|url| url.to_string())
+                            .collect(),
+                        None => vec![],
+                    },
                     local_peer_seed: match node.local_peer_seed {
                         Some(seed) => {
                             hex_bytes(&seed).expect("Seed should be a hex encoded string")
@@ -411,21 +542,167 @@ impl Config {
                     microblock_frequency: node
                         .microblock_frequency
                         .unwrap_or(default_node_config.microblock_frequency),
+                    microblock_max_interval: node.microblock_max_interval,
                     max_microblocks: node
                         .max_microblocks
                         .unwrap_or(default_node_config.max_microblocks),
+                    microblock_assembly_limits: MicroblockAssemblyLimits {
+                        max_txs: node.microblock_max_txs,
+                        max_cost_percent: node.microblock_max_cost_percent,
+                    },
+                    microblock_stop_on_competing_tip: node
+                        .microblock_stop_on_competing_tip
+                        .unwrap_or(default_node_config.microblock_stop_on_competing_tip),
                     wait_time_for_microblocks: node
                         .wait_time_for_microblocks
                         .unwrap_or(default_node_config.wait_time_for_microblocks),
                     prometheus_bind: node.prometheus_bind,
+                    ws_bind: node.ws_bind,
+                    rosetta_bind: node.rosetta_bind,
+                    grpc_bind: node.grpc_bind,
+                    call_scheduler_key: node.call_scheduler_key,
+                    poison_reporter_key: node.poison_reporter_key,
+                    poison_reporter_fee: node
+                        .poison_reporter_fee
+                        .unwrap_or(default_node_config.poison_reporter_fee),
+                    max_fork_depth: node.max_fork_depth,
                     pox_sync_sample_secs: node
                         .pox_sync_sample_secs
                         .unwrap_or(default_node_config.pox_sync_sample_secs),
+                    mempool_admit_unconfirmed: node
+                        .mempool_admit_unconfirmed
+                        .unwrap_or(default_node_config.mempool_admit_unconfirmed),
+                    mempool_rbf_bump_percent: node
+                        .mempool_rbf_bump_percent
+                        .unwrap_or(default_node_config.mempool_rbf_bump_percent),
+                    mempool_admission: MemPoolAdmissionPolicy {
+                        min_fee_rate: node
+                            .mempool_min_fee_rate
+                            .unwrap_or(default_node_config.mempool_admission.min_fee_rate),
+                        max_tx_size: node
+                            .mempool_max_tx_size
+                            .unwrap_or(default_node_config.mempool_admission.max_tx_size),
+                        max_pending_per_origin: node.mempool_max_pending_per_origin,
+                        banned_contracts: HashSet::new(),
+                        admission_predicate: None,
+                    },
+                    mempool_gc: MemPoolGCPolicy {
+                        max_tx_age: node
+                            .mempool_max_tx_age
+                            .unwrap_or(default_node_config.mempool_gc.max_tx_age),
+                        gc_interval: node
+                            .mempool_gc_interval
+                            .unwrap_or(default_node_config.mempool_gc.gc_interval),
+                        max_size: node.mempool_max_size,
+                    },
+                    genesis_data_path: node.genesis_data_path,
+                    genesis_audit: node
+                        .genesis_audit
+                        .unwrap_or(default_node_config.genesis_audit),
+                    expected_genesis_ustx: node.expected_genesis_ustx,
+                    expected_genesis_chainstate_hash: node.expected_genesis_chainstate_hash,
+                    marf_cache_size: node
+                        .marf_cache_size
+                        .unwrap_or(default_node_config.marf_cache_size),
+                    consensus_audit_log_path: node.consensus_audit_log_path,
+                    consensus_audit_log_max_bytes: node
+                        .consensus_audit_log_max_bytes
+                        .unwrap_or(default_node_config.consensus_audit_log_max_bytes),
+                    consensus_audit_log_max_files: node
+                        .consensus_audit_log_max_files
+                        .unwrap_or(default_node_config.consensus_audit_log_max_files),
+                    peer_seed_file: node.peer_seed_file,
+                    block_push_urls: match node.block_push_urls {
+                        Some(block_push_urls) => block_push_urls
+                            .split(",")
+                            .filter(|url| url.len() > 0)
+                            .map(|url| url.to_string())
+                            .collect(),
+                        None => vec![],
+                    },
+                    block_push_retries: node
+                        .block_push_retries
+                        .unwrap_or(default_node_config.block_push_retries),
+                    miner_tx_ordering: match node.miner_deterministic_tx_ordering {
+                        Some(true) => MemPoolTxOrdering::Deterministic,
+                        Some(false) => MemPoolTxOrdering::FeeRate,
+                        None => default_node_config.miner_tx_ordering,
+                    },
+                    miner_coinbase_recipient: node.miner_coinbase_recipient.map(|recipient| {
+                        PrincipalData::parse(&recipient).expect(
+                            "Setting `node.miner_coinbase_recipient` must be a standard or contract principal",
+                        )
+                    }),
+                    miner_assembly_budget: BlockAssemblyBudget {
+                        max_time_ms: node.miner_max_assembly_time_ms,
+                        max_cost_percent: node
+                            .miner_max_cost_percent
+                            .unwrap_or(default_node_config.miner_assembly_budget.max_cost_percent),
+                        reserved_cost_percent: node.miner_reserved_cost_percent.unwrap_or(
+                            default_node_config
+                                .miner_assembly_budget
+                                .reserved_cost_percent,
+                        ),
+                        reserve_min_fee_rate: node.miner_reserve_min_fee_rate.unwrap_or(
+                            default_node_config.miner_assembly_budget.reserve_min_fee_rate,
+                        ),
+                        max_contract_cost_percent: node.miner_max_contract_cost_percent,
+                        denied_contracts: HashSet::new(),
+                        allowed_contracts: None,
+                    },
                 };
+                for (setting, percent) in [
+                    (
+                        "miner_max_cost_percent",
+                        Some(node_config.miner_assembly_budget.max_cost_percent),
+                    ),
+                    (
+                        "miner_reserved_cost_percent",
+                        Some(node_config.miner_assembly_budget.reserved_cost_percent),
+                    ),
+                    (
+                        "miner_max_contract_cost_percent",
+                        node_config.miner_assembly_budget.max_contract_cost_percent,
+                    ),
+                    (
+                        "microblock_max_cost_percent",
+                        node_config.microblock_assembly_limits.max_cost_percent,
+                    ),
+                ]
+                .iter()
+                {
+                    if percent.unwrap_or(0) > 100 {
+                        panic!("Invalid setting `node.{}` (must be at most 100)", setting);
+                    }
+                }
+                if node_config.mempool_admission.max_tx_size > MAX_TRANSACTION_LEN as u64 {
+                    panic!(
+                        "Invalid setting `node.mempool_max_tx_size` (must be at most {})",
+                        MAX_TRANSACTION_LEN
+                    );
+                }
                 node_config.set_bootstrap_node(node.bootstrap_node);
                 if let Some(deny_nodes) = node.deny_nodes {
                     node_config.set_deny_nodes(deny_nodes);
                 }
+                if let Some(relay_nodes) = node.relay_nodes {
+                    node_config.set_relay_nodes(relay_nodes);
+                }
+                if let Some(banned_contracts) = node.mempool_banned_contracts {
+                    node_config.set_mempool_banned_contracts(banned_contracts);
+                }
+                if let Some(denied_contracts) = node.miner_denied_contracts {
+                    node_config.set_miner_denied_contracts(&denied_contracts);
+                }
+                if let Some(allowed_contracts) = node.miner_allowed_contracts {
+                    node_config.set_miner_allowed_contracts(&allowed_contracts);
+                }
+                if let Some(admission_predicate) = node.mempool_admission_predicate {
+                    node_config.set_mempool_admission_predicate(
+                        &admission_predicate,
+                        node.mempool_admission_predicate_runtime,
+                    );
+                }
                 node_config
             }
             None => default_node_config,
@@ -495,12 +772,75 @@ impl Config {
                     poll_time_secs: burnchain
                         .poll_time_secs
                         .unwrap_or(default_burnchain_config.poll_time_secs),
+                    dev_block_time_ms: burnchain
+                        .dev_block_time_ms
+                        .unwrap_or(default_burnchain_config.dev_block_time_ms),
+                    dev_missed_sortitions: burnchain
+                        .dev_missed_sortitions
+                        .unwrap_or(default_burnchain_config.dev_missed_sortitions),
+                    sync_mode: burnchain
+                        .sync_mode
+                        .map(|sync_mode| {
+                            sync_mode
+                                .parse()
+                                .expect("Setting `burnchain.sync_mode` must be `spv` or `rpc`")
+                        })
+                        .unwrap_or(default_burnchain_config.sync_mode),
+                    spv_checkpoints: burnchain
+                        .spv_checkpoints
+                        .map(|checkpoints| {
+                            SpvCheckpoint::parse_list(&checkpoints).expect(
+                                "Setting `burnchain.spv_checkpoints` must be a comma-separated list of `height:hash`",
+                            )
+                        })
+                        .or(default_burnchain_config.spv_checkpoints),
+                    spv_download_threads: burnchain
+                        .spv_download_threads
+                        .map(|threads| threads as usize)
+                        .unwrap_or(default_burnchain_config.spv_download_threads),
+                    utxo_max_chains: burnchain
+                        .utxo_max_chains
+                        .map(|chains| chains as usize)
+                        .unwrap_or(default_burnchain_config.utxo_max_chains),
+                    utxo_max_dust_inputs: burnchain
+                        .utxo_max_dust_inputs
+                        .map(|inputs| inputs as usize)
+                        .unwrap_or(default_burnchain_config.utxo_max_dust_inputs),
+                    utxo_low_funds_threshold: burnchain
+                        .utxo_low_funds_threshold
+                        .or(default_burnchain_config.utxo_low_funds_threshold),
+                    fee_mode: match burnchain.fee_mode.as_ref().map(|mode| mode.as_str()) {
+                        None => default_burnchain_config.fee_mode,
+                        Some("flat") => BurnchainFeeMode::Flat,
+                        Some("static") => BurnchainFeeMode::Static,
+                        Some("estimate") => BurnchainFeeMode::Estimate,
+                        Some(_) => panic!(
+                            "Setting `burnchain.fee_mode` must be `flat`, `static`, or `estimate`"
+                        ),
+                    },
+                    satoshis_per_byte: burnchain
+                        .satoshis_per_byte
+                        .unwrap_or(default_burnchain_config.satoshis_per_byte),
+                    max_satoshis_per_byte: burnchain
+                        .max_satoshis_per_byte
+                        .or(default_burnchain_config.max_satoshis_per_byte),
+                    fee_estimate_target: burnchain
+                        .fee_estimate_target
+                        .unwrap_or(default_burnchain_config.fee_estimate_target),
+                    max_burn_per_block: burnchain
+                        .max_burn_per_block
+                        .or(default_burnchain_config.max_burn_per_block),
+                    rbf_fee_increase_percent: burnchain
+                        .rbf_fee_increase_percent
+                        .unwrap_or(default_burnchain_config.rbf_fee_increase_percent),
                 }
             }
             None => default_burnchain_config,
         };
 
-        let supported_modes = vec!["mocknet", "helium", "neon", "argon", "krypton", "xenon"];
+        let supported_modes = vec![
+            "mocknet", "dev", "helium", "neon", "argon", "krypton", "xenon",
+        ];
 
         if !supported_modes.contains(&burnchain.mode.as_str()) {
             panic!(
@@ -513,6 +853,10 @@ impl Config {
             panic!("Config is missing the setting `burnchain.local_mining_public_key` (mandatory for helium)")
         }
 
+        if burnchain.mode == "dev" && burnchain.dev_block_time_ms == 0 {
+            panic!("Setting `burnchain.dev_block_time_ms` must be positive")
+        }
+
         let initial_balances: Vec<InitialBalance> = match config_file.ustx_balance {
             Some(balances) => balances
                 .iter()
@@ -537,7 +881,10 @@ impl Config {
                     let events_keys: Vec<EventKeyType> = observer
                         .events_keys
                         .iter()
-                        .map(|e| EventKeyType::from_string(e).unwrap())
+                        .map(|e| {
+                            EventKeyType::from_string(e)
+                                .expect(&format!("Invalid events_keys entry '{}'", e))
+                        })
                         .collect();
 
                     let endpoint = format!("{}", observer.endpoint);
@@ -561,7 +908,18 @@ impl Config {
             _ => (),
         };
 
-        let connection_options = match config_file.connection_options {
+        let webhooks = match config_file.webhook {
+            Some(raw_webhooks) => raw_webhooks
+                .iter()
+                .map(|webhook| {
+                    WebhookConfig::from_file(webhook)
+                        .expect(&format!("Invalid webhook for '{}'", webhook.url))
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        let mut connection_options = match config_file.connection_options {
             Some(opts) => {
                 let ip_addr = match opts.public_ip_address {
                     Some(public_ip_address) => {
@@ -677,6 +1035,38 @@ impl Config {
                                 .clone()
                         },
                     ),
+                    mempool_view_max_txs: opts.mempool_view_max_txs.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS
+                            .mempool_view_max_txs
+                            .clone()
+                    }),
+                    max_batch_queries: opts.max_batch_queries.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.max_batch_queries.clone()
+                    }),
+                    block_proposal_token: opts.block_proposal_token,
+                    admin_token: opts.admin_token,
+                    rpc_auth_token: opts.rpc_auth_token,
+                    rpc_rate_limit: opts.rpc_rate_limit.map(|limit| {
+                        limit
+                            .parse()
+                            .expect("Invalid `connection_options.rpc_rate_limit`")
+                    }),
+                    rpc_expensive_rate_limit: opts.rpc_expensive_rate_limit.map(|limit| {
+                        limit
+                            .parse()
+                            .expect("Invalid `connection_options.rpc_expensive_rate_limit`")
+                    }),
+                    rpc_route_rate_limits: opts
+                        .rpc_route_rate_limits
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|(route, limit)| {
+                            let limit: HttpRateLimit = limit
+                                .parse()
+                                .expect("Invalid `connection_options.rpc_route_rate_limits`");
+                            (route, limit)
+                        })
+                        .collect(),
                     download_interval: opts.download_interval.unwrap_or_else(|| {
                         HELIUM_DEFAULT_CONNECTION_OPTIONS.download_interval.clone()
                     }),
@@ -687,13 +1077,37 @@ impl Config {
                     disable_inbound_walks: opts.disable_inbound_walks.unwrap_or(false),
                     disable_inbound_handshakes: opts.disable_inbound_handshakes.unwrap_or(false),
                     force_disconnect_interval: opts.force_disconnect_interval,
+                    stackerdb_contracts: opts
+                        .stackerdb_contracts
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|contract_id| {
+                            QualifiedContractIdentifier::parse(contract_id).expect(&format!(
+                                "Invalid `connection_options.stackerdb_contracts` entry '{}'",
+                                contract_id
+                            ))
+                        })
+                        .collect(),
                     ..ConnectionOptions::default()
                 }
             }
             None => HELIUM_DEFAULT_CONNECTION_OPTIONS.clone(),
         };
-
-        let block_limit = match config_file.block_limit {
+        connection_options.relay_neighbors = node
+            .relay_nodes
+            .iter()
+            .map(|neighbor| neighbor.addr.clone())
+            .collect();
+        connection_options.alt_data_urls = node
+            .alt_data_urls
+            .iter()
+            .map(|url| {
+                UrlString::try_from(url.as_str())
+                    .expect(&format!("Invalid alternative data URL '{}'", url))
+            })
+            .collect();
+
+        let block_limit = match config_file.block_limit.clone() {
             Some(opts) => ExecutionCost {
                 write_length: opts
                     .write_length
@@ -712,13 +1126,33 @@ impl Config {
             None => HELIUM_BLOCK_LIMIT.clone(),
         };
 
+        let epoch_size_limits = match config_file.block_limit {
+            Some(opts) => {
+                let defaults = EpochSizeLimits::default();
+                let max_tx_size = opts.max_tx_size.unwrap_or(defaults.max_tx_size);
+                if max_tx_size > MAX_TRANSACTION_LEN as u64 {
+                    panic!(
+                        "Invalid setting `block_limit.max_tx_size` (must be at most {})",
+                        MAX_TRANSACTION_LEN
+                    );
+                }
+                EpochSizeLimits {
+                    max_epoch_size: opts.max_epoch_size.unwrap_or(defaults.max_epoch_size),
+                    max_tx_size,
+                }
+            }
+            None => EpochSizeLimits::default(),
+        };
+
         Config {
             node,
             burnchain,
             initial_balances,
             events_observers,
+            webhooks,
             connection_options,
             block_limit,
+            epoch_size_limits,
         }
     }
 
@@ -731,12 +1165,13 @@ impl Config {
     }
 
     pub fn get_burn_db_file_path(&self) -> String {
-        let dir_name = if self.burnchain.mode.as_str() == "mocknet" {
-            "mocknet".to_string()
-        } else {
-            let (network, _) = self.burnchain.get_bitcoin_network();
-            network
-        };
+        let dir_name =
+            if self.burnchain.mode.as_str() == "mocknet" || self.burnchain.mode.as_str() == "dev" {
+                self.burnchain.mode.clone()
+            } else {
+                let (network, _) = self.burnchain.get_bitcoin_network();
+                network
+            };
         format!(
             "{}/burnchain/db/{}/{}/sortition.db/",
             self.node.working_dir, self.burnchain.chain, dir_name
@@ -747,14 +1182,42 @@ impl Config {
         format!("{}/chainstate/", self.node.working_dir)
     }
 
+    /// Turn on `chainstate`'s consensus write audit log, if the node is configured to keep one.
+    /// Panics if the log can't be opened.
+    pub fn enable_consensus_audit_log(&self, chainstate: &mut StacksChainState) {
+        if let Some(ref path) = self.node.consensus_audit_log_path {
+            chainstate
+                .enable_consensus_audit_log(
+                    path,
+                    self.node.consensus_audit_log_max_bytes,
+                    self.node.consensus_audit_log_max_files,
+                )
+                .unwrap_or_else(|e| {
+                    panic!("Failed to open consensus audit log in {}: {:?}", path, &e)
+                });
+        }
+    }
+
     pub fn get_peer_db_path(&self) -> String {
         format!("{}/peer_db.sqlite", self.node.working_dir)
     }
 
+    pub fn get_event_queue_db_path(&self) -> String {
+        format!("{}/event_observers.sqlite", self.node.working_dir)
+    }
+
+    pub fn get_call_scheduler_db_path(&self) -> String {
+        format!("{}/call_scheduler.sqlite", self.node.working_dir)
+    }
+
     pub fn get_atlas_db_path(&self) -> String {
         format!("{}/chainstate/atlas_db.sqlite", self.node.working_dir)
     }
 
+    pub fn get_stackerdb_path(&self) -> String {
+        format!("{}/chainstate/stackerdb.sqlite", self.node.working_dir)
+    }
+
     pub fn add_initial_balance(&mut self, address: String, amount: u64) {
         let new_balance = InitialBalance {
             address: PrincipalData::parse_standard_principal(&address)
@@ -795,8 +1258,10 @@ impl std::default::Default for Config {
             node,
             initial_balances: vec![],
             events_observers: vec![],
+            webhooks: vec![],
             connection_options,
             block_limit,
+            epoch_size_limits: EpochSizeLimits::default(),
         }
     }
 }
@@ -820,6 +1285,56 @@ pub struct BurnchainConfig {
     pub burnchain_op_tx_fee: u64,
     pub process_exit_at_block_height: Option<u64>,
     pub poll_time_secs: u64,
+    /// in `dev` mode, how long to wait between simulated burnchain blocks
+    pub dev_block_time_ms: u64,
+    /// in `dev` mode, heights of the simulated burnchain blocks that have no sortition
+    pub dev_missed_sortitions: Vec<u64>,
+    /// whether to get Bitcoin headers and blocks from a peer (`spv`) or from bitcoind's RPC
+    /// interface (`rpc`)
+    pub sync_mode: BitcoinSyncMode,
+    /// Bitcoin header checkpoints to use instead of the compiled-in ones
+    pub spv_checkpoints: Option<Vec<SpvCheckpoint>>,
+    /// how many peer connections to download the headers between checkpoints over
+    pub spv_download_threads: usize,
+    /// how many burnchain transactions (each with its RBF replacements) may have UTXOs reserved
+    /// at once
+    pub utxo_max_chains: usize,
+    /// how many dust UTXOs to sweep into the change of each burnchain transaction
+    pub utxo_max_dust_inputs: usize,
+    /// warn when the miner's unreserved UTXOs are worth less than this many satoshis (by
+    /// default, enough for 10 more block-commits)
+    pub utxo_low_funds_threshold: Option<u64>,
+    /// how to choose the fee of burnchain transactions
+    pub fee_mode: BurnchainFeeMode,
+    /// fee rate in `static` mode, and the fallback rate in `estimate` mode
+    pub satoshis_per_byte: u64,
+    /// in `estimate` mode, the highest fee rate to pay
+    pub max_satoshis_per_byte: Option<u64>,
+    /// in `estimate` mode, how many blocks the fee rate should get a transaction confirmed in
+    pub fee_estimate_target: u64,
+    /// most satoshis to spend on a block-commit for a single burnchain block, counting both the
+    /// commitment and the transaction fees
+    pub max_burn_per_block: Option<u64>,
+    /// in `estimate` mode, replace an unconfirmed block-commit once the fee rate rises by this
+    /// many percent
+    pub rbf_fee_increase_percent: u64,
+}
+
+/// How the miner picks the fee of its burnchain transactions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BurnchainFeeMode {
+    /// `burnchain_op_tx_fee` satoshis, however big the transaction is
+    Flat,
+    /// `satoshis_per_byte`
+    Static,
+    /// whatever bitcoind's `estimatesmartfee` reports, capped at `max_satoshis_per_byte`
+    Estimate,
+}
+
+impl Default for BurnchainFeeMode {
+    fn default() -> BurnchainFeeMode {
+        BurnchainFeeMode::Flat
+    }
 }
 
 impl BurnchainConfig {
@@ -842,6 +1357,20 @@ impl BurnchainConfig {
             burnchain_op_tx_fee: MINIMUM_DUST_FEE,
             process_exit_at_block_height: None,
             poll_time_secs: 10, // TODO: this is a testnet specific value.
+            dev_block_time_ms: 1000,
+            dev_missed_sortitions: vec![],
+            sync_mode: BitcoinSyncMode::Spv,
+            spv_checkpoints: None,
+            spv_download_threads: DEFAULT_SPV_DOWNLOAD_THREADS,
+            utxo_max_chains: 1,
+            utxo_max_dust_inputs: 10,
+            utxo_low_funds_threshold: None,
+            fee_mode: BurnchainFeeMode::Flat,
+            satoshis_per_byte: 1,
+            max_satoshis_per_byte: None,
+            fee_estimate_target: 1,
+            max_burn_per_block: None,
+            rbf_fee_increase_percent: 25,
         }
     }
 
@@ -892,6 +1421,20 @@ pub struct BurnchainConfigFile {
     pub burnchain_op_tx_fee: Option<u64>,
     pub process_exit_at_block_height: Option<u64>,
     pub poll_time_secs: Option<u64>,
+    pub dev_block_time_ms: Option<u64>,
+    pub dev_missed_sortitions: Option<Vec<u64>>,
+    pub sync_mode: Option<String>,
+    pub spv_checkpoints: Option<String>,
+    pub spv_download_threads: Option<u64>,
+    pub utxo_max_chains: Option<u64>,
+    pub utxo_max_dust_inputs: Option<u64>,
+    pub utxo_low_funds_threshold: Option<u64>,
+    pub fee_mode: Option<String>,
+    pub satoshis_per_byte: Option<u64>,
+    pub max_satoshis_per_byte: Option<u64>,
+    pub fee_estimate_target: Option<u64>,
+    pub max_burn_per_block: Option<u64>,
+    pub rbf_fee_increase_percent: Option<u64>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -902,17 +1445,81 @@ pub struct NodeConfig {
     pub rpc_bind: String,
     pub p2p_bind: String,
     pub data_url: String,
+    pub alt_data_urls: Vec<String>,
     pub p2p_address: String,
     pub local_peer_seed: Vec<u8>,
     pub bootstrap_node: Option<Neighbor>,
     pub deny_nodes: Vec<Neighbor>,
+    pub relay_nodes: Vec<Neighbor>,
     pub miner: bool,
     pub mine_microblocks: bool,
+    /// how long (in milliseconds) the miner waits between microblocks
     pub microblock_frequency: u64,
+    /// if set, the miner holds back a microblock that is not yet full until this many
+    /// milliseconds have passed since its last one
+    pub microblock_max_interval: Option<u64>,
+    /// the most microblocks the miner streams off of any one anchored block
     pub max_microblocks: u64,
+    /// limits on how many transactions, and how much of the block's budget, go into each microblock
+    pub microblock_assembly_limits: MicroblockAssemblyLimits,
+    /// whether the miner stops streaming microblocks once the canonical Stacks tip is no longer
+    /// its own anchored block
+    pub microblock_stop_on_competing_tip: bool,
     pub wait_time_for_microblocks: u64,
     pub prometheus_bind: Option<String>,
+    /// where to serve the WebSocket event stream, if anywhere
+    pub ws_bind: Option<String>,
+    /// where to serve the Rosetta API, if anywhere
+    pub rosetta_bind: Option<String>,
+    /// where to serve the gRPC service, if anywhere (requires the `grpc` feature)
+    pub grpc_bind: Option<String>,
+    /// hex private key that signs scheduled contract-calls; the call scheduler only runs if set
+    pub call_scheduler_key: Option<String>,
+    /// hex private key that signs poison-microblock reports; the poison reporter only runs if set
+    pub poison_reporter_key: Option<String>,
+    /// fee paid by each poison-microblock report
+    pub poison_reporter_fee: u64,
+    /// refuse to store blocks more than this many blocks behind the canonical Stacks tip
+    pub max_fork_depth: Option<u64>,
     pub pox_sync_sample_secs: u64,
+    pub mempool_admit_unconfirmed: bool,
+    pub mempool_rbf_bump_percent: u64,
+    pub mempool_admission: MemPoolAdmissionPolicy,
+    pub mempool_gc: MemPoolGCPolicy,
+    /// path to a chainstate.txt-formatted file with the genesis balances, lockups, namespaces and
+    /// names to boot from, instead of the bundled chainstate.txt.  For private networks.
+    pub genesis_data_path: Option<String>,
+    /// whether or not to audit the genesis balances on first boot
+    pub genesis_audit: bool,
+    /// the microSTX the bulk genesis balances must add up to.  Defaults to the total of the
+    /// genesis data's balances.
+    pub expected_genesis_ustx: Option<u64>,
+    /// the SHA256 that the genesis data must have, if the audit is enabled
+    pub expected_genesis_chainstate_hash: Option<String>,
+    /// number of MARF trie nodes (and node hashes) to cache in RAM per open MARF.  0 disables the
+    /// cache.
+    pub marf_cache_size: usize,
+    /// directory to keep an audit log of the consensus writes each processed block makes in.
+    /// Disabled if not set.
+    pub consensus_audit_log_path: Option<String>,
+    /// size past which the consensus audit log is rotated
+    pub consensus_audit_log_max_bytes: u64,
+    /// number of rotated consensus audit log files to keep
+    pub consensus_audit_log_max_files: usize,
+    /// path to a peer DB export (see `blockstack-core peerdb-export`) whose peers are added to
+    /// the frontier on startup
+    pub peer_seed_file: Option<String>,
+    /// data URLs of peers the miner uploads each block it mines to, in addition to relaying it
+    pub block_push_urls: Vec<String>,
+    /// how many more times to try uploading a mined block to a block push URL after a failure
+    pub block_push_retries: u64,
+    /// how the miner orders mempool transactions.  Devnets can order them deterministically,
+    /// so that the same transactions always make up the same blocks.
+    pub miner_tx_ordering: MemPoolTxOrdering,
+    /// who the miner's coinbases pay, if not the miner's own address.  Can be a contract.
+    pub miner_coinbase_recipient: Option<PrincipalData>,
+    /// limits on how long, and how full, the miner assembles its anchored blocks
+    pub miner_assembly_budget: BlockAssemblyBudget,
 }
 
 impl NodeConfig {
@@ -939,17 +1546,47 @@ impl NodeConfig {
             rpc_bind: format!("0.0.0.0:{}", rpc_port),
             p2p_bind: format!("0.0.0.0:{}", p2p_port),
             data_url: format!("http://127.0.0.1:{}", rpc_port),
+            alt_data_urls: vec![],
             p2p_address: format!("127.0.0.1:{}", rpc_port),
             bootstrap_node: None,
             deny_nodes: vec![],
+            relay_nodes: vec![],
             local_peer_seed: local_peer_seed.to_vec(),
             miner: false,
             mine_microblocks: false,
             microblock_frequency: 5000,
+            microblock_max_interval: None,
             max_microblocks: u16::MAX as u64,
+            microblock_assembly_limits: MicroblockAssemblyLimits::default(),
+            microblock_stop_on_competing_tip: false,
             wait_time_for_microblocks: 5000,
             prometheus_bind: None,
+            ws_bind: None,
+            rosetta_bind: None,
+            grpc_bind: None,
+            call_scheduler_key: None,
+            poison_reporter_key: None,
+            poison_reporter_fee: 1000,
+            max_fork_depth: None,
             pox_sync_sample_secs: 30,
+            mempool_admit_unconfirmed: true,
+            mempool_rbf_bump_percent: MEMPOOL_DEFAULT_RBF_BUMP_PERCENT,
+            mempool_admission: MemPoolAdmissionPolicy::default(),
+            mempool_gc: MemPoolGCPolicy::default(),
+            genesis_data_path: None,
+            genesis_audit: true,
+            expected_genesis_ustx: None,
+            expected_genesis_chainstate_hash: None,
+            marf_cache_size: DEFAULT_NODE_CACHE_SIZE,
+            consensus_audit_log_path: None,
+            consensus_audit_log_max_bytes: DEFAULT_AUDIT_LOG_MAX_FILE_BYTES,
+            consensus_audit_log_max_files: DEFAULT_AUDIT_LOG_MAX_FILES,
+            peer_seed_file: None,
+            block_push_urls: vec![],
+            block_push_retries: 3,
+            miner_tx_ordering: MemPoolTxOrdering::FeeRate,
+            miner_coinbase_recipient: None,
+            miner_assembly_budget: BlockAssemblyBudget::default(),
         }
     }
 
@@ -1016,6 +1653,98 @@ impl NodeConfig {
             }
         }
     }
+
+    pub fn set_mempool_banned_contracts(&mut self, banned_contracts: String) {
+        for part in banned_contracts.split(",") {
+            if part.len() > 0 {
+                let contract_id = QualifiedContractIdentifier::parse(part.trim())
+                    .expect("Invalid contract identifier in `node.mempool_banned_contracts`");
+                self.mempool_admission.banned_contracts.insert(contract_id);
+            }
+        }
+    }
+
+    /// Parse a comma-separated list of contract identifiers, given as the named setting
+    fn parse_contract_list(contracts: &str, setting: &str) -> HashSet<QualifiedContractIdentifier> {
+        contracts
+            .split(",")
+            .filter(|part| part.len() > 0)
+            .map(|part| {
+                QualifiedContractIdentifier::parse(part.trim()).expect(&format!(
+                    "Invalid contract identifier in `node.{}`",
+                    setting
+                ))
+            })
+            .collect()
+    }
+
+    /// Set the contracts whose transactions the miner never includes in its blocks
+    pub fn set_miner_denied_contracts(&mut self, denied_contracts: &str) {
+        self.miner_assembly_budget.denied_contracts =
+            NodeConfig::parse_contract_list(denied_contracts, "miner_denied_contracts");
+    }
+
+    /// Set the only contracts whose transactions the miner includes in its blocks
+    pub fn set_miner_allowed_contracts(&mut self, allowed_contracts: &str) {
+        self.miner_assembly_budget.allowed_contracts = Some(NodeConfig::parse_contract_list(
+            allowed_contracts,
+            "miner_allowed_contracts",
+        ));
+    }
+
+    /// Set the mempool admission predicate from a `contract-id::function-name` string, with an
+    /// optional runtime budget in place of the default.
+    pub fn set_mempool_admission_predicate(&mut self, predicate: &str, runtime: Option<u64>) {
+        let comps: Vec<&str> = predicate.trim().split("::").collect();
+        let (contract_id, function_name) = match comps[..] {
+            [contract_id, function_name] => (
+                QualifiedContractIdentifier::parse(contract_id)
+                    .expect("Invalid contract identifier in `node.mempool_admission_predicate`"),
+                ClarityName::try_from(function_name.to_string())
+                    .expect("Invalid function name in `node.mempool_admission_predicate`"),
+            ),
+            _ => panic!(
+                "Invalid `node.mempool_admission_predicate` (expected `contract-id::function-name`)"
+            ),
+        };
+        let mut cost_limit = MEMPOOL_DEFAULT_PREDICATE_COST_LIMIT;
+        if let Some(runtime) = runtime {
+            cost_limit.runtime = runtime;
+        }
+        self.mempool_admission.admission_predicate = Some(MemPoolAdmissionPredicate {
+            contract_id,
+            function_name,
+            cost_limit,
+        });
+    }
+
+    pub fn add_relay_node(&mut self, relay_node: &str) {
+        let comps: Vec<&str> = relay_node.split("@").collect();
+        match comps[..] {
+            [public_key, peer_addr] => {
+                let mut pubk = Secp256k1PublicKey::from_hex(public_key).unwrap();
+                pubk.set_compressed(true);
+
+                let sock_addr = peer_addr.to_socket_addrs().unwrap().next().unwrap();
+                let mut neighbor = NodeConfig::default_neighbor(sock_addr, pubk);
+                neighbor.allowed = -1;
+                self.relay_nodes.push(neighbor);
+            }
+            _ => panic!(
+                "Invalid relay node '{}': expected <public key>@<host>:<port>",
+                relay_node
+            ),
+        }
+    }
+
+    pub fn set_relay_nodes(&mut self, relay_nodes: String) {
+        let parts: Vec<&str> = relay_nodes.split(",").collect();
+        for part in parts.into_iter() {
+            if part.len() > 0 {
+                self.add_relay_node(&part);
+            }
+        }
+    }
 }
 
 #[derive(Clone, Default, Deserialize)]
@@ -1045,12 +1774,21 @@ pub struct ConnectionOptionsFile {
     pub read_only_call_limit_read_count: Option<u64>,
     pub read_only_call_limit_runtime: Option<u64>,
     pub maximum_call_argument_size: Option<u32>,
+    pub mempool_view_max_txs: Option<u64>,
+    pub max_batch_queries: Option<u64>,
+    pub block_proposal_token: Option<String>,
+    pub admin_token: Option<String>,
+    pub rpc_auth_token: Option<String>,
+    pub rpc_rate_limit: Option<String>,
+    pub rpc_expensive_rate_limit: Option<String>,
+    pub rpc_route_rate_limits: Option<HashMap<String, String>>,
     pub download_interval: Option<u64>,
     pub inv_sync_interval: Option<u64>,
     pub public_ip_address: Option<String>,
     pub disable_inbound_walks: Option<bool>,
     pub disable_inbound_handshakes: Option<bool>,
     pub force_disconnect_interval: Option<u64>,
+    pub stackerdb_contracts: Option<Vec<String>>,
 }
 
 #[derive(Clone, Default, Deserialize)]
@@ -1060,6 +1798,8 @@ pub struct BlockLimitFile {
     pub write_count: Option<u64>,
     pub read_count: Option<u64>,
     pub runtime: Option<u64>,
+    pub max_epoch_size: Option<u64>,
+    pub max_tx_size: Option<u64>,
 }
 
 #[derive(Clone, Deserialize, Default)]
@@ -1067,20 +1807,64 @@ pub struct NodeConfigFile {
     pub name: Option<String>,
     pub seed: Option<String>,
     pub deny_nodes: Option<String>,
+    pub relay_nodes: Option<String>,
     pub working_dir: Option<String>,
     pub rpc_bind: Option<String>,
     pub p2p_bind: Option<String>,
     pub p2p_address: Option<String>,
     pub data_url: Option<String>,
+    pub alt_data_urls: Option<String>,
     pub bootstrap_node: Option<String>,
     pub local_peer_seed: Option<String>,
     pub miner: Option<bool>,
     pub mine_microblocks: Option<bool>,
     pub microblock_frequency: Option<u64>,
+    pub microblock_max_interval: Option<u64>,
     pub max_microblocks: Option<u64>,
+    pub microblock_max_txs: Option<u64>,
+    pub microblock_max_cost_percent: Option<u64>,
+    pub microblock_stop_on_competing_tip: Option<bool>,
     pub wait_time_for_microblocks: Option<u64>,
     pub prometheus_bind: Option<String>,
+    pub ws_bind: Option<String>,
+    pub rosetta_bind: Option<String>,
+    pub grpc_bind: Option<String>,
+    pub call_scheduler_key: Option<String>,
+    pub poison_reporter_key: Option<String>,
+    pub poison_reporter_fee: Option<u64>,
+    pub max_fork_depth: Option<u64>,
     pub pox_sync_sample_secs: Option<u64>,
+    pub mempool_admit_unconfirmed: Option<bool>,
+    pub mempool_rbf_bump_percent: Option<u64>,
+    pub mempool_min_fee_rate: Option<u64>,
+    pub mempool_max_tx_size: Option<u64>,
+    pub mempool_max_pending_per_origin: Option<u64>,
+    pub mempool_banned_contracts: Option<String>,
+    pub mempool_admission_predicate: Option<String>,
+    pub mempool_admission_predicate_runtime: Option<u64>,
+    pub mempool_max_tx_age: Option<u64>,
+    pub mempool_gc_interval: Option<u64>,
+    pub mempool_max_size: Option<u64>,
+    pub genesis_data_path: Option<String>,
+    pub genesis_audit: Option<bool>,
+    pub expected_genesis_ustx: Option<u64>,
+    pub expected_genesis_chainstate_hash: Option<String>,
+    pub marf_cache_size: Option<usize>,
+    pub consensus_audit_log_path: Option<String>,
+    pub consensus_audit_log_max_bytes: Option<u64>,
+    pub consensus_audit_log_max_files: Option<usize>,
+    pub peer_seed_file: Option<String>,
+    pub block_push_urls: Option<String>,
+    pub block_push_retries: Option<u64>,
+    pub miner_deterministic_tx_ordering: Option<bool>,
+    pub miner_coinbase_recipient: Option<String>,
+    pub miner_max_assembly_time_ms: Option<u64>,
+    pub miner_max_cost_percent: Option<u64>,
+    pub miner_reserved_cost_percent: Option<u64>,
+    pub miner_reserve_min_fee_rate: Option<u64>,
+    pub miner_max_contract_cost_percent: Option<u64>,
+    pub miner_denied_contracts: Option<String>,
+    pub miner_allowed_contracts: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Default)]
@@ -1095,18 +1879,87 @@ pub struct EventObserverConfig {
     pub events_keys: Vec<EventKeyType>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Deserialize, Default)]
+pub struct WebhookConfigFile {
+    pub url: String,
+    pub trigger: Option<String>,
+    pub events_keys: Option<Vec<String>>,
+    pub template: String,
+    pub content_type: Option<String>,
+}
+
+/// When a webhook is notified
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookTrigger {
+    /// once for each matching event
+    Event,
+    /// once for each block with at least one matching event (or every block, if the webhook
+    /// has no events keys)
+    Block,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: Url,
+    pub trigger: WebhookTrigger,
+    /// the events that notify the webhook; empty means all of them
+    pub events_keys: Vec<EventKeyType>,
+    pub template: Template,
+    pub content_type: String,
+}
+
+impl WebhookConfig {
+    pub fn from_file(webhook: &WebhookConfigFile) -> Result<WebhookConfig, String> {
+        let url = Url::parse(&webhook.url).map_err(|e| format!("Invalid url: {}", e))?;
+        if url.scheme() != "http" {
+            return Err(format!("Unsupported url scheme '{}'", url.scheme()));
+        }
+
+        let trigger = match webhook.trigger.as_ref().map(|t| t.as_str()) {
+            None | Some("event") => WebhookTrigger::Event,
+            Some("block") => WebhookTrigger::Block,
+            Some(other) => return Err(format!("Unknown trigger '{}'", other)),
+        };
+
+        let mut events_keys = vec![];
+        for raw_key in webhook.events_keys.iter().flatten() {
+            match EventKeyType::from_string(raw_key) {
+                Some(key) => events_keys.push(key),
+                None => return Err(format!("Invalid events_keys entry '{}'", raw_key)),
+            }
+        }
+
+        Ok(WebhookConfig {
+            url,
+            trigger,
+            events_keys,
+            template: Template::parse(&webhook.template)?,
+            content_type: webhook
+                .content_type
+                .clone()
+                .unwrap_or("application/json".to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum EventKeyType {
     SmartContractEvent((QualifiedContractIdentifier, String)),
     AssetEvent(AssetIdentifier),
+    /// Print and asset events of any topic or asset defined by the contract
+    ContractEvent(QualifiedContractIdentifier),
     STXEvent,
+    FTEvent,
+    NFTEvent,
+    PrintEvent,
+    MinerRewards,
     MemPoolTransactions,
     AnyEvent,
     BurnchainBlocks,
 }
 
 impl EventKeyType {
-    fn from_string(raw_key: &str) -> Option<EventKeyType> {
+    pub fn from_string(raw_key: &str) -> Option<EventKeyType> {
         if raw_key == "*" {
             return Some(EventKeyType::AnyEvent);
         }
@@ -1115,6 +1968,22 @@ impl EventKeyType {
             return Some(EventKeyType::STXEvent);
         }
 
+        if raw_key == "ft" {
+            return Some(EventKeyType::FTEvent);
+        }
+
+        if raw_key == "nft" {
+            return Some(EventKeyType::NFTEvent);
+        }
+
+        if raw_key == "print" {
+            return Some(EventKeyType::PrintEvent);
+        }
+
+        if raw_key == "miner_rewards" {
+            return Some(EventKeyType::MinerRewards);
+        }
+
         if raw_key == "memtx" {
             return Some(EventKeyType::MemPoolTransactions);
         }
@@ -1126,6 +1995,11 @@ impl EventKeyType {
         let comps: Vec<_> = raw_key.split("::").collect();
         if comps.len() == 1 {
             let split: Vec<_> = comps[0].split(".").collect();
+            if split.len() == 2 {
+                return QualifiedContractIdentifier::parse(comps[0])
+                    .ok()
+                    .map(EventKeyType::ContractEvent);
+            }
             if split.len() != 3 {
                 return None;
             }
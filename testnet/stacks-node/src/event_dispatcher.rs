@@ -1,6 +1,6 @@
-use stacks::chainstate::coordinator::BlockEventDispatcher;
-use stacks::chainstate::stacks::db::StacksHeaderInfo;
-use stacks::chainstate::stacks::StacksBlock;
+use stacks::chainstate::coordinator::{BlockEventDispatcher, StacksChainReorg};
+use stacks::chainstate::stacks::db::{StacksHeaderInfo, StoredTransactionReceipt};
+use stacks::chainstate::stacks::{StacksBlock, StacksMicroblock};
 use stacks::net::atlas::AttachmentInstance;
 use std::collections::hash_map::Entry;
 use std::thread::sleep;
@@ -14,6 +14,8 @@ use async_h1::client;
 use async_std::net::TcpStream;
 use http_types::{Method, Request, Url};
 
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
 use serde_json::json;
 
 use stacks::burnchains::{BurnchainHeaderHash, Txid};
@@ -24,19 +26,47 @@ use stacks::chainstate::stacks::events::{
 use stacks::chainstate::stacks::{
     db::accounts::MinerReward, db::MinerRewardInfo, StacksAddress, StacksBlockId, StacksTransaction,
 };
-use stacks::net::StacksMessageCodec;
+use stacks::net::{StacksMessageCodec, UnconfirmedMicroblockStreamResponse};
+use stacks::util::db::sqlite_open;
 use stacks::util::hash::bytes_to_hex;
 use stacks::vm::analysis::contract_interface_builder::build_contract_interface;
 use stacks::vm::types::{AssetIdentifier, QualifiedContractIdentifier, Value};
 
-use super::config::{EventKeyType, EventObserverConfig};
+use super::config::{EventKeyType, EventObserverConfig, WebhookConfig};
+use super::event_stream::EventStreamServer;
 use super::node::ChainTip;
+use super::webhooks::{BlockEvent, Webhook};
 
 #[derive(Debug, Clone)]
 struct EventObserver {
     endpoint: String,
+    /// on-disk queue of undelivered block payloads, if the dispatcher persists them
+    queue: Option<EventQueue>,
+}
+
+/// Block and burn block payloads are written to the queue before they are sent, and removed
+/// once the observer acknowledges them, so an observer that is unreachable (or a node that is
+/// restarted) does not miss any.
+#[derive(Debug, Clone)]
+struct EventQueue {
+    db_path: String,
+    /// held while delivering, so each observer gets its queued payloads once, in order
+    delivery_lock: Arc<Mutex<()>>,
 }
 
+const EVENT_QUEUE_SCHEMA: &[&str] = &[r#"
+    CREATE TABLE IF NOT EXISTS pending_payloads(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        endpoint TEXT NOT NULL,
+        path TEXT NOT NULL,
+        payload TEXT NOT NULL
+    );"#];
+
+/// How long to wait before re-sending a payload the first time an observer fails to take it
+const SEND_RETRY_BACKOFF_MIN: Duration = Duration::from_secs(1);
+/// Upper bound on the wait between re-sends, which doubles on each failure
+const SEND_RETRY_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
 const STATUS_RESP_TRUE: &str = "success";
 const STATUS_RESP_NOT_COMMITTED: &str = "abort_by_response";
 const STATUS_RESP_POST_CONDITION: &str = "abort_by_post_condition";
@@ -44,6 +74,7 @@ const STATUS_RESP_POST_CONDITION: &str = "abort_by_post_condition";
 pub const PATH_MEMPOOL_TX_SUBMIT: &str = "new_mempool_tx";
 pub const PATH_BURN_BLOCK_SUBMIT: &str = "new_burn_block";
 pub const PATH_BLOCK_PROCESSED: &str = "new_block";
+pub const PATH_REORG: &str = "new_reorg";
 pub const PATH_ATTACHMENT_PROCESSED: &str = "attachments/new";
 
 impl EventObserver {
@@ -55,7 +86,11 @@ impl EventObserver {
                 return;
             }
         };
+        self.post_payload(body, path);
+    }
 
+    /// POST a serialized payload, retrying with backoff until the observer acknowledges it
+    fn post_payload(&self, body: Vec<u8>, path: &str) {
         let url = {
             let joined_components = match path.starts_with("/") {
                 true => format!("{}{}", &self.endpoint, path),
@@ -68,7 +103,7 @@ impl EventObserver {
             ))
         };
 
-        let backoff = Duration::from_millis((1.0 * 1_000.0) as u64);
+        let mut backoff = SEND_RETRY_BACKOFF_MIN;
 
         loop {
             let body = body.clone();
@@ -106,9 +141,113 @@ impl EventObserver {
                 }
             }
             sleep(backoff);
+            backoff = std::cmp::min(backoff * 2, SEND_RETRY_BACKOFF_MAX);
         }
     }
 
+    /// Queue a payload on disk, and then deliver everything queued for this observer, oldest
+    /// first.  Falls back to sending the payload directly if the observer has no queue, or the
+    /// queue cannot be written.
+    fn send_queued_payload(&self, payload: &serde_json::Value, path: &str) {
+        let queue = match self.queue {
+            Some(ref queue) => queue,
+            None => {
+                return self.send_payload(payload, path);
+            }
+        };
+
+        let _delivery = queue
+            .delivery_lock
+            .lock()
+            .expect("Event dispatcher: delivery lock poisoned");
+
+        let conn = match EventObserver::open_queue(&queue.db_path) {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(
+                    "Event dispatcher: failed to open event queue {}: {:?}",
+                    &queue.db_path, &e
+                );
+                return self.send_payload(payload, path);
+            }
+        };
+
+        if let Err(e) = EventObserver::enqueue_payload(&conn, &self.endpoint, path, payload) {
+            warn!(
+                "Event dispatcher: failed to queue payload for {}: {:?}",
+                &self.endpoint, &e
+            );
+            return self.send_payload(payload, path);
+        }
+
+        let pending = match EventObserver::get_queued_payloads(&conn, &self.endpoint) {
+            Ok(pending) => pending,
+            Err(e) => {
+                warn!(
+                    "Event dispatcher: failed to load queued payloads for {}: {:?}",
+                    &self.endpoint, &e
+                );
+                return;
+            }
+        };
+
+        for (id, path, body) in pending.into_iter() {
+            self.post_payload(body.into_bytes(), &path);
+            if let Err(e) = EventObserver::delete_queued_payload(&conn, id) {
+                // it will be sent again
+                warn!(
+                    "Event dispatcher: failed to dequeue delivered payload {} for {}: {:?}",
+                    id, &self.endpoint, &e
+                );
+            }
+        }
+    }
+
+    fn open_queue(db_path: &str) -> Result<Connection, rusqlite::Error> {
+        let conn = sqlite_open(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+        )?;
+        for cmd in EVENT_QUEUE_SCHEMA.iter() {
+            conn.execute(cmd, NO_PARAMS)?;
+        }
+        Ok(conn)
+    }
+
+    fn enqueue_payload(
+        conn: &Connection,
+        endpoint: &str,
+        path: &str,
+        payload: &serde_json::Value,
+    ) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "INSERT INTO pending_payloads (endpoint, path, payload) VALUES (?1, ?2, ?3)",
+            &[endpoint, path, &payload.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Queued payloads for an endpoint, oldest first, as (id, path, serialized payload)
+    fn get_queued_payloads(
+        conn: &Connection,
+        endpoint: &str,
+    ) -> Result<Vec<(i64, String, String)>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT id, path, payload FROM pending_payloads WHERE endpoint = ?1 ORDER BY id ASC",
+        )?;
+        let rows = stmt.query_map(&[endpoint], |row| (row.get(0), row.get(1), row.get(2)))?;
+        let mut pending = vec![];
+        for row in rows {
+            pending.push(row?);
+        }
+        Ok(pending)
+    }
+
+    fn delete_queued_payload(conn: &Connection, id: i64) -> Result<(), rusqlite::Error> {
+        conn.execute("DELETE FROM pending_payloads WHERE id = ?1", &[&id])?;
+        Ok(())
+    }
+
     fn make_new_mempool_txs_payload(transactions: Vec<StacksTransaction>) -> serde_json::Value {
         let raw_txs = transactions
             .into_iter()
@@ -120,6 +259,17 @@ impl EventObserver {
         serde_json::Value::Array(raw_txs)
     }
 
+    fn make_new_microblocks_payload(
+        parent_index_block_hash: &StacksBlockId,
+        microblocks: &[StacksMicroblock],
+    ) -> serde_json::Value {
+        serde_json::to_value(UnconfirmedMicroblockStreamResponse::from_microblocks(
+            parent_index_block_hash,
+            microblocks,
+        ))
+        .expect("BUG: failed to serialize microblocks")
+    }
+
     fn make_new_burn_block_payload(
         burn_block: &BurnchainHeaderHash,
         burn_block_height: u64,
@@ -144,23 +294,31 @@ impl EventObserver {
         })
     }
 
+    fn make_reorg_payload(reorg: &StacksChainReorg) -> serde_json::Value {
+        let orphaned_blocks: Vec<_> = reorg
+            .orphaned_blocks
+            .iter()
+            .map(|block_id| format!("0x{}", block_id))
+            .collect();
+
+        json!({
+            "old_index_block_hash": format!("0x{}", reorg.old_tip),
+            "old_block_height": reorg.old_tip_height,
+            "new_index_block_hash": format!("0x{}", reorg.new_tip),
+            "new_block_height": reorg.new_tip_height,
+            "common_ancestor_index_block_hash": format!("0x{}", reorg.common_ancestor),
+            "common_ancestor_block_height": reorg.common_ancestor_height,
+            "orphaned_index_block_hashes": orphaned_blocks,
+        })
+    }
+
     fn make_new_block_txs_payload(
         receipt: &StacksTransactionReceipt,
         tx_index: u32,
     ) -> serde_json::Value {
         let tx = &receipt.transaction;
 
-        let success = match (receipt.post_condition_aborted, &receipt.result) {
-            (false, Value::Response(response_data)) => {
-                if response_data.committed {
-                    STATUS_RESP_TRUE
-                } else {
-                    STATUS_RESP_NOT_COMMITTED
-                }
-            }
-            (true, Value::Response(_)) => STATUS_RESP_POST_CONDITION,
-            _ => unreachable!(), // Transaction results should always be a Value::Response type
-        };
+        let success = EventObserver::get_tx_status(receipt.post_condition_aborted, &receipt.result);
 
         let (txid, raw_tx) = match tx {
             TransactionOrigin::Burn(txid) => (txid.to_string(), "00".to_string()),
@@ -188,6 +346,64 @@ impl EventObserver {
             "raw_result": format!("0x{}", &raw_result),
             "raw_tx": format!("0x{}", &raw_tx),
             "contract_abi": contract_interface_json,
+            "execution_cost": receipt.execution_cost,
+        })
+    }
+
+    fn get_tx_status(post_condition_aborted: bool, result: &Value) -> &'static str {
+        match (post_condition_aborted, result) {
+            (false, Value::Response(response_data)) => {
+                if response_data.committed {
+                    STATUS_RESP_TRUE
+                } else {
+                    STATUS_RESP_NOT_COMMITTED
+                }
+            }
+            (true, Value::Response(_)) => STATUS_RESP_POST_CONDITION,
+            _ => unreachable!(), // Transaction results should always be a Value::Response type
+        }
+    }
+
+    fn make_replayed_block_txs_payload(receipt: &StoredTransactionReceipt) -> serde_json::Value {
+        let success = EventObserver::get_tx_status(receipt.post_condition_aborted, &receipt.result);
+        let raw_result = {
+            let bytes = receipt.result.serialize_to_vec();
+            bytes_to_hex(&bytes)
+        };
+        json!({
+            "txid": format!("0x{}", &receipt.txid),
+            "tx_index": receipt.tx_index,
+            "status": success,
+            "raw_result": format!("0x{}", &raw_result),
+            "raw_tx": format!("0x{}", &receipt.raw_tx),
+            "contract_abi": receipt.contract_abi.clone().unwrap_or(json!(null)),
+            "execution_cost": receipt.execution_cost,
+        })
+    }
+
+    fn make_new_block_payload(
+        metadata: &StacksHeaderInfo,
+        block: &StacksBlock,
+        parent_index_hash: &StacksBlockId,
+        winner_txid: &Txid,
+        mature_rewards: &serde_json::Value,
+        serialized_events: Vec<serde_json::Value>,
+        serialized_txs: Vec<serde_json::Value>,
+    ) -> serde_json::Value {
+        json!({
+            "block_hash": format!("0x{}", block.block_hash()),
+            "block_height": metadata.block_height,
+            "burn_block_hash": format!("0x{}", metadata.burn_header_hash),
+            "burn_block_height": metadata.burn_header_height,
+            "miner_txid": format!("0x{}", winner_txid),
+            "burn_block_time": metadata.burn_header_timestamp,
+            "index_block_hash": format!("0x{}", metadata.index_block_hash()),
+            "parent_block_hash": format!("0x{}", block.header.parent_block),
+            "parent_index_block_hash": format!("0x{}", parent_index_hash),
+            "parent_microblock": format!("0x{}", block.header.parent_microblock),
+            "matured_miner_rewards": mature_rewards.clone(),
+            "events": serialized_events,
+            "transactions": serialized_txs,
         })
     }
 
@@ -204,7 +420,11 @@ impl EventObserver {
     }
 
     fn send_new_burn_block(&self, payload: &serde_json::Value) {
-        self.send_payload(payload, PATH_BURN_BLOCK_SUBMIT);
+        self.send_queued_payload(payload, PATH_BURN_BLOCK_SUBMIT);
+    }
+
+    fn send_reorg(&self, payload: &serde_json::Value) {
+        self.send_queued_payload(payload, PATH_REORG);
     }
 
     fn send(
@@ -234,23 +454,48 @@ impl EventObserver {
         }
 
         // Wrap events
-        let payload = json!({
-            "block_hash": format!("0x{}", chain_tip.block.block_hash()),
-            "block_height": chain_tip.metadata.block_height,
-            "burn_block_hash": format!("0x{}", chain_tip.metadata.burn_header_hash),
-            "burn_block_height": chain_tip.metadata.burn_header_height,
-            "miner_txid": format!("0x{}", winner_txid),
-            "burn_block_time": chain_tip.metadata.burn_header_timestamp,
-            "index_block_hash": format!("0x{}", chain_tip.metadata.index_block_hash()),
-            "parent_block_hash": format!("0x{}", chain_tip.block.header.parent_block),
-            "parent_index_block_hash": format!("0x{}", parent_index_hash),
-            "parent_microblock": format!("0x{}", chain_tip.block.header.parent_microblock),
-            "matured_miner_rewards": mature_rewards.clone(),
-            "events": serialized_events,
-            "transactions": serialized_txs,
-        });
+        let payload = EventObserver::make_new_block_payload(
+            &chain_tip.metadata,
+            &chain_tip.block,
+            parent_index_hash,
+            winner_txid,
+            mature_rewards,
+            serialized_events,
+            serialized_txs,
+        );
 
         // Send payload
+        self.send_queued_payload(&payload, PATH_BLOCK_PROCESSED);
+    }
+
+    fn send_replayed_block(
+        &self,
+        metadata: &StacksHeaderInfo,
+        block: &StacksBlock,
+        parent_index_hash: &StacksBlockId,
+        winner_txid: &Txid,
+        receipts: &[StoredTransactionReceipt],
+    ) {
+        let serialized_events: Vec<serde_json::Value> = receipts
+            .iter()
+            .flat_map(|receipt| receipt.events.iter().cloned())
+            .collect();
+        let serialized_txs: Vec<serde_json::Value> = receipts
+            .iter()
+            .map(EventObserver::make_replayed_block_txs_payload)
+            .collect();
+
+        // matured miner rewards are not recorded with the receipts
+        let payload = EventObserver::make_new_block_payload(
+            metadata,
+            block,
+            parent_index_hash,
+            winner_txid,
+            &json!([]),
+            serialized_events,
+            serialized_txs,
+        );
+
         self.send_payload(&payload, PATH_BLOCK_PROCESSED);
     }
 }
@@ -260,11 +505,21 @@ pub struct EventDispatcher {
     registered_observers: Vec<EventObserver>,
     contract_events_observers_lookup: HashMap<(QualifiedContractIdentifier, String), HashSet<u16>>,
     assets_observers_lookup: HashMap<AssetIdentifier, HashSet<u16>>,
+    contracts_observers_lookup: HashMap<QualifiedContractIdentifier, HashSet<u16>>,
     burn_block_observers_lookup: HashSet<u16>,
     mempool_observers_lookup: HashSet<u16>,
     stx_observers_lookup: HashSet<u16>,
+    ft_observers_lookup: HashSet<u16>,
+    nft_observers_lookup: HashSet<u16>,
+    print_observers_lookup: HashSet<u16>,
+    miner_rewards_observers_lookup: HashSet<u16>,
     any_event_observers_lookup: HashSet<u16>,
     boot_receipts: Arc<Mutex<Option<Vec<StacksTransactionReceipt>>>>,
+    /// where undelivered block and burn block payloads are kept, if anywhere
+    queue_db_path: Option<String>,
+    /// WebSocket server streaming events to connected clients, if enabled
+    event_stream: Option<EventStreamServer>,
+    webhooks: Vec<Webhook>,
 }
 
 impl BlockEventDispatcher for EventDispatcher {
@@ -305,6 +560,10 @@ impl BlockEventDispatcher for EventDispatcher {
     fn dispatch_boot_receipts(&mut self, receipts: Vec<StacksTransactionReceipt>) {
         self.process_boot_receipts(receipts)
     }
+
+    fn announce_reorg(&self, reorg: &StacksChainReorg) {
+        self.process_reorg(reorg)
+    }
 }
 
 impl EventDispatcher {
@@ -313,14 +572,49 @@ impl EventDispatcher {
             registered_observers: vec![],
             contract_events_observers_lookup: HashMap::new(),
             assets_observers_lookup: HashMap::new(),
+            contracts_observers_lookup: HashMap::new(),
             stx_observers_lookup: HashSet::new(),
+            ft_observers_lookup: HashSet::new(),
+            nft_observers_lookup: HashSet::new(),
+            print_observers_lookup: HashSet::new(),
+            miner_rewards_observers_lookup: HashSet::new(),
             any_event_observers_lookup: HashSet::new(),
             burn_block_observers_lookup: HashSet::new(),
             mempool_observers_lookup: HashSet::new(),
             boot_receipts: Arc::new(Mutex::new(None)),
+            queue_db_path: None,
+            event_stream: None,
+            webhooks: vec![],
         }
     }
 
+    /// Make a dispatcher that keeps block and burn block payloads in the sqlite DB at
+    /// `queue_db_path` until each observer acknowledges them.  Payloads left over from a previous
+    /// run are delivered before the next new one.
+    pub fn new_persistent(queue_db_path: String) -> EventDispatcher {
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.queue_db_path = Some(queue_db_path);
+        dispatcher
+    }
+
+    /// Also stream events to WebSocket clients that connect to `bind`.
+    pub fn serve_event_stream(&mut self, bind: &str) -> std::io::Result<()> {
+        self.event_stream = Some(EventStreamServer::spawn(bind)?);
+        Ok(())
+    }
+
+    /// Also notify a webhook of the blocks and events it is configured for.
+    pub fn register_webhook(&mut self, conf: &WebhookConfig) {
+        self.webhooks.push(Webhook::spawn(conf));
+    }
+
+    fn has_stream_clients(&self) -> bool {
+        self.event_stream
+            .as_ref()
+            .map(|stream| !stream.is_empty())
+            .unwrap_or(false)
+    }
+
     pub fn process_burn_block(
         &self,
         burn_block: &BurnchainHeaderHash,
@@ -387,73 +681,25 @@ impl EventDispatcher {
         for receipt in chain_tip.receipts.iter().chain(boot_receipts.iter()) {
             let tx_hash = receipt.transaction.txid();
             for event in receipt.events.iter() {
-                match event {
-                    StacksTransactionEvent::SmartContractEvent(event_data) => {
-                        if let Some(observer_indexes) =
-                            self.contract_events_observers_lookup.get(&event_data.key)
-                        {
-                            for o_i in observer_indexes {
-                                dispatch_matrix[*o_i as usize].insert(i);
-                            }
-                        }
-                    }
-                    StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(_))
-                    | StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(_))
-                    | StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(_))
-                    | StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(_)) => {
-                        for o_i in &self.stx_observers_lookup {
-                            dispatch_matrix[*o_i as usize].insert(i);
-                        }
-                    }
-                    StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(
-                        event_data,
-                    )) => {
-                        self.update_dispatch_matrix_if_observer_subscribed(
-                            &event_data.asset_identifier,
-                            i,
-                            &mut dispatch_matrix,
-                        );
-                    }
-                    StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(event_data)) => {
-                        self.update_dispatch_matrix_if_observer_subscribed(
-                            &event_data.asset_identifier,
-                            i,
-                            &mut dispatch_matrix,
-                        );
-                    }
-                    StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(event_data)) => {
-                        self.update_dispatch_matrix_if_observer_subscribed(
-                            &event_data.asset_identifier,
-                            i,
-                            &mut dispatch_matrix,
-                        );
-                    }
-                    StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(event_data)) => {
-                        self.update_dispatch_matrix_if_observer_subscribed(
-                            &event_data.asset_identifier,
-                            i,
-                            &mut dispatch_matrix,
-                        );
-                    }
+                for o_i in self.get_event_observers(event) {
+                    dispatch_matrix[o_i as usize].insert(i);
                 }
                 events.push((!receipt.post_condition_aborted, tx_hash, event));
-                for o_i in &self.any_event_observers_lookup {
-                    dispatch_matrix[*o_i as usize].insert(i);
-                }
                 i += 1;
             }
         }
 
-        if dispatch_matrix.len() > 0 {
+        if dispatch_matrix.len() > 0 || self.has_stream_clients() || self.webhooks.len() > 0 {
             let mature_rewards_vec = if let Some(rewards_info) = mature_rewards_info {
                 mature_rewards
                     .iter()
                     .map(|reward| {
                         json!({
-                            "recipient": reward.address.to_string(),
+                            "recipient": reward.recipient.to_string(),
                             "coinbase_amount": reward.coinbase.to_string(),
                             "tx_fees_anchored": reward.tx_fees_anchored.to_string(),
                             "tx_fees_streamed_confirmed": reward.tx_fees_streamed_confirmed.to_string(),
+                            "burned": reward.is_burned(),
                             "from_stacks_block_hash": format!("0x{}", &rewards_info.from_stacks_block_hash),
                             "from_index_consensus_hash": format!("0x{}", StacksBlockId::new(&rewards_info.from_block_consensus_hash,
                                                                                             &rewards_info.from_stacks_block_hash)),
@@ -465,6 +711,7 @@ impl EventDispatcher {
             };
 
             let mature_rewards = serde_json::Value::Array(mature_rewards_vec);
+            let no_mature_rewards = serde_json::Value::Array(vec![]);
 
             for (observer_id, filtered_events_ids) in dispatch_matrix.iter().enumerate() {
                 let filtered_events: Vec<_> = filtered_events_ids
@@ -472,15 +719,160 @@ impl EventDispatcher {
                     .map(|event_id| (*event_id, &events[*event_id]))
                     .collect();
 
+                let wants_mature_rewards = self
+                    .miner_rewards_observers_lookup
+                    .contains(&(observer_id as u16))
+                    || self
+                        .any_event_observers_lookup
+                        .contains(&(observer_id as u16));
+
                 self.registered_observers[observer_id].send(
                     filtered_events,
                     chain_tip,
                     parent_index_hash,
                     &boot_receipts,
                     &winner_txid,
+                    if wants_mature_rewards {
+                        &mature_rewards
+                    } else {
+                        &no_mature_rewards
+                    },
+                );
+            }
+
+            if let Some(ref event_stream) = self.event_stream {
+                let serialized_txs: Vec<_> = chain_tip
+                    .receipts
+                    .iter()
+                    .chain(boot_receipts.iter())
+                    .enumerate()
+                    .map(|(tx_index, receipt)| {
+                        EventObserver::make_new_block_txs_payload(receipt, tx_index as u32)
+                    })
+                    .collect();
+
+                event_stream.broadcast("block", |subscription| {
+                    let serialized_events = events
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (_, _, event))| subscription.wants_event(event))
+                        .map(|(event_index, (committed, txid, event))| {
+                            event.json_serialize(event_index, txid, *committed)
+                        })
+                        .collect();
+                    Some(EventObserver::make_new_block_payload(
+                        &chain_tip.metadata,
+                        &chain_tip.block,
+                        parent_index_hash,
+                        &winner_txid,
+                        if subscription.wants_miner_rewards() {
+                            &mature_rewards
+                        } else {
+                            &no_mature_rewards
+                        },
+                        serialized_events,
+                        serialized_txs.clone(),
+                    ))
+                });
+            }
+
+            if self.webhooks.len() > 0 {
+                let receipts: Vec<_> = chain_tip
+                    .receipts
+                    .iter()
+                    .chain(boot_receipts.iter())
+                    .collect();
+                let serialized_txs: Vec<_> = receipts
+                    .iter()
+                    .enumerate()
+                    .map(|(tx_index, receipt)| {
+                        EventObserver::make_new_block_txs_payload(receipt, tx_index as u32)
+                    })
+                    .collect();
+                let block_events: Vec<BlockEvent> = receipts
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(tx_index, receipt)| {
+                        let txid = receipt.transaction.txid();
+                        let committed = !receipt.post_condition_aborted;
+                        receipt
+                            .events
+                            .iter()
+                            .map(move |event| (tx_index, committed, txid, event))
+                    })
+                    .collect();
+
+                let mut block = EventObserver::make_new_block_payload(
+                    &chain_tip.metadata,
+                    &chain_tip.block,
+                    parent_index_hash,
+                    &winner_txid,
                     &mature_rewards,
+                    vec![],
+                    vec![],
                 );
+                if let Some(fields) = block.as_object_mut() {
+                    fields.remove("events");
+                    fields.remove("transactions");
+                }
+
+                for webhook in self.webhooks.iter() {
+                    webhook.process_block(&block, &serialized_txs, &block_events);
+                }
+            }
+        }
+    }
+
+    /// Tell every observer and stream client that the canonical Stacks chain tip moved to another
+    /// fork, so they can roll back the orphaned blocks.
+    pub fn process_reorg(&self, reorg: &StacksChainReorg) {
+        if self.registered_observers.len() == 0 && !self.has_stream_clients() {
+            return;
+        }
+
+        let payload = EventObserver::make_reorg_payload(reorg);
+
+        for observer in self.registered_observers.iter() {
+            observer.send_reorg(&payload);
+        }
+
+        if let Some(ref event_stream) = self.event_stream {
+            event_stream.broadcast("reorg", |_| Some(payload.clone()));
+        }
+    }
+
+    /// Stream newly-stored unconfirmed microblocks to WebSocket clients.  Event observers are not
+    /// sent microblocks.
+    pub fn process_new_microblocks(
+        &self,
+        parent_index_block_hash: &StacksBlockId,
+        microblocks: &[StacksMicroblock],
+    ) {
+        if microblocks.len() == 0 {
+            return;
+        }
+        if let Some(ref event_stream) = self.event_stream {
+            if event_stream.is_empty() {
+                return;
             }
+            let payload =
+                EventObserver::make_new_microblocks_payload(parent_index_block_hash, microblocks);
+            event_stream.broadcast("microblocks", |_| Some(payload.clone()));
+        }
+    }
+
+    /// Re-send a processed block's `new_block` payload, rebuilt from its stored transaction
+    /// receipts, to every registered observer regardless of its event subscriptions.
+    pub fn replay_block(
+        &self,
+        metadata: &StacksHeaderInfo,
+        block: &StacksBlock,
+        parent_index_hash: &StacksBlockId,
+        winner_txid: &Txid,
+        receipts: &[StoredTransactionReceipt],
+    ) {
+        for observer in self.registered_observers.iter() {
+            observer.send_replayed_block(metadata, block, parent_index_hash, winner_txid, receipts);
         }
     }
 
@@ -495,7 +887,7 @@ impl EventDispatcher {
                     || self.any_event_observers_lookup.contains(&(*obs_id as u16))
             })
             .collect();
-        if interested_observers.len() < 1 {
+        if interested_observers.len() < 1 && !self.has_stream_clients() {
             return;
         }
 
@@ -504,6 +896,16 @@ impl EventDispatcher {
         for (_, observer) in interested_observers.iter() {
             observer.send_new_mempool_txs(&payload);
         }
+
+        if let Some(ref event_stream) = self.event_stream {
+            event_stream.broadcast("mempool_txs", |subscription| {
+                if subscription.wants_mempool() {
+                    Some(payload.clone())
+                } else {
+                    None
+                }
+            });
+        }
     }
 
     pub fn process_new_attachments(&self, attachments: &Vec<AttachmentInstance>) {
@@ -527,24 +929,97 @@ impl EventDispatcher {
         self.boot_receipts = Arc::new(Mutex::new(Some(receipts)));
     }
 
-    fn update_dispatch_matrix_if_observer_subscribed(
+    /// Indexes of the observers subscribed to a transaction event
+    fn get_event_observers(&self, event: &StacksTransactionEvent) -> HashSet<u16> {
+        let mut observers = self.any_event_observers_lookup.clone();
+        match event {
+            StacksTransactionEvent::SmartContractEvent(event_data) => {
+                if let Some(observer_indexes) =
+                    self.contract_events_observers_lookup.get(&event_data.key)
+                {
+                    observers.extend(observer_indexes);
+                }
+                if let Some(observer_indexes) =
+                    self.contracts_observers_lookup.get(&event_data.key.0)
+                {
+                    observers.extend(observer_indexes);
+                }
+                observers.extend(&self.print_observers_lookup);
+            }
+            StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(_))
+            | StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(_))
+            | StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(_))
+            | StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(_))
+            | StacksTransactionEvent::STXEvent(STXEventType::STXUnlockEvent(_)) => {
+                observers.extend(&self.stx_observers_lookup);
+            }
+            StacksTransactionEvent::STXEvent(STXEventType::STXMinerRewardEvent(_)) => {
+                observers.extend(&self.stx_observers_lookup);
+                observers.extend(&self.miner_rewards_observers_lookup);
+            }
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(event_data)) => {
+                self.add_asset_observers(&event_data.asset_identifier, &mut observers);
+                observers.extend(&self.nft_observers_lookup);
+            }
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(event_data)) => {
+                self.add_asset_observers(&event_data.asset_identifier, &mut observers);
+                observers.extend(&self.nft_observers_lookup);
+            }
+            StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(event_data)) => {
+                self.add_asset_observers(&event_data.asset_identifier, &mut observers);
+                observers.extend(&self.ft_observers_lookup);
+            }
+            StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(event_data)) => {
+                self.add_asset_observers(&event_data.asset_identifier, &mut observers);
+                observers.extend(&self.ft_observers_lookup);
+            }
+        }
+        observers
+    }
+
+    fn add_asset_observers(
         &self,
         asset_identifier: &AssetIdentifier,
-        event_index: usize,
-        dispatch_matrix: &mut Vec<HashSet<usize>>,
+        observers: &mut HashSet<u16>,
     ) {
         if let Some(observer_indexes) = self.assets_observers_lookup.get(asset_identifier) {
-            for o_i in observer_indexes {
-                dispatch_matrix[*o_i as usize].insert(event_index);
-            }
+            observers.extend(observer_indexes);
+        }
+        if let Some(observer_indexes) = self
+            .contracts_observers_lookup
+            .get(&asset_identifier.contract_identifier)
+        {
+            observers.extend(observer_indexes);
         }
     }
 
     pub fn register_observer(&mut self, conf: &EventObserverConfig) {
         // let event_observer = EventObserver::new(&conf.address, conf.port);
         info!("Registering event observer at: {}", conf.endpoint);
+        let queue = self.queue_db_path.as_ref().map(|db_path| {
+            match EventObserver::open_queue(db_path)
+                .and_then(|conn| EventObserver::get_queued_payloads(&conn, &conf.endpoint))
+            {
+                Ok(pending) if pending.len() > 0 => {
+                    info!(
+                        "Event observer at {} has {} undelivered payloads; they will be sent first",
+                        conf.endpoint,
+                        pending.len()
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Failed to read event queue {}: {:?}", db_path, &e);
+                }
+            }
+            EventQueue {
+                db_path: db_path.clone(),
+                delivery_lock: Arc::new(Mutex::new(())),
+            }
+        });
         let event_observer = EventObserver {
             endpoint: conf.endpoint.clone(),
+            queue,
         };
 
         let observer_index = self.registered_observers.len() as u16;
@@ -575,6 +1050,24 @@ impl EventDispatcher {
                 EventKeyType::STXEvent => {
                     self.stx_observers_lookup.insert(observer_index);
                 }
+                EventKeyType::FTEvent => {
+                    self.ft_observers_lookup.insert(observer_index);
+                }
+                EventKeyType::NFTEvent => {
+                    self.nft_observers_lookup.insert(observer_index);
+                }
+                EventKeyType::PrintEvent => {
+                    self.print_observers_lookup.insert(observer_index);
+                }
+                EventKeyType::MinerRewards => {
+                    self.miner_rewards_observers_lookup.insert(observer_index);
+                }
+                EventKeyType::ContractEvent(contract_identifier) => {
+                    self.contracts_observers_lookup
+                        .entry(contract_identifier.clone())
+                        .or_insert_with(HashSet::new)
+                        .insert(observer_index);
+                }
                 EventKeyType::AssetEvent(event_key) => {
                     match self.assets_observers_lookup.entry(event_key.clone()) {
                         Entry::Occupied(observer_indexes) => {
@@ -596,3 +1089,144 @@ impl EventDispatcher {
         self.registered_observers.push(event_observer);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stacks::chainstate::stacks::events::{
+        FTTransferEventData, NFTMintEventData, STXTransferEventData, SmartContractEventData,
+    };
+    use stacks::vm::types::{BuffData, PrincipalData};
+    use std::convert::TryInto;
+
+    fn make_observer(events_keys: Vec<EventKeyType>) -> EventObserverConfig {
+        EventObserverConfig {
+            endpoint: "localhost:3700".to_string(),
+            events_keys,
+        }
+    }
+
+    #[test]
+    fn observers_filter_by_contract_and_event_class() {
+        let names =
+            QualifiedContractIdentifier::parse("STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.names")
+                .unwrap();
+        let token =
+            QualifiedContractIdentifier::parse("STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.token")
+                .unwrap();
+        let principal = PrincipalData::from(names.issuer.clone());
+
+        let mut dispatcher = EventDispatcher::new();
+        // 0: everything
+        dispatcher.register_observer(&make_observer(vec![EventKeyType::AnyEvent]));
+        // 1: everything about the names contract
+        dispatcher.register_observer(&make_observer(vec![EventKeyType::ContractEvent(
+            names.clone(),
+        )]));
+        // 2: fungible token events, and prints
+        dispatcher.register_observer(&make_observer(vec![
+            EventKeyType::FTEvent,
+            EventKeyType::PrintEvent,
+        ]));
+        // 3: stx events
+        dispatcher.register_observer(&make_observer(vec![EventKeyType::STXEvent]));
+        // 4: non-fungible token events, and miner rewards
+        dispatcher.register_observer(&make_observer(vec![
+            EventKeyType::NFTEvent,
+            EventKeyType::MinerRewards,
+        ]));
+
+        let names_print = StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+            key: (names.clone(), "print".to_string()),
+            value: Value::Int(1),
+        });
+        let token_print = StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+            key: (token.clone(), "print".to_string()),
+            value: Value::Int(1),
+        });
+        let names_mint =
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(NFTMintEventData {
+                asset_identifier: AssetIdentifier {
+                    contract_identifier: names.clone(),
+                    asset_name: "names".to_string().try_into().unwrap(),
+                },
+                recipient: principal.clone(),
+                value: Value::Int(1),
+            }));
+        let token_transfer =
+            StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(FTTransferEventData {
+                asset_identifier: AssetIdentifier {
+                    contract_identifier: token.clone(),
+                    asset_name: "token".to_string().try_into().unwrap(),
+                },
+                sender: principal.clone(),
+                recipient: principal.clone(),
+                amount: 1,
+            }));
+        let stx_transfer = StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+            STXTransferEventData {
+                sender: principal.clone(),
+                recipient: principal.clone(),
+                amount: 1,
+                memo: BuffData::empty(),
+                long_memo: None,
+            },
+        ));
+
+        let observers = |event| {
+            let mut observers: Vec<_> = dispatcher.get_event_observers(event).into_iter().collect();
+            observers.sort();
+            observers
+        };
+        assert_eq!(observers(&names_print), vec![0, 1, 2]);
+        assert_eq!(observers(&token_print), vec![0, 2]);
+        assert_eq!(observers(&names_mint), vec![0, 1, 4]);
+        assert_eq!(observers(&token_transfer), vec![0, 2]);
+        assert_eq!(observers(&stx_transfer), vec![0, 3]);
+    }
+
+    #[test]
+    fn queued_payloads_are_kept_in_order_per_endpoint() {
+        let db_path =
+            "/tmp/stacks-node-tests/queued_payloads_are_kept_in_order_per_endpoint.sqlite";
+        if std::fs::metadata(db_path).is_ok() {
+            std::fs::remove_file(db_path).unwrap();
+        }
+        std::fs::create_dir_all("/tmp/stacks-node-tests").unwrap();
+
+        let conn = EventObserver::open_queue(db_path).unwrap();
+        EventObserver::enqueue_payload(&conn, "a:3700", PATH_BLOCK_PROCESSED, &json!({"n": 1}))
+            .unwrap();
+        EventObserver::enqueue_payload(&conn, "b:3700", PATH_BLOCK_PROCESSED, &json!({"n": 2}))
+            .unwrap();
+        EventObserver::enqueue_payload(&conn, "a:3700", PATH_BURN_BLOCK_SUBMIT, &json!({"n": 3}))
+            .unwrap();
+
+        let pending = EventObserver::get_queued_payloads(&conn, "a:3700").unwrap();
+        let paths_and_payloads: Vec<_> = pending
+            .iter()
+            .map(|(_, path, payload)| (path.as_str(), payload.as_str()))
+            .collect();
+        assert_eq!(
+            paths_and_payloads,
+            vec![
+                (PATH_BLOCK_PROCESSED, r#"{"n":1}"#),
+                (PATH_BURN_BLOCK_SUBMIT, r#"{"n":3}"#)
+            ]
+        );
+
+        // delivered payloads are gone, even after re-opening the queue
+        EventObserver::delete_queued_payload(&conn, pending[0].0).unwrap();
+        drop(conn);
+        let conn = EventObserver::open_queue(db_path).unwrap();
+        let pending = EventObserver::get_queued_payloads(&conn, "a:3700").unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].2, r#"{"n":3}"#);
+        assert_eq!(
+            EventObserver::get_queued_payloads(&conn, "b:3700")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! WebSocket event streaming.
+//!
+//! An alternative to `[[events_observer]]` for clients that cannot host an HTTP endpoint, such as
+//! browser dapps.  Clients connect to `ws://<ws_bind>/v2/events`, naming the events they want in
+//! the `keys` query parameter with the same syntax as an observer's `events_keys`, and are sent
+//! one JSON text message per new block, microblock stream extension or batch of mempool
+//! transactions.  Clients are never read from except to answer pings, and a client that cannot
+//! keep up is disconnected.
+
+use std::io;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use http_types::Url;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http;
+use tungstenite::{Message, WebSocket};
+
+use stacks::chainstate::stacks::events::{
+    FTEventType, NFTEventType, STXEventType, StacksTransactionEvent,
+};
+
+use super::config::EventKeyType;
+
+/// Path clients connect to
+pub const EVENT_STREAM_PATH: &str = "/v2/events";
+
+/// How long a client has to complete the WebSocket handshake
+const EVENT_STREAM_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a single message may take to write before the client is dropped
+const EVENT_STREAM_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The events a stream client subscribed to.  Every client is sent new blocks and microblocks;
+/// `keys` selects the transaction events included in each block, and whether the client is
+/// sent mempool transactions and matured miner rewards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventSubscription {
+    pub keys: Vec<EventKeyType>,
+}
+
+impl EventSubscription {
+    /// Parse the query string of a client's handshake, e.g. `keys=stx,memtx`.
+    pub fn from_query(query: Option<&str>) -> Result<EventSubscription, String> {
+        let url = Url::parse(&format!(
+            "ws://localhost{}?{}",
+            EVENT_STREAM_PATH,
+            query.unwrap_or("")
+        ))
+        .map_err(|e| format!("Invalid query string: {}", e))?;
+
+        let mut keys = vec![];
+        for (name, value) in url.query_pairs() {
+            if name != "keys" {
+                continue;
+            }
+            for raw_key in value.split(',').filter(|raw_key| raw_key.len() > 0) {
+                match EventKeyType::from_string(raw_key) {
+                    Some(key) => keys.push(key),
+                    None => return Err(format!("Unknown event key '{}'", raw_key)),
+                }
+            }
+        }
+        Ok(EventSubscription { keys })
+    }
+
+    fn has(&self, key: &EventKeyType) -> bool {
+        self.keys.contains(key) || self.keys.contains(&EventKeyType::AnyEvent)
+    }
+
+    pub fn wants_mempool(&self) -> bool {
+        self.has(&EventKeyType::MemPoolTransactions)
+    }
+
+    pub fn wants_miner_rewards(&self) -> bool {
+        self.has(&EventKeyType::MinerRewards)
+    }
+
+    /// Should this transaction event be included in the blocks sent to the client?
+    pub fn wants_event(&self, event: &StacksTransactionEvent) -> bool {
+        let asset_identifier = match event {
+            StacksTransactionEvent::SmartContractEvent(event_data) => {
+                return self.has(&EventKeyType::PrintEvent)
+                    || self.has(&EventKeyType::SmartContractEvent(event_data.key.clone()))
+                    || self.has(&EventKeyType::ContractEvent(event_data.key.0.clone()));
+            }
+            StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(_))
+            | StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(_))
+            | StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(_))
+            | StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(_))
+            | StacksTransactionEvent::STXEvent(STXEventType::STXUnlockEvent(_)) => {
+                return self.has(&EventKeyType::STXEvent);
+            }
+            StacksTransactionEvent::STXEvent(STXEventType::STXMinerRewardEvent(_)) => {
+                return self.has(&EventKeyType::STXEvent) || self.wants_miner_rewards();
+            }
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(event_data)) => {
+                if self.has(&EventKeyType::NFTEvent) {
+                    return true;
+                }
+                &event_data.asset_identifier
+            }
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(event_data)) => {
+                if self.has(&EventKeyType::NFTEvent) {
+                    return true;
+                }
+                &event_data.asset_identifier
+            }
+            StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(event_data)) => {
+                if self.has(&EventKeyType::FTEvent) {
+                    return true;
+                }
+                &event_data.asset_identifier
+            }
+            StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(event_data)) => {
+                if self.has(&EventKeyType::FTEvent) {
+                    return true;
+                }
+                &event_data.asset_identifier
+            }
+        };
+        self.has(&EventKeyType::AssetEvent(asset_identifier.clone()))
+            || self.has(&EventKeyType::ContractEvent(
+                asset_identifier.contract_identifier.clone(),
+            ))
+    }
+}
+
+struct EventStreamClient {
+    addr: SocketAddr,
+    socket: WebSocket<TcpStream>,
+    subscription: EventSubscription,
+}
+
+impl EventStreamClient {
+    fn send(&mut self, message: String) -> Result<(), tungstenite::Error> {
+        // answer any pings, and notice any close, the client sent since the last message
+        self.socket.get_mut().set_nonblocking(true)?;
+        loop {
+            match self.socket.read_message() {
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(ref e)) if e.kind() == ErrorKind::WouldBlock => {
+                    break;
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+        self.socket.get_mut().set_nonblocking(false)?;
+        self.socket.write_message(Message::Text(message))
+    }
+}
+
+/// The WebSocket server.  Clones share the same set of clients.
+#[derive(Clone)]
+pub struct EventStreamServer {
+    local_addr: SocketAddr,
+    clients: Arc<Mutex<Vec<EventStreamClient>>>,
+}
+
+impl EventStreamServer {
+    /// Listen for stream clients on `bind`, accepting them on a background thread.
+    pub fn spawn(bind: &str) -> io::Result<EventStreamServer> {
+        let listener = TcpListener::bind(bind)?;
+        let server = EventStreamServer {
+            local_addr: listener.local_addr()?,
+            clients: Arc::new(Mutex::new(vec![])),
+        };
+
+        let clients = server.clients.clone();
+        thread::Builder::new()
+            .name("event-stream".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let clients = clients.clone();
+                            thread::spawn(move || {
+                                EventStreamServer::accept_client(stream, clients)
+                            });
+                        }
+                        Err(e) => {
+                            warn!("Failed to accept event stream connection: {:?}", &e);
+                        }
+                    }
+                }
+            })?;
+
+        info!(
+            "Streaming events at ws://{}{}",
+            &server.local_addr, EVENT_STREAM_PATH
+        );
+        Ok(server)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Number of connected clients
+    pub fn len(&self) -> usize {
+        match self.clients.lock() {
+            Ok(clients) => clients.len(),
+            Err(_) => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn error_response(status: u16, message: String) -> ErrorResponse {
+        http::Response::builder()
+            .status(status)
+            .body(Some(message))
+            .expect("BUG: failed to build HTTP error response")
+    }
+
+    fn accept_client(stream: TcpStream, clients: Arc<Mutex<Vec<EventStreamClient>>>) {
+        let addr = match stream.peer_addr() {
+            Ok(addr) => addr,
+            Err(e) => {
+                debug!("Event stream client went away: {:?}", &e);
+                return;
+            }
+        };
+        if let Err(e) = stream
+            .set_read_timeout(Some(EVENT_STREAM_HANDSHAKE_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(EVENT_STREAM_WRITE_TIMEOUT)))
+        {
+            warn!(
+                "Failed to set event stream timeouts for {}: {:?}",
+                &addr, &e
+            );
+            return;
+        }
+
+        let mut subscription = None;
+        let handshake = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+            if request.uri().path() != EVENT_STREAM_PATH {
+                return Err(EventStreamServer::error_response(
+                    404,
+                    format!("Events are streamed at {}", EVENT_STREAM_PATH),
+                ));
+            }
+            match EventSubscription::from_query(request.uri().query()) {
+                Ok(parsed) => {
+                    subscription = Some(parsed);
+                    Ok(response)
+                }
+                Err(msg) => Err(EventStreamServer::error_response(400, msg)),
+            }
+        });
+        let socket = match handshake {
+            Ok(socket) => socket,
+            Err(e) => {
+                debug!("Event stream handshake with {} failed: {:?}", &addr, &e);
+                return;
+            }
+        };
+        let subscription =
+            subscription.expect("BUG: accepted an event stream without a subscription");
+
+        debug!(
+            "Event stream client {} subscribed to {:?}",
+            &addr, &subscription.keys
+        );
+        match clients.lock() {
+            Ok(mut clients) => clients.push(EventStreamClient {
+                addr,
+                socket,
+                subscription,
+            }),
+            Err(_) => {
+                error!("Event stream client list mutex is poisoned");
+            }
+        }
+    }
+
+    /// Send a `{"type": kind, "payload": ...}` message to each client, with the payload
+    /// `make_payload` builds for its subscription.  Clients for which it returns `None` are
+    /// skipped.  Clients that have gone away or cannot keep up are dropped.
+    pub fn broadcast<F>(&self, kind: &str, make_payload: F)
+    where
+        F: Fn(&EventSubscription) -> Option<serde_json::Value>,
+    {
+        let mut clients = match self.clients.lock() {
+            Ok(clients) => clients,
+            Err(_) => {
+                error!("Event stream client list mutex is poisoned");
+                return;
+            }
+        };
+
+        let mut i = 0;
+        while i < clients.len() {
+            let payload = match make_payload(&clients[i].subscription) {
+                Some(payload) => payload,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let message = json!({
+                "type": kind,
+                "payload": payload,
+            })
+            .to_string();
+
+            if let Err(e) = clients[i].send(message) {
+                let client = clients.remove(i);
+                debug!("Dropping event stream client {}: {:?}", &client.addr, &e);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::TryInto;
+    use std::time::Instant;
+
+    use stacks::chainstate::stacks::events::{FTTransferEventData, STXTransferEventData};
+    use stacks::vm::types::{
+        AssetIdentifier, BuffData, PrincipalData, QualifiedContractIdentifier,
+    };
+
+    fn make_ft_transfer(contract: &str, asset_name: &str) -> StacksTransactionEvent {
+        StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(FTTransferEventData {
+            asset_identifier: AssetIdentifier {
+                contract_identifier: QualifiedContractIdentifier::parse(contract).unwrap(),
+                asset_name: asset_name.to_string().try_into().unwrap(),
+            },
+            sender: PrincipalData::parse("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR").unwrap(),
+            recipient: PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+            amount: 1,
+        }))
+    }
+
+    #[test]
+    fn subscriptions_filter_events() {
+        let stx_transfer = StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+            STXTransferEventData {
+                sender: PrincipalData::parse("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR").unwrap(),
+                recipient: PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                    .unwrap(),
+                amount: 1,
+                memo: BuffData::empty(),
+                long_memo: None,
+            },
+        ));
+        let token = make_ft_transfer("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR.token", "tok");
+        let other_token =
+            make_ft_transfer("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR.other", "tok");
+
+        let none = EventSubscription::from_query(None).unwrap();
+        assert!(!none.wants_event(&stx_transfer));
+        assert!(!none.wants_mempool());
+
+        let subscription = EventSubscription::from_query(Some(
+            "keys=stx,memtx,ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR.token",
+        ))
+        .unwrap();
+        assert!(subscription.wants_event(&stx_transfer));
+        assert!(subscription.wants_event(&token));
+        assert!(!subscription.wants_event(&other_token));
+        assert!(subscription.wants_mempool());
+        assert!(!subscription.wants_miner_rewards());
+
+        // percent-encoded keys are decoded
+        let any = EventSubscription::from_query(Some("keys=%2A")).unwrap();
+        assert!(any.wants_event(&other_token));
+        assert!(any.wants_miner_rewards());
+
+        assert!(EventSubscription::from_query(Some("keys=stx,bogus")).is_err());
+    }
+
+    #[test]
+    fn clients_are_sent_their_payloads() {
+        let server = EventStreamServer::spawn("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let stream = TcpStream::connect(&addr).unwrap();
+        let (mut socket, _) = tungstenite::client(
+            format!("ws://{}{}?keys=memtx", &addr, EVENT_STREAM_PATH).as_str(),
+            stream,
+        )
+        .unwrap();
+
+        // a client on the wrong path is turned away
+        let stream = TcpStream::connect(&addr).unwrap();
+        assert!(tungstenite::client(format!("ws://{}/v2/info", &addr).as_str(), stream).is_err());
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while server.is_empty() {
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(server.len(), 1);
+
+        server.broadcast("block", |_| Some(json!({"block_height": 1})));
+        server.broadcast("mempool_tx", |subscription| {
+            if subscription.wants_mempool() {
+                Some(json!(["0x00"]))
+            } else {
+                None
+            }
+        });
+
+        for expected in [
+            json!({"type": "block", "payload": {"block_height": 1}}),
+            json!({"type": "mempool_tx", "payload": ["0x00"]}),
+        ]
+        .iter()
+        {
+            match socket.read_message().unwrap() {
+                Message::Text(text) => {
+                    let message: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    assert_eq!(&message, expected);
+                }
+                msg => panic!("Unexpected message {:?}", &msg),
+            }
+        }
+
+        // a client that hangs up is dropped on the next broadcast
+        socket.close(None).unwrap();
+        drop(socket);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !server.is_empty() {
+            assert!(Instant::now() < deadline);
+            server.broadcast("block", |_| Some(json!({"block_height": 2})));
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
@@ -1,5 +1,12 @@
+use std::fs;
+
+use stacks::chainstate::stacks::db::{GenesisBalancesAudit, StacksChainState};
+use stacks::net::RPCGenesisAuditData;
+use stacks::util::hash::Sha256Sum;
 use stx_genesis::GenesisData;
 
+use crate::Config;
+
 // Uses the full production genesis chainstate.txt data when compiled regularly, .e.g. `cargo build`.
 // Uses a small test chainstate.txt file when tests are ran regularly, .e.g. `cargo test`.
 // The production file can be used in tests by specifying the `prod-genesis-chainstate` feature
@@ -14,3 +21,79 @@ lazy_static! {
 lazy_static! {
     pub static ref GENESIS_DATA: GenesisData = GenesisData::new(true);
 }
+
+/// Load the genesis data the node boots from: the file at `node.genesis_data_path` if one is
+/// configured, or the bundled chainstate.txt otherwise.  Panics if the file can't be loaded.
+pub fn load_genesis_data(config: &Config) -> GenesisData {
+    match config.node.genesis_data_path {
+        Some(ref path) => GenesisData::from_file(path)
+            .unwrap_or_else(|e| panic!("Failed to load genesis data from {}: {}", path, e)),
+        None => GENESIS_DATA.clone(),
+    }
+}
+
+/// The SHA256 of the genesis data the node boots from.  Panics if the configured genesis data
+/// file can't be read.
+pub fn genesis_chainstate_hash(config: &Config) -> Sha256Sum {
+    match config.node.genesis_data_path {
+        Some(ref path) => {
+            let data = fs::read(path)
+                .unwrap_or_else(|e| panic!("Failed to read genesis data from {}: {}", path, e));
+            Sha256Sum::from_data(&data)
+        }
+        None => Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH)
+            .expect("FATAL: bundled chainstate.txt hash is not a SHA256"),
+    }
+}
+
+/// Make the audit that the bulk genesis balances are checked against on first boot, if the node
+/// is configured to audit them.  Panics if the genesis data is not the one the node was
+/// configured to expect.
+pub fn make_genesis_balances_audit(config: &Config) -> Option<GenesisBalancesAudit> {
+    if !config.node.genesis_audit {
+        return None;
+    }
+
+    if let Some(ref expected_hash) = config.node.expected_genesis_chainstate_hash {
+        let genesis_hash = genesis_chainstate_hash(config).to_hex();
+        if !expected_hash.eq_ignore_ascii_case(&genesis_hash) {
+            panic!(
+                "Genesis audit failed: the genesis data has SHA256 {}, but `node.expected_genesis_chainstate_hash` is {}",
+                genesis_hash,
+                expected_hash
+            );
+        }
+    }
+
+    let balances_total = load_genesis_data(config).read_balances_total();
+    Some(GenesisBalancesAudit {
+        expected_count: balances_total.count,
+        expected_ustx: match config.node.expected_genesis_ustx {
+            Some(ustx) => ustx as u128,
+            None => balances_total.total_ustx,
+        },
+    })
+}
+
+/// Compare the liquid microSTX at genesis to what the node expects, so the audit result can be
+/// reported on GET /v2/info.
+pub fn get_genesis_audit_data(
+    config: &Config,
+    chainstate: &StacksChainState,
+) -> Option<RPCGenesisAuditData> {
+    let audit = make_genesis_balances_audit(config)?;
+    let genesis = match StacksChainState::get_genesis_header_info(chainstate.db()) {
+        Ok(genesis) => genesis,
+        Err(e) => {
+            warn!("Failed to load genesis header for audit: {:?}", &e);
+            return None;
+        }
+    };
+
+    let expected_liquid_ustx = audit.expected_ustx + config.get_initial_liquid_ustx();
+    Some(RPCGenesisAuditData {
+        expected_liquid_ustx: expected_liquid_ustx as u64,
+        genesis_liquid_ustx: genesis.total_liquid_ustx as u64,
+        passed: expected_liquid_ustx == genesis.total_liquid_ustx,
+    })
+}
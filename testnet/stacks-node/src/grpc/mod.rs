@@ -0,0 +1,24 @@
+//! A gRPC service for the node's core queries, served alongside the HTTP RPC interface when
+//! `node.grpc_bind` is set and the node is built with the `grpc` feature.
+//!
+//! The service (`stacks.node.v1.StacksNode`, see `proto/stacks_node.proto`) reads accounts,
+//! blocks and headers straight out of the node's chainstate, runs read-only contract calls
+//! against it, and broadcasts transactions by posting them to the node's own RPC interface, just
+//! as a wallet would.  Messages are uncompressed protobufs; `GetHeaders` streams its response.
+#![allow(unused_variables)]
+
+use super::config::Config;
+
+#[cfg(feature = "grpc")]
+mod messages;
+#[cfg(feature = "grpc")]
+mod server;
+#[cfg(feature = "grpc")]
+mod service;
+
+pub fn start_serving_grpc(config: Config) {
+    #[cfg(feature = "grpc")]
+    server::start_serving_grpc(config);
+    #[cfg(not(feature = "grpc"))]
+    warn!("gRPC: grpc_bind is set, but this node was built without the `grpc` feature");
+}
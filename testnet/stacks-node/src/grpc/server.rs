@@ -0,0 +1,204 @@
+//! gRPC over HTTP/2:  each call is a `POST` to `/<service>/<method>` whose body is one
+//! length-prefixed message.  The response body is the method's length-prefixed messages (one,
+//! or one per streamed item), followed by trailers giving the call's status.
+
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use h2::server::SendResponse;
+use h2::RecvStream;
+use http::{HeaderMap, HeaderValue, Response};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::service::{handle_call, GrpcContext, GrpcError};
+use crate::config::Config;
+
+/// Largest request message the server will read (the gRPC default)
+const MAX_REQUEST_LEN: usize = 4 * 1024 * 1024;
+
+/// Prefix a message with its compression flag (never set) and its length
+fn frame_message(message: &[u8]) -> Bytes {
+    let mut framed = Vec::with_capacity(5 + message.len());
+    framed.push(0);
+    framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    framed.extend_from_slice(message);
+    Bytes::from(framed)
+}
+
+/// Read a call's request, which must be exactly one uncompressed message
+async fn read_request(body: &mut RecvStream) -> Result<Vec<u8>, GrpcError> {
+    let mut buf = vec![];
+    while let Some(data) = body.data().await {
+        let data = data.map_err(|e| GrpcError::Internal(format!("{}", e)))?;
+        let _ = body.flow_control().release_capacity(data.len());
+        buf.extend_from_slice(&data);
+        if buf.len() > MAX_REQUEST_LEN + 5 {
+            return Err(GrpcError::InvalidArgument(
+                "Request is too large".to_string(),
+            ));
+        }
+    }
+
+    if buf.len() < 5 {
+        return Err(GrpcError::InvalidArgument(
+            "Missing request message".to_string(),
+        ));
+    }
+    if buf[0] != 0 {
+        return Err(GrpcError::Unimplemented(
+            "Compressed messages are not supported".to_string(),
+        ));
+    }
+    let mut len_bytes = [0u8; 4];
+    len_bytes.copy_from_slice(&buf[1..5]);
+    if buf.len() != 5 + u32::from_be_bytes(len_bytes) as usize {
+        return Err(GrpcError::InvalidArgument(
+            "Expected exactly one request message".to_string(),
+        ));
+    }
+    Ok(buf.split_off(5))
+}
+
+/// `grpc-message` is percent-encoded
+fn encode_status_message(message: &str) -> String {
+    message
+        .bytes()
+        .map(|b| {
+            if b >= 0x20 && b <= 0x7e && b != b'%' {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+async fn serve_call(
+    request: http::Request<RecvStream>,
+    mut respond: SendResponse<Bytes>,
+    ctx: Arc<GrpcContext>,
+) {
+    let path = request.uri().path().to_string();
+    let mut body = request.into_body();
+    let result = match read_request(&mut body).await {
+        Ok(message) => {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || handle_call(&ctx, &path, &message))
+                .await
+                .unwrap_or_else(|e| Err(GrpcError::Internal(format!("{}", e))))
+        }
+        Err(e) => Err(e),
+    };
+
+    let response = Response::builder()
+        .status(200)
+        .header("content-type", "application/grpc")
+        .body(())
+        .expect("BUG: invalid gRPC response headers");
+    let mut send = match respond.send_response(response, false) {
+        Ok(send) => send,
+        Err(e) => {
+            warn!("gRPC: failed to respond to {} - {}", &path, e);
+            return;
+        }
+    };
+
+    let mut trailers = HeaderMap::new();
+    match result {
+        Ok(messages) => {
+            for message in messages.iter() {
+                if let Err(e) = send.send_data(frame_message(message), false) {
+                    warn!("gRPC: failed to respond to {} - {}", &path, e);
+                    return;
+                }
+            }
+            trailers.insert("grpc-status", HeaderValue::from(0));
+        }
+        Err(e) => {
+            debug!("gRPC: {} failed: {:?}", &path, &e);
+            trailers.insert("grpc-status", HeaderValue::from(e.code()));
+            if let Ok(message) = HeaderValue::from_str(&encode_status_message(e.message())) {
+                trailers.insert("grpc-message", message);
+            }
+        }
+    }
+    if let Err(e) = send.send_trailers(trailers) {
+        warn!("gRPC: failed to respond to {} - {}", &path, e);
+    }
+}
+
+async fn serve_connection(socket: TcpStream, ctx: Arc<GrpcContext>) -> Result<(), h2::Error> {
+    let mut connection = h2::server::handshake(socket).await?;
+    while let Some(call) = connection.accept().await {
+        let (request, respond) = call?;
+        tokio::spawn(serve_call(request, respond, ctx.clone()));
+    }
+    Ok(())
+}
+
+pub fn start_serving_grpc(config: Config) {
+    let bind_address = config
+        .node
+        .grpc_bind
+        .clone()
+        .expect("BUG: no gRPC bind address");
+    let bind_address = bind_address
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .expect("gRPC: unable to resolve bind address");
+    let ctx = Arc::new(GrpcContext::new(config));
+
+    let mut runtime = tokio::runtime::Builder::new()
+        .threaded_scheduler()
+        .enable_io()
+        .thread_name("grpc")
+        .build()
+        .expect("gRPC: unable to start runtime");
+
+    runtime.block_on(async {
+        let mut listener = TcpListener::bind(&bind_address)
+            .await
+            .expect("gRPC: unable to bind address");
+        info!(
+            "gRPC: server listening on {}",
+            listener.local_addr().expect("gRPC: unable to get addr")
+        );
+
+        loop {
+            let socket = match listener.accept().await {
+                Ok((socket, _)) => socket,
+                Err(err) => {
+                    error!("gRPC: unable to open socket - {:?}", err);
+                    continue;
+                }
+            };
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                if let Err(err) = serve_connection(socket, ctx).await {
+                    warn!("gRPC: connection failed - {}", err);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn status_messages_are_percent_encoded() {
+        assert_eq!(
+            encode_status_message("No block at height 5"),
+            "No block at height 5"
+        );
+        assert_eq!(encode_status_message("100%\n\u{e9}"), "100%25%0A%C3%A9");
+    }
+
+    #[test]
+    fn messages_are_length_prefixed() {
+        assert_eq!(&frame_message(&[7, 8])[..], &[0, 0, 0, 0, 2, 7, 8]);
+    }
+}
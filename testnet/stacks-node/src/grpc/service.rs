@@ -0,0 +1,378 @@
+//! The handlers of the `stacks.node.v1.StacksNode` service's methods.
+
+use std::cmp;
+
+use async_h1::client;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
+use stacks::chainstate::stacks::{StacksBlockId, StacksTransaction};
+use stacks::net::StacksMessageCodec;
+use stacks::vm::clarity::ClarityConnection;
+use stacks::vm::costs::LimitedCostTracker;
+use stacks::vm::errors::{Error as ClarityRuntimeError, InterpreterError};
+use stacks::vm::types::{PrincipalData, QualifiedContractIdentifier};
+use stacks::vm::{SymbolicExpression, Value};
+
+use super::messages::{
+    Account, Block, BlockHeader, BroadcastTransactionRequest, BroadcastTransactionResponse,
+    CallReadOnlyRequest, CallReadOnlyResponse, GetAccountRequest, GetBlockRequest,
+    GetHeadersRequest, Message, Transaction,
+};
+use crate::config::Config;
+use crate::neon_node::TESTNET_CHAIN_ID;
+
+/// Most headers sent by one `GetHeaders` call; clients ask for the rest with another call
+pub const MAX_HEADERS_PER_CALL: u64 = 2000;
+
+/// A failed call, and the gRPC status code it is reported with
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrpcError {
+    InvalidArgument(String),
+    NotFound(String),
+    /// the node would not take a broadcast transaction
+    Rejected(String),
+    Unimplemented(String),
+    Internal(String),
+    /// the chainstate could not be read
+    Unavailable(String),
+}
+
+impl GrpcError {
+    pub fn code(&self) -> u32 {
+        match self {
+            GrpcError::InvalidArgument(_) => 3,
+            GrpcError::NotFound(_) => 5,
+            GrpcError::Rejected(_) => 9,
+            GrpcError::Unimplemented(_) => 12,
+            GrpcError::Internal(_) => 13,
+            GrpcError::Unavailable(_) => 14,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            GrpcError::InvalidArgument(msg)
+            | GrpcError::NotFound(msg)
+            | GrpcError::Rejected(msg)
+            | GrpcError::Unimplemented(msg)
+            | GrpcError::Internal(msg)
+            | GrpcError::Unavailable(msg) => msg,
+        }
+    }
+}
+
+fn unavailable<E: std::fmt::Display>(e: E) -> GrpcError {
+    GrpcError::Unavailable(format!("{}", e))
+}
+
+/// What every handler needs to know about the node
+pub struct GrpcContext {
+    pub config: Config,
+    pub mainnet: bool,
+    pub chain_id: u32,
+}
+
+impl GrpcContext {
+    pub fn new(config: Config) -> GrpcContext {
+        GrpcContext {
+            config,
+            mainnet: false,
+            chain_id: TESTNET_CHAIN_ID,
+        }
+    }
+
+    fn open_chain(&self) -> Result<ChainView, GrpcError> {
+        let sortdb =
+            SortitionDB::open(&self.config.get_burn_db_file_path(), false).map_err(unavailable)?;
+        let (chainstate, _) = StacksChainState::open(
+            self.mainnet,
+            self.chain_id,
+            &self.config.get_chainstate_path(),
+        )
+        .map_err(unavailable)?;
+        let tip = chainstate
+            .get_stacks_chain_tip(&sortdb)
+            .map_err(unavailable)?
+            .ok_or(GrpcError::Unavailable(
+                "No Stacks blocks have been processed".to_string(),
+            ))?;
+        let tip = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            &StacksBlockId::new(&tip.consensus_hash, &tip.anchored_block_hash),
+        )
+        .map_err(unavailable)?
+        .ok_or(GrpcError::Unavailable(
+            "No header for the Stacks chain tip".to_string(),
+        ))?;
+        Ok(ChainView {
+            sortdb,
+            chainstate,
+            tip,
+        })
+    }
+}
+
+/// The node's chainstate, as of its canonical Stacks chain tip
+struct ChainView {
+    sortdb: SortitionDB,
+    chainstate: StacksChainState,
+    tip: StacksHeaderInfo,
+}
+
+impl ChainView {
+    fn header_at_height(&mut self, height: u64) -> Result<StacksHeaderInfo, GrpcError> {
+        let tip_index_hash = self.tip.index_block_hash();
+        let mut tx = self.chainstate.index_tx_begin().map_err(unavailable)?;
+        StacksChainState::get_index_tip_ancestor(&mut tx, &tip_index_hash, height)
+            .map_err(unavailable)?
+            .ok_or(GrpcError::NotFound(format!(
+                "No block at height {}",
+                height
+            )))
+    }
+
+    fn header_by_hash(
+        &self,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<StacksHeaderInfo, GrpcError> {
+        StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.chainstate.db(),
+            index_block_hash,
+        )
+        .map_err(unavailable)?
+        .ok_or(GrpcError::NotFound(format!(
+            "No block {}",
+            index_block_hash
+        )))
+    }
+
+    /// The block a request names by index block hash, or the chain tip if it names none
+    fn block_or_tip(&self, index_block_hash: &[u8]) -> Result<StacksBlockId, GrpcError> {
+        if index_block_hash.len() == 0 {
+            Ok(self.tip.index_block_hash())
+        } else {
+            parse_block_id(index_block_hash)
+        }
+    }
+}
+
+fn parse_block_id(bytes: &[u8]) -> Result<StacksBlockId, GrpcError> {
+    StacksBlockId::from_bytes(bytes).ok_or(GrpcError::InvalidArgument(
+        "Invalid index block hash".to_string(),
+    ))
+}
+
+fn parse_principal(principal: &str) -> Result<PrincipalData, GrpcError> {
+    PrincipalData::parse(principal)
+        .map_err(|_| GrpcError::InvalidArgument(format!("Invalid principal {}", principal)))
+}
+
+pub fn block_header(header: &StacksHeaderInfo) -> BlockHeader {
+    BlockHeader {
+        height: header.block_height,
+        index_block_hash: header.index_block_hash().as_bytes().to_vec(),
+        block_hash: header.anchored_header.block_hash().as_bytes().to_vec(),
+        consensus_hash: header.consensus_hash.as_bytes().to_vec(),
+        parent_block_hash: header.anchored_header.parent_block.as_bytes().to_vec(),
+        parent_microblock_hash: header.anchored_header.parent_microblock.as_bytes().to_vec(),
+        parent_microblock_sequence: header.anchored_header.parent_microblock_sequence as u32,
+        burn_header_hash: header.burn_header_hash.as_bytes().to_vec(),
+        burn_header_height: header.burn_header_height as u64,
+        burn_header_timestamp: header.burn_header_timestamp,
+        raw_header: header.anchored_header.serialize_to_vec(),
+    }
+}
+
+fn get_account(ctx: &GrpcContext, request: GetAccountRequest) -> Result<Account, GrpcError> {
+    let principal = parse_principal(&request.principal)?;
+    let mut chain = ctx.open_chain()?;
+    let tip = chain.block_or_tip(&request.tip)?;
+    let sortdb = &chain.sortdb;
+    let (account, burn_block_height) = chain
+        .chainstate
+        .with_read_only_clarity_tx(&sortdb.index_conn(), &tip, |conn| {
+            let account = StacksChainState::get_account(conn, &principal);
+            let burn_block_height =
+                conn.with_clarity_db_readonly(|db| db.get_current_burnchain_block_height());
+            (account, burn_block_height as u64)
+        })
+        .ok_or(GrpcError::NotFound(format!("No block {}", &tip)))?;
+
+    let (locked, unlock_height) = account
+        .stx_balance
+        .get_locked_balance_at_burn_block(burn_block_height);
+    Ok(Account {
+        principal: request.principal,
+        balance: account
+            .stx_balance
+            .get_available_balance_at_burn_block(burn_block_height)
+            .to_string(),
+        locked: locked.to_string(),
+        unlock_height,
+        nonce: account.nonce,
+        tip: tip.as_bytes().to_vec(),
+    })
+}
+
+fn get_block(ctx: &GrpcContext, request: GetBlockRequest) -> Result<Block, GrpcError> {
+    let mut chain = ctx.open_chain()?;
+    let header = if request.index_block_hash.len() > 0 {
+        chain.header_by_hash(&parse_block_id(&request.index_block_hash)?)?
+    } else {
+        chain.header_at_height(request.height)?
+    };
+    let block = StacksChainState::load_block(
+        &chain.chainstate.blocks_path,
+        &header.consensus_hash,
+        &header.anchored_header.block_hash(),
+    )
+    .map_err(unavailable)?
+    .ok_or(GrpcError::NotFound(format!(
+        "Block {} is not stored",
+        header.index_block_hash()
+    )))?;
+
+    Ok(Block {
+        header: Some(block_header(&header)),
+        transactions: block
+            .txs
+            .iter()
+            .map(|tx| Transaction {
+                txid: tx.txid().as_bytes().to_vec(),
+                raw_tx: tx.serialize_to_vec(),
+            })
+            .collect(),
+    })
+}
+
+fn get_headers(
+    ctx: &GrpcContext,
+    request: GetHeadersRequest,
+) -> Result<Vec<BlockHeader>, GrpcError> {
+    let mut chain = ctx.open_chain()?;
+    let mut end_height = chain.tip.block_height;
+    if request.end_height > 0 {
+        end_height = cmp::min(end_height, request.end_height);
+    }
+    if request.start_height > end_height {
+        return Ok(vec![]);
+    }
+    end_height = cmp::min(end_height, request.start_height + MAX_HEADERS_PER_CALL - 1);
+
+    let mut headers = vec![];
+    for height in request.start_height..=end_height {
+        headers.push(block_header(&chain.header_at_height(height)?));
+    }
+    Ok(headers)
+}
+
+fn call_read_only(
+    ctx: &GrpcContext,
+    request: CallReadOnlyRequest,
+) -> Result<CallReadOnlyResponse, GrpcError> {
+    let contract = QualifiedContractIdentifier::parse(&request.contract).map_err(|_| {
+        GrpcError::InvalidArgument(format!("Invalid contract {}", &request.contract))
+    })?;
+    let sender = parse_principal(&request.sender)?;
+    let mut args = vec![];
+    for arg in request.arguments.iter() {
+        let value = Value::consensus_deserialize(&mut &arg[..])
+            .map_err(|e| GrpcError::InvalidArgument(format!("Invalid argument: {}", e)))?;
+        args.push(SymbolicExpression::atom_value(value));
+    }
+
+    let mut chain = ctx.open_chain()?;
+    let tip = chain.block_or_tip(&request.tip)?;
+    let limit = ctx.config.connection_options.read_only_call_limit.clone();
+    let sortdb = &chain.sortdb;
+    let result = chain
+        .chainstate
+        .maybe_read_only_clarity_tx(&sortdb.index_conn(), &tip, |conn| {
+            let cost_track = conn
+                .with_clarity_db_readonly(|clarity_db| LimitedCostTracker::new(limit, clarity_db))
+                .map_err(|_| {
+                    ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure)
+                })?;
+            conn.with_readonly_clarity_env(sender, cost_track, |env| {
+                env.execute_contract(&contract, &request.function_name, &args, true)
+            })
+        })
+        .ok_or(GrpcError::NotFound(format!("No block {}", &tip)))?;
+
+    Ok(match result {
+        Ok(value) => CallReadOnlyResponse {
+            okay: true,
+            result: value.serialize_to_vec(),
+            cause: "".to_string(),
+        },
+        Err(e) => CallReadOnlyResponse {
+            okay: false,
+            result: vec![],
+            cause: e.to_string(),
+        },
+    })
+}
+
+fn broadcast_transaction(
+    ctx: &GrpcContext,
+    request: BroadcastTransactionRequest,
+) -> Result<BroadcastTransactionResponse, GrpcError> {
+    let tx = StacksTransaction::consensus_deserialize(&mut &request.transaction[..])
+        .map_err(|e| GrpcError::InvalidArgument(format!("Invalid transaction: {}", e)))?;
+
+    // a wildcard bind address is reachable on the loopback interface
+    let rpc_addr = ctx.config.node.rpc_bind.replace("0.0.0.0", "127.0.0.1");
+    let url = Url::parse(&format!("http://{}/v2/transactions", &rpc_addr))
+        .map_err(|e| GrpcError::Internal(format!("{}", e)))?;
+    let mut req = Request::new(Method::Post, url);
+    req.append_header("Content-Type", "application/octet-stream")
+        .expect("Unable to set header");
+    req.set_body(request.transaction);
+
+    async_std::task::block_on(async {
+        let stream = TcpStream::connect(&rpc_addr).await.map_err(unavailable)?;
+        let response = client::connect(stream, req).await.map_err(unavailable)?;
+        if !response.status().is_success() {
+            let reason = response.body_string().await.unwrap_or("".to_string());
+            return Err(GrpcError::Rejected(reason));
+        }
+        Ok(())
+    })?;
+
+    Ok(BroadcastTransactionResponse {
+        txid: tx.txid().as_bytes().to_vec(),
+    })
+}
+
+fn decode<M: Message>(request: &[u8]) -> Result<M, GrpcError> {
+    M::from_bytes(request).map_err(GrpcError::InvalidArgument)
+}
+
+/// Run the method at `path` (e.g. `/stacks.node.v1.StacksNode/GetAccount`) on an encoded
+/// request, returning its encoded responses
+pub fn handle_call(
+    ctx: &GrpcContext,
+    path: &str,
+    request: &[u8],
+) -> Result<Vec<Vec<u8>>, GrpcError> {
+    match path {
+        "/stacks.node.v1.StacksNode/GetAccount" => {
+            get_account(ctx, decode(request)?).map(|response| vec![response.to_bytes()])
+        }
+        "/stacks.node.v1.StacksNode/GetBlock" => {
+            get_block(ctx, decode(request)?).map(|response| vec![response.to_bytes()])
+        }
+        "/stacks.node.v1.StacksNode/GetHeaders" => get_headers(ctx, decode(request)?)
+            .map(|headers| headers.iter().map(|header| header.to_bytes()).collect()),
+        "/stacks.node.v1.StacksNode/CallReadOnly" => {
+            call_read_only(ctx, decode(request)?).map(|response| vec![response.to_bytes()])
+        }
+        "/stacks.node.v1.StacksNode/BroadcastTransaction" => {
+            broadcast_transaction(ctx, decode(request)?).map(|response| vec![response.to_bytes()])
+        }
+        _ => Err(GrpcError::Unimplemented(format!("Unknown method {}", path))),
+    }
+}
@@ -19,17 +19,25 @@ pub use stacks::util;
 
 pub mod monitoring;
 
+pub mod bench;
 pub mod burnchains;
 pub mod config;
 pub mod event_dispatcher;
+pub mod event_stream;
 pub mod genesis_data;
+pub mod grpc;
 pub mod keychain;
 pub mod neon_node;
 pub mod node;
 pub mod operations;
+pub mod poison_reporter;
+pub mod replay;
+pub mod rosetta;
 pub mod run_loop;
+pub mod scheduler;
 pub mod syncctl;
 pub mod tenure;
+pub mod webhooks;
 
 pub use self::burnchains::{
     BitcoinRegtestController, BurnchainController, BurnchainTip, MocknetController,
@@ -43,6 +51,7 @@ pub use self::run_loop::{helium, neon};
 pub use self::tenure::Tenure;
 
 use pico_args::Arguments;
+use stacks::chainstate::stacks::index::storage;
 use std::env;
 
 use std::convert::TryInto;
@@ -82,6 +91,10 @@ fn main() {
             args.finish().unwrap();
             ConfigFile::mocknet()
         }
+        "dev" => {
+            args.finish().unwrap();
+            ConfigFile::dev()
+        }
         "helium" => {
             args.finish().unwrap();
             ConfigFile::helium()
@@ -108,6 +121,40 @@ fn main() {
             println!("==> {}", config_path);
             ConfigFile::from_path(&config_path)
         }
+        "replay-events" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let from_height: u64 = args.value_from_str("--from-height").unwrap();
+            let to_height: u64 = args.value_from_str("--to-height").unwrap();
+            let observer: String = args.value_from_str("--observer").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            match replay::replay_events(&conf, from_height, to_height, &observer) {
+                Ok(num_replayed) => {
+                    println!("Replayed events for {} blocks", num_replayed);
+                }
+                Err(e) => {
+                    eprintln!("Failed to replay events: {}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "bench-sync" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let num_blocks: u64 = args.value_from_str("--blocks").unwrap();
+            args.finish().unwrap();
+            let conf = Config::from_config_file(ConfigFile::from_path(&config_path));
+            match bench::bench_sync(&conf, num_blocks) {
+                Ok(report) => {
+                    report.print();
+                }
+                Err(e) => {
+                    eprintln!("Failed to benchmark sync: {}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
         "version" => {
             println!(
                 "{}",
@@ -130,9 +177,14 @@ fn main() {
     debug!("connection configuration {:?}", &conf.connection_options);
     debug!("block_limit {:?}", &conf.block_limit);
 
+    storage::set_node_cache_size(conf.node.marf_cache_size);
+
     let num_round: u64 = 0; // Infinite number of rounds
 
-    if conf.burnchain.mode == "helium" || conf.burnchain.mode == "mocknet" {
+    if conf.burnchain.mode == "helium"
+        || conf.burnchain.mode == "mocknet"
+        || conf.burnchain.mode == "dev"
+    {
         let mut run_loop = helium::RunLoop::new(conf);
         if let Err(e) = run_loop.start(num_round) {
             warn!("Helium runloop exited: {}", e);
@@ -165,6 +217,11 @@ SUBCOMMANDS:
 
 mocknet\t\tStart a node based on a fast local setup emulating a burnchain. Ideal for smart contract development. 
 
+dev		Start a single-binary local chain on an in-process simulated burnchain, which mines a block
+		every `burnchain.dev_block_time_ms` (1 second by default). Blocks whose heights are listed in
+		`burnchain.dev_missed_sortitions` have no sortition. Use `start --config` with
+		`mode = \"dev\"` under `[burnchain]` to change these settings.
+
 helium\t\tStart a node based on a local setup relying on a local instance of bitcoind.
 \t\tThe following bitcoin.conf is expected:
 \t\t  chain=regtest
@@ -186,6 +243,25 @@ start\t\tStart a node with a config of your own. Can be used for joining a netwo
 \t\tExample:
 \t\t  stacks-node start --config=/path/to/config.toml
 
+replay-events\tRe-send the block events of a range of already-processed blocks to an event observer,
+\t\tusing the transaction receipts stored in the node's chainstate. The node need not be running.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --from-height: first Stacks block height to replay.
+\t\t  --to-height: last Stacks block height to replay.
+\t\t  --observer: address of the event observer, as in an [[events_observer]] endpoint.
+\t\tExample:
+\t\t  stacks-node replay-events --config=/path/to/config.toml --from-height=1 --to-height=100 --observer=localhost:3700
+
+bench-sync\tReplay the first blocks of a node's canonical fork into a scratch chainstate and report
+\t\tblocks/sec, the MARF commit latency distribution, and the wall and CPU time spent. The node
+\t\tneed not be running, and its chainstate is left untouched.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --blocks: number of Stacks blocks to replay.
+\t\tExample:
+\t\t  stacks-node bench-sync --config=/path/to/config.toml --blocks=1000
+
 version\t\tDisplay information about the current version and our release cycle.
 
 help\t\tDisplay this help.
@@ -48,7 +48,12 @@ async fn accept(addr: String, stream: TcpStream) -> http_types::Result<()> {
         "Prometheus monitoring: starting new connection from {}",
         stream.peer_addr()?
     );
-    async_h1::accept(&addr, stream.clone(), |_| async {
+    async_h1::accept(&addr, stream.clone(), |req| async move {
+        // `/` is still served for scrapers configured before the `/metrics` path existed
+        if req.url().path() != "/metrics" && req.url().path() != "/" {
+            return Ok(Response::new(StatusCode::NotFound));
+        }
+
         let encoder = TextEncoder::new();
         let metric_families = gather();
         let mut buffer = vec![];
@@ -4,10 +4,12 @@ use crate::run_loop::RegisteredKey;
 use std::collections::HashMap;
 
 use std::cmp;
-use std::collections::{HashSet, VecDeque};
+use std::collections::VecDeque;
 use std::convert::{TryFrom, TryInto};
 use std::default::Default;
+use std::fs;
 use std::net::SocketAddr;
+use std::time::Duration;
 use std::{thread, thread::JoinHandle};
 
 use stacks::burnchains::{Burnchain, BurnchainHeaderHash, BurnchainParameters, Txid};
@@ -21,7 +23,10 @@ use stacks::chainstate::burn::{BlockHeaderHash, ConsensusHash, VRFSeed};
 use stacks::chainstate::stacks::db::{ChainStateBootData, ClarityTx, StacksChainState};
 use stacks::chainstate::stacks::Error as ChainstateError;
 use stacks::chainstate::stacks::StacksPublicKey;
-use stacks::chainstate::stacks::{miner::StacksMicroblockBuilder, StacksBlockBuilder};
+use stacks::chainstate::stacks::{
+    miner::{MicroblockAssemblyLimits, StacksMicroblockBuilder},
+    StacksBlockBuilder,
+};
 use stacks::chainstate::stacks::{
     CoinbasePayload, StacksAddress, StacksBlock, StacksBlockHeader, StacksMicroblock,
     StacksTransaction, StacksTransactionSigner, TransactionAnchorMode, TransactionPayload,
@@ -29,13 +34,16 @@ use stacks::chainstate::stacks::{
 };
 use stacks::core::mempool::MemPoolDB;
 use stacks::net::{
-    atlas::{AtlasDB, AttachmentInstance},
-    db::{LocalPeer, PeerDB},
+    atlas::AtlasDB,
+    db::{LocalPeer, PeerDB, PeerDBExportEntry},
     dns::DNSResolver,
     p2p::PeerNetwork,
     relay::Relayer,
     rpc::RPCHandlerArgs,
-    Error as NetError, NetworkResult, PeerAddress, StacksMessageCodec,
+    stackerdb::StackerDBs,
+    Error as NetError, NetworkResult, PeerAddress, RPCMinerAssembledBlock, RPCMinerBlockCommit,
+    RPCMinerSortitionData, RPCMinerStatusData, RPCMinerVRFKey, RPCPeerInfoData,
+    StacksBlockAcceptedData, StacksMessageCodec,
 };
 use stacks::util::get_epoch_time_ms;
 use stacks::util::get_epoch_time_secs;
@@ -43,12 +51,19 @@ use stacks::util::hash::{to_hex, Hash160, Sha256Sum};
 use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::util::strings::UrlString;
 use stacks::util::vrf::VRFPublicKey;
+use stacks::vm::types::PrincipalData;
 use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
 use std::sync::{Arc, Mutex};
 
+use async_h1::client;
+use async_std::io::ReadExt;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+
 use crate::burnchains::bitcoin_regtest_controller::BitcoinRegtestController;
 use crate::syncctl::PoxSyncWatchdogComms;
 
+use crate::genesis_data::{genesis_chainstate_hash, get_genesis_audit_data};
 use crate::ChainTip;
 use stacks::burnchains::BurnchainSigner;
 use stacks::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
@@ -56,10 +71,22 @@ use stacks::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
 use stacks::chainstate::coordinator::comm::CoordinatorChannels;
 use stacks::chainstate::coordinator::{get_next_recipients, OnChainRewardSetProvider};
 
-use stacks::monitoring::{increment_stx_blocks_mined_counter, update_active_miners_count_gauge};
+use stacks::monitoring::{
+    increment_block_pushes_counter, increment_miner_sortitions_counters,
+    increment_stx_blocks_mined_counter, update_active_miners_count_gauge,
+};
 
 pub const TESTNET_CHAIN_ID: u32 = 0x80000000;
 pub const TESTNET_PEER_VERSION: u32 = 0xfacade01;
+
+/// How long to wait on each attempt to reach one of our own advertised data URLs
+const DATA_URL_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many times to try reaching each of our advertised data URLs at startup
+const DATA_URL_CHECK_ATTEMPTS: u64 = 3;
+/// How long to wait on each attempt to upload a mined block to a block push URL
+const BLOCK_PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait before retrying a failed block upload
+const BLOCK_PUSH_RETRY_INTERVAL: Duration = Duration::from_secs(1);
 pub const RELAYER_MAX_BUFFER: usize = 100;
 
 struct AssembledAnchorBlock {
@@ -75,7 +102,11 @@ struct MicroblockMinerState {
     miner_key: Secp256k1PrivateKey,
     frequency: u64,
     last_mined: u128,
+    /// when the miner last produced a microblock (or started streaming, if it has not yet)
+    last_produced: u128,
     quantity: u64,
+    /// whether the miner gave up on this stream because a competing anchored block took the tip
+    stopped: bool,
 }
 
 enum RelayerDirective {
@@ -93,6 +124,7 @@ pub struct InitializedNeonNode {
     active_keys: Vec<RegisteredKey>,
     sleep_before_tenure: u64,
     is_miner: bool,
+    miner_status: Arc<Mutex<RPCMinerStatusData>>,
 }
 
 pub struct NeonGenesisNode {
@@ -157,14 +189,18 @@ fn inner_process_tenure(
     Ok(true)
 }
 
-fn inner_generate_coinbase_tx(keychain: &mut Keychain, nonce: u64) -> StacksTransaction {
+fn inner_generate_coinbase_tx(
+    keychain: &mut Keychain,
+    nonce: u64,
+    recipient: Option<PrincipalData>,
+) -> StacksTransaction {
     let mut tx_auth = keychain.get_transaction_auth().unwrap();
     tx_auth.set_origin_nonce(nonce);
 
     let mut tx = StacksTransaction::new(
         TransactionVersion::Testnet,
         tx_auth,
-        TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+        TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), recipient),
     );
     tx.chain_id = TESTNET_CHAIN_ID;
     tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
@@ -260,7 +296,9 @@ fn inner_generate_block_commit_op(
     })
 }
 
-/// Mine and broadcast a single microblock, unconditionally.
+/// Mine and broadcast a single microblock.  If `require_full` is set, the microblock is only kept
+/// if it filled up to the configured assembly limits; otherwise, it is discarded, so its
+/// transactions can wait for the next attempt.
 /// Note that the StacksChainState here **must** be the **same** StacksChainState that gets
 /// maintained by the peer network thread!
 /// NOTE: for now, this must be guarded by the kludgy clarity DB mutex
@@ -269,7 +307,9 @@ fn mine_one_microblock(
     sortdb: &SortitionDB,
     chainstate: &mut StacksChainState,
     mempool: &MemPoolDB,
-) -> Result<StacksMicroblock, NetError> {
+    assembly_limits: &MicroblockAssemblyLimits,
+    require_full: bool,
+) -> Result<Option<StacksMicroblock>, NetError> {
     debug!(
         "Try to mine one microblock off of {}/{} (at seq {})",
         &microblock_state.parent_consensus_hash,
@@ -297,15 +337,25 @@ fn mine_one_microblock(
                 }
             };
 
+        microblock_miner.set_assembly_limits(assembly_limits.clone());
         let mblock = microblock_miner.mine_next_microblock(mempool, &microblock_state.miner_key)?;
-
-        info!("Minted microblock with {} transactions", mblock.txs.len());
-
-        Ok(mblock)
+        if require_full && !microblock_miner.is_full() {
+            debug!(
+                "Hold back microblock with {} transactions until it fills up",
+                mblock.txs.len()
+            );
+            Ok(None)
+        } else {
+            info!("Minted microblock with {} transactions", mblock.txs.len());
+            Ok(Some(mblock))
+        }
     };
 
     let mined_microblock = match mint_result {
-        Ok(mined_microblock) => mined_microblock,
+        Ok(Some(mined_microblock)) => mined_microblock,
+        Ok(None) => {
+            return Ok(None);
+        }
         Err(e) => {
             warn!("Failed to mine microblock: {}", e);
             return Err(e);
@@ -329,7 +379,8 @@ fn mine_one_microblock(
         })?;
 
     microblock_state.quantity += 1;
-    return Ok(mined_microblock);
+    microblock_state.last_produced = get_epoch_time_ms();
+    return Ok(Some(mined_microblock));
 }
 
 fn try_mine_microblock(
@@ -366,7 +417,9 @@ fn try_mine_microblock(
                         miner_key: microblock_privkey.clone(),
                         frequency: config.node.microblock_frequency,
                         last_mined: 0,
+                        last_produced: get_epoch_time_ms(),
                         quantity: 0,
+                        stopped: false,
                     });
                 }
                 Ok(None) => {
@@ -390,9 +443,36 @@ fn try_mine_microblock(
             if microblock_miner.parent_consensus_hash == *ch
                 && microblock_miner.parent_block_hash == *bhh
             {
-                if microblock_miner.last_mined + (microblock_miner.frequency as u128)
-                    < get_epoch_time_ms()
+                if !microblock_miner.stopped && config.node.microblock_stop_on_competing_tip {
+                    let (canonical_consensus_tip, canonical_block_tip) =
+                        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+                    if canonical_consensus_tip != *ch || canonical_block_tip != *bhh {
+                        info!(
+                            "Stop streaming microblocks off of {}/{}: the canonical Stacks tip is now {}/{}",
+                            ch, bhh, &canonical_consensus_tip, &canonical_block_tip
+                        );
+                        microblock_miner.stopped = true;
+                    }
+                }
+                if microblock_miner.quantity >= config.node.max_microblocks {
+                    debug!(
+                        "Mined the maximum of {} microblocks off of {}/{}",
+                        config.node.max_microblocks, ch, bhh
+                    );
+                } else if !microblock_miner.stopped
+                    && microblock_miner.last_mined + (microblock_miner.frequency as u128)
+                        < get_epoch_time_ms()
                 {
+                    // with a maximum interval, only stream a partial microblock once it elapses
+                    let require_full = config
+                        .node
+                        .microblock_max_interval
+                        .map(|max_interval| {
+                            microblock_miner.last_produced + (max_interval as u128)
+                                > get_epoch_time_ms()
+                        })
+                        .unwrap_or(false);
+
                     // opportunistically try and mine, but only if there's no attachable blocks and if
                     // we can acquire the temporary kludgy Clarity DB lock.
                     let num_attachable =
@@ -405,10 +485,12 @@ fn try_mine_microblock(
                                     sortdb,
                                     chainstate,
                                     &mem_pool,
+                                    &config.node.microblock_assembly_limits,
+                                    require_full,
                                 ) {
-                                    Ok(microblock) => {
+                                    Ok(microblock_opt) => {
                                         // will need to relay this
-                                        next_microblock = Some(microblock);
+                                        next_microblock = microblock_opt;
                                     }
                                     Err(e) => {
                                         warn!("Failed to mine one microblock: {:?}", &e);
@@ -437,6 +519,273 @@ fn try_mine_microblock(
     Ok(next_microblock)
 }
 
+/// Fetch /v2/info from the node serving the given data URL
+fn fetch_peer_info(data_url: &UrlString) -> Result<RPCPeerInfoData, String> {
+    let url = data_url
+        .parse_to_block_url()
+        .map_err(|e| format!("invalid URL: {:?}", &e))?;
+    let host = url.host_str().ok_or("no host".to_string())?.to_string();
+    let port = url.port_or_known_default().ok_or("no port".to_string())?;
+    let info_url = Url::parse(&format!("{}/v2/info", data_url.trim_end_matches('/')))
+        .map_err(|e| format!("invalid URL: {:?}", &e))?;
+
+    let request = Request::new(Method::Get, info_url);
+    let mut response = async_std::task::block_on(async move {
+        let connect = TcpStream::connect(format!("{}:{}", &host, port));
+        let stream = match async_std::future::timeout(DATA_URL_CHECK_TIMEOUT, connect).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => return Err(format!("connection failed: {:?}", &e)),
+            Err(_) => return Err("connection timed out".to_string()),
+        };
+        match async_std::future::timeout(DATA_URL_CHECK_TIMEOUT, client::connect(stream, request))
+            .await
+        {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(e)) => Err(format!("request failed: {:?}", &e)),
+            Err(_) => Err("request timed out".to_string()),
+        }
+    })?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP status {}", response.status()));
+    }
+
+    let buffer = async_std::task::block_on(async move {
+        let mut buffer = Vec::new();
+        let mut body = response.take_body();
+        body.read_to_end(&mut buffer)
+            .await
+            .map_err(|e| format!("unable to read body: {:?}", &e))?;
+        Ok::<_, String>(buffer)
+    })?;
+
+    serde_json::from_slice::<RPCPeerInfoData>(&buffer)
+        .map_err(|e| format!("unable to parse /v2/info: {:?}", &e))
+}
+
+/// Upload a block to the node serving the given data URL.
+/// Returns whether or not that node accepted the block (it does not if it already has it).
+fn post_block(
+    data_url: &UrlString,
+    consensus_hash: &ConsensusHash,
+    block_bytes: Vec<u8>,
+) -> Result<bool, String> {
+    let url = data_url
+        .parse_to_block_url()
+        .map_err(|e| format!("invalid URL: {:?}", &e))?;
+    let host = url.host_str().ok_or("no host".to_string())?.to_string();
+    let port = url.port_or_known_default().ok_or("no port".to_string())?;
+    let upload_url = Url::parse(&format!(
+        "{}/v2/blocks/upload/{}",
+        data_url.trim_end_matches('/'),
+        consensus_hash
+    ))
+    .map_err(|e| format!("invalid URL: {:?}", &e))?;
+
+    let mut request = Request::new(Method::Post, upload_url);
+    request
+        .append_header("Content-Type", "application/octet-stream")
+        .map_err(|e| format!("unable to set header: {:?}", &e))?;
+    request.set_body(block_bytes);
+
+    let mut response = async_std::task::block_on(async move {
+        let connect = TcpStream::connect(format!("{}:{}", &host, port));
+        let stream = match async_std::future::timeout(BLOCK_PUSH_TIMEOUT, connect).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => return Err(format!("connection failed: {:?}", &e)),
+            Err(_) => return Err("connection timed out".to_string()),
+        };
+        match async_std::future::timeout(BLOCK_PUSH_TIMEOUT, client::connect(stream, request)).await
+        {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(e)) => Err(format!("request failed: {:?}", &e)),
+            Err(_) => Err("request timed out".to_string()),
+        }
+    })?;
+
+    let status = response.status();
+    let buffer = async_std::task::block_on(async move {
+        let mut buffer = Vec::new();
+        let mut body = response.take_body();
+        body.read_to_end(&mut buffer)
+            .await
+            .map_err(|e| format!("unable to read body: {:?}", &e))?;
+        Ok::<_, String>(buffer)
+    })?;
+
+    if !status.is_success() {
+        return Err(format!(
+            "HTTP status {}: {}",
+            status,
+            String::from_utf8_lossy(&buffer)
+        ));
+    }
+
+    serde_json::from_slice::<StacksBlockAcceptedData>(&buffer)
+        .map(|data| data.accepted)
+        .map_err(|e| format!("unable to parse response: {:?}", &e))
+}
+
+/// Upload a block we just mined directly to each of the configured block push URLs, so that a
+/// slow relay through the peer network doesn't get it orphaned.  Runs in the background.
+fn push_block_to_peers(
+    push_urls: Vec<String>,
+    retries: u64,
+    consensus_hash: ConsensusHash,
+    block: StacksBlock,
+) {
+    if push_urls.is_empty() {
+        return;
+    }
+
+    let block_bytes = block.serialize_to_vec();
+    let block_id = StacksBlockHeader::make_index_block_hash(&consensus_hash, &block.block_hash());
+    let res = thread::Builder::new()
+        .name("block-push".to_string())
+        .spawn(move || {
+            for push_url in push_urls.into_iter() {
+                let data_url = match UrlString::try_from(push_url.as_str()) {
+                    Ok(data_url) => data_url,
+                    Err(e) => {
+                        warn!("Invalid block push URL {:?}: {:?}", &push_url, &e);
+                        increment_block_pushes_counter(false);
+                        continue;
+                    }
+                };
+                let mut result = Err("not attempted".to_string());
+                for attempt in 0..(retries + 1) {
+                    if attempt > 0 {
+                        thread::sleep(BLOCK_PUSH_RETRY_INTERVAL);
+                    }
+                    result = post_block(&data_url, &consensus_hash, block_bytes.clone());
+                    if result.is_ok() {
+                        break;
+                    }
+                    debug!(
+                        "Failed to push block {} to {} (attempt {}): {:?}",
+                        &block_id,
+                        &push_url,
+                        attempt + 1,
+                        &result
+                    );
+                }
+
+                match result {
+                    Ok(accepted) => {
+                        info!(
+                            "Pushed block {} to {} (accepted: {})",
+                            &block_id, &push_url, accepted
+                        );
+                        increment_block_pushes_counter(true);
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to push block {} to {}: {}",
+                            &block_id, &push_url, &e
+                        );
+                        increment_block_pushes_counter(false);
+                    }
+                }
+            }
+        });
+
+    if let Err(e) = res {
+        warn!("Failed to start block push thread: {:?}", &e);
+    }
+}
+
+/// Check that the data URLs we advertise to our peers actually reach this node.  Peers use these
+/// URLs to download blocks and microblocks, so if they're wrong, nobody can download from us.
+fn check_advertised_data_urls(data_urls: Vec<UrlString>, network_id: u32) {
+    for data_url in data_urls.into_iter() {
+        if let Err(e) = data_url.parse_to_block_url() {
+            warn!("Advertised data URL {:?} is invalid: {:?}", &data_url, &e);
+            continue;
+        }
+        if !data_url.has_routable_host() {
+            // the advertised URL is deduced from our public IP address
+            continue;
+        }
+        if !data_url.has_public_host() {
+            warn!(
+                "Advertised data URL {:?} has a loopback or private address; peers outside this network will not be able to download blocks from this node",
+                &data_url
+            );
+        }
+
+        let mut result = Err("not attempted".to_string());
+        for _ in 0..DATA_URL_CHECK_ATTEMPTS {
+            result = fetch_peer_info(&data_url);
+            if result.is_ok() {
+                break;
+            }
+            thread::sleep(DATA_URL_CHECK_TIMEOUT);
+        }
+
+        match result {
+            Ok(peer_info) => {
+                if peer_info.network_id != network_id {
+                    warn!(
+                        "Advertised data URL {:?} is served by a node on network {:x}, not {:x}; peers will not be able to download blocks from this node",
+                        &data_url, peer_info.network_id, network_id
+                    );
+                } else {
+                    info!("Advertised data URL {:?} is reachable", &data_url);
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "Advertised data URL {:?} is not reachable ({}); peers will not be able to download blocks from this node",
+                    &data_url, &e
+                );
+            }
+        }
+    }
+}
+
+/// Add the peers from another node's peer DB export to our frontier, so a freshly-provisioned
+/// node does not have to discover the network from its bootstrap node alone.
+fn seed_peer_db(peerdb: &mut PeerDB, peer_seed_file: &str) {
+    let peers: Vec<PeerDBExportEntry> = match fs::read_to_string(peer_seed_file)
+        .map_err(|e| format!("{:?}", &e))
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| format!("{:?}", &e)))
+    {
+        Ok(peers) => peers,
+        Err(e) => {
+            warn!("Failed to load peer seed file {}: {}", peer_seed_file, &e);
+            return;
+        }
+    };
+
+    let network_id = PeerDB::get_local_peer(peerdb.conn())
+        .expect("Unable to retrieve local peer")
+        .network_id;
+    let mut tx = peerdb.tx_begin().unwrap();
+    let num_imported = PeerDB::import_peers(&mut tx, network_id, &peers).unwrap();
+    tx.commit().unwrap();
+
+    info!(
+        "Seeded peer DB with {} of {} peer(s) from {}",
+        num_imported,
+        peers.len(),
+        peer_seed_file
+    );
+}
+
+/// Apply an update to the miner state reported on GET /v2/admin/miner
+fn update_miner_status<F: FnOnce(&mut RPCMinerStatusData)>(
+    miner_status: &Mutex<RPCMinerStatusData>,
+    update: F,
+) {
+    match miner_status.lock() {
+        Ok(mut miner_status) => update(&mut miner_status),
+        Err(e) => {
+            // can only happen if the p2p thread panics while holding the lock
+            error!("Miner status mutex is poisoned: {:?}", &e);
+        }
+    }
+}
+
 fn spawn_peer(
     mut this: PeerNetwork,
     p2p_sock: &SocketAddr,
@@ -447,7 +796,7 @@ fn spawn_peer(
     coord_comms: CoordinatorChannels,
     mut sync_comms: PoxSyncWatchdogComms,
     miner_tip_arc: Arc<Mutex<Option<(ConsensusHash, BlockHeaderHash, Secp256k1PrivateKey)>>>,
-    attachments_rx: Receiver<HashSet<AttachmentInstance>>,
+    miner_status_arc: Arc<Mutex<RPCMinerStatusData>>,
 ) -> Result<JoinHandle<()>, NetError> {
     let burn_db_path = config.get_burn_db_file_path();
     let stacks_chainstate_path = config.get_chainstate_path();
@@ -465,9 +814,17 @@ fn spawn_peer(
         block_limit,
     )
     .map_err(|e| NetError::ChainstateError(e.to_string()))?;
+    chainstate.size_limits = config.epoch_size_limits.clone();
+    chainstate.max_fork_depth = config.node.max_fork_depth;
+    let genesis_audit = get_genesis_audit_data(&config, &chainstate);
+    let genesis_chainstate_hash = genesis_chainstate_hash(&config);
 
     let mut mem_pool = MemPoolDB::open(false, TESTNET_CHAIN_ID, &stacks_chainstate_path)
         .map_err(NetError::DBError)?;
+    mem_pool.set_admit_unconfirmed(config.node.mempool_admit_unconfirmed);
+    mem_pool.set_rbf_bump_percent(config.node.mempool_rbf_bump_percent);
+    mem_pool.set_admission_policy(config.node.mempool_admission.clone());
+    mem_pool.set_gc_policy(config.node.mempool_gc.clone());
 
     // buffer up blocks to store without stalling the p2p thread
     let mut results_with_data = VecDeque::new();
@@ -478,8 +835,14 @@ fn spawn_peer(
     let server_thread = thread::spawn(move || {
         let handler_args = RPCHandlerArgs {
             exit_at_block_height: exit_at_block_height.as_ref(),
-            genesis_chainstate_hash: Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH)
-                .unwrap(),
+            genesis_chainstate_hash,
+            genesis_audit,
+            miner_status: Some(miner_status_arc),
+            scheduled_calls_path: config
+                .node
+                .call_scheduler_key
+                .as_ref()
+                .map(|_| config.get_call_scheduler_db_path()),
             ..RPCHandlerArgs::default()
         };
 
@@ -541,13 +904,10 @@ fn spawn_peer(
                 SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())
                     .expect("Failed to read canonical stacks chain tip");
 
-            let mut expected_attachments = match attachments_rx.try_recv() {
-                Ok(expected_attachments) => expected_attachments,
-                _ => {
-                    debug!("Atlas: attachment channel is empty");
-                    HashSet::new()
-                }
-            };
+            let mut expected_attachments = coord_comms.take_new_attachments();
+            if expected_attachments.is_empty() {
+                debug!("Atlas: attachments queue is empty");
+            }
 
             // guard p2p runner because it alters unconfirmed state trie
             let network_result = match coord_comms.kludgy_clarity_db_lock() {
@@ -645,6 +1005,7 @@ fn spawn_miner_relayer(
     burnchain: Burnchain,
     coord_comms: CoordinatorChannels,
     miner_tip_arc: Arc<Mutex<Option<(ConsensusHash, BlockHeaderHash, Secp256k1PrivateKey)>>>,
+    miner_status_arc: Arc<Mutex<RPCMinerStatusData>>,
 ) -> Result<(), NetError> {
     // Note: the relayer is *the* block processor, it is responsible for writes to the chainstate --
     //   no other codepaths should be writing once this is spawned.
@@ -661,9 +1022,13 @@ fn spawn_miner_relayer(
         config.block_limit.clone(),
     )
     .map_err(|e| NetError::ChainstateError(e.to_string()))?;
+    chainstate.size_limits = config.epoch_size_limits.clone();
+    chainstate.max_fork_depth = config.node.max_fork_depth;
 
     let mut mem_pool = MemPoolDB::open(false, TESTNET_CHAIN_ID, &stacks_chainstate_path)
         .map_err(NetError::DBError)?;
+    mem_pool.set_gc_policy(config.node.mempool_gc.clone());
+    mem_pool.set_tx_ordering(config.node.miner_tx_ordering);
 
     let mut last_mined_blocks: HashMap<
         BurnchainHeaderHash,
@@ -700,6 +1065,12 @@ fn spawn_miner_relayer(
                                 event_dispatcher
                                     .process_new_mempool_txs(net_receipts.mempool_txs_added);
                             }
+                            for mblocks_data in net_receipts.microblocks_added.iter() {
+                                event_dispatcher.process_new_microblocks(
+                                    &mblocks_data.index_anchor_block,
+                                    &mblocks_data.microblocks,
+                                );
+                            }
                         }
                         Err(e) => {
                             error!("FATAL: kludgy Clarity DB mutex poisoned: {:?}", &e);
@@ -796,6 +1167,13 @@ fn spawn_miner_relayer(
                                     let ch = snapshot.consensus_hash.clone();
                                     let bh = mined_block.block_hash();
 
+                                    push_block_to_peers(
+                                        config.node.block_push_urls.clone(),
+                                        config.node.block_push_retries,
+                                        ch.clone(),
+                                        mined_block.clone(),
+                                    );
+
                                     if let Err(e) = relayer
                                         .broadcast_block(snapshot.consensus_hash, mined_block)
                                     {
@@ -855,6 +1233,7 @@ fn spawn_miner_relayer(
                                 burn_fee_cap,
                                 &mut bitcoin_controller,
                                 &last_mined_blocks_vec.iter().map(|(blk, _)| blk).collect(),
+                                &miner_status_arc,
                             );
                             if let Some((last_mined_block, microblock_privkey)) =
                                 last_mined_block_opt
@@ -886,6 +1265,10 @@ fn spawn_miner_relayer(
                     );
                     if did_register_key {
                         key_registered_at_block = last_burn_block.block_height;
+                        update_miner_status(&miner_status_arc, |status| {
+                            status.pending_vrf_key_registration =
+                                Some(last_burn_block.block_height);
+                        });
                     }
                     bump_processed_counter(&blocks_processed);
                 }
@@ -895,6 +1278,13 @@ fn spawn_miner_relayer(
                     microblock,
                 ) => {
                     let microblock_hash = microblock.block_hash();
+                    event_dispatcher.process_new_microblocks(
+                        &StacksBlockHeader::make_index_block_hash(
+                            &parent_consensus_hash,
+                            &parent_block_hash,
+                        ),
+                        &[microblock.clone()],
+                    );
                     if let Err(e) = relayer.broadcast_microblock(
                         &parent_consensus_hash,
                         &parent_block_hash,
@@ -925,7 +1315,6 @@ impl InitializedNeonNode {
         coord_comms: CoordinatorChannels,
         sync_comms: PoxSyncWatchdogComms,
         burnchain: Burnchain,
-        attachments_rx: Receiver<HashSet<AttachmentInstance>>,
     ) -> InitializedNeonNode {
         // we can call _open_ here rather than _connect_, since connect is first called in
         //   make_genesis_block
@@ -945,6 +1334,7 @@ impl InitializedNeonNode {
         if let Some(ref bootstrap_node) = &config.node.bootstrap_node {
             initial_neighbors.push(bootstrap_node.clone());
         }
+        initial_neighbors.extend(config.node.relay_nodes.iter().cloned());
 
         println!("BOOTSTRAP WITH {:?}", initial_neighbors);
 
@@ -1003,19 +1393,35 @@ impl InitializedNeonNode {
                 )
                 .unwrap();
             }
+            for relay in config.node.relay_nodes.iter() {
+                PeerDB::set_allow_peer(
+                    &mut tx,
+                    relay.addr.network_id,
+                    &relay.addr.addrbytes,
+                    relay.addr.port,
+                    -1,
+                )
+                .unwrap();
+            }
             tx.commit().unwrap();
         }
+        if let Some(ref peer_seed_file) = config.node.peer_seed_file {
+            seed_peer_db(&mut peerdb, peer_seed_file);
+        }
         let atlasdb = AtlasDB::connect(&config.get_atlas_db_path(), true).unwrap();
+        let stackerdbs = StackerDBs::connect(&config.get_stackerdb_path(), true).unwrap();
 
         let local_peer = match PeerDB::get_local_peer(peerdb.conn()) {
             Ok(local_peer) => local_peer,
             _ => panic!("Unable to retrieve local peer"),
         };
+        let local_peer_network_id = local_peer.network_id;
 
         // now we're ready to instantiate a p2p network object, the relayer, and the event dispatcher
         let mut p2p_net = PeerNetwork::new(
             peerdb,
             atlasdb,
+            stackerdbs,
             local_peer.clone(),
             TESTNET_PEER_VERSION,
             burnchain.clone(),
@@ -1035,6 +1441,9 @@ impl InitializedNeonNode {
         // microblock mining can commense
         let miner_tip_arc = Arc::new(Mutex::new(None));
 
+        // miner state, as reported on GET /v2/admin/miner
+        let miner_status_arc = Arc::new(Mutex::new(RPCMinerStatusData::new(miner)));
+
         spawn_miner_relayer(
             relayer,
             local_peer,
@@ -1048,6 +1457,7 @@ impl InitializedNeonNode {
             burnchain,
             coord_comms.clone(),
             miner_tip_arc.clone(),
+            miner_status_arc.clone(),
         )
         .expect("Failed to initialize mine/relay thread");
 
@@ -1061,10 +1471,16 @@ impl InitializedNeonNode {
             coord_comms,
             sync_comms,
             miner_tip_arc.clone(),
-            attachments_rx,
+            miner_status_arc.clone(),
         )
         .expect("Failed to initialize mine/relay thread");
 
+        let mut advertised_data_urls = vec![data_url.clone()];
+        advertised_data_urls.extend(config.connection_options.alt_data_urls.iter().cloned());
+        thread::spawn(move || {
+            check_advertised_data_urls(advertised_data_urls, local_peer_network_id)
+        });
+
         info!("Bound HTTP server on: {}", &config.node.rpc_bind);
         info!("Bound P2P server on: {}", &config.node.p2p_bind);
 
@@ -1081,6 +1497,7 @@ impl InitializedNeonNode {
             is_miner,
             sleep_before_tenure,
             active_keys,
+            miner_status: miner_status_arc,
         }
     }
 
@@ -1151,6 +1568,7 @@ impl InitializedNeonNode {
         burn_fee_cap: u64,
         bitcoin_controller: &mut BitcoinRegtestController,
         last_mined_blocks: &Vec<&AssembledAnchorBlock>,
+        miner_status: &Mutex<RPCMinerStatusData>,
     ) -> Option<(AssembledAnchorBlock, Secp256k1PrivateKey)> {
         let (
             mut stacks_parent_header,
@@ -1325,7 +1743,11 @@ impl InitializedNeonNode {
                                    &prev_block.parent_consensus_hash, &prev_block.anchored_block.block_hash(), prev_block.anchored_block.header.total_work.work,
                                    prev_block.anchored_block.txs.len(), prev_block.my_burn_hash, parent_block_burn_height, stream.len(), prev_block.anchored_block.header.parent_microblock_sequence);
 
-                            return None;
+                            if !bitcoin_controller.commit_fee_rate_rose() {
+                                return None;
+                            }
+                            debug!("Burnchain fee rate has risen since we last tried to mine a block; replacing the block-commit");
+                            best_attempt = cmp::max(best_attempt, prev_block.attempt);
                         } else {
                             // there are new microblocks!
                             // TODO: only consider rebuilding our anchored block if we (a) have
@@ -1343,7 +1765,11 @@ impl InitializedNeonNode {
                                &prev_block.parent_consensus_hash, &prev_block.anchored_block.block_hash(), prev_block.anchored_block.header.total_work.work,
                                prev_block.anchored_block.txs.len(), prev_block.my_burn_hash, parent_block_burn_height);
 
-                        return None;
+                        if !bitcoin_controller.commit_fee_rate_rose() {
+                            return None;
+                        }
+                        debug!("Burnchain fee rate has risen since we last tried to mine a block; replacing the block-commit");
+                        best_attempt = cmp::max(best_attempt, prev_block.attempt);
                     }
                 } else {
                     debug!("Stacks tip has changed since we last tried to mine a block in {} at burn height {}; attempt was {} (for {}/{})",
@@ -1408,7 +1834,11 @@ impl InitializedNeonNode {
         let mblock_pubkey_hash =
             Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_secret_key));
 
-        let coinbase_tx = inner_generate_coinbase_tx(keychain, coinbase_nonce);
+        let coinbase_tx = inner_generate_coinbase_tx(
+            keychain,
+            coinbase_nonce,
+            config.node.miner_coinbase_recipient.clone(),
+        );
 
         // find the longest microblock tail we can build off of
         let microblock_info_opt =
@@ -1468,7 +1898,7 @@ impl InitializedNeonNode {
             }
         }
 
-        let (anchored_block, _, _) = match StacksBlockBuilder::build_anchored_block(
+        let (anchored_block, _, _) = match StacksBlockBuilder::build_anchored_block_with_budget(
             chain_state,
             &burn_db.index_conn(),
             mem_pool,
@@ -1478,6 +1908,7 @@ impl InitializedNeonNode {
             mblock_pubkey_hash,
             &coinbase_tx,
             HELIUM_BLOCK_LIMIT.clone(),
+            &config.node.miner_assembly_budget,
         ) {
             Ok(block) => block,
             Err(e) => {
@@ -1517,9 +1948,9 @@ impl InitializedNeonNode {
         let rest_commit = burn_fee_cap - sunset_burn;
 
         let commit_outs = if burn_block.block_height + 1 < burnchain.pox_constants.sunset_end {
-            RewardSetInfo::into_commit_outs(recipients, false)
+            RewardSetInfo::into_commit_outs(recipients, chain_state.mainnet)
         } else {
-            vec![StacksAddress::burn_address(false)]
+            vec![StacksAddress::burn_address(chain_state.mainnet)]
         };
 
         // let's commit
@@ -1550,6 +1981,28 @@ impl InitializedNeonNode {
             return None;
         }
 
+        update_miner_status(miner_status, |status| {
+            status.last_assembled_block = Some(RPCMinerAssembledBlock {
+                block_hash: anchored_block.block_hash(),
+                parent_consensus_hash: parent_consensus_hash.clone(),
+                burn_block_hash: format!("{}", &burn_block.burn_header_hash),
+                num_txs: anchored_block.txs.len() as u64,
+                attempt,
+            });
+            if let Some(txid) = bitcoin_controller.get_last_txid() {
+                status.inflight_commits.push(RPCMinerBlockCommit {
+                    txid: format!("{}", &txid),
+                    block_hash: anchored_block.block_hash(),
+                    parent_consensus_hash: parent_consensus_hash.clone(),
+                    burn_fee: rest_commit,
+                    sent_at_burn_height: burn_block.block_height,
+                    attempt,
+                    burn_block_height: None,
+                    confirmations: 0,
+                });
+            }
+        });
+
         Some((
             AssembledAnchorBlock {
                 parent_consensus_hash: parent_consensus_hash,
@@ -1585,6 +2038,17 @@ impl InitializedNeonNode {
 
         update_active_miners_count_gauge(block_commits.len() as i64);
 
+        let mut won_sortition = false;
+        if self.is_miner {
+            if let Some(my_commit) = block_commits
+                .iter()
+                .find(|op| op.apparent_sender == self.burnchain_signer)
+            {
+                won_sortition = my_commit.txid == block_snapshot.winning_block_txid;
+                increment_miner_sortitions_counters(won_sortition);
+            }
+        }
+
         for op in block_commits.into_iter() {
             if op.txid == block_snapshot.winning_block_txid {
                 info!(
@@ -1630,6 +2094,39 @@ impl InitializedNeonNode {
             }
         }
 
+        if self.is_miner {
+            let active_keys = &self.active_keys;
+            update_miner_status(&self.miner_status, |status| {
+                status.vrf_keys = active_keys
+                    .iter()
+                    .map(|key| RPCMinerVRFKey {
+                        vrf_public_key: key.vrf_public_key.to_hex(),
+                        block_height: key.block_height,
+                        op_vtxindex: key.op_vtxindex,
+                    })
+                    .collect();
+                if let Some(pending_height) = status.pending_vrf_key_registration {
+                    if active_keys
+                        .iter()
+                        .any(|key| key.block_height > pending_height)
+                    {
+                        status.pending_vrf_key_registration = None;
+                    }
+                }
+
+                status.last_sortition = Some(RPCMinerSortitionData {
+                    burn_block_hash: format!("{}", &block_snapshot.burn_header_hash),
+                    burn_block_height: block_snapshot.block_height,
+                    consensus_hash: block_snapshot.consensus_hash.clone(),
+                    sortition: block_snapshot.sortition,
+                    winning_block_txid: format!("{}", &block_snapshot.winning_block_txid),
+                    winning_stacks_block_hash: block_snapshot.winning_stacks_block_hash.clone(),
+                    won: won_sortition,
+                });
+                status.prune_inflight_commits(block_snapshot.block_height);
+            });
+        }
+
         // no-op on UserBurnSupport ops are not supported / produced at this point.
         self.last_burn_block = Some(block_snapshot);
 
@@ -1687,7 +2184,6 @@ impl NeonGenesisNode {
         blocks_processed: BlocksProcessedCounter,
         coord_comms: CoordinatorChannels,
         sync_comms: PoxSyncWatchdogComms,
-        attachments_rx: Receiver<HashSet<AttachmentInstance>>,
     ) -> InitializedNeonNode {
         let config = self.config;
         let keychain = self.keychain;
@@ -1703,7 +2199,6 @@ impl NeonGenesisNode {
             coord_comms,
             sync_comms,
             self.burnchain,
-            attachments_rx,
         )
     }
 
@@ -1713,7 +2208,6 @@ impl NeonGenesisNode {
         blocks_processed: BlocksProcessedCounter,
         coord_comms: CoordinatorChannels,
         sync_comms: PoxSyncWatchdogComms,
-        attachments_rx: Receiver<HashSet<AttachmentInstance>>,
     ) -> InitializedNeonNode {
         let config = self.config;
         let keychain = self.keychain;
@@ -1729,7 +2223,6 @@ impl NeonGenesisNode {
             coord_comms,
             sync_comms,
             self.burnchain,
-            attachments_rx,
         )
     }
 }
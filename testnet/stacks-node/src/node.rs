@@ -1,13 +1,15 @@
 use super::{
-    genesis_data::GENESIS_DATA, BurnchainController, BurnchainTip, Config, EventDispatcher,
-    Keychain, Tenure,
+    genesis_data::{genesis_chainstate_hash, load_genesis_data, make_genesis_balances_audit},
+    BurnchainController, BurnchainTip, Config, EventDispatcher, Keychain, Tenure,
 };
+use crate::burnchains::{DevBlockRequest, DevBlockTrigger};
 use crate::run_loop::RegisteredKey;
 
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::default::Default;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::{thread, thread::JoinHandle, time};
 
 use stacks::chainstate::burn::db::sortdb::SortitionDB;
@@ -27,8 +29,12 @@ use stacks::chainstate::stacks::{
 };
 use stacks::core::mempool::MemPoolDB;
 use stacks::net::{
-    atlas::AtlasDB, db::PeerDB, p2p::PeerNetwork, rpc::RPCHandlerArgs, Error as NetError,
-    PeerAddress,
+    atlas::AtlasDB,
+    db::PeerDB,
+    p2p::PeerNetwork,
+    rpc::{DevMineCallback, RPCHandlerArgs},
+    stackerdb::StackerDBs,
+    Error as NetError, PeerAddress,
 };
 use stacks::{
     burnchains::{Burnchain, BurnchainHeaderHash, Txid},
@@ -41,6 +47,7 @@ use stacks::util::hash::Sha256Sum;
 use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::util::strings::UrlString;
 use stacks::util::vrf::VRFPublicKey;
+use stx_genesis::GenesisData;
 
 pub const TESTNET_CHAIN_ID: u32 = 0x80000000;
 pub const TESTNET_PEER_VERSION: u32 = 0xfacade01;
@@ -85,11 +92,14 @@ pub struct Node {
     last_sortitioned_block: Option<BurnchainTip>,
     event_dispatcher: EventDispatcher,
     nonce: u64,
+    dev_block_trigger: Option<DevBlockTrigger>,
 }
 
-pub fn get_account_lockups() -> Box<dyn Iterator<Item = ChainstateAccountLockup>> {
+pub fn get_account_lockups(
+    genesis_data: GenesisData,
+) -> Box<dyn Iterator<Item = ChainstateAccountLockup>> {
     Box::new(
-        GENESIS_DATA
+        genesis_data
             .read_lockups()
             .map(|item| ChainstateAccountLockup {
                 address: item.address,
@@ -99,9 +109,11 @@ pub fn get_account_lockups() -> Box<dyn Iterator<Item = ChainstateAccountLockup>
     )
 }
 
-pub fn get_account_balances() -> Box<dyn Iterator<Item = ChainstateAccountBalance>> {
+pub fn get_account_balances(
+    genesis_data: GenesisData,
+) -> Box<dyn Iterator<Item = ChainstateAccountBalance>> {
     Box::new(
-        GENESIS_DATA
+        genesis_data
             .read_balances()
             .map(|item| ChainstateAccountBalance {
                 address: item.address,
@@ -120,12 +132,14 @@ fn spawn_peer(
     exit_at_block_height: Option<u64>,
     genesis_chainstate_hash: Sha256Sum,
     poll_timeout: u64,
+    dev_mine: Option<DevMineCallback>,
 ) -> Result<JoinHandle<()>, NetError> {
     this.bind(p2p_sock, rpc_sock).unwrap();
     let server_thread = thread::spawn(move || {
         let handler_args = RPCHandlerArgs {
             exit_at_block_height: exit_at_block_height.as_ref(),
             genesis_chainstate_hash: genesis_chainstate_hash,
+            dev_mine,
             ..RPCHandlerArgs::default()
         };
 
@@ -189,14 +203,19 @@ impl Node {
             .map(|e| (e.address.clone(), e.amount))
             .collect();
 
+        let genesis_data = load_genesis_data(&config);
+        let lockups_data = genesis_data.clone();
+
         let mut boot_data = ChainStateBootData {
             initial_balances,
+            initial_locks: vec![],
             first_burnchain_block_hash: BurnchainHeaderHash::zero(),
             first_burnchain_block_height: 0,
             first_burnchain_block_timestamp: 0,
             post_flight_callback: Some(boot_block_exec),
-            get_bulk_initial_lockups: Some(Box::new(get_account_lockups)),
-            get_bulk_initial_balances: Some(Box::new(get_account_balances)),
+            get_bulk_initial_lockups: Some(Box::new(move || get_account_lockups(lockups_data))),
+            get_bulk_initial_balances: Some(Box::new(move || get_account_balances(genesis_data))),
+            genesis_balances_audit: make_genesis_balances_audit(&config),
         };
 
         let chain_state_result = StacksChainState::open_and_exec(
@@ -207,7 +226,7 @@ impl Node {
             config.block_limit.clone(),
         );
 
-        let (chain_state, receipts) = match chain_state_result {
+        let (mut chain_state, receipts) = match chain_state_result {
             Ok(res) => res,
             Err(err) => panic!(
                 "Error while opening chain state at path {}: {:?}",
@@ -215,6 +234,9 @@ impl Node {
                 err
             ),
         };
+        chain_state.size_limits = config.epoch_size_limits.clone();
+        chain_state.max_fork_depth = config.node.max_fork_depth;
+        config.enable_consensus_audit_log(&mut chain_state);
         let mut event_dispatcher = EventDispatcher::new();
 
         for observer in &config.events_observers {
@@ -234,6 +256,7 @@ impl Node {
             burnchain_tip: None,
             nonce: 0,
             event_dispatcher,
+            dev_block_trigger: None,
         }
     }
 
@@ -254,11 +277,14 @@ impl Node {
         let chainstate_path = config.get_chainstate_path();
         let sortdb_path = config.get_burn_db_file_path();
 
-        let (chain_state, _) =
+        let (mut chain_state, _) =
             match StacksChainState::open(false, TESTNET_CHAIN_ID, &chainstate_path) {
                 Ok(x) => x,
                 Err(_e) => panic!(),
             };
+        chain_state.size_limits = config.epoch_size_limits.clone();
+        chain_state.max_fork_depth = config.node.max_fork_depth;
+        config.enable_consensus_audit_log(&mut chain_state);
 
         let mut node = Node {
             active_registered_key: None,
@@ -271,6 +297,7 @@ impl Node {
             burnchain_tip: None,
             nonce: 0,
             event_dispatcher,
+            dev_block_trigger: None,
         };
 
         node.spawn_peer_server();
@@ -296,6 +323,12 @@ impl Node {
         node
     }
 
+    /// Let clients ask for blocks through `POST /v2/dev/mine` (only in `dev` burnchain mode).
+    /// Must be called before the peer server is spawned.
+    pub fn set_dev_block_trigger(&mut self, trigger: DevBlockTrigger) {
+        self.dev_block_trigger = Some(trigger);
+    }
+
     pub fn spawn_peer_server(&mut self) {
         // we can call _open_ here rather than _connect_, since connect is first called in
         //   make_genesis_block
@@ -318,6 +351,7 @@ impl Node {
         if let Some(ref bootstrap_node) = self.config.node.bootstrap_node {
             initial_neighbors.push(bootstrap_node.clone());
         }
+        initial_neighbors.extend(self.config.node.relay_nodes.iter().cloned());
 
         println!("BOOTSTRAP WITH {:?}", initial_neighbors);
 
@@ -376,9 +410,20 @@ impl Node {
                 )
                 .unwrap();
             }
+            for relay in self.config.node.relay_nodes.iter() {
+                PeerDB::set_allow_peer(
+                    &mut tx,
+                    relay.addr.network_id,
+                    &relay.addr.addrbytes,
+                    relay.addr.port,
+                    -1,
+                )
+                .unwrap();
+            }
             tx.commit().unwrap();
         }
         let atlasdb = AtlasDB::connect(&self.config.get_peer_db_path(), true).unwrap();
+        let stackerdbs = StackerDBs::connect(&self.config.get_stackerdb_path(), true).unwrap();
 
         let local_peer = match PeerDB::get_local_peer(peerdb.conn()) {
             Ok(local_peer) => local_peer,
@@ -387,10 +432,16 @@ impl Node {
 
         let event_dispatcher = self.event_dispatcher.clone();
         let exit_at_block_height = self.config.burnchain.process_exit_at_block_height.clone();
+        let dev_mine = self.dev_block_trigger.clone().map(|trigger| {
+            let callback: DevMineCallback =
+                Arc::new(move |request| trigger.request(DevBlockRequest::from(request)));
+            callback
+        });
 
         let p2p_net = PeerNetwork::new(
             peerdb,
             atlasdb,
+            stackerdbs,
             local_peer,
             TESTNET_PEER_VERSION,
             burnchain,
@@ -405,8 +456,9 @@ impl Node {
             self.config.get_chainstate_path(),
             event_dispatcher,
             exit_at_block_height,
-            Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH).unwrap(),
+            genesis_chainstate_hash(&self.config),
             1000,
+            dev_mine,
         )
         .unwrap();
 
@@ -515,12 +567,14 @@ impl Node {
             Some(block) => block.clone(),
         };
 
-        // Generates a proof out of the sortition hash provided in the params.
+        // Generates a proof out of the sortition hash of the burnchain tip, which is the last
+        // sortitioned block unless some blocks were mined without a sortition since.
+        let burnchain_tip = self.burnchain_tip.as_ref().unwrap_or(&block_to_build_upon);
         let vrf_proof = self
             .keychain
             .generate_proof(
                 &registered_key.vrf_public_key,
-                block_to_build_upon.block_snapshot.sortition_hash.as_bytes(),
+                burnchain_tip.block_snapshot.sortition_hash.as_bytes(),
             )
             .unwrap();
 
@@ -544,8 +598,9 @@ impl Node {
             },
         };
 
-        let mem_pool = MemPoolDB::open(false, TESTNET_CHAIN_ID, &self.chain_state.root_path)
+        let mut mem_pool = MemPoolDB::open(false, TESTNET_CHAIN_ID, &self.chain_state.root_path)
             .expect("FATAL: failed to open mempool");
+        mem_pool.set_tx_ordering(self.config.node.miner_tx_ordering);
 
         // Construct the coinbase transaction - 1st txn that should be handled and included in
         // the upcoming tenure.
@@ -578,20 +633,12 @@ impl Node {
         if self.active_registered_key.is_some() {
             let registered_key = self.active_registered_key.clone().unwrap();
 
-            let vrf_proof = self
-                .keychain
-                .generate_proof(
-                    &registered_key.vrf_public_key,
-                    burnchain_tip.block_snapshot.sortition_hash.as_bytes(),
-                )
-                .unwrap();
-
             let op = self.generate_block_commit_op(
                 anchored_block_from_ongoing_tenure.header.block_hash(),
                 burn_fee,
                 &registered_key,
                 &burnchain_tip,
-                VRFSeed::from_proof(&vrf_proof),
+                VRFSeed::from_proof(&anchored_block_from_ongoing_tenure.header.proof),
             );
 
             let mut op_signer = self.keychain.generate_op_signer();
@@ -794,6 +841,12 @@ impl Node {
         })
     }
 
+    /// Give back the coinbase nonce of the last tenure, whose block will never be processed
+    /// (e.g. because its block-commit was mined without a sortition).
+    pub fn abandon_tenure(&mut self) {
+        self.nonce -= 1;
+    }
+
     // Constructs a coinbase transaction
     fn generate_coinbase_tx(&mut self) -> StacksTransaction {
         let mut tx_auth = self.keychain.get_transaction_auth().unwrap();
@@ -802,7 +855,7 @@ impl Node {
         let mut tx = StacksTransaction::new(
             TransactionVersion::Testnet,
             tx_auth,
-            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32]), None),
         );
         tx.chain_id = TESTNET_CHAIN_ID;
         tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
@@ -0,0 +1,246 @@
+//! The poison reporter: watches the microblock streams this node has stored for forks -- two
+//! microblocks signed by the same miner at the same sequence -- and reports each one in a
+//! `PoisonMicroblock` transaction signed with the node's `poison_reporter_key`.  If the report
+//! is mined while the forked stream's parent block is still immature, the reporter is paid the
+//! poison-microblock commission out of that miner's coinbase.
+//!
+//! Only the lowest-sequence fork of each stream is reported, since that is the one the chain
+//! keeps.  Reports go through the node's own RPC interface, just as the call scheduler's do.
+
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::{StacksChainState, MINER_REWARD_MATURITY};
+use stacks::chainstate::stacks::{
+    StacksAddress, StacksBlockId, StacksPrivateKey, StacksPublicKey, StacksTransaction,
+    StacksTransactionSigner, TransactionAnchorMode, TransactionAuth, TransactionPayload,
+    TransactionPostConditionMode, TransactionSpendingCondition, TransactionVersion,
+};
+
+use super::config::Config;
+use super::neon_node::TESTNET_CHAIN_ID;
+use super::scheduler::{get_nonce, local_rpc_addr, p2pkh_address, post_transaction, SubmitError};
+
+/// How often to scan for microblock stream forks
+const POISON_REPORTER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Build and sign a poison-microblock report
+fn make_poison_tx(
+    payload: TransactionPayload,
+    privk: &StacksPrivateKey,
+    nonce: u64,
+    fee: u64,
+) -> Result<StacksTransaction, String> {
+    let mut spending_condition =
+        TransactionSpendingCondition::new_singlesig_p2pkh(StacksPublicKey::from_private(privk))
+            .ok_or("Failed to create spending condition".to_string())?;
+    spending_condition.set_nonce(nonce);
+    spending_condition.set_fee_rate(fee);
+
+    let mut tx = StacksTransaction::new(
+        TransactionVersion::Testnet,
+        TransactionAuth::Standard(spending_condition),
+        payload,
+    );
+    tx.chain_id = TESTNET_CHAIN_ID;
+    tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
+    tx.post_condition_mode = TransactionPostConditionMode::Deny;
+
+    let mut tx_signer = StacksTransactionSigner::new(&tx);
+    tx_signer
+        .sign_origin(privk)
+        .map_err(|e| format!("Failed to sign transaction: {:?}", e))?;
+    tx_signer
+        .get_tx()
+        .ok_or("Failed to sign transaction".to_string())
+}
+
+pub struct PoisonReporter {
+    config: Config,
+    privk: StacksPrivateKey,
+    address: StacksAddress,
+    /// the loopback address of the node's RPC interface
+    rpc_addr: String,
+    /// the forks (parent block and sequence) already reported
+    reported: HashSet<(StacksBlockId, u16)>,
+}
+
+impl PoisonReporter {
+    pub fn new(config: Config) -> PoisonReporter {
+        let privk = StacksPrivateKey::from_hex(
+            config
+                .node
+                .poison_reporter_key
+                .as_ref()
+                .expect("BUG: no poison reporter key"),
+        )
+        .expect("Invalid poison_reporter_key");
+        let address = p2pkh_address(&privk);
+        let rpc_addr = local_rpc_addr(&config);
+
+        PoisonReporter {
+            config,
+            privk,
+            address,
+            rpc_addr,
+            reported: HashSet::new(),
+        }
+    }
+
+    fn get_stacks_tip_height(&self) -> Option<u64> {
+        let sortdb = SortitionDB::open(&self.config.get_burn_db_file_path(), false).ok()?;
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).ok()?;
+        Some(tip.canonical_stacks_tip_height)
+    }
+
+    /// Report every fork off of a block that is still young enough to be punished
+    pub fn report_forks(&mut self, chainstate: &StacksChainState, stacks_tip_height: u64) {
+        let min_height = stacks_tip_height.saturating_sub(MINER_REWARD_MATURITY);
+        let forks =
+            match StacksChainState::find_microblock_stream_forks(chainstate.db(), min_height) {
+                Ok(forks) => forks,
+                Err(e) => {
+                    warn!(
+                        "Poison reporter: failed to scan for microblock forks: {:?}",
+                        &e
+                    );
+                    return;
+                }
+            };
+
+        let forks: Vec<_> = forks
+            .into_iter()
+            .filter_map(|(parent, payload)| match payload {
+                TransactionPayload::PoisonMicroblock(ref header, _) => {
+                    Some(((parent, header.sequence), payload))
+                }
+                _ => None,
+            })
+            .collect();
+
+        // forget forks that have fallen out of the punishment window
+        self.reported
+            .retain(|fork| forks.iter().any(|(key, _)| key == fork));
+
+        let mut nonce = None;
+        for (key, payload) in forks.into_iter() {
+            if self.reported.contains(&key) {
+                continue;
+            }
+            let next_nonce = match nonce {
+                Some(nonce) => nonce,
+                None => match get_nonce(&self.rpc_addr, &self.address) {
+                    Ok(nonce) => nonce,
+                    Err(e) => {
+                        warn!("Poison reporter: failed to load nonce: {:?}", &e);
+                        return;
+                    }
+                },
+            };
+
+            let result = make_poison_tx(
+                payload,
+                &self.privk,
+                next_nonce,
+                self.config.node.poison_reporter_fee,
+            )
+            .map_err(SubmitError::Rejected)
+            .and_then(|tx| post_transaction(&self.rpc_addr, &tx).map(|_| tx));
+            match result {
+                Ok(tx) => {
+                    info!(
+                        "Poison reporter: reported microblock fork off of {} at sequence {} in {}",
+                        &key.0,
+                        key.1,
+                        &tx.txid()
+                    );
+                    nonce = Some(next_nonce + 1);
+                    self.reported.insert(key);
+                }
+                Err(SubmitError::Rejected(reason)) => {
+                    warn!(
+                        "Poison reporter: report of microblock fork off of {} at sequence {} was rejected: {}",
+                        &key.0, key.1, &reason
+                    );
+                    nonce = Some(next_nonce);
+                    self.reported.insert(key);
+                }
+                Err(SubmitError::Unreachable(reason)) => {
+                    warn!(
+                        "Poison reporter: failed to report microblock fork off of {}, will retry: {}",
+                        &key.0, &reason
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Scan for forks forever
+    pub fn run(mut self) {
+        info!(
+            "Poison reporter: reporting microblock stream forks from {}",
+            &self.address
+        );
+
+        let mut chainstate = None;
+        loop {
+            thread::sleep(POISON_REPORTER_POLL_INTERVAL);
+            let stacks_tip_height = match self.get_stacks_tip_height() {
+                Some(height) => height,
+                None => continue,
+            };
+            if chainstate.is_none() {
+                chainstate = StacksChainState::open(
+                    false,
+                    TESTNET_CHAIN_ID,
+                    &self.config.get_chainstate_path(),
+                )
+                .map(|(chainstate, _)| chainstate)
+                .ok();
+            }
+            if let Some(ref chainstate) = chainstate {
+                self.report_forks(chainstate, stacks_tip_height);
+            }
+        }
+    }
+}
+
+pub fn start_poison_reporter(config: Config) {
+    PoisonReporter::new(config).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stacks::chainstate::burn::BlockHeaderHash;
+    use stacks::chainstate::stacks::StacksMicroblockHeader;
+    use stacks::util::hash::Sha512Trunc256Sum;
+
+    #[test]
+    fn poison_report_tx_is_signed_on_chain_only() {
+        let privk = StacksPrivateKey::new();
+        let miner_privk = StacksPrivateKey::new();
+
+        let mut header_1 = StacksMicroblockHeader::first_unsigned(
+            &BlockHeaderHash([1u8; 32]),
+            &Sha512Trunc256Sum([2u8; 32]),
+        );
+        let mut header_2 = StacksMicroblockHeader::first_unsigned(
+            &BlockHeaderHash([1u8; 32]),
+            &Sha512Trunc256Sum([3u8; 32]),
+        );
+        header_1.sign(&miner_privk).unwrap();
+        header_2.sign(&miner_privk).unwrap();
+
+        let payload = TransactionPayload::PoisonMicroblock(header_1, header_2);
+        let tx = make_poison_tx(payload.clone(), &privk, 3, 1000).unwrap();
+        tx.verify().unwrap();
+        assert_eq!(tx.get_origin_nonce(), 3);
+        assert_eq!(tx.get_fee_rate(), 1000);
+        assert_eq!(tx.anchor_mode, TransactionAnchorMode::OnChainOnly);
+        assert_eq!(tx.payload, payload);
+    }
+}
@@ -0,0 +1,108 @@
+use std::cmp;
+use std::fs;
+
+use stacks::burnchains::Txid;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::StacksBlockHeader;
+
+use super::config::{Config, EventKeyType, EventObserverConfig};
+use super::event_dispatcher::EventDispatcher;
+use super::neon_node::TESTNET_CHAIN_ID;
+
+/// Re-send the `new_block` events of the canonical fork's blocks at heights `from_height`
+/// through `to_height` (inclusive) to the event observer at `observer`, using the transaction
+/// receipts stored in the node's chainstate.  Heights above the chain tip are skipped.
+/// Returns the number of blocks replayed.
+pub fn replay_events(
+    conf: &Config,
+    from_height: u64,
+    to_height: u64,
+    observer: &str,
+) -> Result<u64, String> {
+    if from_height > to_height {
+        return Err(format!(
+            "--from-height {} is above --to-height {}",
+            from_height, to_height
+        ));
+    }
+
+    let chainstate_path = conf.get_chainstate_path();
+    let burn_db_path = conf.get_burn_db_file_path();
+    if fs::metadata(&chainstate_path).is_err() || fs::metadata(&burn_db_path).is_err() {
+        return Err(format!("No chainstate found in {}", &conf.node.working_dir));
+    }
+
+    let sortdb = SortitionDB::open(&burn_db_path, false)
+        .map_err(|e| format!("Failed to open sortition DB: {}", e))?;
+    let (mut chainstate, _) = StacksChainState::open(false, TESTNET_CHAIN_ID, &chainstate_path)
+        .map_err(|e| format!("Failed to open chainstate: {}", e))?;
+
+    let tip = chainstate
+        .get_stacks_chain_tip(&sortdb)
+        .map_err(|e| format!("Failed to load the Stacks chain tip: {}", e))?
+        .ok_or("No Stacks blocks have been processed".to_string())?;
+    let tip_index_hash =
+        StacksBlockHeader::make_index_block_hash(&tip.consensus_hash, &tip.anchored_block_hash);
+
+    let endpoint = observer
+        .trim_start_matches("http://")
+        .trim_end_matches('/')
+        .to_string();
+    let mut event_dispatcher = EventDispatcher::new();
+    event_dispatcher.register_observer(&EventObserverConfig {
+        endpoint,
+        events_keys: vec![EventKeyType::AnyEvent],
+    });
+
+    // the boot block (height 0) is never announced
+    let from_height = cmp::max(from_height, 1);
+    let to_height = cmp::min(to_height, tip.height);
+
+    let mut num_replayed = 0;
+    for height in from_height..=to_height {
+        let header = {
+            let mut tx = chainstate
+                .index_tx_begin()
+                .map_err(|e| format!("Failed to begin chainstate transaction: {}", e))?;
+            StacksChainState::get_index_tip_ancestor(&mut tx, &tip_index_hash, height)
+                .map_err(|e| format!("Failed to load block header at height {}: {}", height, e))?
+                .ok_or(format!("No block header at height {}", height))?
+        };
+        let index_block_hash = header.index_block_hash();
+
+        let block = StacksChainState::load_block(
+            &chainstate.blocks_path,
+            &header.consensus_hash,
+            &header.anchored_header.block_hash(),
+        )
+        .map_err(|e| format!("Failed to load block {}: {}", &index_block_hash, e))?
+        .ok_or(format!("Block {} is invalid", &index_block_hash))?;
+
+        let parent_index_hash =
+            StacksChainState::get_parent_block_id(chainstate.db(), &index_block_hash)
+                .map_err(|e| format!("Failed to load parent of {}: {}", &index_block_hash, e))?
+                .ok_or(format!("No parent for block {}", &index_block_hash))?;
+
+        let winner_txid =
+            SortitionDB::get_block_snapshot_consensus(sortdb.conn(), &header.consensus_hash)
+                .map_err(|e| format!("Failed to load snapshot {}: {}", &header.consensus_hash, e))?
+                .map(|sn| sn.winning_block_txid)
+                .unwrap_or(Txid([0u8; 32]));
+
+        let receipts =
+            StacksChainState::get_transaction_receipts(chainstate.db(), &index_block_hash)
+                .map_err(|e| format!("Failed to load receipts for {}: {}", &index_block_hash, e))?;
+
+        info!(
+            "Replay events for block {} at height {} ({} transactions)",
+            &index_block_hash,
+            height,
+            receipts.len()
+        );
+        event_dispatcher.replay_block(&header, &block, &parent_index_hash, &winner_txid, &receipts);
+        num_replayed += 1;
+    }
+
+    Ok(num_replayed)
+}
@@ -0,0 +1,532 @@
+//! The Rosetta Construction API, for single-signature STX transfers.
+//!
+//! A transfer is described by a `token_transfer` debit and credit of the same amount, and
+//! optionally a `fee` debit of the sender.  `/construction/preprocess` turns them into options
+//! for `/construction/metadata`, which looks up the sender's nonce and suggests a fee at the
+//! minimum fee rate.  `/construction/payloads` builds the unsigned transaction and the sighash
+//! the sender must sign (with `ecdsa_recovery`), and `/construction/combine` attaches the
+//! signature.  `/construction/submit` posts the signed transaction to the node's RPC
+//! interface, just as a wallet would.
+
+use async_h1::client;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+
+use stacks::address::AddressHashMode;
+use stacks::burnchains::Address;
+use stacks::chainstate::stacks::db::blocks::MINIMUM_TX_FEE_RATE_PER_BYTE;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::{
+    SinglesigHashMode, SinglesigSpendingCondition, StacksAddress, StacksPublicKey,
+    StacksTransaction, TokenTransferMemo, TransactionAuth, TransactionAuthFlags,
+    TransactionPayload, TransactionPublicKeyEncoding, TransactionSpendingCondition,
+    TransactionVersion, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+    C32_ADDRESS_VERSION_TESTNET_SINGLESIG, TOKEN_TRANSFER_MEMO_LENGTH,
+};
+use stacks::net::StacksMessageCodec;
+use stacks::util::hash::{hex_bytes, to_hex};
+use stacks::util::secp256k1::MessageSignature;
+use stacks::vm::types::PrincipalData;
+
+use super::operations::{self, OP_FEE, OP_TOKEN_TRANSFER};
+use super::{decode_transaction, transaction_identifier, RosettaContext, RosettaError};
+
+pub const SIGNATURE_TYPE: &str = "ecdsa_recovery";
+pub const CURVE_TYPE: &str = "secp256k1";
+
+/// An STX transfer, as described by a request's operations
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferIntent {
+    pub sender: StacksAddress,
+    pub recipient: PrincipalData,
+    pub amount: u64,
+    pub fee: Option<u64>,
+}
+
+fn op_amount(op: &serde_json::Value) -> Result<i128, RosettaError> {
+    if op["amount"]["currency"] != operations::currency() {
+        return Err(RosettaError::InvalidOperations(
+            "Only STX amounts are supported".to_string(),
+        ));
+    }
+    op["amount"]["value"]
+        .as_str()
+        .and_then(|value| value.parse::<i128>().ok())
+        .ok_or(RosettaError::InvalidOperations(
+            "Operation has no amount".to_string(),
+        ))
+}
+
+fn op_address(op: &serde_json::Value) -> Result<String, RosettaError> {
+    op["account"]["address"]
+        .as_str()
+        .map(|address| address.to_string())
+        .ok_or(RosettaError::InvalidOperations(
+            "Operation has no account".to_string(),
+        ))
+}
+
+impl TransferIntent {
+    pub fn from_operations(ops: &serde_json::Value) -> Result<TransferIntent, RosettaError> {
+        let ops = ops.as_array().ok_or(RosettaError::MalformedRequest(
+            "Missing operations".to_string(),
+        ))?;
+
+        let mut debit = None;
+        let mut credit = None;
+        let mut fee = None;
+        for op in ops.iter() {
+            match op["type"].as_str() {
+                Some(OP_TOKEN_TRANSFER) => {
+                    let amount = op_amount(op)?;
+                    let address = op_address(op)?;
+                    let slot = if amount < 0 { &mut debit } else { &mut credit };
+                    if slot.is_some() {
+                        return Err(RosettaError::InvalidOperations(
+                            "Expected one debit and one credit".to_string(),
+                        ));
+                    }
+                    *slot = Some((address, amount));
+                }
+                Some(OP_FEE) => {
+                    let amount = op_amount(op)?;
+                    if amount > 0 || fee.is_some() {
+                        return Err(RosettaError::InvalidOperations(
+                            "Expected at most one fee debit".to_string(),
+                        ));
+                    }
+                    fee = Some((op_address(op)?, -amount));
+                }
+                _ => {
+                    return Err(RosettaError::InvalidOperations(format!(
+                        "Unsupported operation type {}",
+                        op["type"]
+                    )));
+                }
+            }
+        }
+
+        let (sender, debit_amount) = debit.ok_or(RosettaError::InvalidOperations(
+            "Missing token_transfer debit".to_string(),
+        ))?;
+        let (recipient, credit_amount) = credit.ok_or(RosettaError::InvalidOperations(
+            "Missing token_transfer credit".to_string(),
+        ))?;
+        if debit_amount + credit_amount != 0 || credit_amount == 0 {
+            return Err(RosettaError::InvalidOperations(
+                "Debit and credit amounts do not match".to_string(),
+            ));
+        }
+        let amount = to_ustx(credit_amount)?;
+
+        let sender = StacksAddress::from_string(&sender).ok_or(RosettaError::InvalidAccount(
+            format!("Invalid sender {}", sender),
+        ))?;
+        let recipient = PrincipalData::parse(&recipient).map_err(|_| {
+            RosettaError::InvalidAccount(format!("Invalid recipient {}", recipient))
+        })?;
+        let fee = match fee {
+            Some((payer, fee)) => {
+                if payer != sender.to_string() {
+                    return Err(RosettaError::InvalidOperations(
+                        "The fee must be paid by the sender".to_string(),
+                    ));
+                }
+                Some(to_ustx(fee)?)
+            }
+            None => None,
+        };
+
+        Ok(TransferIntent {
+            sender,
+            recipient,
+            amount,
+            fee,
+        })
+    }
+}
+
+fn to_ustx(value: i128) -> Result<u64, RosettaError> {
+    if value < 0 || value > (u64::MAX as i128) {
+        return Err(RosettaError::InvalidOperations(format!(
+            "Amount {} out of range",
+            value
+        )));
+    }
+    Ok(value as u64)
+}
+
+fn parse_memo(memo: &serde_json::Value) -> Result<TokenTransferMemo, RosettaError> {
+    let mut bytes = [0u8; TOKEN_TRANSFER_MEMO_LENGTH];
+    if let Some(memo) = memo.as_str() {
+        if memo.len() > TOKEN_TRANSFER_MEMO_LENGTH {
+            return Err(RosettaError::MalformedRequest(format!(
+                "Memo is longer than {} bytes",
+                TOKEN_TRANSFER_MEMO_LENGTH
+            )));
+        }
+        bytes[..memo.len()].copy_from_slice(memo.as_bytes());
+    }
+    Ok(TokenTransferMemo(bytes))
+}
+
+fn parse_public_key(public_key: &serde_json::Value) -> Result<StacksPublicKey, RosettaError> {
+    if public_key["curve_type"] != CURVE_TYPE {
+        return Err(RosettaError::InvalidPublicKey(format!(
+            "Only {} keys are supported",
+            CURVE_TYPE
+        )));
+    }
+    let hex = public_key["hex_bytes"]
+        .as_str()
+        .ok_or(RosettaError::InvalidPublicKey(
+            "Missing hex_bytes".to_string(),
+        ))?;
+    StacksPublicKey::from_hex(hex).map_err(|e| RosettaError::InvalidPublicKey(e.to_string()))
+}
+
+fn get_u64(value: &serde_json::Value, name: &str) -> Result<u64, RosettaError> {
+    match value {
+        serde_json::Value::String(ref s) => s.parse::<u64>().ok(),
+        _ => value.as_u64(),
+    }
+    .ok_or(RosettaError::MalformedRequest(format!("Missing {}", name)))
+}
+
+fn p2pkh_address(ctx: &RosettaContext, public_key: &StacksPublicKey) -> StacksAddress {
+    let version = if ctx.mainnet {
+        C32_ADDRESS_VERSION_MAINNET_SINGLESIG
+    } else {
+        C32_ADDRESS_VERSION_TESTNET_SINGLESIG
+    };
+    StacksAddress::from_public_keys(
+        version,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![public_key.clone()],
+    )
+    .expect("BUG: failed to make an address from one public key")
+}
+
+/// Build the unsigned transfer, to be authorized by `spending_condition`
+fn make_unsigned_transfer(
+    ctx: &RosettaContext,
+    intent: &TransferIntent,
+    mut spending_condition: TransactionSpendingCondition,
+    memo: TokenTransferMemo,
+    nonce: u64,
+    fee: u64,
+) -> StacksTransaction {
+    spending_condition.set_nonce(nonce);
+    spending_condition.set_fee_rate(fee);
+    let version = if ctx.mainnet {
+        TransactionVersion::Mainnet
+    } else {
+        TransactionVersion::Testnet
+    };
+    let mut tx = StacksTransaction::new(
+        version,
+        TransactionAuth::Standard(spending_condition),
+        TransactionPayload::TokenTransfer(intent.recipient.clone(), intent.amount, memo, None),
+    );
+    tx.chain_id = ctx.chain_id;
+    tx
+}
+
+pub fn handle_derive(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let public_key = parse_public_key(&request["public_key"])?;
+    Ok(json!({
+        "account_identifier": { "address": p2pkh_address(ctx, &public_key).to_string() },
+    }))
+}
+
+pub fn handle_preprocess(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let intent = TransferIntent::from_operations(&request["operations"])?;
+    // check the memo now, rather than after the caller has looked up metadata
+    parse_memo(&request["metadata"]["memo"])?;
+
+    let mut options = json!({
+        "sender_address": intent.sender.to_string(),
+        "recipient_address": intent.recipient.to_string(),
+        "amount": intent.amount.to_string(),
+    });
+    if let Some(fee) = intent.fee {
+        options["fee"] = json!(fee.to_string());
+    }
+    if let Some(memo) = request["metadata"]["memo"].as_str() {
+        options["memo"] = json!(memo);
+    }
+    Ok(json!({
+        "options": options,
+        "required_public_keys": [{ "address": intent.sender.to_string() }],
+    }))
+}
+
+pub fn handle_metadata(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let options = &request["options"];
+    let sender = options["sender_address"]
+        .as_str()
+        .ok_or(RosettaError::MalformedRequest(
+            "Missing sender_address".to_string(),
+        ))?;
+    let sender = StacksAddress::from_string(sender).ok_or(RosettaError::InvalidAccount(
+        format!("Invalid sender {}", sender),
+    ))?;
+    let recipient = options["recipient_address"]
+        .as_str()
+        .ok_or(RosettaError::MalformedRequest(
+            "Missing recipient_address".to_string(),
+        ))?;
+    let intent = TransferIntent {
+        sender,
+        recipient: PrincipalData::parse(recipient).map_err(|_| {
+            RosettaError::InvalidAccount(format!("Invalid recipient {}", recipient))
+        })?,
+        amount: get_u64(&options["amount"], "amount")?,
+        fee: None,
+    };
+    let memo = parse_memo(&options["memo"])?;
+
+    let mut chain = ctx.open_chain()?;
+    let tip = chain.tip.index_block_hash();
+    let sortdb = &chain.sortdb;
+    let principal = PrincipalData::from(intent.sender.clone());
+    let nonce = chain
+        .chainstate
+        .with_read_only_clarity_tx(&sortdb.index_conn(), &tip, |conn| {
+            StacksChainState::get_account(conn, &principal).nonce
+        })
+        .ok_or(RosettaError::BlockNotFound)?;
+
+    // the transaction's length does not depend on the signer's key
+    let fee = match options.get("fee") {
+        Some(fee) => get_u64(fee, "fee")?,
+        None => {
+            // the sender's public key is not known yet, but the length of a signed
+            // single-signature transaction does not depend on it
+            let spending_condition =
+                TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
+                    hash_mode: SinglesigHashMode::P2PKH,
+                    signer: intent.sender.bytes.clone(),
+                    nonce: 0,
+                    fee_rate: 0,
+                    key_encoding: TransactionPublicKeyEncoding::Compressed,
+                    signature: MessageSignature::empty(),
+                });
+            let tx = make_unsigned_transfer(ctx, &intent, spending_condition, memo, nonce, 0);
+            (tx.serialize_to_vec().len() as u64) * MINIMUM_TX_FEE_RATE_PER_BYTE
+        }
+    };
+
+    let mut metadata = json!({
+        "nonce": nonce.to_string(),
+        "fee": fee.to_string(),
+    });
+    if let Some(memo) = options["memo"].as_str() {
+        metadata["memo"] = json!(memo);
+    }
+    Ok(json!({
+        "metadata": metadata,
+        "suggested_fee": [operations::amount(fee as i128)],
+    }))
+}
+
+pub fn handle_payloads(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let intent = TransferIntent::from_operations(&request["operations"])?;
+    let metadata = &request["metadata"];
+    let nonce = get_u64(&metadata["nonce"], "nonce")?;
+    let fee = match intent.fee {
+        Some(fee) => fee,
+        None => get_u64(&metadata["fee"], "fee")?,
+    };
+    let memo = parse_memo(&metadata["memo"])?;
+
+    let public_key = request["public_keys"]
+        .as_array()
+        .and_then(|keys| keys.first())
+        .ok_or(RosettaError::MalformedRequest(
+            "Missing the sender's public key".to_string(),
+        ))?;
+    let public_key = parse_public_key(public_key)?;
+    let signer = p2pkh_address(ctx, &public_key);
+    if signer != intent.sender {
+        return Err(RosettaError::InvalidPublicKey(format!(
+            "Public key is for {}, not {}",
+            &signer, &intent.sender
+        )));
+    }
+
+    let spending_condition = TransactionSpendingCondition::new_singlesig_p2pkh(public_key)
+        .expect("BUG: failed to make a p2pkh spending condition");
+    let tx = make_unsigned_transfer(ctx, &intent, spending_condition, memo, nonce, fee);
+    let sighash = TransactionSpendingCondition::make_sighash_presign(
+        &tx.sign_begin(),
+        &TransactionAuthFlags::AuthStandard,
+        fee,
+        nonce,
+    );
+    Ok(json!({
+        "unsigned_transaction": to_hex(&tx.serialize_to_vec()),
+        "payloads": [{
+            "address": signer.to_string(),
+            "account_identifier": { "address": signer.to_string() },
+            "hex_bytes": to_hex(sighash.as_bytes()),
+            "signature_type": SIGNATURE_TYPE,
+        }],
+    }))
+}
+
+/// Convert an `ecdsa_recovery` signature (r, s, v) into a Stacks signature (v, r, s)
+pub fn signature_from_rsv(bytes: &[u8]) -> Result<MessageSignature, RosettaError> {
+    if bytes.len() != 65 {
+        return Err(RosettaError::InvalidSignature(
+            "Expected a 65-byte signature".to_string(),
+        ));
+    }
+    let mut vrs = [0u8; 65];
+    vrs[0] = bytes[64];
+    vrs[1..].copy_from_slice(&bytes[0..64]);
+    Ok(MessageSignature(vrs))
+}
+
+pub fn handle_combine(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let unsigned_tx =
+        request["unsigned_transaction"]
+            .as_str()
+            .ok_or(RosettaError::MalformedRequest(
+                "Missing unsigned_transaction".to_string(),
+            ))?;
+    let mut tx = decode_transaction(unsigned_tx)?;
+
+    let signatures = request["signatures"]
+        .as_array()
+        .ok_or(RosettaError::MalformedRequest(
+            "Missing signatures".to_string(),
+        ))?;
+    if signatures.len() != 1 {
+        return Err(RosettaError::InvalidSignature(
+            "Expected exactly one signature".to_string(),
+        ));
+    }
+    let signature = &signatures[0];
+    if signature["signature_type"] != SIGNATURE_TYPE {
+        return Err(RosettaError::InvalidSignature(format!(
+            "Only {} signatures are supported",
+            SIGNATURE_TYPE
+        )));
+    }
+    let signature_bytes = signature["hex_bytes"]
+        .as_str()
+        .and_then(|hex| hex_bytes(hex).ok())
+        .ok_or(RosettaError::InvalidSignature(
+            "Signature is not hex".to_string(),
+        ))?;
+    let signature = signature_from_rsv(&signature_bytes)?;
+
+    match tx.auth {
+        TransactionAuth::Standard(TransactionSpendingCondition::Singlesig(ref mut condition)) => {
+            condition.set_signature(signature);
+        }
+        _ => {
+            return Err(RosettaError::InvalidTransaction(
+                "Only single-signature transactions are supported".to_string(),
+            ));
+        }
+    }
+    tx.verify()
+        .map_err(|e| RosettaError::InvalidSignature(format!("{}", e)))?;
+
+    Ok(json!({ "signed_transaction": to_hex(&tx.serialize_to_vec()) }))
+}
+
+pub fn handle_parse(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let signed = request["signed"].as_bool().unwrap_or(false);
+    let tx = request["transaction"]
+        .as_str()
+        .ok_or(RosettaError::MalformedRequest(
+            "Missing transaction".to_string(),
+        ))
+        .and_then(decode_transaction)?;
+    let ops = operations::transaction_operations(&tx, None, &[])
+        .map_err(RosettaError::InvalidTransaction)?;
+
+    let mut response = json!({ "operations": ops });
+    if signed {
+        response["account_identifier_signers"] =
+            json!([{ "address": tx.origin_address().to_string() }]);
+    }
+    Ok(response)
+}
+
+pub fn handle_hash(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let tx = request["signed_transaction"]
+        .as_str()
+        .ok_or(RosettaError::MalformedRequest(
+            "Missing signed_transaction".to_string(),
+        ))
+        .and_then(decode_transaction)?;
+    Ok(json!({ "transaction_identifier": transaction_identifier(&tx.txid()) }))
+}
+
+pub async fn handle_submit(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let tx = request["signed_transaction"]
+        .as_str()
+        .ok_or(RosettaError::MalformedRequest(
+            "Missing signed_transaction".to_string(),
+        ))
+        .and_then(decode_transaction)?;
+
+    // a wildcard bind address is reachable on the loopback interface
+    let rpc_addr = ctx.config.node.rpc_bind.replace("0.0.0.0", "127.0.0.1");
+    let url = Url::parse(&format!("http://{}/v2/transactions", &rpc_addr))
+        .map_err(|e| RosettaError::SubmitFailed(format!("{}", e)))?;
+    let mut req = Request::new(Method::Post, url);
+    req.append_header("Content-Type", "application/octet-stream")
+        .expect("Unable to set header");
+    req.set_body(tx.serialize_to_vec());
+
+    let stream = TcpStream::connect(&rpc_addr)
+        .await
+        .map_err(|e| RosettaError::SubmitFailed(format!("{}", e)))?;
+    let response = client::connect(stream, req)
+        .await
+        .map_err(|e| RosettaError::SubmitFailed(format!("{}", e)))?;
+    if !response.status().is_success() {
+        let reason = response.body_string().await.unwrap_or("".to_string());
+        return Err(RosettaError::SubmitFailed(reason));
+    }
+
+    Ok(json!({ "transaction_identifier": transaction_identifier(&tx.txid()) }))
+}
@@ -0,0 +1,633 @@
+//! A Rosetta API server (https://www.rosetta-api.org), served when `node.rosetta_bind` is set.
+//!
+//! The Data API (`/network/*`, `/block`, `/block/transaction`, `/account/balance` and
+//! `/mempool/*`) is read straight out of the node's chainstate, sortition DB and mempool, so
+//! only blocks the node has processed (along with their stored transaction receipts) can be
+//! served.  Blocks are identified by their Stacks block height and `0x`-prefixed index block
+//! hash.  The Construction API builds, signs and submits single-signature STX transfers; see
+//! `construction`.  How transactions map to operations is described in `operations`.
+
+pub mod construction;
+pub mod operations;
+
+use std::cmp;
+use std::sync::Arc;
+
+use async_std::net::{TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::task;
+use http_types::{Body, Method, Response, StatusCode};
+
+use stacks::burnchains::Txid;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
+use stacks::chainstate::stacks::{StacksBlockId, StacksTransaction};
+use stacks::core::mempool::MemPoolDB;
+use stacks::net::StacksMessageCodec;
+use stacks::util::hash::hex_bytes;
+use stacks::vm::types::PrincipalData;
+use stacks::vm::Value;
+
+use super::config::Config;
+use super::neon_node::TESTNET_CHAIN_ID;
+
+pub const ROSETTA_VERSION: &str = "1.4.10";
+pub const ROSETTA_BLOCKCHAIN: &str = "stacks";
+
+/// Maximum number of transaction identifiers returned by `/mempool`
+pub const MAX_MEMPOOL_TXIDS: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RosettaError {
+    UnknownNetwork(String),
+    MalformedRequest(String),
+    BlockNotFound,
+    TransactionNotFound,
+    InvalidAccount(String),
+    InvalidOperations(String),
+    InvalidPublicKey(String),
+    InvalidTransaction(String),
+    InvalidSignature(String),
+    SubmitFailed(String),
+    ChainstateUnavailable(String),
+}
+
+impl RosettaError {
+    pub fn code(&self) -> u32 {
+        match self {
+            RosettaError::UnknownNetwork(_) => 1,
+            RosettaError::MalformedRequest(_) => 2,
+            RosettaError::BlockNotFound => 3,
+            RosettaError::TransactionNotFound => 4,
+            RosettaError::InvalidAccount(_) => 5,
+            RosettaError::InvalidOperations(_) => 6,
+            RosettaError::InvalidPublicKey(_) => 7,
+            RosettaError::InvalidTransaction(_) => 8,
+            RosettaError::InvalidSignature(_) => 9,
+            RosettaError::SubmitFailed(_) => 10,
+            RosettaError::ChainstateUnavailable(_) => 11,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            RosettaError::UnknownNetwork(_) => "Unknown network",
+            RosettaError::MalformedRequest(_) => "Malformed request",
+            RosettaError::BlockNotFound => "Block not found",
+            RosettaError::TransactionNotFound => "Transaction not found",
+            RosettaError::InvalidAccount(_) => "Invalid account",
+            RosettaError::InvalidOperations(_) => "Invalid operations",
+            RosettaError::InvalidPublicKey(_) => "Invalid public key",
+            RosettaError::InvalidTransaction(_) => "Invalid transaction",
+            RosettaError::InvalidSignature(_) => "Invalid signature",
+            RosettaError::SubmitFailed(_) => "Failed to submit transaction",
+            RosettaError::ChainstateUnavailable(_) => "Chainstate unavailable",
+        }
+    }
+
+    /// Whether the same request may succeed later
+    pub fn retriable(&self) -> bool {
+        match self {
+            RosettaError::BlockNotFound
+            | RosettaError::TransactionNotFound
+            | RosettaError::SubmitFailed(_)
+            | RosettaError::ChainstateUnavailable(_) => true,
+            _ => false,
+        }
+    }
+
+    fn details(&self) -> Option<&str> {
+        match self {
+            RosettaError::UnknownNetwork(ref msg)
+            | RosettaError::MalformedRequest(ref msg)
+            | RosettaError::InvalidAccount(ref msg)
+            | RosettaError::InvalidOperations(ref msg)
+            | RosettaError::InvalidPublicKey(ref msg)
+            | RosettaError::InvalidTransaction(ref msg)
+            | RosettaError::InvalidSignature(ref msg)
+            | RosettaError::SubmitFailed(ref msg)
+            | RosettaError::ChainstateUnavailable(ref msg) => Some(msg),
+            RosettaError::BlockNotFound | RosettaError::TransactionNotFound => None,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut json = json!({
+            "code": self.code(),
+            "message": self.message(),
+            "retriable": self.retriable(),
+        });
+        if let Some(details) = self.details() {
+            json["details"] = json!({ "message": details });
+        }
+        json
+    }
+
+    /// One of each error, as listed by `/network/options`
+    pub fn all() -> Vec<RosettaError> {
+        vec![
+            RosettaError::UnknownNetwork("".to_string()),
+            RosettaError::MalformedRequest("".to_string()),
+            RosettaError::BlockNotFound,
+            RosettaError::TransactionNotFound,
+            RosettaError::InvalidAccount("".to_string()),
+            RosettaError::InvalidOperations("".to_string()),
+            RosettaError::InvalidPublicKey("".to_string()),
+            RosettaError::InvalidTransaction("".to_string()),
+            RosettaError::InvalidSignature("".to_string()),
+            RosettaError::SubmitFailed("".to_string()),
+            RosettaError::ChainstateUnavailable("".to_string()),
+        ]
+    }
+}
+
+/// What every handler needs to know about the node
+pub struct RosettaContext {
+    pub config: Config,
+    pub mainnet: bool,
+    pub chain_id: u32,
+}
+
+impl RosettaContext {
+    pub fn new(config: Config) -> RosettaContext {
+        RosettaContext {
+            config,
+            mainnet: false,
+            chain_id: TESTNET_CHAIN_ID,
+        }
+    }
+
+    pub fn network_name(&self) -> &'static str {
+        if self.mainnet {
+            "mainnet"
+        } else {
+            "testnet"
+        }
+    }
+
+    pub fn network_identifier(&self) -> serde_json::Value {
+        json!({ "blockchain": ROSETTA_BLOCKCHAIN, "network": self.network_name() })
+    }
+
+    /// Check that a request is for this node's network
+    pub fn check_network(&self, request: &serde_json::Value) -> Result<(), RosettaError> {
+        let network = &request["network_identifier"];
+        if network["blockchain"] != ROSETTA_BLOCKCHAIN || network["network"] != self.network_name()
+        {
+            return Err(RosettaError::UnknownNetwork(format!(
+                "Expected {}",
+                self.network_identifier()
+            )));
+        }
+        Ok(())
+    }
+
+    fn open_chain(&self) -> Result<ChainView, RosettaError> {
+        let sortdb = SortitionDB::open(&self.config.get_burn_db_file_path(), false)
+            .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?;
+        let (chainstate, _) = StacksChainState::open(
+            self.mainnet,
+            self.chain_id,
+            &self.config.get_chainstate_path(),
+        )
+        .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?;
+        let tip = chainstate
+            .get_stacks_chain_tip(&sortdb)
+            .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?
+            .ok_or(RosettaError::ChainstateUnavailable(
+                "No Stacks blocks have been processed".to_string(),
+            ))?;
+        let tip = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            &StacksBlockId::new(&tip.consensus_hash, &tip.anchored_block_hash),
+        )
+        .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?
+        .ok_or(RosettaError::ChainstateUnavailable(
+            "No header for the Stacks chain tip".to_string(),
+        ))?;
+        Ok(ChainView {
+            sortdb,
+            chainstate,
+            tip,
+        })
+    }
+}
+
+/// The node's chainstate, as of its canonical Stacks chain tip
+struct ChainView {
+    sortdb: SortitionDB,
+    chainstate: StacksChainState,
+    tip: StacksHeaderInfo,
+}
+
+impl ChainView {
+    fn header_at_height(&mut self, height: u64) -> Result<StacksHeaderInfo, RosettaError> {
+        let tip_index_hash = self.tip.index_block_hash();
+        let mut tx = self
+            .chainstate
+            .index_tx_begin()
+            .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?;
+        StacksChainState::get_index_tip_ancestor(&mut tx, &tip_index_hash, height)
+            .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?
+            .ok_or(RosettaError::BlockNotFound)
+    }
+
+    fn header_by_hash(
+        &self,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<StacksHeaderInfo, RosettaError> {
+        StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.chainstate.db(),
+            index_block_hash,
+        )
+        .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?
+        .ok_or(RosettaError::BlockNotFound)
+    }
+
+    /// Find the block a (partial) block identifier names.  Blocks named by height are looked up
+    /// on the canonical fork; if no block is named, the chain tip is used.
+    fn lookup_block(
+        &mut self,
+        block_identifier: &serde_json::Value,
+    ) -> Result<StacksHeaderInfo, RosettaError> {
+        if let Some(hash) = block_identifier["hash"].as_str() {
+            let index_block_hash = parse_block_hash(hash)?;
+            let header = self.header_by_hash(&index_block_hash)?;
+            if let Some(index) = block_identifier["index"].as_u64() {
+                if index != header.block_height {
+                    return Err(RosettaError::BlockNotFound);
+                }
+            }
+            Ok(header)
+        } else if let Some(index) = block_identifier["index"].as_u64() {
+            self.header_at_height(index)
+        } else {
+            Ok(self.tip.clone())
+        }
+    }
+
+    fn parent_of(&self, header: &StacksHeaderInfo) -> Result<StacksHeaderInfo, RosettaError> {
+        if header.block_height == 0 {
+            return Ok(header.clone());
+        }
+        let parent = self
+            .chainstate
+            .get_parent(&header.index_block_hash())
+            .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?;
+        self.header_by_hash(&parent)
+    }
+
+    /// The block's transactions, from their stored receipts
+    fn block_transactions(
+        &self,
+        header: &StacksHeaderInfo,
+    ) -> Result<Vec<serde_json::Value>, RosettaError> {
+        let receipts = StacksChainState::get_transaction_receipts(
+            self.chainstate.db(),
+            &header.index_block_hash(),
+        )
+        .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?;
+
+        let mut transactions = vec![];
+        for receipt in receipts.into_iter() {
+            let tx = decode_transaction(&receipt.raw_tx)?;
+            let success = !receipt.post_condition_aborted
+                && match receipt.result {
+                    Value::Response(ref data) => data.committed,
+                    _ => true,
+                };
+            let ops = operations::transaction_operations(&tx, Some(success), &receipt.events)
+                .map_err(RosettaError::ChainstateUnavailable)?;
+            transactions.push(json!({
+                "transaction_identifier": transaction_identifier(&receipt.txid),
+                "operations": ops,
+                "metadata": {
+                    "tx_index": receipt.tx_index,
+                    "result": receipt.result,
+                },
+            }));
+        }
+        Ok(transactions)
+    }
+}
+
+pub fn block_identifier(header: &StacksHeaderInfo) -> serde_json::Value {
+    json!({
+        "index": header.block_height,
+        "hash": format!("0x{}", header.index_block_hash()),
+    })
+}
+
+pub fn transaction_identifier(txid: &Txid) -> serde_json::Value {
+    json!({ "hash": format!("0x{}", txid) })
+}
+
+fn parse_block_hash(hash: &str) -> Result<StacksBlockId, RosettaError> {
+    StacksBlockId::from_hex(hash.trim_start_matches("0x"))
+        .map_err(|_| RosettaError::MalformedRequest(format!("Invalid block hash {}", hash)))
+}
+
+fn parse_txid(request: &serde_json::Value) -> Result<Txid, RosettaError> {
+    let hash = request["transaction_identifier"]["hash"].as_str().ok_or(
+        RosettaError::MalformedRequest("Missing transaction_identifier".to_string()),
+    )?;
+    Txid::from_hex(hash.trim_start_matches("0x"))
+        .map_err(|_| RosettaError::MalformedRequest(format!("Invalid transaction hash {}", hash)))
+}
+
+/// Decode a hex-encoded transaction, with or without a `0x` prefix
+pub fn decode_transaction(hex: &str) -> Result<StacksTransaction, RosettaError> {
+    let bytes = hex_bytes(hex.trim_start_matches("0x"))
+        .map_err(|_| RosettaError::InvalidTransaction("Transaction is not hex".to_string()))?;
+    StacksTransaction::consensus_deserialize(&mut &bytes[..])
+        .map_err(|e| RosettaError::InvalidTransaction(format!("{}", e)))
+}
+
+fn handle_network_list(ctx: &RosettaContext) -> Result<serde_json::Value, RosettaError> {
+    Ok(json!({ "network_identifiers": [ctx.network_identifier()] }))
+}
+
+fn handle_network_options(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let errors: Vec<_> = RosettaError::all()
+        .iter()
+        .map(|e| {
+            json!({
+                "code": e.code(),
+                "message": e.message(),
+                "retriable": e.retriable(),
+            })
+        })
+        .collect();
+    Ok(json!({
+        "version": {
+            "rosetta_version": ROSETTA_VERSION,
+            "node_version": stacks::version_string(
+                option_env!("CARGO_PKG_NAME").unwrap_or("stacks-node"),
+                option_env!("CARGO_PKG_VERSION").unwrap_or("0.0.0.0")
+            ),
+        },
+        "allow": {
+            "operation_statuses": [
+                { "status": operations::STATUS_SUCCESS, "successful": true },
+                { "status": operations::STATUS_FAILED, "successful": false },
+            ],
+            "operation_types": operations::OPERATION_TYPES,
+            "errors": errors,
+            "historical_balance_lookup": true,
+            "mempool_coins": false,
+        },
+    }))
+}
+
+fn handle_network_status(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let mut chain = ctx.open_chain()?;
+    let genesis = chain.header_at_height(0)?;
+    Ok(json!({
+        "current_block_identifier": block_identifier(&chain.tip),
+        "current_block_timestamp": chain.tip.burn_header_timestamp * 1000,
+        "genesis_block_identifier": block_identifier(&genesis),
+        "peers": [],
+    }))
+}
+
+fn handle_block(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let mut chain = ctx.open_chain()?;
+    let header = chain.lookup_block(&request["block_identifier"])?;
+    let parent = chain.parent_of(&header)?;
+    let transactions = chain.block_transactions(&header)?;
+    Ok(json!({
+        "block": {
+            "block_identifier": block_identifier(&header),
+            "parent_block_identifier": block_identifier(&parent),
+            "timestamp": header.burn_header_timestamp * 1000,
+            "transactions": transactions,
+            "metadata": {
+                "block_hash": format!("0x{}", header.anchored_header.block_hash()),
+                "burn_block_height": header.burn_header_height,
+                "burn_block_hash": format!("0x{}", header.burn_header_hash),
+            },
+        },
+    }))
+}
+
+fn handle_block_transaction(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let txid = parse_txid(request)?;
+    let mut chain = ctx.open_chain()?;
+    let header = chain.lookup_block(&request["block_identifier"])?;
+    let tx_hash = transaction_identifier(&txid);
+    chain
+        .block_transactions(&header)?
+        .into_iter()
+        .find(|tx| tx["transaction_identifier"] == tx_hash)
+        .map(|tx| json!({ "transaction": tx }))
+        .ok_or(RosettaError::TransactionNotFound)
+}
+
+fn handle_account_balance(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let address =
+        request["account_identifier"]["address"]
+            .as_str()
+            .ok_or(RosettaError::MalformedRequest(
+                "Missing account_identifier".to_string(),
+            ))?;
+    let principal = PrincipalData::parse(address)
+        .map_err(|_| RosettaError::InvalidAccount(format!("Invalid address {}", address)))?;
+
+    let mut chain = ctx.open_chain()?;
+    let header = chain.lookup_block(&request["block_identifier"])?;
+    let sortdb = &chain.sortdb;
+    let account = chain
+        .chainstate
+        .with_read_only_clarity_tx(&sortdb.index_conn(), &header.index_block_hash(), |conn| {
+            StacksChainState::get_account(conn, &principal)
+        })
+        .ok_or(RosettaError::BlockNotFound)?;
+
+    // the PoX lock plus any independent locks, until the last of them unlocks
+    let balance = &account.stx_balance;
+    let locked = balance
+        .locks
+        .iter()
+        .fold(balance.amount_locked, |total, lock| total + lock.amount);
+    let unlock_height = balance
+        .locks
+        .iter()
+        .fold(balance.unlock_height, |height, lock| {
+            cmp::max(height, lock.unlock_height)
+        });
+
+    Ok(json!({
+        "block_identifier": block_identifier(&header),
+        "balances": [operations::amount(balance.get_total_balance() as i128)],
+        "metadata": {
+            "sequence_number": account.nonce,
+            "locked": locked.to_string(),
+            "unlock_height": unlock_height,
+        },
+    }))
+}
+
+fn handle_mempool(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let mempool = MemPoolDB::open(ctx.mainnet, ctx.chain_id, &ctx.config.get_chainstate_path())
+        .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?;
+    let txids: Vec<_> = MemPoolDB::get_pending_txs(mempool.conn(), MAX_MEMPOOL_TXIDS)
+        .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?
+        .iter()
+        .map(|tx_info| transaction_identifier(&tx_info.metadata.txid))
+        .collect();
+    Ok(json!({ "transaction_identifiers": txids }))
+}
+
+fn handle_mempool_transaction(
+    ctx: &RosettaContext,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    ctx.check_network(request)?;
+    let txid = parse_txid(request)?;
+    let mempool = MemPoolDB::open(ctx.mainnet, ctx.chain_id, &ctx.config.get_chainstate_path())
+        .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?;
+    let tx_info = MemPoolDB::get_tx(mempool.conn(), &txid)
+        .map_err(|e| RosettaError::ChainstateUnavailable(format!("{}", e)))?
+        .ok_or(RosettaError::TransactionNotFound)?;
+    let ops = operations::transaction_operations(&tx_info.tx, None, &[])
+        .map_err(RosettaError::InvalidTransaction)?;
+    Ok(json!({
+        "transaction": {
+            "transaction_identifier": transaction_identifier(&txid),
+            "operations": ops,
+        },
+    }))
+}
+
+/// Route a request to its handler
+async fn handle_request(
+    ctx: &RosettaContext,
+    path: &str,
+    request: &serde_json::Value,
+) -> Result<serde_json::Value, RosettaError> {
+    match path {
+        "/network/list" => handle_network_list(ctx),
+        "/network/options" => handle_network_options(ctx, request),
+        "/network/status" => handle_network_status(ctx, request),
+        "/block" => handle_block(ctx, request),
+        "/block/transaction" => handle_block_transaction(ctx, request),
+        "/account/balance" => handle_account_balance(ctx, request),
+        "/mempool" => handle_mempool(ctx, request),
+        "/mempool/transaction" => handle_mempool_transaction(ctx, request),
+        "/construction/derive" => construction::handle_derive(ctx, request),
+        "/construction/preprocess" => construction::handle_preprocess(ctx, request),
+        "/construction/metadata" => construction::handle_metadata(ctx, request),
+        "/construction/payloads" => construction::handle_payloads(ctx, request),
+        "/construction/combine" => construction::handle_combine(ctx, request),
+        "/construction/parse" => construction::handle_parse(ctx, request),
+        "/construction/hash" => construction::handle_hash(ctx, request),
+        "/construction/submit" => construction::handle_submit(ctx, request).await,
+        _ => Err(RosettaError::MalformedRequest(format!(
+            "Unknown endpoint {}",
+            path
+        ))),
+    }
+}
+
+pub fn start_serving_rosetta(config: Config) {
+    let bind_address = config
+        .node
+        .rosetta_bind
+        .clone()
+        .expect("BUG: no Rosetta bind address");
+    let ctx = Arc::new(RosettaContext::new(config));
+
+    async_std::task::block_on(async {
+        let listener = TcpListener::bind(bind_address)
+            .await
+            .expect("Rosetta: unable to bind address");
+        let addr = format!(
+            "http://{}",
+            listener.local_addr().expect("Rosetta: unable to get addr")
+        );
+        info!("Rosetta: server listening on {}", addr);
+
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    error!("Rosetta: unable to open socket - {:?}", err);
+                    continue;
+                }
+            };
+            let addr = addr.clone();
+            let ctx = ctx.clone();
+
+            task::spawn(async {
+                if let Err(err) = accept(addr, stream, ctx).await {
+                    warn!("Rosetta: connection failed - {}", err);
+                }
+            });
+        }
+    });
+}
+
+async fn accept(
+    addr: String,
+    stream: TcpStream,
+    ctx: Arc<RosettaContext>,
+) -> http_types::Result<()> {
+    async_h1::accept(&addr, stream.clone(), |req| {
+        let ctx = ctx.clone();
+        async move {
+            if req.method() != Method::Post {
+                return Ok(Response::new(StatusCode::MethodNotAllowed));
+            }
+
+            let path = req.url().path().to_string();
+            let result = match req.body_string().await {
+                Ok(body) => match serde_json::from_str::<serde_json::Value>(&body) {
+                    Ok(request) => handle_request(&ctx, &path, &request).await,
+                    Err(e) => Err(RosettaError::MalformedRequest(format!("{}", e))),
+                },
+                Err(e) => Err(RosettaError::MalformedRequest(format!("{}", e))),
+            };
+
+            let (status, body) = match result {
+                Ok(body) => (StatusCode::Ok, body),
+                Err(e) => {
+                    debug!("Rosetta: {} failed: {:?}", &path, &e);
+                    (StatusCode::InternalServerError, e.to_json())
+                }
+            };
+            let mut response = Response::new(status);
+            response
+                .append_header("Content-Type", "application/json")
+                .expect("Unable to set headers");
+            response.set_body(Body::from(body.to_string()));
+            Ok(response)
+        }
+    })
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,301 @@
+//! Mapping of Stacks transactions, and the events in their receipts, to Rosetta operations.
+//!
+//! Every change to an account's STX balance is an operation:  the fee paid by a transaction's
+//! payer, a token-transfer payload's debit and credit, the STX transfers, mints and burns in
+//! the transaction's events, matured miner rewards, and genesis lockups unlocking (the last two
+//! are recorded as events of the block's coinbase).  Balances are reported as the account's
+//! total balance, so locks and their expiry change no balance and are reported without an
+//! amount.
+
+use stacks::chainstate::stacks::{StacksTransaction, TransactionPayload};
+use stacks::net::StacksMessageCodec;
+use stacks::util::hash::to_hex;
+
+pub const OP_FEE: &str = "fee";
+pub const OP_TOKEN_TRANSFER: &str = "token_transfer";
+pub const OP_COINBASE: &str = "coinbase";
+pub const OP_CONTRACT_CALL: &str = "contract_call";
+pub const OP_SMART_CONTRACT: &str = "smart_contract";
+pub const OP_POISON_MICROBLOCK: &str = "poison_microblock";
+pub const OP_STX_TRANSFER: &str = "stx_transfer";
+pub const OP_STX_MINT: &str = "stx_mint";
+pub const OP_STX_BURN: &str = "stx_burn";
+pub const OP_STX_LOCK: &str = "stx_lock";
+pub const OP_STX_UNLOCK: &str = "stx_unlock";
+pub const OP_MINER_REWARD: &str = "miner_reward";
+
+pub const OPERATION_TYPES: &[&str] = &[
+    OP_FEE,
+    OP_TOKEN_TRANSFER,
+    OP_COINBASE,
+    OP_CONTRACT_CALL,
+    OP_SMART_CONTRACT,
+    OP_POISON_MICROBLOCK,
+    OP_STX_TRANSFER,
+    OP_STX_MINT,
+    OP_STX_BURN,
+    OP_STX_LOCK,
+    OP_STX_UNLOCK,
+    OP_MINER_REWARD,
+];
+
+pub const STATUS_SUCCESS: &str = "success";
+pub const STATUS_FAILED: &str = "failed";
+
+pub fn currency() -> serde_json::Value {
+    json!({ "symbol": "STX", "decimals": 6 })
+}
+
+/// A signed uSTX amount
+pub fn amount(value: i128) -> serde_json::Value {
+    json!({ "value": value.to_string(), "currency": currency() })
+}
+
+/// Accumulates a transaction's operations, numbering them as they are added
+struct Operations {
+    ops: Vec<serde_json::Value>,
+}
+
+impl Operations {
+    fn push(
+        &mut self,
+        op_type: &str,
+        status: Option<&'static str>,
+        account: String,
+        value: Option<i128>,
+        related: Option<usize>,
+        metadata: Option<serde_json::Value>,
+    ) -> usize {
+        let index = self.ops.len();
+        let mut op = json!({
+            "operation_identifier": { "index": index },
+            "type": op_type,
+            "account": { "address": account },
+        });
+        if let Some(status) = status {
+            op["status"] = json!(status);
+        }
+        if let Some(value) = value {
+            op["amount"] = amount(value);
+        }
+        if let Some(related) = related {
+            op["related_operations"] = json!([{ "index": related }]);
+        }
+        if let Some(metadata) = metadata {
+            op["metadata"] = metadata;
+        }
+        self.ops.push(op);
+        index
+    }
+
+    /// Add a debit and a credit of the same amount
+    fn push_transfer(
+        &mut self,
+        op_type: &str,
+        status: Option<&'static str>,
+        sender: String,
+        recipient: String,
+        value: i128,
+        metadata: Option<serde_json::Value>,
+    ) {
+        let debit = self.push(op_type, status, sender, Some(-value), None, metadata);
+        self.push(op_type, status, recipient, Some(value), Some(debit), None);
+    }
+}
+
+fn event_amount(event: &serde_json::Value, field: &str) -> Result<i128, String> {
+    event[field]
+        .as_str()
+        .and_then(|value| value.parse::<i128>().ok())
+        .ok_or(format!("Malformed event: no amount in '{}'", field))
+}
+
+fn event_principal(event: &serde_json::Value, field: &str) -> Result<String, String> {
+    event[field]
+        .as_str()
+        .map(|principal| principal.to_string())
+        .ok_or(format!("Malformed event: no principal in '{}'", field))
+}
+
+/// The operations of a transaction.  `success` is whether the transaction's payload took effect
+/// (None if it has not been mined, in which case no statuses are given), and `events` are its
+/// receipt's events, as stored in the chainstate.
+pub fn transaction_operations(
+    tx: &StacksTransaction,
+    success: Option<bool>,
+    events: &[serde_json::Value],
+) -> Result<Vec<serde_json::Value>, String> {
+    let mut ops = Operations { ops: vec![] };
+    let status = success.map(|success| {
+        if success {
+            STATUS_SUCCESS
+        } else {
+            STATUS_FAILED
+        }
+    });
+    // the fee is paid whether or not the payload took effect
+    let fee_status = success.map(|_| STATUS_SUCCESS);
+
+    let fee = tx.get_fee_rate();
+    if fee > 0 {
+        let payer = tx.sponsor_address().unwrap_or(tx.origin_address());
+        ops.push(
+            OP_FEE,
+            fee_status,
+            payer.to_string(),
+            Some(-(fee as i128)),
+            None,
+            None,
+        );
+    }
+
+    let origin = tx.origin_address().to_string();
+    match tx.payload {
+        TransactionPayload::TokenTransfer(ref recipient, ref value, ref memo, ref long_memo) => {
+            let mut metadata = json!({ "memo": format!("0x{}", to_hex(&memo.0)) });
+            if let Some(long_memo) = long_memo {
+                metadata["long_memo"] =
+                    json!(format!("0x{}", to_hex(&long_memo.serialize_to_vec())));
+            }
+            ops.push_transfer(
+                OP_TOKEN_TRANSFER,
+                status,
+                origin,
+                recipient.to_string(),
+                *value as i128,
+                Some(metadata),
+            );
+            // the payload's transfer is its only event
+            return Ok(ops.ops);
+        }
+        TransactionPayload::Coinbase(..) => {
+            ops.push(OP_COINBASE, status, origin, None, None, None);
+        }
+        TransactionPayload::ContractCall(ref call) => {
+            ops.push(
+                OP_CONTRACT_CALL,
+                status,
+                origin,
+                None,
+                None,
+                Some(json!({
+                    "contract_identifier": call.to_clarity_contract_id().to_string(),
+                    "function_name": call.function_name.to_string(),
+                })),
+            );
+        }
+        TransactionPayload::SmartContract(ref contract) => {
+            ops.push(
+                OP_SMART_CONTRACT,
+                status,
+                origin.clone(),
+                None,
+                None,
+                Some(json!({
+                    "contract_identifier": format!("{}.{}", &origin, contract.name.as_str()),
+                })),
+            );
+        }
+        TransactionPayload::PoisonMicroblock(..) => {
+            ops.push(OP_POISON_MICROBLOCK, status, origin, None, None, None);
+        }
+    }
+
+    let is_coinbase = match tx.payload {
+        TransactionPayload::Coinbase(..) => true,
+        _ => false,
+    };
+    for event in events.iter() {
+        match event["type"].as_str() {
+            Some("stx_transfer_event") => {
+                let event = &event["stx_transfer_event"];
+                ops.push_transfer(
+                    OP_STX_TRANSFER,
+                    status,
+                    event_principal(event, "sender")?,
+                    event_principal(event, "recipient")?,
+                    event_amount(event, "amount")?,
+                    None,
+                );
+            }
+            Some("stx_mint_event") => {
+                // the only mints a coinbase records are genesis lockups unlocking
+                let event = &event["stx_mint_event"];
+                ops.push(
+                    if is_coinbase {
+                        OP_STX_UNLOCK
+                    } else {
+                        OP_STX_MINT
+                    },
+                    status,
+                    event_principal(event, "recipient")?,
+                    Some(event_amount(event, "amount")?),
+                    None,
+                    None,
+                );
+            }
+            Some("stx_burn_event") => {
+                let event = &event["stx_burn_event"];
+                ops.push(
+                    OP_STX_BURN,
+                    status,
+                    event_principal(event, "sender")?,
+                    Some(-event_amount(event, "amount")?),
+                    None,
+                    None,
+                );
+            }
+            Some("stx_lock_event") => {
+                let event = &event["stx_lock_event"];
+                ops.push(
+                    OP_STX_LOCK,
+                    status,
+                    event_principal(event, "locked_address")?,
+                    None,
+                    None,
+                    Some(json!({
+                        "locked_amount": event["locked_amount"],
+                        "unlock_height": event["unlock_height"],
+                    })),
+                );
+            }
+            Some("stx_unlock_event") => {
+                let event = &event["stx_unlock_event"];
+                ops.push(
+                    OP_STX_UNLOCK,
+                    status,
+                    event_principal(event, "unlocked_address")?,
+                    None,
+                    None,
+                    Some(json!({
+                        "unlocked_amount": event["unlocked_amount"],
+                    })),
+                );
+            }
+            Some("stx_miner_reward_event") => {
+                let event = &event["stx_miner_reward_event"];
+                let total = event_amount(event, "coinbase_amount")?
+                    + event_amount(event, "tx_fees_anchored")?
+                    + event_amount(event, "tx_fees_streamed_produced")?
+                    + event_amount(event, "tx_fees_streamed_confirmed")?;
+                ops.push(
+                    OP_MINER_REWARD,
+                    status,
+                    event_principal(event, "recipient")?,
+                    Some(total),
+                    None,
+                    Some(json!({
+                        "coinbase_amount": event["coinbase_amount"],
+                        "tx_fees_anchored": event["tx_fees_anchored"],
+                        "tx_fees_streamed_produced": event["tx_fees_streamed_produced"],
+                        "tx_fees_streamed_confirmed": event["tx_fees_streamed_confirmed"],
+                        "from_index_block_hash": event["from_index_block_hash"],
+                    })),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    Ok(ops.ops)
+}
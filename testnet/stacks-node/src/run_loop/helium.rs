@@ -1,10 +1,13 @@
 use super::RunLoopCallbacks;
-use crate::burnchains::Error as BurnchainControllerError;
+use crate::burnchains::{DevBlockRequest, DevBlockTrigger, Error as BurnchainControllerError};
+use crate::tenure::TenureArtifacts;
 use crate::{
-    BitcoinRegtestController, BurnchainController, ChainTip, Config, MocknetController, Node,
+    BitcoinRegtestController, BurnchainController, BurnchainTip, ChainTip, Config,
+    MocknetController, Node,
 };
 use stacks::burnchains::BurnchainHeaderHash;
 use stacks::chainstate::stacks::db::ClarityTx;
+use std::time::Duration;
 
 /// RunLoop is coordinating a simulated burnchain and some simulated nodes
 /// taking turns in producing blocks.
@@ -12,6 +15,7 @@ pub struct RunLoop {
     config: Config,
     pub node: Node,
     pub callbacks: RunLoopCallbacks,
+    dev_block_trigger: Option<DevBlockTrigger>,
 }
 
 impl RunLoop {
@@ -25,13 +29,63 @@ impl RunLoop {
         boot_exec: Box<dyn FnOnce(&mut ClarityTx) -> ()>,
     ) -> Self {
         // Build node based on config
-        let node = Node::new(config.clone(), boot_exec);
+        let mut node = Node::new(config.clone(), boot_exec);
+
+        let dev_block_trigger = if config.burnchain.mode == "dev" {
+            let trigger = DevBlockTrigger::new();
+            node.set_dev_block_trigger(trigger.clone());
+            Some(trigger)
+        } else {
+            None
+        };
 
         Self {
             config,
             node,
             callbacks: RunLoopCallbacks::new(),
+            dev_block_trigger,
+        }
+    }
+
+    /// In `dev` mode, get a handle with which other threads can make the simulated burnchain
+    /// mine its next block right away.
+    pub fn get_dev_block_trigger(&self) -> Option<DevBlockTrigger> {
+        self.dev_block_trigger.clone()
+    }
+
+    /// In `dev` mode, wait until it is time to mine the burnchain block after `burnchain_tip` (or
+    /// until one is requested) before the node assembles the Stacks block to commit to in it.
+    fn wait_for_dev_block_request(&self, burnchain_tip: &BurnchainTip) -> Option<DevBlockRequest> {
+        let trigger = self.dev_block_trigger.as_ref()?;
+        let deadline = burnchain_tip.received_at
+            + Duration::from_millis(self.config.burnchain.dev_block_time_ms);
+        Some(trigger.wait_for_request(deadline).unwrap_or_default())
+    }
+
+    /// In `dev` mode, have the burnchain mine the block that was requested, now that the node has
+    /// committed to its Stacks block.
+    fn release_dev_block(&self, request: &DevBlockRequest, artifacts: Option<&TenureArtifacts>) {
+        let trigger = match self.dev_block_trigger {
+            Some(ref trigger) => trigger,
+            None => return,
+        };
+        if let Some(artifacts) = artifacts {
+            for txid in request.txids.iter() {
+                if !artifacts
+                    .anchored_block
+                    .txs
+                    .iter()
+                    .any(|tx| tx.txid() == *txid)
+                {
+                    warn!(
+                        "Block {} does not include requested transaction {}",
+                        artifacts.anchored_block.block_hash(),
+                        txid
+                    );
+                }
+            }
         }
+        trigger.release(request.sortition);
     }
 
     /// Starts the testnet runloop.
@@ -45,6 +99,12 @@ impl RunLoop {
         let mut burnchain: Box<dyn BurnchainController> = match &self.config.burnchain.mode[..] {
             "helium" => Box::new(BitcoinRegtestController::new(self.config.clone(), None)),
             "mocknet" => MocknetController::generic(self.config.clone()),
+            "dev" => MocknetController::dev(
+                self.config.clone(),
+                self.dev_block_trigger
+                    .clone()
+                    .expect("BUG: no dev block trigger in dev mode"),
+            ),
             _ => unreachable!(),
         };
 
@@ -93,11 +153,11 @@ impl RunLoop {
         let _ = burnchain.sortdb_mut();
 
         // Run the tenure, keep the artifacts
-        let artifacts_from_1st_tenure = match first_tenure.run(&burnchain.sortdb_ref().index_conn())
-        {
-            Some(res) => res,
-            None => panic!("Error while running 1st tenure"),
-        };
+        let mut artifacts_from_1st_tenure =
+            match first_tenure.run(&burnchain.sortdb_ref().index_conn()) {
+                Some(res) => res,
+                None => panic!("Error while running 1st tenure"),
+            };
 
         // Tenures are instantiating their own chainstate, so that nodes can keep a clean chainstate,
         // while having the option of running multiple tenures concurrently and try different strategies.
@@ -112,6 +172,36 @@ impl RunLoop {
 
         let (mut burnchain_tip, _) = burnchain.sync(None)?;
 
+        // In dev mode, the burnchain block can be mined without a sortition.  Keep trying to
+        // bootstrap the chain until one picks the genesis block.
+        while !burnchain_tip.block_snapshot.sortition {
+            self.node.process_burnchain_state(&burnchain_tip);
+            self.node.abandon_tenure();
+
+            let genesis_burnchain_tip = artifacts_from_1st_tenure.parent_block.clone();
+            let mut tenure = match self.node.initiate_genesis_tenure(&genesis_burnchain_tip) {
+                Some(res) => res,
+                None => panic!("Error while initiating genesis tenure"),
+            };
+            self.callbacks.invoke_new_tenure(
+                round_index,
+                &genesis_burnchain_tip,
+                &chain_tip,
+                &mut tenure,
+            );
+            artifacts_from_1st_tenure = match tenure.run(&burnchain.sortdb_ref().index_conn()) {
+                Some(res) => res,
+                None => panic!("Error while running 1st tenure"),
+            };
+            self.node.commit_artifacts(
+                &artifacts_from_1st_tenure.anchored_block,
+                &artifacts_from_1st_tenure.parent_block,
+                &mut burnchain,
+                artifacts_from_1st_tenure.burn_fee,
+            );
+            burnchain_tip = burnchain.sync(None)?.0;
+        }
+
         self.callbacks
             .invoke_new_burn_chain_state(round_index, &burnchain_tip, &chain_tip);
 
@@ -153,9 +243,12 @@ impl RunLoop {
                 return Ok(());
             }
 
-            // Run the last initialized tenure
+            // Run the last initialized tenure.  In dev mode, only assemble its block once it's
+            // time to mine the next burnchain block.
+            let mut dev_block_request = None;
             let artifacts_from_tenure = match leader_tenure {
                 Some(mut tenure) => {
+                    dev_block_request = self.wait_for_dev_block_request(&burnchain_tip);
                     self.callbacks.invoke_new_tenure(
                         round_index,
                         &burnchain_tip,
@@ -180,6 +273,10 @@ impl RunLoop {
                 None => {}
             }
 
+            if let Some(ref request) = dev_block_request {
+                self.release_dev_block(request, artifacts_from_tenure.as_ref());
+            }
+
             let (new_burnchain_tip, _) = burnchain.sync(None)?;
             burnchain_tip = new_burnchain_tip;
 
@@ -198,6 +295,14 @@ impl RunLoop {
             match artifacts_from_tenure {
                 // Pass if we're missing the artifacts from the current tenure.
                 None => continue,
+                // In dev mode, the burnchain block can be mined without a sortition.  The
+                // tenure's block was proven over the previous sortition hash, so it can't be
+                // committed again: build a new one on the same parent instead.
+                Some(_) if !burnchain_tip.block_snapshot.sortition => {
+                    self.node.abandon_tenure();
+                    leader_tenure = self.node.initiate_new_tenure();
+                    continue;
+                }
                 Some(ref artifacts) => {
                     // Have the node process its tenure.
                     // We should have some additional checks here, and ensure that the previous artifacts are legit.
@@ -116,9 +116,9 @@ impl RunLoopCallbacks {
                 _ => println!("-> Tx {:?}", tx.auth),
             }
             match &tx.payload {
-                TransactionPayload::Coinbase(_) => println!("   Coinbase"),
+                TransactionPayload::Coinbase(..) => println!("   Coinbase"),
                 TransactionPayload::SmartContract(contract) => println!("   Publish smart contract\n**************************\n{:?}\n**************************", contract.code_body),
-                TransactionPayload::TokenTransfer(recipent, amount, _) => println!("   Transfering {} µSTX to {}", amount, recipent.to_string()),
+                TransactionPayload::TokenTransfer(recipent, amount, ..) => println!("   Transfering {} µSTX to {}", amount, recipent.to_string()),
                 _ => println!("   {:?}", tx.payload)
             }
         }
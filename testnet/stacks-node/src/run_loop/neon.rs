@@ -1,4 +1,5 @@
 use crate::{
+    genesis_data::{load_genesis_data, make_genesis_balances_audit},
     neon_node,
     node::{get_account_balances, get_account_lockups},
     BitcoinRegtestController, BurnchainController, Config, EventDispatcher, Keychain,
@@ -16,15 +17,77 @@ use stacks::chainstate::stacks::boot::STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR;
 use stacks::chainstate::stacks::db::{ChainStateBootData, ClarityTx, StacksChainState};
 use stacks::vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
 use std::cmp;
-use std::sync::mpsc::sync_channel;
 use std::thread;
 
 use super::RunLoopCallbacks;
 
+use crate::grpc::start_serving_grpc;
 use crate::monitoring::start_serving_monitoring_metrics;
+use crate::poison_reporter::start_poison_reporter;
+use crate::rosetta::start_serving_rosetta;
+use crate::scheduler::start_call_scheduler;
 
 use crate::syncctl::PoxSyncWatchdog;
 
+/// Build the boot data used to instantiate a fresh chainstate for this node's config:  the
+/// configured and genesis balances and lockups, and the PoX contract's burnchain parameters.
+pub fn make_chainstate_boot_data(
+    config: &Config,
+    burnchain_config: &Burnchain,
+) -> ChainStateBootData {
+    let initial_balances = config
+        .initial_balances
+        .iter()
+        .map(|e| (e.address.clone(), e.amount))
+        .collect();
+
+    let genesis_data = load_genesis_data(config);
+    let lockups_data = genesis_data.clone();
+
+    let first_block_height = burnchain_config.first_block_height as u128;
+    let pox_prepare_length = burnchain_config.pox_constants.prepare_length as u128;
+    let pox_reward_cycle_length = burnchain_config.pox_constants.reward_cycle_length as u128;
+    let pox_rejection_fraction = burnchain_config.pox_constants.pox_rejection_fraction as u128;
+
+    let boot_block = Box::new(move |clarity_tx: &mut ClarityTx| {
+        let contract = QualifiedContractIdentifier::parse(&format!(
+            "{}.pox",
+            STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR
+        ))
+        .expect("Failed to construct boot code contract address");
+        let sender = PrincipalData::from(contract.clone());
+        let params = vec![
+            Value::UInt(first_block_height),
+            Value::UInt(pox_prepare_length),
+            Value::UInt(pox_reward_cycle_length),
+            Value::UInt(pox_rejection_fraction),
+        ];
+
+        clarity_tx.connection().as_transaction(|conn| {
+            conn.run_contract_call(
+                &sender,
+                &contract,
+                "set-burnchain-parameters",
+                &params,
+                |_, _| false,
+            )
+            .expect("Failed to set burnchain parameters in PoX contract");
+        });
+    });
+
+    ChainStateBootData {
+        initial_balances,
+        initial_locks: vec![],
+        post_flight_callback: Some(boot_block),
+        first_burnchain_block_hash: burnchain_config.first_block_hash.clone(),
+        first_burnchain_block_height: burnchain_config.first_block_height as u32,
+        first_burnchain_block_timestamp: burnchain_config.first_block_timestamp,
+        get_bulk_initial_lockups: Some(Box::new(move || get_account_lockups(lockups_data))),
+        get_bulk_initial_balances: Some(Box::new(move || get_account_balances(genesis_data))),
+        genesis_balances_audit: make_genesis_balances_audit(config),
+    }
+}
+
 /// Coordinating a node running in neon mode.
 #[cfg(test)]
 pub struct RunLoop {
@@ -143,66 +206,29 @@ impl RunLoop {
         let mainnet = false;
         let chainid = neon_node::TESTNET_CHAIN_ID;
         let block_limit = self.config.block_limit.clone();
-        let initial_balances = self
-            .config
-            .initial_balances
-            .iter()
-            .map(|e| (e.address.clone(), e.amount))
-            .collect();
-
         // setup dispatcher
-        let mut event_dispatcher = EventDispatcher::new();
+        let mut event_dispatcher =
+            EventDispatcher::new_persistent(self.config.get_event_queue_db_path());
         for observer in self.config.events_observers.iter() {
             event_dispatcher.register_observer(observer);
         }
+        for webhook in self.config.webhooks.iter() {
+            event_dispatcher.register_webhook(webhook);
+        }
+        if let Some(ref ws_bind) = self.config.node.ws_bind {
+            event_dispatcher
+                .serve_event_stream(ws_bind)
+                .expect("FATAL: failed to bind the WebSocket event stream");
+        }
 
         let mut coordinator_dispatcher = event_dispatcher.clone();
 
         let chainstate_path = self.config.get_chainstate_path();
         let coordinator_burnchain_config = burnchain_config.clone();
 
-        let (attachments_tx, attachments_rx) = sync_channel(1);
-        let first_block_height = burnchain_config.first_block_height as u128;
-        let pox_prepare_length = burnchain_config.pox_constants.prepare_length as u128;
-        let pox_reward_cycle_length = burnchain_config.pox_constants.reward_cycle_length as u128;
-        let pox_rejection_fraction = burnchain_config.pox_constants.pox_rejection_fraction as u128;
-
-        let boot_block = Box::new(move |clarity_tx: &mut ClarityTx| {
-            let contract = QualifiedContractIdentifier::parse(&format!(
-                "{}.pox",
-                STACKS_BOOT_CODE_CONTRACT_ADDRESS_STR
-            ))
-            .expect("Failed to construct boot code contract address");
-            let sender = PrincipalData::from(contract.clone());
-            let params = vec![
-                Value::UInt(first_block_height),
-                Value::UInt(pox_prepare_length),
-                Value::UInt(pox_reward_cycle_length),
-                Value::UInt(pox_rejection_fraction),
-            ];
-
-            clarity_tx.connection().as_transaction(|conn| {
-                conn.run_contract_call(
-                    &sender,
-                    &contract,
-                    "set-burnchain-parameters",
-                    &params,
-                    |_, _| false,
-                )
-                .expect("Failed to set burnchain parameters in PoX contract");
-            });
-        });
-        let mut boot_data = ChainStateBootData {
-            initial_balances,
-            post_flight_callback: Some(boot_block),
-            first_burnchain_block_hash: coordinator_burnchain_config.first_block_hash,
-            first_burnchain_block_height: coordinator_burnchain_config.first_block_height as u32,
-            first_burnchain_block_timestamp: coordinator_burnchain_config.first_block_timestamp,
-            get_bulk_initial_lockups: Some(Box::new(get_account_lockups)),
-            get_bulk_initial_balances: Some(Box::new(get_account_balances)),
-        };
+        let mut boot_data = make_chainstate_boot_data(&self.config, &burnchain_config);
 
-        let (chain_state_db, receipts) = StacksChainState::open_and_exec(
+        let (mut chain_state_db, receipts) = StacksChainState::open_and_exec(
             mainnet,
             chainid,
             &chainstate_path,
@@ -210,13 +236,14 @@ impl RunLoop {
             block_limit,
         )
         .unwrap();
+        chain_state_db.size_limits = self.config.epoch_size_limits.clone();
+        self.config.enable_consensus_audit_log(&mut chain_state_db);
         coordinator_dispatcher.dispatch_boot_receipts(receipts);
 
         thread::spawn(move || {
             ChainsCoordinator::run(
                 chain_state_db,
                 coordinator_burnchain_config,
-                attachments_tx,
                 &mut coordinator_dispatcher,
                 coordinator_receivers,
             );
@@ -248,7 +275,6 @@ impl RunLoop {
                 self.get_blocks_processed_arc(),
                 coordinator_senders,
                 pox_watchdog.make_comms_handle(),
-                attachments_rx,
             )
         } else {
             node.into_initialized_node(
@@ -256,7 +282,6 @@ impl RunLoop {
                 self.get_blocks_processed_arc(),
                 coordinator_senders,
                 pox_watchdog.make_comms_handle(),
-                attachments_rx,
             )
         };
 
@@ -274,6 +299,34 @@ impl RunLoop {
             });
         }
 
+        if self.config.node.rosetta_bind.is_some() {
+            let config = self.config.clone();
+            thread::spawn(move || {
+                start_serving_rosetta(config);
+            });
+        }
+
+        if self.config.node.grpc_bind.is_some() {
+            let config = self.config.clone();
+            thread::spawn(move || {
+                start_serving_grpc(config);
+            });
+        }
+
+        if self.config.node.call_scheduler_key.is_some() {
+            let config = self.config.clone();
+            thread::spawn(move || {
+                start_call_scheduler(config);
+            });
+        }
+
+        if self.config.node.poison_reporter_key.is_some() {
+            let config = self.config.clone();
+            thread::spawn(move || {
+                start_poison_reporter(config);
+            });
+        }
+
         let mut block_height = 1.max(burnchain_config.first_block_height);
 
         let mut burnchain_height = block_height;
@@ -0,0 +1,333 @@
+//! The call scheduler: sends the contract-calls that an operator has scheduled over
+//! `/v2/admin/scheduled_calls` once the burnchain reaches their target heights, signed with the
+//! node's `call_scheduler_key`.  This lets a node do keeper duties for a protocol (e.g. calling
+//! its epoch-advancing function) without anyone having to be around to sign.
+//!
+//! The queue lives in a sqlite database in the node's working directory, so calls scheduled
+//! before a restart are still sent after it.  Transactions go through the node's own RPC
+//! interface, just as a wallet's would.  A call the node rejects is marked as failed; if the node
+//! can't be reached at all, its calls stay pending and are retried on the next pass.
+
+use std::convert::TryFrom;
+use std::thread;
+use std::time::Duration;
+
+use async_h1::client;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+
+use stacks::address::AddressHashMode;
+use stacks::burnchains::Address;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::{
+    StacksAddress, StacksPrivateKey, StacksPublicKey, StacksTransaction, StacksTransactionSigner,
+    TransactionAnchorMode, TransactionAuth, TransactionContractCall, TransactionPayload,
+    TransactionPostConditionMode, TransactionSpendingCondition, TransactionVersion,
+};
+use stacks::core::scheduler::{ScheduledCall, ScheduledCallDB};
+use stacks::net::{AccountEntryResponse, StacksMessageCodec};
+use stacks::vm::types::Value;
+use stacks::vm::{ClarityName, ContractName};
+
+use super::config::Config;
+use super::neon_node::TESTNET_CHAIN_ID;
+
+/// How often to check for calls that have come due
+const SCHEDULER_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum SubmitError {
+    /// the node's RPC interface could not be reached; try again later
+    Unreachable(String),
+    /// the call can't be sent, or the node would not take it
+    Rejected(String),
+}
+
+/// The next nonce for `address`, counting its transactions already in the node's mempool
+pub fn get_nonce(rpc_addr: &str, address: &StacksAddress) -> Result<u64, SubmitError> {
+    let url = Url::parse(&format!(
+        "http://{}/v2/accounts/{}?proof=0&mempool=1",
+        rpc_addr, address
+    ))
+    .map_err(|e| SubmitError::Unreachable(format!("{}", e)))?;
+    let req = Request::new(Method::Get, url);
+
+    async_std::task::block_on(async {
+        let stream = TcpStream::connect(rpc_addr)
+            .await
+            .map_err(|e| SubmitError::Unreachable(format!("{}", e)))?;
+        let response = client::connect(stream, req)
+            .await
+            .map_err(|e| SubmitError::Unreachable(format!("{}", e)))?;
+        if !response.status().is_success() {
+            return Err(SubmitError::Unreachable(format!(
+                "Failed to load account {}: {}",
+                address,
+                response.status()
+            )));
+        }
+        let body = response
+            .body_string()
+            .await
+            .map_err(|e| SubmitError::Unreachable(format!("{}", e)))?;
+        let account: AccountEntryResponse =
+            serde_json::from_str(&body).map_err(|e| SubmitError::Unreachable(format!("{}", e)))?;
+        Ok(account.nonce)
+    })
+}
+
+/// Post a transaction to the node's RPC interface
+pub fn post_transaction(rpc_addr: &str, tx: &StacksTransaction) -> Result<(), SubmitError> {
+    let url = Url::parse(&format!("http://{}/v2/transactions", rpc_addr))
+        .map_err(|e| SubmitError::Unreachable(format!("{}", e)))?;
+    let mut req = Request::new(Method::Post, url);
+    req.append_header("Content-Type", "application/octet-stream")
+        .expect("Unable to set header");
+    req.set_body(tx.serialize_to_vec());
+
+    async_std::task::block_on(async {
+        let stream = TcpStream::connect(rpc_addr)
+            .await
+            .map_err(|e| SubmitError::Unreachable(format!("{}", e)))?;
+        let response = client::connect(stream, req)
+            .await
+            .map_err(|e| SubmitError::Unreachable(format!("{}", e)))?;
+        if !response.status().is_success() {
+            let reason = response.body_string().await.unwrap_or("".to_string());
+            return Err(SubmitError::Rejected(reason));
+        }
+        Ok(())
+    })
+}
+
+/// The node's RPC interface, as reachable from the node itself
+pub fn local_rpc_addr(config: &Config) -> String {
+    // a wildcard bind address is reachable on the loopback interface
+    config.node.rpc_bind.replace("0.0.0.0", "127.0.0.1")
+}
+
+/// The testnet single-sig address of a signing key
+pub fn p2pkh_address(privk: &StacksPrivateKey) -> StacksAddress {
+    StacksAddress::from_public_keys(
+        AddressHashMode::SerializeP2PKH.to_version_testnet(),
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![StacksPublicKey::from_private(privk)],
+    )
+    .expect("BUG: failed to derive address from private key")
+}
+
+/// Build and sign the transaction that makes a scheduled call
+fn make_call_tx(
+    call: &ScheduledCall,
+    privk: &StacksPrivateKey,
+    nonce: u64,
+) -> Result<StacksTransaction, String> {
+    let address = StacksAddress::from_string(&call.contract_address)
+        .ok_or_else(|| format!("Invalid contract address {}", &call.contract_address))?;
+    let contract_name = ContractName::try_from(call.contract_name.clone())
+        .map_err(|_| format!("Invalid contract name {}", &call.contract_name))?;
+    let function_name = ClarityName::try_from(call.function_name.clone())
+        .map_err(|_| format!("Invalid function name {}", &call.function_name))?;
+    let function_args = call
+        .function_args
+        .iter()
+        .map(|hex| Value::try_deserialize_hex_untyped(hex))
+        .collect::<Result<Vec<Value>, _>>()
+        .map_err(|e| format!("Invalid function argument: {}", e))?;
+
+    let payload = TransactionPayload::ContractCall(TransactionContractCall {
+        address,
+        contract_name,
+        function_name,
+        function_args,
+    });
+
+    let mut spending_condition =
+        TransactionSpendingCondition::new_singlesig_p2pkh(StacksPublicKey::from_private(privk))
+            .ok_or("Failed to create spending condition".to_string())?;
+    spending_condition.set_nonce(nonce);
+    spending_condition.set_fee_rate(call.fee);
+
+    let mut tx = StacksTransaction::new(
+        TransactionVersion::Testnet,
+        TransactionAuth::Standard(spending_condition),
+        payload,
+    );
+    tx.chain_id = TESTNET_CHAIN_ID;
+    tx.anchor_mode = TransactionAnchorMode::Any;
+    tx.post_condition_mode = TransactionPostConditionMode::Deny;
+
+    let mut tx_signer = StacksTransactionSigner::new(&tx);
+    tx_signer
+        .sign_origin(privk)
+        .map_err(|e| format!("Failed to sign transaction: {:?}", e))?;
+    tx_signer
+        .get_tx()
+        .ok_or("Failed to sign transaction".to_string())
+}
+
+pub struct CallScheduler {
+    config: Config,
+    privk: StacksPrivateKey,
+    address: StacksAddress,
+    /// the loopback address of the node's RPC interface
+    rpc_addr: String,
+}
+
+impl CallScheduler {
+    pub fn new(config: Config) -> CallScheduler {
+        let privk = StacksPrivateKey::from_hex(
+            config
+                .node
+                .call_scheduler_key
+                .as_ref()
+                .expect("BUG: no call scheduler key"),
+        )
+        .expect("Invalid call_scheduler_key");
+        let address = p2pkh_address(&privk);
+        let rpc_addr = local_rpc_addr(&config);
+
+        CallScheduler {
+            config,
+            privk,
+            address,
+            rpc_addr,
+        }
+    }
+
+    /// Send every pending call whose target height is at or below `burn_height`
+    pub fn submit_due_calls(&self, db: &mut ScheduledCallDB, burn_height: u64) {
+        let calls = match db.get_due(burn_height) {
+            Ok(calls) => calls,
+            Err(e) => {
+                warn!("Call scheduler: failed to load scheduled calls: {:?}", &e);
+                return;
+            }
+        };
+        if calls.is_empty() {
+            return;
+        }
+
+        let mut nonce = match get_nonce(&self.rpc_addr, &self.address) {
+            Ok(nonce) => nonce,
+            Err(e) => {
+                warn!("Call scheduler: failed to load nonce: {:?}", &e);
+                return;
+            }
+        };
+
+        for call in calls.iter() {
+            let result = make_call_tx(call, &self.privk, nonce)
+                .map_err(SubmitError::Rejected)
+                .and_then(|tx| post_transaction(&self.rpc_addr, &tx).map(|_| tx));
+            let db_result = match result {
+                Ok(tx) => {
+                    info!(
+                        "Call scheduler: submitted call {} ({}.{}::{}) in {}",
+                        call.id,
+                        &call.contract_address,
+                        &call.contract_name,
+                        &call.function_name,
+                        &tx.txid()
+                    );
+                    nonce += 1;
+                    db.set_submitted(call.id, &format!("{}", &tx.txid()))
+                }
+                Err(SubmitError::Rejected(reason)) => {
+                    warn!("Call scheduler: call {} failed: {}", call.id, &reason);
+                    db.set_failed(call.id, &reason)
+                }
+                Err(SubmitError::Unreachable(reason)) => {
+                    warn!(
+                        "Call scheduler: failed to submit call {}, will retry: {}",
+                        call.id, &reason
+                    );
+                    return;
+                }
+            };
+            if let Err(e) = db_result {
+                warn!(
+                    "Call scheduler: failed to record the outcome of call {}: {:?}",
+                    call.id, &e
+                );
+            }
+        }
+    }
+
+    fn get_burn_height(&self) -> Option<u64> {
+        let sortdb = SortitionDB::open(&self.config.get_burn_db_file_path(), false).ok()?;
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).ok()?;
+        Some(tip.block_height)
+    }
+
+    /// Check for due calls forever
+    pub fn run(self) {
+        let db_path = self.config.get_call_scheduler_db_path();
+        let mut db = ScheduledCallDB::open(&db_path).expect("Failed to open call scheduler DB");
+        info!(
+            "Call scheduler: sending scheduled calls from {}",
+            &self.address
+        );
+
+        let mut last_burn_height = None;
+        loop {
+            thread::sleep(SCHEDULER_POLL_INTERVAL);
+            let burn_height = match self.get_burn_height() {
+                Some(height) => height,
+                None => continue,
+            };
+            if last_burn_height != Some(burn_height) {
+                debug!("Call scheduler: burnchain height is now {}", burn_height);
+                last_burn_height = Some(burn_height);
+            }
+            self.submit_due_calls(&mut db, burn_height);
+        }
+    }
+}
+
+pub fn start_call_scheduler(config: Config) {
+    CallScheduler::new(config).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stacks::core::scheduler::ScheduledCallStatus;
+
+    #[test]
+    fn scheduled_call_tx_is_signed_contract_call() {
+        let privk = StacksPrivateKey::new();
+        let call = ScheduledCall {
+            id: 1,
+            contract_address: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+            contract_name: "keeper".to_string(),
+            function_name: "advance-epoch".to_string(),
+            function_args: vec!["0100000000000000000000000000000001".to_string()],
+            target_burn_height: 100,
+            fee: 500,
+            status: ScheduledCallStatus::Pending,
+            txid: None,
+            error: None,
+        };
+
+        let tx = make_call_tx(&call, &privk, 7).unwrap();
+        tx.verify().unwrap();
+        assert_eq!(tx.get_origin_nonce(), 7);
+        assert_eq!(tx.get_fee_rate(), 500);
+        match tx.payload {
+            TransactionPayload::ContractCall(ref cc) => {
+                assert_eq!(cc.contract_name.as_str(), "keeper");
+                assert_eq!(cc.function_name.as_str(), "advance-epoch");
+                assert_eq!(cc.function_args, vec![Value::UInt(1)]);
+            }
+            _ => panic!("Not a contract-call"),
+        }
+
+        let bad_call = ScheduledCall {
+            function_args: vec!["zz".to_string()],
+            ..call
+        };
+        assert!(make_call_tx(&bad_call, &privk, 7).is_err());
+    }
+}
@@ -83,8 +83,9 @@ impl<'a> Tenure {
             self.config.block_limit.clone(),
         )
         .unwrap();
+        chain_state.size_limits = self.config.epoch_size_limits.clone();
 
-        let (anchored_block, _, _) = StacksBlockBuilder::build_anchored_block(
+        let (anchored_block, _, _) = StacksBlockBuilder::build_anchored_block_with_budget(
             &mut chain_state,
             burn_dbconn,
             &mut self.mem_pool,
@@ -94,6 +95,7 @@ impl<'a> Tenure {
             self.microblock_pubkeyhash.clone(),
             &self.coinbase_tx,
             self.config.block_limit.clone(),
+            &self.config.node.miner_assembly_budget,
         )
         .unwrap();
 
@@ -110,13 +112,14 @@ impl<'a> Tenure {
 
     #[cfg(test)]
     pub fn open_chainstate(&self) -> StacksChainState {
-        let (chain_state, _) = StacksChainState::open_with_block_limit(
+        let (mut chain_state, _) = StacksChainState::open_with_block_limit(
             false,
             TESTNET_CHAIN_ID,
             &self.config.get_chainstate_path(),
             self.config.block_limit.clone(),
         )
         .unwrap();
+        chain_state.size_limits = self.config.epoch_size_limits.clone();
         chain_state
     }
 }
@@ -44,8 +44,12 @@ pub fn make_bad_stacks_transfer(
     recipient: &PrincipalData,
     amount: u64,
 ) -> Vec<u8> {
-    let payload =
-        TransactionPayload::TokenTransfer(recipient.clone(), amount, TokenTransferMemo([0; 34]));
+    let payload = TransactionPayload::TokenTransfer(
+        recipient.clone(),
+        amount,
+        TokenTransferMemo([0; 34]),
+        None,
+    );
 
     let mut spending_condition =
         TransactionSpendingCondition::new_singlesig_p2pkh(StacksPublicKey::from_private(sender))
@@ -27,6 +27,8 @@ use super::Config;
 use crate::helium::RunLoop;
 use rand::RngCore;
 use std::convert::TryInto;
+use std::thread;
+use std::time::Duration;
 
 use stacks::vm::database::BurnStateDB;
 
@@ -190,8 +192,12 @@ pub fn make_stacks_transfer(
     recipient: &PrincipalData,
     amount: u64,
 ) -> Vec<u8> {
-    let payload =
-        TransactionPayload::TokenTransfer(recipient.clone(), amount, TokenTransferMemo([0; 34]));
+    let payload = TransactionPayload::TokenTransfer(
+        recipient.clone(),
+        amount,
+        TokenTransferMemo([0; 34]),
+        None,
+    );
     serialize_sign_standard_single_sig_tx(payload.into(), sender, nonce, fee_rate)
 }
 
@@ -202,8 +208,12 @@ pub fn make_stacks_transfer_mblock_only(
     recipient: &PrincipalData,
     amount: u64,
 ) -> Vec<u8> {
-    let payload =
-        TransactionPayload::TokenTransfer(recipient.clone(), amount, TokenTransferMemo([0; 34]));
+    let payload = TransactionPayload::TokenTransfer(
+        recipient.clone(),
+        amount,
+        TokenTransferMemo([0; 34]),
+        None,
+    );
     serialize_sign_standard_single_sig_tx_anchor_mode(
         payload.into(),
         sender,
@@ -225,7 +235,7 @@ pub fn make_poison(
 }
 
 pub fn make_coinbase(sender: &StacksPrivateKey, nonce: u64, fee_rate: u64) -> Vec<u8> {
-    let payload = TransactionPayload::Coinbase(CoinbasePayload([0; 32]));
+    let payload = TransactionPayload::Coinbase(CoinbasePayload([0; 32]), None);
     serialize_sign_standard_single_sig_tx(payload.into(), sender, nonce, fee_rate)
 }
 
@@ -367,7 +377,7 @@ fn should_succeed_mining_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
 
@@ -399,7 +409,7 @@ fn should_succeed_mining_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
 
@@ -431,7 +441,7 @@ fn should_succeed_mining_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
 
@@ -472,7 +482,7 @@ fn should_succeed_mining_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
 
@@ -513,7 +523,7 @@ fn should_succeed_mining_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
 
@@ -522,7 +532,7 @@ fn should_succeed_mining_valid_txs() {
                     assert!(contract_tx.chain_id == TESTNET_CHAIN_ID);
 
                     assert!(match contract_tx.payload {
-                        TransactionPayload::TokenTransfer(_, _, _) => true,
+                        TransactionPayload::TokenTransfer(..) => true,
                         _ => false,
                     });
 
@@ -551,6 +561,41 @@ fn should_succeed_mining_valid_txs() {
     run_loop.start(num_rounds).unwrap();
 }
 
+#[test]
+fn should_succeed_mining_on_dev_burnchain() {
+    let mut conf = new_test_conf();
+    conf.burnchain.mode = "dev".to_string();
+    conf.burnchain.commit_anchor_block_within = 0;
+    // blocks only come from the trigger below
+    conf.burnchain.dev_block_time_ms = 3_600_000;
+    conf.burnchain.dev_missed_sortitions = vec![4];
+
+    let num_rounds = 4;
+    let mut run_loop = RunLoop::new(conf.clone());
+
+    let block_trigger = run_loop.get_dev_block_trigger().unwrap();
+    thread::spawn(move || {
+        for _ in 0..100 {
+            block_trigger.mine_block(true);
+            thread::sleep(Duration::from_millis(50));
+        }
+    });
+
+    run_loop
+        .callbacks
+        .on_new_stacks_chain_state(|round, burnchain_tip, chain_tip, _, _| {
+            assert!(burnchain_tip.block_snapshot.sortition);
+            assert_eq!(chain_tip.metadata.block_height, round + 1);
+            // burnchain block 4 has no sortition, so its block is mined in block 5 instead
+            let expected_burn_height = if round < 2 { round + 2 } else { round + 3 };
+            assert_eq!(
+                burnchain_tip.block_snapshot.block_height,
+                expected_burn_height
+            );
+        });
+    run_loop.start(num_rounds).unwrap();
+}
+
 #[test]
 #[ignore]
 fn should_succeed_handling_malformed_and_valid_txs() {
@@ -613,7 +658,7 @@ fn should_succeed_handling_malformed_and_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
                 }
@@ -629,7 +674,7 @@ fn should_succeed_handling_malformed_and_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
 
@@ -653,7 +698,7 @@ fn should_succeed_handling_malformed_and_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
                 }
@@ -669,7 +714,7 @@ fn should_succeed_handling_malformed_and_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
                 }
@@ -685,7 +730,7 @@ fn should_succeed_handling_malformed_and_valid_txs() {
                     let coinbase_tx = &chain_tip.block.txs[0];
                     assert!(coinbase_tx.chain_id == TESTNET_CHAIN_ID);
                     assert!(match coinbase_tx.payload {
-                        TransactionPayload::Coinbase(_) => true,
+                        TransactionPayload::Coinbase(..) => true,
                         _ => false,
                     });
 
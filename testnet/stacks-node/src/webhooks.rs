@@ -0,0 +1,447 @@
+//! Webhooks: HTTP notifications whose body is rendered from a template, so that automation tools
+//! can be told when something happens on chain without having to consume the raw observer
+//! payloads.
+//!
+//! Each `[[webhook]]` in the config names a URL, the events that notify it (in the syntax of an
+//! observer's `events_keys`), and a template for the request body.  The template is rendered over
+//! a JSON context with these fields:
+//!
+//! * `block` -- the block's fields, as in a `new_block` payload (without its events and
+//!   transactions)
+//! * `tx` and `event` -- the matching event and the transaction that emitted it, as in a
+//!   `new_block` payload (event triggers only)
+//! * `events` -- the block's matching events (block triggers only)
+//!
+//! Each webhook's notifications are posted in order from its own thread, so a slow or unreachable
+//! webhook never holds up block processing.  Once its queue is full, new notifications are
+//! dropped.
+
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+use async_h1::client;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+
+use stacks::burnchains::Txid;
+use stacks::chainstate::stacks::events::StacksTransactionEvent;
+
+use super::config::{WebhookConfig, WebhookTrigger};
+use super::event_stream::EventSubscription;
+
+/// How many notifications may wait to be delivered to a webhook before new ones are dropped
+const WEBHOOK_QUEUE_LEN: usize = 1024;
+/// How many times a notification is posted before it is given up on
+const WEBHOOK_MAX_ATTEMPTS: u32 = 3;
+/// How long to wait before re-posting a notification the first time; doubles on each failure
+const WEBHOOK_RETRY_BACKOFF_MIN: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Field { path: Vec<String>, json: bool },
+}
+
+/// A Handlebars-style template.  `{{path.to.field}}` inserts a field of the context -- strings
+/// verbatim, anything else as JSON -- and `{{json path.to.field}}` inserts it as JSON, so that
+/// strings come out quoted and escaped.  Array elements are selected by index, e.g.
+/// `{{events.0.type}}`.  A missing field inserts nothing (or `null`, with `json`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(raw: &str) -> Result<Template, String> {
+        let mut segments = vec![];
+        let mut rest = raw;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            let field = &rest[start + 2..];
+            let end = field
+                .find("}}")
+                .ok_or("Unterminated '{{' in template".to_string())?;
+            let words: Vec<_> = field[..end].split_whitespace().collect();
+            let (json, path) = match words.as_slice() {
+                [path] => (false, path),
+                ["json", path] => (true, path),
+                _ => {
+                    return Err(format!(
+                        "Invalid template field '{{{{{}}}}}'",
+                        &field[..end]
+                    ))
+                }
+            };
+            let path: Vec<_> = path.split('.').map(|c| c.to_string()).collect();
+            if path.iter().any(|c| c.len() == 0) {
+                return Err(format!("Invalid template field path '{}'", path.join(".")));
+            }
+            segments.push(Segment::Field { path, json });
+            rest = &field[end + 2..];
+        }
+        if rest.len() > 0 {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        Ok(Template { segments })
+    }
+
+    pub fn render(&self, context: &serde_json::Value) -> String {
+        let mut rendered = String::new();
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Field { path, json: true } => rendered.push_str(
+                    &Template::lookup(context, path)
+                        .unwrap_or(&serde_json::Value::Null)
+                        .to_string(),
+                ),
+                Segment::Field { path, json: false } => match Template::lookup(context, path) {
+                    None | Some(serde_json::Value::Null) => {}
+                    Some(serde_json::Value::String(text)) => rendered.push_str(text),
+                    Some(value) => rendered.push_str(&value.to_string()),
+                },
+            }
+        }
+        rendered
+    }
+
+    fn lookup<'a>(
+        context: &'a serde_json::Value,
+        path: &[String],
+    ) -> Option<&'a serde_json::Value> {
+        path.iter()
+            .try_fold(context, |value, component| match value {
+                serde_json::Value::Array(items) => component
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get(index)),
+                _ => value.get(component.as_str()),
+            })
+    }
+}
+
+/// One event of a block, with the index of its transaction and whether that transaction's
+/// effects were committed
+pub type BlockEvent<'a> = (usize, bool, Txid, &'a StacksTransactionEvent);
+
+#[derive(Clone)]
+pub struct Webhook {
+    url: Url,
+    trigger: WebhookTrigger,
+    subscription: EventSubscription,
+    template: Template,
+    /// queue of rendered bodies, drained by the webhook's delivery thread
+    sender: SyncSender<String>,
+}
+
+impl Webhook {
+    /// Start the webhook's delivery thread.
+    pub fn spawn(config: &WebhookConfig) -> Webhook {
+        let (sender, receiver) = sync_channel::<String>(WEBHOOK_QUEUE_LEN);
+        let url = config.url.clone();
+        let content_type = config.content_type.clone();
+        thread::Builder::new()
+            .name(format!("webhook:{}", &url))
+            .spawn(move || {
+                for body in receiver {
+                    Webhook::deliver(&url, &content_type, body);
+                }
+            })
+            .expect("FATAL: failed to start webhook delivery thread");
+        Webhook::with_sender(config, sender)
+    }
+
+    fn with_sender(config: &WebhookConfig, sender: SyncSender<String>) -> Webhook {
+        Webhook {
+            url: config.url.clone(),
+            trigger: config.trigger.clone(),
+            subscription: EventSubscription {
+                keys: config.events_keys.clone(),
+            },
+            template: config.template.clone(),
+            sender,
+        }
+    }
+
+    fn wants_event(&self, event: &StacksTransactionEvent) -> bool {
+        self.subscription.keys.len() == 0 || self.subscription.wants_event(event)
+    }
+
+    /// Render and queue the webhook's notifications for a block.  `txs` are the block's
+    /// serialized transactions, and `events` are all of its events, in order.
+    pub fn process_block(
+        &self,
+        block: &serde_json::Value,
+        txs: &[serde_json::Value],
+        events: &[BlockEvent],
+    ) {
+        let matching = events
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, _, event))| self.wants_event(event));
+        match self.trigger {
+            WebhookTrigger::Event => {
+                for (event_index, (tx_index, committed, txid, event)) in matching {
+                    self.notify(self.template.render(&json!({
+                        "block": block,
+                        "tx": txs[*tx_index],
+                        "event": event.json_serialize(event_index, txid, *committed),
+                    })));
+                }
+            }
+            WebhookTrigger::Block => {
+                let serialized_events: Vec<_> = matching
+                    .map(|(event_index, (_, committed, txid, event))| {
+                        event.json_serialize(event_index, txid, *committed)
+                    })
+                    .collect();
+                if serialized_events.len() == 0 && self.subscription.keys.len() > 0 {
+                    return;
+                }
+                self.notify(self.template.render(&json!({
+                    "block": block,
+                    "events": serialized_events,
+                })));
+            }
+        }
+    }
+
+    fn notify(&self, body: String) {
+        match self.sender.try_send(body) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                warn!(
+                    "Webhook {}: queue is full, dropping notification",
+                    &self.url
+                );
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                error!("Webhook {}: delivery thread is gone", &self.url);
+            }
+        }
+    }
+
+    fn deliver(url: &Url, content_type: &str, body: String) {
+        let mut backoff = WEBHOOK_RETRY_BACKOFF_MIN;
+        for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+            match Webhook::post(url, content_type, body.clone()) {
+                Ok(()) => return,
+                Err(e) => {
+                    warn!("Webhook {}: attempt {} failed: {}", url, attempt, e);
+                }
+            }
+            if attempt < WEBHOOK_MAX_ATTEMPTS {
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+        }
+        error!(
+            "Webhook {}: giving up on notification after {} attempts",
+            url, WEBHOOK_MAX_ATTEMPTS
+        );
+    }
+
+    fn post(url: &Url, content_type: &str, body: String) -> Result<(), String> {
+        let addr = format!(
+            "{}:{}",
+            url.host_str().unwrap_or("localhost"),
+            url.port_or_known_default().unwrap_or(80)
+        );
+        let mut req = Request::new(Method::Post, url.clone());
+        req.append_header("Content-Type", content_type)
+            .map_err(|e| format!("invalid content type: {}", e))?;
+        req.set_body(body);
+
+        async_std::task::block_on(async {
+            let stream = TcpStream::connect(addr)
+                .await
+                .map_err(|e| format!("connection failed: {}", e))?;
+            let response = client::connect(stream, req)
+                .await
+                .map_err(|e| format!("request failed: {}", e))?;
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(format!("responded {}", response.status()))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::mpsc::Receiver;
+
+    use stacks::chainstate::stacks::events::{
+        STXEventType, STXTransferEventData, SmartContractEventData,
+    };
+    use stacks::vm::types::{BuffData, PrincipalData, QualifiedContractIdentifier, Value};
+
+    use super::super::config::{EventKeyType, WebhookConfigFile};
+
+    fn make_webhook(
+        toml_keys: Vec<&str>,
+        trigger: &str,
+        template: &str,
+    ) -> (Webhook, Receiver<String>) {
+        let config = WebhookConfig::from_file(&WebhookConfigFile {
+            url: "http://localhost:9000/hook".to_string(),
+            trigger: Some(trigger.to_string()),
+            events_keys: Some(toml_keys.iter().map(|k| k.to_string()).collect()),
+            template: template.to_string(),
+            content_type: None,
+        })
+        .unwrap();
+        let (sender, receiver) = sync_channel(16);
+        (Webhook::with_sender(&config, sender), receiver)
+    }
+
+    fn make_print(contract: &str, topic: &str, value: Value) -> StacksTransactionEvent {
+        StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+            key: (
+                QualifiedContractIdentifier::parse(contract).unwrap(),
+                topic.to_string(),
+            ),
+            value,
+        })
+    }
+
+    #[test]
+    fn templates_render_fields() {
+        let context = json!({
+            "block": { "block_height": 12, "block_hash": "0x01" },
+            "events": [{ "type": "contract_event" }],
+            "memo": "say \"hi\"",
+        });
+
+        let template =
+            Template::parse("height {{block.block_height}} hash {{ block.block_hash }}").unwrap();
+        assert_eq!(template.render(&context), "height 12 hash 0x01");
+
+        let template = Template::parse("{{events.0.type}}/{{events.1.type}}/{{nope}}").unwrap();
+        assert_eq!(template.render(&context), "contract_event//");
+
+        let template = Template::parse(r#"{"memo": {{json memo}}, "x": {{json nope}}}"#).unwrap();
+        assert_eq!(
+            template.render(&context),
+            r#"{"memo": "say \"hi\"", "x": null}"#
+        );
+
+        let template = Template::parse("{{block}}").unwrap();
+        assert_eq!(
+            template.render(&context),
+            r#"{"block_hash":"0x01","block_height":12}"#
+        );
+
+        assert!(Template::parse("{{block.block_height").is_err());
+        assert!(Template::parse("{{}}").is_err());
+        assert!(Template::parse("{{block..block_height}}").is_err());
+        assert!(Template::parse("{{yaml block}}").is_err());
+    }
+
+    #[test]
+    fn webhooks_are_configured() {
+        let mut file = WebhookConfigFile {
+            url: "http://localhost:9000/hook".to_string(),
+            trigger: None,
+            events_keys: Some(vec![
+                "STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.names::print".to_string(),
+            ]),
+            template: "{{event.txid}}".to_string(),
+            content_type: None,
+        };
+        let config = WebhookConfig::from_file(&file).unwrap();
+        assert_eq!(config.trigger, WebhookTrigger::Event);
+        assert_eq!(config.content_type, "application/json");
+        assert_eq!(
+            config.events_keys,
+            vec![EventKeyType::SmartContractEvent((
+                QualifiedContractIdentifier::parse(
+                    "STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.names"
+                )
+                .unwrap(),
+                "print".to_string()
+            ))]
+        );
+
+        file.trigger = Some("transaction".to_string());
+        assert!(WebhookConfig::from_file(&file).is_err());
+        file.trigger = Some("block".to_string());
+        file.url = "https://localhost:9000/hook".to_string();
+        assert!(WebhookConfig::from_file(&file).is_err());
+        file.url = "http://localhost:9000/hook".to_string();
+        file.events_keys = Some(vec!["nope".to_string()]);
+        assert!(WebhookConfig::from_file(&file).is_err());
+    }
+
+    #[test]
+    fn webhooks_notify_on_matching_events() {
+        let names = "STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.names";
+        let print = make_print(names, "print", Value::UInt(7));
+        let other_print = make_print(
+            "STB44HYPYAT2BB2QE513NSP81HTMYWBJP02HPGK6.token",
+            "print",
+            Value::UInt(8),
+        );
+        let stx_transfer = StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+            STXTransferEventData {
+                sender: PrincipalData::parse("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR").unwrap(),
+                recipient: PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                    .unwrap(),
+                amount: 1,
+                memo: BuffData { data: vec![] },
+                long_memo: None,
+            },
+        ));
+
+        let block = json!({ "block_height": 12 });
+        let txs = vec![json!({ "txid": "0xaa" }), json!({ "txid": "0xbb" })];
+        let events: Vec<BlockEvent> = vec![
+            (0, true, Txid([0xaa; 32]), &stx_transfer),
+            (1, true, Txid([0xbb; 32]), &other_print),
+            (1, true, Txid([0xbb; 32]), &print),
+        ];
+
+        // contract X emits topic Y
+        let (webhook, receiver) = make_webhook(
+            vec![&format!("{}::print", names)],
+            "event",
+            "{{block.block_height}} {{tx.txid}} {{event.event_index}} {{event.contract_event.contract_identifier}}",
+        );
+        webhook.process_block(&block, &txs, &events);
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![format!("12 0xbb 2 {}", names)]
+        );
+
+        let (webhook, receiver) = make_webhook(vec!["stx", "print"], "event", "{{event.type}}");
+        webhook.process_block(&block, &txs, &events);
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec!["stx_transfer_event", "contract_event", "contract_event"]
+        );
+
+        let (webhook, receiver) = make_webhook(
+            vec!["print"],
+            "block",
+            "{{block.block_height}} {{json events.1.event_index}}",
+        );
+        webhook.process_block(&block, &txs, &events);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec!["12 2"]);
+
+        // no matching events, no notification
+        let (webhook, receiver) = make_webhook(vec!["nft"], "block", "{{block.block_height}}");
+        webhook.process_block(&block, &txs, &events);
+        assert_eq!(receiver.try_iter().count(), 0);
+
+        // without events keys, every block notifies
+        let (webhook, receiver) = make_webhook(vec![], "block", "{{block.block_height}}");
+        webhook.process_block(&block, &txs, &[]);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec!["12"]);
+    }
+}